@@ -266,3 +266,46 @@ pub fn parse(_aml: &[u8]) -> Result<AMLCode, String> {
 	// TODO
 	todo!();
 }
+
+/// Reads an integer constant of at most one byte at offset `*off` in `aml`, then advances the
+/// offset.
+fn read_byte_const(aml: &[u8], off: &mut usize) -> Option<u8> {
+	let (val, len) = match *aml.get(*off)? {
+		ZERO_OP => (0, 1),
+		ONE_OP => (1, 1),
+		BYTE_PREFIX => (*aml.get(*off + 1)?, 2),
+		// Some firmwares store the value without prefix
+		val => (val, 1),
+	};
+	*off += len;
+	Some(val)
+}
+
+/// Returns the values of the sleep type registers (`SLP_TYPa` and `SLP_TYPb`) for the sleep
+/// state object named `name` (example: `_S5_`) declared in the given AML code.
+///
+/// Since AML code cannot be interpreted yet, the object is found by scanning the bytecode for
+/// its declaration.
+///
+/// If the object is not found, the function returns `None`.
+pub fn find_sleep_type(aml: &[u8], name: &[u8; 4]) -> Option<(u8, u8)> {
+	let pos = aml.windows(name.len()).enumerate().find_map(|(i, w)| {
+		// The name must be declared by `NameOp`, optionally with the root prefix
+		let declared = (i >= 1 && aml[i - 1] == NAME_OP)
+			|| (i >= 2 && aml[i - 2] == NAME_OP && aml[i - 1] == b'\\');
+		(w == name && declared).then_some(i)
+	})?;
+
+	let mut off = pos + name.len();
+	if *aml.get(off)? != PACKAGE_OP {
+		return None;
+	}
+	// Skip `PkgLength`, whose first byte tells the number of bytes following it
+	let pkg_len_bytes = (*aml.get(off + 1)? >> 6) as usize;
+	// Skip `NumElements`
+	off += 2 + pkg_len_bytes + 1;
+
+	let slp_typa = read_byte_const(aml, &mut off)?;
+	let slp_typb = read_byte_const(aml, &mut off)?;
+	Some((slp_typa, slp_typb))
+}
@@ -7,16 +7,22 @@
 //! The structure implemented in this module uses a temporary virtual memory
 //! context to get a copy of the data.
 
+use crate::acpi::dsdt::Dsdt;
+use crate::acpi::fadt::Fadt;
 use crate::acpi::rsdt::Rsdt;
 use crate::acpi::ACPITable;
 use crate::acpi::ACPITableHeader;
+use crate::errno;
 use crate::errno::Errno;
 use crate::memory;
 use crate::memory::malloc;
 use crate::memory::vmem;
+use crate::memory::vmem::VMem;
 use crate::util;
-use crate::util::boxed::Box;
-use crate::util::container::hashmap::HashMap;
+use crate::util::container::vec::Vec;
+use crate::util::math;
+use core::cmp::max;
+use core::cmp::min;
 use core::ffi::c_void;
 use core::mem::size_of;
 use core::num::NonZeroUsize;
@@ -112,6 +118,30 @@ unsafe fn find_rsdp() -> Option<&'static mut Rsdp> {
 	None
 }
 
+/// The virtual address at which physical memory is mapped on the temporary virtual memory
+/// context to be read.
+const TMP_MAP_BEGIN: usize = memory::PAGE_SIZE;
+
+/// Maps `pages` pages of physical memory beginning at the page containing `phys` on the
+/// temporary virtual memory context `vmem`, then returns the virtual address corresponding to
+/// `phys`.
+///
+/// Previous mappings made with this function are overwritten.
+fn tmp_map(vmem: &dyn VMem, phys: *const c_void, pages: usize) -> Result<*const c_void, Errno> {
+	let begin = util::down_align(phys, memory::PAGE_SIZE);
+	vmem.map_range(begin, TMP_MAP_BEGIN as _, pages, 0)?;
+	Ok((TMP_MAP_BEGIN + (phys as usize - begin as usize)) as _)
+}
+
+/// Returns the length of the ACPI table located at the physical address `phys`.
+///
+/// `vmem` is the temporary virtual memory context used to read physical memory.
+fn get_table_length(vmem: &dyn VMem, phys: *const c_void) -> Result<usize, Errno> {
+	// The header may cross a page boundary
+	let header = tmp_map(vmem, phys, 2)? as *const ACPITableHeader;
+	Ok(unsafe { (*header).get_length() })
+}
+
 /// Structure containing a copy of the ACPI data read from memory.
 #[derive(Debug)]
 pub struct ACPIData {
@@ -125,7 +155,96 @@ pub struct ACPIData {
 }
 
 impl ACPIData {
-	// TODO Clean
+	/// Returns the physical address and length of the DSDT referenced by the FADT located at
+	/// `fadt_phys`.
+	///
+	/// `vmem` is the temporary virtual memory context used to read physical memory.
+	fn get_dsdt_range(
+		vmem: &dyn VMem,
+		fadt_phys: *const c_void,
+	) -> Result<Option<(usize, usize)>, Errno> {
+		let len = get_table_length(vmem, fadt_phys)?;
+		let fadt = tmp_map(vmem, fadt_phys, math::ceil_div(len, memory::PAGE_SIZE) + 1)?;
+		let fadt = unsafe {
+			// Safe because the pointer has been mapped before
+			&*(fadt as *const Fadt)
+		};
+		let Some(dsdt_phys) = fadt.get_dsdt_addr() else {
+			return Ok(None);
+		};
+		let dsdt_len = get_table_length(vmem, dsdt_phys as _)?;
+		Ok(Some((dsdt_phys, dsdt_len)))
+	}
+
+	/// Copies the physical memory range containing the RSDT at `rsdt_phys` and every table it
+	/// references, including the DSDT.
+	///
+	/// `vmem` is the temporary virtual memory context used to read physical memory. It must be
+	/// bound.
+	///
+	/// The function returns the physical address of the beginning of the range along with the
+	/// copy.
+	fn copy_tables(
+		vmem: &dyn VMem,
+		rsdt_phys: *const c_void,
+	) -> Result<(usize, malloc::Alloc<u8>), Errno> {
+		// Getting the physical address of every tables
+		let rsdt_len = get_table_length(vmem, rsdt_phys)?;
+		let rsdt = tmp_map(
+			vmem,
+			rsdt_phys,
+			math::ceil_div(rsdt_len, memory::PAGE_SIZE) + 1,
+		)?;
+		let rsdt = unsafe {
+			// Safe because the pointer has been mapped before
+			&*(rsdt as *const Rsdt)
+		};
+		if !rsdt.header.check::<Rsdt>() {
+			panic!("Invalid ACPI structure!");
+		}
+		let mut tables = Vec::new();
+		let mut res = Ok(());
+		rsdt.foreach_table(|table| {
+			if res.is_ok() {
+				res = tables.push(table as *const c_void);
+			}
+		});
+		res?;
+
+		// Computing the range of physical memory containing every tables
+		let mut begin = rsdt_phys as usize;
+		let mut end = begin + rsdt_len;
+		for table in tables {
+			let len = get_table_length(vmem, table)?;
+			begin = min(begin, table as usize);
+			end = max(end, table as usize + len);
+
+			// The DSDT is referenced by the FADT instead of the RSDT
+			let header = tmp_map(vmem, table, 2)? as *const ACPITableHeader;
+			let signature = unsafe { *(*header).get_signature() };
+			if signature == *Fadt::get_expected_signature() {
+				if let Some((dsdt, dsdt_len)) = Self::get_dsdt_range(vmem, table)? {
+					begin = min(begin, dsdt);
+					end = max(end, dsdt + dsdt_len);
+				}
+			}
+		}
+
+		// Copying the range
+		let size = NonZeroUsize::new(end - begin).ok_or_else(|| errno!(EINVAL))?;
+		let pages = math::ceil_div(
+			end - util::down_align(begin as *const c_void, memory::PAGE_SIZE) as usize,
+			memory::PAGE_SIZE,
+		);
+		let src = tmp_map(vmem, begin as _, pages)?;
+		let mut data = malloc::Alloc::<u8>::new_default(size)?;
+		unsafe {
+			copy_nonoverlapping(src as *const u8, data.as_ptr_mut(), size.get());
+		}
+
+		Ok((begin, data))
+	}
+
 	/// Reads the ACPI data from memory and returns a buffer containing it with its offset in
 	/// physical memory.
 	///
@@ -141,81 +260,14 @@ impl ACPIData {
 			panic!("Invalid ACPI pointer!");
 		}
 
-		// Temporary vmem used to read the data, since it cannot be located anywhere on the
-		// physical memory.
+		// Temporary vmem used to read the data, since it can be located anywhere on the physical
+		// memory
 		let tmp_vmem = vmem::new()?;
 		let rsdt_phys_ptr = rsdp.rsdt_address as *const c_void;
-		let rsdt_map_begin = util::down_align(rsdt_phys_ptr, memory::PAGE_SIZE);
-		crate::println!("being: {:p}", rsdt_map_begin); // TODO rm
-												// Mapping the RSDT to make it readable
-		tmp_vmem.map_range(rsdt_map_begin, memory::PAGE_SIZE as _, 2, 0)?;
-
 		tmp_vmem.bind();
-		let (off, data) = {
-			let rsdt_ptr = (memory::PAGE_SIZE + (rsdt_phys_ptr as usize - rsdt_map_begin as usize))
-				as *const Rsdt;
-			let rsdt = unsafe {
-				// Safe because the pointer has been mapped before
-				&*rsdt_ptr
-			};
-
-			if !rsdt.header.check::<Rsdt>() {
-				panic!("Invalid ACPI structure!");
-			}
-
-			// Getting every ACPI tables
-			let mut tables = HashMap::new();
-			rsdt.foreach_table(|table_ptr| {
-				// Mapping the table to read its length
-				let table_map_begin = util::down_align(table_ptr, memory::PAGE_SIZE);
-				if tmp_vmem
-					.map_range(table_map_begin as _, (memory::PAGE_SIZE * 3) as _, 2, 0)
-					.is_err()
-				{
-					panic!("Unexpected error when reading ACPI data");
-				}
-
-				let table_offset = table_ptr as usize - table_map_begin as usize;
-				let table = unsafe {
-					// Safe because the pointer has been mapped before
-					&*(((memory::PAGE_SIZE * 3) + table_offset) as *const ACPITableHeader)
-				};
-
-				let b = unsafe {
-					let mut ptr = malloc::alloc(table.get_length().try_into().unwrap()).unwrap();
-					ptr::copy_nonoverlapping(
-						table as *const _ as *const _,
-						ptr.as_mut(),
-						table.get_length(),
-					);
-
-					Box::from_raw(ptr.as_ptr() as *mut ())
-				};
-				tables.insert(table.get_signature(), b).unwrap();
-			});
-
-			// FIXME: This is garbage due to a merge in order to make things compile.
-			// This whole function needs a rewrite
-			let lowest = core::ptr::null::<c_void>();
-			let highest = core::ptr::null::<c_void>();
-
-			// Mapping the full ACPI data
-			let begin = util::down_align(lowest, memory::PAGE_SIZE);
-			let end = util::align(highest, memory::PAGE_SIZE);
-			let pages = (end as usize - begin as usize) / memory::PAGE_SIZE;
-			tmp_vmem.map_range(begin, memory::PAGE_SIZE as _, pages, 0)?;
-
-			// FIXME: unwrap here is garbage
-			let size = NonZeroUsize::new(pages * memory::PAGE_SIZE).unwrap();
-			let mut dest = malloc::Alloc::<u8>::new_default(size)?;
-			let src = memory::PAGE_SIZE as *const u8;
-			unsafe {
-				copy_nonoverlapping(src, dest.as_ptr_mut(), size.get());
-			}
-
-			(begin as usize, dest)
-		};
+		let res = Self::copy_tables(&*tmp_vmem, rsdt_phys_ptr);
 		crate::bind_vmem();
+		let (off, data) = res?;
 
 		Ok(Some(Self {
 			off,
@@ -225,6 +277,25 @@ impl ACPIData {
 		}))
 	}
 
+	/// Returns a reference to the DSDT, referenced by the FADT.
+	///
+	/// If the table doesn't exist, the function returns `None`.
+	pub fn get_dsdt(&self) -> Option<&Dsdt> {
+		let dsdt_phys = self.get_table_sized::<Fadt>()?.get_dsdt_addr()?;
+		let header_ptr = unsafe { self.data.as_ptr().add(dsdt_phys - self.off) };
+		let header = unsafe { &*(header_ptr as *const ACPITableHeader) };
+		let table = unsafe {
+			let table_ptr =
+				ptr::from_raw_parts::<Dsdt>(header_ptr as *const (), header.get_length());
+			&*table_ptr
+		};
+		if !table.get_header().check::<Dsdt>() {
+			panic!("Invalid ACPI structure!");
+		}
+
+		Some(table)
+	}
+
 	// TODO Minimize duplicate code between get_table_*
 
 	/// Returns a reference to the ACPI table with type `T`.
@@ -1,23 +1,38 @@
 //! This module handles ACPI's Fixed ACPI Description Table (FADT).
 
-use super::dsdt::Dsdt;
 use super::ACPITable;
 use super::ACPITableHeader;
-use core::slice;
+use core::mem::offset_of;
+use core::mem::size_of;
 
-/// TODO doc
+/// Address space of a [`GenericAddr`]: system memory.
+pub const ADDR_SPACE_MEMORY: u8 = 0;
+/// Address space of a [`GenericAddr`]: system I/O ports.
+pub const ADDR_SPACE_IO: u8 = 1;
+
+/// FADT flag: the reset register is supported.
+pub const FLAG_RESET_REG_SUP: u32 = 1 << 10;
+
+/// A register address, as described by the Generic Address Structure of the ACPI
+/// specification.
+#[repr(C, packed)]
 pub struct GenericAddr {
-	addr_space: u8,
-	bit_width: u8,
-	bit_offset: u8,
-	access_size: u8,
-	address: u8,
+	/// The address space in which the register is located.
+	pub addr_space: u8,
+	/// The size of the register in bits.
+	pub bit_width: u8,
+	/// The offset of the register in bits.
+	pub bit_offset: u8,
+	/// The access size.
+	pub access_size: u8,
+	/// The address of the register in its address space.
+	pub address: u64,
 }
 
 /// The Fixed ACPI Description Table.
 ///
 /// The documentation of every fields can be found in the ACPI documentation.
-#[repr(C)]
+#[repr(C, packed)]
 pub struct Fadt {
 	/// The table's header.
 	pub header: ACPITableHeader,
@@ -84,29 +99,19 @@ pub struct Fadt {
 }
 
 impl Fadt {
-	/// Returns a reference to the DSDT if it exists.
-	pub fn get_dsdt(&self) -> Option<&Dsdt> {
-		let dsdt = if self.x_dsdt != 0 {
-			self.x_dsdt
-		} else {
-			self.dsdt as _
-		} as *const u8;
-		// TODO Add displacement relative to ACPI data buffer
-
-		if !dsdt.is_null() {
-			let dsdt = unsafe {
-				let dsdt_slice = slice::from_raw_parts(dsdt, 1);
-				&*(dsdt_slice as *const [u8] as *const [()] as *const Dsdt)
-			};
-
-			if !dsdt.get_header().check::<Dsdt>() {
-				panic!("Invalid ACPI structure!");
-			}
-
-			Some(dsdt)
+	/// Returns the physical address of the DSDT.
+	///
+	/// If the table doesn't reference a DSDT, the function returns `None`.
+	pub fn get_dsdt_addr(&self) -> Option<usize> {
+		// The extended field is present only from revision 2
+		let x_dsdt_end = offset_of!(Self, x_dsdt) + size_of::<u64>();
+		let x_dsdt = if self.get_header().get_length() >= x_dsdt_end {
+			usize::try_from(self.x_dsdt).unwrap_or(0)
 		} else {
-			None
-		}
+			0
+		};
+		let dsdt = if x_dsdt != 0 { x_dsdt } else { self.dsdt as _ };
+		(dsdt != 0).then_some(dsdt)
 	}
 }
 
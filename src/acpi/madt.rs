@@ -25,6 +25,11 @@ pub struct Madt {
 }
 
 impl Madt {
+	/// Returns the physical address of the local APIC's registers.
+	pub fn get_local_apic_addr(&self) -> u32 {
+		self.local_apic_addr
+	}
+
 	/// Executes the given closure for each entry in the MADT.
 	pub fn foreach_entry<F: Fn(&EntryHeader)>(&self, f: F) {
 		let entries_len = self.header.get_length() - ENTRIES_OFF;
@@ -47,6 +52,14 @@ impl ACPITable for Madt {
 	}
 }
 
+/// Entry type: a processor and its local APIC.
+pub const ENTRY_LOCAL_APIC: u8 = 0;
+
+/// Local APIC flag: the processor is enabled.
+const LOCAL_APIC_ENABLED: u32 = 0b01;
+/// Local APIC flag: the processor can be enabled at runtime.
+const LOCAL_APIC_ONLINE_CAPABLE: u32 = 0b10;
+
 /// Represents an MADT entry header.
 #[repr(C)]
 #[derive(Debug)]
@@ -68,3 +81,30 @@ impl EntryHeader {
 		self.length
 	}
 }
+
+/// An MADT entry describing a processor and its local APIC.
+#[repr(C, packed)]
+pub struct LocalApic {
+	/// The entry's header.
+	pub header: EntryHeader,
+
+	/// The ACPI ID of the processor.
+	processor_id: u8,
+	/// The ID of the processor's local APIC.
+	apic_id: u8,
+	/// Flags.
+	flags: u32,
+}
+
+impl LocalApic {
+	/// Returns the ID of the processor's local APIC.
+	pub fn get_apic_id(&self) -> u8 {
+		self.apic_id
+	}
+
+	/// Tells whether the processor can be used.
+	pub fn is_usable(&self) -> bool {
+		let flags = self.flags;
+		flags & (LOCAL_APIC_ENABLED | LOCAL_APIC_ONLINE_CAPABLE) != 0
+	}
+}
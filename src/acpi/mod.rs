@@ -6,9 +6,10 @@
 //!   available tables.
 //! - TODO
 
+use crate::cpu::smp;
+use core::ffi::c_void;
 use core::mem::size_of;
 use data::ACPIData;
-use dsdt::Dsdt;
 use fadt::Fadt;
 use madt::Madt;
 
@@ -17,6 +18,7 @@ mod data;
 mod dsdt;
 mod fadt;
 mod madt;
+pub mod power;
 mod rsdt;
 
 /// An ACPI table header.
@@ -115,12 +117,17 @@ pub fn init() {
 	if let Some(data) = data {
 		if let Some(madt) = data.get_table_sized::<Madt>() {
 			// Registering CPU cores
-			madt.foreach_entry(|e: &madt::EntryHeader| match e.get_type() {
-				0 => {
-					// TODO Register a new CPU
+			madt.foreach_entry(|e: &madt::EntryHeader| {
+				if e.get_type() == madt::ENTRY_LOCAL_APIC {
+					let entry = unsafe { &*(e as *const _ as *const madt::LocalApic) };
+					if entry.is_usable() {
+						smp::register_cpu(entry.get_apic_id());
+					}
 				}
-
-				_ => {}
+			});
+			let apic_addr = madt.get_local_apic_addr() as usize as *mut c_void;
+			smp::init_bsp(apic_addr).unwrap_or_else(|e| {
+				panic!("Cannot initialize the local APIC! ({e})");
 			});
 		}
 
@@ -132,17 +139,13 @@ pub fn init() {
 				.map_or(false, |fadt| fadt.century != 0);
 		}
 
-		// Getting the DSDT
-		let dsdt = data.get_table_unsized::<Dsdt>().or_else(|| {
-			data.get_table_sized::<Fadt>()
-				.and_then(|fadt| fadt.get_dsdt())
-		});
-		if let Some(dsdt) = dsdt {
-			// Parsing AML code
-			let aml = dsdt.get_aml();
-			let _ast = aml::parse(aml);
-
-			// TODO
+		// Getting the sleep type values for the soft-off state
+		let s5 = data
+			.get_dsdt()
+			.and_then(|dsdt| aml::find_sleep_type(dsdt.get_aml(), b"_S5_"));
+		if let Some(fadt) = data.get_table_sized::<Fadt>() {
+			power::init(fadt, s5);
 		}
+		// TODO Parse and interpret AML code
 	}
 }
@@ -0,0 +1,135 @@
+//! ACPI power management allows to power off and to reset the system through the registers
+//! described by the FADT.
+
+use super::fadt;
+use super::fadt::Fadt;
+use super::ACPITable;
+use crate::io;
+
+/// The bit of the PM1 control registers telling whether ACPI mode is enabled (`SCI_EN`).
+const SCI_EN: u16 = 1;
+/// The bit of the PM1 control registers triggering the transition to the sleep state (`SLP_EN`).
+const SLP_EN: u16 = 1 << 13;
+/// The offset of the sleep type field (`SLP_TYPx`) in the PM1 control registers.
+const SLP_TYP_SHIFT: u16 = 10;
+
+/// The number of polls of the PM1 control register to wait for ACPI mode to be enabled.
+const ENABLE_POLLS: usize = 300000;
+
+/// Informations required to power off and reset the system.
+#[derive(Clone, Copy, Debug)]
+struct PowerInfo {
+	/// The port of the System Management Interrupt command register.
+	smi_cmd: u16,
+	/// The value to write to `smi_cmd` to enable ACPI mode.
+	acpi_enable: u8,
+
+	/// The port of the PM1a control register.
+	pm1a_cnt: u16,
+	/// The port of the PM1b control register. If zero, the register doesn't exist.
+	pm1b_cnt: u16,
+	/// The values of `SLP_TYPa` and `SLP_TYPb` for the soft-off state (S5), if known.
+	s5: Option<(u8, u8)>,
+
+	/// The port of the reset register along with the value to write to it, if supported.
+	reset: Option<(u16, u8)>,
+}
+
+/// The power management informations, set at boot.
+static mut POWER_INFO: Option<PowerInfo> = None;
+
+/// Initializes power management from the FADT `fadt`.
+///
+/// `s5` is the values of the sleep type registers for the soft-off state, found in the DSDT.
+///
+/// This function must be called only once, at boot.
+pub(super) fn init(fadt: &Fadt, s5: Option<(u8, u8)>) {
+	// The reset register is available from revision 2 and is supported only on I/O space
+	let reset_reg = &fadt.reset_reg;
+	let reset = (fadt.get_header().revision >= 2
+		&& fadt.flags & fadt::FLAG_RESET_REG_SUP != 0
+		&& reset_reg.addr_space == fadt::ADDR_SPACE_IO)
+		.then_some((reset_reg.address as u16, fadt.reset_value));
+
+	let info = PowerInfo {
+		smi_cmd: fadt.smi_commandport as _,
+		acpi_enable: fadt.acpi_enable,
+
+		pm1a_cnt: fadt.pm1a_control_block as _,
+		pm1b_cnt: fadt.pm1b_control_block as _,
+		s5,
+
+		reset,
+	};
+	unsafe {
+		// Safe because the value is only set once
+		POWER_INFO = Some(info);
+	}
+}
+
+/// Returns the power management informations, if available.
+fn get_info() -> Option<PowerInfo> {
+	unsafe {
+		// Safe because the value is only set once at boot
+		POWER_INFO
+	}
+}
+
+/// Enables ACPI mode if the firmware has not done it already.
+///
+/// If ACPI mode cannot be enabled, the function returns `false`.
+fn enable(info: &PowerInfo) -> bool {
+	if unsafe { io::inw(info.pm1a_cnt) } & SCI_EN != 0 {
+		return true;
+	}
+	if info.smi_cmd == 0 || info.acpi_enable == 0 {
+		return false;
+	}
+
+	unsafe {
+		io::outb(info.smi_cmd, info.acpi_enable);
+	}
+	(0..ENABLE_POLLS).any(|_| unsafe { io::inw(info.pm1a_cnt) } & SCI_EN != 0)
+}
+
+/// Powers the system off by entering the soft-off state (S5).
+///
+/// If the operation is not supported, the function returns.
+pub fn poweroff() {
+	let Some(info) = get_info() else {
+		return;
+	};
+	let Some((slp_typa, slp_typb)) = info.s5 else {
+		return;
+	};
+	if info.pm1a_cnt == 0 || !enable(&info) {
+		return;
+	}
+
+	unsafe {
+		let val = io::inw(info.pm1a_cnt) & !(0b111 << SLP_TYP_SHIFT);
+		io::outw(
+			info.pm1a_cnt,
+			val | ((slp_typa as u16) << SLP_TYP_SHIFT) | SLP_EN,
+		);
+		if info.pm1b_cnt != 0 {
+			let val = io::inw(info.pm1b_cnt) & !(0b111 << SLP_TYP_SHIFT);
+			io::outw(
+				info.pm1b_cnt,
+				val | ((slp_typb as u16) << SLP_TYP_SHIFT) | SLP_EN,
+			);
+		}
+	}
+}
+
+/// Resets the system using the reset register.
+///
+/// If the operation is not supported, the function returns.
+pub fn reset() {
+	let Some((port, value)) = get_info().and_then(|info| info.reset) else {
+		return;
+	};
+	unsafe {
+		io::outb(port, value);
+	}
+}
@@ -115,6 +115,8 @@ pub struct ArgsParser<'s> {
 	init: Option<&'s [u8]>,
 	/// Whether the kernel boots silently.
 	silent: bool,
+	/// Whether memory mappings are allowed to be both writable and executable.
+	allow_wx: bool,
 }
 
 impl<'s> ArgsParser<'s> {
@@ -124,6 +126,7 @@ impl<'s> ArgsParser<'s> {
 			root: None,
 			init: None,
 			silent: false,
+			allow_wx: false,
 		};
 
 		let mut iter = TokenIterator {
@@ -176,6 +179,8 @@ impl<'s> ArgsParser<'s> {
 
 				b"-silent" => s.silent = true,
 
+				b"-allow-wx" => s.allow_wx = true,
+
 				_ => {
 					return Err(ParseError {
 						cmdline,
@@ -203,6 +208,11 @@ impl<'s> ArgsParser<'s> {
 	pub fn is_silent(&self) -> bool {
 		self.silent
 	}
+
+	/// If `true`, userspace is allowed to create mappings that are both writable and executable.
+	pub fn is_write_exec_allowed(&self) -> bool {
+		self.allow_wx
+	}
 }
 
 #[cfg(test)]
@@ -248,4 +258,10 @@ mod test {
 	fn cmdline7() {
 		assert!(ArgsParser::parse(b"-root 1 0 -init bleh -silent").is_ok());
 	}
+
+	#[test_case]
+	fn cmdline8() {
+		let args = ArgsParser::parse(b"-root 1 0 -allow-wx").unwrap();
+		assert!(args.is_write_exec_allowed());
+	}
 }
@@ -0,0 +1,7 @@
+//! This module implements decompression algorithms.
+//!
+//! Decompressors take the whole compressed data and write the result to a buffer large enough
+//! to hold it.
+
+pub mod zlib;
+pub mod zstd;
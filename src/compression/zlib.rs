@@ -0,0 +1,387 @@
+//! zlib is a format wrapping a DEFLATE stream with a header and a checksum. DEFLATE compresses
+//! data with LZ77 and Huffman coding.
+//!
+//! The formats are defined by RFC1950 and RFC1951.
+
+use crate::crypto::checksum;
+use crate::errno;
+use crate::errno::EResult;
+
+/// The maximum length of a Huffman code in bits.
+const MAX_BITS: usize = 15;
+/// The maximum number of literal/length codes.
+const MAX_LITLEN_CODES: usize = 288;
+/// The maximum number of distance codes.
+const MAX_DIST_CODES: usize = 32;
+
+/// The base lengths for length codes `257` to `285`.
+const LENGTH_BASE: [u16; 29] = [
+	3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+	163, 195, 227, 258,
+];
+/// The number of extra bits for length codes `257` to `285`.
+const LENGTH_EXTRA: [u8; 29] = [
+	0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+/// The base distances for distance codes `0` to `29`.
+const DIST_BASE: [u16; 30] = [
+	1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+	2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+/// The number of extra bits for distance codes `0` to `29`.
+const DIST_EXTRA: [u8; 30] = [
+	0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+	13,
+];
+/// The order in which code length code lengths are stored in dynamic blocks.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+	16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Reads a stream bit by bit, starting from the least significant bit of each byte.
+struct BitReader<'a> {
+	/// The stream.
+	src: &'a [u8],
+	/// The offset of the next byte to be loaded.
+	off: usize,
+
+	/// Bits loaded but not consumed yet.
+	buf: u32,
+	/// The number of bits in `buf`.
+	count: u32,
+}
+
+impl<'a> BitReader<'a> {
+	/// Reads `n` bits, with `n` lower than or equal to `16`.
+	fn bits(&mut self, n: u32) -> EResult<u32> {
+		while self.count < n {
+			let byte = *self.src.get(self.off).ok_or_else(|| errno!(EINVAL))?;
+			self.off += 1;
+			self.buf |= (byte as u32) << self.count;
+			self.count += 8;
+		}
+
+		let val = self.buf & ((1 << n) - 1);
+		self.buf >>= n;
+		self.count -= n;
+		Ok(val)
+	}
+
+	/// Discards the remaining bits of the current byte.
+	fn align(&mut self) {
+		self.buf = 0;
+		self.count = 0;
+	}
+}
+
+/// A canonical Huffman code.
+struct Huffman<const N: usize> {
+	/// The number of codes for each length.
+	count: [u16; MAX_BITS + 1],
+	/// The symbols, sorted by code.
+	symbol: [u16; N],
+}
+
+impl<const N: usize> Huffman<N> {
+	/// Builds the code from the length of the code of each symbol.
+	///
+	/// A length of zero means the symbol is not used.
+	fn new(lengths: &[u8]) -> EResult<Self> {
+		let mut code = Self {
+			count: [0; MAX_BITS + 1],
+			symbol: [0; N],
+		};
+		for len in lengths {
+			code.count[*len as usize] += 1;
+		}
+
+		// Check the code is not over-subscribed
+		let mut left: i32 = 1;
+		for len in 1..=MAX_BITS {
+			left = (left << 1) - code.count[len] as i32;
+			if left < 0 {
+				return Err(errno!(EINVAL));
+			}
+		}
+
+		// Offset of the first symbol of each length in the table
+		let mut offs = [0u16; MAX_BITS + 1];
+		for len in 1..MAX_BITS {
+			offs[len + 1] = offs[len] + code.count[len];
+		}
+		for (sym, len) in lengths.iter().enumerate() {
+			if *len != 0 {
+				code.symbol[offs[*len as usize] as usize] = sym as _;
+				offs[*len as usize] += 1;
+			}
+		}
+
+		Ok(code)
+	}
+
+	/// Decodes a symbol from `reader`.
+	fn decode(&self, reader: &mut BitReader) -> EResult<u16> {
+		// The code read so far, the first code of the current length and its index in the table
+		let mut code: i32 = 0;
+		let mut first: i32 = 0;
+		let mut index: i32 = 0;
+		for len in 1..=MAX_BITS {
+			code |= reader.bits(1)? as i32;
+			let count = self.count[len] as i32;
+			if code - first < count {
+				return Ok(self.symbol[(index + code - first) as usize]);
+			}
+			index += count;
+			first = (first + count) << 1;
+			code <<= 1;
+		}
+
+		// Incomplete code
+		Err(errno!(EINVAL))
+	}
+}
+
+/// Decodes the content of a compressed block with the given codes.
+///
+/// `out` is the offset in `dst` at which decompressed data is written. It is updated by the
+/// function.
+fn decode_block(
+	reader: &mut BitReader,
+	dst: &mut [u8],
+	out: &mut usize,
+	litlen: &Huffman<MAX_LITLEN_CODES>,
+	dist: &Huffman<MAX_DIST_CODES>,
+) -> EResult<()> {
+	loop {
+		let sym = litlen.decode(reader)? as usize;
+		match sym {
+			0..=255 => {
+				*dst.get_mut(*out).ok_or_else(|| errno!(EINVAL))? = sym as u8;
+				*out += 1;
+			}
+
+			256 => return Ok(()),
+
+			_ => {
+				let sym = sym - 257;
+				if sym >= LENGTH_BASE.len() {
+					return Err(errno!(EINVAL));
+				}
+				let len =
+					LENGTH_BASE[sym] as usize + reader.bits(LENGTH_EXTRA[sym] as _)? as usize;
+
+				let sym = dist.decode(reader)? as usize;
+				if sym >= DIST_BASE.len() {
+					return Err(errno!(EINVAL));
+				}
+				let distance =
+					DIST_BASE[sym] as usize + reader.bits(DIST_EXTRA[sym] as _)? as usize;
+
+				if distance > *out || *out + len > dst.len() {
+					return Err(errno!(EINVAL));
+				}
+				// Copy byte by byte since the source and destination may overlap
+				for i in *out..(*out + len) {
+					dst[i] = dst[i - distance];
+				}
+				*out += len;
+			}
+		}
+	}
+}
+
+/// Builds the codes of a dynamic block from its header.
+fn read_dynamic_codes(
+	reader: &mut BitReader,
+) -> EResult<(Huffman<MAX_LITLEN_CODES>, Huffman<MAX_DIST_CODES>)> {
+	let nlen = reader.bits(5)? as usize + 257;
+	let ndist = reader.bits(5)? as usize + 1;
+	let ncode = reader.bits(4)? as usize + 4;
+	if nlen > 286 || ndist > 30 {
+		return Err(errno!(EINVAL));
+	}
+
+	let mut lengths = [0u8; MAX_LITLEN_CODES + MAX_DIST_CODES];
+	for i in CODE_LENGTH_ORDER.iter().take(ncode) {
+		lengths[*i] = reader.bits(3)? as _;
+	}
+	let lencode: Huffman<19> = Huffman::new(&lengths[..19])?;
+
+	let mut i = 0;
+	while i < nlen + ndist {
+		let sym = lencode.decode(reader)?;
+		let (val, repeat) = match sym {
+			0..=15 => (sym as u8, 1),
+			16 => {
+				let prev = *i
+					.checked_sub(1)
+					.and_then(|i| lengths.get(i))
+					.ok_or_else(|| errno!(EINVAL))?;
+				(prev, 3 + reader.bits(2)? as usize)
+			}
+			17 => (0, 3 + reader.bits(3)? as usize),
+			_ => (0, 11 + reader.bits(7)? as usize),
+		};
+		if i + repeat > nlen + ndist {
+			return Err(errno!(EINVAL));
+		}
+		lengths[i..(i + repeat)].fill(val);
+		i += repeat;
+	}
+	// The end-of-block code is required
+	if lengths[256] == 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	Ok((
+		Huffman::new(&lengths[..nlen])?,
+		Huffman::new(&lengths[nlen..(nlen + ndist)])?,
+	))
+}
+
+/// Decompresses the DEFLATE stream `src` into `dst`.
+///
+/// On success, the function returns the number of bytes consumed from `src` and the number of
+/// bytes written to `dst`.
+fn inflate_impl(src: &[u8], dst: &mut [u8]) -> EResult<(usize, usize)> {
+	let mut reader = BitReader {
+		src,
+		off: 0,
+
+		buf: 0,
+		count: 0,
+	};
+	let mut out = 0;
+
+	loop {
+		let last = reader.bits(1)? != 0;
+		match reader.bits(2)? {
+			// Stored
+			0 => {
+				reader.align();
+				let header = src
+					.get(reader.off..(reader.off + 4))
+					.ok_or_else(|| errno!(EINVAL))?;
+				let len = u16::from_le_bytes([header[0], header[1]]) as usize;
+				let nlen = u16::from_le_bytes([header[2], header[3]]) as usize;
+				if len != !nlen & 0xffff {
+					return Err(errno!(EINVAL));
+				}
+				reader.off += 4;
+
+				let data = src
+					.get(reader.off..(reader.off + len))
+					.ok_or_else(|| errno!(EINVAL))?;
+				dst.get_mut(out..(out + len))
+					.ok_or_else(|| errno!(EINVAL))?
+					.copy_from_slice(data);
+				reader.off += len;
+				out += len;
+			}
+
+			// Fixed Huffman codes
+			1 => {
+				let mut lengths = [0u8; MAX_LITLEN_CODES];
+				lengths[..144].fill(8);
+				lengths[144..256].fill(9);
+				lengths[256..280].fill(7);
+				lengths[280..].fill(8);
+				let litlen = Huffman::new(&lengths)?;
+				let dist = Huffman::new(&[5; 30])?;
+				decode_block(&mut reader, dst, &mut out, &litlen, &dist)?;
+			}
+
+			// Dynamic Huffman codes
+			2 => {
+				let (litlen, dist) = read_dynamic_codes(&mut reader)?;
+				decode_block(&mut reader, dst, &mut out, &litlen, &dist)?;
+			}
+
+			_ => return Err(errno!(EINVAL)),
+		}
+
+		if last {
+			break;
+		}
+	}
+
+	Ok((reader.off, out))
+}
+
+/// Decompresses the raw DEFLATE stream `src` into `dst`.
+///
+/// On success, the function returns the number of bytes written to `dst`.
+///
+/// If the stream is invalid or if `dst` is too small, the function returns an error.
+pub fn inflate(src: &[u8], dst: &mut [u8]) -> EResult<usize> {
+	inflate_impl(src, dst).map(|(_, len)| len)
+}
+
+/// Decompresses the zlib stream `src` into `dst`.
+///
+/// On success, the function returns the number of bytes written to `dst`.
+///
+/// If the stream is invalid, uses a preset dictionary, or if `dst` is too small, the function
+/// returns an error.
+pub fn decompress(src: &[u8], dst: &mut [u8]) -> EResult<usize> {
+	let [cmf, flg, ..] = *src else {
+		return Err(errno!(EINVAL));
+	};
+	let method = cmf & 0xf;
+	let window_log = cmf >> 4;
+	let dict = flg & 0x20 != 0;
+	if method != 8 || window_log > 7 || ((cmf as u16) << 8 | flg as u16) % 31 != 0 || dict {
+		return Err(errno!(EINVAL));
+	}
+
+	let (consumed, len) = inflate_impl(&src[2..], dst)?;
+	let checksum = src
+		.get((2 + consumed)..(2 + consumed + 4))
+		.ok_or_else(|| errno!(EINVAL))?;
+	let checksum = u32::from_be_bytes(checksum.try_into().unwrap());
+	if checksum != checksum::compute_adler32(&dst[..len]) {
+		return Err(errno!(EINVAL));
+	}
+
+	Ok(len)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn zlib_stored() {
+		let src = [
+			0x78, 0x01, 0x01, 0x05, 0x00, 0xfa, 0xff, b'h', b'e', b'l', b'l', b'o', 0x06, 0x2c,
+			0x02, 0x15,
+		];
+		let mut dst = [0u8; 16];
+		assert_eq!(decompress(&src, &mut dst), Ok(5));
+		assert_eq!(&dst[..5], b"hello");
+	}
+
+	#[test_case]
+	fn zlib_fixed() {
+		let src = [
+			0x78, 0x9c, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x00, 0x3a, 0x2e,
+			0x06, 0x7d,
+		];
+		let mut dst = [0u8; 32];
+		assert_eq!(decompress(&src, &mut dst), Ok(17));
+		assert_eq!(&dst[..17], b"hello hello hello");
+	}
+
+	#[test_case]
+	fn zlib_invalid() {
+		let mut dst = [0u8; 32];
+		assert!(decompress(&[], &mut dst).is_err());
+		assert!(decompress(&[0x78, 0x9c, 0xff], &mut dst).is_err());
+		// Destination too small
+		let src = [
+			0x78, 0x9c, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x00, 0x3a, 0x2e,
+			0x06, 0x7d,
+		];
+		assert!(decompress(&src, &mut dst[..4]).is_err());
+	}
+}
@@ -0,0 +1,937 @@
+//! Zstandard is a compression format combining LZ77 with Huffman and Finite State Entropy (FSE)
+//! coding.
+//!
+//! The format is defined by RFC8878. Dictionaries are not supported.
+
+use crate::crypto::checksum;
+use crate::errno;
+use crate::errno::EResult;
+use crate::util::container::vec::Vec;
+use core::cmp::min;
+
+/// The magic number of a frame.
+const MAGIC: u32 = 0xfd2fb528;
+/// The magic number of a skippable frame. The lowest 4 bits can have any value.
+const SKIPPABLE_MAGIC: u32 = 0x184d2a50;
+
+/// The maximum size of a block once decompressed.
+const MAX_BLOCK_SIZE: usize = 128 * 1024;
+
+/// The maximum length of a Huffman code in bits.
+const HUF_MAX_BITS: u32 = 11;
+/// The maximum accuracy log for the FSE table of Huffman weights.
+const HUF_WEIGHTS_MAX_LOG: u32 = 6;
+
+/// The maximum accuracy log of a FSE table.
+const FSE_MAX_LOG: u32 = 9;
+/// The maximum number of symbols of a FSE table.
+const FSE_MAX_SYMBOLS: usize = 256;
+
+/// The maximum accuracy log for literals lengths.
+const LL_MAX_LOG: u32 = 9;
+/// The maximum accuracy log for offsets.
+const OF_MAX_LOG: u32 = 8;
+/// The maximum accuracy log for match lengths.
+const ML_MAX_LOG: u32 = 9;
+
+/// The maximum offset code.
+const OF_MAX_CODE: u8 = 31;
+
+/// The predefined distribution for literals lengths, with its accuracy log.
+const LL_DEFAULT: (&[i16], u32) = (
+	&[
+		4, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 2, 1, 1, 1,
+		1, 1, -1, -1, -1, -1,
+	],
+	6,
+);
+/// The predefined distribution for offsets, with its accuracy log.
+const OF_DEFAULT: (&[i16], u32) = (
+	&[
+		1, 1, 1, 1, 1, 1, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1,
+	],
+	5,
+);
+/// The predefined distribution for match lengths, with its accuracy log.
+const ML_DEFAULT: (&[i16], u32) = (
+	&[
+		1, 4, 3, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+		1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1, -1, -1,
+	],
+	6,
+);
+
+/// The baselines for literals length codes.
+const LL_BASE: [u32; 36] = [
+	0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 18, 20, 22, 24, 28, 32, 40, 48, 64,
+	128, 256, 512, 1024, 2048, 4096, 8192, 16384, 32768, 65536,
+];
+/// The number of extra bits for literals length codes.
+const LL_EXTRA: [u8; 36] = [
+	0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 3, 3, 4, 6, 7, 8, 9, 10, 11,
+	12, 13, 14, 15, 16,
+];
+/// The baselines for match length codes.
+const ML_BASE: [u32; 53] = [
+	3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27,
+	28, 29, 30, 31, 32, 33, 34, 35, 37, 39, 41, 43, 47, 51, 59, 67, 83, 99, 131, 259, 515, 1027,
+	2051, 4099, 8195, 16387, 32771, 65539,
+];
+/// The number of extra bits for match length codes.
+const ML_EXTRA: [u8; 53] = [
+	0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+	0, 1, 1, 1, 1, 2, 2, 3, 3, 4, 4, 5, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16,
+];
+
+/// Returns the index of the highest bit set in `n`, which must not be zero.
+fn highest_bit(n: u32) -> u32 {
+	31 - n.leading_zeros()
+}
+
+/// Reads `n` bits from `src`, starting at the bit at offset `off`.
+///
+/// Bits are numbered from the least significant bit of the first byte. Bits past the end of
+/// `src` are read as zeros.
+fn read_bits(src: &[u8], off: usize, n: u32) -> u64 {
+	let mut val = 0;
+	let mut done = 0;
+	while done < n {
+		let bit = off + done as usize;
+		let byte = src.get(bit / 8).copied().unwrap_or(0);
+		let shift = (bit % 8) as u32;
+		let count = min(8 - shift, n - done);
+		val |= (((byte >> shift) as u64) & ((1 << count) - 1)) << done;
+		done += count;
+	}
+	val
+}
+
+/// Reads a little-endian integer of `len` bytes at the beginning of `src`.
+fn read_le(src: &[u8], len: usize) -> EResult<u64> {
+	let bytes = src.get(..len).ok_or_else(|| errno!(EINVAL))?;
+	Ok(bytes
+		.iter()
+		.rev()
+		.fold(0, |val, byte| (val << 8) | *byte as u64))
+}
+
+/// Reads a stream backward, starting from the last bit, which is preceded by padding.
+struct BackwardReader<'a> {
+	/// The stream.
+	src: &'a [u8],
+	/// The offset of the bit following the next bit to be read. Reading past the beginning of the
+	/// stream makes this value negative.
+	off: isize,
+}
+
+impl<'a> BackwardReader<'a> {
+	/// Creates a new reader on `src`.
+	fn new(src: &'a [u8]) -> EResult<Self> {
+		// The highest bit set in the last byte marks the end of the stream
+		let last = *src.last().ok_or_else(|| errno!(EINVAL))?;
+		if last == 0 {
+			return Err(errno!(EINVAL));
+		}
+		let padding = 8 - highest_bit(last as _) as isize;
+		Ok(Self {
+			src,
+			off: src.len() as isize * 8 - padding,
+		})
+	}
+
+	/// Reads `n` bits. Bits before the beginning of the stream are read as zeros.
+	fn read(&mut self, n: u32) -> u64 {
+		self.off -= n as isize;
+		if self.off >= 0 {
+			return read_bits(self.src, self.off as usize, n);
+		}
+
+		let available = n as isize + self.off;
+		if available <= 0 {
+			return 0;
+		}
+		read_bits(self.src, 0, available as u32) << -self.off
+	}
+}
+
+/// An entry of a FSE decoding table.
+#[derive(Clone, Copy, Default)]
+struct FseEntry {
+	/// The decoded symbol.
+	symbol: u8,
+	/// The number of bits to read to compute the next state.
+	bits: u8,
+	/// The value to add to the bits read to get the next state.
+	base: u16,
+}
+
+/// A FSE decoding table.
+struct FseTable {
+	/// The accuracy log of the table.
+	log: u32,
+	/// The entries of the table, for each state.
+	entries: [FseEntry; 1 << FSE_MAX_LOG],
+}
+
+impl FseTable {
+	/// Builds a table from a normalized distribution of probabilities.
+	///
+	/// A probability of `-1` means *less than 1*.
+	fn from_distribution(probs: &[i16], log: u32) -> EResult<Self> {
+		let mut table = Self {
+			log,
+			entries: [FseEntry::default(); 1 << FSE_MAX_LOG],
+		};
+		let size = 1usize << log;
+
+		// Symbols with a *less than 1* probability are placed at the end of the table
+		let mut state_desc = [0u16; FSE_MAX_SYMBOLS];
+		let mut high_threshold = size;
+		for (sym, prob) in probs.iter().enumerate() {
+			if *prob == -1 {
+				high_threshold -= 1;
+				table.entries[high_threshold].symbol = sym as _;
+				state_desc[sym] = 1;
+			}
+		}
+
+		// Other symbols are spread over the table
+		let step = (size >> 1) + (size >> 3) + 3;
+		let mask = size - 1;
+		let mut pos = 0;
+		for (sym, prob) in probs.iter().enumerate() {
+			if *prob <= 0 {
+				continue;
+			}
+			state_desc[sym] = *prob as _;
+			for _ in 0..*prob {
+				table.entries[pos].symbol = sym as _;
+				loop {
+					pos = (pos + step) & mask;
+					if pos < high_threshold {
+						break;
+					}
+				}
+			}
+		}
+		if pos != 0 {
+			return Err(errno!(EINVAL));
+		}
+
+		for entry in &mut table.entries[..size] {
+			let desc = state_desc[entry.symbol as usize];
+			state_desc[entry.symbol as usize] += 1;
+			entry.bits = (log - highest_bit(desc as _)) as _;
+			entry.base = ((desc << entry.bits) as usize - size) as _;
+		}
+
+		Ok(table)
+	}
+
+	/// Builds a table always decoding `symbol`.
+	fn rle(symbol: u8) -> Self {
+		let mut table = Self {
+			log: 0,
+			entries: [FseEntry::default(); 1 << FSE_MAX_LOG],
+		};
+		table.entries[0].symbol = symbol;
+		table
+	}
+
+	/// Reads the description of a table at the beginning of `src`.
+	///
+	/// `max_log` is the maximum accuracy log allowed.
+	///
+	/// On success, the function returns the table and the size of the description in bytes.
+	fn read(src: &[u8], max_log: u32) -> EResult<(Self, usize)> {
+		let mut off = 0;
+		let log = read_bits(src, off, 4) as u32 + 5;
+		off += 4;
+		if log > max_log {
+			return Err(errno!(EINVAL));
+		}
+
+		let mut probs = [0i16; FSE_MAX_SYMBOLS];
+		let mut count = 0;
+		let mut remaining = 1i32 << log;
+		while remaining > 0 && count < FSE_MAX_SYMBOLS {
+			let bits = highest_bit((remaining + 1) as _) + 1;
+			let mut val = read_bits(src, off, bits) as i32;
+			off += bits as usize;
+
+			// Small values are encoded on one bit less
+			let lower_mask = (1 << (bits - 1)) - 1;
+			let threshold = (1 << bits) - 1 - (remaining + 1);
+			if (val & lower_mask) < threshold {
+				off -= 1;
+				val &= lower_mask;
+			} else if val > lower_mask {
+				val -= threshold;
+			}
+
+			let prob = val - 1;
+			remaining -= prob.abs();
+			probs[count] = prob as _;
+			count += 1;
+
+			// A zero probability is followed by the number of zero probabilities that follow
+			if prob == 0 {
+				loop {
+					let repeat = read_bits(src, off, 2) as usize;
+					off += 2;
+					let end = min(count + repeat, FSE_MAX_SYMBOLS);
+					probs[count..end].fill(0);
+					count = end;
+					if repeat != 3 {
+						break;
+					}
+				}
+			}
+		}
+		let len = (off + 7) / 8;
+		if remaining != 0 || count >= FSE_MAX_SYMBOLS || len > src.len() {
+			return Err(errno!(EINVAL));
+		}
+
+		Ok((Self::from_distribution(&probs[..count], log)?, len))
+	}
+
+	/// Reads the initial state from `reader`.
+	fn init_state(&self, reader: &mut BackwardReader) -> usize {
+		reader.read(self.log) as _
+	}
+
+	/// Returns the symbol for the state `state`.
+	fn peek(&self, state: usize) -> u8 {
+		self.entries[state].symbol
+	}
+
+	/// Updates the state `state` with bits from `reader`.
+	fn update(&self, state: &mut usize, reader: &mut BackwardReader) {
+		let entry = &self.entries[*state];
+		*state = entry.base as usize + reader.read(entry.bits as _) as usize;
+	}
+
+	/// Decodes the stream `src` with two interleaved states into `dst`.
+	///
+	/// On success, the function returns the number of decoded symbols.
+	fn decode_interleaved(&self, src: &[u8], dst: &mut [u8]) -> EResult<usize> {
+		let mut reader = BackwardReader::new(src)?;
+		let mut states = [self.init_state(&mut reader), self.init_state(&mut reader)];
+
+		let mut count = 0;
+		for i in (0..2).cycle() {
+			if count + 2 > dst.len() {
+				return Err(errno!(EINVAL));
+			}
+			dst[count] = self.peek(states[i]);
+			self.update(&mut states[i], &mut reader);
+			count += 1;
+
+			// When the stream is exhausted, the other state gives the last symbol
+			if reader.off < 0 {
+				dst[count] = self.peek(states[1 - i]);
+				count += 1;
+				break;
+			}
+		}
+
+		Ok(count)
+	}
+}
+
+/// A Huffman decoding table.
+struct HufTable {
+	/// The length of the longest code in bits.
+	max_bits: u32,
+	/// The decoded symbol for each state.
+	symbols: [u8; 1 << HUF_MAX_BITS],
+	/// The length of the code for each state.
+	bits: [u8; 1 << HUF_MAX_BITS],
+}
+
+impl HufTable {
+	/// Builds a table from the weights of the symbols.
+	///
+	/// The weight of the last symbol is not given since it can be deduced from the others.
+	fn from_weights(weights: &[u8]) -> EResult<Self> {
+		if weights.len() >= 256 {
+			return Err(errno!(EINVAL));
+		}
+		let mut sum = 0u32;
+		for w in weights {
+			if *w as u32 > HUF_MAX_BITS {
+				return Err(errno!(EINVAL));
+			}
+			if *w > 0 {
+				sum += 1 << (*w - 1);
+			}
+		}
+		if sum == 0 {
+			return Err(errno!(EINVAL));
+		}
+		let max_bits = highest_bit(sum) + 1;
+		if max_bits > HUF_MAX_BITS {
+			return Err(errno!(EINVAL));
+		}
+		// The weight of the last symbol completes the sum to a power of two
+		let left = (1 << max_bits) - sum;
+		if !left.is_power_of_two() {
+			return Err(errno!(EINVAL));
+		}
+		let last_weight = highest_bit(left) + 1;
+
+		let mut lengths = [0u8; 256];
+		for (i, w) in weights.iter().enumerate() {
+			if *w > 0 {
+				lengths[i] = (max_bits + 1 - *w as u32) as _;
+			}
+		}
+		lengths[weights.len()] = (max_bits + 1 - last_weight) as _;
+		let lengths = &lengths[..=weights.len()];
+
+		let mut table = Self {
+			max_bits,
+			symbols: [0; 1 << HUF_MAX_BITS],
+			bits: [0; 1 << HUF_MAX_BITS],
+		};
+
+		// Compute the first state for each code length. Longer codes come first
+		let mut rank_count = [0u32; HUF_MAX_BITS as usize + 1];
+		for len in lengths {
+			rank_count[*len as usize] += 1;
+		}
+		let mut rank_idx = [0u32; HUF_MAX_BITS as usize + 1];
+		for len in (1..=max_bits as usize).rev() {
+			rank_idx[len - 1] = rank_idx[len] + rank_count[len] * (1 << (max_bits as usize - len));
+			table.bits[rank_idx[len] as usize..rank_idx[len - 1] as usize].fill(len as _);
+		}
+		if rank_idx[0] != 1 << max_bits {
+			return Err(errno!(EINVAL));
+		}
+
+		// Each symbol covers the states whose highest bits match its code
+		for (sym, len) in lengths.iter().enumerate() {
+			if *len != 0 {
+				let start = rank_idx[*len as usize] as usize;
+				let count = 1 << (max_bits - *len as u32);
+				table.symbols[start..(start + count)].fill(sym as _);
+				rank_idx[*len as usize] += count as u32;
+			}
+		}
+
+		Ok(table)
+	}
+
+	/// Reads the description of a table at the beginning of `src`.
+	///
+	/// On success, the function returns the table and the size of the description in bytes.
+	fn read(src: &[u8]) -> EResult<(Self, usize)> {
+		let header = *src.first().ok_or_else(|| errno!(EINVAL))? as usize;
+		let mut weights = [0u8; 255];
+		let (count, len) = if header < 128 {
+			// Weights are compressed with FSE
+			let data = src.get(1..(1 + header)).ok_or_else(|| errno!(EINVAL))?;
+			let (fse, table_len) = FseTable::read(data, HUF_WEIGHTS_MAX_LOG)?;
+			let count = fse.decode_interleaved(&data[table_len..], &mut weights)?;
+			(count, 1 + header)
+		} else {
+			// Weights are stored on 4 bits each
+			let count = header - 127;
+			let data = src
+				.get(1..(1 + (count + 1) / 2))
+				.ok_or_else(|| errno!(EINVAL))?;
+			for (i, w) in weights[..count].iter_mut().enumerate() {
+				let byte = data[i / 2];
+				*w = if i % 2 == 0 { byte >> 4 } else { byte & 0xf };
+			}
+			(count, 1 + data.len())
+		};
+
+		Ok((Self::from_weights(&weights[..count])?, len))
+	}
+
+	/// Decodes the stream `src`, filling `dst`.
+	fn decode_stream(&self, src: &[u8], dst: &mut [u8]) -> EResult<()> {
+		let mut reader = BackwardReader::new(src)?;
+		let mask = (1 << self.max_bits) - 1;
+		let mut state = reader.read(self.max_bits) as usize;
+		for b in dst {
+			*b = self.symbols[state];
+			let bits = self.bits[state] as u32;
+			state = ((state << bits) + reader.read(bits) as usize) & mask;
+		}
+
+		// The stream must be entirely consumed
+		if reader.off != -(self.max_bits as isize) {
+			return Err(errno!(EINVAL));
+		}
+		Ok(())
+	}
+}
+
+/// The state of the decoder, kept between blocks of a frame.
+struct Context {
+	/// The Huffman table for literals.
+	huf: Option<HufTable>,
+	/// The FSE table for literals lengths.
+	ll: Option<FseTable>,
+	/// The FSE table for offsets.
+	of: Option<FseTable>,
+	/// The FSE table for match lengths.
+	ml: Option<FseTable>,
+	/// The repeated offsets, from the most recent one.
+	rep: [usize; 3],
+}
+
+/// Updates the FSE table `table` according to the compression mode `mode`.
+///
+/// Arguments:
+/// - `src` is the data following the header of the sequences section. It may start with the
+/// description of the table
+/// - `default` is the predefined distribution
+/// - `max_log` is the maximum accuracy log allowed
+///
+/// On success, the function returns the number of bytes consumed from `src`.
+fn update_table(
+	table: &mut Option<FseTable>,
+	mode: u8,
+	src: &[u8],
+	default: (&[i16], u32),
+	max_log: u32,
+) -> EResult<usize> {
+	match mode {
+		// Predefined
+		0 => {
+			*table = Some(FseTable::from_distribution(default.0, default.1)?);
+			Ok(0)
+		}
+
+		// RLE
+		1 => {
+			let sym = *src.first().ok_or_else(|| errno!(EINVAL))?;
+			*table = Some(FseTable::rle(sym));
+			Ok(1)
+		}
+
+		// Compressed
+		2 => {
+			let (new, len) = FseTable::read(src, max_log)?;
+			*table = Some(new);
+			Ok(len)
+		}
+
+		// Repeat
+		_ => {
+			if table.is_none() {
+				return Err(errno!(EINVAL));
+			}
+			Ok(0)
+		}
+	}
+}
+
+/// Decodes the literals section at the beginning of the compressed block `src` into `lits`.
+///
+/// On success, the function returns the size of the section and the number of literals.
+fn decode_literals(ctx: &mut Context, src: &[u8], lits: &mut [u8]) -> EResult<(usize, usize)> {
+	let b0 = *src.first().ok_or_else(|| errno!(EINVAL))?;
+	let ty = b0 & 3;
+	let size_format = (b0 >> 2) & 3;
+
+	match ty {
+		// Raw and RLE
+		0 | 1 => {
+			let (header_len, size) = match size_format {
+				0 | 2 => (1, (b0 >> 3) as usize),
+				1 => (2, (read_le(src, 2)? >> 4) as usize),
+				_ => (3, (read_le(src, 3)? >> 4) as usize),
+			};
+			let dst = lits.get_mut(..size).ok_or_else(|| errno!(EINVAL))?;
+			if ty == 0 {
+				let data = src
+					.get(header_len..(header_len + size))
+					.ok_or_else(|| errno!(EINVAL))?;
+				dst.copy_from_slice(data);
+				Ok((header_len + size, size))
+			} else {
+				let byte = *src.get(header_len).ok_or_else(|| errno!(EINVAL))?;
+				dst.fill(byte);
+				Ok((header_len + 1, size))
+			}
+		}
+
+		// Compressed and treeless
+		_ => {
+			let (header_len, bits) = match size_format {
+				0 | 1 => (3, 10),
+				2 => (4, 14),
+				_ => (5, 18),
+			};
+			let header = read_le(src, header_len)?;
+			let mask = (1 << bits) - 1;
+			let size = ((header >> 4) & mask) as usize;
+			let comp_size = ((header >> (4 + bits)) & mask) as usize;
+
+			let mut data = src
+				.get(header_len..(header_len + comp_size))
+				.ok_or_else(|| errno!(EINVAL))?;
+			if ty == 2 {
+				let (table, len) = HufTable::read(data)?;
+				ctx.huf = Some(table);
+				data = &data[len..];
+			}
+			let huf = ctx.huf.as_ref().ok_or_else(|| errno!(EINVAL))?;
+
+			let dst = lits.get_mut(..size).ok_or_else(|| errno!(EINVAL))?;
+			if size_format == 0 {
+				huf.decode_stream(data, dst)?;
+			} else {
+				// Four streams, preceded by a jump table
+				let jump = data.get(..6).ok_or_else(|| errno!(EINVAL))?;
+				let mut sizes = [0usize; 4];
+				for (i, s) in jump.chunks_exact(2).enumerate() {
+					sizes[i] = u16::from_le_bytes([s[0], s[1]]) as usize;
+				}
+				sizes[3] = (data.len() - 6)
+					.checked_sub(sizes[0] + sizes[1] + sizes[2])
+					.ok_or_else(|| errno!(EINVAL))?;
+
+				let segment = (size + 3) / 4;
+				if segment * 3 > size {
+					return Err(errno!(EINVAL));
+				}
+				let mut data = &data[6..];
+				for (i, dst) in dst.chunks_mut(segment).enumerate() {
+					huf.decode_stream(&data[..sizes[i]], dst)?;
+					data = &data[sizes[i]..];
+				}
+			}
+
+			Ok((header_len + comp_size, size))
+		}
+	}
+}
+
+/// Decodes the sequences section `src` and executes the sequences, writing data to `dst`.
+///
+/// Arguments:
+/// - `lits` are the literals of the block
+/// - `frame_start` is the offset in `dst` of the beginning of the frame
+/// - `out` is the offset in `dst` at which data is written. It is updated by the function
+fn decode_sequences(
+	ctx: &mut Context,
+	src: &[u8],
+	lits: &[u8],
+	dst: &mut [u8],
+	frame_start: usize,
+	out: &mut usize,
+) -> EResult<()> {
+	let b0 = *src.first().ok_or_else(|| errno!(EINVAL))? as usize;
+	let (count, mut off) = match b0 {
+		0..=127 => (b0, 1),
+		128..=254 => (((b0 - 128) << 8) + read_le(&src[1..], 1)? as usize, 2),
+		_ => (read_le(&src[1..], 2)? as usize + 0x7f00, 3),
+	};
+
+	let mut lits_off = 0;
+	if count > 0 {
+		let modes = *src.get(off).ok_or_else(|| errno!(EINVAL))?;
+		off += 1;
+		if modes & 3 != 0 {
+			return Err(errno!(EINVAL));
+		}
+		off += update_table(&mut ctx.ll, modes >> 6, &src[off..], LL_DEFAULT, LL_MAX_LOG)?;
+		off += update_table(
+			&mut ctx.of,
+			(modes >> 4) & 3,
+			&src[off..],
+			OF_DEFAULT,
+			OF_MAX_LOG,
+		)?;
+		off += update_table(
+			&mut ctx.ml,
+			(modes >> 2) & 3,
+			&src[off..],
+			ML_DEFAULT,
+			ML_MAX_LOG,
+		)?;
+		let (Some(ll), Some(of), Some(ml)) = (&ctx.ll, &ctx.of, &ctx.ml) else {
+			unreachable!();
+		};
+
+		let mut reader = BackwardReader::new(&src[off..])?;
+		let mut ll_state = ll.init_state(&mut reader);
+		let mut of_state = of.init_state(&mut reader);
+		let mut ml_state = ml.init_state(&mut reader);
+
+		for i in 0..count {
+			let ll_code = ll.peek(ll_state) as usize;
+			let of_code = of.peek(of_state);
+			let ml_code = ml.peek(ml_state) as usize;
+			if ll_code >= LL_BASE.len() || ml_code >= ML_BASE.len() || of_code > OF_MAX_CODE {
+				return Err(errno!(EINVAL));
+			}
+
+			let of_value = (1u64 << of_code) + reader.read(of_code as _);
+			let match_len =
+				ML_BASE[ml_code] as usize + reader.read(ML_EXTRA[ml_code] as _) as usize;
+			let lits_len =
+				LL_BASE[ll_code] as usize + reader.read(LL_EXTRA[ll_code] as _) as usize;
+			if i + 1 < count {
+				ll.update(&mut ll_state, &mut reader);
+				ml.update(&mut ml_state, &mut reader);
+				of.update(&mut of_state, &mut reader);
+			}
+
+			// Resolve the offset
+			let offset = if of_value > 3 {
+				let offset = of_value as usize - 3;
+				ctx.rep = [offset, ctx.rep[0], ctx.rep[1]];
+				offset
+			} else {
+				// With no literals, repeated offsets are shifted by one
+				let idx = of_value as usize - 1 + (lits_len == 0) as usize;
+				match idx {
+					0 => ctx.rep[0],
+					1 => {
+						let offset = ctx.rep[1];
+						ctx.rep = [offset, ctx.rep[0], ctx.rep[2]];
+						offset
+					}
+					_ => {
+						let offset = if idx == 2 {
+							ctx.rep[2]
+						} else {
+							ctx.rep[0].wrapping_sub(1)
+						};
+						ctx.rep = [offset, ctx.rep[0], ctx.rep[1]];
+						offset
+					}
+				}
+			};
+
+			// Copy literals
+			let lits = lits
+				.get(lits_off..(lits_off + lits_len))
+				.ok_or_else(|| errno!(EINVAL))?;
+			dst.get_mut(*out..(*out + lits_len))
+				.ok_or_else(|| errno!(EINVAL))?
+				.copy_from_slice(lits);
+			lits_off += lits_len;
+			*out += lits_len;
+
+			// Copy the match byte by byte since the source and destination may overlap
+			if offset == 0 || offset > *out - frame_start || *out + match_len > dst.len() {
+				return Err(errno!(EINVAL));
+			}
+			for j in *out..(*out + match_len) {
+				dst[j] = dst[j - offset];
+			}
+			*out += match_len;
+		}
+
+		// The stream must be entirely consumed
+		if reader.off != 0 {
+			return Err(errno!(EINVAL));
+		}
+	}
+
+	// Copy the remaining literals
+	let lits = &lits[lits_off..];
+	dst.get_mut(*out..(*out + lits.len()))
+		.ok_or_else(|| errno!(EINVAL))?
+		.copy_from_slice(lits);
+	*out += lits.len();
+
+	Ok(())
+}
+
+/// Decodes the frame at the beginning of `src`, writing data to `dst` at offset `out`.
+///
+/// `lits` is the buffer used to store literals.
+///
+/// On success, the function returns the size of the frame and the number of bytes written.
+fn decode_frame(
+	src: &[u8],
+	dst: &mut [u8],
+	out: usize,
+	lits: &mut [u8],
+) -> EResult<(usize, usize)> {
+	let magic = read_le(src, 4)? as u32;
+	if magic & !0xf == SKIPPABLE_MAGIC {
+		let size = read_le(&src[4..], 4)? as usize;
+		let len = 8 + size;
+		if len > src.len() {
+			return Err(errno!(EINVAL));
+		}
+		return Ok((len, 0));
+	}
+	if magic != MAGIC {
+		return Err(errno!(EINVAL));
+	}
+
+	// Frame header
+	let desc = *src.get(4).ok_or_else(|| errno!(EINVAL))?;
+	let fcs_flag = desc >> 6;
+	let single_segment = desc & 0x20 != 0;
+	let has_checksum = desc & 0x04 != 0;
+	let dict_id_len = [0, 1, 2, 4][(desc & 3) as usize];
+	if desc & 0x08 != 0 {
+		return Err(errno!(EINVAL));
+	}
+	let mut off = 5;
+	if !single_segment {
+		// The window descriptor is not needed since the whole frame is kept in memory
+		off += 1;
+	}
+	if read_le(src.get(off..).ok_or_else(|| errno!(EINVAL))?, dict_id_len)? != 0 {
+		return Err(errno!(EINVAL));
+	}
+	off += dict_id_len;
+	let fcs_len = match fcs_flag {
+		0 => single_segment as usize,
+		1 => 2,
+		2 => 4,
+		_ => 8,
+	};
+	let content_size = if fcs_len > 0 {
+		let size = read_le(src.get(off..).ok_or_else(|| errno!(EINVAL))?, fcs_len)?;
+		Some(if fcs_len == 2 { size + 256 } else { size })
+	} else {
+		None
+	};
+	off += fcs_len;
+
+	let mut ctx = Context {
+		huf: None,
+		ll: None,
+		of: None,
+		ml: None,
+		rep: [1, 4, 8],
+	};
+	let mut cur = out;
+	loop {
+		let header = read_le(src.get(off..).ok_or_else(|| errno!(EINVAL))?, 3)? as usize;
+		off += 3;
+		let last = header & 1 != 0;
+		let size = header >> 3;
+
+		match (header >> 1) & 3 {
+			// Raw
+			0 => {
+				let data = src.get(off..(off + size)).ok_or_else(|| errno!(EINVAL))?;
+				dst.get_mut(cur..(cur + size))
+					.ok_or_else(|| errno!(EINVAL))?
+					.copy_from_slice(data);
+				off += size;
+				cur += size;
+			}
+
+			// RLE
+			1 => {
+				let byte = *src.get(off).ok_or_else(|| errno!(EINVAL))?;
+				dst.get_mut(cur..(cur + size))
+					.ok_or_else(|| errno!(EINVAL))?
+					.fill(byte);
+				off += 1;
+				cur += size;
+			}
+
+			// Compressed
+			2 => {
+				let block = src.get(off..(off + size)).ok_or_else(|| errno!(EINVAL))?;
+				let (lits_section_len, lits_len) = decode_literals(&mut ctx, block, lits)?;
+				decode_sequences(
+					&mut ctx,
+					&block[lits_section_len..],
+					&lits[..lits_len],
+					dst,
+					out,
+					&mut cur,
+				)?;
+				off += size;
+			}
+
+			_ => return Err(errno!(EINVAL)),
+		}
+
+		if last {
+			break;
+		}
+	}
+
+	let len = cur - out;
+	if content_size.is_some_and(|size| size != len as u64) {
+		return Err(errno!(EINVAL));
+	}
+	if has_checksum {
+		let checksum = read_le(src.get(off..).ok_or_else(|| errno!(EINVAL))?, 4)? as u32;
+		if checksum != checksum::compute_xxh64(&dst[out..cur], 0) as u32 {
+			return Err(errno!(EINVAL));
+		}
+		off += 4;
+	}
+
+	Ok((off, len))
+}
+
+/// Decompresses the Zstandard frames in `src` into `dst`.
+///
+/// On success, the function returns the number of bytes written to `dst`.
+///
+/// If the data is invalid, requires a dictionary, or if `dst` is too small, the function returns
+/// an error.
+pub fn decompress(src: &[u8], dst: &mut [u8]) -> EResult<usize> {
+	let mut lits = Vec::from_elem(0u8, MAX_BLOCK_SIZE)?;
+
+	let mut off = 0;
+	let mut out = 0;
+	while off < src.len() {
+		let (frame_len, len) = decode_frame(&src[off..], dst, out, &mut lits)?;
+		off += frame_len;
+		out += len;
+	}
+
+	Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn zstd_raw_rle() {
+		// A frame with a raw block, followed by a frame with a RLE block
+		let src = [
+			0x28, 0xb5, 0x2f, 0xfd, 0x20, 0x05, 0x29, 0x00, 0x00, b'h', b'e', b'l', b'l', b'o',
+			0x28, 0xb5, 0x2f, 0xfd, 0x20, 0x04, 0x23, 0x00, 0x00, b'x',
+		];
+		let mut dst = [0u8; 16];
+		assert_eq!(decompress(&src, &mut dst), Ok(9));
+		assert_eq!(&dst[..9], b"helloxxxx");
+	}
+
+	#[test_case]
+	fn zstd_compressed() {
+		let src = [
+			0x28, 0xb5, 0x2f, 0xfd, 0x24, 0x1e, 0x6d, 0x00, 0x00, 0x38, 0x68, 0x65, 0x6c, 0x6c,
+			0x6f, 0x20, 0x21, 0x01, 0x00, 0xa9, 0x4b, 0x11, 0x5f, 0x59, 0x9a, 0xc8,
+		];
+		let mut dst = [0u8; 64];
+		assert_eq!(decompress(&src, &mut dst), Ok(30));
+		assert_eq!(&dst[..30], b"hello hello hello hello hello!");
+	}
+
+	#[test_case]
+	fn zstd_invalid() {
+		let mut dst = [0u8; 64];
+		assert!(decompress(&[0x28, 0xb5, 0x2f], &mut dst).is_err());
+		// Wrong checksum
+		let src = [
+			0x28, 0xb5, 0x2f, 0xfd, 0x24, 0x1e, 0x6d, 0x00, 0x00, 0x38, 0x68, 0x65, 0x6c, 0x6c,
+			0x6f, 0x20, 0x21, 0x01, 0x00, 0xa9, 0x4b, 0x11, 0x5f, 0x59, 0x9a, 0xc9,
+		];
+		assert!(decompress(&src, &mut dst).is_err());
+	}
+}
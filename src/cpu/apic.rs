@@ -0,0 +1,208 @@
+//! The local APIC (Advanced Programmable Interrupt Controller) is the interrupt controller
+//! attached to each CPU core.
+//!
+//! It allows to send IPIs (Inter-Processor Interrupts) to other cores, and provides a timer that
+//! is local to the core.
+//!
+//! The registers of every local APIC are mapped at the same physical address. Each core
+//! accesses its own local APIC through this address.
+
+use crate::errno::AllocResult;
+use crate::idt;
+use crate::memory::mmio::MMIO;
+use core::ffi::c_void;
+use core::hint;
+use core::mem::ManuallyDrop;
+use core::ptr;
+use core::ptr::null_mut;
+use core::sync::atomic::AtomicPtr;
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::Ordering;
+
+/// Register: the ID of the local APIC.
+const REG_ID: usize = 0x20;
+/// Register: End Of Interrupt.
+const REG_EOI: usize = 0xb0;
+/// Register: Spurious Interrupt Vector.
+const REG_SPURIOUS: usize = 0xf0;
+/// Register: Error Status.
+const REG_ESR: usize = 0x280;
+/// Register: Interrupt Command, low half.
+const REG_ICR_LOW: usize = 0x300;
+/// Register: Interrupt Command, high half.
+const REG_ICR_HIGH: usize = 0x310;
+/// Register: timer entry of the Local Vector Table.
+const REG_LVT_TIMER: usize = 0x320;
+/// Register: timer initial count.
+const REG_TIMER_INIT: usize = 0x380;
+/// Register: timer current count.
+const REG_TIMER_CURRENT: usize = 0x390;
+/// Register: timer divide configuration.
+const REG_TIMER_DIV: usize = 0x3e0;
+
+/// Spurious Interrupt Vector flag: enables the local APIC.
+const SPURIOUS_ENABLE: u32 = 1 << 8;
+
+/// Interrupt Command flag: the IPI has not been accepted yet by the target.
+const ICR_PENDING: u32 = 1 << 12;
+/// Interrupt Command flag: level assert.
+const ICR_ASSERT: u32 = 1 << 14;
+/// Interrupt Command flag: level triggered.
+const ICR_LEVEL: u32 = 1 << 15;
+/// Interrupt Command delivery mode: NMI.
+const ICR_NMI: u32 = 0b100 << 8;
+/// Interrupt Command delivery mode: INIT.
+const ICR_INIT: u32 = 0b101 << 8;
+/// Interrupt Command delivery mode: Start-Up.
+const ICR_STARTUP: u32 = 0b110 << 8;
+/// Interrupt Command destination shorthand: the current core.
+const ICR_SELF: u32 = 0b01 << 18;
+/// Interrupt Command destination shorthand: every cores except the current one.
+const ICR_ALL_EXCLUDING_SELF: u32 = 0b11 << 18;
+
+/// Timer mode: the interrupt is fired periodically.
+const TIMER_PERIODIC: u32 = 1 << 17;
+/// Timer entry flag: the interrupt is masked.
+const TIMER_MASKED: u32 = 1 << 16;
+/// Timer divide configuration: divides the bus frequency by 16.
+const TIMER_DIV_16: u32 = 0b0011;
+
+/// The virtual address of the local APIC's registers. If null, the local APIC is not enabled.
+static REGS: AtomicPtr<c_void> = AtomicPtr::new(null_mut());
+/// The number of timer ticks per second, with the divide configuration [`TIMER_DIV_16`].
+static TIMER_FREQUENCY: AtomicU32 = AtomicU32::new(0);
+
+/// Reads the register at offset `reg`.
+fn read(reg: usize) -> u32 {
+	let regs = REGS.load(Ordering::Relaxed);
+	unsafe { ptr::read_volatile(regs.add(reg) as *const u32) }
+}
+
+/// Writes `val` to the register at offset `reg`.
+fn write(reg: usize, val: u32) {
+	let regs = REGS.load(Ordering::Relaxed);
+	unsafe {
+		ptr::write_volatile(regs.add(reg) as *mut u32, val);
+	}
+}
+
+/// Maps the registers of the local APIC, located at the physical address `phys_addr`.
+///
+/// This function must be called only once, on the bootstrap processor.
+pub fn map(phys_addr: *mut c_void) -> AllocResult<()> {
+	// The registers remain mapped as long as the system is running
+	let mut mmio = ManuallyDrop::new(MMIO::new(phys_addr, 1, false)?);
+	REGS.store(mmio.as_mut_ptr(), Ordering::Release);
+	Ok(())
+}
+
+/// Tells whether the registers of the local APIC are mapped.
+#[inline]
+pub fn is_mapped() -> bool {
+	!REGS.load(Ordering::Relaxed).is_null()
+}
+
+/// Enables the local APIC of the current core.
+pub fn enable() {
+	write(REG_SPURIOUS, SPURIOUS_ENABLE | idt::APIC_SPURIOUS_VECTOR);
+	// Clear errors
+	write(REG_ESR, 0);
+	end_of_interrupt();
+}
+
+/// Returns the ID of the local APIC of the current core.
+pub fn get_id() -> u8 {
+	(read(REG_ID) >> 24) as _
+}
+
+/// Sends an End-Of-Interrupt to the local APIC of the current core.
+#[no_mangle]
+pub extern "C" fn apic_end_of_interrupt() {
+	write(REG_EOI, 0);
+}
+
+/// Sends an End-Of-Interrupt to the local APIC of the current core.
+#[inline]
+pub fn end_of_interrupt() {
+	apic_end_of_interrupt();
+}
+
+/// Writes the interrupt command `cmd` for the local APIC `dest`, then waits for the command to
+/// be accepted.
+fn send_command(dest: u8, cmd: u32) {
+	write(REG_ICR_HIGH, (dest as u32) << 24);
+	write(REG_ICR_LOW, cmd);
+	while read(REG_ICR_LOW) & ICR_PENDING != 0 {
+		hint::spin_loop();
+	}
+}
+
+/// Sends the IPI with interrupt vector `vector` to the core with local APIC ID `dest`.
+pub fn send_ipi(dest: u8, vector: u32) {
+	send_command(dest, vector);
+}
+
+/// Sends the IPI with interrupt vector `vector` to the current core.
+pub fn send_self_ipi(vector: u32) {
+	send_command(0, ICR_SELF | vector);
+}
+
+/// Sends the IPI with interrupt vector `vector` to every cores except the current one.
+pub fn broadcast_ipi(vector: u32) {
+	send_command(0, ICR_ALL_EXCLUDING_SELF | vector);
+}
+
+/// Sends a Non-Maskable Interrupt to every cores except the current one.
+///
+/// Unlike other IPIs, it is received even by cores that have interrupts disabled.
+pub fn broadcast_nmi() {
+	send_command(0, ICR_NMI | ICR_ALL_EXCLUDING_SELF);
+}
+
+/// Sends an INIT IPI to the core with local APIC ID `dest`, resetting it.
+pub fn send_init(dest: u8) {
+	send_command(dest, ICR_INIT | ICR_LEVEL | ICR_ASSERT);
+	send_command(dest, ICR_INIT | ICR_LEVEL);
+}
+
+/// Sends a Start-Up IPI to the core with local APIC ID `dest`.
+///
+/// The core starts executing in real mode at the physical address `page * 0x1000`.
+pub fn send_startup(dest: u8, page: u8) {
+	send_command(dest, ICR_STARTUP | page as u32);
+}
+
+/// Measures the frequency of the local APIC timer.
+///
+/// `delay` is a function busy-waiting for the given number of nanoseconds, which is used as a
+/// reference.
+pub fn calibrate_timer<F: FnOnce(u64)>(delay: F) {
+	const DURATION: u64 = 10_000_000;
+
+	write(REG_TIMER_DIV, TIMER_DIV_16);
+	write(REG_LVT_TIMER, TIMER_MASKED);
+	write(REG_TIMER_INIT, u32::MAX);
+	delay(DURATION);
+	let elapsed = u32::MAX - read(REG_TIMER_CURRENT);
+	write(REG_TIMER_INIT, 0);
+
+	let freq = elapsed as u64 * 1_000_000_000 / DURATION;
+	TIMER_FREQUENCY.store(freq as _, Ordering::Relaxed);
+}
+
+/// Starts the timer of the local APIC of the current core, firing the interrupt
+/// [`idt::APIC_TIMER_VECTOR`] `freq` times per second.
+///
+/// The timer must have been calibrated with [`calibrate_timer`] before.
+pub fn start_timer(freq: u32) {
+	let count = TIMER_FREQUENCY.load(Ordering::Relaxed) / freq;
+	write(REG_TIMER_DIV, TIMER_DIV_16);
+	write(REG_LVT_TIMER, TIMER_PERIODIC | idt::APIC_TIMER_VECTOR);
+	write(REG_TIMER_INIT, count.max(1));
+}
+
+/// Stops the timer of the local APIC of the current core.
+pub fn stop_timer() {
+	write(REG_LVT_TIMER, TIMER_MASKED);
+	write(REG_TIMER_INIT, 0);
+}
@@ -4,9 +4,13 @@
 
 .global cpuid_has_sse
 .global get_hwcap
+.global cpuid_get_ext_features
+.global cpuid_get_features_ecx
 
 .type cpuid_has_sse, @function
 .type get_hwcap, @function
+.type cpuid_get_ext_features, @function
+.type cpuid_get_features_ecx, @function
 
 .section .text
 
@@ -31,3 +35,42 @@ get_hwcap:
 
 	pop %ebx
 	ret
+
+/*
+ * Returns the structured extended feature flags (CPUID 7.0:EBX). If the leaf is not supported,
+ * the function returns zero.
+ */
+cpuid_get_ext_features:
+	push %ebx
+
+	xor %eax, %eax
+	cpuid
+	cmp $0x7, %eax
+	jb 1f
+
+	mov $0x7, %eax
+	xor %ecx, %ecx
+	cpuid
+	mov %ebx, %eax
+
+	pop %ebx
+	ret
+
+1:
+	xor %eax, %eax
+
+	pop %ebx
+	ret
+
+/*
+ * Returns the feature flags in ECX (CPUID 1:ECX).
+ */
+cpuid_get_features_ecx:
+	push %ebx
+
+	mov $0x1, %eax
+	cpuid
+	mov %ecx, %eax
+
+	pop %ebx
+	ret
@@ -0,0 +1,213 @@
+//! The idle loop runs on a CPU core when it has no process to run.
+//!
+//! Instead of spinning, the core is put in a low-power idle state (C-state) until the next
+//! interruption. Every core supports halting with `hlt`. If the CPU supports `monitor`/`mwait`,
+//! the deeper C-states it enumerates are also available.
+//!
+//! Deeper states save more power but take longer to leave. While the scheduler tick of the core
+//! is running, the core is expected to be woken up soon, thus the shallowest state is used. When
+//! the tick is stopped, the core may stay idle for a long time, thus the deepest state is used.
+//!
+//! The number of times each state has been entered and the time spent in it are recorded for
+//! each core.
+
+use super::MAX_CPUS;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::unit::Timestamp;
+use crate::time::unit::TimestampScale;
+use crate::util::lock::IntMutex;
+use core::arch::asm;
+use core::arch::x86::__cpuid;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::Ordering;
+
+/// The CPUID bit telling whether `monitor`/`mwait` are supported (CPUID 1:ECX).
+const CPUID_MWAIT: u32 = 1 << 3;
+/// The CPUID bit telling whether the `mwait` extensions are enumerated (CPUID 5:ECX).
+const CPUID_MWAIT_EMX: u32 = 1 << 0;
+
+/// An idle state of a CPU core.
+pub struct IdleState {
+	/// The name of the state.
+	pub name: &'static str,
+	/// The description of the state.
+	pub desc: &'static str,
+	/// The hint given to `mwait` to enter the state. If `None`, the state is entered with
+	/// `hlt`.
+	hint: Option<u32>,
+}
+
+/// The number of idle states.
+const STATES_COUNT: usize = 4;
+/// The list of idle states, from the shallowest to the deepest.
+pub static STATES: [IdleState; STATES_COUNT] = [
+	IdleState {
+		name: "HLT",
+		desc: "CPUIDLE CORE HALT",
+		hint: None,
+	},
+	IdleState {
+		name: "C1",
+		desc: "MWAIT 0x00",
+		hint: Some(0x00),
+	},
+	IdleState {
+		name: "C2",
+		desc: "MWAIT 0x10",
+		hint: Some(0x10),
+	},
+	IdleState {
+		name: "C3",
+		desc: "MWAIT 0x20",
+		hint: Some(0x20),
+	},
+];
+
+/// The set of available idle states, where each bit represents the state with the same index
+/// in [`STATES`].
+static AVAILABLE: AtomicU32 = AtomicU32::new(1);
+
+/// Statistics of an idle state on a CPU core.
+#[derive(Clone, Copy, Default)]
+pub struct StateStats {
+	/// The number of times the state has been entered.
+	pub usage: u64,
+	/// The total time spent in the state, in nanoseconds.
+	pub time: u64,
+}
+
+/// The idle state of a CPU core.
+struct CpuIdle {
+	/// The statistics of each idle state.
+	stats: [StateStats; STATES_COUNT],
+	/// If the core is idle, the index of its idle state and the timestamp at which it was
+	/// entered.
+	entered: Option<(usize, Option<Timestamp>)>,
+}
+
+/// The initial idle state of a CPU core.
+#[allow(clippy::declare_interior_mutable_const)]
+const CPU_IDLE_INIT: IntMutex<CpuIdle> = IntMutex::new(CpuIdle {
+	stats: [StateStats {
+		usage: 0,
+		time: 0,
+	}; STATES_COUNT],
+	entered: None,
+});
+/// The idle state of each CPU core.
+static CPUS: [IntMutex<CpuIdle>; MAX_CPUS] = [CPU_IDLE_INIT; MAX_CPUS];
+
+/// The initial value of [`TICK_STOPPED`].
+#[allow(clippy::declare_interior_mutable_const)]
+const TICK_STOPPED_INIT: AtomicBool = AtomicBool::new(false);
+/// Tells, for each CPU core, whether its scheduler tick is stopped.
+static TICK_STOPPED: [AtomicBool; MAX_CPUS] = [TICK_STOPPED_INIT; MAX_CPUS];
+
+/// The initial value of [`MONITOR`].
+#[allow(clippy::declare_interior_mutable_const)]
+const MONITOR_INIT: AtomicU32 = AtomicU32::new(0);
+/// A variable monitored by `mwait`, for each CPU core.
+static MONITOR: [AtomicU32; MAX_CPUS] = [MONITOR_INIT; MAX_CPUS];
+
+/// Detects the available idle states.
+pub fn init() {
+	let mut available = 1;
+	if unsafe { super::cpuid_get_features_ecx() } & CPUID_MWAIT != 0 {
+		let leaf = unsafe { __cpuid(5) };
+		if leaf.ecx & CPUID_MWAIT_EMX != 0 {
+			// Each nibble gives the number of sub-states of a C-state, starting with C0
+			for (i, state) in STATES.iter().enumerate().skip(1) {
+				let cstate = (state.hint.unwrap() >> 4) + 1;
+				if (leaf.edx >> (cstate * 4)) & 0xf != 0 {
+					available |= 1 << i;
+				}
+			}
+		} else {
+			// Only C1 is guaranteed to be supported
+			available |= 1 << 1;
+		}
+	}
+	AVAILABLE.store(available, Ordering::Relaxed);
+}
+
+/// Returns an iterator over the available idle states, with their index in [`STATES`].
+pub fn iter_states() -> impl Iterator<Item = (usize, &'static IdleState)> {
+	let available = AVAILABLE.load(Ordering::Relaxed);
+	STATES
+		.iter()
+		.enumerate()
+		.filter(move |(i, _)| available & (1 << i) != 0)
+}
+
+/// Tells whether the scheduler tick of the CPU core `core` is stopped.
+pub fn is_tick_stopped(core: usize) -> bool {
+	TICK_STOPPED[core].load(Ordering::Relaxed)
+}
+
+/// Sets whether the scheduler tick of the CPU core `core` is stopped.
+pub fn set_tick_stopped(core: usize, stopped: bool) {
+	TICK_STOPPED[core].store(stopped, Ordering::Relaxed);
+}
+
+/// Returns the statistics of the idle state with index `state` on the CPU core `core`.
+pub fn get_stats(core: usize, state: usize) -> StateStats {
+	CPUS[core].lock().stats[state]
+}
+
+/// Returns the total time spent idle by the CPU core `core`, in nanoseconds.
+pub fn get_idle_time(core: usize) -> u64 {
+	CPUS[core].lock().stats.iter().map(|s| s.time).sum()
+}
+
+/// Returns the current timestamp of the monotonic clock, in nanoseconds.
+///
+/// If the clock is not available yet, the function returns `None`.
+fn now() -> Option<Timestamp> {
+	clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond).ok()
+}
+
+/// Puts the current CPU core in an idle state until the next interruption.
+pub fn idle() {
+	crate::cli!();
+	let core = super::get_current_id();
+	let state = if is_tick_stopped(core) {
+		iter_states().last()
+	} else {
+		iter_states().next()
+	};
+	let (index, state) = state.unwrap();
+	{
+		let mut cpu = CPUS[core].lock();
+		cpu.stats[index].usage += 1;
+		cpu.entered = Some((index, now()));
+	}
+
+	// `sti` enables interrupts only after the next instruction, thus an interruption cannot be
+	// missed between the two instructions
+	unsafe {
+		match state.hint {
+			Some(hint) => {
+				let addr = MONITOR[core].as_ptr();
+				asm!("monitor", in("eax") addr, in("ecx") 0, in("edx") 0);
+				asm!("sti", "mwait", in("eax") hint, in("ecx") 0);
+			}
+			None => asm!("sti", "hlt"),
+		}
+	}
+	// If the interruption has returned here, the idle time has already been accounted
+}
+
+/// Accounts for the time spent idle by the current CPU core, if it was idle.
+///
+/// This function is called at the beginning of each interruption.
+pub fn exit() {
+	let mut cpu = CPUS[super::get_current_id()].lock();
+	let Some((index, start)) = cpu.entered.take() else {
+		return;
+	};
+	if let (Some(start), Some(end)) = (start, now()) {
+		cpu.stats[index].time += end.saturating_sub(start);
+	}
+}
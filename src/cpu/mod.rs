@@ -1,13 +1,24 @@
 //! CPU-specific features.
 
+pub mod apic;
+pub mod idle;
+pub mod rdrand;
+pub mod smap;
+pub mod smp;
 pub mod sse;
 
+use core::arch::asm;
 use core::ffi::c_void;
 
 extern "C" {
 	/// Tells whether the CPU has SSE.
 	fn cpuid_has_sse() -> bool;
 
+	/// Returns the structured extended feature flags (CPUID 7.0:EBX).
+	fn cpuid_get_ext_features() -> u32;
+	/// Returns the feature flags (CPUID 1:ECX).
+	fn cpuid_get_features_ecx() -> u32;
+
 	/// Returns HWCAP bitmask for ELF.
 	pub fn get_hwcap() -> u32;
 
@@ -26,3 +37,27 @@ extern "C" {
 	/// Sets the content of the %cr4 register.
 	pub fn cr4_set(flags: u32);
 }
+
+/// The maximum number of CPUs supported by the kernel.
+pub const MAX_CPUS: usize = 8;
+
+/// Returns the ID of the current CPU, in the range `0..MAX_CPUS`.
+///
+/// The bootstrap processor always has ID `0`.
+pub fn get_current_id() -> usize {
+	// Before the local APIC is mapped, only the bootstrap processor is running
+	if !apic::is_mapped() {
+		return 0;
+	}
+	smp::get_id_from_apic(apic::get_id()).unwrap_or(0)
+}
+
+/// Returns the value of the Time Stamp Counter, which is incremented at each clock cycle.
+pub fn rdtsc() -> u64 {
+	let lo: u32;
+	let hi: u32;
+	unsafe {
+		asm!("rdtsc", out("eax") lo, out("edx") hi, options(nomem, nostack));
+	}
+	((hi as u64) << 32) | lo as u64
+}
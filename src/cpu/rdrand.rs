@@ -0,0 +1,63 @@
+//! The `RDRAND` and `RDSEED` instructions return random numbers from a hardware generator
+//! embedded in the CPU.
+//!
+//! `RDSEED` returns numbers directly from the entropy source, while `RDRAND` returns numbers
+//! from a generator periodically reseeded from it.
+
+use core::arch::asm;
+
+/// The CPUID bit telling whether `RDRAND` is supported (CPUID 1:ECX).
+const CPUID_RDRAND: u32 = 1 << 30;
+/// The CPUID bit telling whether `RDSEED` is supported (CPUID 7.0:EBX).
+const CPUID_RDSEED: u32 = 1 << 18;
+
+/// The number of attempts before giving up when the generator has no number available.
+const RETRIES: usize = 10;
+
+/// Tells whether the CPU supports `RDRAND`.
+pub fn has_rdrand() -> bool {
+	unsafe { super::cpuid_get_features_ecx() & CPUID_RDRAND != 0 }
+}
+
+/// Tells whether the CPU supports `RDSEED`.
+pub fn has_rdseed() -> bool {
+	unsafe { super::cpuid_get_ext_features() & CPUID_RDSEED != 0 }
+}
+
+/// Returns a random number from `RDRAND`.
+///
+/// If no number is available, the function returns `None`.
+///
+/// The caller must ensure the instruction is supported with [`has_rdrand`].
+pub fn rdrand() -> Option<u32> {
+	for _ in 0..RETRIES {
+		let val: u32;
+		let ok: u8;
+		unsafe {
+			asm!("rdrand {}", "setc {}", out(reg) val, out(reg_byte) ok, options(nomem, nostack));
+		}
+		if ok != 0 {
+			return Some(val);
+		}
+	}
+	None
+}
+
+/// Returns a random number from `RDSEED`.
+///
+/// If no number is available, the function returns `None`.
+///
+/// The caller must ensure the instruction is supported with [`has_rdseed`].
+pub fn rdseed() -> Option<u32> {
+	for _ in 0..RETRIES {
+		let val: u32;
+		let ok: u8;
+		unsafe {
+			asm!("rdseed {}", "setc {}", out(reg) val, out(reg_byte) ok, options(nomem, nostack));
+		}
+		if ok != 0 {
+			return Some(val);
+		}
+	}
+	None
+}
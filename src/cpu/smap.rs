@@ -0,0 +1,114 @@
+//! SMEP (Supervisor Mode Execution Prevention) and SMAP (Supervisor Mode Access Prevention)
+//! prevent the kernel from respectively executing and accessing userspace memory.
+//!
+//! This hardens the kernel against exploits making it use pointers controlled by userspace.
+//!
+//! Since the kernel still needs to access userspace memory on purpose (for example, to read the
+//! arguments of system calls), SMAP can be lifted temporarily by opening a user-access window,
+//! with [`UserAccess`] or [`wrap`].
+//!
+//! A window does not extend to interruption handlers: they clear the `AC` flag on entry, and the
+//! flag of the interrupted code is restored when returning from the interruption.
+
+use core::arch::asm;
+use core::sync::atomic;
+use core::sync::atomic::AtomicBool;
+
+/// The CPUID bit telling whether SMEP is supported.
+const CPUID_SMEP: u32 = 1 << 7;
+/// The CPUID bit telling whether SMAP is supported.
+const CPUID_SMAP: u32 = 1 << 20;
+
+/// The `%cr4` bit enabling SMEP.
+const CR4_SMEP: u32 = 1 << 20;
+/// The `%cr4` bit enabling SMAP.
+const CR4_SMAP: u32 = 1 << 21;
+
+/// The Alignment Check flag in `EFLAGS`. When set, SMAP is lifted.
+const EFLAGS_AC: u32 = 1 << 18;
+
+/// Tells whether SMAP is enabled.
+///
+/// This variable is also read by interruption handlers, to clear the `AC` flag on entry.
+#[no_mangle]
+static SMAP_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Enables SMEP and SMAP, if supported by the CPU.
+///
+/// Before SMEP is enabled, the kernel's code must have been made unaccessible from userspace.
+pub fn init() {
+	let features = unsafe { super::cpuid_get_ext_features() };
+
+	let mut cr4 = unsafe { super::cr4_get() };
+	if features & CPUID_SMEP != 0 {
+		cr4 |= CR4_SMEP;
+	}
+	if features & CPUID_SMAP != 0 {
+		cr4 |= CR4_SMAP;
+	}
+	unsafe {
+		super::cr4_set(cr4);
+	}
+
+	SMAP_ENABLED.store(features & CPUID_SMAP != 0, atomic::Ordering::Relaxed);
+}
+
+/// Tells whether the `AC` flag is set in `EFLAGS`.
+fn is_access_allowed() -> bool {
+	let eflags: usize;
+	unsafe {
+		asm!("pushf", "pop {}", out(reg) eflags);
+	}
+	eflags as u32 & EFLAGS_AC != 0
+}
+
+/// A user-access window. While an instance exists, the kernel is allowed to access userspace
+/// memory.
+///
+/// Windows can be nested. When dropped, the window restores the state it found at creation.
+pub struct UserAccess {
+	/// Tells whether access to userspace was already allowed when the window was opened.
+	prev: bool,
+}
+
+impl UserAccess {
+	/// Opens a new window.
+	pub fn new() -> Self {
+		if !SMAP_ENABLED.load(atomic::Ordering::Relaxed) {
+			return Self {
+				prev: true,
+			};
+		}
+
+		let prev = is_access_allowed();
+		unsafe {
+			// Not `nomem`, so that memory accesses are not moved out of the window
+			asm!("stac", options(nostack));
+		}
+		Self {
+			prev,
+		}
+	}
+}
+
+impl Default for UserAccess {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Drop for UserAccess {
+	fn drop(&mut self) {
+		if !self.prev {
+			unsafe {
+				asm!("clac", options(nostack));
+			}
+		}
+	}
+}
+
+/// Executes the closure `f` inside of a user-access window.
+pub fn wrap<F: FnOnce() -> T, T>(f: F) -> T {
+	let _access = UserAccess::new();
+	f()
+}
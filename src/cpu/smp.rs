@@ -0,0 +1,297 @@
+//! SMP (Symmetric MultiProcessing) allows the kernel to run on several CPU cores at once.
+//!
+//! At boot, only the BSP (BootStrap Processor) is running. The other cores, called APs
+//! (Application Processors), are listed by ACPI and started with the INIT-SIPI-SIPI sequence:
+//! each core then runs the trampoline code, which switches to protected mode before jumping to
+//! [`ap_main`].
+//!
+//! Each core is identified by the ID of its local APIC. The kernel assigns a contiguous ID to
+//! each core, the BSP having ID `0`.
+
+use super::apic;
+use super::MAX_CPUS;
+use crate::errno::EResult;
+use crate::gdt;
+use crate::idt;
+use crate::memory;
+use crate::memory::vmem;
+use crate::process;
+use crate::process::scheduler::CpuSet;
+use crate::process::tss::TSS;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::unit::TimestampScale;
+use core::cmp::max;
+use core::ffi::c_void;
+use core::hint;
+use core::ptr;
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::AtomicU8;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
+
+/// The physical address the trampoline is copied to. This value must match the one in
+/// `trampoline.s`.
+const TRAMPOLINE_ADDR: usize = 0x8000;
+/// The frequency of the scheduler's tick on application processors, in hertz.
+pub const AP_TICK_FREQUENCY: u32 = 100;
+/// The delay between the INIT IPI and the first Start-Up IPI, in nanoseconds.
+const INIT_DELAY: u64 = 10_000_000;
+/// The delay before sending the second Start-Up IPI, in nanoseconds.
+const STARTUP_DELAY: u64 = 1_000_000;
+/// The maximum time to wait for an application processor to start, in nanoseconds.
+const START_TIMEOUT: u64 = 100_000_000;
+/// The maximum number of iterations to wait for other cores to halt.
+const HALT_TIMEOUT_SPINS: usize = 10_000_000;
+
+extern "C" {
+	/// The beginning of the trampoline code.
+	fn smp_trampoline();
+	/// The data of the trampoline, in the layout of [`TrampolineData`].
+	fn smp_trampoline_data();
+	/// The end of the trampoline code.
+	fn smp_trampoline_end();
+}
+
+/// The data passed to an application processor through the trampoline.
+#[repr(C)]
+struct TrampolineData {
+	/// The value of the `%cr0` register.
+	cr0: u32,
+	/// The value of the `%cr3` register.
+	cr3: u32,
+	/// The value of the `%cr4` register.
+	cr4: u32,
+	/// The initial stack pointer.
+	stack: u32,
+	/// The address of the function to jump to.
+	entry: u32,
+}
+
+/// The number of cores that have been detected.
+static CPUS_COUNT: AtomicUsize = AtomicUsize::new(0);
+/// The initial value of an entry in `APIC_IDS`.
+#[allow(clippy::declare_interior_mutable_const)]
+const APIC_ID_INIT: AtomicU8 = AtomicU8::new(0);
+/// The local APIC ID of each core, by kernel ID.
+static APIC_IDS: [AtomicU8; MAX_CPUS] = [APIC_ID_INIT; MAX_CPUS];
+/// The set of cores that are running. The BSP is always running.
+static ONLINE: AtomicU32 = AtomicU32::new(1);
+/// If non-zero, cores receiving a Non-Maskable Interrupt halt.
+///
+/// Read by the NMI handler, in `idt.s`.
+#[export_name = "smp_halt_requested"]
+static HALT_REQUESTED: AtomicU32 = AtomicU32::new(0);
+
+/// Registers a core with the local APIC ID `apic_id`.
+///
+/// If the maximum number of cores is reached, the core is ignored.
+///
+/// This function must be called only at boot, before [`init_bsp`].
+pub fn register_cpu(apic_id: u8) {
+	let id = CPUS_COUNT.load(Ordering::Relaxed);
+	if id >= MAX_CPUS {
+		return;
+	}
+	APIC_IDS[id].store(apic_id, Ordering::Relaxed);
+	CPUS_COUNT.store(id + 1, Ordering::Relaxed);
+}
+
+/// Returns the number of cores detected on the system.
+pub fn get_cpus_count() -> usize {
+	max(CPUS_COUNT.load(Ordering::Relaxed), 1)
+}
+
+/// Returns the set of cores that are running.
+pub fn get_online_cpus() -> CpuSet {
+	ONLINE.load(Ordering::Acquire)
+}
+
+/// Returns the local APIC ID of the core with kernel ID `id`.
+pub fn get_apic_id(id: usize) -> u8 {
+	APIC_IDS[id].load(Ordering::Relaxed)
+}
+
+/// Returns the kernel ID of the core with the local APIC ID `apic_id`.
+pub fn get_id_from_apic(apic_id: u8) -> Option<usize> {
+	(0..get_cpus_count()).find(|i| APIC_IDS[*i].load(Ordering::Relaxed) == apic_id)
+}
+
+/// Initializes the local APIC of the BSP, whose registers are located at the physical address
+/// `apic_addr`.
+///
+/// The BSP is given the kernel ID `0`.
+///
+/// This function must be called only once, at boot, after registering every cores.
+pub fn init_bsp(apic_addr: *mut c_void) -> EResult<()> {
+	apic::map(apic_addr)?;
+	let bsp_apic_id = apic::get_id();
+	match get_id_from_apic(bsp_apic_id) {
+		Some(i) => {
+			let id = APIC_IDS[0].swap(bsp_apic_id, Ordering::Relaxed);
+			APIC_IDS[i].store(id, Ordering::Relaxed);
+		}
+		// The BSP is missing from the ACPI tables. Ignore other cores since they cannot be trusted
+		None => {
+			APIC_IDS[0].store(bsp_apic_id, Ordering::Relaxed);
+			CPUS_COUNT.store(1, Ordering::Relaxed);
+		}
+	}
+	apic::enable();
+	Ok(())
+}
+
+/// Busy-waits for at least `ns` nanoseconds.
+///
+/// Interrupts must be enabled for the clock to advance.
+fn delay(ns: u64) {
+	let now = || clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond).unwrap_or(0);
+	let end = now() + ns;
+	while now() < end {
+		hint::spin_loop();
+	}
+}
+
+/// Starts the application processor with kernel ID `id`.
+///
+/// `data` is the trampoline's data.
+///
+/// If the core does not start before the timeout, the function returns `false`.
+fn start_ap(id: usize, data: *mut TrampolineData) -> bool {
+	let stack = process::get_scheduler().lock().get_tmp_stack(id as _);
+	unsafe {
+		ptr::write_volatile(
+			data,
+			TrampolineData {
+				cr0: super::cr0_get(),
+				cr3: super::cr3_get() as _,
+				cr4: super::cr4_get(),
+				stack: stack as _,
+				entry: ap_main as usize as _,
+			},
+		);
+	}
+
+	let apic_id = APIC_IDS[id].load(Ordering::Relaxed);
+	let page = (TRAMPOLINE_ADDR / memory::PAGE_SIZE) as u8;
+	let is_online = || get_online_cpus() & (1 << id) != 0;
+	apic::send_init(apic_id);
+	delay(INIT_DELAY);
+	apic::send_startup(apic_id, page);
+	delay(STARTUP_DELAY);
+	if !is_online() {
+		apic::send_startup(apic_id, page);
+	}
+
+	let now = || clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond).unwrap_or(0);
+	let timeout = now() + START_TIMEOUT;
+	while !is_online() {
+		if now() >= timeout {
+			return false;
+		}
+		hint::spin_loop();
+	}
+	true
+}
+
+/// Starts every application processors.
+///
+/// The scheduler must be initialized before calling this function, since the application
+/// processors start running processes as soon as they are online.
+///
+/// This function must be called only once, at boot.
+pub fn init() -> EResult<()> {
+	let count = get_cpus_count();
+	if count <= 1 || !apic::is_mapped() {
+		return Ok(());
+	}
+
+	vmem::tlb::init()?;
+
+	// Interrupts are required to measure time
+	crate::sti!();
+	apic::calibrate_timer(delay);
+
+	// Copy the trampoline. Application processors start in real mode, thus it must be located
+	// in the first megabyte of memory
+	let trampoline = smp_trampoline as usize;
+	let len = smp_trampoline_end as usize - trampoline;
+	let dest = memory::kern_to_virt(TRAMPOLINE_ADDR as *mut u8) as *mut u8;
+	unsafe {
+		ptr::copy_nonoverlapping(trampoline as *const u8, dest, len);
+	}
+	let data_off = smp_trampoline_data as usize - trampoline;
+	let data = unsafe { dest.add(data_off) as *mut TrampolineData };
+
+	// The trampoline keeps running right after enabling paging, thus it must be identity mapped
+	crate::get_vmem().lock().as_ref().unwrap().map(
+		TRAMPOLINE_ADDR as _,
+		TRAMPOLINE_ADDR as _,
+		vmem::x86::FLAG_WRITE,
+	)?;
+
+	for id in 1..count {
+		if !start_ap(id, data) {
+			crate::println!("CPU {id} failed to start");
+		}
+	}
+
+	crate::cli!();
+	crate::get_vmem()
+		.lock()
+		.as_ref()
+		.unwrap()
+		.unmap(TRAMPOLINE_ADDR as _)?;
+	Ok(())
+}
+
+/// The entry point of application processors, jumped to by the trampoline.
+extern "C" fn ap_main() -> ! {
+	let id = super::get_current_id();
+	gdt::init_ap(id);
+	idt::load();
+	super::sse::enable();
+	TSS::init();
+	apic::enable();
+	apic::start_timer(AP_TICK_FREQUENCY);
+
+	ONLINE.fetch_or(1 << id, Ordering::Release);
+	crate::enter_loop();
+}
+
+/// Halts the current core, on request of [`halt_others`].
+///
+/// Called by the NMI handler.
+#[no_mangle]
+extern "C" fn smp_halt() -> ! {
+	let id = super::get_current_id();
+	ONLINE.fetch_and(!(1 << id), Ordering::Release);
+	loop {
+		crate::cli!();
+		crate::hlt!();
+	}
+}
+
+/// Halts every cores except the current one, then waits for them to stop.
+///
+/// This function must be called before the kernel stops running in a way that other cores
+/// cannot cope with, such as when panicking, rebooting or executing another kernel.
+///
+/// Other cores are stopped whatever they are doing, thus the locks they hold are never released.
+pub fn halt_others() {
+	if !apic::is_mapped() {
+		return;
+	}
+	let current = 1 << super::get_current_id();
+	if get_online_cpus() & !current == 0 {
+		return;
+	}
+	HALT_REQUESTED.store(1, Ordering::SeqCst);
+	apic::broadcast_nmi();
+	for _ in 0..HALT_TIMEOUT_SPINS {
+		if get_online_cpus() & !current == 0 {
+			break;
+		}
+		hint::spin_loop();
+	}
+}
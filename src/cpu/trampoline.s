@@ -0,0 +1,90 @@
+/*
+ * Startup code for application processors.
+ *
+ * This code is copied to a fixed physical address below 1MB before starting the application
+ * processors, since they begin executing in real mode. It switches the core to protected mode,
+ * enables paging with the kernel's page directory, then jumps to the kernel.
+ *
+ * Since the code is copied, every absolute address is computed relative to the copy, with the
+ * expression `TRAMPOLINE_ADDR + (symbol - smp_trampoline)`.
+ */
+
+.global smp_trampoline
+.global smp_trampoline_data
+.global smp_trampoline_end
+
+/*
+ * The physical address the trampoline is copied to.
+ */
+.set TRAMPOLINE_ADDR,	0x8000
+
+.section .text
+
+.code16
+smp_trampoline:
+	cli
+	cld
+
+	xor %ax, %ax
+	mov %ax, %ds
+
+	lgdtl (TRAMPOLINE_ADDR + (trampoline_gdt_desc - smp_trampoline))
+
+	# Enable protected mode
+	mov %cr0, %eax
+	or $1, %eax
+	mov %eax, %cr0
+
+	ljmpl $0x8, $(TRAMPOLINE_ADDR + (trampoline_32 - smp_trampoline))
+
+.code32
+trampoline_32:
+	mov $0x10, %ax
+	mov %ax, %ds
+	mov %ax, %es
+	mov %ax, %ss
+	xor %ax, %ax
+	mov %ax, %fs
+	mov %ax, %gs
+
+	# Enable paging with the same configuration as the bootstrap processor
+	mov (TRAMPOLINE_ADDR + (smp_trampoline_data - smp_trampoline) + 8), %eax
+	mov %eax, %cr4
+	mov (TRAMPOLINE_ADDR + (smp_trampoline_data - smp_trampoline) + 4), %eax
+	mov %eax, %cr3
+	mov (TRAMPOLINE_ADDR + (smp_trampoline_data - smp_trampoline)), %eax
+	mov %eax, %cr0
+
+	# Jump to the kernel
+	mov (TRAMPOLINE_ADDR + (smp_trampoline_data - smp_trampoline) + 12), %esp
+	xor %ebp, %ebp
+	mov (TRAMPOLINE_ADDR + (smp_trampoline_data - smp_trampoline) + 16), %eax
+	call *%eax
+
+.align 8
+
+/*
+ * The temporary GDT, containing flat code and data segments.
+ */
+trampoline_gdt:
+	.quad 0
+	.quad 0x00cf9a000000ffff
+	.quad 0x00cf92000000ffff
+
+trampoline_gdt_desc:
+	.word trampoline_gdt_desc - trampoline_gdt - 1
+	.long (TRAMPOLINE_ADDR + (trampoline_gdt - smp_trampoline))
+
+/*
+ * Data filled by the kernel before starting each application processor: the values of %cr0,
+ * %cr3 and %cr4, the stack pointer and the entry point.
+ */
+.align 4
+smp_trampoline_data:
+	.long 0
+	.long 0
+	.long 0
+	.long 0
+	.long 0
+
+smp_trampoline_end:
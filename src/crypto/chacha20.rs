@@ -56,6 +56,15 @@ pub fn block(input: &[u8; 64], output: &mut [u8; 64]) {
 		quarter_round!(buff[3], buff[4], buff[9], buff[14]);
 	}
 
+	// Add the input to the permuted state so that the function cannot be inverted
+	let mut input_words: [u32; 16] = [0; 16];
+	unsafe {
+		ptr::copy_nonoverlapping(input.as_ptr(), input_words.as_mut_ptr() as *mut u8, 64);
+	}
+	for (b, i) in buff.iter_mut().zip(input_words.iter()) {
+		*b = b.wrapping_add(*i);
+	}
+
 	unsafe {
 		ptr::copy_nonoverlapping(buff.as_ptr() as *mut u8, output.as_mut_ptr(), 64);
 	}
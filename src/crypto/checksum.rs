@@ -64,6 +64,106 @@ pub fn compute_crc32(data: &[u8], table: &[u32; 256]) -> u32 {
 	!crc
 }
 
+/// Computes the Adler-32 checksum on the given data `data`, as defined by RFC1950.
+pub fn compute_adler32(data: &[u8]) -> u32 {
+	/// The largest prime smaller than 65536.
+	const MOD: u32 = 65521;
+	/// The number of bytes that can be summed without overflowing before reducing.
+	const CHUNK: usize = 5552;
+
+	let mut a: u32 = 1;
+	let mut b: u32 = 0;
+	for chunk in data.chunks(CHUNK) {
+		for byte in chunk {
+			a += *byte as u32;
+			b += a;
+		}
+		a %= MOD;
+		b %= MOD;
+	}
+
+	(b << 16) | a
+}
+
+/// Computes the XXH64 hash on the given data `data`, with the seed `seed`.
+pub fn compute_xxh64(data: &[u8], seed: u64) -> u64 {
+	const PRIME1: u64 = 0x9e3779b185ebca87;
+	const PRIME2: u64 = 0xc2b2ae3d27d4eb4f;
+	const PRIME3: u64 = 0x165667b19e3779f9;
+	const PRIME4: u64 = 0x85ebca77c2b2ae63;
+	const PRIME5: u64 = 0x27d4eb2f165667c5;
+
+	fn round(acc: u64, lane: u64) -> u64 {
+		acc.wrapping_add(lane.wrapping_mul(PRIME2))
+			.rotate_left(31)
+			.wrapping_mul(PRIME1)
+	}
+	fn merge(acc: u64, val: u64) -> u64 {
+		(acc ^ round(0, val))
+			.wrapping_mul(PRIME1)
+			.wrapping_add(PRIME4)
+	}
+	fn read64(data: &[u8]) -> u64 {
+		u64::from_le_bytes(data[..8].try_into().unwrap())
+	}
+
+	let mut stripes = data.chunks_exact(32);
+	let mut hash = if data.len() >= 32 {
+		let mut acc = [
+			seed.wrapping_add(PRIME1).wrapping_add(PRIME2),
+			seed.wrapping_add(PRIME2),
+			seed,
+			seed.wrapping_sub(PRIME1),
+		];
+		for stripe in &mut stripes {
+			for (i, acc) in acc.iter_mut().enumerate() {
+				*acc = round(*acc, read64(&stripe[(i * 8)..]));
+			}
+		}
+
+		let hash = acc[0]
+			.rotate_left(1)
+			.wrapping_add(acc[1].rotate_left(7))
+			.wrapping_add(acc[2].rotate_left(12))
+			.wrapping_add(acc[3].rotate_left(18));
+		acc.iter().fold(hash, |hash, acc| merge(hash, *acc))
+	} else {
+		seed.wrapping_add(PRIME5)
+	};
+	hash = hash.wrapping_add(data.len() as u64);
+
+	let mut remaining = stripes.remainder();
+	while remaining.len() >= 8 {
+		hash ^= round(0, read64(remaining));
+		hash = hash
+			.rotate_left(27)
+			.wrapping_mul(PRIME1)
+			.wrapping_add(PRIME4);
+		remaining = &remaining[8..];
+	}
+	if remaining.len() >= 4 {
+		let lane = u32::from_le_bytes(remaining[..4].try_into().unwrap()) as u64;
+		hash ^= lane.wrapping_mul(PRIME1);
+		hash = hash
+			.rotate_left(23)
+			.wrapping_mul(PRIME2)
+			.wrapping_add(PRIME3);
+		remaining = &remaining[4..];
+	}
+	for byte in remaining {
+		hash ^= (*byte as u64).wrapping_mul(PRIME5);
+		hash = hash.rotate_left(11).wrapping_mul(PRIME1);
+	}
+
+	// Avalanche
+	hash ^= hash >> 33;
+	hash = hash.wrapping_mul(PRIME2);
+	hash ^= hash >> 29;
+	hash = hash.wrapping_mul(PRIME3);
+	hash ^= hash >> 32;
+	hash
+}
+
 #[cfg(test)]
 mod test {
 	use super::*;
@@ -78,4 +178,20 @@ mod test {
 
 	// TODO More tests on RFC1071
 	// TODO Test CRC32
+
+	#[test_case]
+	fn adler32() {
+		assert_eq!(compute_adler32(b""), 1);
+		assert_eq!(compute_adler32(b"Wikipedia"), 0x11e60398);
+	}
+
+	#[test_case]
+	fn xxh64() {
+		assert_eq!(compute_xxh64(b"", 0), 0xef46db3751d8e999);
+		assert_eq!(compute_xxh64(b"a", 0), 0xd24ec4f1a98c6e5b);
+		assert_eq!(
+			compute_xxh64(b"Nobody inspects the spammish repetition", 0),
+			0xfbcea83c8a378bf1
+		);
+	}
 }
@@ -1,151 +1,271 @@
-//! This module implements randomness functions.
+//! This module implements the kernel's cryptographically secure pseudorandom number generator
+//! (CSPRNG).
+//!
+//! Entropy is gathered from several sources:
+//! - The jitter of the CPU's timestamp counter, sampled at boot
+//! - The `RDSEED` and `RDRAND` instructions, when available
+//! - The timings of interrupts
+//!
+//! Samples are mixed into an *input pool*. Once enough entropy has been credited to it, the
+//! input pool is used to reseed the *output generator*, which is ChaCha20 used with fast key
+//! erasure: after each request, the key is replaced by output which has not been handed out, so
+//! that a compromise of the state cannot reveal previous outputs.
 
+use crate::cpu;
+use crate::cpu::rdrand;
 use crate::crypto::chacha20;
 use crate::errno::EResult;
-use crate::util::container::ring_buffer::RingBuffer;
-use crate::util::container::vec::Vec;
 use crate::util::lock::IntMutex;
+use core::cmp::min;
 
-/// The size of the entropy buffer in bytes.
-const ENTROPY_BUFFER_SIZE: usize = 32768;
-/// The minimum number of bytes needed to read entropy.
-const ENTROPY_THRESHOLD: usize = 1024;
+/// The ChaCha20 constant.
+const CHACHA_CONSTANT: &[u8; 16] = b"expand 32-byte k";
 
-// TODO Implement entropy extraction (Fast Key Erasure?)
+/// The number of bits of entropy the input pool must hold to reseed the output generator.
+const RESEED_THRESHOLD: usize = 256;
+/// The number of interrupts to be collected in the fast pool before it is mixed into the input
+/// pool.
+const FAST_POOL_INTERRUPTS: usize = 64;
+/// The number of samples of the timestamp counter's jitter taken at boot.
+const JITTER_SAMPLES: usize = 1024;
+/// The number of jitter samples for one bit of entropy to be credited.
+const JITTER_SAMPLES_PER_BIT: usize = 4;
+/// The number of hardware random numbers used at each reseed.
+const HW_SAMPLES: usize = 8;
+/// The nonce used when deriving the key of the output generator from the input pool.
+const RESEED_NONCE: u64 = u64::MAX;
 
-/// An entropy pool.
+/// Computes a ChaCha20 block with the given key, counter and nonce, and writes it to `output`.
+fn chacha_block(key: &[u8; 32], counter: u64, nonce: u64, output: &mut [u8; 64]) {
+	let mut input = [0; 64];
+	input[..16].copy_from_slice(CHACHA_CONSTANT);
+	input[16..48].copy_from_slice(key);
+	input[48..56].copy_from_slice(&counter.to_le_bytes());
+	input[56..].copy_from_slice(&nonce.to_le_bytes());
+	chacha20::block(&input, output);
+}
+
+/// An entropy pool, along with the output generator seeded from it.
 pub struct EntropyPool {
-	/// Data pending to be treated. This buffer is used as a cache when input data is not large
-	/// enough.
-	pending: RingBuffer<u8, Vec<u8>>,
-	/// The buffer containing entropy.
-	buff: RingBuffer<u8, Vec<u8>>,
+	/// The input pool, in which entropy is accumulated.
+	input: [u8; 32],
+	/// The number of blocks mixed into the input pool, making each mixing unique.
+	mix_counter: u64,
+	/// The estimated number of bits of entropy mixed into the input pool since the last reseed.
+	entropy_bits: usize,
+
+	/// The fast pool, in which interrupt samples are folded before being mixed into the input
+	/// pool.
+	fast_pool: [u8; 32],
+	/// The current position in the fast pool.
+	fast_pos: usize,
+	/// The number of interrupts folded into the fast pool.
+	fast_count: usize,
 
-	/// The ChaCha20 counter.
-	counter: u64,
+	/// The key of the output generator.
+	key: [u8; 32],
+	/// Tells whether the output generator has been seeded with enough entropy.
+	ready: bool,
 
-	/// The seed to be used for pseudo-random generation (when the pool runs out of entropy).
-	pseudo_seed: u64,
+	/// Tells whether the CPU supports `RDSEED`.
+	has_rdseed: bool,
+	/// Tells whether the CPU supports `RDRAND`.
+	has_rdrand: bool,
 }
 
 impl EntropyPool {
-	/// Creates a new instance.
-	pub fn new() -> EResult<Self> {
-		Ok(Self {
-			pending: RingBuffer::new(crate::vec![0; 56]?),
-			buff: RingBuffer::new(crate::vec![0; ENTROPY_BUFFER_SIZE]?),
+	/// Creates a new instance, without entropy.
+	pub fn new() -> Self {
+		Self {
+			input: [0; 32],
+			mix_counter: 0,
+			entropy_bits: 0,
+
+			fast_pool: [0; 32],
+			fast_pos: 0,
+			fast_count: 0,
+
+			key: [0; 32],
+			ready: false,
 
-			counter: 0,
+			has_rdseed: rdrand::has_rdseed(),
+			has_rdrand: rdrand::has_rdrand(),
+		}
+	}
 
-			pseudo_seed: 0,
-		})
+	/// Tells whether the pool has been seeded with enough entropy to produce secure output.
+	pub fn is_ready(&self) -> bool {
+		self.ready
 	}
 
-	/// Returns the number of available bytes.
-	pub fn available_bytes(&self) -> usize {
-		self.buff.get_data_len()
+	/// Mixes `data` into the input pool, without crediting entropy.
+	pub fn mix(&mut self, data: &[u8]) {
+		let mut output = [0; 64];
+		for chunk in data.chunks(8) {
+			let mut nonce = [0; 8];
+			nonce[..chunk.len()].copy_from_slice(chunk);
+			chacha_block(
+				&self.input,
+				self.mix_counter,
+				u64::from_le_bytes(nonce),
+				&mut output,
+			);
+			self.input.copy_from_slice(&output[..32]);
+			self.mix_counter = self.mix_counter.wrapping_add(1);
+		}
 	}
 
-	/// Reads entropy from the pool.
+	/// Credits `bits` bits of entropy to the input pool.
 	///
-	/// The function returns the number of bytes read.
+	/// If the input pool holds enough entropy, the output generator is reseeded.
+	pub fn credit(&mut self, bits: usize) {
+		self.entropy_bits = self.entropy_bits.saturating_add(bits);
+		if self.entropy_bits >= RESEED_THRESHOLD {
+			self.reseed();
+		}
+	}
+
+	/// Mixes numbers from the hardware generator into the input pool.
 	///
-	/// If the pool do not contain enough entropy, the function returns `0`, unless
-	/// `bypass_threshold` is set to `true`. In which case, randomness is not guaranteed.
-	pub fn read(&mut self, buff: &mut [u8], bypass_threshold: bool) -> usize {
-		let available = self.buff.get_data_len();
-
-		if available < ENTROPY_THRESHOLD {
-			if !bypass_threshold {
-				return 0;
+	/// Numbers from `RDSEED` are credited as full entropy. Numbers from `RDRAND` are not
+	/// credited since they are derived from a seed.
+	fn mix_hw(&mut self) {
+		for _ in 0..HW_SAMPLES {
+			let seed = self.has_rdseed.then(rdrand::rdseed).flatten();
+			if let Some(val) = seed {
+				self.mix(&val.to_ne_bytes());
+				self.entropy_bits = self.entropy_bits.saturating_add(32);
+			} else if let Some(val) = self.has_rdrand.then(rdrand::rdrand).flatten() {
+				self.mix(&val.to_ne_bytes());
 			}
+		}
+	}
 
-			let mut seed = self.pseudo_seed;
-			for b in buff.iter_mut() {
-				seed = 6364136223846793005u64.wrapping_mul(seed).wrapping_add(1);
-				*b = (seed & 0xff) as _;
-			}
-			self.pseudo_seed = seed;
+	/// Reseeds the output generator from the input pool.
+	fn reseed(&mut self) {
+		self.mix_hw();
 
-			buff.len()
-		} else {
-			self.buff.read(buff)
-		}
+		let mut output = [0; 64];
+		chacha_block(&self.input, self.mix_counter, RESEED_NONCE, &mut output);
+		self.mix_counter = self.mix_counter.wrapping_add(1);
+		// The input pool is replaced so that the new key cannot be recovered from it
+		self.key.copy_from_slice(&output[..32]);
+		self.input.copy_from_slice(&output[32..]);
+		output.fill(0);
+
+		self.entropy_bits = 0;
+		self.ready = true;
 	}
 
-	/// Upddates the seed for the pseudorandom generator.
-	fn update_seed(&mut self, buff: &[u8; 8]) {
-		let mut seed = 0;
-		for (i, b) in buff.iter().enumerate() {
-			seed |= (*b as u64) << (i * 8);
+	/// Feeds the pool with the sample `sample`, taken at the time of an interrupt.
+	///
+	/// Samples are folded into the fast pool, which is cheap enough to be done at each
+	/// interrupt. One bit of entropy is credited each time the fast pool is mixed into the input
+	/// pool.
+	pub fn add_interrupt(&mut self, sample: &[u8]) {
+		for b in sample {
+			let pos = self.fast_pos;
+			self.fast_pool[pos] = self.fast_pool[pos].rotate_left(3) ^ *b;
+			self.fast_pos = (pos + 1) % self.fast_pool.len();
 		}
 
-		self.pseudo_seed = seed;
+		self.fast_count += 1;
+		if self.fast_count >= FAST_POOL_INTERRUPTS {
+			let fast_pool = self.fast_pool;
+			self.mix(&fast_pool);
+			self.fast_count = 0;
+			self.credit(1);
+		}
 	}
 
-	/// Writes entropy to the pool.
+	/// Fills `buff` with random bytes from the output generator.
 	///
-	/// The function returns the number of bytes written.
-	pub fn write(&mut self, buff: &[u8]) -> usize {
-		let mut off = 0;
-		let mut total = 0;
-
-		let mut input: [u8; 64] = [0; 64];
-		let mut output: [u8; 64] = [0; 64];
-
-		// Encode with ChaCha20
-		while off < buff.len() && buff.len() - off >= self.pending.get_size() {
-			// Add data
-			input[0..48].copy_from_slice(&buff[off..(off + 48)]);
-			off += 48;
-
-			// Add counter to buffer
-			for i in 0..8 {
-				input[48 + i] = ((self.counter >> (i * 8)) & 0xff) as _;
-			}
-			off += 8;
+	/// If the pool is not ready, the output is not guaranteed to be unpredictable.
+	pub fn read(&mut self, buff: &mut [u8]) {
+		let mut output = [0; 64];
+		// The first block is reserved for the next key
+		for (counter, chunk) in (1..).zip(buff.chunks_mut(output.len())) {
+			chacha_block(&self.key, counter, 0, &mut output);
+			chunk.copy_from_slice(&output[..chunk.len()]);
+		}
 
-			// Add nonce
-			input[56..].copy_from_slice(&buff[off..(off + 8)]);
-			off += 8;
+		// Fast key erasure
+		chacha_block(&self.key, 0, 0, &mut output);
+		self.key.copy_from_slice(&output[..32]);
+		output.fill(0);
+	}
 
-			// Encode
-			chacha20::block(&input, &mut output);
+	/// Writes the data `buff` to the pool.
+	///
+	/// Since the quality of the data is unknown, no entropy is credited for it.
+	pub fn write(&mut self, buff: &[u8]) {
+		self.mix(buff);
+	}
+}
 
-			// Update pseudo seed
-			let mut seed_in: [u8; 8] = [0; 8];
-			seed_in.copy_from_slice(&input[0..8]);
-			self.update_seed(&seed_in);
+impl Default for EntropyPool {
+	fn default() -> Self {
+		Self::new()
+	}
+}
 
-			let l = self.buff.write(&output);
-			if l == 0 {
-				break;
-			}
+/// The entropy pool.
+pub static ENTROPY_POOL: IntMutex<Option<EntropyPool>> = IntMutex::new(None);
 
-			total += l;
-			self.counter += 1;
-		}
+/// The maximum number of bytes generated at once while holding the lock on the entropy pool.
+const READ_CHUNK: usize = 256;
 
-		// Put remaining bytes into pending buffer
-		while off < buff.len() {
-			let l = self.pending.write(&buff[off..]);
-			if l == 0 {
-				break;
-			}
+/// Tells whether the entropy pool has been seeded with enough entropy.
+pub fn is_ready() -> bool {
+	ENTROPY_POOL
+		.lock()
+		.as_ref()
+		.map(EntropyPool::is_ready)
+		.unwrap_or(false)
+}
 
-			total += l;
+/// Fills `buff` with random bytes.
+///
+/// The lock on the entropy pool is released periodically, since it disables interruptions.
+///
+/// If the pool is not ready, the output is not guaranteed to be unpredictable.
+pub fn fill(buff: &mut [u8]) {
+	let mut off = 0;
+	while off < buff.len() {
+		let end = min(off + READ_CHUNK, buff.len());
+		if let Some(pool) = &mut *ENTROPY_POOL.lock() {
+			pool.read(&mut buff[off..end]);
 		}
-
-		total
+		off = end;
 	}
 }
 
-/// The entropy pool.
-pub static ENTROPY_POOL: IntMutex<Option<EntropyPool>> = IntMutex::new(None);
+/// Returns a random number.
+///
+/// If the entropy pool has not been seeded with enough entropy, the number is not guaranteed to
+/// be unpredictable.
+pub fn get_random_u32() -> u32 {
+	let mut buff = [0; 4];
+	fill(&mut buff);
+	u32::from_ne_bytes(buff)
+}
 
 /// Initializes randomness sources.
+///
+/// The pool is seeded with the hardware generator and the jitter of the timestamp counter.
 pub fn init() -> EResult<()> {
-	*ENTROPY_POOL.lock() = Some(EntropyPool::new()?);
+	let mut pool = EntropyPool::new();
+	pool.mix_hw();
+
+	// The time taken by mixing varies with the state of caches and pipelines
+	let mut prev = cpu::rdtsc();
+	for _ in 0..JITTER_SAMPLES {
+		let now = cpu::rdtsc();
+		pool.mix(&now.wrapping_sub(prev).to_ne_bytes());
+		prev = now;
+	}
+	pool.credit(JITTER_SAMPLES / JITTER_SAMPLES_PER_BIT);
 
+	*ENTROPY_POOL.lock() = Some(pool);
 	Ok(())
 }
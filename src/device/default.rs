@@ -11,11 +11,9 @@ use crate::device::DeviceID;
 use crate::errno;
 use crate::errno::EResult;
 use crate::errno::Errno;
-use crate::file::blocking::BlockHandler;
 use crate::file::path::Path;
 use crate::logger::LOGGER;
 use crate::process::mem_space::MemSpace;
-use crate::process::Process;
 use crate::syscall::ioctl;
 use crate::util::io;
 use crate::util::io::IO;
@@ -99,12 +97,9 @@ impl IO for ZeroDeviceHandle {
 
 /// The random device allows to get random bytes.
 ///
-/// This device will block reading until enough entropy is available.
+/// Reading from this device blocks until the entropy pool has been seeded with enough entropy.
 #[derive(Default)]
-pub struct RandomDeviceHandle {
-	/// The device's block handler.
-	block_handler: BlockHandler,
-}
+pub struct RandomDeviceHandle {}
 
 impl DeviceHandle for RandomDeviceHandle {
 	fn ioctl(
@@ -117,9 +112,8 @@ impl DeviceHandle for RandomDeviceHandle {
 		Err(errno!(EINVAL))
 	}
 
-	fn add_waiting_process(&mut self, proc: &mut Process, mask: u32) -> Result<(), Errno> {
-		self.block_handler.add_waiting_process(proc, mask)
-	}
+	// The pool becomes ready from interrupt handlers, which cannot wake processes. Thus, waiting
+	// processes are not put to sleep and the pool is polled instead
 }
 
 impl IO for RandomDeviceHandle {
@@ -128,40 +122,33 @@ impl IO for RandomDeviceHandle {
 	}
 
 	fn read(&mut self, _: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
-		let mut pool = rand::ENTROPY_POOL.lock();
-
-		self.block_handler.wake_processes(io::POLLIN);
-
-		if let Some(pool) = &mut *pool {
-			let len = pool.read(buff, false);
-			Ok((len as _, false))
-		} else {
-			Ok((0, true))
+		if !rand::is_ready() {
+			return Ok((0, false));
 		}
+		rand::fill(buff);
+		Ok((buff.len() as _, false))
 	}
 
 	fn write(&mut self, _: u64, buff: &[u8]) -> Result<u64, Errno> {
 		let mut pool = rand::ENTROPY_POOL.lock();
-
-		self.block_handler.wake_processes(io::POLLOUT);
-
-		if let Some(pool) = &mut *pool {
-			let len = pool.write(buff);
-			Ok(len as _)
-		} else {
-			Err(errno!(EINVAL))
-		}
+		let pool = pool.as_mut().ok_or_else(|| errno!(EINVAL))?;
+		pool.write(buff);
+		Ok(buff.len() as _)
 	}
 
-	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
-		Ok(io::POLLIN | io::POLLOUT)
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		let mut result = io::POLLOUT;
+		if rand::is_ready() {
+			result |= io::POLLIN;
+		}
+		Ok(result & mask)
 	}
 }
 
 /// This device works exactly like the random device, except it doesn't block.
 ///
-/// If not enough entropy is available, the output might not have a sufficient
-/// quality.
+/// If the entropy pool has not been seeded with enough entropy yet, the output is not
+/// guaranteed to be unpredictable.
 #[derive(Default)]
 pub struct URandomDeviceHandle {}
 
@@ -183,25 +170,15 @@ impl IO for URandomDeviceHandle {
 	}
 
 	fn read(&mut self, _: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
-		let mut pool = rand::ENTROPY_POOL.lock();
-
-		if let Some(pool) = &mut *pool {
-			let len = pool.read(buff, true);
-			Ok((len as _, false))
-		} else {
-			Ok((0, true))
-		}
+		rand::fill(buff);
+		Ok((buff.len() as _, false))
 	}
 
 	fn write(&mut self, _: u64, buff: &[u8]) -> Result<u64, Errno> {
 		let mut pool = rand::ENTROPY_POOL.lock();
-
-		if let Some(pool) = &mut *pool {
-			let len = pool.write(buff);
-			Ok(len as _)
-		} else {
-			Err(errno!(EINVAL))
-		}
+		let pool = pool.as_mut().ok_or_else(|| errno!(EINVAL))?;
+		pool.write(buff);
+		Ok(buff.len() as _)
 	}
 
 	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
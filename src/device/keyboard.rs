@@ -3,6 +3,7 @@
 use crate::device::manager::DeviceManager;
 use crate::device::manager::PhysicalDevice;
 use crate::errno::Errno;
+use crate::power;
 use crate::tty;
 
 /// Enumation of keyboard keys.
@@ -573,6 +574,10 @@ impl KeyboardManager {
 					_ => None,
 				};
 				tty::switch(id);
+
+				if key == KeyboardKey::KeyDelete {
+					power::ctrl_alt_del();
+				}
 			}
 
 			// Getting the tty
@@ -3,15 +3,17 @@
 //! A device file is an interface with a device of the system, which can be
 //! internal or external, or even virtual such as a TTY.
 //!
+//! Device files are created in devtmpfs as soon as devices are registered, and removed when they
+//! are unregistered. See [`crate::file::fs::devtmpfs`].
+//!
 //! Since files management requires devices to be initialized in order to access filesystems, the
-//! system first needs to initialize devices. However, at that stage, device files cannot be
-//! created.
+//! system first needs to initialize devices. At that stage, devtmpfs cannot be mounted yet.
 //!
 //! Thus, devices are initialized in stages:
-//! - **stage 1**: files management is not yet initialized, which means device files are not
-//! created when devices are registered
-//! - **stage 2**: files management is initialized, device files can be created. When switching to
-//! that stage, the files of all device that are already registered are created
+//! - **stage 1**: files management is not yet initialized. Device files are created in devtmpfs,
+//! but are not reachable since it is not mounted
+//! - **stage 2**: files management is initialized. The default devices are created and devtmpfs
+//! is mounted, making every device files reachable. The sysfs is mounted as well
 
 pub mod bar;
 pub mod bus;
@@ -28,9 +30,12 @@ use crate::device::manager::DeviceManager;
 use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file;
+use crate::file::fs::devtmpfs;
+use crate::file::fs::sysfs::SysFS;
+use crate::file::mountpoint;
+use crate::file::mountpoint::MountSource;
+use crate::file::open_file::FileOwner;
 use crate::file::path::Path;
-use crate::file::perm::AccessProfile;
-use crate::file::vfs;
 use crate::file::FileContent;
 use crate::file::Mode;
 use crate::process::mem_space::MemSpace;
@@ -38,14 +43,18 @@ use crate::process::Process;
 use crate::syscall::ioctl;
 use crate::util::boxed::Box;
 use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
 use crate::util::io::IO;
 use crate::util::lock::IntMutex;
 use crate::util::lock::Mutex;
-use crate::util::lock::MutexGuard;
 use crate::util::ptr::arc::Arc;
 use crate::util::TryClone;
+use core::any::Any;
+use core::ffi::c_int;
 use core::ffi::c_void;
 use core::fmt;
+use core::num::NonZeroU64;
 use keyboard::KeyboardManager;
 use storage::StorageManager;
 
@@ -128,6 +137,30 @@ pub trait DeviceHandle: IO {
 	fn add_waiting_process(&mut self, _proc: &mut Process, _mask: u32) -> Result<(), Errno> {
 		Ok(())
 	}
+
+	/// Registers or unregisters an open file for signal-driven IO on the device.
+	///
+	/// Arguments:
+	/// - `fd` is the file descriptor reported to the owner.
+	/// - `owner` is the owner of the open file.
+	/// - `on` tells whether the open file is registered.
+	///
+	/// If the device cannot block, the function does nothing.
+	fn set_async(
+		&mut self,
+		_fd: c_int,
+		_owner: &Arc<IntMutex<FileOwner>>,
+		_on: bool,
+	) -> Result<(), Errno> {
+		Ok(())
+	}
+
+	/// Returns the size of a block of the device in bytes.
+	///
+	/// If the device is not a block device, the function returns `None`.
+	fn get_block_size(&self) -> Option<NonZeroU64> {
+		None
+	}
 }
 
 /// Structure representing a device, either a block device or a char device.
@@ -194,55 +227,18 @@ impl Device {
 		self.handle.as_mut()
 	}
 
-	/// Creates the device file associated with the structure.
+	/// Creates the device file associated with the structure in devtmpfs.
 	///
 	/// If the file already exist, the function does nothing.
-	///
-	/// The function takes a mutex guard because it needs to unlock the device
-	/// in order to create the file without a deadlock since the VFS accesses a device to write on
-	/// the filesystem.
-	pub fn create_file(dev: MutexGuard<Device, true>) -> EResult<()> {
-		let file_content = dev.id.to_file_content();
-		let path = dev.path.try_clone()?;
-		let mode = dev.mode;
-
-		drop(dev);
-
-		// Tells whether the file already exists
-		let file_exists = vfs::get_file_from_path(&path, &AccessProfile::KERNEL, true).is_ok();
-		if !file_exists {
-			// Create the directories in which the device file is located
-			let mut dir_path = path;
-			let filename = dir_path.pop().unwrap();
-			file::util::create_dirs(&dir_path)?;
-
-			// Get the parent directory
-			let parent_mutex = vfs::get_file_from_path(&dir_path, &AccessProfile::KERNEL, true)?;
-			let mut parent = parent_mutex.lock();
-
-			// Create the device file
-			vfs::create_file(
-				&mut parent,
-				filename,
-				&AccessProfile::KERNEL,
-				mode,
-				file_content,
-			)?;
-		}
-
-		Ok(())
+	pub fn create_file(&self) -> EResult<()> {
+		devtmpfs::add_node(&self.path, self.mode, self.id.to_file_content())
 	}
 
-	/// If exists, removes the device file.
+	/// If exists, removes the device file from devtmpfs.
 	///
 	/// If the file doesn't exist, the function does nothing.
-	pub fn remove_file(&mut self) -> EResult<()> {
-		if let Ok(file_mutex) = vfs::get_file_from_path(&self.path, &AccessProfile::KERNEL, true) {
-			let mut file = file_mutex.lock();
-			vfs::remove_file(&mut file, &AccessProfile::KERNEL)?;
-		}
-
-		Ok(())
+	pub fn remove_file(&self) -> EResult<()> {
+		devtmpfs::remove_node(&self.path)
 	}
 }
 
@@ -262,6 +258,10 @@ impl IO for Device {
 	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
 		self.handle.poll(mask)
 	}
+
+	fn flush(&mut self) -> Result<(), Errno> {
+		self.handle.flush()
+	}
 }
 
 impl Drop for Device {
@@ -272,26 +272,44 @@ impl Drop for Device {
 	}
 }
 
+/// The path at which the sysfs is mounted at boot.
+const SYSFS_PATH: &[u8] = b"/sys";
+
 /// The list of registered devices.
 static DEVICES: Mutex<HashMap<DeviceID, Arc<Mutex<Device>>>> = Mutex::new(HashMap::new());
 
+/// Executes `f` on the sysfs, if loaded.
+fn with_sysfs<F: FnOnce(&mut SysFS) -> EResult<()>>(f: F) -> EResult<()> {
+	let source = MountSource::NoDev(b"sysfs".try_into()?);
+	let Some(fs) = mountpoint::get_fs(&source) else {
+		return Ok(());
+	};
+	let mut fs_guard = fs.lock();
+	let fs = &mut *fs_guard as &mut dyn Any;
+	f(fs.downcast_mut::<SysFS>().unwrap())
+}
+
 /// Registers the given device.
 ///
 /// If the device ID is already used, the function fails.
 ///
-/// If files management is initialized, the function creates the associated device file.
+/// The function creates the associated device file.
 pub fn register(device: Device) -> Result<(), Errno> {
 	let id = device.id.clone();
 	let dev_mutex = Arc::new(Mutex::new(device))?;
 
 	{
 		let mut devs = DEVICES.lock();
-		devs.insert(id, dev_mutex.clone())?;
+		devs.insert(id.clone(), dev_mutex.clone())?;
 	}
 
-	// Create file if files management has been initialized
-	if file::is_init() {
-		Device::create_file(dev_mutex.lock())?;
+	let name = {
+		let dev = dev_mutex.lock();
+		dev.create_file()?;
+		dev.get_path().last().map(String::try_clone).transpose()?
+	};
+	if let Some(name) = name {
+		with_sysfs(|sysfs| sysfs.add_device(&id, name))?;
 	}
 
 	Ok(())
@@ -301,7 +319,7 @@ pub fn register(device: Device) -> Result<(), Errno> {
 ///
 /// If the device doesn't exist, the function does nothing.
 ///
-/// If files management is initialized, the function removes the associated device file.
+/// The function removes the associated device file.
 pub fn unregister(id: &DeviceID) -> Result<(), Errno> {
 	let dev_mutex = {
 		let mut devs = DEVICES.lock();
@@ -310,8 +328,9 @@ pub fn unregister(id: &DeviceID) -> Result<(), Errno> {
 
 	if let Some(dev_mutex) = dev_mutex {
 		// Remove file
-		let mut dev = dev_mutex.lock();
-		dev.remove_file()?;
+		dev_mutex.lock().remove_file()?;
+		// The device must not be locked since reading attributes in the sysfs locks it
+		with_sysfs(|sysfs| sysfs.remove_device(id))?;
 	}
 
 	Ok(())
@@ -325,6 +344,17 @@ pub fn get(id: &DeviceID) -> Option<Arc<Mutex<Device>>> {
 	devs.get(id).cloned()
 }
 
+/// Returns the IDs of every registered devices, along with the path to their file.
+pub fn list() -> EResult<Vec<(DeviceID, Path)>> {
+	let devs = DEVICES.lock();
+	let mut list = Vec::with_capacity(devs.len())?;
+	for (id, dev_mutex) in devs.iter() {
+		let dev = dev_mutex.lock();
+		list.push((id.clone(), dev.get_path().try_clone()?))?;
+	}
+	Ok(list)
+}
+
 /// Initializes devices management.
 pub fn init() -> Result<(), Errno> {
 	let keyboard_manager = KeyboardManager::new();
@@ -349,21 +379,21 @@ pub fn init() -> Result<(), Errno> {
 	Ok(())
 }
 
-/// Switches to stage 2, creating device files of devices that are already registered.
+/// Switches to stage 2, creating the default devices, then mounting devtmpfs and sysfs.
 ///
 /// This function must be used only once at boot, after files management has been initialized.
 pub fn stage2() -> Result<(), Errno> {
 	default::create().unwrap_or_else(|e| panic!("Failed to create default devices! ({e})"));
 
-	// Unsafe access is made to avoid a deadlock
-	// This is acceptable since the container is not borrowed as mutable, both here and further
-	// into the function
-	let devices = unsafe { DEVICES.get_payload() };
+	let path = Path::from_str(devtmpfs::MOUNT_PATH, false)?;
+	file::util::create_dirs(&path)?;
+	let source = MountSource::NoDev(String::try_from(b"devtmpfs")?);
+	mountpoint::create(source, None, 0, path, b"")?;
 
-	for (_, dev_mutex) in devices.iter() {
-		let dev_guard = dev_mutex.lock();
-		Device::create_file(dev_guard)?;
-	}
+	let path = Path::from_str(SYSFS_PATH, false)?;
+	file::util::create_dirs(&path)?;
+	let source = MountSource::NoDev(String::try_from(b"sysfs")?);
+	mountpoint::create(source, None, 0, path, b"")?;
 
 	Ok(())
 }
@@ -18,6 +18,8 @@ use crate::device::DeviceType;
 use crate::errno;
 use crate::errno::EResult;
 use crate::errno::Errno;
+use crate::file::mountpoint;
+use crate::file::mountpoint::MountSource;
 use crate::file::path::Path;
 use crate::file::Mode;
 use crate::memory::malloc;
@@ -94,6 +96,13 @@ pub trait StorageInterface {
 	/// If the offset and size are out of bounds, the function returns an error.
 	fn write(&mut self, buf: &[u8], offset: u64, size: u64) -> Result<(), Errno>;
 
+	/// Writes the content of the device's volatile write cache to the storage medium.
+	///
+	/// By default, the interface is assumed to have no write cache.
+	fn flush(&mut self) -> Result<(), Errno> {
+		Ok(())
+	}
+
 	// Unit testing is done through ramdisk testing
 	/// Reads bytes from storage at offset `offset`, writing the data to `buf`.
 	///
@@ -300,7 +309,7 @@ impl DeviceHandle for StorageDeviceHandle {
 				// Write to userspace
 				let mut mem_space_guard = mem_space.lock();
 				let hd_geo_ptr: SyscallPtr<HdGeometry> = (argp as usize).into();
-				let hd_geo_ref = hd_geo_ptr
+				let mut hd_geo_ref = hd_geo_ptr
 					.get_mut(&mut mem_space_guard)?
 					.ok_or_else(|| errno!(EFAULT))?;
 				*hd_geo_ref = hd_geo;
@@ -309,7 +318,15 @@ impl DeviceHandle for StorageDeviceHandle {
 			}
 
 			ioctl::BLKRRPART => {
-				StorageManager::clear_partitions(self.major)?;
+				// Partitions cannot contain partition tables
+				if self.partition.is_some() {
+					return Err(errno!(EINVAL));
+				}
+				// Partitions cannot be removed while in use
+				if StorageManager::is_partition_mounted(self.major, self.storage_id) {
+					return Err(errno!(EBUSY));
+				}
+				StorageManager::clear_partitions(self.major, self.storage_id)?;
 				StorageManager::read_partitions(
 					self.interface.clone(),
 					self.major,
@@ -321,18 +338,11 @@ impl DeviceHandle for StorageDeviceHandle {
 			}
 
 			ioctl::BLKSSZGET => {
-				let blk_size = {
-					if let Some(interface) = self.interface.upgrade() {
-						let interface = interface.lock();
-						interface.get_block_size().get()
-					} else {
-						0
-					}
-				};
+				let blk_size = self.get_block_size().map(NonZeroU64::get).unwrap_or(0);
 
 				let mut mem_space_guard = mem_space.lock();
 				let size_ptr: SyscallPtr<u32> = (argp as usize).into();
-				let size_ref = size_ptr
+				let mut size_ref = size_ptr
 					.get_mut(&mut mem_space_guard)?
 					.ok_or_else(|| errno!(EFAULT))?;
 				*size_ref = blk_size as _;
@@ -345,7 +355,7 @@ impl DeviceHandle for StorageDeviceHandle {
 
 				let mut mem_space_guard = mem_space.lock();
 				let size_ptr: SyscallPtr<u64> = (argp as usize).into();
-				let size_ref = size_ptr
+				let mut size_ref = size_ptr
 					.get_mut(&mut mem_space_guard)?
 					.ok_or_else(|| errno!(EFAULT))?;
 				*size_ref = size;
@@ -356,6 +366,12 @@ impl DeviceHandle for StorageDeviceHandle {
 			_ => Err(errno!(ENOTTY)),
 		}
 	}
+
+	fn get_block_size(&self) -> Option<NonZeroU64> {
+		let interface = self.interface.upgrade()?;
+		let interface = interface.lock();
+		Some(interface.get_block_size())
+	}
 }
 
 impl IO for StorageDeviceHandle {
@@ -390,11 +406,16 @@ impl IO for StorageDeviceHandle {
 
 				None => (0, interface.get_size()),
 			};
-			if (offset + buff.len() as u64) > size {
+			let end = offset
+				.checked_add(buff.len() as u64)
+				.ok_or_else(|| errno!(EINVAL))?;
+			if end > size {
 				return Err(errno!(EINVAL));
 			}
 
-			interface.read_bytes(buff, start + offset)
+			let (len, _) = interface.read_bytes(buff, start + offset)?;
+			// The end of the whole device is not relevant for partitions
+			Ok((len, end >= size))
 		} else {
 			Err(errno!(ENODEV))
 		}
@@ -416,7 +437,10 @@ impl IO for StorageDeviceHandle {
 
 				None => (0, interface.get_size()),
 			};
-			if (offset + buff.len() as u64) > size {
+			let end = offset
+				.checked_add(buff.len() as u64)
+				.ok_or_else(|| errno!(EINVAL))?;
+			if end > size {
 				return Err(errno!(EINVAL));
 			}
 
@@ -429,6 +453,12 @@ impl IO for StorageDeviceHandle {
 	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
 		Ok(0)
 	}
+
+	fn flush(&mut self) -> Result<(), Errno> {
+		let interface = self.interface.upgrade().ok_or_else(|| errno!(ENODEV))?;
+		let mut interface = interface.lock();
+		interface.flush()
+	}
 }
 
 /// An instance of StorageManager manages devices on a whole major number.
@@ -475,9 +505,12 @@ impl StorageManager {
 		};
 		let partitions = partitions_table.get_partitions(&mut *s)?;
 
-		let iter = partitions.into_iter().take(MAX_PARTITIONS - 1).enumerate();
-		for (i, partition) in iter {
-			let part_nbr = (i + 1) as u32;
+		// Partitions whose number does not fit in the range of minor numbers are ignored
+		let iter = partitions
+			.into_iter()
+			.filter(|p| (p.get_number() as usize) < MAX_PARTITIONS);
+		for partition in iter {
+			let part_nbr = partition.get_number();
 
 			// Add the partition number to the path
 			let path_str = crate::format!("{path_prefix}{part_nbr}")?;
@@ -494,8 +527,7 @@ impl StorageManager {
 			let device = Device::new(
 				DeviceID {
 					type_: DeviceType::Block,
-					// TODO use a different major for different storage device types
-					major: STORAGE_MAJOR,
+					major,
 					minor: storage_id * MAX_PARTITIONS as u32 + part_nbr,
 				},
 				path,
@@ -508,16 +540,43 @@ impl StorageManager {
 		Ok(())
 	}
 
-	/// Clears device files for every partitions.
+	/// Returns the IDs of the devices of the partitions of a storage device.
 	///
-	/// `major` is the major number of the devices to be removed.
-	pub fn clear_partitions(major: u32) -> Result<(), Errno> {
-		for i in 1..MAX_PARTITIONS {
-			device::unregister(&DeviceID {
-				type_: DeviceType::Block,
-				major,
-				minor: i as _,
-			})?;
+	/// Arguments:
+	/// - `major` is the major number of the device.
+	/// - `storage_id` is the ID of the storage device in the manager.
+	fn partition_ids(major: u32, storage_id: u32) -> impl Iterator<Item = DeviceID> {
+		(1..MAX_PARTITIONS as u32).map(move |i| DeviceID {
+			type_: DeviceType::Block,
+			major,
+			minor: storage_id * MAX_PARTITIONS as u32 + i,
+		})
+	}
+
+	/// Tells whether a partition of a storage device is mounted.
+	///
+	/// Arguments:
+	/// - `major` is the major number of the device.
+	/// - `storage_id` is the ID of the storage device in the manager.
+	pub fn is_partition_mounted(major: u32, storage_id: u32) -> bool {
+		Self::partition_ids(major, storage_id).any(|id| {
+			let source = MountSource::Device {
+				dev_type: id.type_,
+				major: id.major,
+				minor: id.minor,
+			};
+			mountpoint::get_fs(&source).is_some()
+		})
+	}
+
+	/// Clears device files for every partitions of a storage device.
+	///
+	/// Arguments:
+	/// - `major` is the major number of the device.
+	/// - `storage_id` is the ID of the storage device in the manager.
+	pub fn clear_partitions(major: u32, storage_id: u32) -> Result<(), Errno> {
+		for id in Self::partition_ids(major, storage_id) {
+			device::unregister(&id)?;
 		}
 
 		Ok(())
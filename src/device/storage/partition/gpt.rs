@@ -203,13 +203,13 @@ impl Gpt {
 		true
 	}
 
-	/// Returns the list of entries in the table.
+	/// Returns the list of used entries in the table, along with their partition number.
 	///
 	/// `storage` is the storage device interface.
 	fn get_entries(
 		&self,
 		storage: &mut dyn StorageInterface,
-	) -> Result<Vec<Box<GPTEntry>>, Errno> {
+	) -> Result<Vec<(u32, Box<GPTEntry>)>, Errno> {
 		let block_size = storage.get_block_size();
 		let blocks_count = storage.get_blocks_count();
 
@@ -246,7 +246,9 @@ impl Gpt {
 				return Err(errno!(EINVAL));
 			}
 
-			entries.push(entry)?;
+			// Partitions are numbered after their index in the table, even if some entries
+			// are unused
+			entries.push((i + 1, entry))?;
 		}
 
 		Ok(entries)
@@ -268,7 +270,8 @@ impl Table for Gpt {
 		let alternate_entries = alternate_hdr.get_entries(storage)?;
 
 		// Check entries correctness
-		for (main_entry, alternate_entry) in main_entries.iter().zip(alternate_entries.iter()) {
+		let entries = main_entries.iter().zip(alternate_entries.iter());
+		for ((_, main_entry), (_, alternate_entry)) in entries {
 			if !main_entry.eq(alternate_entry, main_hdr.entry_size as _, blocks_count) {
 				return Err(errno!(EINVAL));
 			}
@@ -285,14 +288,14 @@ impl Table for Gpt {
 		let blocks_count = storage.get_blocks_count();
 		let mut partitions = Vec::new();
 
-		for e in self.get_entries(storage)? {
+		for (number, e) in self.get_entries(storage)? {
 			let start = translate_lba(e.start, blocks_count).ok_or_else(|| errno!(EINVAL))?;
 			let end = translate_lba(e.end, blocks_count).ok_or_else(|| errno!(EINVAL))?;
 			// Doesn't overflow because the condition `end >= start` has already been
 			// checked + 1 is required because the ending LBA is included
 			let size = (end - start) + 1;
 
-			partitions.push(Partition::new(start, size))?;
+			partitions.push(Partition::new(number, start, size))?;
 		}
 
 		Ok(partitions)
@@ -3,6 +3,10 @@
 //!
 //! The partition table is located on the first sector of the boot disk,
 //! alongside with the boot code.
+//!
+//! The table has room for four primary partitions. One of them may be an extended partition,
+//! which contains a chain of Extended Boot Records (EBR), each describing a logical partition
+//! and the location of the next EBR.
 
 use super::Partition;
 use super::Table;
@@ -13,6 +17,16 @@ use crate::util::container::vec::Vec;
 /// The signature of the MBR partition table.
 const MBR_SIGNATURE: u16 = 0xaa55;
 
+/// Partition type: protective partition for a GPT disk.
+const TYPE_GPT_PROTECTIVE: u8 = 0xee;
+/// The partition types of extended partitions.
+const TYPES_EXTENDED: [u8; 3] = [0x05, 0x0f, 0x85];
+
+/// The number of the first logical partition.
+const FIRST_LOGICAL: u32 = 5;
+/// The maximum number of logical partitions read, to prevent looping on corrupted chains.
+const MAX_LOGICAL: u32 = 64;
+
 /// Structure representing a partition.
 #[derive(Clone)]
 #[repr(C, packed)]
@@ -58,18 +72,34 @@ impl Clone for MbrTable {
 	}
 }
 
-impl Table for MbrTable {
-	fn read(storage: &mut dyn StorageInterface) -> Result<Option<Self>, Errno> {
-		let mut first_sector: [u8; 512] = [0; 512];
+impl MbrPartition {
+	/// Tells whether the entry is used.
+	fn is_used(&self) -> bool {
+		self.partition_type != 0
+	}
 
-		if first_sector.len() as u64 > storage.get_size() {
+	/// Tells whether the partition is an extended partition.
+	fn is_extended(&self) -> bool {
+		TYPES_EXTENDED.contains(&self.partition_type)
+	}
+}
+
+impl MbrTable {
+	/// Reads the table located on the block `lba` of the storage interface `storage`.
+	///
+	/// If no table is present, the function returns `None`.
+	fn read_at(storage: &mut dyn StorageInterface, lba: u64) -> Result<Option<Self>, Errno> {
+		let mut sector: [u8; 512] = [0; 512];
+
+		let off = lba * storage.get_block_size().get();
+		if off + sector.len() as u64 > storage.get_size() {
 			return Ok(None);
 		}
-		storage.read_bytes(&mut first_sector, 0)?;
+		storage.read_bytes(&mut sector, off)?;
 
 		// Valid because taking the pointer to the buffer on the stack which has the
 		// same size as the structure
-		let mbr_table = unsafe { &*(first_sector.as_ptr() as *const MbrTable) };
+		let mbr_table = unsafe { &*(sector.as_ptr() as *const MbrTable) };
 		if mbr_table.signature != MBR_SIGNATURE {
 			return Ok(None);
 		}
@@ -77,21 +107,70 @@ impl Table for MbrTable {
 		Ok(Some(mbr_table.clone()))
 	}
 
+	/// Reads the logical partitions of the extended partition starting at block `ext_start`
+	/// and inserts them in `partitions`.
+	fn read_logical(
+		storage: &mut dyn StorageInterface,
+		ext_start: u64,
+		partitions: &mut Vec<Partition>,
+	) -> Result<(), Errno> {
+		let mut ebr_lba = ext_start;
+		for number in FIRST_LOGICAL..(FIRST_LOGICAL + MAX_LOGICAL) {
+			let Some(ebr) = Self::read_at(storage, ebr_lba)? else {
+				break;
+			};
+
+			// The first entry describes the logical partition, relative to the EBR
+			let logical = &ebr.partitions[0];
+			if logical.is_used() {
+				let start = ebr_lba + logical.lba_start as u64;
+				partitions.push(Partition::new(number, start, logical.sectors_count as _))?;
+			}
+
+			// The second entry points to the next EBR, relative to the extended partition
+			let next = &ebr.partitions[1];
+			if !next.is_used() || next.lba_start == 0 {
+				break;
+			}
+			ebr_lba = ext_start + next.lba_start as u64;
+		}
+
+		Ok(())
+	}
+}
+
+impl Table for MbrTable {
+	fn read(storage: &mut dyn StorageInterface) -> Result<Option<Self>, Errno> {
+		Self::read_at(storage, 0)
+	}
+
 	fn get_type(&self) -> &'static str {
 		"MBR"
 	}
 
-	fn get_partitions(&self, _: &mut dyn StorageInterface) -> Result<Vec<Partition>, Errno> {
+	fn get_partitions(&self, storage: &mut dyn StorageInterface) -> Result<Vec<Partition>, Errno> {
 		let mut partitions = Vec::<Partition>::new();
 
-		for mbr_partition in self.partitions.iter() {
-			if mbr_partition.partition_type != 0 {
-				let partition = Partition::new(
-					mbr_partition.lba_start as _,
-					mbr_partition.sectors_count as _,
-				);
-				partitions.push(partition)?;
+		// Primary partitions are numbered after their slot in the table
+		let mut ext_start = None;
+		for (i, mbr_partition) in self.partitions.iter().enumerate() {
+			// A protective partition covers a GPT disk whose table could not be read
+			if !mbr_partition.is_used() || mbr_partition.partition_type == TYPE_GPT_PROTECTIVE {
+				continue;
+			}
+			if mbr_partition.is_extended() {
+				ext_start.get_or_insert(mbr_partition.lba_start as u64);
+				continue;
 			}
+			let partition = Partition::new(
+				(i + 1) as _,
+				mbr_partition.lba_start as _,
+				mbr_partition.sectors_count as _,
+			);
+			partitions.push(partition)?;
+		}
+		if let Some(ext_start) = ext_start {
+			Self::read_logical(storage, ext_start, &mut partitions)?;
 		}
 
 		Ok(partitions)
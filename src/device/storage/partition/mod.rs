@@ -13,6 +13,8 @@ use mbr::MbrTable;
 
 /// Structure representing a disk partition.
 pub struct Partition {
+	/// The number of the partition in the table, starting at `1`.
+	number: u32,
 	/// The offset to the first sector of the partition.
 	offset: u64,
 	/// The number of sectors in the partition.
@@ -20,15 +22,22 @@ pub struct Partition {
 }
 
 impl Partition {
-	/// Creates a new instance with the given partition offset `offset` and size
-	/// `size`.
-	pub fn new(offset: u64, size: u64) -> Self {
+	/// Creates a new instance with the given partition number `number`, offset `offset` and
+	/// size `size`.
+	pub fn new(number: u32, offset: u64, size: u64) -> Self {
 		Self {
+			number,
 			offset,
 			size,
 		}
 	}
 
+	/// Returns the number of the partition in the table, starting at `1`.
+	#[inline]
+	pub fn get_number(&self) -> u32 {
+		self.number
+	}
+
 	/// Returns the offset of the first sector of the partition.
 	#[inline]
 	pub fn get_offset(&self) -> u64 {
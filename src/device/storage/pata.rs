@@ -597,10 +597,20 @@ impl StorageInterface for PATAInterface {
 				}
 			}
 
-			self.cache_flush();
 			i += count;
 		}
 
 		Ok(())
 	}
+
+	fn flush(&mut self) -> Result<(), Errno> {
+		self.select(false);
+		self.cache_flush();
+
+		let status = self.get_status();
+		if (status & STATUS_ERR != 0) || (status & STATUS_DF != 0) {
+			return Err(errno!(EIO));
+		}
+		Ok(())
+	}
 }
@@ -4,8 +4,11 @@
 use crate::device::DeviceHandle;
 use crate::errno;
 use crate::errno::Errno;
+use crate::file::open_file::FileOwner;
+use crate::file::perm::CAP_SYS_ADMIN;
 use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::mem_space::MemSpace;
+use crate::process::pgroup;
 use crate::process::pid::Pid;
 use crate::process::signal::Signal;
 use crate::process::signal::SignalHandler;
@@ -20,6 +23,7 @@ use crate::util::io;
 use crate::util::io::IO;
 use crate::util::lock::IntMutex;
 use crate::util::ptr::arc::Arc;
+use core::ffi::c_int;
 use core::ffi::c_void;
 
 /// Structure representing a TTY device's handle.
@@ -51,7 +55,9 @@ impl TTYDeviceHandle {
 
 	/// Checks whether the process is allowed to read from the TTY.
 	///
-	/// If not, it is killed with a `SIGTTIN` signal.
+	/// If the process belongs to a background process group of the session of the TTY, its group
+	/// is sent a `SIGTTIN` signal and the function returns [`errno::ERESTARTSYS`], so that the
+	/// operation is retried once the process is resumed.
 	///
 	/// Arguments:
 	/// - `process` is the process.
@@ -59,44 +65,53 @@ impl TTYDeviceHandle {
 	///
 	/// This function must be called before performing the read operation.
 	fn check_sigttin(&self, proc: &mut Process, tty: &TTY) -> Result<(), Errno> {
-		if proc.pgid != tty.get_pgrp() {
-			if proc.is_signal_blocked(&Signal::SIGTTIN)
-				|| proc.get_signal_handler(&Signal::SIGTTIN) == SignalHandler::Ignore
-				|| proc.is_in_orphan_process_group()
-			{
-				return Err(errno!(EIO));
-			}
-
-			proc.kill_group(Signal::SIGTTIN, false);
+		if tty.get_sid() != proc.sid || proc.pgid == tty.get_pgrp() {
+			return Ok(());
+		}
+		// The signal cannot stop the process
+		if proc.is_signal_blocked(&Signal::SIGTTIN)
+			|| proc.get_signal_handler(&Signal::SIGTTIN) == SignalHandler::Ignore
+			|| proc.is_in_orphan_process_group()
+		{
+			return Err(errno!(EIO));
 		}
 
-		Ok(())
+		proc.kill_group(Signal::SIGTTIN, false);
+		Err(errno!(ERESTARTSYS))
 	}
 
-	/// Checks whether the process is allowed to write to the TTY.
+	/// Checks whether the process is allowed to write to the TTY or to change its settings.
 	///
-	/// If not, it is killed with a `SIGTTOU` signal.
+	/// If the process belongs to a background process group of the session of the TTY, its group
+	/// is sent a `SIGTTOU` signal and the function returns [`errno::ERESTARTSYS`], so that the
+	/// operation is retried once the process is resumed.
 	///
 	/// Arguments:
 	/// - `process` is the process.
 	/// - `tty` is the TTY.
+	/// - `always` tells whether the check applies regardless of the `TOSTOP` flag. This is the
+	/// case for operations changing the settings of the TTY.
 	///
-	/// This function must be called before performing the write operation.
-	fn check_sigttou(&self, proc: &mut Process, tty: &TTY) -> Result<(), Errno> {
-		if tty.get_termios().c_lflag & termios::TOSTOP != 0 {
-			if proc.is_signal_blocked(&Signal::SIGTTIN)
-				|| proc.get_signal_handler(&Signal::SIGTTIN) == SignalHandler::Ignore
-			{
-				return Ok(());
-			}
-			if proc.is_in_orphan_process_group() {
-				return Err(errno!(EIO));
-			}
-
-			proc.kill_group(Signal::SIGTTOU, false);
+	/// This function must be called before performing the operation.
+	fn check_sigttou(&self, proc: &mut Process, tty: &TTY, always: bool) -> Result<(), Errno> {
+		if tty.get_sid() != proc.sid || proc.pgid == tty.get_pgrp() {
+			return Ok(());
+		}
+		if !always && tty.get_termios().c_lflag & termios::TOSTOP == 0 {
+			return Ok(());
+		}
+		// If the signal is blocked or ignored, the operation is allowed
+		if proc.is_signal_blocked(&Signal::SIGTTOU)
+			|| proc.get_signal_handler(&Signal::SIGTTOU) == SignalHandler::Ignore
+		{
+			return Ok(());
+		}
+		if proc.is_in_orphan_process_group() {
+			return Err(errno!(EIO));
 		}
 
-		Ok(())
+		proc.kill_group(Signal::SIGTTOU, false);
+		Err(errno!(ERESTARTSYS))
 	}
 }
 
@@ -115,7 +130,7 @@ impl DeviceHandle for TTYDeviceHandle {
 			ioctl::TCGETS => {
 				let mut mem_space_guard = mem_space.lock();
 				let termios_ptr: SyscallPtr<Termios> = (argp as usize).into();
-				let termios_ref = termios_ptr
+				let mut termios_ref = termios_ptr
 					.get_mut(&mut mem_space_guard)?
 					.ok_or_else(|| errno!(EFAULT))?;
 				*termios_ref = tty.get_termios().clone();
@@ -125,7 +140,7 @@ impl DeviceHandle for TTYDeviceHandle {
 
 			// TODO Implement correct behaviours for each
 			ioctl::TCSETS | ioctl::TCSETSW | ioctl::TCSETSF => {
-				self.check_sigttou(&mut proc, &tty)?;
+				self.check_sigttou(&mut proc, &tty, true)?;
 
 				let mem_space_guard = mem_space.lock();
 				let termios_ptr: SyscallPtr<Termios> = (argp as usize).into();
@@ -137,26 +152,94 @@ impl DeviceHandle for TTYDeviceHandle {
 				Ok(0)
 			}
 
+			ioctl::TIOCSCTTY => {
+				if tty.get_sid() == proc.sid {
+					return Ok(0);
+				}
+				// Only a session leader without controlling terminal can acquire one
+				if !proc.is_session_leader()
+					|| (!proc.get_tty().is_same(&tty_mutex) && proc.has_ctty())
+				{
+					return Err(errno!(EPERM));
+				}
+				// Stealing the terminal from another session requires privileges
+				if tty.get_sid() != 0
+					&& (argp as usize != 1 || !proc.access_profile.has_cap(CAP_SYS_ADMIN))
+				{
+					return Err(errno!(EPERM));
+				}
+
+				tty.set_sid(proc.sid);
+				tty.set_pgrp(proc.pgid);
+				proc.set_tty(tty_mutex.clone());
+
+				Ok(0)
+			}
+
+			ioctl::TIOCNOTTY => {
+				if tty.get_sid() != proc.sid {
+					return Err(errno!(ENOTTY));
+				}
+				if proc.is_session_leader() {
+					// Dropping to avoid deadlock since the foreground group may include the
+					// process
+					drop(proc);
+					tty.send_signal(Signal::SIGHUP);
+					tty.send_signal(Signal::SIGCONT);
+					tty.set_sid(0);
+					tty.set_pgrp(0);
+				}
+
+				Ok(0)
+			}
+
 			ioctl::TIOCGPGRP => {
+				if tty.get_sid() != proc.sid {
+					return Err(errno!(ENOTTY));
+				}
+
 				let mut mem_space_guard = mem_space.lock();
 				let pgid_ptr: SyscallPtr<Pid> = (argp as usize).into();
-				let pgid_ref = pgid_ptr
+				let mut pgid_ref = pgid_ptr
 					.get_mut(&mut mem_space_guard)?
 					.ok_or_else(|| errno!(EFAULT))?;
-				*pgid_ref = tty.get_pgrp();
+				*pgid_ref = proc.to_local_pid(tty.get_pgrp());
 
 				Ok(0)
 			}
 
 			ioctl::TIOCSPGRP => {
-				self.check_sigttou(&mut proc, &tty)?;
+				if tty.get_sid() != proc.sid {
+					return Err(errno!(ENOTTY));
+				}
+				self.check_sigttou(&mut proc, &tty, true)?;
 
 				let mem_space_guard = mem_space.lock();
 				let pgid_ptr: SyscallPtr<Pid> = (argp as usize).into();
 				let pgid = pgid_ptr
 					.get(&mem_space_guard)?
 					.ok_or_else(|| errno!(EFAULT))?;
-				tty.set_pgrp(*pgid);
+				// The group must exist in the session of the terminal
+				let pgid = proc.to_global_pid(*pgid).ok_or_else(|| errno!(EPERM))?;
+				if pgroup::get_session(pgid) != Some(proc.sid) {
+					return Err(errno!(EPERM));
+				}
+				tty.set_pgrp(pgid);
+
+				Ok(0)
+			}
+
+			ioctl::TIOCGSID => {
+				if tty.get_sid() != proc.sid {
+					return Err(errno!(ENOTTY));
+				}
+
+				let mut mem_space_guard = mem_space.lock();
+				let sid_ptr: SyscallPtr<Pid> = (argp as usize).into();
+				let mut sid_ref = sid_ptr
+					.get_mut(&mut mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				*sid_ref = proc.to_local_pid(tty.get_sid());
 
 				Ok(0)
 			}
@@ -164,7 +247,7 @@ impl DeviceHandle for TTYDeviceHandle {
 			ioctl::TIOCGWINSZ => {
 				let mut mem_space_guard = mem_space.lock();
 				let winsize: SyscallPtr<WinSize> = (argp as usize).into();
-				let winsize_ref = winsize
+				let mut winsize_ref = winsize
 					.get_mut(&mut mem_space_guard)?
 					.ok_or_else(|| errno!(EFAULT))?;
 				*winsize_ref = tty.get_winsize().clone();
@@ -196,6 +279,21 @@ impl DeviceHandle for TTYDeviceHandle {
 
 		tty.add_waiting_process(proc, mask)
 	}
+
+	fn set_async(
+		&mut self,
+		fd: c_int,
+		owner: &Arc<IntMutex<FileOwner>>,
+		on: bool,
+	) -> Result<(), Errno> {
+		let tty_mutex = self
+			.tty
+			.clone()
+			.unwrap_or_else(|| Process::current_assert().lock().get_tty());
+		let mut tty = tty_mutex.lock();
+
+		Ok(tty.set_async(fd, owner, on)?)
+	}
 }
 
 impl IO for TTYDeviceHandle {
@@ -224,7 +322,7 @@ impl IO for TTYDeviceHandle {
 		let mut proc = proc_mutex.lock();
 		let mut tty = tty_mutex.lock();
 
-		self.check_sigttou(&mut proc, &tty)?;
+		self.check_sigttou(&mut proc, &tty, false)?;
 
 		tty.write(buff);
 		Ok(buff.len() as _)
@@ -73,6 +73,16 @@ impl Errno {
 		self.errno
 	}
 
+	/// Tells whether the errno requests the interrupted system call to be restarted.
+	///
+	/// Such errnos are internal to the kernel and are never returned to userspace.
+	pub fn is_restart(&self) -> bool {
+		matches!(
+			self.errno,
+			ERESTARTSYS | ERESTARTNOINTR | ERESTARTNOHAND | ERESTART_RESTARTBLOCK
+		)
+	}
+
 	/// Returns the error message for the given errno.
 	pub fn strerror(&self) -> &'static str {
 		match self.errno {
@@ -208,6 +218,10 @@ impl Errno {
 			ERFKILL => "Operation not possible due to RF-kill",
 			EHWPOISON => "Memory page has hardware error",
 
+			ERESTARTSYS | ERESTARTNOINTR | ERESTARTNOHAND | ERESTART_RESTARTBLOCK => {
+				"Interrupted system call should be restarted"
+			}
+
 			_ => "Unknown error",
 		}
 	}
@@ -535,6 +549,16 @@ pub const ERFKILL: i32 = 132;
 /// Memory page has hardware error.
 pub const EHWPOISON: i32 = 133;
 
+/// Interrupted system call, restarted if the signal handler has `SA_RESTART` (internal).
+pub const ERESTARTSYS: i32 = 512;
+/// Interrupted system call, always restarted (internal).
+pub const ERESTARTNOINTR: i32 = 513;
+/// Interrupted system call, restarted only if no signal handler is executed (internal).
+pub const ERESTARTNOHAND: i32 = 514;
+/// Interrupted system call, restarted with `restart_syscall` if no signal handler is executed
+/// (internal).
+pub const ERESTART_RESTARTBLOCK: i32 = 516;
+
 /// An alias to [`core::result::Result`] with [`Errno`] as error type.
 pub type EResult<T> = Result<T, Errno>;
 
@@ -1,12 +1,11 @@
 //! This interface allows to register callbacks for each interrupts.
 
+use crate::cpu;
 use crate::crypto::rand;
-use crate::crypto::rand::EntropyPool;
 use crate::errno::AllocResult;
 use crate::idt;
-use crate::idt::pic;
 use crate::process::regs::Regs;
-use crate::process::tss::TSS;
+use crate::process::tss;
 use crate::util;
 use crate::util::boxed::Box;
 use crate::util::container::vec::Vec;
@@ -164,12 +163,6 @@ pub unsafe extern "C" fn unlock_callbacks(id: usize) {
 	CALLBACKS[id].unlock();
 }
 
-/// Feeds the entropy pool using the given data.
-fn feed_entropy<T>(pool: &mut EntropyPool, val: &T) {
-	let buff = util::as_slice(val);
-	pool.write(buff);
-}
-
 /// This function is called whenever an interruption is triggered.
 ///
 /// Arguments:
@@ -181,14 +174,16 @@ fn feed_entropy<T>(pool: &mut EntropyPool, val: &T) {
 /// - `ring` tells the ring at which the code was running
 #[no_mangle]
 extern "C" fn event_handler(id: u32, code: u32, ring: u32, regs: &Regs) {
-	// Feed entropy pool
+	// The interrupt wakes the core up if it was idle
+	cpu::idle::exit();
+
+	// Feed entropy pool with the timing of the interrupt
 	{
 		let mut pool = rand::ENTROPY_POOL.lock();
 		if let Some(pool) = &mut *pool {
-			feed_entropy(pool, &id);
-			feed_entropy(pool, &code);
-			feed_entropy(pool, &ring);
-			feed_entropy(pool, regs);
+			let tsc = cpu::rdtsc();
+			let sample = [tsc as u32, (tsc >> 32) as u32, id, code, ring, regs.eip];
+			pool.add_interrupt(util::as_slice(&sample));
 		}
 	}
 
@@ -201,12 +196,12 @@ extern "C" fn event_handler(id: u32, code: u32, ring: u32, regs: &Regs) {
 			CallbackResult::Idle => {
 				// Unlock to avoid deadlocks
 				if id >= ERROR_MESSAGES.len() as u32 {
-					pic::end_of_interrupt((id - ERROR_MESSAGES.len() as u32) as _);
+					idt::end_of_interrupt(id);
 				}
 				drop(callbacks);
 
 				unsafe {
-					crate::loop_reset(TSS.0.esp0 as _);
+					crate::loop_reset(tss::get_current().esp0 as _);
 				}
 			}
 
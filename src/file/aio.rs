@@ -0,0 +1,204 @@
+//! Linux asynchronous IO (AIO) allows to submit IO operations on files, then to reap their
+//! completions later.
+//!
+//! Operations are submitted to an *AIO context*, which stores their completion events until they
+//! are reaped with `io_getevents`.
+//!
+//! Operations are executed synchronously on submission, so they are always complete when
+//! `io_submit` returns.
+
+use crate::errno;
+use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::file::open_file::OpenFile;
+use crate::process::mem_space;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::MapConstraint;
+use crate::process::mem_space::MapResidence;
+use crate::process::mem_space::MemSpace;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+use crate::util::lock::IntMutex;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use core::ffi::c_ulong;
+use core::ffi::c_void;
+use core::num::NonZeroUsize;
+
+/// Operation: Read at an offset, like `pread`.
+pub const IOCB_CMD_PREAD: u16 = 0;
+/// Operation: Write at an offset, like `pwrite`.
+pub const IOCB_CMD_PWRITE: u16 = 1;
+/// Operation: Synchronize a file, like `fsync`.
+pub const IOCB_CMD_FSYNC: u16 = 2;
+/// Operation: Synchronize the data of a file, like `fdatasync`.
+pub const IOCB_CMD_FDSYNC: u16 = 3;
+/// Operation: Vectored read at an offset, like `preadv`.
+pub const IOCB_CMD_PREADV: u16 = 7;
+/// Operation: Vectored write at an offset, like `pwritev`.
+pub const IOCB_CMD_PWRITEV: u16 = 8;
+
+/// The maximum number of events of a context.
+pub const MAX_EVENTS: u32 = 65536;
+
+/// The ID of an AIO context, which is the address of its ring in userspace.
+pub type AioContextId = c_ulong;
+
+/// An IO control block, describing an operation to submit.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct Iocb {
+	/// Data passed back unchanged in the completion event.
+	pub aio_data: u64,
+	/// Reserved.
+	pub aio_key: u32,
+	/// Flags specific to the operation, like the flags of `preadv2`.
+	pub aio_rw_flags: u32,
+	/// The operation to perform.
+	pub aio_lio_opcode: u16,
+	/// The priority of the request.
+	pub aio_reqprio: i16,
+	/// The file descriptor to perform the operation on.
+	pub aio_fildes: u32,
+	/// The address of the buffer, or IO vector.
+	pub aio_buf: u64,
+	/// The size of the buffer, or number of entries in the IO vector.
+	pub aio_nbytes: u64,
+	/// The offset in the file.
+	pub aio_offset: i64,
+	/// Reserved.
+	pub aio_reserved2: u64,
+	/// Flags.
+	pub aio_flags: u32,
+	/// The eventfd to notify on completion. Unsupported.
+	pub aio_resfd: u32,
+}
+
+/// A completion event.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct IoEvent {
+	/// The data of the associated control block.
+	pub data: u64,
+	/// The address of the associated control block in userspace.
+	pub obj: u64,
+	/// The result of the operation. On error, this is the negated errno.
+	pub res: i64,
+	/// Secondary result.
+	pub res2: i64,
+}
+
+/// An AIO context.
+pub struct AioContext {
+	/// The maximum number of events the context can hold.
+	max_events: u32,
+	/// The completion events waiting to be reaped.
+	events: Vec<IoEvent>,
+}
+
+impl AioContext {
+	/// Tells whether the context cannot hold more completion events.
+	pub fn is_full(&self) -> bool {
+		self.events.len() >= self.max_events as usize
+	}
+
+	/// Pushes the completion event `event`.
+	pub fn push_event(&mut self, event: IoEvent) -> AllocResult<()> {
+		self.events.push(event)
+	}
+
+	/// Returns the number of completion events waiting to be reaped.
+	pub fn get_events_count(&self) -> usize {
+		self.events.len()
+	}
+
+	/// Removes the first completion events and writes them to `buf`.
+	///
+	/// The length of `buf` must not exceed the number of events waiting to be reaped.
+	pub fn pop_events(&mut self, buf: &mut [IoEvent]) {
+		let count = buf.len();
+		buf.copy_from_slice(&self.events[..count]);
+		self.events.rotate_left(count);
+		self.events.truncate(self.events.len() - count);
+	}
+}
+
+/// The table of AIO contexts of a memory space.
+pub type AioContextTable = HashMap<AioContextId, Arc<Mutex<AioContext>>>;
+
+/// Creates a new context able to hold `max_events` events.
+///
+/// The ring of the context is mapped in the memory space `mem_space`. It is left empty, which
+/// tells userspace to reap events through the system call.
+///
+/// The function returns the ID of the context along with the context.
+pub fn create(
+	mem_space: &mut MemSpace,
+	max_events: u32,
+) -> EResult<(AioContextId, Arc<Mutex<AioContext>>)> {
+	let ring = mem_space.map(
+		MapConstraint::None,
+		NonZeroUsize::new(1).unwrap(),
+		mem_space::MAPPING_FLAG_USER,
+		MapResidence::Normal,
+	)?;
+	let ctx = Arc::new(Mutex::new(AioContext {
+		max_events,
+		events: Vec::new(),
+	}));
+	match ctx {
+		Ok(ctx) => Ok((ring as _, ctx)),
+		Err(e) => {
+			let _ = destroy(mem_space, ring as _);
+			Err(e.into())
+		}
+	}
+}
+
+/// Unmaps the ring of the context with ID `id` from the memory space `mem_space`.
+pub fn destroy(mem_space: &mut MemSpace, id: AioContextId) -> AllocResult<()> {
+	let pages = NonZeroUsize::new(1).unwrap();
+	mem_space.unmap(id as *const c_void, pages, false)
+}
+
+/// Performs a read or a write at an offset.
+///
+/// Arguments:
+/// - `mem_space` is the memory space of the current process.
+/// - `open_file_mutex` is the open file.
+/// - `buf` is the buffer in userspace.
+/// - `len` is the size of the buffer.
+/// - `offset` is the offset in the file.
+/// - `write` tells whether the operation is a write.
+///
+/// The function returns the number of bytes read or written.
+pub fn read_write(
+	mem_space: &IntMutex<MemSpace>,
+	open_file_mutex: &Mutex<OpenFile>,
+	buf: SyscallSlice<u8>,
+	len: usize,
+	offset: u64,
+	write: bool,
+) -> EResult<i64> {
+	let mut open_file = open_file_mutex.lock();
+	let mut mem_space_guard = mem_space.lock();
+
+	// Change the offset temporarily
+	let prev_off = open_file.get_offset();
+	open_file.set_offset(offset);
+	let res = if write {
+		buf.get(&mem_space_guard, len)
+			.and_then(|slice| slice.ok_or_else(|| errno!(EFAULT)))
+			.and_then(|slice| open_file.write(0, &slice))
+	} else {
+		buf.get_mut(&mut mem_space_guard, len)
+			.and_then(|slice| slice.ok_or_else(|| errno!(EFAULT)))
+			.and_then(|mut slice| open_file.read(0, &mut slice))
+			.map(|(len, _)| len)
+	};
+	// Restore previous offset
+	open_file.set_offset(prev_off);
+
+	Ok(res? as _)
+}
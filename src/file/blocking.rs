@@ -1,12 +1,20 @@
 //! When a resource is blocking, a process trying to use it must be put in `Sleeping` state until
 //! the resource is available.
 
+use crate::errno::AllocResult;
 use crate::errno::Errno;
+use crate::file::open_file::FileOwner;
 use crate::process;
 use crate::process::pid::Pid;
 use crate::process::Process;
 use crate::util::container::hashmap::HashMap;
+use crate::util::container::vec::Vec;
 use crate::util::io;
+use crate::util::lock::IntMutex;
+use crate::util::ptr::arc::Arc;
+use crate::util::ptr::arc::Weak;
+use core::ffi::c_int;
+use core::ptr;
 
 /// Handler allowing to make a process sleep when waiting on a resource, then resume its execution
 /// when the resource is available.
@@ -14,6 +22,9 @@ use crate::util::io;
 pub struct BlockHandler {
 	/// The list of processes waiting on the resource, along with the mask of events to wait for.
 	waiting_procs: HashMap<Pid, u32>,
+	/// The open files registered for signal-driven IO, along with the file descriptor reported
+	/// to their owner.
+	async_files: Vec<(c_int, Weak<IntMutex<FileOwner>>)>,
 }
 
 impl BlockHandler {
@@ -21,6 +32,7 @@ impl BlockHandler {
 	pub fn new() -> Self {
 		Self {
 			waiting_procs: HashMap::new(),
+			async_files: Vec::new(),
 		}
 	}
 
@@ -37,8 +49,42 @@ impl BlockHandler {
 		Ok(())
 	}
 
+	/// Registers or unregisters the open file with the given owner for signal-driven IO.
+	///
+	/// Arguments:
+	/// - `fd` is the file descriptor reported to the owner.
+	/// - `owner` is the owner of the open file.
+	/// - `on` tells whether the open file is registered.
+	pub fn set_async(
+		&mut self,
+		fd: c_int,
+		owner: &Arc<IntMutex<FileOwner>>,
+		on: bool,
+	) -> AllocResult<()> {
+		// Remove the previous registration along with the ones of closed files
+		self.async_files.retain(|(_, o)| {
+			o.upgrade()
+				.map(|o| !ptr::eq(&*o, &**owner))
+				.unwrap_or(false)
+		});
+		if on {
+			self.async_files.push((fd, Arc::downgrade(owner)))?;
+		}
+		Ok(())
+	}
+
 	/// Wakes processes for the events in the given mask.
+	///
+	/// The owners of the open files registered for signal-driven IO are notified.
 	pub fn wake_processes(&mut self, mask: u32) {
+		self.async_files.retain(|(fd, owner)| {
+			let Some(owner) = owner.upgrade() else {
+				return false;
+			};
+			owner.lock().send_signal(*fd, mask);
+			true
+		});
+
 		self.waiting_procs.retain(|pid, m| {
 			let Some(proc_mutex) = Process::get_by_pid(*pid) else {
 				return false;
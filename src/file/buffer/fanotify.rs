@@ -0,0 +1,679 @@
+//! fanotify allows to monitor accesses to files, for instance to scan them for malware.
+//!
+//! Unlike [`inotify`](super::inotify), each event read from an instance comes with a new file
+//! descriptor to the accessed file, opened in the listening process.
+//!
+//! Files are monitored through *marks*, placed either on a file or on a whole mountpoint.
+//!
+//! Instances of class [`FAN_CLASS_CONTENT`] or higher can also receive *permission events*. In
+//! this case, the process accessing the file is blocked until the listener allows or denies the
+//! access by writing a response (`struct fanotify_response`) to the instance. If the instance is
+//! closed before responding, the access is allowed.
+//!
+//! Accesses made through file descriptors given by an instance do not generate events.
+//!
+//! TODO: Close events are not generated yet, since open file descriptions may be dropped while
+//! the current process is locked.
+
+use super::Buffer;
+use crate::errno::EResult;
+use crate::file::blocking::BlockHandler;
+use crate::file::buffer;
+use crate::file::fd::FileDescriptorTable;
+use crate::file::fd::FD_CLOEXEC;
+use crate::file::open_file;
+use crate::file::open_file::OpenFile;
+use crate::file::perm::AccessProfile;
+use crate::file::Errno;
+use crate::file::File;
+use crate::file::FileContent;
+use crate::file::FileLocation;
+use crate::file::FileType;
+use crate::file::FILE_CACHE;
+use crate::process::mem_space::MemSpace;
+use crate::process::pid::Pid;
+use crate::process::scheduler;
+use crate::process::Process;
+use crate::syscall::ioctl;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::io;
+use crate::util::io::IO;
+use crate::util::lock::IntMutex;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use core::ffi::c_int;
+use core::ffi::c_uint;
+use core::ffi::c_void;
+use core::sync::atomic;
+use core::sync::atomic::AtomicU32;
+
+/// Event: The file was accessed.
+pub const FAN_ACCESS: u64 = 0x00000001;
+/// Event: The file was modified.
+pub const FAN_MODIFY: u64 = 0x00000002;
+/// Event: A file open for writing was closed.
+pub const FAN_CLOSE_WRITE: u64 = 0x00000008;
+/// Event: A file not open for writing was closed.
+pub const FAN_CLOSE_NOWRITE: u64 = 0x00000010;
+/// Event: The file was opened.
+pub const FAN_OPEN: u64 = 0x00000020;
+/// Event: The events queue overflowed.
+pub const FAN_Q_OVERFLOW: u64 = 0x00004000;
+/// Permission event: The file is being opened.
+pub const FAN_OPEN_PERM: u64 = 0x00010000;
+/// Permission event: The file is being read.
+pub const FAN_ACCESS_PERM: u64 = 0x00020000;
+/// Event flag: The event concerns the entries of a directory.
+pub const FAN_EVENT_ON_CHILD: u64 = 0x08000000;
+/// Event flag: The subject of the event is a directory.
+pub const FAN_ONDIR: u64 = 0x40000000;
+
+/// Every events that only notify the listener.
+const NOTIFICATION_EVENTS: u64 =
+	FAN_ACCESS | FAN_MODIFY | FAN_CLOSE_WRITE | FAN_CLOSE_NOWRITE | FAN_OPEN;
+/// Every events that require a response from the listener.
+const PERMISSION_EVENTS: u64 = FAN_OPEN_PERM | FAN_ACCESS_PERM;
+/// Every valid bits in the mask of a mark.
+pub const FAN_ALL_MARK_BITS: u64 =
+	NOTIFICATION_EVENTS | PERMISSION_EVENTS | FAN_EVENT_ON_CHILD | FAN_ONDIR;
+
+/// Class: The instance only receives notification events.
+pub const FAN_CLASS_NOTIF: c_uint = 0x0;
+/// Class: The instance may receive permission events, to check the content of files.
+pub const FAN_CLASS_CONTENT: c_uint = 0x4;
+/// Class: The instance may receive permission events, before the content of files is available.
+pub const FAN_CLASS_PRE_CONTENT: c_uint = 0x8;
+
+/// Response: Allow the access.
+const FAN_ALLOW: u32 = 0x01;
+/// Response: Deny the access.
+const FAN_DENY: u32 = 0x02;
+/// Response flag: Audit the decision (ignored).
+const FAN_AUDIT: u32 = 0x10;
+
+/// The file descriptor reported for events that are not associated with a file.
+const FAN_NOFD: c_int = -1;
+/// The version of the format of events.
+const FANOTIFY_METADATA_VERSION: u8 = 3;
+/// The size of an event, as read from an instance (`struct fanotify_event_metadata`).
+const EVENT_METADATA_SIZE: usize = 24;
+/// The size of a response, as written to an instance (`struct fanotify_response`).
+const RESPONSE_SIZE: usize = 8;
+
+/// The maximum number of events in the queue of an instance, unless unlimited.
+const MAX_QUEUED_EVENTS: usize = 16384;
+/// The maximum number of marks per instance, unless unlimited.
+const MAX_MARKS: usize = 8192;
+
+/// The object a mark is placed on.
+#[derive(Clone, Eq, PartialEq)]
+pub enum MarkTarget {
+	/// A single file.
+	File(FileLocation),
+	/// Every files of the mountpoint with the given ID.
+	Mount(u32),
+}
+
+impl MarkTarget {
+	/// Tells whether the file at location `location` is covered by the target.
+	fn matches(&self, location: &FileLocation) -> bool {
+		match self {
+			Self::File(loc) => loc == location,
+			Self::Mount(id) => location.get_mountpoint_id() == Some(*id),
+		}
+	}
+}
+
+/// A mark, telling which events to report for a target.
+struct Mark {
+	/// The target of the mark.
+	target: MarkTarget,
+	/// The mask of events to report.
+	mask: u64,
+	/// The mask of events to ignore.
+	ignored_mask: u64,
+	/// If `false`, the ignored mask is cleared when the file is modified.
+	ignored_surv_modify: bool,
+}
+
+/// A queued event.
+struct Event {
+	/// The mask of events.
+	mask: u64,
+	/// The file the event happened on. If `None`, the event is not associated with a file.
+	file: Option<Arc<Mutex<File>>>,
+	/// The location of the file, used to merge events.
+	location: Option<FileLocation>,
+	/// The PID of the process that caused the event.
+	pid: Pid,
+	/// If this is a permission event, the ID of the request.
+	request: Option<u32>,
+}
+
+/// A fanotify instance.
+pub struct Fanotify {
+	/// The class of the instance.
+	class: c_uint,
+	/// The flags of the open file descriptions created for events.
+	event_f_flags: i32,
+	/// Tells whether the number of queued events is unlimited.
+	unlimited_queue: bool,
+	/// Tells whether the number of marks is unlimited.
+	unlimited_marks: bool,
+
+	/// The marks.
+	marks: Vec<Mark>,
+	/// The queue of events waiting to be read.
+	events: Vec<Event>,
+	/// Requests of read permission events, waiting for a response, by file descriptor given to
+	/// the listener.
+	pending: HashMap<c_int, u32>,
+	/// Responses to permission requests, by request ID. `true` means the access is allowed.
+	responses: HashMap<u32, bool>,
+	/// Tells whether the instance has been closed.
+	closed: bool,
+
+	/// The block handler for processes waiting for events.
+	block_handler: BlockHandler,
+	/// The block handler for processes waiting for responses.
+	response_handler: BlockHandler,
+}
+
+impl Fanotify {
+	/// Creates a new instance.
+	///
+	/// Arguments:
+	/// - `class` is the class of the instance.
+	/// - `event_f_flags` is the set of flags of the open file descriptions created for events.
+	/// - `unlimited_queue` tells whether the number of queued events is unlimited.
+	/// - `unlimited_marks` tells whether the number of marks is unlimited.
+	pub fn new(
+		class: c_uint,
+		event_f_flags: i32,
+		unlimited_queue: bool,
+		unlimited_marks: bool,
+	) -> Self {
+		Self {
+			class,
+			event_f_flags,
+			unlimited_queue,
+			unlimited_marks,
+
+			marks: Vec::new(),
+			events: Vec::new(),
+			pending: HashMap::new(),
+			responses: HashMap::new(),
+			closed: false,
+
+			block_handler: BlockHandler::new(),
+			response_handler: BlockHandler::new(),
+		}
+	}
+
+	/// Adds the events of `mask` to the mark on `target`, creating it if it doesn't exist.
+	///
+	/// Arguments:
+	/// - `ignored` tells whether the events are added to the ignored mask instead.
+	/// - `surv_modify` tells whether the ignored mask survives modifications of the file.
+	pub fn add_mark(
+		&mut self,
+		target: MarkTarget,
+		mask: u64,
+		ignored: bool,
+		surv_modify: bool,
+	) -> EResult<()> {
+		if mask & PERMISSION_EVENTS != 0 && self.class == FAN_CLASS_NOTIF {
+			return Err(errno!(EINVAL));
+		}
+		let mark = match self.marks.iter_mut().find(|m| m.target == target) {
+			Some(mark) => mark,
+			None => {
+				if !self.unlimited_marks && self.marks.len() >= MAX_MARKS {
+					return Err(errno!(ENOSPC));
+				}
+				self.marks.push(Mark {
+					target,
+					mask: 0,
+					ignored_mask: 0,
+					ignored_surv_modify: false,
+				})?;
+				self.marks.last_mut().unwrap()
+			}
+		};
+		if ignored {
+			mark.ignored_mask |= mask;
+			mark.ignored_surv_modify |= surv_modify;
+		} else {
+			mark.mask |= mask;
+		}
+		Ok(())
+	}
+
+	/// Removes the events of `mask` from the mark on `target`. If the mark has no event left, it
+	/// is removed.
+	///
+	/// `ignored` tells whether the events are removed from the ignored mask instead.
+	///
+	/// If the mark doesn't exist, the function returns an error.
+	pub fn remove_mark(&mut self, target: &MarkTarget, mask: u64, ignored: bool) -> EResult<()> {
+		let index = self
+			.marks
+			.iter()
+			.position(|m| m.target == *target)
+			.ok_or_else(|| errno!(ENOENT))?;
+		let mark = &mut self.marks[index];
+		if ignored {
+			mark.ignored_mask &= !mask;
+		} else {
+			mark.mask &= !mask;
+		}
+		if mark.mask == 0 && mark.ignored_mask == 0 {
+			self.marks.remove(index);
+		}
+		Ok(())
+	}
+
+	/// Removes every marks on mountpoints if `mount` is `true`, or on files otherwise.
+	pub fn flush_marks(&mut self, mount: bool) {
+		self.marks
+			.retain(|m| matches!(m.target, MarkTarget::Mount(_)) != mount);
+	}
+
+	/// Returns the events of `mask` on the file at location `location` that have to be reported.
+	///
+	/// If no event has to be reported, the function returns zero.
+	fn filter(&mut self, location: &FileLocation, mask: u64) -> u64 {
+		let mut interest = 0;
+		let mut ignored = 0;
+		for mark in self.marks.iter_mut() {
+			if !mark.target.matches(location) {
+				continue;
+			}
+			if mask & FAN_MODIFY != 0 && !mark.ignored_surv_modify {
+				mark.ignored_mask = 0;
+			}
+			interest |= mark.mask;
+			ignored |= mark.ignored_mask;
+		}
+		// Events on directories are reported only if requested
+		if mask & FAN_ONDIR != 0 && interest & FAN_ONDIR == 0 {
+			return 0;
+		}
+		let events = mask & interest & !ignored & (NOTIFICATION_EVENTS | PERMISSION_EVENTS);
+		if events == 0 {
+			return 0;
+		}
+		events | (mask & FAN_ONDIR)
+	}
+
+	/// Pushes the event `event` on the queue.
+	///
+	/// Notification events are merged with the last queued event if it concerns the same file and
+	/// process.
+	///
+	/// If the event could not be queued, the function returns `false`.
+	fn push_event(&mut self, event: Event) -> bool {
+		if event.request.is_none() {
+			if let Some(last) = self.events.last_mut() {
+				let same = last.request.is_none()
+					&& last.location.is_some()
+					&& last.location == event.location
+					&& last.pid == event.pid;
+				if same {
+					last.mask |= event.mask;
+					return true;
+				}
+			}
+		}
+		let res = if self.unlimited_queue || self.events.len() + 1 < MAX_QUEUED_EVENTS {
+			self.events.push(event)
+		} else {
+			if self.events.last().map(|e| e.mask) != Some(FAN_Q_OVERFLOW) {
+				let _ = self.events.push(Event {
+					mask: FAN_Q_OVERFLOW,
+					file: None,
+					location: None,
+					pid: 0,
+					request: None,
+				});
+				self.block_handler.wake_processes(io::POLLIN);
+			}
+			return false;
+		};
+		// On allocation failure, the event is lost
+		if res.is_err() {
+			return false;
+		}
+		self.block_handler.wake_processes(io::POLLIN);
+		true
+	}
+
+	/// Returns the response to the permission request with ID `id`.
+	///
+	/// If no response has been given yet, the function returns `None`.
+	fn take_response(&mut self, id: u32) -> Option<bool> {
+		if let Some(allow) = self.responses.remove(&id) {
+			return Some(allow);
+		}
+		// Once closed, no response can be given anymore
+		self.closed.then_some(true)
+	}
+
+	/// Closes the instance, allowing every pending permission requests.
+	fn close(&mut self) {
+		self.closed = true;
+		self.events.clear();
+		self.pending.clear();
+		self.response_handler.wake_processes(io::POLLIN);
+	}
+
+	/// Opens a file descriptor to the file of `event` in the file descriptors table `fds`.
+	///
+	/// If the event is a permission event, the request is recorded in `pending`.
+	///
+	/// The function returns the ID of the file descriptor.
+	fn open_event_fd(
+		event: &Event,
+		event_f_flags: i32,
+		fds: &mut FileDescriptorTable,
+		pending: &mut HashMap<c_int, u32>,
+	) -> EResult<c_int> {
+		let Some(file) = &event.file else {
+			return Ok(FAN_NOFD);
+		};
+		let mut open_file = OpenFile::new(file.clone(), event_f_flags)?;
+		open_file.set_notify(false);
+		let fd_flags = if event_f_flags & open_file::O_CLOEXEC != 0 {
+			FD_CLOEXEC
+		} else {
+			0
+		};
+		let fd = fds.create_fd(fd_flags, open_file)?.get_id() as c_int;
+		if let Some(id) = event.request {
+			if let Err(e) = pending.insert(fd, id) {
+				let _ = fds.close_fd(fd as _);
+				return Err(e.into());
+			}
+		}
+		Ok(fd)
+	}
+}
+
+impl Buffer for Fanotify {
+	fn get_capacity(&self) -> usize {
+		0
+	}
+
+	fn increment_open(&mut self, _read: bool, _write: bool) {}
+
+	fn decrement_open(&mut self, _read: bool, _write: bool) {}
+
+	fn add_waiting_process(&mut self, proc: &mut Process, mask: u32) -> Result<(), Errno> {
+		self.block_handler.add_waiting_process(proc, mask)
+	}
+
+	fn ioctl(
+		&mut self,
+		_mem_space: Arc<IntMutex<MemSpace>>,
+		_request: ioctl::Request,
+		_argp: *const c_void,
+	) -> Result<u32, Errno> {
+		Err(errno!(ENOTTY))
+	}
+}
+
+impl IO for Fanotify {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	/// Note: This implemention ignores the offset.
+	///
+	/// Only whole events are read. If the buffer is too small to hold an event, the function
+	/// returns an error.
+	fn read(&mut self, _: u64, buf: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if self.events.is_empty() {
+			return Ok((0, false));
+		}
+		if buf.len() < EVENT_METADATA_SIZE {
+			return Err(errno!(EINVAL));
+		}
+
+		let fds_mutex = {
+			let proc_mutex = Process::current_assert();
+			let proc = proc_mutex.lock();
+			proc.get_fds().unwrap().clone()
+		};
+		let mut fds = fds_mutex.lock();
+
+		let mut off = 0;
+		let mut count = 0;
+		while let Some(event) = self.events.get(count) {
+			if off + EVENT_METADATA_SIZE > buf.len() {
+				break;
+			}
+			let fd = match Self::open_event_fd(
+				event,
+				self.event_f_flags,
+				&mut fds,
+				&mut self.pending,
+			) {
+				Ok(fd) => fd,
+				// Report the error only if nothing has been read
+				Err(e) if count == 0 => return Err(e),
+				Err(_) => break,
+			};
+
+			let b = &mut buf[off..(off + EVENT_METADATA_SIZE)];
+			b[0..4].copy_from_slice(&(EVENT_METADATA_SIZE as u32).to_ne_bytes());
+			b[4] = FANOTIFY_METADATA_VERSION;
+			b[5] = 0;
+			b[6..8].copy_from_slice(&(EVENT_METADATA_SIZE as u16).to_ne_bytes());
+			b[8..16].copy_from_slice(&event.mask.to_ne_bytes());
+			b[16..20].copy_from_slice(&fd.to_ne_bytes());
+			b[20..24].copy_from_slice(&(event.pid as i32).to_ne_bytes());
+
+			off += EVENT_METADATA_SIZE;
+			count += 1;
+		}
+		self.events.rotate_left(count);
+		self.events.truncate(self.events.len() - count);
+		Ok((off as _, false))
+	}
+
+	/// Writes a response to a permission event.
+	///
+	/// If the file descriptor given in the response doesn't correspond to a permission event
+	/// waiting for a response, the function returns an error.
+	fn write(&mut self, _: u64, buf: &[u8]) -> Result<u64, Errno> {
+		if buf.len() < RESPONSE_SIZE {
+			return Err(errno!(EINVAL));
+		}
+		let fd = c_int::from_ne_bytes(buf[0..4].try_into().unwrap());
+		let response = u32::from_ne_bytes(buf[4..8].try_into().unwrap());
+		let allow = match response & !FAN_AUDIT {
+			FAN_ALLOW => true,
+			FAN_DENY => false,
+			_ => return Err(errno!(EINVAL)),
+		};
+
+		let id = *self.pending.get(&fd).ok_or_else(|| errno!(ENOENT))?;
+		self.responses.insert(id, allow)?;
+		self.pending.remove(&fd);
+		self.response_handler.wake_processes(io::POLLIN);
+		Ok(RESPONSE_SIZE as _)
+	}
+
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		if mask & io::POLLIN != 0 && !self.events.is_empty() {
+			Ok(io::POLLIN)
+		} else {
+			Ok(0)
+		}
+	}
+}
+
+/// The existing instances, by location of their file.
+static INSTANCES: Mutex<HashMap<FileLocation, Arc<Mutex<Fanotify>>>> = Mutex::new(HashMap::new());
+/// The ID of the next permission request.
+static NEXT_REQUEST: AtomicU32 = AtomicU32::new(0);
+
+/// Creates a file for the fanotify instance `fanotify`.
+///
+/// `access_profile` is the access profile of the owner of the instance.
+pub fn create(fanotify: Fanotify, access_profile: &AccessProfile) -> EResult<Arc<Mutex<File>>> {
+	let fanotify = Arc::new(Mutex::new(fanotify))?;
+	let loc = buffer::register(None, fanotify.clone())?;
+	if let Err(e) = INSTANCES.lock().insert(loc.clone(), fanotify) {
+		buffer::release(&loc);
+		return Err(e.into());
+	}
+
+	let file = File::new(
+		String::try_from(b"anon_inode:[fanotify]")?,
+		access_profile.get_euid(),
+		access_profile.get_egid(),
+		0o600,
+		loc,
+		FileContent::Fifo,
+	)?;
+	Ok(Arc::new_in(Mutex::new(file), &FILE_CACHE)?)
+}
+
+/// Returns the fanotify instance of the file at location `loc`.
+///
+/// If the file is not a fanotify instance, the function returns `None`.
+pub fn get(loc: &FileLocation) -> Option<Arc<Mutex<Fanotify>>> {
+	INSTANCES.lock().get(loc).cloned()
+}
+
+/// Frees the fanotify instance at location `loc` if it is not open anymore.
+///
+/// If the file is not a fanotify instance, the function does nothing.
+pub fn release_if_unused(loc: &FileLocation) {
+	if OpenFile::is_open(loc) {
+		return;
+	}
+	let Some(fanotify) = INSTANCES.lock().remove(loc) else {
+		return;
+	};
+	fanotify.lock().close();
+	buffer::release(loc);
+}
+
+/// Returns [`FAN_ONDIR`] if `file` is a directory, or zero otherwise.
+pub fn dir_flag(file: &File) -> u64 {
+	if file.get_type() == FileType::Directory {
+		FAN_ONDIR
+	} else {
+		0
+	}
+}
+
+/// Returns the PID of the current process.
+fn current_pid() -> Pid {
+	Process::current().map(|proc| proc.lock().pid).unwrap_or(0)
+}
+
+/// Notifies the event `mask` on the file `file`, located at `location`.
+///
+/// If the file is a directory, `mask` must include [`FAN_ONDIR`].
+///
+/// The current process must not be locked.
+pub fn notify(file: &Arc<Mutex<File>>, location: &FileLocation, mask: u64) {
+	if !matches!(location, FileLocation::Filesystem { .. }) {
+		return;
+	}
+	let instances = INSTANCES.lock();
+	if instances.is_empty() {
+		return;
+	}
+	let pid = current_pid();
+	for (_, fanotify) in instances.iter() {
+		let mut fanotify = fanotify.lock();
+		let mask = fanotify.filter(location, mask & !PERMISSION_EVENTS);
+		if mask == 0 {
+			continue;
+		}
+		fanotify.push_event(Event {
+			mask,
+			file: Some(file.clone()),
+			location: Some(location.clone()),
+			pid,
+			request: None,
+		});
+	}
+}
+
+/// Submits the permission event `mask` on the file `file`, located at `location`, to every
+/// interested instances, then waits for their responses.
+///
+/// If the file is a directory, `mask` must include [`FAN_ONDIR`].
+///
+/// No lock must be held by the caller, since the current process may sleep.
+///
+/// If any instance denies the access, the function returns `EPERM`.
+pub fn check_permission(
+	file: &Arc<Mutex<File>>,
+	location: &FileLocation,
+	mask: u64,
+) -> EResult<()> {
+	if !matches!(location, FileLocation::Filesystem { .. }) {
+		return Ok(());
+	}
+	let mut requests = Vec::new();
+	{
+		let instances = INSTANCES.lock();
+		if instances.is_empty() {
+			return Ok(());
+		}
+		let pid = current_pid();
+		for (_, fanotify_mutex) in instances.iter() {
+			let mut fanotify = fanotify_mutex.lock();
+			if fanotify.class == FAN_CLASS_NOTIF {
+				continue;
+			}
+			let mask = fanotify.filter(location, mask & (PERMISSION_EVENTS | FAN_ONDIR));
+			if mask == 0 {
+				continue;
+			}
+			let id = NEXT_REQUEST.fetch_add(1, atomic::Ordering::Relaxed);
+			let queued = fanotify.push_event(Event {
+				mask,
+				file: Some(file.clone()),
+				location: Some(location.clone()),
+				pid,
+				request: Some(id),
+			});
+			// If the event is lost, the access is allowed
+			if queued {
+				requests.push((fanotify_mutex.clone(), id))?;
+			}
+		}
+	}
+
+	let mut allowed = true;
+	for (fanotify_mutex, id) in requests {
+		loop {
+			{
+				let mut fanotify = fanotify_mutex.lock();
+				if let Some(allow) = fanotify.take_response(id) {
+					allowed &= allow;
+					break;
+				}
+				let proc_mutex = Process::current_assert();
+				let mut proc = proc_mutex.lock();
+				fanotify
+					.response_handler
+					.add_waiting_process(&mut proc, io::POLLIN)?;
+			}
+			scheduler::end_tick();
+		}
+	}
+	if !allowed {
+		return Err(errno!(EPERM));
+	}
+	Ok(())
+}
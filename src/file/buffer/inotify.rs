@@ -0,0 +1,469 @@
+//! inotify allows to monitor events on files, such as their creation, deletion or modification.
+//!
+//! An inotify instance is a buffer from which events can be read. Files are watched through
+//! *watch descriptors*, each watching one file for a given set of events.
+//!
+//! Events are generated by the VFS. When watching a directory, events also happen for the files
+//! it contains, in which case the name of the file is given along with the event.
+
+use super::Buffer;
+use crate::errno::EResult;
+use crate::file::blocking::BlockHandler;
+use crate::file::buffer;
+use crate::file::open_file::OpenFile;
+use crate::file::perm::AccessProfile;
+use crate::file::vfs;
+use crate::file::Errno;
+use crate::file::File;
+use crate::file::FileContent;
+use crate::file::FileLocation;
+use crate::file::FileType;
+use crate::file::FILE_CACHE;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::MemSpace;
+use crate::syscall::ioctl;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::io;
+use crate::util::io::IO;
+use crate::util::lock::IntMutex;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use core::ffi::c_int;
+use core::ffi::c_void;
+use core::sync::atomic;
+use core::sync::atomic::AtomicU32;
+
+/// Event: The file was accessed.
+pub const IN_ACCESS: u32 = 0x00000001;
+/// Event: The file was modified.
+pub const IN_MODIFY: u32 = 0x00000002;
+/// Event: The metadata of the file changed.
+pub const IN_ATTRIB: u32 = 0x00000004;
+/// Event: A file open for writing was closed.
+pub const IN_CLOSE_WRITE: u32 = 0x00000008;
+/// Event: A file not open for writing was closed.
+pub const IN_CLOSE_NOWRITE: u32 = 0x00000010;
+/// Event: The file was opened.
+pub const IN_OPEN: u32 = 0x00000020;
+/// Event: A file was moved out of the watched directory.
+pub const IN_MOVED_FROM: u32 = 0x00000040;
+/// Event: A file was moved into the watched directory.
+pub const IN_MOVED_TO: u32 = 0x00000080;
+/// Event: A file was created in the watched directory.
+pub const IN_CREATE: u32 = 0x00000100;
+/// Event: A file was deleted from the watched directory.
+pub const IN_DELETE: u32 = 0x00000200;
+/// Event: The watched file was deleted.
+pub const IN_DELETE_SELF: u32 = 0x00000400;
+/// Event: The watched file was moved.
+pub const IN_MOVE_SELF: u32 = 0x00000800;
+/// Every events.
+pub const IN_ALL_EVENTS: u32 = 0x00000fff;
+
+/// Event: The filesystem of the watched file was unmounted.
+pub const IN_UNMOUNT: u32 = 0x00002000;
+/// Event: The events queue overflowed.
+pub const IN_Q_OVERFLOW: u32 = 0x00004000;
+/// Event: The watch was removed.
+pub const IN_IGNORED: u32 = 0x00008000;
+/// Event flag: The subject of the event is a directory.
+pub const IN_ISDIR: u32 = 0x40000000;
+
+/// Watch flag: Only watch the file if it is a directory.
+pub const IN_ONLYDIR: u32 = 0x01000000;
+/// Watch flag: Do not follow symbolic links.
+pub const IN_DONT_FOLLOW: u32 = 0x02000000;
+/// Watch flag: Do not generate events for files after they have been unlinked.
+pub const IN_EXCL_UNLINK: u32 = 0x04000000;
+/// Watch flag: Fail if the file is already watched.
+pub const IN_MASK_CREATE: u32 = 0x10000000;
+/// Watch flag: Add the events to the mask of the existing watch instead of replacing it.
+pub const IN_MASK_ADD: u32 = 0x20000000;
+/// Watch flag: Remove the watch after the first event.
+pub const IN_ONESHOT: u32 = 0x80000000;
+
+/// The maximum number of events in the queue of an instance.
+const MAX_QUEUED_EVENTS: usize = 16384;
+/// The maximum number of watches per instance.
+const MAX_WATCHES: usize = 8192;
+
+/// The size of the header of an event, as read from an instance (`struct inotify_event`). The
+/// header is followed by the name of the file, padded with null bytes.
+const EVENT_HEADER_SIZE: usize = 16;
+
+/// A queued event.
+#[derive(Eq, PartialEq)]
+struct Event {
+	/// The watch descriptor.
+	wd: c_int,
+	/// The mask of events.
+	mask: u32,
+	/// The cookie associating related events.
+	cookie: u32,
+	/// The name of the file in the watched directory, if any.
+	name: Option<String>,
+}
+
+impl Event {
+	/// Returns the length of the name once padded.
+	fn name_len(&self) -> usize {
+		self.name
+			.as_ref()
+			.map(|name| (name.len() + 1).next_multiple_of(EVENT_HEADER_SIZE))
+			.unwrap_or(0)
+	}
+
+	/// Returns the total size of the event in bytes.
+	fn size(&self) -> usize {
+		EVENT_HEADER_SIZE + self.name_len()
+	}
+
+	/// Writes the event to `buf`, which must be at least [`Self::size`] bytes long.
+	fn write(&self, buf: &mut [u8]) {
+		let hdr_size = EVENT_HEADER_SIZE;
+		let name_len = self.name_len();
+		buf[0..4].copy_from_slice(&self.wd.to_ne_bytes());
+		buf[4..8].copy_from_slice(&self.mask.to_ne_bytes());
+		buf[8..12].copy_from_slice(&self.cookie.to_ne_bytes());
+		buf[12..16].copy_from_slice(&(name_len as u32).to_ne_bytes());
+
+		let name_buf = &mut buf[hdr_size..(hdr_size + name_len)];
+		name_buf.fill(0);
+		if let Some(name) = &self.name {
+			name_buf[..name.len()].copy_from_slice(name.as_bytes());
+		}
+	}
+}
+
+/// A watch on a file.
+struct Watch {
+	/// The location of the watched file.
+	location: FileLocation,
+	/// The mask of events to watch, along with watch flags.
+	mask: u32,
+}
+
+/// An inotify instance.
+#[derive(Default)]
+pub struct Inotify {
+	/// The watches, by watch descriptor.
+	watches: HashMap<c_int, Watch>,
+	/// The next watch descriptor to be allocated.
+	next_wd: c_int,
+	/// The queue of events waiting to be read.
+	events: Vec<Event>,
+
+	/// The instance's block handler.
+	block_handler: BlockHandler,
+}
+
+impl Inotify {
+	/// Adds a watch on the file at location `location` with the mask of events `mask`.
+	///
+	/// If the file is already watched, the watch is updated.
+	///
+	/// The function returns the watch descriptor.
+	pub fn add_watch(&mut self, location: FileLocation, mask: u32) -> EResult<c_int> {
+		let existing = self
+			.watches
+			.iter()
+			.find(|(_, watch)| watch.location == location)
+			.map(|(wd, _)| *wd);
+		if let Some(wd) = existing {
+			if mask & IN_MASK_CREATE != 0 {
+				return Err(errno!(EEXIST));
+			}
+			let watch = self.watches.get_mut(&wd).unwrap();
+			if mask & IN_MASK_ADD != 0 {
+				watch.mask |= mask;
+			} else {
+				watch.mask = mask;
+			}
+			return Ok(wd);
+		}
+
+		if self.watches.len() >= MAX_WATCHES {
+			return Err(errno!(ENOSPC));
+		}
+		self.next_wd += 1;
+		let wd = self.next_wd;
+		self.watches.insert(
+			wd,
+			Watch {
+				location,
+				mask,
+			},
+		)?;
+		Ok(wd)
+	}
+
+	/// Removes the watch with descriptor `wd`.
+	///
+	/// If the watch doesn't exist, the function returns an error.
+	pub fn remove_watch(&mut self, wd: c_int) -> EResult<()> {
+		self.watches.remove(&wd).ok_or_else(|| errno!(EINVAL))?;
+		self.push_event(Event {
+			wd,
+			mask: IN_IGNORED,
+			cookie: 0,
+			name: None,
+		});
+		Ok(())
+	}
+
+	/// Pushes the event `event` on the queue.
+	///
+	/// If the event is identical to the last queued event, it is merged with it.
+	fn push_event(&mut self, event: Event) {
+		if self.events.last() == Some(&event) {
+			return;
+		}
+		let overflow = Event {
+			wd: -1,
+			mask: IN_Q_OVERFLOW,
+			cookie: 0,
+			name: None,
+		};
+		let res = if self.events.len() + 1 < MAX_QUEUED_EVENTS {
+			self.events.push(event)
+		} else if self.events.last() != Some(&overflow) {
+			self.events.push(overflow)
+		} else {
+			Ok(())
+		};
+		// On allocation failure, the event is lost
+		if res.is_ok() {
+			self.block_handler.wake_processes(io::POLLIN);
+		}
+	}
+
+	/// Queues an event for each watch on the file at location `location` that is interested in
+	/// events of `mask`.
+	///
+	/// Arguments:
+	/// - `name` is the name of the file in the watched directory, if the event happened on a
+	/// file it contains.
+	/// - `cookie` associates related events.
+	fn notify(&mut self, location: &FileLocation, mask: u32, name: Option<&[u8]>, cookie: u32) {
+		let mut oneshots = Vec::new();
+		let mut events = Vec::new();
+		for (wd, watch) in self.watches.iter() {
+			if watch.location != *location || watch.mask & mask & IN_ALL_EVENTS == 0 {
+				continue;
+			}
+			let Ok(name) = name.map(String::try_from).transpose() else {
+				continue;
+			};
+			let _ = events.push(Event {
+				wd: *wd,
+				mask,
+				cookie,
+				name,
+			});
+			if watch.mask & IN_ONESHOT != 0 {
+				let _ = oneshots.push(*wd);
+			}
+		}
+		for event in events {
+			self.push_event(event);
+		}
+		for wd in oneshots {
+			let _ = self.remove_watch(wd);
+		}
+	}
+
+	/// Removes every watches on the file at location `location`.
+	fn forget(&mut self, location: &FileLocation) {
+		let mut wds = Vec::new();
+		for (wd, watch) in self.watches.iter() {
+			if watch.location == *location {
+				let _ = wds.push(*wd);
+			}
+		}
+		for wd in wds {
+			let _ = self.remove_watch(wd);
+		}
+	}
+}
+
+impl Buffer for Inotify {
+	fn get_capacity(&self) -> usize {
+		0
+	}
+
+	fn increment_open(&mut self, _read: bool, _write: bool) {}
+
+	fn decrement_open(&mut self, _read: bool, _write: bool) {}
+
+	fn add_waiting_process(
+		&mut self,
+		proc: &mut crate::process::Process,
+		mask: u32,
+	) -> Result<(), Errno> {
+		self.block_handler.add_waiting_process(proc, mask)
+	}
+
+	fn ioctl(
+		&mut self,
+		mem_space: Arc<IntMutex<MemSpace>>,
+		request: ioctl::Request,
+		argp: *const c_void,
+	) -> Result<u32, Errno> {
+		match request.get_old_format() {
+			ioctl::FIONREAD => {
+				let mut mem_space_guard = mem_space.lock();
+				let count_ptr: SyscallPtr<c_int> = (argp as usize).into();
+				let mut count_ref = count_ptr
+					.get_mut(&mut mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				*count_ref = self.events.iter().map(Event::size).sum::<usize>() as _;
+			}
+
+			_ => return Err(errno!(ENOTTY)),
+		}
+
+		Ok(0)
+	}
+}
+
+impl IO for Inotify {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	/// Note: This implemention ignores the offset.
+	///
+	/// Only whole events are read. If the buffer is too small to hold the first event, the
+	/// function returns an error.
+	fn read(&mut self, _: u64, buf: &mut [u8]) -> Result<(u64, bool), Errno> {
+		let mut off = 0;
+		let mut count = 0;
+		for event in self.events.iter() {
+			let size = event.size();
+			if off + size > buf.len() {
+				break;
+			}
+			event.write(&mut buf[off..]);
+			off += size;
+			count += 1;
+		}
+		if count == 0 && !self.events.is_empty() {
+			return Err(errno!(EINVAL));
+		}
+		self.events.rotate_left(count);
+		self.events.truncate(self.events.len() - count);
+		Ok((off as _, false))
+	}
+
+	fn write(&mut self, _: u64, _: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		if mask & io::POLLIN != 0 && !self.events.is_empty() {
+			Ok(io::POLLIN)
+		} else {
+			Ok(0)
+		}
+	}
+}
+
+/// The existing instances, by location of their file.
+static INSTANCES: Mutex<HashMap<FileLocation, Arc<Mutex<Inotify>>>> = Mutex::new(HashMap::new());
+/// The next cookie to associate related events.
+static NEXT_COOKIE: AtomicU32 = AtomicU32::new(1);
+
+/// Creates a new inotify instance.
+///
+/// `access_profile` is the access profile of the owner of the instance.
+pub fn create(access_profile: &AccessProfile) -> EResult<Arc<Mutex<File>>> {
+	let inotify = Arc::new(Mutex::new(Inotify::default()))?;
+	let loc = buffer::register(None, inotify.clone())?;
+	if let Err(e) = INSTANCES.lock().insert(loc.clone(), inotify) {
+		buffer::release(&loc);
+		return Err(e.into());
+	}
+
+	let file = File::new(
+		String::try_from(b"anon_inode:inotify")?,
+		access_profile.get_euid(),
+		access_profile.get_egid(),
+		0o600,
+		loc,
+		FileContent::Fifo,
+	)?;
+	Ok(Arc::new_in(Mutex::new(file), &FILE_CACHE)?)
+}
+
+/// Returns the inotify instance of the file at location `loc`.
+///
+/// If the file is not an inotify instance, the function returns `None`.
+pub fn get(loc: &FileLocation) -> Option<Arc<Mutex<Inotify>>> {
+	INSTANCES.lock().get(loc).cloned()
+}
+
+/// Frees the inotify instance at location `loc` if it is not open anymore.
+///
+/// If the file is not an inotify instance, the function does nothing.
+pub fn release_if_unused(loc: &FileLocation) {
+	if OpenFile::is_open(loc) {
+		return;
+	}
+	if INSTANCES.lock().remove(loc).is_some() {
+		buffer::release(loc);
+	}
+}
+
+/// Returns a new cookie, to associate the events of a same operation.
+pub fn new_cookie() -> u32 {
+	NEXT_COOKIE.fetch_add(1, atomic::Ordering::Relaxed)
+}
+
+/// Executes `f` on every instance.
+fn for_each<F: FnMut(&mut Inotify)>(mut f: F) {
+	let instances = INSTANCES.lock();
+	for (_, inotify) in instances.iter() {
+		f(&mut inotify.lock());
+	}
+}
+
+/// Notifies the event `mask` on the file at location `location`.
+///
+/// Arguments:
+/// - `name` is the name of the file the event happened on, if `location` is the location of its
+/// parent directory.
+/// - `cookie` associates related events, or is zero.
+pub fn notify(location: &FileLocation, mask: u32, name: Option<&[u8]>, cookie: u32) {
+	for_each(|inotify| inotify.notify(location, mask, name, cookie));
+}
+
+/// Notifies the event `mask` on the file `file`, both to the watches on the file and to the
+/// watches on its parent directory.
+pub fn notify_file(file: &File, mask: u32) {
+	if !matches!(file.get_location(), FileLocation::Filesystem { .. }) {
+		return;
+	}
+	if INSTANCES.lock().is_empty() {
+		return;
+	}
+	let mask = match file.get_type() {
+		FileType::Directory => mask | IN_ISDIR,
+		_ => mask,
+	};
+	notify(file.get_location(), mask, None, 0);
+
+	let Ok(parent_mutex) =
+		vfs::get_file_from_path(file.get_parent_path(), &AccessProfile::KERNEL, true)
+	else {
+		return;
+	};
+	let parent_location = parent_mutex.lock().get_location().clone();
+	notify(&parent_location, mask, Some(file.get_name().as_bytes()), 0);
+}
+
+/// Removes every watches on the file at location `location`, after its removal.
+pub fn forget(location: &FileLocation) {
+	for_each(|inotify| inotify.forget(location));
+}
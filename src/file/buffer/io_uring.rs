@@ -0,0 +1,593 @@
+//! io_uring is an interface to perform IO operations through a pair of rings shared between the
+//! kernel and userspace.
+//!
+//! Userspace places *submission queue entries* (SQE) on the submission queue, then notifies the
+//! kernel with `io_uring_enter`. The result of each operation is placed as a *completion queue
+//! entry* (CQE) on the completion queue, from which userspace reads it without a system call.
+//!
+//! The rings are mapped into userspace with `mmap` on the instance's file descriptor, at the
+//! offsets [`IORING_OFF_SQ_RING`], [`IORING_OFF_CQ_RING`] and [`IORING_OFF_SQES`].
+
+use super::Buffer;
+use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::file::blocking::BlockHandler;
+use crate::file::buffer;
+use crate::file::open_file::OpenFile;
+use crate::file::perm::AccessProfile;
+use crate::file::Errno;
+use crate::file::File;
+use crate::file::FileContent;
+use crate::file::FileLocation;
+use crate::file::FILE_CACHE;
+use crate::memory;
+use crate::memory::buddy;
+use crate::memory::buddy::FrameOrder;
+use crate::process::mem_space::MemSpace;
+use crate::process::Process;
+use crate::syscall::ioctl;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::io;
+use crate::util::io::IO;
+use crate::util::lock::IntMutex;
+use crate::util::lock::Mutex;
+use crate::util::math;
+use crate::util::ptr::arc::Arc;
+use core::ffi::c_void;
+use core::mem::size_of;
+use core::ptr;
+use core::ptr::NonNull;
+use core::sync::atomic;
+use core::sync::atomic::AtomicU32;
+
+/// Setup flag: The size of the completion queue is given by `cq_entries`.
+pub const IORING_SETUP_CQSIZE: u32 = 1 << 3;
+/// Setup flag: Clamp the numbers of entries to the maximum instead of failing.
+pub const IORING_SETUP_CLAMP: u32 = 1 << 4;
+
+/// `mmap` offset of the submission queue ring.
+pub const IORING_OFF_SQ_RING: u64 = 0;
+/// `mmap` offset of the completion queue ring.
+pub const IORING_OFF_CQ_RING: u64 = 0x8000000;
+/// `mmap` offset of the array of submission queue entries.
+pub const IORING_OFF_SQES: u64 = 0x10000000;
+
+/// Operation: Do nothing.
+pub const IORING_OP_NOP: u8 = 0;
+/// Operation: Vectored read, like `preadv2`.
+pub const IORING_OP_READV: u8 = 1;
+/// Operation: Vectored write, like `pwritev2`.
+pub const IORING_OP_WRITEV: u8 = 2;
+/// Operation: Synchronize a file, like `fsync`.
+pub const IORING_OP_FSYNC: u8 = 3;
+/// The last supported operation.
+pub const IORING_OP_LAST: u8 = IORING_OP_FSYNC;
+
+/// SQE flag: `fd` is an index in the registered files.
+pub const IOSQE_FIXED_FILE: u8 = 1 << 0;
+
+/// The maximum number of entries in the submission queue.
+const MAX_ENTRIES: u32 = 32768;
+/// The maximum number of entries in the completion queue.
+const MAX_CQ_ENTRIES: u32 = 2 * MAX_ENTRIES;
+/// The maximum number of registered files.
+pub const MAX_FIXED_FILES: usize = 1024;
+
+/// Offsets of the fields of the submission queue ring.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IoSqringOffsets {
+	/// The index of the first entry to be consumed by the kernel.
+	pub head: u32,
+	/// The index after the last entry produced by userspace.
+	pub tail: u32,
+	/// The mask to apply to indexes to get a position in the ring.
+	pub ring_mask: u32,
+	/// The number of entries in the ring.
+	pub ring_entries: u32,
+	/// The ring's flags.
+	pub flags: u32,
+	/// The number of invalid entries which have been dropped.
+	pub dropped: u32,
+	/// The array of indexes into the submission queue entries.
+	pub array: u32,
+	/// Reserved.
+	pub resv1: u32,
+	/// Reserved.
+	pub user_addr: u64,
+}
+
+/// Offsets of the fields of the completion queue ring.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IoCqringOffsets {
+	/// The index of the first entry to be consumed by userspace.
+	pub head: u32,
+	/// The index after the last entry produced by the kernel.
+	pub tail: u32,
+	/// The mask to apply to indexes to get a position in the ring.
+	pub ring_mask: u32,
+	/// The number of entries in the ring.
+	pub ring_entries: u32,
+	/// The number of completions that were lost because the ring was full.
+	pub overflow: u32,
+	/// The array of completion queue entries.
+	pub cqes: u32,
+	/// The ring's flags.
+	pub flags: u32,
+	/// Reserved.
+	pub resv1: u32,
+	/// Reserved.
+	pub user_addr: u64,
+}
+
+/// Parameters of an instance, given to and returned by `io_uring_setup`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IoUringParams {
+	/// The number of entries in the submission queue.
+	pub sq_entries: u32,
+	/// The number of entries in the completion queue.
+	pub cq_entries: u32,
+	/// Setup flags.
+	pub flags: u32,
+	/// The CPU of the polling thread. Unsupported.
+	pub sq_thread_cpu: u32,
+	/// The idle time of the polling thread. Unsupported.
+	pub sq_thread_idle: u32,
+	/// The features supported by the kernel.
+	pub features: u32,
+	/// The instance whose workers are shared. Unsupported.
+	pub wq_fd: u32,
+	/// Reserved.
+	pub resv: [u32; 3],
+	/// Offsets of the submission queue ring.
+	pub sq_off: IoSqringOffsets,
+	/// Offsets of the completion queue ring.
+	pub cq_off: IoCqringOffsets,
+}
+
+/// A submission queue entry.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct Sqe {
+	/// The operation to perform.
+	pub opcode: u8,
+	/// SQE flags.
+	pub flags: u8,
+	/// The priority of the request.
+	pub ioprio: u16,
+	/// The file descriptor to perform the operation on.
+	pub fd: i32,
+	/// The offset in the file.
+	pub off: u64,
+	/// The address of the buffer, or IO vector.
+	pub addr: u64,
+	/// The length of the buffer, or number of entries in the IO vector.
+	pub len: u32,
+	/// Flags specific to the operation.
+	pub op_flags: u32,
+	/// Data passed back unchanged in the completion.
+	pub user_data: u64,
+	/// Unused.
+	pub pad: [u64; 3],
+}
+
+/// A completion queue entry.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+struct Cqe {
+	/// The data of the associated submission.
+	user_data: u64,
+	/// The result of the operation. On error, this is the negated errno.
+	res: i32,
+	/// Flags.
+	flags: u32,
+}
+
+/// Offset of the array of indexes in the submission queue ring.
+const SQ_ARRAY_OFF: u32 = 24;
+/// Offset of the array of entries in the completion queue ring.
+const CQ_CQES_OFF: u32 = 32;
+
+/// A physically contiguous memory region shared with userspace.
+struct Region {
+	/// The physical address of the region.
+	phys: NonNull<c_void>,
+	/// The buddy order of the allocation.
+	order: FrameOrder,
+	/// The size of the region in bytes.
+	size: usize,
+	/// The list of pages of the region, shared with mappings.
+	pages: Option<Arc<Vec<NonNull<[u8; memory::PAGE_SIZE]>>>>,
+}
+
+impl Region {
+	/// Allocates a zeroed region of at least `size` bytes.
+	fn new(size: usize) -> AllocResult<Self> {
+		let pages_count = math::ceil_div(size, memory::PAGE_SIZE);
+		let order = buddy::get_order(pages_count);
+		let phys = buddy::alloc(order, buddy::FLAG_ZONE_TYPE_KERNEL)?;
+		// On failure, the region is freed when dropped
+		let mut region = Self {
+			phys,
+			order,
+			size,
+			pages: None,
+		};
+
+		let mut pages = Vec::with_capacity(pages_count)?;
+		for i in 0..pages_count {
+			let page = (phys.as_ptr() as usize + i * memory::PAGE_SIZE) as *mut _;
+			pages.push(NonNull::new(page).unwrap())?;
+		}
+		region.pages = Some(Arc::new(pages)?);
+
+		unsafe {
+			ptr::write_bytes(region.as_ptr(), 0, buddy::get_frame_size(order));
+		}
+		Ok(region)
+	}
+
+	/// Returns the virtual address of the region in kernelspace.
+	fn as_ptr(&self) -> *mut u8 {
+		memory::kern_to_virt(self.phys.as_ptr()) as _
+	}
+
+	/// Returns a reference to the `u32` at offset `off` in the region.
+	fn u32_at(&self, off: u32) -> &AtomicU32 {
+		debug_assert!(off as usize + size_of::<u32>() <= self.size);
+		unsafe { &*(self.as_ptr().add(off as _) as *const AtomicU32) }
+	}
+}
+
+impl Drop for Region {
+	fn drop(&mut self) {
+		// If the region is still mapped, it cannot be freed
+		// TODO free the region when the last mapping is removed
+		if let Some(pages) = self.pages.take() {
+			if Arc::into_inner(pages).is_none() {
+				return;
+			}
+		}
+		buddy::free(self.phys.as_ptr(), self.order);
+	}
+}
+
+/// An io_uring instance.
+pub struct IoUring {
+	/// The number of entries in the submission queue.
+	sq_entries: u32,
+	/// The number of entries in the completion queue.
+	cq_entries: u32,
+
+	/// The submission queue ring.
+	sq_ring: Region,
+	/// The completion queue ring.
+	cq_ring: Region,
+	/// The array of submission queue entries.
+	sqes: Region,
+
+	/// The registered files, by index.
+	files: Vec<Option<Arc<Mutex<OpenFile>>>>,
+
+	/// The instance's block handler.
+	block_handler: BlockHandler,
+}
+
+impl IoUring {
+	/// Creates a new instance with the parameters `params`.
+	///
+	/// On success, the parameters are updated with the values to be returned to userspace.
+	fn new(params: &mut IoUringParams) -> EResult<Self> {
+		if params.flags & !(IORING_SETUP_CQSIZE | IORING_SETUP_CLAMP) != 0 {
+			return Err(errno!(EINVAL));
+		}
+		let clamp = params.flags & IORING_SETUP_CLAMP != 0;
+
+		let mut sq_entries = params.sq_entries;
+		if sq_entries == 0 {
+			return Err(errno!(EINVAL));
+		}
+		if sq_entries > MAX_ENTRIES {
+			if !clamp {
+				return Err(errno!(EINVAL));
+			}
+			sq_entries = MAX_ENTRIES;
+		}
+		let sq_entries = sq_entries.next_power_of_two();
+
+		let cq_entries = if params.flags & IORING_SETUP_CQSIZE != 0 {
+			let mut cq_entries = params.cq_entries;
+			if cq_entries == 0 {
+				return Err(errno!(EINVAL));
+			}
+			if cq_entries > MAX_CQ_ENTRIES {
+				if !clamp {
+					return Err(errno!(EINVAL));
+				}
+				cq_entries = MAX_CQ_ENTRIES;
+			}
+			let cq_entries = cq_entries.next_power_of_two();
+			if cq_entries < sq_entries {
+				return Err(errno!(EINVAL));
+			}
+			cq_entries
+		} else {
+			2 * sq_entries
+		};
+
+		let sq_ring = Region::new(SQ_ARRAY_OFF as usize + sq_entries as usize * size_of::<u32>())?;
+		let cq_ring = Region::new(CQ_CQES_OFF as usize + cq_entries as usize * size_of::<Cqe>())?;
+		let sqes = Region::new(sq_entries as usize * size_of::<Sqe>())?;
+
+		params.sq_off = IoSqringOffsets {
+			head: 0,
+			tail: 4,
+			ring_mask: 8,
+			ring_entries: 12,
+			flags: 16,
+			dropped: 20,
+			array: SQ_ARRAY_OFF,
+			resv1: 0,
+			user_addr: 0,
+		};
+		params.cq_off = IoCqringOffsets {
+			head: 0,
+			tail: 4,
+			ring_mask: 8,
+			ring_entries: 12,
+			overflow: 16,
+			cqes: CQ_CQES_OFF,
+			flags: 20,
+			resv1: 0,
+			user_addr: 0,
+		};
+		params.sq_entries = sq_entries;
+		params.cq_entries = cq_entries;
+		params.features = 0;
+
+		sq_ring
+			.u32_at(params.sq_off.ring_mask)
+			.store(sq_entries - 1, atomic::Ordering::Relaxed);
+		sq_ring
+			.u32_at(params.sq_off.ring_entries)
+			.store(sq_entries, atomic::Ordering::Relaxed);
+		cq_ring
+			.u32_at(params.cq_off.ring_mask)
+			.store(cq_entries - 1, atomic::Ordering::Relaxed);
+		cq_ring
+			.u32_at(params.cq_off.ring_entries)
+			.store(cq_entries, atomic::Ordering::Relaxed);
+
+		Ok(Self {
+			sq_entries,
+			cq_entries,
+
+			sq_ring,
+			cq_ring,
+			sqes,
+
+			files: Vec::new(),
+
+			block_handler: BlockHandler::new(),
+		})
+	}
+
+	/// Returns the pages of the region to be mapped at offset `off`.
+	///
+	/// If no region is mapped at this offset, the function returns `None`.
+	pub fn get_region(&self, off: u64) -> Option<Arc<Vec<NonNull<[u8; memory::PAGE_SIZE]>>>> {
+		let region = match off {
+			IORING_OFF_SQ_RING => &self.sq_ring,
+			IORING_OFF_CQ_RING => &self.cq_ring,
+			IORING_OFF_SQES => &self.sqes,
+			_ => return None,
+		};
+		region.pages.clone()
+	}
+
+	/// Takes the next entry from the submission queue.
+	///
+	/// Entries with an invalid index are dropped.
+	///
+	/// If the queue is empty, the function returns `None`.
+	pub fn pop_sqe(&mut self) -> Option<Sqe> {
+		let head = self.sq_ring.u32_at(0);
+		let tail = self.sq_ring.u32_at(4);
+		loop {
+			let h = head.load(atomic::Ordering::Relaxed);
+			if h == tail.load(atomic::Ordering::Acquire) {
+				return None;
+			}
+			let array_off = SQ_ARRAY_OFF + (h & (self.sq_entries - 1)) * size_of::<u32>() as u32;
+			let index = self
+				.sq_ring
+				.u32_at(array_off)
+				.load(atomic::Ordering::Relaxed);
+			let sqe = (index < self.sq_entries).then(|| unsafe {
+				let ptr = self.sqes.as_ptr().add(index as usize * size_of::<Sqe>());
+				ptr::read_volatile(ptr as *const Sqe)
+			});
+			head.store(h.wrapping_add(1), atomic::Ordering::Release);
+			match sqe {
+				Some(sqe) => return Some(sqe),
+				None => {
+					self.sq_ring
+						.u32_at(20)
+						.fetch_add(1, atomic::Ordering::Relaxed);
+				}
+			}
+		}
+	}
+
+	/// Places a completion on the completion queue.
+	///
+	/// Arguments:
+	/// - `user_data` is the data of the associated submission.
+	/// - `res` is the result of the operation.
+	///
+	/// If the queue is full, the completion is lost and the overflow counter is incremented.
+	pub fn push_cqe(&mut self, user_data: u64, res: i32) {
+		let head = self.cq_ring.u32_at(0).load(atomic::Ordering::Acquire);
+		let tail = self.cq_ring.u32_at(4);
+		let t = tail.load(atomic::Ordering::Relaxed);
+		if t.wrapping_sub(head) >= self.cq_entries {
+			self.cq_ring
+				.u32_at(16)
+				.fetch_add(1, atomic::Ordering::Relaxed);
+			return;
+		}
+
+		let cqe = Cqe {
+			user_data,
+			res,
+			flags: 0,
+		};
+		let off = CQ_CQES_OFF as usize + (t & (self.cq_entries - 1)) as usize * size_of::<Cqe>();
+		unsafe {
+			ptr::write_volatile(self.cq_ring.as_ptr().add(off) as *mut Cqe, cqe);
+		}
+		tail.store(t.wrapping_add(1), atomic::Ordering::Release);
+		self.block_handler.wake_processes(io::POLLIN);
+	}
+
+	/// Returns the number of entries in the completion queue.
+	pub fn get_cq_entries(&self) -> u32 {
+		self.cq_entries
+	}
+
+	/// Returns the number of completions waiting to be consumed by userspace.
+	pub fn get_completions_count(&self) -> u32 {
+		let head = self.cq_ring.u32_at(0).load(atomic::Ordering::Acquire);
+		let tail = self.cq_ring.u32_at(4).load(atomic::Ordering::Relaxed);
+		tail.wrapping_sub(head)
+	}
+
+	/// Returns the registered file at index `index`.
+	pub fn get_file(&self, index: usize) -> Option<Arc<Mutex<OpenFile>>> {
+		self.files.get(index)?.clone()
+	}
+
+	/// Registers the files `files`.
+	///
+	/// If files are already registered, the function returns an error.
+	pub fn register_files(&mut self, files: Vec<Option<Arc<Mutex<OpenFile>>>>) -> EResult<()> {
+		if !self.files.is_empty() {
+			return Err(errno!(EBUSY));
+		}
+		self.files = files;
+		Ok(())
+	}
+
+	/// Unregisters the registered files.
+	///
+	/// If no file is registered, the function returns an error.
+	pub fn unregister_files(&mut self) -> EResult<()> {
+		if self.files.is_empty() {
+			return Err(errno!(ENXIO));
+		}
+		self.files = Vec::new();
+		Ok(())
+	}
+}
+
+impl Buffer for IoUring {
+	fn get_capacity(&self) -> usize {
+		0
+	}
+
+	fn increment_open(&mut self, _read: bool, _write: bool) {}
+
+	fn decrement_open(&mut self, _read: bool, _write: bool) {}
+
+	fn add_waiting_process(&mut self, proc: &mut Process, mask: u32) -> Result<(), Errno> {
+		self.block_handler.add_waiting_process(proc, mask)
+	}
+
+	fn ioctl(
+		&mut self,
+		_mem_space: Arc<IntMutex<MemSpace>>,
+		_request: ioctl::Request,
+		_argp: *const c_void,
+	) -> Result<u32, Errno> {
+		Err(errno!(ENOTTY))
+	}
+}
+
+impl IO for IoUring {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, _: u64, _: &mut [u8]) -> Result<(u64, bool), Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn write(&mut self, _: u64, _: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		let mut result = 0;
+		if mask & io::POLLIN != 0 && self.get_completions_count() > 0 {
+			result |= io::POLLIN;
+		}
+		// Operations are executed on submission, so there is always room for submissions
+		if mask & io::POLLOUT != 0 {
+			result |= io::POLLOUT;
+		}
+		Ok(result)
+	}
+}
+
+/// The existing instances, by location of their file.
+static INSTANCES: Mutex<HashMap<FileLocation, Arc<Mutex<IoUring>>>> = Mutex::new(HashMap::new());
+
+/// Creates a new io_uring instance with the parameters `params`.
+///
+/// On success, the parameters are updated with the values to be returned to userspace.
+///
+/// `access_profile` is the access profile of the owner of the instance.
+pub fn create(
+	params: &mut IoUringParams,
+	access_profile: &AccessProfile,
+) -> EResult<Arc<Mutex<File>>> {
+	let io_uring = Arc::new(Mutex::new(IoUring::new(params)?))?;
+	let loc = buffer::register(None, io_uring.clone())?;
+	if let Err(e) = INSTANCES.lock().insert(loc.clone(), io_uring) {
+		buffer::release(&loc);
+		return Err(e.into());
+	}
+
+	let file = File::new(
+		String::try_from(b"anon_inode:[io_uring]")?,
+		access_profile.get_euid(),
+		access_profile.get_egid(),
+		0o600,
+		loc,
+		FileContent::Fifo,
+	)?;
+	Ok(Arc::new_in(Mutex::new(file), &FILE_CACHE)?)
+}
+
+/// Returns the io_uring instance of the file at location `loc`.
+///
+/// If the file is not an io_uring instance, the function returns `None`.
+pub fn get(loc: &FileLocation) -> Option<Arc<Mutex<IoUring>>> {
+	INSTANCES.lock().get(loc).cloned()
+}
+
+/// Frees the io_uring instance at location `loc` if it is not open anymore.
+///
+/// If the file is not an io_uring instance, the function does nothing.
+pub fn release_if_unused(loc: &FileLocation) {
+	if OpenFile::is_open(loc) {
+		return;
+	}
+	if INSTANCES.lock().remove(loc).is_some() {
+		buffer::release(loc);
+	}
+}
@@ -0,0 +1,263 @@
+//! An anonymous file is a regular file that lives only in memory and has no path on any
+//! filesystem. Such files are created with the `memfd_create` system call.
+//!
+//! Seals can be added to an anonymous file to restrict the operations allowed on it, which
+//! allows to share it safely between processes.
+//!
+//! The content of an anonymous file is entirely stored in the page cache, so the buffer itself
+//! holds only the seals.
+
+use super::Buffer;
+use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::file::buffer;
+use crate::file::open_file::OpenFile;
+use crate::file::page_cache;
+use crate::file::perm::AccessProfile;
+use crate::file::Errno;
+use crate::file::File;
+use crate::file::FileContent;
+use crate::file::FileLocation;
+use crate::file::FILE_CACHE;
+use crate::process;
+use crate::process::mem_space::MemSpace;
+use crate::syscall::ioctl;
+use crate::util::container::vec::Vec;
+use crate::util::io;
+use crate::util::io::IO;
+use crate::util::lock::IntMutex;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use crate::util::DisplayableStr;
+use core::any::Any;
+use core::ffi::c_void;
+
+/// If this seal is set, any further call to `fcntl` with `F_ADD_SEALS` fails.
+pub const F_SEAL_SEAL: i32 = 1;
+/// If this seal is set, the size of the file in question cannot be reduced.
+pub const F_SEAL_SHRINK: i32 = 2;
+/// If this seal is set, the size of the file in question cannot be increased.
+pub const F_SEAL_GROW: i32 = 4;
+/// If this seal is set, you cannot modify the contents of the file.
+pub const F_SEAL_WRITE: i32 = 8;
+/// Like `F_SEAL_WRITE`, except already existing shared writable mappings are still allowed to
+/// modify the content of the file.
+pub const F_SEAL_FUTURE_WRITE: i32 = 16;
+
+/// Structure representing an anonymous file.
+#[derive(Debug, Default)]
+pub struct MemFd {
+	/// The set of seals applied to the file.
+	seals: i32,
+}
+
+impl MemFd {
+	/// Returns the set of seals applied to the file.
+	pub fn get_seals(&self) -> i32 {
+		self.seals
+	}
+
+	/// Adds the given set of seals to the file.
+	///
+	/// `mapped` tells whether the file is mapped by at least one shared writable mapping.
+	///
+	/// If the file is sealed against adding seals, the function returns
+	/// [`crate::errno::EPERM`].
+	///
+	/// If `F_SEAL_WRITE` is to be added while the file is mapped by a shared writable mapping,
+	/// the function returns [`crate::errno::EBUSY`].
+	pub fn add_seals(&mut self, seals: i32, mapped: bool) -> EResult<()> {
+		let all = F_SEAL_SEAL | F_SEAL_SHRINK | F_SEAL_GROW | F_SEAL_WRITE | F_SEAL_FUTURE_WRITE;
+		if seals & !all != 0 {
+			return Err(errno!(EINVAL));
+		}
+		if self.seals & F_SEAL_SEAL != 0 {
+			return Err(errno!(EPERM));
+		}
+		if seals & F_SEAL_WRITE != 0 && mapped {
+			return Err(errno!(EBUSY));
+		}
+
+		self.seals |= seals;
+		Ok(())
+	}
+
+	/// Tells whether the content of the file can be modified.
+	pub fn is_write_sealed(&self) -> bool {
+		self.seals & (F_SEAL_WRITE | F_SEAL_FUTURE_WRITE) != 0
+	}
+
+	/// Checks whether writing to the file is allowed by its seals.
+	///
+	/// Arguments:
+	/// - `end` is the offset of the end of the write, in bytes
+	/// - `size` is the current size of the file, in bytes
+	///
+	/// If the write is not allowed, the function returns [`crate::errno::EPERM`].
+	pub fn check_write(&self, end: u64, size: u64) -> EResult<()> {
+		if self.is_write_sealed() {
+			return Err(errno!(EPERM));
+		}
+		if end > size && self.seals & F_SEAL_GROW != 0 {
+			return Err(errno!(EPERM));
+		}
+		Ok(())
+	}
+
+	/// Checks whether changing the size of the file is allowed by its seals.
+	///
+	/// Arguments:
+	/// - `new_size` is the new size of the file, in bytes
+	/// - `size` is the current size of the file, in bytes
+	///
+	/// If the change is not allowed, the function returns [`crate::errno::EPERM`].
+	pub fn check_resize(&self, new_size: u64, size: u64) -> EResult<()> {
+		if new_size < size && self.seals & F_SEAL_SHRINK != 0 {
+			return Err(errno!(EPERM));
+		}
+		if new_size > size && self.seals & F_SEAL_GROW != 0 {
+			return Err(errno!(EPERM));
+		}
+		Ok(())
+	}
+}
+
+impl Buffer for MemFd {
+	fn get_capacity(&self) -> usize {
+		0
+	}
+
+	fn increment_open(&mut self, _read: bool, _write: bool) {}
+
+	fn decrement_open(&mut self, _read: bool, _write: bool) {}
+
+	fn ioctl(
+		&mut self,
+		_mem_space: Arc<IntMutex<MemSpace>>,
+		_request: ioctl::Request,
+		_argp: *const c_void,
+	) -> Result<u32, Errno> {
+		Err(errno!(ENOTTY))
+	}
+}
+
+// The page cache is the only storage of the file. Thus, pages that are not cached yet are empty
+impl IO for MemFd {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, _off: u64, _buf: &mut [u8]) -> Result<(u64, bool), Errno> {
+		Ok((0, true))
+	}
+
+	fn write(&mut self, _off: u64, _buf: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		Ok(mask & (io::POLLIN | io::POLLOUT))
+	}
+}
+
+/// Creates a new anonymous file.
+///
+/// Arguments:
+/// - `name` is the name of the file. It is used only for debugging purposes
+/// - `allow_sealing` tells whether seals can be added to the file
+/// - `access_profile` is the access profile of the owner of the file
+pub fn create(
+	name: &[u8],
+	allow_sealing: bool,
+	access_profile: &AccessProfile,
+) -> EResult<Arc<Mutex<File>>> {
+	let memfd = MemFd {
+		seals: if allow_sealing { 0 } else { F_SEAL_SEAL },
+	};
+	let loc = buffer::register(None, Arc::new(Mutex::new(memfd))?)?;
+
+	let name = crate::format!("memfd:{}", DisplayableStr(name))?;
+	let file = File::new(
+		name,
+		access_profile.get_euid(),
+		access_profile.get_egid(),
+		0o777,
+		loc,
+		FileContent::Regular,
+	)?;
+	Ok(Arc::new_in(Mutex::new(file), &FILE_CACHE)?)
+}
+
+/// Returns the set of seals of the anonymous file at location `loc`.
+///
+/// If the file is not an anonymous file, the function returns `None`.
+pub fn get_seals(loc: &FileLocation) -> Option<i32> {
+	let buff_mutex = buffer::get(loc)?;
+	let mut buff = buff_mutex.lock();
+	let memfd = (&mut *buff as &mut dyn Any).downcast_mut::<MemFd>()?;
+	Some(memfd.get_seals())
+}
+
+/// Checks whether the seals of the file at location `loc` allow a write.
+///
+/// For details on arguments, see [`MemFd::check_write`].
+///
+/// If the file is not an anonymous file, the function does nothing.
+pub fn check_write(loc: &FileLocation, end: u64, size: u64) -> EResult<()> {
+	let Some(buff_mutex) = buffer::get(loc) else {
+		return Ok(());
+	};
+	let mut buff = buff_mutex.lock();
+	match (&mut *buff as &mut dyn Any).downcast_mut::<MemFd>() {
+		Some(memfd) => memfd.check_write(end, size),
+		None => Ok(()),
+	}
+}
+
+/// Checks whether the seals of the file at location `loc` allow changing its size.
+///
+/// For details on arguments, see [`MemFd::check_resize`].
+///
+/// If the file is not an anonymous file, the function does nothing.
+pub fn check_resize(loc: &FileLocation, new_size: u64, size: u64) -> EResult<()> {
+	let Some(buff_mutex) = buffer::get(loc) else {
+		return Ok(());
+	};
+	let mut buff = buff_mutex.lock();
+	match (&mut *buff as &mut dyn Any).downcast_mut::<MemFd>() {
+		Some(memfd) => memfd.check_resize(new_size, size),
+		None => Ok(()),
+	}
+}
+
+/// Tells whether the file at location `loc` is mapped by at least one shared writable mapping,
+/// in any process.
+///
+/// Since this function locks processes and their memory spaces, the caller must not hold any of
+/// them.
+pub fn has_shared_writable_mapping(loc: &FileLocation) -> AllocResult<bool> {
+	// Memory spaces are collected first to avoid locking them while holding the scheduler
+	let mut mem_spaces = Vec::new();
+	{
+		let mut sched = process::get_scheduler().lock();
+		for (_, proc_mutex) in sched.iter_process() {
+			if let Some(mem_space) = proc_mutex.lock().get_mem_space() {
+				mem_spaces.push(mem_space.clone())?;
+			}
+		}
+	}
+	Ok(mem_spaces
+		.iter()
+		.any(|mem_space| mem_space.lock().has_shared_writable_mapping(loc)))
+}
+
+/// Frees the anonymous file at location `loc` if it is neither open nor mapped anymore.
+///
+/// If the file is not an anonymous file, the function does nothing.
+pub fn release_if_unused(loc: &FileLocation) {
+	if get_seals(loc).is_none() || OpenFile::is_open(loc) || page_cache::is_mapped(loc) {
+		return;
+	}
+	page_cache::remove(loc);
+	buffer::release(loc);
+}
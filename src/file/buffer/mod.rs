@@ -1,5 +1,10 @@
 //! A buffer is an FIFO resource which may be blocking. The resource is represented by a file.
 
+pub mod fanotify;
+pub mod inotify;
+pub mod io_uring;
+pub mod memfd;
+pub mod pidfd;
 pub mod pipe;
 pub mod socket;
 
@@ -7,6 +12,7 @@ use crate::errno::AllocError;
 use crate::errno::AllocResult;
 use crate::errno::Errno;
 use crate::file::blocking::BlockHandler;
+use crate::file::open_file::FileOwner;
 use crate::file::FileLocation;
 use crate::process::mem_space::MemSpace;
 use crate::process::Process;
@@ -19,6 +25,7 @@ use crate::util::lock::Mutex;
 use crate::util::ptr::arc::Arc;
 use crate::util::TryDefault;
 use core::any::Any;
+use core::ffi::c_int;
 use core::ffi::c_void;
 
 /// Trait representing a buffer.
@@ -52,6 +59,23 @@ pub trait Buffer: IO + Any {
 		Ok(())
 	}
 
+	/// Registers or unregisters an open file for signal-driven IO on the buffer.
+	///
+	/// Arguments:
+	/// - `fd` is the file descriptor reported to the owner.
+	/// - `owner` is the owner of the open file.
+	/// - `on` tells whether the open file is registered.
+	///
+	/// If the buffer cannot block, the function does nothing.
+	fn set_async(
+		&mut self,
+		_fd: c_int,
+		_owner: &Arc<IntMutex<FileOwner>>,
+		_on: bool,
+	) -> Result<(), Errno> {
+		Ok(())
+	}
+
 	/// Performs an ioctl operation on the file.
 	///
 	/// Arguments:
@@ -0,0 +1,179 @@
+//! A pidfd is a file descriptor referring to a process.
+//!
+//! Unlike a PID, which may be reused by another process once the referred process has been
+//! reaped, a pidfd keeps referring to the same process. Sending a signal through it never
+//! reaches another process.
+//!
+//! A pidfd becomes readable when the process exits, which allows to wait for it with `poll`.
+
+use super::Buffer;
+use crate::errno::EResult;
+use crate::file::blocking::BlockHandler;
+use crate::file::buffer;
+use crate::file::open_file::OpenFile;
+use crate::file::perm::AccessProfile;
+use crate::file::Errno;
+use crate::file::File;
+use crate::file::FileContent;
+use crate::file::FileLocation;
+use crate::file::FILE_CACHE;
+use crate::process::mem_space::MemSpace;
+use crate::process::pid::Pid;
+use crate::process::Process;
+use crate::process::State;
+use crate::syscall::ioctl;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+use crate::util::io;
+use crate::util::io::IO;
+use crate::util::lock::IntMutex;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use crate::util::ptr::arc::Weak;
+use core::ffi::c_void;
+
+/// A pidfd.
+pub struct PidFd {
+	/// The PID of the referred process.
+	pid: Pid,
+	/// The referred process. If the process has been reaped, the pointer cannot be upgraded
+	/// anymore.
+	process: Weak<IntMutex<Process>>,
+	/// Tells whether the process has exited.
+	exited: bool,
+
+	/// The pidfd's block handler.
+	block_handler: BlockHandler,
+}
+
+impl PidFd {
+	/// Returns the PID of the referred process.
+	pub fn get_pid(&self) -> Pid {
+		self.pid
+	}
+
+	/// Returns the referred process.
+	///
+	/// If the process has been reaped, the function returns `None`.
+	pub fn get_process(&self) -> Option<Arc<IntMutex<Process>>> {
+		self.process.upgrade()
+	}
+
+	/// Marks the process as exited and wakes the processes waiting on the pidfd.
+	fn set_exited(&mut self) {
+		self.exited = true;
+		self.block_handler.wake_processes(io::POLLIN);
+	}
+}
+
+impl Buffer for PidFd {
+	fn get_capacity(&self) -> usize {
+		0
+	}
+
+	fn increment_open(&mut self, _read: bool, _write: bool) {}
+
+	fn decrement_open(&mut self, _read: bool, _write: bool) {}
+
+	fn add_waiting_process(&mut self, proc: &mut Process, mask: u32) -> Result<(), Errno> {
+		self.block_handler.add_waiting_process(proc, mask)
+	}
+
+	fn ioctl(
+		&mut self,
+		_mem_space: Arc<IntMutex<MemSpace>>,
+		_request: ioctl::Request,
+		_argp: *const c_void,
+	) -> Result<u32, Errno> {
+		Err(errno!(ENOTTY))
+	}
+}
+
+impl IO for PidFd {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, _: u64, _: &mut [u8]) -> Result<(u64, bool), Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn write(&mut self, _: u64, _: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		if mask & io::POLLIN != 0 && self.exited {
+			Ok(io::POLLIN)
+		} else {
+			Ok(0)
+		}
+	}
+}
+
+/// The existing pidfds, by location of their file.
+static INSTANCES: Mutex<HashMap<FileLocation, Arc<Mutex<PidFd>>>> = Mutex::new(HashMap::new());
+
+/// Creates a new pidfd referring to the process `proc`.
+///
+/// Arguments:
+/// - `proc_mutex` is the mutex of `proc`, which must be locked by the caller.
+/// - `access_profile` is the access profile of the owner of the pidfd.
+pub fn create(
+	proc_mutex: &Arc<IntMutex<Process>>,
+	proc: &Process,
+	access_profile: &AccessProfile,
+) -> EResult<Arc<Mutex<File>>> {
+	let pidfd = Arc::new(Mutex::new(PidFd {
+		pid: proc.pid,
+		process: Arc::downgrade(proc_mutex),
+		exited: matches!(proc.get_state(), State::Zombie),
+
+		block_handler: BlockHandler::new(),
+	}))?;
+	let loc = buffer::register(None, pidfd.clone())?;
+	if let Err(e) = INSTANCES.lock().insert(loc.clone(), pidfd) {
+		buffer::release(&loc);
+		return Err(e.into());
+	}
+
+	let file = File::new(
+		String::try_from(b"anon_inode:[pidfd]")?,
+		access_profile.get_euid(),
+		access_profile.get_egid(),
+		0o600,
+		loc,
+		FileContent::Fifo,
+	)?;
+	Ok(Arc::new_in(Mutex::new(file), &FILE_CACHE)?)
+}
+
+/// Returns the pidfd of the file at location `loc`.
+///
+/// If the file is not a pidfd, the function returns `None`.
+pub fn get(loc: &FileLocation) -> Option<Arc<Mutex<PidFd>>> {
+	INSTANCES.lock().get(loc).cloned()
+}
+
+/// Frees the pidfd at location `loc` if it is not open anymore.
+///
+/// If the file is not a pidfd, the function does nothing.
+pub fn release_if_unused(loc: &FileLocation) {
+	if OpenFile::is_open(loc) {
+		return;
+	}
+	if INSTANCES.lock().remove(loc).is_some() {
+		buffer::release(loc);
+	}
+}
+
+/// Notifies the pidfds referring to the process with PID `pid` that it exited.
+pub fn notify_exit(pid: Pid) {
+	let instances = INSTANCES.lock();
+	for (_, pidfd) in instances.iter() {
+		let mut pidfd = pidfd.lock();
+		if pidfd.pid == pid {
+			pidfd.set_exited();
+		}
+	}
+}
@@ -1,29 +1,217 @@
 //! A pipe is an object that links two file descriptors together. One reading
 //! and another writing, with a buffer in between.
+//!
+//! The buffer is made of references to pages, so that data can be moved between pipes and files
+//! with `splice` and `tee` without being copied.
 
 use super::Buffer;
+use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::file::buffer;
 use crate::file::buffer::BlockHandler;
+use crate::file::open_file::FileOwner;
+use crate::file::page_cache;
 use crate::file::Errno;
-use crate::limits;
+use crate::file::FileLocation;
+use crate::memory;
+use crate::memory::buddy;
 use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::mem_space::MemSpace;
+use crate::process::mem_space::PHYSICAL_REF_COUNTER;
 use crate::process::Process;
 use crate::syscall::ioctl;
-use crate::util::container::ring_buffer::RingBuffer;
 use crate::util::container::vec::Vec;
 use crate::util::io;
 use crate::util::io::IO;
 use crate::util::lock::IntMutex;
 use crate::util::ptr::arc::Arc;
 use crate::util::TryDefault;
+use core::any::Any;
+use core::cmp::min;
 use core::ffi::c_int;
 use core::ffi::c_void;
+use core::ptr::NonNull;
+use core::slice;
+
+/// The number of pages a pipe can hold.
+const PIPE_PAGES: usize = 16;
+/// The maximum capacity of a pipe in bytes, unless the process is privileged.
+pub const PIPE_MAX_SIZE: usize = 1048576;
+
+/// A reference to a page of data held by a pipe.
+///
+/// Pages are referenced on the physical reference counter, which allows moving them between
+/// pipes, or sharing them with other pipes and the page cache, without copying their content.
+#[derive(Debug)]
+pub struct PipePage {
+	/// The physical address of the page.
+	ptr: NonNull<c_void>,
+	/// If the page belongs to the page cache, the location of the file and the offset of the page
+	/// in the file, in pages.
+	cached: Option<(FileLocation, u64)>,
+
+	/// The offset of the data in the page.
+	off: usize,
+	/// The length of the data in the page.
+	len: usize,
+}
+
+impl PipePage {
+	/// Allocates a new page, without data.
+	pub fn new() -> AllocResult<Self> {
+		let ptr = buddy::alloc(0, buddy::FLAG_ZONE_TYPE_KERNEL)?;
+		if let Err(e) = PHYSICAL_REF_COUNTER.lock().increment(ptr.as_ptr()) {
+			buddy::free(ptr.as_ptr(), 0);
+			return Err(e);
+		}
+		Ok(Self {
+			ptr,
+			cached: None,
+
+			off: 0,
+			len: 0,
+		})
+	}
+
+	/// Creates a reference to a page of the page cache.
+	///
+	/// Arguments:
+	/// - `location` is the location of the file.
+	/// - `page_off` is the offset of the page in the file, in pages.
+	/// - `ptr` is the physical address of the page, which must have been pinned with
+	/// [`page_cache::pin`]. The pin is released when the reference is dropped.
+	/// - `off` and `len` are the offset and the length of the data in the page.
+	pub fn from_cache(
+		location: FileLocation,
+		page_off: u64,
+		ptr: NonNull<c_void>,
+		off: usize,
+		len: usize,
+	) -> Self {
+		Self {
+			ptr,
+			cached: Some((location, page_off)),
+
+			off,
+			len,
+		}
+	}
+
+	/// Returns another reference to the same data.
+	pub fn try_clone(&self) -> AllocResult<Self> {
+		PHYSICAL_REF_COUNTER.lock().increment(self.ptr.as_ptr())?;
+		Ok(Self {
+			ptr: self.ptr,
+			cached: self.cached.clone(),
+
+			off: self.off,
+			len: self.len,
+		})
+	}
+
+	/// Returns the whole content of the page.
+	///
+	/// # Safety
+	///
+	/// The content must not be modified if the page is shared.
+	unsafe fn content<'a>(&self) -> &'a mut [u8] {
+		let virt_ptr = memory::kern_to_virt(self.ptr.as_ptr()) as *mut u8;
+		slice::from_raw_parts_mut(virt_ptr, memory::PAGE_SIZE)
+	}
+
+	/// Returns the data of the page.
+	pub fn data(&self) -> &[u8] {
+		unsafe { &self.content()[self.off..(self.off + self.len)] }
+	}
+
+	/// Returns the length of the data in the page.
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Tells whether the page contains no data.
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Tells whether data can be appended to the page.
+	fn is_writable(&self) -> bool {
+		self.cached.is_none() && !PHYSICAL_REF_COUNTER.lock().is_shared(self.ptr.as_ptr())
+	}
+
+	/// Appends as much data from `buf` as possible to the page.
+	///
+	/// The function returns the number of bytes written.
+	///
+	/// The page must be writable.
+	pub fn append(&mut self, buf: &[u8]) -> usize {
+		let start = self.off + self.len;
+		let len = min(buf.len(), memory::PAGE_SIZE - start);
+		unsafe {
+			self.content()[start..(start + len)].copy_from_slice(&buf[..len]);
+		}
+		self.len += len;
+		len
+	}
+
+	/// Appends data read from `io` at offset `off` to the page, reading at most `max` bytes.
+	///
+	/// The page must be writable.
+	///
+	/// The function returns the number of bytes read and whether the end of file has been
+	/// reached.
+	pub fn read_from<I: IO>(
+		&mut self,
+		io: &mut I,
+		off: u64,
+		max: usize,
+	) -> EResult<(usize, bool)> {
+		let start = self.off + self.len;
+		let len = min(max, memory::PAGE_SIZE - start);
+		let buf = unsafe { &mut self.content()[start..(start + len)] };
+		let (len, eof) = io.read(off, buf)?;
+		self.len += len as usize;
+		Ok((len as _, eof))
+	}
+
+	/// Removes the first `len` bytes of data of the page.
+	fn consume(&mut self, len: usize) {
+		let len = min(len, self.len);
+		self.off += len;
+		self.len -= len;
+	}
+
+	/// Limits the data of the page to its first `len` bytes.
+	fn truncate(&mut self, len: usize) {
+		self.len = min(len, self.len);
+	}
+}
+
+impl Drop for PipePage {
+	fn drop(&mut self) {
+		match &self.cached {
+			Some((location, page_off)) => page_cache::unpin(location, *page_off, self.ptr),
+			None => {
+				let mut ref_counter = PHYSICAL_REF_COUNTER.lock();
+				ref_counter.decrement(self.ptr.as_ptr());
+				if ref_counter.can_free(self.ptr.as_ptr()) {
+					buddy::free(self.ptr.as_ptr(), 0);
+				}
+			}
+		}
+	}
+}
 
 /// Structure representing a buffer buffer.
+///
+/// Data is stored in a list of pages, which can be moved to and from other pipes and files
+/// without being copied with `splice`.
 #[derive(Debug)]
 pub struct PipeBuffer {
-	/// The buffer's buffer.
-	buffer: RingBuffer<u8, Vec<u8>>,
+	/// The pages of data, in order.
+	pages: Vec<PipePage>,
+	/// The maximum number of pages in the buffer.
+	max_pages: usize,
 
 	/// The number of reading ends attached to the pipe.
 	read_ends: u32,
@@ -37,19 +225,122 @@ pub struct PipeBuffer {
 impl PipeBuffer {
 	/// Returns the length of the data to be read in the buffer.
 	pub fn get_data_len(&self) -> usize {
-		self.buffer.get_data_len()
+		self.pages.iter().map(PipePage::len).sum()
 	}
 
 	/// Returns the available space in the buffer in bytes.
 	pub fn get_available_len(&self) -> usize {
-		self.buffer.get_available_len()
+		let last = self
+			.pages
+			.last()
+			.filter(|page| page.is_writable())
+			.map(|page| memory::PAGE_SIZE - page.off - page.len)
+			.unwrap_or(0);
+		last + self.get_free_pages() * memory::PAGE_SIZE
+	}
+
+	/// Returns the number of pages that can be added to the buffer.
+	pub fn get_free_pages(&self) -> usize {
+		self.max_pages.saturating_sub(self.pages.len())
+	}
+
+	/// Sets the capacity of the pipe to at least `size` bytes.
+	///
+	/// The capacity is rounded up to a power of two number of pages.
+	///
+	/// If the data currently in the pipe does not fit in the new capacity, the function returns
+	/// `EBUSY`.
+	///
+	/// On success, the function returns the new capacity in bytes.
+	pub fn set_capacity(&mut self, size: usize) -> EResult<usize> {
+		let pages = size
+			.div_ceil(memory::PAGE_SIZE)
+			.max(1)
+			.checked_next_power_of_two()
+			.ok_or_else(|| errno!(EINVAL))?;
+		if self.pages.len() > pages {
+			return Err(errno!(EBUSY));
+		}
+		self.max_pages = pages;
+		self.block_handler.wake_processes(io::POLLOUT);
+		Ok(pages * memory::PAGE_SIZE)
+	}
+
+	/// Tells whether reading ends are attached to the pipe.
+	pub fn has_readers(&self) -> bool {
+		self.read_ends > 0
+	}
+
+	/// Tells whether writing ends are attached to the pipe.
+	pub fn has_writers(&self) -> bool {
+		self.write_ends > 0
+	}
+
+	/// Returns references to the pages holding the first `len` bytes of data, without consuming
+	/// them.
+	///
+	/// At most `max_pages` pages are returned.
+	pub fn peek_pages(&self, len: usize, max_pages: usize) -> AllocResult<Vec<PipePage>> {
+		let mut pages = Vec::new();
+		let mut remaining = len;
+		for page in self.pages.iter().take(max_pages) {
+			if remaining == 0 {
+				break;
+			}
+			let mut page = page.try_clone()?;
+			page.truncate(remaining);
+			remaining -= page.len();
+			pages.push(page)?;
+		}
+		Ok(pages)
+	}
+
+	/// Consumes the first `len` bytes of data.
+	pub fn consume(&mut self, len: usize) {
+		let mut remaining = len;
+		while remaining > 0 {
+			let Some(page) = self.pages.first_mut() else {
+				break;
+			};
+			let l = min(remaining, page.len());
+			page.consume(l);
+			remaining -= l;
+			if page.is_empty() {
+				self.pages.remove(0);
+			}
+		}
+		self.block_handler.wake_processes(io::POLLOUT);
+	}
+
+	/// Removes the pages holding the first `len` bytes of data and returns them.
+	///
+	/// At most `max_pages` pages are removed.
+	pub fn take_pages(&mut self, len: usize, max_pages: usize) -> AllocResult<Vec<PipePage>> {
+		let pages = self.peek_pages(len, max_pages)?;
+		self.consume(pages.iter().map(PipePage::len).sum());
+		Ok(pages)
+	}
+
+	/// Appends the page `page` to the buffer, regardless of the capacity.
+	///
+	/// If no reading end is attached to the pipe, the function returns `EPIPE`.
+	pub fn push_page(&mut self, page: PipePage) -> EResult<()> {
+		if !self.has_readers() {
+			return Err(errno!(EPIPE));
+		}
+		if !page.is_empty() {
+			self.pages.push(page)?;
+			self.block_handler.wake_processes(io::POLLIN);
+		}
+		Ok(())
 	}
 }
 
 impl TryDefault for PipeBuffer {
 	fn try_default() -> Result<Self, Self::Error> {
 		Ok(Self {
-			buffer: RingBuffer::new(crate::vec![0; limits::PIPE_BUF]?),
+			pages: Vec::new(),
+			max_pages: PIPE_PAGES,
 
 			read_ends: 0,
 			write_ends: 0,
@@ -61,7 +352,7 @@ impl TryDefault for PipeBuffer {
 
 impl Buffer for PipeBuffer {
 	fn get_capacity(&self) -> usize {
-		self.buffer.get_size()
+		self.max_pages * memory::PAGE_SIZE
 	}
 
 	fn increment_open(&mut self, read: bool, write: bool) {
@@ -96,6 +387,15 @@ impl Buffer for PipeBuffer {
 		self.block_handler.add_waiting_process(proc, mask)
 	}
 
+	fn set_async(
+		&mut self,
+		fd: c_int,
+		owner: &Arc<IntMutex<FileOwner>>,
+		on: bool,
+	) -> Result<(), Errno> {
+		Ok(self.block_handler.set_async(fd, owner, on)?)
+	}
+
 	fn ioctl(
 		&mut self,
 		mem_space: Arc<IntMutex<MemSpace>>,
@@ -106,7 +406,7 @@ impl Buffer for PipeBuffer {
 			ioctl::FIONREAD => {
 				let mut mem_space_guard = mem_space.lock();
 				let count_ptr: SyscallPtr<c_int> = (argp as usize).into();
-				let count_ref = count_ptr
+				let mut count_ref = count_ptr
 					.get_mut(&mut mem_space_guard)?
 					.ok_or_else(|| errno!(EFAULT))?;
 				*count_ref = self.get_available_len() as _;
@@ -126,25 +426,41 @@ impl IO for PipeBuffer {
 
 	/// Note: This implemention ignores the offset.
 	fn read(&mut self, _: u64, buf: &mut [u8]) -> Result<(u64, bool), Errno> {
-		let len = self.buffer.read(buf);
+		let mut len = 0;
+		for page in self.pages.iter() {
+			if len >= buf.len() {
+				break;
+			}
+			let data = page.data();
+			let l = min(data.len(), buf.len() - len);
+			buf[len..(len + l)].copy_from_slice(&data[..l]);
+			len += l;
+		}
+		self.consume(len);
 		let eof = self.write_ends == 0 && self.get_data_len() == 0;
 
-		self.block_handler.wake_processes(io::POLLOUT);
-
 		Ok((len as _, eof))
 	}
 
 	/// Note: This implemention ignores the offset.
 	fn write(&mut self, _: u64, buf: &[u8]) -> Result<u64, Errno> {
-		if self.read_ends > 0 {
-			let len = self.buffer.write(buf);
-
-			self.block_handler.wake_processes(io::POLLIN);
+		if !self.has_readers() {
+			return Err(errno!(EPIPE));
+		}
 
-			Ok(len as _)
-		} else {
-			Err(errno!(EPIPE))
+		let mut len = 0;
+		if let Some(page) = self.pages.last_mut().filter(|page| page.is_writable()) {
+			len += page.append(buf);
 		}
+		while len < buf.len() && self.get_free_pages() > 0 {
+			let mut page = PipePage::new()?;
+			len += page.append(&buf[len..]);
+			self.pages.push(page)?;
+		}
+
+		self.block_handler.wake_processes(io::POLLIN);
+
+		Ok(len as _)
 	}
 
 	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
@@ -163,3 +479,13 @@ impl IO for PipeBuffer {
 		Ok(result)
 	}
 }
+
+/// Executes the closure `f` with the pipe buffer of the file at location `loc`.
+///
+/// If the location doesn't match a pipe, the function returns `None`.
+pub fn pipe_do<T, F: FnOnce(&mut PipeBuffer) -> T>(loc: &FileLocation, f: F) -> Option<T> {
+	let buff_mutex = buffer::get(loc)?;
+	let mut buff = buff_mutex.lock();
+	let pipe = (&mut *buff as &mut dyn Any).downcast_mut::<PipeBuffer>()?;
+	Some(f(pipe))
+}
@@ -4,6 +4,7 @@ use super::Buffer;
 use crate::errno::AllocResult;
 use crate::errno::Errno;
 use crate::file::buffer::BlockHandler;
+use crate::file::open_file::FileOwner;
 use crate::net::osi;
 use crate::net::SocketDesc;
 use crate::net::SocketDomain;
@@ -207,6 +208,15 @@ impl Buffer for Socket {
 		self.block_handler.add_waiting_process(proc, mask)
 	}
 
+	fn set_async(
+		&mut self,
+		fd: c_int,
+		owner: &Arc<IntMutex<FileOwner>>,
+		on: bool,
+	) -> Result<(), Errno> {
+		Ok(self.block_handler.set_async(fd, owner, on)?)
+	}
+
 	fn ioctl(
 		&mut self,
 		_mem_space: Arc<IntMutex<MemSpace>>,
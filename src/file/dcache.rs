@@ -0,0 +1,99 @@
+//! The dentry cache (dcache) keeps the result of the lookup of names in directories, so that
+//! path resolution doesn't have to query the filesystem for each component.
+//!
+//! An entry is identified by a mountpoint, the inode of a directory and a name. It gives either
+//! the inode of the file with this name, or tells that no such file exists (negative entry).
+//!
+//! Entries are kept only for filesystems that require caching. Other filesystems, such as the
+//! procfs, may change without going through the VFS.
+
+use crate::errno::AllocResult;
+use crate::file::INode;
+use crate::util::container::lru::LruCache;
+use crate::util::container::string::String;
+use crate::util::lock::Mutex;
+
+/// The maximum number of entries in the cache.
+const CAPACITY: usize = 4096;
+
+/// The key of an entry.
+#[derive(Eq, Hash, PartialEq)]
+struct DentryKey {
+	/// The ID of the mountpoint.
+	mountpoint_id: u32,
+	/// The inode of the parent directory.
+	parent: INode,
+	/// The name of the entry.
+	name: String,
+}
+
+/// The cache, mapping entries to the inode of the file, or `None` for negative entries.
+static DCACHE: Mutex<LruCache<DentryKey, Option<INode>>> = Mutex::new(LruCache::new(CAPACITY));
+
+/// Looks for the entry `name` in the directory with inode `parent` on the mountpoint with ID
+/// `mountpoint_id`.
+///
+/// If the entry is not in the cache, the function returns `None`. If the entry is negative, it
+/// returns `Some(None)`.
+pub fn get(mountpoint_id: u32, parent: INode, name: &[u8]) -> Option<Option<INode>> {
+	let key = DentryKey {
+		mountpoint_id,
+		parent,
+		name: String::try_from(name).ok()?,
+	};
+	DCACHE.lock().get(&key).cloned()
+}
+
+/// Inserts the entry `name` in the directory with inode `parent` on the mountpoint with ID
+/// `mountpoint_id`.
+///
+/// `inode` is the inode of the file, or `None` if the file doesn't exist.
+pub fn insert(
+	mountpoint_id: u32,
+	parent: INode,
+	name: &[u8],
+	inode: Option<INode>,
+) -> AllocResult<()> {
+	let key = DentryKey {
+		mountpoint_id,
+		parent,
+		name: String::try_from(name)?,
+	};
+	DCACHE.lock().insert(key, inode)?;
+	Ok(())
+}
+
+/// Removes the entry `name` in the directory with inode `parent` on the mountpoint with ID
+/// `mountpoint_id`.
+///
+/// The cache is scanned rather than looked up with a key so that invalidation can never fail
+/// for lack of memory.
+pub fn invalidate(mountpoint_id: u32, parent: INode, name: &[u8]) {
+	DCACHE.lock().retain(|k, _| {
+		k.mountpoint_id != mountpoint_id || k.parent != parent || k.name.as_bytes() != name
+	});
+}
+
+/// Removes every entries referring to the inode `inode` on the mountpoint with ID
+/// `mountpoint_id`, either as a parent directory or as a file.
+///
+/// This function must be called when an inode is freed, since it may be reused later.
+pub fn invalidate_inode(mountpoint_id: u32, inode: INode) {
+	DCACHE.lock().retain(|k, v| {
+		k.mountpoint_id != mountpoint_id || (k.parent != inode && *v != Some(inode))
+	});
+}
+
+/// Removes every entries of the mountpoint with ID `mountpoint_id`.
+pub fn invalidate_mountpoint(mountpoint_id: u32) {
+	DCACHE
+		.lock()
+		.retain(|k, _| k.mountpoint_id != mountpoint_id);
+}
+
+/// Evicts at most `count` entries from the cache.
+///
+/// The function returns the number of evicted entries.
+pub fn shrink(count: usize) -> usize {
+	DCACHE.lock().shrink(count)
+}
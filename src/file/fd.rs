@@ -7,16 +7,23 @@ use crate::errno::CollectResult;
 use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file::open_file::OpenFile;
+use crate::file::open_file::OPEN_FILE_CACHE;
 use crate::limits;
+use crate::process::rlimit::RLim;
 use crate::util::container::vec::Vec;
 use crate::util::io::IO;
 use crate::util::lock::Mutex;
 use crate::util::ptr::arc::Arc;
 use core::cmp::max;
+use core::cmp::min;
 
 /// The maximum number of file descriptors that can be open system-wide at once.
 const TOTAL_MAX_FD: usize = 4294967295;
 
+/// The maximum value of the limit on the number of file descriptors of a process
+/// (`RLIMIT_NOFILE`).
+pub const NR_OPEN: u32 = 1048576;
+
 /// File descriptor flag: If set, the file descriptor is closed on successful
 /// call to `execve`.
 pub const FD_CLOEXEC: i32 = 1;
@@ -78,7 +85,7 @@ impl FileDescriptor {
 	/// - `flags` is the set of flags associated with the file descriptor
 	/// - `location` is the location of the open file the file descriptor points to
 	pub fn new(id: u32, flags: i32, open_file: OpenFile) -> EResult<Self> {
-		let open_file = Arc::new(Mutex::new(open_file))?;
+		let open_file = Arc::new_in(Mutex::new(open_file), &OPEN_FILE_CACHE)?;
 		Ok(Self {
 			id,
 			flags,
@@ -156,6 +163,8 @@ pub struct FileDescriptorTable {
 	// TODO use a BTreeMap or BTreeSet instead?
 	/// The list of file descriptors.
 	fds: Vec<FileDescriptor>,
+	/// A value one greater than the maximum ID of a new file descriptor (`RLIMIT_NOFILE`).
+	limit: u32,
 }
 
 impl FileDescriptorTable {
@@ -166,7 +175,7 @@ impl FileDescriptorTable {
 	/// `min` is the minimum value for the file descriptor to be returned.
 	fn get_available_fd(&self, min: Option<u32>) -> EResult<u32> {
 		let min = min.unwrap_or(0);
-		if min >= limits::OPEN_MAX {
+		if min >= self.limit {
 			return Err(errno!(EMFILE));
 		}
 
@@ -187,7 +196,7 @@ impl FileDescriptorTable {
 		// unwrap cannot fail because
 		let id = self.fds.last().map(|fd| fd.get_id() + 1).unwrap();
 		let id = max(id, min);
-		if id < limits::OPEN_MAX {
+		if id < self.limit {
 			Ok(id)
 		} else {
 			Err(errno!(EMFILE))
@@ -245,7 +254,7 @@ impl FileDescriptorTable {
 		let new_id = match constraint {
 			NewFDConstraint::None => self.get_available_fd(None)?,
 			NewFDConstraint::Fixed(id) => {
-				if id >= limits::OPEN_MAX {
+				if id >= self.limit {
 					return Err(errno!(EMFILE));
 				}
 				id
@@ -294,9 +303,17 @@ impl FileDescriptorTable {
 			.0?;
 		Ok(Self {
 			fds,
+			limit: self.limit,
 		})
 	}
 
+	/// Sets the limit on the IDs of new file descriptors.
+	///
+	/// File descriptors already open with a greater ID are kept.
+	pub fn set_limit(&mut self, limit: RLim) {
+		self.limit = min(limit, NR_OPEN as RLim) as _;
+	}
+
 	/// Closes the file descriptor with the ID `id`.
 	///
 	/// The function returns an Err if the file descriptor doesn't exist.
@@ -315,6 +332,7 @@ impl Default for FileDescriptorTable {
 	fn default() -> Self {
 		Self {
 			fds: Vec::new(),
+			limit: limits::OPEN_MAX,
 		}
 	}
 }
@@ -353,6 +371,18 @@ mod test {
 		assert_eq!(fd, 1);
 	}
 
+	#[test_case]
+	fn fd_limit() {
+		let mut fds = FileDescriptorTable::default();
+		fds.set_limit(2);
+		fds.create_fd(0, dummy_open_file()).unwrap();
+		fds.create_fd(0, dummy_open_file()).unwrap();
+		assert!(fds.create_fd(0, dummy_open_file()).is_err());
+		assert!(fds
+			.duplicate_fd(0, NewFDConstraint::Fixed(2), false)
+			.is_err());
+	}
+
 	#[test_case]
 	fn fd_dup() {
 		let mut fds = FileDescriptorTable::default();
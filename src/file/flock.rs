@@ -0,0 +1,92 @@
+//! `flock` locks are advisory locks placed on whole files.
+//!
+//! A lock is held by an open file description. Thus, it is shared by every file descriptors
+//! duplicated from the same description, and released when the last of them is closed.
+//!
+//! A file may be locked either by several shared locks, or by a single exclusive lock.
+
+use crate::errno::EResult;
+use crate::file::blocking::BlockHandler;
+use crate::file::FileLocation;
+use crate::process::Process;
+use crate::util::container::hashmap::HashMap;
+use crate::util::io;
+use crate::util::lock::Mutex;
+
+/// The type of a lock.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Kind {
+	/// A lock that may be held by several open file descriptions at once.
+	Shared,
+	/// A lock that may be held by only one open file description.
+	Exclusive,
+}
+
+/// The locks held on a file.
+#[derive(Default)]
+struct FileLocks {
+	/// The number of shared locks.
+	shared: usize,
+	/// Tells whether an exclusive lock is held.
+	exclusive: bool,
+
+	/// The processes waiting for a lock to be released.
+	block_handler: BlockHandler,
+}
+
+/// The locked files, by location.
+static LOCKS: Mutex<HashMap<FileLocation, FileLocks>> = Mutex::new(HashMap::new());
+
+/// Acquires a lock of type `kind` on the file at location `location`.
+///
+/// If the lock cannot be acquired because of conflicting locks, the function returns `false`. In
+/// this case, if `waiter` is given, the process is put to sleep until a lock on the file is
+/// released.
+pub fn acquire(
+	location: &FileLocation,
+	kind: Kind,
+	waiter: Option<&mut Process>,
+) -> EResult<bool> {
+	let mut locks = LOCKS.lock();
+	let file_locks = match locks.get_mut(location) {
+		Some(file_locks) => file_locks,
+		None => {
+			locks.insert(location.clone(), FileLocks::default())?;
+			locks.get_mut(location).unwrap()
+		}
+	};
+	let available = match kind {
+		Kind::Shared => !file_locks.exclusive,
+		Kind::Exclusive => !file_locks.exclusive && file_locks.shared == 0,
+	};
+	if available {
+		match kind {
+			Kind::Shared => file_locks.shared += 1,
+			Kind::Exclusive => file_locks.exclusive = true,
+		}
+		return Ok(true);
+	}
+	if let Some(proc) = waiter {
+		file_locks
+			.block_handler
+			.add_waiting_process(proc, io::POLLIN)?;
+	}
+	Ok(false)
+}
+
+/// Releases a lock of type `kind` on the file at location `location`, waking up the processes
+/// waiting for it.
+pub fn release(location: &FileLocation, kind: Kind) {
+	let mut locks = LOCKS.lock();
+	let Some(file_locks) = locks.get_mut(location) else {
+		return;
+	};
+	match kind {
+		Kind::Shared => file_locks.shared = file_locks.shared.saturating_sub(1),
+		Kind::Exclusive => file_locks.exclusive = false,
+	}
+	file_locks.block_handler.wake_processes(io::POLLIN);
+	if file_locks.shared == 0 && !file_locks.exclusive {
+		locks.remove(location);
+	}
+}
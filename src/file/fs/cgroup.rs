@@ -0,0 +1,539 @@
+//! The cgroup2 filesystem exposes the hierarchy of control groups (see
+//! [`crate::process::cgroup`]).
+//!
+//! Each directory represents a group. Creating a directory creates a child group, and removing
+//! a directory removes the group, which must neither contain processes nor have children.
+//!
+//! Each directory contains interface files allowing to read and change the group's parameters:
+//! - `cgroup.controllers`: the available controllers
+//! - `cgroup.events`: tells whether the group contains processes
+//! - `cgroup.procs`: the PIDs of the processes of the group. Writing a PID moves the process,
+//! along with its threads, to the group
+//! - `cgroup.subtree_control`: the controllers enabled for the children of the group
+//! - `cpu.max`: the maximum CPU time of the group over a period, in microseconds
+//! - `cpu.stat`: statistics about the CPU usage of the group
+//! - `cpu.weight`: the weight of the group for the distribution of CPU time
+//! - `memory.current`: the amount of memory used by the group, in bytes
+//! - `memory.events`: counters of the events of the memory controller
+//! - `memory.max`: the maximum amount of memory the group can use, in bytes
+//!
+//! Limits are not available on the root group.
+//!
+//! Since the hierarchy is global, the filesystem holds no state: the inode of a file is made of
+//! the ID of its group and the index of the file.
+
+use super::Filesystem;
+use super::FilesystemType;
+use super::MountOptions;
+use super::Statfs;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::path::Path;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::DirEntry;
+use crate::file::File;
+use crate::file::FileContent;
+use crate::file::FileLocation;
+use crate::file::FileType;
+use crate::file::INode;
+use crate::file::Mode;
+use crate::memory;
+use crate::process;
+use crate::process::cgroup;
+use crate::process::cgroup::CGroup;
+use crate::process::Process;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use core::cmp::min;
+use core::str;
+use core::sync::atomic::Ordering;
+
+/// The number of bits of an inode holding the index of the file in its group's directory.
+const FILE_BITS: u32 = 4;
+/// The maximum length of a name in the filesystem.
+const MAX_NAME_LEN: usize = 255;
+
+/// An interface file of a group.
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Interface {
+	/// `cgroup.controllers`
+	Controllers,
+	/// `cgroup.events`
+	Events,
+	/// `cgroup.procs`
+	Procs,
+	/// `cgroup.subtree_control`
+	SubtreeControl,
+	/// `cpu.max`
+	CpuMax,
+	/// `cpu.stat`
+	CpuStat,
+	/// `cpu.weight`
+	CpuWeight,
+	/// `memory.current`
+	MemoryCurrent,
+	/// `memory.events`
+	MemoryEvents,
+	/// `memory.max`
+	MemoryMax,
+}
+
+/// The interface files with their names. The index of a file is its position in the list plus
+/// one, the index zero being the directory itself.
+const INTERFACES: &[(&[u8], Interface)] = &[
+	(b"cgroup.controllers", Interface::Controllers),
+	(b"cgroup.events", Interface::Events),
+	(b"cgroup.procs", Interface::Procs),
+	(b"cgroup.subtree_control", Interface::SubtreeControl),
+	(b"cpu.max", Interface::CpuMax),
+	(b"cpu.stat", Interface::CpuStat),
+	(b"cpu.weight", Interface::CpuWeight),
+	(b"memory.current", Interface::MemoryCurrent),
+	(b"memory.events", Interface::MemoryEvents),
+	(b"memory.max", Interface::MemoryMax),
+];
+
+impl Interface {
+	/// Tells whether the file exists in the directory of the group `group`.
+	fn exists_in(self, group: &CGroup) -> bool {
+		!group.is_root()
+			|| matches!(
+				self,
+				Self::Controllers | Self::Procs | Self::SubtreeControl | Self::CpuStat
+			)
+	}
+
+	/// Returns the permissions of the file.
+	fn get_mode(self) -> Mode {
+		match self {
+			Self::Procs
+			| Self::SubtreeControl
+			| Self::CpuMax
+			| Self::CpuWeight
+			| Self::MemoryMax => 0o644,
+			_ => 0o444,
+		}
+	}
+
+	/// Returns the content of the file for the group `group`.
+	fn read(self, group: &CGroup) -> EResult<String> {
+		let content = match self {
+			Self::Controllers => {
+				let mut s = String::new();
+				for (i, name) in cgroup::CONTROLLERS.iter().enumerate() {
+					if i > 0 {
+						s.push(b' ')?;
+					}
+					s.push_str(name)?;
+				}
+				s.push(b'\n')?;
+				s
+			}
+			Self::Events => {
+				crate::format!("populated {}\nfrozen 0\n", group.is_populated() as u8)?
+			}
+			Self::Procs => {
+				let mut s = String::new();
+				let mut sched = process::get_scheduler().lock();
+				for (pid, proc_mutex) in sched.iter_process() {
+					let proc = proc_mutex.lock();
+					if proc.tgid == *pid && proc.get_cgroup().get_id() == group.get_id() {
+						s.push_str(crate::format!("{pid}\n")?)?;
+					}
+				}
+				s
+			}
+			Self::SubtreeControl => {
+				let enabled = group.get_subtree_control();
+				let mut s = String::new();
+				for (_, name) in cgroup::CONTROLLERS
+					.iter()
+					.enumerate()
+					.filter(|(i, _)| enabled & (1 << i) != 0)
+				{
+					if !s.is_empty() {
+						s.push(b' ')?;
+					}
+					s.push_str(name)?;
+				}
+				s.push(b'\n')?;
+				s
+			}
+			Self::CpuMax => match group.get_cpu_max() {
+				(Some(quota), period) => crate::format!("{quota} {period}\n")?,
+				(None, period) => crate::format!("max {period}\n")?,
+			},
+			Self::CpuStat => {
+				let (usage, nr_periods, nr_throttled) = group.get_cpu_stat();
+				crate::format!(
+					"usage_usec {}\nnr_periods {nr_periods}\nnr_throttled {nr_throttled}\n",
+					usage / 1000
+				)?
+			}
+			Self::CpuWeight => crate::format!("{}\n", group.get_cpu_weight())?,
+			Self::MemoryCurrent => crate::format!(
+				"{}\n",
+				group.get_memory_current() as u64 * memory::PAGE_SIZE as u64
+			)?,
+			Self::MemoryEvents => {
+				let events = &group.memory_events;
+				crate::format!(
+					"low 0\nhigh 0\nmax {}\noom {}\noom_kill {}\n",
+					events.max.load(Ordering::Relaxed),
+					events.oom.load(Ordering::Relaxed),
+					events.oom_kill.load(Ordering::Relaxed)
+				)?
+			}
+			Self::MemoryMax => match group.get_memory_max() {
+				usize::MAX => crate::format!("max\n")?,
+				max => crate::format!("{}\n", max as u64 * memory::PAGE_SIZE as u64)?,
+			},
+		};
+		Ok(content)
+	}
+
+	/// Writes the value `val` to the file for the group `group`.
+	fn write(self, group: &Arc<CGroup>, val: &str) -> EResult<()> {
+		let val = val.trim();
+		match self {
+			Self::Procs => {
+				let pid = val.parse().map_err(|_| errno!(EINVAL))?;
+				let tgid = if pid == 0 {
+					Process::current_assert().lock().tgid
+				} else {
+					Process::get_by_pid(pid)
+						.ok_or_else(|| errno!(ESRCH))?
+						.lock()
+						.tgid
+				};
+				// Move every threads of the process
+				let mut threads = Vec::new();
+				{
+					let mut sched = process::get_scheduler().lock();
+					for (_, proc_mutex) in sched.iter_process() {
+						if proc_mutex.lock().tgid == tgid {
+							threads.push(proc_mutex.clone())?;
+						}
+					}
+				}
+				for proc_mutex in threads {
+					proc_mutex.lock().set_cgroup(group.clone())?;
+				}
+			}
+			Self::SubtreeControl => {
+				let mut enabled = group.get_subtree_control();
+				for token in val.split_whitespace() {
+					let (enable, name) = match token.as_bytes() {
+						[b'+', name @ ..] => (true, name),
+						[b'-', name @ ..] => (false, name),
+						_ => return Err(errno!(EINVAL)),
+					};
+					let i = cgroup::CONTROLLERS
+						.iter()
+						.position(|c| *c == name)
+						.ok_or_else(|| errno!(EINVAL))?;
+					if enable {
+						enabled |= 1 << i;
+					} else {
+						enabled &= !(1 << i);
+					}
+				}
+				group.set_subtree_control(enabled);
+			}
+			Self::CpuMax => {
+				let mut values = val.split_whitespace();
+				let quota = cgroup::parse_max(values.next().ok_or_else(|| errno!(EINVAL))?)?;
+				let period = match values.next() {
+					Some(period) => period.parse().map_err(|_| errno!(EINVAL))?,
+					None => group.get_cpu_max().1,
+				};
+				group.set_cpu_max(quota, period)?;
+			}
+			Self::CpuWeight => {
+				let weight = val.parse().map_err(|_| errno!(EINVAL))?;
+				group.set_cpu_weight(weight)?;
+			}
+			Self::MemoryMax => {
+				let max = cgroup::parse_max(val)?;
+				group.set_memory_max(cgroup::bytes_to_pages(max));
+			}
+			_ => return Err(errno!(EINVAL)),
+		}
+		Ok(())
+	}
+}
+
+/// Returns the inode of the file at index `index` in the directory of the group with ID `id`.
+fn to_inode(id: u32, index: usize) -> INode {
+	((id as INode) << FILE_BITS) | index as INode
+}
+
+/// Returns the group and the interface file corresponding to the inode `inode`.
+///
+/// If the inode corresponds to the group's directory, the interface is `None`.
+fn from_inode(inode: INode) -> EResult<(Arc<CGroup>, Option<Interface>)> {
+	let group = cgroup::get((inode >> FILE_BITS) as _).ok_or_else(|| errno!(ENOENT))?;
+	let index = (inode & ((1 << FILE_BITS) - 1)) as usize;
+	if index == 0 {
+		return Ok((group, None));
+	}
+	let (_, interface) = INTERFACES.get(index - 1).ok_or_else(|| errno!(ENOENT))?;
+	if !interface.exists_in(&group) {
+		return Err(errno!(ENOENT));
+	}
+	Ok((group, Some(*interface)))
+}
+
+/// Returns the group corresponding to the directory with inode `inode`.
+fn get_dir(inode: INode) -> EResult<Arc<CGroup>> {
+	match from_inode(inode)? {
+		(group, None) => Ok(group),
+		_ => Err(errno!(ENOTDIR)),
+	}
+}
+
+/// Structure representing the cgroup2 filesystem.
+pub struct CGroupFS {
+	/// Tells whether the filesystem is readonly.
+	readonly: bool,
+}
+
+impl Filesystem for CGroupFS {
+	fn get_name(&self) -> &[u8] {
+		b"cgroup2"
+	}
+
+	fn is_readonly(&self) -> bool {
+		self.readonly
+	}
+
+	fn must_cache(&self) -> bool {
+		false
+	}
+
+	fn get_stat(&self, _io: &mut dyn IO) -> Result<Statfs, Errno> {
+		Ok(Statfs {
+			f_type: 0,
+			f_bsize: memory::PAGE_SIZE as _,
+			f_blocks: 0,
+			f_bfree: 0,
+			f_bavail: 0,
+			f_files: 0,
+			f_ffree: 0,
+			f_fsid: Default::default(),
+			f_namelen: MAX_NAME_LEN as _,
+			f_frsize: 0,
+			f_flags: 0,
+		})
+	}
+
+	fn get_root_inode(&self, _io: &mut dyn IO) -> Result<INode, Errno> {
+		Ok(to_inode(cgroup::get_root()?.get_id(), 0))
+	}
+
+	fn get_inode(
+		&mut self,
+		io: &mut dyn IO,
+		parent: Option<INode>,
+		name: &[u8],
+	) -> Result<INode, Errno> {
+		let parent = match parent {
+			Some(parent) => parent,
+			None => self.get_root_inode(io)?,
+		};
+		let group = get_dir(parent)?;
+		match name {
+			b"." => return Ok(parent),
+			b".." => {
+				let id = group.get_parent().unwrap_or(&group).get_id();
+				return Ok(to_inode(id, 0));
+			}
+			_ => {}
+		}
+		let interface = INTERFACES
+			.iter()
+			.enumerate()
+			.find(|(_, (n, i))| *n == name && i.exists_in(&group));
+		if let Some((index, _)) = interface {
+			return Ok(to_inode(group.get_id(), index + 1));
+		}
+		let child = group.get_child(name).ok_or_else(|| errno!(ENOENT))?;
+		Ok(to_inode(child.get_id(), 0))
+	}
+
+	fn load_file(&mut self, _io: &mut dyn IO, inode: INode, name: String) -> Result<File, Errno> {
+		let location = FileLocation::Filesystem {
+			mountpoint_id: 0, // dummy value to be replaced
+			inode,
+		};
+		let (group, interface) = from_inode(inode)?;
+		if let Some(interface) = interface {
+			return File::new(
+				name,
+				0,
+				0,
+				interface.get_mode(),
+				location,
+				FileContent::Regular,
+			);
+		}
+
+		let mut entries = HashMap::new();
+		let parent_id = group.get_parent().unwrap_or(&group).get_id();
+		for (name, inode) in [(b".".as_slice(), inode), (b"..", to_inode(parent_id, 0))] {
+			entries.insert(
+				name.try_into()?,
+				DirEntry {
+					inode,
+					entry_type: FileType::Directory,
+				},
+			)?;
+		}
+		for (i, (name, interface)) in INTERFACES.iter().enumerate() {
+			if interface.exists_in(&group) {
+				entries.insert(
+					(*name).try_into()?,
+					DirEntry {
+						inode: to_inode(group.get_id(), i + 1),
+						entry_type: FileType::Regular,
+					},
+				)?;
+			}
+		}
+		let children = group.list_children()?;
+		let links = 2 + children.len();
+		for (name, id) in children {
+			entries.insert(
+				name,
+				DirEntry {
+					inode: to_inode(id, 0),
+					entry_type: FileType::Directory,
+				},
+			)?;
+		}
+
+		let mut file = File::new(name, 0, 0, 0o755, location, FileContent::Directory(entries))?;
+		file.set_hard_links_count(links as _);
+		Ok(file)
+	}
+
+	fn is_empty_dir(&mut self, _io: &mut dyn IO, inode: INode) -> Result<bool, Errno> {
+		// Interface files do not prevent the removal of a group
+		Ok(get_dir(inode)?.list_children()?.is_empty())
+	}
+
+	fn add_file(
+		&mut self,
+		io: &mut dyn IO,
+		parent_inode: INode,
+		name: String,
+		_uid: Uid,
+		_gid: Gid,
+		_mode: Mode,
+		content: FileContent,
+	) -> Result<File, Errno> {
+		if !matches!(content, FileContent::Directory(_)) {
+			return Err(errno!(EACCES));
+		}
+		let parent = get_dir(parent_inode)?;
+		if INTERFACES.iter().any(|(n, _)| *n == name.as_bytes()) {
+			return Err(errno!(EEXIST));
+		}
+		let group = CGroup::create_child(&parent, name.as_bytes())?;
+		self.load_file(io, to_inode(group.get_id(), 0), name)
+	}
+
+	fn add_link(
+		&mut self,
+		_io: &mut dyn IO,
+		_parent_inode: INode,
+		_name: &[u8],
+		_inode: INode,
+	) -> Result<(), Errno> {
+		Err(errno!(EACCES))
+	}
+
+	fn update_inode(&mut self, _io: &mut dyn IO, _file: &File) -> Result<(), Errno> {
+		Ok(())
+	}
+
+	fn remove_file(
+		&mut self,
+		_io: &mut dyn IO,
+		parent_inode: INode,
+		name: &[u8],
+	) -> Result<u16, Errno> {
+		let parent = get_dir(parent_inode)?;
+		if INTERFACES.iter().any(|(n, _)| *n == name) {
+			return Err(errno!(EPERM));
+		}
+		parent.remove_child(name)?;
+		Ok(0)
+	}
+
+	fn read_node(
+		&mut self,
+		_io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		buf: &mut [u8],
+	) -> Result<u64, Errno> {
+		let (group, Some(interface)) = from_inode(inode)? else {
+			return Err(errno!(EISDIR));
+		};
+		let content = interface.read(&group)?;
+
+		let content_bytes = content.as_bytes();
+		let off = min(off, content_bytes.len() as u64) as usize;
+		let len = min(content_bytes.len() - off, buf.len());
+		buf[..len].copy_from_slice(&content_bytes[off..(off + len)]);
+		Ok(len as _)
+	}
+
+	fn write_node(
+		&mut self,
+		_io: &mut dyn IO,
+		inode: INode,
+		_off: u64,
+		buf: &[u8],
+	) -> Result<(), Errno> {
+		let (group, Some(interface)) = from_inode(inode)? else {
+			return Err(errno!(EISDIR));
+		};
+		if interface.get_mode() & 0o200 == 0 {
+			return Err(errno!(EACCES));
+		}
+		let val = str::from_utf8(buf).map_err(|_| errno!(EINVAL))?;
+		interface.write(&group, val)
+	}
+}
+
+/// Structure representing the cgroup2 filesystem type.
+pub struct CGroupFsType {}
+
+impl FilesystemType for CGroupFsType {
+	fn get_name(&self) -> &'static [u8] {
+		b"cgroup2"
+	}
+
+	fn detect(&self, _io: &mut dyn IO) -> Result<bool, Errno> {
+		Ok(false)
+	}
+
+	fn load_filesystem(
+		&self,
+		_io: &mut dyn IO,
+		_mountpath: Path,
+		readonly: bool,
+		_options: &MountOptions,
+	) -> Result<Arc<Mutex<dyn Filesystem>>, Errno> {
+		Ok(Arc::new(Mutex::new(CGroupFS {
+			readonly,
+		}))?)
+	}
+}
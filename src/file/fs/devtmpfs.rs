@@ -0,0 +1,161 @@
+//! devtmpfs is a tmpfs in which the kernel creates a file for each registered device.
+//!
+//! Device files appear and disappear as devices are registered and unregistered, without
+//! userspace having to create them with `mknod`.
+//!
+//! There is only one instance of the filesystem, shared by every mountpoints, and populated even
+//! before being mounted. It is mounted on [`MOUNT_PATH`] at boot.
+
+use super::tmp::TmpFS;
+use super::Filesystem;
+use super::FilesystemType;
+use super::MountOptions;
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::path::Path;
+use crate::file::FileContent;
+use crate::file::INode;
+use crate::file::Mode;
+use crate::util::container::hashmap::HashMap;
+use crate::util::io::DummyIO;
+use crate::util::io::IO;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use crate::util::TryClone;
+
+/// The path at which the filesystem is mounted at boot. The paths of device files are located
+/// under it.
+pub const MOUNT_PATH: &[u8] = b"/dev";
+
+/// The maximum amount of memory the filesystem can use in bytes.
+const MAX_SIZE: usize = 16 * 1024 * 1024;
+
+/// The instance of the filesystem. Initialized on first use.
+static DEVTMPFS: Mutex<Option<Arc<Mutex<TmpFS>>>> = Mutex::new(None);
+
+/// Returns the instance of the filesystem, initializing it if necessary.
+fn get() -> EResult<Arc<Mutex<TmpFS>>> {
+	let mut devtmpfs = DEVTMPFS.lock();
+	if let Some(fs) = &*devtmpfs {
+		return Ok(fs.clone());
+	}
+
+	let fs = Arc::new(Mutex::new(TmpFS::new(MAX_SIZE, 0o777, 0, 0, false)?))?;
+	*devtmpfs = Some(fs.clone());
+	Ok(fs)
+}
+
+/// Returns the path of `path` relative to [`MOUNT_PATH`].
+///
+/// If `path` is not located under [`MOUNT_PATH`], the function returns an error.
+fn get_relative(path: &Path) -> EResult<Path> {
+	let mount_path = Path::from_str(MOUNT_PATH, false)?;
+	if !path.begins_with(&mount_path)
+		|| path.get_elements_count() <= mount_path.get_elements_count()
+	{
+		return Err(errno!(EINVAL));
+	}
+	path.range_from(mount_path.get_elements_count()..)
+}
+
+/// Returns the inode of the parent directory of the file at `path`, relative to the root of the
+/// filesystem.
+///
+/// If `create` is `true`, missing directories are created. Else, the function returns `None` if
+/// a directory is missing.
+fn get_parent(
+	fs: &mut TmpFS,
+	io: &mut dyn IO,
+	path: &Path,
+	create: bool,
+) -> EResult<Option<INode>> {
+	let mut parent = fs.get_root_inode(io)?;
+	for i in 0..(path.get_elements_count() - 1) {
+		parent = match fs.get_inode(io, Some(parent), path[i].as_bytes()) {
+			Ok(inode) => inode,
+
+			Err(e) if e.as_int() == errno::ENOENT && create => fs
+				.add_file(
+					io,
+					parent,
+					path[i].try_clone()?,
+					0,
+					0,
+					0o755,
+					FileContent::Directory(HashMap::new()),
+				)?
+				.get_location()
+				.get_inode(),
+
+			Err(e) if e.as_int() == errno::ENOENT => return Ok(None),
+			Err(e) => return Err(e),
+		};
+	}
+	Ok(Some(parent))
+}
+
+/// Creates the device file at `path`, with the given `mode` and `content`.
+///
+/// Missing parent directories are created.
+///
+/// If the file already exists, the function does nothing.
+pub fn add_node(path: &Path, mode: Mode, content: FileContent) -> EResult<()> {
+	let path = get_relative(path)?;
+	let name = path.last().unwrap();
+
+	let fs_mutex = get()?;
+	let mut fs = fs_mutex.lock();
+	let io = &mut DummyIO {};
+
+	let parent = get_parent(&mut fs, io, &path, true)?.unwrap();
+	if fs.get_inode(io, Some(parent), name.as_bytes()).is_ok() {
+		return Ok(());
+	}
+	fs.add_file(io, parent, name.try_clone()?, 0, 0, mode, content)?;
+
+	Ok(())
+}
+
+/// Removes the device file at `path`.
+///
+/// If the file doesn't exist, the function does nothing.
+pub fn remove_node(path: &Path) -> EResult<()> {
+	let path = get_relative(path)?;
+	let name = path.last().unwrap();
+
+	let fs_mutex = get()?;
+	let mut fs = fs_mutex.lock();
+	let io = &mut DummyIO {};
+
+	let Some(parent) = get_parent(&mut fs, io, &path, false)? else {
+		return Ok(());
+	};
+	match fs.remove_file(io, parent, name.as_bytes()) {
+		Err(e) if e.as_int() != errno::ENOENT => Err(e),
+		_ => Ok(()),
+	}
+}
+
+/// Structure representing the devtmpfs file system type.
+pub struct DevTmpFsType {}
+
+impl FilesystemType for DevTmpFsType {
+	fn get_name(&self) -> &'static [u8] {
+		b"devtmpfs"
+	}
+
+	fn detect(&self, _io: &mut dyn IO) -> Result<bool, Errno> {
+		Ok(false)
+	}
+
+	fn load_filesystem(
+		&self,
+		_io: &mut dyn IO,
+		_mountpath: Path,
+		_readonly: bool,
+		_options: &MountOptions,
+	) -> Result<Arc<Mutex<dyn Filesystem>>, Errno> {
+		Ok(get()? as _)
+	}
+}
@@ -0,0 +1,388 @@
+//! A directory of exFAT is a list of 32 bytes entries.
+//!
+//! A file is described by an entry set: a File entry, followed by a Stream Extension entry and
+//! one or more File Name entries. The File entry holds the attributes and timestamps of the file,
+//! and the Stream Extension entry holds the location and size of its data. The integrity of the
+//! set is protected by a checksum stored in the File entry.
+
+use super::Stream;
+use crate::errno::AllocResult;
+use crate::time::unit::Timespec;
+use crate::util::container::vec::Vec;
+use crate::util::math;
+use core::cmp::min;
+
+/// The size of a directory entry in bytes.
+pub const ENTRY_SIZE: usize = 32;
+
+/// A raw directory entry.
+pub type RawEntry = [u8; ENTRY_SIZE];
+
+/// Entry type: end of the directory. Every following entries are unused as well.
+pub const TYPE_END: u8 = 0x00;
+/// Entry type bit: the entry is in use.
+pub const TYPE_IN_USE: u8 = 0x80;
+/// Entry type: Allocation Bitmap.
+pub const TYPE_BITMAP: u8 = 0x81;
+/// Entry type: Up-case Table.
+pub const TYPE_UPCASE: u8 = 0x82;
+/// Entry type: File.
+pub const TYPE_FILE: u8 = 0x85;
+/// Entry type: Stream Extension.
+pub const TYPE_STREAM: u8 = 0xc0;
+/// Entry type: File Name.
+pub const TYPE_NAME: u8 = 0xc1;
+
+/// File attribute: the file is read-only.
+pub const ATTR_READ_ONLY: u16 = 0x01;
+/// File attribute: the file is a directory.
+pub const ATTR_DIRECTORY: u16 = 0x10;
+/// File attribute: the file has been modified since the last backup.
+pub const ATTR_ARCHIVE: u16 = 0x20;
+
+/// Stream flag: clusters can be allocated to the stream. Always set.
+const STREAM_ALLOCATION_POSSIBLE: u8 = 0x01;
+/// Stream flag: the clusters of the stream are contiguous and the FAT is not used.
+const STREAM_NO_FAT_CHAIN: u8 = 0x02;
+
+/// The number of UTF-16 code units in a File Name entry.
+const NAME_CHARS_PER_ENTRY: usize = 15;
+/// The maximum length of a name, in UTF-16 code units.
+pub const MAX_NAME_LEN: usize = 255;
+/// The maximum number of secondary entries in a File entry set.
+const MAX_SECONDARY: usize = 1 + MAX_NAME_LEN.div_ceil(NAME_CHARS_PER_ENTRY);
+
+/// UTC offset flag: the offset is valid.
+const UTC_OFFSET_VALID: u8 = 0x80;
+/// The exFAT timestamp of 1980-01-01, the earliest representable date.
+const MIN_TIMESTAMP: u32 = (1 << 21) | (1 << 16);
+
+/// Returns the number of days between the Unix epoch and the given date of the Gregorian
+/// calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+	let year = if month <= 2 { year - 1 } else { year };
+	let era = year.div_euclid(400);
+	let year_of_era = year - era * 400;
+	let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+	era * 146097 + day_of_era - 719468
+}
+
+/// Returns the date of the Gregorian calendar `(year, month, day)` which is `days` days after
+/// the Unix epoch.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+	let days = days + 719468;
+	let era = days.div_euclid(146097);
+	let day_of_era = days - era * 146097;
+	let year_of_era =
+		(day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let mp = (5 * day_of_year + 2) / 153;
+	let day = day_of_year - (153 * mp + 2) / 5 + 1;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 };
+	let year = year_of_era + era * 400;
+	(if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+/// Converts the given exFAT timestamp to a timestamp.
+///
+/// Arguments:
+/// - `ts` is the timestamp, in the format of MS-DOS.
+/// - `inc` is the number of 10 milliseconds intervals to add to the timestamp.
+/// - `utc_off` is the offset from UTC, in intervals of 15 minutes.
+fn to_timestamp(ts: u32, inc: u8, utc_off: u8) -> Timespec {
+	let year = 1980 + (ts >> 25) as i64;
+	let month = ((ts >> 21) & 0xf) as i64;
+	let day = ((ts >> 16) & 0x1f) as i64;
+	let hour = ((ts >> 11) & 0x1f) as i64;
+	let minute = ((ts >> 5) & 0x3f) as i64;
+	let inc = min(inc, 199) as i64;
+	let sec = (ts & 0x1f) as i64 * 2 + inc / 100;
+	let mut secs = days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + sec;
+	if utc_off & UTC_OFFSET_VALID != 0 {
+		// The offset is a signed 7 bits value
+		let off = ((utc_off << 1) as i8 >> 1) as i64;
+		secs -= off * 15 * 60;
+	}
+	if secs < 0 {
+		return Timespec::default();
+	}
+	Timespec {
+		tv_sec: secs as _,
+		tv_nsec: ((inc % 100) * 10_000_000) as _,
+	}
+}
+
+/// Converts the given timestamp to an exFAT timestamp, in UTC.
+///
+/// The function returns the timestamp in the format of MS-DOS, the number of 10 milliseconds
+/// intervals to add to it and the offset from UTC.
+fn from_timestamp(ts: Timespec) -> (u32, u8, u8) {
+	let centis = (ts.tv_nsec / 10_000_000) as u32;
+	let ts = ts.tv_sec;
+	let (year, month, day) = civil_from_days((ts / 86400) as i64);
+	if year < 1980 {
+		return (MIN_TIMESTAMP, 0, UTC_OFFSET_VALID);
+	}
+	let year = min(year - 1980, 127) as u32;
+	let secs = (ts % 86400) as u32;
+	let ts = (year << 25)
+		| ((month as u32) << 21)
+		| ((day as u32) << 16)
+		| ((secs / 3600) << 11)
+		| (((secs / 60) % 60) << 5)
+		| ((secs % 60) / 2);
+	(ts, ((secs % 2) * 100 + centis) as _, UTC_OFFSET_VALID)
+}
+
+/// Computes the hash of the name `name`, which must be converted to uppercase.
+pub fn name_hash(name: &[u16]) -> u16 {
+	name.iter()
+		.flat_map(|c| c.to_le_bytes())
+		.fold(0, |hash: u16, b| hash.rotate_right(1).wrapping_add(b as _))
+}
+
+/// The set of entries describing a file.
+pub struct EntrySet {
+	/// The raw entries, beginning with the File entry.
+	entries: Vec<RawEntry>,
+}
+
+impl EntrySet {
+	/// Creates a new set, with an empty stream.
+	///
+	/// Arguments:
+	/// - `name` is the name of the file.
+	/// - `hash` is the hash of the name, in uppercase.
+	/// - `attributes` are the attributes of the file.
+	/// - `ts` is the timestamp of creation of the file.
+	pub fn new(name: &[u16], hash: u16, attributes: u16, ts: Timespec) -> AllocResult<Self> {
+		let mut file = [0; ENTRY_SIZE];
+		file[0] = TYPE_FILE;
+		let mut stream = [0; ENTRY_SIZE];
+		stream[0] = TYPE_STREAM;
+		stream[1] = STREAM_ALLOCATION_POSSIBLE;
+
+		let mut set = Self {
+			entries: Vec::new(),
+		};
+		set.entries.push(file)?;
+		set.entries.push(stream)?;
+		set.set_name(name, hash)?;
+		set.set_attributes(attributes);
+		set.set_time(8, Some(20), 22, ts);
+		set.set_mtime(ts);
+		set.set_atime(ts);
+		Ok(set)
+	}
+
+	/// Parses the entry set at the beginning of `entries`.
+	///
+	/// If the set is invalid or incomplete, the function returns `None`.
+	pub fn parse(entries: &[RawEntry]) -> AllocResult<Option<Self>> {
+		let Some(file) = entries.first() else {
+			return Ok(None);
+		};
+		let count = file[1] as usize;
+		if file[0] != TYPE_FILE || !(2..=MAX_SECONDARY).contains(&count) {
+			return Ok(None);
+		}
+		let Some(set) = entries.get(..=count) else {
+			return Ok(None);
+		};
+		let name_len = set[1][3] as usize;
+		let name_entries = math::ceil_div(name_len, NAME_CHARS_PER_ENTRY);
+		if set[1][0] != TYPE_STREAM || name_len == 0 || count < 1 + name_entries {
+			return Ok(None);
+		}
+		if set[2..(2 + name_entries)].iter().any(|e| e[0] != TYPE_NAME) {
+			return Ok(None);
+		}
+
+		let set = Self {
+			entries: Vec::from_slice(set)?,
+		};
+		if set.checksum() != u16::from_le_bytes([file[2], file[3]]) {
+			return Ok(None);
+		}
+		Ok(Some(set))
+	}
+
+	/// Returns the raw entries of the set.
+	pub fn get_entries(&self) -> &[RawEntry] {
+		self.entries.as_slice()
+	}
+
+	/// Returns the number of entries in the set.
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	/// Computes the checksum of the set.
+	fn checksum(&self) -> u16 {
+		self.entries
+			.iter()
+			.flatten()
+			.enumerate()
+			// The checksum itself is skipped
+			.filter(|(i, _)| *i != 2 && *i != 3)
+			.fold(0, |sum: u16, (_, b)| {
+				sum.rotate_right(1).wrapping_add(*b as _)
+			})
+	}
+
+	/// Updates the checksum of the set. This function must be called before writing the set.
+	pub fn update_checksum(&mut self) {
+		let checksum = self.checksum();
+		self.entries[0][2..4].copy_from_slice(&checksum.to_le_bytes());
+	}
+
+	/// Returns the name of the file.
+	pub fn get_name(&self) -> AllocResult<Vec<u16>> {
+		let len = self.entries[1][3] as usize;
+		let mut name = Vec::with_capacity(len)?;
+		for e in &self.entries.as_slice()[2..] {
+			for c in e[2..].chunks_exact(2).take(len - name.len()) {
+				name.push(u16::from_le_bytes([c[0], c[1]]))?;
+			}
+		}
+		Ok(name)
+	}
+
+	/// Returns the hash of the name of the file.
+	pub fn get_name_hash(&self) -> u16 {
+		u16::from_le_bytes([self.entries[1][4], self.entries[1][5]])
+	}
+
+	/// Replaces the name of the file.
+	///
+	/// Arguments:
+	/// - `name` is the new name. Its length must not exceed [`MAX_NAME_LEN`].
+	/// - `hash` is the hash of the name, in uppercase.
+	pub fn set_name(&mut self, name: &[u16], hash: u16) -> AllocResult<()> {
+		self.entries.truncate(2);
+		for chunk in name.chunks(NAME_CHARS_PER_ENTRY) {
+			let mut e = [0; ENTRY_SIZE];
+			e[0] = TYPE_NAME;
+			for (i, c) in chunk.iter().enumerate() {
+				e[(2 + i * 2)..(4 + i * 2)].copy_from_slice(&c.to_le_bytes());
+			}
+			self.entries.push(e)?;
+		}
+		self.entries[0][1] = (self.entries.len() - 1) as _;
+		self.entries[1][3] = name.len() as _;
+		self.entries[1][4..6].copy_from_slice(&hash.to_le_bytes());
+		Ok(())
+	}
+
+	/// Returns the attributes of the file.
+	pub fn get_attributes(&self) -> u16 {
+		u16::from_le_bytes([self.entries[0][4], self.entries[0][5]])
+	}
+
+	/// Sets the attributes of the file.
+	pub fn set_attributes(&mut self, attributes: u16) {
+		self.entries[0][4..6].copy_from_slice(&attributes.to_le_bytes());
+	}
+
+	/// Tells whether the file is a directory.
+	pub fn is_directory(&self) -> bool {
+		self.get_attributes() & ATTR_DIRECTORY != 0
+	}
+
+	/// Returns the timestamp stored at offset `off` of the File entry, with its 10 milliseconds
+	/// increment at offset `inc_off` and its UTC offset at `utc_off`.
+	fn get_time(&self, off: usize, inc_off: Option<usize>, utc_off: usize) -> Timespec {
+		let e = &self.entries[0];
+		let ts = u32::from_le_bytes(e[off..(off + 4)].try_into().unwrap());
+		to_timestamp(ts, inc_off.map(|i| e[i]).unwrap_or(0), e[utc_off])
+	}
+
+	/// Stores the timestamp `ts` at offset `off` of the File entry, with its 10 milliseconds
+	/// increment at offset `inc_off` and its UTC offset at `utc_off`.
+	fn set_time(&mut self, off: usize, inc_off: Option<usize>, utc_off: usize, ts: Timespec) {
+		let (ts, inc, utc) = from_timestamp(ts);
+		let e = &mut self.entries[0];
+		e[off..(off + 4)].copy_from_slice(&ts.to_le_bytes());
+		if let Some(inc_off) = inc_off {
+			e[inc_off] = inc;
+		}
+		e[utc_off] = utc;
+	}
+
+	/// Returns the timestamp of the last modification of the file.
+	pub fn get_mtime(&self) -> Timespec {
+		self.get_time(12, Some(21), 23)
+	}
+
+	/// Sets the timestamp of the last modification of the file.
+	pub fn set_mtime(&mut self, ts: Timespec) {
+		self.set_time(12, Some(21), 23, ts);
+	}
+
+	/// Returns the timestamp of the last access to the file.
+	pub fn get_atime(&self) -> Timespec {
+		self.get_time(16, None, 24)
+	}
+
+	/// Sets the timestamp of the last access to the file.
+	pub fn set_atime(&mut self, ts: Timespec) {
+		self.set_time(16, None, 24, ts);
+	}
+
+	/// Returns the data stream of the file.
+	pub fn get_stream(&self) -> Stream {
+		let e = &self.entries[1];
+		Stream {
+			first_cluster: u32::from_le_bytes(e[20..24].try_into().unwrap()),
+			size: u64::from_le_bytes(e[24..32].try_into().unwrap()),
+			valid_size: u64::from_le_bytes(e[8..16].try_into().unwrap()),
+			contiguous: e[1] & STREAM_NO_FAT_CHAIN != 0,
+		}
+	}
+
+	/// Sets the data stream of the file.
+	pub fn set_stream(&mut self, stream: &Stream) {
+		let e = &mut self.entries[1];
+		e[1] = STREAM_ALLOCATION_POSSIBLE;
+		if stream.contiguous {
+			e[1] |= STREAM_NO_FAT_CHAIN;
+		}
+		e[8..16].copy_from_slice(&stream.valid_size.to_le_bytes());
+		e[20..24].copy_from_slice(&stream.first_cluster.to_le_bytes());
+		e[24..32].copy_from_slice(&stream.size.to_le_bytes());
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn exfat_timestamp() {
+		for (sec, nsec) in [
+			(315532800, 0),
+			(1000000000, 990000000),
+			(1700000001, 10000000),
+		] {
+			let ts = Timespec {
+				tv_sec: sec,
+				tv_nsec: nsec,
+			};
+			let (dos, inc, utc_off) = from_timestamp(ts);
+			assert_eq!(to_timestamp(dos, inc, utc_off), ts);
+		}
+	}
+
+	#[test_case]
+	fn exfat_set_checksum() {
+		let name: [u16; 3] = [b'A' as _, b'B' as _, b'C' as _];
+		let mut set =
+			EntrySet::new(&name, name_hash(&name), ATTR_ARCHIVE, Timespec::default()).unwrap();
+		set.update_checksum();
+		let parsed = EntrySet::parse(set.get_entries()).unwrap().unwrap();
+		assert_eq!(parsed.get_name().unwrap().as_slice(), &name);
+		set.entries[2][2] = b'X';
+		assert!(EntrySet::parse(set.get_entries()).unwrap().is_none());
+	}
+}
@@ -0,0 +1,1301 @@
+//! exFAT is the filesystem used on SDXC cards and on most large removable drives.
+//!
+//! The volume begins with a boot sector describing its layout: the File Allocation Table (FAT)
+//! and the cluster heap, where the content of files is stored. The data of a file is a stream of
+//! clusters. Either the clusters of a stream are contiguous, or they are chained through the FAT,
+//! in which each entry gives the cluster following the one with the same index.
+//!
+//! Unlike FAT, the allocation state of clusters is not stored in the FAT but in the Allocation
+//! Bitmap, which is a file of the root directory, as is the Up-case Table used to compare names.
+//! See [`upcase`].
+//!
+//! Files are described by sets of directory entries. See [`entry`].
+//!
+//! Since files have no inode number, inodes are allocated when files are found in directories,
+//! and only live as long as the filesystem is mounted. The filesystem has no hard links: a link
+//! created with [`Filesystem::add_link`] is only meant to exist while the file is renamed, the
+//! previous entry set being removed afterwards.
+//!
+//! Mount options are:
+//! - `uid` and `gid`: the owner of every files
+//! - `umask`: the mask applied to the permissions of every files, in octal
+//! - `fmask` and `dmask`: the same, for regular files and directories only respectively
+
+mod entry;
+mod upcase;
+
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::EntryVisitor;
+use crate::file::fs::Filesystem;
+use crate::file::fs::FilesystemType;
+use crate::file::fs::MountOptionDesc;
+use crate::file::fs::MountOptions;
+use crate::file::fs::Statfs;
+use crate::file::path::Path;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::DirEntry;
+use crate::file::File;
+use crate::file::FileContent;
+use crate::file::FileLocation;
+use crate::file::FileType;
+use crate::file::INode;
+use crate::file::Mode;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+use crate::util::lock::Mutex;
+use crate::util::math;
+use crate::util::ptr::arc::Arc;
+use core::char;
+use core::cmp::max;
+use core::cmp::min;
+use core::cmp::Ordering;
+use core::str;
+use entry::EntrySet;
+use entry::RawEntry;
+use entry::ENTRY_SIZE;
+use upcase::UpcaseTable;
+
+/// The name of the filesystem, as written in the boot sector.
+const FS_NAME: &[u8] = b"EXFAT   ";
+/// The signature at the end of the boot sector.
+const BOOT_SIGNATURE: u16 = 0xaa55;
+
+/// The filesystem's magic number, as returned by `statfs`.
+const EXFAT_MAGIC: u32 = 0x2011bab0;
+
+/// The index of the first cluster of the cluster heap.
+const FIRST_CLUSTER: u32 = 2;
+/// FAT entry value: end of a chain of clusters.
+const END_OF_CHAIN: u32 = 0xffffffff;
+
+/// Volume flag: the second FAT and Allocation Bitmap are the active ones.
+const VOLUME_ACTIVE_FAT: u16 = 0x1;
+
+/// The inode of the root directory, which has no entry set.
+const ROOT_INODE: INode = 1;
+
+/// The default mask applied to the permissions of files.
+const DEFAULT_UMASK: Mode = 0o022;
+
+/// The fields of the boot sector used by the driver.
+struct BootSector {
+	/// The offset of the active FAT, in sectors.
+	fat_offset: u32,
+	/// The offset of the cluster heap, in sectors.
+	cluster_heap_offset: u32,
+	/// The number of clusters in the heap.
+	cluster_count: u32,
+	/// The first cluster of the root directory.
+	root_cluster: u32,
+	/// The volume's flags.
+	volume_flags: u16,
+	/// The log2 of the size of a sector in bytes.
+	sector_shift: u8,
+	/// The log2 of the size of a cluster in sectors.
+	cluster_shift: u8,
+}
+
+impl BootSector {
+	/// Reads the boot sector from the device.
+	///
+	/// If the device does not contain an exFAT filesystem, the function returns `None`.
+	fn read(io: &mut dyn IO) -> EResult<Option<Self>> {
+		let mut buf = [0; 512];
+		io.read(0, &mut buf)?;
+		if &buf[3..11] != FS_NAME || u16::from_le_bytes([buf[510], buf[511]]) != BOOT_SIGNATURE {
+			return Ok(None);
+		}
+
+		let u32_at = |off: usize| u32::from_le_bytes(buf[off..(off + 4)].try_into().unwrap());
+		let fat_length = u32_at(84);
+		let volume_flags = u16::from_le_bytes([buf[106], buf[107]]);
+		let sector_shift = buf[108];
+		let cluster_shift = buf[109];
+		// The size of a cluster cannot exceed 32 MiB
+		if !(9..=12).contains(&sector_shift) || sector_shift + cluster_shift > 25 {
+			return Ok(None);
+		}
+		let mut fat_offset = u32_at(80);
+		if volume_flags & VOLUME_ACTIVE_FAT != 0 {
+			fat_offset += fat_length;
+		}
+		let cluster_count = u32_at(92);
+		if cluster_count == 0 {
+			return Ok(None);
+		}
+
+		Ok(Some(Self {
+			fat_offset,
+			cluster_heap_offset: u32_at(88),
+			cluster_count,
+			root_cluster: u32_at(96),
+			volume_flags,
+			sector_shift,
+			cluster_shift,
+		}))
+	}
+}
+
+/// A stream of clusters holding the data of a file.
+#[derive(Clone, Copy)]
+pub struct Stream {
+	/// The first cluster of the stream. If zero, no cluster is allocated.
+	first_cluster: u32,
+	/// The size of the stream in bytes.
+	size: u64,
+	/// The size of the data that has been written, in bytes. Data after this size is read as
+	/// zeros.
+	valid_size: u64,
+	/// Tells whether the clusters are contiguous, in which case the FAT is not used.
+	contiguous: bool,
+}
+
+impl Stream {
+	/// Returns an empty stream.
+	fn empty() -> Self {
+		Self {
+			first_cluster: 0,
+			size: 0,
+			valid_size: 0,
+			contiguous: false,
+		}
+	}
+}
+
+/// The location of the entry set of a file.
+struct Node {
+	/// The inode of the parent directory.
+	parent: INode,
+	/// The offset of the entry set in the parent directory, in bytes.
+	off: u64,
+	/// The position of each entry of the set on the device.
+	pos: Vec<u64>,
+}
+
+/// An entry set found in a directory.
+struct DirSet {
+	/// The offset of the entry set in the directory, in bytes.
+	off: u64,
+	/// The position of each entry of the set on the device.
+	pos: Vec<u64>,
+	/// The entry set.
+	set: EntrySet,
+}
+
+/// Converts the name `name` from UTF-8 to UTF-16.
+///
+/// If the name is empty, too long or contains characters that are not allowed, the function
+/// returns an error.
+fn encode_name(name: &[u8]) -> EResult<Vec<u16>> {
+	let name = str::from_utf8(name).map_err(|_| errno!(EINVAL))?;
+	let mut units = Vec::new();
+	for c in name.encode_utf16() {
+		if c < 0x20 || (c < 0x80 && b"\"*/:<>?\\|".contains(&(c as u8))) {
+			return Err(errno!(EINVAL));
+		}
+		units.push(c)?;
+	}
+	if units.is_empty() {
+		return Err(errno!(EINVAL));
+	}
+	if units.len() > entry::MAX_NAME_LEN {
+		return Err(errno!(ENAMETOOLONG));
+	}
+	Ok(units)
+}
+
+/// Converts the name `name` from UTF-16 to UTF-8.
+///
+/// Invalid code units are replaced with the replacement character.
+fn decode_name(name: &[u16]) -> EResult<String> {
+	let mut s = String::new();
+	for c in char::decode_utf16(name.iter().copied()) {
+		let c = c.unwrap_or(char::REPLACEMENT_CHARACTER);
+		let mut buf = [0; 4];
+		s.push_str(c.encode_utf8(&mut buf))?;
+	}
+	Ok(s)
+}
+
+/// Returns the index of the first run of `n` unused entries in the list of raw entries `raw`.
+fn find_free_slot(raw: &[(u64, RawEntry)], n: usize) -> Option<usize> {
+	let mut run = 0;
+	for (i, (_, e)) in raw.iter().enumerate() {
+		if e[0] & entry::TYPE_IN_USE != 0 {
+			run = 0;
+			continue;
+		}
+		run += 1;
+		if run == n {
+			return Some(i + 1 - n);
+		}
+	}
+	None
+}
+
+/// Structure representing a instance of the exFAT filesystem.
+pub struct ExfatFs {
+	/// Tells whether the filesystem is mounted in read-only.
+	readonly: bool,
+	/// The owner user of every files.
+	uid: Uid,
+	/// The owner group of every files.
+	gid: Gid,
+	/// The mask applied to the permissions of regular files.
+	fmask: Mode,
+	/// The mask applied to the permissions of directories.
+	dmask: Mode,
+
+	/// The log2 of the size of a cluster in bytes.
+	cluster_shift: u8,
+	/// The offset of the active FAT on the device, in bytes.
+	fat_off: u64,
+	/// The offset of the cluster heap on the device, in bytes.
+	heap_off: u64,
+	/// The number of clusters in the heap.
+	cluster_count: u32,
+	/// The stream of the root directory.
+	root: Stream,
+
+	/// The Allocation Bitmap, in which each bit tells whether the cluster is allocated.
+	bitmap: Vec<u8>,
+	/// The stream of the Allocation Bitmap.
+	bitmap_stream: Stream,
+	/// The number of free clusters.
+	free_clusters: u32,
+	/// The Up-case Table.
+	upcase: UpcaseTable,
+
+	/// The location of the entry set of each allocated inode.
+	nodes: HashMap<INode, Node>,
+	/// The inode of each known entry set, by parent directory and offset in the directory.
+	///
+	/// After a rename, the previous entry set of a file remains in this map until removed.
+	locations: HashMap<(INode, u64), INode>,
+	/// The next inode to be allocated.
+	next_inode: INode,
+
+	/// The last cluster found while walking a chain of clusters, to speed up sequential accesses.
+	///
+	/// The tuple contains the first cluster of the chain, the index of the cluster in the chain
+	/// and the cluster itself.
+	walk_cache: Option<(u32, u32, u32)>,
+}
+
+impl ExfatFs {
+	/// Creates a new instance from the boot sector `boot`.
+	///
+	/// Arguments:
+	/// - `io` is the IO interface.
+	/// - `readonly` tells whether the filesystem is mounted in read-only.
+	/// - `options` are the mount options.
+	fn new(
+		io: &mut dyn IO,
+		boot: BootSector,
+		readonly: bool,
+		options: &MountOptions,
+	) -> EResult<Self> {
+		let umask = match options.get_number(b"umask", 8)? {
+			Some(umask) => (umask & 0o777) as Mode,
+			None => DEFAULT_UMASK,
+		};
+		let fmask = match options.get_number(b"fmask", 8)? {
+			Some(fmask) => (fmask & 0o777) as Mode,
+			None => umask,
+		};
+		let dmask = match options.get_number(b"dmask", 8)? {
+			Some(dmask) => (dmask & 0o777) as Mode,
+			None => umask,
+		};
+		let uid = match options.get_number(b"uid", 10)? {
+			Some(uid) => uid.try_into().map_err(|_| errno!(EINVAL))?,
+			None => 0,
+		};
+		let gid = match options.get_number(b"gid", 10)? {
+			Some(gid) => gid.try_into().map_err(|_| errno!(EINVAL))?,
+			None => 0,
+		};
+
+		let mut fs = Self {
+			readonly,
+			uid,
+			gid,
+			fmask,
+			dmask,
+
+			cluster_shift: boot.sector_shift + boot.cluster_shift,
+			fat_off: (boot.fat_offset as u64) << boot.sector_shift,
+			heap_off: (boot.cluster_heap_offset as u64) << boot.sector_shift,
+			cluster_count: boot.cluster_count,
+			root: Stream {
+				first_cluster: boot.root_cluster,
+				size: 0,
+				valid_size: 0,
+				contiguous: false,
+			},
+
+			bitmap: Vec::new(),
+			bitmap_stream: Stream::empty(),
+			free_clusters: 0,
+			upcase: UpcaseTable::identity(),
+
+			nodes: HashMap::new(),
+			locations: HashMap::new(),
+			next_inode: ROOT_INODE + 1,
+
+			walk_cache: None,
+		};
+
+		// The size of the root directory is the length of its chain of clusters
+		let mut cluster = boot.root_cluster;
+		let mut len = 0;
+		while cluster != END_OF_CHAIN {
+			if !fs.is_valid_cluster(cluster) || len >= fs.cluster_count {
+				return Err(errno!(EUCLEAN));
+			}
+			len += 1;
+			cluster = fs.read_fat(io, cluster)?;
+		}
+		fs.root.size = (len as u64) << fs.cluster_shift;
+		fs.root.valid_size = fs.root.size;
+
+		// Look for the Allocation Bitmap and the Up-case Table
+		let root = fs.root;
+		let mut bitmap = None;
+		let mut upcase = None;
+		for (_, e) in fs.read_raw_dir(io, &root)? {
+			let stream = Stream {
+				first_cluster: u32::from_le_bytes(e[20..24].try_into().unwrap()),
+				size: u64::from_le_bytes(e[24..32].try_into().unwrap()),
+				valid_size: u64::from_le_bytes(e[24..32].try_into().unwrap()),
+				contiguous: false,
+			};
+			match e[0] {
+				entry::TYPE_END => break,
+				// When there are two bitmaps, the flag tells which FAT the bitmap belongs to
+				entry::TYPE_BITMAP
+					if (e[1] & 1) as u16 == boot.volume_flags & VOLUME_ACTIVE_FAT =>
+				{
+					bitmap = Some(stream);
+				}
+				entry::TYPE_UPCASE => {
+					upcase = Some((stream, u32::from_le_bytes(e[4..8].try_into().unwrap())));
+				}
+				_ => {}
+			}
+		}
+		let bitmap_stream = bitmap.ok_or_else(|| errno!(EUCLEAN))?;
+		let (upcase_stream, upcase_checksum) = upcase.ok_or_else(|| errno!(EUCLEAN))?;
+
+		// Load the Allocation Bitmap
+		let bitmap_len = math::ceil_div(fs.cluster_count as usize, 8);
+		if bitmap_stream.size < bitmap_len as u64 {
+			return Err(errno!(EUCLEAN));
+		}
+		let mut bitmap = Vec::from_elem(0u8, bitmap_len)?;
+		fs.read_stream(io, &bitmap_stream, 0, bitmap.as_mut_slice())?;
+		fs.bitmap = bitmap;
+		fs.bitmap_stream = bitmap_stream;
+		fs.free_clusters = (FIRST_CLUSTER..(FIRST_CLUSTER + fs.cluster_count))
+			.filter(|c| fs.is_free(*c))
+			.count() as _;
+
+		// Load the Up-case Table
+		let upcase_len = upcase_stream.size.try_into().map_err(|_| errno!(EUCLEAN))?;
+		let mut raw = Vec::from_elem(0u8, upcase_len)?;
+		fs.read_stream(io, &upcase_stream, 0, raw.as_mut_slice())?;
+		fs.upcase = UpcaseTable::parse(raw.as_slice(), upcase_checksum)?;
+
+		Ok(fs)
+	}
+
+	/// Returns the size of a cluster in bytes.
+	fn cluster_size(&self) -> u64 {
+		1 << self.cluster_shift
+	}
+
+	/// Tells whether the given cluster is in the cluster heap.
+	fn is_valid_cluster(&self, cluster: u32) -> bool {
+		cluster >= FIRST_CLUSTER && cluster - FIRST_CLUSTER < self.cluster_count
+	}
+
+	/// Returns the offset of the given cluster on the device.
+	fn cluster_off(&self, cluster: u32) -> u64 {
+		self.heap_off + (((cluster - FIRST_CLUSTER) as u64) << self.cluster_shift)
+	}
+
+	/// Returns the FAT entry of the given cluster.
+	fn read_fat(&self, io: &mut dyn IO, cluster: u32) -> EResult<u32> {
+		let mut buf = [0; 4];
+		io.read(self.fat_off + cluster as u64 * 4, &mut buf)?;
+		Ok(u32::from_le_bytes(buf))
+	}
+
+	/// Sets the FAT entry of the given cluster.
+	fn write_fat(&self, io: &mut dyn IO, cluster: u32, value: u32) -> EResult<()> {
+		io.write(self.fat_off + cluster as u64 * 4, &value.to_le_bytes())?;
+		Ok(())
+	}
+
+	/// Returns the cluster with index `index` in the stream `stream`.
+	fn nth_cluster(&mut self, io: &mut dyn IO, stream: &Stream, index: u32) -> EResult<u32> {
+		if stream.contiguous {
+			let cluster = stream.first_cluster.wrapping_add(index);
+			if !self.is_valid_cluster(cluster) {
+				return Err(errno!(EUCLEAN));
+			}
+			return Ok(cluster);
+		}
+
+		let (mut i, mut cluster) = match self.walk_cache {
+			Some((first, i, cluster)) if first == stream.first_cluster && i <= index => {
+				(i, cluster)
+			}
+			_ => (0, stream.first_cluster),
+		};
+		while i < index {
+			if !self.is_valid_cluster(cluster) {
+				return Err(errno!(EUCLEAN));
+			}
+			cluster = self.read_fat(io, cluster)?;
+			i += 1;
+		}
+		if !self.is_valid_cluster(cluster) {
+			return Err(errno!(EUCLEAN));
+		}
+		self.walk_cache = Some((stream.first_cluster, index, cluster));
+		Ok(cluster)
+	}
+
+	/// Returns the position on the device of the byte at offset `off` in the stream `stream`.
+	fn stream_pos(&mut self, io: &mut dyn IO, stream: &Stream, off: u64) -> EResult<u64> {
+		let cluster = self.nth_cluster(io, stream, (off >> self.cluster_shift) as _)?;
+		Ok(self.cluster_off(cluster) + (off & (self.cluster_size() - 1)))
+	}
+
+	/// Reads from the stream `stream` at offset `off` into `buf`.
+	///
+	/// The range must be in the allocated clusters of the stream.
+	fn read_stream(
+		&mut self,
+		io: &mut dyn IO,
+		stream: &Stream,
+		off: u64,
+		buf: &mut [u8],
+	) -> EResult<()> {
+		let mut i = 0;
+		while i < buf.len() {
+			let pos = self.stream_pos(io, stream, off + i as u64)?;
+			let inner_off = (off + i as u64) & (self.cluster_size() - 1);
+			let len = min(buf.len() - i, (self.cluster_size() - inner_off) as usize);
+			io.read(pos, &mut buf[i..(i + len)])?;
+			i += len;
+		}
+		Ok(())
+	}
+
+	/// Writes `buf` to the stream `stream` at offset `off`.
+	///
+	/// The range must be in the allocated clusters of the stream.
+	fn write_stream(
+		&mut self,
+		io: &mut dyn IO,
+		stream: &Stream,
+		off: u64,
+		buf: &[u8],
+	) -> EResult<()> {
+		let mut i = 0;
+		while i < buf.len() {
+			let pos = self.stream_pos(io, stream, off + i as u64)?;
+			let inner_off = (off + i as u64) & (self.cluster_size() - 1);
+			let len = min(buf.len() - i, (self.cluster_size() - inner_off) as usize);
+			io.write(pos, &buf[i..(i + len)])?;
+			i += len;
+		}
+		Ok(())
+	}
+
+	/// Writes zeros to the stream `stream` in the range of `len` bytes beginning at offset `off`.
+	fn zero_stream(
+		&mut self,
+		io: &mut dyn IO,
+		stream: &Stream,
+		off: u64,
+		len: u64,
+	) -> EResult<()> {
+		let zeros = Vec::from_elem(0u8, min(len, self.cluster_size()) as usize)?;
+		let mut i = 0;
+		while i < len {
+			let l = min(len - i, zeros.len() as u64);
+			self.write_stream(io, stream, off + i, &zeros.as_slice()[..(l as usize)])?;
+			i += l;
+		}
+		Ok(())
+	}
+
+	/// Tells whether the given cluster is free.
+	fn is_free(&self, cluster: u32) -> bool {
+		let i = (cluster - FIRST_CLUSTER) as usize;
+		self.bitmap[i / 8] & (1 << (i % 8)) == 0
+	}
+
+	/// Sets the allocation state of the given cluster in the Allocation Bitmap.
+	fn set_allocated(&mut self, io: &mut dyn IO, cluster: u32, allocated: bool) -> EResult<()> {
+		let i = (cluster - FIRST_CLUSTER) as usize;
+		if allocated {
+			self.bitmap[i / 8] |= 1 << (i % 8);
+			self.free_clusters -= 1;
+		} else {
+			self.bitmap[i / 8] &= !(1 << (i % 8));
+			self.free_clusters += 1;
+		}
+		let stream = self.bitmap_stream;
+		let pos = self.stream_pos(io, &stream, (i / 8) as _)?;
+		io.write(pos, &self.bitmap[(i / 8)..(i / 8 + 1)])?;
+		Ok(())
+	}
+
+	/// Returns a free cluster, searching from the cluster `hint` onwards.
+	fn find_free(&self, hint: u32) -> Option<u32> {
+		let end = FIRST_CLUSTER + self.cluster_count;
+		let hint = if self.is_valid_cluster(hint) {
+			hint
+		} else {
+			FIRST_CLUSTER
+		};
+		(hint..end)
+			.chain(FIRST_CLUSTER..hint)
+			.find(|c| self.is_free(*c))
+	}
+
+	/// Changes the size of the stream `stream` to `size`, allocating or freeing clusters.
+	///
+	/// Clusters are allocated contiguously when possible. If not, the chain of clusters is
+	/// written to the FAT.
+	///
+	/// The valid size of the stream is not increased.
+	fn resize(&mut self, io: &mut dyn IO, stream: &mut Stream, size: u64) -> EResult<()> {
+		let old = math::ceil_div(stream.size, self.cluster_size());
+		let new = math::ceil_div(size, self.cluster_size());
+		if new > self.cluster_count as u64 {
+			return Err(errno!(ENOSPC));
+		}
+		let (old, new) = (old as u32, new as u32);
+		// The chain is about to change
+		self.walk_cache = None;
+
+		match new.cmp(&old) {
+			Ordering::Greater => {
+				if new - old > self.free_clusters {
+					return Err(errno!(ENOSPC));
+				}
+				let mut last = match old {
+					0 => None,
+					old => Some(self.nth_cluster(io, stream, old - 1)?),
+				};
+				for _ in old..new {
+					let hint = last.map(|c| c + 1).unwrap_or(FIRST_CLUSTER);
+					let cluster = self.find_free(hint).ok_or_else(|| errno!(ENOSPC))?;
+					self.set_allocated(io, cluster, true)?;
+					match last {
+						None => {
+							stream.first_cluster = cluster;
+							stream.contiguous = true;
+						}
+						Some(prev) => {
+							if stream.contiguous && cluster != prev + 1 {
+								// The clusters are not contiguous anymore
+								for c in stream.first_cluster..prev {
+									self.write_fat(io, c, c + 1)?;
+								}
+								stream.contiguous = false;
+							}
+							if !stream.contiguous {
+								self.write_fat(io, prev, cluster)?;
+							}
+						}
+					}
+					if !stream.contiguous {
+						self.write_fat(io, cluster, END_OF_CHAIN)?;
+					}
+					last = Some(cluster);
+				}
+			}
+			Ordering::Less => {
+				let mut clusters = Vec::new();
+				for i in new..old {
+					clusters.push(self.nth_cluster(io, stream, i)?)?;
+				}
+				if new > 0 && !stream.contiguous {
+					let last = self.nth_cluster(io, stream, new - 1)?;
+					self.write_fat(io, last, END_OF_CHAIN)?;
+				}
+				for c in clusters {
+					self.set_allocated(io, c, false)?;
+				}
+				if new == 0 {
+					*stream = Stream::empty();
+				}
+				self.walk_cache = None;
+			}
+			Ordering::Equal => {}
+		}
+
+		stream.size = size;
+		stream.valid_size = min(stream.valid_size, size);
+		Ok(())
+	}
+
+	/// Returns the location of the entry set of the file with inode `inode`.
+	fn get_node(&self, inode: INode) -> EResult<&Node> {
+		self.nodes.get(&inode).ok_or_else(|| errno!(ENOENT))
+	}
+
+	/// Reads the entry set of the file with inode `inode`.
+	fn read_set(&self, io: &mut dyn IO, inode: INode) -> EResult<EntrySet> {
+		let node = self.get_node(inode)?;
+		let mut raw = Vec::with_capacity(node.pos.len())?;
+		for pos in node.pos.iter() {
+			let mut e = [0; ENTRY_SIZE];
+			io.read(*pos, &mut e)?;
+			raw.push(e)?;
+		}
+		EntrySet::parse(raw.as_slice())?.ok_or_else(|| errno!(EUCLEAN))
+	}
+
+	/// Writes the entry set `set` of the file with inode `inode`.
+	fn write_set(&self, io: &mut dyn IO, inode: INode, set: &mut EntrySet) -> EResult<()> {
+		set.update_checksum();
+		let node = self.get_node(inode)?;
+		for (pos, e) in node.pos.iter().zip(set.get_entries()) {
+			io.write(*pos, e)?;
+		}
+		Ok(())
+	}
+
+	/// Returns the stream of the directory with inode `inode`.
+	///
+	/// If the file is not a directory, the function returns an error.
+	fn get_dir_stream(&self, io: &mut dyn IO, inode: INode) -> EResult<Stream> {
+		if inode == ROOT_INODE {
+			return Ok(self.root);
+		}
+		let set = self.read_set(io, inode)?;
+		if !set.is_directory() {
+			return Err(errno!(ENOTDIR));
+		}
+		Ok(set.get_stream())
+	}
+
+	/// Sets the stream of the directory with inode `inode`.
+	fn set_dir_stream(&mut self, io: &mut dyn IO, inode: INode, stream: &Stream) -> EResult<()> {
+		if inode == ROOT_INODE {
+			self.root = *stream;
+			return Ok(());
+		}
+		let mut set = self.read_set(io, inode)?;
+		set.set_stream(stream);
+		self.write_set(io, inode, &mut set)
+	}
+
+	/// Reads every entries of the directory with stream `stream`, with their respective
+	/// positions on the device.
+	fn read_raw_dir(&mut self, io: &mut dyn IO, stream: &Stream) -> EResult<Vec<(u64, RawEntry)>> {
+		let mut raw = Vec::new();
+		let mut buf = Vec::from_elem(0u8, self.cluster_size() as usize)?;
+		let clusters = math::ceil_div(stream.size, self.cluster_size());
+		for i in 0..clusters {
+			let cluster = self.nth_cluster(io, stream, i as _)?;
+			let off = self.cluster_off(cluster);
+			io.read(off, buf.as_mut_slice())?;
+			for (j, e) in buf.as_slice().chunks_exact(ENTRY_SIZE).enumerate() {
+				raw.push((off + (j * ENTRY_SIZE) as u64, e.try_into().unwrap()))?;
+			}
+		}
+		Ok(raw)
+	}
+
+	/// Returns the entry sets of the directory with inode `inode`.
+	///
+	/// If the file is not a directory, the function returns an error.
+	fn read_dir(&mut self, io: &mut dyn IO, inode: INode) -> EResult<Vec<DirSet>> {
+		let stream = self.get_dir_stream(io, inode)?;
+		let raw = self.read_raw_dir(io, &stream)?;
+
+		let mut sets = Vec::new();
+		let mut i = 0;
+		while i < raw.len() {
+			let t = raw[i].1[0];
+			if t == entry::TYPE_END {
+				break;
+			}
+			if t != entry::TYPE_FILE {
+				i += 1;
+				continue;
+			}
+			let end = min(raw.len(), i + 1 + raw[i].1[1] as usize);
+			let mut entries = Vec::with_capacity(end - i)?;
+			let mut pos = Vec::with_capacity(end - i)?;
+			for (p, e) in &raw.as_slice()[i..end] {
+				entries.push(*e)?;
+				pos.push(*p)?;
+			}
+			match EntrySet::parse(entries.as_slice())? {
+				Some(set) => {
+					sets.push(DirSet {
+						off: (i * ENTRY_SIZE) as _,
+						pos,
+						set,
+					})?;
+					i = end;
+				}
+				// Invalid sets are ignored
+				None => i += 1,
+			}
+		}
+		Ok(sets)
+	}
+
+	/// Returns the entry set with name `name` in the directory with inode `parent`.
+	///
+	/// Names are compared case-insensitively.
+	fn find(&mut self, io: &mut dyn IO, parent: INode, name: &[u8]) -> EResult<Option<DirSet>> {
+		// A name that cannot be encoded cannot be present
+		let Ok(name) = encode_name(name) else {
+			return Ok(None);
+		};
+		let name = self.upcase.upcase_name(name.as_slice())?;
+		let hash = entry::name_hash(name.as_slice());
+		for dir_set in self.read_dir(io, parent)? {
+			if dir_set.set.get_name_hash() != hash {
+				continue;
+			}
+			let set_name = self
+				.upcase
+				.upcase_name(dir_set.set.get_name()?.as_slice())?;
+			if set_name.as_slice() == name.as_slice() {
+				return Ok(Some(dir_set));
+			}
+		}
+		Ok(None)
+	}
+
+	/// Returns the inode of the entry set located at offset `off` in the directory with inode
+	/// `parent`, allocating it if necessary.
+	///
+	/// `pos` is the position of each entry of the set on the device.
+	fn get_set_inode(&mut self, parent: INode, off: u64, pos: &[u64]) -> EResult<INode> {
+		if let Some(inode) = self.locations.get(&(parent, off)) {
+			return Ok(*inode);
+		}
+
+		let inode = self.next_inode;
+		self.nodes.insert(
+			inode,
+			Node {
+				parent,
+				off,
+				pos: Vec::from_slice(pos)?,
+			},
+		)?;
+		self.locations.insert((parent, off), inode)?;
+		self.next_inode += 1;
+		Ok(inode)
+	}
+
+	/// Writes the entry set `set` in a free slot of the directory with inode `parent`, extending
+	/// the directory if necessary.
+	///
+	/// The function returns the offset of the set in the directory and the positions of its
+	/// entries on the device.
+	fn insert_set(
+		&mut self,
+		io: &mut dyn IO,
+		parent: INode,
+		set: &mut EntrySet,
+	) -> EResult<(u64, Vec<u64>)> {
+		set.update_checksum();
+		let mut stream = self.get_dir_stream(io, parent)?;
+		let mut raw = self.read_raw_dir(io, &stream)?;
+		let index = loop {
+			if let Some(index) = find_free_slot(raw.as_slice(), set.len()) {
+				break index;
+			}
+			// Extend the directory with a cluster
+			let old_size = stream.size;
+			self.resize(io, &mut stream, old_size + self.cluster_size())?;
+			self.zero_stream(io, &stream, old_size, self.cluster_size())?;
+			stream.valid_size = stream.size;
+			self.set_dir_stream(io, parent, &stream)?;
+			raw = self.read_raw_dir(io, &stream)?;
+		};
+
+		let mut pos = Vec::with_capacity(set.len())?;
+		for ((p, _), e) in raw.as_slice()[index..].iter().zip(set.get_entries()) {
+			io.write(*p, e)?;
+			pos.push(*p)?;
+		}
+		Ok(((index * ENTRY_SIZE) as _, pos))
+	}
+
+	/// Marks the entries at the positions `pos` as unused.
+	fn remove_set(io: &mut dyn IO, pos: &[u64], set: &EntrySet) -> EResult<()> {
+		for (p, e) in pos.iter().zip(set.get_entries()) {
+			io.write(*p, &[e[0] & !entry::TYPE_IN_USE])?;
+		}
+		Ok(())
+	}
+
+	/// Returns the permissions of a file with the given attributes.
+	fn get_permissions(&self, attributes: u16) -> Mode {
+		let mut mode = if attributes & entry::ATTR_DIRECTORY != 0 {
+			0o777 & !self.dmask
+		} else {
+			0o777 & !self.fmask
+		};
+		if attributes & entry::ATTR_READ_ONLY != 0 {
+			mode &= !0o222;
+		}
+		mode
+	}
+
+	/// Creates the file structure for the file with inode `inode`, described by `set`.
+	///
+	/// If `set` is `None`, the file is the root directory.
+	fn build_file(&self, inode: INode, name: String, set: Option<&EntrySet>) -> EResult<File> {
+		let (attributes, stream) = match set {
+			Some(set) => (set.get_attributes(), set.get_stream()),
+			None => (entry::ATTR_DIRECTORY, self.root),
+		};
+		let is_dir = attributes & entry::ATTR_DIRECTORY != 0;
+		let content = if is_dir {
+			FileContent::Directory(HashMap::new())
+		} else {
+			FileContent::Regular
+		};
+
+		let file_location = FileLocation::Filesystem {
+			mountpoint_id: 0, // dummy value to be replaced
+			inode,
+		};
+		let mut file = File::new(
+			name,
+			self.uid,
+			self.gid,
+			self.get_permissions(attributes),
+			file_location,
+			content,
+		)?;
+		file.set_hard_links_count(if is_dir { 2 } else { 1 });
+		let allocated = math::ceil_div(stream.size, self.cluster_size()) << self.cluster_shift;
+		file.blocks_count = allocated / 512;
+		file.set_size(stream.size);
+		let (mtime, atime) = set
+			.map(|s| (s.get_mtime(), s.get_atime()))
+			.unwrap_or_default();
+		// exFAT has no status change timestamp
+		file.ctime = mtime;
+		file.mtime = mtime;
+		file.atime = atime;
+
+		Ok(file)
+	}
+}
+
+impl Filesystem for ExfatFs {
+	fn get_name(&self) -> &[u8] {
+		b"exfat"
+	}
+
+	fn is_readonly(&self) -> bool {
+		self.readonly
+	}
+
+	fn must_cache(&self) -> bool {
+		true
+	}
+
+	fn get_stat(&self, _io: &mut dyn IO) -> Result<Statfs, Errno> {
+		Ok(Statfs {
+			f_type: EXFAT_MAGIC,
+			f_bsize: self.cluster_size() as _,
+			f_blocks: self.cluster_count as _,
+			f_bfree: self.free_clusters as _,
+			f_bavail: self.free_clusters as _,
+			f_files: 0,
+			f_ffree: 0,
+			f_fsid: Default::default(),
+			f_namelen: entry::MAX_NAME_LEN as _,
+			f_frsize: self.cluster_size() as _,
+			f_flags: 0, // TODO
+		})
+	}
+
+	fn get_root_inode(&self, _io: &mut dyn IO) -> Result<INode, Errno> {
+		Ok(ROOT_INODE)
+	}
+
+	fn get_inode(
+		&mut self,
+		io: &mut dyn IO,
+		parent: Option<INode>,
+		name: &[u8],
+	) -> Result<INode, Errno> {
+		let parent = parent.unwrap_or(ROOT_INODE);
+		match name {
+			b"." => Ok(parent),
+			b".." if parent == ROOT_INODE => Ok(ROOT_INODE),
+			b".." => Ok(self.get_node(parent)?.parent),
+			_ => {
+				let dir_set = self.find(io, parent, name)?.ok_or_else(|| errno!(ENOENT))?;
+				self.get_set_inode(parent, dir_set.off, dir_set.pos.as_slice())
+			}
+		}
+	}
+
+	fn load_file(&mut self, io: &mut dyn IO, inode: INode, name: String) -> Result<File, Errno> {
+		if inode == ROOT_INODE {
+			return self.build_file(inode, name, None);
+		}
+		let set = self.read_set(io, inode)?;
+		self.build_file(inode, name, Some(&set))
+	}
+
+	fn iter_entries(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		f: &mut EntryVisitor,
+	) -> Result<(), Errno> {
+		let sets = self.read_dir(io, inode)?;
+		let parent = match inode {
+			ROOT_INODE => ROOT_INODE,
+			inode => self.get_node(inode)?.parent,
+		};
+
+		// `.` and `..` are not stored on the filesystem. Offsets `0` and `1` are used for them,
+		// and entry sets use their offset in the directory plus two
+		let dots = [(&b"."[..], inode), (&b".."[..], parent)];
+		for (i, (name, inode)) in dots.into_iter().enumerate().skip(off as _) {
+			let entry = DirEntry {
+				inode,
+				entry_type: FileType::Directory,
+			};
+			if !f(name, &entry, i as u64 + 1)? {
+				return Ok(());
+			}
+		}
+		for dir_set in sets {
+			let cookie = dir_set.off + 2;
+			if cookie < off {
+				continue;
+			}
+			let name = decode_name(dir_set.set.get_name()?.as_slice())?;
+			let entry = DirEntry {
+				inode: self.get_set_inode(inode, dir_set.off, dir_set.pos.as_slice())?,
+				entry_type: if dir_set.set.is_directory() {
+					FileType::Directory
+				} else {
+					FileType::Regular
+				},
+			};
+			let next = cookie + (dir_set.pos.len() * ENTRY_SIZE) as u64;
+			if !f(name.as_bytes(), &entry, next)? {
+				break;
+			}
+		}
+		Ok(())
+	}
+
+	fn add_file(
+		&mut self,
+		io: &mut dyn IO,
+		parent_inode: INode,
+		name: String,
+		_uid: Uid,
+		_gid: Gid,
+		mode: Mode,
+		content: FileContent,
+	) -> Result<File, Errno> {
+		if self.readonly {
+			return Err(errno!(EROFS));
+		}
+
+		let mut attributes = match content {
+			FileContent::Regular => entry::ATTR_ARCHIVE,
+			FileContent::Directory(_) => entry::ATTR_DIRECTORY,
+			// Other types of files are not supported
+			_ => return Err(errno!(EPERM)),
+		};
+		if mode & 0o200 == 0 {
+			attributes |= entry::ATTR_READ_ONLY;
+		}
+		let name16 = encode_name(name.as_bytes())?;
+		if self.find(io, parent_inode, name.as_bytes())?.is_some() {
+			return Err(errno!(EEXIST));
+		}
+
+		// The timestamps of creation are given by the file structure
+		let file = self.build_file(0, String::new(), None)?;
+		let hash = entry::name_hash(self.upcase.upcase_name(name16.as_slice())?.as_slice());
+		let mut set = EntrySet::new(name16.as_slice(), hash, attributes, file.mtime)?;
+
+		// A directory always has at least one cluster
+		let mut stream = Stream::empty();
+		if set.is_directory() {
+			self.resize(io, &mut stream, self.cluster_size())?;
+			let res = self.zero_stream(io, &stream, 0, self.cluster_size());
+			if let Err(e) = res {
+				self.resize(io, &mut stream, 0)?;
+				return Err(e);
+			}
+			stream.valid_size = stream.size;
+			set.set_stream(&stream);
+		}
+
+		let (off, pos) = match self.insert_set(io, parent_inode, &mut set) {
+			Ok(res) => res,
+			Err(e) => {
+				self.resize(io, &mut stream, 0)?;
+				return Err(e);
+			}
+		};
+		let inode = self.get_set_inode(parent_inode, off, pos.as_slice())?;
+
+		self.build_file(inode, name, Some(&set))
+	}
+
+	fn add_link(
+		&mut self,
+		io: &mut dyn IO,
+		parent_inode: INode,
+		name: &[u8],
+		inode: INode,
+	) -> Result<(), Errno> {
+		if self.readonly {
+			return Err(errno!(EROFS));
+		}
+		if inode == ROOT_INODE {
+			return Err(errno!(EINVAL));
+		}
+
+		let name16 = encode_name(name)?;
+		if self.find(io, parent_inode, name)?.is_some() {
+			return Err(errno!(EEXIST));
+		}
+		// The parent must not be moved under the directory itself
+		let mut p = parent_inode;
+		while p != ROOT_INODE {
+			if p == inode {
+				return Err(errno!(EINVAL));
+			}
+			p = self.get_node(p)?.parent;
+		}
+
+		let mut set = self.read_set(io, inode)?;
+		let hash = entry::name_hash(self.upcase.upcase_name(name16.as_slice())?.as_slice());
+		set.set_name(name16.as_slice(), hash)?;
+		let (off, pos) = self.insert_set(io, parent_inode, &mut set)?;
+
+		let node = self.nodes.get_mut(&inode).ok_or_else(|| errno!(ENOENT))?;
+		let old_parent = node.parent;
+		let old_off = node.off;
+		let old_pos = core::mem::replace(&mut node.pos, pos);
+		node.parent = parent_inode;
+		node.off = off;
+		self.locations.insert((parent_inode, off), inode)?;
+
+		// A directory cannot have several links: the previous entry set is removed right away.
+		// For other files, it is removed afterwards by the caller
+		if set.is_directory() {
+			Self::remove_set(io, old_pos.as_slice(), &set)?;
+			self.locations.remove(&(old_parent, old_off));
+		}
+		Ok(())
+	}
+
+	fn update_inode(&mut self, io: &mut dyn IO, file: &File) -> Result<(), Errno> {
+		if self.readonly {
+			return Err(errno!(EROFS));
+		}
+		let inode = file.get_location().get_inode();
+		if inode == ROOT_INODE {
+			return Ok(());
+		}
+
+		let mut set = self.read_set(io, inode)?;
+		if !set.is_directory() {
+			let mut stream = set.get_stream();
+			if file.get_size() != stream.size {
+				self.resize(io, &mut stream, file.get_size())?;
+				set.set_stream(&stream);
+			}
+		}
+
+		let mut attributes = set.get_attributes() & !entry::ATTR_READ_ONLY;
+		if file.get_permissions() & 0o200 == 0 {
+			attributes |= entry::ATTR_READ_ONLY;
+		}
+		set.set_attributes(attributes);
+		set.set_mtime(file.mtime);
+		set.set_atime(file.atime);
+		self.write_set(io, inode, &mut set)
+	}
+
+	fn remove_file(
+		&mut self,
+		io: &mut dyn IO,
+		parent_inode: INode,
+		name: &[u8],
+	) -> Result<u16, Errno> {
+		if self.readonly {
+			return Err(errno!(EROFS));
+		}
+		if name == b"." || name == b".." {
+			return Err(errno!(EINVAL));
+		}
+
+		let dir_set = self
+			.find(io, parent_inode, name)?
+			.ok_or_else(|| errno!(ENOENT))?;
+		let inode = self.get_set_inode(parent_inode, dir_set.off, dir_set.pos.as_slice())?;
+		let node = self.get_node(inode)?;
+		// The entry set is the previous location of a renamed file
+		let stale = node.parent != parent_inode || node.off != dir_set.off;
+		if !stale && dir_set.set.is_directory() && !self.read_dir(io, inode)?.is_empty() {
+			return Err(errno!(ENOTEMPTY));
+		}
+
+		Self::remove_set(io, dir_set.pos.as_slice(), &dir_set.set)?;
+		self.locations.remove(&(parent_inode, dir_set.off));
+		if stale {
+			return Ok(1);
+		}
+
+		let mut stream = dir_set.set.get_stream();
+		self.resize(io, &mut stream, 0)?;
+		self.nodes.remove(&inode);
+		Ok(0)
+	}
+
+	fn read_node(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		buf: &mut [u8],
+	) -> Result<u64, Errno> {
+		if inode == ROOT_INODE {
+			return Err(errno!(EISDIR));
+		}
+		let set = self.read_set(io, inode)?;
+		if set.is_directory() {
+			return Err(errno!(EISDIR));
+		}
+		let stream = set.get_stream();
+		if off >= stream.size {
+			return Ok(0);
+		}
+
+		let len = min(buf.len() as u64, stream.size - off) as usize;
+		// Data after the valid size is read as zeros
+		let valid_len = min(len as u64, stream.valid_size.saturating_sub(off)) as usize;
+		self.read_stream(io, &stream, off, &mut buf[..valid_len])?;
+		buf[valid_len..len].fill(0);
+		Ok(len as _)
+	}
+
+	fn write_node(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		buf: &[u8],
+	) -> Result<(), Errno> {
+		if self.readonly {
+			return Err(errno!(EROFS));
+		}
+		if inode == ROOT_INODE {
+			return Err(errno!(EISDIR));
+		}
+		let mut set = self.read_set(io, inode)?;
+		if set.is_directory() {
+			return Err(errno!(EISDIR));
+		}
+
+		let mut stream = set.get_stream();
+		let end = off
+			.checked_add(buf.len() as u64)
+			.ok_or_else(|| errno!(EFBIG))?;
+		if end > stream.size {
+			self.resize(io, &mut stream, end)?;
+		}
+		// The data between the valid size and the beginning of the write must be read as zeros
+		if off > stream.valid_size {
+			self.zero_stream(io, &stream, stream.valid_size, off - stream.valid_size)?;
+		}
+		self.write_stream(io, &stream, off, buf)?;
+		stream.valid_size = max(stream.valid_size, end);
+
+		set.set_stream(&stream);
+		set.set_attributes(set.get_attributes() | entry::ATTR_ARCHIVE);
+		self.write_set(io, inode, &mut set)
+	}
+}
+
+/// Structure representing the exFAT file system type.
+pub struct ExfatFsType {}
+
+impl FilesystemType for ExfatFsType {
+	fn get_name(&self) -> &'static [u8] {
+		b"exfat"
+	}
+
+	fn detect(&self, io: &mut dyn IO) -> Result<bool, Errno> {
+		Ok(BootSector::read(io)?.is_some())
+	}
+
+	fn get_mount_options(&self) -> &'static [MountOptionDesc] {
+		&[
+			MountOptionDesc {
+				name: b"uid",
+				has_value: true,
+			},
+			MountOptionDesc {
+				name: b"gid",
+				has_value: true,
+			},
+			MountOptionDesc {
+				name: b"umask",
+				has_value: true,
+			},
+			MountOptionDesc {
+				name: b"fmask",
+				has_value: true,
+			},
+			MountOptionDesc {
+				name: b"dmask",
+				has_value: true,
+			},
+		]
+	}
+
+	fn load_filesystem(
+		&self,
+		io: &mut dyn IO,
+		_mountpath: Path,
+		readonly: bool,
+		options: &MountOptions,
+	) -> Result<Arc<Mutex<dyn Filesystem>>, Errno> {
+		let boot = BootSector::read(io)?.ok_or_else(|| errno!(EINVAL))?;
+		let fs = ExfatFs::new(io, boot, readonly, options)?;
+
+		Ok(Arc::new(Mutex::new(fs))? as _)
+	}
+}
@@ -0,0 +1,87 @@
+//! The up-case table maps UTF-16 code units to their uppercase form.
+//!
+//! Names of exFAT are case-insensitive: two names are equal if they are equal once converted to
+//! uppercase through the table. Since the table is stored on the volume, the comparison does not
+//! depend on the version of Unicode known by the implementation.
+//!
+//! The table is compressed: a value of `0xffff` is followed by the number of code units which
+//! are mapped to themselves.
+
+use crate::errno;
+use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::util::container::vec::Vec;
+
+/// The value introducing a range of code units mapped to themselves.
+const IDENTITY_RANGE: u16 = 0xffff;
+/// The number of UTF-16 code units.
+const CODE_UNITS_COUNT: usize = 0x10000;
+
+/// The up-case table of a volume.
+pub struct UpcaseTable {
+	/// The uppercase form of each code unit, by value. Code units out of the table are mapped to
+	/// themselves.
+	table: Vec<u16>,
+}
+
+impl UpcaseTable {
+	/// Returns a table mapping every code units to themselves.
+	pub fn identity() -> Self {
+		Self {
+			table: Vec::new(),
+		}
+	}
+
+	/// Parses the table from its raw content `raw`.
+	///
+	/// `checksum` is the checksum of the table, as stored in its directory entry. If the content
+	/// does not match it, the function returns `EUCLEAN`.
+	pub fn parse(raw: &[u8], checksum: u32) -> EResult<Self> {
+		let sum = raw
+			.iter()
+			.fold(0, |sum: u32, b| sum.rotate_right(1).wrapping_add(*b as _));
+		if sum != checksum {
+			return Err(errno!(EUCLEAN));
+		}
+
+		let mut table = Vec::new();
+		let mut values = raw
+			.chunks_exact(2)
+			.map(|c| u16::from_le_bytes([c[0], c[1]]));
+		while let Some(value) = values.next() {
+			if table.len() >= CODE_UNITS_COUNT {
+				return Err(errno!(EUCLEAN));
+			}
+			if value != IDENTITY_RANGE {
+				table.push(value)?;
+				continue;
+			}
+			let count = values.next().ok_or_else(|| errno!(EUCLEAN))?;
+			if table.len() + count as usize > CODE_UNITS_COUNT {
+				return Err(errno!(EUCLEAN));
+			}
+			for _ in 0..count {
+				let c = table.len() as u16;
+				table.push(c)?;
+			}
+		}
+
+		Ok(Self {
+			table,
+		})
+	}
+
+	/// Returns the uppercase form of the code unit `c`.
+	pub fn upcase(&self, c: u16) -> u16 {
+		self.table.as_slice().get(c as usize).copied().unwrap_or(c)
+	}
+
+	/// Returns the uppercase form of the name `name`.
+	pub fn upcase_name(&self, name: &[u16]) -> AllocResult<Vec<u16>> {
+		let mut upcase = Vec::with_capacity(name.len())?;
+		for c in name {
+			upcase.push(self.upcase(*c))?;
+		}
+		Ok(upcase)
+	}
+}
@@ -15,6 +15,7 @@ use crate::file::FileType;
 use crate::file::Mode;
 use crate::limits;
 use crate::memory::malloc;
+use crate::time::unit::Timespec;
 use crate::util::boxed::Box;
 use crate::util::container::string::String;
 use crate::util::io::IO;
@@ -103,6 +104,13 @@ const INODE_FLAG_JOURNAL_FILE: u32 = 0x40000;
 /// The size of a sector in bytes.
 const SECTOR_SIZE: u32 = 512;
 
+/// The size of the base inode structure, which is the inode size on revision `0`.
+const BASE_INODE_SIZE: usize = 128;
+/// The value of `extra_isize` from which the extra timestamp fields are present.
+const EXTRA_TIMESTAMPS_SIZE: u16 = 16;
+/// Mask of the epoch bits in an extra timestamp field.
+const EXTRA_EPOCH_MASK: u32 = 0b11;
+
 /// The limit length for a symlink to be stored in the inode itself instead of a
 /// separate block.
 const SYMLINK_INODE_STORE_LIMIT: u64 = 60;
@@ -288,14 +296,14 @@ impl Ext2INode {
 	/// Increments the number of used sectors of one block.
 	///
 	/// `blk_size` is the size of a block.
-	fn increment_used_sectors(&mut self, blk_size: u32) {
+	pub fn increment_used_sectors(&mut self, blk_size: u32) {
 		self.used_sectors += math::ceil_div(blk_size, SECTOR_SIZE);
 	}
 
 	/// Decrements the number of used sectors of one block.
 	///
 	/// `blk_size` is the size of a block.
-	fn decrement_used_sectors(&mut self, blk_size: u32) {
+	pub fn decrement_used_sectors(&mut self, blk_size: u32) {
 		if self.used_sectors > 0 {
 			self.used_sectors -= math::ceil_div(blk_size, SECTOR_SIZE);
 		}
@@ -380,7 +388,7 @@ impl Ext2INode {
 	/// - `io` is the I/O interface.
 	///
 	/// If the block doesn't exist, the function returns `None`.
-	fn get_content_block_off(
+	pub fn get_content_block_off(
 		&self,
 		i: u32,
 		superblock: &Superblock,
@@ -576,10 +584,8 @@ impl Ext2INode {
 			}
 		}
 
-		// Remaining bytes to check
-		let remaining = blk.len() % size_of::<usize>();
 		// Checking the remaining bytes
-		for b in &blk[remaining..] {
+		for b in &blk[(len * size_of::<usize>())..] {
 			if *b != 0 {
 				return false;
 			}
@@ -623,9 +629,14 @@ impl Ext2INode {
 			let byte_off = (begin as u64 * blk_size as u64) + inner_off;
 
 			let b = unsafe { read::<u32>(byte_off, io)? };
+			if b == 0 {
+				return Ok(false);
+			}
 
 			let next_off = off - blk_per_blk * inner_index;
 			if self.indirections_free(n - 1, b, next_off, superblock, io)? {
+				// Removing the reference to the freed block
+				write::<u32>(&0, byte_off, io)?;
 				// Reading the current block
 				let mut buff =
 					malloc::Alloc::<u8>::new_default(NonZeroUsize::new(blk_size as _).unwrap())?;
@@ -643,6 +654,7 @@ impl Ext2INode {
 			Ok(false)
 		} else {
 			superblock.free_block(io, begin)?;
+			self.decrement_used_sectors(blk_size);
 			Ok(true)
 		}
 	}
@@ -668,9 +680,12 @@ impl Ext2INode {
 
 		// If direct block, handle it directly
 		if level == 0 {
-			superblock.free_block(io, self.direct_block_ptrs[i as usize])?;
-			self.direct_block_ptrs[i as usize] = 0;
-			self.decrement_used_sectors(blk_size);
+			let blk = self.direct_block_ptrs[i as usize];
+			if blk != 0 {
+				superblock.free_block(io, blk)?;
+				self.direct_block_ptrs[i as usize] = 0;
+				self.decrement_used_sectors(blk_size);
+			}
 
 			return Ok(());
 		}
@@ -695,11 +710,10 @@ impl Ext2INode {
 		};
 
 		if let Some(begin) = Self::blk_offset_to_option(begin_id) {
-			let empty = self.indirections_free(level, begin, target, superblock, io)?;
+			let freed = self.indirections_free(level, begin, target, superblock, io)?;
 
-			// If the block has zero entries left, free it
-			if empty {
-				superblock.free_block(io, begin)?;
+			// If the block had zero entries left, it has been freed
+			if freed {
 				match level {
 					1 => self.singly_indirect_block_ptr = 0,
 					2 => self.doubly_indirect_block_ptr = 0,
@@ -707,8 +721,6 @@ impl Ext2INode {
 
 					_ => unreachable!(),
 				}
-
-				self.decrement_used_sectors(blk_size);
 			}
 		}
 
@@ -833,8 +845,8 @@ impl Ext2INode {
 	/// - `io` is the I/O interface.
 	/// - `size` is the new size of the inode's content.
 	///
-	/// If `size` is greater than or equal to the previous size, the function
-	/// does nothing.
+	/// If `size` is greater than the previous size, the file is extended with a hole, which is
+	/// read as zeros and takes no block until written.
 	pub fn truncate(
 		&mut self,
 		superblock: &mut Superblock,
@@ -842,16 +854,33 @@ impl Ext2INode {
 		size: u64,
 	) -> Result<(), Errno> {
 		let old_size = self.get_size(superblock);
-		if size >= old_size {
+		if size == old_size {
 			return Ok(());
 		}
 
 		// Changing the size
 		self.set_size(superblock, size);
+		if size > old_size {
+			return Ok(());
+		}
 
 		// The size of a block
 		let blk_size = superblock.get_block_size();
 
+		// Zeroing the end of the last block, so that the data is not visible again if the file
+		// is extended later
+		let inner_off = (size % blk_size as u64) as usize;
+		if inner_off != 0 {
+			let blk_off = (size / blk_size as u64) as u32;
+			if let Some(blk_off) = self.get_content_block_off(blk_off, superblock, io)? {
+				let mut blk_buff =
+					malloc::Alloc::<u8>::new_default(NonZeroUsize::new(blk_size as _).unwrap())?;
+				read_block(blk_off as _, superblock, io, blk_buff.as_slice_mut())?;
+				blk_buff.as_slice_mut()[inner_off..].fill(0);
+				write_block(blk_off as _, superblock, io, blk_buff.as_slice())?;
+			}
+		}
+
 		// The index of the beginning block to free
 		let begin = math::ceil_div(size, blk_size as _) as u32;
 		// The index of the end block to free
@@ -864,6 +893,119 @@ impl Ext2INode {
 		Ok(())
 	}
 
+	/// Allocates the content blocks in the range of `len` bytes beginning at offset `off`.
+	///
+	/// Arguments:
+	/// - `off` is the offset of the beginning of the range.
+	/// - `len` is the length of the range.
+	/// - `superblock` is the filesystem's superblock.
+	/// - `io` is the I/O interface.
+	///
+	/// Blocks that are already allocated are left untouched. The size of the file is not changed,
+	/// which allows preallocating blocks past its end.
+	pub fn allocate(
+		&mut self,
+		off: u64,
+		len: u64,
+		superblock: &mut Superblock,
+		io: &mut dyn IO,
+	) -> Result<(), Errno> {
+		let blk_size = superblock.get_block_size() as u64;
+		let end = off.checked_add(len).ok_or_else(|| errno!(EFBIG))?;
+
+		let begin = off / blk_size;
+		let end = math::ceil_div(end, blk_size);
+		let end: u32 = end.try_into().map_err(|_| errno!(EFBIG))?;
+		for i in (begin as u32)..end {
+			if self.get_content_block_off(i, superblock, io)?.is_none() {
+				self.alloc_content_block(i, superblock, io)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Makes a hole in the range of `len` bytes beginning at offset `off`.
+	///
+	/// Arguments:
+	/// - `off` is the offset of the beginning of the range.
+	/// - `len` is the length of the range.
+	/// - `superblock` is the filesystem's superblock.
+	/// - `io` is the I/O interface.
+	///
+	/// Blocks that are entirely in the range are freed, while the parts of the blocks at the
+	/// edges of the range are zeroed. The range is limited to the size of the file, which is not
+	/// changed.
+	pub fn punch_hole(
+		&mut self,
+		off: u64,
+		len: u64,
+		superblock: &mut Superblock,
+		io: &mut dyn IO,
+	) -> Result<(), Errno> {
+		let blk_size = superblock.get_block_size() as u64;
+		let end = min(off.saturating_add(len), self.get_size(superblock));
+		if off >= end {
+			return Ok(());
+		}
+
+		let mut blk_buff =
+			malloc::Alloc::<u8>::new_default(NonZeroUsize::new(blk_size as _).unwrap())?;
+		for i in (off / blk_size)..math::ceil_div(end, blk_size) {
+			let blk_begin = i * blk_size;
+			let blk_end = blk_begin + blk_size;
+			if off <= blk_begin && blk_end <= end {
+				// TODO Optimize
+				self.free_content_block(i as _, superblock, io)?;
+				continue;
+			}
+
+			// Zeroing the part of the block that is in the range
+			let Some(blk_off) = self.get_content_block_off(i as _, superblock, io)? else {
+				continue;
+			};
+			let inner_begin = (max(off, blk_begin) - blk_begin) as usize;
+			let inner_end = (min(end, blk_end) - blk_begin) as usize;
+			read_block(blk_off as _, superblock, io, blk_buff.as_slice_mut())?;
+			blk_buff.as_slice_mut()[inner_begin..inner_end].fill(0);
+			write_block(blk_off as _, superblock, io, blk_buff.as_slice())?;
+		}
+
+		Ok(())
+	}
+
+	/// Returns the offset of the beginning of the next region of data, or of the next hole if
+	/// `hole` is set, at or after offset `off`.
+	///
+	/// Arguments:
+	/// - `off` is the offset from which the search begins.
+	/// - `hole` tells whether a hole is searched instead of data.
+	/// - `superblock` is the filesystem's superblock.
+	/// - `io` is the I/O interface.
+	///
+	/// Unallocated blocks are holes. If no such region is found before the end of the file, the
+	/// function returns `None`.
+	pub fn next_extent(
+		&self,
+		off: u64,
+		hole: bool,
+		superblock: &Superblock,
+		io: &mut dyn IO,
+	) -> Result<Option<u64>, Errno> {
+		let blk_size = superblock.get_block_size() as u64;
+		let end = math::ceil_div(self.get_size(superblock), blk_size);
+		for i in (off / blk_size)..end {
+			// TODO Optimize: skip whole unallocated indirection blocks
+			let allocated = self
+				.get_content_block_off(i as _, superblock, io)?
+				.is_some();
+			if allocated != hole {
+				return Ok(Some(max(i * blk_size, off)));
+			}
+		}
+		Ok(None)
+	}
+
 	/// Frees all content blocks by doing redirections.
 	///
 	/// Arguments:
@@ -1011,6 +1153,25 @@ impl Ext2INode {
 		&'n self,
 		superblock: &'s Superblock,
 		io: &'i mut dyn IO,
+	) -> Result<Option<DirentIterator<'n, 's, 'i>>, Errno> {
+		self.iter_dirent_from(superblock, io, 0)
+	}
+
+	/// Returns an iterator to the node's directory entries, starting at the entry located at
+	/// offset `off`.
+	///
+	/// Arguments:
+	/// - `superblock` is the filesystem's superblock.
+	/// - `io` is the I/O interface.
+	/// - `off` is the offset of the first entry. It must be the offset of an entry, or the size
+	/// of the directory.
+	///
+	/// If the node is not a directory, the function returns `None`.
+	pub fn iter_dirent_from<'n, 's, 'i>(
+		&'n self,
+		superblock: &'s Superblock,
+		io: &'i mut dyn IO,
+		off: u64,
 	) -> Result<Option<DirentIterator<'n, 's, 'i>>, Errno> {
 		if self.get_type() == FileType::Directory {
 			let blk_size = superblock.get_block_size();
@@ -1023,8 +1184,9 @@ impl Ext2INode {
 
 				buff: malloc::Alloc::<u8>::new_default(NonZeroUsize::new(blk_size as _).unwrap())?,
 
-				off: 0,
+				off,
 				size,
+				loaded: false,
 			}))
 		} else {
 			Ok(None)
@@ -1156,14 +1318,14 @@ impl Ext2INode {
 		}
 	}
 
-	// TODO Clean: Code from `foreach_directory_entry` has been duplicated to avoid
-	// borrow errors
 	/// Removes the entry from the current directory.
 	///
 	/// Arguments:
 	/// - `superblock` is the filesystem's superblock.
 	/// - `io` is the I/O interface.
 	/// - `name` is the name of the entry.
+	///
+	/// Content blocks following the last used entry are freed.
 	pub fn remove_dirent<S: AsRef<[u8]>>(
 		&mut self,
 		superblock: &mut Superblock,
@@ -1177,8 +1339,8 @@ impl Ext2INode {
 		let mut buff =
 			malloc::Alloc::<u8>::new_default(NonZeroUsize::new(blk_size as _).unwrap())?;
 
-		// The previous free entry with its offset
-		let mut prev_free: Option<(u64, Box<DirectoryEntry>)> = None;
+		// The number of content blocks up to the last one containing a used entry
+		let mut used_blks = 0;
 
 		let size = self.get_size(superblock);
 		let mut i = 0;
@@ -1186,6 +1348,10 @@ impl Ext2INode {
 			let len = min((size - i) as usize, blk_size as usize);
 			self.read_content(i, &mut buff.as_slice_mut()[..len], superblock, io)?;
 
+			// The previous entry in the block with its offset
+			let mut prev: Option<(u64, Box<DirectoryEntry>)> = None;
+			let mut used = false;
+
 			let mut j = 0;
 			while j < len {
 				// Safe because the data is block-aligned and an entry cannot be larger than the
@@ -1201,44 +1367,39 @@ impl Ext2INode {
 				// The offset of the entry
 				let off = i + j as u64;
 
-				if !entry.is_free() {
-					if entry.get_name(superblock) == name.as_ref() {
-						// The entry has name `name`, free it
+				if !entry.is_free() && entry.get_name(superblock) == name.as_ref() {
+					// Entries cannot cross block boundaries, so the entry is merged with the
+					// previous one in the same block. If first in the block, it is marked free
+					// instead
+					if let Some((prev_off, prev)) = &mut prev {
+						prev.merge(entry);
+						self.write_dirent(superblock, io, prev, *prev_off)?;
+					} else {
 						entry.set_inode(0);
 						self.write_dirent(superblock, io, &entry, off)?;
-
-						if let Some((prev_free_off, prev_free)) = &mut prev_free {
-							// Merging previous entry with the current if they are on the same page
-							if *prev_free_off >= i {
-								prev_free.merge(entry);
-								self.write_dirent(superblock, io, prev_free, *prev_free_off)?;
-							}
-						} else {
-							prev_free = Some((off, entry));
-						}
-					} else {
-						// The entry is not free, skip it
-						prev_free = None;
+						prev = Some((off, entry));
 					}
+				} else {
+					used |= !entry.is_free();
+					prev = Some((off, entry));
 				}
 
 				j += total_size;
 			}
 
+			if used {
+				used_blks = (i / blk_size as u64) as u32 + 1;
+			}
 			i += blk_size as u64;
 		}
 
-		// If the last content blocks can be freed, free them
-		if let Some((last_free_off, _)) = prev_free {
-			// The first content block that can be freed
-			let first_free_blk = math::ceil_div(last_free_off, blk_size as u64) as u32;
-			// The number of content blocks in the inode
-			let blk_count = math::ceil_div(self.get_size(superblock), blk_size as u64) as u32;
-
-			for i in first_free_blk..blk_count {
+		// Freeing the content blocks following the last used entry
+		let blk_count = math::ceil_div(size, blk_size as u64) as u32;
+		if used_blks < blk_count {
+			for i in used_blks..blk_count {
 				self.free_content_block(i, superblock, io)?;
 			}
-			self.set_size(superblock, first_free_blk as u64 * blk_size as u64);
+			self.set_size(superblock, used_blks as u64 * blk_size as u64);
 		}
 
 		Ok(())
@@ -1341,6 +1502,41 @@ impl Ext2INode {
 		self.direct_block_ptrs[0] = ((major as u32) << 8) | (minor as u32);
 	}
 
+	/// Returns the timestamps of the inode, in order: `ctime`, `mtime` and `atime`.
+	///
+	/// `extra` is the extended part of the inode. If it does not contain the extra timestamp
+	/// fields, timestamps have a precision of one second.
+	pub fn get_timestamps(&self, extra: Option<&Ext2INodeExtra>) -> [Timespec; 3] {
+		let (ctime_extra, mtime_extra, atime_extra) = match extra {
+			Some(extra) if extra.has_timestamps() => {
+				(extra.ctime_extra, extra.mtime_extra, extra.atime_extra)
+			}
+			_ => (0, 0, 0),
+		};
+		[
+			decode_timestamp(self.ctime, ctime_extra),
+			decode_timestamp(self.mtime, mtime_extra),
+			decode_timestamp(self.atime, atime_extra),
+		]
+	}
+
+	/// Sets the timestamps of the inode, in order: `ctime`, `mtime` and `atime`.
+	///
+	/// `extra` is the extended part of the inode. If it contains the extra timestamp fields, the
+	/// nanoseconds are stored in them too.
+	pub fn set_timestamps(&mut self, extra: Option<&mut Ext2INodeExtra>, ts: [Timespec; 3]) {
+		let [(ctime, ctime_extra), (mtime, mtime_extra), (atime, atime_extra)] =
+			ts.map(|ts| encode_timestamp(&ts));
+		self.ctime = ctime;
+		self.mtime = mtime;
+		self.atime = atime;
+		if let Some(extra) = extra.filter(|e| e.has_timestamps()) {
+			extra.ctime_extra = ctime_extra;
+			extra.mtime_extra = mtime_extra;
+			extra.atime_extra = atime_extra;
+		}
+	}
+
 	/// Writes the inode on the device.
 	pub fn write(&self, i: u32, superblock: &Superblock, io: &mut dyn IO) -> Result<(), Errno> {
 		let off = Self::get_disk_offset(i, superblock, io)?;
@@ -1348,6 +1544,101 @@ impl Ext2INode {
 	}
 }
 
+/// Decodes a timestamp from its seconds field `sec` and its extra field `extra`.
+///
+/// The two lowest bits of the extra field extend the seconds to 34 bits. The remaining bits are
+/// the nanoseconds.
+fn decode_timestamp(sec: u32, extra: u32) -> Timespec {
+	let sec = sec as i32 as i64 + (((extra & EXTRA_EPOCH_MASK) as i64) << 32);
+	Timespec {
+		tv_sec: max(sec, 0) as _,
+		tv_nsec: (extra >> 2) as _,
+	}
+}
+
+/// Encodes the timestamp `ts` into a seconds field and an extra field.
+fn encode_timestamp(ts: &Timespec) -> (u32, u32) {
+	let sec = ts.tv_sec as i64;
+	let epoch = ((sec - sec as i32 as i64) >> 32) as u32 & EXTRA_EPOCH_MASK;
+	(sec as u32, ((ts.tv_nsec as u32) << 2) | epoch)
+}
+
+/// The extended part of an inode, following the base structure when the inode size is larger
+/// than `128` bytes.
+///
+/// Only the fields covered by `extra_isize` are valid.
+#[repr(C, packed)]
+pub struct Ext2INodeExtra {
+	/// The size of the valid extended fields in bytes.
+	pub extra_isize: u16,
+	/// Upper 16 bits of the inode's checksum.
+	pub checksum_hi: u16,
+	/// Extra bits for the timestamp of the last modification of the metadata.
+	pub ctime_extra: u32,
+	/// Extra bits for the timestamp of the last modification of the content.
+	pub mtime_extra: u32,
+	/// Extra bits for the timestamp of the last access.
+	pub atime_extra: u32,
+	/// Timestamp of the creation.
+	pub crtime: u32,
+	/// Extra bits for the timestamp of the creation.
+	pub crtime_extra: u32,
+	/// Upper 32 bits of the version number.
+	pub version_hi: u32,
+	/// Project ID.
+	pub projid: u32,
+}
+
+impl Ext2INodeExtra {
+	/// Creates the extended part for a new inode.
+	///
+	/// If the size of inodes on the filesystem does not allow it, the function returns `None`.
+	pub fn new(superblock: &Superblock) -> Option<Self> {
+		if superblock.get_inode_size() < BASE_INODE_SIZE + size_of::<Self>() {
+			return None;
+		}
+		Some(Self {
+			extra_isize: size_of::<Self>() as _,
+			checksum_hi: 0,
+			ctime_extra: 0,
+			mtime_extra: 0,
+			atime_extra: 0,
+			crtime: 0,
+			crtime_extra: 0,
+			version_hi: 0,
+			projid: 0,
+		})
+	}
+
+	/// Reads the extended part of the `i`th inode from the given device.
+	///
+	/// If the size of inodes on the filesystem does not allow it, the function returns `None`.
+	///
+	/// Arguments:
+	/// - `i` is the inode's index (starting at `1`).
+	/// - `superblock` is the filesystem's superblock.
+	/// - `io` is the I/O interface.
+	pub fn read(i: u32, superblock: &Superblock, io: &mut dyn IO) -> Result<Option<Self>, Errno> {
+		if superblock.get_inode_size() < BASE_INODE_SIZE + size_of::<Self>() {
+			return Ok(None);
+		}
+		let off = Ext2INode::get_disk_offset(i, superblock, io)? + BASE_INODE_SIZE as u64;
+		let extra = unsafe { read::<Self>(off, io)? };
+		Ok(Some(extra))
+	}
+
+	/// Tells whether the extra timestamp fields are valid.
+	pub fn has_timestamps(&self) -> bool {
+		self.extra_isize >= EXTRA_TIMESTAMPS_SIZE
+	}
+
+	/// Writes the extended part of the `i`th inode on the device.
+	pub fn write(&self, i: u32, superblock: &Superblock, io: &mut dyn IO) -> Result<(), Errno> {
+		let off = Ext2INode::get_disk_offset(i, superblock, io)? + BASE_INODE_SIZE as u64;
+		write(self, off, io)
+	}
+}
+
 /// An itertor on the directory entries of a node (including free entries).
 ///
 /// The iterator gives the offset of the directory entry and the directory entry
@@ -1367,6 +1658,8 @@ pub struct DirentIterator<'n, 's, 'i> {
 	off: u64,
 	/// The size of the directory's data.
 	size: u64,
+	/// Tells whether the block containing the current offset is in the buffer.
+	loaded: bool,
 }
 
 impl<'n, 's, 'i> Iterator for DirentIterator<'n, 's, 'i> {
@@ -1380,19 +1673,19 @@ impl<'n, 's, 'i> Iterator for DirentIterator<'n, 's, 'i> {
 			return None;
 		}
 
-		// The length of the buffer
-		let len = min((self.size - self.off) as usize, blk_size as usize);
-
-		// At beginning, read the first block
-		if self.off == 0 {
+		// Read the block containing the entry if not done yet
+		if !self.loaded {
+			let blk_off = self.off - self.off % blk_size;
+			let len = min(self.size - blk_off, blk_size) as usize;
 			if let Err(e) = self.node.read_content(
-				self.off,
+				blk_off,
 				&mut self.buff.as_slice_mut()[..len],
 				self.superblock,
 				self.io,
 			) {
 				return Some(Err(e));
 			}
+			self.loaded = true;
 		}
 
 		// The offset of the entry in the current block
@@ -1414,16 +1707,9 @@ impl<'n, 's, 'i> Iterator for DirentIterator<'n, 's, 'i> {
 		let prev_off = self.off;
 		self.off += total_size as u64;
 
-		// If the block is over, read the next
+		// If the block is over, the next one has to be read
 		if self.off / blk_size > prev_off / blk_size {
-			if let Err(e) = self.node.read_content(
-				self.off,
-				&mut self.buff.as_slice_mut()[..len],
-				self.superblock,
-				self.io,
-			) {
-				return Some(Err(e));
-			}
+			self.loaded = false;
 		}
 
 		Some(Ok((prev_off, entry)))
@@ -22,15 +22,23 @@
 //! Since the size of a block pointer is 4 bytes, the maximum size of a file is:
 //! `(12 * n) + ((n/4) * n) + ((n/4)^^2 * n) + ((n/4)^^3 * n)`
 //! Where `n` is the size of a block.
+//!
+//! If the filesystem has a journal (ext3), it is replayed at mount and every modification is then
+//! performed in a transaction. See [`crate::file::fs::journal`].
 
 mod block_group_descriptor;
 mod directory_entry;
 mod inode;
+mod xattr;
 
 use crate::errno;
 use crate::errno::Errno;
+use crate::file::fs::journal::Journal;
+use crate::file::fs::journal::Transaction;
+use crate::file::fs::EntryVisitor;
 use crate::file::fs::Filesystem;
 use crate::file::fs::FilesystemType;
+use crate::file::fs::MountOptions;
 use crate::file::fs::Statfs;
 use crate::file::path::Path;
 use crate::file::perm::Gid;
@@ -44,7 +52,7 @@ use crate::file::INode;
 use crate::file::Mode;
 use crate::memory::malloc;
 use crate::time::clock;
-use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::clock::CLOCK_REALTIME;
 use crate::time::unit::TimestampScale;
 use crate::util::container::hashmap::HashMap;
 use crate::util::container::string::String;
@@ -53,7 +61,6 @@ use crate::util::io::IO;
 use crate::util::lock::Mutex;
 use crate::util::math;
 use crate::util::ptr::arc::Arc;
-use crate::util::TryClone;
 use block_group_descriptor::BlockGroupDescriptor;
 use core::cmp::max;
 use core::cmp::min;
@@ -64,6 +71,7 @@ use core::mem::MaybeUninit;
 use core::num::NonZeroUsize;
 use core::slice;
 use inode::Ext2INode;
+use inode::Ext2INodeExtra;
 
 // TODO Take into account user's UID/GID when allocating block/inode to handle
 // reserved blocks/inodes
@@ -359,8 +367,22 @@ impl Superblock {
 	}
 
 	/// Returns the number of block groups.
+	///
+	/// The last group may contain less blocks than the others.
 	fn get_block_groups_count(&self) -> u32 {
-		self.total_blocks / self.blocks_per_group
+		math::ceil_div(
+			self.total_blocks - self.superblock_block_number,
+			self.blocks_per_group,
+		)
+	}
+
+	/// Returns the block group of the block `blk` along with the index of the block in the
+	/// group.
+	///
+	/// The first block of the first group is the block containing the superblock.
+	fn get_block_group(&self, blk: u32) -> (u32, u32) {
+		let i = blk - self.superblock_block_number;
+		(i / self.blocks_per_group, i % self.blocks_per_group)
 	}
 
 	/// Returns the size of a fragment.
@@ -428,7 +450,9 @@ impl Superblock {
 			read_block(bitmap_blk_index as _, self, io, buff.as_slice_mut())?;
 
 			if let Some(j) = Self::search_bitmap_blk(buff.as_slice()) {
-				return Ok(Some(i * (blk_size * 8) + j));
+				let j = i * (blk_size * 8) + j;
+				// Bits past the end of the bitmap do not correspond to any entry
+				return Ok((j < size).then_some(j));
 			}
 
 			i += 1;
@@ -568,10 +592,10 @@ impl Superblock {
 		for i in 0..self.get_block_groups_count() {
 			let bgd = BlockGroupDescriptor::read(i as _, self, io)?;
 			if bgd.unallocated_blocks_number > 0 {
-				if let Some(j) =
-					self.search_bitmap(io, bgd.block_usage_bitmap_addr, self.blocks_per_group)?
-				{
-					let blk = i * self.blocks_per_group + j;
+				let first = self.superblock_block_number + i * self.blocks_per_group;
+				let size = min(self.blocks_per_group, self.total_blocks - first);
+				if let Some(j) = self.search_bitmap(io, bgd.block_usage_bitmap_addr, size)? {
+					let blk = first + j;
 					if blk > 2 && blk < self.total_blocks {
 						return Ok(blk);
 					} else {
@@ -599,10 +623,9 @@ impl Superblock {
 			return Err(errno!(EUCLEAN));
 		}
 
-		let group = blk / self.blocks_per_group;
+		let (group, bitfield_index) = self.get_block_group(blk);
 		let mut bgd = BlockGroupDescriptor::read(group, self, io)?;
 
-		let bitfield_index = blk % self.blocks_per_group;
 		let prev = self.set_bitmap(io, bgd.block_usage_bitmap_addr, bitfield_index, true)?;
 		if !prev {
 			bgd.unallocated_blocks_number -= 1;
@@ -629,10 +652,9 @@ impl Superblock {
 			return Err(errno!(EUCLEAN));
 		}
 
-		let group = blk / self.blocks_per_group;
+		let (group, bitfield_index) = self.get_block_group(blk);
 		let mut bgd = BlockGroupDescriptor::read(group, self, io)?;
 
-		let bitfield_index = blk % self.blocks_per_group;
 		let prev = self.set_bitmap(io, bgd.block_usage_bitmap_addr, bitfield_index, false)?;
 		if prev {
 			bgd.unallocated_blocks_number += 1;
@@ -660,6 +682,29 @@ struct Ext2Fs {
 
 	/// Tells whether the filesystem is mounted in read-only.
 	readonly: bool,
+
+	/// The filesystem's journal, if any.
+	///
+	/// If present, every modification of the filesystem is performed in a transaction.
+	journal: Option<Journal>,
+}
+
+/// Loads the journal of the filesystem with the superblock `superblock`.
+///
+/// `io` is the I/O interface.
+fn load_journal(superblock: &Superblock, io: &mut dyn IO) -> Result<Journal, Errno> {
+	let inode = Ext2INode::read(superblock.journal_inode, superblock, io)?;
+	let blk_size = superblock.get_block_size();
+	let count = inode.get_size(superblock) / blk_size as u64;
+
+	let mut blocks = Vec::with_capacity(count as _)?;
+	for i in 0..count {
+		let blk = inode
+			.get_content_block_off(i as _, superblock, io)?
+			.ok_or_else(|| errno!(EUCLEAN))?;
+		blocks.push(blk as _)?;
+	}
+	Journal::load(io, blocks, blk_size as _)
 }
 
 impl Ext2Fs {
@@ -685,10 +730,8 @@ impl Ext2Fs {
 		// Checking the filesystem doesn't require features that are not implemented by
 		// the driver
 		if superblock.major_version >= 1 {
-			// TODO Implement journal
-			let unsupported_required_features = REQUIRED_FEATURE_COMPRESSION
-				| REQUIRED_FEATURE_JOURNAL_REPLAY
-				| REQUIRED_FEATURE_JOURNAL_DEVIXE;
+			let unsupported_required_features =
+				REQUIRED_FEATURE_COMPRESSION | REQUIRED_FEATURE_JOURNAL_DEVIXE;
 
 			if superblock.required_features & unsupported_required_features != 0 {
 				// TODO Log?
@@ -704,7 +747,7 @@ impl Ext2Fs {
 			}
 		}
 
-		let timestamp = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Second)?;
+		let timestamp = clock::current_time(CLOCK_REALTIME, TimestampScale::Second)?;
 		if superblock.mount_count_since_fsck >= superblock.mount_count_before_fsck {
 			return Err(errno!(EINVAL));
 		}
@@ -713,6 +756,25 @@ impl Ext2Fs {
 			return Err(errno::EINVAL);
 		}*/
 
+		// Replaying the journal, in case the filesystem has not been unmounted cleanly
+		let has_journal = superblock.major_version >= 1
+			&& superblock.optional_features & OPTIONAL_FEATURE_JOURNAL != 0
+			&& superblock.journal_inode != 0;
+		let journal = if has_journal {
+			let mut journal = load_journal(&superblock, io)?;
+			journal.recover(io)?;
+			// TODO Clear the flag when unmounting
+			// The flag remains set while the filesystem is mounted in read-write
+			if readonly {
+				superblock.required_features &= !REQUIRED_FEATURE_JOURNAL_REPLAY;
+			} else {
+				superblock.required_features |= REQUIRED_FEATURE_JOURNAL_REPLAY;
+			}
+			Some(journal)
+		} else {
+			None
+		};
+
 		superblock.mount_count_since_fsck += 1;
 
 		// Setting the last mount path
@@ -742,12 +804,49 @@ impl Ext2Fs {
 			superblock,
 
 			readonly,
+
+			journal,
 		})
 	}
+
+	/// Executes `f` on the filesystem in a transaction, which is committed to the journal once
+	/// `f` returns.
+	///
+	/// If the filesystem has no journal, `f` writes directly to the device.
+	///
+	/// Since the in-memory state of the filesystem is not rolled back, modifications made before
+	/// an error are committed anyway.
+	fn journaled<T, F: FnOnce(&mut Self, &mut dyn IO) -> Result<T, Errno>>(
+		&mut self,
+		io: &mut dyn IO,
+		f: F,
+	) -> Result<T, Errno> {
+		let Some(mut journal) = self.journal.take() else {
+			return f(self, io);
+		};
+
+		let mut transaction = Transaction::new(io, self.superblock.get_block_size() as _);
+		let res = f(self, &mut transaction);
+		let blocks = transaction.into_blocks();
+		let commit_res = journal.commit(io, &blocks);
+		self.journal = Some(journal);
+
+		let val = res?;
+		commit_res?;
+		Ok(val)
+	}
+
+	/// Updates the timestamp of the last write, then writes the superblock on the device.
+	///
+	/// This function must be called after each operation modifying the filesystem, so that the
+	/// free blocks and inodes counts stay consistent with the bitmaps.
+	fn write_superblock(&mut self, io: &mut dyn IO) -> Result<(), Errno> {
+		let timestamp = clock::current_time(CLOCK_REALTIME, TimestampScale::Second)?;
+		self.superblock.last_write_timestamp = timestamp as _;
+		self.superblock.write(io)
+	}
 }
 
-// TODO Update the write timestamp when the fs is written (take mount flags into
-// account)
 impl Filesystem for Ext2Fs {
 	fn get_name(&self) -> &[u8] {
 		b"ext2"
@@ -813,42 +912,8 @@ impl Filesystem for Ext2Fs {
 		let file_content = match file_type {
 			FileType::Regular => FileContent::Regular,
 
-			FileType::Directory => {
-				let mut entries = Vec::new();
-
-				for res in inode_.iter_dirent(&self.superblock, io)?.unwrap() {
-					let (_, entry) = res?;
-					if entry.is_free() {
-						continue;
-					}
-
-					entries.push((
-						entry.get_inode(),
-						entry.get_type(&self.superblock),
-						String::try_from(entry.get_name(&self.superblock))?,
-					))?;
-				}
-
-				// Creating entries with types
-				let mut final_entries = HashMap::new();
-
-				for (inode, entry_type, name) in entries {
-					let entry_type = match entry_type {
-						Some(entry_type) => entry_type,
-						None => Ext2INode::read(inode, &self.superblock, io)?.get_type(),
-					};
-
-					final_entries.insert(
-						name.try_clone()?,
-						DirEntry {
-							inode: inode as _,
-							entry_type,
-						},
-					)?;
-				}
-
-				FileContent::Directory(final_entries)
-			}
+			// Entries are read on demand through `iter_entries`
+			FileType::Directory => FileContent::Directory(HashMap::new()),
 
 			FileType::Link => FileContent::Link(inode_.get_link(&self.superblock, io)?),
 
@@ -890,13 +955,62 @@ impl Filesystem for Ext2Fs {
 		file.set_hard_links_count(inode_.hard_links_count as _);
 		file.blocks_count = inode_.used_sectors as _;
 		file.set_size(inode_.get_size(&self.superblock));
-		file.ctime = inode_.ctime as _;
-		file.mtime = inode_.mtime as _;
-		file.atime = inode_.atime as _;
+		let extra = Ext2INodeExtra::read(inode as _, &self.superblock, io)?;
+		[file.ctime, file.mtime, file.atime] = inode_.get_timestamps(extra.as_ref());
 
 		Ok(file)
 	}
 
+	fn iter_entries(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		f: &mut EntryVisitor,
+	) -> Result<(), Errno> {
+		let inode_ = Ext2INode::read(inode as _, &self.superblock, io)?;
+		let blk_size = self.superblock.get_block_size() as u64;
+
+		let mut off = off;
+		loop {
+			// Entries are read one block at a time, so that the whole directory is never loaded
+			// in memory at once
+			let mut entries = Vec::new();
+			let mut done = true;
+			let iter = inode_
+				.iter_dirent_from(&self.superblock, io, off)?
+				.ok_or_else(|| errno!(ENOTDIR))?;
+			for res in iter {
+				let (entry_off, entry) = res?;
+				done = false;
+				off = entry_off + entry.get_total_size() as u64;
+				if !entry.is_free() {
+					entries.push((entry, off))?;
+				}
+				if off % blk_size == 0 {
+					break;
+				}
+			}
+			if done {
+				return Ok(());
+			}
+
+			for (entry, next) in entries {
+				let entry_type = match entry.get_type(&self.superblock) {
+					Some(entry_type) => entry_type,
+					None => Ext2INode::read(entry.get_inode(), &self.superblock, io)?.get_type(),
+				};
+				let dir_entry = DirEntry {
+					inode: entry.get_inode() as _,
+					entry_type,
+				};
+				if !f(entry.get_name(&self.superblock), &dir_entry, next)? {
+					return Ok(());
+				}
+			}
+		}
+	}
+
 	fn add_file(
 		&mut self,
 		io: &mut dyn IO,
@@ -911,111 +1025,122 @@ impl Filesystem for Ext2Fs {
 			return Err(errno!(EROFS));
 		}
 
-		let mut parent = Ext2INode::read(parent_inode as _, &self.superblock, io)?;
-
-		// Checking the parent file is a directory
-		if parent.get_type() != FileType::Directory {
-			return Err(errno!(ENOTDIR));
-		}
-
-		// Checking if the file already exists
-		if parent.get_dirent(&name, &self.superblock, io)?.is_some() {
-			return Err(errno!(EEXIST));
-		}
-
-		let inode_index = self.superblock.get_free_inode(io)?;
-		let location = FileLocation::Filesystem {
-			mountpoint_id: 0, // dummy value to be replaced
-			inode: inode_index as _,
-		};
-
-		// The file
-		let mut file = File::new(name, uid, gid, mode, location, content)?;
-
-		let mut inode = Ext2INode {
-			mode: Ext2INode::get_file_mode(file.get_type(), mode),
-			uid,
-			size_low: 0,
-			ctime: file.ctime as _,
-			mtime: file.mtime as _,
-			atime: file.atime as _,
-			dtime: 0,
-			gid,
-			hard_links_count: 1,
-			used_sectors: 0,
-			flags: 0,
-			os_specific_0: 0,
-			direct_block_ptrs: [0; inode::DIRECT_BLOCKS_COUNT as usize],
-			singly_indirect_block_ptr: 0,
-			doubly_indirect_block_ptr: 0,
-			triply_indirect_block_ptr: 0,
-			generation: 0,
-			extended_attributes_block: 0,
-			size_high: 0,
-			fragment_addr: 0,
-			os_specific_1: [0; 12],
-		};
+		self.journaled(io, |fs, io| {
+			let mut parent = Ext2INode::read(parent_inode as _, &fs.superblock, io)?;
 
-		match file.get_content() {
-			FileContent::Directory(_) => {
-				// Adding `.` and `..` entries
-				inode.add_dirent(
-					&mut self.superblock,
-					io,
-					inode_index,
-					b".",
-					FileType::Directory,
-				)?;
-				inode.hard_links_count += 1;
-				file.set_hard_links_count(inode.hard_links_count);
-
-				inode.add_dirent(
-					&mut self.superblock,
-					io,
-					parent_inode as _,
-					b"..",
-					FileType::Directory,
-				)?;
-				parent.hard_links_count += 1;
+			// Checking the parent file is a directory
+			if parent.get_type() != FileType::Directory {
+				return Err(errno!(ENOTDIR));
 			}
 
-			FileContent::Link(target) => {
-				inode.set_link(&mut self.superblock, io, target.as_bytes())?
+			// Checking if the file already exists
+			if parent.get_dirent(&name, &fs.superblock, io)?.is_some() {
+				return Err(errno!(EEXIST));
 			}
 
-			FileContent::BlockDevice {
-				major,
-				minor,
-			}
-			| FileContent::CharDevice {
-				major,
-				minor,
-			} => {
-				if *major > (u8::MAX as u32) || *minor > (u8::MAX as u32) {
-					return Err(errno!(ENODEV));
+			let inode_index = fs.superblock.get_free_inode(io)?;
+			// The generation changes each time the inode is reused, to invalidate file handles
+			let generation = Ext2INode::read(inode_index, &fs.superblock, io)?
+				.generation
+				.wrapping_add(1);
+			let location = FileLocation::Filesystem {
+				mountpoint_id: 0, // dummy value to be replaced
+				inode: inode_index as _,
+			};
+
+			// The file
+			let mut file = File::new(name, uid, gid, mode, location, content)?;
+
+			let mut inode = Ext2INode {
+				mode: Ext2INode::get_file_mode(file.get_type(), mode),
+				uid,
+				size_low: 0,
+				ctime: 0,
+				mtime: 0,
+				atime: 0,
+				dtime: 0,
+				gid,
+				hard_links_count: 1,
+				used_sectors: 0,
+				flags: 0,
+				os_specific_0: 0,
+				direct_block_ptrs: [0; inode::DIRECT_BLOCKS_COUNT as usize],
+				singly_indirect_block_ptr: 0,
+				doubly_indirect_block_ptr: 0,
+				triply_indirect_block_ptr: 0,
+				generation,
+				extended_attributes_block: 0,
+				size_high: 0,
+				fragment_addr: 0,
+				os_specific_1: [0; 12],
+			};
+			let mut extra = Ext2INodeExtra::new(&fs.superblock);
+			inode.set_timestamps(extra.as_mut(), [file.ctime, file.mtime, file.atime]);
+
+			match file.get_content() {
+				FileContent::Directory(_) => {
+					// Adding `.` and `..` entries
+					inode.add_dirent(
+						&mut fs.superblock,
+						io,
+						inode_index,
+						b".",
+						FileType::Directory,
+					)?;
+					inode.hard_links_count += 1;
+					file.set_hard_links_count(inode.hard_links_count);
+
+					inode.add_dirent(
+						&mut fs.superblock,
+						io,
+						parent_inode as _,
+						b"..",
+						FileType::Directory,
+					)?;
+					parent.hard_links_count += 1;
 				}
 
-				inode.set_device(*major as u8, *minor as u8);
-			}
+				FileContent::Link(target) => {
+					inode.set_link(&mut fs.superblock, io, target.as_bytes())?
+				}
 
-			_ => {}
-		}
+				FileContent::BlockDevice {
+					major,
+					minor,
+				}
+				| FileContent::CharDevice {
+					major,
+					minor,
+				} => {
+					if *major > (u8::MAX as u32) || *minor > (u8::MAX as u32) {
+						return Err(errno!(ENODEV));
+					}
 
-		inode.write(inode_index, &self.superblock, io)?;
-		let dir = file.get_type() == FileType::Directory;
-		self.superblock.mark_inode_used(io, inode_index, dir)?;
-		self.superblock.write(io)?;
-
-		parent.add_dirent(
-			&mut self.superblock,
-			io,
-			inode_index,
-			file.get_name(),
-			file.get_type(),
-		)?;
-		parent.write(parent_inode as _, &self.superblock, io)?;
+					inode.set_device(*major as u8, *minor as u8);
+				}
 
-		Ok(file)
+				_ => {}
+			}
+
+			inode.write(inode_index, &fs.superblock, io)?;
+			if let Some(extra) = &extra {
+				extra.write(inode_index, &fs.superblock, io)?;
+			}
+			let dir = file.get_type() == FileType::Directory;
+			fs.superblock.mark_inode_used(io, inode_index, dir)?;
+
+			parent.add_dirent(
+				&mut fs.superblock,
+				io,
+				inode_index,
+				file.get_name(),
+				file.get_type(),
+			)?;
+			parent.write(parent_inode as _, &fs.superblock, io)?;
+			fs.write_superblock(io)?;
+
+			Ok(file)
+		})
 	}
 
 	fn add_link(
@@ -1029,74 +1154,79 @@ impl Filesystem for Ext2Fs {
 			return Err(errno!(EROFS));
 		}
 
-		// Parent inode
-		let mut parent = Ext2INode::read(parent_inode as _, &self.superblock, io)?;
+		self.journaled(io, |fs, io| {
+			// Parent inode
+			let mut parent = Ext2INode::read(parent_inode as _, &fs.superblock, io)?;
 
-		// Checking the parent file is a directory
-		if parent.get_type() != FileType::Directory {
-			return Err(errno!(ENOTDIR));
-		}
+			// Checking the parent file is a directory
+			if parent.get_type() != FileType::Directory {
+				return Err(errno!(ENOTDIR));
+			}
 
-		// Checking the entry doesn't exist
-		if parent.get_dirent(name, &self.superblock, io)?.is_some() {
-			return Err(errno!(EEXIST));
-		}
+			// Checking the entry doesn't exist
+			if parent.get_dirent(name, &fs.superblock, io)?.is_some() {
+				return Err(errno!(EEXIST));
+			}
 
-		// The inode
-		let mut inode_ = Ext2INode::read(inode as _, &self.superblock, io)?;
-		// Checking the maximum number of links is not exceeded
-		if inode_.hard_links_count >= u16::MAX {
-			return Err(errno!(EMFILE));
-		}
+			// The inode
+			let mut inode_ = Ext2INode::read(inode as _, &fs.superblock, io)?;
+			// Checking the maximum number of links is not exceeded
+			if inode_.hard_links_count >= u16::MAX {
+				return Err(errno!(EMFILE));
+			}
 
-		match inode_.get_type() {
-			FileType::Directory => {
-				// Removing previous dirent
-				let old_parent_entry = inode_.get_dirent(b"..", &self.superblock, io)?;
-				if let Some((_, old_parent_entry)) = old_parent_entry {
-					let old_parent_inode = old_parent_entry.get_inode();
-					let mut old_parent =
-						Ext2INode::read(old_parent_inode as _, &self.superblock, io)?;
-					// TODO Write a function to remove by inode instead of name
-					if let Some(iter) = old_parent.iter_dirent(&self.superblock, io)? {
-						for res in iter {
-							let (_, e) = res?;
-
-							if e.get_inode() == inode as _ {
-								let ent_name = e.get_name(&self.superblock);
-								old_parent.remove_dirent(&mut self.superblock, io, ent_name)?;
-
-								break;
+			match inode_.get_type() {
+				FileType::Directory => {
+					if let Some((off, mut entry)) = inode_.get_dirent(b"..", &fs.superblock, io)? {
+						// Removing the entry from the previous parent
+						let old_parent_inode = entry.get_inode();
+						let mut old_parent = if old_parent_inode == parent_inode as u32 {
+							None
+						} else {
+							Some(Ext2INode::read(old_parent_inode, &fs.superblock, io)?)
+						};
+						let old_parent_ref = old_parent.as_mut().unwrap_or(&mut parent);
+						// TODO Write a function to remove by inode instead of name
+						let mut old_name = None;
+						if let Some(iter) = old_parent_ref.iter_dirent(&fs.superblock, io)? {
+							for res in iter {
+								let (_, e) = res?;
+								if e.get_inode() == inode as _ {
+									old_name = Some(String::try_from(e.get_name(&fs.superblock))?);
+									break;
+								}
 							}
 						}
+						if let Some(old_name) = old_name {
+							old_parent_ref.remove_dirent(&mut fs.superblock, io, old_name)?;
+						}
+						// The `..` entry of the directory does not point to its previous parent
+						// anymore
+						old_parent_ref.hard_links_count -= 1;
+						if let Some(old_parent) = old_parent {
+							old_parent.write(old_parent_inode, &fs.superblock, io)?;
+						}
+
+						// Updating the `..` entry
+						entry.set_inode(parent_inode as _);
+						inode_.write_dirent(&mut fs.superblock, io, &entry, off)?;
+						parent.hard_links_count += 1;
 					}
 				}
 
-				// Updating the `..` entry
-				if let Some((off, mut entry)) = inode_.get_dirent(b"..", &self.superblock, io)? {
-					entry.set_inode(parent_inode as _);
-					inode_.write_dirent(&mut self.superblock, io, &entry, off)?;
+				_ => {
+					// Updating links count
+					inode_.hard_links_count += 1;
 				}
 			}
 
-			_ => {
-				// Updating links count
-				inode_.hard_links_count += 1;
-			}
-		}
-
-		// Writing directory entry
-		parent.add_dirent(
-			&mut self.superblock,
-			io,
-			inode as _,
-			name,
-			inode_.get_type(),
-		)?;
+			// Writing directory entry
+			parent.add_dirent(&mut fs.superblock, io, inode as _, name, inode_.get_type())?;
 
-		parent.write(parent_inode as _, &self.superblock, io)?;
-		inode_.write(inode as _, &self.superblock, io)?;
-		Ok(())
+			parent.write(parent_inode as _, &fs.superblock, io)?;
+			inode_.write(inode as _, &fs.superblock, io)?;
+			fs.write_superblock(io)
+		})
 	}
 
 	fn update_inode(&mut self, io: &mut dyn IO, file: &File) -> Result<(), Errno> {
@@ -1104,22 +1234,27 @@ impl Filesystem for Ext2Fs {
 			return Err(errno!(EROFS));
 		}
 
-		// The inode number
-		let inode = file.get_location().get_inode();
-		// The inode
-		let mut inode_ = Ext2INode::read(inode as _, &self.superblock, io)?;
-
-		// Changing file size if it has been truncated
-		inode_.truncate(&mut self.superblock, io, file.get_size())?;
-
-		// Updating file attributes
-		inode_.uid = file.get_uid();
-		inode_.gid = file.get_gid();
-		inode_.set_permissions(file.get_permissions());
-		inode_.ctime = file.ctime as _;
-		inode_.mtime = file.mtime as _;
-		inode_.atime = file.atime as _;
-		inode_.write(inode as _, &self.superblock, io)
+		self.journaled(io, |fs, io| {
+			// The inode number
+			let inode = file.get_location().get_inode();
+			// The inode
+			let mut inode_ = Ext2INode::read(inode as _, &fs.superblock, io)?;
+
+			// Changing file size if it has been truncated
+			inode_.truncate(&mut fs.superblock, io, file.get_size())?;
+
+			// Updating file attributes
+			inode_.uid = file.get_uid();
+			inode_.gid = file.get_gid();
+			inode_.set_permissions(file.get_permissions());
+			let mut extra = Ext2INodeExtra::read(inode as _, &fs.superblock, io)?;
+			inode_.set_timestamps(extra.as_mut(), [file.ctime, file.mtime, file.atime]);
+			inode_.write(inode as _, &fs.superblock, io)?;
+			if let Some(extra) = &extra {
+				extra.write(inode as _, &fs.superblock, io)?;
+			}
+			fs.write_superblock(io)
+		})
 	}
 
 	fn remove_file(
@@ -1131,74 +1266,88 @@ impl Filesystem for Ext2Fs {
 		if unlikely(self.readonly) {
 			return Err(errno!(EROFS));
 		}
-		if parent_inode < 1 {
-			return Err(errno!(EINVAL));
-		}
 
-		if name == b"." || name == b".." {
-			return Err(errno!(EINVAL));
-		}
+		self.journaled(io, |fs, io| {
+			if parent_inode < 1 {
+				return Err(errno!(EINVAL));
+			}
 
-		// The parent inode
-		let mut parent = Ext2INode::read(parent_inode as _, &self.superblock, io)?;
+			if name == b"." || name == b".." {
+				return Err(errno!(EINVAL));
+			}
 
-		// Checking the parent file is a directory
-		if parent.get_type() != FileType::Directory {
-			return Err(errno!(ENOTDIR));
-		}
+			// The parent inode
+			let mut parent = Ext2INode::read(parent_inode as _, &fs.superblock, io)?;
 
-		// The inode number
-		let inode = parent
-			.get_dirent(name, &self.superblock, io)?
-			.map(|(_, ent)| ent)
-			.ok_or_else(|| errno!(ENOENT))?
-			.get_inode();
-		// The inode
-		let mut inode_ = Ext2INode::read(inode, &self.superblock, io)?;
-
-		// If directory, removing `.` and `..` entries
-		if inode_.get_type() == FileType::Directory {
-			// Removing `.`
-			if inode_.hard_links_count > 0
-				&& inode_.get_dirent(b".", &self.superblock, io)?.is_some()
-			{
-				inode_.hard_links_count -= 1;
+			// Checking the parent file is a directory
+			if parent.get_type() != FileType::Directory {
+				return Err(errno!(ENOTDIR));
 			}
 
-			// Removing `..`
-			if parent.hard_links_count > 0
-				&& inode_.get_dirent(b"..", &self.superblock, io)?.is_some()
-			{
-				parent.hard_links_count -= 1;
+			// The inode number
+			let inode = parent
+				.get_dirent(name, &fs.superblock, io)?
+				.map(|(_, ent)| ent)
+				.ok_or_else(|| errno!(ENOENT))?
+				.get_inode();
+			// The inode
+			let mut inode_ = Ext2INode::read(inode, &fs.superblock, io)?;
+
+			// If directory, removing `.` and `..` entries
+			if inode_.get_type() == FileType::Directory {
+				// Checking the directory is empty
+				for res in inode_.iter_dirent(&fs.superblock, io)?.unwrap() {
+					let (_, entry) = res?;
+					let name = entry.get_name(&fs.superblock);
+					if !entry.is_free() && name != b"." && name != b".." {
+						return Err(errno!(ENOTEMPTY));
+					}
+				}
+
+				// Removing `.`
+				if inode_.hard_links_count > 0
+					&& inode_.get_dirent(b".", &fs.superblock, io)?.is_some()
+				{
+					inode_.hard_links_count -= 1;
+				}
+
+				// Removing `..`
+				if parent.hard_links_count > 0
+					&& inode_.get_dirent(b"..", &fs.superblock, io)?.is_some()
+				{
+					parent.hard_links_count -= 1;
+				}
 			}
-		}
 
-		// Removing the directory entry
-		parent.remove_dirent(&mut self.superblock, io, name)?;
-		parent.write(parent_inode as _, &self.superblock, io)?;
+			// Removing the directory entry
+			parent.remove_dirent(&mut fs.superblock, io, name)?;
+			parent.write(parent_inode as _, &fs.superblock, io)?;
 
-		// Decrementing the hard links count
-		if inode_.hard_links_count > 0 {
-			inode_.hard_links_count -= 1;
-		}
+			// Decrementing the hard links count
+			if inode_.hard_links_count > 0 {
+				inode_.hard_links_count -= 1;
+			}
 
-		// If this is the last link, remove the inode
-		if inode_.hard_links_count <= 0 {
-			let timestamp = clock::current_time(clock::CLOCK_MONOTONIC, TimestampScale::Second)?;
-			inode_.dtime = timestamp as _;
+			// If this is the last link, remove the inode
+			if inode_.hard_links_count <= 0 {
+				let timestamp =
+					clock::current_time(clock::CLOCK_REALTIME, TimestampScale::Second)?;
+				inode_.dtime = timestamp as _;
 
-			inode_.free_content(&mut self.superblock, io)?;
+				inode_.free_content(&mut fs.superblock, io)?;
+				inode_.free_xattr(&mut fs.superblock, io)?;
 
-			// Freeing inode
-			self.superblock
-				.free_inode(io, inode, inode_.get_type() == FileType::Directory)?;
-			self.superblock.write(io)?;
-		}
+				// Freeing inode
+				fs.superblock
+					.free_inode(io, inode, inode_.get_type() == FileType::Directory)?;
+			}
 
-		// Writing the inode
-		inode_.write(inode, &self.superblock, io)?;
+			// Writing the inode
+			inode_.write(inode, &fs.superblock, io)?;
+			fs.write_superblock(io)?;
 
-		Ok(inode_.hard_links_count)
+			Ok(inode_.hard_links_count)
+		})
 	}
 
 	fn read_node(
@@ -1226,15 +1375,131 @@ impl Filesystem for Ext2Fs {
 		if unlikely(self.readonly) {
 			return Err(errno!(EROFS));
 		}
+
+		self.journaled(io, |fs, io| {
+			if inode < 1 {
+				return Err(errno!(EINVAL));
+			}
+
+			let mut inode_ = Ext2INode::read(inode as _, &fs.superblock, io)?;
+			inode_.write_content(off, buf, &mut fs.superblock, io)?;
+			inode_.write(inode as _, &fs.superblock, io)?;
+
+			fs.write_superblock(io)
+		})
+	}
+
+	fn allocate(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		len: u64,
+	) -> Result<(), Errno> {
+		if unlikely(self.readonly) {
+			return Err(errno!(EROFS));
+		}
+
+		self.journaled(io, |fs, io| {
+			if inode < 1 {
+				return Err(errno!(EINVAL));
+			}
+
+			let mut inode_ = Ext2INode::read(inode as _, &fs.superblock, io)?;
+			let res = inode_.allocate(off, len, &mut fs.superblock, io);
+			// Blocks allocated before a failure remain referenced by the inode
+			inode_.write(inode as _, &fs.superblock, io)?;
+			fs.write_superblock(io)?;
+
+			res
+		})
+	}
+
+	fn punch_hole(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		len: u64,
+	) -> Result<(), Errno> {
+		if unlikely(self.readonly) {
+			return Err(errno!(EROFS));
+		}
+
+		self.journaled(io, |fs, io| {
+			if inode < 1 {
+				return Err(errno!(EINVAL));
+			}
+
+			let mut inode_ = Ext2INode::read(inode as _, &fs.superblock, io)?;
+			inode_.punch_hole(off, len, &mut fs.superblock, io)?;
+			inode_.write(inode as _, &fs.superblock, io)?;
+
+			fs.write_superblock(io)
+		})
+	}
+
+	fn next_extent(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		hole: bool,
+	) -> Result<Option<u64>, Errno> {
 		if inode < 1 {
 			return Err(errno!(EINVAL));
 		}
 
-		let mut inode_ = Ext2INode::read(inode as _, &self.superblock, io)?;
-		inode_.write_content(off, buf, &mut self.superblock, io)?;
-		inode_.write(inode as _, &self.superblock, io)?;
+		let inode_ = Ext2INode::read(inode as _, &self.superblock, io)?;
+		inode_.next_extent(off, hole, &self.superblock, io)
+	}
 
-		self.superblock.write(io)
+	fn get_xattr(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		name: &[u8],
+	) -> Result<Option<Vec<u8>>, Errno> {
+		let inode_ = Ext2INode::read(inode as _, &self.superblock, io)?;
+		inode_.get_xattr(name, &self.superblock, io)
+	}
+
+	fn list_xattr(&mut self, io: &mut dyn IO, inode: INode) -> Result<Vec<String>, Errno> {
+		let inode_ = Ext2INode::read(inode as _, &self.superblock, io)?;
+		inode_.list_xattr(&self.superblock, io)
+	}
+
+	fn set_xattr(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		name: &[u8],
+		value: Option<&[u8]>,
+	) -> Result<bool, Errno> {
+		if unlikely(self.readonly) {
+			return Err(errno!(EROFS));
+		}
+
+		self.journaled(io, |fs, io| {
+			let mut inode_ = Ext2INode::read(inode as _, &fs.superblock, io)?;
+			let exists = inode_.set_xattr(name, value, &mut fs.superblock, io)?;
+			inode_.write(inode as _, &fs.superblock, io)?;
+			fs.write_superblock(io)?;
+
+			Ok(exists)
+		})
+	}
+
+	fn get_generation(&mut self, io: &mut dyn IO, inode: INode) -> Result<u32, Errno> {
+		if inode < 1 || inode > self.superblock.total_inodes as INode {
+			return Err(errno!(ESTALE));
+		}
+		let inode_ = Ext2INode::read(inode as _, &self.superblock, io)?;
+		// The inode has been freed
+		if inode_.hard_links_count == 0 {
+			return Err(errno!(ESTALE));
+		}
+		Ok(inode_.generation)
 	}
 }
 
@@ -1255,6 +1520,7 @@ impl FilesystemType for Ext2FsType {
 		io: &mut dyn IO,
 		mountpath: Path,
 		readonly: bool,
+		_options: &MountOptions,
 	) -> Result<Arc<Mutex<dyn Filesystem>>, Errno> {
 		let superblock = Superblock::read(io)?;
 		let fs = Ext2Fs::new(superblock, io, mountpath, readonly)?;
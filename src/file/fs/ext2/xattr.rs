@@ -0,0 +1,389 @@
+//! The extended attributes of an inode are stored in a single block, referenced by the inode.
+//!
+//! The block begins with a header, followed by the list of entries, sorted by name and
+//! terminated by four zero bytes. The values of the entries are stored at the end of the block,
+//! growing downwards.
+//!
+//! A block may be shared by several inodes having the same attributes. In this case, the block
+//! is copied before being modified.
+
+use super::inode::Ext2INode;
+use super::read_block;
+use super::write_block;
+use super::Superblock;
+use super::OPTIONAL_FEATURE_INODE_EXTENDED;
+use crate::errno;
+use crate::errno::EResult;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+use core::cmp::Ordering;
+
+/// The signature of an extended attributes block.
+const XATTR_MAGIC: u32 = 0xea020000;
+/// The size of the header of an extended attributes block.
+const HEADER_SIZE: usize = 32;
+/// The size of an entry, without its name.
+const ENTRY_HEADER_SIZE: usize = 16;
+
+/// The prefix of names for each namespace index.
+///
+/// For ACLs, the prefix is the full name of the attribute.
+const PREFIXES: [(u8, &[u8]); 5] = [
+	(1, b"user."),
+	(2, b"system.posix_acl_access"),
+	(3, b"system.posix_acl_default"),
+	(4, b"trusted."),
+	(6, b"security."),
+];
+
+/// Splits the full name `name` of an attribute into its namespace index and the rest of the
+/// name.
+///
+/// If the namespace is not supported, the function returns `None`.
+fn split_name(name: &[u8]) -> Option<(u8, &[u8])> {
+	PREFIXES.iter().find_map(|(index, prefix)| {
+		let suffix = name.strip_prefix(*prefix)?;
+		let is_acl = matches!(index, 2 | 3);
+		(is_acl == suffix.is_empty()).then_some((*index, suffix))
+	})
+}
+
+/// Rounds `n` up to a multiple of four bytes.
+fn pad(n: usize) -> usize {
+	(n + 3) & !3
+}
+
+/// Reads a little-endian `u16` at offset `off` in `buf`.
+fn read_u16(buf: &[u8], off: usize) -> u16 {
+	u16::from_le_bytes([buf[off], buf[off + 1]])
+}
+
+/// Reads a little-endian `u32` at offset `off` in `buf`.
+fn read_u32(buf: &[u8], off: usize) -> u32 {
+	u32::from_le_bytes([buf[off], buf[off + 1], buf[off + 2], buf[off + 3]])
+}
+
+/// Writes the little-endian `u32` `val` at offset `off` in `buf`.
+fn write_u32(buf: &mut [u8], off: usize, val: u32) {
+	buf[off..(off + 4)].copy_from_slice(&val.to_le_bytes());
+}
+
+/// An extended attribute.
+struct Entry {
+	/// The index of the namespace.
+	index: u8,
+	/// The name, without the namespace prefix.
+	name: Vec<u8>,
+	/// The value.
+	value: Vec<u8>,
+}
+
+impl Entry {
+	/// Compares the entry with the given index and name, in the order in which entries are
+	/// sorted in the block.
+	fn cmp_key(&self, index: u8, name: &[u8]) -> Ordering {
+		self.index
+			.cmp(&index)
+			.then(self.name.len().cmp(&name.len()))
+			.then(self.name.as_slice().cmp(name))
+	}
+
+	/// Returns the hash of the entry.
+	fn hash(&self) -> u32 {
+		let mut hash: u32 = 0;
+		for b in self.name.iter() {
+			// Names are hashed as signed characters
+			hash = (hash << 5) ^ (hash >> 27) ^ (*b as i8 as u32);
+		}
+		for word in self.value.chunks(4) {
+			let mut bytes = [0; 4];
+			bytes[..word.len()].copy_from_slice(word);
+			hash = (hash << 16) ^ (hash >> 16) ^ u32::from_le_bytes(bytes);
+		}
+		hash
+	}
+}
+
+/// Reads the extended attributes block `blk`, returning its reference count and its entries.
+fn read_entries(blk: u32, superblock: &Superblock, io: &mut dyn IO) -> EResult<(u32, Vec<Entry>)> {
+	let blk_size = superblock.get_block_size() as usize;
+	let mut buf = Vec::from_elem(0u8, blk_size)?;
+	read_block(blk as _, superblock, io, buf.as_mut_slice())?;
+
+	if read_u32(&buf, 0) != XATTR_MAGIC || read_u32(&buf, 8) != 1 {
+		return Err(errno!(EUCLEAN));
+	}
+	let refcount = read_u32(&buf, 4);
+
+	let mut entries = Vec::new();
+	let mut off = HEADER_SIZE;
+	loop {
+		if off + 4 > blk_size {
+			return Err(errno!(EUCLEAN));
+		}
+		if read_u32(&buf, off) == 0 {
+			break;
+		}
+		if off + ENTRY_HEADER_SIZE > blk_size {
+			return Err(errno!(EUCLEAN));
+		}
+
+		let name_len = buf[off] as usize;
+		let index = buf[off + 1];
+		let value_off = read_u16(&buf, off + 2) as usize;
+		let value_inode = read_u32(&buf, off + 4);
+		let value_size = read_u32(&buf, off + 8) as usize;
+
+		let name_begin = off + ENTRY_HEADER_SIZE;
+		let name_end = name_begin + name_len;
+		let value_end = value_off + value_size;
+		// Values stored in a separate inode are not supported
+		if name_end > blk_size || value_end > blk_size || value_inode != 0 {
+			return Err(errno!(EUCLEAN));
+		}
+
+		let mut name = Vec::new();
+		name.extend_from_slice(&buf[name_begin..name_end])?;
+		let mut value = Vec::new();
+		value.extend_from_slice(&buf[value_off..value_end])?;
+		entries.push(Entry {
+			index,
+			name,
+			value,
+		})?;
+
+		off += pad(ENTRY_HEADER_SIZE + name_len);
+	}
+
+	Ok((refcount, entries))
+}
+
+/// Serializes the entries `entries` into the block buffer `buf`, with the reference count
+/// `refcount`.
+///
+/// If the entries do not fit in a block, the function returns `ENOSPC`.
+fn write_entries(entries: &[Entry], refcount: u32, buf: &mut [u8]) -> EResult<()> {
+	buf.fill(0);
+
+	let mut entries_end = HEADER_SIZE;
+	let mut values_begin = buf.len();
+	let mut blk_hash = Some(0u32);
+	for e in entries {
+		let off = entries_end;
+		let value_size = pad(e.value.len());
+		entries_end += pad(ENTRY_HEADER_SIZE + e.name.len());
+		// Keep room for the terminating zero
+		if entries_end + 4 + value_size > values_begin {
+			return Err(errno!(ENOSPC));
+		}
+		let value_off = if e.value.is_empty() {
+			0
+		} else {
+			values_begin -= value_size;
+			values_begin
+		};
+
+		let hash = e.hash();
+		buf[off] = e.name.len() as _;
+		buf[off + 1] = e.index;
+		buf[(off + 2)..(off + 4)].copy_from_slice(&(value_off as u16).to_le_bytes());
+		write_u32(buf, off + 8, e.value.len() as _);
+		write_u32(buf, off + 12, hash);
+		let name_begin = off + ENTRY_HEADER_SIZE;
+		buf[name_begin..(name_begin + e.name.len())].copy_from_slice(&e.name);
+		buf[value_off..(value_off + e.value.len())].copy_from_slice(&e.value);
+
+		// If an entry has no hash, the block has none either
+		blk_hash = blk_hash
+			.filter(|_| hash != 0)
+			.map(|h| (h << 16) ^ (h >> 16) ^ hash);
+	}
+
+	write_u32(buf, 0, XATTR_MAGIC);
+	write_u32(buf, 4, refcount);
+	write_u32(buf, 8, 1);
+	write_u32(buf, 12, blk_hash.unwrap_or(0));
+	Ok(())
+}
+
+/// Releases one reference to the extended attributes block `blk`, which has `refcount`
+/// references.
+///
+/// If this is the last reference, the block is freed.
+fn release_block(
+	blk: u32,
+	refcount: u32,
+	superblock: &mut Superblock,
+	io: &mut dyn IO,
+) -> EResult<()> {
+	if refcount > 1 {
+		let mut buf = Vec::from_elem(0u8, superblock.get_block_size() as _)?;
+		read_block(blk as _, superblock, io, buf.as_mut_slice())?;
+		write_u32(&mut buf, 4, refcount - 1);
+		write_block(blk as _, superblock, io, buf.as_slice())
+	} else {
+		superblock.free_block(io, blk)
+	}
+}
+
+impl Ext2INode {
+	/// Reads the extended attributes of the inode, returning the reference count of their block
+	/// and the entries.
+	fn read_xattrs(&self, superblock: &Superblock, io: &mut dyn IO) -> EResult<(u32, Vec<Entry>)> {
+		match self.extended_attributes_block {
+			0 => Ok((0, Vec::new())),
+			blk => read_entries(blk, superblock, io),
+		}
+	}
+
+	/// Returns the value of the extended attribute with the full name `name`.
+	///
+	/// Arguments:
+	/// - `superblock` is the filesystem's superblock.
+	/// - `io` is the I/O interface.
+	///
+	/// If the attribute doesn't exist, the function returns `None`.
+	pub fn get_xattr(
+		&self,
+		name: &[u8],
+		superblock: &Superblock,
+		io: &mut dyn IO,
+	) -> EResult<Option<Vec<u8>>> {
+		let Some((index, name)) = split_name(name) else {
+			return Ok(None);
+		};
+		let (_, entries) = self.read_xattrs(superblock, io)?;
+		let value = entries
+			.into_iter()
+			.find(|e| e.cmp_key(index, name) == Ordering::Equal)
+			.map(|e| e.value);
+		Ok(value)
+	}
+
+	/// Returns the full names of the extended attributes of the inode.
+	///
+	/// Arguments:
+	/// - `superblock` is the filesystem's superblock.
+	/// - `io` is the I/O interface.
+	pub fn list_xattr(&self, superblock: &Superblock, io: &mut dyn IO) -> EResult<Vec<String>> {
+		let (_, entries) = self.read_xattrs(superblock, io)?;
+		let mut names = Vec::new();
+		for e in entries {
+			// Skip attributes of unsupported namespaces
+			let Some((_, prefix)) = PREFIXES.iter().find(|(index, _)| *index == e.index) else {
+				continue;
+			};
+			let mut name = String::try_from(*prefix)?;
+			name.push_str(&e.name)?;
+			names.push(name)?;
+		}
+		Ok(names)
+	}
+
+	/// Sets the value of the extended attribute with the full name `name`.
+	///
+	/// Arguments:
+	/// - `value` is the new value. If `None`, the attribute is removed.
+	/// - `superblock` is the filesystem's superblock.
+	/// - `io` is the I/O interface.
+	///
+	/// The function returns `true` if the attribute was already present.
+	///
+	/// The inode has to be written back afterwards.
+	pub fn set_xattr(
+		&mut self,
+		name: &[u8],
+		value: Option<&[u8]>,
+		superblock: &mut Superblock,
+		io: &mut dyn IO,
+	) -> EResult<bool> {
+		let Some((index, name)) = split_name(name) else {
+			return match value {
+				Some(_) => Err(errno!(EOPNOTSUPP)),
+				None => Ok(false),
+			};
+		};
+		let (refcount, mut entries) = self.read_xattrs(superblock, io)?;
+
+		let pos = entries
+			.iter()
+			.position(|e| e.cmp_key(index, name) != Ordering::Less)
+			.unwrap_or(entries.len());
+		let exists = entries
+			.get(pos)
+			.map(|e| e.cmp_key(index, name) == Ordering::Equal)
+			.unwrap_or(false);
+		match value {
+			Some(value) => {
+				let mut v = Vec::new();
+				v.extend_from_slice(value)?;
+				if exists {
+					entries[pos].value = v;
+				} else {
+					let mut n = Vec::new();
+					n.extend_from_slice(name)?;
+					entries.insert(
+						pos,
+						Entry {
+							index,
+							name: n,
+							value: v,
+						},
+					)?;
+				}
+			}
+			None if exists => {
+				entries.remove(pos);
+			}
+			None => return Ok(false),
+		}
+
+		let old_blk = self.extended_attributes_block;
+		let blk_size = superblock.get_block_size();
+		if entries.is_empty() {
+			release_block(old_blk, refcount, superblock, io)?;
+			self.extended_attributes_block = 0;
+			self.decrement_used_sectors(blk_size);
+			return Ok(exists);
+		}
+
+		let mut buf = Vec::from_elem(0u8, blk_size as _)?;
+		write_entries(&entries, 1, buf.as_mut_slice())?;
+		if old_blk != 0 && refcount <= 1 {
+			write_block(old_blk as _, superblock, io, buf.as_slice())?;
+		} else {
+			// The block is either missing or shared with other inodes
+			let blk = superblock.get_free_block(io)?;
+			superblock.mark_block_used(io, blk)?;
+			write_block(blk as _, superblock, io, buf.as_slice())?;
+			if old_blk != 0 {
+				release_block(old_blk, refcount, superblock, io)?;
+			} else {
+				self.increment_used_sectors(blk_size);
+			}
+			self.extended_attributes_block = blk;
+			if superblock.major_version >= 1 {
+				superblock.optional_features |= OPTIONAL_FEATURE_INODE_EXTENDED;
+			}
+		}
+		Ok(exists)
+	}
+
+	/// Releases the extended attributes of the inode.
+	///
+	/// Arguments:
+	/// - `superblock` is the filesystem's superblock.
+	/// - `io` is the I/O interface.
+	pub fn free_xattr(&mut self, superblock: &mut Superblock, io: &mut dyn IO) -> EResult<()> {
+		let blk = self.extended_attributes_block;
+		if blk == 0 {
+			return Ok(());
+		}
+		let (refcount, _) = read_entries(blk, superblock, io)?;
+		release_block(blk, refcount, superblock, io)?;
+		self.extended_attributes_block = 0;
+		self.decrement_used_sectors(superblock.get_block_size());
+		Ok(())
+	}
+}
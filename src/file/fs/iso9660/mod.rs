@@ -0,0 +1,617 @@
+//! ISO9660 is the read-only filesystem used on optical discs (CD-ROM, DVD), and on the images of
+//! live systems and installation media.
+//!
+//! The device is divided into logical sectors of 2048 bytes. Starting at sector 16, a list of
+//! Volume Descriptors describes the volume. The Primary Volume Descriptor holds the directory
+//! record of the root directory.
+//!
+//! Each directory is an extent containing a list of directory records, one for each of its
+//! entries. A record carries the location and size of the entry's extent, its flags and its name.
+//! Records never cross the boundary of a sector.
+//!
+//! Plain ISO9660 only provides short uppercase names and no POSIX attributes. Those are added by
+//! the Rock Ridge extensions, in the System Use area of records. See [`rock_ridge`].
+//!
+//! Since files have no inode number, the inode of a file is the offset of its directory record
+//! on the device. For directories, it is the offset of the `.` record at the beginning of their
+//! extent, so that every links to a same directory are given the same inode.
+
+mod rock_ridge;
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::fs::Filesystem;
+use crate::file::fs::FilesystemType;
+use crate::file::fs::MountOptions;
+use crate::file::fs::Statfs;
+use crate::file::path::Path;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::DirEntry;
+use crate::file::File;
+use crate::file::FileContent;
+use crate::file::FileLocation;
+use crate::file::FileType;
+use crate::file::INode;
+use crate::file::Mode;
+use crate::time::unit::Timespec;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+use crate::util::lock::Mutex;
+use crate::util::math;
+use crate::util::ptr::arc::Arc;
+use core::cmp::min;
+use core::ffi::c_long;
+use rock_ridge::Attributes;
+
+/// The size of a logical sector in bytes.
+const SECTOR_SIZE: u64 = 2048;
+/// The sector at which the list of Volume Descriptors begins.
+const DESCRIPTORS_START: u64 = 16;
+/// The maximum number of Volume Descriptors read before giving up.
+const DESCRIPTORS_MAX: u64 = 64;
+/// The identifier present in every Volume Descriptors.
+const IDENTIFIER: &[u8] = b"CD001";
+
+/// Volume Descriptor type: Primary Volume Descriptor.
+const VD_PRIMARY: u8 = 1;
+/// Volume Descriptor type: terminator of the list.
+const VD_TERMINATOR: u8 = 255;
+
+/// The filesystem's magic number, as returned by `statfs`.
+const ISOFS_MAGIC: u32 = 0x9660;
+/// The maximum length of a name, with Rock Ridge.
+const MAX_NAME_LEN: usize = 255;
+
+/// Record flag: the entry is a directory.
+const FLAG_DIRECTORY: u8 = 0x02;
+
+/// Returns the number of days between the Unix epoch and the given date of the Gregorian
+/// calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+	let year = if month <= 2 { year - 1 } else { year };
+	let era = year.div_euclid(400);
+	let year_of_era = year - era * 400;
+	let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+	era * 146097 + day_of_era - 719468
+}
+
+/// Returns the timestamp of the given date.
+///
+/// Arguments:
+/// - `gmt_off` is the offset from GMT in intervals of 15 minutes.
+/// - `nsec` is the number of nanoseconds to add to the date.
+///
+/// Dates before the Unix epoch are clamped to zero.
+fn to_timestamp(date: [i64; 6], gmt_off: i8, nsec: c_long) -> Timespec {
+	let [year, month, day, hour, min, sec] = date;
+	let secs = days_from_civil(year, month, day) * 86400 + hour * 3600 + min * 60 + sec
+		- gmt_off as i64 * 15 * 60;
+	if secs < 0 {
+		return Timespec::default();
+	}
+	Timespec {
+		tv_sec: secs as _,
+		tv_nsec: nsec,
+	}
+}
+
+/// Converts the given date in the 7 bytes format of directory records to a timestamp.
+fn date7_to_timestamp(date: &[u8]) -> Timespec {
+	let [year, month, day, hour, min, sec, gmt_off] = date[..7].try_into().unwrap();
+	to_timestamp(
+		[
+			1900 + year as i64,
+			month as _,
+			day as _,
+			hour as _,
+			min as _,
+			sec as _,
+		],
+		gmt_off as _,
+		0,
+	)
+}
+
+/// Converts the given date in the 17 bytes format of Volume Descriptors to a timestamp.
+///
+/// The format is made of 16 ASCII digits (`YYYYMMDDHHMMSScc`) followed by the offset from GMT.
+fn date17_to_timestamp(date: &[u8]) -> Timespec {
+	let digits = |r: core::ops::Range<usize>| {
+		date[r]
+			.iter()
+			.fold(0, |n, c| n * 10 + c.wrapping_sub(b'0') as i64)
+	};
+	let year = digits(0..4);
+	// A zero year means the date is not specified
+	if year == 0 {
+		return Timespec::default();
+	}
+	to_timestamp(
+		[
+			year,
+			digits(4..6),
+			digits(6..8),
+			digits(8..10),
+			digits(10..12),
+			digits(12..14),
+		],
+		date[16] as _,
+		// Hundredths of a second
+		(digits(14..16) * 10_000_000) as _,
+	)
+}
+
+/// A directory record.
+struct DirRecord {
+	/// The block at which the extent begins.
+	extent: u32,
+	/// The length of the extended attribute record, in blocks, at the beginning of the extent.
+	ext_attr_len: u8,
+	/// The size of the data in bytes.
+	size: u32,
+	/// The recording date, in the 7 bytes format.
+	date: [u8; 7],
+	/// The record's flags.
+	flags: u8,
+	/// The record's name, as stored on the disk.
+	name: Vec<u8>,
+	/// The System Use area.
+	system_use: Vec<u8>,
+}
+
+impl DirRecord {
+	/// Parses the record at the beginning of the given buffer.
+	///
+	/// If the buffer does not contain a valid record, the function returns `None`.
+	fn parse(buf: &[u8]) -> Result<Option<Self>, Errno> {
+		let len = *buf.first().unwrap_or(&0) as usize;
+		if len < 34 || len > buf.len() {
+			return Ok(None);
+		}
+		let buf = &buf[..len];
+		let name_len = buf[32] as usize;
+		// The System Use area starts at an even offset
+		let su_off = 33 + name_len + (name_len + 1) % 2;
+		let (Some(name), Some(system_use)) = (buf.get(33..(33 + name_len)), buf.get(su_off..))
+		else {
+			return Ok(None);
+		};
+
+		let mut name_buf = Vec::new();
+		name_buf.extend_from_slice(name)?;
+		let mut su_buf = Vec::new();
+		su_buf.extend_from_slice(system_use)?;
+
+		Ok(Some(Self {
+			extent: u32::from_le_bytes(buf[2..6].try_into().unwrap()),
+			ext_attr_len: buf[1],
+			size: u32::from_le_bytes(buf[10..14].try_into().unwrap()),
+			date: buf[18..25].try_into().unwrap(),
+			flags: buf[25],
+			name: name_buf,
+			system_use: su_buf,
+		}))
+	}
+
+	/// Reads the record located at offset `off` on the device.
+	///
+	/// If no valid record is present at this offset, the function returns an error.
+	fn read(io: &mut dyn IO, off: u64) -> Result<Self, Errno> {
+		let mut buf: [u8; 255] = [0; 255];
+		// Records never cross the boundary of a sector
+		let len = min(buf.len() as u64, SECTOR_SIZE - off % SECTOR_SIZE) as usize;
+		io.read(off, &mut buf[..len])?;
+		Self::parse(&buf[..len])?.ok_or_else(|| errno!(EUCLEAN))
+	}
+
+	/// Returns the offset of the data of the record on the device.
+	fn get_data_off(&self, blk_size: u32) -> u64 {
+		(self.extent as u64 + self.ext_attr_len as u64) * blk_size as u64
+	}
+
+	/// Tells whether the record is the `.` entry of a directory.
+	fn is_current(&self) -> bool {
+		self.name.as_slice() == b"\0"
+	}
+
+	/// Tells whether the record is the `..` entry of a directory.
+	fn is_parent(&self) -> bool {
+		self.name.as_slice() == b"\x01"
+	}
+}
+
+/// Structure representing a instance of the ISO9660 filesystem.
+pub struct Iso9660Fs {
+	/// The size of a logical block in bytes.
+	blk_size: u32,
+	/// The number of logical blocks on the volume.
+	blocks_count: u32,
+	/// The inode of the root directory.
+	root_inode: INode,
+
+	/// If Rock Ridge is in use, the number of bytes to skip at the beginning of the System Use
+	/// area of each record.
+	rock_ridge: Option<usize>,
+}
+
+impl Iso9660Fs {
+	/// Reads the Primary Volume Descriptor from the device.
+	///
+	/// If not found, the function returns `None`.
+	fn read_primary_descriptor(io: &mut dyn IO) -> Result<Option<[u8; 2048]>, Errno> {
+		let mut buf = [0; 2048];
+		for i in DESCRIPTORS_START..(DESCRIPTORS_START + DESCRIPTORS_MAX) {
+			io.read(i * SECTOR_SIZE, &mut buf)?;
+			if &buf[1..6] != IDENTIFIER {
+				break;
+			}
+			match buf[0] {
+				VD_PRIMARY => return Ok(Some(buf)),
+				VD_TERMINATOR => break,
+				_ => {}
+			}
+		}
+		Ok(None)
+	}
+
+	/// Creates a new instance from the Primary Volume Descriptor `desc`.
+	fn new(io: &mut dyn IO, desc: &[u8; 2048]) -> Result<Self, Errno> {
+		let blk_size = u16::from_le_bytes(desc[128..130].try_into().unwrap()) as u32;
+		if !matches!(blk_size, 512 | 1024 | 2048) {
+			return Err(errno!(EINVAL));
+		}
+		let blocks_count = u32::from_le_bytes(desc[80..84].try_into().unwrap());
+		let root = DirRecord::parse(&desc[156..190])?.ok_or_else(|| errno!(EINVAL))?;
+
+		let root_inode = root.get_data_off(blk_size);
+		// Rock Ridge is detected on the `.` record of the root directory
+		let rock_ridge = rock_ridge::detect(&DirRecord::read(io, root_inode)?.system_use);
+
+		Ok(Self {
+			blk_size,
+			blocks_count,
+			root_inode,
+
+			rock_ridge,
+		})
+	}
+
+	/// Returns the Rock Ridge attributes of the record `rec`.
+	///
+	/// If Rock Ridge is not in use, the function returns default attributes.
+	fn get_attributes(&self, io: &mut dyn IO, rec: &DirRecord) -> Result<Attributes, Errno> {
+		let Some(skip) = self.rock_ridge else {
+			return Ok(Attributes::default());
+		};
+		let su = rec.system_use.get(skip..).unwrap_or(&[]);
+		rock_ridge::parse(io, su, self.blk_size)
+	}
+
+	/// Returns the type of the file represented by the record `rec` with attributes `attrs`.
+	fn get_type(rec: &DirRecord, attrs: &Attributes) -> FileType {
+		if attrs.child_link.is_some() {
+			return FileType::Directory;
+		}
+		match attrs.mode.and_then(FileType::from_mode) {
+			Some(t) => t,
+			None if rec.flags & FLAG_DIRECTORY != 0 => FileType::Directory,
+			None => FileType::Regular,
+		}
+	}
+
+	/// Returns the inode of the entry represented by the record `rec` with attributes `attrs`,
+	/// located at offset `off` on the device.
+	fn get_entry_inode(&self, off: u64, rec: &DirRecord, attrs: &Attributes) -> INode {
+		if let Some(blk) = attrs.child_link.or(attrs.parent_link) {
+			blk as u64 * self.blk_size as u64
+		} else if rec.flags & FLAG_DIRECTORY != 0 {
+			rec.get_data_off(self.blk_size)
+		} else {
+			off
+		}
+	}
+
+	/// Returns the name of the entry represented by the record `rec` with attributes `attrs`.
+	///
+	/// Without Rock Ridge, the version number and the trailing dot are stripped and the name is
+	/// converted to lowercase.
+	fn get_entry_name(rec: &DirRecord, attrs: &Attributes) -> Result<String, Errno> {
+		if rec.is_current() {
+			return Ok(String::try_from(b".")?);
+		}
+		if rec.is_parent() {
+			return Ok(String::try_from(b"..")?);
+		}
+		if let Some(name) = &attrs.name {
+			return Ok(String::try_from(name.as_slice())?);
+		}
+
+		let mut name = rec.name.as_slice();
+		if let Some(i) = name.iter().position(|c| *c == b';') {
+			name = &name[..i];
+		}
+		if let [n @ .., b'.'] = name {
+			name = n;
+		}
+		let mut lowercase = Vec::new();
+		lowercase.extend_from_slice(name)?;
+		lowercase.make_ascii_lowercase();
+		Ok(String::try_from(lowercase.as_slice())?)
+	}
+
+	/// Returns the list of the entries of the directory represented by the record `dir`.
+	///
+	/// Each entry is returned with its offset on the device.
+	fn read_dir(&self, io: &mut dyn IO, dir: &DirRecord) -> Result<Vec<(u64, DirRecord)>, Errno> {
+		let begin = dir.get_data_off(self.blk_size);
+		let size = dir.size as u64;
+
+		let mut entries = Vec::new();
+		let mut buf = Vec::from_elem(0u8, SECTOR_SIZE as usize)?;
+		let mut off = 0;
+		while off < size {
+			let sector_off = off % SECTOR_SIZE;
+			if sector_off == 0 {
+				let len = min(SECTOR_SIZE, size - off) as usize;
+				io.read(begin + off, &mut buf[..len])?;
+			}
+
+			match DirRecord::parse(&buf[(sector_off as usize)..])? {
+				Some(rec) => {
+					let len = buf[sector_off as usize] as u64;
+					entries.push((begin + off, rec))?;
+					off += len;
+				}
+				// Padding at the end of the sector
+				None => off = (off / SECTOR_SIZE + 1) * SECTOR_SIZE,
+			}
+		}
+
+		Ok(entries)
+	}
+}
+
+impl Filesystem for Iso9660Fs {
+	fn get_name(&self) -> &[u8] {
+		b"iso9660"
+	}
+
+	fn is_readonly(&self) -> bool {
+		true
+	}
+
+	fn must_cache(&self) -> bool {
+		true
+	}
+
+	fn get_stat(&self, _io: &mut dyn IO) -> Result<Statfs, Errno> {
+		Ok(Statfs {
+			f_type: ISOFS_MAGIC,
+			f_bsize: self.blk_size,
+			f_blocks: self.blocks_count as _,
+			f_bfree: 0,
+			f_bavail: 0,
+			f_files: 0,
+			f_ffree: 0,
+			f_fsid: Default::default(),
+			f_namelen: MAX_NAME_LEN as _,
+			f_frsize: self.blk_size,
+			f_flags: 0, // TODO
+		})
+	}
+
+	fn get_root_inode(&self, _io: &mut dyn IO) -> Result<INode, Errno> {
+		Ok(self.root_inode)
+	}
+
+	fn get_inode(
+		&mut self,
+		io: &mut dyn IO,
+		parent: Option<INode>,
+		name: &[u8],
+	) -> Result<INode, Errno> {
+		let parent_inode = parent.unwrap_or(self.root_inode);
+
+		let parent = DirRecord::read(io, parent_inode)?;
+		if parent.flags & FLAG_DIRECTORY == 0 {
+			return Err(errno!(ENOTDIR));
+		}
+
+		for (off, rec) in self.read_dir(io, &parent)? {
+			let attrs = self.get_attributes(io, &rec)?;
+			if attrs.relocated {
+				continue;
+			}
+			if Self::get_entry_name(&rec, &attrs)?.as_bytes() == name {
+				return Ok(self.get_entry_inode(off, &rec, &attrs));
+			}
+		}
+		Err(errno!(ENOENT))
+	}
+
+	fn load_file(&mut self, io: &mut dyn IO, inode: INode, name: String) -> Result<File, Errno> {
+		let rec = DirRecord::read(io, inode)?;
+		let attrs = self.get_attributes(io, &rec)?;
+		let file_type = Self::get_type(&rec, &attrs);
+		// TODO Support files made of several extents (multi-extent flag)
+
+		let file_content = match file_type {
+			FileType::Regular => FileContent::Regular,
+
+			FileType::Directory => {
+				let mut entries = HashMap::new();
+
+				for (off, rec) in self.read_dir(io, &rec)? {
+					let attrs = self.get_attributes(io, &rec)?;
+					if attrs.relocated {
+						continue;
+					}
+
+					entries.insert(
+						Self::get_entry_name(&rec, &attrs)?,
+						DirEntry {
+							inode: self.get_entry_inode(off, &rec, &attrs),
+							entry_type: Self::get_type(&rec, &attrs),
+						},
+					)?;
+				}
+
+				FileContent::Directory(entries)
+			}
+
+			FileType::Link => {
+				let target = attrs.link.as_deref().unwrap_or(&[]);
+				FileContent::Link(String::try_from(target)?)
+			}
+
+			FileType::Fifo => FileContent::Fifo,
+
+			FileType::Socket => FileContent::Socket,
+
+			FileType::BlockDevice => {
+				let (major, minor) = attrs.dev.unwrap_or((0, 0));
+				FileContent::BlockDevice {
+					major,
+					minor,
+				}
+			}
+
+			FileType::CharDevice => {
+				let (major, minor) = attrs.dev.unwrap_or((0, 0));
+				FileContent::CharDevice {
+					major,
+					minor,
+				}
+			}
+		};
+
+		let mode = attrs.mode.unwrap_or(match file_type {
+			FileType::Directory => 0o555,
+			_ => 0o444,
+		});
+		let nlink = attrs.nlink.unwrap_or(match file_type {
+			FileType::Directory => 2,
+			_ => 1,
+		});
+		let size = match &attrs.link {
+			Some(link) => link.len() as u64,
+			None => rec.size as u64,
+		};
+		let timestamp = date7_to_timestamp(&rec.date);
+
+		let file_location = FileLocation::Filesystem {
+			mountpoint_id: 0, // dummy value to be replaced
+			inode,
+		};
+		let mut file = File::new(
+			name,
+			attrs.uid.unwrap_or(0) as _,
+			attrs.gid.unwrap_or(0) as _,
+			mode & 0o7777,
+			file_location,
+			file_content,
+		)?;
+		file.set_hard_links_count(nlink as _);
+		file.blocks_count = math::ceil_div(rec.size as u64, 512);
+		file.set_size(size);
+		file.ctime = attrs.ctime.unwrap_or(timestamp);
+		file.mtime = attrs.mtime.unwrap_or(timestamp);
+		file.atime = attrs.atime.unwrap_or(timestamp);
+
+		Ok(file)
+	}
+
+	fn add_file(
+		&mut self,
+		_io: &mut dyn IO,
+		_parent_inode: INode,
+		_name: String,
+		_uid: Uid,
+		_gid: Gid,
+		_mode: Mode,
+		_content: FileContent,
+	) -> Result<File, Errno> {
+		Err(errno!(EROFS))
+	}
+
+	fn add_link(
+		&mut self,
+		_io: &mut dyn IO,
+		_parent_inode: INode,
+		_name: &[u8],
+		_inode: INode,
+	) -> Result<(), Errno> {
+		Err(errno!(EROFS))
+	}
+
+	fn update_inode(&mut self, _io: &mut dyn IO, _file: &File) -> Result<(), Errno> {
+		Err(errno!(EROFS))
+	}
+
+	fn remove_file(
+		&mut self,
+		_io: &mut dyn IO,
+		_parent_inode: INode,
+		_name: &[u8],
+	) -> Result<u16, Errno> {
+		Err(errno!(EROFS))
+	}
+
+	fn read_node(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		buf: &mut [u8],
+	) -> Result<u64, Errno> {
+		let rec = DirRecord::read(io, inode)?;
+		let size = rec.size as u64;
+		if off >= size {
+			return Ok(0);
+		}
+
+		let len = min(buf.len() as u64, size - off) as usize;
+		io.read(rec.get_data_off(self.blk_size) + off, &mut buf[..len])?;
+		Ok(len as _)
+	}
+
+	fn write_node(
+		&mut self,
+		_io: &mut dyn IO,
+		_inode: INode,
+		_off: u64,
+		_buf: &[u8],
+	) -> Result<(), Errno> {
+		Err(errno!(EROFS))
+	}
+}
+
+/// Structure representing the ISO9660 file system type.
+pub struct Iso9660FsType {}
+
+impl FilesystemType for Iso9660FsType {
+	fn get_name(&self) -> &'static [u8] {
+		b"iso9660"
+	}
+
+	fn detect(&self, io: &mut dyn IO) -> Result<bool, Errno> {
+		Ok(Iso9660Fs::read_primary_descriptor(io)?.is_some())
+	}
+
+	fn load_filesystem(
+		&self,
+		io: &mut dyn IO,
+		_mountpath: Path,
+		_readonly: bool,
+		_options: &MountOptions,
+	) -> Result<Arc<Mutex<dyn Filesystem>>, Errno> {
+		let desc = Iso9660Fs::read_primary_descriptor(io)?.ok_or_else(|| errno!(EINVAL))?;
+		let fs = Iso9660Fs::new(io, &desc)?;
+
+		Ok(Arc::new(Mutex::new(fs))? as _)
+	}
+}
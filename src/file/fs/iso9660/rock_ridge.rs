@@ -0,0 +1,258 @@
+//! The Rock Ridge Interchange Protocol (RRIP) extends ISO9660 with POSIX file attributes: long
+//! names, permissions, ownership, symbolic links, device files and timestamps.
+//!
+//! Its entries are stored in the System Use area of directory records, following the System Use
+//! Sharing Protocol (SUSP). When the area is too small, entries continue in a Continuation Area,
+//! located elsewhere on the device.
+
+use super::date17_to_timestamp;
+use super::date7_to_timestamp;
+use crate::device::id;
+use crate::errno::Errno;
+use crate::file::Mode;
+use crate::time::unit::Timespec;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+
+/// The maximum number of Continuation Areas followed for a single record, to prevent loops on
+/// corrupted filesystems.
+const MAX_CONTINUATIONS: usize = 32;
+
+/// `NM` flag: the entry refers to the current directory.
+const NM_CURRENT: u8 = 0x02;
+/// `NM` flag: the entry refers to the parent directory.
+const NM_PARENT: u8 = 0x04;
+
+/// `SL` component flag: the component continues in the next component record.
+const SL_CONTINUE: u8 = 0x01;
+/// `SL` component flag: the component refers to the current directory.
+const SL_CURRENT: u8 = 0x02;
+/// `SL` component flag: the component refers to the parent directory.
+const SL_PARENT: u8 = 0x04;
+/// `SL` component flag: the component refers to the root directory.
+const SL_ROOT: u8 = 0x08;
+
+/// `TF` flag: the creation time is recorded.
+const TF_CREATION: u8 = 0x01;
+/// `TF` flag: the modification time is recorded.
+const TF_MODIFY: u8 = 0x02;
+/// `TF` flag: the access time is recorded.
+const TF_ACCESS: u8 = 0x04;
+/// `TF` flag: the attributes change time is recorded.
+const TF_ATTRIBUTES: u8 = 0x08;
+/// `TF` flag: timestamps are recorded in the 17 bytes format instead of the 7 bytes format.
+const TF_LONG_FORM: u8 = 0x80;
+
+/// Reads a both-endian 32 bits value from `buf`.
+///
+/// Such values are stored on 8 bytes, little-endian first. Only the little-endian half is used.
+fn read_both32(buf: &[u8]) -> Option<u32> {
+	let bytes = buf.get(..4)?;
+	Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+/// The attributes of a file, as given by Rock Ridge entries.
+///
+/// Attributes that are not recorded are left to `None`.
+#[derive(Default)]
+pub struct Attributes {
+	/// The file's mode, including its type.
+	pub mode: Option<Mode>,
+	/// The number of hard links to the file.
+	pub nlink: Option<u32>,
+	/// The owner user ID.
+	pub uid: Option<u32>,
+	/// The owner group ID.
+	pub gid: Option<u32>,
+
+	/// The file's name.
+	pub name: Option<Vec<u8>>,
+	/// The target of the symbolic link.
+	pub link: Option<Vec<u8>>,
+	/// The device's major and minor numbers.
+	pub dev: Option<(u32, u32)>,
+
+	/// Timestamp of the last modification of the metadata.
+	pub ctime: Option<Timespec>,
+	/// Timestamp of the last modification of the content.
+	pub mtime: Option<Timespec>,
+	/// Timestamp of the last access.
+	pub atime: Option<Timespec>,
+
+	/// If `true`, the record is a relocated directory that must not be listed.
+	pub relocated: bool,
+	/// The block of the relocated directory this record stands for.
+	pub child_link: Option<u32>,
+	/// The block of the actual parent of a relocated directory.
+	pub parent_link: Option<u32>,
+}
+
+impl Attributes {
+	/// Handles the entry with signature `sig` and data `data`.
+	///
+	/// If the entry is a continuation, the function returns the location of the Continuation Area
+	/// as a tuple `(block, offset, length)`.
+	fn handle_entry(
+		&mut self,
+		sig: &[u8],
+		data: &[u8],
+		link_continue: &mut bool,
+	) -> Option<(u32, u32, u32)> {
+		match sig {
+			b"PX" => {
+				self.mode = read_both32(data);
+				self.nlink = data.get(8..).and_then(read_both32);
+				self.uid = data.get(16..).and_then(read_both32);
+				self.gid = data.get(24..).and_then(read_both32);
+			}
+
+			b"NM" => {
+				let (Some(flags), Some(content)) = (data.first(), data.get(1..)) else {
+					return None;
+				};
+				if flags & (NM_CURRENT | NM_PARENT) != 0 {
+					return None;
+				}
+				let name = self.name.get_or_insert_with(Vec::new);
+				// If the name is split, the next parts are found in the following entries
+				let _ = name.extend_from_slice(content);
+			}
+
+			b"SL" => {
+				let link = self.link.get_or_insert_with(Vec::new);
+				let mut comps = data.get(1..).unwrap_or(&[]);
+				while let [flags, len, rest @ ..] = comps {
+					let Some(content) = rest.get(..*len as usize) else {
+						break;
+					};
+					if !*link_continue && !link.is_empty() && link.last() != Some(&b'/') {
+						let _ = link.push(b'/');
+					}
+					let _ = if flags & SL_ROOT != 0 {
+						link.push(b'/')
+					} else if flags & SL_PARENT != 0 {
+						link.extend_from_slice(b"..")
+					} else if flags & SL_CURRENT != 0 {
+						link.push(b'.')
+					} else {
+						link.extend_from_slice(content)
+					};
+					*link_continue = flags & SL_CONTINUE != 0;
+					comps = &rest[*len as usize..];
+				}
+			}
+
+			b"PN" => {
+				let high = read_both32(data)?;
+				let low = data.get(8..).and_then(read_both32)?;
+				self.dev = if high == 0 {
+					Some((id::major(low as _), id::minor(low as _)))
+				} else {
+					Some((high, low))
+				};
+			}
+
+			b"TF" => {
+				let flags = *data.first()?;
+				let (size, parse): (usize, fn(&[u8]) -> Timespec) = if flags & TF_LONG_FORM != 0 {
+					(17, date17_to_timestamp)
+				} else {
+					(7, date7_to_timestamp)
+				};
+				let mut stamps = data[1..].chunks_exact(size).map(parse);
+				if flags & TF_CREATION != 0 {
+					stamps.next();
+				}
+				if flags & TF_MODIFY != 0 {
+					self.mtime = stamps.next();
+				}
+				if flags & TF_ACCESS != 0 {
+					self.atime = stamps.next();
+				}
+				if flags & TF_ATTRIBUTES != 0 {
+					self.ctime = stamps.next();
+				}
+			}
+
+			b"RE" => self.relocated = true,
+			b"CL" => self.child_link = read_both32(data),
+			b"PL" => self.parent_link = read_both32(data),
+
+			b"CE" => {
+				let block = read_both32(data)?;
+				let offset = data.get(8..).and_then(read_both32)?;
+				let len = data.get(16..).and_then(read_both32)?;
+				return Some((block, offset, len));
+			}
+
+			_ => {}
+		}
+		None
+	}
+}
+
+/// Iterates on the SUSP entries of the area `area`, calling `f` with the signature and data of
+/// each.
+///
+/// The iteration stops at the `ST` entry, or when `f` returns `false`.
+fn foreach_entry<F: FnMut(&[u8], &[u8]) -> bool>(mut area: &[u8], mut f: F) {
+	while area.len() >= 4 {
+		let sig = &area[..2];
+		let len = area[2] as usize;
+		if len < 4 || len > area.len() || sig == b"ST" {
+			break;
+		}
+		if !f(sig, &area[4..len]) {
+			break;
+		}
+		area = &area[len..];
+	}
+}
+
+/// Checks for the `SP` entry at the beginning of the System Use area `su` of the root directory's
+/// `.` record, which indicates the use of SUSP.
+///
+/// If present, the function returns the number of bytes to skip at the beginning of the System
+/// Use area of every other records.
+pub fn detect(su: &[u8]) -> Option<usize> {
+	match su {
+		[b'S', b'P', 7, 1, 0xbe, 0xef, skip, ..] => Some(*skip as usize),
+		_ => None,
+	}
+}
+
+/// Parses the Rock Ridge attributes stored in the System Use area `su`, following Continuation
+/// Areas.
+///
+/// Arguments:
+/// - `io` is the IO interface
+/// - `blk_size` is the size of a logical block of the filesystem in bytes
+pub fn parse(io: &mut dyn IO, su: &[u8], blk_size: u32) -> Result<Attributes, Errno> {
+	let mut attrs = Attributes::default();
+	let mut link_continue = false;
+
+	let mut continuation = None;
+	foreach_entry(su, |sig, data| {
+		continuation = attrs.handle_entry(sig, data, &mut link_continue);
+		continuation.is_none()
+	});
+
+	for _ in 0..MAX_CONTINUATIONS {
+		let Some((block, offset, len)) = continuation.take() else {
+			break;
+		};
+		// A Continuation Area cannot be larger than a block
+		if offset.saturating_add(len) > blk_size {
+			break;
+		}
+		let mut buf = Vec::from_elem(0u8, len as usize)?;
+		io.read(block as u64 * blk_size as u64 + offset as u64, &mut buf)?;
+
+		foreach_entry(&buf, |sig, data| {
+			continuation = attrs.handle_entry(sig, data, &mut link_continue);
+			continuation.is_none()
+		});
+	}
+
+	Ok(attrs)
+}
@@ -0,0 +1,542 @@
+//! The journal allows to modify a filesystem atomically, so that an unclean shutdown doesn't
+//! leave it in an inconsistent state.
+//!
+//! Modifications are grouped into transactions. Before being written to their location on the
+//! device, the blocks modified by a transaction are written to the journal, followed by a commit
+//! block. If a shutdown happens before the commit block is written, the transaction is ignored.
+//! Otherwise, the transaction is replayed on the next mount.
+//!
+//! The layout of the journal is the one of Linux's JBD2, so that a journal written by the kernel
+//! can be recovered by other systems, and conversely. Every field is big-endian.
+//!
+//! Transactions are written synchronously: once committed, the blocks of a transaction are
+//! written to their location right away, after which the journal is empty again. A transaction
+//! modifying more blocks than the journal can hold is split into several ones.
+//!
+//! The device is flushed between each step of a commit, so that a step never reaches the storage
+//! medium before the previous ones.
+
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::map::Map;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+use core::cmp::min;
+
+/// The signature of journal blocks.
+const MAGIC: u32 = 0xc03b3998;
+
+/// Block type: descriptor, listing the blocks of a transaction that follow it.
+const BLOCKTYPE_DESCRIPTOR: u32 = 1;
+/// Block type: commit, marking the end of a transaction.
+const BLOCKTYPE_COMMIT: u32 = 2;
+/// Block type: superblock, version 1.
+const BLOCKTYPE_SUPERBLOCK_V1: u32 = 3;
+/// Block type: superblock, version 2.
+const BLOCKTYPE_SUPERBLOCK_V2: u32 = 4;
+/// Block type: revoke, listing blocks that must not be replayed from previous transactions.
+const BLOCKTYPE_REVOKE: u32 = 5;
+
+/// Incompatible feature: the journal contains revoke blocks.
+const FEATURE_INCOMPAT_REVOKE: u32 = 0x1;
+/// Incompatible feature: block numbers are 64 bits wide.
+const FEATURE_INCOMPAT_64BIT: u32 = 0x2;
+/// Incompatible feature: commit blocks may be written without waiting for the transaction's
+/// blocks.
+const FEATURE_INCOMPAT_ASYNC_COMMIT: u32 = 0x4;
+/// The incompatible features supported by the implementation.
+const SUPPORTED_INCOMPAT: u32 =
+	FEATURE_INCOMPAT_REVOKE | FEATURE_INCOMPAT_64BIT | FEATURE_INCOMPAT_ASYNC_COMMIT;
+
+/// Tag flag: the first four bytes of the block have been zeroed because they matched the
+/// signature.
+const TAG_FLAG_ESCAPE: u16 = 0x1;
+/// Tag flag: the tag is not followed by a UUID, which is the same as the previous tag's.
+const TAG_FLAG_SAME_UUID: u16 = 0x2;
+/// Tag flag: the tag is the last of the descriptor block.
+const TAG_FLAG_LAST_TAG: u16 = 0x8;
+
+/// The size of the header of journal blocks in bytes.
+const HEADER_SIZE: usize = 12;
+/// The size of a UUID in bytes.
+const UUID_SIZE: usize = 16;
+
+/// Returns the big-endian `u16` at offset `off` in `buf`.
+fn get_u16(buf: &[u8], off: usize) -> u16 {
+	u16::from_be_bytes([buf[off], buf[off + 1]])
+}
+
+/// Returns the big-endian `u32` at offset `off` in `buf`.
+fn get_u32(buf: &[u8], off: usize) -> u32 {
+	u32::from_be_bytes([buf[off], buf[off + 1], buf[off + 2], buf[off + 3]])
+}
+
+/// Writes `val` as big-endian at offset `off` in `buf`.
+fn set_u16(buf: &mut [u8], off: usize, val: u16) {
+	buf[off..(off + 2)].copy_from_slice(&val.to_be_bytes());
+}
+
+/// Writes `val` as big-endian at offset `off` in `buf`.
+fn set_u32(buf: &mut [u8], off: usize, val: u32) {
+	buf[off..(off + 4)].copy_from_slice(&val.to_be_bytes());
+}
+
+/// Writes a block header of type `blocktype` with sequence number `sequence` at the beginning of
+/// `buf`.
+fn set_header(buf: &mut [u8], blocktype: u32, sequence: u32) {
+	set_u32(buf, 0, MAGIC);
+	set_u32(buf, 4, blocktype);
+	set_u32(buf, 8, sequence);
+}
+
+/// A journal stored on a device.
+pub struct Journal {
+	/// The location on the device of each block of the journal, in blocks.
+	blocks: Vec<u64>,
+	/// The size of a block in bytes.
+	blk_size: usize,
+
+	/// The number of blocks of the journal.
+	maxlen: u32,
+	/// The index of the first block of the log.
+	first: u32,
+	/// The sequence number of the next transaction.
+	sequence: u32,
+	/// The index of the block at which the log begins. If zero, the journal is empty.
+	start: u32,
+	/// The incompatible features used by the journal.
+	incompat: u32,
+	/// The UUID of the journal.
+	uuid: [u8; UUID_SIZE],
+}
+
+impl Journal {
+	/// Loads the journal.
+	///
+	/// Arguments:
+	/// - `io` is the I/O interface of the device.
+	/// - `blocks` is the location on the device of each block of the journal, in blocks.
+	/// - `blk_size` is the size of a block in bytes.
+	///
+	/// If the journal is invalid or uses unsupported features, the function returns an error.
+	pub fn load(io: &mut dyn IO, blocks: Vec<u64>, blk_size: usize) -> EResult<Self> {
+		let Some(sb_blk) = blocks.first() else {
+			return Err(errno!(EINVAL));
+		};
+		let mut buf = Vec::from_elem(0u8, blk_size)?;
+		io.read(sb_blk * blk_size as u64, &mut buf)?;
+
+		if get_u32(&buf, 0) != MAGIC {
+			return Err(errno!(EINVAL));
+		}
+		let incompat = match get_u32(&buf, 4) {
+			BLOCKTYPE_SUPERBLOCK_V1 => 0,
+			BLOCKTYPE_SUPERBLOCK_V2 => get_u32(&buf, 0x28),
+			_ => return Err(errno!(EINVAL)),
+		};
+		if incompat & !SUPPORTED_INCOMPAT != 0 {
+			return Err(errno!(EINVAL));
+		}
+
+		let maxlen = get_u32(&buf, 0x10);
+		let first = get_u32(&buf, 0x14);
+		let valid = get_u32(&buf, 0xc) as usize == blk_size
+			&& maxlen as usize <= blocks.len()
+			&& first > 0 && first < maxlen;
+		if !valid {
+			return Err(errno!(EINVAL));
+		}
+
+		let mut uuid = [0; UUID_SIZE];
+		uuid.copy_from_slice(&buf[0x30..(0x30 + UUID_SIZE)]);
+
+		Ok(Self {
+			blocks,
+			blk_size,
+
+			maxlen,
+			first,
+			sequence: get_u32(&buf, 0x18),
+			start: get_u32(&buf, 0x1c),
+			incompat,
+			uuid,
+		})
+	}
+
+	/// Reads the block `i` of the journal into `buf`.
+	fn read_blk(&self, io: &mut dyn IO, i: u32, buf: &mut [u8]) -> EResult<()> {
+		io.read(self.blocks[i as usize] * self.blk_size as u64, buf)?;
+		Ok(())
+	}
+
+	/// Writes `buf` to the block `i` of the journal.
+	fn write_blk(&self, io: &mut dyn IO, i: u32, buf: &[u8]) -> EResult<()> {
+		io.write(self.blocks[i as usize] * self.blk_size as u64, buf)?;
+		Ok(())
+	}
+
+	/// Returns the index of the block following the block `i` in the log, which wraps around at
+	/// the end of the journal.
+	fn next(&self, i: u32) -> u32 {
+		if i + 1 >= self.maxlen {
+			self.first
+		} else {
+			i + 1
+		}
+	}
+
+	/// Writes the sequence number and the beginning of the log `start` to the journal's
+	/// superblock.
+	fn write_superblock(&self, io: &mut dyn IO, start: u32) -> EResult<()> {
+		let mut buf = Vec::from_elem(0u8, self.blk_size)?;
+		self.read_blk(io, 0, &mut buf)?;
+		set_u32(&mut buf, 0x18, self.sequence);
+		set_u32(&mut buf, 0x1c, start);
+		self.write_blk(io, 0, &buf)
+	}
+
+	/// Returns the size of a descriptor block tag in bytes, without the UUID.
+	fn tag_size(&self) -> usize {
+		if self.incompat & FEATURE_INCOMPAT_64BIT != 0 {
+			12
+		} else {
+			8
+		}
+	}
+
+	/// Returns the tags of the descriptor block `buf`.
+	///
+	/// Each tag is made of the location of the block on the device and of its flags.
+	fn get_tags(&self, buf: &[u8]) -> EResult<Vec<(u64, u16)>> {
+		let mut tags = Vec::new();
+		let mut off = HEADER_SIZE;
+		while off + self.tag_size() <= buf.len() {
+			let mut blk = get_u32(buf, off) as u64;
+			let flags = get_u16(buf, off + 6);
+			if self.incompat & FEATURE_INCOMPAT_64BIT != 0 {
+				blk |= (get_u32(buf, off + 8) as u64) << 32;
+			}
+			tags.push((blk, flags))?;
+
+			off += self.tag_size();
+			if flags & TAG_FLAG_SAME_UUID == 0 {
+				off += UUID_SIZE;
+			}
+			if flags & TAG_FLAG_LAST_TAG != 0 {
+				break;
+			}
+		}
+		Ok(tags)
+	}
+
+	/// Returns the blocks listed in the revoke block `buf`.
+	fn get_revoked(&self, buf: &[u8]) -> EResult<Vec<u64>> {
+		let wide = self.incompat & FEATURE_INCOMPAT_64BIT != 0;
+		let entry_size = if wide { 8 } else { 4 };
+		let count = min(get_u32(buf, HEADER_SIZE) as usize, buf.len());
+
+		let mut revoked = Vec::new();
+		let mut off = HEADER_SIZE + 4;
+		while off + entry_size <= count {
+			let blk = if wide {
+				((get_u32(buf, off) as u64) << 32) | get_u32(buf, off + 4) as u64
+			} else {
+				get_u32(buf, off) as u64
+			};
+			revoked.push(blk)?;
+			off += entry_size;
+		}
+		Ok(revoked)
+	}
+
+	/// Replays the committed transactions remaining in the journal, then empties it.
+	///
+	/// This function must be called when mounting the filesystem, before any other access to it.
+	///
+	/// `io` is the I/O interface of the device.
+	pub fn recover(&mut self, io: &mut dyn IO) -> EResult<()> {
+		if self.start == 0 {
+			return Ok(());
+		}
+		let mut buf = Vec::from_elem(0u8, self.blk_size)?;
+		let mut data = Vec::from_elem(0u8, self.blk_size)?;
+
+		// Finding the end of the log and the revoked blocks, along with the sequence number of
+		// the last transaction revoking them
+		let mut revoked = HashMap::new();
+		let mut sequence = self.sequence;
+		let mut i = self.start;
+		loop {
+			self.read_blk(io, i, &mut buf)?;
+			if get_u32(&buf, 0) != MAGIC || get_u32(&buf, 8) != sequence {
+				break;
+			}
+			match get_u32(&buf, 4) {
+				BLOCKTYPE_DESCRIPTOR => {
+					for _ in self.get_tags(&buf)?.iter() {
+						i = self.next(i);
+					}
+				}
+				BLOCKTYPE_REVOKE => {
+					for blk in self.get_revoked(&buf)?.iter() {
+						revoked.insert(*blk, sequence)?;
+					}
+				}
+				BLOCKTYPE_COMMIT => sequence = sequence.wrapping_add(1),
+				_ => break,
+			}
+			i = self.next(i);
+		}
+		let end = sequence;
+
+		// Writing the blocks of committed transactions to their location
+		sequence = self.sequence;
+		i = self.start;
+		while sequence != end {
+			self.read_blk(io, i, &mut buf)?;
+			match get_u32(&buf, 4) {
+				BLOCKTYPE_DESCRIPTOR => {
+					for (blk, flags) in self.get_tags(&buf)?.iter() {
+						i = self.next(i);
+						let is_revoked = revoked
+							.get(blk)
+							.map(|seq| sequence.wrapping_sub(*seq) as i32 <= 0)
+							.unwrap_or(false);
+						if is_revoked {
+							continue;
+						}
+						self.read_blk(io, i, &mut data)?;
+						if flags & TAG_FLAG_ESCAPE != 0 {
+							set_u32(&mut data, 0, MAGIC);
+						}
+						io.write(blk * self.blk_size as u64, &data)?;
+					}
+				}
+				BLOCKTYPE_COMMIT => sequence = sequence.wrapping_add(1),
+				_ => {}
+			}
+			i = self.next(i);
+		}
+
+		self.sequence = end;
+		self.start = 0;
+		self.write_superblock(io, 0)
+	}
+
+	/// Returns the maximum number of blocks a transaction can modify to fit in the journal.
+	///
+	/// `tags_per_desc` is the number of tags in a descriptor block.
+	fn max_transaction_blocks(&self, tags_per_desc: usize) -> usize {
+		// Every `tags_per_desc` blocks require a descriptor block, and the transaction ends with a
+		// commit block
+		let space = ((self.maxlen - self.first) as usize).saturating_sub(1);
+		let full_descs = space / (tags_per_desc + 1);
+		let rem = (space % (tags_per_desc + 1)).saturating_sub(1);
+		full_descs * tags_per_desc + rem
+	}
+
+	/// Commits a transaction modifying the blocks `blocks`, then writes them to their location.
+	///
+	/// If the transaction doesn't fit in the journal, it is split into several transactions,
+	/// which are atomic individually but not as a whole.
+	///
+	/// Arguments:
+	/// - `io` is the I/O interface of the device.
+	/// - `blocks` is the new content of each modified block, by location on the device in
+	/// blocks.
+	pub fn commit(&mut self, io: &mut dyn IO, blocks: &Map<u64, Vec<u8>>) -> EResult<()> {
+		if blocks.is_empty() {
+			return Ok(());
+		}
+
+		// Reserving space for a UUID in each descriptor block, although only the first tag has
+		// one
+		let tags_per_desc = (self.blk_size - HEADER_SIZE - UUID_SIZE) / self.tag_size();
+		let max_blocks = self.max_transaction_blocks(tags_per_desc);
+		if max_blocks == 0 {
+			return Err(errno!(ENOSPC));
+		}
+
+		let mut keys = Vec::with_capacity(blocks.len())?;
+		for (blk, _) in blocks.iter() {
+			keys.push(*blk)?;
+		}
+		for chunk in keys.chunks(max_blocks) {
+			self.commit_blocks(io, blocks, chunk, tags_per_desc)?;
+		}
+		Ok(())
+	}
+
+	/// Commits a transaction modifying the blocks at locations `keys`, then writes them to their
+	/// location.
+	///
+	/// Arguments:
+	/// - `io` is the I/O interface of the device.
+	/// - `blocks` is the new content of each modified block, by location on the device in
+	/// blocks.
+	/// - `keys` is the list of locations of the blocks of the transaction, which must fit in the
+	/// journal.
+	/// - `tags_per_desc` is the number of tags in a descriptor block.
+	fn commit_blocks(
+		&mut self,
+		io: &mut dyn IO,
+		blocks: &Map<u64, Vec<u8>>,
+		keys: &[u64],
+		tags_per_desc: usize,
+	) -> EResult<()> {
+		let mut desc = Vec::from_elem(0u8, self.blk_size)?;
+		let mut data = Vec::from_elem(0u8, self.blk_size)?;
+		let mut i = self.first;
+		for chunk in keys.chunks(tags_per_desc) {
+			desc.fill(0);
+			set_header(&mut desc, BLOCKTYPE_DESCRIPTOR, self.sequence);
+			let desc_i = i;
+			i += 1;
+
+			let mut off = HEADER_SIZE;
+			for (j, blk) in chunk.iter().enumerate() {
+				data.copy_from_slice(blocks.get(*blk).unwrap());
+				// A block beginning with the signature would be mistaken for a journal block
+				let mut flags = 0;
+				if get_u32(&data, 0) == MAGIC {
+					set_u32(&mut data, 0, 0);
+					flags |= TAG_FLAG_ESCAPE;
+				}
+				if j > 0 {
+					flags |= TAG_FLAG_SAME_UUID;
+				}
+				if j + 1 == chunk.len() {
+					flags |= TAG_FLAG_LAST_TAG;
+				}
+
+				set_u32(&mut desc, off, *blk as u32);
+				set_u16(&mut desc, off + 6, flags);
+				if self.incompat & FEATURE_INCOMPAT_64BIT != 0 {
+					set_u32(&mut desc, off + 8, (*blk >> 32) as u32);
+				}
+				off += self.tag_size();
+				if j == 0 {
+					desc[off..(off + UUID_SIZE)].copy_from_slice(&self.uuid);
+					off += UUID_SIZE;
+				}
+
+				self.write_blk(io, i, &data)?;
+				i += 1;
+			}
+			self.write_blk(io, desc_i, &desc)?;
+		}
+		// The log must be complete before the commit block makes it valid
+		io.flush()?;
+
+		desc.fill(0);
+		set_header(&mut desc, BLOCKTYPE_COMMIT, self.sequence);
+		self.write_blk(io, i, &desc)?;
+		io.flush()?;
+
+		// From here, the transaction is replayed if the writes below are interrupted
+		self.write_superblock(io, self.first)?;
+		io.flush()?;
+		for blk in keys {
+			io.write(blk * self.blk_size as u64, blocks.get(*blk).unwrap())?;
+		}
+		// The blocks must be at their location before the log is discarded
+		io.flush()?;
+		self.sequence = self.sequence.wrapping_add(1);
+		self.write_superblock(io, 0)?;
+		io.flush()
+	}
+}
+
+/// An I/O interface recording the blocks modified through it, to be committed to a journal as a
+/// single transaction with [`Journal::commit`].
+///
+/// Reads return the modified content of the blocks. The underlying device is not written.
+pub struct Transaction<'i> {
+	/// The I/O interface of the device.
+	io: &'i mut dyn IO,
+	/// The size of a block in bytes.
+	blk_size: usize,
+	/// The new content of each modified block, by location on the device in blocks.
+	blocks: Map<u64, Vec<u8>>,
+}
+
+impl<'i> Transaction<'i> {
+	/// Creates a new transaction on the device with the I/O interface `io`.
+	///
+	/// `blk_size` is the size of a block in bytes.
+	pub fn new(io: &'i mut dyn IO, blk_size: usize) -> Self {
+		Self {
+			io,
+			blk_size,
+			blocks: Map::new(),
+		}
+	}
+
+	/// Returns the modified blocks.
+	pub fn into_blocks(self) -> Map<u64, Vec<u8>> {
+		self.blocks
+	}
+
+	/// Returns the content of the block `blk`, reading it from the device if it has not been
+	/// modified yet.
+	fn get_block(&mut self, blk: u64) -> EResult<&mut Vec<u8>> {
+		if self.blocks.get(blk).is_none() {
+			let mut content = Vec::from_elem(0u8, self.blk_size)?;
+			self.io.read(blk * self.blk_size as u64, &mut content)?;
+			self.blocks.insert(blk, content)?;
+		}
+		Ok(self.blocks.get_mut(blk).unwrap())
+	}
+}
+
+impl<'i> IO for Transaction<'i> {
+	fn get_size(&self) -> u64 {
+		self.io.get_size()
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		let mut i = 0;
+		while i < buff.len() {
+			let off = offset + i as u64;
+			let blk = off / self.blk_size as u64;
+			let inner_off = (off % self.blk_size as u64) as usize;
+			let len = min(buff.len() - i, self.blk_size - inner_off);
+
+			let dst = &mut buff[i..(i + len)];
+			match self.blocks.get(blk) {
+				Some(content) => dst.copy_from_slice(&content[inner_off..(inner_off + len)]),
+				None => {
+					self.io.read(off, dst)?;
+				}
+			}
+
+			i += len;
+		}
+
+		let eof = offset + i as u64 >= self.get_size();
+		Ok((i as _, eof))
+	}
+
+	fn write(&mut self, offset: u64, buff: &[u8]) -> Result<u64, Errno> {
+		let mut i = 0;
+		while i < buff.len() {
+			let off = offset + i as u64;
+			let blk = off / self.blk_size as u64;
+			let inner_off = (off % self.blk_size as u64) as usize;
+			let len = min(buff.len() - i, self.blk_size - inner_off);
+
+			let content = self.get_block(blk)?;
+			content[inner_off..(inner_off + len)].copy_from_slice(&buff[i..(i + len)]);
+
+			i += len;
+		}
+
+		Ok(i as _)
+	}
+
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		self.io.poll(mask)
+	}
+}
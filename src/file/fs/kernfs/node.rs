@@ -9,9 +9,8 @@ use crate::file::perm::Uid;
 use crate::file::FileContent;
 use crate::file::Mode;
 use crate::time::clock;
-use crate::time::clock::CLOCK_MONOTONIC;
-use crate::time::unit::Timestamp;
-use crate::time::unit::TimestampScale;
+use crate::time::clock::CLOCK_REALTIME;
+use crate::time::unit::Timespec;
 use crate::util::io::IO;
 use core::any::Any;
 
@@ -50,28 +49,28 @@ pub trait KernFSNode: Any + IO {
 	fn set_gid(&mut self, _gid: Gid) {}
 
 	/// Returns the timestamp of the last access to the file.
-	fn get_atime(&self) -> Timestamp {
-		0
+	fn get_atime(&self) -> Timespec {
+		Timespec::default()
 	}
 
 	/// Sets the timestamp of the last access to the file.
-	fn set_atime(&mut self, _ts: Timestamp) {}
+	fn set_atime(&mut self, _ts: Timespec) {}
 
 	/// Returns the timestamp of the last modification of the file's metadata.
-	fn get_ctime(&self) -> Timestamp {
-		0
+	fn get_ctime(&self) -> Timespec {
+		Timespec::default()
 	}
 
 	/// Sets the timestamp of the last modification of the file's metadata.
-	fn set_ctime(&mut self, _ts: Timestamp) {}
+	fn set_ctime(&mut self, _ts: Timespec) {}
 
 	/// Returns the timestamp of the last modification of the file's content.
-	fn get_mtime(&self) -> Timestamp {
-		0
+	fn get_mtime(&self) -> Timespec {
+		Timespec::default()
 	}
 
 	/// Sets the timestamp of the last modification of the file's content.
-	fn set_mtime(&mut self, _ts: Timestamp) {}
+	fn set_mtime(&mut self, _ts: Timespec) {}
 
 	/// Returns an immutable reference to the node's content.
 	fn get_content(&mut self) -> EResult<KernFSContent<'_>>;
@@ -94,11 +93,11 @@ pub struct DummyKernFSNode {
 	gid: Gid,
 
 	/// Timestamp of the last modification of the metadata.
-	ctime: Timestamp,
+	ctime: Timespec,
 	/// Timestamp of the last modification of the file.
-	mtime: Timestamp,
+	mtime: Timespec,
 	/// Timestamp of the last access to the file.
-	atime: Timestamp,
+	atime: Timespec,
 
 	/// The node's content.
 	content: FileContent,
@@ -114,7 +113,7 @@ impl DummyKernFSNode {
 	/// - `content` is the node's content.
 	pub fn new(mode: Mode, uid: Uid, gid: Gid, content: FileContent) -> Self {
 		// The current timestamp
-		let ts = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Second).unwrap_or(0);
+		let ts = clock::current_time_struct::<Timespec>(CLOCK_REALTIME).unwrap_or_default();
 
 		Self {
 			hard_links_count: 1,
@@ -165,27 +164,27 @@ impl KernFSNode for DummyKernFSNode {
 		self.gid = gid;
 	}
 
-	fn get_atime(&self) -> Timestamp {
+	fn get_atime(&self) -> Timespec {
 		self.atime
 	}
 
-	fn set_atime(&mut self, ts: Timestamp) {
+	fn set_atime(&mut self, ts: Timespec) {
 		self.atime = ts;
 	}
 
-	fn get_ctime(&self) -> Timestamp {
+	fn get_ctime(&self) -> Timespec {
 		self.ctime
 	}
 
-	fn set_ctime(&mut self, ts: Timestamp) {
+	fn set_ctime(&mut self, ts: Timespec) {
 		self.ctime = ts;
 	}
 
-	fn get_mtime(&self) -> Timestamp {
+	fn get_mtime(&self) -> Timespec {
 		self.mtime
 	}
 
-	fn set_mtime(&mut self, ts: Timestamp) {
+	fn set_mtime(&mut self, ts: Timespec) {
 		self.mtime = ts;
 	}
 
@@ -1,27 +1,41 @@
 //! A filesystem is the representation of the file hierarchy on a storage
 //! device.
 
+pub mod cgroup;
+pub mod devtmpfs;
+pub mod exfat;
 pub mod ext2;
 pub mod initramfs;
+pub mod iso9660;
+pub mod journal;
 pub mod kernfs;
+pub mod overlay;
 pub mod procfs;
+pub mod squashfs;
+pub mod sysfs;
 pub mod tmp;
 
 use super::path::Path;
 use super::File;
 use crate::errno;
+use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file::perm::Gid;
 use crate::file::perm::Uid;
+use crate::file::DirEntry;
 use crate::file::FileContent;
 use crate::file::INode;
 use crate::file::Mode;
 use crate::util::container::hashmap::HashMap;
 use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
 use crate::util::io::IO;
 use crate::util::lock::Mutex;
 use crate::util::ptr::arc::Arc;
+use crate::util::DisplayableStr;
 use core::any::Any;
+use core::fmt;
+use core::str;
 
 /// This structure is used in the f_fsid field of statfs. It is currently
 /// unused.
@@ -60,6 +74,11 @@ pub struct Statfs {
 	f_flags: u32,
 }
 
+/// A function called on each entry when iterating over a directory.
+///
+/// See [`Filesystem::iter_entries`].
+pub type EntryVisitor<'f> = dyn FnMut(&[u8], &DirEntry, u64) -> Result<bool, Errno> + 'f;
+
 /// Trait representing a filesystem.
 pub trait Filesystem: Any {
 	/// Returns the name of the filesystem.
@@ -101,6 +120,54 @@ pub trait Filesystem: Any {
 	/// - `name` is the file's name.
 	fn load_file(&mut self, io: &mut dyn IO, inode: INode, name: String) -> Result<File, Errno>;
 
+	/// Iterates over the entries of the directory with inode `inode`, starting at offset `off`.
+	///
+	/// Arguments:
+	/// - `io` is the IO interface.
+	/// - `inode` is the directory's inode.
+	/// - `off` is the offset of the first entry. Offsets are opaque values defined by the
+	/// filesystem, `0` being the beginning of the directory.
+	/// - `f` is called for each entry with its name, the entry itself and the offset of the
+	/// next entry. If it returns `false`, the iteration stops.
+	///
+	/// If the file is not a directory, the function returns an error.
+	///
+	/// By default, the whole directory is loaded and entries are iterated over by index.
+	fn iter_entries(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		f: &mut EntryVisitor,
+	) -> Result<(), Errno> {
+		let dir = self.load_file(io, inode, String::new())?;
+		let FileContent::Directory(entries) = dir.get_content() else {
+			return Err(errno!(ENOTDIR));
+		};
+		for (i, (name, entry)) in entries.iter().enumerate().skip(off as _) {
+			if !f(name.as_bytes(), entry, i as u64 + 1)? {
+				break;
+			}
+		}
+		Ok(())
+	}
+
+	/// Tells whether the directory with inode `inode` is empty, which is required to remove it.
+	///
+	/// Arguments:
+	/// - `io` is the IO interface.
+	/// - `inode` is the directory's inode.
+	///
+	/// By default, a directory is empty if it has no entry other than `.` and `..`.
+	fn is_empty_dir(&mut self, io: &mut dyn IO, inode: INode) -> Result<bool, Errno> {
+		let mut empty = true;
+		self.iter_entries(io, inode, 0, &mut |name, _, _| {
+			empty = matches!(name, b"." | b"..");
+			Ok(empty)
+		})?;
+		Ok(empty)
+	}
+
 	/// Adds a file to the filesystem at inode `inode`.
 	///
 	/// Arguments:
@@ -199,6 +266,264 @@ pub trait Filesystem: Any {
 		off: u64,
 		buf: &[u8],
 	) -> Result<(), Errno>;
+
+	/// Allocates the blocks of the given inode `inode` in the range of `len` bytes beginning at
+	/// offset `off`, so that subsequent writes in this range cannot fail for lack of space.
+	///
+	/// Arguments:
+	/// - `io` is the IO interface.
+	/// - `inode` is the file's inode.
+	/// - `off` is the offset of the beginning of the range.
+	/// - `len` is the length of the range in bytes.
+	///
+	/// Blocks that are already allocated are left untouched, and newly allocated blocks are read
+	/// as zeros. The size of the file is not changed.
+	///
+	/// If this feature is not supported by the filesystem, the function returns an error.
+	fn allocate(
+		&mut self,
+		_io: &mut dyn IO,
+		_inode: INode,
+		_off: u64,
+		_len: u64,
+	) -> Result<(), Errno> {
+		Err(errno!(EOPNOTSUPP))
+	}
+
+	/// Deallocates the blocks of the given inode `inode` in the range of `len` bytes beginning at
+	/// offset `off`, so that the range becomes a hole which is read as zeros.
+	///
+	/// Arguments:
+	/// - `io` is the IO interface.
+	/// - `inode` is the file's inode.
+	/// - `off` is the offset of the beginning of the range.
+	/// - `len` is the length of the range in bytes.
+	///
+	/// Blocks that are only partially in the range are zeroed instead of being freed. The size
+	/// of the file is not changed.
+	///
+	/// If this feature is not supported by the filesystem, the function returns an error.
+	fn punch_hole(
+		&mut self,
+		_io: &mut dyn IO,
+		_inode: INode,
+		_off: u64,
+		_len: u64,
+	) -> Result<(), Errno> {
+		Err(errno!(EOPNOTSUPP))
+	}
+
+	/// Returns the offset of the beginning of the next region of data of the given inode `inode`,
+	/// or of the next hole if `hole` is set, at or after offset `off`.
+	///
+	/// Arguments:
+	/// - `io` is the IO interface.
+	/// - `inode` is the file's inode.
+	/// - `off` is the offset from which the search begins.
+	/// - `hole` tells whether a hole is searched instead of data.
+	///
+	/// If no such region is found before the end of the file, the function returns `None`.
+	///
+	/// By default, files are considered to have no hole.
+	fn next_extent(
+		&mut self,
+		_io: &mut dyn IO,
+		_inode: INode,
+		off: u64,
+		hole: bool,
+	) -> Result<Option<u64>, Errno> {
+		Ok((!hole).then_some(off))
+	}
+
+	/// Returns the value of the extended attribute `name` of the inode `inode`.
+	///
+	/// Arguments:
+	/// - `io` is the IO interface.
+	/// - `inode` is the file's inode.
+	/// - `name` is the full name of the attribute, including its namespace prefix.
+	///
+	/// If the attribute doesn't exist, the function returns `None`.
+	///
+	/// If extended attributes are not supported by the filesystem, the function returns an
+	/// error.
+	fn get_xattr(
+		&mut self,
+		_io: &mut dyn IO,
+		_inode: INode,
+		_name: &[u8],
+	) -> Result<Option<Vec<u8>>, Errno> {
+		Err(errno!(EOPNOTSUPP))
+	}
+
+	/// Returns the full names of the extended attributes of the inode `inode`.
+	///
+	/// Arguments:
+	/// - `io` is the IO interface.
+	/// - `inode` is the file's inode.
+	///
+	/// If extended attributes are not supported by the filesystem, the function returns an
+	/// error.
+	fn list_xattr(&mut self, _io: &mut dyn IO, _inode: INode) -> Result<Vec<String>, Errno> {
+		Err(errno!(EOPNOTSUPP))
+	}
+
+	/// Sets the value of the extended attribute `name` of the inode `inode`.
+	///
+	/// Arguments:
+	/// - `io` is the IO interface.
+	/// - `inode` is the file's inode.
+	/// - `name` is the full name of the attribute, including its namespace prefix.
+	/// - `value` is the new value of the attribute. If `None`, the attribute is removed.
+	///
+	/// The function returns `true` if the attribute was already present.
+	///
+	/// If extended attributes are not supported by the filesystem, the function returns an
+	/// error.
+	fn set_xattr(
+		&mut self,
+		_io: &mut dyn IO,
+		_inode: INode,
+		_name: &[u8],
+		_value: Option<&[u8]>,
+	) -> Result<bool, Errno> {
+		Err(errno!(EOPNOTSUPP))
+	}
+
+	/// Returns the generation number of the inode `inode`, used to build file handles.
+	///
+	/// Since an inode may be reused after its file is removed, the generation number of an inode
+	/// must change each time it is reused, so that handles to the previous file are detected as
+	/// stale.
+	///
+	/// Arguments:
+	/// - `io` is the IO interface.
+	/// - `inode` is the file's inode.
+	///
+	/// If the inode is not in use, the function returns `ESTALE`.
+	///
+	/// If the filesystem cannot provide persistent file handles, the function returns an error.
+	fn get_generation(&mut self, _io: &mut dyn IO, _inode: INode) -> Result<u32, Errno> {
+		Err(errno!(EOPNOTSUPP))
+	}
+}
+
+/// Description of a mount option accepted by a filesystem.
+pub struct MountOptionDesc {
+	/// The name of the option.
+	pub name: &'static [u8],
+	/// Tells whether the option takes a value, in the form `name=value`.
+	pub has_value: bool,
+}
+
+/// The mount options given to a filesystem, checked against the options it declares.
+pub struct MountOptions<'d> {
+	/// The list of options with their respective values, in the order they were given.
+	options: Vec<(&'static [u8], Option<&'d [u8]>)>,
+}
+
+impl<'d> MountOptions<'d> {
+	/// Returns an empty set of options.
+	pub fn empty() -> Self {
+		Self {
+			options: Vec::new(),
+		}
+	}
+
+	/// Parses the string of mount options `data`, in which options are separated by commas.
+	///
+	/// `desc` is the list of options accepted by the filesystem.
+	///
+	/// If an option is unknown, or if an option is given a value while it does not take one (or
+	/// the other way around), the function returns `EINVAL`.
+	pub fn parse(data: &'d [u8], desc: &[MountOptionDesc]) -> EResult<Self> {
+		let mut options = Vec::new();
+		for opt in data.split(|c| *c == b',').filter(|opt| !opt.is_empty()) {
+			let (name, value) = match opt.iter().position(|c| *c == b'=') {
+				Some(i) => (&opt[..i], Some(&opt[(i + 1)..])),
+				None => (opt, None),
+			};
+			let desc = desc
+				.iter()
+				.find(|desc| desc.name == name)
+				.ok_or_else(|| errno!(EINVAL))?;
+			if desc.has_value != value.is_some() {
+				return Err(errno!(EINVAL));
+			}
+			options.push((desc.name, value))?;
+		}
+		Ok(Self {
+			options,
+		})
+	}
+
+	/// Tells whether the option with name `name` is set.
+	pub fn is_set(&self, name: &[u8]) -> bool {
+		self.options.iter().any(|(n, _)| *n == name)
+	}
+
+	/// Returns the value of the option with name `name`.
+	///
+	/// If the option is given several times, the last value is returned.
+	pub fn get(&self, name: &[u8]) -> Option<&'d [u8]> {
+		self.options
+			.iter()
+			.rev()
+			.find(|(n, _)| *n == name)
+			.and_then(|(_, value)| *value)
+	}
+
+	/// Returns the value of the option with name `name`, parsed as a number in base `radix`.
+	///
+	/// If the value is not a valid number, the function returns `EINVAL`.
+	pub fn get_number(&self, name: &[u8], radix: u32) -> EResult<Option<u64>> {
+		let Some(value) = self.get(name) else {
+			return Ok(None);
+		};
+		let n = str::from_utf8(value)
+			.ok()
+			.and_then(|s| u64::from_str_radix(s, radix).ok())
+			.ok_or_else(|| errno!(EINVAL))?;
+		Ok(Some(n))
+	}
+
+	/// Returns the value of the option with name `name`, parsed as a size in bytes.
+	///
+	/// The size may be followed by one of the suffixes `k`, `m` or `g` (case-insensitive) for
+	/// kibibytes, mebibytes and gibibytes.
+	///
+	/// If the value is not a valid size, the function returns `EINVAL`.
+	pub fn get_size(&self, name: &[u8]) -> EResult<Option<u64>> {
+		let Some(value) = self.get(name) else {
+			return Ok(None);
+		};
+		let (digits, shift) = match value.last().map(u8::to_ascii_lowercase) {
+			Some(b'k') => (&value[..(value.len() - 1)], 10),
+			Some(b'm') => (&value[..(value.len() - 1)], 20),
+			Some(b'g') => (&value[..(value.len() - 1)], 30),
+			_ => (value, 0),
+		};
+		let size = str::from_utf8(digits)
+			.ok()
+			.and_then(|s| s.parse::<u64>().ok())
+			.and_then(|n| n.checked_mul(1 << shift))
+			.ok_or_else(|| errno!(EINVAL))?;
+		Ok(Some(size))
+	}
+}
+
+impl<'d> fmt::Display for MountOptions<'d> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		for (i, (name, value)) in self.options.iter().enumerate() {
+			if i > 0 {
+				write!(fmt, ",")?;
+			}
+			write!(fmt, "{}", DisplayableStr(name))?;
+			if let Some(value) = value {
+				write!(fmt, "={}", DisplayableStr(value))?;
+			}
+		}
+		Ok(())
+	}
 }
 
 /// Trait representing a filesystem type.
@@ -211,17 +536,24 @@ pub trait FilesystemType {
 	/// `io` is the IO interface.
 	fn detect(&self, io: &mut dyn IO) -> Result<bool, Errno>;
 
+	/// Returns the list of mount options accepted by the filesystem.
+	fn get_mount_options(&self) -> &'static [MountOptionDesc] {
+		&[]
+	}
+
 	/// Creates a new instance of the filesystem to mount it.
 	///
 	/// Arguments:
 	/// - `io` is the IO interface.
 	/// - `mountpath` is the path on which the filesystem is mounted.
 	/// - `readonly` tells whether the filesystem is mounted in read-only.
+	/// - `options` are the filesystem-specific mount options.
 	fn load_filesystem(
 		&self,
 		io: &mut dyn IO,
 		mountpath: Path,
 		readonly: bool,
+		options: &MountOptions,
 	) -> Result<Arc<Mutex<dyn Filesystem>>, Errno>;
 }
 
@@ -269,10 +601,61 @@ pub fn detect(io: &mut dyn IO) -> Result<Arc<dyn FilesystemType>, Errno> {
 ///
 /// This function must be called only once, at initialization.
 pub fn register_defaults() -> Result<(), Errno> {
+	register(devtmpfs::DevTmpFsType {})?;
+	register(exfat::ExfatFsType {})?;
 	register(ext2::Ext2FsType {})?;
+	register(iso9660::Iso9660FsType {})?;
+	register(overlay::OverlayFsType {})?;
+	register(squashfs::SquashFsType {})?;
 	register(tmp::TmpFsType {})?;
 	register(procfs::ProcFsType {})?;
-	// TODO sysfs
+	register(sysfs::SysFsType {})?;
+	register(cgroup::CGroupFsType {})?;
 
 	Ok(())
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	/// Options used for testing.
+	const OPTIONS: &[MountOptionDesc] = &[
+		MountOptionDesc {
+			name: b"noatime",
+			has_value: false,
+		},
+		MountOptionDesc {
+			name: b"size",
+			has_value: true,
+		},
+	];
+
+	#[test_case]
+	fn mount_options_parse0() {
+		let opts = MountOptions::parse(b"", OPTIONS).unwrap();
+		assert!(!opts.is_set(b"noatime"));
+		assert_eq!(opts.get(b"size"), None);
+	}
+
+	#[test_case]
+	fn mount_options_parse1() {
+		let opts = MountOptions::parse(b"noatime,size=10,size=4k", OPTIONS).unwrap();
+		assert!(opts.is_set(b"noatime"));
+		assert_eq!(opts.get(b"size"), Some(&b"4k"[..]));
+		assert_eq!(opts.get_size(b"size").unwrap(), Some(4096));
+	}
+
+	#[test_case]
+	fn mount_options_parse2() {
+		assert!(MountOptions::parse(b"unknown", OPTIONS).is_err());
+		assert!(MountOptions::parse(b"noatime=1", OPTIONS).is_err());
+		assert!(MountOptions::parse(b"size", OPTIONS).is_err());
+	}
+
+	#[test_case]
+	fn mount_options_size() {
+		let opts = MountOptions::parse(b"size=abc", OPTIONS).unwrap();
+		assert!(opts.get_size(b"size").is_err());
+	}
+}
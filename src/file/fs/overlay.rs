@@ -0,0 +1,745 @@
+//! The overlay filesystem combines several directories, called layers, into a single tree.
+//!
+//! The upper layer is writable, while lower layers are read-only. When looking up a file, layers
+//! are searched from the top to the bottom and the first instance found is used, except for
+//! directories, whose contents are merged.
+//!
+//! Before a file from a lower layer is modified, it is copied to the upper layer along with its
+//! parent directories (*copy-up*). When a file from a lower layer is removed, a *whiteout* is
+//! created in the upper layer to hide it. A whiteout is a character device with device number
+//! `0:0`. A directory of the upper layer containing a file named [`OPAQUE_MARKER`] is *opaque*:
+//! it hides the directories at the same path in lower layers.
+//!
+//! Mount options are:
+//! - `lowerdir`: the lower layers, separated by `:`, from the top to the bottom
+//! - `upperdir`: the upper layer. If not specified, the filesystem is read-only
+//! - `workdir`: accepted for compatibility, but not used
+//!
+//! Layers are given as absolute paths, and cannot be located under the mountpoint of the overlay.
+
+use super::Filesystem;
+use super::FilesystemType;
+use super::MountOptionDesc;
+use super::MountOptions;
+use super::Statfs;
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::page_cache;
+use crate::file::path::Path;
+use crate::file::perm::AccessProfile;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::vfs;
+use crate::file::DirEntry;
+use crate::file::File;
+use crate::file::FileContent;
+use crate::file::FileLocation;
+use crate::file::FileType;
+use crate::file::INode;
+use crate::file::Mode;
+use crate::memory;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use crate::util::TryClone;
+
+/// The overlay filesystem's magic number.
+const OVERLAYFS_MAGIC: u32 = 0x794c7630;
+
+/// The inode of the root directory.
+const ROOT_INODE: INode = 1;
+
+/// The name of the file marking a directory as opaque.
+pub const OPAQUE_MARKER: &[u8] = b".wh..wh..opq";
+
+/// Tells whether the file `file` is a whiteout.
+fn is_whiteout(file: &File) -> bool {
+	matches!(
+		file.get_content(),
+		FileContent::CharDevice {
+			major: 0,
+			minor: 0,
+		}
+	)
+}
+
+/// Tells whether the directory `dir` is opaque.
+fn is_opaque(dir: &File) -> EResult<bool> {
+	let mut opaque = false;
+	dir.iter_entries(0, |name, _, _| {
+		opaque = name == OPAQUE_MARKER;
+		Ok(!opaque)
+	})?;
+	Ok(opaque)
+}
+
+/// Returns the file with name `name` in the directory `dir` of a layer.
+///
+/// If the file doesn't exist, the function returns `None`.
+fn lookup(dir: &File, name: &String) -> EResult<Option<Arc<Mutex<File>>>> {
+	match vfs::get_file_from_parent(dir, name.try_clone()?, &AccessProfile::KERNEL, false) {
+		Ok(file) => Ok(Some(file)),
+		Err(e) if e.as_int() == errno::ENOENT || e.as_int() == errno::ENOTDIR => Ok(None),
+		Err(e) => Err(e),
+	}
+}
+
+/// Removes the whiteout with name `name` in the directory `dir` of the upper layer.
+///
+/// If the function removed a whiteout, it returns `true`.
+fn remove_whiteout(dir: &File, name: &String) -> EResult<bool> {
+	let Some(file_mutex) = lookup(dir, name)? else {
+		return Ok(false);
+	};
+	let mut file = file_mutex.lock();
+	if !is_whiteout(&file) {
+		return Ok(false);
+	}
+	vfs::remove_file(&mut file, &AccessProfile::KERNEL)?;
+	Ok(true)
+}
+
+/// Copies the content of the regular file `src` to `dst`.
+fn copy_content(src: &mut File, dst: &mut File) -> EResult<()> {
+	let mut buf = Vec::from_elem(0u8, memory::PAGE_SIZE)?;
+	let mut off = 0;
+	loop {
+		let (len, eof) = src.read(off, &mut buf)?;
+		dst.write(off, &buf[..(len as usize)])?;
+		off += len;
+
+		if eof || len == 0 {
+			break;
+		}
+	}
+	Ok(())
+}
+
+/// Parses the path to a layer.
+///
+/// `mountpath` is the path on which the overlay is mounted.
+fn parse_layer(mountpath: &Path, path: &[u8]) -> EResult<Path> {
+	let path = Path::from_str(path, true)?;
+	// Resolving a layer located under the overlay would recurse into the overlay itself
+	if !path.is_absolute() || path.begins_with(mountpath) {
+		return Err(errno!(EINVAL));
+	}
+	Ok(path)
+}
+
+/// The instances of a file in the layers it is visible in, from the top to the bottom, with the
+/// index of their layer.
+type Instances = Vec<(usize, Arc<Mutex<File>>)>;
+
+/// Structure representing an overlay filesystem.
+pub struct OverlayFS {
+	/// The path to the upper layer. If `None`, the filesystem is read-only.
+	upper: Option<Path>,
+	/// The paths to the lower layers, from the top to the bottom.
+	lower: Vec<Path>,
+	/// Tells whether the filesystem is mounted in read-only.
+	readonly: bool,
+
+	/// The path of each allocated inode, relative to the root of the filesystem.
+	paths: HashMap<INode, Path>,
+	/// The inode allocated for each path.
+	inodes: HashMap<Path, INode>,
+	/// The next inode to be allocated.
+	next_inode: INode,
+}
+
+impl OverlayFS {
+	/// Creates a new instance.
+	///
+	/// Arguments:
+	/// - `mountpath` is the path on which the filesystem is mounted.
+	/// - `readonly` tells whether the filesystem is mounted in read-only.
+	/// - `options` are the mount options.
+	pub fn new(mountpath: &Path, readonly: bool, options: &MountOptions) -> EResult<Self> {
+		let mut lower = Vec::new();
+		if let Some(value) = options.get(b"lowerdir") {
+			for path in value.split(|c| *c == b':') {
+				lower.push(parse_layer(mountpath, path)?)?;
+			}
+		}
+		let upper = options
+			.get(b"upperdir")
+			.map(|value| parse_layer(mountpath, value))
+			.transpose()?;
+		// TODO Use the work directory to make copy-up atomic
+		if lower.is_empty() {
+			return Err(errno!(EINVAL));
+		}
+
+		// Check layers
+		for layer in upper.iter().chain(lower.iter()) {
+			let file_mutex = vfs::get_file_from_path(layer, &AccessProfile::KERNEL, true)?;
+			if file_mutex.lock().get_type() != FileType::Directory {
+				return Err(errno!(ENOTDIR));
+			}
+		}
+
+		let mut fs = Self {
+			upper,
+			lower,
+			readonly,
+
+			paths: HashMap::new(),
+			inodes: HashMap::new(),
+			next_inode: ROOT_INODE + 1,
+		};
+		let root = Path::from_str(b"", false)?;
+		fs.paths.insert(ROOT_INODE, root.try_clone()?)?;
+		fs.inodes.insert(root, ROOT_INODE)?;
+
+		Ok(fs)
+	}
+
+	/// Returns an iterator over the paths of layers, from the top to the bottom.
+	fn layers(&self) -> impl Iterator<Item = &Path> {
+		self.upper.iter().chain(self.lower.iter())
+	}
+
+	/// Tells whether the layer with index `layer` is the upper layer.
+	fn is_upper(&self, layer: usize) -> bool {
+		self.upper.is_some() && layer == 0
+	}
+
+	/// Returns the path of the file with inode `inode`, relative to the root of the filesystem.
+	fn get_path(&self, inode: INode) -> EResult<Path> {
+		let path = self.paths.get(&inode).ok_or_else(|| errno!(ENOENT))?;
+		Ok(path.try_clone()?)
+	}
+
+	/// Returns the inode for the path `path`, allocating it if necessary.
+	fn get_path_inode(&mut self, path: Path) -> EResult<INode> {
+		if let Some(inode) = self.inodes.get(&path) {
+			return Ok(*inode);
+		}
+
+		let inode = self.next_inode;
+		self.paths.insert(inode, path.try_clone()?)?;
+		if let Err(e) = self.inodes.insert(path, inode) {
+			self.paths.remove(&inode);
+			return Err(e.into());
+		}
+		self.next_inode += 1;
+
+		Ok(inode)
+	}
+
+	/// Returns the instances of the file at `path`, relative to the root of the filesystem.
+	///
+	/// If `skip_upper` is `true`, the upper layer is ignored.
+	///
+	/// Only a directory can have several instances, in which case their contents are merged.
+	///
+	/// If the file doesn't exist, the function returns an empty list.
+	fn resolve(&self, path: &Path, skip_upper: bool) -> EResult<Instances> {
+		let skip = if skip_upper && self.upper.is_some() {
+			1
+		} else {
+			0
+		};
+
+		let mut instances = Vec::new();
+		for (i, layer) in self.layers().enumerate().skip(skip) {
+			let root = vfs::get_file_from_path(layer, &AccessProfile::KERNEL, true)?;
+			instances.push((i, root))?;
+		}
+
+		for i in 0..path.get_elements_count() {
+			let mut next = Vec::new();
+
+			for (layer, dir_mutex) in instances.iter() {
+				let Some(file_mutex) = lookup(&dir_mutex.lock(), &path[i])? else {
+					continue;
+				};
+				let file = file_mutex.lock();
+				if is_whiteout(&file) {
+					break;
+				}
+
+				// A file that is not a directory hides every instance below
+				if file.get_type() != FileType::Directory {
+					drop(file);
+					if next.is_empty() {
+						next.push((*layer, file_mutex))?;
+					}
+					break;
+				}
+
+				let opaque = is_opaque(&file)?;
+				drop(file);
+				next.push((*layer, file_mutex))?;
+				if opaque {
+					break;
+				}
+			}
+
+			instances = next;
+		}
+
+		Ok(instances)
+	}
+
+	/// Returns the topmost instance of the file at `path`, along with a boolean telling whether
+	/// it is located on the upper layer.
+	///
+	/// If the file doesn't exist, the function returns an error.
+	fn get_top(&self, path: &Path) -> EResult<(Arc<Mutex<File>>, bool)> {
+		let instances = self.resolve(path, false)?;
+		let (layer, file) = instances.first().ok_or_else(|| errno!(ENOENT))?;
+		Ok((file.clone(), self.is_upper(*layer)))
+	}
+
+	/// Merges the entries of the instances `dirs` of the directory at `path`.
+	fn merge_entries(
+		&mut self,
+		path: &Path,
+		dirs: &Instances,
+	) -> EResult<HashMap<String, DirEntry>> {
+		// Entries hidden by a whiteout are set to `None`
+		let mut merged: HashMap<String, Option<DirEntry>> = HashMap::new();
+		for (_, dir_mutex) in dirs.iter() {
+			let dir = dir_mutex.lock();
+			if dir.get_type() != FileType::Directory {
+				continue;
+			}
+			// Entries are collected first since looking them up requires the filesystem of
+			// the layer, which is locked while iterating
+			let mut entries = Vec::new();
+			dir.iter_entries(0, |name, entry, _| {
+				entries.push((String::try_from(name)?, entry.clone()))?;
+				Ok(true)
+			})?;
+
+			for (name, entry) in entries.iter() {
+				let name_bytes = name.as_bytes();
+				if matches!(name_bytes, b"." | b"..")
+					|| name_bytes == OPAQUE_MARKER
+					|| merged.contains_key(name)
+				{
+					continue;
+				}
+
+				let whiteout = entry.entry_type == FileType::CharDevice
+					&& lookup(&dir, name)?
+						.map(|file| is_whiteout(&file.lock()))
+						.unwrap_or(true);
+				let entry = if whiteout {
+					None
+				} else {
+					let mut child_path = path.try_clone()?;
+					child_path.push(name.try_clone()?)?;
+					Some(DirEntry {
+						inode: self.get_path_inode(child_path)?,
+						entry_type: entry.entry_type,
+					})
+				};
+				merged.insert(name.try_clone()?, entry)?;
+			}
+		}
+
+		let mut entries = HashMap::new();
+		for (name, entry) in merged.iter() {
+			if let Some(entry) = entry {
+				entries.insert(name.try_clone()?, entry.clone())?;
+			}
+		}
+
+		let mut parent_path = path.try_clone()?;
+		parent_path.pop();
+		entries.insert(
+			String::try_from(b".")?,
+			DirEntry {
+				inode: self.get_path_inode(path.try_clone()?)?,
+				entry_type: FileType::Directory,
+			},
+		)?;
+		entries.insert(
+			String::try_from(b"..")?,
+			DirEntry {
+				inode: self.get_path_inode(parent_path)?,
+				entry_type: FileType::Directory,
+			},
+		)?;
+
+		Ok(entries)
+	}
+
+	/// Copies the file at `path` to the upper layer, along with its parent directories, if not
+	/// already there.
+	///
+	/// The function returns the instance of the file on the upper layer.
+	fn copy_up(&self, path: &Path) -> EResult<Arc<Mutex<File>>> {
+		if self.upper.is_none() {
+			return Err(errno!(EROFS));
+		}
+		let (src_mutex, in_upper) = self.get_top(path)?;
+		if in_upper {
+			return Ok(src_mutex);
+		}
+
+		// The root directory is always present on the upper layer, so the path is not empty
+		let mut parent_path = path.try_clone()?;
+		let name = parent_path.pop().ok_or_else(|| errno!(ENOENT))?;
+		let parent_mutex = self.copy_up(&parent_path)?;
+		let mut parent = parent_mutex.lock();
+
+		let mut src = src_mutex.lock();
+		let content = match src.get_content() {
+			FileContent::Directory(_) => FileContent::Directory(HashMap::new()),
+			content => content.try_clone()?,
+		};
+		let file_mutex = vfs::create_file(
+			&mut parent,
+			name,
+			&AccessProfile::KERNEL,
+			src.get_permissions(),
+			content,
+		)?;
+
+		{
+			let mut file = file_mutex.lock();
+			if src.get_type() == FileType::Regular {
+				copy_content(&mut src, &mut file)?;
+			}
+
+			file.set_uid(src.get_uid());
+			file.set_gid(src.get_gid());
+			file.ctime = src.ctime;
+			file.mtime = src.mtime;
+			file.atime = src.atime;
+			file.sync()?;
+		}
+
+		Ok(file_mutex)
+	}
+}
+
+impl Filesystem for OverlayFS {
+	fn get_name(&self) -> &[u8] {
+		b"overlay"
+	}
+
+	fn is_readonly(&self) -> bool {
+		self.readonly || self.upper.is_none()
+	}
+
+	fn must_cache(&self) -> bool {
+		// Layers have their own cache
+		false
+	}
+
+	fn get_stat(&self, _io: &mut dyn IO) -> EResult<Statfs> {
+		// Files are written on the upper layer, so its usage is reported
+		let layer = self.layers().next().unwrap();
+		let file_mutex = vfs::get_file_from_path(layer, &AccessProfile::KERNEL, true)?;
+		let mountpoint_mutex = file_mutex
+			.lock()
+			.get_location()
+			.get_mountpoint()
+			.ok_or_else(|| errno!(ENOENT))?;
+		let mountpoint = mountpoint_mutex.lock();
+
+		let io_mutex = mountpoint.get_source().get_io()?;
+		let mut io = io_mutex.lock();
+
+		let fs_mutex = mountpoint.get_filesystem();
+		let fs = fs_mutex.lock();
+
+		let mut stat = fs.get_stat(&mut *io)?;
+		stat.f_type = OVERLAYFS_MAGIC;
+		Ok(stat)
+	}
+
+	fn get_root_inode(&self, _io: &mut dyn IO) -> EResult<INode> {
+		Ok(ROOT_INODE)
+	}
+
+	fn get_inode(
+		&mut self,
+		_io: &mut dyn IO,
+		parent: Option<INode>,
+		name: &[u8],
+	) -> EResult<INode> {
+		let parent = parent.unwrap_or(ROOT_INODE);
+		let mut path = self.get_path(parent)?;
+		match name {
+			b"." => return Ok(parent),
+
+			b".." => {
+				path.pop();
+				return self.get_path_inode(path);
+			}
+
+			OPAQUE_MARKER => return Err(errno!(ENOENT)),
+
+			_ => path.push(String::try_from(name)?)?,
+		}
+
+		if self.resolve(&path, false)?.is_empty() {
+			return Err(errno!(ENOENT));
+		}
+		self.get_path_inode(path)
+	}
+
+	fn load_file(&mut self, _io: &mut dyn IO, inode: INode, name: String) -> EResult<File> {
+		let path = self.get_path(inode)?;
+		let instances = self.resolve(&path, false)?;
+		let (_, top_mutex) = instances.first().ok_or_else(|| errno!(ENOENT))?;
+
+		let is_dir = top_mutex.lock().get_type() == FileType::Directory;
+		let content = if is_dir {
+			FileContent::Directory(self.merge_entries(&path, &instances)?)
+		} else {
+			top_mutex.lock().get_content().try_clone()?
+		};
+
+		let top = top_mutex.lock();
+		let location = FileLocation::Filesystem {
+			mountpoint_id: 0, // dummy value to be replaced
+			inode,
+		};
+		let mut file = File::new(
+			name,
+			top.get_uid(),
+			top.get_gid(),
+			top.get_permissions(),
+			location,
+			content,
+		)?;
+		file.set_hard_links_count(top.get_hard_links_count());
+		file.set_size(top.get_size());
+		file.ctime = top.ctime;
+		file.mtime = top.mtime;
+		file.atime = top.atime;
+
+		Ok(file)
+	}
+
+	fn add_file(
+		&mut self,
+		io: &mut dyn IO,
+		parent_inode: INode,
+		name: String,
+		uid: Uid,
+		gid: Gid,
+		mode: Mode,
+		content: FileContent,
+	) -> EResult<File> {
+		let parent_path = self.get_path(parent_inode)?;
+		let mut path = parent_path.try_clone()?;
+		path.push(name.try_clone()?)?;
+
+		{
+			let parent_mutex = self.copy_up(&parent_path)?;
+			let mut parent = parent_mutex.lock();
+
+			let replaces_whiteout = remove_whiteout(&parent, &name)?;
+			let is_dir = matches!(content, FileContent::Directory(_));
+
+			let file_mutex = vfs::create_file(
+				&mut parent,
+				name.try_clone()?,
+				&AccessProfile::KERNEL,
+				mode,
+				content,
+			)?;
+			let mut file = file_mutex.lock();
+			file.set_uid(uid);
+			file.set_gid(gid);
+			file.sync()?;
+
+			// The directory replaces a removed one, whose content must not reappear
+			if is_dir && replaces_whiteout {
+				vfs::create_file(
+					&mut file,
+					String::try_from(OPAQUE_MARKER)?,
+					&AccessProfile::KERNEL,
+					0,
+					FileContent::Regular,
+				)?;
+			}
+		}
+
+		let inode = self.get_path_inode(path)?;
+		self.load_file(io, inode, name)
+	}
+
+	fn add_link(
+		&mut self,
+		_io: &mut dyn IO,
+		parent_inode: INode,
+		name: &[u8],
+		inode: INode,
+	) -> EResult<()> {
+		let name = String::try_from(name)?;
+		let target_mutex = self.copy_up(&self.get_path(inode)?)?;
+		let parent_mutex = self.copy_up(&self.get_path(parent_inode)?)?;
+		let mut parent = parent_mutex.lock();
+
+		remove_whiteout(&parent, &name)?;
+		// Inodes are allocated per path, so the new link gets its own inode on the overlay
+		let mut target = target_mutex.lock();
+		vfs::create_link(
+			&mut target,
+			&mut parent,
+			name.as_bytes(),
+			&AccessProfile::KERNEL,
+		)
+	}
+
+	fn update_inode(&mut self, _io: &mut dyn IO, file: &File) -> EResult<()> {
+		let path = self.get_path(file.get_location().get_inode())?;
+		let (cur_mutex, in_upper) = self.get_top(&path)?;
+
+		let upper_mutex = if in_upper {
+			cur_mutex
+		} else {
+			// Accesses alone do not require a copy-up
+			let changed = {
+				let cur = cur_mutex.lock();
+				cur.get_uid() != file.get_uid()
+					|| cur.get_gid() != file.get_gid()
+					|| cur.get_permissions() != file.get_permissions()
+					|| cur.mtime != file.mtime
+					|| (cur.get_type() == FileType::Regular && cur.get_size() != file.get_size())
+			};
+			if !changed {
+				return Ok(());
+			}
+			self.copy_up(&path)?
+		};
+
+		let mut upper = upper_mutex.lock();
+		if upper.get_type() == FileType::Regular && upper.get_size() != file.get_size() {
+			page_cache::truncate(&mut upper, file.get_size());
+		}
+		upper.set_uid(file.get_uid());
+		upper.set_gid(file.get_gid());
+		upper.set_permissions(file.get_permissions());
+		upper.ctime = file.ctime;
+		upper.mtime = file.mtime;
+		upper.atime = file.atime;
+		upper.sync()
+	}
+
+	fn remove_file(&mut self, _io: &mut dyn IO, parent_inode: INode, name: &[u8]) -> EResult<u16> {
+		let parent_path = self.get_path(parent_inode)?;
+		let mut path = parent_path.try_clone()?;
+		path.push(String::try_from(name)?)?;
+
+		let instances = self.resolve(&path, false)?;
+		let (layer, file_mutex) = instances.first().ok_or_else(|| errno!(ENOENT))?;
+		let in_upper = self.is_upper(*layer);
+		let is_dir = file_mutex.lock().get_type() == FileType::Directory;
+		if is_dir && self.merge_entries(&path, &instances)?.len() > 2 {
+			return Err(errno!(ENOTEMPTY));
+		}
+		// If present on a lower layer, the file has to be hidden by a whiteout
+		let in_lower = !self.resolve(&path, true)?.is_empty();
+		let parent_mutex = self.copy_up(&parent_path)?;
+
+		let mut links_left = 0;
+		if in_upper {
+			let mut file = file_mutex.lock();
+			if is_dir {
+				// Remove the whiteouts and marker remaining in the directory
+				for name in file.get_entry_names()?.iter() {
+					if let Some(entry_mutex) = lookup(&file, name)? {
+						vfs::remove_file(&mut entry_mutex.lock(), &AccessProfile::KERNEL)?;
+					}
+				}
+			} else if !in_lower {
+				links_left = file.get_hard_links_count().saturating_sub(1);
+			}
+			vfs::remove_file(&mut file, &AccessProfile::KERNEL)?;
+		}
+		if in_lower {
+			let mut parent = parent_mutex.lock();
+			vfs::create_file(
+				&mut parent,
+				String::try_from(name)?,
+				&AccessProfile::KERNEL,
+				0,
+				FileContent::CharDevice {
+					major: 0,
+					minor: 0,
+				},
+			)?;
+		}
+
+		if links_left == 0 {
+			if let Some(inode) = self.inodes.remove(&path) {
+				self.paths.remove(&inode);
+			}
+		}
+		Ok(links_left)
+	}
+
+	fn read_node(
+		&mut self,
+		_io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		buf: &mut [u8],
+	) -> EResult<u64> {
+		let (file_mutex, _) = self.get_top(&self.get_path(inode)?)?;
+		let (len, _) = file_mutex.lock().read(off, buf)?;
+		Ok(len)
+	}
+
+	fn write_node(&mut self, _io: &mut dyn IO, inode: INode, off: u64, buf: &[u8]) -> EResult<()> {
+		let file_mutex = self.copy_up(&self.get_path(inode)?)?;
+		file_mutex.lock().write(off, buf)?;
+		Ok(())
+	}
+}
+
+/// Structure representing the overlay file system type.
+pub struct OverlayFsType {}
+
+impl FilesystemType for OverlayFsType {
+	fn get_name(&self) -> &'static [u8] {
+		b"overlay"
+	}
+
+	fn detect(&self, _io: &mut dyn IO) -> Result<bool, Errno> {
+		Ok(false)
+	}
+
+	fn get_mount_options(&self) -> &'static [MountOptionDesc] {
+		&[
+			MountOptionDesc {
+				name: b"lowerdir",
+				has_value: true,
+			},
+			MountOptionDesc {
+				name: b"upperdir",
+				has_value: true,
+			},
+			MountOptionDesc {
+				name: b"workdir",
+				has_value: true,
+			},
+		]
+	}
+
+	fn load_filesystem(
+		&self,
+		_io: &mut dyn IO,
+		mountpath: Path,
+		readonly: bool,
+		options: &MountOptions,
+	) -> Result<Arc<Mutex<dyn Filesystem>>, Errno> {
+		Ok(Arc::new(Mutex::new(OverlayFS::new(
+			&mountpath, readonly, options,
+		)?))?)
+	}
+}
@@ -0,0 +1,64 @@
+//! This module implements the buddyinfo node, allowing to retrieve the number of free frames of
+//! each order in the buddy allocator, which is useful to diagnose memory fragmentation.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::FileContent;
+use crate::memory::buddy;
+use crate::util::container::string::String;
+use crate::util::io;
+use crate::util::io::IO;
+use core::cmp::min;
+
+/// The names of the buddy allocator's zones, by index.
+pub const ZONE_NAMES: [&str; buddy::ZONES_COUNT] = ["User", "MMIO", "Kernel", "DMA"];
+
+/// Structure representing the buddyinfo node.
+pub struct BuddyInfo {}
+
+impl KernFSNode for BuddyInfo {
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Dynamic(FileContent::Regular))
+	}
+}
+
+impl IO for BuddyInfo {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		// Generating content
+		let mut content = String::new();
+		for (name, counts) in ZONE_NAMES.iter().zip(buddy::get_free_frames()) {
+			content.push_str(crate::format!("Node 0, zone {name:>8}")?)?;
+			for count in counts {
+				content.push_str(crate::format!(" {count:>6}")?)?;
+			}
+			content.push(b'\n')?;
+		}
+
+		// Copying content to userspace buffer
+		let content_bytes = content.as_bytes();
+		let offset = min(offset, content_bytes.len() as u64);
+		let len = min((content_bytes.len() as u64 - offset) as usize, buff.len());
+		buff[..len].copy_from_slice(&content_bytes[(offset as usize)..(offset as usize + len)]);
+
+		let eof = (offset + len as u64) >= content_bytes.len() as u64;
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		Ok(mask & (io::POLLIN | io::POLLOUT))
+	}
+}
@@ -0,0 +1,134 @@
+//! This module implements the kallsyms node, allowing to retrieve the list of the kernel's
+//! symbols.
+//!
+//! Addresses are shown only to privileged users, like Linux does with `kptr_restrict`. Other
+//! users see null addresses.
+
+use crate::elf;
+use crate::elf::ELF32SectionHeader;
+use crate::elf::ELF32Sym;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::perm::CAP_SYSLOG;
+use crate::file::FileContent;
+use crate::memory;
+use crate::multiboot;
+use crate::process::Process;
+use crate::util::container::string::String;
+use crate::util::io;
+use crate::util::io::IO;
+use crate::util::DisplayableStr;
+use core::cmp::min;
+
+/// Returns the character representing the type of the symbol `sym`.
+///
+/// If the symbol is not to be listed, the function returns `None`.
+fn get_symbol_type(sym: &ELF32Sym) -> Option<char> {
+	let c = match sym.st_info & 0xf {
+		elf::STT_FUNC => 't',
+		elf::STT_OBJECT => 'd',
+		_ => return None,
+	};
+	// Global symbols are represented in uppercase
+	if sym.st_info >> 4 != 0 {
+		Some(c.to_ascii_uppercase())
+	} else {
+		Some(c)
+	}
+}
+
+/// Structure representing the kallsyms node.
+pub struct KAllSyms {}
+
+impl KernFSNode for KAllSyms {
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Dynamic(FileContent::Regular))
+	}
+}
+
+impl IO for KAllSyms {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		let privileged = Process::current_assert()
+			.lock()
+			.access_profile
+			.has_cap(CAP_SYSLOG);
+
+		// Generating content
+		let boot_info = multiboot::get_boot_info();
+		let sections = memory::kern_to_virt(boot_info.elf_sections);
+		let sections_count = boot_info.elf_num as usize;
+		let shndx = boot_info.elf_shndx as usize;
+		let entsize = boot_info.elf_entsize as usize;
+		let Some(strtab_section) =
+			elf::get_section(sections, sections_count, shndx, entsize, b".strtab")
+		else {
+			return Ok((0, true));
+		};
+
+		let mut content = String::new();
+		let mut res = Ok(());
+		elf::foreach_sections(
+			sections,
+			sections_count,
+			shndx,
+			entsize,
+			|hdr: &ELF32SectionHeader, _name: &[u8]| {
+				if hdr.sh_type != elf::SHT_SYMTAB {
+					return true;
+				}
+
+				let ptr = memory::kern_to_virt(hdr.sh_addr as *const u8);
+				let mut i: usize = 0;
+				while i < hdr.sh_size as usize {
+					let sym = unsafe { &*(ptr.add(i) as *const ELF32Sym) };
+					i += hdr.sh_entsize as usize;
+
+					if sym.st_name == 0 || !sym.is_defined() {
+						continue;
+					}
+					let Some(sym_type) = get_symbol_type(sym) else {
+						continue;
+					};
+					let addr = if privileged { sym.st_value } else { 0 };
+					let name = elf::get_symbol_name(strtab_section, sym.st_name);
+
+					res = crate::format!("{addr:08x} {sym_type} {}\n", DisplayableStr(name))
+						.and_then(|line| content.push_str(line));
+					if res.is_err() {
+						return false;
+					}
+				}
+
+				true
+			},
+		);
+		res?;
+
+		// Copying content to userspace buffer
+		let content_bytes = content.as_bytes();
+		let offset = min(offset, content_bytes.len() as u64);
+		let len = min((content_bytes.len() as u64 - offset) as usize, buff.len());
+		buff[..len].copy_from_slice(&content_bytes[(offset as usize)..(offset as usize + len)]);
+
+		let eof = (offset + len as u64) >= content_bytes.len() as u64;
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		Ok(mask & (io::POLLIN | io::POLLOUT))
+	}
+}
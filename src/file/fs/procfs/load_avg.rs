@@ -0,0 +1,81 @@
+//! The loadavg node returns the load averages of the system, along with the number of running
+//! processes and the last allocated PID.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::FileContent;
+use crate::process;
+use crate::process::scheduler::FIXED_1;
+use crate::process::scheduler::FSHIFT;
+use crate::process::Process;
+use crate::util::io;
+use crate::util::io::IO;
+use core::cmp::min;
+
+/// Returns the integer part and the first two decimals of the load average `load`.
+fn split_load(load: usize) -> (usize, usize) {
+	// Round to the second decimal
+	let load = load + FIXED_1 / 200;
+	let int = load >> FSHIFT;
+	let frac = ((load & (FIXED_1 - 1)) * 100) >> FSHIFT;
+	(int, frac)
+}
+
+/// Structure representing the loadavg node.
+pub struct LoadAvg {}
+
+impl KernFSNode for LoadAvg {
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Dynamic(FileContent::Regular))
+	}
+}
+
+impl IO for LoadAvg {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		// Generating content
+		let (load_avg, running, total) = {
+			let sched = process::get_scheduler().lock();
+			(
+				sched.get_load_avg(),
+				sched.get_running_count(),
+				sched.get_processes_count(),
+			)
+		};
+		let last_pid = Process::current_pid_ns()
+			.map(|ns| ns.get_last_pid())
+			.unwrap_or(0);
+		let (int1, frac1) = split_load(load_avg[0]);
+		let (int5, frac5) = split_load(load_avg[1]);
+		let (int15, frac15) = split_load(load_avg[2]);
+		let content = crate::format!(
+			"{int1}.{frac1:02} {int5}.{frac5:02} {int15}.{frac15:02} {running}/{total} {last_pid}\n"
+		)?;
+
+		// Copying content to userspace buffer
+		let content_bytes = content.as_bytes();
+		let offset = min(offset, content_bytes.len() as u64);
+		let len = min((content_bytes.len() as u64 - offset) as usize, buff.len());
+		buff[..len].copy_from_slice(&content_bytes[(offset as usize)..(offset as usize + len)]);
+
+		let eof = (offset + len as u64) >= content_bytes.len() as u64;
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		Ok(mask & (io::POLLIN | io::POLLOUT))
+	}
+}
@@ -5,8 +5,10 @@ use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file::fs::kernfs::content::KernFSContent;
 use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::page_cache;
 use crate::file::FileContent;
 use crate::memory;
+use crate::memory::slab;
 use crate::util::io::IO;
 use core::cmp::min;
 
@@ -30,11 +32,36 @@ impl IO for MemInfo {
 		}
 
 		// Generating content
-		let mem_info = memory::stats::MEM_INFO.lock();
-		let content = mem_info.to_string()?;
+		let (mem_total, mem_free) = {
+			let mem_info = memory::stats::MEM_INFO.lock();
+			(mem_info.mem_total, mem_info.mem_free)
+		};
+		let page_kb = memory::PAGE_SIZE / 1024;
+		let cached = page_cache::get_cached_pages() * page_kb;
+		let dirty = page_cache::get_dirty_pages() * page_kb;
+		let slab = slab::get_pages_count() * page_kb;
+		// Clean pages of the page cache can be reclaimed without writing them first
+		let mem_available = mem_free + cached.saturating_sub(dirty);
+		let content = crate::format!(
+			"MemTotal: {mem_total:>8} kB
+MemFree: {mem_free:>8} kB
+MemAvailable: {mem_available:>8} kB
+Buffers:        0 kB
+Cached: {cached:>8} kB
+SwapCached:        0 kB
+Dirty: {dirty:>8} kB
+Shmem:        0 kB
+Slab: {slab:>8} kB
+SReclaimable:        0 kB
+SUnreclaim: {slab:>8} kB
+SwapTotal:        0 kB
+SwapFree:        0 kB
+"
+		)?;
 
 		// Copying content to userspace buffer
 		let content_bytes = content.as_bytes();
+		let offset = min(offset, content_bytes.len() as u64);
 		let len = min((content_bytes.len() as u64 - offset) as usize, buff.len());
 		buff[..len].copy_from_slice(&content_bytes[(offset as usize)..(offset as usize + len)]);
 
@@ -1,18 +1,29 @@
 //! The procfs is a virtual filesystem which provides informations about
 //! processes.
-
+//!
+//! The processes directories at the root of the filesystem are named after the PID of the
+//! process as seen from the PID namespace of the process accessing the filesystem. Processes that
+//! are not visible from this namespace are hidden.
+
+mod buddy_info;
+mod kallsyms;
+mod load_avg;
 mod mem_info;
 mod proc_dir;
 mod self_link;
+mod slab_info;
 mod sys_dir;
 mod uptime;
 mod version;
+mod vm_stat;
+mod zone_info;
 
 use super::kernfs;
 use super::kernfs::node::DummyKernFSNode;
 use super::kernfs::KernFS;
 use super::Filesystem;
 use super::FilesystemType;
+use super::MountOptions;
 use crate::errno::AllocError;
 use crate::errno::AllocResult;
 use crate::errno::Errno;
@@ -29,19 +40,36 @@ use crate::file::Mode;
 use crate::process;
 use crate::process::oom;
 use crate::process::pid::Pid;
+use crate::process::Process;
 use crate::util::boxed::Box;
 use crate::util::container::hashmap::HashMap;
 use crate::util::container::string::String;
 use crate::util::io::IO;
 use crate::util::lock::Mutex;
 use crate::util::ptr::arc::Arc;
+use crate::util::TryClone;
+use buddy_info::BuddyInfo;
 use core::any::Any;
+use core::str;
+use kallsyms::KAllSyms;
+use load_avg::LoadAvg;
 use mem_info::MemInfo;
+use proc_dir::ns::NsFile;
 use proc_dir::ProcDir;
 use self_link::SelfNode;
+use slab_info::SlabInfo;
 use sys_dir::SysDir;
 use uptime::Uptime;
 use version::Version;
+use vm_stat::VmStat;
+use zone_info::ZoneInfo;
+
+pub use proc_dir::ns::NsType;
+
+/// Parses the name of a process's directory, returning the PID it contains.
+fn parse_pid(name: &[u8]) -> Option<Pid> {
+	str::from_utf8(name).ok()?.parse().ok()
+}
 
 /// Structure representing the procfs.
 ///
@@ -65,6 +93,39 @@ impl ProcFS {
 
 		let mut entries = HashMap::new();
 
+		// Create /proc/buddyinfo
+		let node = BuddyInfo {};
+		let inode = fs.fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"buddyinfo".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
+		// Create /proc/kallsyms
+		let node = KAllSyms {};
+		let inode = fs.fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"kallsyms".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
+		// Create /proc/loadavg
+		let node = LoadAvg {};
+		let inode = fs.fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"loadavg".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
 		// Create /proc/meminfo
 		let node = MemInfo {};
 		let inode = fs.fs.add_node(Box::new(node)?)?;
@@ -99,6 +160,17 @@ impl ProcFS {
 			},
 		)?;
 
+		// Create /proc/slabinfo
+		let node = SlabInfo {};
+		let inode = fs.fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"slabinfo".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
 		// Create /proc/sys
 		let node = SysDir::new(&mut fs.fs)?;
 		let inode = fs.fs.add_node(Box::new(node)?)?;
@@ -132,6 +204,28 @@ impl ProcFS {
 			},
 		)?;
 
+		// Create /proc/zoneinfo
+		let node = ZoneInfo {};
+		let inode = fs.fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"zoneinfo".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
+		// Create /proc/vmstat
+		let node = VmStat {};
+		let inode = fs.fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"vmstat".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
 		// Add the root node
 		let root_node = DummyKernFSNode::new(0o555, 0, 0, FileContent::Directory(entries));
 		fs.fs.set_root(Box::new(root_node)?)?;
@@ -173,6 +267,16 @@ impl ProcFS {
 		Ok(())
 	}
 
+	/// Returns the PID of the process and the type of the namespace referred to by the namespace
+	/// file with inode `inode`.
+	///
+	/// If the file is not a namespace file, the function returns `None`.
+	pub fn get_ns_file(&self, inode: INode) -> Option<(Pid, NsType)> {
+		let node = self.fs.get_node(inode).ok()?;
+		let node = (&**node as &dyn Any).downcast_ref::<NsFile>()?;
+		Some((node.get_pid(), node.get_type()))
+	}
+
 	/// Removes the process with pid `pid` from the filesystem.
 	///
 	/// If the process doesn't exist, the function does nothing.
@@ -231,11 +335,45 @@ impl Filesystem for ProcFS {
 		parent: Option<INode>,
 		name: &[u8],
 	) -> Result<INode, Errno> {
-		self.fs.get_inode(io, parent, name)
+		let parent = parent.unwrap_or(kernfs::ROOT_INODE);
+		if parent == kernfs::ROOT_INODE {
+			if let Some(pid) = parse_pid(name) {
+				let pid_ns = Process::current_pid_ns().ok_or_else(|| errno!(ENOENT))?;
+				return pid_ns
+					.to_global(pid)
+					.and_then(|pid| self.procs.get(&pid).cloned())
+					.ok_or_else(|| errno!(ENOENT));
+			}
+		}
+		self.fs.get_inode(io, Some(parent), name)
 	}
 
 	fn load_file(&mut self, io: &mut dyn IO, inode: INode, name: String) -> Result<File, Errno> {
-		self.fs.load_file(io, inode, name)
+		let mut file = self.fs.load_file(io, inode, name)?;
+		if inode != kernfs::ROOT_INODE {
+			return Ok(file);
+		}
+		let Some(pid_ns) = Process::current_pid_ns().filter(|ns| !ns.is_root()) else {
+			return Ok(file);
+		};
+
+		// Show only the processes of the namespace, with their PID in it
+		let FileContent::Directory(entries) = &file.content else {
+			unreachable!();
+		};
+		let mut visible = HashMap::new();
+		for (name, entry) in entries.iter() {
+			let name = match parse_pid(name.as_bytes()) {
+				Some(pid) => match pid_ns.to_local(pid) {
+					Some(pid) => crate::format!("{pid}")?,
+					None => continue,
+				},
+				None => name.try_clone()?,
+			};
+			visible.insert(name, entry.clone())?;
+		}
+		file.content = FileContent::Directory(visible);
+		Ok(file)
 	}
 
 	fn add_file(
@@ -312,6 +450,7 @@ impl FilesystemType for ProcFsType {
 		_io: &mut dyn IO,
 		_mountpath: Path,
 		readonly: bool,
+		_options: &MountOptions,
 	) -> Result<Arc<Mutex<dyn Filesystem>>, Errno> {
 		Ok(Arc::new(Mutex::new(ProcFS::new(readonly)?))?)
 	}
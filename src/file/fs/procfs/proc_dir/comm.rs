@@ -0,0 +1,96 @@
+//! The comm node allows to read and modify the name of the process.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::process::pid::Pid;
+use crate::process::Process;
+use crate::process::COMM_LEN;
+use crate::util::io;
+use crate::util::io::IO;
+use core::cmp::min;
+
+/// Structure representing the comm node of the procfs.
+pub struct Comm {
+	/// The PID of the process.
+	pub pid: Pid,
+}
+
+impl KernFSNode for Comm {
+	fn get_mode(&self) -> Mode {
+		0o644
+	}
+
+	fn get_uid(&self) -> Uid {
+		if let Some(proc_mutex) = Process::get_by_pid(self.pid) {
+			proc_mutex.lock().access_profile.get_euid()
+		} else {
+			0
+		}
+	}
+
+	fn get_gid(&self) -> Gid {
+		if let Some(proc_mutex) = Process::get_by_pid(self.pid) {
+			proc_mutex.lock().access_profile.get_egid()
+		} else {
+			0
+		}
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Dynamic(FileContent::Regular))
+	}
+}
+
+impl IO for Comm {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		let proc_mutex = Process::get_by_pid(self.pid).ok_or_else(|| errno!(ENOENT))?;
+		let proc = proc_mutex.lock();
+
+		// Generating content
+		let comm = proc.get_comm();
+		let mut content = [0; COMM_LEN];
+		content[..comm.len()].copy_from_slice(comm);
+		content[comm.len()] = b'\n';
+
+		// Copying content to userspace buffer
+		let content_bytes = &content[..(comm.len() + 1)];
+		let offset = min(offset, content_bytes.len() as u64);
+		let len = min((content_bytes.len() as u64 - offset) as usize, buff.len());
+		buff[..len].copy_from_slice(&content_bytes[(offset as usize)..(offset as usize + len)]);
+
+		let eof = (offset + len as u64) >= content_bytes.len() as u64;
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, buff: &[u8]) -> Result<u64, Errno> {
+		// Only a thread of the same group may rename the process
+		let tgid = Process::current_assert().lock().tgid;
+		let proc_mutex = Process::get_by_pid(self.pid).ok_or_else(|| errno!(ENOENT))?;
+		let mut proc = proc_mutex.lock();
+		if proc.tgid != tgid {
+			return Err(errno!(EINVAL));
+		}
+
+		let name = buff.strip_suffix(b"\n").unwrap_or(buff);
+		proc.set_comm(name);
+		Ok(buff.len() as _)
+	}
+
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		Ok(mask & (io::POLLIN | io::POLLOUT))
+	}
+}
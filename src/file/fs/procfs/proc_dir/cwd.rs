@@ -44,7 +44,8 @@ impl KernFSNode for Cwd {
 		let content = Process::get_by_pid(self.pid)
 			.map(|mutex| {
 				let proc = mutex.lock();
-				crate::format!("{}", &*proc.cwd)
+				let fs = proc.get_fs().lock();
+				crate::format!("{}", &*fs.cwd)
 			})
 			.transpose()?
 			.unwrap_or_default();
@@ -1,9 +1,13 @@
 //! This module implements the directory of a process in the procfs.
 
 mod cmdline;
+mod comm;
 mod cwd;
 mod exe;
 mod mounts;
+pub mod ns;
+mod oom_score_adj;
+mod sched;
 mod stat;
 mod status;
 
@@ -26,9 +30,14 @@ use crate::util::boxed::Box;
 use crate::util::container::hashmap::HashMap;
 use crate::util::io::IO;
 use cmdline::Cmdline;
+use comm::Comm;
+use core::any::Any;
 use cwd::Cwd;
 use exe::Exe;
 use mounts::Mounts;
+use ns::NsDir;
+use oom_score_adj::OomScoreAdj;
+use sched::Sched;
 use stat::Stat;
 use status::Status;
 
@@ -63,6 +72,19 @@ impl ProcDir {
 			},
 		)?;
 
+		// Create /proc/<pid>/comm
+		let node = Comm {
+			pid,
+		};
+		let inode = fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"comm".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
 		// Create /proc/<pid>/cwd
 		let node = Cwd {
 			pid,
@@ -102,6 +124,43 @@ impl ProcDir {
 			},
 		)?;
 
+		// Create /proc/<pid>/ns
+		let node = NsDir::new(pid, fs)?;
+		let inode = fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"ns".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Directory,
+			},
+		)?;
+
+		// Create /proc/<pid>/oom_score_adj
+		let node = OomScoreAdj {
+			pid,
+		};
+		let inode = fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"oom_score_adj".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
+		// Create /proc/<pid>/sched
+		let node = Sched {
+			pid,
+		};
+		let inode = fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"sched".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
 		// Create /proc/<pid>/stat
 		let node = Stat {
 			pid,
@@ -144,7 +203,14 @@ impl ProcDir {
 		match &mut self.content {
 			FileContent::Directory(entries) => {
 				for (_, entry) in entries.iter() {
-					oom::wrap(|| fs.remove_node(entry.inode).map_err(|_| AllocError));
+					let node = oom::wrap(|| fs.remove_node(entry.inode).map_err(|_| AllocError));
+					// Nested directories own nodes too
+					if let Some(mut node) = node {
+						let node = node.as_mut() as &mut dyn Any;
+						if let Some(node) = node.downcast_mut::<NsDir>() {
+							node.drop_inner(fs);
+						}
+					}
 				}
 
 				entries.clear();
@@ -1,11 +1,13 @@
 //! This module implements a procfs node which allows to get the list of
 //! mountpoint.
 
+use crate::errno::AllocResult;
 use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file::fs::kernfs::content::KernFSContent;
 use crate::file::fs::kernfs::node::KernFSNode;
 use crate::file::mountpoint;
+use crate::file::mountpoint::MountPoint;
 use crate::file::perm::Gid;
 use crate::file::perm::Uid;
 use crate::file::FileContent;
@@ -16,6 +18,37 @@ use crate::util::container::string::String;
 use crate::util::io::IO;
 use core::cmp::min;
 
+/// The names of mount flags, as displayed in the list of options.
+const FLAG_NAMES: [(u32, &str); 8] = [
+	(mountpoint::FLAG_NOSUID, "nosuid"),
+	(mountpoint::FLAG_NODEV, "nodev"),
+	(mountpoint::FLAG_NOEXEC, "noexec"),
+	(mountpoint::FLAG_SYNCHRONOUS, "sync"),
+	(mountpoint::FLAG_MANDLOCK, "mand"),
+	(mountpoint::FLAG_NOATIME, "noatime"),
+	(mountpoint::FLAG_NODIRATIME, "nodiratime"),
+	(mountpoint::FLAG_RELATIME, "relatime"),
+];
+
+/// Returns the list of options of the mountpoint `mp`, including its mount flags.
+fn get_options(mp: &MountPoint) -> AllocResult<String> {
+	let flags = mp.get_flags();
+
+	let mut options = String::try_from(if mp.is_readonly() { "ro" } else { "rw" })?;
+	for (flag, name) in FLAG_NAMES {
+		if flags & flag != 0 {
+			options.push(b',')?;
+			options.push_str(name)?;
+		}
+	}
+	let fs_options = mp.get_options()?;
+	if !fs_options.is_empty() {
+		options.push(b',')?;
+		options.push_str(fs_options)?;
+	}
+	Ok(options)
+}
+
 /// Structure representing the mounts node of the procfs.
 pub struct Mounts {
 	/// The PID of the process.
@@ -58,22 +91,26 @@ impl IO for Mounts {
 			return Ok((0, false));
 		}
 
+		let mnt_ns = Process::get_by_pid(self.pid)
+			.ok_or_else(|| errno!(ENOENT))?
+			.lock()
+			.get_mnt_ns()
+			.clone();
+
 		// Generating content
 		let mut content = String::new();
-		let container = mountpoint::MOUNT_POINTS.lock();
-
-		for (_, mp_mutex) in container.iter() {
+		for mp_mutex in mnt_ns.get_mountpoints()? {
 			let mp = mp_mutex.lock();
 
 			let fs_type = mp.get_filesystem_type();
-			let flags = "TODO"; // TODO
+			let options = get_options(&mp)?;
 
 			let s = crate::format!(
 				"{} {} {} {} 0 0\n",
 				mp.get_source(),
 				mp.get_path(),
 				fs_type,
-				flags
+				options
 			)?;
 			content.push_str(s)?;
 		}
@@ -0,0 +1,252 @@
+//! This module implements the `ns` directory of a process, which contains a file for each
+//! namespace of the process.
+//!
+//! Each file shows the type and the ID of the namespace. Opening one of them gives a file
+//! descriptor which can be passed to `setns` to join the namespace.
+
+use crate::errno::AllocError;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::fs::kernfs::KernFS;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::DirEntry;
+use crate::file::FileContent;
+use crate::file::FileType;
+use crate::file::Mode;
+use crate::process::oom;
+use crate::process::pid::Pid;
+use crate::process::Process;
+use crate::util::boxed::Box;
+use crate::util::container::hashmap::HashMap;
+use crate::util::io::IO;
+use core::cmp::min;
+
+/// The type of a namespace file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NsType {
+	/// The mount namespace of the process.
+	Mnt,
+	/// The PID namespace of the process.
+	Pid,
+	/// The PID namespace in which the children of the process are created.
+	PidForChildren,
+}
+
+impl NsType {
+	/// The list of namespace types.
+	const ALL: [Self; 3] = [Self::Mnt, Self::Pid, Self::PidForChildren];
+
+	/// Returns the name of the namespace file.
+	fn get_file_name(&self) -> &'static [u8] {
+		match self {
+			Self::Mnt => b"mnt",
+			Self::Pid => b"pid",
+			Self::PidForChildren => b"pid_for_children",
+		}
+	}
+
+	/// Returns the name of the namespace type, as shown in the file's content.
+	fn get_type_name(&self) -> &'static str {
+		match self {
+			Self::Mnt => "mnt",
+			Self::Pid | Self::PidForChildren => "pid",
+		}
+	}
+}
+
+/// Structure representing a namespace file.
+pub struct NsFile {
+	/// The PID of the process.
+	pid: Pid,
+	/// The type of the namespace.
+	ns_type: NsType,
+}
+
+impl NsFile {
+	/// Returns the PID of the process.
+	pub fn get_pid(&self) -> Pid {
+		self.pid
+	}
+
+	/// Returns the type of the namespace.
+	pub fn get_type(&self) -> NsType {
+		self.ns_type
+	}
+}
+
+impl KernFSNode for NsFile {
+	fn get_mode(&self) -> Mode {
+		0o444
+	}
+
+	fn get_uid(&self) -> Uid {
+		if let Some(proc_mutex) = Process::get_by_pid(self.pid) {
+			proc_mutex.lock().access_profile.get_euid()
+		} else {
+			0
+		}
+	}
+
+	fn get_gid(&self) -> Gid {
+		if let Some(proc_mutex) = Process::get_by_pid(self.pid) {
+			proc_mutex.lock().access_profile.get_egid()
+		} else {
+			0
+		}
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(FileContent::Regular.into())
+	}
+}
+
+impl IO for NsFile {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		let proc_mutex = Process::get_by_pid(self.pid).ok_or_else(|| errno!(ENOENT))?;
+		let id = {
+			let proc = proc_mutex.lock();
+			match self.ns_type {
+				NsType::Mnt => proc.get_mnt_ns().get_id(),
+				NsType::Pid => proc.get_pid_ns().get_id(),
+				NsType::PidForChildren => proc.get_pid_ns_for_children().get_id(),
+			}
+		};
+
+		// Generating content
+		let content = crate::format!("{}:[{id}]\n", self.ns_type.get_type_name())?;
+
+		// Copying content to userspace buffer
+		let content_bytes = content.as_bytes();
+		let len = min((content_bytes.len() as u64 - offset) as usize, buff.len());
+		buff[..len].copy_from_slice(&content_bytes[(offset as usize)..(offset as usize + len)]);
+
+		let eof = (offset + len as u64) >= content_bytes.len() as u64;
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Err(errno!(EINVAL))
+	}
+}
+
+/// Structure representing the `ns` directory of a process.
+pub struct NsDir {
+	/// The PID of the process.
+	pid: Pid,
+	/// The content of the directory. This will always be a Directory variant.
+	content: FileContent,
+}
+
+impl NsDir {
+	/// Creates a new instance for the process with the given PID `pid`.
+	///
+	/// The function adds a node for each namespace to the given kernfs `fs`.
+	pub fn new(pid: Pid, fs: &mut KernFS) -> Result<Self, Errno> {
+		let mut entries = HashMap::new();
+		for ns_type in NsType::ALL {
+			let node = NsFile {
+				pid,
+				ns_type,
+			};
+			let inode = fs.add_node(Box::new(node)?)?;
+			entries.insert(
+				ns_type.get_file_name().try_into()?,
+				DirEntry {
+					inode,
+					entry_type: FileType::Regular,
+				},
+			)?;
+		}
+
+		Ok(Self {
+			pid,
+			content: FileContent::Directory(entries),
+		})
+	}
+
+	/// Removes inner nodes in order to drop the current node.
+	///
+	/// `fs` is the procfs.
+	pub fn drop_inner(&mut self, fs: &mut KernFS) {
+		match &mut self.content {
+			FileContent::Directory(entries) => {
+				for (_, entry) in entries.iter() {
+					oom::wrap(|| fs.remove_node(entry.inode).map_err(|_| AllocError));
+				}
+
+				entries.clear();
+			}
+
+			_ => unreachable!(),
+		}
+	}
+}
+
+impl KernFSNode for NsDir {
+	fn get_mode(&self) -> Mode {
+		0o511
+	}
+
+	fn get_uid(&self) -> Uid {
+		if let Some(proc_mutex) = Process::get_by_pid(self.pid) {
+			proc_mutex.lock().access_profile.get_euid()
+		} else {
+			0
+		}
+	}
+
+	fn get_gid(&self) -> Gid {
+		if let Some(proc_mutex) = Process::get_by_pid(self.pid) {
+			proc_mutex.lock().access_profile.get_egid()
+		} else {
+			0
+		}
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Owned(&mut self.content))
+	}
+}
+
+impl IO for NsDir {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, _offset: u64, _buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Err(errno!(EINVAL))
+	}
+}
+
+impl Drop for NsDir {
+	fn drop(&mut self) {
+		// Making sure inner nodes have been dropped
+		match &self.content {
+			FileContent::Directory(entries) => debug_assert!(entries.is_empty()),
+			_ => unreachable!(),
+		}
+	}
+}
@@ -0,0 +1,106 @@
+//! The oom_score_adj node allows to read and modify the adjustment of the OOM score of the
+//! process.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::perm::CAP_SYS_RESOURCE;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::process::oom;
+use crate::process::pid::Pid;
+use crate::process::Process;
+use crate::util::io;
+use crate::util::io::IO;
+use core::cmp::min;
+use core::str;
+
+/// Structure representing the oom_score_adj node of the procfs.
+pub struct OomScoreAdj {
+	/// The PID of the process.
+	pub pid: Pid,
+}
+
+impl KernFSNode for OomScoreAdj {
+	fn get_mode(&self) -> Mode {
+		0o644
+	}
+
+	fn get_uid(&self) -> Uid {
+		if let Some(proc_mutex) = Process::get_by_pid(self.pid) {
+			proc_mutex.lock().access_profile.get_euid()
+		} else {
+			0
+		}
+	}
+
+	fn get_gid(&self) -> Gid {
+		if let Some(proc_mutex) = Process::get_by_pid(self.pid) {
+			proc_mutex.lock().access_profile.get_egid()
+		} else {
+			0
+		}
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Dynamic(FileContent::Regular))
+	}
+}
+
+impl IO for OomScoreAdj {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		let proc_mutex = Process::get_by_pid(self.pid).ok_or_else(|| errno!(ENOENT))?;
+		let oom_score_adj = proc_mutex.lock().oom_score_adj;
+
+		// Generating content
+		let content = crate::format!("{oom_score_adj}\n")?;
+
+		// Copying content to userspace buffer
+		let content_bytes = content.as_bytes();
+		let offset = min(offset, content_bytes.len() as u64);
+		let len = min((content_bytes.len() as u64 - offset) as usize, buff.len());
+		buff[..len].copy_from_slice(&content_bytes[(offset as usize)..(offset as usize + len)]);
+
+		let eof = (offset + len as u64) >= content_bytes.len() as u64;
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, buff: &[u8]) -> Result<u64, Errno> {
+		let val: i16 = str::from_utf8(buff)
+			.ok()
+			.and_then(|s| s.trim().parse().ok())
+			.ok_or_else(|| errno!(EINVAL))?;
+		if !(oom::OOM_SCORE_ADJ_MIN..=oom::OOM_SCORE_ADJ_MAX).contains(&val) {
+			return Err(errno!(EINVAL));
+		}
+
+		let privileged = Process::current_assert()
+			.lock()
+			.access_profile
+			.has_cap(CAP_SYS_RESOURCE);
+		let proc_mutex = Process::get_by_pid(self.pid).ok_or_else(|| errno!(ENOENT))?;
+		let mut proc = proc_mutex.lock();
+		// Only a privileged user can make a process less likely to be killed
+		if val < proc.oom_score_adj && !privileged {
+			return Err(errno!(EACCES));
+		}
+		proc.oom_score_adj = val;
+
+		Ok(buff.len() as _)
+	}
+
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		Ok(mask & (io::POLLIN | io::POLLOUT))
+	}
+}
@@ -0,0 +1,122 @@
+//! This module implements the sched file, which allows to retrieve the scheduling statistics of
+//! the process.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::process::pid::Pid;
+use crate::process::Process;
+use crate::util::io;
+use crate::util::io::IO;
+use core::cmp::min;
+use core::fmt;
+use core::str;
+
+/// A duration in nanoseconds, displayed as milliseconds with six decimals.
+struct Millis(u64);
+
+impl fmt::Display for Millis {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{:>13}.{:06}", self.0 / 1_000_000, self.0 % 1_000_000)
+	}
+}
+
+/// Structure representing the sched node of the procfs.
+pub struct Sched {
+	/// The PID of the process.
+	pub pid: Pid,
+}
+
+impl KernFSNode for Sched {
+	fn get_mode(&self) -> Mode {
+		0o444
+	}
+
+	fn get_uid(&self) -> Uid {
+		if let Some(proc_mutex) = Process::get_by_pid(self.pid) {
+			proc_mutex.lock().access_profile.get_euid()
+		} else {
+			0
+		}
+	}
+
+	fn get_gid(&self) -> Gid {
+		if let Some(proc_mutex) = Process::get_by_pid(self.pid) {
+			proc_mutex.lock().access_profile.get_egid()
+		} else {
+			0
+		}
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(FileContent::Regular.into())
+	}
+}
+
+impl IO for Sched {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		let proc_mutex = Process::get_by_pid(self.pid).ok_or_else(|| errno!(ENOENT))?;
+		let proc = proc_mutex.lock();
+
+		let name = unsafe { str::from_utf8_unchecked(proc.get_comm()) };
+		let entity = proc.get_sched_entity();
+		let prio = match proc.get_rt_priority() {
+			Some(rt_priority) => 99 - rt_priority as i32,
+			None => 120 + proc.get_nice(),
+		};
+
+		// Generating content
+		let content = crate::format!(
+			"{name} ({pid}, #threads: 1)
+-------------------------------------------------------------------
+se.exec_start                                : {exec_start}
+se.vruntime                                  : {vruntime}
+se.sum_exec_runtime                          : {sum_exec_runtime}
+nr_switches                                  : {nr_switches:>20}
+nr_voluntary_switches                        : {nr_voluntary_switches:>20}
+nr_involuntary_switches                      : {nr_involuntary_switches:>20}
+se.load.weight                               : {weight:>20}
+policy                                       : {policy:>20}
+prio                                         : {prio:>20}
+",
+			pid = proc.pid,
+			exec_start = Millis(entity.exec_start),
+			vruntime = Millis(entity.vruntime),
+			sum_exec_runtime = Millis(entity.sum_exec_runtime),
+			nr_switches = entity.nr_switches,
+			nr_voluntary_switches = entity.nr_voluntary_switches,
+			nr_involuntary_switches = entity.nr_involuntary_switches,
+			weight = proc.priority,
+			policy = proc.sched_policy,
+		)?;
+
+		// Copying content to userspace buffer
+		let content_bytes = content.as_bytes();
+		let len = min((content_bytes.len() as u64 - offset) as usize, buff.len());
+		buff[..len].copy_from_slice(&content_bytes[(offset as usize)..(offset as usize + len)]);
+
+		let eof = (offset + len as u64) >= content_bytes.len() as u64;
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		Ok(mask & (io::POLLIN | io::POLLOUT))
+	}
+}
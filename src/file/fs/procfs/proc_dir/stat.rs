@@ -10,9 +10,11 @@ use crate::file::perm::Uid;
 use crate::file::FileContent;
 use crate::file::Mode;
 use crate::process::pid::Pid;
+use crate::process::rusage;
 use crate::process::Process;
 use crate::util::io::IO;
 use core::cmp::min;
+use core::str;
 
 /// Structure representing the stat node of the procfs.
 pub struct Stat {
@@ -59,26 +61,30 @@ impl IO for Stat {
 		let proc_mutex = Process::get_by_pid(self.pid).ok_or_else(|| errno!(ENOENT))?;
 		let proc = proc_mutex.lock();
 
-		let name = proc
-			.argv
-			.iter()
-			.map(|name| unsafe { name.as_str_unchecked() })
-			.next()
-			.unwrap_or("?");
+		let name = unsafe { str::from_utf8_unchecked(proc.get_comm()) };
 
 		let state = proc.get_state();
 		let state_char = state.get_char();
 
-		let pid = proc.pid;
-		let ppid = proc.get_parent_pid();
-		let pgid = proc.pgid;
-		let sid = 0; // TODO
-
-		let user_jiffies = 0; // TODO
-		let kernel_jiffies = 0; // TODO
-
-		let priority = proc.priority;
-		let nice = proc.nice;
+		// PIDs are shown as seen from the namespace of the reading process
+		let pid_ns = Process::current_pid_ns().ok_or_else(|| errno!(ESRCH))?;
+		let local = |pid| pid_ns.to_local(pid).unwrap_or(0);
+		let pid = local(proc.pid);
+		let ppid = local(proc.get_parent_pid());
+		let pgid = local(proc.pgid);
+		let sid = local(proc.sid);
+
+		let times = proc.get_cpu_times();
+		let utime = rusage::to_clock_ticks(times.utime);
+		let stime = rusage::to_clock_ticks(times.stime);
+		let cutime = rusage::to_clock_ticks(times.cutime);
+		let cstime = rusage::to_clock_ticks(times.cstime);
+
+		let nice = proc.get_nice();
+		let priority = match proc.get_rt_priority() {
+			Some(rt_priority) => -1 - rt_priority as i32,
+			None => 20 + nice,
+		};
 
 		let num_threads = 1; // TODO
 
@@ -93,7 +99,7 @@ impl IO for Stat {
 		// Generating content
 		let content = crate::format!(
 			"{pid} ({name}) {state_char} {ppid} {pgid} {sid} TODO TODO 0 \
-0 0 0 0 {user_jiffies} {kernel_jiffies} TODO TODO {priority} {nice} {num_threads} 0 {vmem_usage} \
+0 0 0 0 {utime} {stime} {cutime} {cstime} {priority} {nice} {num_threads} 0 {vmem_usage} \
 TODO TODO TODO TODO {esp} {eip} TODO TODO TODO TODO 0 0 0 TODO TODO TODO TODO TODO TODO TODO TODO \
 TODO TODO TODO TODO TODO TODO TODO TODO TODO"
 		)?;
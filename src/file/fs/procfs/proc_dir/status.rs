@@ -9,10 +9,63 @@ use crate::file::perm::Gid;
 use crate::file::perm::Uid;
 use crate::file::FileContent;
 use crate::file::Mode;
+use crate::memory;
+use crate::process;
 use crate::process::pid::Pid;
+use crate::process::pid::PidNamespace;
+use crate::process::rlimit;
+use crate::process::scheduler::CpuSet;
 use crate::process::Process;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
 use crate::util::io::IO;
 use core::cmp::min;
+use core::ptr;
+use core::str;
+
+/// Returns the PIDs of the process with PID `pid`, in the root namespace, in each namespace from
+/// `reader` down to the process's namespace `ns`, separated by spaces.
+fn ns_pids(ns: &PidNamespace, reader: &PidNamespace, pid: Pid) -> EResult<String> {
+	let mut pids = Vec::new();
+	for ns in ns.iter_path() {
+		pids.push(ns.to_local(pid).unwrap_or(0))?;
+		if ptr::eq(ns, reader) {
+			break;
+		}
+	}
+	let mut list = String::new();
+	for (i, pid) in pids.iter().rev().enumerate() {
+		if i > 0 {
+			list.push(b' ')?;
+		}
+		list.push_str(crate::format!("{pid}")?)?;
+	}
+	Ok(list)
+}
+
+/// Returns the list of CPUs in the given set, as ranges separated by commas (example: `0-3,5`).
+fn cpus_list(set: CpuSet) -> EResult<String> {
+	let mut list = String::new();
+	let mut cpu = 0;
+	while cpu < CpuSet::BITS {
+		if set & (1 << cpu) == 0 {
+			cpu += 1;
+			continue;
+		}
+		let begin = cpu;
+		while cpu < CpuSet::BITS && set & (1 << cpu) != 0 {
+			cpu += 1;
+		}
+		let sep = if list.is_empty() { "" } else { "," };
+		let range = if cpu - begin > 1 {
+			crate::format!("{sep}{begin}-{}", cpu - 1)?
+		} else {
+			crate::format!("{sep}{begin}")?
+		};
+		list.push_str(range)?;
+	}
+	Ok(list)
+}
 
 /// Structure representing the status node of the procfs.
 pub struct Status {
@@ -56,16 +109,17 @@ impl IO for Status {
 			return Ok((0, false));
 		}
 
+		let online = process::get_scheduler().lock().get_online_cpus();
 		let proc_mutex = Process::get_by_pid(self.pid).ok_or_else(|| errno!(ENOENT))?;
 		let proc = proc_mutex.lock();
 
-		let name = proc
-			.argv
-			.iter()
-			.map(|name| unsafe { name.as_str_unchecked() })
-			.next()
-			.unwrap_or("?");
+		let name = unsafe { str::from_utf8_unchecked(proc.get_comm()) };
 		let state = proc.get_state();
+		let cpus_allowed = proc.cpu_affinity & online;
+
+		// PIDs are shown as seen from the namespace of the reading process
+		let reader_ns = Process::current_pid_ns().ok_or_else(|| errno!(ESRCH))?;
+		let local = |pid| reader_ns.to_local(pid).unwrap_or(0);
 
 		// TODO Fill every fields with process's data
 		// Generating content
@@ -73,25 +127,25 @@ impl IO for Status {
 			"Name: {name}
 Umask: {umask:4o}
 State: {state_char} ({state_name})
-Tgid: 0
+Tgid: {tgid}
 Ngid: 0
 Pid: {pid}
 PPid: {ppid}
 TracerPid: 0
-Uid: {uid} {euid} {suid} {ruid}
-Gid: {gid} {egid} {sgid} {rgid}
+Uid: {uid} {euid} {suid} {fsuid}
+Gid: {gid} {egid} {sgid} {fsgid}
 FDSize: TODO
 Groups: TODO
-NStgid: TODO
-NSpid: TODO
-NSpgid: TODO
-NSsid: TODO
+NStgid: {ns_tgid}
+NSpid: {ns_pid}
+NSpgid: {ns_pgid}
+NSsid: {ns_sid}
 VmPeak: TODO kB
 VmSize: TODO kB
 VmLck: TODO kB
 VmPin: TODO kB
 VmHWM: TODO kB
-VmRSS: TODO kB
+VmRSS: {vm_rss} kB
 RssAnon: TODO kB
 RssFile: TODO kB
 RssShmem: TODO kB
@@ -105,42 +159,63 @@ HugetlbPages: TODO kB
 CoreDumping: TODO
 THP_enabled: TODO
 Threads: TODO
-SigQ: TODO/TODO
-SigPnd: 0000000000000000
+SigQ: {sig_queued}/{sig_limit}
+SigPnd: {sig_pending:016x}
 ShdPnd: 0000000000000000
-SigBlk: 0000000000000000
+SigBlk: {sig_blocked:016x}
 SigIgn: 0000000000000000
 SigCgt: 0000000000000000
-CapInh: 0000000000000000
-CapPrm: 0000000000000000
-CapEff: 0000000000000000
-CapBnd: 000001ffffffffff
+CapInh: {cap_inh:016x}
+CapPrm: {cap_prm:016x}
+CapEff: {cap_eff:016x}
+CapBnd: {cap_bnd:016x}
 CapAmb: 0000000000000000
-NoNewPrivs: 0
-Seccomp: 0
-Seccomp_filters: 0
+NoNewPrivs: {no_new_privs}
+Seccomp: {seccomp}
+Seccomp_filters: {seccomp_filters}
 Speculation_Store_Bypass: thread vulnerable
 SpeculationIndirectBranch: conditional enabled
-Cpus_allowed: ff
-Cpus_allowed_list: 0-7
+Cpus_allowed: {cpus_allowed:x}
+Cpus_allowed_list: {cpus_allowed_list}
 Mems_allowed: 00000001
 Mems_allowed_list: 0
-voluntary_ctxt_switches: 0
-nonvoluntary_ctxt_switches: 0
+voluntary_ctxt_switches: {voluntary_switches}
+nonvoluntary_ctxt_switches: {involuntary_switches}
 ",
-			umask = proc.umask,
+			umask = proc.get_fs().lock().umask,
 			state_char = state.get_char(),
 			state_name = state.as_str(),
-			pid = proc.pid,
-			ppid = proc.get_parent_pid(),
+			tgid = local(proc.tgid),
+			pid = local(proc.pid),
+			ppid = local(proc.get_parent_pid()),
+			ns_tgid = ns_pids(proc.get_pid_ns(), &reader_ns, proc.tgid)?,
+			ns_pid = ns_pids(proc.get_pid_ns(), &reader_ns, proc.pid)?,
+			ns_pgid = ns_pids(proc.get_pid_ns(), &reader_ns, proc.pgid)?,
+			ns_sid = ns_pids(proc.get_pid_ns(), &reader_ns, proc.sid)?,
 			uid = proc.access_profile.get_uid(),
 			euid = proc.access_profile.get_euid(),
 			suid = proc.access_profile.get_suid(),
-			ruid = 0, // TODO
+			fsuid = proc.access_profile.get_fsuid(),
 			gid = proc.access_profile.get_gid(),
 			egid = proc.access_profile.get_egid(),
 			sgid = proc.access_profile.get_sgid(),
-			rgid = 0, // TODO
+			fsgid = proc.access_profile.get_fsgid(),
+			vm_rss = proc.get_rss() * memory::PAGE_SIZE / 1024,
+			sig_queued = proc.get_queued_signals_count(),
+			sig_limit = proc.rlimits.get_cur(rlimit::RLIMIT_SIGPENDING),
+			sig_pending = proc.get_pending_signals(),
+			sig_blocked = proc.sigmask,
+			cap_inh = proc.access_profile.get_cap_inheritable(),
+			cap_prm = proc.access_profile.get_cap_permitted(),
+			cap_eff = proc.access_profile.get_cap_effective(),
+			cap_bnd = proc.access_profile.get_cap_bounding(),
+			no_new_privs = proc.no_new_privs as u8,
+			seccomp = proc.seccomp.get_mode(),
+			seccomp_filters = proc.seccomp.get_filters_count(),
+			cpus_allowed = cpus_allowed,
+			cpus_allowed_list = cpus_list(cpus_allowed)?,
+			voluntary_switches = proc.get_sched_entity().nr_voluntary_switches,
+			involuntary_switches = proc.get_sched_entity().nr_involuntary_switches,
 		)?;
 
 		// Copying content to userspace buffer
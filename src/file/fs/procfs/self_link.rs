@@ -11,7 +11,7 @@ use crate::file::perm::Uid;
 use crate::file::FileContent;
 use crate::file::Mode;
 use crate::process::Process;
-use crate::time::unit::Timestamp;
+use crate::time::unit::Timespec;
 use crate::util::io::IO;
 
 /// The `self` symlink.
@@ -42,26 +42,30 @@ impl KernFSNode for SelfNode {
 
 	fn set_gid(&mut self, _: Gid) {}
 
-	fn get_atime(&self) -> Timestamp {
-		0
+	fn get_atime(&self) -> Timespec {
+		Timespec::default()
 	}
 
-	fn set_atime(&mut self, _: Timestamp) {}
+	fn set_atime(&mut self, _: Timespec) {}
 
-	fn get_ctime(&self) -> Timestamp {
-		0
+	fn get_ctime(&self) -> Timespec {
+		Timespec::default()
 	}
 
-	fn set_ctime(&mut self, _: Timestamp) {}
+	fn set_ctime(&mut self, _: Timespec) {}
 
-	fn get_mtime(&self) -> Timestamp {
-		0
+	fn get_mtime(&self) -> Timespec {
+		Timespec::default()
 	}
 
-	fn set_mtime(&mut self, _: Timestamp) {}
+	fn set_mtime(&mut self, _: Timespec) {}
 
 	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
-		let pid = Process::current_assert().lock().pid;
+		let pid = {
+			let proc_mutex = Process::current_assert();
+			let proc = proc_mutex.lock();
+			proc.to_local_pid(proc.pid)
+		};
 		let pid_string = crate::format!("{pid}")?;
 		Ok(FileContent::Link(pid_string).into())
 	}
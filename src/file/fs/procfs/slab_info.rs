@@ -0,0 +1,75 @@
+//! This module implements the slabinfo node, allowing to retrieve statistics about the slab
+//! caches.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::FileContent;
+use crate::memory::slab;
+use crate::util::container::string::String;
+use crate::util::io;
+use crate::util::io::IO;
+use core::cmp::min;
+
+/// Structure representing the slabinfo node.
+pub struct SlabInfo {}
+
+impl KernFSNode for SlabInfo {
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Dynamic(FileContent::Regular))
+	}
+}
+
+impl IO for SlabInfo {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		// Generating content
+		let mut content = String::try_from(
+			b"slabinfo - version: 2.1\n\
+# name            <active_objs> <num_objs> <objsize> <objperslab> <pagesperslab>\n",
+		)?;
+		let mut res = Ok(());
+		slab::foreach(|cache| {
+			if res.is_err() {
+				return;
+			}
+			let stats = cache.get_stats();
+			res = crate::format!(
+				"{:<17} {:>13} {:>10} {:>9} {:>12} {:>14}\n",
+				cache.get_name(),
+				stats.active_objs,
+				stats.num_objs,
+				stats.obj_size,
+				stats.objs_per_slab,
+				1
+			)
+			.and_then(|line| content.push_str(line));
+		});
+		res?;
+
+		// Copying content to userspace buffer
+		let content_bytes = content.as_bytes();
+		let offset = min(offset, content_bytes.len() as u64);
+		let len = min((content_bytes.len() as u64 - offset) as usize, buff.len());
+		buff[..len].copy_from_slice(&content_bytes[(offset as usize)..(offset as usize + len)]);
+
+		let eof = (offset + len as u64) >= content_bytes.len() as u64;
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		Ok(mask & (io::POLLIN | io::POLLOUT))
+	}
+}
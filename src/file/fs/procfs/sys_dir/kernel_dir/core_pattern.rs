@@ -0,0 +1,83 @@
+//! The `core_pattern` node allows to read and modify the pattern giving the path of core dump
+//! files.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::process::coredump;
+use crate::util::io;
+use crate::util::io::IO;
+use core::cmp::min;
+
+/// Structure representing the `core_pattern` node.
+pub struct CorePattern {}
+
+impl KernFSNode for CorePattern {
+	fn get_mode(&self) -> Mode {
+		0o644
+	}
+
+	fn get_uid(&self) -> Uid {
+		0
+	}
+
+	fn get_gid(&self) -> Gid {
+		0
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(FileContent::Regular.into())
+	}
+}
+
+impl IO for CorePattern {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		// Generating content
+		let content = {
+			let pattern = coredump::CORE_PATTERN.lock();
+			if pattern.is_empty() {
+				crate::format!("core\n")?
+			} else {
+				crate::format!("{}\n", crate::util::DisplayableStr(&pattern))?
+			}
+		};
+
+		// Copying content to userspace buffer
+		let content_bytes = content.as_bytes();
+		let offset = min(offset, content_bytes.len() as u64);
+		let len = min((content_bytes.len() as u64 - offset) as usize, buff.len());
+		buff[..len].copy_from_slice(&content_bytes[(offset as usize)..(offset as usize + len)]);
+
+		let eof = (offset + len as u64) >= content_bytes.len() as u64;
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, buff: &[u8]) -> Result<u64, Errno> {
+		let val = buff.strip_suffix(b"\n").unwrap_or(buff);
+		if val.is_empty() || val.len() > coredump::CORE_PATTERN_MAX || val.contains(&b'\0') {
+			return Err(errno!(EINVAL));
+		}
+
+		let mut pattern = coredump::CORE_PATTERN.lock();
+		pattern.clear();
+		pattern.extend_from_slice(val)?;
+		Ok(buff.len() as _)
+	}
+
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		Ok(mask & (io::POLLIN | io::POLLOUT))
+	}
+}
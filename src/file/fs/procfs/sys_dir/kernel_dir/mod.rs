@@ -1,6 +1,8 @@
 //! TODO doc
 
+mod core_pattern;
 mod osrelease;
+mod randomize_va_space;
 
 use super::kernfs::KernFS;
 use crate::errno::EResult;
@@ -16,7 +18,9 @@ use crate::file::Mode;
 use crate::util::boxed::Box;
 use crate::util::container::hashmap::HashMap;
 use crate::util::io::IO;
+use core_pattern::CorePattern;
 use osrelease::OsRelease;
+use randomize_va_space::RandomizeVaSpace;
 
 // TODO Handle dropping
 /// Structure representing the `kernel` directory.
@@ -46,6 +50,26 @@ impl KernelDir {
 			},
 		)?;
 
+		let node = RandomizeVaSpace {};
+		let inode = fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"randomize_va_space".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
+		let node = CorePattern {};
+		let inode = fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"core_pattern".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
 		Ok(Self {
 			content: FileContent::Directory(entries),
 		})
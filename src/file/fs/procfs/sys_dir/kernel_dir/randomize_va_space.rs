@@ -0,0 +1,83 @@
+//! The `randomize_va_space` node allows to read and modify the level of randomization of the
+//! userspace memory layout.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::process::mem_space;
+use crate::util::io;
+use crate::util::io::IO;
+use core::cmp::min;
+use core::str;
+use core::sync::atomic;
+
+/// The maximum randomization level.
+const MAX_LEVEL: u32 = 2;
+
+/// Structure representing the `randomize_va_space` node.
+pub struct RandomizeVaSpace {}
+
+impl KernFSNode for RandomizeVaSpace {
+	fn get_mode(&self) -> Mode {
+		0o644
+	}
+
+	fn get_uid(&self) -> Uid {
+		0
+	}
+
+	fn get_gid(&self) -> Gid {
+		0
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(FileContent::Regular.into())
+	}
+}
+
+impl IO for RandomizeVaSpace {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		// Generating content
+		let val = mem_space::RANDOMIZE_VA_SPACE.load(atomic::Ordering::Relaxed);
+		let content = crate::format!("{val}\n")?;
+
+		// Copying content to userspace buffer
+		let content_bytes = content.as_bytes();
+		let offset = min(offset, content_bytes.len() as u64);
+		let len = min((content_bytes.len() as u64 - offset) as usize, buff.len());
+		buff[..len].copy_from_slice(&content_bytes[(offset as usize)..(offset as usize + len)]);
+
+		let eof = (offset + len as u64) >= content_bytes.len() as u64;
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, buff: &[u8]) -> Result<u64, Errno> {
+		let val: u32 = str::from_utf8(buff)
+			.ok()
+			.and_then(|s| s.trim().parse().ok())
+			.ok_or_else(|| errno!(EINVAL))?;
+		if val > MAX_LEVEL {
+			return Err(errno!(EINVAL));
+		}
+
+		mem_space::RANDOMIZE_VA_SPACE.store(val, atomic::Ordering::Relaxed);
+		Ok(buff.len() as _)
+	}
+
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		Ok(mask & (io::POLLIN | io::POLLOUT))
+	}
+}
@@ -1,6 +1,7 @@
 //! TODO doc
 
 mod kernel_dir;
+mod vm_dir;
 
 use super::kernfs;
 use super::kernfs::KernFS;
@@ -18,6 +19,7 @@ use crate::util::boxed::Box;
 use crate::util::container::hashmap::HashMap;
 use crate::util::io::IO;
 use kernel_dir::KernelDir;
+use vm_dir::VmDir;
 
 // TODO Handle dropping
 /// Structure representing the `sys` directory.
@@ -47,6 +49,17 @@ impl SysDir {
 			},
 		)?;
 
+		// Creating /proc/sys/vm
+		let node = VmDir::new(fs)?;
+		let inode = fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"vm".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Directory,
+			},
+		)?;
+
 		Ok(Self {
 			content: FileContent::Directory(entries),
 		})
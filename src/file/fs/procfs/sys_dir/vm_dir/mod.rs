@@ -0,0 +1,111 @@
+//! The `vm` directory contains tunables of the virtual memory subsystem.
+
+mod dirty;
+
+use super::kernfs::KernFS;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::DirEntry;
+use crate::file::FileContent;
+use crate::file::FileType;
+use crate::file::Mode;
+use crate::util::boxed::Box;
+use crate::util::container::hashmap::HashMap;
+use crate::util::io::IO;
+use dirty::DirtyTunable;
+use dirty::TunableField;
+
+// TODO Handle dropping
+/// Structure representing the `vm` directory.
+pub struct VmDir {
+	/// The content of the directory. This will always be a Directory variant.
+	content: FileContent,
+}
+
+impl VmDir {
+	/// Creates a new instance.
+	///
+	/// The function adds every nodes to the given kernfs `fs`.
+	pub fn new(fs: &mut KernFS) -> Result<Self, Errno> {
+		let mut entries = HashMap::new();
+
+		// TODO On fail, remove previously inserted nodes
+
+		let tunables: [(&[u8], TunableField, u32); 4] = [
+			(
+				b"dirty_background_ratio",
+				|t| &mut t.dirty_background_ratio,
+				100,
+			),
+			(
+				b"dirty_expire_centisecs",
+				|t| &mut t.dirty_expire_centisecs,
+				u32::MAX,
+			),
+			(b"dirty_ratio", |t| &mut t.dirty_ratio, 100),
+			(
+				b"dirty_writeback_centisecs",
+				|t| &mut t.dirty_writeback_centisecs,
+				u32::MAX,
+			),
+		];
+		for (name, field, max) in tunables {
+			let node = DirtyTunable {
+				field,
+				max,
+			};
+			let inode = fs.add_node(Box::new(node)?)?;
+			entries.insert(
+				name.try_into()?,
+				DirEntry {
+					inode,
+					entry_type: FileType::Regular,
+				},
+			)?;
+		}
+
+		Ok(Self {
+			content: FileContent::Directory(entries),
+		})
+	}
+}
+
+impl KernFSNode for VmDir {
+	fn get_mode(&self) -> Mode {
+		0o555
+	}
+
+	fn get_uid(&self) -> Uid {
+		0
+	}
+
+	fn get_gid(&self) -> Gid {
+		0
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Owned(&mut self.content))
+	}
+}
+
+impl IO for VmDir {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, _offset: u64, _buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Err(errno!(EINVAL))
+	}
+}
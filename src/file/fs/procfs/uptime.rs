@@ -1,11 +1,17 @@
-//! The uptime node returns the amount of time elapsed since the system started up.
+//! The uptime node returns the amount of time elapsed since the system started up, along with
+//! the time spent idle by the CPUs.
 
+use crate::cpu::idle;
+use crate::cpu::smp;
 use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file::fs::kernfs::content::KernFSContent;
 use crate::file::fs::kernfs::node::KernFSNode;
 use crate::file::FileContent;
 use crate::file::Mode;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::unit::TimestampScale;
 use crate::util::io::IO;
 use core::cmp::min;
 
@@ -28,8 +34,16 @@ impl IO for Uptime {
 	}
 
 	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
-		// TODO
-		let content = crate::format!("0.00 0.00\n")?;
+		let uptime = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond)?;
+		// The idle time is the sum of the idle times of every CPU
+		let idle: u64 = (0..smp::get_cpus_count()).map(idle::get_idle_time).sum();
+		let content = crate::format!(
+			"{}.{:02} {}.{:02}\n",
+			uptime / 1_000_000_000,
+			(uptime / 10_000_000) % 100,
+			idle / 1_000_000_000,
+			(idle / 10_000_000) % 100
+		)?;
 		let content_bytes = content.as_bytes();
 
 		// Copy content to userspace buffer
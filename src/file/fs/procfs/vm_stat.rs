@@ -0,0 +1,75 @@
+//! This module implements the vmstat node, allowing to retrieve counters about the virtual
+//! memory of the system.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::page_cache;
+use crate::file::FileContent;
+use crate::memory;
+use crate::memory::slab;
+use crate::memory::stats;
+use crate::util::io;
+use crate::util::io::IO;
+use core::cmp::min;
+use core::sync::atomic;
+
+/// Structure representing the vmstat node.
+pub struct VmStat {}
+
+impl KernFSNode for VmStat {
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Dynamic(FileContent::Regular))
+	}
+}
+
+impl IO for VmStat {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		// Generating content
+		let free_pages = stats::MEM_INFO.lock().mem_free * 1024 / memory::PAGE_SIZE;
+		let content = crate::format!(
+			"nr_free_pages {free_pages}
+nr_file_pages {}
+nr_dirty {}
+nr_slab_unreclaimable {}
+pgalloc {}
+pgfree {}
+pgfault {}
+pswpin 0
+pswpout 0
+",
+			page_cache::get_cached_pages(),
+			page_cache::get_dirty_pages(),
+			slab::get_pages_count(),
+			stats::PGALLOC.load(atomic::Ordering::Relaxed),
+			stats::PGFREE.load(atomic::Ordering::Relaxed),
+			stats::PGFAULT.load(atomic::Ordering::Relaxed),
+		)?;
+
+		// Copying content to userspace buffer
+		let content_bytes = content.as_bytes();
+		let offset = min(offset, content_bytes.len() as u64);
+		let len = min((content_bytes.len() as u64 - offset) as usize, buff.len());
+		buff[..len].copy_from_slice(&content_bytes[(offset as usize)..(offset as usize + len)]);
+
+		let eof = (offset + len as u64) >= content_bytes.len() as u64;
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		Ok(mask & (io::POLLIN | io::POLLOUT))
+	}
+}
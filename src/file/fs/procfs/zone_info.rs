@@ -0,0 +1,81 @@
+//! This module implements the zoneinfo node, allowing to retrieve informations about the zones
+//! of the buddy allocator, including the state of the per-CPU page caches.
+
+use super::buddy_info::ZONE_NAMES;
+use crate::cpu;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::FileContent;
+use crate::memory::buddy;
+use crate::memory::pcp;
+use crate::util::container::string::String;
+use crate::util::io;
+use crate::util::io::IO;
+use core::cmp::min;
+
+/// Structure representing the zoneinfo node.
+pub struct ZoneInfo {}
+
+impl KernFSNode for ZoneInfo {
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Dynamic(FileContent::Regular))
+	}
+}
+
+impl IO for ZoneInfo {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		// Generating content
+		let mut content = String::new();
+		let free_frames = buddy::get_free_frames();
+		for (zone, name) in ZONE_NAMES.iter().enumerate() {
+			let free: usize = free_frames[zone]
+				.iter()
+				.enumerate()
+				.map(|(order, count)| count << order)
+				.sum();
+			content.push_str(crate::format!("Node 0, zone {name:>8}\n")?)?;
+			content.push_str(crate::format!("  pages free     {free}\n")?)?;
+			content.push_str(crate::format!("  pagesets\n")?)?;
+			for cpu in 0..cpu::MAX_CPUS {
+				let stats = pcp::get_stats(cpu)[zone];
+				content.push_str(crate::format!("    cpu: {cpu}\n")?)?;
+				content.push_str(crate::format!("              count:   {}\n", stats.count)?)?;
+				content.push_str(crate::format!("              high:    {}\n", pcp::HIGH)?)?;
+				content.push_str(crate::format!("              batch:   {}\n", pcp::BATCH)?)?;
+				content.push_str(crate::format!("              hits:    {}\n", stats.hits)?)?;
+				content.push_str(crate::format!(
+					"              refills: {}\n",
+					stats.refills
+				)?)?;
+				content.push_str(crate::format!("              drains:  {}\n", stats.drains)?)?;
+			}
+		}
+
+		// Copying content to userspace buffer
+		let content_bytes = content.as_bytes();
+		let offset = min(offset, content_bytes.len() as u64);
+		let len = min((content_bytes.len() as u64 - offset) as usize, buff.len());
+		buff[..len].copy_from_slice(&content_bytes[(offset as usize)..(offset as usize + len)]);
+
+		let eof = (offset + len as u64) >= content_bytes.len() as u64;
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		Ok(mask & (io::POLLIN | io::POLLOUT))
+	}
+}
@@ -0,0 +1,916 @@
+//! SquashFS is a compressed read-only filesystem, mostly used for root filesystems of live
+//! systems, embedded devices and initrds.
+//!
+//! The superblock, at the beginning of the device, gives the location of each table. Inodes and
+//! directory listings are stored in metadata blocks of up to 8192 bytes, each preceded by a 16
+//! bits header giving its size on the device and whether it is compressed.
+//!
+//! The content of regular files is stored in data blocks of the size given by the superblock. The
+//! tail of a file, smaller than a block, may be packed with others in a fragment block.
+//!
+//! A file is referred to by an inode reference, made of the offset of the metadata block holding
+//! its inode relative to the beginning of the inode table, and of the offset of the inode in the
+//! decompressed block. This reference is used as the inode of the file.
+//!
+//! Only version 4.0 of the format is supported, with zlib or zstd compression.
+
+use crate::compression::zlib;
+use crate::compression::zstd;
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::Filesystem;
+use crate::file::fs::FilesystemType;
+use crate::file::fs::MountOptions;
+use crate::file::fs::Statfs;
+use crate::file::path::Path;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::DirEntry;
+use crate::file::File;
+use crate::file::FileContent;
+use crate::file::FileLocation;
+use crate::file::FileType;
+use crate::file::INode;
+use crate::file::Mode;
+use crate::time::unit::Timespec;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+use crate::util::lock::Mutex;
+use crate::util::math;
+use crate::util::ptr::arc::Arc;
+use core::cmp::min;
+
+/// The filesystem's magic number, also returned by `statfs`.
+const SQUASHFS_MAGIC: u32 = 0x73717368;
+/// The size of the superblock in bytes.
+const SUPERBLOCK_SIZE: usize = 96;
+/// The maximum size of a metadata block, once decompressed.
+const METADATA_SIZE: usize = 8192;
+/// The maximum length of a name.
+const MAX_NAME_LEN: usize = 256;
+
+/// Compression algorithm: zlib.
+const COMPRESSION_ZLIB: u16 = 1;
+/// Compression algorithm: zstd.
+const COMPRESSION_ZSTD: u16 = 6;
+
+/// Metadata block header flag: the block is stored uncompressed.
+const METADATA_UNCOMPRESSED: u16 = 0x8000;
+/// Data block size flag: the block is stored uncompressed.
+const BLOCK_UNCOMPRESSED: u32 = 1 << 24;
+/// Fragment index telling that a file has no fragment.
+const NO_FRAGMENT: u32 = 0xffffffff;
+
+/// Inode type: directory.
+const INODE_DIR: u16 = 1;
+/// Inode type: regular file.
+const INODE_FILE: u16 = 2;
+/// Inode type: symbolic link.
+const INODE_SYMLINK: u16 = 3;
+/// Inode type: block device.
+const INODE_BLKDEV: u16 = 4;
+/// Inode type: char device.
+const INODE_CHRDEV: u16 = 5;
+/// Inode type: FIFO.
+const INODE_FIFO: u16 = 6;
+/// Inode type: socket.
+const INODE_SOCKET: u16 = 7;
+/// The offset between the types of basic inodes and their extended version.
+const INODE_EXTENDED: u16 = 7;
+
+/// Returns the file type associated with the given inode type.
+///
+/// Extended types are handled as their basic counterpart.
+fn to_file_type(inode_type: u16) -> Option<FileType> {
+	let inode_type = match inode_type {
+		t @ 1..=7 => t,
+		t @ 8..=14 => t - INODE_EXTENDED,
+		_ => return None,
+	};
+	match inode_type {
+		INODE_DIR => Some(FileType::Directory),
+		INODE_FILE => Some(FileType::Regular),
+		INODE_SYMLINK => Some(FileType::Link),
+		INODE_BLKDEV => Some(FileType::BlockDevice),
+		INODE_CHRDEV => Some(FileType::CharDevice),
+		INODE_FIFO => Some(FileType::Fifo),
+		INODE_SOCKET => Some(FileType::Socket),
+		_ => None,
+	}
+}
+
+/// The superblock of the filesystem.
+struct Superblock {
+	/// The number of inodes.
+	inode_count: u32,
+	/// The size of a data block in bytes.
+	block_size: u32,
+	/// The compression algorithm.
+	compression: u16,
+	/// The number of entries in the ID table.
+	id_count: u16,
+	/// The reference to the inode of the root directory.
+	root_inode: u64,
+	/// The number of bytes used by the filesystem on the device.
+	bytes_used: u64,
+	/// The offset of the ID table.
+	id_table: u64,
+	/// The offset of the inode table.
+	inode_table: u64,
+	/// The offset of the directory table.
+	dir_table: u64,
+	/// The offset of the fragment table.
+	frag_table: u64,
+}
+
+impl Superblock {
+	/// Reads the superblock from the device.
+	///
+	/// If the device does not contain a SquashFS filesystem, the function returns `None`.
+	fn read(io: &mut dyn IO) -> EResult<Option<Self>> {
+		let mut buf = [0; SUPERBLOCK_SIZE];
+		io.read(0, &mut buf)?;
+		let u16_at = |off: usize| u16::from_le_bytes(buf[off..(off + 2)].try_into().unwrap());
+		let u32_at = |off: usize| u32::from_le_bytes(buf[off..(off + 4)].try_into().unwrap());
+		let u64_at = |off: usize| u64::from_le_bytes(buf[off..(off + 8)].try_into().unwrap());
+		if u32_at(0) != SQUASHFS_MAGIC {
+			return Ok(None);
+		}
+
+		let block_size = u32_at(12);
+		let compression = u16_at(20);
+		let block_log = u16_at(22);
+		let version = (u16_at(28), u16_at(30));
+		let valid_block_size =
+			(4096..=1048576).contains(&block_size) && block_size == 1 << block_log;
+		let valid_compression = matches!(compression, COMPRESSION_ZLIB | COMPRESSION_ZSTD);
+		if version != (4, 0) || !valid_block_size || !valid_compression {
+			return Err(errno!(EINVAL));
+		}
+
+		Ok(Some(Self {
+			inode_count: u32_at(4),
+			block_size,
+			compression,
+			id_count: u16_at(26),
+			root_inode: u64_at(32),
+			bytes_used: u64_at(40),
+			id_table: u64_at(48),
+			inode_table: u64_at(64),
+			dir_table: u64_at(72),
+			frag_table: u64_at(80),
+		}))
+	}
+}
+
+/// A position in a chain of metadata blocks.
+struct MetadataPos {
+	/// The offset of the current metadata block on the device.
+	block: u64,
+	/// The offset in the decompressed block.
+	offset: usize,
+}
+
+/// The content of an inode, depending on its type.
+enum InodeContent {
+	/// A directory.
+	Directory {
+		/// The offset of the metadata block of the listing, relative to the directory table.
+		block: u32,
+		/// The offset of the listing in the decompressed block.
+		offset: u16,
+		/// The size of the listing, plus 3.
+		size: u32,
+		/// The inode number of the parent directory.
+		parent: u32,
+	},
+	/// A regular file.
+	Regular {
+		/// The offset of the first data block on the device.
+		start: u64,
+		/// The size of the file in bytes.
+		size: u64,
+		/// The index of the fragment holding the tail of the file, or [`NO_FRAGMENT`].
+		fragment: u32,
+		/// The offset of the tail of the file in the fragment.
+		frag_off: u32,
+		/// The sizes of the data blocks on the device, with their flags.
+		blocks: Vec<u32>,
+	},
+	/// A symbolic link.
+	Link(Vec<u8>),
+	/// A block device, with its encoded device number.
+	BlockDevice(u32),
+	/// A char device, with its encoded device number.
+	CharDevice(u32),
+	/// A FIFO.
+	Fifo,
+	/// A socket.
+	Socket,
+}
+
+/// An inode.
+struct Inode {
+	/// The permissions of the file.
+	perms: u16,
+	/// The index of the owner's UID in the ID table.
+	uid_idx: u16,
+	/// The index of the owner's GID in the ID table.
+	gid_idx: u16,
+	/// The timestamp of the last modification.
+	mtime: u32,
+	/// The inode number.
+	inode_number: u32,
+	/// The number of hard links.
+	nlink: u32,
+	/// The content of the inode.
+	content: InodeContent,
+}
+
+/// Decodes the device number `dev` into its major and minor numbers.
+fn decode_dev(dev: u32) -> (u32, u32) {
+	let major = (dev >> 8) & 0xfff;
+	let minor = (dev & 0xff) | ((dev >> 12) & 0xfff00);
+	(major, minor)
+}
+
+/// An entry of a directory listing.
+struct Entry {
+	/// The name of the entry.
+	name: String,
+	/// The reference to the inode of the entry.
+	inode: INode,
+	/// The inode number of the entry.
+	inode_number: u32,
+	/// The type of the entry.
+	entry_type: FileType,
+}
+
+/// Structure representing a instance of the SquashFS filesystem.
+pub struct SquashFs {
+	/// The superblock.
+	sb: Superblock,
+
+	/// The last metadata block read, with its offset on the device and the offset of the next
+	/// block.
+	metadata_cache: Option<(u64, u64, Vec<u8>)>,
+	/// The last data or fragment block read, with its offset on the device.
+	block_cache: Option<(u64, Vec<u8>)>,
+
+	/// Maps the inode number of each directory encountered so far to its reference, to resolve
+	/// the `..` entries.
+	dirs: HashMap<u32, INode>,
+}
+
+impl SquashFs {
+	/// Decompresses `src` into `dst` with the filesystem's compression algorithm.
+	///
+	/// The function returns the number of bytes written to `dst`.
+	fn decompress(&self, src: &[u8], dst: &mut [u8]) -> EResult<usize> {
+		let res = match self.sb.compression {
+			COMPRESSION_ZLIB => zlib::decompress(src, dst),
+			COMPRESSION_ZSTD => zstd::decompress(src, dst),
+			_ => Err(errno!(EINVAL)),
+		};
+		res.map_err(|_| errno!(EUCLEAN))
+	}
+
+	/// Loads the metadata block at offset `off` on the device.
+	///
+	/// The function returns the offset of the next block along with the decompressed content.
+	fn load_metadata(&mut self, io: &mut dyn IO, off: u64) -> EResult<(u64, &[u8])> {
+		if !matches!(&self.metadata_cache, Some((o, ..)) if *o == off) {
+			let mut hdr = [0; 2];
+			io.read(off, &mut hdr)?;
+			let hdr = u16::from_le_bytes(hdr);
+			let size = (hdr & !METADATA_UNCOMPRESSED) as usize;
+			if size == 0 || size > METADATA_SIZE {
+				return Err(errno!(EUCLEAN));
+			}
+
+			let mut raw = Vec::from_elem(0u8, size)?;
+			io.read(off + 2, &mut raw)?;
+			let data = if hdr & METADATA_UNCOMPRESSED != 0 {
+				raw
+			} else {
+				let mut data = Vec::from_elem(0u8, METADATA_SIZE)?;
+				let len = self.decompress(&raw, &mut data)?;
+				data.truncate(len);
+				data
+			};
+			self.metadata_cache = Some((off, off + 2 + size as u64, data));
+		}
+
+		let (_, next, data) = self.metadata_cache.as_ref().unwrap();
+		Ok((*next, data.as_slice()))
+	}
+
+	/// Reads metadata at position `pos` to fill `buf`, following the chain of blocks if needed.
+	///
+	/// `pos` is updated to point right after the data that has been read.
+	fn read_metadata(
+		&mut self,
+		io: &mut dyn IO,
+		pos: &mut MetadataPos,
+		buf: &mut [u8],
+	) -> EResult<()> {
+		let mut i = 0;
+		while i < buf.len() {
+			let (next, data) = self.load_metadata(io, pos.block)?;
+			if pos.offset >= data.len() {
+				pos.offset -= data.len();
+				pos.block = next;
+				continue;
+			}
+
+			let len = min(buf.len() - i, data.len() - pos.offset);
+			buf[i..(i + len)].copy_from_slice(&data[pos.offset..(pos.offset + len)]);
+			i += len;
+			pos.offset += len;
+		}
+		Ok(())
+	}
+
+	/// Reads a value of `N` bytes from metadata at position `pos`.
+	fn read_metadata_array<const N: usize>(
+		&mut self,
+		io: &mut dyn IO,
+		pos: &mut MetadataPos,
+	) -> EResult<[u8; N]> {
+		let mut buf = [0; N];
+		self.read_metadata(io, pos, &mut buf)?;
+		Ok(buf)
+	}
+
+	/// Reads the entry `index` of the table at offset `table` on the device.
+	///
+	/// The table is made of a list of offsets of metadata blocks, containing entries of `N`
+	/// bytes.
+	fn read_table_entry<const N: usize>(
+		&mut self,
+		io: &mut dyn IO,
+		table: u64,
+		index: u32,
+	) -> EResult<[u8; N]> {
+		let per_block = (METADATA_SIZE / N) as u64;
+		let mut ptr = [0; 8];
+		io.read(table + (index as u64 / per_block) * 8, &mut ptr)?;
+		let mut pos = MetadataPos {
+			block: u64::from_le_bytes(ptr),
+			offset: (index as u64 % per_block) as usize * N,
+		};
+		self.read_metadata_array(io, &mut pos)
+	}
+
+	/// Returns the ID at index `index` of the ID table.
+	fn get_id(&mut self, io: &mut dyn IO, index: u16) -> EResult<u32> {
+		if index >= self.sb.id_count {
+			return Err(errno!(EUCLEAN));
+		}
+		let entry = self.read_table_entry::<4>(io, self.sb.id_table, index as _)?;
+		Ok(u32::from_le_bytes(entry))
+	}
+
+	/// Returns the offset on the device and the size field of the fragment block `index`.
+	fn get_fragment(&mut self, io: &mut dyn IO, index: u32) -> EResult<(u64, u32)> {
+		let entry = self.read_table_entry::<16>(io, self.sb.frag_table, index)?;
+		let start = u64::from_le_bytes(entry[0..8].try_into().unwrap());
+		let size = u32::from_le_bytes(entry[8..12].try_into().unwrap());
+		Ok((start, size))
+	}
+
+	/// Reads the inode with reference `inode`.
+	fn read_inode(&mut self, io: &mut dyn IO, inode: INode) -> EResult<Inode> {
+		let mut pos = MetadataPos {
+			block: self.sb.inode_table + (inode >> 16),
+			offset: (inode & 0xffff) as usize,
+		};
+		let hdr: [u8; 16] = self.read_metadata_array(io, &mut pos)?;
+		let u16_at =
+			|buf: &[u8], off: usize| u16::from_le_bytes(buf[off..(off + 2)].try_into().unwrap());
+		let u32_at =
+			|buf: &[u8], off: usize| u32::from_le_bytes(buf[off..(off + 4)].try_into().unwrap());
+		let u64_at =
+			|buf: &[u8], off: usize| u64::from_le_bytes(buf[off..(off + 8)].try_into().unwrap());
+
+		let inode_type = u16_at(&hdr, 0);
+		let file_type = to_file_type(inode_type).ok_or_else(|| errno!(EUCLEAN))?;
+		let extended = inode_type > INODE_EXTENDED;
+		let (nlink, content) = match file_type {
+			FileType::Directory if extended => {
+				let buf: [u8; 24] = self.read_metadata_array(io, &mut pos)?;
+				let content = InodeContent::Directory {
+					block: u32_at(&buf, 8),
+					offset: u16_at(&buf, 18),
+					size: u32_at(&buf, 4),
+					parent: u32_at(&buf, 12),
+				};
+				(u32_at(&buf, 0), content)
+			}
+			FileType::Directory => {
+				let buf: [u8; 16] = self.read_metadata_array(io, &mut pos)?;
+				let content = InodeContent::Directory {
+					block: u32_at(&buf, 0),
+					offset: u16_at(&buf, 10),
+					size: u16_at(&buf, 8) as _,
+					parent: u32_at(&buf, 12),
+				};
+				(u32_at(&buf, 4), content)
+			}
+
+			FileType::Regular => {
+				let (start, size, fragment, frag_off, nlink) = if extended {
+					let buf: [u8; 40] = self.read_metadata_array(io, &mut pos)?;
+					(
+						u64_at(&buf, 0),
+						u64_at(&buf, 8),
+						u32_at(&buf, 28),
+						u32_at(&buf, 32),
+						u32_at(&buf, 24),
+					)
+				} else {
+					let buf: [u8; 16] = self.read_metadata_array(io, &mut pos)?;
+					(
+						u32_at(&buf, 0) as _,
+						u32_at(&buf, 12) as _,
+						u32_at(&buf, 4),
+						u32_at(&buf, 8),
+						1,
+					)
+				};
+
+				let block_size = self.sb.block_size as u64;
+				let blocks_count = if fragment == NO_FRAGMENT {
+					math::ceil_div(size, block_size)
+				} else {
+					size / block_size
+				};
+				let mut raw = Vec::from_elem(0u8, blocks_count as usize * 4)?;
+				self.read_metadata(io, &mut pos, &mut raw)?;
+				let mut blocks = Vec::with_capacity(blocks_count as _)?;
+				for b in raw.chunks_exact(4) {
+					blocks.push(u32::from_le_bytes(b.try_into().unwrap()))?;
+				}
+
+				let content = InodeContent::Regular {
+					start,
+					size,
+					fragment,
+					frag_off,
+					blocks,
+				};
+				(nlink, content)
+			}
+
+			FileType::Link => {
+				let buf: [u8; 8] = self.read_metadata_array(io, &mut pos)?;
+				let size = u32_at(&buf, 4) as usize;
+				if size > METADATA_SIZE {
+					return Err(errno!(EUCLEAN));
+				}
+				let mut target = Vec::from_elem(0u8, size)?;
+				self.read_metadata(io, &mut pos, &mut target)?;
+				(u32_at(&buf, 0), InodeContent::Link(target))
+			}
+
+			FileType::BlockDevice | FileType::CharDevice => {
+				let buf: [u8; 8] = self.read_metadata_array(io, &mut pos)?;
+				let dev = u32_at(&buf, 4);
+				let content = match file_type {
+					FileType::BlockDevice => InodeContent::BlockDevice(dev),
+					_ => InodeContent::CharDevice(dev),
+				};
+				(u32_at(&buf, 0), content)
+			}
+
+			FileType::Fifo | FileType::Socket => {
+				let buf: [u8; 4] = self.read_metadata_array(io, &mut pos)?;
+				let content = match file_type {
+					FileType::Fifo => InodeContent::Fifo,
+					_ => InodeContent::Socket,
+				};
+				(u32_at(&buf, 0), content)
+			}
+		};
+
+		Ok(Inode {
+			perms: u16_at(&hdr, 2),
+			uid_idx: u16_at(&hdr, 4),
+			gid_idx: u16_at(&hdr, 6),
+			mtime: u32_at(&hdr, 8),
+			inode_number: u32_at(&hdr, 12),
+			nlink,
+			content,
+		})
+	}
+
+	/// Returns the reference to the parent of the directory `dir`, with reference `inode`.
+	///
+	/// The parent must have been listed before. If not, the root directory is returned.
+	fn get_parent(&self, inode: INode, dir: &Inode) -> INode {
+		let InodeContent::Directory {
+			parent, ..
+		} = dir.content
+		else {
+			return inode;
+		};
+		if inode == self.sb.root_inode {
+			return inode;
+		}
+		self.dirs
+			.get(&parent)
+			.cloned()
+			.unwrap_or(self.sb.root_inode)
+	}
+
+	/// Returns the list of the entries of the directory `dir`.
+	///
+	/// The `.` and `..` entries are not included since they are not stored on the device.
+	fn read_dir(&mut self, io: &mut dyn IO, dir: &Inode) -> EResult<Vec<Entry>> {
+		let InodeContent::Directory {
+			block,
+			offset,
+			size,
+			..
+		} = dir.content
+		else {
+			return Err(errno!(ENOTDIR));
+		};
+		let mut pos = MetadataPos {
+			block: self.sb.dir_table + block as u64,
+			offset: offset as _,
+		};
+		// The size includes the `.` and `..` entries, which are not stored
+		let mut remaining = size.saturating_sub(3) as usize;
+
+		let mut entries = Vec::new();
+		while remaining > 0 {
+			let hdr: [u8; 12] = self.read_metadata_array(io, &mut pos)?;
+			let count = u32::from_le_bytes(hdr[0..4].try_into().unwrap()) + 1;
+			let start = u32::from_le_bytes(hdr[4..8].try_into().unwrap());
+			let base = u32::from_le_bytes(hdr[8..12].try_into().unwrap());
+			if count > 256 {
+				return Err(errno!(EUCLEAN));
+			}
+			remaining = remaining.checked_sub(12).ok_or_else(|| errno!(EUCLEAN))?;
+
+			for _ in 0..count {
+				let ent: [u8; 8] = self.read_metadata_array(io, &mut pos)?;
+				let offset = u16::from_le_bytes(ent[0..2].try_into().unwrap());
+				let delta = i16::from_le_bytes(ent[2..4].try_into().unwrap());
+				let entry_type = u16::from_le_bytes(ent[4..6].try_into().unwrap());
+				let name_len = u16::from_le_bytes(ent[6..8].try_into().unwrap()) as usize + 1;
+				if name_len > MAX_NAME_LEN {
+					return Err(errno!(EUCLEAN));
+				}
+				let mut name = [0; MAX_NAME_LEN];
+				self.read_metadata(io, &mut pos, &mut name[..name_len])?;
+				remaining = remaining
+					.checked_sub(8 + name_len)
+					.ok_or_else(|| errno!(EUCLEAN))?;
+
+				let entry = Entry {
+					name: String::try_from(&name[..name_len])?,
+					inode: ((start as u64) << 16) | offset as u64,
+					inode_number: base.wrapping_add_signed(delta as _),
+					entry_type: to_file_type(entry_type).ok_or_else(|| errno!(EUCLEAN))?,
+				};
+				if entry.entry_type == FileType::Directory {
+					self.dirs.insert(entry.inode_number, entry.inode)?;
+				}
+				entries.push(entry)?;
+			}
+		}
+
+		Ok(entries)
+	}
+
+	/// Loads the data block at offset `off` on the device, with the size field `size`.
+	///
+	/// The function returns the decompressed content of the block.
+	fn load_block(&mut self, io: &mut dyn IO, off: u64, size: u32) -> EResult<&[u8]> {
+		if !matches!(&self.block_cache, Some((o, _)) if *o == off) {
+			let len = (size & !BLOCK_UNCOMPRESSED) as usize;
+			if len > self.sb.block_size as usize {
+				return Err(errno!(EUCLEAN));
+			}
+
+			let mut raw = Vec::from_elem(0u8, len)?;
+			io.read(off, &mut raw)?;
+			let data = if size & BLOCK_UNCOMPRESSED != 0 {
+				raw
+			} else {
+				let mut data = Vec::from_elem(0u8, self.sb.block_size as usize)?;
+				let len = self.decompress(&raw, &mut data)?;
+				data.truncate(len);
+				data
+			};
+			self.block_cache = Some((off, data));
+		}
+
+		let (_, data) = self.block_cache.as_ref().unwrap();
+		Ok(data.as_slice())
+	}
+}
+
+impl Filesystem for SquashFs {
+	fn get_name(&self) -> &[u8] {
+		b"squashfs"
+	}
+
+	fn is_readonly(&self) -> bool {
+		true
+	}
+
+	fn must_cache(&self) -> bool {
+		true
+	}
+
+	fn get_stat(&self, _io: &mut dyn IO) -> Result<Statfs, Errno> {
+		Ok(Statfs {
+			f_type: SQUASHFS_MAGIC,
+			f_bsize: self.sb.block_size,
+			f_blocks: math::ceil_div(self.sb.bytes_used, self.sb.block_size as u64) as _,
+			f_bfree: 0,
+			f_bavail: 0,
+			f_files: self.sb.inode_count as _,
+			f_ffree: 0,
+			f_fsid: Default::default(),
+			f_namelen: MAX_NAME_LEN as _,
+			f_frsize: self.sb.block_size,
+			f_flags: 0, // TODO
+		})
+	}
+
+	fn get_root_inode(&self, _io: &mut dyn IO) -> Result<INode, Errno> {
+		Ok(self.sb.root_inode)
+	}
+
+	fn get_inode(
+		&mut self,
+		io: &mut dyn IO,
+		parent: Option<INode>,
+		name: &[u8],
+	) -> Result<INode, Errno> {
+		let parent_inode = parent.unwrap_or(self.sb.root_inode);
+		let parent = self.read_inode(io, parent_inode)?;
+
+		match name {
+			b"." => return Ok(parent_inode),
+			b".." => return Ok(self.get_parent(parent_inode, &parent)),
+			_ => {}
+		}
+		self.read_dir(io, &parent)?
+			.into_iter()
+			.find(|e| e.name.as_bytes() == name)
+			.map(|e| e.inode)
+			.ok_or_else(|| errno!(ENOENT))
+	}
+
+	fn load_file(&mut self, io: &mut dyn IO, inode: INode, name: String) -> Result<File, Errno> {
+		let node = self.read_inode(io, inode)?;
+
+		let file_content = match &node.content {
+			InodeContent::Directory {
+				..
+			} => {
+				self.dirs.insert(node.inode_number, inode)?;
+
+				let mut entries = HashMap::new();
+				entries.insert(
+					String::try_from(b".")?,
+					DirEntry {
+						inode,
+						entry_type: FileType::Directory,
+					},
+				)?;
+				entries.insert(
+					String::try_from(b"..")?,
+					DirEntry {
+						inode: self.get_parent(inode, &node),
+						entry_type: FileType::Directory,
+					},
+				)?;
+				for e in self.read_dir(io, &node)? {
+					entries.insert(
+						e.name,
+						DirEntry {
+							inode: e.inode,
+							entry_type: e.entry_type,
+						},
+					)?;
+				}
+
+				FileContent::Directory(entries)
+			}
+
+			InodeContent::Regular {
+				..
+			} => FileContent::Regular,
+
+			InodeContent::Link(target) => FileContent::Link(String::try_from(target.as_slice())?),
+
+			InodeContent::BlockDevice(dev) => {
+				let (major, minor) = decode_dev(*dev);
+				FileContent::BlockDevice {
+					major,
+					minor,
+				}
+			}
+
+			InodeContent::CharDevice(dev) => {
+				let (major, minor) = decode_dev(*dev);
+				FileContent::CharDevice {
+					major,
+					minor,
+				}
+			}
+
+			InodeContent::Fifo => FileContent::Fifo,
+
+			InodeContent::Socket => FileContent::Socket,
+		};
+
+		let size = match &node.content {
+			InodeContent::Regular {
+				size, ..
+			} => *size,
+			InodeContent::Link(target) => target.len() as u64,
+			_ => 0,
+		};
+		let uid = self.get_id(io, node.uid_idx)?;
+		let gid = self.get_id(io, node.gid_idx)?;
+
+		let file_location = FileLocation::Filesystem {
+			mountpoint_id: 0, // dummy value to be replaced
+			inode,
+		};
+		let mut file = File::new(
+			name,
+			uid as _,
+			gid as _,
+			node.perms as Mode & 0o7777,
+			file_location,
+			file_content,
+		)?;
+		file.set_hard_links_count(node.nlink as _);
+		file.blocks_count = math::ceil_div(size, 512);
+		file.set_size(size);
+		let mtime = Timespec::from_secs(node.mtime as _);
+		file.ctime = mtime;
+		file.mtime = mtime;
+		file.atime = mtime;
+
+		Ok(file)
+	}
+
+	fn add_file(
+		&mut self,
+		_io: &mut dyn IO,
+		_parent_inode: INode,
+		_name: String,
+		_uid: Uid,
+		_gid: Gid,
+		_mode: Mode,
+		_content: FileContent,
+	) -> Result<File, Errno> {
+		Err(errno!(EROFS))
+	}
+
+	fn add_link(
+		&mut self,
+		_io: &mut dyn IO,
+		_parent_inode: INode,
+		_name: &[u8],
+		_inode: INode,
+	) -> Result<(), Errno> {
+		Err(errno!(EROFS))
+	}
+
+	fn update_inode(&mut self, _io: &mut dyn IO, _file: &File) -> Result<(), Errno> {
+		Err(errno!(EROFS))
+	}
+
+	fn remove_file(
+		&mut self,
+		_io: &mut dyn IO,
+		_parent_inode: INode,
+		_name: &[u8],
+	) -> Result<u16, Errno> {
+		Err(errno!(EROFS))
+	}
+
+	fn read_node(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		buf: &mut [u8],
+	) -> Result<u64, Errno> {
+		let node = self.read_inode(io, inode)?;
+		let InodeContent::Regular {
+			start,
+			size,
+			fragment,
+			frag_off,
+			blocks,
+		} = node.content
+		else {
+			return Err(errno!(EINVAL));
+		};
+		if off >= size {
+			return Ok(0);
+		}
+
+		let block_size = self.sb.block_size as u64;
+		let len = min(buf.len() as u64, size - off) as usize;
+		let mut i = 0;
+		while i < len {
+			let cur = off + i as u64;
+			let blk_index = (cur / block_size) as usize;
+			let blk_off = (cur % block_size) as usize;
+
+			let data = match blocks.get(blk_index) {
+				// Sparse block
+				Some(0) => None,
+				Some(blk_size) => {
+					let blk_start = blocks[..blk_index]
+						.iter()
+						.map(|s| (s & !BLOCK_UNCOMPRESSED) as u64)
+						.sum::<u64>();
+					let data = self.load_block(io, start + blk_start, *blk_size)?;
+					Some(data.get(blk_off..).unwrap_or(&[]))
+				}
+				// Tail of the file, in a fragment
+				None if fragment != NO_FRAGMENT => {
+					let (frag_start, frag_size) = self.get_fragment(io, fragment)?;
+					let data = self.load_block(io, frag_start, frag_size)?;
+					Some(data.get((frag_off as usize + blk_off)..).unwrap_or(&[]))
+				}
+				None => return Err(errno!(EUCLEAN)),
+			};
+
+			let chunk = min(len - i, block_size as usize - blk_off);
+			match data {
+				Some(data) => {
+					if data.len() < chunk {
+						return Err(errno!(EUCLEAN));
+					}
+					buf[i..(i + chunk)].copy_from_slice(&data[..chunk]);
+				}
+				None => buf[i..(i + chunk)].fill(0),
+			}
+			i += chunk;
+		}
+
+		Ok(len as _)
+	}
+
+	fn write_node(
+		&mut self,
+		_io: &mut dyn IO,
+		_inode: INode,
+		_off: u64,
+		_buf: &[u8],
+	) -> Result<(), Errno> {
+		Err(errno!(EROFS))
+	}
+}
+
+/// Structure representing the SquashFS file system type.
+pub struct SquashFsType {}
+
+impl FilesystemType for SquashFsType {
+	fn get_name(&self) -> &'static [u8] {
+		b"squashfs"
+	}
+
+	fn detect(&self, io: &mut dyn IO) -> Result<bool, Errno> {
+		let mut magic = [0; 4];
+		io.read(0, &mut magic)?;
+		Ok(u32::from_le_bytes(magic) == SQUASHFS_MAGIC)
+	}
+
+	fn load_filesystem(
+		&self,
+		io: &mut dyn IO,
+		_mountpath: Path,
+		_readonly: bool,
+		_options: &MountOptions,
+	) -> Result<Arc<Mutex<dyn Filesystem>>, Errno> {
+		let sb = Superblock::read(io)?.ok_or_else(|| errno!(EINVAL))?;
+		let fs = SquashFs {
+			sb,
+
+			metadata_cache: None,
+			block_cache: None,
+
+			dirs: HashMap::new(),
+		};
+
+		Ok(Arc::new(Mutex::new(fs))? as _)
+	}
+}
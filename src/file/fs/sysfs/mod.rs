@@ -0,0 +1,625 @@
+//! The sysfs is a virtual filesystem which exposes the devices and buses of the system, allowing
+//! userspace to enumerate the hardware.
+//!
+//! The hierarchy is the following:
+//! - `block/<name>`: block devices, with their size and queue parameters
+//! - `bus/pci/devices/<address>`: links to the PCI devices
+//! - `dev/block/<major>:<minor>` and `dev/char/<major>:<minor>`: links to devices, by number
+//! - `devices/pci0000:00/<address>`: PCI devices, with their IDs
+//! - `devices/system/cpu/cpu<id>/cpuidle/state<index>`: the idle states of each CPU, with their
+//! statistics
+//! - `devices/virtual/<name>`: char devices
+//!
+//! Each device directory contains a `uevent` attribute describing it, in the format used by
+//! udev.
+//!
+//! PCI devices are listed when the filesystem is created. Device files are added and removed as
+//! devices are registered and unregistered.
+
+mod attr;
+
+use super::kernfs::node::DummyKernFSNode;
+use super::kernfs::node::KernFSNode;
+use super::kernfs::KernFS;
+use super::Filesystem;
+use super::FilesystemType;
+use super::MountOptions;
+use crate::cpu::idle;
+use crate::cpu::smp;
+use crate::device;
+use crate::device::bus::pci::PCIDevice;
+use crate::device::bus::pci::PCIManager;
+use crate::device::manager;
+use crate::device::manager::PhysicalDevice;
+use crate::device::DeviceID;
+use crate::device::DeviceType;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::Statfs;
+use crate::file::path::Path;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::DirEntry;
+use crate::file::File;
+use crate::file::FileContent;
+use crate::file::FileType;
+use crate::file::INode;
+use crate::file::Mode;
+use crate::util::boxed::Box;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use crate::util::TryClone;
+use attr::Attr;
+use core::any::Any;
+use core::num::NonZeroU64;
+
+/// The size of a sector in bytes, which is the unit of the `size` attribute of block devices.
+const SECTOR_SIZE: u64 = 512;
+
+/// The entries of a directory being built.
+type Entries = HashMap<String, DirEntry>;
+
+/// Returns an attribute with the fixed content `content`.
+fn static_attr(content: String) -> Attr<impl Fn() -> EResult<String>> {
+	Attr(move || Ok(content.try_clone()?))
+}
+
+/// Returns the size of a block of the device with ID `id`, in bytes.
+fn get_block_size(id: &DeviceID) -> EResult<u64> {
+	let dev = device::get(id).ok_or_else(|| errno!(ENODEV))?;
+	let mut dev = dev.lock();
+	let blk_size = dev.get_handle().get_block_size();
+	Ok(blk_size.map(NonZeroU64::get).unwrap_or(SECTOR_SIZE))
+}
+
+/// Structure representing the sysfs.
+///
+/// On the inside, the sysfs works using a kernfs.
+pub struct SysFS {
+	/// The kernfs.
+	fs: KernFS,
+
+	/// The inode of the `block` directory.
+	block_dir: INode,
+	/// The inode of the `dev/block` directory.
+	dev_block_dir: INode,
+	/// The inode of the `dev/char` directory.
+	dev_char_dir: INode,
+	/// The inode of the `devices/virtual` directory.
+	virtual_dir: INode,
+
+	/// The registered devices, with the name of their directory and the list of their nodes.
+	devices: HashMap<DeviceID, (String, Vec<INode>)>,
+}
+
+impl SysFS {
+	/// Creates a new instance.
+	///
+	/// `readonly` tells whether the filesystem is readonly.
+	pub fn new(readonly: bool) -> EResult<Self> {
+		let mut fs = KernFS::new(b"sysfs".try_into()?, readonly)?;
+
+		// Create /sys/bus/pci/devices and /sys/devices/pci0000:00
+		let mut pci_devices = HashMap::new();
+		let mut pci_root = HashMap::new();
+		if let Some(pci_manager) = manager::get::<PCIManager>() {
+			let pci_manager = pci_manager.lock();
+			let pci_manager = &*pci_manager as &dyn Any;
+			if let Some(pci_manager) = pci_manager.downcast_ref::<PCIManager>() {
+				for dev in pci_manager.get_devices().iter() {
+					Self::add_pci_device(&mut fs, &mut pci_root, &mut pci_devices, dev)?;
+				}
+			}
+		}
+		let pci_devices = Self::add_dir(&mut fs, pci_devices)?;
+		let pci_bus = Self::add_dir(
+			&mut fs,
+			Self::entries(b"devices", pci_devices, FileType::Directory)?,
+		)?;
+		let bus = Self::add_dir(
+			&mut fs,
+			Self::entries(b"pci", pci_bus, FileType::Directory)?,
+		)?;
+
+		let pci_root = Self::add_dir(&mut fs, pci_root)?;
+		let virtual_dir = Self::add_dir(&mut fs, HashMap::new())?;
+		let mut devices = Self::entries(b"pci0000:00", pci_root, FileType::Directory)?;
+		devices.insert(
+			b"virtual".try_into()?,
+			DirEntry {
+				inode: virtual_dir,
+				entry_type: FileType::Directory,
+			},
+		)?;
+		let cpu_dir = Self::add_cpus(&mut fs)?;
+		let system_dir = Self::add_dir(
+			&mut fs,
+			Self::entries(b"cpu", cpu_dir, FileType::Directory)?,
+		)?;
+		devices.insert(
+			b"system".try_into()?,
+			DirEntry {
+				inode: system_dir,
+				entry_type: FileType::Directory,
+			},
+		)?;
+		let devices = Self::add_dir(&mut fs, devices)?;
+
+		// Create /sys/dev
+		let dev_block_dir = Self::add_dir(&mut fs, HashMap::new())?;
+		let dev_char_dir = Self::add_dir(&mut fs, HashMap::new())?;
+		let mut dev = Self::entries(b"block", dev_block_dir, FileType::Directory)?;
+		dev.insert(
+			b"char".try_into()?,
+			DirEntry {
+				inode: dev_char_dir,
+				entry_type: FileType::Directory,
+			},
+		)?;
+		let dev = Self::add_dir(&mut fs, dev)?;
+
+		// Create /sys/block
+		let block_dir = Self::add_dir(&mut fs, HashMap::new())?;
+
+		// Add the root node
+		let mut entries = HashMap::new();
+		for (name, inode) in [
+			(b"block".as_slice(), block_dir),
+			(b"bus", bus),
+			(b"dev", dev),
+			(b"devices", devices),
+		] {
+			entries.insert(
+				name.try_into()?,
+				DirEntry {
+					inode,
+					entry_type: FileType::Directory,
+				},
+			)?;
+		}
+		let root_node = DummyKernFSNode::new(0o555, 0, 0, FileContent::Directory(entries));
+		fs.set_root(Box::new(root_node)?)?;
+
+		let mut fs = Self {
+			fs,
+
+			block_dir,
+			dev_block_dir,
+			dev_char_dir,
+			virtual_dir,
+
+			devices: HashMap::new(),
+		};
+
+		// Add existing devices
+		for (id, path) in device::list()? {
+			if let Some(name) = path.last() {
+				fs.add_device(&id, name.try_clone()?)?;
+			}
+		}
+
+		Ok(fs)
+	}
+
+	/// Returns directory entries containing the single entry `name`, pointing to `inode`.
+	fn entries(name: &[u8], inode: INode, entry_type: FileType) -> EResult<Entries> {
+		let mut entries = HashMap::new();
+		entries.insert(
+			name.try_into()?,
+			DirEntry {
+				inode,
+				entry_type,
+			},
+		)?;
+		Ok(entries)
+	}
+
+	/// Adds a directory with the given entries to `fs`, returning its inode.
+	fn add_dir(fs: &mut KernFS, entries: Entries) -> EResult<INode> {
+		let node = DummyKernFSNode::new(0o555, 0, 0, FileContent::Directory(entries));
+		fs.add_node(Box::new(node)?)
+	}
+
+	/// Adds the node `node` to `fs`, and the entry `name` pointing to it in `entries`.
+	///
+	/// The function returns the inode of the node.
+	fn add_entry<N: KernFSNode>(
+		fs: &mut KernFS,
+		entries: &mut Entries,
+		name: &[u8],
+		mut node: N,
+	) -> EResult<INode> {
+		let entry_type = node.get_content()?.as_type();
+		let inode = fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			name.try_into()?,
+			DirEntry {
+				inode,
+				entry_type,
+			},
+		)?;
+		Ok(inode)
+	}
+
+	/// Returns a symbolic link node pointing to `target`.
+	fn link(target: String) -> DummyKernFSNode {
+		DummyKernFSNode::new(0o777, 0, 0, FileContent::Link(target))
+	}
+
+	/// Inserts the entry `name` pointing to `inode` in the directory `dir`.
+	fn insert_entry(
+		&mut self,
+		dir: INode,
+		name: String,
+		inode: INode,
+		entry_type: FileType,
+	) -> EResult<()> {
+		let node = self.fs.get_node_mut(dir)?;
+		let mut content = node.get_content()?;
+		let FileContent::Directory(entries) = &mut *content else {
+			return Err(errno!(ENOTDIR));
+		};
+		entries.insert(
+			name,
+			DirEntry {
+				inode,
+				entry_type,
+			},
+		)?;
+		Ok(())
+	}
+
+	/// Removes the entry `name` from the directory `dir`.
+	fn remove_entry(&mut self, dir: INode, name: &[u8]) -> EResult<()> {
+		let node = self.fs.get_node_mut(dir)?;
+		let mut content = node.get_content()?;
+		let FileContent::Directory(entries) = &mut *content else {
+			return Err(errno!(ENOTDIR));
+		};
+		entries.remove(name);
+		Ok(())
+	}
+
+	/// Adds the directory of each CPU to `fs`, with its idle states, then returns the inode of
+	/// the directory containing them.
+	fn add_cpus(fs: &mut KernFS) -> EResult<INode> {
+		let mut cpus = HashMap::new();
+		for id in 0..smp::get_cpus_count() {
+			let mut states = HashMap::new();
+			for (i, (index, state)) in idle::iter_states().enumerate() {
+				let mut entries = HashMap::new();
+				let name = crate::format!("{}\n", state.name)?;
+				Self::add_entry(fs, &mut entries, b"name", static_attr(name))?;
+				let desc = crate::format!("{}\n", state.desc)?;
+				Self::add_entry(fs, &mut entries, b"desc", static_attr(desc))?;
+				let usage = Attr(move || {
+					let usage = idle::get_stats(id, index).usage;
+					Ok(crate::format!("{usage}\n")?)
+				});
+				Self::add_entry(fs, &mut entries, b"usage", usage)?;
+				// The time is in microseconds
+				let time = Attr(move || {
+					let time = idle::get_stats(id, index).time / 1000;
+					Ok(crate::format!("{time}\n")?)
+				});
+				Self::add_entry(fs, &mut entries, b"time", time)?;
+				let state_dir = Self::add_dir(fs, entries)?;
+				states.insert(
+					crate::format!("state{i}")?,
+					DirEntry {
+						inode: state_dir,
+						entry_type: FileType::Directory,
+					},
+				)?;
+			}
+			let cpuidle = Self::add_dir(fs, states)?;
+			let cpu_dir =
+				Self::add_dir(fs, Self::entries(b"cpuidle", cpuidle, FileType::Directory)?)?;
+			cpus.insert(
+				crate::format!("cpu{id}")?,
+				DirEntry {
+					inode: cpu_dir,
+					entry_type: FileType::Directory,
+				},
+			)?;
+		}
+		Self::add_dir(fs, cpus)
+	}
+
+	/// Adds the directory of the PCI device `dev`.
+	///
+	/// Arguments:
+	/// - `fs` is the kernfs
+	/// - `pci_root` is the directory of the PCI root bridge, in which the device's directory is
+	/// inserted
+	/// - `pci_devices` is the directory in which the link to the device's directory is inserted
+	fn add_pci_device(
+		fs: &mut KernFS,
+		pci_root: &mut Entries,
+		pci_devices: &mut Entries,
+		dev: &PCIDevice,
+	) -> EResult<()> {
+		let addr = crate::format!(
+			"0000:{:02x}:{:02x}.{:x}",
+			dev.get_bus(),
+			dev.get_device(),
+			dev.get_function()
+		)?;
+		let vendor = dev.get_vendor_id();
+		let device = dev.get_device_id();
+		let class = ((dev.get_class() as u32) << 16)
+			| ((dev.get_subclass() as u32) << 8)
+			| dev.get_prog_if() as u32;
+
+		let mut entries = HashMap::new();
+		let attrs = [
+			(b"vendor".as_slice(), crate::format!("0x{vendor:04x}\n")?),
+			(b"device", crate::format!("0x{device:04x}\n")?),
+			(b"class", crate::format!("0x{class:06x}\n")?),
+			(
+				b"irq",
+				crate::format!("{}\n", dev.get_interrupt_line().unwrap_or(0))?,
+			),
+			(
+				b"uevent",
+				crate::format!(
+					"PCI_CLASS={class:X}\nPCI_ID={vendor:04X}:{device:04X}\nPCI_SLOT_NAME={addr}\n"
+				)?,
+			),
+		];
+		for (name, content) in attrs {
+			Self::add_entry(fs, &mut entries, name, static_attr(content))?;
+		}
+		let inode = Self::add_dir(fs, entries)?;
+		pci_root.insert(
+			addr.try_clone()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Directory,
+			},
+		)?;
+
+		let target = crate::format!("../../../devices/pci0000:00/{addr}")?;
+		Self::add_entry(fs, pci_devices, addr.as_bytes(), Self::link(target))?;
+		Ok(())
+	}
+
+	/// Adds the directory of the device with ID `id`, with the name `name`.
+	///
+	/// If the device is already present, the function does nothing.
+	pub fn add_device(&mut self, id: &DeviceID, name: String) -> EResult<()> {
+		if self.devices.contains_key(id) {
+			return Ok(());
+		}
+
+		let (major, minor) = (id.major, id.minor);
+		let mut nodes = Vec::new();
+		let mut entries = HashMap::new();
+
+		let content = crate::format!("{major}:{minor}\n")?;
+		nodes.push(Self::add_entry(
+			&mut self.fs,
+			&mut entries,
+			b"dev",
+			static_attr(content),
+		)?)?;
+
+		let (devtype, parent_dir, dev_dir, target) = match id.type_ {
+			DeviceType::Block => {
+				// Create the `size` attribute, in sectors
+				let dev_id = id.clone();
+				let size = Attr(move || {
+					let dev = device::get(&dev_id).ok_or_else(|| errno!(ENODEV))?;
+					let size = dev.lock().get_size() / SECTOR_SIZE;
+					Ok(crate::format!("{size}\n")?)
+				});
+				nodes.push(Self::add_entry(&mut self.fs, &mut entries, b"size", size)?)?;
+
+				// Create the `queue` directory
+				let mut queue = HashMap::new();
+				for name in [
+					b"hw_sector_size".as_slice(),
+					b"logical_block_size",
+					b"physical_block_size",
+				] {
+					let dev_id = id.clone();
+					let attr = Attr(move || Ok(crate::format!("{}\n", get_block_size(&dev_id)?)?));
+					nodes.push(Self::add_entry(&mut self.fs, &mut queue, name, attr)?)?;
+				}
+				let rotational = static_attr(String::try_from(b"0\n")?);
+				nodes.push(Self::add_entry(
+					&mut self.fs,
+					&mut queue,
+					b"rotational",
+					rotational,
+				)?)?;
+				let queue = Self::add_dir(&mut self.fs, queue)?;
+				nodes.push(queue)?;
+				entries.insert(
+					b"queue".try_into()?,
+					DirEntry {
+						inode: queue,
+						entry_type: FileType::Directory,
+					},
+				)?;
+
+				(
+					"disk",
+					self.block_dir,
+					self.dev_block_dir,
+					crate::format!("../../block/{name}")?,
+				)
+			}
+
+			DeviceType::Char => (
+				"char",
+				self.virtual_dir,
+				self.dev_char_dir,
+				crate::format!("../../devices/virtual/{name}")?,
+			),
+		};
+
+		let uevent =
+			crate::format!("MAJOR={major}\nMINOR={minor}\nDEVNAME={name}\nDEVTYPE={devtype}\n")?;
+		nodes.push(Self::add_entry(
+			&mut self.fs,
+			&mut entries,
+			b"uevent",
+			static_attr(uevent),
+		)?)?;
+
+		// Insert the device's directory
+		let dir = Self::add_dir(&mut self.fs, entries)?;
+		nodes.push(dir)?;
+		self.insert_entry(parent_dir, name.try_clone()?, dir, FileType::Directory)?;
+
+		// Insert the link in `dev`
+		let link = self.fs.add_node(Box::new(Self::link(target))?)?;
+		nodes.push(link)?;
+		let link_name = crate::format!("{major}:{minor}")?;
+		self.insert_entry(dev_dir, link_name, link, FileType::Link)?;
+
+		self.devices.insert(id.clone(), (name, nodes))?;
+		Ok(())
+	}
+
+	/// Removes the directory of the device with ID `id`.
+	///
+	/// If the device is not present, the function does nothing.
+	pub fn remove_device(&mut self, id: &DeviceID) -> EResult<()> {
+		let Some((name, nodes)) = self.devices.remove(id) else {
+			return Ok(());
+		};
+
+		let (parent_dir, dev_dir) = match id.type_ {
+			DeviceType::Block => (self.block_dir, self.dev_block_dir),
+			DeviceType::Char => (self.virtual_dir, self.dev_char_dir),
+		};
+		self.remove_entry(parent_dir, name.as_bytes())?;
+		let link_name = crate::format!("{}:{}", id.major, id.minor)?;
+		self.remove_entry(dev_dir, link_name.as_bytes())?;
+
+		for inode in nodes {
+			self.fs.remove_node(inode)?;
+		}
+		Ok(())
+	}
+}
+
+impl Filesystem for SysFS {
+	fn get_name(&self) -> &[u8] {
+		self.fs.get_name()
+	}
+
+	fn is_readonly(&self) -> bool {
+		self.fs.is_readonly()
+	}
+
+	fn must_cache(&self) -> bool {
+		self.fs.must_cache()
+	}
+
+	fn get_stat(&self, io: &mut dyn IO) -> Result<Statfs, Errno> {
+		self.fs.get_stat(io)
+	}
+
+	fn get_root_inode(&self, io: &mut dyn IO) -> Result<INode, Errno> {
+		self.fs.get_root_inode(io)
+	}
+
+	fn get_inode(
+		&mut self,
+		io: &mut dyn IO,
+		parent: Option<INode>,
+		name: &[u8],
+	) -> Result<INode, Errno> {
+		self.fs.get_inode(io, parent, name)
+	}
+
+	fn load_file(&mut self, io: &mut dyn IO, inode: INode, name: String) -> Result<File, Errno> {
+		self.fs.load_file(io, inode, name)
+	}
+
+	fn add_file(
+		&mut self,
+		_io: &mut dyn IO,
+		_parent_inode: INode,
+		_name: String,
+		_uid: Uid,
+		_gid: Gid,
+		_mode: Mode,
+		_content: FileContent,
+	) -> Result<File, Errno> {
+		Err(errno!(EACCES))
+	}
+
+	fn add_link(
+		&mut self,
+		_io: &mut dyn IO,
+		_parent_inode: INode,
+		_name: &[u8],
+		_inode: INode,
+	) -> Result<(), Errno> {
+		Err(errno!(EACCES))
+	}
+
+	fn update_inode(&mut self, _io: &mut dyn IO, _file: &File) -> Result<(), Errno> {
+		Ok(())
+	}
+
+	fn remove_file(
+		&mut self,
+		_io: &mut dyn IO,
+		_parent_inode: INode,
+		_name: &[u8],
+	) -> Result<u16, Errno> {
+		Err(errno!(EACCES))
+	}
+
+	fn read_node(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		buf: &mut [u8],
+	) -> Result<u64, Errno> {
+		self.fs.read_node(io, inode, off, buf)
+	}
+
+	fn write_node(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		buf: &[u8],
+	) -> Result<(), Errno> {
+		self.fs.write_node(io, inode, off, buf)
+	}
+}
+
+/// Structure representing the sysfs file system type.
+pub struct SysFsType {}
+
+impl FilesystemType for SysFsType {
+	fn get_name(&self) -> &'static [u8] {
+		b"sysfs"
+	}
+
+	fn detect(&self, _io: &mut dyn IO) -> Result<bool, Errno> {
+		Ok(false)
+	}
+
+	fn load_filesystem(
+		&self,
+		_io: &mut dyn IO,
+		_mountpath: Path,
+		readonly: bool,
+		_options: &MountOptions,
+	) -> Result<Arc<Mutex<dyn Filesystem>>, Errno> {
+		Ok(Arc::new(Mutex::new(SysFS::new(readonly)?))?)
+	}
+}
@@ -2,6 +2,12 @@
 //!
 //! The files are stored on the kernel's memory and thus are removed when the
 //! filesystem is unmounted.
+//!
+//! Mount options are:
+//! - `size`: the maximum amount of memory the filesystem can use in bytes, optionally followed
+//! by the suffix `k`, `m` or `g`
+//! - `mode`: the permissions of the root directory, in octal
+//! - `uid` and `gid`: the owner of the root directory
 
 mod node;
 
@@ -9,6 +15,8 @@ use super::kernfs::node::KernFSNode;
 use super::kernfs::KernFS;
 use super::Filesystem;
 use super::FilesystemType;
+use super::MountOptionDesc;
+use super::MountOptions;
 use crate::errno;
 use crate::file::fs::kernfs::node::DummyKernFSNode;
 use crate::file::fs::Statfs;
@@ -31,6 +39,8 @@ use node::TmpFSRegular;
 
 /// The default maximum amount of memory the filesystem can use in bytes.
 const DEFAULT_MAX_SIZE: usize = 512 * 1024 * 1024;
+/// The default mode of the root directory.
+const DEFAULT_ROOT_MODE: Mode = 0o777;
 
 /// Returns the size in bytes used by the given node `node`.
 fn get_used_size<N: KernFSNode>(node: &N) -> usize {
@@ -55,8 +65,15 @@ impl TmpFS {
 	///
 	/// Arguments:
 	/// - `max_size` is the maximum amount of memory the filesystem can use in bytes.
+	/// - `mode`, `uid` and `gid` are the permissions and owner of the root directory.
 	/// - `readonly` tells whether the filesystem is readonly.
-	pub fn new(max_size: usize, readonly: bool) -> Result<Self, Errno> {
+	pub fn new(
+		max_size: usize,
+		mode: Mode,
+		uid: Uid,
+		gid: Gid,
+		readonly: bool,
+	) -> Result<Self, Errno> {
 		let mut fs = Self {
 			max_size,
 			size: 0,
@@ -65,7 +82,8 @@ impl TmpFS {
 		};
 
 		// Adding the root node
-		let root_node = DummyKernFSNode::new(0o777, 0, 0, FileContent::Directory(HashMap::new()));
+		let root_node =
+			DummyKernFSNode::new(mode, uid, gid, FileContent::Directory(HashMap::new()));
 		fs.update_size(get_used_size(&root_node) as _, |fs| {
 			fs.fs.set_root(Box::new(root_node)?)?;
 			Ok(())
@@ -228,15 +246,53 @@ impl FilesystemType for TmpFsType {
 		Ok(false)
 	}
 
+	fn get_mount_options(&self) -> &'static [MountOptionDesc] {
+		&[
+			MountOptionDesc {
+				name: b"size",
+				has_value: true,
+			},
+			MountOptionDesc {
+				name: b"mode",
+				has_value: true,
+			},
+			MountOptionDesc {
+				name: b"uid",
+				has_value: true,
+			},
+			MountOptionDesc {
+				name: b"gid",
+				has_value: true,
+			},
+		]
+	}
+
 	fn load_filesystem(
 		&self,
 		_io: &mut dyn IO,
 		_mountpath: Path,
 		readonly: bool,
+		options: &MountOptions,
 	) -> Result<Arc<Mutex<dyn Filesystem>>, Errno> {
+		let max_size = match options.get_size(b"size")? {
+			Some(size) => size.try_into().map_err(|_| errno!(EINVAL))?,
+			None => DEFAULT_MAX_SIZE,
+		};
+		let mode = match options.get_number(b"mode", 8)? {
+			Some(mode) if mode <= 0o7777 => mode as _,
+			Some(_) => return Err(errno!(EINVAL)),
+			None => DEFAULT_ROOT_MODE,
+		};
+		let uid = match options.get_number(b"uid", 10)? {
+			Some(uid) => uid.try_into().map_err(|_| errno!(EINVAL))?,
+			None => 0,
+		};
+		let gid = match options.get_number(b"gid", 10)? {
+			Some(gid) => gid.try_into().map_err(|_| errno!(EINVAL))?,
+			None => 0,
+		};
 		Ok(Arc::new(Mutex::new(TmpFS::new(
-			DEFAULT_MAX_SIZE,
-			readonly,
+			max_size, mode, uid, gid, readonly,
 		)?))?)
 	}
 }
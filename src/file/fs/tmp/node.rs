@@ -9,9 +9,8 @@ use crate::file::perm::Uid;
 use crate::file::FileContent;
 use crate::file::Mode;
 use crate::time::clock;
-use crate::time::clock::CLOCK_MONOTONIC;
-use crate::time::unit::Timestamp;
-use crate::time::unit::TimestampScale;
+use crate::time::clock::CLOCK_REALTIME;
+use crate::time::unit::Timespec;
 use crate::util::container::vec::Vec;
 use crate::util::io::IO;
 use core::cmp::max;
@@ -30,11 +29,11 @@ pub struct TmpFSRegular {
 	gid: Gid,
 
 	/// Timestamp of the last modification of the metadata.
-	ctime: Timestamp,
+	ctime: Timespec,
 	/// Timestamp of the last modification of the file.
-	mtime: Timestamp,
+	mtime: Timespec,
 	/// Timestamp of the last access to the file.
-	atime: Timestamp,
+	atime: Timespec,
 
 	/// The content of the file.
 	content: Vec<u8>,
@@ -44,7 +43,7 @@ impl TmpFSRegular {
 	/// Creates a new instance.
 	pub fn new(mode: Mode, uid: Uid, gid: Gid) -> Self {
 		// The current timestamp
-		let ts = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Second).unwrap_or(0);
+		let ts = clock::current_time_struct::<Timespec>(CLOCK_REALTIME).unwrap_or_default();
 
 		Self {
 			hard_links_count: 1,
@@ -95,27 +94,27 @@ impl KernFSNode for TmpFSRegular {
 		self.gid = gid;
 	}
 
-	fn get_atime(&self) -> Timestamp {
+	fn get_atime(&self) -> Timespec {
 		self.atime
 	}
 
-	fn set_atime(&mut self, ts: Timestamp) {
+	fn set_atime(&mut self, ts: Timespec) {
 		self.atime = ts;
 	}
 
-	fn get_ctime(&self) -> Timestamp {
+	fn get_ctime(&self) -> Timespec {
 		self.ctime
 	}
 
-	fn set_ctime(&mut self, ts: Timestamp) {
+	fn set_ctime(&mut self, ts: Timespec) {
 		self.ctime = ts;
 	}
 
-	fn get_mtime(&self) -> Timestamp {
+	fn get_mtime(&self) -> Timespec {
 		self.mtime
 	}
 
-	fn set_mtime(&mut self, ts: Timestamp) {
+	fn set_mtime(&mut self, ts: Timespec) {
 		self.mtime = ts;
 	}
 
@@ -0,0 +1,62 @@
+//! The inode cache (icache) keeps the files loaded from filesystems, so that opening or
+//! retrieving the status of the same file repeatedly doesn't require reading its inode, and the
+//! entries of directories, each time.
+//!
+//! The cache holds copies of the files as they are stored on the filesystem. An entry must be
+//! invalidated each time the associated inode is modified.
+//!
+//! Like the [`dcache`](super::dcache), the cache is used only for filesystems that require
+//! caching.
+
+use crate::errno::EResult;
+use crate::file::File;
+use crate::file::FileLocation;
+use crate::util::container::lru::LruCache;
+use crate::util::container::string::String;
+use crate::util::lock::Mutex;
+use crate::util::TryClone;
+
+/// The maximum number of files in the cache.
+const CAPACITY: usize = 1024;
+
+/// The cache, by location of the file.
+static ICACHE: Mutex<LruCache<FileLocation, File>> = Mutex::new(LruCache::new(CAPACITY));
+
+/// Returns a copy of the file at location `location`, with the name `name`.
+///
+/// If the file is not in the cache, the function returns `None`.
+pub fn get(location: &FileLocation, name: String) -> EResult<Option<File>> {
+	let mut icache = ICACHE.lock();
+	let Some(file) = icache.get(location) else {
+		return Ok(None);
+	};
+	let mut file = file.try_clone()?;
+	file.name = name;
+	Ok(Some(file))
+}
+
+/// Inserts a copy of the file `file` in the cache.
+pub fn insert(file: &File) -> EResult<()> {
+	let file = file.try_clone()?;
+	ICACHE.lock().insert(file.location.clone(), file)?;
+	Ok(())
+}
+
+/// Removes the file at location `location` from the cache.
+pub fn invalidate(location: &FileLocation) {
+	ICACHE.lock().remove(location);
+}
+
+/// Removes every files of the mountpoint with ID `mountpoint_id` from the cache.
+pub fn invalidate_mountpoint(mountpoint_id: u32) {
+	ICACHE
+		.lock()
+		.retain(|loc, _| loc.get_mountpoint_id() != Some(mountpoint_id));
+}
+
+/// Evicts at most `count` files from the cache.
+///
+/// The function returns the number of evicted files.
+pub fn shrink(count: usize) -> usize {
+	ICACHE.lock().shrink(count)
+}
@@ -6,17 +6,25 @@
 //! The root filesystem is passed to the kernel as an argument on boot.
 //! Other filesystems are mounted into subdirectories.
 
+pub mod aio;
 pub mod blocking;
 pub mod buffer;
+pub mod dcache;
 pub mod fd;
+pub mod flock;
 pub mod fs;
-pub mod mapping;
+pub mod icache;
 pub mod mountpoint;
 pub mod open_file;
+pub mod page_cache;
 pub mod path;
 pub mod perm;
+pub mod readahead;
+pub mod record_lock;
 pub mod util;
 pub mod vfs;
+pub mod writeback;
+pub mod xattr;
 
 use crate::device;
 use crate::device::DeviceID;
@@ -24,19 +32,22 @@ use crate::device::DeviceType;
 use crate::errno;
 use crate::errno::EResult;
 use crate::errno::Errno;
+use crate::file::buffer::memfd;
 use crate::file::buffer::pipe::PipeBuffer;
 use crate::file::buffer::socket::Socket;
 use crate::file::fs::Filesystem;
 use crate::file::perm::Gid;
 use crate::file::perm::Uid;
+use crate::memory::slab;
+use crate::memory::slab::SlabCache;
 use crate::process::mem_space::MemSpace;
 use crate::syscall::ioctl;
 use crate::time::clock;
-use crate::time::clock::CLOCK_MONOTONIC;
-use crate::time::unit::Timestamp;
-use crate::time::unit::TimestampScale;
+use crate::time::clock::CLOCK_REALTIME;
+use crate::time::unit::Timespec;
 use crate::util::container::hashmap::HashMap;
 use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
 use crate::util::io::IO;
 use crate::util::lock::IntMutex;
 use crate::util::lock::Mutex;
@@ -191,6 +202,15 @@ impl FileLocation {
 		mountpoint::from_id(self.get_mountpoint_id()?)
 	}
 
+	/// Returns the flags of the mountpoint.
+	///
+	/// If the location is not on a mountpoint, the function returns `0`.
+	pub fn get_mount_flags(&self) -> u32 {
+		self.get_mountpoint()
+			.map(|mp| mp.lock().get_flags())
+			.unwrap_or(0)
+	}
+
 	/// Returns the inode.
 	pub fn get_inode(&self) -> INode {
 		match self {
@@ -285,6 +305,13 @@ impl TryClone for FileContent {
 	}
 }
 
+/// The slab cache of files, as shared between their users.
+pub static FILE_CACHE: SlabCache = SlabCache::new(
+	"inode_cache",
+	Arc::<Mutex<File>>::inner_size(),
+	Some(slab::zero_ctor::<{ Arc::<Mutex<File>>::inner_size() }>),
+);
+
 /// Structure representing a file.
 #[derive(Debug)]
 pub struct File {
@@ -309,11 +336,11 @@ pub struct File {
 	mode: Mode,
 
 	/// Timestamp of the last modification of the metadata.
-	pub ctime: Timestamp,
+	pub ctime: Timespec,
 	/// Timestamp of the last modification of the file's content.
-	pub mtime: Timestamp,
+	pub mtime: Timespec,
 	/// Timestamp of the last access to the file.
-	pub atime: Timestamp,
+	pub atime: Timespec,
 
 	/// The location the file is stored on.
 	location: FileLocation,
@@ -346,7 +373,7 @@ impl File {
 		location: FileLocation,
 		content: FileContent,
 	) -> Result<Self, Errno> {
-		let timestamp = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Second).unwrap_or(0);
+		let timestamp = clock::current_time_struct::<Timespec>(CLOCK_REALTIME).unwrap_or_default();
 
 		Ok(Self {
 			name,
@@ -414,7 +441,7 @@ impl File {
 	pub fn set_permissions(&mut self, mode: Mode) {
 		self.mode = mode & 0o7777;
 
-		let timestamp = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Second).unwrap_or(0);
+		let timestamp = clock::current_time_struct::<Timespec>(CLOCK_REALTIME).unwrap_or_default();
 		self.ctime = timestamp;
 	}
 
@@ -433,7 +460,7 @@ impl File {
 	pub fn set_hard_links_count(&mut self, count: u16) {
 		self.hard_links_count = count;
 
-		let timestamp = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Second).unwrap_or(0);
+		let timestamp = clock::current_time_struct::<Timespec>(CLOCK_REALTIME).unwrap_or_default();
 		self.ctime = timestamp;
 	}
 
@@ -451,7 +478,7 @@ impl File {
 	pub fn set_uid(&mut self, uid: Uid) {
 		self.uid = uid;
 
-		let timestamp = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Second).unwrap_or(0);
+		let timestamp = clock::current_time_struct::<Timespec>(CLOCK_REALTIME).unwrap_or_default();
 		self.ctime = timestamp;
 	}
 
@@ -464,19 +491,93 @@ impl File {
 	pub fn set_gid(&mut self, gid: Gid) {
 		self.gid = gid;
 
-		let timestamp = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Second).unwrap_or(0);
+		let timestamp = clock::current_time_struct::<Timespec>(CLOCK_REALTIME).unwrap_or_default();
 		self.ctime = timestamp;
 	}
 
-	/// Tells whether the directory is empty or not.
+	/// Iterates over the entries of the directory, starting at offset `off`.
+	///
+	/// For each entry, `f` is called with its name, the entry itself and the offset of the next
+	/// entry. If it returns `false`, the iteration stops.
+	///
+	/// Offsets are opaque values defined by the filesystem, `0` being the beginning of the
+	/// directory.
+	///
+	/// Since the filesystem is locked during the iteration, `f` must not access it.
 	///
 	/// If the current file isn't a directory, the function returns an error.
-	pub fn is_empty_directory(&self) -> Result<bool, Errno> {
-		if let FileContent::Directory(entries) = &self.content {
-			Ok(entries.is_empty())
-		} else {
-			Err(errno!(ENOTDIR))
+	pub fn iter_entries<F>(&self, off: u64, mut f: F) -> EResult<()>
+	where
+		F: FnMut(&[u8], &DirEntry, u64) -> EResult<bool>,
+	{
+		if self.get_type() != FileType::Directory {
+			return Err(errno!(ENOTDIR));
 		}
+		let Some(mountpoint_mutex) = self.location.get_mountpoint() else {
+			// The file is not on a filesystem, so its entries are in memory
+			let FileContent::Directory(entries) = &self.content else {
+				return Err(errno!(ENOTDIR));
+			};
+			for (i, (name, entry)) in entries.iter().enumerate().skip(off as _) {
+				if !f(name.as_bytes(), entry, i as u64 + 1)? {
+					break;
+				}
+			}
+			return Ok(());
+		};
+		let mountpoint = mountpoint_mutex.lock();
+
+		let io_mutex = mountpoint.get_source().get_io()?;
+		let mut io = io_mutex.lock();
+
+		let fs_mutex = mountpoint.get_filesystem();
+		let mut fs = fs_mutex.lock();
+
+		fs.iter_entries(&mut *io, self.location.get_inode(), off, &mut f)
+	}
+
+	/// Returns the names of the entries of the directory, except `.` and `..`.
+	///
+	/// If the current file isn't a directory, the function returns an error.
+	pub fn get_entry_names(&self) -> EResult<Vec<String>> {
+		let mut names = Vec::new();
+		self.iter_entries(0, |name, _, _| {
+			if !matches!(name, b"." | b"..") {
+				names.push(String::try_from(name)?)?;
+			}
+			Ok(true)
+		})?;
+		Ok(names)
+	}
+
+	/// Tells whether the directory is empty or not, ignoring the `.` and `..` entries.
+	///
+	/// The filesystem may consider a directory empty even if it has entries (see
+	/// [`Filesystem::is_empty_dir`]).
+	///
+	/// If the current file isn't a directory, the function returns an error.
+	pub fn is_empty_directory(&self) -> EResult<bool> {
+		if let Some(mountpoint_mutex) = self.location.get_mountpoint() {
+			if self.get_type() != FileType::Directory {
+				return Err(errno!(ENOTDIR));
+			}
+			let mountpoint = mountpoint_mutex.lock();
+
+			let io_mutex = mountpoint.get_source().get_io()?;
+			let mut io = io_mutex.lock();
+
+			let fs_mutex = mountpoint.get_filesystem();
+			let mut fs = fs_mutex.lock();
+
+			return fs.is_empty_dir(&mut *io, self.location.get_inode());
+		}
+
+		let mut empty = true;
+		self.iter_entries(0, |name, _, _| {
+			empty = matches!(name, b"." | b"..");
+			Ok(empty)
+		})?;
+		Ok(empty)
 	}
 
 	/// Adds the directory entry `entry` to the current directory's entries.
@@ -598,12 +699,187 @@ impl File {
 			let fs_mutex = mountpoint.get_filesystem();
 			let mut fs = fs_mutex.lock();
 
-			fs.update_inode(&mut *io, self)
+			fs.update_inode(&mut *io, self)?;
+			icache::invalidate(&self.location);
+			Ok(())
 		} else {
 			Ok(())
 		}
 	}
 
+	/// Checks the file can be modified on its mountpoint.
+	///
+	/// If the mountpoint is read-only, the function returns `EROFS`.
+	pub fn check_mount_writable(&self) -> EResult<()> {
+		if self.location.get_mount_flags() & mountpoint::FLAG_RDONLY != 0 {
+			return Err(errno!(EROFS));
+		}
+		Ok(())
+	}
+
+	/// Tells whether the mountpoint of the file allows executing it.
+	pub fn is_mount_executable(&self) -> bool {
+		self.location.get_mount_flags() & mountpoint::FLAG_NOEXEC == 0
+	}
+
+	/// Tells whether the content of the file goes through the page cache.
+	///
+	/// This is the case for regular files, unless the filesystem doesn't require caching (for
+	/// example, if the content of files is generated on the fly). Even then, files that are
+	/// mapped in memory remain in the cache so that mappings and I/O stay consistent.
+	pub fn is_cached(&self) -> bool {
+		if !matches!(self.content, FileContent::Regular) {
+			return false;
+		}
+		let must_cache = self
+			.location
+			.get_mountpoint()
+			.map(|mp| mp.lock().get_filesystem().lock().must_cache())
+			.unwrap_or(true);
+		must_cache || page_cache::contains(&self.location)
+	}
+
+	/// Reads the content of the file at offset `off` directly from its storage, bypassing the
+	/// page cache.
+	fn read_raw(&mut self, off: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		self.io_op(|io, fs| {
+			let Some(io_mutex) = io else {
+				return Ok((0, true));
+			};
+			let mut io = io_mutex.lock();
+
+			if let Some((fs_mutex, inode)) = fs {
+				let mut fs = fs_mutex.lock();
+				let len = fs.read_node(&mut *io, inode, off, buff)?;
+				let eof = off + len >= self.size;
+				Ok((len, eof))
+			} else {
+				io.read(off, buff)
+			}
+		})
+	}
+
+	/// Writes the buffer `buff` at offset `off` directly to the storage of the file, bypassing
+	/// the page cache.
+	fn write_raw(&mut self, off: u64, buff: &[u8]) -> Result<u64, Errno> {
+		let len = self.io_op(|io, fs| {
+			let Some(io_mutex) = io else {
+				return Ok(0);
+			};
+			let mut io = io_mutex.lock();
+
+			if let Some((fs_mutex, inode)) = fs {
+				let mut fs = fs_mutex.lock();
+				fs.write_node(&mut *io, inode, off, buff)?;
+				Ok(buff.len() as _)
+			} else {
+				io.write(off, buff)
+			}
+		})?;
+		// Update file's size
+		self.size = max(off + len, self.size);
+		icache::invalidate(&self.location);
+		Ok(len)
+	}
+
+	/// Allocates storage for the content of the file in the range of `len` bytes beginning at
+	/// offset `off`.
+	///
+	/// If `keep_size` is not set and the range extends past the end of the file, the file is
+	/// extended to the end of the range.
+	///
+	/// Files that are not stored on a filesystem need no allocation.
+	pub fn allocate(&mut self, off: u64, len: u64, keep_size: bool) -> EResult<()> {
+		let end = off.checked_add(len).ok_or_else(|| errno!(EFBIG))?;
+		let size = self.get_size();
+		let grow = !keep_size && end > size;
+		if grow {
+			memfd::check_write(&self.location, end, size)?;
+		}
+
+		self.io_op(|io, fs| {
+			let (Some(io_mutex), Some((fs_mutex, inode))) = (io, fs) else {
+				return Ok(());
+			};
+			let mut io = io_mutex.lock();
+			let mut fs = fs_mutex.lock();
+			fs.allocate(&mut *io, inode, off, len)
+		})?;
+		if grow {
+			page_cache::truncate(self, end);
+		}
+		icache::invalidate(&self.location);
+		Ok(())
+	}
+
+	/// Deallocates the content of the file in the range of `len` bytes beginning at offset `off`,
+	/// so that it is read as zeros.
+	///
+	/// The size of the file is not changed.
+	pub fn punch_hole(&mut self, off: u64, len: u64) -> EResult<()> {
+		memfd::check_write(&self.location, 0, 0)?;
+
+		// Pending modifications in the range must reach the filesystem before the hole is made
+		page_cache::sync(self)?;
+		self.io_op(|io, fs| {
+			let (Some(io_mutex), Some((fs_mutex, inode))) = (io, fs) else {
+				return Ok(());
+			};
+			let mut io = io_mutex.lock();
+			let mut fs = fs_mutex.lock();
+			fs.punch_hole(&mut *io, inode, off, len)
+		})?;
+		page_cache::punch_hole(self, off, len);
+		icache::invalidate(&self.location);
+		Ok(())
+	}
+
+	/// Sets the size of the file to `size` bytes.
+	///
+	/// If the file is extended, the new content is read as zeros.
+	///
+	/// If the seals of the file forbid the change, the function returns
+	/// [`crate::errno::EPERM`].
+	pub fn truncate(&mut self, size: u64) -> EResult<()> {
+		memfd::check_resize(&self.location, size, self.get_size())?;
+		page_cache::truncate(self, size);
+		icache::invalidate(&self.location);
+		Ok(())
+	}
+
+	/// Returns the offset of the beginning of the next region of data of the file, or of the next
+	/// hole if `hole` is set, at or after offset `off`.
+	///
+	/// The end of the file is considered a hole. If `off` is past the end of the file or if no
+	/// data follows it, the function returns [`errno::ENXIO`].
+	pub fn next_extent(&mut self, off: u64, hole: bool) -> EResult<u64> {
+		let size = self.get_size();
+		if off >= size {
+			return Err(errno!(ENXIO));
+		}
+
+		let next = if matches!(self.content, FileContent::Regular) {
+			// Data that has not been written back yet must be visible to the filesystem
+			page_cache::sync(self)?;
+			self.io_op(|io, fs| match (io, fs) {
+				(Some(io_mutex), Some((fs_mutex, inode))) => {
+					let mut io = io_mutex.lock();
+					let mut fs = fs_mutex.lock();
+					fs.next_extent(&mut *io, inode, off, hole)
+				}
+				// The cache is the only storage of the file
+				_ => Ok(page_cache::next_extent(&self.location, off, hole)),
+			})?
+		} else {
+			(!hole).then_some(off)
+		};
+		match next {
+			Some(next) if next < size => Ok(next),
+			_ if hole => Ok(size),
+			_ => Err(errno!(ENXIO)),
+		}
+	}
+
 	/// Wrapper for I/O operations on files.
 	///
 	/// For the current file, the function takes a closure which provides the following arguments:
@@ -703,6 +979,37 @@ impl File {
 	}
 }
 
+impl TryClone for File {
+	/// Clones the file's metadata and content.
+	///
+	/// The clone does not inherit a deferred removal.
+	fn try_clone(&self) -> Result<Self, Self::Error> {
+		Ok(Self {
+			name: self.name.try_clone()?,
+			parent_path: self.parent_path.try_clone()?,
+
+			hard_links_count: self.hard_links_count,
+
+			blocks_count: self.blocks_count,
+			size: self.size,
+
+			uid: self.uid,
+			gid: self.gid,
+			mode: self.mode,
+
+			ctime: self.ctime,
+			mtime: self.mtime,
+			atime: self.atime,
+
+			location: self.location.clone(),
+			content: self.content.try_clone()?,
+
+			deferred_remove: false,
+			removed: false,
+		})
+	}
+}
+
 impl Drop for File {
 	/// This function is used in case removal of the file has been deferred, but `close` has not
 	/// been called.
@@ -715,9 +1022,20 @@ impl Drop for File {
 }
 
 impl AccessProfile {
-	fn check_read_access_impl(uid: Uid, gid: Gid, file: &File) -> bool {
-		// If root, bypass checks
-		if uid == perm::ROOT_UID || gid == perm::ROOT_GID {
+	/// Returns whether the agent has the given capability for the given kind of IDs.
+	///
+	/// When checking with real IDs, the real user ID determines whether the agent is
+	/// privileged.
+	fn has_cap_for(&self, cap: u32, effective: bool) -> bool {
+		if effective {
+			self.has_cap(cap)
+		} else {
+			self.get_uid() == perm::ROOT_UID
+		}
+	}
+
+	fn check_read_access_impl(uid: Uid, gid: Gid, bypass: bool, file: &File) -> bool {
+		if bypass {
 			return true;
 		}
 
@@ -732,14 +1050,16 @@ impl AccessProfile {
 
 	/// Tells whether the agent can read the file.
 	///
-	/// `effective` tells whether to use effective IDs. If not, real IDs are used.
+	/// `effective` tells whether to use filesystem IDs. If not, real IDs are used.
 	pub fn check_read_access(&self, file: &File, effective: bool) -> bool {
 		let (uid, gid) = if effective {
-			(self.get_euid(), self.get_egid())
+			(self.get_fsuid(), self.get_fsgid())
 		} else {
 			(self.get_uid(), self.get_gid())
 		};
-		Self::check_read_access_impl(uid, gid, file)
+		let bypass = self.has_cap_for(perm::CAP_DAC_OVERRIDE, effective)
+			|| self.has_cap_for(perm::CAP_DAC_READ_SEARCH, effective);
+		Self::check_read_access_impl(uid, gid, bypass, file)
 	}
 
 	/// Tells whether the agent can read the file.
@@ -756,9 +1076,8 @@ impl AccessProfile {
 		self.can_read_file(file)
 	}
 
-	fn check_write_access_impl(uid: Uid, gid: Gid, file: &File) -> bool {
-		// If root, bypass checks
-		if uid == perm::ROOT_UID || gid == perm::ROOT_GID {
+	fn check_write_access_impl(uid: Uid, gid: Gid, bypass: bool, file: &File) -> bool {
+		if bypass {
 			return true;
 		}
 
@@ -773,14 +1092,15 @@ impl AccessProfile {
 
 	/// Tells whether the agent can write the file.
 	///
-	/// `effective` tells whether to use effective IDs. If not, real IDs are used.
+	/// `effective` tells whether to use filesystem IDs. If not, real IDs are used.
 	pub fn check_write_access(&self, file: &File, effective: bool) -> bool {
 		let (uid, gid) = if effective {
-			(self.get_euid(), self.get_egid())
+			(self.get_fsuid(), self.get_fsgid())
 		} else {
 			(self.get_uid(), self.get_gid())
 		};
-		Self::check_write_access_impl(uid, gid, file)
+		let bypass = self.has_cap_for(perm::CAP_DAC_OVERRIDE, effective);
+		Self::check_write_access_impl(uid, gid, bypass, file)
 	}
 
 	/// Tells whether the agent can write the file.
@@ -795,11 +1115,12 @@ impl AccessProfile {
 		self.can_write_file(file) && self.can_execute_file(file)
 	}
 
-	fn check_execute_access_impl(uid: Uid, gid: Gid, file: &File) -> bool {
-		// If root, bypass checks (unless the file is a regular file)
-		if !matches!(file.content, FileContent::Regular)
-			&& (uid == perm::ROOT_UID || gid == perm::ROOT_GID)
-		{
+	fn check_execute_access_impl(uid: Uid, gid: Gid, bypass: bool, file: &File) -> bool {
+		// A regular file can be executed only if at least one execute bit is set
+		let exec_bits = perm::S_IXUSR | perm::S_IXGRP | perm::S_IXOTH;
+		let executable =
+			!matches!(file.content, FileContent::Regular) || file.mode & exec_bits != 0;
+		if bypass && executable {
 			return true;
 		}
 
@@ -814,14 +1135,18 @@ impl AccessProfile {
 
 	/// Tells whether the agent can execute the file.
 	///
-	/// `effective` tells whether to use effective IDs. If not, real IDs are used.
+	/// `effective` tells whether to use filesystem IDs. If not, real IDs are used.
 	pub fn check_execute_access(&self, file: &File, effective: bool) -> bool {
 		let (uid, gid) = if effective {
-			(self.get_euid(), self.get_egid())
+			(self.get_fsuid(), self.get_fsgid())
 		} else {
 			(self.get_uid(), self.get_gid())
 		};
-		Self::check_execute_access_impl(uid, gid, file)
+		// Searching a directory is also allowed with `CAP_DAC_READ_SEARCH`
+		let bypass = self.has_cap_for(perm::CAP_DAC_OVERRIDE, effective)
+			|| (matches!(file.content, FileContent::Directory(_))
+				&& self.has_cap_for(perm::CAP_DAC_READ_SEARCH, effective));
+		Self::check_execute_access_impl(uid, gid, bypass, file)
 	}
 
 	/// Tells whether the agent can execute the file.
@@ -838,52 +1163,37 @@ impl AccessProfile {
 
 	/// Tells whether the agent can set permissions for the given file.
 	pub fn can_set_file_permissions(&self, file: &File) -> bool {
-		let euid = self.get_euid();
-		euid == perm::ROOT_UID || euid == file.get_uid()
+		self.has_cap(perm::CAP_FOWNER) || self.get_fsuid() == file.get_uid()
 	}
 }
 
 impl IO for File {
 	fn get_size(&self) -> u64 {
-		self.size
+		if self.is_cached() {
+			page_cache::get_size(self)
+		} else {
+			self.size
+		}
 	}
 
 	fn read(&mut self, off: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
-		self.io_op(|io, fs| {
-			let Some(io_mutex) = io else {
-				return Ok((0, true));
-			};
-			let mut io = io_mutex.lock();
-
-			if let Some((fs_mutex, inode)) = fs {
-				let mut fs = fs_mutex.lock();
-				let len = fs.read_node(&mut *io, inode, off, buff)?;
-				let eof = off + len >= self.size;
-				Ok((len, eof))
-			} else {
-				io.read(off, buff)
-			}
-		})
+		if self.is_cached() {
+			page_cache::read(self, off, buff)
+		} else {
+			self.read_raw(off, buff)
+		}
 	}
 
 	fn write(&mut self, off: u64, buff: &[u8]) -> Result<u64, Errno> {
-		let len = self.io_op(|io, fs| {
-			let Some(io_mutex) = io else {
-				return Ok(0);
-			};
-			let mut io = io_mutex.lock();
-
-			if let Some((fs_mutex, inode)) = fs {
-				let mut fs = fs_mutex.lock();
-				fs.write_node(&mut *io, inode, off, buff)?;
-				Ok(buff.len() as _)
-			} else {
-				io.write(off, buff)
-			}
-		})?;
-		// Update file's size
-		self.size = max(off + len, self.size);
-		Ok(len)
+		if self.is_cached() {
+			let end = off
+				.checked_add(buff.len() as _)
+				.ok_or_else(|| errno!(EFBIG))?;
+			memfd::check_write(&self.location, end, self.get_size())?;
+			page_cache::write(self, off, buff)
+		} else {
+			self.write_raw(off, buff)
+		}
 	}
 
 	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
@@ -915,7 +1225,7 @@ pub fn init(root: Option<(u32, u32)>) -> Result<(), Errno> {
 
 		None => MountSource::NoDev(String::try_from(b"tmpfs")?),
 	};
-	mountpoint::create(mount_source, None, 0, Path::root())?;
+	mountpoint::create(mount_source, None, 0, Path::root(), b"")?;
 
 	Ok(())
 }
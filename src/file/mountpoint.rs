@@ -1,26 +1,43 @@
 //! A mount point is a directory in which a filesystem is mounted.
+//!
+//! Mountpoints are grouped into mount namespaces. Each process belongs to a namespace, which
+//! defines the mountpoints it can see. When a new namespace is created, the mountpoints of the
+//! parent namespace are copied into it, so that mount and unmount events in one namespace do not
+//! affect the other, unless they are propagated through shared mountpoints.
 
+use super::dcache;
 use super::fs;
 use super::fs::Filesystem;
 use super::fs::FilesystemType;
+use super::fs::MountOptions;
+use super::icache;
 use super::path::Path;
 use super::vfs;
+use super::File;
 use super::FileContent;
+use super::INode;
 use crate::device;
 use crate::device::DeviceID;
 use crate::device::DeviceType;
 use crate::errno::AllocResult;
+use crate::errno::CollectResult;
+use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file::perm::AccessProfile;
+use crate::process;
+use crate::process::Process;
 use crate::util::container::hashmap::HashMap;
 use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
 use crate::util::io::DummyIO;
 use crate::util::io::IO;
 use crate::util::lock::Mutex;
 use crate::util::ptr::arc::Arc;
+use crate::util::ptr::arc::Weak;
 use crate::util::TryClone;
-use core::cmp::max;
 use core::fmt;
+use core::sync::atomic;
+use core::sync::atomic::AtomicU32;
 
 /// Permits mandatory locking on files.
 pub const FLAG_MANDLOCK: u32 = 0b000000000001;
@@ -182,6 +199,8 @@ struct LoadedFS {
 
 	/// The filesystem.
 	fs: Arc<Mutex<dyn Filesystem>>,
+	/// The filesystem-specific mount options the filesystem has been loaded with.
+	options: String,
 }
 
 /// The list of loaded filesystems associated with their respective sources.
@@ -195,6 +214,7 @@ static FILESYSTEMS: Mutex<HashMap<MountSource, LoadedFS>> = Mutex::new(HashMap::
 /// automaticaly.
 /// - `path` is the path to the directory on which the filesystem is mounted.
 /// - `readonly` tells whether the filesystem is mount in readonly.
+/// - `data` is the string of filesystem-specific mount options.
 ///
 /// On success, the function returns the loaded filesystem.
 fn load_fs(
@@ -202,6 +222,7 @@ fn load_fs(
 	fs_type: Option<Arc<dyn FilesystemType>>,
 	path: Path,
 	readonly: bool,
+	data: &[u8],
 ) -> Result<Arc<Mutex<dyn Filesystem>>, Errno> {
 	// Getting the I/O interface
 	let io_mutex = source.get_io()?;
@@ -216,7 +237,9 @@ fn load_fs(
 			_ => fs::detect(&mut *io)?,
 		},
 	};
-	let fs = fs_type.load_filesystem(&mut *io, path, readonly)?;
+	let options = MountOptions::parse(data, fs_type.get_mount_options())?;
+	let fs = fs_type.load_filesystem(&mut *io, path, readonly, &options)?;
+	let options = crate::format!("{options}")?;
 
 	// Inserting new filesystem into filesystems list
 	let mut container = FILESYSTEMS.lock();
@@ -226,6 +249,7 @@ fn load_fs(
 			ref_count: 1,
 
 			fs: fs.clone(),
+			options,
 		},
 	)?;
 
@@ -248,6 +272,18 @@ fn get_fs_(source: &MountSource, take: bool) -> Option<Arc<Mutex<dyn Filesystem>
 	Some(fs.fs.clone())
 }
 
+/// Returns the filesystem-specific mount options of the loaded filesystem with the given
+/// source `source`.
+///
+/// If the filesystem isn't loaded, the function returns an empty string.
+fn get_fs_options(source: &MountSource) -> AllocResult<String> {
+	let container = FILESYSTEMS.lock();
+	match container.get(source) {
+		Some(fs) => fs.options.try_clone(),
+		None => Ok(String::new()),
+	}
+}
+
 /// Returns the loaded filesystem with the given source `source`.
 ///
 /// If the filesystem isn't loaded, the function returns `None`.
@@ -273,6 +309,60 @@ fn drop_fs(source: &MountSource) {
 	}
 }
 
+/// Flushes the write cache of the device of every loaded filesystem.
+pub fn flush_devices() -> EResult<()> {
+	// The interfaces are collected first to avoid holding the lock while flushing
+	let ios = {
+		let container = FILESYSTEMS.lock();
+		let mut ios = Vec::new();
+		for (source, _) in container.iter() {
+			ios.push(source.get_io()?)?;
+		}
+		ios
+	};
+
+	let mut res = Ok(());
+	for io_mutex in ios.iter() {
+		// An error on a device must not prevent others from being flushed
+		if let Err(e) = io_mutex.lock().flush() {
+			res = Err(e);
+		}
+	}
+	res
+}
+
+/// The propagation type of a mountpoint, telling whether mount and unmount events happening
+/// under it are propagated to other mountpoints.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Propagation {
+	/// Events are neither propagated nor received.
+	Private,
+	/// Events are propagated to, and received from, the mountpoints of the peer group with the
+	/// given ID.
+	Shared(u32),
+	/// Events are received from the peer group with the given ID, but not propagated.
+	Slave(u32),
+}
+
+/// A requested change of propagation type, without peer group.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PropagationType {
+	/// Makes the mountpoint private.
+	Private,
+	/// Makes the mountpoint shared, in a new peer group if it isn't shared already.
+	Shared,
+	/// Makes the mountpoint a slave of its current peer group.
+	Slave,
+}
+
+/// The ID of the next peer group to be allocated.
+static NEXT_PEER_GROUP: AtomicU32 = AtomicU32::new(1);
+
+/// Allocates a new peer group ID.
+fn alloc_peer_group() -> u32 {
+	NEXT_PEER_GROUP.fetch_add(1, atomic::Ordering::Relaxed)
+}
+
 /// Structure representing a mount point.
 pub struct MountPoint {
 	/// The ID of the mountpoint.
@@ -289,24 +379,35 @@ pub struct MountPoint {
 	fs: Arc<Mutex<dyn Filesystem>>,
 	/// The name of the filesystem's type.
 	fs_type_name: String,
+	/// The inode of the root of the mountpoint on the filesystem.
+	///
+	/// This is the root of the filesystem, unless the mountpoint is a bind mount.
+	root: INode,
+	/// The propagation type of the mountpoint.
+	propagation: Propagation,
+	/// The namespace the mountpoint belongs to. If `None`, the mountpoint has not been inserted
+	/// yet.
+	ns: Option<Weak<MountNamespace>>,
 }
 
 impl MountPoint {
 	/// Creates a new instance.
 	///
+	/// The ID of the mountpoint is allocated on insertion.
+	///
 	/// Arguments:
-	/// - `id` is the ID of the mountpoint.
 	/// - `source` is the source of the mountpoint.
 	/// - `fs_type` is the filesystem type. If `None`, the function tries to detect it
 	/// automaticaly.
 	/// - `flags` are the mount flags.
 	/// - `path` is the path on which the filesystem is to be mounted.
+	/// - `data` is the string of filesystem-specific mount options.
 	fn new(
-		id: u32,
 		source: MountSource,
 		fs_type: Option<Arc<dyn FilesystemType>>,
 		flags: u32,
 		path: Path,
+		data: &[u8],
 	) -> Result<Self, Errno> {
 		// Tells whether the filesystem will be mounted in read-only
 		let readonly = flags & FLAG_RDONLY != 0;
@@ -316,25 +417,62 @@ impl MountPoint {
 			Some(fs) => fs,
 
 			// Filesystem doesn't exist, load it
-			None => load_fs(source.try_clone()?, fs_type, path.try_clone()?, readonly)?,
+			None => load_fs(
+				source.try_clone()?,
+				fs_type,
+				path.try_clone()?,
+				readonly,
+				data,
+			)?,
 		};
 
-		// TODO Increment number of references to the filesystem
+		// The filesystem is referenced from here on, so that it is released if the function fails
+		let mut mountpoint = Self {
+			id: 0,
+
+			flags,
+			path,
 
-		let fs_type_name = {
-			let fs = fs_mutex.lock();
-			String::try_from(fs.get_name())?
+			source,
+			fs: fs_mutex,
+			fs_type_name: String::new(),
+			root: 0,
+			propagation: Propagation::Private,
+			ns: None,
 		};
+		{
+			let io_mutex = mountpoint.source.get_io()?;
+			let mut io = io_mutex.lock();
+			let fs = mountpoint.fs.lock();
+			mountpoint.fs_type_name = String::try_from(fs.get_name())?;
+			mountpoint.root = fs.get_root_inode(&mut *io)?;
+		}
+		Ok(mountpoint)
+	}
 
+	/// Creates a new instance bound to the file with inode `root` on the filesystem of the
+	/// mountpoint `mountpoint`.
+	///
+	/// The ID of the mountpoint is allocated on insertion.
+	///
+	/// `path` is the path on which the file is to be mounted.
+	///
+	/// The new mountpoint shares the filesystem, the flags and the propagation type of
+	/// `mountpoint`.
+	fn new_bind(mountpoint: &MountPoint, root: INode, path: Path) -> Result<Self, Errno> {
+		let fs = get_fs_(&mountpoint.source, true).ok_or_else(|| errno!(ENODEV))?;
 		Ok(Self {
-			id,
+			id: 0,
 
-			flags,
+			flags: mountpoint.flags,
 			path,
 
-			source,
-			fs: fs_mutex,
-			fs_type_name,
+			source: mountpoint.source.try_clone()?,
+			fs,
+			fs_type_name: mountpoint.fs_type_name.try_clone()?,
+			root,
+			propagation: mountpoint.propagation,
+			ns: None,
 		})
 	}
 
@@ -373,6 +511,26 @@ impl MountPoint {
 	pub fn get_filesystem_type(&self) -> &String {
 		&self.fs_type_name
 	}
+
+	/// Returns the filesystem-specific mount options, in the form of a comma-separated list.
+	pub fn get_options(&self) -> AllocResult<String> {
+		get_fs_options(&self.source)
+	}
+
+	/// Returns the inode of the root of the mountpoint on its filesystem.
+	pub fn get_root(&self) -> INode {
+		self.root
+	}
+
+	/// Returns the propagation type of the mountpoint.
+	pub fn get_propagation(&self) -> Propagation {
+		self.propagation
+	}
+
+	/// Tells whether the mountpoint receives events from the peer group `group`.
+	fn receives_from(&self, group: u32) -> bool {
+		matches!(self.propagation, Propagation::Shared(g) | Propagation::Slave(g) if g == group)
+	}
 }
 
 impl Drop for MountPoint {
@@ -381,12 +539,134 @@ impl Drop for MountPoint {
 	}
 }
 
-/// The list of mountpoints with their respective ID.
+/// The list of mountpoints with their respective ID, across every namespaces.
 pub static MOUNT_POINTS: Mutex<HashMap<u32, Arc<Mutex<MountPoint>>>> = Mutex::new(HashMap::new());
-/// A map from mountpoint paths to mountpoint IDs.
-pub static PATH_TO_ID: Mutex<HashMap<Path, u32>> = Mutex::new(HashMap::new());
 
-/// Creates a new mountpoint.
+/// A mount namespace, which is the set of mountpoints visible to the processes using it.
+pub struct MountNamespace {
+	/// The ID of the namespace.
+	id: u32,
+	/// A map from mountpoint paths to mountpoint IDs.
+	mounts: Mutex<HashMap<Path, u32>>,
+}
+
+/// The root mount namespace.
+static ROOT_NS: Mutex<Option<Arc<MountNamespace>>> = Mutex::new(None);
+/// The ID of the next mount namespace to be created.
+static NEXT_NS_ID: AtomicU32 = AtomicU32::new(1);
+
+impl MountNamespace {
+	/// Creates an empty namespace.
+	fn new() -> AllocResult<Arc<Self>> {
+		Arc::new(Self {
+			id: NEXT_NS_ID.fetch_add(1, atomic::Ordering::Relaxed),
+			mounts: Mutex::new(HashMap::new()),
+		})
+	}
+
+	/// Returns the root namespace.
+	pub fn get_root() -> AllocResult<Arc<Self>> {
+		let mut root = ROOT_NS.lock();
+		if let Some(root) = &*root {
+			return Ok(root.clone());
+		}
+		let ns = Self::new()?;
+		*root = Some(ns.clone());
+		Ok(ns)
+	}
+
+	/// Returns the ID of the namespace.
+	pub fn get_id(&self) -> u32 {
+		self.id
+	}
+
+	/// Returns the namespace of the current process.
+	///
+	/// If no process is running, the function returns the root namespace.
+	pub fn current() -> AllocResult<Arc<Self>> {
+		if process::is_initialized() {
+			if let Some(ns) = Process::current_mnt_ns() {
+				return Ok(ns);
+			}
+		}
+		Self::get_root()
+	}
+
+	/// Creates a new namespace containing a copy of each mountpoint of the namespace.
+	///
+	/// Copies keep the propagation type of their original, so that shared mountpoints keep
+	/// exchanging events with their copies.
+	pub fn copy(&self) -> EResult<Arc<Self>> {
+		let ns = Self::new()?;
+		let mounts = {
+			let mounts = self.mounts.lock();
+			let mut copy = Vec::new();
+			for (path, id) in mounts.iter() {
+				copy.push((path.try_clone()?, *id))?;
+			}
+			copy
+		};
+		for (path, id) in mounts {
+			let Some(mp_mutex) = from_id(id) else {
+				continue;
+			};
+			let copy = {
+				let mp = mp_mutex.lock();
+				MountPoint::new_bind(&mp, mp.get_root(), path.try_clone()?)?
+			};
+			insert(copy, &ns, path)?;
+		}
+		Ok(ns)
+	}
+
+	/// Returns the list of mountpoints of the namespace.
+	pub fn get_mountpoints(&self) -> EResult<Vec<Arc<Mutex<MountPoint>>>> {
+		let mounts = self.mounts.lock();
+		let mut mountpoints = Vec::new();
+		for (_, id) in mounts.iter() {
+			if let Some(mp) = from_id(*id) {
+				mountpoints.push(mp)?;
+			}
+		}
+		Ok(mountpoints)
+	}
+
+	/// Returns the deepest mountpoint of the namespace in the path `path`.
+	///
+	/// If no mountpoint is in the path, the function returns `None`.
+	pub fn get_deepest(&self, path: &Path) -> Option<Arc<Mutex<MountPoint>>> {
+		// Mountpoints are not locked, since the caller may be holding the lock of one of them
+		let container = self.mounts.lock();
+		let (_, id) = container
+			.iter()
+			.filter(|(mount_path, _)| path.begins_with(mount_path))
+			.max_by_key(|(mount_path, _)| mount_path.get_elements_count())?;
+		from_id(*id)
+	}
+
+	/// Returns the mountpoint of the namespace with path `path`.
+	///
+	/// If it doesn't exist, the function returns `None`.
+	pub fn get(&self, path: &Path) -> Option<Arc<Mutex<MountPoint>>> {
+		let container = self.mounts.lock();
+		let id = container.get(path)?;
+		from_id(*id)
+	}
+}
+
+impl Drop for MountNamespace {
+	fn drop(&mut self) {
+		let mounts = self.mounts.lock();
+		let mut mount_points = MOUNT_POINTS.lock();
+		for (_, id) in mounts.iter() {
+			mount_points.remove(id);
+			dcache::invalidate_mountpoint(*id);
+			icache::invalidate_mountpoint(*id);
+		}
+	}
+}
+
+/// Creates a new mountpoint in the namespace of the current process.
 ///
 /// If a mountpoint is already present at the same path, the function fails.
 ///
@@ -395,52 +675,277 @@ pub static PATH_TO_ID: Mutex<HashMap<Path, u32>> = Mutex::new(HashMap::new());
 /// - `fs_type` is the filesystem type. If `None`, the function tries to detect it automaticaly.
 /// - `flags` are the mount flags.
 /// - `path` is the path on which the filesystem is to be mounted.
+/// - `data` is the string of filesystem-specific mount options.
 pub fn create(
 	source: MountSource,
 	fs_type: Option<Arc<dyn FilesystemType>>,
 	flags: u32,
 	path: Path,
+	data: &[u8],
 ) -> Result<Arc<Mutex<MountPoint>>, Errno> {
-	// TODO clean
-	// PATH_TO_ID is locked first and during the whole function to prevent a race condition between
-	// the locks of MOUNT_POINTS
-	let mut path_to_id = PATH_TO_ID.lock();
+	// The filesystem is loaded before locking the lists of mountpoints since loading it may
+	// require resolving paths
+	let mountpoint = MountPoint::new(source, fs_type, flags, path.try_clone()?, data)?;
+	insert_propagated(mountpoint, &MountNamespace::current()?, path)
+}
+
+/// Creates a bind mount of the file `file` on the path `path`, making the file accessible from
+/// both locations.
+///
+/// If `recursive` is set, the mountpoints located under `file` are bound as well, at the
+/// corresponding locations under `path`.
+///
+/// If a mountpoint is already present at the same path, the function fails.
+pub fn create_bind(
+	file: &File,
+	path: Path,
+	recursive: bool,
+) -> Result<Arc<Mutex<MountPoint>>, Errno> {
+	let ns = MountNamespace::current()?;
+	let source_mutex = file
+		.get_location()
+		.get_mountpoint()
+		.ok_or_else(|| errno!(EINVAL))?;
+	let mountpoint = {
+		let source = source_mutex.lock();
+		MountPoint::new_bind(&source, file.get_location().get_inode(), path.try_clone()?)?
+	};
+	let mountpoint = insert_propagated(mountpoint, &ns, path.try_clone()?)?;
+	if !recursive {
+		return Ok(mountpoint);
+	}
+
+	// The list of submounts is built beforehand since new mountpoints may be located under the
+	// source
+	let source_path = file.get_path()?;
+	let submounts = {
+		let mounts = ns.mounts.lock();
+		let mut submounts = Vec::new();
+		for (mount_path, id) in mounts.iter() {
+			if mount_path != &source_path && mount_path.begins_with(&source_path) {
+				submounts.push((mount_path.try_clone()?, *id))?;
+			}
+		}
+		submounts
+	};
+	for (mount_path, id) in submounts {
+		let Some(submount_mutex) = from_id(id) else {
+			continue;
+		};
+		let suffix = mount_path.range_from(source_path.get_elements_count()..)?;
+		let sub_path = path.concat(&suffix)?;
+		let submount = {
+			let submount = submount_mutex.lock();
+			MountPoint::new_bind(&submount, submount.get_root(), sub_path.try_clone()?)?
+		};
+		insert_propagated(submount, &ns, sub_path)?;
+	}
+
+	Ok(mountpoint)
+}
+
+/// Inserts the mountpoint `mountpoint` at the path `path` in the namespace `ns`, allocating its
+/// ID.
+///
+/// If a mountpoint is already present at the same path, the function fails.
+fn insert(
+	mut mountpoint: MountPoint,
+	ns: &Arc<MountNamespace>,
+	path: Path,
+) -> Result<Arc<Mutex<MountPoint>>, Errno> {
+	// The namespace is locked first to prevent a race condition between the locks of MOUNT_POINTS
+	let mut mounts = ns.mounts.lock();
+	let mut mount_points = MOUNT_POINTS.lock();
+	if mounts.get(&path).is_some() {
+		return Err(errno!(EBUSY));
+	}
 
 	// TODO clean
 	// ID allocation
-	let id = {
-		let mut id = 0;
+	let id = mount_points.iter().map(|(i, _)| *i).max().unwrap_or(0) + 1;
+	mountpoint.id = id;
+	mountpoint.ns = Some(Arc::downgrade(ns));
+	let mountpoint = Arc::new(Mutex::new(mountpoint))?;
 
-		for (i, _) in MOUNT_POINTS.lock().iter() {
-			id = max(*i, id);
-		}
+	// Insertion
+	mount_points.insert(id, mountpoint.clone())?;
+	if let Err(e) = mounts.insert(path, id) {
+		mount_points.remove(&id);
+		return Err(e.into());
+	}
 
-		id + 1
+	Ok(mountpoint)
+}
+
+/// A parent mountpoint, along with the path of its child relative to it.
+type Parent = (Arc<Mutex<MountPoint>>, Path);
+
+/// Returns the mountpoint containing the mountpoint at path `path` in the namespace `ns`, along
+/// with the path of the latter relative to the former.
+///
+/// If the path is the root of the VFS, the function returns `None`.
+fn get_parent(ns: &MountNamespace, path: &Path) -> Result<Option<Parent>, Errno> {
+	let count = path.get_elements_count();
+	if count == 0 {
+		return Ok(None);
+	}
+	let Some(parent_mutex) = ns.get_deepest(&path.range_to(..(count - 1))?) else {
+		return Ok(None);
 	};
+	let suffix = {
+		let parent = parent_mutex.lock();
+		path.range_from(parent.get_path().get_elements_count()..)?
+	};
+	Ok(Some((parent_mutex, suffix)))
+}
 
-	let mountpoint = Arc::new(Mutex::new(MountPoint::new(
-		id,
-		source,
-		fs_type,
-		flags,
-		path.try_clone()?,
-	)?))?;
+/// A mountpoint receiving propagated events, along with its namespace.
+type Receiver = (Arc<Mutex<MountPoint>>, Arc<MountNamespace>);
 
-	// Insertion
-	{
-		let mut mount_points = MOUNT_POINTS.lock();
+/// Returns the mountpoints receiving the events of the peer group `group`, except the mountpoint
+/// with ID `except`.
+///
+/// Receivers are searched in every namespaces. Each receiver is returned along with its
+/// namespace.
+fn get_receivers(group: u32, except: u32) -> Result<Vec<Receiver>, Errno> {
+	let mount_points = MOUNT_POINTS
+		.lock()
+		.iter()
+		.map(|(_, mp)| mp.clone())
+		.collect::<CollectResult<Vec<_>>>()
+		.0?;
+	let mut receivers = Vec::new();
+	for mp_mutex in mount_points {
+		let mp = mp_mutex.lock();
+		if mp.get_id() == except || !mp.receives_from(group) {
+			continue;
+		}
+		// Mountpoints of a namespace being destroyed are skipped
+		let Some(ns) = mp.ns.as_ref().and_then(Weak::upgrade) else {
+			continue;
+		};
+		drop(mp);
+		receivers.push((mp_mutex, ns))?;
+	}
+	Ok(receivers)
+}
 
-		mount_points.insert(id, mountpoint.clone())?;
-		if let Err(e) = path_to_id.insert(path, id) {
-			mount_points.remove(&id);
-			return Err(e.into());
+/// Inserts the mountpoint `mountpoint` at the path `path` in the namespace `ns`, then propagates
+/// it to the mountpoints receiving the events of its parent mountpoint, if the latter is shared.
+///
+/// The new mountpoint and its copies then form a new peer group. Copies created in slave
+/// mountpoints are slaves of this group.
+fn insert_propagated(
+	mountpoint: MountPoint,
+	ns: &Arc<MountNamespace>,
+	path: Path,
+) -> Result<Arc<Mutex<MountPoint>>, Errno> {
+	let mountpoint = insert(mountpoint, ns, path.try_clone()?)?;
+	let Some((parent_mutex, suffix)) = get_parent(ns, &path)? else {
+		return Ok(mountpoint);
+	};
+	let (parent_id, parent_propagation) = {
+		let parent = parent_mutex.lock();
+		(parent.get_id(), parent.get_propagation())
+	};
+	let Propagation::Shared(parent_group) = parent_propagation else {
+		return Ok(mountpoint);
+	};
+
+	let group = {
+		let mut mp = mountpoint.lock();
+		match mp.propagation {
+			Propagation::Shared(group) => group,
+			_ => {
+				let group = alloc_peer_group();
+				mp.propagation = Propagation::Shared(group);
+				group
+			}
+		}
+	};
+	for (receiver_mutex, receiver_ns) in get_receivers(parent_group, parent_id)? {
+		let (copy_path, propagation) = {
+			let receiver = receiver_mutex.lock();
+			let propagation = match receiver.get_propagation() {
+				Propagation::Shared(_) => Propagation::Shared(group),
+				_ => Propagation::Slave(group),
+			};
+			(receiver.get_path().concat(&suffix)?, propagation)
+		};
+		let mut copy = {
+			let mp = mountpoint.lock();
+			MountPoint::new_bind(&mp, mp.get_root(), copy_path.try_clone()?)?
+		};
+		copy.propagation = propagation;
+		// If a mountpoint is already present, the event is not propagated there
+		match insert(copy, &receiver_ns, copy_path) {
+			Err(e) if e == errno!(EBUSY) => {}
+			res => {
+				res?;
+			}
 		}
 	}
 
 	Ok(mountpoint)
 }
 
-/// Removes the mountpoint at the given path `path`.
+/// Changes the propagation type of the mountpoint at path `path`, in the namespace of the current
+/// process.
+///
+/// If `recursive` is set, the change also applies to every mountpoint located under `path`.
+///
+/// If no mountpoint is present at `path`, the function returns `EINVAL`.
+pub fn set_propagation(
+	path: &Path,
+	propagation: PropagationType,
+	recursive: bool,
+) -> Result<(), Errno> {
+	let ns = MountNamespace::current()?;
+	let targets = {
+		let mounts = ns.mounts.lock();
+		if mounts.get(path).is_none() {
+			return Err(errno!(EINVAL));
+		}
+		let mut targets = Vec::new();
+		for (mount_path, id) in mounts.iter() {
+			if mount_path == path || (recursive && mount_path.begins_with(path)) {
+				targets.push(*id)?;
+			}
+		}
+		targets
+	};
+
+	for id in targets {
+		let Some(mp_mutex) = from_id(id) else {
+			continue;
+		};
+		let group = match mp_mutex.lock().get_propagation() {
+			Propagation::Shared(group) => Some(group),
+			_ => None,
+		};
+		// A shared mountpoint that has no peer cannot become a slave of its group
+		let has_peers = match (propagation, group) {
+			(PropagationType::Slave, Some(group)) => !get_receivers(group, id)?.is_empty(),
+			_ => false,
+		};
+
+		let mut mp = mp_mutex.lock();
+		mp.propagation = match (propagation, mp.propagation) {
+			(PropagationType::Private, _) => Propagation::Private,
+			(PropagationType::Shared, Propagation::Shared(group)) => Propagation::Shared(group),
+			(PropagationType::Shared, _) => Propagation::Shared(alloc_peer_group()),
+			(PropagationType::Slave, Propagation::Shared(group)) if has_peers => {
+				Propagation::Slave(group)
+			}
+			(PropagationType::Slave, Propagation::Shared(_)) => Propagation::Private,
+			(PropagationType::Slave, p) => p,
+		};
+	}
+
+	Ok(())
+}
+
+/// Removes the mountpoint at the given path `path`, in the namespace of the current process.
 ///
 /// Data is sychronized to the associated storage device, if any, before removing the mountpoint.
 ///
@@ -448,49 +953,84 @@ pub fn create(
 ///
 /// If the mountpoint is busy, the function returns `EBUSY`.
 pub fn remove(path: &Path) -> Result<(), Errno> {
-	let mut path_to_id = PATH_TO_ID.lock();
+	let ns = MountNamespace::current()?;
+	let parent = get_parent(&ns, path)?;
+	let removed = remove_impl(&ns, path)?;
+
+	// Propagating the removal to the receivers of the parent mountpoint, if shared
+	let Some((parent_mutex, suffix)) = parent else {
+		return Ok(());
+	};
+	let (parent_id, parent_propagation) = {
+		let parent = parent_mutex.lock();
+		(parent.get_id(), parent.get_propagation())
+	};
+	let Propagation::Shared(parent_group) = parent_propagation else {
+		return Ok(());
+	};
+	for (receiver_mutex, receiver_ns) in get_receivers(parent_group, parent_id)? {
+		let copy_path = receiver_mutex.lock().get_path().concat(&suffix)?;
+		let Some(copy_mutex) = receiver_ns.get(&copy_path) else {
+			continue;
+		};
+		// Only copies of the removed mountpoint are removed
+		let is_copy = {
+			let copy = copy_mutex.lock();
+			copy.get_source() == &removed.0 && copy.get_root() == removed.1
+		};
+		if is_copy {
+			remove_impl(&receiver_ns, &copy_path)?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Removes the mountpoint at the given path `path` in the namespace `ns`, without propagation.
+///
+/// On success, the function returns the source and the root inode of the removed mountpoint.
+fn remove_impl(ns: &MountNamespace, path: &Path) -> Result<(MountSource, INode), Errno> {
+	let mut mounts = ns.mounts.lock();
 	let mut mount_points = MOUNT_POINTS.lock();
 
-	let id = *path_to_id.get(path).ok_or(errno!(EINVAL))?;
-	let _mountpoint = mount_points.get(&id).ok_or(errno!(EINVAL))?;
+	let id = *mounts.get(path).ok_or(errno!(EINVAL))?;
+	let mountpoint = mount_points.get(&id).ok_or(errno!(EINVAL))?;
+	let removed = {
+		let mountpoint = mountpoint.lock();
+		(mountpoint.get_source().try_clone()?, mountpoint.get_root())
+	};
 
 	// TODO Check if busy (EBUSY)
 	// TODO Check if another mount point is present in a subdirectory (EBUSY)
 
 	// TODO sync fs
 
-	path_to_id.remove(path);
+	mounts.remove(path);
 	mount_points.remove(&id);
+	// The ID may be reused by another mountpoint
+	dcache::invalidate_mountpoint(id);
+	icache::invalidate_mountpoint(id);
+
+	Ok(removed)
+}
 
+/// Changes the flags of the mountpoint at path `path` to `flags`.
+///
+/// The filesystem itself is left untouched, so that other mountpoints of the same filesystem keep
+/// their own flags.
+///
+/// If no mountpoint is present at `path`, the function returns `EINVAL`.
+pub fn remount(path: &Path, flags: u32) -> Result<(), Errno> {
+	let mp_mutex = from_path(path).ok_or_else(|| errno!(EINVAL))?;
+	mp_mutex.lock().flags = flags;
 	Ok(())
 }
 
-/// Returns the deepest mountpoint in the path `path`.
+/// Returns the deepest mountpoint in the path `path`, in the namespace of the current process.
 ///
 /// If no mountpoint is in the path, the function returns `None`.
 pub fn get_deepest(path: &Path) -> Option<Arc<Mutex<MountPoint>>> {
-	let container = MOUNT_POINTS.lock();
-
-	let mut max: Option<Arc<Mutex<MountPoint>>> = None;
-	for (_, mp) in container.iter() {
-		let mp_guard = mp.lock();
-		let mount_path = mp_guard.get_path();
-
-		if let Some(max) = max.as_mut() {
-			let max = max.lock();
-			let max_path = max.get_path();
-
-			if max_path.get_elements_count() >= mount_path.get_elements_count() {
-				continue;
-			}
-		}
-
-		if path.begins_with(mount_path) {
-			max = Some(mp.clone());
-		}
-	}
-
-	max
+	MountNamespace::current().ok()?.get_deepest(path)
 }
 
 /// Returns the mountpoint with id `id`.
@@ -501,11 +1041,9 @@ pub fn from_id(id: u32) -> Option<Arc<Mutex<MountPoint>>> {
 	container.get(&id).cloned()
 }
 
-/// Returns the mountpoint with path `path`.
+/// Returns the mountpoint with path `path`, in the namespace of the current process.
 ///
 /// If it doesn't exist, the function returns `None`.
 pub fn from_path(path: &Path) -> Option<Arc<Mutex<MountPoint>>> {
-	let container = PATH_TO_ID.lock();
-	let id = container.get(path)?;
-	from_id(*id)
+	MountNamespace::current().ok()?.get(path)
 }
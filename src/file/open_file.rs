@@ -7,19 +7,42 @@ use crate::errno;
 use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file::buffer;
+use crate::file::buffer::fanotify;
+use crate::file::buffer::inotify;
+use crate::file::buffer::io_uring;
+use crate::file::buffer::memfd;
+use crate::file::buffer::pidfd;
+use crate::file::flock;
 use crate::file::mountpoint;
+use crate::file::readahead;
+use crate::file::writeback;
 use crate::file::DeviceID;
 use crate::file::File;
 use crate::file::FileContent;
 use crate::file::FileLocation;
+use crate::memory::slab;
+use crate::memory::slab::SlabCache;
 use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::mem_space::MemSpace;
+use crate::process::pgroup;
+use crate::process::pid::Pid;
+use crate::process::rlimit;
+use crate::process::rlimit::RLim;
+use crate::process::signal::SigInfo;
+use crate::process::signal::Signal;
+use crate::process::signal::POLL_ERR;
+use crate::process::signal::POLL_HUP;
+use crate::process::signal::POLL_IN;
+use crate::process::signal::POLL_OUT;
+use crate::process::signal::POLL_PRI;
+use crate::process::signal::SI_KERNEL;
 use crate::process::Process;
 use crate::syscall::ioctl;
 use crate::time::clock;
-use crate::time::clock::CLOCK_MONOTONIC;
-use crate::time::unit::TimestampScale;
+use crate::time::clock::CLOCK_REALTIME;
+use crate::time::unit::Timespec;
 use crate::util::container::hashmap::HashMap;
+use crate::util::io;
 use crate::util::io::IO;
 use crate::util::lock::IntMutex;
 use crate::util::lock::Mutex;
@@ -59,6 +82,8 @@ pub const O_NOCTTY: i32 = 0b00000000000000000000000100000000;
 pub const O_NOFOLLOW: i32 = 0b00000000000000100000000000000000;
 /// I/O is non blocking.
 pub const O_NONBLOCK: i32 = 0b00000000000000000000100000000000;
+/// The file is open only to refer to it. Reading from and writing to it is not allowed.
+pub const O_PATH: i32 = 0b00000000001000000000000000000000;
 /// When using `write`, the data has been transfered to the hardware before
 /// returning.
 pub const O_SYNC: i32 = 0b00000000000100000001000000000000;
@@ -70,6 +95,85 @@ pub const O_TRUNC: i32 = 0b00000000000000000000001000000000;
 /// Counts the number of time each file is open.
 static OPEN_FILES: Mutex<HashMap<FileLocation, usize>> = Mutex::new(HashMap::new());
 
+/// The flags of an open file description which can be changed after the file has been opened.
+const STATUS_FLAGS: i32 = O_APPEND | O_ASYNC | O_DIRECT | O_NOATIME | O_NONBLOCK;
+
+/// The recipient of the signals generated by an open file description.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Owner {
+	/// Signals are not sent.
+	#[default]
+	None,
+	/// Signals are sent to the thread with the given TID.
+	Thread(Pid),
+	/// Signals are sent to the process with the given PID.
+	Process(Pid),
+	/// Signals are sent to the process group with the given ID.
+	ProcessGroup(Pid),
+}
+
+/// The settings of signal-driven IO for an open file description.
+#[derive(Debug, Default)]
+pub struct FileOwner {
+	/// The recipient of the signals.
+	pub owner: Owner,
+	/// The signal sent when IO becomes possible. If zero, `SIGIO` is sent.
+	pub sig: c_int,
+}
+
+impl FileOwner {
+	/// Sends the signal notifying the poll events `mask` on the file descriptor `fd` to the
+	/// owner.
+	pub fn send_signal(&self, fd: c_int, mask: u32) {
+		let (sig, info) = if self.sig == 0 {
+			(Signal::SIGPOLL, SigInfo::new(&Signal::SIGPOLL, SI_KERNEL))
+		} else {
+			let Ok(sig) = Signal::try_from(self.sig as u32) else {
+				return;
+			};
+			let code = if mask & io::POLLIN != 0 {
+				POLL_IN
+			} else if mask & io::POLLOUT != 0 {
+				POLL_OUT
+			} else if mask & io::POLLPRI != 0 {
+				POLL_PRI
+			} else if mask & io::POLLHUP != 0 {
+				POLL_HUP
+			} else {
+				POLL_ERR
+			};
+			let info = SigInfo::from_poll(&sig, code, mask, fd);
+			(sig, info)
+		};
+
+		// If the signal cannot be queued, it is discarded
+		let send = |pid: Pid| {
+			if let Some(proc_mutex) = Process::get_by_pid(pid) {
+				let _ = proc_mutex.lock().kill_with_info(&sig, info, false);
+			}
+		};
+		match self.owner {
+			Owner::None => {}
+			Owner::Thread(pid) | Owner::Process(pid) => send(pid),
+			Owner::ProcessGroup(pgid) => {
+				let Ok(members) = pgroup::get_members(pgid) else {
+					return;
+				};
+				for pid in members {
+					send(pid);
+				}
+			}
+		}
+	}
+}
+
+/// The slab cache of open file descriptions.
+pub static OPEN_FILE_CACHE: SlabCache = SlabCache::new(
+	"filp",
+	Arc::<Mutex<OpenFile>>::inner_size(),
+	Some(slab::zero_ctor::<{ Arc::<Mutex<OpenFile>>::inner_size() }>),
+);
+
 /// An open file description.
 ///
 /// This structure is pointed to by file descriptors and point to files.
@@ -86,6 +190,15 @@ pub struct OpenFile {
 	/// The current offset in the file.
 	/// If pointing to a directory, this is the offset in directory entries.
 	curr_off: u64,
+	/// The readahead state.
+	readahead: readahead::State,
+	/// Tells whether accesses through the open file generate fanotify events.
+	notify: bool,
+	/// The `flock` lock held by the open file description, if any.
+	flock: Option<flock::Kind>,
+	/// The settings of signal-driven IO, shared with the resources on which the open file is
+	/// registered.
+	owner: Arc<IntMutex<FileOwner>>,
 }
 
 impl OpenFile {
@@ -105,6 +218,10 @@ impl OpenFile {
 			flags,
 
 			curr_off: 0,
+			readahead: Default::default(),
+			notify: true,
+			flock: None,
+			owner: Arc::new(IntMutex::new(FileOwner::default()))?,
 		};
 
 		// Update the open file counter
@@ -167,23 +284,96 @@ impl OpenFile {
 		self.flags
 	}
 
-	/// Sets the open file flags.
+	/// Sets the open file status flags.
 	///
-	/// File access mode (`O_RDONLY`, `O_WRONLY`, `O_RDWR`) and file creation flags
-	/// (`O_CREAT`, `O_EXCL`, `O_NOCTTY`, `O_TRUNC`) are ignored.
+	/// Only `O_APPEND`, `O_ASYNC`, `O_DIRECT`, `O_NOATIME` and `O_NONBLOCK` can be changed. Other
+	/// flags are ignored.
 	pub fn set_flags(&mut self, flags: i32) {
-		let ignored_flags = 0b11 | O_RDWR | O_CREAT | O_EXCL | O_NOCTTY | O_TRUNC;
-		self.flags = (self.flags & ignored_flags) | (flags & !ignored_flags);
+		self.flags = (self.flags & !STATUS_FLAGS) | (flags & STATUS_FLAGS);
+	}
+
+	/// Returns the recipient of the signals generated by the open file.
+	pub fn get_owner(&self) -> Owner {
+		self.owner.lock().owner
+	}
+
+	/// Sets the recipient of the signals generated by the open file.
+	pub fn set_owner(&mut self, owner: Owner) {
+		self.owner.lock().owner = owner;
+	}
+
+	/// Returns the signal sent when IO becomes possible on the open file. If zero, `SIGIO` is
+	/// sent.
+	pub fn get_sig(&self) -> c_int {
+		self.owner.lock().sig
+	}
+
+	/// Sets the signal sent when IO becomes possible on the open file. If zero, `SIGIO` is sent.
+	pub fn set_sig(&mut self, sig: c_int) {
+		self.owner.lock().sig = sig;
+	}
+
+	/// Sets whether accesses through the open file generate fanotify events.
+	pub fn set_notify(&mut self, notify: bool) {
+		self.notify = notify;
+	}
+
+	/// Submits the fanotify permission event `mask` for the open file `open_file`, then waits for
+	/// the decision of listeners.
+	///
+	/// Since the current process may sleep, `open_file` must not be locked.
+	///
+	/// If the access is denied, the function returns an error.
+	pub fn check_permission(open_file: &Mutex<Self>, mask: u64) -> EResult<()> {
+		let (file, location) = {
+			let open_file = open_file.lock();
+			if !open_file.notify {
+				return Ok(());
+			}
+			(open_file.get_file().clone(), open_file.location.clone())
+		};
+		fanotify::check_permission(&file, &location, mask)
+	}
+
+	/// Places a `flock` lock of type `kind` on the file, replacing the lock already held by the
+	/// open file description, if any. The replaced lock is released first.
+	///
+	/// If the lock cannot be acquired because of conflicting locks, the function returns `false`.
+	/// In this case, if `waiter` is given, the process is put to sleep until a lock on the file
+	/// is released.
+	pub fn flock(&mut self, kind: flock::Kind, waiter: Option<&mut Process>) -> EResult<bool> {
+		match self.flock {
+			Some(held) if held == kind => return Ok(true),
+			Some(_) => self.release_flock(),
+			None => {}
+		}
+		let acquired = flock::acquire(&self.location, kind, waiter)?;
+		if acquired {
+			self.flock = Some(kind);
+		}
+		Ok(acquired)
+	}
+
+	/// Releases the `flock` lock held by the open file description, if any.
+	pub fn release_flock(&mut self) {
+		if let Some(kind) = self.flock.take() {
+			flock::release(&self.location, kind);
+		}
+	}
+
+	/// Sets the advice on the pattern of accesses to the file.
+	pub fn set_advice(&mut self, advice: readahead::Advice) {
+		self.readahead.set_advice(advice);
 	}
 
 	/// Tells whether the open file can be read from.
 	pub fn can_read(&self) -> bool {
-		!matches!(self.flags & 0b11, O_WRONLY)
+		self.flags & O_PATH == 0 && !matches!(self.flags & 0b11, O_WRONLY)
 	}
 
 	/// Tells whether the open file can be written to.
 	pub fn can_write(&self) -> bool {
-		matches!(self.flags & 0b11, O_WRONLY | O_RDWR)
+		self.flags & O_PATH == 0 && matches!(self.flags & 0b11, O_WRONLY | O_RDWR)
 	}
 
 	/// Tells whether the access time (`atime`) must be updated on access.
@@ -193,7 +383,7 @@ impl OpenFile {
 		};
 		let mp_guard = mp.lock();
 
-		mp_guard.get_flags() & mountpoint::FLAG_NOATIME != 0
+		mp_guard.get_flags() & (mountpoint::FLAG_NOATIME | mountpoint::FLAG_RDONLY) == 0
 	}
 
 	/// Returns the current offset in the file.
@@ -206,6 +396,33 @@ impl OpenFile {
 		self.curr_off = off;
 	}
 
+	/// Returns the number of bytes out of `len` that can be written at the current offset
+	/// without the file's size exceeding `limit` (`RLIMIT_FSIZE`).
+	///
+	/// Only regular files are subject to the limit.
+	///
+	/// If the offset is already at or beyond the limit, the function returns
+	/// [`errno::EFBIG`].
+	pub fn check_size_limit(&self, len: usize, limit: RLim) -> EResult<usize> {
+		if limit == rlimit::RLIM_INFINITY {
+			return Ok(len);
+		}
+		let file = self.get_file().lock();
+		if !matches!(file.get_content(), FileContent::Regular) {
+			return Ok(len);
+		}
+
+		let off = if self.flags & O_APPEND != 0 {
+			file.get_size()
+		} else {
+			self.curr_off
+		};
+		if off >= limit {
+			return Err(errno!(EFBIG));
+		}
+		Ok(min(len as u64, limit - off) as _)
+	}
+
 	/// Performs an ioctl operation on the file.
 	pub fn ioctl(
 		&mut self,
@@ -219,7 +436,7 @@ impl OpenFile {
 				ioctl::FIONREAD => {
 					let mut mem_space_guard = mem_space.lock();
 					let count_ptr: SyscallPtr<c_int> = (argp as usize).into();
-					let count_ref = count_ptr
+					let mut count_ref = count_ptr
 						.get_mut(&mut mem_space_guard)?
 						.ok_or_else(|| errno!(EFAULT))?;
 
@@ -291,6 +508,49 @@ impl OpenFile {
 
 		Ok(())
 	}
+
+	/// Registers or unregisters the open file for signal-driven IO on the underlying resource.
+	///
+	/// Arguments:
+	/// - `fd` is the file descriptor reported to the owner.
+	/// - `on` tells whether the open file is registered.
+	///
+	/// If the file cannot block, the function does nothing.
+	pub fn set_async(&mut self, fd: c_int, on: bool) -> EResult<()> {
+		let file = self.get_file().lock();
+		let (type_, major, minor) = match file.get_content() {
+			FileContent::Fifo | FileContent::Socket => {
+				if let Some(buff_mutex) = buffer::get(self.get_location()) {
+					let mut buff = buff_mutex.lock();
+					return buff.set_async(fd, &self.owner, on);
+				}
+				return Ok(());
+			}
+
+			FileContent::BlockDevice {
+				major,
+				minor,
+			} => (DeviceType::Block, *major, *minor),
+
+			FileContent::CharDevice {
+				major,
+				minor,
+			} => (DeviceType::Char, *major, *minor),
+
+			_ => return Ok(()),
+		};
+
+		let dev_mutex = device::get(&DeviceID {
+			type_,
+			major,
+			minor,
+		});
+		if let Some(dev_mutex) = dev_mutex {
+			let mut dev = dev_mutex.lock();
+			dev.get_handle().set_async(fd, &self.owner, on)?;
+		}
+		Ok(())
+	}
 }
 
 impl IO for OpenFile {
@@ -311,13 +571,21 @@ impl IO for OpenFile {
 		}
 
 		// Update access timestamp
-		let timestamp = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Second).unwrap_or(0);
+		let timestamp = clock::current_time_struct::<Timespec>(CLOCK_REALTIME).unwrap_or_default();
 		if self.is_atime_updated() {
 			file.atime = timestamp;
-			file.sync()?; // TODO Lazy
+			writeback::mark_dirty(self.get_file(), &self.location)?;
 		}
 
 		let (len, eof) = file.read(self.curr_off, buf)?;
+		if matches!(file.get_content(), FileContent::Regular) {
+			self.readahead
+				.on_read(self.file.as_ref().unwrap(), self.curr_off, len)?;
+		}
+		drop(file);
+		if self.notify {
+			fanotify::notify(self.get_file(), &self.location, fanotify::FAN_ACCESS);
+		}
 
 		self.curr_off += len;
 		Ok((len as _, eof))
@@ -341,14 +609,20 @@ impl IO for OpenFile {
 		}
 
 		// Update access timestamps
-		let timestamp = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Second).unwrap_or(0);
+		let timestamp = clock::current_time_struct::<Timespec>(CLOCK_REALTIME).unwrap_or_default();
 		if self.is_atime_updated() {
 			file.atime = timestamp;
 		}
 		file.mtime = timestamp;
-		file.sync()?; // TODO Lazy
 
 		let len = file.write(self.curr_off, buf)?;
+		writeback::mark_dirty(self.get_file(), &self.location)?;
+		inotify::notify_file(&file, inotify::IN_MODIFY);
+		drop(file);
+		if self.notify {
+			fanotify::notify(self.get_file(), &self.location, fanotify::FAN_MODIFY);
+		}
+		writeback::balance()?;
 
 		self.curr_off += len;
 		Ok(len as _)
@@ -361,6 +635,7 @@ impl IO for OpenFile {
 
 impl Drop for OpenFile {
 	fn drop(&mut self) {
+		self.release_flock();
 		// If the file points to a buffer, decrement the number of open ends
 		if let Some(buff_mutex) = buffer::get(&self.location) {
 			let mut buff = buff_mutex.lock();
@@ -376,5 +651,10 @@ impl Drop for OpenFile {
 				}
 			}
 		}
+		memfd::release_if_unused(&self.location);
+		inotify::release_if_unused(&self.location);
+		fanotify::release_if_unused(&self.location);
+		pidfd::release_if_unused(&self.location);
+		io_uring::release_if_unused(&self.location);
 	}
 }
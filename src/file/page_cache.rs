@@ -0,0 +1,715 @@
+//! The page cache keeps the content of regular files in memory, by pages.
+//!
+//! Every read and write on a regular file goes through the page cache, so that the filesystem
+//! driver is queried only when a page is not in memory yet. Modified pages are marked as dirty
+//! and written back to the file later.
+//!
+//! Pages used by file-backed memory mappings are the same as the ones in the cache, so that
+//! writes performed through a mapping are visible to subsequent reads on the file and
+//! conversely.
+//!
+//! Each cached page holds one reference on the physical reference counter, plus one for each
+//! mapping pointing to it. A page that is mapped must not be evicted.
+
+use crate::errno::EResult;
+use crate::file::File;
+use crate::file::FileLocation;
+use crate::memory;
+use crate::memory::buddy;
+use crate::memory::physical_ref_counter::PhysRefCounter;
+use crate::process::mem_space::rmap;
+use crate::process::mem_space::PHYSICAL_REF_COUNTER;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::map::Map;
+use crate::util::lock::Mutex;
+use core::cmp::max;
+use core::cmp::min;
+use core::ffi::c_void;
+use core::ops::RangeBounds;
+use core::ptr::NonNull;
+use core::slice;
+use core::sync::atomic;
+use core::sync::atomic::AtomicUsize;
+
+/// The number of dirty pages in the cache.
+static DIRTY_PAGES: AtomicUsize = AtomicUsize::new(0);
+
+/// A page of a file in the cache.
+struct Page {
+	/// The physical pointer to the page.
+	ptr: NonNull<c_void>,
+	/// Tells whether the page has been modified since the last time it was written back.
+	dirty: bool,
+}
+
+impl Page {
+	/// Sets whether the page is dirty, updating the number of dirty pages accordingly.
+	fn set_dirty(&mut self, dirty: bool) {
+		match (self.dirty, dirty) {
+			(false, true) => DIRTY_PAGES.fetch_add(1, atomic::Ordering::Relaxed),
+			(true, false) => DIRTY_PAGES.fetch_sub(1, atomic::Ordering::Relaxed),
+			_ => return,
+		};
+		self.dirty = dirty;
+	}
+}
+
+/// The cached pages of a file.
+struct CachedFile {
+	/// The cached pages, by offset in pages.
+	pages: Map<u64, Page>,
+	/// The size of the file in bytes, including data that has not been written back yet.
+	size: u64,
+	/// Tells whether the file has been removed. If set, pages are not written back anymore.
+	removed: bool,
+}
+
+impl CachedFile {
+	/// Creates a new instance for the given file.
+	fn new(file: &File) -> Self {
+		Self {
+			pages: Map::new(),
+			size: file.size,
+			removed: false,
+		}
+	}
+
+	/// Writes the dirty pages in the range `range`, in pages, back to the file `file`.
+	///
+	/// If `mapped_dirty` is set, pages that are mapped in memory are considered dirty, since they
+	/// may have been modified through a mapping without the cache knowing it.
+	fn sync<R: RangeBounds<u64>>(
+		&mut self,
+		file: &mut File,
+		range: R,
+		mapped_dirty: bool,
+	) -> EResult<()> {
+		if self.removed || !has_storage(file.get_location()) {
+			return Ok(());
+		}
+
+		// Pages are written in order since a filesystem may not allow writing past the end of a
+		// file
+		let size = self.size;
+		for (off, page) in self.pages.range_mut(range) {
+			let mapped = mapped_dirty && PHYSICAL_REF_COUNTER.lock().is_shared(page.ptr.as_ptr());
+			if !page.dirty && !mapped {
+				continue;
+			}
+			write_back(file, size, *off, page.ptr)?;
+			page.set_dirty(false);
+		}
+
+		Ok(())
+	}
+
+	/// Tells whether at least one page of the file is mapped in memory.
+	fn is_mapped(&self) -> bool {
+		let ref_counter = PHYSICAL_REF_COUNTER.lock();
+		self.pages
+			.iter()
+			.any(|(_, page)| ref_counter.is_shared(page.ptr.as_ptr()))
+	}
+}
+
+/// The cached files, by location.
+///
+/// To avoid deadlocks, the lock of a file must always be acquired before the lock of the cache.
+static PAGE_CACHE: Mutex<HashMap<FileLocation, CachedFile>> = Mutex::new(HashMap::new());
+
+/// Returns a slice to the content of the page at the given physical address.
+///
+/// # Safety
+///
+/// The page must be a cached page, allocated in the kernel zone.
+unsafe fn page_content<'a>(ptr: NonNull<c_void>) -> &'a mut [u8] {
+	let virt_ptr = memory::kern_to_virt(ptr.as_ptr()) as *mut u8;
+	slice::from_raw_parts_mut(virt_ptr, memory::PAGE_SIZE)
+}
+
+/// Tells whether the file at location `loc` has a storage to which cached pages are written
+/// back, which allows evicting them.
+///
+/// Anonymous files are the only regular files with a virtual location. The cache is their only
+/// storage, so their pages are never dirty nor evicted.
+fn has_storage(loc: &FileLocation) -> bool {
+	matches!(loc, FileLocation::Filesystem { .. })
+}
+
+/// Releases the reference of the cache on the given page, freeing it if not used anymore.
+fn free_page(ref_counter: &mut PhysRefCounter, ptr: NonNull<c_void>) {
+	ref_counter.decrement(ptr.as_ptr());
+	if ref_counter.can_free(ptr.as_ptr()) {
+		buddy::free(ptr.as_ptr(), 0);
+	}
+}
+
+/// Writes the content of the page at offset `off` in pages back to the file `file`.
+///
+/// Only the part of the page that is within the file's size `size` is written.
+fn write_back(file: &mut File, size: u64, off: u64, ptr: NonNull<c_void>) -> EResult<()> {
+	let file_off = off * memory::PAGE_SIZE as u64;
+	if file_off >= size {
+		return Ok(());
+	}
+
+	let len = min(size - file_off, memory::PAGE_SIZE as u64) as usize;
+	let content = unsafe { &page_content(ptr)[..len] };
+
+	let mut i = 0;
+	while i < len {
+		let l = file.write_raw(file_off + i as u64, &content[i..])? as usize;
+		if l == 0 {
+			break;
+		}
+		i += l;
+	}
+
+	Ok(())
+}
+
+/// Reads the page at offset `off` in pages of the file `file`, to be inserted in the cache.
+///
+/// If `fill` is `false`, the content of the file is not read and the page is filled with zeros
+/// instead.
+///
+/// On success, the function returns the physical address of the page, referenced once on the
+/// physical reference counter.
+fn read_page(file: &mut File, off: u64, fill: bool) -> EResult<NonNull<c_void>> {
+	let ptr = buddy::alloc(0, buddy::FLAG_ZONE_TYPE_KERNEL)?;
+	let content = unsafe { page_content(ptr) };
+	let len = if fill {
+		match file.read_raw(off * memory::PAGE_SIZE as u64, content) {
+			Ok((len, _)) => len as usize,
+			Err(e) => {
+				buddy::free(ptr.as_ptr(), 0);
+				return Err(e);
+			}
+		}
+	} else {
+		0
+	};
+	content[len..].fill(0);
+
+	if let Err(e) = PHYSICAL_REF_COUNTER.lock().increment(ptr.as_ptr()) {
+		buddy::free(ptr.as_ptr(), 0);
+		return Err(e.into());
+	}
+	Ok(ptr)
+}
+
+/// Returns the cached file associated with `file` in the cache `page_cache`.
+///
+/// If the file is not in the cache yet, the function inserts it.
+fn get_cached_file<'c>(
+	page_cache: &'c mut HashMap<FileLocation, CachedFile>,
+	file: &File,
+) -> EResult<&'c mut CachedFile> {
+	let loc = file.get_location();
+	if page_cache.get(loc).is_none() {
+		page_cache.insert(loc.clone(), CachedFile::new(file))?;
+	}
+	Ok(page_cache.get_mut(loc).unwrap())
+}
+
+/// Executes the given closure with the page at offset `off` in pages of the file `file`.
+///
+/// If the page is not in the cache, the function reads it from the file, then inserts it. If
+/// `fill` is `false`, the content of the file is not read and the page is filled with zeros
+/// instead.
+///
+/// The lock on the cache is not held while reading the file, so that other files can be
+/// accessed in the meantime.
+fn page_do<T, F: FnOnce(&mut Page) -> EResult<T>>(
+	file: &mut File,
+	off: u64,
+	fill: bool,
+	f: F,
+) -> EResult<T> {
+	let loc = file.get_location();
+	{
+		let mut page_cache = PAGE_CACHE.lock();
+		if let Some(page) = page_cache
+			.get_mut(loc)
+			.and_then(|cached_file| cached_file.pages.get_mut(off))
+		{
+			return f(page);
+		}
+	}
+
+	let ptr = read_page(file, off, fill)?;
+	let mut page_cache = PAGE_CACHE.lock();
+	let cached_file = match get_cached_file(&mut page_cache, file) {
+		Ok(cached_file) => cached_file,
+		Err(e) => {
+			free_page(&mut PHYSICAL_REF_COUNTER.lock(), ptr);
+			return Err(e);
+		}
+	};
+	// Since the file is locked, the page cannot have been inserted in the meantime
+	let page = Page {
+		ptr,
+		dirty: false,
+	};
+	match cached_file.pages.insert(off, page) {
+		Ok(page) => f(page),
+		Err(e) => {
+			free_page(&mut PHYSICAL_REF_COUNTER.lock(), ptr);
+			Err(e.into())
+		}
+	}
+}
+
+/// Returns the size of the file `file` in bytes, taking into account data that has not been
+/// written back yet.
+pub fn get_size(file: &File) -> u64 {
+	let page_cache = PAGE_CACHE.lock();
+	match page_cache.get(file.get_location()) {
+		Some(cached_file) => cached_file.size,
+		None => file.size,
+	}
+}
+
+/// Reads the content of the file `file` at offset `off` in bytes to the buffer `buf`.
+///
+/// The function returns the number of bytes read and whether the end of the file has been
+/// reached.
+pub fn read(file: &mut File, off: u64, buf: &mut [u8]) -> EResult<(u64, bool)> {
+	let size = get_size(file);
+	if off >= size {
+		return Ok((0, true));
+	}
+	let len = min(buf.len() as u64, size - off) as usize;
+
+	let mut i = 0;
+	while i < len {
+		let cur = off + i as u64;
+		let page_off = cur / memory::PAGE_SIZE as u64;
+		let inner_off = (cur % memory::PAGE_SIZE as u64) as usize;
+		let l = min(len - i, memory::PAGE_SIZE - inner_off);
+
+		page_do(file, page_off, true, |page| {
+			let content = unsafe { page_content(page.ptr) };
+			buf[i..(i + l)].copy_from_slice(&content[inner_off..(inner_off + l)]);
+			Ok(())
+		})?;
+
+		i += l;
+	}
+
+	let eof = off + len as u64 >= size;
+	Ok((len as _, eof))
+}
+
+/// Writes the buffer `buf` to the file `file` at offset `off` in bytes.
+///
+/// Modified pages are marked as dirty and written back to the file later.
+///
+/// The function returns the number of bytes written.
+pub fn write(file: &mut File, off: u64, buf: &[u8]) -> EResult<u64> {
+	let dirty = has_storage(file.get_location());
+	let mut i = 0;
+	while i < buf.len() {
+		let cur = off + i as u64;
+		let page_off = cur / memory::PAGE_SIZE as u64;
+		let inner_off = (cur % memory::PAGE_SIZE as u64) as usize;
+		let l = min(buf.len() - i, memory::PAGE_SIZE - inner_off);
+
+		// If the whole page is overwritten, reading its previous content is useless
+		let fill = l < memory::PAGE_SIZE;
+		page_do(file, page_off, fill, |page| {
+			let content = unsafe { page_content(page.ptr) };
+			content[inner_off..(inner_off + l)].copy_from_slice(&buf[i..(i + l)]);
+			page.set_dirty(dirty);
+			Ok(())
+		})?;
+
+		i += l;
+	}
+
+	let size = {
+		let mut page_cache = PAGE_CACHE.lock();
+		let cached_file = get_cached_file(&mut page_cache, file)?;
+		cached_file.size = max(cached_file.size, off + buf.len() as u64);
+		cached_file.size
+	};
+	file.size = size;
+
+	Ok(buf.len() as _)
+}
+
+/// Sets the size of the file `file` to `size` bytes.
+///
+/// Cached pages that are past the new end of the file are discarded, unless they are mapped in
+/// memory.
+pub fn truncate(file: &mut File, size: u64) {
+	file.size = size;
+
+	let mut page_cache = PAGE_CACHE.lock();
+	let Some(cached_file) = page_cache.get_mut(file.get_location()) else {
+		return;
+	};
+	let mut ref_counter = PHYSICAL_REF_COUNTER.lock();
+	let first = size / memory::PAGE_SIZE as u64;
+
+	// Zeroing the end of the last page
+	let inner_off = (size % memory::PAGE_SIZE as u64) as usize;
+	if let Some(page) = cached_file.pages.get_mut(first) {
+		unsafe {
+			page_content(page.ptr)[inner_off..].fill(0);
+		}
+	}
+
+	let first_removed = if inner_off == 0 { first } else { first + 1 };
+	cached_file.pages.retain(|off, page| {
+		let keep = *off < first_removed || ref_counter.is_shared(page.ptr.as_ptr());
+		if !keep {
+			page.set_dirty(false);
+			free_page(&mut ref_counter, page.ptr);
+		}
+		keep
+	});
+
+	cached_file.size = size;
+}
+
+/// Zeroes the cached content of the file `file` in the range of `len` bytes beginning at offset
+/// `off`, after a hole has been punched in it.
+///
+/// Pages that are entirely in the range are discarded, unless they are mapped in memory. Dirty
+/// pages in the range must have been written back beforehand, since their modifications are
+/// lost.
+pub fn punch_hole(file: &File, off: u64, len: u64) {
+	let mut page_cache = PAGE_CACHE.lock();
+	let Some(cached_file) = page_cache.get_mut(file.get_location()) else {
+		return;
+	};
+	let mut ref_counter = PHYSICAL_REF_COUNTER.lock();
+	let end = off.saturating_add(len);
+	cached_file.pages.retain(|page_off, page| {
+		let page_begin = *page_off * memory::PAGE_SIZE as u64;
+		let page_end = page_begin + memory::PAGE_SIZE as u64;
+		if page_end <= off || page_begin >= end {
+			return true;
+		}
+
+		let whole = off <= page_begin && page_end <= end;
+		if whole && !ref_counter.is_shared(page.ptr.as_ptr()) {
+			page.set_dirty(false);
+			free_page(&mut ref_counter, page.ptr);
+			return false;
+		}
+		let inner_begin = (max(off, page_begin) - page_begin) as usize;
+		let inner_end = (min(end, page_end) - page_begin) as usize;
+		unsafe {
+			page_content(page.ptr)[inner_begin..inner_end].fill(0);
+		}
+		true
+	});
+}
+
+/// Returns the offset of the beginning of the next cached region of the file at location `loc`,
+/// or of the next region that is not cached if `hole` is set, at or after offset `off`.
+///
+/// This is meaningful only for files whose only storage is the cache, for which pages that are
+/// not cached are holes. If no such region is found, the function returns `None`.
+pub fn next_extent(loc: &FileLocation, off: u64, hole: bool) -> Option<u64> {
+	let page_cache = PAGE_CACHE.lock();
+	let first = off / memory::PAGE_SIZE as u64;
+	let pages = page_cache
+		.get(loc)
+		.map(|cached_file| cached_file.pages.range(first..));
+	let page_off = |i: u64| max(i * memory::PAGE_SIZE as u64, off);
+	if !hole {
+		let (i, _) = pages?.next()?;
+		return Some(page_off(*i));
+	}
+
+	// Looking for the first gap between cached pages
+	let mut i = first;
+	for (page_i, _) in pages.into_iter().flatten() {
+		if *page_i != i {
+			break;
+		}
+		i += 1;
+	}
+	Some(page_off(i))
+}
+
+/// Writes every dirty pages of the file `file` back to it.
+pub fn sync(file: &mut File) -> EResult<()> {
+	let mut page_cache = PAGE_CACHE.lock();
+	let Some(cached_file) = page_cache.get_mut(file.get_location()) else {
+		return Ok(());
+	};
+	cached_file.sync(file, .., true)
+}
+
+/// Writes the dirty pages of the file `file` in the range beginning at offset `off` with size
+/// `pages` pages back to it.
+///
+/// Contrary to [`sync`], pages that are mapped in memory are written only if they have been
+/// marked as dirty with [`mark_dirty`].
+pub fn sync_range(file: &mut File, off: u64, pages: usize) -> EResult<()> {
+	let mut page_cache = PAGE_CACHE.lock();
+	let Some(cached_file) = page_cache.get_mut(file.get_location()) else {
+		return Ok(());
+	};
+	cached_file.sync(file, off..off.saturating_add(pages as u64), false)
+}
+
+/// Marks the page at offset `off` in pages of the file at location `loc` as dirty, after it has
+/// been modified through a memory mapping.
+///
+/// If the page is not in the cache, the function does nothing.
+pub fn mark_dirty(loc: &FileLocation, off: u64) {
+	let mut page_cache = PAGE_CACHE.lock();
+	let Some(page) = page_cache
+		.get_mut(loc)
+		.and_then(|cached_file| cached_file.pages.get_mut(off))
+	else {
+		return;
+	};
+	page.set_dirty(has_storage(loc));
+}
+
+/// Reads the `pages` pages of the file `file` beginning at offset `off` in pages into the cache,
+/// if they are not already present.
+///
+/// Pages past the end of the file are ignored.
+pub fn prefetch(file: &mut File, off: u64, pages: usize) -> EResult<()> {
+	let size_pages = get_size(file).div_ceil(memory::PAGE_SIZE as u64);
+	let end = min(off.saturating_add(pages as u64), size_pages);
+	for off in off..end {
+		page_do(file, off, true, |_| Ok(()))?;
+	}
+	Ok(())
+}
+
+/// Writes back then evicts the pages of the file `file` in the range beginning at offset `off`
+/// with size `pages` pages.
+///
+/// Pages that are mapped in memory are not evicted.
+pub fn discard(file: &mut File, off: u64, pages: usize) -> EResult<()> {
+	let loc = file.get_location();
+	if !has_storage(loc) {
+		return Ok(());
+	}
+	let mut page_cache = PAGE_CACHE.lock();
+	let Some(cached_file) = page_cache.get_mut(loc) else {
+		return Ok(());
+	};
+	let range = off..off.saturating_add(pages as u64);
+	cached_file.sync(file, range.clone(), false)?;
+
+	let mut ref_counter = PHYSICAL_REF_COUNTER.lock();
+	cached_file.pages.retain(|off, page| {
+		let keep = !range.contains(off) || page.dirty || ref_counter.is_shared(page.ptr.as_ptr());
+		if !keep {
+			free_page(&mut ref_counter, page.ptr);
+		}
+		keep
+	});
+	if cached_file.pages.is_empty() {
+		page_cache.remove(file.get_location());
+	}
+	Ok(())
+}
+
+/// Maps the page at offset `off` in pages of the file `file`.
+///
+/// On success, the function returns the physical address of the page. The page is referenced
+/// once more on the physical reference counter, and the reference must be released with
+/// [`unmap`] when the page is not used anymore.
+pub fn map(file: &mut File, off: u64) -> EResult<NonNull<c_void>> {
+	page_do(file, off, true, |page| {
+		PHYSICAL_REF_COUNTER.lock().increment(page.ptr.as_ptr())?;
+		Ok(page.ptr)
+	})
+}
+
+/// Releases a mapping of the page at offset `off` in pages of the file at location `loc`.
+///
+/// `ptr` is the physical address of the page that was mapped. If it doesn't match the cached
+/// page, the function does nothing.
+///
+/// The caller must have released its reference on the physical reference counter beforehand.
+pub fn unmap(loc: &FileLocation, off: u64, ptr: *const c_void) {
+	let mut page_cache = PAGE_CACHE.lock();
+	let Some(cached_file) = page_cache.get_mut(loc) else {
+		return;
+	};
+	let Some(page) = cached_file.pages.get_mut(off) else {
+		return;
+	};
+	if page.ptr.as_ptr() as *const _ != ptr {
+		return;
+	}
+
+	// TODO Make use of dirty flag if present on the current architecure to know whether the page
+	// has been modified through the mapping
+	page.set_dirty(has_storage(loc));
+
+	// If the file has been removed, the page is useless once unmapped
+	let mut ref_counter = PHYSICAL_REF_COUNTER.lock();
+	free_if_removed(&mut page_cache, &mut ref_counter, loc, off);
+}
+
+/// If the file at location `loc` has been removed, frees its cached page at offset `off` in
+/// pages, unless it is still shared.
+fn free_if_removed(
+	page_cache: &mut HashMap<FileLocation, CachedFile>,
+	ref_counter: &mut PhysRefCounter,
+	loc: &FileLocation,
+	off: u64,
+) {
+	let Some(cached_file) = page_cache.get_mut(loc) else {
+		return;
+	};
+	let Some(page) = cached_file.pages.get(off) else {
+		return;
+	};
+	if !cached_file.removed || ref_counter.is_shared(page.ptr.as_ptr()) {
+		return;
+	}
+	let mut page = cached_file.pages.remove(&off).unwrap();
+	page.set_dirty(false);
+	free_page(ref_counter, page.ptr);
+	if cached_file.pages.is_empty() {
+		page_cache.remove(loc);
+	}
+}
+
+/// Pins the page at offset `off` in pages of the file `file`, so that its content can be
+/// accessed without holding the lock on the file. This allows transferring data between files
+/// without copying it to an intermediate buffer.
+///
+/// A pinned page is not evicted from the cache. It must be released with [`unpin`].
+///
+/// On success, the function returns the physical address of the page.
+pub fn pin(file: &mut File, off: u64) -> EResult<NonNull<c_void>> {
+	map(file, off)
+}
+
+/// Returns a slice to the content of the pinned page at the given physical address.
+///
+/// # Safety
+///
+/// The page must have been returned by [`pin`] and must not be unpinned while the slice is used.
+pub unsafe fn pinned_content<'a>(ptr: NonNull<c_void>) -> &'a [u8] {
+	page_content(ptr)
+}
+
+/// Releases a page pinned with [`pin`].
+///
+/// `loc` and `off` are the location of the file and the offset of the page in pages, and `ptr`
+/// is the physical address of the page.
+///
+/// Contrary to [`unmap`], the page is not marked dirty since it is only read through pins.
+pub fn unpin(loc: &FileLocation, off: u64, ptr: NonNull<c_void>) {
+	let mut page_cache = PAGE_CACHE.lock();
+	let mut ref_counter = PHYSICAL_REF_COUNTER.lock();
+	let cached = page_cache
+		.get(loc)
+		.and_then(|cached_file| cached_file.pages.get(off))
+		.map(|page| page.ptr == ptr)
+		.unwrap_or(false);
+	if !cached {
+		// The page left the cache while pinned, so it is not referenced by the cache anymore
+		free_page(&mut ref_counter, ptr);
+		return;
+	}
+	ref_counter.decrement(ptr.as_ptr());
+	// If the file has been removed, the page is useless once unpinned
+	free_if_removed(&mut page_cache, &mut ref_counter, loc, off);
+}
+
+/// Returns the number of pages in the cache.
+pub fn get_cached_pages() -> usize {
+	PAGE_CACHE
+		.lock()
+		.iter()
+		.map(|(_, cached_file)| cached_file.pages.len())
+		.sum()
+}
+
+/// Returns the number of dirty pages in the cache.
+pub fn get_dirty_pages() -> usize {
+	DIRTY_PAGES.load(atomic::Ordering::Relaxed)
+}
+
+/// Tells whether the file at location `loc` is in the cache.
+pub fn contains(loc: &FileLocation) -> bool {
+	PAGE_CACHE.lock().contains_key(loc)
+}
+
+/// Tells whether at least one page of the file at location `loc` is mapped in memory.
+pub fn is_mapped(loc: &FileLocation) -> bool {
+	PAGE_CACHE
+		.lock()
+		.get(loc)
+		.map(CachedFile::is_mapped)
+		.unwrap_or(false)
+}
+
+/// Discards the cached pages of the file at location `loc`, without writing them back.
+///
+/// This function is meant to be called when the file is removed. Pages that are still mapped in
+/// memory remain until they are unmapped.
+pub fn remove(loc: &FileLocation) {
+	let mut page_cache = PAGE_CACHE.lock();
+	let Some(cached_file) = page_cache.get_mut(loc) else {
+		return;
+	};
+
+	let mut ref_counter = PHYSICAL_REF_COUNTER.lock();
+	cached_file.pages.retain(|_, page| {
+		let keep = ref_counter.is_shared(page.ptr.as_ptr());
+		if !keep {
+			page.set_dirty(false);
+			free_page(&mut ref_counter, page.ptr);
+		}
+		keep
+	});
+
+	cached_file.removed = true;
+	if cached_file.pages.is_empty() {
+		page_cache.remove(loc);
+	}
+}
+
+/// Evicts up to `count` pages from the cache to free memory.
+///
+/// Only pages that are not dirty are evicted. Pages that are mapped are evicted only if they are
+/// not mapped by any shared mapping.
+///
+/// The function returns the number of evicted pages.
+pub fn shrink(count: usize) -> usize {
+	let mut page_cache = PAGE_CACHE.lock();
+
+	let mut evicted = 0;
+	let mut ref_counter = PHYSICAL_REF_COUNTER.lock();
+	page_cache.retain(|loc, cached_file| {
+		if evicted >= count || !has_storage(loc) {
+			return true;
+		}
+
+		cached_file.pages.retain(|_, page| {
+			if evicted >= count || page.dirty {
+				return true;
+			}
+			// Pages mapped only by private mappings cannot have been modified through them, so
+			// they can be unmapped and read back from the file on the next access
+			let keep = ref_counter.is_shared(page.ptr.as_ptr())
+				&& !rmap::unmap_private(page.ptr.as_ptr(), &mut ref_counter, 1);
+			if !keep {
+				free_page(&mut ref_counter, page.ptr);
+				evicted += 1;
+			}
+			keep
+		});
+		!cached_file.pages.is_empty()
+	});
+
+	evicted
+}
@@ -1,6 +1,13 @@
 //! UNIX permissions are detailed in the POSIX specification.
 //!
 //! This module implements management of such permissions.
+//!
+//! The privileges of the superuser are split into capabilities, which can be held independently.
+//! An agent holds several sets of capabilities:
+//! - permitted: the capabilities the agent may use
+//! - effective: the capabilities that are actually used for permission checks
+//! - inheritable: the capabilities that may be kept across a program execution
+//! - bounding: the limit on the capabilities that may be gained on program execution
 
 use super::Mode;
 use crate::errno::EResult;
@@ -47,6 +54,155 @@ pub const S_ISGID: Mode = 0o2000;
 /// Sticky bit.
 pub const S_ISVTX: Mode = 0o1000;
 
+/// Change the owner and group of files.
+pub const CAP_CHOWN: u32 = 0;
+/// Bypass file read, write and execute permission checks.
+pub const CAP_DAC_OVERRIDE: u32 = 1;
+/// Bypass file read and directory search permission checks.
+pub const CAP_DAC_READ_SEARCH: u32 = 2;
+/// Bypass checks requiring to be the owner of a file.
+pub const CAP_FOWNER: u32 = 3;
+/// Keep set-user-ID and set-group-ID bits when modifying a file.
+pub const CAP_FSETID: u32 = 4;
+/// Bypass permission checks for sending signals.
+pub const CAP_KILL: u32 = 5;
+/// Change group IDs arbitrarily.
+pub const CAP_SETGID: u32 = 6;
+/// Change user IDs arbitrarily.
+pub const CAP_SETUID: u32 = 7;
+/// Modify the bounding set and add any capability from the bounding set to the inheritable set.
+pub const CAP_SETPCAP: u32 = 8;
+/// Set the immutable and append-only flags of files.
+pub const CAP_LINUX_IMMUTABLE: u32 = 9;
+/// Bind sockets to privileged ports.
+pub const CAP_NET_BIND_SERVICE: u32 = 10;
+/// Broadcast and listen to multicast.
+pub const CAP_NET_BROADCAST: u32 = 11;
+/// Perform network administration operations.
+pub const CAP_NET_ADMIN: u32 = 12;
+/// Use raw and packet sockets.
+pub const CAP_NET_RAW: u32 = 13;
+/// Lock memory.
+pub const CAP_IPC_LOCK: u32 = 14;
+/// Bypass permission checks on System V IPC objects.
+pub const CAP_IPC_OWNER: u32 = 15;
+/// Load and unload kernel modules.
+pub const CAP_SYS_MODULE: u32 = 16;
+/// Perform I/O port operations.
+pub const CAP_SYS_RAWIO: u32 = 17;
+/// Use `chroot`.
+pub const CAP_SYS_CHROOT: u32 = 18;
+/// Trace arbitrary processes.
+pub const CAP_SYS_PTRACE: u32 = 19;
+/// Use `acct`.
+pub const CAP_SYS_PACCT: u32 = 20;
+/// Perform a range of system administration operations.
+pub const CAP_SYS_ADMIN: u32 = 21;
+/// Use `reboot` and `kexec_load`.
+pub const CAP_SYS_BOOT: u32 = 22;
+/// Change the scheduling of arbitrary processes and raise priorities.
+pub const CAP_SYS_NICE: u32 = 23;
+/// Override resource limits.
+pub const CAP_SYS_RESOURCE: u32 = 24;
+/// Set the system clock.
+pub const CAP_SYS_TIME: u32 = 25;
+/// Perform privileged operations on virtual terminals.
+pub const CAP_SYS_TTY_CONFIG: u32 = 26;
+/// Create device files with `mknod`.
+pub const CAP_MKNOD: u32 = 27;
+/// Establish leases on arbitrary files.
+pub const CAP_LEASE: u32 = 28;
+/// Write records to the kernel auditing log.
+pub const CAP_AUDIT_WRITE: u32 = 29;
+/// Configure the kernel auditing.
+pub const CAP_AUDIT_CONTROL: u32 = 30;
+/// Set the capabilities of files.
+pub const CAP_SETFCAP: u32 = 31;
+/// Override Mandatory Access Control.
+pub const CAP_MAC_OVERRIDE: u32 = 32;
+/// Configure Mandatory Access Control.
+pub const CAP_MAC_ADMIN: u32 = 33;
+/// Perform privileged `syslog` operations and view kernel addresses.
+pub const CAP_SYSLOG: u32 = 34;
+/// Trigger something that will wake up the system.
+pub const CAP_WAKE_ALARM: u32 = 35;
+/// Block system suspend.
+pub const CAP_BLOCK_SUSPEND: u32 = 36;
+/// Read the audit log.
+pub const CAP_AUDIT_READ: u32 = 37;
+/// Use performance monitoring.
+pub const CAP_PERFMON: u32 = 38;
+/// Use privileged BPF operations.
+pub const CAP_BPF: u32 = 39;
+/// Perform checkpoint and restore operations.
+pub const CAP_CHECKPOINT_RESTORE: u32 = 40;
+/// The last valid capability.
+pub const CAP_LAST_CAP: u32 = CAP_CHECKPOINT_RESTORE;
+
+/// Type representing a set of capabilities, where each bit corresponds to a capability.
+pub type CapSet = u64;
+/// The set containing every capabilities.
+pub const CAP_FULL_SET: CapSet = (1 << (CAP_LAST_CAP + 1)) - 1;
+
+/// The capabilities related to the filesystem, which follow the filesystem user ID.
+const CAP_FS_SET: CapSet = (1 << CAP_CHOWN)
+	| (1 << CAP_DAC_OVERRIDE)
+	| (1 << CAP_DAC_READ_SEARCH)
+	| (1 << CAP_FOWNER)
+	| (1 << CAP_FSETID)
+	| (1 << CAP_LINUX_IMMUTABLE)
+	| (1 << CAP_MKNOD)
+	| (1 << CAP_MAC_OVERRIDE);
+
+/// The name of the extended attribute storing the capabilities of a file.
+const XATTR_CAPS_NAME: &[u8] = b"security.capability";
+/// Mask of the revision in the capabilities of a file.
+const VFS_CAP_REVISION_MASK: u32 = 0xff000000;
+/// Revision 1 of file capabilities, with 32 bits sets.
+const VFS_CAP_REVISION_1: u32 = 0x01000000;
+/// Revision 2 of file capabilities, with 64 bits sets.
+const VFS_CAP_REVISION_2: u32 = 0x02000000;
+/// Revision 3 of file capabilities, with 64 bits sets and the ID of the root user.
+const VFS_CAP_REVISION_3: u32 = 0x03000000;
+/// If set in the capabilities of a file, the permitted set becomes effective on execution.
+const VFS_CAP_FLAGS_EFFECTIVE: u32 = 0x000001;
+
+/// Returns the set containing only the given capability.
+///
+/// If the capability is invalid, the function returns an empty set.
+pub fn cap_to_set(cap: u32) -> CapSet {
+	if cap <= CAP_LAST_CAP {
+		1 << cap
+	} else {
+		0
+	}
+}
+
+/// Returns the capabilities of the given file, read from its extended attributes.
+///
+/// The function returns the permitted set, the inheritable set and whether the permitted set
+/// is effective. If the file has no capabilities, the function returns `None`.
+fn get_file_caps(file: &File) -> Option<(CapSet, CapSet, bool)> {
+	let val = file
+		.get_xattr(&AccessProfile::KERNEL, XATTR_CAPS_NAME)
+		.ok()?;
+	let word = |i: usize| -> Option<u32> {
+		let bytes = val.get((i * 4)..((i + 1) * 4))?;
+		Some(u32::from_le_bytes(bytes.try_into().ok()?))
+	};
+	let magic = word(0)?;
+	let effective = magic & VFS_CAP_FLAGS_EFFECTIVE != 0;
+	match magic & VFS_CAP_REVISION_MASK {
+		VFS_CAP_REVISION_1 => Some((word(1)? as _, word(2)? as _, effective)),
+		VFS_CAP_REVISION_2 | VFS_CAP_REVISION_3 => {
+			let permitted = word(1)? as CapSet | (word(3)? as CapSet) << 32;
+			let inheritable = word(2)? as CapSet | (word(4)? as CapSet) << 32;
+			Some((permitted, inheritable, effective))
+		}
+		_ => None,
+	}
+}
+
 /// A set of informations determining whether an agent (example: a process) can access a resource.
 ///
 /// Implementations of this structure may contain functions to check access to an object. Custom
@@ -81,6 +237,20 @@ pub struct AccessProfile {
 	suid: Uid,
 	/// The saved group ID.
 	sgid: Gid,
+
+	/// The user ID used to check accesses to files.
+	fsuid: Uid,
+	/// The group ID used to check accesses to files.
+	fsgid: Gid,
+
+	/// The permitted capabilities.
+	cap_permitted: CapSet,
+	/// The effective capabilities.
+	cap_effective: CapSet,
+	/// The inheritable capabilities.
+	cap_inheritable: CapSet,
+	/// The bounding set of capabilities.
+	cap_bounding: CapSet,
 }
 
 impl AccessProfile {
@@ -94,10 +264,21 @@ impl AccessProfile {
 
 		suid: 0,
 		sgid: 0,
+
+		fsuid: 0,
+		fsgid: 0,
+
+		cap_permitted: CAP_FULL_SET,
+		cap_effective: CAP_FULL_SET,
+		cap_inheritable: 0,
+		cap_bounding: CAP_FULL_SET,
 	};
 
 	/// Creates a profile from the given IDs.
+	///
+	/// The superuser holds every capabilities.
 	pub fn new(uid: Uid, gid: Gid) -> Self {
+		let caps = if uid == ROOT_UID { CAP_FULL_SET } else { 0 };
 		Self {
 			uid,
 			gid,
@@ -107,6 +288,14 @@ impl AccessProfile {
 
 			suid: uid,
 			sgid: gid,
+
+			fsuid: uid,
+			fsgid: gid,
+
+			cap_permitted: caps,
+			cap_effective: caps,
+			cap_inheritable: 0,
+			cap_bounding: CAP_FULL_SET,
 		}
 	}
 
@@ -130,6 +319,11 @@ impl AccessProfile {
 		self.suid
 	}
 
+	/// Returns the filesystem user ID.
+	pub fn get_fsuid(&self) -> Uid {
+		self.fsuid
+	}
+
 	/// Returns the real group ID.
 	pub fn get_gid(&self) -> Gid {
 		self.gid
@@ -145,38 +339,189 @@ impl AccessProfile {
 		self.sgid
 	}
 
-	/// Tells whether the agent is privileged (root).
-	pub fn is_privileged(&self) -> bool {
-		self.uid == ROOT_UID
-			|| self.euid == ROOT_UID
-			|| self.gid == ROOT_GID
-			|| self.egid == ROOT_GID
+	/// Returns the filesystem group ID.
+	pub fn get_fsgid(&self) -> Gid {
+		self.fsgid
+	}
+
+	/// Returns the permitted capabilities.
+	pub fn get_cap_permitted(&self) -> CapSet {
+		self.cap_permitted
+	}
+
+	/// Returns the effective capabilities.
+	pub fn get_cap_effective(&self) -> CapSet {
+		self.cap_effective
+	}
+
+	/// Returns the inheritable capabilities.
+	pub fn get_cap_inheritable(&self) -> CapSet {
+		self.cap_inheritable
+	}
+
+	/// Returns the bounding set of capabilities.
+	pub fn get_cap_bounding(&self) -> CapSet {
+		self.cap_bounding
+	}
+
+	/// Tells whether the agent has the given capability in its effective set.
+	pub fn has_cap(&self, cap: u32) -> bool {
+		self.cap_effective & cap_to_set(cap) != 0
+	}
+
+	/// Sets the capabilities of the agent in the way the `capset` system call does.
+	///
+	/// The permitted set cannot be raised, the effective set must be a subset of the permitted
+	/// set and the inheritable set can only be raised with capabilities from the permitted set,
+	/// unless the agent has [`CAP_SETPCAP`].
+	///
+	/// If the change is not allowed, the function returns an error.
+	pub fn set_caps(
+		&mut self,
+		effective: CapSet,
+		permitted: CapSet,
+		inheritable: CapSet,
+	) -> EResult<()> {
+		let inheritable_limit = if self.has_cap(CAP_SETPCAP) {
+			self.cap_inheritable | self.cap_bounding
+		} else {
+			self.cap_inheritable | self.cap_permitted
+		};
+		if inheritable & !inheritable_limit != 0
+			|| permitted & !self.cap_permitted != 0
+			|| effective & !permitted != 0
+		{
+			return Err(errno!(EPERM));
+		}
+		self.cap_effective = effective;
+		self.cap_permitted = permitted;
+		self.cap_inheritable = inheritable;
+		Ok(())
+	}
+
+	/// Removes the given capability from the bounding set.
+	///
+	/// If the agent doesn't have [`CAP_SETPCAP`], the function returns an error.
+	pub fn drop_bounding_cap(&mut self, cap: u32) -> EResult<()> {
+		if cap > CAP_LAST_CAP {
+			return Err(errno!(EINVAL));
+		}
+		if !self.has_cap(CAP_SETPCAP) {
+			return Err(errno!(EPERM));
+		}
+		self.cap_bounding &= !cap_to_set(cap);
+		Ok(())
+	}
+
+	/// Updates capabilities after a change of user IDs, the previous IDs being `old_uid`,
+	/// `old_euid` and `old_suid`.
+	///
+	/// Capabilities are lost when the agent stops being the superuser.
+	fn fix_caps_after_setuid(&mut self, old_uid: Uid, old_euid: Uid, old_suid: Uid) {
+		let was_root = old_uid == ROOT_UID || old_euid == ROOT_UID || old_suid == ROOT_UID;
+		let is_root = self.uid == ROOT_UID || self.euid == ROOT_UID || self.suid == ROOT_UID;
+		if was_root && !is_root {
+			self.cap_permitted = 0;
+			self.cap_effective = 0;
+		}
+		if old_euid == ROOT_UID && self.euid != ROOT_UID {
+			self.cap_effective = 0;
+		} else if old_euid != ROOT_UID && self.euid == ROOT_UID {
+			self.cap_effective = self.cap_permitted;
+		}
+		// The filesystem user ID follows the effective user ID
+		let old_fsuid = self.fsuid;
+		self.fsuid = self.euid;
+		self.fix_caps_after_setfsuid(old_fsuid);
+	}
+
+	/// Updates capabilities after a change of the filesystem user ID, the previous one being
+	/// `old_fsuid`.
+	///
+	/// Filesystem capabilities are lost when the filesystem user ID stops being the superuser,
+	/// and gained back from the permitted set when it becomes the superuser again.
+	fn fix_caps_after_setfsuid(&mut self, old_fsuid: Uid) {
+		if old_fsuid == ROOT_UID && self.fsuid != ROOT_UID {
+			self.cap_effective &= !CAP_FS_SET;
+		} else if old_fsuid != ROOT_UID && self.fsuid == ROOT_UID {
+			self.cap_effective |= self.cap_permitted & CAP_FS_SET;
+		}
+	}
+
+	/// Tells whether the user ID `uid` is one of the real, effective or saved user IDs.
+	fn is_current_uid(&self, uid: Uid) -> bool {
+		uid == self.uid || uid == self.euid || uid == self.suid
+	}
+
+	/// Tells whether the group ID `gid` is one of the real, effective or saved group IDs.
+	fn is_current_gid(&self, gid: Gid) -> bool {
+		gid == self.gid || gid == self.egid || gid == self.sgid
+	}
+
+	/// Returns the profile of the agent once it has executed the file `file`.
+	///
+	/// If `setid` is set, the set-user-ID and set-group-ID bits of the file are honored, setting
+	/// the effective IDs to the owner of the file, and so are the capabilities of the file. The
+	/// saved IDs are then set to the effective IDs.
+	///
+	/// The superuser gains every capabilities from its bounding set.
+	pub fn for_exec(&self, file: &File, setid: bool) -> Self {
+		let mut ap = *self;
+		let mut file_caps = None;
+		if setid {
+			let mode = file.get_mode();
+			if mode & S_ISUID != 0 {
+				ap.euid = file.get_uid();
+			}
+			// Without group execution permission, the bit denotes mandatory locking
+			if mode & S_ISGID != 0 && mode & S_IXGRP != 0 {
+				ap.egid = file.get_gid();
+			}
+			file_caps = get_file_caps(file);
+		}
+		ap.suid = ap.euid;
+		ap.sgid = ap.egid;
+		ap.fsuid = ap.euid;
+		ap.fsgid = ap.egid;
+
+		let (f_permitted, f_inheritable, f_effective) =
+			if ap.uid == ROOT_UID || ap.euid == ROOT_UID {
+				(CAP_FULL_SET, CAP_FULL_SET, ap.euid == ROOT_UID)
+			} else {
+				file_caps.unwrap_or((0, 0, false))
+			};
+		ap.cap_permitted =
+			(self.cap_inheritable & f_inheritable) | (f_permitted & self.cap_bounding);
+		ap.cap_effective = if f_effective { ap.cap_permitted } else { 0 };
+		ap
 	}
 
 	/// Sets the user ID in the same way the `setgid` system call does.
 	///
 	/// If the agent is not privileged enough to make the change, the function returns an error.
 	pub fn set_uid(&mut self, uid: Uid) -> EResult<()> {
-		if self.euid == ROOT_UID {
-			// privileged
+		let (old_uid, old_euid, old_suid) = (self.uid, self.euid, self.suid);
+		if self.has_cap(CAP_SETUID) {
 			self.uid = uid;
 			self.euid = uid;
 			self.suid = uid;
-			Ok(())
-		} else if uid == self.uid || uid == self.euid || uid == self.suid {
+		} else if self.is_current_uid(uid) {
 			self.euid = uid;
-			Ok(())
 		} else {
-			Err(errno!(EPERM))
+			return Err(errno!(EPERM));
 		}
+		self.fix_caps_after_setuid(old_uid, old_euid, old_suid);
+		Ok(())
 	}
 
 	/// Sets the effective user ID.
 	///
 	/// If the agent is not privileged enough to make the change, the function returns an error.
 	pub fn set_euid(&mut self, uid: Uid) -> EResult<()> {
-		if uid == ROOT_UID || uid == self.uid || uid == self.euid || uid == self.suid {
+		if self.has_cap(CAP_SETUID) || self.is_current_uid(uid) {
+			let (old_uid, old_euid, old_suid) = (self.uid, self.euid, self.suid);
 			self.euid = uid;
+			self.fix_caps_after_setuid(old_uid, old_euid, old_suid);
 			Ok(())
 		} else {
 			Err(errno!(EPERM))
@@ -187,29 +532,161 @@ impl AccessProfile {
 	///
 	/// If the agent is not privileged enough to make the change, the function returns an error.
 	pub fn set_gid(&mut self, gid: Gid) -> EResult<()> {
-		if self.egid == ROOT_GID {
-			// privileged
+		if self.has_cap(CAP_SETGID) {
 			self.gid = gid;
 			self.egid = gid;
 			self.sgid = gid;
-			Ok(())
-		} else if gid == self.gid || gid == self.egid || gid == self.sgid {
+		} else if self.is_current_gid(gid) {
 			self.egid = gid;
-			Ok(())
 		} else {
-			Err(errno!(EPERM))
+			return Err(errno!(EPERM));
 		}
+		self.fsgid = self.egid;
+		Ok(())
 	}
 
 	/// Sets the effective group ID.
 	///
 	/// If the agent is not privileged enough to make the change, the function returns an error.
 	pub fn set_egid(&mut self, gid: Uid) -> EResult<()> {
-		if gid == ROOT_GID || gid == self.gid || gid == self.egid || gid == self.sgid {
+		if self.has_cap(CAP_SETGID) || self.is_current_gid(gid) {
 			self.egid = gid;
+			self.fsgid = gid;
 			Ok(())
 		} else {
 			Err(errno!(EPERM))
 		}
 	}
+
+	/// Sets the real, effective and saved user IDs in the way the `setresuid` system call does.
+	///
+	/// IDs that are `None` are left unchanged.
+	///
+	/// Without [`CAP_SETUID`], each new ID must be one of the current real, effective or saved
+	/// user IDs. Otherwise, the function returns an error and no ID is changed.
+	pub fn set_resuid(
+		&mut self,
+		uid: Option<Uid>,
+		euid: Option<Uid>,
+		suid: Option<Uid>,
+	) -> EResult<()> {
+		if !self.has_cap(CAP_SETUID)
+			&& ![uid, euid, suid]
+				.into_iter()
+				.flatten()
+				.all(|id| self.is_current_uid(id))
+		{
+			return Err(errno!(EPERM));
+		}
+		let (old_uid, old_euid, old_suid) = (self.uid, self.euid, self.suid);
+		self.uid = uid.unwrap_or(self.uid);
+		self.euid = euid.unwrap_or(self.euid);
+		self.suid = suid.unwrap_or(self.suid);
+		self.fix_caps_after_setuid(old_uid, old_euid, old_suid);
+		Ok(())
+	}
+
+	/// Sets the real, effective and saved group IDs in the way the `setresgid` system call does.
+	///
+	/// IDs that are `None` are left unchanged.
+	///
+	/// Without [`CAP_SETGID`], each new ID must be one of the current real, effective or saved
+	/// group IDs. Otherwise, the function returns an error and no ID is changed.
+	pub fn set_resgid(
+		&mut self,
+		gid: Option<Gid>,
+		egid: Option<Gid>,
+		sgid: Option<Gid>,
+	) -> EResult<()> {
+		if !self.has_cap(CAP_SETGID)
+			&& ![gid, egid, sgid]
+				.into_iter()
+				.flatten()
+				.all(|id| self.is_current_gid(id))
+		{
+			return Err(errno!(EPERM));
+		}
+		self.gid = gid.unwrap_or(self.gid);
+		self.egid = egid.unwrap_or(self.egid);
+		self.sgid = sgid.unwrap_or(self.sgid);
+		self.fsgid = self.egid;
+		Ok(())
+	}
+
+	/// Sets the filesystem user ID in the way the `setfsuid` system call does.
+	///
+	/// Without [`CAP_SETUID`], the new ID must be one of the current real, effective, saved or
+	/// filesystem user IDs. Otherwise, the ID is left unchanged.
+	///
+	/// The function returns the previous filesystem user ID.
+	pub fn set_fsuid(&mut self, uid: Uid) -> Uid {
+		let old_fsuid = self.fsuid;
+		if self.has_cap(CAP_SETUID) || self.is_current_uid(uid) || uid == self.fsuid {
+			self.fsuid = uid;
+			self.fix_caps_after_setfsuid(old_fsuid);
+		}
+		old_fsuid
+	}
+
+	/// Sets the filesystem group ID in the way the `setfsgid` system call does.
+	///
+	/// Without [`CAP_SETGID`], the new ID must be one of the current real, effective, saved or
+	/// filesystem group IDs. Otherwise, the ID is left unchanged.
+	///
+	/// The function returns the previous filesystem group ID.
+	pub fn set_fsgid(&mut self, gid: Gid) -> Gid {
+		let old_fsgid = self.fsgid;
+		if self.has_cap(CAP_SETGID) || self.is_current_gid(gid) || gid == self.fsgid {
+			self.fsgid = gid;
+		}
+		old_fsgid
+	}
+
+	/// Tells whether the agent can read or modify the resource limits of the agent `target`.
+	///
+	/// Without [`CAP_SYS_RESOURCE`], the real, effective and saved IDs of `target` must all match
+	/// the real IDs of the agent.
+	pub fn can_access_rlimits(&self, target: &AccessProfile) -> bool {
+		if self.has_cap(CAP_SYS_RESOURCE) {
+			return true;
+		}
+		[target.uid, target.euid, target.suid]
+			.into_iter()
+			.all(|id| id == self.uid)
+			&& [target.gid, target.egid, target.sgid]
+				.into_iter()
+				.all(|id| id == self.gid)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn perm_rlimits_same_user() {
+		let agent = AccessProfile::new(1000, 1000);
+		assert!(agent.can_access_rlimits(&AccessProfile::new(1000, 1000)));
+	}
+
+	#[test_case]
+	fn perm_rlimits_other_user() {
+		let agent = AccessProfile::new(1000, 1000);
+		assert!(!agent.can_access_rlimits(&AccessProfile::new(1001, 1000)));
+		assert!(!agent.can_access_rlimits(&AccessProfile::new(1000, 1001)));
+		assert!(!agent.can_access_rlimits(&AccessProfile::KERNEL));
+	}
+
+	#[test_case]
+	fn perm_rlimits_setuid_target() {
+		let agent = AccessProfile::new(1000, 1000);
+		let mut target = AccessProfile::new(1000, 1000);
+		target.euid = ROOT_UID;
+		assert!(!agent.can_access_rlimits(&target));
+	}
+
+	#[test_case]
+	fn perm_rlimits_privileged() {
+		assert!(AccessProfile::KERNEL.can_access_rlimits(&AccessProfile::new(1000, 1000)));
+	}
 }
@@ -0,0 +1,155 @@
+//! Readahead reads pages of a file into the page cache before they are requested, so that
+//! sequential reads do not have to wait for the storage on each page.
+//!
+//! Sequential reads are detected on each open file description. When a read continues where the
+//! previous one stopped, a window of pages following it is prefetched. The window doubles each
+//! time reads get close to the end of the prefetched pages, up to a maximum. A read at another
+//! offset resets it.
+//!
+//! Prefetching is performed asynchronously by the worker of a dedicated workqueue, so that the
+//! read itself does not wait for it.
+
+use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::file::page_cache;
+use crate::file::File;
+use crate::memory;
+use crate::process::workqueue::Work;
+use crate::process::workqueue::WorkQueue;
+use crate::util::container::vec::Vec;
+use crate::util::lock::IntMutex;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use core::cmp::max;
+use core::cmp::min;
+use core::mem;
+
+/// The size of the window in pages on the first sequential read.
+const MIN_WINDOW: usize = 4;
+/// The maximum size of the window in pages.
+const MAX_WINDOW: usize = 32;
+
+/// An advice on the pattern of accesses to a file, given with `posix_fadvise`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Advice {
+	/// No advice. Readahead is performed when sequential reads are detected.
+	#[default]
+	Normal,
+	/// Accesses are sequential. The maximum window is used immediately.
+	Sequential,
+	/// Accesses are random. Readahead is disabled.
+	Random,
+}
+
+/// The readahead state of an open file description.
+#[derive(Debug, Default)]
+pub struct State {
+	/// The advice on the accesses to the file.
+	advice: Advice,
+	/// The offset in pages following the last read.
+	next: usize,
+	/// The current size of the window in pages. If zero, no sequential read has been detected.
+	window: usize,
+	/// The offset in pages up to which pages have been prefetched.
+	ahead: usize,
+}
+
+impl State {
+	/// Sets the advice on the accesses to the file.
+	pub fn set_advice(&mut self, advice: Advice) {
+		self.advice = advice;
+		self.window = 0;
+	}
+
+	/// Updates the state after reading `len` bytes at offset `off` from the file `file`, and
+	/// schedules the prefetch of the following pages if the read is sequential.
+	pub fn on_read(&mut self, file: &Arc<Mutex<File>>, off: u64, len: u64) -> AllocResult<()> {
+		if len == 0 || self.advice == Advice::Random {
+			return Ok(());
+		}
+		let first = (off / memory::PAGE_SIZE as u64) as usize;
+		let end = (off + len).div_ceil(memory::PAGE_SIZE as u64) as usize;
+
+		// A read may finish in the middle of a page, in which case the next one begins on it
+		let sequential = first == self.next || first + 1 == self.next;
+		self.next = end;
+		if !sequential && self.advice != Advice::Sequential {
+			self.window = 0;
+			self.ahead = end;
+			return Ok(());
+		}
+
+		// Prefetch only when reads get close to the end of the pages already prefetched
+		if self.window != 0 && self.ahead >= end + self.window / 2 {
+			return Ok(());
+		}
+		self.window = match (self.advice, self.window) {
+			(Advice::Sequential, _) => MAX_WINDOW,
+			(_, 0) => MIN_WINDOW,
+			(_, window) => min(window * 2, MAX_WINDOW),
+		};
+		let target = end + self.window;
+		let begin = max(self.ahead, end);
+		schedule(file, begin, target - begin)?;
+		self.ahead = target;
+		Ok(())
+	}
+}
+
+/// A pending prefetch.
+struct Request {
+	/// The file to read.
+	file: Arc<Mutex<File>>,
+	/// The offset of the first page to read.
+	off: usize,
+	/// The number of pages to read.
+	pages: usize,
+}
+
+/// The list of pending prefetches.
+static PENDING: Mutex<Vec<Request>> = Mutex::new(Vec::new());
+/// The workqueue performing prefetches, with the work item draining the pending list.
+static QUEUE: IntMutex<Option<(WorkQueue, Arc<Work>)>> = IntMutex::new(None);
+
+/// Creates the workqueue performing prefetches.
+///
+/// This function must be called only once, after the creation of the init process.
+pub fn init() -> EResult<()> {
+	let wq = WorkQueue::new(b"kreadahead")?;
+	let work = Work::new(run)?;
+	*QUEUE.lock() = Some((wq, work));
+	Ok(())
+}
+
+/// Schedules the prefetch of `pages` pages of the file `file`, beginning at offset `off` in
+/// pages.
+///
+/// Since only the file's mutex is stored, this function can be called while the file is
+/// locked.
+///
+/// If the workqueue is not created yet, the prefetch is performed once it is.
+pub fn schedule(file: &Arc<Mutex<File>>, off: usize, pages: usize) -> AllocResult<()> {
+	PENDING.lock().push(Request {
+		file: file.clone(),
+		off,
+		pages,
+	})?;
+	if let Some((wq, work)) = &*QUEUE.lock() {
+		wq.queue_work(work)?;
+	}
+	Ok(())
+}
+
+/// Performs the pending prefetches.
+fn run() {
+	// Requests are taken out of the list to avoid holding its lock while reading files
+	let requests = mem::take(&mut *PENDING.lock());
+	for req in requests.iter() {
+		let mut file = req.file.lock();
+		if !file.is_cached() {
+			continue;
+		}
+		// Readahead is only an optimization. On failure, pages are read when requested
+		let _ = page_cache::prefetch(&mut file, req.off as u64, req.pages);
+	}
+}
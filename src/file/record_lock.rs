@@ -0,0 +1,344 @@
+//! POSIX record locks are advisory locks placed on ranges of bytes of files, through `fcntl`.
+//!
+//! A lock is held by a process. Every locks of a process on a file are released when the process
+//! closes any file descriptor to the file, or when it exits.
+//!
+//! A range may be locked either by several read locks, or by a single write lock. Locks of a same
+//! process never conflict with each other: placing a lock on a range already locked by the
+//! process replaces the previous lock on this range.
+
+use crate::errno;
+use crate::errno::EResult;
+use crate::file::blocking::BlockHandler;
+use crate::file::FileLocation;
+use crate::process::pid::Pid;
+use crate::process::Process;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::vec::Vec;
+use crate::util::io;
+use crate::util::lock::Mutex;
+
+/// The type of a lock.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Kind {
+	/// A lock preventing other processes from placing write locks.
+	Read,
+	/// A lock preventing other processes from placing any lock.
+	Write,
+}
+
+/// A lock on a range of bytes.
+#[derive(Clone, Debug)]
+pub struct Lock {
+	/// The type of the lock.
+	pub kind: Kind,
+	/// The offset of the first byte of the range.
+	pub start: u64,
+	/// The offset of the end of the range (exclusive). If [`u64::MAX`], the range extends to the
+	/// end of the file, however large it becomes.
+	pub end: u64,
+	/// The PID of the process holding the lock.
+	pub pid: Pid,
+}
+
+impl Lock {
+	/// Tells whether the lock's range overlaps the range from `start` to `end`.
+	fn overlaps(&self, start: u64, end: u64) -> bool {
+		self.start < end && start < self.end
+	}
+
+	/// Tells whether the lock conflicts with `other`.
+	fn conflicts(&self, other: &Lock) -> bool {
+		self.pid != other.pid
+			&& (self.kind == Kind::Write || other.kind == Kind::Write)
+			&& self.overlaps(other.start, other.end)
+	}
+}
+
+/// The locks placed on a file.
+#[derive(Default)]
+struct FileLocks {
+	/// The locks, sorted by PID and start offset.
+	locks: Vec<Lock>,
+
+	/// The processes waiting for a lock to be released.
+	block_handler: BlockHandler,
+}
+
+impl FileLocks {
+	/// Returns an iterator over the locks conflicting with `lock`.
+	fn conflicts<'a>(&'a self, lock: &'a Lock) -> impl Iterator<Item = &'a Lock> {
+		self.locks.iter().filter(|l| l.conflicts(lock))
+	}
+
+	/// Removes the range from `start` to `end` from the locks of the process with PID `pid`.
+	///
+	/// If `new` is given, the lock is placed on the range instead. Adjacent locks of the same type
+	/// are merged with it.
+	fn replace(&mut self, pid: Pid, start: u64, end: u64, new: Option<Lock>) -> EResult<()> {
+		let mut locks = Vec::with_capacity(self.locks.len() + 2)?;
+		let mut new = new;
+		for l in self.locks.iter() {
+			if l.pid != pid {
+				locks.push(l.clone())?;
+				continue;
+			}
+			// Merge with the new lock if adjacent or overlapping and of the same type
+			if let Some(new) = &mut new {
+				if l.kind == new.kind && l.start <= new.end && new.start <= l.end {
+					new.start = new.start.min(l.start);
+					new.end = new.end.max(l.end);
+					continue;
+				}
+			}
+			if !l.overlaps(start, end) {
+				locks.push(l.clone())?;
+				continue;
+			}
+			// Keep the parts outside of the range
+			if l.start < start {
+				locks.push(Lock {
+					end: start,
+					..l.clone()
+				})?;
+			}
+			if l.end > end {
+				locks.push(Lock {
+					start: end,
+					..l.clone()
+				})?;
+			}
+		}
+		if let Some(new) = new {
+			locks.push(new)?;
+		}
+		locks.sort_unstable_by_key(|l| (l.pid, l.start));
+		self.locks = locks;
+		self.block_handler.wake_processes(io::POLLIN);
+		Ok(())
+	}
+}
+
+/// The state of record locks.
+struct State {
+	/// The locks, by location of the file.
+	files: HashMap<FileLocation, FileLocks>,
+	/// The requests processes are waiting on, by PID. Used to detect deadlocks.
+	waiting: HashMap<Pid, (FileLocation, Lock)>,
+}
+
+impl State {
+	/// Tells whether the process requesting `lock` on the file at location `location` would wait
+	/// on itself, through the chain of processes holding conflicting locks and waiting themselves.
+	fn would_deadlock(&self, location: &FileLocation, lock: &Lock) -> EResult<bool> {
+		let mut owners = Vec::new();
+		let mut visited = Vec::new();
+		let mut request = Some((location, lock));
+		loop {
+			if let Some((location, lock)) = request.take() {
+				if let Some(file_locks) = self.files.get(location) {
+					for l in file_locks.conflicts(lock) {
+						owners.push(l.pid)?;
+					}
+				}
+			}
+			let Some(owner) = owners.pop() else {
+				return Ok(false);
+			};
+			if owner == lock.pid {
+				return Ok(true);
+			}
+			if visited.contains(&owner) {
+				continue;
+			}
+			visited.push(owner)?;
+			request = self.waiting.get(&owner).map(|(loc, lock)| (loc, lock));
+		}
+	}
+}
+
+/// The state of record locks.
+static STATE: Mutex<State> = Mutex::new(State {
+	files: HashMap::new(),
+	waiting: HashMap::new(),
+});
+
+/// Returns a lock conflicting with `lock` on the file at location `location`.
+///
+/// If no lock conflicts, the function returns `None`.
+pub fn get_conflict(location: &FileLocation, lock: &Lock) -> Option<Lock> {
+	let state = STATE.lock();
+	let file_locks = state.files.get(location)?;
+	let conflict = file_locks.conflicts(lock).next().cloned();
+	conflict
+}
+
+/// Places the lock `lock` on the file at location `location`.
+///
+/// If the lock cannot be placed because of conflicting locks, the function returns `false`. In
+/// this case, if `waiter` is given, the process is put to sleep until a lock on the file is
+/// released.
+///
+/// If waiting would result in a deadlock, the function returns `EDEADLK`.
+pub fn set(location: &FileLocation, lock: Lock, waiter: Option<&mut Process>) -> EResult<bool> {
+	let mut state = STATE.lock();
+	let state = &mut *state;
+	let file_locks = match state.files.get_mut(location) {
+		Some(file_locks) => file_locks,
+		None => {
+			state.files.insert(location.clone(), FileLocks::default())?;
+			state.files.get_mut(location).unwrap()
+		}
+	};
+
+	if file_locks.conflicts(&lock).next().is_some() {
+		let Some(proc) = waiter else {
+			return Ok(false);
+		};
+		if state.would_deadlock(location, &lock)? {
+			return Err(errno!(EDEADLK));
+		}
+		let pid = lock.pid;
+		state.waiting.insert(pid, (location.clone(), lock))?;
+		let file_locks = state.files.get_mut(location).unwrap();
+		if let Err(e) = file_locks
+			.block_handler
+			.add_waiting_process(proc, io::POLLIN)
+		{
+			state.waiting.remove(&pid);
+			return Err(e);
+		}
+		return Ok(false);
+	}
+
+	let (pid, start, end) = (lock.pid, lock.start, lock.end);
+	file_locks.replace(pid, start, end, Some(lock))?;
+	state.waiting.remove(&pid);
+	Ok(true)
+}
+
+/// Removes the locks of the process with PID `pid` on the range from `start` to `end` of the file
+/// at location `location`.
+pub fn unlock(location: &FileLocation, pid: Pid, start: u64, end: u64) -> EResult<()> {
+	let mut state = STATE.lock();
+	let Some(file_locks) = state.files.get_mut(location) else {
+		return Ok(());
+	};
+	file_locks.replace(pid, start, end, None)?;
+	if file_locks.locks.is_empty() {
+		state.files.remove(location);
+	}
+	Ok(())
+}
+
+/// Tells that the process with PID `pid` is not waiting for a lock anymore.
+pub fn cancel_wait(pid: Pid) {
+	STATE.lock().waiting.remove(&pid);
+}
+
+/// Releases every locks of the process with PID `pid` on the file at location `location`.
+pub fn release_file(location: &FileLocation, pid: Pid) {
+	let mut state = STATE.lock();
+	let Some(file_locks) = state.files.get_mut(location) else {
+		return;
+	};
+	let count = file_locks.locks.len();
+	file_locks.locks.retain(|l| l.pid != pid);
+	if file_locks.locks.len() != count {
+		file_locks.block_handler.wake_processes(io::POLLIN);
+	}
+	if file_locks.locks.is_empty() {
+		state.files.remove(location);
+	}
+}
+
+/// Releases every locks of the process with PID `pid`, after it exited.
+pub fn release_all(pid: Pid) {
+	let mut state = STATE.lock();
+	state.waiting.remove(&pid);
+	state.files.retain(|_, file_locks| {
+		let count = file_locks.locks.len();
+		file_locks.locks.retain(|l| l.pid != pid);
+		if file_locks.locks.len() != count {
+			file_locks.block_handler.wake_processes(io::POLLIN);
+		}
+		!file_locks.locks.is_empty()
+	});
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	/// Returns a lock with the given parameters.
+	fn lock(kind: Kind, start: u64, end: u64, pid: Pid) -> Lock {
+		Lock {
+			kind,
+			start,
+			end,
+			pid,
+		}
+	}
+
+	/// Returns the ranges of the locks in `file_locks`.
+	fn ranges(file_locks: &FileLocks) -> Vec<(u64, u64)> {
+		let mut ranges = Vec::new();
+		for l in file_locks.locks.iter() {
+			ranges.push((l.start, l.end)).unwrap();
+		}
+		ranges
+	}
+
+	#[test_case]
+	fn record_lock_split() {
+		let mut file_locks = FileLocks::default();
+		file_locks
+			.replace(1, 0, 100, Some(lock(Kind::Write, 0, 100, 1)))
+			.unwrap();
+		file_locks.replace(1, 40, 60, None).unwrap();
+		assert_eq!(ranges(&file_locks).as_slice(), &[(0, 40), (60, 100)]);
+
+		file_locks
+			.replace(1, 40, 60, Some(lock(Kind::Read, 40, 60, 1)))
+			.unwrap();
+		assert_eq!(
+			ranges(&file_locks).as_slice(),
+			&[(0, 40), (40, 60), (60, 100)]
+		);
+	}
+
+	#[test_case]
+	fn record_lock_merge() {
+		let mut file_locks = FileLocks::default();
+		file_locks
+			.replace(1, 0, 10, Some(lock(Kind::Read, 0, 10, 1)))
+			.unwrap();
+		file_locks
+			.replace(1, 20, 30, Some(lock(Kind::Read, 20, 30, 1)))
+			.unwrap();
+		file_locks
+			.replace(1, 10, 20, Some(lock(Kind::Read, 10, 20, 1)))
+			.unwrap();
+		assert_eq!(ranges(&file_locks).as_slice(), &[(0, 30)]);
+	}
+
+	#[test_case]
+	fn record_lock_conflict() {
+		let mut file_locks = FileLocks::default();
+		file_locks
+			.replace(1, 0, 10, Some(lock(Kind::Read, 0, 10, 1)))
+			.unwrap();
+		assert!(file_locks
+			.conflicts(&lock(Kind::Read, 5, 15, 2))
+			.next()
+			.is_none());
+		assert!(file_locks
+			.conflicts(&lock(Kind::Write, 5, 15, 2))
+			.next()
+			.is_some());
+		assert!(file_locks
+			.conflicts(&lock(Kind::Write, 10, 15, 2))
+			.next()
+			.is_none());
+	}
+}
@@ -81,7 +81,8 @@ pub fn copy_file(old: &mut File, new_parent: &mut File, new_name: String) -> ERe
 		}
 
 		// Copy the directory recursively
-		FileContent::Directory(entries) => {
+		FileContent::Directory(_) => {
+			let names = old.get_entry_names()?;
 			let new_mutex = vfs::create_file(
 				new_parent,
 				new_name,
@@ -92,12 +93,11 @@ pub fn copy_file(old: &mut File, new_parent: &mut File, new_name: String) -> ERe
 			let mut new = new_mutex.lock();
 
 			// TODO On fail, undo
-			for (name, _) in entries.iter() {
-				let old_mutex =
-					vfs::get_file_from_parent(&mut new, name.try_clone()?, &ap, false)?;
+			for name in names {
+				let old_mutex = vfs::get_file_from_parent(old, name.try_clone()?, &ap, false)?;
 				let mut old = old_mutex.lock();
 
-				copy_file(&mut old, &mut new, name.try_clone()?)?;
+				copy_file(&mut old, &mut new, name)?;
 			}
 		}
 
@@ -117,9 +117,8 @@ pub fn copy_file(old: &mut File, new_parent: &mut File, new_name: String) -> ERe
 /// - `access_profile` is the access profile, to check permissions
 pub fn remove_recursive(file: &mut File, access_profile: &AccessProfile) -> EResult<()> {
 	match file.get_content() {
-		FileContent::Directory(entries) => {
-			for (name, _) in entries.iter() {
-				let name = name.try_clone()?;
+		FileContent::Directory(_) => {
+			for name in file.get_entry_names()? {
 				let subfile_mutex = vfs::get_file_from_parent(file, name, access_profile, false)?;
 				let mut subfile = subfile_mutex.lock();
 
@@ -5,29 +5,41 @@
 //! calling the filesystems' functions directly.
 
 use crate::errno;
+use crate::errno::AllocResult;
 use crate::errno::EResult;
 use crate::file::buffer;
-use crate::file::mapping;
+use crate::file::buffer::inotify;
+use crate::file::buffer::memfd;
+use crate::file::dcache;
+use crate::file::fs::Filesystem;
+use crate::file::icache;
 use crate::file::mountpoint;
 use crate::file::open_file::OpenFile;
+use crate::file::page_cache;
 use crate::file::path::Path;
 use crate::file::perm;
 use crate::file::perm::AccessProfile;
+use crate::file::util;
+use crate::file::writeback;
 use crate::file::File;
 use crate::file::FileContent;
 use crate::file::FileLocation;
 use crate::file::FileType;
+use crate::file::INode;
 use crate::file::Mode;
 use crate::file::MountPoint;
+use crate::file::FILE_CACHE;
 use crate::limits;
+use crate::process::Process;
 use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
 use crate::util::lock::Mutex;
 use crate::util::ptr::arc::Arc;
 use crate::util::TryClone;
+use core::ffi::c_void;
 use core::ptr::NonNull;
 
-// TODO implement and use cache
-
 /// Updates the location of the file `file` according to the given mountpoint
 /// `mountpoint`.
 ///
@@ -41,6 +53,80 @@ fn update_location(file: &mut File, mountpoint: &MountPoint) {
 	}
 }
 
+/// Returns the inode of the entry `name` in the directory with inode `parent`, on the
+/// filesystem `fs` of mountpoint `mountpoint`.
+///
+/// The lookup goes through the dentry cache if the filesystem requires caching.
+fn lookup(
+	mountpoint: &MountPoint,
+	io: &mut dyn IO,
+	fs: &mut dyn Filesystem,
+	parent: INode,
+	name: &[u8],
+) -> EResult<INode> {
+	if !fs.must_cache() {
+		return fs.get_inode(io, Some(parent), name);
+	}
+	let mountpoint_id = mountpoint.get_id();
+	if let Some(entry) = dcache::get(mountpoint_id, parent, name) {
+		return entry.ok_or_else(|| errno!(ENOENT));
+	}
+
+	let res = fs.get_inode(io, Some(parent), name);
+	match res {
+		Ok(inode) => dcache::insert(mountpoint_id, parent, name, Some(inode))?,
+		Err(e) if e.as_int() == errno::ENOENT => {
+			dcache::insert(mountpoint_id, parent, name, None)?
+		}
+		Err(_) => {}
+	}
+	res
+}
+
+/// Loads the file with inode `inode` from the filesystem `fs` of mountpoint `mountpoint`, with
+/// the name `name`.
+///
+/// The file is taken from the inode cache if the filesystem requires caching.
+fn load_file(
+	mountpoint: &MountPoint,
+	io: &mut dyn IO,
+	fs: &mut dyn Filesystem,
+	inode: INode,
+	name: String,
+) -> EResult<File> {
+	let must_cache = fs.must_cache();
+	if must_cache {
+		let location = FileLocation::Filesystem {
+			mountpoint_id: mountpoint.get_id(),
+			inode,
+		};
+		if let Some(file) = icache::get(&location, name.try_clone()?)? {
+			return Ok(file);
+		}
+	}
+
+	let mut file = fs.load_file(io, inode, name)?;
+	update_location(&mut file, mountpoint);
+	if must_cache {
+		icache::insert(&file)?;
+	}
+	Ok(file)
+}
+
+/// Updates the dentry cache after the entry `name` has been added to, or removed from, the
+/// directory `parent`.
+///
+/// `inode` is the inode of the entry, or `None` if it has been removed.
+fn update_dentry(parent: &FileLocation, name: &[u8], inode: Option<INode>) {
+	let Some(mountpoint_id) = parent.get_mountpoint_id() else {
+		return;
+	};
+	let parent = parent.get_inode();
+	if dcache::insert(mountpoint_id, parent, name, inode).is_err() {
+		dcache::invalidate(mountpoint_id, parent, name);
+	}
+}
+
 /// Returns the file corresponding to the given location `location`.
 ///
 /// This function doesn't set the name of the file since it cannot be known solely on its
@@ -64,116 +150,217 @@ pub fn get_file_by_location(location: &FileLocation) -> EResult<Arc<Mutex<File>>
 			let fs_mutex = mountpoint.get_filesystem();
 			let mut fs = fs_mutex.lock();
 
-			let mut file = fs.load_file(&mut *io, *inode, String::new())?;
-			update_location(&mut file, &mountpoint);
-
-			Ok(Arc::new(Mutex::new(file))?)
+			let file = load_file(&mountpoint, &mut *io, &mut *fs, *inode, String::new())?;
+			Ok(Arc::new_in(Mutex::new(file), &FILE_CACHE)?)
 		}
 
 		FileLocation::Virtual {
 			id,
 		} => {
 			let name = crate::format!("virtual:{id}")?;
-			let content = FileContent::Fifo; // TODO
-
-			let file = Arc::new(Mutex::new(File::new(
+			// Anonymous files are the only regular files with a virtual location
+			let size = buffer::get(location)
+				.filter(|_| memfd::get_seals(location).is_some())
+				.map(|buff| buff.lock().get_size());
+			let content = match size {
+				Some(_) => FileContent::Regular,
+				None => FileContent::Fifo, // TODO
+			};
+
+			let mut file = File::new(
 				name,
 				0, // TODO
 				0, // TODO
 				0o666,
 				location.clone(),
 				content,
-			)?))?;
-			Ok(file)
+			)?;
+			if let Some(size) = size {
+				file.set_size(size);
+			}
+			Ok(Arc::new_in(Mutex::new(file), &FILE_CACHE)?)
 		}
 	}
 }
 
-/// `follows_count` is the number of links that have been followed since the
-/// beginning of the path resolution.
-fn get_file_by_path_impl(
-	path: &Path,
-	ap: &AccessProfile,
-	follow_links: bool,
-	follows_count: usize,
-) -> EResult<Arc<Mutex<File>>> {
-	let path = Path::root().concat(path)?;
+/// Settings for a path resolution.
+pub struct ResolutionSettings {
+	/// The root directory of the resolution, as an absolute path in the VFS.
+	///
+	/// Absolute symbolic links are resolved from this directory, and `..` cannot go above it.
+	pub root: Path,
+	/// The access profile used to check permissions.
+	pub access_profile: AccessProfile,
+	/// Tells whether symbolic links are followed on the last component of the path.
+	pub follow_links: bool,
+}
 
-	// Get the path's deepest mountpoint
-	let mountpoint_mutex = mountpoint::get_deepest(&path).ok_or_else(|| errno!(ENOENT))?;
-	let mountpoint = mountpoint_mutex.lock();
-	let mountpath = mountpoint.get_path();
+impl ResolutionSettings {
+	/// Returns the settings to resolve paths from the root of the VFS, with the kernel's access
+	/// profile.
+	pub fn kernel(follow_links: bool) -> Self {
+		Self {
+			root: Path::root(),
+			access_profile: AccessProfile::KERNEL,
+			follow_links,
+		}
+	}
 
-	// Get the IO interface
+	/// Returns the settings to resolve paths for the process `proc`, from its root directory and
+	/// with its access profile.
+	pub fn for_process(proc: &Process, follow_links: bool) -> AllocResult<Self> {
+		Ok(Self {
+			root: (*proc.get_fs().lock().chroot).try_clone()?,
+			access_profile: proc.access_profile,
+			follow_links,
+		})
+	}
+}
+
+/// Loads the root directory of the mountpoint `mountpoint`, with the name `name`.
+fn load_root(mountpoint: &MountPoint, name: String) -> EResult<File> {
 	let io_mutex = mountpoint.get_source().get_io()?;
 	let mut io = io_mutex.lock();
+	let fs_mutex = mountpoint.get_filesystem();
+	let mut fs = fs_mutex.lock();
 
-	// Get the path of the file beginning from the start of its filesystem
-	let inner_path = path.range_from(mountpoint.get_path().get_elements_count()..)?;
+	load_file(mountpoint, &mut *io, &mut *fs, mountpoint.get_root(), name)
+}
+
+/// Loads the root directory of the VFS.
+fn load_vfs_root() -> EResult<File> {
+	let mountpoint_mutex = mountpoint::from_path(&Path::root()).ok_or_else(|| errno!(ENOENT))?;
+	let mountpoint = mountpoint_mutex.lock();
+	load_root(&mountpoint, String::new())
+}
 
-	// The filesystem
+/// Loads the entry `name` of the directory `dir`.
+///
+/// Filesystems mounted on the entry are not taken into account.
+fn load_entry(dir: &File, name: String) -> EResult<File> {
+	let mountpoint_mutex = dir
+		.get_location()
+		.get_mountpoint()
+		.ok_or_else(|| errno!(ENOENT))?;
+	let mountpoint = mountpoint_mutex.lock();
+	let io_mutex = mountpoint.get_source().get_io()?;
+	let mut io = io_mutex.lock();
 	let fs_mutex = mountpoint.get_filesystem();
 	let mut fs = fs_mutex.lock();
 
-	// The root inode
-	let mut inode = fs.get_root_inode(&mut *io)?;
-	let mut file = fs.load_file(&mut *io, inode, String::new())?;
+	let parent = dir.get_location().get_inode();
+	let inode = lookup(&mountpoint, &mut *io, &mut *fs, parent, &name)?;
+	load_file(&mountpoint, &mut *io, &mut *fs, inode, name)
+}
 
-	for i in 0..inner_path.get_elements_count() {
-		inode = fs.get_inode(&mut *io, Some(inode), &inner_path[i])?;
+/// Loads the entry `name` of the directory `dir` located at `path`.
+///
+/// If a filesystem is mounted on the entry, the function returns the root of this filesystem.
+fn load_child(dir: &File, path: &Path, name: String) -> EResult<File> {
+	match mountpoint::from_path(path) {
+		Some(mountpoint) => load_root(&mountpoint.lock(), name),
+		None => load_entry(dir, name),
+	}
+}
 
-		// Check permissions
-		if i < inner_path.get_elements_count() - 1 && !ap.can_search_directory(&file) {
+/// Pushes the components of `path` on the stack `components`, so that the first one is on top.
+fn push_components(components: &mut Vec<String>, path: &Path) -> EResult<()> {
+	for i in (0..path.get_elements_count()).rev() {
+		components.push(path[i].try_clone()?)?;
+	}
+	Ok(())
+}
+
+/// Resolves the path `path` with the given settings, and returns the file it points to.
+///
+/// Path components are resolved one after the other from the root of the VFS. Relative paths
+/// are treated as beginning from it as well.
+///
+/// For each component, search permission is checked on the current directory. `..` goes back to
+/// the previous directory, without going above the root of the resolution. Each symbolic link
+/// followed along the way counts towards a limit of [`limits::SYMLOOP_MAX`] links, after which
+/// the function fails with `ELOOP`.
+///
+/// If the file doesn't exist, the function returns an error.
+fn resolve_path_impl(path: &Path, settings: &ResolutionSettings) -> EResult<File> {
+	// The components left to resolve, the next one being on top
+	let mut components = Vec::new();
+	push_components(&mut components, path)?;
+	// The directories leading to the current file
+	let mut ancestors = Vec::new();
+	let mut cur_path = Path::root();
+	let mut file = load_vfs_root()?;
+	let mut follows_count = 0;
+
+	while let Some(name) = components.pop() {
+		if file.get_type() != FileType::Directory {
+			return Err(errno!(ENOTDIR));
+		}
+		if !settings.access_profile.can_search_directory(&file) {
 			return Err(errno!(EACCES));
 		}
-		// Get file
-		file = fs.load_file(&mut *io, inode, inner_path[i].try_clone()?)?;
 
-		// If this is not the last element, or if links are followed
-		if i < inner_path.get_elements_count() - 1 || follow_links {
-			// If symbolic link, resolve it
-			if let FileContent::Link(link_path) = file.get_content() {
-				if follows_count > limits::SYMLOOP_MAX {
-					return Err(errno!(ELOOP));
+		match name.as_bytes() {
+			b"." => continue,
+			b".." => {
+				if cur_path != settings.root {
+					if let Some(parent) = ancestors.pop() {
+						cur_path.pop();
+						file = parent;
+					}
 				}
+				continue;
+			}
+			_ => {}
+		}
 
-				let mut prefix = inner_path.range_to(..i)?;
-				prefix.set_absolute(false);
-
-				let link_path = Path::from_str(link_path.as_bytes(), false)?;
-
-				let mut suffix = inner_path.range_from((i + 1)..)?;
-				suffix.set_absolute(false);
+		let mut child_path = cur_path.try_clone()?;
+		child_path.push(name.try_clone()?)?;
+		let child = load_child(&file, &child_path, name)?;
 
-				let parent_path = mountpath.concat(&prefix)?;
-				let new_path = parent_path.concat(&link_path)?;
-				let new_path = new_path.concat(&suffix)?;
+		let last = components.is_empty();
+		if let FileContent::Link(target) = child.get_content() {
+			if !last || settings.follow_links {
+				follows_count += 1;
+				if follows_count > limits::SYMLOOP_MAX {
+					return Err(errno!(ELOOP));
+				}
 
-				drop(fs);
-				drop(io);
-				drop(mountpoint);
-				return get_file_by_path_impl(&new_path, ap, follow_links, follows_count + 1);
+				let target = Path::from_str(target.as_bytes(), false)?;
+				push_components(&mut components, &target)?;
+				if target.is_absolute() {
+					push_components(&mut components, &settings.root)?;
+					ancestors.clear();
+					cur_path = Path::root();
+					file = load_vfs_root()?;
+				}
+				continue;
 			}
 		}
-	}
-
-	let mut parent_path = path;
-	parent_path.pop();
-	file.set_parent_path(parent_path);
 
-	drop(fs);
+		ancestors.push(file)?;
+		cur_path = child_path;
+		file = child;
+	}
 
-	update_location(&mut file, &mountpoint);
-	let file = Arc::new(Mutex::new(file))?;
+	cur_path.pop();
+	file.set_parent_path(cur_path);
 	Ok(file)
 }
 
-// TODO Add a param to choose between the mountpoint and the fs root?
-/// Returns a reference to the file at path `path`.
+/// Returns a reference to the file at path `path`, resolved with the settings `settings`.
 ///
 /// If the file doesn't exist, the function returns an error.
 ///
-/// If the path is relative, the function starts from the root.
+/// If the path is relative, the function starts from the root of the VFS.
+pub fn resolve_path(path: &Path, settings: &ResolutionSettings) -> EResult<Arc<Mutex<File>>> {
+	let file = resolve_path_impl(path, settings)?;
+	Ok(Arc::new_in(Mutex::new(file), &FILE_CACHE)?)
+}
+
+/// Returns a reference to the file at path `path`.
+///
+/// This function is equivalent to [`resolve_path`], from the root of the VFS.
 ///
 /// Arguments:
 /// - `ap` is the access profile to check permissions
@@ -183,7 +370,12 @@ pub fn get_file_from_path(
 	ap: &AccessProfile,
 	follow_links: bool,
 ) -> EResult<Arc<Mutex<File>>> {
-	get_file_by_path_impl(path, ap, follow_links, 0)
+	let settings = ResolutionSettings {
+		root: Path::root(),
+		access_profile: *ap,
+		follow_links,
+	};
+	resolve_path(path, &settings)
 }
 
 /// Returns a reference to the file `name` located in the directory `parent`.
@@ -209,40 +401,26 @@ pub fn get_file_from_parent(
 		return Err(errno!(EACCES));
 	}
 
-	// Get the path's deepest mountpoint
-	let mountpoint_mutex = parent
-		.get_location()
-		.get_mountpoint()
-		.ok_or_else(|| errno!(ENOENT))?;
-	let mountpoint = mountpoint_mutex.lock();
-
-	// Get the IO interface
-	let io_mutex = mountpoint.get_source().get_io()?;
-	let mut io = io_mutex.lock();
-
-	// The filesystem
-	let fs_mutex = mountpoint.get_filesystem();
-	let mut fs = fs_mutex.lock();
-
-	let inode = fs.get_inode(&mut *io, Some(parent.get_location().get_inode()), &name)?;
-	let mut file = fs.load_file(&mut *io, inode, name)?;
-
-	if follow_links {
-		if let FileContent::Link(link_path) = file.get_content() {
-			let link_path = Path::from_str(link_path.as_bytes(), false)?;
-			let new_path = parent.get_path()?.concat(&link_path)?;
+	let parent_path = parent.get_path()?;
+	let mut path = parent_path.try_clone()?;
+	path.push(name.try_clone()?)?;
+	let settings = ResolutionSettings {
+		root: Path::root(),
+		access_profile: *ap,
+		follow_links,
+	};
 
-			drop(fs);
-			drop(io);
-			drop(mountpoint);
-			return get_file_by_path_impl(&new_path, ap, follow_links, 1);
-		}
+	// Going up or following a link requires a full resolution
+	if matches!(name.as_bytes(), b"." | b"..") {
+		return resolve_path(&path, &settings);
+	}
+	let mut file = load_child(parent, &path, name)?;
+	if follow_links && matches!(file.get_content(), FileContent::Link(_)) {
+		return resolve_path(&path, &settings);
 	}
 
-	file.set_parent_path(parent.get_path()?);
-	update_location(&mut file, &mountpoint);
-
-	Ok(Arc::new(Mutex::new(file))?)
+	file.set_parent_path(parent_path);
+	Ok(Arc::new_in(Mutex::new(file), &FILE_CACHE)?)
 }
 
 /// Creates a file, adds it to the VFS, then returns it. The file will be
@@ -277,13 +455,13 @@ pub fn create_file(
 		return Err(errno!(EACCES));
 	}
 
-	let uid = ap.get_euid();
+	let uid = ap.get_fsuid();
 	let gid = if parent.get_mode() & perm::S_ISGID != 0 {
 		// If SGID is set, the newly created file shall inherit the group ID of the
 		// parent directory
 		parent.get_gid()
 	} else {
-		ap.get_egid()
+		ap.get_fsgid()
 	};
 
 	// Get the mountpoint
@@ -310,14 +488,31 @@ pub fn create_file(
 	// Add the file to the filesystem
 	let parent_inode = parent.get_location().get_inode();
 	let mut file = fs.add_file(&mut *io, parent_inode, name, uid, gid, mode, content)?;
+	drop(fs);
+	update_location(&mut file, &mountpoint);
 
 	// Add the file to the parent's entries
 	file.set_parent_path(parent.get_path()?);
 	parent.add_entry(file.get_name().try_clone()?, file.as_dir_entry())?;
-
-	drop(fs);
-	update_location(&mut file, &mountpoint);
-	Ok(Arc::new(Mutex::new(file))?)
+	icache::invalidate(parent.get_location());
+	update_dentry(
+		parent.get_location(),
+		file.get_name().as_bytes(),
+		Some(file.get_location().get_inode()),
+	);
+
+	let mut mask = inotify::IN_CREATE;
+	if file.get_type() == FileType::Directory {
+		mask |= inotify::IN_ISDIR;
+	}
+	inotify::notify(
+		parent.get_location(),
+		mask,
+		Some(file.get_name().as_bytes()),
+		0,
+	);
+
+	Ok(Arc::new_in(Mutex::new(file), &FILE_CACHE)?)
 }
 
 /// Creates a new hard link.
@@ -332,6 +527,22 @@ pub fn create_link(
 	parent: &mut File,
 	name: &[u8],
 	ap: &AccessProfile,
+) -> EResult<()> {
+	link_impl(target, parent, name, ap)?;
+	let mut mask = inotify::IN_CREATE;
+	if target.get_type() == FileType::Directory {
+		mask |= inotify::IN_ISDIR;
+	}
+	inotify::notify(parent.get_location(), mask, Some(name), 0);
+	Ok(())
+}
+
+/// Implementation of [`create_link`], without generating inotify events.
+fn link_impl(
+	target: &mut File,
+	parent: &mut File,
+	name: &[u8],
+	ap: &AccessProfile,
 ) -> EResult<()> {
 	// Check the parent file is a directory
 	if parent.get_type() != FileType::Directory {
@@ -374,6 +585,14 @@ pub fn create_link(
 	)?;
 	target.set_hard_links_count(target.get_hard_links_count() + 1);
 
+	icache::invalidate(parent.get_location());
+	icache::invalidate(target.get_location());
+	update_dentry(
+		parent.get_location(),
+		name,
+		Some(target.get_location().get_inode()),
+	);
+
 	Ok(())
 }
 
@@ -385,6 +604,13 @@ pub fn create_link(
 ///
 /// If the file is a non-empty directory, the function returns an error.
 pub fn remove_file(file: &mut File, ap: &AccessProfile) -> EResult<()> {
+	remove_impl(file, ap, true)
+}
+
+/// Implementation of [`remove_file`].
+///
+/// `notify` tells whether inotify events are generated for the removal of the directory entry.
+fn remove_impl(file: &mut File, ap: &AccessProfile, notify: bool) -> EResult<()> {
 	// The parent directory
 	let parent_mutex = get_file_from_path(file.get_parent_path(), ap, true)?;
 	let parent = parent_mutex.lock();
@@ -427,30 +653,118 @@ pub fn remove_file(file: &mut File, ap: &AccessProfile) -> EResult<()> {
 
 	// Remove the file
 	let links_left = fs.remove_file(&mut *io, parent_location.get_inode(), name)?;
+	icache::invalidate(parent_location);
+	icache::invalidate(location);
+	update_dentry(parent_location, name.as_bytes(), None);
+	if notify {
+		let mut mask = inotify::IN_DELETE;
+		if file.get_type() == FileType::Directory {
+			mask |= inotify::IN_ISDIR;
+		}
+		inotify::notify(parent_location, mask, Some(name.as_bytes()), 0);
+	}
 	if links_left == 0 {
+		inotify::notify(location, inotify::IN_DELETE_SELF, None, 0);
+		inotify::forget(location);
+		// The inode may be reused by another file
+		if let Some(mountpoint_id) = location.get_mountpoint_id() {
+			dcache::invalidate_inode(mountpoint_id, location.get_inode());
+		}
 		// If the file is a named pipe or socket, free its now unused buffer
 		buffer::release(location);
+		page_cache::remove(location);
+		writeback::forget(location);
 	}
 
 	Ok(())
 }
 
-/// Maps the page at offset `off` in the file at location `loc`.
+/// Moves the file `old` to the directory `new_parent`, with the name `new_name`.
 ///
-/// On success, the function returns a reference to the page.
+/// `ap` is the access profile to check permissions.
 ///
-/// If the file doesn't exist, the function returns an error.
-pub fn map_file(loc: FileLocation, off: usize) -> EResult<NonNull<u8>> {
-	// TODO if the page is being init, read from disk
-	mapping::map(loc, off)?;
+/// If the file and the new parent are on different filesystems, the file is copied, then the old
+/// one is removed.
+pub fn rename(
+	old: &mut File,
+	new_parent: &mut File,
+	new_name: String,
+	ap: &AccessProfile,
+) -> EResult<()> {
+	// TODO Check permissions if sticky bit is set
+
+	if new_parent.get_location().get_mountpoint_id() != old.get_location().get_mountpoint_id() {
+		// Old and new are on different filesystems.
+
+		// TODO On fail, undo
 
-	todo!();
+		util::copy_file(old, new_parent, new_name)?;
+		util::remove_recursive(old, ap)?;
+		return Ok(());
+	}
+
+	// Old and new are both on the same filesystem
+	let old_parent_location = get_file_from_path(old.get_parent_path(), ap, true)?
+		.lock()
+		.get_location()
+		.clone();
+	let old_name = old.get_name().try_clone()?;
+
+	// TODO On fail, undo
+
+	// Create link at new location
+	// The `..` entry is already updated by the file system since having the same
+	// directory in several locations is not allowed
+	link_impl(old, new_parent, &new_name, ap)?;
+
+	let is_dir = old.get_type() == FileType::Directory;
+	if !is_dir {
+		remove_impl(old, ap, false)?;
+	}
+
+	let dir_flag = if is_dir { inotify::IN_ISDIR } else { 0 };
+	let cookie = inotify::new_cookie();
+	inotify::notify(
+		&old_parent_location,
+		inotify::IN_MOVED_FROM | dir_flag,
+		Some(old_name.as_bytes()),
+		cookie,
+	);
+	inotify::notify(
+		new_parent.get_location(),
+		inotify::IN_MOVED_TO | dir_flag,
+		Some(&new_name),
+		cookie,
+	);
+	inotify::notify(old.get_location(), inotify::IN_MOVE_SELF, None, 0);
+
+	Ok(())
 }
 
-/// Maps the page at offset `off` in the file at location `loc`.
+/// Maps the page at offset `off` in pages in the file at location `loc`.
+///
+/// The page is shared with every other mappings of the same page of the file.
+///
+/// On success, the function returns the physical address of the page.
+///
+/// If the file doesn't exist, the function returns an error.
+pub fn map_file(loc: &FileLocation, off: u64) -> EResult<NonNull<c_void>> {
+	let file_mutex = get_file_by_location(loc)?;
+	let mut file = file_mutex.lock();
+	page_cache::map(&mut file, off)
+}
+
+/// Unmaps the page at offset `off` in pages in the file at location `loc`.
+///
+/// `ptr` is the physical address of the page, as returned by `map_file`.
 ///
 /// If the page is not mapped, the function does nothing.
-pub fn unmap_file(loc: &FileLocation, off: usize) {
-	// TODO sync to disk if necessary
-	mapping::unmap(loc, off);
+pub fn unmap_file(loc: &FileLocation, off: u64, ptr: *const c_void) {
+	page_cache::unmap(loc, off, ptr);
+	// The page may have been modified through the mapping
+	if let Ok(file) = get_file_by_location(loc) {
+		// TODO Report the error to the user
+		let _ = writeback::mark_dirty(&file, loc);
+	}
+	memfd::release_if_unused(loc);
 }
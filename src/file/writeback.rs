@@ -0,0 +1,334 @@
+//! Writeback is the procedure of writing modified files back to their storage.
+//!
+//! Instead of writing each modification immediately, files are marked as dirty and are
+//! periodically flushed in the background once they have been dirty for long enough.
+//!
+//! If the amount of dirty pages in the page cache exceeds a threshold, flushing is started
+//! regardless of the period. If it exceeds a higher threshold, processes writing to files are
+//! made to wait until files have been flushed.
+//!
+//! Periodic and background flushes are performed by a dedicated kernel thread, which sleeps
+//! between two flushes.
+//!
+//! A file that fails to be flushed stays dirty, so that the flush is retried later. The error is
+//! kept until it is reported by the next synchronization of the file.
+
+use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::mountpoint;
+use crate::file::page_cache;
+use crate::file::File;
+use crate::file::FileLocation;
+use crate::memory;
+use crate::memory::stats;
+use crate::process::kthread;
+use crate::process::kthread::KThread;
+use crate::process::Process;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::hrtimer;
+use crate::time::unit::Timestamp;
+use crate::time::unit::TimestampScale;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::vec::Vec;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+
+/// The tunables of the writeback, exposed in `/proc/sys/vm`.
+pub struct Tunables {
+	/// The interval between two periodic flushes, in centiseconds.
+	///
+	/// If zero, periodic flushes are disabled.
+	pub dirty_writeback_centisecs: u32,
+	/// The duration after which a dirty file is old enough to be flushed, in centiseconds.
+	pub dirty_expire_centisecs: u32,
+	/// The percentage of the memory that dirty pages can fill before every dirty files are
+	/// flushed in the background.
+	pub dirty_background_ratio: u32,
+	/// The percentage of the memory that dirty pages can fill before processes writing to files
+	/// flush them by themselves.
+	pub dirty_ratio: u32,
+}
+
+/// The writeback's tunables.
+pub static TUNABLES: Mutex<Tunables> = Mutex::new(Tunables {
+	dirty_writeback_centisecs: 500,
+	dirty_expire_centisecs: 3000,
+	dirty_background_ratio: 10,
+	dirty_ratio: 20,
+});
+
+/// A file waiting to be flushed.
+struct DirtyFile {
+	/// The file.
+	file: Arc<Mutex<File>>,
+	/// The timestamp in milliseconds at which the file has become dirty.
+	since: Timestamp,
+	/// The last error that occurred while flushing the file, if not reported yet.
+	error: Option<Errno>,
+}
+
+/// The list of dirty files, by location.
+static DIRTY_FILES: Mutex<HashMap<FileLocation, DirtyFile>> = Mutex::new(HashMap::new());
+/// The thread performing periodic and background flushes.
+static FLUSHER: Mutex<Option<KThread>> = Mutex::new(None);
+
+/// Returns the current timestamp in milliseconds.
+fn now() -> Timestamp {
+	clock::current_time(CLOCK_MONOTONIC, TimestampScale::Millisecond).unwrap_or(0)
+}
+
+/// Tells whether the number of dirty pages exceeds `ratio` percent of the memory.
+fn is_over_ratio(ratio: u32) -> bool {
+	let total_pages = stats::MEM_INFO.lock().mem_total * 1024 / memory::PAGE_SIZE;
+	page_cache::get_dirty_pages() * 100 > total_pages * ratio as usize
+}
+
+/// Marks the file `file` as dirty, so that it gets written back later.
+///
+/// `loc` is the location of the file.
+///
+/// Since only the file's mutex is stored, this function can be called while the file is
+/// locked.
+pub fn mark_dirty(file: &Arc<Mutex<File>>, loc: &FileLocation) -> AllocResult<()> {
+	let mut dirty_files = DIRTY_FILES.lock();
+	if dirty_files.contains_key(loc) {
+		return Ok(());
+	}
+	dirty_files.insert(
+		loc.clone(),
+		DirtyFile {
+			file: file.clone(),
+			since: now(),
+			error: None,
+		},
+	)?;
+	Ok(())
+}
+
+/// Puts the file at location `loc` back in the list after its flush failed with the error
+/// `error`, so that the flush is retried later and the error reported.
+///
+/// If the file has been marked as dirty again in the meantime, the function keeps the earliest
+/// timestamp.
+fn requeue(loc: FileLocation, mut dirty_file: DirtyFile, error: Errno) -> AllocResult<()> {
+	dirty_file.error = Some(error);
+	let mut dirty_files = DIRTY_FILES.lock();
+	match dirty_files.get_mut(&loc) {
+		Some(cur) => {
+			cur.since = cur.since.min(dirty_file.since);
+			cur.error = Some(error);
+		}
+		None => {
+			dirty_files.insert(loc, dirty_file)?;
+		}
+	}
+	Ok(())
+}
+
+/// Removes the file at location `loc` from the dirty files, without writing it back.
+///
+/// This function is meant to be called when the file is removed.
+pub fn forget(loc: &FileLocation) {
+	DIRTY_FILES.lock().remove(loc);
+}
+
+/// Writes the content and the status of the file `file` back to its storage.
+fn flush_file(file: &Mutex<File>) -> EResult<()> {
+	let mut file = file.lock();
+	page_cache::sync(&mut file)?;
+	file.sync()
+}
+
+/// Takes the dirty files for which `f` returns `true` out of the list.
+fn take<F: FnMut(&FileLocation, &DirtyFile) -> bool>(
+	mut f: F,
+) -> AllocResult<Vec<(FileLocation, DirtyFile)>> {
+	let mut dirty_files = DIRTY_FILES.lock();
+	let mut locs = Vec::new();
+	for (loc, dirty_file) in dirty_files.iter() {
+		if f(loc, dirty_file) {
+			locs.push(loc.clone())?;
+		}
+	}
+	// Allocating beforehand so that no file is lost if an allocation fails
+	let mut files = Vec::with_capacity(locs.len())?;
+	for loc in locs {
+		let dirty_file = dirty_files.remove(&loc).unwrap();
+		files.push((loc, dirty_file))?;
+	}
+	Ok(files)
+}
+
+/// Flushes each file of the list `files`.
+///
+/// Files that fail to be flushed are put back in the list. If a flush fails, or if an error
+/// occurred on a previous flush that has not been reported yet, the function returns the error.
+fn flush_files(files: Vec<(FileLocation, DirtyFile)>) -> EResult<()> {
+	let mut res = Ok(());
+	for (loc, dirty_file) in files {
+		// An error on a file must not prevent others from being flushed
+		match flush_file(&dirty_file.file) {
+			Ok(()) => {
+				if let Some(e) = dirty_file.error {
+					res = Err(e);
+				}
+			}
+			Err(e) => {
+				// If the file cannot be put back, it is flushed again only once modified
+				let _ = requeue(loc, dirty_file, e);
+				res = Err(e);
+			}
+		}
+	}
+	res
+}
+
+/// Flushes the write cache of the device on which the mountpoint with ID `mountpoint_id` is
+/// mounted.
+///
+/// If the mountpoint doesn't exist anymore, the function does nothing.
+fn flush_device(mountpoint_id: u32) -> EResult<()> {
+	let Some(mountpoint_mutex) = mountpoint::from_id(mountpoint_id) else {
+		return Ok(());
+	};
+	let io_mutex = mountpoint_mutex.lock().get_source().get_io()?;
+	let mut io = io_mutex.lock();
+	io.flush()
+}
+
+/// Flushes dirty files.
+///
+/// If `all` is `false`, only files that have been dirty for long enough are flushed.
+///
+/// The files must not be locked by the caller.
+pub fn flush(all: bool) -> EResult<()> {
+	let expire = TUNABLES.lock().dirty_expire_centisecs as Timestamp * 10;
+	let now = now();
+
+	// Files are taken out of the list and flushed without holding its lock, to allow marking
+	// files as dirty in the meantime
+	let files = take(|_, dirty_file| all || now >= dirty_file.since + expire)?;
+	flush_files(files)
+}
+
+/// Writes the file `file` back to its storage, then flushes the write cache of the device so
+/// that the data reaches the storage medium.
+///
+/// If a previous flush of the file failed and the error has not been reported yet, the function
+/// returns it.
+///
+/// The file must not be locked by the caller.
+pub fn sync_file(file: &Arc<Mutex<File>>) -> EResult<()> {
+	let loc = file.lock().get_location().clone();
+	// The file is written back here, it doesn't need to be flushed later
+	let dirty_file = DIRTY_FILES.lock().remove(&loc);
+	let error = dirty_file.as_ref().and_then(|dirty_file| dirty_file.error);
+	if let Err(e) = flush_file(file) {
+		let dirty_file = dirty_file.unwrap_or_else(|| DirtyFile {
+			file: file.clone(),
+			since: now(),
+			error: None,
+		});
+		requeue(loc, dirty_file, e)?;
+		return Err(e);
+	}
+	if let Some(mountpoint_id) = loc.get_mountpoint_id() {
+		flush_device(mountpoint_id)?;
+	}
+	match error {
+		Some(e) => Err(e),
+		None => Ok(()),
+	}
+}
+
+/// Writes back every dirty files located on the mountpoint with ID `mountpoint_id`, then
+/// flushes the write cache of its device.
+///
+/// The files must not be locked by the caller.
+pub fn sync_mountpoint(mountpoint_id: u32) -> EResult<()> {
+	let files = take(|loc, _| loc.get_mountpoint_id() == Some(mountpoint_id))?;
+	let res = flush_files(files);
+	// The device is flushed even on error, so that files that were written reach the medium
+	flush_device(mountpoint_id)?;
+	res
+}
+
+/// Writes back every dirty files, then flushes the write cache of every mounted device.
+///
+/// The files must not be locked by the caller.
+pub fn sync_all() -> EResult<()> {
+	let res = flush(true);
+	mountpoint::flush_devices()?;
+	res
+}
+
+/// The function of the flusher thread.
+fn flusher() {
+	let pid = Process::current_assert().lock().pid;
+	let mut last_flush = now();
+	while !kthread::should_stop() {
+		let (interval, background_ratio) = {
+			let tunables = TUNABLES.lock();
+			(
+				tunables.dirty_writeback_centisecs as Timestamp * 10,
+				tunables.dirty_background_ratio,
+			)
+		};
+
+		let over_ratio = is_over_ratio(background_ratio);
+		let now = now();
+		let due = interval != 0 && now >= last_flush + interval;
+		if due || over_ratio {
+			// Errors are kept on the files that failed, to be reported when they are synchronized
+			let _ = flush(over_ratio);
+			last_flush = now;
+		}
+
+		// Sleep until the next periodic flush, or until woken up because too many pages are
+		// dirty or the tunables have changed
+		let deadline = (interval != 0).then_some((last_flush + interval) * 1_000_000);
+		let timer = deadline.filter(|deadline| hrtimer::insert(*deadline, pid).is_ok());
+		kthread::sleep();
+		if let Some(deadline) = timer {
+			hrtimer::remove(deadline, pid);
+		}
+	}
+}
+
+/// Starts the flusher thread.
+///
+/// This function must be called only once, after the creation of the init process.
+pub fn init() -> EResult<()> {
+	let thread = kthread::spawn(b"kflushd", flusher)?;
+	*FLUSHER.lock() = Some(thread);
+	Ok(())
+}
+
+/// Wakes the flusher thread up, so that it checks whether a flush is required.
+///
+/// If the thread is not started yet, the function does nothing.
+pub fn wake() {
+	if let Some(thread) = &*FLUSHER.lock() {
+		thread.wake();
+	}
+}
+
+/// If too many pages are dirty, flushes every dirty files. If the background threshold is
+/// exceeded, the flusher thread is woken up instead.
+///
+/// This function is meant to be called by processes after writing to a file, without the file
+/// being locked.
+pub fn balance() -> EResult<()> {
+	let (background_ratio, ratio) = {
+		let tunables = TUNABLES.lock();
+		(tunables.dirty_background_ratio, tunables.dirty_ratio)
+	};
+	if is_over_ratio(ratio) {
+		flush(true)?;
+	} else if is_over_ratio(background_ratio) {
+		wake();
+	}
+	Ok(())
+}
@@ -0,0 +1,230 @@
+//! Extended attributes are name/value pairs associated with files, beside their content.
+//!
+//! The name of an attribute begins with the prefix of a namespace, which determines who is
+//! allowed to read or write it:
+//! - `user.`: attributes of regular files and directories, following the file's permissions
+//! - `trusted.`: attributes only visible to processes with `CAP_SYS_ADMIN`
+//! - `security.`: security labels and capabilities, readable by anyone and only writable by
+//! processes with `CAP_SYS_ADMIN`, or `CAP_SETFCAP` for capabilities
+//! - `system.`: attributes interpreted by the kernel, such as ACLs (not supported)
+//!
+//! Attributes are stored by the filesystem of the file. See
+//! [`Filesystem::get_xattr`](super::fs::Filesystem::get_xattr).
+
+use super::fs::Filesystem;
+use super::perm;
+use super::perm::AccessProfile;
+use super::File;
+use super::FileType;
+use super::INode;
+use crate::errno;
+use crate::errno::EResult;
+use crate::limits;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+
+/// Flag for `setxattr`: fail if the attribute already exists.
+pub const XATTR_CREATE: i32 = 1;
+/// Flag for `setxattr`: fail if the attribute doesn't exist.
+pub const XATTR_REPLACE: i32 = 2;
+
+/// A namespace of extended attributes.
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Namespace {
+	User,
+	Trusted,
+	Security,
+	System,
+}
+
+impl Namespace {
+	/// Returns the namespace of the attribute with the given full name.
+	///
+	/// If the namespace is unknown, the function returns `None`.
+	fn from_name(name: &[u8]) -> Option<Self> {
+		if name.starts_with(b"user.") {
+			Some(Self::User)
+		} else if name.starts_with(b"trusted.") {
+			Some(Self::Trusted)
+		} else if name.starts_with(b"security.") {
+			Some(Self::Security)
+		} else if name.starts_with(b"system.") {
+			Some(Self::System)
+		} else {
+			None
+		}
+	}
+}
+
+/// Checks the name `name` of an extended attribute and returns its namespace.
+fn check_name(name: &[u8]) -> EResult<Namespace> {
+	if name.is_empty() || name.len() > limits::XATTR_NAME_MAX {
+		return Err(errno!(ERANGE));
+	}
+	let namespace = Namespace::from_name(name).ok_or_else(|| errno!(EOPNOTSUPP))?;
+	// ACLs are not supported
+	if namespace == Namespace::System {
+		return Err(errno!(EOPNOTSUPP));
+	}
+	Ok(namespace)
+}
+
+impl File {
+	/// Executes `f` with the filesystem of the file.
+	///
+	/// If the file is not located on a filesystem, the function returns an error.
+	fn xattr_op<R, F>(&self, f: F) -> EResult<R>
+	where
+		F: FnOnce(&mut dyn IO, &mut dyn Filesystem, INode) -> EResult<R>,
+	{
+		let mountpoint_mutex = self
+			.location
+			.get_mountpoint()
+			.ok_or_else(|| errno!(EOPNOTSUPP))?;
+		let mountpoint = mountpoint_mutex.lock();
+
+		let io_mutex = mountpoint.get_source().get_io()?;
+		let mut io = io_mutex.lock();
+
+		let fs_mutex = mountpoint.get_filesystem();
+		let mut fs = fs_mutex.lock();
+
+		f(&mut *io, &mut *fs, self.location.get_inode())
+	}
+
+	/// Checks the agent with access profile `ap` can access the extended attribute `name` of the
+	/// file.
+	///
+	/// `write` tells whether the access is for modification.
+	///
+	/// Since attributes that cannot be read are not supposed to be seen, reading them fails
+	/// with `ENODATA` instead of a permission error.
+	fn check_xattr_access(&self, ap: &AccessProfile, name: &[u8], write: bool) -> EResult<()> {
+		match check_name(name)? {
+			Namespace::User => {
+				if !matches!(self.get_type(), FileType::Regular | FileType::Directory) {
+					return Err(if write {
+						errno!(EPERM)
+					} else {
+						errno!(ENODATA)
+					});
+				}
+				let allowed = if write {
+					ap.can_write_file(self)
+				} else {
+					ap.can_read_file(self)
+				};
+				if !allowed {
+					return Err(errno!(EACCES));
+				}
+			}
+			Namespace::Trusted => {
+				if !ap.has_cap(perm::CAP_SYS_ADMIN) {
+					return Err(if write {
+						errno!(EPERM)
+					} else {
+						errno!(ENODATA)
+					});
+				}
+			}
+			Namespace::Security => {
+				// Setting the capabilities of a file requires a dedicated capability
+				let cap = if name == b"security.capability" {
+					perm::CAP_SETFCAP
+				} else {
+					perm::CAP_SYS_ADMIN
+				};
+				if write && !ap.has_cap(cap) {
+					return Err(errno!(EPERM));
+				}
+			}
+			Namespace::System => unreachable!(),
+		}
+		Ok(())
+	}
+
+	/// Returns the value of the extended attribute `name` of the file.
+	///
+	/// `ap` is the access profile used to check permissions.
+	///
+	/// If the attribute doesn't exist, the function returns `ENODATA`.
+	pub fn get_xattr(&self, ap: &AccessProfile, name: &[u8]) -> EResult<Vec<u8>> {
+		self.check_xattr_access(ap, name, false)?;
+		self.xattr_op(|io, fs, inode| fs.get_xattr(io, inode, name))?
+			.ok_or_else(|| errno!(ENODATA))
+	}
+
+	/// Returns the names of the extended attributes of the file.
+	///
+	/// `ap` is the access profile used to check permissions. Attributes the agent isn't allowed
+	/// to see are not included.
+	pub fn list_xattr(&self, ap: &AccessProfile) -> EResult<Vec<String>> {
+		let mut names = self.xattr_op(|io, fs, inode| fs.list_xattr(io, inode))?;
+		names.retain(|name| {
+			Namespace::from_name(name.as_bytes()) != Some(Namespace::Trusted)
+				|| ap.has_cap(perm::CAP_SYS_ADMIN)
+		});
+		Ok(names)
+	}
+
+	/// Sets the value of the extended attribute `name` of the file.
+	///
+	/// Arguments:
+	/// - `ap` is the access profile used to check permissions.
+	/// - `value` is the new value.
+	/// - `flags` is a combination of [`XATTR_CREATE`] and [`XATTR_REPLACE`].
+	pub fn set_xattr(
+		&self,
+		ap: &AccessProfile,
+		name: &[u8],
+		value: &[u8],
+		flags: i32,
+	) -> EResult<()> {
+		if flags & !(XATTR_CREATE | XATTR_REPLACE) != 0 {
+			return Err(errno!(EINVAL));
+		}
+		if value.len() > limits::XATTR_SIZE_MAX {
+			return Err(errno!(E2BIG));
+		}
+		self.check_xattr_access(ap, name, true)?;
+		self.check_mount_writable()?;
+
+		self.xattr_op(|io, fs, inode| {
+			if fs.is_readonly() {
+				return Err(errno!(EROFS));
+			}
+			if flags != 0 {
+				let exists = fs.get_xattr(io, inode, name)?.is_some();
+				if exists && flags & XATTR_CREATE != 0 {
+					return Err(errno!(EEXIST));
+				}
+				if !exists && flags & XATTR_REPLACE != 0 {
+					return Err(errno!(ENODATA));
+				}
+			}
+			fs.set_xattr(io, inode, name, Some(value))?;
+			Ok(())
+		})
+	}
+
+	/// Removes the extended attribute `name` of the file.
+	///
+	/// `ap` is the access profile used to check permissions.
+	///
+	/// If the attribute doesn't exist, the function returns `ENODATA`.
+	pub fn remove_xattr(&self, ap: &AccessProfile, name: &[u8]) -> EResult<()> {
+		self.check_xattr_access(ap, name, true)?;
+		self.check_mount_writable()?;
+
+		self.xattr_op(|io, fs, inode| {
+			if fs.is_readonly() {
+				return Err(errno!(EROFS));
+			}
+			if !fs.set_xattr(io, inode, name, None)? {
+				return Err(errno!(ENODATA));
+			}
+			Ok(())
+		})
+	}
+}
@@ -4,10 +4,12 @@
 //! It is a deprecated structure that still must be used in order to switch to protected mode,
 //! handle protection rings and load the Task State Segment (TSS).
 
+use crate::cpu;
 use crate::memory;
 use core::arch::asm;
 use core::ffi::c_void;
 use core::fmt;
+use core::mem::size_of;
 use core::ptr;
 
 /// The address in physical memory to the beginning of the GDT.
@@ -25,6 +27,8 @@ pub const USER_DS: usize = 32;
 pub const TSS_OFFSET: usize = 40;
 /// The offset of Thread Local Storage (TLS) entries.
 pub const TLS_OFFSET: usize = 48;
+/// The number of entries in the GDT.
+const ENTRIES_COUNT: usize = 9;
 
 /// Structure representing a GDT entry.
 #[repr(transparent)]
@@ -130,6 +134,20 @@ impl fmt::Debug for Entry {
 	}
 }
 
+/// A GDT descriptor, as loaded by the `lgdt` instruction.
+#[repr(C, packed)]
+struct Descriptor {
+	/// The size of the GDT in bytes, minus 1.
+	size: u16,
+	/// The address of the GDT.
+	offset: u32,
+}
+
+/// The GDTs of application processors, by CPU ID. The bootstrap processor uses the GDT set up
+/// at boot.
+static mut AP_GDTS: [[Entry; ENTRIES_COUNT]; cpu::MAX_CPUS] =
+	[[Entry(0); ENTRIES_COUNT]; cpu::MAX_CPUS];
+
 /// Creates a segment selector for the given segment offset and ring.
 #[inline(always)]
 pub fn make_segment_selector(offset: u32, ring: u32) -> u16 {
@@ -137,15 +155,59 @@ pub fn make_segment_selector(offset: u32, ring: u32) -> u16 {
 	(offset | ring) as _
 }
 
-/// Returns the pointer to the segment at offset `offset`.
+/// Returns the pointer to the segment at offset `offset` in the GDT of the current CPU.
 pub fn get_segment_ptr(offset: usize) -> *mut u64 {
-	unsafe { memory::kern_to_virt(PHYS_PTR.add(offset as _)) as _ }
+	match cpu::get_current_id() {
+		0 => unsafe { memory::kern_to_virt(PHYS_PTR.add(offset as _)) as _ },
+		id => unsafe { (AP_GDTS[id].as_mut_ptr() as *mut u8).add(offset) as _ },
+	}
 }
 
-/// Refreshes the GDT's cache.
+/// Refreshes the GDT's cache on the current CPU.
 #[inline(always)]
 pub fn flush() {
+	match cpu::get_current_id() {
+		0 => unsafe {
+			asm!("lgdt GDT_DESC_VIRT_PTR");
+		},
+		id => {
+			let desc = Descriptor {
+				size: (size_of::<[Entry; ENTRIES_COUNT]>() - 1) as _,
+				offset: unsafe { AP_GDTS[id].as_ptr() as _ },
+			};
+			unsafe {
+				asm!("lgdt [{}]", in(reg) &desc);
+			}
+		}
+	}
+}
+
+/// Sets up and loads the GDT of the application processor with ID `id`, then reloads segment
+/// registers.
+///
+/// This function must be called only once, by the application processor itself.
+pub fn init_ap(id: usize) {
+	// Copy the kernel and user segments from the bootstrap processor
+	unsafe {
+		let bsp_gdt = memory::kern_to_virt(PHYS_PTR) as *const Entry;
+		let count = TSS_OFFSET / size_of::<Entry>();
+		ptr::copy_nonoverlapping(bsp_gdt, AP_GDTS[id].as_mut_ptr(), count);
+	}
+	flush();
+
 	unsafe {
-		asm!("lgdt GDT_DESC_VIRT_PTR");
+		asm!(
+			"mov ds, {ds:x}",
+			"mov es, {ds:x}",
+			"mov ss, {ds:x}",
+			"push {cs}",
+			"lea {tmp}, [2f]",
+			"push {tmp}",
+			"retf",
+			"2:",
+			ds = in(reg) KERNEL_DS,
+			cs = const KERNEL_CS,
+			tmp = out(reg) _,
+		);
 	}
 }
@@ -22,6 +22,9 @@
 .type error\n, @function
 
 error\n:
+	# Enforce SMAP while handling the interruption, whatever the interrupted code was doing
+CLEAR_AC
+
 	push %ebp
 	mov %esp, %ebp
 
@@ -59,6 +62,9 @@ RESTORE_REGS
 .type error\n, @function
 
 error\n:
+	# Enforce SMAP while handling the interruption, whatever the interrupted code was doing
+CLEAR_AC
+
 	# Retrieve the error code and write it after the stack pointer so that it can be retrieved
 	# after the stack frame
 	push %eax
@@ -110,6 +116,9 @@ RESTORE_REGS
 .global irq\n
 
 irq\n:
+	# Enforce SMAP while handling the interruption, whatever the interrupted code was doing
+CLEAR_AC
+
 	push %ebp
 	mov %esp, %ebp
 
@@ -142,6 +151,47 @@ RESTORE_REGS
 
 
 
+/*
+ * This macro creates a function to handle an interruption coming from the local APIC.
+ * `name` is the name of the function and `n` is the interrupt vector.
+ */
+.macro APIC_IRQ	name, n
+.global \name
+
+\name:
+	# Enforce SMAP while handling the interruption, whatever the interrupted code was doing
+CLEAR_AC
+
+	push %ebp
+	mov %esp, %ebp
+
+	# Allocate space for registers and retrieve them
+GET_REGS \name
+
+	# Get the ring
+	mov 8(%ebp), %eax
+	and $0b11, %eax
+
+	# Push arguments to call event_handler
+	push %esp # regs
+	push %eax # ring
+	push $0 # code
+	push $\n # id
+	call event_handler
+	add $16, %esp
+
+	call apic_end_of_interrupt
+
+RESTORE_REGS
+
+	# Restore the context
+	mov %ebp, %esp
+	pop %ebp
+	iret
+.endm
+
+
+
 /*
  * Create the handlers for every errors.
  */
@@ -198,6 +248,32 @@ IRQ 13
 IRQ 14
 IRQ 15
 
+/*
+ * Create the handlers for the local APIC.
+ */
+APIC_IRQ apic_timer, 0x30
+APIC_IRQ tlb_shootdown, 0x31
+
+/*
+ * Handler for Non-Maskable Interrupts. If the core has been asked to halt, it stops here since
+ * the rest of the kernel may be in any state. Else, the interruption is handled as an error.
+ */
+.global nmi
+.type nmi, @function
+
+nmi:
+	cmpl $0, smp_halt_requested
+	je error2
+	call smp_halt
+
+/*
+ * Spurious interrupts must not be acknowledged.
+ */
+.global apic_spurious
+
+apic_spurious:
+	iret
+
 
 
 /*
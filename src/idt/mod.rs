@@ -4,6 +4,7 @@
 
 pub mod pic;
 
+use crate::cpu::apic;
 use crate::util;
 use core::ffi::c_void;
 use core::mem::size_of;
@@ -20,6 +21,14 @@ const ID_PRIVILEGE_RING_3: u8 = 0b00000110;
 /// Flag telling that the interrupt is present.
 const ID_PRESENT: u8 = 0b00000001;
 
+/// The IDT vector index for the local APIC timer.
+pub const APIC_TIMER_VECTOR: u32 = 0x30;
+/// The IDT vector index for TLB shootdown IPIs.
+pub const TLB_SHOOTDOWN_VECTOR: u32 = 0x31;
+/// The IDT vector index for spurious interrupts of the local APIC.
+///
+/// On some CPUs, the lowest four bits of this vector must be set.
+pub const APIC_SPURIOUS_VECTOR: u32 = 0x3f;
 /// The IDT vector index for system calls.
 pub const SYSCALL_ENTRY: usize = 0x80;
 /// The number of entries into the IDT.
@@ -138,6 +147,12 @@ extern "C" {
 	fn error30();
 	fn error31();
 
+	fn nmi();
+
+	fn apic_timer();
+	fn tlb_shootdown();
+	fn apic_spurious();
+
 	fn syscall();
 }
 
@@ -171,7 +186,7 @@ pub fn init() {
 
 		id[0x00] = create_id(error0 as _, 0x8, 0x8e);
 		id[0x01] = create_id(error1 as _, 0x8, 0x8e);
-		id[0x02] = create_id(error2 as _, 0x8, 0x8e);
+		id[0x02] = create_id(nmi as _, 0x8, 0x8e);
 		id[0x03] = create_id(error3 as _, 0x8, 0x8e);
 		id[0x04] = create_id(error4 as _, 0x8, 0x8e);
 		id[0x05] = create_id(error5 as _, 0x8, 0x8e);
@@ -219,9 +234,20 @@ pub fn init() {
 		id[0x2e] = create_id(irq14 as _, 0x8, 0x8e);
 		id[0x2f] = create_id(irq15 as _, 0x8, 0x8e);
 
+		id[APIC_TIMER_VECTOR as usize] = create_id(apic_timer as _, 0x8, 0x8e);
+		id[TLB_SHOOTDOWN_VECTOR as usize] = create_id(tlb_shootdown as _, 0x8, 0x8e);
+		id[APIC_SPURIOUS_VECTOR as usize] = create_id(apic_spurious as _, 0x8, 0x8e);
+
 		id[SYSCALL_ENTRY] = create_id(syscall as _, 0x8, 0xee);
 	}
 
+	load();
+}
+
+/// Loads the IDT on the current CPU core.
+///
+/// The IDT must have been initialized with [`init`] before.
+pub fn load() {
 	let idt = InterruptDescriptorTable {
 		size: (size_of::<InterruptDescriptor>() * ENTRIES_COUNT - 1) as u16,
 		offset: unsafe { ID.assume_init_ref().as_ptr() as u32 },
@@ -231,6 +257,15 @@ pub fn init() {
 	}
 }
 
+/// Sends an End-Of-Interrupt for the interrupt vector `id`, to the controller it comes from.
+pub fn end_of_interrupt(id: u32) {
+	if id >= APIC_TIMER_VECTOR {
+		apic::end_of_interrupt();
+	} else {
+		pic::end_of_interrupt((id - 0x20) as _);
+	}
+}
+
 /// Tells whether interruptions are enabled.
 pub fn is_interrupt_enabled() -> bool {
 	unsafe { interrupt_is_enabled() != 0 }
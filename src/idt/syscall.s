@@ -14,6 +14,9 @@
  */
 syscall:
 	cli
+	# Enforce SMAP while handling the interruption, whatever the interrupted code was doing
+CLEAR_AC
+
 	push %ebp
 	mov %esp, %ebp
 
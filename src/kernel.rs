@@ -43,6 +43,7 @@
 
 pub mod acpi;
 pub mod cmdline;
+pub mod compression;
 pub mod cpu;
 pub mod crypto;
 pub mod debug;
@@ -57,6 +58,7 @@ pub mod gdt;
 #[macro_use]
 pub mod idt;
 pub mod io;
+pub mod kexec;
 pub mod limits;
 pub mod logger;
 pub mod memory;
@@ -81,13 +83,17 @@ pub mod vga;
 use crate::errno::Errno;
 use crate::file::fs::initramfs;
 use crate::file::path::Path;
+use crate::file::readahead;
 use crate::file::perm::AccessProfile;
 use crate::file::vfs;
+use crate::file::writeback;
 use crate::logger::LOGGER;
 use crate::memory::vmem;
 use crate::memory::vmem::VMem;
 use crate::process::exec;
 use crate::process::exec::ExecInfo;
+use crate::process::mem_space;
+use crate::process::workqueue;
 use crate::process::Process;
 use crate::util::boxed::Box;
 use crate::util::container::string::String;
@@ -95,7 +101,7 @@ use crate::util::container::vec::Vec;
 use crate::util::lock::Mutex;
 use core::arch::asm;
 use core::ffi::c_void;
-use core::ptr::null;
+use core::sync::atomic;
 
 /// The kernel's name.
 pub const NAME: &str = env!("CARGO_PKG_NAME");
@@ -125,9 +131,11 @@ pub fn wait() {
 }
 
 /// Enters the kernel loop and processes every interrupts indefinitely.
+///
+/// Between interrupts, the current CPU core is put in an idle state.
 pub fn enter_loop() -> ! {
 	loop {
-		wait();
+		cpu::idle::idle();
 	}
 }
 
@@ -151,13 +159,15 @@ fn init_vmem() -> Result<(), Errno> {
 
 	// TODO Enable GLOBAL in cr4
 
-	// Mapping the kernelspace
-	kernel_vmem.map_range(
-		null::<c_void>(),
-		memory::PROCESS_END,
-		memory::get_kernelspace_size() / memory::PAGE_SIZE,
-		vmem::x86::FLAG_WRITE,
-	)?;
+	// Mapping the physical memory in the kernelspace, using huge pages to reduce TLB pressure. The
+	// vmalloc region is left unmapped
+	for off in (0..memory::get_direct_map_size()).step_by(vmem::HUGE_PAGE_SIZE) {
+		kernel_vmem.map_huge(
+			off as _,
+			(memory::PROCESS_END as usize + off) as _,
+			vmem::x86::FLAG_WRITE,
+		)?;
+	}
 
 	// Mapping VGA's buffer
 	let vga_flags = vmem::x86::FLAG_CACHE_DISABLE
@@ -225,10 +235,15 @@ fn init(init_path: String) -> Result<(), Errno> {
 		access_profile: AccessProfile::KERNEL,
 		argv: vec![init_path]?,
 		envp: env,
+		randomize: true,
 	};
 	let program_image = exec::build_image(&mut file, exec_info)?;
 
-	exec::exec(&mut proc, program_image)
+	exec::exec(&mut proc, program_image)?;
+	if let Some(name) = path.last() {
+		proc.set_comm(name);
+	}
+	Ok(())
 }
 
 /// This is the main function of the Rust source code, responsible for the
@@ -272,6 +287,9 @@ pub extern "C" fn kernel_main(magic: u32, multiboot_ptr: *const c_void) -> ! {
 	if init_vmem().is_err() {
 		panic!("Cannot initialize kernel virtual memory!");
 	}
+	// Preventing the kernel from executing or accessing userspace memory unintentionally
+	cpu::smap::init();
+	cpu::idle::init();
 
 	// From here, the kernel considers that memory management has been fully
 	// initialized
@@ -292,12 +310,15 @@ pub extern "C" fn kernel_main(magic: u32, multiboot_ptr: *const c_void) -> ! {
 		}
 	};
 	LOGGER.lock().silent = args_parser.is_silent();
+	mem_space::ALLOW_WRITE_EXEC.store(
+		args_parser.is_write_exec_allowed(),
+		atomic::Ordering::Relaxed,
+	);
 
 	println!("Booting Maestro kernel version {VERSION}");
 
-	// FIXME
-	//println!("Initializing ACPI...");
-	//acpi::init();
+	println!("Initializing ACPI...");
+	acpi::init();
 
 	println!("Initializing time management...");
 	if time::init().is_err() {
@@ -325,10 +346,15 @@ pub extern "C" fn kernel_main(magic: u32, multiboot_ptr: *const c_void) -> ! {
 
 	println!("Initializing processes...");
 	process::init().unwrap_or_else(|e| panic!("Failed to init processes! ({e})"));
+	println!("Starting application processors...");
+	cpu::smp::init().unwrap_or_else(|e| panic!("Failed to start application processors! ({e})"));
 
 	let init_path = args_parser.get_init_path().unwrap_or(INIT_PATH);
 	let init_path = String::try_from(init_path).unwrap();
 	init(init_path).unwrap_or_else(|e| panic!("Cannot execute init process: {e}"));
+	workqueue::init().unwrap_or_else(|e| panic!("Failed to initialize workqueues! ({e})"));
+	writeback::init().unwrap_or_else(|e| panic!("Failed to start the writeback thread! ({e})"));
+	readahead::init().unwrap_or_else(|e| panic!("Failed to start the readahead worker! ({e})"));
 
 	drop(args_parser);
 	enter_loop();
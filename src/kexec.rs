@@ -0,0 +1,264 @@
+//! kexec allows to load a new kernel in memory, then to jump to it without going through the
+//! firmware, making reboots faster.
+//!
+//! A kernel image is made of segments, which are staged in memory that doesn't overlap with
+//! their destination. When the image is executed, a trampoline placed in an identity-mapped
+//! page disables paging, copies every segment to its destination, then jumps to the entry point
+//! of the new kernel.
+
+use crate::cpu::smp;
+use crate::errno::AllocError;
+use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::memory;
+use crate::memory::buddy;
+use crate::memory::buddy::FrameOrder;
+use crate::memory::memmap;
+use crate::util::container::vec::Vec;
+use crate::util::lock::Mutex;
+use core::ffi::c_void;
+use core::mem::size_of;
+use core::ops::Range;
+use core::ptr;
+use core::ptr::NonNull;
+use core::slice;
+
+/// The maximum number of segments in a kernel image.
+pub const SEGMENT_MAX: usize = 16;
+
+/// The maximum number of allocation attempts to get memory that doesn't overlap with the
+/// destination of segments.
+const ALLOC_ATTEMPTS: usize = 16;
+
+extern "C" {
+	/// The trampoline copying segments to their destination, then jumping to the new kernel.
+	fn kexec_trampoline(list: u32, entry: u32) -> !;
+	/// The end of the trampoline's code.
+	static kexec_trampoline_end: c_void;
+}
+
+/// A segment of a kernel image.
+pub struct Segment {
+	/// The data of the segment, copied at the beginning of the destination.
+	pub data: Vec<u8>,
+	/// The physical address of the destination.
+	pub mem: usize,
+	/// The size of the destination in bytes. The part not covered by the data is zeroed.
+	pub memsz: usize,
+}
+
+/// An entry of the list of copies performed by the trampoline.
+#[repr(C)]
+struct Copy {
+	/// The physical address of the destination.
+	dst: u32,
+	/// The physical address of the source. If zero, the destination is zeroed.
+	src: u32,
+	/// The size of the copy in bytes.
+	size: u32,
+}
+
+/// A physically contiguous block of memory.
+struct Block {
+	/// The virtual address of the block.
+	ptr: NonNull<c_void>,
+	/// The order of the block.
+	order: FrameOrder,
+}
+
+impl Block {
+	/// Allocates a block of at least `size` bytes which doesn't overlap any of the ranges of
+	/// physical memory `forbidden`.
+	fn new(size: usize, forbidden: &[Range<usize>]) -> AllocResult<Self> {
+		let order = buddy::get_order(size.div_ceil(memory::PAGE_SIZE));
+		// Overlapping blocks are kept until the end to avoid getting them again
+		let mut rejected = Vec::new();
+		let mut res = Err(AllocError);
+		for _ in 0..ALLOC_ATTEMPTS {
+			let block = Self {
+				ptr: buddy::alloc_kernel(order)?,
+				order,
+			};
+			let begin = block.get_phys();
+			let end = begin + buddy::get_frame_size(order);
+			if forbidden.iter().all(|r| end <= r.start || r.end <= begin) {
+				res = Ok(block);
+				break;
+			}
+			rejected.push(block)?;
+		}
+		res
+	}
+
+	/// Returns the physical address of the block.
+	fn get_phys(&self) -> usize {
+		memory::kern_to_phys(self.ptr.as_ptr()) as _
+	}
+
+	/// Returns a slice over the block.
+	fn as_slice_mut(&mut self) -> &mut [u8] {
+		unsafe {
+			slice::from_raw_parts_mut(self.ptr.as_ptr() as _, buddy::get_frame_size(self.order))
+		}
+	}
+}
+
+impl Drop for Block {
+	fn drop(&mut self) {
+		buddy::free_kernel(self.ptr.as_ptr(), self.order);
+	}
+}
+
+/// A kernel image staged in memory.
+pub struct Image {
+	/// The physical address of the entry point.
+	entry: usize,
+	/// The control page, containing the trampoline followed by the list of copies.
+	control: Block,
+	/// The blocks containing the data of each segment.
+	_segments: Vec<Block>,
+}
+
+impl Image {
+	/// Stages a new kernel image.
+	///
+	/// Arguments:
+	/// - `entry` is the physical address of the entry point
+	/// - `segments` is the list of segments
+	///
+	/// If a segment is not page-aligned, has more data than its destination or overlaps with
+	/// another segment, the function returns [`crate::errno::EINVAL`].
+	///
+	/// If a segment is outside of the physical memory, the function returns
+	/// [`crate::errno::EADDRNOTAVAIL`].
+	pub fn new(entry: usize, segments: &[Segment]) -> EResult<Self> {
+		if segments.len() > SEGMENT_MAX {
+			return Err(errno!(EINVAL));
+		}
+
+		// Checking segments
+		let mem_info = memmap::get_info();
+		let mem_end =
+			mem_info.phys_main_begin as usize + mem_info.phys_main_pages * memory::PAGE_SIZE;
+		let mut ranges = Vec::new();
+		for seg in segments {
+			let aligned = seg.mem % memory::PAGE_SIZE == 0 && seg.memsz % memory::PAGE_SIZE == 0;
+			if !aligned || seg.data.len() > seg.memsz {
+				return Err(errno!(EINVAL));
+			}
+			let end = seg
+				.mem
+				.checked_add(seg.memsz)
+				.ok_or_else(|| errno!(EADDRNOTAVAIL))?;
+			if end > mem_end {
+				return Err(errno!(EADDRNOTAVAIL));
+			}
+			let range = seg.mem..end;
+			if ranges
+				.iter()
+				.any(|r: &Range<usize>| range.start < r.end && r.start < range.end)
+			{
+				return Err(errno!(EINVAL));
+			}
+			ranges.push(range)?;
+		}
+
+		// Staging segments and building the list of copies
+		let mut blocks = Vec::new();
+		let mut copies = Vec::new();
+		for seg in segments {
+			let data_len = seg.data.len();
+			if data_len > 0 {
+				let mut block = Block::new(data_len, &ranges)?;
+				block.as_slice_mut()[..data_len].copy_from_slice(&seg.data);
+				copies.push(Copy {
+					dst: seg.mem as _,
+					src: block.get_phys() as _,
+					size: data_len as _,
+				})?;
+				blocks.push(block)?;
+			}
+			if seg.memsz > data_len {
+				copies.push(Copy {
+					dst: (seg.mem + data_len) as _,
+					src: 0,
+					size: (seg.memsz - data_len) as _,
+				})?;
+			}
+		}
+		copies.push(Copy {
+			dst: 0,
+			src: 0,
+			size: 0,
+		})?;
+
+		// Building the control page
+		let trampoline = unsafe {
+			let begin = kexec_trampoline as *const u8;
+			let end = &kexec_trampoline_end as *const _ as *const u8;
+			slice::from_raw_parts(begin, end as usize - begin as usize)
+		};
+		let list_off = trampoline.len().next_multiple_of(size_of::<u32>());
+		let mut control = Block::new(memory::PAGE_SIZE, &ranges)?;
+		let control_slice = control.as_slice_mut();
+		control_slice[..trampoline.len()].copy_from_slice(trampoline);
+		unsafe {
+			let list = control_slice.as_mut_ptr().add(list_off) as *mut Copy;
+			ptr::copy_nonoverlapping(copies.as_ptr(), list, copies.len());
+		}
+
+		Ok(Self {
+			entry,
+			control,
+			_segments: blocks,
+		})
+	}
+}
+
+/// The currently loaded kernel image.
+static IMAGE: Mutex<Option<Image>> = Mutex::new(None);
+
+/// Sets the kernel image to be executed by [`exec`], replacing the previous one.
+///
+/// If `None`, the current image is unloaded.
+pub fn load(image: Option<Image>) {
+	*IMAGE.lock() = image;
+}
+
+/// Tells whether a kernel image is loaded.
+pub fn is_loaded() -> bool {
+	IMAGE.lock().is_some()
+}
+
+/// Executes the loaded kernel image.
+///
+/// If no image is loaded, the function returns.
+pub fn exec() {
+	let guard = IMAGE.lock();
+	let Some(image) = guard.as_ref() else {
+		return;
+	};
+
+	cli!();
+	crate::bind_vmem();
+
+	// Identity-map the control page so that execution continues once paging is disabled
+	let control_phys = image.control.get_phys();
+	{
+		let vmem = crate::get_vmem().lock();
+		let vmem = vmem.as_ref().unwrap();
+		if vmem.map(control_phys as _, control_phys as _, 0).is_err() {
+			return;
+		}
+	}
+
+	let trampoline_len =
+		unsafe { &kexec_trampoline_end as *const _ as usize - kexec_trampoline as usize };
+	let list = control_phys + trampoline_len.next_multiple_of(size_of::<u32>());
+	// Other cores must not run while the current kernel is being overwritten
+	smp::halt_others();
+	unsafe {
+		let trampoline: extern "C" fn(u32, u32) -> ! = core::mem::transmute(control_phys);
+		trampoline(list as _, image.entry as _);
+	}
+}
@@ -0,0 +1,55 @@
+.section .text
+
+.global kexec_trampoline
+.global kexec_trampoline_end
+
+.type kexec_trampoline, @function
+
+# Copies the segments of a new kernel to their destination, then jumps to its entry point.
+#
+# This code is copied to an identity-mapped page before being executed. Thus, it must be
+# position-independent and must not use the stack once paging is disabled.
+#
+# Arguments:
+# - the physical address of the list of copies to perform. Each entry is made of the destination,
+#   the source (if zero, the destination is zeroed instead) and the size in bytes. The list ends
+#   with an entry whose size is zero
+# - the physical address of the entry point of the new kernel
+kexec_trampoline:
+	mov 4(%esp), %edx # `list` argument
+	mov 8(%esp), %ebx # `entry` argument
+
+	# Disabling paging
+	mov %cr0, %eax
+	and $0x7fffffff, %eax
+	mov %eax, %cr0
+
+	cld
+1:
+	mov (%edx), %edi
+	mov 4(%edx), %esi
+	mov 8(%edx), %ecx
+	test %ecx, %ecx
+	jz 3f
+	test %esi, %esi
+	jz 2f
+	rep movsb
+	add $12, %edx
+	jmp 1b
+2:
+	xor %eax, %eax
+	rep stosb
+	add $12, %edx
+	jmp 1b
+
+3:
+	# Jumping to the new kernel with a clean state
+	xor %eax, %eax
+	xor %ecx, %ecx
+	xor %edx, %edx
+	xor %esi, %esi
+	xor %edi, %edi
+	xor %ebp, %ebp
+	xor %esp, %esp
+	jmp *%ebx
+kexec_trampoline_end:
@@ -66,7 +66,7 @@ pub const SS_REPL_MAX: usize = 4;
 pub const STREAM_MAX: usize = 8;
 /// Maximum number of symbolic links that can be reliably traversed in the
 /// resolution of a pathname in the absence of a loop.
-pub const SYMLOOP_MAX: usize = 8;
+pub const SYMLOOP_MAX: usize = 40;
 /// Maximum number of timers per process supported by the implementation.
 pub const TIMER_MAX: usize = 32;
 /// Maximum length of the trace event name (not including the terminating null).
@@ -121,3 +121,9 @@ pub const POSIX_REC_MIN_XFER_SIZE: usize = 1024;
 pub const POSIX_REC_XFER_ALIGN: usize = 4096;
 /// Maximum number of bytes in a symbolic link.
 pub const SYMLINK_MAX: usize = 4096;
+/// Maximum number of bytes in the name of an extended attribute.
+pub const XATTR_NAME_MAX: usize = 255;
+/// Maximum number of bytes in the value of an extended attribute.
+pub const XATTR_SIZE_MAX: usize = 65536;
+/// Maximum number of bytes in the list of the names of the extended attributes of a file.
+pub const XATTR_LIST_MAX: usize = 65536;
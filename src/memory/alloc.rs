@@ -12,6 +12,9 @@
 //! - User: Memory used for userspace mappings. This zone doesn't requires virtual memory to
 //! correspond with the physical memory, thus it can be located outside of the
 //! kernelspace.
+//! - DMA: Memory below 16MiB, which is reachable by legacy devices performing DMA (Direct Memory
+//! Access), such as the ISA DMA controller. Like the kernel zone, it is located in the
+//! kernelspace, so the kernel falls back to it when the kernel zone is full.
 
 use crate::memory;
 use crate::memory::buddy;
@@ -21,6 +24,9 @@ use crate::util::math;
 use core::cmp::min;
 use core::ffi::c_void;
 
+/// The physical address of the end of the DMA zone.
+const DMA_END: usize = 0x1000000;
+
 /// Initializes the memory allocators.
 pub fn init() {
 	let mmap_info = memmap::get_info();
@@ -42,15 +48,35 @@ pub fn init() {
 	// Updating the number of available pages
 	available_pages -= math::ceil_div(metadata_size, memory::PAGE_SIZE);
 
+	// The beginning of the DMA zone
+	let dma_zone_begin = util::align(phys_metadata_end, memory::PAGE_SIZE) as *mut c_void;
+	// The maximum number of pages the DMA zone can hold. If the kernel's image and the metadata
+	// already reach the end of the zone, it is empty
+	let dma_max = DMA_END.saturating_sub(dma_zone_begin as usize) / memory::PAGE_SIZE;
+	// The number of frames the DMA zone holds.
+	let dma_zone_frames = min(available_pages, dma_max);
+	// The DMA zone
+	let dma_zone = buddy::Zone::new(metadata_begin, dma_zone_frames as _, dma_zone_begin);
+
+	// Updating the number of available pages
+	available_pages -= dma_zone_frames;
+
 	// The beginning of the kernel's zone
-	let kernel_zone_begin = util::align(phys_metadata_end, memory::PAGE_SIZE) as *mut c_void;
+	let kernel_zone_begin = unsafe { dma_zone_begin.add(dma_zone_frames * memory::PAGE_SIZE) };
+	// The beginning of the kernel zone's metadata
+	let kernel_metadata_begin =
+		unsafe { metadata_begin.add(dma_zone_frames * buddy::get_frame_metadata_size()) };
 	// The maximum number of pages the kernel zone can hold.
 	let kernel_max =
-		(memory::get_kernelspace_size() - phys_metadata_end as usize) / memory::PAGE_SIZE;
+		(memory::get_direct_map_size() - kernel_zone_begin as usize) / memory::PAGE_SIZE;
 	// The number of frames the kernel zone holds.
 	let kernel_zone_frames = min(available_pages, kernel_max);
 	// The kernel's zone
-	let kernel_zone = buddy::Zone::new(metadata_begin, kernel_zone_frames as _, kernel_zone_begin);
+	let kernel_zone = buddy::Zone::new(
+		kernel_metadata_begin,
+		kernel_zone_frames as _,
+		kernel_zone_begin,
+	);
 
 	// Updating the number of available pages
 	available_pages -= kernel_zone_frames;
@@ -59,8 +85,9 @@ pub fn init() {
 	let userspace_zone_begin =
 		unsafe { kernel_zone_begin.add(kernel_zone_frames * memory::PAGE_SIZE) };
 	// The beginning of the userspace zone's metadata
-	let userspace_metadata_begin =
-		unsafe { metadata_begin.add(kernel_zone_frames * buddy::get_frame_metadata_size()) };
+	let userspace_metadata_begin = unsafe {
+		kernel_metadata_begin.add(kernel_zone_frames * buddy::get_frame_metadata_size())
+	};
 	let user_zone = buddy::Zone::new(
 		userspace_metadata_begin,
 		available_pages as _,
@@ -73,5 +100,6 @@ pub fn init() {
 		user_zone,
 		unsafe { core::mem::zeroed() }, // TODO MMIO
 		kernel_zone,
+		dma_zone,
 	]);
 }
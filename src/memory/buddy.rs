@@ -7,18 +7,21 @@
 //! The order of a frame is the `n` in the expression `pow(2, n)` that represents the
 //! size of a frame in pages.
 
+use super::pcp;
 use super::stats;
 use crate::errno::AllocError;
 use crate::errno::AllocResult;
 use crate::memory;
 use crate::util::lock::*;
 use crate::util::math;
+use core::cmp::max;
 use core::cmp::min;
 use core::ffi::c_void;
 use core::intrinsics::likely;
 use core::mem::size_of;
 use core::mem::MaybeUninit;
 use core::ptr::NonNull;
+use core::sync::atomic;
 
 /// Type representing the order of a memory frame.
 pub type FrameOrder = u8;
@@ -31,7 +34,7 @@ type FrameID = u32;
 pub const MAX_ORDER: FrameOrder = 17;
 
 /// The number of memory zones.
-pub const ZONES_COUNT: usize = 3;
+pub const ZONES_COUNT: usize = 4;
 
 /// The mask for the zone ID in buddy allocator flags.
 const ZONE_TYPE_MASK: Flags = 0b11;
@@ -42,6 +45,15 @@ pub const FLAG_ZONE_TYPE_USER: Flags = 0b00;
 pub const FLAG_ZONE_TYPE_MMIO: Flags = 0b01;
 /// Buddy allocator flag: allocate in kernel zone
 pub const FLAG_ZONE_TYPE_KERNEL: Flags = 0b10;
+/// Buddy allocator flag: allocate in DMA zone, below 16MiB in physical memory.
+///
+/// Frames in this zone are reachable by devices that can only address 24 bits.
+pub const FLAG_ZONE_TYPE_DMA: Flags = 0b11;
+/// Buddy allocator flag: allocate below 4GiB in physical memory, for devices that can only
+/// address 32 bits.
+///
+/// Since physical addresses are 32 bits long on this architecture, every zone is suitable.
+pub const FLAG_ZONE_TYPE_DMA32: Flags = FLAG_ZONE_TYPE_USER;
 
 /// Value indicating that the frame is used.
 pub const FRAME_STATE_USED: FrameID = !0_u32;
@@ -124,6 +136,44 @@ impl Zone {
 		(self.pages_count as usize) * memory::PAGE_SIZE
 	}
 
+	/// Returns the number of free frames of order `order` in the zone.
+	fn get_free_frames_count(&self, order: FrameOrder) -> usize {
+		let Some(first) = self.free_list[order as usize] else {
+			return 0;
+		};
+
+		let mut count = 1;
+		let mut frame = unsafe { &*first };
+		while frame.next != frame.get_id(self) {
+			frame = unsafe { &*self.get_frame(frame.next) };
+			count += 1;
+		}
+		count
+	}
+
+	/// Frees the frames from identifier `begin` to `end` (exclusive).
+	///
+	/// The range is divided into the largest frames whose boundaries match the buddy system.
+	fn free_range(&mut self, mut begin: FrameID, end: FrameID) {
+		while begin < end {
+			let mut order = min(begin.trailing_zeros(), MAX_ORDER as _) as FrameOrder;
+			while begin + math::pow2(order as FrameID) > end {
+				order -= 1;
+			}
+
+			let frame = unsafe { &mut *self.get_frame(begin) };
+			frame.order = order;
+			frame.mark_free(self);
+			frame.coalesce(self);
+
+			let pages = math::pow2(order as usize);
+			self.allocated_pages -= pages;
+			update_stats(-4 * pages as isize);
+
+			begin += pages as FrameID;
+		}
+	}
+
 	/// Returns an available frame owned by this zone, with an order of at least
 	/// `order`.
 	fn get_available_frame(&self, order: FrameOrder) -> Option<&'static mut Frame> {
@@ -494,36 +544,101 @@ fn get_zone_for_pointer<'z>(
 ///
 /// `order` is the order of the frame to be allocated.
 ///
-/// The given frame shall fit the flags `flags`.
+/// The given frame shall fit the flags `flags`. If the zone given by the flags is full, the
+/// frame is allocated in the zones that come after it. Thus, the DMA zone is used only when no
+/// other zone is available.
 ///
 /// If no suitable frame is found, the function returns an Err.
 pub fn alloc(order: FrameOrder, flags: Flags) -> AllocResult<NonNull<c_void>> {
 	debug_assert!(order <= MAX_ORDER);
 
+	let begin_zone = (flags & ZONE_TYPE_MASK) as usize;
+	let try_alloc = || {
+		(begin_zone..ZONES_COUNT).find_map(|zone| {
+			// Single pages are allocated through the per-CPU caches
+			if order == 0 {
+				pcp::alloc(zone)
+			} else {
+				alloc_in_zone(zone, order)
+			}
+		})
+	};
+	// If no frame is available, pages in the per-CPU caches may be coalesced into one
+	let ptr = try_alloc()
+		.or_else(|| {
+			(order > 0 && pcp::drain_all() > 0)
+				.then(try_alloc)
+				.flatten()
+		})
+		.ok_or(AllocError)?;
+
+	stats::PGALLOC.fetch_add(math::pow2(order as usize), atomic::Ordering::Relaxed);
+	update_stats(4 * math::pow2(order as usize) as isize);
+	NonNull::new(ptr).ok_or(AllocError)
+}
+
+/// Allocates a frame of order `order` in the zone with index `zone`, bypassing the per-CPU
+/// caches.
+///
+/// If no frame is available in the zone, the function returns `None`.
+fn alloc_in_zone(zone: usize, order: FrameOrder) -> Option<*mut c_void> {
 	let mut zones = ZONES.lock();
 	let zones = unsafe { zones.assume_init_mut() };
+	let zone = &mut zones[zone];
 
-	let begin_zone = (flags & ZONE_TYPE_MASK) as usize;
-	for zone in &mut zones[begin_zone..] {
-		let Some(frame) = zone.get_available_frame(order) else {
-			continue;
-		};
+	let frame = zone.get_available_frame(order)?;
+	debug_assert!(!frame.is_used());
+	frame.split(zone, order);
 
-		debug_assert!(!frame.is_used());
-		frame.split(zone, order);
+	let ptr = frame.get_ptr(zone);
+	debug_assert!(ptr.is_aligned_to(memory::PAGE_SIZE));
+	debug_assert!(ptr >= zone.begin && ptr < (zone.begin as usize + zone.get_size()) as _);
 
-		let ptr = frame.get_ptr(&zone);
-		debug_assert!(ptr.is_aligned_to(memory::PAGE_SIZE));
-		debug_assert!(ptr >= zone.begin && ptr < (zone.begin as usize + zone.get_size()) as _);
+	frame.mark_used();
+	zone.allocated_pages += math::pow2(order as usize);
 
-		frame.mark_used();
-		zone.allocated_pages += math::pow2(order as usize);
+	Some(ptr)
+}
 
-		update_stats(4 * math::pow2(order as usize) as isize);
-		return NonNull::new(ptr).ok_or(AllocError);
+/// Frees the frame at `ptr` with order `order` in the zone `zone`, bypassing the per-CPU caches.
+fn free_in_zone(zone: &mut Zone, ptr: *const c_void, order: FrameOrder) {
+	let frame_id = zone.get_frame_id_from_ptr(ptr);
+	debug_assert!(frame_id < zone.pages_count);
+
+	let frame = zone.get_frame(frame_id);
+	unsafe {
+		debug_assert!((*frame).is_used());
+		(*frame).mark_free(zone);
+		(*frame).coalesce(zone);
 	}
 
-	Err(AllocError)
+	zone.allocated_pages -= math::pow2(order as usize);
+}
+
+/// Fills `pages` with the physical addresses of single pages allocated in the zone with index
+/// `zone`, to refill a per-CPU cache.
+///
+/// The function returns the number of allocated pages, which may be less than the length of
+/// `pages` if the zone runs out of memory.
+pub(super) fn refill(zone: usize, pages: &mut [*mut c_void]) -> usize {
+	pages
+		.iter_mut()
+		.map_while(|page| {
+			*page = alloc_in_zone(zone, 0)?;
+			Some(())
+		})
+		.count()
+}
+
+/// Frees the single pages whose physical addresses are in `pages` to the zone with index `zone`,
+/// to drain a per-CPU cache.
+pub(super) fn drain(zone: usize, pages: &[*mut c_void]) {
+	let mut zones = ZONES.lock();
+	let zones = unsafe { zones.assume_init_mut() };
+	let zone = &mut zones[zone];
+	for page in pages {
+		free_in_zone(zone, *page, 0);
+	}
 }
 
 /// Calls `alloc` with order `order`.
@@ -546,22 +661,25 @@ pub fn free(ptr: *const c_void, order: FrameOrder) {
 	debug_assert!(ptr.is_aligned_to(memory::PAGE_SIZE));
 	debug_assert!(order <= MAX_ORDER);
 
-	let mut zones = ZONES.lock();
-	let zones = unsafe { zones.assume_init_mut() };
-
-	let zone = get_zone_for_pointer(zones, ptr).unwrap();
-
-	let frame_id = zone.get_frame_id_from_ptr(ptr);
-	debug_assert!(frame_id < zone.pages_count);
-
-	let frame = zone.get_frame(frame_id);
-	unsafe {
-		debug_assert!((*frame).is_used());
-		(*frame).mark_free(&zone);
-		(*frame).coalesce(zone);
+	if order == 0 {
+		let index = {
+			let zones = ZONES.lock();
+			let zones = unsafe { zones.assume_init_ref() };
+			zones
+				.iter()
+				.position(|z| ptr >= z.begin && (ptr as usize) < (z.begin as usize) + z.get_size())
+				.unwrap()
+		};
+		// The zones' lock must not be held while accessing the per-CPU caches
+		pcp::free(index, ptr as _);
+	} else {
+		let mut zones = ZONES.lock();
+		let zones = unsafe { zones.assume_init_mut() };
+		let zone = get_zone_for_pointer(zones, ptr).unwrap();
+		free_in_zone(zone, ptr, order);
 	}
 
-	zone.allocated_pages -= math::pow2(order as usize);
+	stats::PGFREE.fetch_add(math::pow2(order as usize), atomic::Ordering::Relaxed);
 	update_stats(-4 * math::pow2(order as usize) as isize);
 }
 
@@ -573,6 +691,77 @@ pub fn free_kernel(ptr: *const c_void, order: FrameOrder) {
 	free(memory::kern_to_phys(ptr), order);
 }
 
+/// Allocates `count` physically contiguous pages of memory.
+///
+/// The underlying frame is rounded up to the next power of two, then the pages past `count`
+/// are given back to the allocator, so that no memory is wasted.
+///
+/// The pages shall fit the flags `flags`.
+///
+/// The function returns the *physical* address to the first page. The pages must be freed with
+/// [`free_pages`].
+///
+/// If no suitable frame is found, the function returns an Err.
+pub fn alloc_pages(count: usize, flags: Flags) -> AllocResult<NonNull<c_void>> {
+	let order = match count {
+		0 | 1 => 0,
+		_ => (usize::BITS - (count - 1).leading_zeros()) as FrameOrder,
+	};
+	if order > MAX_ORDER {
+		return Err(AllocError);
+	}
+	let ptr = alloc(order, flags)?;
+
+	let mut zones = ZONES.lock();
+	let zones = unsafe { zones.assume_init_mut() };
+	let zone = get_zone_for_pointer(zones, ptr.as_ptr()).unwrap();
+
+	let begin = zone.get_frame_id_from_ptr(ptr.as_ptr());
+	let end = begin + max(count, 1) as FrameID;
+	// Marking every kept frames as used, so that freed pages are not coalesced with them
+	for id in begin..end {
+		unsafe {
+			(*zone.get_frame(id)).mark_used();
+		}
+	}
+	zone.free_range(end, begin + math::pow2(order as FrameID));
+
+	Ok(ptr)
+}
+
+/// Frees `count` pages that were allocated with [`alloc_pages`].
+///
+/// `ptr` is the *physical* address to the first page. `count` must be the same as the one given
+/// to allocate the pages.
+pub fn free_pages(ptr: *const c_void, count: usize) {
+	debug_assert!(ptr.is_aligned_to(memory::PAGE_SIZE));
+
+	let mut zones = ZONES.lock();
+	let zones = unsafe { zones.assume_init_mut() };
+	let zone = get_zone_for_pointer(zones, ptr).unwrap();
+
+	let begin = zone.get_frame_id_from_ptr(ptr);
+	let end = begin + max(count, 1) as FrameID;
+	debug_assert!(end <= zone.pages_count);
+	zone.free_range(begin, end);
+}
+
+/// Returns the number of free frames of each order, for every zones.
+///
+/// The first index of the returned array is the zone and the second is the order.
+pub fn get_free_frames() -> [[usize; (MAX_ORDER + 1) as usize]; ZONES_COUNT] {
+	let zones = ZONES.lock();
+	let zones = unsafe { zones.assume_init_ref() };
+
+	let mut counts = [[0; (MAX_ORDER + 1) as usize]; ZONES_COUNT];
+	for (zone, counts) in zones.iter().zip(counts.iter_mut()) {
+		for (order, count) in counts.iter_mut().enumerate() {
+			*count = zone.get_free_frames_count(order as _);
+		}
+	}
+	counts
+}
+
 /// Updates stats on memory usage.
 ///
 /// `n` is the delta of allocated chunks:
@@ -590,10 +779,13 @@ pub fn update_stats(n: isize) {
 
 /// Returns the total number of pages allocated by the buddy allocator.
 pub fn allocated_pages_count() -> usize {
-	let zones = ZONES.lock();
-	let zones = unsafe { zones.assume_init_ref() };
-
-	zones.iter().map(|z| z.allocated_pages).sum()
+	let allocated: usize = {
+		let zones = ZONES.lock();
+		let zones = unsafe { zones.assume_init_ref() };
+		zones.iter().map(|z| z.allocated_pages).sum()
+	};
+	// Pages in the per-CPU caches are free
+	allocated - pcp::cached_count()
 }
 
 #[cfg(test)]
@@ -707,6 +899,24 @@ mod test {
 		debug_assert_eq!(allocated_pages_count(), alloc_pages);
 	}
 
+	#[test_case]
+	fn buddy_pages() {
+		let allocated = allocated_pages_count();
+
+		for count in [1, 3, 5, 8, 13] {
+			let p = alloc_pages(count, FLAG_ZONE_TYPE_KERNEL).unwrap();
+			assert_eq!(allocated_pages_count(), allocated + count);
+
+			let virt_ptr = memory::kern_to_virt(p.as_ptr()) as *mut u8;
+			let slice = unsafe { slice::from_raw_parts_mut(virt_ptr, count * memory::PAGE_SIZE) };
+			slice.fill(!0);
+
+			free_pages(p.as_ptr(), count);
+		}
+
+		debug_assert_eq!(allocated_pages_count(), allocated);
+	}
+
 	struct TestDupNode {
 		next: *mut TestDupNode,
 	}
@@ -732,6 +942,19 @@ mod test {
 		tortoise == hoare
 	}
 
+	#[test_case]
+	fn buddy_dma() {
+		let alloc_pages = allocated_pages_count();
+
+		// The DMA zone may be empty
+		if let Ok(p) = alloc(0, FLAG_ZONE_TYPE_DMA) {
+			assert!((p.as_ptr() as usize) < 0x1000000);
+			free(p.as_ptr(), 0);
+		}
+
+		debug_assert_eq!(allocated_pages_count(), alloc_pages);
+	}
+
 	/// Testing whether the allocator returns pages that are already allocated
 	#[test_case]
 	fn buddy_full_duplicate() {
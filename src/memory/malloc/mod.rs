@@ -13,6 +13,7 @@ use crate::errno::AllocError;
 use crate::errno::AllocResult;
 use crate::memory;
 use crate::memory::malloc::ptr::NonNull;
+use crate::memory::slab;
 use crate::util::lock::IntMutex;
 use block::Block;
 use chunk::Chunk;
@@ -45,6 +46,11 @@ static MUTEX: IntMutex<()> = IntMutex::new(());
 /// leak. Writing outside of the allocated range (buffer overflow) results in an
 /// undefined behaviour.
 pub unsafe fn alloc(n: NonZeroUsize) -> AllocResult<NonNull<c_void>> {
+	// Small allocations are served by slab caches
+	if let Some(cache) = slab::get_malloc_cache(n.get()) {
+		return cache.alloc();
+	}
+
 	let _ = MUTEX.lock();
 
 	let free_chunk = chunk::get_available_chunk(n)?;
@@ -73,6 +79,18 @@ pub unsafe fn alloc(n: NonZeroUsize) -> AllocResult<NonNull<c_void>> {
 /// If the reallocation fails, the chunk is left untouched and the function
 /// returns an error.
 pub unsafe fn realloc(ptr: NonNull<c_void>, n: NonZeroUsize) -> AllocResult<NonNull<c_void>> {
+	if let Some(cache) = slab::get_cache_for(ptr) {
+		let obj_size = cache.get_obj_size();
+		if n.get() <= obj_size {
+			return Ok(ptr);
+		}
+
+		let mut new_ptr = alloc(n)?;
+		ptr::copy_nonoverlapping(ptr.as_ptr(), new_ptr.as_mut(), obj_size);
+		cache.free(ptr);
+		return Ok(new_ptr);
+	}
+
 	let _ = MUTEX.lock();
 
 	let chunk = Chunk::from_ptr(ptr.as_ptr());
@@ -116,6 +134,12 @@ pub unsafe fn realloc(ptr: NonNull<c_void>, n: NonZeroUsize) -> AllocResult<NonN
 ///
 /// Using memory after it was freed causes an undefined behaviour.
 pub unsafe fn free(mut ptr: NonNull<c_void>) {
+	if let Some(cache) = slab::get_cache_for(ptr) {
+		// The object has been used, it may not be in its constructed state anymore
+		cache.free_unconstructed(ptr);
+		return;
+	}
+
 	let _ = MUTEX.lock();
 
 	let chunk = Chunk::from_ptr(ptr.as_mut());
@@ -7,15 +7,21 @@
 //! - Userspace: Virtual memory below `PROCESS_END`, used by the currently running process
 //! - Kernelspace: Virtual memory above `PROCESS_END`, used by the kernel itself and shared accross
 //! processes
+//!
+//! The kernelspace itself begins with a direct mapping of the physical memory, followed by the
+//! region used by [`vmalloc`].
 
 pub mod alloc;
 pub mod buddy;
 pub mod malloc;
 pub mod memmap;
 pub mod mmio;
+pub mod pcp;
 pub mod physical_ref_counter;
+pub mod slab;
 pub mod stack;
 pub mod stats;
+pub mod vmalloc;
 pub mod vmem;
 
 use core::ffi::c_void;
@@ -32,6 +38,8 @@ pub const KERNEL_PHYS_BEGIN: *const c_void = 0x100000 as *const _;
 pub const ALLOC_BEGIN: *mut c_void = 0x40000000 as *mut _;
 /// Pointer to the end of the virtual memory reserved to the process.
 pub const PROCESS_END: *mut c_void = 0xc0000000 as *mut _;
+/// The size of the region of the kernelspace reserved to [`vmalloc`], in bytes.
+pub const VMALLOC_SIZE: usize = 0x8000000;
 
 extern "C" {
 	/// The kernel begin symbol, giving the pointer to the begin of the kernel
@@ -59,6 +67,19 @@ pub fn get_kernelspace_size() -> usize {
 	usize::MAX - PROCESS_END as usize + 1
 }
 
+/// Returns the size of the direct mapping of the physical memory in the kernelspace, in bytes.
+#[inline(always)]
+pub fn get_direct_map_size() -> usize {
+	get_kernelspace_size() - VMALLOC_SIZE
+}
+
+/// Returns a pointer to the beginning of the region reserved to [`vmalloc`] in the virtual
+/// memory.
+#[inline(always)]
+pub fn get_vmalloc_begin() -> *const c_void {
+	(PROCESS_END as usize + get_direct_map_size()) as _
+}
+
 /// Returns the size of the kernel image in bytes.
 #[inline(always)]
 pub fn get_kernel_size() -> usize {
@@ -79,7 +100,7 @@ pub fn get_kernel_virtual_end() -> *const c_void {
 
 /// Converts a kernel physical address to a virtual address.
 pub fn kern_to_virt<T>(ptr: *const T) -> *const T {
-	if (ptr as usize) < get_kernelspace_size() {
+	if (ptr as usize) < get_direct_map_size() {
 		((ptr as usize) + (PROCESS_END as usize)) as *const T
 	} else {
 		ptr
@@ -0,0 +1,149 @@
+//! Per-CPU page caches (pcp) sit in front of the buddy allocator for allocations of single
+//! pages, which are the most frequent.
+//!
+//! Each CPU has a cache of free pages for each zone. Allocations and frees of single pages are
+//! served from the cache of the current CPU, without taking the buddy allocator's lock. When a
+//! cache is empty, it is refilled with a batch of pages from the buddy allocator. When it
+//! exceeds its high watermark, a batch of pages is drained back to the buddy allocator.
+//!
+//! From the point of view of the buddy allocator, pages in the caches are allocated.
+
+use super::buddy;
+use crate::cpu;
+use crate::util::lock::IntMutex;
+use core::ffi::c_void;
+use core::ptr::null_mut;
+
+/// The number of pages moved at once between a cache and the buddy allocator.
+pub const BATCH: usize = 16;
+/// The maximum number of pages in a cache. When reached, a batch of pages is drained.
+pub const HIGH: usize = 4 * BATCH;
+
+/// Statistics of a cache.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Stats {
+	/// The number of pages currently in the cache.
+	pub count: usize,
+	/// The number of allocations served by the cache.
+	pub hits: usize,
+	/// The number of times the cache has been refilled from the buddy allocator.
+	pub refills: usize,
+	/// The number of times a batch of pages has been drained to the buddy allocator.
+	pub drains: usize,
+}
+
+/// A cache of free pages for a zone.
+struct PageCache {
+	/// The physical addresses of the pages in the cache. Only the `count` first elements are
+	/// valid. The last pages are the most recently freed, thus the most likely to be hot.
+	pages: [*mut c_void; HIGH],
+	/// The statistics of the cache.
+	stats: Stats,
+}
+
+impl PageCache {
+	/// Creates an empty cache.
+	const fn new() -> Self {
+		Self {
+			pages: [null_mut(); HIGH],
+			stats: Stats {
+				count: 0,
+				hits: 0,
+				refills: 0,
+				drains: 0,
+			},
+		}
+	}
+}
+
+/// An empty cache, used to initialize the caches.
+const EMPTY_CACHE: PageCache = PageCache::new();
+/// The empty caches of a CPU, used to initialize the caches.
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_CPU_CACHES: IntMutex<[PageCache; buddy::ZONES_COUNT]> =
+	IntMutex::new([EMPTY_CACHE; buddy::ZONES_COUNT]);
+
+/// The caches, by CPU, then by zone.
+static CACHES: [IntMutex<[PageCache; buddy::ZONES_COUNT]>; cpu::MAX_CPUS] =
+	[EMPTY_CPU_CACHES; cpu::MAX_CPUS];
+
+/// Allocates a page in the zone with index `zone`, from the cache of the current CPU.
+///
+/// If the cache is empty, it is refilled first.
+///
+/// If no page is available in the zone, the function returns `None`.
+pub(super) fn alloc(zone: usize) -> Option<*mut c_void> {
+	let mut caches = CACHES[cpu::get_current_id()].lock();
+	let cache = &mut caches[zone];
+
+	if cache.stats.count == 0 {
+		let count = buddy::refill(zone, &mut cache.pages[..BATCH]);
+		if count == 0 {
+			return None;
+		}
+		cache.stats.count = count;
+		cache.stats.refills += 1;
+	}
+
+	cache.stats.count -= 1;
+	cache.stats.hits += 1;
+	Some(cache.pages[cache.stats.count])
+}
+
+/// Frees the page at physical address `ptr` in the zone with index `zone`, to the cache of the
+/// current CPU.
+///
+/// If the cache is full, a batch of the least recently freed pages is drained first.
+pub(super) fn free(zone: usize, ptr: *mut c_void) {
+	let mut caches = CACHES[cpu::get_current_id()].lock();
+	let cache = &mut caches[zone];
+
+	if cache.stats.count == HIGH {
+		buddy::drain(zone, &cache.pages[..BATCH]);
+		cache.pages.copy_within(BATCH.., 0);
+		cache.stats.count -= BATCH;
+		cache.stats.drains += 1;
+	}
+
+	cache.pages[cache.stats.count] = ptr;
+	cache.stats.count += 1;
+}
+
+/// Drains every pages of every caches back to the buddy allocator, allowing them to be
+/// coalesced into larger frames.
+///
+/// The function returns the number of drained pages.
+pub(super) fn drain_all() -> usize {
+	let mut total = 0;
+	for caches in CACHES.iter() {
+		let mut caches = caches.lock();
+		for (zone, cache) in caches.iter_mut().enumerate() {
+			if cache.stats.count == 0 {
+				continue;
+			}
+			buddy::drain(zone, &cache.pages[..cache.stats.count]);
+			total += cache.stats.count;
+			cache.stats.count = 0;
+			cache.stats.drains += 1;
+		}
+	}
+	total
+}
+
+/// Returns the total number of pages in the caches.
+pub(super) fn cached_count() -> usize {
+	CACHES
+		.iter()
+		.map(|caches| caches.lock().iter().map(|c| c.stats.count).sum::<usize>())
+		.sum()
+}
+
+/// Returns the statistics of the caches of the CPU with ID `cpu`, by zone.
+pub fn get_stats(cpu: usize) -> [Stats; buddy::ZONES_COUNT] {
+	let caches = CACHES[cpu].lock();
+	let mut stats = [Stats::default(); buddy::ZONES_COUNT];
+	for (stats, cache) in stats.iter_mut().zip(caches.iter()) {
+		*stats = cache.stats;
+	}
+	stats
+}
@@ -0,0 +1,515 @@
+//! The slab allocator provides caches of fixed-size objects, on top of the buddy allocator.
+//!
+//! Each cache allocates slabs, which are pages of memory divided into objects of the same size.
+//! Since objects of the same size are grouped together, allocating and freeing them is fast and
+//! doesn't fragment memory.
+//!
+//! A cache may have a constructor, which is called on every object when its slab is created.
+//! When freed, objects must be left in their constructed state, so that they can be reused
+//! without being constructed again. For this reason, the list of free objects of a slab is kept
+//! in its header instead of in the objects themselves.
+//!
+//! Small allocations performed with `malloc` are served by caches of several size classes.
+//! Objects allocated often, such as files and processes, have their own caches.
+
+use crate::errno::AllocResult;
+use crate::memory;
+use crate::memory::buddy;
+use crate::util;
+use crate::util::lock::IntMutex;
+use core::ffi::c_void;
+use core::mem::size_of;
+use core::ptr;
+use core::ptr::NonNull;
+use core::slice;
+use core::sync::atomic;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::AtomicPtr;
+use core::sync::atomic::AtomicU32;
+
+/// The required alignment for objects.
+const ALIGNMENT: usize = 8;
+
+/// The number of pages in the kernelspace.
+const KERNELSPACE_PAGES: usize = (usize::MAX - 0xc0000000 + 1) / memory::PAGE_SIZE;
+
+/// Bitmap telling which pages of the kernelspace are slabs.
+///
+/// This allows `malloc` to know which allocator a pointer comes from.
+static SLAB_PAGES: [AtomicU32; KERNELSPACE_PAGES / 32] = {
+	#[allow(clippy::declare_interior_mutable_const)]
+	const INIT: AtomicU32 = AtomicU32::new(0);
+	[INIT; KERNELSPACE_PAGES / 32]
+};
+
+/// The first registered cache. Other caches are linked to it through their `next` field.
+static CACHES: AtomicPtr<SlabCache> = AtomicPtr::new(ptr::null_mut());
+
+/// The header of a slab, located at the beginning of its page.
+///
+/// The header is followed by the stack of the indexes of free objects in the slab, then by the
+/// objects themselves.
+struct Slab {
+	/// The cache owning the slab.
+	cache: *const SlabCache,
+
+	/// The previous slab in the cache's list of partial slabs.
+	prev: Option<NonNull<Slab>>,
+	/// The next slab in the cache's list of partial slabs.
+	next: Option<NonNull<Slab>>,
+
+	/// The number of allocated objects in the slab. The number of elements on the stack of free
+	/// objects is the number of objects per slab minus this value.
+	inuse: usize,
+}
+
+/// Returns the offset of the first object in a slab with `objs` objects.
+const fn objs_offset(objs: usize) -> usize {
+	let end = size_of::<Slab>() + objs * size_of::<u16>();
+	(end + ALIGNMENT - 1) & !(ALIGNMENT - 1)
+}
+
+/// Returns the index of the page at `ptr` in [`SLAB_PAGES`], along with the mask of its bit.
+fn page_bit(ptr: *const c_void) -> (usize, u32) {
+	let page = (ptr as usize - memory::PROCESS_END as usize) / memory::PAGE_SIZE;
+	(page / 32, 1 << (page % 32))
+}
+
+/// The mutable state of a cache.
+struct CacheState {
+	/// The list of slabs that have at least one free object.
+	partial: Option<NonNull<Slab>>,
+
+	/// The total number of slabs.
+	slabs_count: usize,
+	/// The number of allocated objects.
+	active_count: usize,
+}
+
+/// Statistics about a cache.
+pub struct CacheStats {
+	/// The number of allocated objects.
+	pub active_objs: usize,
+	/// The total number of objects, either allocated or not.
+	pub num_objs: usize,
+	/// The size of an object in bytes.
+	pub obj_size: usize,
+	/// The number of objects per slab.
+	pub objs_per_slab: usize,
+}
+
+/// A cache of objects of the same size.
+pub struct SlabCache {
+	/// The name of the cache.
+	name: &'static str,
+	/// The size of an object in bytes.
+	obj_size: usize,
+	/// The number of objects per slab.
+	objs_per_slab: usize,
+	/// The offset of the first object in a slab.
+	objs_offset: usize,
+	/// The constructor of the objects, if any.
+	ctor: Option<fn(NonNull<c_void>)>,
+
+	/// The state of the cache.
+	state: IntMutex<CacheState>,
+
+	/// Tells whether the cache has been registered in the list of caches.
+	registered: AtomicBool,
+	/// The next cache in the list of caches.
+	next: AtomicPtr<SlabCache>,
+}
+
+impl SlabCache {
+	/// Creates a new cache.
+	///
+	/// Arguments:
+	/// - `name` is the name of the cache, as reported in `/proc/slabinfo`
+	/// - `size` is the size of an object in bytes
+	/// - `ctor` is the constructor of objects
+	///
+	/// A slab being a single page, an object cannot be larger than a page, minus the header of
+	/// the slab.
+	pub const fn new(name: &'static str, size: usize, ctor: Option<fn(NonNull<c_void>)>) -> Self {
+		let obj_size = if size == 0 { 1 } else { size };
+		let obj_size = (obj_size + ALIGNMENT - 1) & !(ALIGNMENT - 1);
+
+		// Each object takes an entry on the stack of free objects
+		let mut objs_per_slab =
+			(memory::PAGE_SIZE - size_of::<Slab>()) / (obj_size + size_of::<u16>());
+		// Aligning the objects may leave no room for the last one
+		while objs_offset(objs_per_slab) + objs_per_slab * obj_size > memory::PAGE_SIZE {
+			objs_per_slab -= 1;
+		}
+		assert!(objs_per_slab > 0);
+
+		Self {
+			name,
+			obj_size,
+			objs_per_slab,
+			objs_offset: objs_offset(objs_per_slab),
+			ctor,
+
+			state: IntMutex::new(CacheState {
+				partial: None,
+
+				slabs_count: 0,
+				active_count: 0,
+			}),
+
+			registered: AtomicBool::new(false),
+			next: AtomicPtr::new(ptr::null_mut()),
+		}
+	}
+
+	/// Returns the name of the cache.
+	pub fn get_name(&self) -> &'static str {
+		self.name
+	}
+
+	/// Returns the size of an object in bytes.
+	pub fn get_obj_size(&self) -> usize {
+		self.obj_size
+	}
+
+	/// Returns the number of objects per slab.
+	fn objs_per_slab(&self) -> usize {
+		self.objs_per_slab
+	}
+
+	/// Returns the stack of the indexes of free objects of the slab `slab`.
+	///
+	/// Only the first `objs_per_slab - inuse` elements are free objects.
+	///
+	/// # Safety
+	///
+	/// The slab must belong to the current cache, and the cache must be locked.
+	unsafe fn free_stack<'a>(&self, slab: NonNull<Slab>) -> &'a mut [u16] {
+		let ptr = slab.as_ptr().add(1) as *mut u16;
+		slice::from_raw_parts_mut(ptr, self.objs_per_slab)
+	}
+
+	/// Returns the pointer to the object with index `i` in the slab `slab`.
+	fn get_obj(&self, slab: NonNull<Slab>, i: usize) -> NonNull<c_void> {
+		let obj = slab.as_ptr() as usize + self.objs_offset + i * self.obj_size;
+		unsafe { NonNull::new_unchecked(obj as _) }
+	}
+
+	/// Returns statistics about the cache.
+	pub fn get_stats(&self) -> CacheStats {
+		let state = self.state.lock();
+		CacheStats {
+			active_objs: state.active_count,
+			num_objs: state.slabs_count * self.objs_per_slab(),
+			obj_size: self.obj_size,
+			objs_per_slab: self.objs_per_slab(),
+		}
+	}
+
+	/// Inserts the cache in the list of caches, if not already done.
+	fn register(&'static self) {
+		if self.registered.swap(true, atomic::Ordering::AcqRel) {
+			return;
+		}
+
+		let this = self as *const _ as *mut _;
+		let mut head = CACHES.load(atomic::Ordering::Acquire);
+		loop {
+			self.next.store(head, atomic::Ordering::Release);
+			match CACHES.compare_exchange_weak(
+				head,
+				this,
+				atomic::Ordering::AcqRel,
+				atomic::Ordering::Acquire,
+			) {
+				Ok(_) => break,
+				Err(h) => head = h,
+			}
+		}
+	}
+
+	/// Allocates a new slab and inserts it in the list of partial slabs.
+	fn new_slab(&'static self, state: &mut CacheState) -> AllocResult<NonNull<Slab>> {
+		let page = buddy::alloc_kernel(0)?;
+		let slab = page.cast::<Slab>();
+
+		for i in 0..self.objs_per_slab {
+			if let Some(ctor) = self.ctor {
+				ctor(self.get_obj(slab, i));
+			}
+		}
+		// Build the stack of free objects, so that they are allocated in order
+		let free = unsafe { self.free_stack(slab) };
+		for (i, index) in free.iter_mut().rev().enumerate() {
+			*index = i as _;
+		}
+
+		unsafe {
+			ptr::write(
+				slab.as_ptr(),
+				Slab {
+					cache: self,
+
+					prev: None,
+					next: state.partial,
+
+					inuse: 0,
+				},
+			);
+			if let Some(mut next) = state.partial {
+				next.as_mut().prev = Some(slab);
+			}
+		}
+		state.partial = Some(slab);
+		state.slabs_count += 1;
+
+		let (i, bit) = page_bit(slab.as_ptr() as _);
+		SLAB_PAGES[i].fetch_or(bit, atomic::Ordering::Release);
+
+		Ok(slab)
+	}
+
+	/// Removes the slab `slab` from the list of partial slabs.
+	///
+	/// # Safety
+	///
+	/// The slab must be in the list.
+	unsafe fn unlink(state: &mut CacheState, slab: &mut Slab) {
+		if let Some(mut prev) = slab.prev {
+			prev.as_mut().next = slab.next;
+		} else {
+			state.partial = slab.next;
+		}
+		if let Some(mut next) = slab.next {
+			next.as_mut().prev = slab.prev;
+		}
+		slab.prev = None;
+		slab.next = None;
+	}
+
+	/// Allocates an object.
+	///
+	/// If the cache has a constructor, the object is in its constructed state. Otherwise, its
+	/// content is **not** initialized.
+	pub fn alloc(&'static self) -> AllocResult<NonNull<c_void>> {
+		self.register();
+
+		let mut state = self.state.lock();
+		let mut slab_ptr = match state.partial {
+			Some(slab) => slab,
+			None => self.new_slab(&mut state)?,
+		};
+		let slab = unsafe { slab_ptr.as_mut() };
+
+		// A partial slab always has at least one free object
+		let free = unsafe { self.free_stack(slab_ptr) };
+		let index = free[self.objs_per_slab - slab.inuse - 1];
+		slab.inuse += 1;
+		if slab.inuse == self.objs_per_slab {
+			unsafe {
+				Self::unlink(&mut state, slab);
+			}
+		}
+		state.active_count += 1;
+
+		Ok(self.get_obj(slab_ptr, index as _))
+	}
+
+	/// Frees the object at `ptr`.
+	///
+	/// # Safety
+	///
+	/// The object must have been allocated with the current cache. If the cache has a
+	/// constructor, the object must be in its constructed state.
+	///
+	/// Using the object after it was freed causes an undefined behaviour.
+	pub unsafe fn free(&self, ptr: NonNull<c_void>) {
+		let slab_ptr = util::down_align(ptr.as_ptr(), memory::PAGE_SIZE) as *mut Slab;
+		let slab_ptr = NonNull::new_unchecked(slab_ptr);
+		let slab = &mut *slab_ptr.as_ptr();
+		debug_assert_eq!(slab.cache, self as *const _);
+		let index = (ptr.as_ptr() as usize - slab_ptr.as_ptr() as usize - self.objs_offset)
+			/ self.obj_size;
+
+		let mut state = self.state.lock();
+		let was_full = slab.inuse == self.objs_per_slab;
+
+		let free = self.free_stack(slab_ptr);
+		free[self.objs_per_slab - slab.inuse] = index as _;
+		slab.inuse -= 1;
+		state.active_count -= 1;
+
+		if slab.inuse == 0 {
+			// The slab is empty, give it back to the buddy allocator
+			if !was_full {
+				Self::unlink(&mut state, slab);
+			}
+			state.slabs_count -= 1;
+
+			let (i, bit) = page_bit(slab as *const _ as _);
+			SLAB_PAGES[i].fetch_and(!bit, atomic::Ordering::Release);
+			buddy::free_kernel(slab as *const _ as _, 0);
+		} else if was_full {
+			slab.prev = None;
+			slab.next = state.partial;
+			if let Some(mut next) = state.partial {
+				next.as_mut().prev = NonNull::new(slab);
+			}
+			state.partial = NonNull::new(slab);
+		}
+	}
+
+	/// Frees the object at `ptr`, which may not be in its constructed state.
+	///
+	/// If the cache has a constructor, it is called on the object again before freeing it.
+	///
+	/// # Safety
+	///
+	/// The object must have been allocated with the current cache.
+	///
+	/// Using the object after it was freed causes an undefined behaviour.
+	pub unsafe fn free_unconstructed(&self, ptr: NonNull<c_void>) {
+		if let Some(ctor) = self.ctor {
+			ctor(ptr);
+		}
+		self.free(ptr);
+	}
+}
+
+/// Calls `f` on every caches that have been used at least once.
+pub fn foreach<F: FnMut(&'static SlabCache)>(mut f: F) {
+	// Caches are never removed from the list, so it can be walked without locking
+	let mut cache = CACHES.load(atomic::Ordering::Acquire);
+	while let Some(c) = unsafe { cache.as_ref() } {
+		f(c);
+		cache = c.next.load(atomic::Ordering::Acquire);
+	}
+}
+
+/// Returns the total number of pages used by slabs.
+pub fn get_pages_count() -> usize {
+	let mut count = 0;
+	foreach(|cache| {
+		let stats = cache.get_stats();
+		count += stats.num_objs / stats.objs_per_slab;
+	});
+	count
+}
+
+/// A constructor filling objects of `SIZE` bytes with zeros, so that a new slab never exposes
+/// the previous content of its page.
+pub fn zero_ctor<const SIZE: usize>(obj: NonNull<c_void>) {
+	unsafe {
+		ptr::write_bytes(obj.as_ptr() as *mut u8, 0, SIZE);
+	}
+}
+
+/// The caches used by `malloc`, by size class.
+static MALLOC_CACHES: [SlabCache; 7] = [
+	SlabCache::new("kmalloc-8", 8, None),
+	SlabCache::new("kmalloc-16", 16, None),
+	SlabCache::new("kmalloc-32", 32, None),
+	SlabCache::new("kmalloc-64", 64, None),
+	SlabCache::new("kmalloc-128", 128, None),
+	SlabCache::new("kmalloc-256", 256, None),
+	SlabCache::new("kmalloc-512", 512, None),
+];
+
+/// Returns the `malloc` cache for allocations of `size` bytes.
+///
+/// If the size is too large to be allocated in a cache, the function returns `None`.
+pub fn get_malloc_cache(size: usize) -> Option<&'static SlabCache> {
+	MALLOC_CACHES.iter().find(|c| c.obj_size >= size)
+}
+
+/// Returns the cache owning the object at `ptr`.
+///
+/// If the object doesn't belong to a slab, the function returns `None`.
+pub fn get_cache_for(ptr: NonNull<c_void>) -> Option<&'static SlabCache> {
+	let (i, bit) = page_bit(ptr.as_ptr());
+	if SLAB_PAGES[i].load(atomic::Ordering::Acquire) & bit == 0 {
+		return None;
+	}
+
+	let slab = util::down_align(ptr.as_ptr(), memory::PAGE_SIZE) as *const Slab;
+	unsafe { (*slab).cache.as_ref() }
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	/// A cache used for testing.
+	static TEST_CACHE: SlabCache = SlabCache::new("test", 24, Some(test_ctor));
+
+	/// Fills the object with a recognizable pattern.
+	fn test_ctor(obj: NonNull<c_void>) {
+		unsafe {
+			ptr::write_bytes(obj.as_ptr() as *mut u8, 0x42, 24);
+		}
+	}
+
+	/// Tells whether the whole object is in its constructed state.
+	fn is_constructed(obj: NonNull<c_void>) -> bool {
+		let content = unsafe { *(obj.as_ptr() as *const [u8; 24]) };
+		content.iter().all(|b| *b == 0x42)
+	}
+
+	#[test_case]
+	fn slab_alloc_free() {
+		let objs_per_slab = TEST_CACHE.objs_per_slab();
+
+		let mut objs = [None; 100];
+		for obj in objs.iter_mut() {
+			let ptr = TEST_CACHE.alloc().unwrap();
+			assert!(is_constructed(ptr));
+			assert!(get_cache_for(ptr).is_some());
+
+			*obj = Some(ptr);
+		}
+		let stats = TEST_CACHE.get_stats();
+		assert_eq!(stats.active_objs, 100);
+		assert!(stats.num_objs >= 100 && stats.num_objs < 100 + objs_per_slab);
+
+		for obj in objs.iter() {
+			unsafe {
+				TEST_CACHE.free(obj.unwrap());
+			}
+		}
+		let stats = TEST_CACHE.get_stats();
+		assert_eq!(stats.active_objs, 0);
+		assert_eq!(stats.num_objs, 0);
+	}
+
+	#[test_case]
+	fn slab_free_keeps_constructed() {
+		let a = TEST_CACHE.alloc().unwrap();
+		let b = TEST_CACHE.alloc().unwrap();
+		unsafe {
+			TEST_CACHE.free(a);
+		}
+		// The freed object is reused without being constructed again
+		let c = TEST_CACHE.alloc().unwrap();
+		assert_eq!(c, a);
+		assert!(is_constructed(c));
+
+		unsafe {
+			TEST_CACHE.free(b);
+			TEST_CACHE.free(c);
+		}
+	}
+
+	#[test_case]
+	fn slab_free_unconstructed() {
+		let a = TEST_CACHE.alloc().unwrap();
+		unsafe {
+			ptr::write_bytes(a.as_ptr() as *mut u8, 0, 24);
+			TEST_CACHE.free_unconstructed(a);
+		}
+		let b = TEST_CACHE.alloc().unwrap();
+		assert!(is_constructed(b));
+		unsafe {
+			TEST_CACHE.free(b);
+		}
+	}
+}
@@ -1,8 +1,7 @@
 //! This module implements statistics about memory usage.
 
-use crate::errno::AllocResult;
-use crate::util::container::string::String;
 use crate::util::lock::Mutex;
+use core::sync::atomic::AtomicUsize;
 
 /// This structure stores memory usage informations. Each field is in KiB.
 pub struct MemInfo {
@@ -12,21 +11,15 @@ pub struct MemInfo {
 	pub mem_free: usize,
 }
 
-impl MemInfo {
-	/// Returns the string representation of the current structure.
-	pub fn to_string(&self) -> AllocResult<String> {
-		crate::format!(
-			"MemTotal: {} kB
-MemFree: {} kB
-",
-			self.mem_total,
-			self.mem_free,
-		)
-	}
-}
-
 /// The global variable storing memory usage informations.
 pub static MEM_INFO: Mutex<MemInfo> = Mutex::new(MemInfo {
 	mem_total: 0,
 	mem_free: 0,
 });
+
+/// The number of pages allocated since boot.
+pub static PGALLOC: AtomicUsize = AtomicUsize::new(0);
+/// The number of pages freed since boot.
+pub static PGFREE: AtomicUsize = AtomicUsize::new(0);
+/// The number of page faults since boot.
+pub static PGFAULT: AtomicUsize = AtomicUsize::new(0);
@@ -0,0 +1,211 @@
+//! `vmalloc` allows to allocate large chunks of kernel memory that do not need to be physically
+//! contiguous.
+//!
+//! Physical frames are allocated one by one, then mapped contiguously into a dedicated region
+//! of the kernelspace, located after the direct mapping of the physical memory.
+//!
+//! Since frames do not need to be contiguous, such allocations succeed even when the physical
+//! memory is too fragmented for the buddy allocator to return large blocks. However, they are
+//! slower and cause more TLB pressure than allocations in the direct mapping.
+//!
+//! A guard page is left unmapped after each allocation, so that overflows cause a page fault
+//! instead of corrupting the next allocation.
+
+use super::buddy;
+use super::vmem;
+use crate::errno::AllocError;
+use crate::errno::AllocResult;
+use crate::memory;
+use crate::util::container::map::Map;
+use crate::util::lock::Mutex;
+use core::ffi::c_void;
+use core::num::NonZeroUsize;
+use core::ptr::NonNull;
+use core::slice;
+
+/// The flags used to map allocations in virtual memory.
+const FLAGS: u32 = vmem::x86::FLAG_WRITE | vmem::x86::FLAG_GLOBAL;
+
+/// The list of allocated areas. The key is the address of the beginning of the area and the value
+/// is its size in pages, without the guard page.
+static AREAS: Mutex<Map<usize, usize>> = Mutex::new(Map::new());
+
+/// Finds a free range of virtual memory of `pages` pages, plus a guard page, in the vmalloc
+/// region.
+///
+/// `areas` is the list of allocated areas.
+///
+/// If no range is large enough, the function returns `None`.
+fn find_free(areas: &Map<usize, usize>, pages: usize) -> Option<usize> {
+	let needed = pages.checked_add(1)?.checked_mul(memory::PAGE_SIZE)?;
+	let end = memory::get_vmalloc_begin() as usize + memory::VMALLOC_SIZE;
+
+	let mut cursor = memory::get_vmalloc_begin() as usize;
+	for (begin, size) in areas.iter() {
+		if *begin - cursor >= needed {
+			return Some(cursor);
+		}
+		cursor = *begin + (*size + 1) * memory::PAGE_SIZE;
+	}
+	(end - cursor >= needed).then_some(cursor)
+}
+
+/// Unmaps the `pages` first pages of the area beginning at `addr` and frees the underlying
+/// physical frames.
+///
+/// # Safety
+///
+/// The pages must not be in use anymore.
+unsafe fn release(vmem: &dyn vmem::VMem, addr: usize, pages: usize) {
+	for i in 0..pages {
+		let virt = (addr + i * memory::PAGE_SIZE) as *const c_void;
+		if let Some(phys) = vmem.translate(virt) {
+			buddy::free(phys, 0);
+		}
+		// Unmapping cannot fail since the page table exists and is not freed
+		let _ = vmem.unmap(virt);
+	}
+}
+
+/// Allocates `size` bytes of kernel memory, backed by physical frames that are not necessarily
+/// contiguous.
+///
+/// The size is rounded up to a multiple of the page size.
+///
+/// If not enough physical or virtual memory is available, the function returns an error.
+///
+/// The allocated memory is **not** initialized, meaning it may contain garbage, or even
+/// sensitive informations.
+pub fn vmalloc(size: NonZeroUsize) -> AllocResult<NonNull<c_void>> {
+	let pages = size.get().div_ceil(memory::PAGE_SIZE);
+
+	let mut areas = AREAS.lock();
+	let addr = find_free(&areas, pages).ok_or(AllocError)?;
+
+	let vmem_guard = crate::get_vmem().lock();
+	let vmem = vmem_guard.as_ref().unwrap();
+	for i in 0..pages {
+		let res = buddy::alloc(0, buddy::FLAG_ZONE_TYPE_USER).and_then(|phys| {
+			let virt = (addr + i * memory::PAGE_SIZE) as *const c_void;
+			vmem.map(phys.as_ptr(), virt, FLAGS).map_err(|e| {
+				buddy::free(phys.as_ptr(), 0);
+				e
+			})
+		});
+		if let Err(e) = res {
+			unsafe {
+				// Safe because the pages have not been returned to the caller
+				release(vmem.as_ref(), addr, i);
+			}
+			return Err(e);
+		}
+	}
+
+	if let Err(e) = areas.insert(addr, pages) {
+		unsafe {
+			// Safe because the pages have not been returned to the caller
+			release(vmem.as_ref(), addr, pages);
+		}
+		return Err(e);
+	}
+	Ok(NonNull::new(addr as *mut c_void).unwrap())
+}
+
+/// Frees the allocation at `ptr`, previously returned by [`vmalloc`].
+///
+/// If `ptr` is not the beginning of an allocation, the function panics.
+///
+/// # Safety
+///
+/// The allocation must not be used after being freed.
+pub unsafe fn vfree(ptr: NonNull<c_void>) {
+	let mut areas = AREAS.lock();
+	let Some(pages) = areas.remove(&(ptr.as_ptr() as usize)) else {
+		panic!("vfree: invalid pointer {:p}", ptr.as_ptr());
+	};
+
+	let vmem_guard = crate::get_vmem().lock();
+	let vmem = vmem_guard.as_ref().unwrap();
+	release(vmem.as_ref(), ptr.as_ptr() as usize, pages);
+}
+
+/// Structure representing an allocation made with [`vmalloc`], which is freed when dropped.
+#[derive(Debug)]
+pub struct VAlloc {
+	/// Slice representing the allocation.
+	slice: NonNull<[u8]>,
+}
+
+impl VAlloc {
+	/// Allocates `size` bytes of zero-ed kernel memory.
+	///
+	/// If not enough physical or virtual memory is available, the function returns an error.
+	pub fn new(size: NonZeroUsize) -> AllocResult<Self> {
+		let ptr = vmalloc(size)?;
+		let slice = unsafe {
+			// Safe because the allocation is at least `size` bytes long
+			let slice = slice::from_raw_parts_mut(ptr.as_ptr() as *mut u8, size.get());
+			slice.fill(0);
+			NonNull::new(slice as *mut [u8]).unwrap()
+		};
+
+		Ok(Self {
+			slice,
+		})
+	}
+
+	/// Returns an immutable reference to the underlying slice.
+	pub fn as_slice(&self) -> &[u8] {
+		unsafe { self.slice.as_ref() }
+	}
+
+	/// Returns a mutable reference to the underlying slice.
+	pub fn as_slice_mut(&mut self) -> &mut [u8] {
+		unsafe { self.slice.as_mut() }
+	}
+
+	/// Returns the allocation as pointer.
+	pub fn as_ptr(&self) -> *const u8 {
+		self.as_slice().as_ptr()
+	}
+}
+
+impl Drop for VAlloc {
+	fn drop(&mut self) {
+		unsafe {
+			vfree(self.slice.cast());
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn vmalloc0() {
+		let alloc_pages = buddy::allocated_pages_count();
+
+		let size = NonZeroUsize::new(memory::PAGE_SIZE * 16 + 1).unwrap();
+		let mut alloc = VAlloc::new(size).unwrap();
+		assert!(alloc.as_slice().iter().all(|b| *b == 0));
+		alloc.as_slice_mut().fill(!0);
+		drop(alloc);
+
+		assert_eq!(buddy::allocated_pages_count(), alloc_pages);
+	}
+
+	#[test_case]
+	fn vmalloc_reuse() {
+		let size = NonZeroUsize::new(memory::PAGE_SIZE).unwrap();
+		let a = vmalloc(size).unwrap();
+		unsafe {
+			vfree(a);
+		}
+		let b = vmalloc(size).unwrap();
+		assert_eq!(a, b);
+		unsafe {
+			vfree(b);
+		}
+	}
+}
@@ -3,9 +3,13 @@
 
 // TODO Make this file fully cross-platform
 
+pub mod tlb;
 #[cfg(target_arch = "x86")]
 pub mod x86;
 
+#[cfg(target_arch = "x86")]
+use self::x86 as arch;
+
 use crate::cpu;
 use crate::elf;
 use crate::errno::AllocError;
@@ -18,6 +22,9 @@ use crate::util::math;
 use crate::util::TryClone;
 use core::ffi::c_void;
 
+/// The size of a huge page in bytes.
+pub const HUGE_PAGE_SIZE: usize = arch::HUGE_PAGE_SIZE;
+
 /// Trait representing virtual memory context handler.
 ///
 /// This trait is the interface to manipulate virtual memory on any architecture.
@@ -58,6 +65,20 @@ pub trait VMem: TryClone<Error = AllocError> {
 		pages: usize,
 		flags: u32,
 	) -> AllocResult<()>;
+	/// Maps the given physical address `physaddr` to the given virtual address `virtaddr` with
+	/// the given flags, using a single huge page of [`HUGE_PAGE_SIZE`] bytes.
+	///
+	/// Both addresses must be aligned on the size of a huge page.
+	///
+	/// Huge pages reduce the pressure on the TLB for large mappings.
+	///
+	/// This function automaticaly invalidates the page(s) in the cache.
+	fn map_huge(
+		&self,
+		physaddr: *const c_void,
+		virtaddr: *const c_void,
+		flags: u32,
+	) -> AllocResult<()>;
 
 	/// Unmaps the page at virtual address `virtaddr`.
 	///
@@ -76,20 +97,29 @@ pub trait VMem: TryClone<Error = AllocError> {
 	/// Tells whether the handler is bound or not.
 	fn is_bound(&self) -> bool;
 
-	/// Invalides the page at address `addr`.
+	/// Invalides the page at address `addr`, on every CPU cores running the context.
 	fn invalidate_page(&self, addr: *const c_void);
-	/// Flushes the modifications of the context if bound.
+	/// Flushes the modifications of the context if bound, and on every other CPU cores running
+	/// the context.
 	///
 	/// This function should be called after applying modifications to the context for them to be
 	/// taken into account.
 	fn flush(&self);
 
+	/// Clones the context into a new boxed handler.
+	///
+	/// This allows to clone a context without knowing its concrete type.
+	fn try_clone_box(&self) -> AllocResult<Box<dyn VMem>>;
+
 	/// Protects the kernel's read-only sections from writing.
+	///
+	/// The `.user_text` section, which contains code executed in userspace, is made accessible
+	/// from userspace.
 	fn protect_kernel(&self) -> AllocResult<()> {
 		let boot_info = multiboot::get_boot_info();
 
 		let mut res = Ok(());
-		let f = |section: &elf::ELF32SectionHeader, _name: &[u8]| {
+		let f = |section: &elf::ELF32SectionHeader, name: &[u8]| {
 			if section.sh_flags & elf::SHF_WRITE != 0
 				|| section.sh_addralign as usize != memory::PAGE_SIZE
 			{
@@ -99,7 +129,12 @@ pub trait VMem: TryClone<Error = AllocError> {
 			let phys_addr = memory::kern_to_phys(section.sh_addr as _);
 			let virt_addr = memory::kern_to_virt(section.sh_addr as _);
 			let pages = math::ceil_div(section.sh_size, memory::PAGE_SIZE as _) as usize;
-			if let Err(e) = self.map_range(phys_addr, virt_addr, pages, x86::FLAG_USER) {
+			let flags = if name == b".user_text" {
+				arch::FLAG_USER
+			} else {
+				0
+			};
+			if let Err(e) = self.map_range(phys_addr, virt_addr, pages, flags) {
 				res = Err(e);
 				return false;
 			}
@@ -122,13 +157,12 @@ pub trait VMem: TryClone<Error = AllocError> {
 
 /// Creates a new virtual memory context handler for the current architecture.
 pub fn new() -> AllocResult<Box<dyn VMem>> {
-	Ok(Box::new(x86::X86VMem::new()?)? as Box<dyn VMem>)
+	Ok(Box::new(arch::ArchVMem::new()?)? as Box<dyn VMem>)
 }
 
 /// Clones the virtual memory context handler `vmem`.
 pub fn try_clone(vmem: &dyn VMem) -> AllocResult<Box<dyn VMem>> {
-	let vmem = unsafe { &*(vmem as *const dyn VMem as *const x86::X86VMem) };
-	Ok(Box::new(vmem.try_clone()?)? as Box<dyn VMem>)
+	vmem.try_clone_box()
 }
 
 /// Tells whether the read-only pages protection is enabled.
@@ -207,7 +241,7 @@ pub unsafe fn switch<F: FnOnce() -> T, T>(vmem: &dyn VMem, f: F) -> T {
 			let result = f();
 
 			// Restoring the previous vmem
-			x86::paging_enable(cr3 as _);
+			arch::paging_enable(cr3 as _);
 
 			result
 		}
@@ -272,6 +306,22 @@ mod test {
 		}
 	}
 
+	#[test_case]
+	fn vmem_map_huge0() {
+		let vmem = new().unwrap();
+		vmem.map_huge(HUGE_PAGE_SIZE as _, HUGE_PAGE_SIZE as _, 0)
+			.unwrap();
+
+		for i in (0..(HUGE_PAGE_SIZE * 3)).step_by(memory::PAGE_SIZE) {
+			let result = vmem.translate(i as _);
+			if (HUGE_PAGE_SIZE..(HUGE_PAGE_SIZE * 2)).contains(&i) {
+				assert_eq!(result, Some(i as _));
+			} else {
+				assert_eq!(result, None);
+			}
+		}
+	}
+
 	// TODO More tests on map
 	// TODO Test on map_range
 
@@ -0,0 +1,150 @@
+//! Each CPU core caches translations of virtual addresses in its own TLB (Translation Lookaside
+//! Buffer). When a virtual memory context is modified, stale entries must be invalidated on every
+//! core that might be using the context, not only on the current one.
+//!
+//! A TLB shootdown is performed by sending an IPI to the other cores, which invalidate the
+//! requested pages if they run the modified context, then acknowledge the request. The core
+//! initiating the shootdown waits for every acknowledgement before returning, so that the
+//! modified pages can be safely reused afterwards.
+//!
+//! Only one shootdown can be in progress at a time. Since the cores usually wait for locks with
+//! interrupts disabled, a core spinning on a lock keeps handling pending requests to avoid
+//! deadlocks.
+
+use super::arch;
+use crate::cpu;
+use crate::cpu::apic;
+use crate::cpu::smp;
+use crate::errno::AllocResult;
+use crate::event;
+use crate::event::CallbackHook;
+use crate::event::CallbackResult;
+use crate::idt;
+use crate::memory;
+use crate::process::regs::Regs;
+use crate::util::lock::Mutex;
+use core::ffi::c_void;
+use core::hint;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
+
+/// Above this number of pages, the whole TLB is flushed instead of invalidating each page.
+const FULL_FLUSH_THRESHOLD: usize = 32;
+/// The number of pages meaning the whole context has to be flushed.
+const ALL_PAGES: usize = usize::MAX;
+
+/// Tells whether a shootdown is in progress.
+static LOCK: AtomicBool = AtomicBool::new(false);
+/// The set of cores that have not acknowledged the current request yet.
+static PENDING: AtomicU32 = AtomicU32::new(0);
+/// The physical address of the page directory of the context to invalidate.
+static REQ_DIR: AtomicUsize = AtomicUsize::new(0);
+/// The address of the first page to invalidate.
+static REQ_ADDR: AtomicUsize = AtomicUsize::new(0);
+/// The number of pages to invalidate.
+static REQ_PAGES: AtomicUsize = AtomicUsize::new(0);
+
+/// The hook of the shootdown IPI's handler.
+static HOOK: Mutex<Option<CallbackHook>> = Mutex::new(None);
+
+/// Invalidates `pages` pages starting at `addr` in the TLB of the current core.
+///
+/// If `pages` is too large, the whole TLB is flushed instead.
+fn invalidate_local(addr: *const c_void, pages: usize) {
+	if pages > FULL_FLUSH_THRESHOLD {
+		unsafe {
+			arch::tlb_reload();
+		}
+		return;
+	}
+	for i in 0..pages {
+		unsafe {
+			arch::invlpg(addr.add(i * memory::PAGE_SIZE));
+		}
+	}
+}
+
+/// Handles the current request if it concerns the current core, then acknowledges it.
+///
+/// Interrupts must be disabled when calling this function.
+fn handle() {
+	let bit = 1 << cpu::get_current_id();
+	if PENDING.load(Ordering::Acquire) & bit == 0 {
+		return;
+	}
+	let dir = REQ_DIR.load(Ordering::Relaxed);
+	let addr = REQ_ADDR.load(Ordering::Relaxed) as *const c_void;
+	let pages = REQ_PAGES.load(Ordering::Relaxed);
+	// Kernel space is shared by every contexts
+	let kernel = addr >= memory::PROCESS_END as _ && pages != ALL_PAGES;
+	if kernel || unsafe { cpu::cr3_get() } as usize == dir {
+		invalidate_local(addr, pages);
+	}
+	PENDING.fetch_and(!bit, Ordering::Release);
+}
+
+/// Handles the pending shootdown request for the current core, if any.
+///
+/// This function is meant to be called while waiting in a loop with interrupts disabled.
+pub fn handle_pending() {
+	if PENDING.load(Ordering::Relaxed) == 0 || !apic::is_mapped() {
+		return;
+	}
+	idt::wrap_disable_interrupts(handle);
+}
+
+/// Invalidates `pages` pages starting at `addr` in the context whose page directory is located
+/// at the physical address `dir`, on every other core.
+///
+/// If `pages` is [`usize::MAX`], the whole context is flushed.
+///
+/// The function returns once every other core has acknowledged the request.
+pub fn shootdown(dir: *const c_void, addr: *const c_void, pages: usize) {
+	if !apic::is_mapped() {
+		return;
+	}
+	// Interrupts are disabled to avoid being moved to another core during the shootdown
+	idt::wrap_disable_interrupts(|| {
+		let self_bit = 1 << cpu::get_current_id();
+		let others = smp::get_online_cpus() & !self_bit;
+		if others == 0 {
+			return;
+		}
+
+		while LOCK
+			.compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+			.is_err()
+		{
+			handle();
+			hint::spin_loop();
+		}
+
+		REQ_DIR.store(dir as _, Ordering::Relaxed);
+		REQ_ADDR.store(addr as _, Ordering::Relaxed);
+		REQ_PAGES.store(pages, Ordering::Relaxed);
+		PENDING.store(others, Ordering::Release);
+		apic::broadcast_ipi(idt::TLB_SHOOTDOWN_VECTOR);
+		while PENDING.load(Ordering::Acquire) != 0 {
+			hint::spin_loop();
+		}
+
+		LOCK.store(false, Ordering::Release);
+	});
+}
+
+/// Registers the handler of the shootdown IPI.
+///
+/// This function must be called before starting application processors.
+pub fn init() -> AllocResult<()> {
+	let hook = event::register_callback(
+		idt::TLB_SHOOTDOWN_VECTOR,
+		|_: u32, _: u32, _: &Regs, _: u32| {
+			handle();
+			CallbackResult::Continue
+		},
+	)?;
+	*HOOK.lock() = hook;
+	Ok(())
+}
@@ -22,16 +22,24 @@
 //!
 //! The Page Size Extension (PSE) allows to map 4MB large blocks without using a
 //! page table.
+//!
+//! Kernel space page tables are shared between every contexts. However, since large blocks are
+//! stored directly in the page directory, kernel space entries of the page directory are kept in
+//! a reference copy and every modification to them is propagated to every contexts.
 
 use crate::cpu;
 use crate::errno::AllocResult;
 use crate::memory;
 use crate::memory::buddy;
+use crate::memory::vmem::tlb;
 use crate::memory::vmem::VMem;
+use crate::util::boxed::Box;
+use crate::util::container::vec::Vec;
 use crate::util::lock::Mutex;
 use crate::util::TryClone;
 use core::ffi::c_void;
 use core::ptr;
+use core::ptr::null;
 use core::slice;
 
 /// x86 paging flag. If set, prevents the CPU from updating the associated
@@ -61,6 +69,9 @@ pub const FLAGS_MASK: u32 = 0xfff;
 /// since it must be page-aligned.
 pub const ADDR_MASK: u32 = !FLAGS_MASK;
 
+/// The size of a huge page (PSE) in bytes.
+pub const HUGE_PAGE_SIZE: usize = 1024 * memory::PAGE_SIZE;
+
 /// x86 page fault flag. If set, the page was present.
 pub const PAGE_FAULT_PRESENT: u32 = 0b00001;
 /// x86 page fault flag. If set, the error was caused by a write operation, else
@@ -83,7 +94,7 @@ extern "C" {
 	pub fn paging_disable();
 
 	/// Executes the `invlpg` instruction for the address `addr`.
-	fn invlpg(addr: *const c_void);
+	pub(super) fn invlpg(addr: *const c_void);
 	/// Reloads the TLB (Translation Lookaside Buffer).
 	pub fn tlb_reload();
 }
@@ -120,6 +131,46 @@ unsafe fn get_kernel_tables() -> AllocResult<&'static [*mut u32; 256]> {
 	Ok(&KERNEL_TABLES)
 }
 
+/// The kernel space part of page directories.
+struct KernelSpace {
+	/// The reference copy of kernel space page directory entries.
+	///
+	/// An entry that is not present has not been initialized yet.
+	entries: [u32; 256],
+	/// The list of every page directories, to which modifications are propagated.
+	dirs: Vec<*mut u32>,
+}
+
+impl KernelSpace {
+	/// Returns the value of the `n`th kernel space page directory entry.
+	///
+	/// If the entry is not initialized, the function initializes it with the `n`th kernel space
+	/// paging table.
+	fn get_entry(&mut self, n: usize) -> AllocResult<u32> {
+		if self.entries[n] & FLAG_PRESENT == 0 {
+			// Safe because only one thread is running when the first vmem is created
+			let ptr = unsafe { get_kernel_table(n)? };
+			self.entries[n] = ptr as u32 | FLAG_PRESENT | FLAG_WRITE | FLAG_USER | FLAG_GLOBAL;
+		}
+
+		Ok(self.entries[n])
+	}
+
+	/// Sets the value of the `n`th kernel space page directory entry in every page directories.
+	fn set_entry(&mut self, n: usize, value: u32) {
+		self.entries[n] = value;
+		for dir in self.dirs.iter() {
+			obj_set(*dir, 768 + n, value);
+		}
+	}
+}
+
+/// The kernel space part of page directories.
+static KERNEL_SPACE: Mutex<KernelSpace> = Mutex::new(KernelSpace {
+	entries: [0; 256],
+	dirs: Vec::new(),
+});
+
 /// Returns the physical address to the `n`th kernel space paging table.
 ///
 /// # Safety
@@ -190,6 +241,9 @@ fn free_obj(obj: *mut u32) {
 	buddy::free_kernel(obj as _, 0)
 }
 
+/// The virtual memory context handler of the architecture, as created by [`super::new`].
+pub type ArchVMem = X86VMem;
+
 /// The structure representing virtual memory context handler for the x86
 /// architecture.
 #[derive(Debug)]
@@ -218,7 +272,7 @@ mod table {
 			}
 		};
 
-		obj_set(
+		set(
 			vmem,
 			index,
 			(memory::kern_to_phys(v as _) as u32) | (flags | FLAG_PRESENT),
@@ -226,24 +280,46 @@ mod table {
 		Ok(())
 	}
 
+	/// Sets the entry at index `index` of the page directory to `value`.
+	///
+	/// If the entry is in kernel space, the modification is propagated to every page
+	/// directories.
+	fn set(vmem: *mut u32, index: usize, value: u32) {
+		if index < 768 {
+			obj_set(vmem, index, value);
+		} else {
+			KERNEL_SPACE.lock().set_entry(index - 768, value);
+		}
+	}
+
 	/// Expands a large block into a page table.
 	///
 	/// This function allocates a new page table and fills it so that the memory mapping keeps the
 	/// same behavior.
 	pub fn expand(vmem: *mut u32, index: usize) -> AllocResult<()> {
-		let mut dir_entry_value = obj_get(vmem, index);
+		let dir_entry_value = obj_get(vmem, index);
 		debug_assert!(dir_entry_value & FLAG_PRESENT != 0);
 		debug_assert!(dir_entry_value & FLAG_PAGE_SIZE != 0);
 
 		let base_addr = dir_entry_value & ADDR_MASK;
 		let flags = dir_entry_value & FLAGS_MASK & !FLAG_PAGE_SIZE;
-		table::create(vmem, index, flags)?;
-		dir_entry_value = obj_get(vmem, index);
-		let table_addr = (dir_entry_value & ADDR_MASK) as *mut u32;
+		let table = {
+			if index < 768 {
+				alloc_obj()?
+			} else {
+				unsafe { get_kernel_table(index - 768)? }
+			}
+		};
+		// The table is filled before being inserted since the block might be in use
 		for i in 0..1024 {
 			let addr = base_addr + (i * memory::PAGE_SIZE) as u32;
-			obj_set(table_addr, i, addr | flags);
+			obj_set(table, i, addr | flags);
 		}
+		set(
+			vmem,
+			index,
+			(memory::kern_to_phys(table as _) as u32) | flags,
+		);
 
 		Ok(())
 	}
@@ -337,16 +413,20 @@ impl X86VMem {
 		let vmem = Self {
 			page_dir: alloc_obj()?,
 		};
+		vmem.init_kernel_space()?;
 
-		let flags = FLAG_PRESENT | FLAG_WRITE | FLAG_USER | FLAG_GLOBAL;
-		for i in 0..256 {
-			// Safe because only one thread is running when the first vmem is created
-			let ptr = unsafe { get_kernel_table(i)? };
+		Ok(vmem)
+	}
 
-			obj_set(vmem.page_dir, 768 + i, ptr as u32 | flags);
+	/// Maps the kernel space into the page directory and registers it so that further
+	/// modifications of the kernel space are propagated to it.
+	fn init_kernel_space(&self) -> AllocResult<()> {
+		let mut kernel_space = KERNEL_SPACE.lock();
+		for i in 0..256 {
+			let value = kernel_space.get_entry(i)?;
+			obj_set(self.page_dir, 768 + i, value);
 		}
-
-		Ok(vmem)
+		kernel_space.dirs.push(self.page_dir)
 	}
 
 	/// Returns the index of the element corresponding to the given virtual
@@ -423,10 +503,14 @@ impl X86VMem {
 
 		let dir_entry_index = Self::get_addr_element_index(virtaddr, 1);
 		let dir_entry_value = obj_get(self.page_dir, dir_entry_index);
-		if dir_entry_index < 768
-			&& dir_entry_value & FLAG_PRESENT != 0
-			&& dir_entry_value & FLAG_PAGE_SIZE == 0
-		{
+		if dir_entry_index >= 768 {
+			// Kernel space tables are kept to be reused when the block is expanded
+			KERNEL_SPACE
+				.lock()
+				.set_entry(dir_entry_index - 768, (physaddr as u32) | flags);
+			return;
+		}
+		if dir_entry_value & FLAG_PRESENT != 0 && dir_entry_value & FLAG_PAGE_SIZE == 0 {
 			table::delete(self.page_dir, dir_entry_index);
 		}
 
@@ -443,27 +527,17 @@ impl X86VMem {
 
 		obj_set(self.page_dir, dir_entry_index, 0);
 	}
-}
 
-impl VMem for X86VMem {
-	fn translate(&self, ptr: *const c_void) -> Option<*const c_void> {
-		if let Some(e) = self.resolve(ptr) {
-			let entry_value = unsafe { *e };
-			let remain_mask = if entry_value & FLAG_PAGE_SIZE == 0 {
-				memory::PAGE_SIZE - 1
-			} else {
-				1024 * memory::PAGE_SIZE - 1
-			};
-
-			let mut virtptr = (entry_value & ADDR_MASK) as usize;
-			virtptr |= ptr as usize & remain_mask;
-			Some(virtptr as _)
-		} else {
-			None
-		}
+	/// Invalidates `pages` pages starting at `virtaddr` on other CPU cores running the context.
+	///
+	/// If `pages` is [`usize::MAX`], the whole context is flushed.
+	fn shootdown(&self, virtaddr: *const c_void, pages: usize) {
+		tlb::shootdown(memory::kern_to_phys(self.page_dir as _), virtaddr, pages);
 	}
 
-	fn map(
+	/// Maps the page at `physaddr` to `virtaddr`, invalidating the page on the current core
+	/// only.
+	fn map_impl(
 		&self,
 		physaddr: *const c_void,
 		virtaddr: *const c_void,
@@ -503,11 +577,80 @@ impl VMem for X86VMem {
 		obj_set(table, table_entry_index, (physaddr as u32) | flags);
 
 		// Invalidating the page
-		self.invalidate_page(virtaddr);
+		unsafe {
+			invlpg(virtaddr);
+		}
 
 		Ok(())
 	}
 
+	/// Unmaps the page at `virtaddr`, invalidating the page on the current core only.
+	fn unmap_impl(&self, virtaddr: *const c_void) -> AllocResult<()> {
+		#[cfg(config_debug_debug)]
+		self.check_unmap(virtaddr, false);
+
+		debug_assert!(virtaddr.is_aligned_to(memory::PAGE_SIZE));
+
+		// Locking the global mutex to avoid data races while modifying kernel space
+		// tables
+		let _ = GLOBAL_MUTEX.lock();
+
+		let dir_entry_index = Self::get_addr_element_index(virtaddr, 1);
+		let mut dir_entry_value = obj_get(self.page_dir, dir_entry_index);
+		if dir_entry_value & FLAG_PRESENT == 0 {
+			return Ok(());
+		} else if dir_entry_value & FLAG_PAGE_SIZE != 0 {
+			table::expand(self.page_dir, dir_entry_index)?;
+			dir_entry_value = obj_get(self.page_dir, dir_entry_index);
+		}
+
+		let table = (dir_entry_value & ADDR_MASK) as *mut u32;
+		let table_entry_index = Self::get_addr_element_index(virtaddr, 0);
+		obj_set(table, table_entry_index, 0);
+
+		// Invalidating the page
+		unsafe {
+			invlpg(virtaddr);
+		}
+
+		// Removing the table if it is empty and if not a kernel space table
+		if table::is_empty(self.page_dir, dir_entry_index) && dir_entry_index < 768 {
+			table::delete(self.page_dir, dir_entry_index);
+		}
+
+		Ok(())
+	}
+}
+
+impl VMem for X86VMem {
+	fn translate(&self, ptr: *const c_void) -> Option<*const c_void> {
+		if let Some(e) = self.resolve(ptr) {
+			let entry_value = unsafe { *e };
+			let remain_mask = if entry_value & FLAG_PAGE_SIZE == 0 {
+				memory::PAGE_SIZE - 1
+			} else {
+				1024 * memory::PAGE_SIZE - 1
+			};
+
+			let mut virtptr = (entry_value & ADDR_MASK) as usize;
+			virtptr |= ptr as usize & remain_mask;
+			Some(virtptr as _)
+		} else {
+			None
+		}
+	}
+
+	fn map(
+		&self,
+		physaddr: *const c_void,
+		virtaddr: *const c_void,
+		flags: u32,
+	) -> AllocResult<()> {
+		self.map_impl(physaddr, virtaddr, flags)?;
+		self.shootdown(virtaddr, 1);
+		Ok(())
+	}
+
 	fn map_range(
 		&self,
 		physaddr: *const c_void,
@@ -523,6 +666,7 @@ impl VMem for X86VMem {
 		);
 		debug_assert_eq!(flags & ADDR_MASK, 0);
 
+		let mut result = Ok(());
 		let mut i = 0;
 		while i < pages {
 			let off = i * memory::PAGE_SIZE;
@@ -537,47 +681,51 @@ impl VMem for X86VMem {
 				i += 1024;
 
 				// Invalidating the pages
-				self.invalidate_page(next_virtaddr); // TODO Check if invalidating the whole
-				                     // table
+				unsafe {
+					invlpg(next_virtaddr); // TODO Check if invalidating the whole table
+				}
 			} else {
-				self.map(next_physaddr, next_virtaddr, flags)?;
+				result = self.map_impl(next_physaddr, next_virtaddr, flags);
+				if result.is_err() {
+					break;
+				}
 				i += 1;
 			}
 		}
 
-		Ok(())
+		// Invalidating the pages mapped so far on other cores
+		self.shootdown(virtaddr, i.min(pages));
+		result
 	}
 
-	fn unmap(&self, virtaddr: *const c_void) -> AllocResult<()> {
+	fn map_huge(
+		&self,
+		physaddr: *const c_void,
+		virtaddr: *const c_void,
+		flags: u32,
+	) -> AllocResult<()> {
 		#[cfg(config_debug_debug)]
-		self.check_unmap(virtaddr, false);
+		self.check_map(virtaddr, physaddr, true);
 
-		debug_assert!(virtaddr.is_aligned_to(memory::PAGE_SIZE));
-
-		// Locking the global mutex to avoid data races while modifying kernel space
-		// tables
-		let _ = GLOBAL_MUTEX.lock();
+		debug_assert!(physaddr.is_aligned_to(HUGE_PAGE_SIZE));
+		debug_assert!(virtaddr.is_aligned_to(HUGE_PAGE_SIZE));
+		debug_assert_eq!(flags & ADDR_MASK, 0);
 
-		let dir_entry_index = Self::get_addr_element_index(virtaddr, 1);
-		let dir_entry_value = obj_get(self.page_dir, dir_entry_index);
-		if dir_entry_value & FLAG_PRESENT == 0 {
-			return Ok(());
-		} else if dir_entry_value & FLAG_PAGE_SIZE != 0 {
-			table::expand(self.page_dir, dir_entry_index)?;
+		self.map_pse(physaddr, virtaddr, flags);
+		// Invalidating the pages
+		for i in 0..1024 {
+			unsafe {
+				invlpg(((virtaddr as usize) + i * memory::PAGE_SIZE) as _);
+			}
 		}
+		self.shootdown(virtaddr, 1024);
 
-		let table = (dir_entry_value & ADDR_MASK) as *mut u32;
-		let table_entry_index = Self::get_addr_element_index(virtaddr, 0);
-		obj_set(table, table_entry_index, 0);
-
-		// Invalidating the page
-		self.invalidate_page(virtaddr);
-
-		// Removing the table if it is empty and if not a kernel space table
-		if table::is_empty(self.page_dir, dir_entry_index) && dir_entry_index < 768 {
-			table::delete(self.page_dir, dir_entry_index);
-		}
+		Ok(())
+	}
 
+	fn unmap(&self, virtaddr: *const c_void) -> AllocResult<()> {
+		self.unmap_impl(virtaddr)?;
+		self.shootdown(virtaddr, 1);
 		Ok(())
 	}
 
@@ -585,6 +733,7 @@ impl VMem for X86VMem {
 		debug_assert!(virtaddr.is_aligned_to(memory::PAGE_SIZE));
 		debug_assert!((virtaddr as usize) + (pages * memory::PAGE_SIZE) >= (virtaddr as usize));
 
+		let mut result = Ok(());
 		let mut i = 0;
 		while i < pages {
 			let off = i * memory::PAGE_SIZE;
@@ -601,15 +750,21 @@ impl VMem for X86VMem {
 				i += 1024;
 
 				// Invalidating the pages
-				self.invalidate_page(next_virtaddr); // TODO Check if invalidating the whole
-				                     // table
+				unsafe {
+					invlpg(next_virtaddr); // TODO Check if invalidating the whole table
+				}
 			} else {
-				self.unmap(next_virtaddr)?;
+				result = self.unmap_impl(next_virtaddr);
+				if result.is_err() {
+					break;
+				}
 				i += 1;
 			}
 		}
 
-		Ok(())
+		// Invalidating the pages unmapped so far on other cores
+		self.shootdown(virtaddr, i.min(pages));
+		result
 	}
 
 	fn bind(&self) {
@@ -625,21 +780,23 @@ impl VMem for X86VMem {
 	}
 
 	fn invalidate_page(&self, addr: *const c_void) {
-		// TODO Also invalidate on other CPU core (TLB shootdown)
-
 		unsafe {
 			invlpg(addr);
 		}
+		self.shootdown(addr, 1);
 	}
 
 	fn flush(&self) {
-		// TODO Also invalidate on other CPU core (TLB shootdown)
-
 		if self.is_bound() {
 			unsafe {
 				tlb_reload();
 			}
 		}
+		self.shootdown(null(), usize::MAX);
+	}
+
+	fn try_clone_box(&self) -> AllocResult<Box<dyn VMem>> {
+		Ok(Box::new(self.try_clone()?)? as _)
 	}
 }
 
@@ -648,8 +805,9 @@ impl TryClone for X86VMem {
 		let s = Self {
 			page_dir: alloc_obj()?,
 		};
+		s.init_kernel_space()?;
 
-		for i in 0..1024 {
+		for i in 0..768 {
 			let src_dir_entry_value = obj_get(self.page_dir, i);
 			if src_dir_entry_value & FLAG_PRESENT == 0 {
 				continue;
@@ -659,21 +817,11 @@ impl TryClone for X86VMem {
 				let src_table = (src_dir_entry_value & ADDR_MASK) as *const u32;
 				let src_table = memory::kern_to_virt(src_table as _) as _;
 
-				let dest_table = {
-					if i < 768 {
-						let dest_table = alloc_obj()?;
-
-						unsafe {
-							// Safe because pointers are valid
-							ptr::copy_nonoverlapping::<u32>(src_table, dest_table, 1024);
-						}
-						dest_table
-					} else {
-						// Safe because only one thread is running the first time this function is
-						// called
-						unsafe { get_kernel_table(i - 768)? }
-					}
-				};
+				let dest_table = alloc_obj()?;
+				unsafe {
+					// Safe because pointers are valid
+					ptr::copy_nonoverlapping::<u32>(src_table, dest_table, 1024);
+				}
 
 				obj_set(
 					s.page_dir,
@@ -699,6 +847,8 @@ impl Drop for X86VMem {
 			panic!("Dropping virtual memory context handler while in use!");
 		}
 
+		KERNEL_SPACE.lock().dirs.retain(|dir| *dir != self.page_dir);
+
 		for i in 0..768 {
 			let dir_entry_value = obj_get(self.page_dir, i);
 
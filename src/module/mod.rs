@@ -19,7 +19,7 @@ use crate::elf::ELF32Sym;
 use crate::errno;
 use crate::errno::Errno;
 use crate::memory;
-use crate::memory::malloc;
+use crate::memory::vmalloc::VAlloc;
 use crate::multiboot;
 use crate::util::container::hashmap::HashMap;
 use crate::util::container::string::String;
@@ -101,7 +101,7 @@ pub struct Module {
 	deps: Vec<Dependency>,
 
 	/// The module's memory.
-	mem: malloc::Alloc<u8>,
+	mem: VAlloc,
 	/// The size of the module's memory.
 	mem_size: usize,
 
@@ -195,13 +195,14 @@ impl Module {
 			e
 		})?;
 
-		// Allocate memory for the module
+		// Allocate memory for the module. Since the image can be large, it does not need to be
+		// physically contiguous
 		let mem_size =
 			NonZeroUsize::new(Self::get_load_size(&parser)).ok_or_else(|| errno!(EINVAL))?;
-		let mut mem = malloc::Alloc::<u8>::new_default(mem_size)?;
+		let mut mem = VAlloc::new(mem_size)?;
 
 		// The base virtual address at which the module is loaded
-		let load_base = unsafe { mem.as_ptr() as u32 };
+		let load_base = mem.as_ptr() as u32;
 
 		// Copying the module's image
 		parser
@@ -335,7 +336,7 @@ impl Module {
 
 			deps,
 
-			mem: mem as _,
+			mem,
 			mem_size: mem_size.get(),
 
 			fini,
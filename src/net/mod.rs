@@ -11,8 +11,7 @@ pub mod tcp;
 
 use crate::errno::Errno;
 use crate::file::perm::AccessProfile;
-use crate::file::perm::ROOT_GID;
-use crate::file::perm::ROOT_UID;
+use crate::file::perm::CAP_NET_RAW;
 use crate::net::sockaddr::SockAddrIn;
 use crate::net::sockaddr::SockAddrIn6;
 use crate::util::container::hashmap::HashMap;
@@ -269,7 +268,7 @@ impl AccessProfile {
 	/// Tells whether the agent has the permission to use the socket domain.
 	pub fn can_use_sock_domain(&self, domain: &SocketDomain) -> bool {
 		match domain {
-			SocketDomain::AfPacket => self.get_euid() == ROOT_UID || self.get_egid() == ROOT_GID,
+			SocketDomain::AfPacket => self.has_cap(CAP_NET_RAW),
 			_ => true,
 		}
 	}
@@ -325,7 +324,7 @@ impl AccessProfile {
 	/// Tells whether the agent has the permission to use the socket type.
 	pub fn can_use_sock_type(&self, sock_type: &SocketType) -> bool {
 		match sock_type {
-			SocketType::SockRaw => self.get_euid() == ROOT_UID || self.get_egid() == ROOT_GID,
+			SocketType::SockRaw => self.has_cap(CAP_NET_RAW),
 			_ => true,
 		}
 	}
@@ -1,11 +1,23 @@
 //! This module handles system power.
 
+use crate::acpi;
+use crate::cpu::smp;
 use crate::io;
+use crate::process::pid;
+use crate::process::signal::Signal;
+use crate::process::Process;
 use core::arch::asm;
+use core::sync::atomic;
+use core::sync::atomic::AtomicBool;
+
+/// Tells whether pressing Ctrl-Alt-Del reboots the system right away. If not, `SIGINT` is sent
+/// to the init process instead.
+pub static CTRL_ALT_DEL: AtomicBool = AtomicBool::new(true);
 
 /// Halts the kernel until reboot.
 pub fn halt() -> ! {
-	// TODO Send a signal to all other cores to stop them
+	cli!();
+	smp::halt_others();
 	loop {
 		unsafe {
 			asm!("cli", "hlt");
@@ -15,16 +27,33 @@ pub fn halt() -> ! {
 
 /// Powers the system down.
 pub fn shutdown() -> ! {
-	// TODO Use ACPI to power off the system
-	todo!()
+	cli!();
+	smp::halt_others();
+
+	// First try: ACPI
+	acpi::power::poweroff();
+
+	// Second try: emulators' shutdown ports
+	unsafe {
+		// QEMU
+		io::outw(0x604, 0x2000);
+		// Bochs and older versions of QEMU
+		io::outw(0xb004, 0x2000);
+		// VirtualBox
+		io::outw(0x4004, 0x3400);
+	}
+
+	// Giving up
+	halt();
 }
 
 /// Reboots the system.
 pub fn reboot() -> ! {
 	cli!();
+	smp::halt_others();
 
 	// First try: ACPI
-	// TODO Use ACPI reset to ensure everything reboots
+	acpi::power::reset();
 
 	// Second try: PS/2
 	loop {
@@ -53,3 +82,13 @@ pub fn reboot() -> ! {
 	// Giving up
 	halt();
 }
+
+/// Handles the Ctrl-Alt-Del key combination.
+pub fn ctrl_alt_del() {
+	if CTRL_ALT_DEL.load(atomic::Ordering::Relaxed) {
+		reboot();
+	}
+	if let Some(init_mutex) = Process::get_by_pid(pid::INIT_PID) {
+		init_mutex.lock().kill(&Signal::SIGINT, false);
+	}
+}
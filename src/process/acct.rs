@@ -0,0 +1,209 @@
+//! BSD process accounting writes a record to a file each time a process exits, allowing to
+//! audit the programs run on the system.
+//!
+//! Records use the version 3 of the format used by Linux.
+
+use super::Process;
+use super::COMM_LEN;
+use crate::errno::EResult;
+use crate::file::File;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::clock::CLOCK_REALTIME;
+use crate::time::unit::TimeUnit;
+use crate::time::unit::Timestamp;
+use crate::time::unit::TimestampScale;
+use crate::util::io::IO;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use core::mem::size_of;
+use core::slice;
+
+/// Record flag: the process has been forked but hasn't executed a program.
+pub const AFORK: u8 = 0x01;
+/// Record flag: the process used superuser privileges.
+pub const ASU: u8 = 0x02;
+/// Record flag: the process dumped its core.
+pub const ACORE: u8 = 0x08;
+/// Record flag: the process was killed by a signal.
+pub const AXSIG: u8 = 0x10;
+
+/// The version of the records' format.
+const ACCT_VERSION: u8 = 3;
+/// The number of clock ticks per second for times in records.
+const AHZ: u64 = 100;
+
+/// The number of bits of the mantissa of a `comp_t`.
+const MANT_SIZE: u32 = 13;
+/// The number of bits of the exponent of a `comp_t`.
+const EXP_SIZE: u32 = 3;
+
+/// An accounting record.
+#[repr(C)]
+#[derive(Default)]
+struct AcctV3 {
+	/// Flags.
+	ac_flag: u8,
+	/// Always set to [`ACCT_VERSION`].
+	ac_version: u8,
+	/// The controlling terminal.
+	ac_tty: u16,
+	/// The exit code of the process.
+	ac_exitcode: u32,
+	/// The real user ID.
+	ac_uid: u32,
+	/// The real group ID.
+	ac_gid: u32,
+	/// The process ID.
+	ac_pid: u32,
+	/// The parent process ID.
+	ac_ppid: u32,
+	/// The creation time, in seconds since the epoch.
+	ac_btime: u32,
+	/// The elapsed time in clock ticks, as a IEEE 754 single precision float.
+	ac_etime: u32,
+	/// The user time in clock ticks.
+	ac_utime: u16,
+	/// The system time in clock ticks.
+	ac_stime: u16,
+	/// The average memory usage in kilobytes.
+	ac_mem: u16,
+	/// The number of characters transferred.
+	ac_io: u16,
+	/// The number of blocks read or written.
+	ac_rw: u16,
+	/// The number of minor page faults.
+	ac_minflt: u16,
+	/// The number of major page faults.
+	ac_majflt: u16,
+	/// The number of swaps.
+	ac_swaps: u16,
+	/// The name of the command.
+	ac_comm: [u8; COMM_LEN],
+}
+
+/// The file records are written to. If `None`, accounting is disabled.
+static ACCT_FILE: Mutex<Option<Arc<Mutex<File>>>> = Mutex::new(None);
+
+/// Encodes the given value into a `comp_t`, a 16 bits float with a 3 bits base 8 exponent and a
+/// 13 bits mantissa.
+fn encode_comp(mut value: u64) -> u16 {
+	let max_mant = (1 << MANT_SIZE) - 1;
+	let mut exp = 0;
+	let mut round = false;
+	while value > max_mant {
+		round = value & (1 << (EXP_SIZE - 1)) != 0;
+		value >>= EXP_SIZE;
+		exp += 1;
+	}
+	if round {
+		value += 1;
+		if value > max_mant {
+			value >>= EXP_SIZE;
+			exp += 1;
+		}
+	}
+	if exp >= 1 << EXP_SIZE {
+		return u16::MAX;
+	}
+	((exp << MANT_SIZE) | value) as _
+}
+
+/// Encodes the given value into the bits of a IEEE 754 single precision float.
+fn encode_float(value: u64) -> u32 {
+	if value == 0 {
+		return 0;
+	}
+	let shift = value.leading_zeros();
+	let exp = 127 + 63 - shift;
+	// Remove the implicit leading bit
+	let mant = ((value << shift) >> 40) as u32 & 0x7fffff;
+	mant | (exp << 23)
+}
+
+/// Converts the given duration in nanoseconds to clock ticks.
+fn to_ticks(nanos: u64) -> u64 {
+	nanos / (1000000000 / AHZ)
+}
+
+/// Sets the file records are written to.
+///
+/// If `None`, accounting is disabled.
+pub fn set_file(file: Option<Arc<Mutex<File>>>) {
+	*ACCT_FILE.lock() = file;
+}
+
+/// Writes the accounting record for the given process, which is exiting.
+///
+/// If accounting is disabled, the function does nothing.
+pub fn write_record(proc: &Process) -> EResult<()> {
+	let Some(file) = ACCT_FILE.lock().clone() else {
+		return Ok(());
+	};
+
+	let now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond)?;
+	let elapsed = now.saturating_sub(proc.start_time);
+	let realtime = clock::current_time(CLOCK_REALTIME, TimestampScale::Nanosecond)?;
+	let btime: Timestamp = realtime.saturating_sub(elapsed) / 1000000000;
+
+	let termsig = proc.get_termsig();
+	let mut flags = proc.acct_flags;
+	if termsig != 0 {
+		flags |= AXSIG;
+	}
+	// TODO Set only if a privilege has actually been used
+	if proc.access_profile.get_euid() == 0 {
+		flags |= ASU;
+	}
+	let exit_status = proc.get_exit_status().unwrap_or(0) as u32;
+	let rusage = proc.get_rusage();
+	let mut comm = [0; COMM_LEN];
+	let name = proc.get_comm();
+	comm[..name.len()].copy_from_slice(name);
+	let record = AcctV3 {
+		ac_flag: flags,
+		ac_version: ACCT_VERSION,
+		ac_tty: 0, // TODO
+		ac_exitcode: (exit_status & 0xff) << 8 | (termsig as u32 & 0x7f),
+		ac_uid: proc.access_profile.get_uid() as _,
+		ac_gid: proc.access_profile.get_gid() as _,
+		ac_pid: proc.tgid as _,
+		ac_ppid: proc.get_parent_pid() as _,
+		ac_btime: btime as _,
+		ac_etime: encode_float(to_ticks(elapsed)),
+		ac_utime: encode_comp(to_ticks(rusage.ru_utime.to_nano())),
+		ac_stime: encode_comp(to_ticks(rusage.ru_stime.to_nano())),
+		ac_mem: encode_comp(rusage.ru_maxrss as _),
+		ac_minflt: encode_comp(rusage.ru_minflt as _),
+		ac_majflt: encode_comp(rusage.ru_majflt as _),
+		ac_comm: comm,
+		..Default::default()
+	};
+
+	let buf =
+		unsafe { slice::from_raw_parts(&record as *const _ as *const u8, size_of::<AcctV3>()) };
+	let mut file = file.lock();
+	let off = file.get_size();
+	file.write(off, buf)?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn acct_encode_comp() {
+		assert_eq!(encode_comp(0), 0);
+		assert_eq!(encode_comp(8191), 8191);
+		assert_eq!(encode_comp(8192), (1 << 13) | 1024);
+		assert_eq!(encode_comp(u64::MAX), u16::MAX);
+	}
+
+	#[test_case]
+	fn acct_encode_float() {
+		assert_eq!(encode_float(0), 0);
+		assert_eq!(encode_float(1), 0x3f800000);
+		assert_eq!(encode_float(100), 0x42c80000);
+	}
+}
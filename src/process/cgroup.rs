@@ -0,0 +1,463 @@
+//! Control groups (cgroups) organize processes in a hierarchy in order to distribute and limit
+//! system resources between them.
+//!
+//! Each process belongs to exactly one group, which it inherits from its parent. Groups are
+//! managed from userspace through the cgroup2 filesystem.
+//!
+//! The following controllers are available:
+//! - `cpu`: the scheduling weight of the processes of a group is scaled by the group's weight
+//! (`cpu.weight`) and the CPU time of the group may be limited over a period (`cpu.max`)
+//! - `memory`: the physical memory mapped by the processes of a group is accounted
+//! (`memory.current`) and may be limited (`memory.max`)
+//!
+//! Limits of a group also apply to its descendants.
+
+use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::memory;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use crate::util::TryClone;
+use core::cmp::max;
+use core::iter;
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
+
+/// The minimum value of `cpu.weight`.
+pub const CPU_WEIGHT_MIN: u32 = 1;
+/// The default value of `cpu.weight`.
+pub const CPU_WEIGHT_DEFAULT: u32 = 100;
+/// The maximum value of `cpu.weight`.
+pub const CPU_WEIGHT_MAX: u32 = 10000;
+
+/// The default length of a period for `cpu.max`, in microseconds.
+pub const CPU_PERIOD_DEFAULT: u64 = 100_000;
+/// The minimum length of a period or quota for `cpu.max`, in microseconds.
+pub const CPU_PERIOD_MIN: u64 = 1_000;
+/// The maximum length of a period for `cpu.max`, in microseconds.
+pub const CPU_PERIOD_MAX: u64 = 1_000_000;
+
+/// The controllers available on every group.
+pub const CONTROLLERS: &[&[u8]] = &[b"cpu", b"memory"];
+
+/// The bandwidth of a group for the `cpu` controller.
+///
+/// Durations and timestamps are in nanoseconds, unless specified otherwise.
+#[derive(Default)]
+struct CpuBandwidth {
+	/// The maximum CPU time of the group per period, in microseconds. If `None`, the group is not
+	/// limited.
+	quota: Option<u64>,
+	/// The length of a period, in microseconds.
+	period: u64,
+
+	/// The total CPU time used by the group.
+	usage: u64,
+	/// The timestamp of the beginning of the current period.
+	period_start: u64,
+	/// The CPU time used by the group during the current period.
+	period_usage: u64,
+	/// Tells whether the group has been throttled during the current period.
+	throttled: bool,
+
+	/// The number of elapsed periods.
+	nr_periods: u64,
+	/// The number of periods during which the group has been throttled.
+	nr_throttled: u64,
+}
+
+impl CpuBandwidth {
+	/// Starts a new period if the current one is over at the timestamp `now`.
+	fn refresh(&mut self, now: u64) {
+		if now < self.period_start + self.period * 1000 {
+			return;
+		}
+		if self.quota.is_some() {
+			self.nr_periods += 1;
+		}
+		self.period_start = now;
+		self.period_usage = 0;
+		self.throttled = false;
+	}
+
+	/// Tells whether the group has exhausted its quota for the current period.
+	fn is_exhausted(&self) -> bool {
+		self.quota
+			.map(|quota| self.period_usage >= quota * 1000)
+			.unwrap_or(false)
+	}
+}
+
+/// Counters of the events of the `memory` controller.
+#[derive(Default)]
+pub struct MemoryEvents {
+	/// The number of times the group was about to exceed its limit.
+	pub max: AtomicU32,
+	/// The number of times the group ran out of memory.
+	pub oom: AtomicU32,
+	/// The number of processes of the group killed by the OOM killer.
+	pub oom_kill: AtomicU32,
+}
+
+/// A control group.
+pub struct CGroup {
+	/// The ID of the group, which is unique as long as the group exists.
+	id: u32,
+	/// The name of the group.
+	name: String,
+	/// The parent group. If `None`, the group is the root.
+	parent: Option<Arc<CGroup>>,
+	/// The children groups, by name.
+	children: Mutex<HashMap<String, Arc<CGroup>>>,
+	/// The controllers enabled for the children of the group, as the indexes in
+	/// [`CONTROLLERS`].
+	subtree_control: AtomicU32,
+
+	/// The number of processes in the group and its descendants.
+	nr_procs: AtomicUsize,
+
+	/// The value of `cpu.weight`.
+	cpu_weight: AtomicU32,
+	/// The CPU bandwidth of the group.
+	cpu: Mutex<CpuBandwidth>,
+
+	/// The number of physical pages charged to the group and its descendants.
+	memory_current: AtomicUsize,
+	/// The maximum number of physical pages the group and its descendants can use.
+	memory_max: AtomicUsize,
+	/// The events of the `memory` controller.
+	pub memory_events: MemoryEvents,
+}
+
+/// The ID of the next group to be created.
+static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+/// The root group.
+static ROOT: Mutex<Option<Arc<CGroup>>> = Mutex::new(None);
+/// Every existing groups, by ID.
+static GROUPS: Mutex<HashMap<u32, Arc<CGroup>>> = Mutex::new(HashMap::new());
+
+/// Returns the root group.
+pub fn get_root() -> AllocResult<Arc<CGroup>> {
+	let mut root = ROOT.lock();
+	if let Some(root) = &*root {
+		return Ok(root.clone());
+	}
+	let group = CGroup::new(String::new(), None)?;
+	*root = Some(group.clone());
+	Ok(group)
+}
+
+/// Returns the group with ID `id`.
+///
+/// If the group does not exist, the function returns `None`.
+pub fn get(id: u32) -> Option<Arc<CGroup>> {
+	GROUPS.lock().get(&id).cloned()
+}
+
+impl CGroup {
+	/// Creates a new group with the name `name` and the parent `parent`, and registers it.
+	fn new(name: String, parent: Option<Arc<CGroup>>) -> AllocResult<Arc<Self>> {
+		let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+		let group = Arc::new(Self {
+			id,
+			name,
+			parent,
+			children: Mutex::new(HashMap::new()),
+			subtree_control: AtomicU32::new(0),
+
+			nr_procs: AtomicUsize::new(0),
+
+			cpu_weight: AtomicU32::new(CPU_WEIGHT_DEFAULT),
+			cpu: Mutex::new(CpuBandwidth {
+				period: CPU_PERIOD_DEFAULT,
+				..Default::default()
+			}),
+
+			memory_current: AtomicUsize::new(0),
+			memory_max: AtomicUsize::new(usize::MAX),
+			memory_events: MemoryEvents::default(),
+		})?;
+		GROUPS.lock().insert(id, group.clone())?;
+		Ok(group)
+	}
+
+	/// Returns the ID of the group.
+	pub fn get_id(&self) -> u32 {
+		self.id
+	}
+
+	/// Returns the name of the group.
+	pub fn get_name(&self) -> &[u8] {
+		self.name.as_bytes()
+	}
+
+	/// Returns the parent of the group. If `None`, the group is the root.
+	pub fn get_parent(&self) -> Option<&Arc<CGroup>> {
+		self.parent.as_ref()
+	}
+
+	/// Tells whether the group is the root.
+	pub fn is_root(&self) -> bool {
+		self.parent.is_none()
+	}
+
+	/// Returns an iterator over the group and its ancestors, from the group to the root.
+	pub fn iter_path(&self) -> impl Iterator<Item = &CGroup> {
+		iter::successors(Some(self), |group| group.parent.as_deref())
+	}
+
+	/// Tells whether the group is `other` or one of its descendants.
+	pub fn is_in(&self, other: &CGroup) -> bool {
+		self.iter_path().any(|group| group.id == other.id)
+	}
+
+	/// Returns the path of the group, relative to the root.
+	pub fn get_path(&self) -> AllocResult<String> {
+		let mut names = Vec::new();
+		for group in self.iter_path().filter(|group| !group.is_root()) {
+			names.push(group.get_name())?;
+		}
+		let mut path = String::new();
+		for name in names.iter().rev() {
+			path.push(b'/')?;
+			path.push_str(name)?;
+		}
+		if path.is_empty() {
+			path.push(b'/')?;
+		}
+		Ok(path)
+	}
+
+	/// Returns the child group with the name `name`.
+	pub fn get_child(&self, name: &[u8]) -> Option<Arc<CGroup>> {
+		self.children.lock().get(name).cloned()
+	}
+
+	/// Returns the names and IDs of the children groups.
+	pub fn list_children(&self) -> AllocResult<Vec<(String, u32)>> {
+		let children = self.children.lock();
+		let mut list = Vec::with_capacity(children.len())?;
+		for (name, child) in children.iter() {
+			list.push((name.try_clone()?, child.id))?;
+		}
+		Ok(list)
+	}
+
+	/// Creates a child group of `parent` with the name `name`.
+	///
+	/// If a group with the same name already exists, the function returns
+	/// [`crate::errno::EEXIST`].
+	pub fn create_child(parent: &Arc<Self>, name: &[u8]) -> EResult<Arc<CGroup>> {
+		let mut children = parent.children.lock();
+		if children.contains_key(name) {
+			return Err(errno!(EEXIST));
+		}
+		let child = Self::new(String::try_from(name)?, Some(parent.clone()))?;
+		if let Err(e) = children.insert(String::try_from(name)?, child.clone()) {
+			GROUPS.lock().remove(&child.id);
+			return Err(e.into());
+		}
+		Ok(child)
+	}
+
+	/// Removes the child group with the name `name`.
+	///
+	/// If the group contains processes or has children, the function returns
+	/// [`crate::errno::EBUSY`].
+	pub fn remove_child(&self, name: &[u8]) -> EResult<()> {
+		let mut children = self.children.lock();
+		let child = children.get(name).ok_or_else(|| errno!(ENOENT))?;
+		if child.is_populated() || !child.children.lock().is_empty() {
+			return Err(errno!(EBUSY));
+		}
+		GROUPS.lock().remove(&child.id);
+		children.remove(name);
+		Ok(())
+	}
+
+	/// Returns the set of controllers enabled for the children of the group.
+	pub fn get_subtree_control(&self) -> u32 {
+		self.subtree_control.load(Ordering::Relaxed)
+	}
+
+	/// Sets the set of controllers enabled for the children of the group.
+	pub fn set_subtree_control(&self, controllers: u32) {
+		self.subtree_control.store(controllers, Ordering::Relaxed);
+	}
+
+	/// Tells whether the group or one of its descendants contains a process.
+	pub fn is_populated(&self) -> bool {
+		self.nr_procs.load(Ordering::Relaxed) > 0
+	}
+
+	/// Adds a process to the group.
+	pub fn attach(&self) {
+		for group in self.iter_path() {
+			group.nr_procs.fetch_add(1, Ordering::Relaxed);
+		}
+	}
+
+	/// Removes a process from the group.
+	pub fn detach(&self) {
+		for group in self.iter_path() {
+			group.nr_procs.fetch_sub(1, Ordering::Relaxed);
+		}
+	}
+
+	/// Returns the value of `cpu.weight`.
+	pub fn get_cpu_weight(&self) -> u32 {
+		self.cpu_weight.load(Ordering::Relaxed)
+	}
+
+	/// Sets the value of `cpu.weight`.
+	///
+	/// If the value is out of bounds, the function returns [`crate::errno::ERANGE`].
+	pub fn set_cpu_weight(&self, weight: u32) -> EResult<()> {
+		if !(CPU_WEIGHT_MIN..=CPU_WEIGHT_MAX).contains(&weight) {
+			return Err(errno!(ERANGE));
+		}
+		self.cpu_weight.store(weight, Ordering::Relaxed);
+		Ok(())
+	}
+
+	/// Scales the scheduling weight `weight` of a process of the group according to the weights
+	/// of the group and its ancestors.
+	///
+	/// The share of the group is divided among its processes, so that the CPU time the group
+	/// receives does not depend on the number of processes it contains.
+	pub fn scale_weight(&self, weight: usize) -> usize {
+		if self.is_root() {
+			return weight;
+		}
+		let weight = self.iter_path().filter(|group| !group.is_root()).fold(
+			weight as u64,
+			|weight, group| {
+				weight.saturating_mul(group.get_cpu_weight() as u64) / CPU_WEIGHT_DEFAULT as u64
+			},
+		);
+		let procs = max(self.nr_procs.load(Ordering::Relaxed), 1) as u64;
+		(weight / procs).clamp(1, u32::MAX as _) as _
+	}
+
+	/// Returns the value of `cpu.max`, as the quota and the period in microseconds.
+	pub fn get_cpu_max(&self) -> (Option<u64>, u64) {
+		let cpu = self.cpu.lock();
+		(cpu.quota, cpu.period)
+	}
+
+	/// Sets the value of `cpu.max` to the quota `quota` and the period `period` in microseconds.
+	///
+	/// If a value is out of bounds, the function returns [`crate::errno::EINVAL`].
+	pub fn set_cpu_max(&self, quota: Option<u64>, period: u64) -> EResult<()> {
+		if !(CPU_PERIOD_MIN..=CPU_PERIOD_MAX).contains(&period)
+			|| quota.is_some_and(|quota| quota < CPU_PERIOD_MIN)
+		{
+			return Err(errno!(EINVAL));
+		}
+		let mut cpu = self.cpu.lock();
+		cpu.quota = quota;
+		cpu.period = period;
+		Ok(())
+	}
+
+	/// Charges the CPU time `delta` in nanoseconds to the group and its ancestors.
+	///
+	/// `now` is the current timestamp, in nanoseconds.
+	pub fn charge_cpu(&self, delta: u64, now: u64) {
+		for group in self.iter_path() {
+			let mut cpu = group.cpu.lock();
+			cpu.refresh(now);
+			cpu.usage += delta;
+			cpu.period_usage += delta;
+			if cpu.is_exhausted() && !cpu.throttled {
+				cpu.throttled = true;
+				cpu.nr_throttled += 1;
+			}
+		}
+	}
+
+	/// Tells whether the processes of the group are prevented from running because the group or
+	/// one of its ancestors has exhausted its CPU quota.
+	///
+	/// `now` is the current timestamp, in nanoseconds.
+	pub fn is_throttled(&self, now: u64) -> bool {
+		self.iter_path().any(|group| {
+			let mut cpu = group.cpu.lock();
+			cpu.refresh(now);
+			cpu.is_exhausted()
+		})
+	}
+
+	/// Returns the statistics of the `cpu` controller: the total CPU time used by the group in
+	/// nanoseconds, the number of elapsed periods and the number of throttled periods.
+	pub fn get_cpu_stat(&self) -> (u64, u64, u64) {
+		let cpu = self.cpu.lock();
+		(cpu.usage, cpu.nr_periods, cpu.nr_throttled)
+	}
+
+	/// Returns the number of physical pages charged to the group.
+	pub fn get_memory_current(&self) -> usize {
+		self.memory_current.load(Ordering::Relaxed)
+	}
+
+	/// Returns the value of `memory.max` in pages.
+	pub fn get_memory_max(&self) -> usize {
+		self.memory_max.load(Ordering::Relaxed)
+	}
+
+	/// Sets the value of `memory.max` in pages.
+	///
+	/// The limit is enforced on the next allocation, thus memory already in use is not reclaimed
+	/// right away.
+	pub fn set_memory_max(&self, pages: usize) {
+		self.memory_max.store(pages, Ordering::Relaxed);
+	}
+
+	/// Charges `pages` physical pages to the group and its ancestors.
+	pub fn charge_memory(&self, pages: usize) {
+		for group in self.iter_path() {
+			group.memory_current.fetch_add(pages, Ordering::Relaxed);
+		}
+	}
+
+	/// Uncharges `pages` physical pages from the group and its ancestors.
+	pub fn uncharge_memory(&self, pages: usize) {
+		for group in self.iter_path() {
+			group.memory_current.fetch_sub(pages, Ordering::Relaxed);
+		}
+	}
+
+	/// Returns the closest group, among the group and its ancestors, that cannot be charged one
+	/// more page without exceeding its limit.
+	///
+	/// If every group can be charged, the function returns `None`.
+	pub fn get_memory_limited(&self) -> Option<&CGroup> {
+		self.iter_path()
+			.find(|group| group.get_memory_current() >= group.get_memory_max())
+	}
+}
+
+/// Parses a value of a `max` file, either an integer or `max` for no limit.
+///
+/// If the value is invalid, the function returns [`crate::errno::EINVAL`].
+pub fn parse_max(s: &str) -> EResult<Option<u64>> {
+	match s {
+		"max" => Ok(None),
+		s => s.parse().map(Some).map_err(|_| errno!(EINVAL)),
+	}
+}
+
+/// Converts the size `size` in bytes into a number of pages, rounded down. If `None`, the
+/// function returns [`usize::MAX`].
+pub fn bytes_to_pages(size: Option<u64>) -> usize {
+	size.map(|size| {
+		(size / memory::PAGE_SIZE as u64)
+			.try_into()
+			.unwrap_or(usize::MAX)
+	})
+	.unwrap_or(usize::MAX)
+}
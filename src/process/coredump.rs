@@ -0,0 +1,502 @@
+//! A core dump is a file describing the memory and the registers state of a process at the
+//! moment it has been killed by a signal, allowing post-mortem debugging.
+//!
+//! Core files use the ELF format, with a `PT_NOTE` segment describing the state of the process,
+//! followed by a `PT_LOAD` segment for each memory mapping.
+
+use super::mem_space;
+use super::signal::SigInfo;
+use super::signal::Signal;
+use super::Process;
+use super::COMM_LEN;
+use crate::cpu::smap;
+use crate::elf;
+use crate::elf::ELF32ELFHeader;
+use crate::elf::ELF32ProgramHeader;
+use crate::errno;
+use crate::errno::EResult;
+use crate::file::path::Path;
+use crate::file::vfs;
+use crate::file::vfs::ResolutionSettings;
+use crate::file::File;
+use crate::file::FileContent;
+use crate::file::FileType;
+use crate::gdt;
+use crate::memory;
+use crate::process::rlimit;
+use crate::time::clock;
+use crate::time::clock::CLOCK_REALTIME;
+use crate::time::unit::TimestampScale;
+use crate::time::unit::Timeval;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+use crate::util::lock::Mutex;
+use crate::util::TryClone;
+use core::cmp::min;
+use core::mem::size_of;
+use core::slice;
+
+/// The maximum length of the core pattern in bytes.
+pub const CORE_PATTERN_MAX: usize = 128;
+/// The core pattern used when none has been set.
+const DEFAULT_PATTERN: &[u8] = b"core";
+
+/// Note type: the status and registers of the process.
+const NT_PRSTATUS: u32 = 1;
+/// Note type: informations about the process.
+const NT_PRPSINFO: u32 = 3;
+/// Note type: the informations of the signal that killed the process.
+const NT_SIGINFO: u32 = 0x53494749;
+/// Note type: the x87 FPU, MMX and SSE state, in the format of `fxsave`.
+const NT_PRXFPREG: u32 = 0x46e62b7f;
+/// The name of the notes.
+const NOTE_NAME: &[u8] = b"CORE\0";
+
+/// The pattern giving the path of core files, as set through `/proc/sys/kernel/core_pattern`.
+///
+/// The following specifiers are expanded:
+/// - `%%`: a `%` character
+/// - `%p`: the PID of the process
+/// - `%i`: the TID of the thread
+/// - `%u`: the real user ID
+/// - `%g`: the real group ID
+/// - `%s`: the number of the signal
+/// - `%t`: the time of the dump, in seconds since the epoch
+/// - `%h`: the hostname
+/// - `%e`: the name of the command
+///
+/// If empty, [`DEFAULT_PATTERN`] is used.
+pub static CORE_PATTERN: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+
+/// The status of a process, as written in the `NT_PRSTATUS` note (`struct elf_prstatus`).
+#[repr(C)]
+#[derive(Default)]
+struct ElfPrStatus {
+	/// The number of the signal.
+	si_signo: i32,
+	/// The code of the signal.
+	si_code: i32,
+	/// The errno associated with the signal.
+	si_errno: i32,
+	/// The current signal.
+	pr_cursig: i16,
+	/// Padding.
+	_pad: i16,
+	/// The set of pending signals.
+	pr_sigpend: u32,
+	/// The set of blocked signals.
+	pr_sighold: u32,
+	/// The ID of the thread.
+	pr_pid: i32,
+	/// The ID of the parent process.
+	pr_ppid: i32,
+	/// The ID of the process group.
+	pr_pgrp: i32,
+	/// The ID of the session.
+	pr_sid: i32,
+	/// The user time.
+	pr_utime: Timeval32,
+	/// The system time.
+	pr_stime: Timeval32,
+	/// The cumulative user time.
+	pr_cutime: Timeval32,
+	/// The cumulative system time.
+	pr_cstime: Timeval32,
+	/// The general purpose registers, in the layout of `struct user_regs_struct`.
+	pr_reg: [u32; 17],
+	/// Tells whether the floating point registers are valid.
+	pr_fpvalid: i32,
+}
+
+/// A timestamp in a note.
+#[repr(C)]
+#[derive(Default)]
+struct Timeval32 {
+	/// Seconds.
+	tv_sec: i32,
+	/// Microseconds.
+	tv_usec: i32,
+}
+
+impl From<&Timeval> for Timeval32 {
+	fn from(val: &Timeval) -> Self {
+		Self {
+			tv_sec: val.tv_sec as _,
+			tv_usec: val.tv_usec as _,
+		}
+	}
+}
+
+/// Informations about a process, as written in the `NT_PRPSINFO` note (`struct elf_prpsinfo`).
+#[repr(C)]
+struct ElfPrPsInfo {
+	/// The numeric state of the process.
+	pr_state: u8,
+	/// The character representing the state of the process.
+	pr_sname: u8,
+	/// Tells whether the process is a zombie.
+	pr_zomb: u8,
+	/// The nice value of the process.
+	pr_nice: i8,
+	/// The flags of the process.
+	pr_flag: u32,
+	/// The real user ID.
+	pr_uid: u16,
+	/// The real group ID.
+	pr_gid: u16,
+	/// The ID of the process.
+	pr_pid: i32,
+	/// The ID of the parent process.
+	pr_ppid: i32,
+	/// The ID of the process group.
+	pr_pgrp: i32,
+	/// The ID of the session.
+	pr_sid: i32,
+	/// The name of the command.
+	pr_fname: [u8; COMM_LEN],
+	/// The beginning of the arguments of the command, separated by spaces.
+	pr_psargs: [u8; 80],
+}
+
+/// Returns the bytes representation of the given value.
+fn as_bytes<T>(val: &T) -> &[u8] {
+	unsafe { slice::from_raw_parts(val as *const _ as *const u8, size_of::<T>()) }
+}
+
+/// Pads `notes` with zeros to a 4 bytes boundary.
+fn pad_note(notes: &mut Vec<u8>) -> EResult<()> {
+	while notes.len() % 4 != 0 {
+		notes.push(0)?;
+	}
+	Ok(())
+}
+
+/// Appends the note with type `type_` and content `desc` to `notes`.
+fn push_note(notes: &mut Vec<u8>, type_: u32, desc: &[u8]) -> EResult<()> {
+	notes.extend_from_slice(&(NOTE_NAME.len() as u32).to_ne_bytes())?;
+	notes.extend_from_slice(&(desc.len() as u32).to_ne_bytes())?;
+	notes.extend_from_slice(&type_.to_ne_bytes())?;
+	notes.extend_from_slice(NOTE_NAME)?;
+	pad_note(notes)?;
+	notes.extend_from_slice(desc)?;
+	pad_note(notes)?;
+	Ok(())
+}
+
+/// Returns the notes describing the state of the process `proc`, killed by the signal `sig`
+/// with the informations `info`.
+fn build_notes(proc: &Process, sig: &Signal, info: &SigInfo) -> EResult<Vec<u8>> {
+	let regs = &proc.regs;
+	let rusage = proc.get_rusage();
+	let user_ds = (gdt::USER_DS | 3) as u32;
+	let prstatus = ElfPrStatus {
+		si_signo: info.si_signo,
+		si_code: info.si_code,
+		si_errno: info.si_errno,
+		pr_cursig: sig.get_id() as _,
+		pr_sigpend: proc.sigpending as _,
+		pr_sighold: proc.sigmask as _,
+		pr_pid: proc.tid as _,
+		pr_ppid: proc.get_parent_pid() as _,
+		pr_pgrp: proc.pgid as _,
+		pr_sid: proc.sid as _,
+		pr_utime: (&rusage.ru_utime).into(),
+		pr_stime: (&rusage.ru_stime).into(),
+		pr_reg: [
+			regs.ebx,
+			regs.ecx,
+			regs.edx,
+			regs.esi,
+			regs.edi,
+			regs.ebp,
+			regs.eax,
+			user_ds,
+			user_ds,
+			regs.fs,
+			regs.gs,
+			// The original `eax` is not kept
+			regs.eax,
+			regs.eip,
+			(gdt::USER_CS | 3) as _,
+			regs.eflags,
+			regs.esp,
+			user_ds,
+		],
+		pr_fpvalid: 1,
+		..Default::default()
+	};
+
+	let mut prpsinfo = ElfPrPsInfo {
+		pr_state: 0,
+		pr_sname: b'R',
+		pr_zomb: 0,
+		pr_nice: 0,
+		pr_flag: 0,
+		pr_uid: proc.access_profile.get_uid() as _,
+		pr_gid: proc.access_profile.get_gid() as _,
+		pr_pid: proc.tgid as _,
+		pr_ppid: proc.get_parent_pid() as _,
+		pr_pgrp: proc.pgid as _,
+		pr_sid: proc.sid as _,
+		pr_fname: [0; COMM_LEN],
+		pr_psargs: [0; 80],
+	};
+	let comm = proc.get_comm();
+	prpsinfo.pr_fname[..comm.len()].copy_from_slice(comm);
+	let mut off = 0;
+	for arg in proc.argv.iter() {
+		if off >= prpsinfo.pr_psargs.len() - 1 {
+			break;
+		}
+		if off > 0 {
+			prpsinfo.pr_psargs[off] = b' ';
+			off += 1;
+		}
+		let len = min(arg.len(), prpsinfo.pr_psargs.len() - 1 - off);
+		prpsinfo.pr_psargs[off..(off + len)].copy_from_slice(&arg.as_bytes()[..len]);
+		off += len;
+	}
+
+	let mut notes = Vec::new();
+	push_note(&mut notes, NT_PRSTATUS, as_bytes(&prstatus))?;
+	push_note(&mut notes, NT_PRPSINFO, as_bytes(&prpsinfo))?;
+	push_note(&mut notes, NT_SIGINFO, as_bytes(info))?;
+	push_note(&mut notes, NT_PRXFPREG, &regs.fxstate)?;
+	Ok(notes)
+}
+
+/// Returns the path of the core file of the process `proc`, killed by the signal `sig`.
+///
+/// If the pattern requests piping the core to a program, the function returns `None`.
+fn get_core_path(proc: &Process, sig: &Signal) -> EResult<Option<Path>> {
+	let pattern = CORE_PATTERN.lock();
+	let pattern = if pattern.is_empty() {
+		DEFAULT_PATTERN
+	} else {
+		&pattern
+	};
+	// TODO Support piping to a helper program
+	if pattern.first() == Some(&b'|') {
+		return Ok(None);
+	}
+
+	let mut name = Vec::new();
+	let mut iter = pattern.iter();
+	while let Some(c) = iter.next() {
+		if *c != b'%' {
+			name.push(*c)?;
+			continue;
+		}
+		let spec = match iter.next() {
+			Some(b'%') => crate::format!("%")?,
+			Some(b'p') => crate::format!("{}", proc.tgid)?,
+			Some(b'i') => crate::format!("{}", proc.tid)?,
+			Some(b'u') => crate::format!("{}", proc.access_profile.get_uid())?,
+			Some(b'g') => crate::format!("{}", proc.access_profile.get_gid())?,
+			Some(b's') => crate::format!("{}", sig.get_id())?,
+			Some(b't') => {
+				let now = clock::current_time(CLOCK_REALTIME, TimestampScale::Second)?;
+				crate::format!("{now}")?
+			}
+			Some(b'h') => crate::HOSTNAME.lock().as_slice().try_into()?,
+			Some(b'e') => proc.get_comm().try_into()?,
+			// Unknown specifiers are dropped
+			_ => continue,
+		};
+		// A slash in an expanded specifier must not create a sub-directory
+		for c in spec.as_bytes() {
+			name.push(if *c == b'/' { b'!' } else { *c })?;
+		}
+	}
+
+	// The path is relative to the working directory of the process
+	let mut path = Path::from_str(&name, true)?;
+	let fs = proc.get_fs().lock();
+	let path = if path.is_absolute() {
+		path.set_absolute(false);
+		fs.chroot.concat(&path)?
+	} else {
+		fs.cwd.concat(&path)?
+	};
+	Ok(Some(path))
+}
+
+/// Creates the core file at `path` for the process `proc`.
+///
+/// If the file already exists, it is truncated.
+fn create_core_file(
+	proc: &Process,
+	mut path: Path,
+) -> EResult<crate::util::ptr::arc::Arc<Mutex<File>>> {
+	let name = path.pop().ok_or_else(|| errno!(ENOENT))?;
+	let rs = ResolutionSettings::for_process(proc, true)?;
+	let parent_mutex = vfs::resolve_path(&path, &rs)?;
+	let mut parent = parent_mutex.lock();
+
+	let ap = rs.access_profile;
+	match vfs::get_file_from_parent(&parent, name.try_clone()?, &ap, false) {
+		Ok(file_mutex) => {
+			{
+				let mut file = file_mutex.lock();
+				// Do not overwrite a file that does not belong to the process
+				if file.get_type() != FileType::Regular
+					|| file.get_hard_links_count() > 1
+					|| file.get_uid() != ap.get_fsuid()
+				{
+					return Err(errno!(EPERM));
+				}
+				if !ap.can_write_file(&file) {
+					return Err(errno!(EACCES));
+				}
+				file.check_mount_writable()?;
+				file.set_size(0);
+			}
+			Ok(file_mutex)
+		}
+		Err(e) if e.as_int() == errno::ENOENT => {
+			vfs::create_file(&mut parent, name, &ap, 0o600, FileContent::Regular)
+		}
+		Err(e) => Err(e),
+	}
+}
+
+/// A writer appending data to a core file, up to a size limit.
+struct CoreWriter<'f> {
+	/// The core file.
+	file: &'f mut File,
+	/// The current offset in the file.
+	off: u64,
+	/// The maximum size of the file in bytes.
+	limit: u64,
+}
+
+impl CoreWriter<'_> {
+	/// Writes `buf` at the current offset. Data beyond the size limit is discarded.
+	fn write(&mut self, buf: &[u8]) -> EResult<()> {
+		let len = min(buf.len() as u64, self.limit.saturating_sub(self.off)) as usize;
+		if len > 0 {
+			self.file.write(self.off, &buf[..len])?;
+		}
+		self.off += buf.len() as u64;
+		Ok(())
+	}
+}
+
+/// Writes the core file of the process `proc`, killed by the signal `sig` with the informations
+/// `info`.
+///
+/// The process must be the current process.
+///
+/// If the process is not dumpable or if the limit `RLIMIT_CORE` is zero, the function does
+/// nothing and returns `false`. Else, it returns `true` if the core has been dumped.
+pub fn dump(proc: &Process, sig: &Signal, info: &SigInfo) -> EResult<bool> {
+	let limit = proc.rlimits.get_cur(rlimit::RLIMIT_CORE);
+	if !proc.dumpable || limit == 0 {
+		return Ok(false);
+	}
+	let Some(mem_space_mutex) = proc.get_mem_space() else {
+		return Ok(false);
+	};
+	let Some(path) = get_core_path(proc, sig)? else {
+		return Ok(false);
+	};
+	let file_mutex = create_core_file(proc, path)?;
+	let mut file = file_mutex.lock();
+
+	let notes = build_notes(proc, sig, info)?;
+	let mem_space = mem_space_mutex.lock();
+	if !mem_space.is_bound() {
+		mem_space.bind();
+	}
+
+	let phnum = mem_space.iter_mappings().count() + 1;
+	let notes_off = size_of::<ELF32ELFHeader>() + phnum * size_of::<ELF32ProgramHeader>();
+	let segments_off = (notes_off + notes.len()).next_multiple_of(memory::PAGE_SIZE);
+
+	let mut e_ident = [0; elf::EI_NIDENT];
+	e_ident[..4].copy_from_slice(b"\x7fELF");
+	e_ident[elf::EI_CLASS] = elf::ELFCLASS32;
+	e_ident[elf::EI_DATA] = elf::ELFDATA2LSB;
+	e_ident[elf::EI_VERSION] = 1;
+	let ehdr = ELF32ELFHeader {
+		e_ident,
+		e_type: elf::ET_CORE,
+		e_machine: elf::EM_386,
+		e_version: 1,
+		e_entry: 0,
+		e_phoff: size_of::<ELF32ELFHeader>() as _,
+		e_shoff: 0,
+		e_flags: 0,
+		e_ehsize: size_of::<ELF32ELFHeader>() as _,
+		e_phentsize: size_of::<ELF32ProgramHeader>() as _,
+		e_phnum: phnum as _,
+		e_shentsize: 0,
+		e_shnum: 0,
+		e_shstrndx: 0,
+	};
+
+	let mut writer = CoreWriter {
+		file: &mut file,
+		off: 0,
+		limit,
+	};
+	writer.write(as_bytes(&ehdr))?;
+	let note_phdr = ELF32ProgramHeader {
+		p_type: elf::PT_NOTE,
+		p_offset: notes_off as _,
+		p_vaddr: 0,
+		p_paddr: 0,
+		p_filesz: notes.len() as _,
+		p_memsz: 0,
+		p_flags: 0,
+		p_align: 0,
+	};
+	writer.write(as_bytes(&note_phdr))?;
+	let mut off = segments_off;
+	for mapping in mem_space.iter_mappings() {
+		let size = mapping.get_size().get() * memory::PAGE_SIZE;
+		let flags = mapping.get_flags();
+		let mut p_flags = elf::PF_R;
+		if flags & mem_space::MAPPING_FLAG_WRITE != 0 {
+			p_flags |= elf::PF_W;
+		}
+		if flags & mem_space::MAPPING_FLAG_EXEC != 0 {
+			p_flags |= elf::PF_X;
+		}
+		let phdr = ELF32ProgramHeader {
+			p_type: elf::PT_LOAD,
+			p_offset: off as _,
+			p_vaddr: mapping.get_begin() as _,
+			p_paddr: 0,
+			p_filesz: size as _,
+			p_memsz: size as _,
+			p_flags,
+			p_align: memory::PAGE_SIZE as _,
+		};
+		writer.write(as_bytes(&phdr))?;
+		off += size;
+	}
+	writer.write(&notes)?;
+	writer.write(&[0; memory::PAGE_SIZE][..(segments_off - writer.off as usize)])?;
+
+	let mut page = [0u8; memory::PAGE_SIZE];
+	for mapping in mem_space.iter_mappings() {
+		for i in 0..mapping.get_size().get() {
+			// Pages that have never been accessed are not faulted in
+			if mapping.get_physical_page(i).is_some() {
+				let ptr = (mapping.get_begin() as usize + i * memory::PAGE_SIZE) as *const u8;
+				smap::wrap(|| unsafe {
+					page.copy_from_slice(slice::from_raw_parts(ptr, memory::PAGE_SIZE));
+				});
+			} else {
+				page.fill(0);
+			}
+			writer.write(&page)?;
+			if writer.off >= limit {
+				return Ok(true);
+			}
+		}
+	}
+
+	Ok(true)
+}
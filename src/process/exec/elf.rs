@@ -2,6 +2,9 @@
 
 use super::vdso;
 use crate::cpu;
+use crate::cpu::smap;
+use crate::cpu::smap::UserAccess;
+use crate::crypto::rand;
 use crate::elf;
 use crate::elf::parser::ELFParser;
 use crate::elf::relocation::Relocation;
@@ -40,6 +43,24 @@ use core::ptr;
 use core::ptr::null;
 use core::slice;
 use core::str;
+use core::sync::atomic;
+
+/// The address at which position-independent executables are loaded.
+const ET_DYN_BASE: usize = 0x400000;
+/// The number of bits of randomness of the load base of position-independent executables, in
+/// pages.
+const ET_DYN_RND_BITS: u32 = 8;
+/// The number of bits of randomness of the interpreter's load base, in pages.
+const INTERP_RND_BITS: u32 = 8;
+/// The number of bits of randomness of the `brk` base, in pages.
+const BRK_RND_BITS: u32 = 13;
+/// The number of bits of randomness of the gap between the top of the user stack and the end of
+/// the userspace, in pages.
+const STACK_RND_BITS: u32 = 11;
+/// The frequency of the clock ticks reported to userspace, in Hz.
+const USER_HZ: isize = 100;
+/// The number of random bytes given to the program.
+const RANDOM_LEN: usize = 16;
 
 /// Used to define the end of the entries list.
 const AT_NULL: i32 = 0;
@@ -116,8 +137,8 @@ struct ELFLoadInfo {
 
 	/// The load base of the interpreter program
 	interp_load_base: Option<*const c_void>,
-	/// The pointer to the entry point to be given to the interpreter
-	interp_entry: Option<*const c_void>,
+	/// The pointer to the entry point of the program itself, to be given to the interpreter
+	prog_entry: *const c_void,
 }
 
 /// An entry of System V's Auxilary Vectors.
@@ -135,6 +156,8 @@ enum AuxEntryDescValue {
 	Number(isize),
 	/// A string of bytes.
 	String(&'static [u8]),
+	/// An array of bytes, copied without a terminating nullbyte.
+	Bytes(Vec<u8>),
 }
 
 /// Structure describing an auxilary vector entry.
@@ -186,19 +209,16 @@ fn build_auxilary(
 		AuxEntryDescValue::Number(memory::PAGE_SIZE as _),
 	))?;
 
-	if let Some(base) = load_info.interp_load_base {
-		aux.push(AuxEntryDesc::new(
-			AT_BASE,
-			AuxEntryDescValue::Number(base as _),
-		))?;
-	}
-
-	if let Some(entry) = load_info.interp_entry {
-		aux.push(AuxEntryDesc::new(
-			AT_ENTRY,
-			AuxEntryDescValue::Number(entry as _),
-		))?;
-	}
+	// If no interpreter is present, the base is null
+	let interp_base = load_info.interp_load_base.unwrap_or(null());
+	aux.push(AuxEntryDesc::new(
+		AT_BASE,
+		AuxEntryDescValue::Number(interp_base as _),
+	))?;
+	aux.push(AuxEntryDesc::new(
+		AT_ENTRY,
+		AuxEntryDescValue::Number(load_info.prog_entry as _),
+	))?;
 
 	aux.push(AuxEntryDesc::new(AT_NOTELF, AuxEntryDescValue::Number(0)))?;
 	aux.push(AuxEntryDesc::new(
@@ -228,15 +248,29 @@ fn build_auxilary(
 		AuxEntryDescValue::Number(hwcap as _),
 	))?;
 
-	aux.push(AuxEntryDesc::new(AT_SECURE, AuxEntryDescValue::Number(0)))?; // TODO
+	aux.push(AuxEntryDesc::new(
+		AT_CLKTCK,
+		AuxEntryDescValue::Number(USER_HZ),
+	))?;
+
+	// The dynamic linker must not trust the environment of set-user-ID and set-group-ID programs
+	let ap = &exec_info.access_profile;
+	let secure = ap.get_euid() != ap.get_uid() || ap.get_egid() != ap.get_gid();
+	aux.push(AuxEntryDesc::new(
+		AT_SECURE,
+		AuxEntryDescValue::Number(secure as _),
+	))?;
 	aux.push(AuxEntryDesc::new(
 		AT_BASE_PLATFORM,
 		AuxEntryDescValue::String(crate::NAME.as_bytes()),
 	))?;
+	// Used by the libc to initialize stack canaries
+	let mut random = crate::vec![0; RANDOM_LEN]?;
+	rand::fill(random.as_mut_slice());
 	aux.push(AuxEntryDesc::new(
 		AT_RANDOM,
-		AuxEntryDescValue::String(&[0; 16]),
-	))?; // TODO
+		AuxEntryDescValue::Bytes(random),
+	))?;
 	aux.push(AuxEntryDesc::new(
 		AT_EXECFN,
 		AuxEntryDescValue::String("TODO\0".as_bytes()),
@@ -268,6 +302,9 @@ fn read_exec_file(file: &mut File, ap: &AccessProfile) -> Result<Vec<u8>, Errno>
 	if !ap.can_execute_file(file) {
 		return Err(errno!(ENOEXEC));
 	}
+	if !file.is_mount_executable() {
+		return Err(errno!(EACCES));
+	}
 
 	let len = file.get_size().try_into().map_err(|_| AllocError)?;
 	let mut image = crate::vec![0u8; len]?;
@@ -293,6 +330,22 @@ impl ELFExecutor {
 		})
 	}
 
+	/// Returns the level of randomization to apply to the program's memory layout.
+	///
+	/// For details, see [`mem_space::RANDOMIZE_VA_SPACE`].
+	fn get_randomize_level(&self) -> u32 {
+		if self.info.randomize {
+			mem_space::RANDOMIZE_VA_SPACE.load(atomic::Ordering::Relaxed)
+		} else {
+			0
+		}
+	}
+
+	/// Returns a random offset in bytes, with `bits` bits of randomness in pages.
+	fn get_random_offset(bits: u32) -> usize {
+		(rand::get_random_u32() as usize & ((1 << bits) - 1)) * memory::PAGE_SIZE
+	}
+
 	/// Returns two values:
 	/// - The size in bytes of the buffer to store the arguments and environment variables, padding
 	/// included.
@@ -306,8 +359,10 @@ impl ELFExecutor {
 		// The size of the block storing the arguments and environment
 		let mut info_block_size = 0;
 		for a in aux {
-			if let AuxEntryDescValue::String(slice) = a.a_val {
-				info_block_size += slice.len() + 1;
+			match &a.a_val {
+				AuxEntryDescValue::Number(_) => {}
+				AuxEntryDescValue::String(slice) => info_block_size += slice.len() + 1,
+				AuxEntryDescValue::Bytes(bytes) => info_block_size += bytes.len(),
 			}
 		}
 		for e in envp {
@@ -422,15 +477,15 @@ impl ELFExecutor {
 
 		// Setting auxilary vector
 		for a in aux {
-			let val = match a.a_val {
-				AuxEntryDescValue::Number(n) => n as _,
+			let val = match &a.a_val {
+				AuxEntryDescValue::Number(n) => *n as _,
 
 				AuxEntryDescValue::String(slice) => {
 					// The offset of the beginning of the variable in the information block
 					let begin = info_off;
 
 					// Copying the string into the information block
-					for b in slice {
+					for b in *slice {
 						info_slice[info_off] = *b;
 						info_off += 1;
 					}
@@ -440,6 +495,17 @@ impl ELFExecutor {
 
 					&mut info_slice[begin] as *mut _ as _
 				}
+
+				AuxEntryDescValue::Bytes(bytes) => {
+					// The offset of the beginning of the array in the information block
+					let begin = info_off;
+
+					// Copying the array into the information block
+					info_slice[begin..(begin + bytes.len())].copy_from_slice(bytes);
+					info_off += bytes.len();
+
+					&mut info_slice[begin] as *mut _ as _
+				}
 			};
 
 			// Setting the entry
@@ -558,13 +624,24 @@ impl ELFExecutor {
 		// The size in bytes of the phdr table
 		let phdr_size = phentsize * phnum;
 
+		let phoff = ehdr.e_phoff as usize;
 		let phdr = elf
 			.iter_segments()
-			.filter(|seg| seg.p_type == elf::PT_PHDR)
-			.map(|seg| seg.p_vaddr as *mut c_void)
-			.next();
+			.find(|seg| seg.p_type == elf::PT_PHDR)
+			.map(|seg| seg.p_vaddr as usize)
+			// Else, look for a loadable segment containing the table
+			.or_else(|| {
+				elf.iter_segments()
+					.filter(|seg| seg.p_type == elf::PT_LOAD)
+					.find(|seg| {
+						let begin = seg.p_offset as usize;
+						let end = begin + seg.p_filesz as usize;
+						phoff >= begin && phoff + phdr_size <= end
+					})
+					.map(|seg| seg.p_vaddr as usize + phoff - seg.p_offset as usize)
+			});
 		let (phdr, phdr_needs_copy) = match phdr {
-			Some(phdr) => (phdr, false),
+			Some(phdr) => ((load_base as usize + phdr) as *mut c_void, false),
 
 			// Not phdr segment. Load it manually
 			None => {
@@ -583,11 +660,10 @@ impl ELFExecutor {
 			}
 		};
 
-		let mut entry_point =
-			(load_base as usize + elf.get_header().e_entry as usize) as *const c_void;
+		let prog_entry = (load_base as usize + ehdr.e_entry as usize) as *const c_void;
+		let mut entry_point = prog_entry;
 
 		let mut interp_load_base = None;
-		let mut interp_entry = None;
 
 		// Loading the interpreter, if present
 		let interp_path = elf.get_interpreter_path();
@@ -606,12 +682,17 @@ impl ELFExecutor {
 
 			let interp_image = read_exec_file(&mut interp_file, &self.info.access_profile)?;
 			let interp_elf = ELFParser::new(interp_image.as_slice())?;
-			let i_load_base = load_end as _; // TODO ASLR
+			// The interpreter is loaded at an arbitrary address, so it must be relocatable
+			if interp_elf.get_header().e_type != elf::ET_DYN {
+				return Err(errno!(ELIBBAD));
+			}
+			let mut i_load_base = util::align(load_end, memory::PAGE_SIZE);
+			if self.get_randomize_level() >= 1 {
+				i_load_base = unsafe { i_load_base.add(Self::get_random_offset(INTERP_RND_BITS)) };
+			}
 			let load_info = self.load_elf(&interp_elf, mem_space, i_load_base, true)?;
 
 			interp_load_base = Some(i_load_base as _);
-			interp_entry =
-				Some((load_base as usize + elf.get_header().e_entry as usize) as *const c_void);
 			load_end = load_info.load_end;
 			entry_point = load_info.entry_point;
 		}
@@ -619,6 +700,8 @@ impl ELFExecutor {
 		// Switch to the process's vmem to write onto the virtual memory
 		unsafe {
 			vmem::switch(&**mem_space.get_vmem(), move || -> EResult<()> {
+				let _access = UserAccess::new();
+
 				// Copy segments' data
 				for seg in elf.iter_segments() {
 					Self::copy_segment(load_base, seg, elf.get_image());
@@ -678,7 +761,7 @@ impl ELFExecutor {
 			entry_point,
 
 			interp_load_base,
-			interp_entry,
+			prog_entry,
 		})
 	}
 }
@@ -693,17 +776,50 @@ impl Executor for ELFExecutor {
 		// Parsing the ELF file
 		let parser = ELFParser::new(image.as_slice())?;
 
+		let randomize_level = self.get_randomize_level();
+
 		// The process's new memory space
 		let mut mem_space = MemSpace::new()?;
+		if randomize_level >= 1 {
+			mem_space.randomize_mmap_base()?;
+		}
+
+		// Position-independent executables cannot be loaded at the beginning of the memory
+		let mut load_base = null::<c_void>();
+		if parser.get_header().e_type == elf::ET_DYN {
+			load_base = ET_DYN_BASE as _;
+			if randomize_level >= 1 {
+				load_base = unsafe { load_base.add(Self::get_random_offset(ET_DYN_RND_BITS)) };
+			}
+		}
 
 		// Loading the ELF
-		let load_info = self.load_elf(&parser, &mut mem_space, null::<c_void>(), false)?;
+		let load_info = self.load_elf(&parser, &mut mem_space, load_base, false)?;
 
-		// The user stack
-		let user_stack = mem_space.map_stack(
+		// The user stack, placed at the end of the userspace, below a random gap
+		let stack_gap = if randomize_level >= 1 {
+			Self::get_random_offset(STACK_RND_BITS)
+		} else {
+			0
+		};
+		let stack_size = process::USER_STACK_SIZE * memory::PAGE_SIZE;
+		let stack_hint = memory::PROCESS_END as usize - stack_gap - stack_size;
+		let stack_begin = mem_space.map(
+			MapConstraint::Hint(stack_hint as _),
 			process::USER_STACK_SIZE.try_into().unwrap(),
 			process::USER_STACK_FLAGS,
+			MapResidence::Normal,
 		)?;
+		// Safe because the pointer stays in the range of the mapping
+		let stack_top = unsafe { stack_begin.add(stack_size) };
+		mem_space.set_stack_top(stack_top);
+		// Randomize the top of the stack inside of its first page, keeping it aligned
+		let stack_off = if randomize_level >= 1 {
+			(rand::get_random_u32() as usize % memory::PAGE_SIZE) & !0xf
+		} else {
+			0
+		};
+		let user_stack = unsafe { stack_top.sub(stack_off) };
 
 		// Map the vDSO
 		let vdso = vdso::map(&mut mem_space)?;
@@ -716,7 +832,7 @@ impl Executor for ELFExecutor {
 		// Pre-allocating pages on the user stack to write the initial data
 		{
 			// The number of pages to allocate on the user stack to write the initial data
-			let pages_count = math::ceil_div(total_size, memory::PAGE_SIZE);
+			let pages_count = math::ceil_div(stack_off + total_size, memory::PAGE_SIZE);
 			// Checking that the data doesn't exceed the stack's size
 			if pages_count >= process::USER_STACK_SIZE {
 				return Err(errno!(ENOMEM));
@@ -724,18 +840,21 @@ impl Executor for ELFExecutor {
 
 			// Allocating the pages on the stack to write the initial data
 			let stack_len = pages_count * memory::PAGE_SIZE;
-			mem_space.alloc((user_stack as usize - stack_len) as *const u8, stack_len)?;
+			mem_space.alloc((stack_top as usize - stack_len) as *const u8, stack_len)?;
 		}
 
 		// The initial pointer for `brk`
-		let brk_ptr = util::align(load_info.load_end, memory::PAGE_SIZE);
+		let mut brk_ptr = util::align(load_info.load_end, memory::PAGE_SIZE);
+		if randomize_level >= 2 {
+			brk_ptr = unsafe { brk_ptr.add(Self::get_random_offset(BRK_RND_BITS)) };
+		}
 		mem_space.set_brk_init(brk_ptr as _);
 
 		// Switching to the process's vmem to write onto the virtual memory
 		unsafe {
 			vmem::switch(&**mem_space.get_vmem(), move || {
 				// Initializing the userspace stack
-				self.init_stack(user_stack, &self.info.argv, &self.info.envp, &aux);
+				smap::wrap(|| self.init_stack(user_stack, &self.info.argv, &self.info.envp, &aux));
 			});
 		}
 
@@ -747,6 +866,7 @@ impl Executor for ELFExecutor {
 
 		Ok(ProgramImage {
 			argv: self.info.argv.try_clone()?,
+			access_profile: self.info.access_profile,
 
 			mem_space,
 
@@ -13,10 +13,13 @@ use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file::perm::AccessProfile;
 use crate::file::File;
+use crate::process::acct;
 use crate::process::mem_space::MemSpace;
 use crate::process::regs::Regs;
 use crate::process::signal::SignalHandler;
 use crate::process::Process;
+use crate::syscall::personality::ADDR_NO_RANDOMIZE;
+use crate::util::container::hashmap::HashMap;
 use crate::util::container::string::String;
 use crate::util::container::vec::Vec;
 use crate::util::lock::IntMutex;
@@ -32,12 +35,16 @@ pub struct ExecInfo {
 	pub argv: Vec<String>,
 	/// The list of environment variables.
 	pub envp: Vec<String>,
+	/// Tells whether the layout of the program's memory space may be randomized.
+	pub randomize: bool,
 }
 
 /// A built program image.
 pub struct ProgramImage {
 	/// The argv of the program.
 	argv: Vec<String>,
+	/// The access profile of the process once the program is executed.
+	access_profile: AccessProfile,
 
 	/// The image's memory space.
 	mem_space: MemSpace,
@@ -79,6 +86,17 @@ pub fn build_image(file: &mut File, info: ExecInfo) -> EResult<ProgramImage> {
 /// Executes the program image `image` on the process `proc`.
 pub fn exec(proc: &mut Process, image: ProgramImage) -> EResult<()> {
 	proc.argv = Arc::new(image.argv)?;
+	// Executing a set-user-ID or set-group-ID program makes the process non-dumpable, cancels
+	// the parent death signal and re-enables the randomization of the memory layout
+	let ap = &image.access_profile;
+	let setid = ap.get_euid() != ap.get_uid() || ap.get_egid() != ap.get_gid();
+	proc.dumpable = !setid;
+	proc.acct_flags &= !acct::AFORK;
+	if setid {
+		proc.pdeath_signal = None;
+		proc.personality &= !ADDR_NO_RANDOMIZE;
+	}
+	proc.access_profile = image.access_profile;
 	// TODO Set exec path
 
 	// Duplicate the file descriptor table
@@ -91,11 +109,13 @@ pub fn exec(proc: &mut Process, image: ProgramImage) -> EResult<()> {
 		})
 		.transpose()?;
 
-	// Set the new memory space to the process
+	// Set the new memory space to the process. AIO contexts are bound to the previous one
 	proc.set_mem_space(Some(Arc::new(IntMutex::new(image.mem_space))?));
+	proc.aio_contexts = Arc::new(Mutex::new(HashMap::new()))?;
 
 	// Set new file descriptor table
 	proc.set_fds(fds);
+	proc.apply_rlimits();
 
 	// Set the process's stacks
 	proc.user_stack = Some(image.user_stack);
@@ -103,7 +123,8 @@ pub fn exec(proc: &mut Process, image: ProgramImage) -> EResult<()> {
 	proc.update_tss();
 
 	// Reset signals
-	proc.sigmask.clear_all();
+	proc.sigmask = 0;
+	proc.restart_block = None;
 	{
 		let mut handlers = proc.signal_handlers.lock();
 		for i in 0..handlers.len() {
@@ -113,6 +134,8 @@ pub fn exec(proc: &mut Process, image: ProgramImage) -> EResult<()> {
 
 	proc.reset_vfork();
 	proc.clear_tls_entries();
+	proc.set_robust_list(None);
+	proc.set_clear_child_tid(None);
 
 	// Set the process's registers
 	let regs = Regs {
@@ -96,7 +96,7 @@ pub fn map(mem_space: &mut MemSpace) -> Result<MappedVDSO, Errno> {
 	let ptr = mem_space.map(
 		MapConstraint::None,
 		vdso_pages,
-		mem_space::MAPPING_FLAG_USER,
+		mem_space::MAPPING_FLAG_USER | mem_space::MAPPING_FLAG_EXEC,
 		MapResidence::Static {
 			pages: img.pages.clone(),
 		},
@@ -0,0 +1,484 @@
+//! A futex (fast userspace mutex) is a 32 bits integer in userspace on which processes can wait
+//! until woken up by another process.
+//!
+//! Userspace uses them to implement locks: the kernel is only involved when a process needs to
+//! wait on a contended lock.
+//!
+//! Waiting processes are stored in wait queues, identified by a key:
+//! - Private futexes, which are only shared between the threads of a process, are identified by
+//! the memory space and their address
+//! - Shared futexes, which may be shared between processes through shared memory, are identified
+//! by their physical address
+//!
+//! Priority-inheritance (PI) futexes hold the TID of their owner. While processes wait on a PI
+//! futex, its owner runs with the highest priority among them so that it releases the futex
+//! quickly.
+//!
+//! A process can register a *robust list*, which is a list of the futexes it holds. When the
+//! process exits, the futexes in the list are marked with [`FUTEX_OWNER_DIED`] and a waiter is
+//! woken up, so that locks are not left held forever.
+
+use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::process::mem_space;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::MemSpace;
+use crate::process::pid::Pid;
+use crate::process::Process;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::vec::Vec;
+use crate::util::lock::IntMutex;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use core::cmp::max;
+use core::ffi::c_void;
+
+/// Bit of a PI futex telling that processes are waiting on it.
+pub const FUTEX_WAITERS: u32 = 0x80000000;
+/// Bit of a futex telling that its owner died while holding it.
+pub const FUTEX_OWNER_DIED: u32 = 0x40000000;
+/// The mask of the TID of the owner of a futex.
+pub const FUTEX_TID_MASK: u32 = 0x3fffffff;
+/// Bitset matching every waiter.
+pub const FUTEX_BITSET_MATCH_ANY: u32 = u32::MAX;
+
+/// The maximum number of entries of a robust list handled at exit, protecting against circular
+/// lists.
+const ROBUST_LIST_LIMIT: usize = 2048;
+
+/// The head of a robust list, in userspace.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct RobustListHead {
+	/// The address of the first entry. Each entry begins with the address of the next one, the
+	/// last entry pointing back to the head.
+	///
+	/// If the lowest bit of an entry's address is set, the futex is a PI futex.
+	pub list: usize,
+	/// The offset of the futex relative to each entry.
+	pub futex_offset: isize,
+	/// The entry being added or removed, which might not be linked in the list.
+	pub list_op_pending: usize,
+}
+
+/// The key identifying a futex.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum FutexKey {
+	/// A futex which is private to a memory space.
+	Private {
+		/// The address of the memory space.
+		mem_space: usize,
+		/// The virtual address of the futex.
+		addr: usize,
+	},
+	/// A futex which may be shared between memory spaces.
+	Shared {
+		/// The physical address of the futex.
+		phys: usize,
+	},
+}
+
+impl FutexKey {
+	/// Returns the key of the futex at address `addr`.
+	///
+	/// Arguments:
+	/// - `mem_space_mutex` is the memory space containing the futex.
+	/// - `mem_space` is the locked memory space.
+	/// - `private` tells whether the futex is known to be private to the memory space.
+	///
+	/// If the futex is not mapped, the function returns `EFAULT`.
+	pub fn new(
+		mem_space_mutex: &Arc<IntMutex<MemSpace>>,
+		mem_space: &mut MemSpace,
+		addr: *const u32,
+		private: bool,
+	) -> EResult<Self> {
+		if addr as usize % 4 != 0 {
+			return Err(errno!(EINVAL));
+		}
+		let mapping = mem_space
+			.get_mapping_mut_for(addr as *const c_void)
+			.ok_or_else(|| errno!(EFAULT))?;
+		let shared = mapping.get_flags() & mem_space::MAPPING_FLAG_SHARED != 0;
+		if private || !shared {
+			return Ok(Self::Private {
+				mem_space: mem_space_mutex.as_ptr() as usize,
+				addr: addr as usize,
+			});
+		}
+
+		// Make sure the page is present to get its physical address
+		mem_space.alloc(addr, 1)?;
+		let phys = mem_space
+			.get_vmem()
+			.translate(addr as *const c_void)
+			.ok_or_else(|| errno!(EFAULT))?;
+		Ok(Self::Shared {
+			phys: phys as usize,
+		})
+	}
+}
+
+/// A process waiting on a futex.
+#[derive(Debug)]
+struct Waiter {
+	/// The PID of the process.
+	pid: Pid,
+	/// The bitset of the wait. The process is woken up only by wakes sharing a bit with it.
+	bitset: u32,
+}
+
+/// The table of futexes wait queues.
+#[derive(Default)]
+pub struct FutexTable {
+	/// The wait queues, by futex key.
+	queues: HashMap<FutexKey, Vec<Waiter>>,
+	/// The key of the futex each waiting process is waiting on, by PID.
+	waiters: HashMap<Pid, FutexKey>,
+
+	/// The owner of each PI futex with waiters, by futex key.
+	pi_owners: HashMap<FutexKey, Pid>,
+	/// The priority processes had before inheriting one from the waiters of their PI futexes, by
+	/// PID.
+	base_priorities: HashMap<Pid, usize>,
+}
+
+impl FutexTable {
+	/// Makes the process with PID `pid` wait on the futex with key `key`.
+	///
+	/// `bitset` is the bitset of the wait.
+	///
+	/// The function does not make the process sleep.
+	pub fn enqueue(&mut self, key: FutexKey, pid: Pid, bitset: u32) -> AllocResult<()> {
+		let waiter = Waiter {
+			pid,
+			bitset,
+		};
+		match self.queues.get_mut(&key) {
+			Some(queue) => queue.push(waiter)?,
+			None => {
+				let mut queue = Vec::new();
+				queue.push(waiter)?;
+				self.queues.insert(key.clone(), queue)?;
+			}
+		}
+		if let Err(e) = self.waiters.insert(pid, key.clone()) {
+			self.remove_from(&key, pid);
+			return Err(e);
+		}
+		Ok(())
+	}
+
+	/// Tells whether the process with PID `pid` is waiting on a futex.
+	pub fn is_waiting(&self, pid: Pid) -> bool {
+		self.waiters.contains_key(&pid)
+	}
+
+	/// Tells whether processes are waiting on the futex with key `key`.
+	pub fn has_waiters(&self, key: &FutexKey) -> bool {
+		self.queues.contains_key(key)
+	}
+
+	/// Removes the process with PID `pid` from the wait queue it is in, if any.
+	pub fn dequeue(&mut self, pid: Pid) {
+		let Some(key) = self.waiters.remove(&pid) else {
+			return;
+		};
+		self.remove_from(&key, pid);
+		// The owner of a PI futex may not inherit the priority of the process anymore
+		if let Some(owner) = self.pi_owners.get(&key).cloned() {
+			if !self.has_waiters(&key) {
+				self.pi_owners.remove(&key);
+			}
+			self.update_pi_boost(owner);
+		}
+	}
+
+	/// Removes every reference to the process with PID `pid`, which is exiting.
+	pub fn remove_process(&mut self, pid: Pid) {
+		self.dequeue(pid);
+		self.pi_owners.retain(|_, owner| *owner != pid);
+		self.base_priorities.remove(&pid);
+	}
+
+	/// Removes the process with PID `pid` from the queue of the futex with key `key`.
+	fn remove_from(&mut self, key: &FutexKey, pid: Pid) {
+		let Some(queue) = self.queues.get_mut(key) else {
+			return;
+		};
+		queue.retain(|w| w.pid != pid);
+		if queue.is_empty() {
+			self.queues.remove(key);
+		}
+	}
+
+	/// Wakes at most `count` processes waiting on the futex with key `key`, in waiting order.
+	///
+	/// Only waiters whose bitset shares a bit with `bitset` are woken up.
+	///
+	/// The function returns the number of woken processes.
+	pub fn wake(&mut self, key: &FutexKey, count: usize, bitset: u32) -> usize {
+		let Some(queue) = self.queues.get_mut(key) else {
+			return 0;
+		};
+		let waiters = &mut self.waiters;
+		let mut woken = 0;
+		queue.retain(|w| {
+			if woken >= count || w.bitset & bitset == 0 {
+				return true;
+			}
+			woken += 1;
+			waiters.remove(&w.pid);
+			if let Some(proc_mutex) = Process::get_by_pid(w.pid) {
+				proc_mutex.lock().wake();
+			}
+			false
+		});
+		if queue.is_empty() {
+			self.queues.remove(key);
+		}
+		woken
+	}
+
+	/// Sets the process with PID `owner` as the owner of the PI futex with key `key`, making it
+	/// inherit the priority of the futex's waiters.
+	pub fn set_pi_owner(&mut self, key: FutexKey, owner: Pid) -> AllocResult<()> {
+		let prev = self.pi_owners.insert(key, owner)?;
+		if let Some(prev) = prev.filter(|prev| *prev != owner) {
+			self.update_pi_boost(prev);
+		}
+		self.update_pi_boost(owner);
+		Ok(())
+	}
+
+	/// Hands the PI futex with key `key` over to the first process waiting on it, and wakes it
+	/// up.
+	///
+	/// The function returns the PID of the new owner and whether other processes are still
+	/// waiting. If no process is waiting, the function returns `None`.
+	pub fn pi_handoff(&mut self, key: &FutexKey) -> Option<(Pid, bool)> {
+		let queue = self.queues.get_mut(key)?;
+		let waiter = queue.remove(0);
+		let waiters_left = !queue.is_empty();
+		if !waiters_left {
+			self.queues.remove(key);
+		}
+		self.waiters.remove(&waiter.pid);
+
+		let prev_owner = if waiters_left {
+			// Cannot fail since the key is already present
+			let prev = self
+				.pi_owners
+				.insert(key.clone(), waiter.pid)
+				.ok()
+				.flatten();
+			self.update_pi_boost(waiter.pid);
+			prev
+		} else {
+			self.pi_owners.remove(key)
+		};
+		if let Some(prev_owner) = prev_owner.filter(|prev| *prev != waiter.pid) {
+			self.update_pi_boost(prev_owner);
+		}
+
+		if let Some(proc_mutex) = Process::get_by_pid(waiter.pid) {
+			proc_mutex.lock().wake();
+		}
+		Some((waiter.pid, waiters_left))
+	}
+
+	/// Updates the priority of the process with PID `pid` according to the waiters of the PI
+	/// futexes it owns.
+	fn update_pi_boost(&mut self, pid: Pid) {
+		let Some(proc_mutex) = Process::get_by_pid(pid) else {
+			self.base_priorities.remove(&pid);
+			return;
+		};
+
+		// The highest priority among the waiters
+		let inherited = self
+			.pi_owners
+			.iter()
+			.filter(|(_, owner)| **owner == pid)
+			.filter_map(|(key, _)| self.queues.get(key))
+			.flat_map(|queue| queue.iter())
+			.filter_map(|w| Process::get_by_pid(w.pid))
+			.map(|proc_mutex| proc_mutex.lock().priority)
+			.max()
+			.unwrap_or(0);
+
+		let mut proc = proc_mutex.lock();
+		let old = proc.priority;
+		let base = self.base_priorities.get(&pid).cloned().unwrap_or(old);
+		let new = max(base, inherited);
+		if new != base {
+			// On allocation failure, the process does not inherit the priority
+			if self.base_priorities.insert(pid, base).is_err() {
+				return;
+			}
+		} else {
+			self.base_priorities.remove(&pid);
+		}
+		proc.priority = new;
+	}
+
+	/// Wakes at most `wake_count` processes waiting on the futex with key `from`, then moves at
+	/// most `requeue_count` of the remaining waiters to the futex with key `to`.
+	///
+	/// The function returns the number of woken processes and the number of requeued processes.
+	pub fn requeue(
+		&mut self,
+		from: &FutexKey,
+		to: &FutexKey,
+		wake_count: usize,
+		requeue_count: usize,
+	) -> AllocResult<(usize, usize)> {
+		let woken = self.wake(from, wake_count, FUTEX_BITSET_MATCH_ANY);
+		if from == to {
+			let remaining = self.queues.get(from).map(Vec::len).unwrap_or(0);
+			return Ok((woken, remaining.min(requeue_count)));
+		}
+
+		let mut requeued = 0;
+		while requeued < requeue_count {
+			let Some(queue) = self.queues.get_mut(from) else {
+				break;
+			};
+			let (pid, bitset) = (queue[0].pid, queue[0].bitset);
+			// Insert in the new queue first so that the waiter is not lost on allocation failure
+			self.enqueue(to.clone(), pid, bitset)?;
+			if let Some(queue) = self.queues.get_mut(from) {
+				queue.remove(0);
+				if queue.is_empty() {
+					self.queues.remove(from);
+				}
+			}
+			requeued += 1;
+		}
+		Ok((woken, requeued))
+	}
+}
+
+/// The futexes wait queues.
+pub static FUTEXES: Mutex<FutexTable> = Mutex::new(FutexTable {
+	queues: HashMap::new(),
+	waiters: HashMap::new(),
+
+	pi_owners: HashMap::new(),
+	base_priorities: HashMap::new(),
+});
+
+/// Clears the TID at `tidptr`, then wakes up a process waiting on it.
+///
+/// This allows threads to wait for the exit of a thread which has been created with
+/// `CLONE_CHILD_CLEARTID`.
+///
+/// `mem_space_mutex` is the memory space containing the TID. It must be bound.
+pub fn clear_tid(
+	mem_space_mutex: &Arc<IntMutex<MemSpace>>,
+	tidptr: SyscallPtr<i32>,
+) -> EResult<()> {
+	let mut mem_space = mem_space_mutex.lock();
+	{
+		let Some(mut tid) = tidptr.get_mut(&mut mem_space)? else {
+			return Ok(());
+		};
+		*tid = 0;
+	}
+
+	let key = FutexKey::new(
+		mem_space_mutex,
+		&mut mem_space,
+		tidptr.as_ptr() as *const u32,
+		false,
+	)?;
+	FUTEXES.lock().wake(&key, 1, FUTEX_BITSET_MATCH_ANY);
+	Ok(())
+}
+
+/// Releases the futex of the entry `entry` of a robust list, if it is held by the process with
+/// TID `tid`.
+///
+/// Arguments:
+/// - `mem_space_mutex` is the memory space containing the list.
+/// - `mem_space` is the locked memory space.
+/// - `futex_offset` is the offset of the futex relative to the entry.
+fn release_robust_futex(
+	mem_space_mutex: &Arc<IntMutex<MemSpace>>,
+	mem_space: &mut MemSpace,
+	entry: usize,
+	futex_offset: isize,
+	tid: Pid,
+) -> EResult<()> {
+	let addr = ((entry & !1) as isize).wrapping_add(futex_offset) as usize;
+	let ptr: SyscallPtr<u32> = addr.into();
+	let waiters = {
+		let Some(mut val) = ptr.get_mut(mem_space)? else {
+			return Ok(());
+		};
+		if *val & FUTEX_TID_MASK != tid as u32 {
+			return Ok(());
+		}
+		let waiters = *val & FUTEX_WAITERS != 0;
+		*val = (*val & FUTEX_WAITERS) | FUTEX_OWNER_DIED;
+		waiters
+	};
+
+	if waiters {
+		// A PI waiter takes the futex over, keeping `FUTEX_OWNER_DIED`
+		let key = FutexKey::new(mem_space_mutex, mem_space, ptr.as_ptr(), false)?;
+		FUTEXES.lock().wake(&key, 1, FUTEX_BITSET_MATCH_ANY);
+	}
+	Ok(())
+}
+
+/// Releases the futexes in the robust list of the process with TID `tid`, which is exiting.
+///
+/// Arguments:
+/// - `mem_space_mutex` is the memory space containing the list. It must be bound.
+/// - `head` is the head of the list.
+///
+/// Since the list is in userspace, it may be corrupted. In that case, the function stops
+/// walking it.
+pub fn exit_robust_list(
+	mem_space_mutex: &Arc<IntMutex<MemSpace>>,
+	head: SyscallPtr<RobustListHead>,
+	tid: Pid,
+) -> EResult<()> {
+	let mut mem_space = mem_space_mutex.lock();
+	let head_addr = head.as_ptr() as usize;
+	let Some(head) = head.get(&mem_space)?.map(|head| *head) else {
+		return Ok(());
+	};
+
+	let mut entry = head.list;
+	for _ in 0..ROBUST_LIST_LIMIT {
+		if entry == head_addr {
+			break;
+		}
+		// Get the next entry before the futex is released, since a waiter may then unlink it
+		let next_ptr: SyscallPtr<usize> = (entry & !1).into();
+		let next = *next_ptr.get(&mem_space)?.ok_or_else(|| errno!(EFAULT))?;
+		if entry != head.list_op_pending {
+			release_robust_futex(
+				mem_space_mutex,
+				&mut mem_space,
+				entry,
+				head.futex_offset,
+				tid,
+			)?;
+		}
+		entry = next;
+	}
+
+	if head.list_op_pending != 0 {
+		release_robust_futex(
+			mem_space_mutex,
+			&mut mem_space,
+			head.list_op_pending,
+			head.futex_offset,
+			tid,
+		)?;
+	}
+	Ok(())
+}
@@ -2,6 +2,9 @@
 //!
 //! This feature allows reducing the overhead linked to context switches.
 
+use crate::errno;
+use crate::errno::EResult;
+use core::ffi::c_int;
 use core::ffi::c_void;
 
 /// An entry of an IO vector used for sparse buffers IO.
@@ -15,3 +18,21 @@ pub struct IOVec {
 }
 
 // TODO add a function to turn into an entry into a SyscallSlice?
+
+/// Per-call flag of `preadv2`/`pwritev2`: High priority request, which may be polled.
+pub const RWF_HIPRI: c_int = 0x1;
+/// Per-call flag of `preadv2`/`pwritev2`: Synchronize the written data, like `O_DSYNC`.
+pub const RWF_DSYNC: c_int = 0x2;
+/// Per-call flag of `preadv2`/`pwritev2`: Synchronize the written data and metadata, like
+/// `O_SYNC`.
+pub const RWF_SYNC: c_int = 0x4;
+/// Per-call flag of `preadv2`/`pwritev2`: Do not block, like `O_NONBLOCK`.
+pub const RWF_NOWAIT: c_int = 0x8;
+
+/// Checks the per-call flags `flags` of `preadv2`/`pwritev2` are supported.
+pub fn check_rw_flags(flags: c_int) -> EResult<()> {
+	if flags & !(RWF_HIPRI | RWF_DSYNC | RWF_SYNC | RWF_NOWAIT) != 0 {
+		return Err(errno!(EOPNOTSUPP));
+	}
+	Ok(())
+}
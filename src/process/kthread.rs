@@ -0,0 +1,417 @@
+//! Kernel threads are tasks running only in kernelspace, without a memory space of their own.
+//!
+//! They are scheduled like other processes, which allows to run background work such as flushing
+//! dirty pages or processing deferred work. A kernel thread ignores signals and has no parent: it
+//! is removed from the scheduler once its function returns.
+//!
+//! A kernel thread cannot be interrupted at an arbitrary point. Instead, it has to check regularly
+//! whether it has been asked to park or to stop, using [`should_park`], [`park_me`] and
+//! [`should_stop`]. Typically, a kernel thread loops until [`should_stop`] returns `true`, calling
+//! [`sleep`] when it has no work to do.
+
+use super::cgroup;
+use super::get_scheduler;
+use super::pid::Pid;
+use super::pid::PidNamespace;
+use super::regs::Regs;
+use super::rlimit::RLimits;
+use super::rusage::CpuTimes;
+use super::rusage::RUsage;
+use super::scheduler;
+use super::seccomp::Seccomp;
+use super::signal;
+use super::signal::SignalHandler;
+use super::FsInfo;
+use super::Process;
+use super::State;
+use super::VForkState;
+use super::COMM_LEN;
+use super::DEFAULT_UMASK;
+use super::TLS_ENTRIES_COUNT;
+use super::ZOMBIE_THREADS;
+use crate::errno::EResult;
+use crate::file::mountpoint::MountNamespace;
+use crate::file::path::Path;
+use crate::file::perm::AccessProfile;
+use crate::gdt;
+use crate::memory;
+use crate::memory::buddy;
+use crate::memory::buddy::FrameOrder;
+use crate::process::oom;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::timer::TimerManager;
+use crate::time::unit::TimestampScale;
+use crate::tty;
+use crate::util::boxed::Box;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::vec::Vec;
+use crate::util::lock::IntMutex;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use core::ffi::c_void;
+use core::ptr::NonNull;
+use core::sync::atomic;
+use core::sync::atomic::AtomicBool;
+
+/// The order of the stack of a kernel thread, in pages.
+const STACK_ORDER: FrameOrder = 4;
+
+/// The state shared between a kernel thread and its handle.
+struct Control {
+	/// If set, the thread has to stop.
+	should_stop: AtomicBool,
+	/// If set, the thread has to park.
+	should_park: AtomicBool,
+	/// If set, the thread has been woken up while running, so it must not go to sleep.
+	woken: AtomicBool,
+	/// Tells whether the thread is parked.
+	parked: AtomicBool,
+	/// Tells whether the thread has exited.
+	exited: AtomicBool,
+
+	/// The PID of the process waiting for the thread to park or exit, if any.
+	waiter: Mutex<Option<Pid>>,
+}
+
+impl Control {
+	/// Wakes the process waiting for the thread, if any.
+	fn notify(&self) {
+		let waiter = *self.waiter.lock();
+		if let Some(proc_mutex) = waiter.and_then(Process::get_by_pid) {
+			proc_mutex.lock().wake();
+		}
+	}
+
+	/// Makes the current process sleep until `cond` returns `true`.
+	fn wait(&self, cond: impl Fn(&Self) -> bool) {
+		let proc_mutex = Process::current_assert();
+		*self.waiter.lock() = Some(proc_mutex.lock().pid);
+		loop {
+			{
+				let mut proc = proc_mutex.lock();
+				// The condition is checked while the process is locked so that a wakeup cannot be
+				// missed
+				if cond(self) {
+					break;
+				}
+				proc.set_state(State::Sleeping);
+			}
+			scheduler::end_tick();
+		}
+		*self.waiter.lock() = None;
+	}
+}
+
+/// The function run by a kernel thread.
+trait Entry {
+	/// Runs the function. If it has already run, the function does nothing.
+	fn run(&mut self);
+}
+
+impl<F: FnOnce()> Entry for Option<F> {
+	fn run(&mut self) {
+		if let Some(f) = self.take() {
+			f();
+		}
+	}
+}
+
+/// The state of a process specific to kernel threads.
+pub struct KThreadInfo {
+	/// The state shared with the thread's handle.
+	control: Arc<Control>,
+	/// The function to run. Taken when the thread starts.
+	entry: Option<Box<dyn Entry>>,
+	/// The beginning of the thread's stack.
+	stack: NonNull<c_void>,
+}
+
+impl Drop for KThreadInfo {
+	fn drop(&mut self) {
+		buddy::free_kernel(self.stack.as_ptr(), STACK_ORDER);
+	}
+}
+
+/// A handle to a kernel thread.
+pub struct KThread {
+	/// The thread's process.
+	proc: Arc<IntMutex<Process>>,
+	/// The state shared with the thread.
+	control: Arc<Control>,
+}
+
+impl KThread {
+	/// Returns the PID of the thread.
+	pub fn get_pid(&self) -> Pid {
+		self.proc.lock().pid
+	}
+
+	/// Wakes the thread up if it is sleeping with [`sleep`].
+	///
+	/// If the thread is running, its next call to [`sleep`] returns immediately.
+	pub fn wake(&self) {
+		self.control.woken.store(true, atomic::Ordering::Release);
+		self.proc.lock().wake();
+	}
+
+	/// Asks the thread to park, then waits until it is parked.
+	///
+	/// This function must be called from process context.
+	pub fn park(&self) {
+		self.control
+			.should_park
+			.store(true, atomic::Ordering::Release);
+		self.wake();
+		self.control.wait(|c| {
+			c.parked.load(atomic::Ordering::Acquire) || c.exited.load(atomic::Ordering::Acquire)
+		});
+	}
+
+	/// Resumes the thread if parked.
+	pub fn unpark(&self) {
+		self.control
+			.should_park
+			.store(false, atomic::Ordering::Release);
+		self.wake();
+	}
+
+	/// Asks the thread to stop, then waits until its function has returned.
+	///
+	/// This function must be called from process context.
+	pub fn stop(self) {
+		self.control
+			.should_stop
+			.store(true, atomic::Ordering::Release);
+		self.unpark();
+		self.control
+			.wait(|c| c.exited.load(atomic::Ordering::Acquire));
+	}
+}
+
+/// Returns the state shared with the handle of the current kernel thread.
+///
+/// If the current process is not a kernel thread, the function panics.
+fn current_control() -> Arc<Control> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+	let info = proc.kthread.as_ref().expect("not a kernel thread");
+	info.control.clone()
+}
+
+/// Tells whether the current kernel thread has been asked to stop.
+pub fn should_stop() -> bool {
+	current_control()
+		.should_stop
+		.load(atomic::Ordering::Acquire)
+}
+
+/// Tells whether the current kernel thread has been asked to park.
+pub fn should_park() -> bool {
+	current_control()
+		.should_park
+		.load(atomic::Ordering::Acquire)
+}
+
+/// If the current kernel thread has been asked to park, makes it sleep until it is unparked or
+/// asked to stop.
+pub fn park_me() {
+	let control = current_control();
+	let proc_mutex = Process::current_assert();
+	loop {
+		{
+			let mut proc = proc_mutex.lock();
+			if !control.should_park.load(atomic::Ordering::Acquire)
+				|| control.should_stop.load(atomic::Ordering::Acquire)
+			{
+				break;
+			}
+			control.parked.store(true, atomic::Ordering::Release);
+			proc.set_state(State::Sleeping);
+		}
+		control.notify();
+		scheduler::end_tick();
+	}
+	control.parked.store(false, atomic::Ordering::Release);
+}
+
+/// Makes the current kernel thread sleep until it is woken up with [`KThread::wake`], or asked
+/// to park or to stop.
+pub fn sleep() {
+	let control = current_control();
+	{
+		let proc_mutex = Process::current_assert();
+		let mut proc = proc_mutex.lock();
+		if control.woken.swap(false, atomic::Ordering::AcqRel)
+			|| control.should_park.load(atomic::Ordering::Acquire)
+			|| control.should_stop.load(atomic::Ordering::Acquire)
+		{
+			return;
+		}
+		proc.set_state(State::Sleeping);
+	}
+	scheduler::end_tick();
+	control.woken.store(false, atomic::Ordering::Release);
+}
+
+/// The first function executed by a kernel thread.
+///
+/// The function runs the thread's function, then exits the thread.
+extern "C" fn trampoline() -> ! {
+	let proc_mutex = Process::current_assert();
+	let (mut entry, control) = {
+		let mut proc = proc_mutex.lock();
+		let info = proc.kthread.as_mut().unwrap();
+		(info.entry.take().unwrap(), info.control.clone())
+	};
+	entry.run();
+	drop(entry);
+
+	{
+		let mut proc = proc_mutex.lock();
+		proc.set_state(State::Zombie);
+		// The thread has no parent to wait for it
+		oom::wrap(|| ZOMBIE_THREADS.lock().push(proc.pid));
+	}
+	control.exited.store(true, atomic::Ordering::Release);
+	control.notify();
+	drop(proc_mutex);
+
+	scheduler::end_tick();
+	unreachable!();
+}
+
+/// Creates a kernel thread named `name`, running the function `f`, and places it into the
+/// scheduler's queue.
+pub fn spawn<F: FnOnce() + 'static>(name: &[u8], f: F) -> EResult<KThread> {
+	let control = Arc::new(Control {
+		should_stop: AtomicBool::new(false),
+		should_park: AtomicBool::new(false),
+		woken: AtomicBool::new(false),
+		parked: AtomicBool::new(false),
+		exited: AtomicBool::new(false),
+
+		waiter: Mutex::new(None),
+	})?;
+	let entry = Box::new(Some(f))?;
+	let stack = buddy::alloc_kernel(STACK_ORDER)?;
+	let info = KThreadInfo {
+		control: control.clone(),
+		entry: Some(entry),
+		stack,
+	};
+	let stack_top = (stack.as_ptr() as usize) + (memory::PAGE_SIZE << STACK_ORDER);
+
+	// The return address of the trampoline, which never returns
+	let esp = stack_top - 16;
+	unsafe {
+		*(esp as *mut u32) = 0;
+	}
+	let regs = Regs {
+		esp: esp as _,
+		eip: trampoline as usize as _,
+		..Default::default()
+	};
+
+	let pid_ns = PidNamespace::get_root()?;
+	let pid = pid_ns.alloc()?;
+	let mut comm = [0; COMM_LEN];
+	let len = name.len().min(COMM_LEN - 1);
+	comm[..len].copy_from_slice(&name[..len]);
+	let process = Process {
+		pid,
+		pgid: 0,
+		sid: 0,
+		tid: pid,
+		tgid: pid,
+		threads: Arc::new(Mutex::new(crate::vec![pid]?))?,
+		pid_ns: pid_ns.clone(),
+		pid_ns_for_children: pid_ns,
+		mnt_ns: MountNamespace::get_root()?,
+
+		argv: Arc::new(Vec::new())?,
+		exec_path: Arc::new(Path::root())?,
+
+		tty: tty::get(None).unwrap(),
+
+		access_profile: AccessProfile::KERNEL,
+		personality: 0,
+		oom_score_adj: 0,
+		comm,
+		dumpable: false,
+		pdeath_signal: None,
+		no_new_privs: false,
+		child_subreaper: false,
+		seccomp: Seccomp::default(),
+
+		state: State::Running,
+		vfork_state: VForkState::None,
+
+		priority: scheduler::NICE_0_WEIGHT,
+		nice: 0,
+		cpu_affinity: scheduler::CPU_SET_ALL,
+		sched_policy: scheduler::SCHED_OTHER,
+		rt_priority: 0,
+		sched_entity: Default::default(),
+		cgroup: cgroup::get_root()?,
+
+		parent: None,
+		children: Vec::new(),
+
+		regs,
+		// The thread always runs in kernelspace
+		syscalling: true,
+		restart_block: None,
+
+		handled_signal: None,
+		saved_regs: Regs::default(),
+		saved_sigmask: 0,
+		waitable: false,
+
+		timer_manager: Arc::new(Mutex::new(TimerManager::new(pid)?))?,
+
+		mem_space: None,
+		aio_contexts: Arc::new(Mutex::new(HashMap::new()))?,
+		user_stack: None,
+		kernel_stack: Some(stack_top as _),
+
+		fs: Arc::new(Mutex::new(FsInfo {
+			cwd: Arc::new(Path::root())?,
+			chroot: Arc::new(Path::root())?,
+			umask: DEFAULT_UMASK,
+		}))?,
+		file_descriptors: None,
+
+		sigmask: 0,
+		sigwait: 0,
+		sigpending: 0,
+		sigqueue: Vec::new(),
+		signal_handlers: Arc::new(Mutex::new([SignalHandler::Ignore; signal::SIGNALS_COUNT]))?,
+
+		tls_entries: [gdt::Entry::default(); TLS_ENTRIES_COUNT],
+
+		set_child_tid: None,
+		clear_child_tid: None,
+		robust_list: None,
+
+		rusage: RUsage::default(),
+		cpu_times: CpuTimes::default(),
+		cpu_time_start: 0,
+		start_time: clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond)?,
+		acct_flags: 0,
+		rlimits: RLimits::default(),
+
+		exit_status: 0,
+		termsig: 0,
+		core_dumped: false,
+
+		kthread: Some(info),
+	};
+	process.cgroup.attach();
+
+	let proc = get_scheduler().lock().add_process(process)?;
+	Ok(KThread {
+		proc,
+		control,
+	})
+}
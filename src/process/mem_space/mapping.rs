@@ -4,9 +4,12 @@
 //! system calls.
 
 use super::gap::MemGap;
+use super::rmap;
 use super::MapResidence;
 use super::MemSpace;
-use crate::file::vfs;
+use super::Rss;
+use crate::cpu::smap;
+use crate::cpu::smap::UserAccess;
 use crate::memory;
 use crate::memory::buddy;
 use crate::memory::physical_ref_counter::PhysRefCounter;
@@ -14,10 +17,9 @@ use crate::memory::vmem;
 use crate::memory::vmem::VMem;
 use crate::process::oom;
 use crate::process::AllocResult;
-use crate::process::EResult;
-use crate::util::io::IO;
 use crate::util::lock::*;
 use crate::util::ptr::arc::Arc;
+use core::cmp::min;
 use core::ffi::c_void;
 use core::fmt;
 use core::num::NonZeroUsize;
@@ -73,6 +75,8 @@ pub struct MemMapping {
 
 	/// Pointer to the virtual memory context handler.
 	vmem: Arc<dyn VMem>,
+	/// The number of physical pages mapped in the memory space the mapping belongs to.
+	rss: Arc<Rss>,
 }
 
 impl MemMapping {
@@ -87,12 +91,14 @@ impl MemMapping {
 	/// - `file` is the open file the mapping points to, with an offset in it.
 	/// If `None`, the mapping doesn't point to any file.
 	/// - `vmem` is the virtual memory context handler associated with the mapping.
+	/// - `rss` is the counter of physical pages of the memory space the mapping belongs to.
 	pub fn new(
 		begin: *mut c_void,
 		size: NonZeroUsize,
 		flags: u8,
 		residence: MapResidence,
 		vmem: Arc<dyn VMem>,
+		rss: Arc<Rss>,
 	) -> Self {
 		debug_assert!(begin.is_aligned_to(memory::PAGE_SIZE));
 
@@ -104,6 +110,7 @@ impl MemMapping {
 			residence,
 
 			vmem,
+			rss,
 		}
 	}
 
@@ -122,6 +129,18 @@ impl MemMapping {
 		self.flags
 	}
 
+	/// Sets the mapping's flags.
+	///
+	/// The virtual memory context has to be updated after calling this function.
+	pub fn set_flags(&mut self, flags: u8) {
+		self.flags = flags;
+	}
+
+	/// Returns the residence of the mapping.
+	pub fn get_residence(&self) -> &MapResidence {
+		&self.residence
+	}
+
 	/// Returns a reference to the virtual memory context handler associated
 	/// with the mapping.
 	pub fn get_vmem(&self) -> &Arc<dyn VMem> {
@@ -163,11 +182,22 @@ impl MemMapping {
 		}
 	}
 
+	/// Tells whether writes to the mapping have to be tracked, to know which pages must be
+	/// written back to the file.
+	///
+	/// This is the case for shared mappings associated with a file.
+	pub fn is_write_tracked(&self) -> bool {
+		self.flags & super::MAPPING_FLAG_SHARED != 0
+			&& matches!(self.residence, MapResidence::File { .. })
+	}
+
 	/// Tells whether the page at offset `offset` is waiting for Copy-On-Write.
 	pub fn is_cow(&self, offset: usize) -> bool {
-		self.flags & super::MAPPING_FLAG_SHARED == 0
-			&& self.residence.is_normal()
-			&& self.is_shared(offset)
+		let residence_cow = matches!(
+			self.residence,
+			MapResidence::Normal | MapResidence::File { .. }
+		);
+		self.flags & super::MAPPING_FLAG_SHARED == 0 && residence_cow && self.is_shared(offset)
 	}
 
 	// TODO Move into architecture-specific code
@@ -179,7 +209,11 @@ impl MemMapping {
 	fn get_vmem_flags(&self, allocated: bool, offset: usize) -> u32 {
 		let mut flags = 0;
 
-		if self.flags & super::MAPPING_FLAG_WRITE != 0 && allocated && !self.is_cow(offset) {
+		// Pages of tracked mappings are given the write permission only once marked as dirty
+		if self.flags & super::MAPPING_FLAG_WRITE != 0
+			&& allocated && !self.is_cow(offset)
+			&& !self.is_write_tracked()
+		{
 			flags |= vmem::x86::FLAG_WRITE;
 		}
 		if self.flags & super::MAPPING_FLAG_USER != 0 {
@@ -205,13 +239,13 @@ impl MemMapping {
 			if self.is_cow(offset) {
 				let mut cow_buffer = crate::vec![0u8; memory::PAGE_SIZE]?;
 
-				unsafe {
+				smap::wrap(|| unsafe {
 					ptr::copy_nonoverlapping(
 						virt_ptr,
 						cow_buffer.as_mut_slice().as_mut_ptr() as _,
 						memory::PAGE_SIZE,
 					);
-				}
+				});
 
 				Some(cow_buffer)
 			} else {
@@ -220,28 +254,48 @@ impl MemMapping {
 		};
 
 		let prev_phys_ptr = self.get_physical_page(offset);
-		if self.residence.is_normal() && cow_buffer.is_none() && prev_phys_ptr.is_some() {
+		if cow_buffer.is_none() && prev_phys_ptr.is_some() {
 			return Ok(());
 		}
 
-		// Map new page
-		let new_phys_ptr = self.residence.alloc_page(offset)?;
+		// Map new page. A copy of a file's page is private to the mapping
+		let new_phys_ptr = if cow_buffer.is_some() && !self.residence.is_normal() {
+			MapResidence::alloc()?
+		} else {
+			self.residence.alloc_page(offset)?
+		};
+		let shared = self.flags & super::MAPPING_FLAG_SHARED != 0;
+		if let Err(errno) = rmap::add(
+			new_phys_ptr.as_ptr(),
+			&self.vmem,
+			virt_ptr,
+			&self.rss,
+			shared,
+		) {
+			self.residence.free_page(offset, new_phys_ptr.as_ptr());
+			return Err(errno);
+		}
 		let flags = self.get_vmem_flags(true, offset);
 		if let Err(errno) = self.vmem.map(new_phys_ptr.as_ptr(), virt_ptr, flags) {
+			rmap::remove(new_phys_ptr.as_ptr(), &self.vmem, virt_ptr);
 			self.residence.free_page(offset, new_phys_ptr.as_ptr());
 			return Err(errno);
 		}
 
 		// Free previous page
 		if let Some(prev_phys_ptr) = prev_phys_ptr {
+			rmap::remove(prev_phys_ptr, &self.vmem, virt_ptr);
 			self.residence.free_page(offset, prev_phys_ptr);
+		} else {
+			self.rss.add(1);
 		}
 
 		// Copying data if necessary
-		if self.residence.is_normal() {
+		if self.residence.is_normal() || cow_buffer.is_some() {
 			unsafe {
 				// FIXME: switching vmem at each call to `map` is suboptimal (try to batch)
 				vmem::switch(&*self.vmem, move || {
+					let _access = UserAccess::new();
 					vmem::write_lock_wrap(|| {
 						if let Some(buffer) = cow_buffer {
 							ptr::copy_nonoverlapping(
@@ -304,7 +358,9 @@ impl MemMapping {
 			if phys_ptr == get_default_page() {
 				return;
 			}
+			rmap::remove(phys_ptr, &self.vmem, virt_ptr);
 			self.residence.free_page(offset, phys_ptr);
+			self.rss.sub(1);
 		}
 	}
 
@@ -356,6 +412,7 @@ impl MemMapping {
 			residence: self.residence.clone(),
 
 			vmem: self.vmem.clone(),
+			rss: self.rss.clone(),
 		});
 
 		let gap = NonZeroUsize::new(size).map(|size| MemGap::new(begin_ptr, size));
@@ -382,6 +439,7 @@ impl MemMapping {
 						residence,
 
 						vmem: self.vmem.clone(),
+						rss: self.rss.clone(),
 					}
 				})
 		};
@@ -397,6 +455,110 @@ impl MemMapping {
 		(prev, gap, next)
 	}
 
+	/// Splits the current mapping into up to three mappings, without unmapping anything.
+	///
+	/// Arguments:
+	/// - `begin` is the index of the first page of the middle mapping.
+	/// - `size` is the number of pages of the middle mapping.
+	///
+	/// If the middle region is out of bounds, it is truncated to the end of the mapping.
+	///
+	/// The function returns the mapping before the middle region, the middle mapping and the
+	/// mapping after the middle region.
+	pub fn split(self, begin: usize, size: NonZeroUsize) -> (Option<Self>, Self, Option<Self>) {
+		debug_assert!(begin < self.size.get());
+		let size = NonZeroUsize::new(min(size.get(), self.size.get() - begin)).unwrap();
+
+		let new = |off: usize, size: NonZeroUsize| {
+			let mut residence = self.residence.clone();
+			residence.offset_add(off);
+
+			Self {
+				begin: unsafe { self.begin.add(off * memory::PAGE_SIZE) },
+				size,
+				flags: self.flags,
+
+				residence,
+
+				vmem: self.vmem.clone(),
+				rss: self.rss.clone(),
+			}
+		};
+
+		let prev = NonZeroUsize::new(begin).map(|size| new(0, size));
+		let middle = new(begin, size);
+		let end = begin + size.get();
+		let next = NonZeroUsize::new(self.size.get() - end).map(|size| new(end, size));
+
+		(prev, middle, next)
+	}
+
+	/// Discards the page at offset `offset` of the mapping, replacing it with the default page.
+	///
+	/// If the page is not shared, its physical memory is freed. The next access to the page
+	/// shall return zeros.
+	///
+	/// This function doesn't flush the virtual memory context.
+	pub fn discard_page(&mut self, offset: usize) -> AllocResult<()> {
+		let virt_ptr = (self.begin as usize + offset * memory::PAGE_SIZE) as *const c_void;
+
+		self.free_phys_page(offset);
+		let flags = self.get_vmem_flags(false, offset);
+		self.vmem.map(get_default_page(), virt_ptr, flags)
+	}
+
+	/// Removes the write permission on the page at offset `offset` in the virtual memory context,
+	/// so that the next write to it triggers a page fault.
+	///
+	/// The permission is given back on the next call to `update_vmem` for the page.
+	///
+	/// If the page is not allocated, the function does nothing.
+	pub fn write_protect_page(&mut self, offset: usize) {
+		let virt_ptr = (self.begin as usize + offset * memory::PAGE_SIZE) as *const c_void;
+
+		if let Some(phys_ptr) = self.get_physical_page(offset) {
+			let flags = self.get_vmem_flags(true, offset) & !vmem::x86::FLAG_WRITE;
+			// Cannot fail because the page for the vmem structure is already mapped
+			self.vmem.map(phys_ptr, virt_ptr, flags).unwrap();
+		}
+	}
+
+	/// Gives the write permission on the page at offset `offset` of a tracked mapping, once it
+	/// has been marked as dirty.
+	///
+	/// The permission is removed on the next call to `update_vmem` or `write_protect_page` for
+	/// the page.
+	///
+	/// If the mapping is not writable or if the page is not allocated, the function does
+	/// nothing.
+	pub fn allow_write(&mut self, offset: usize) {
+		if self.flags & super::MAPPING_FLAG_WRITE == 0 {
+			return;
+		}
+		let virt_ptr = (self.begin as usize + offset * memory::PAGE_SIZE) as *const c_void;
+
+		if let Some(phys_ptr) = self.get_physical_page(offset) {
+			let flags = self.get_vmem_flags(true, offset) | vmem::x86::FLAG_WRITE;
+			// Cannot fail because the page for the vmem structure is already mapped
+			self.vmem.map(phys_ptr, virt_ptr, flags).unwrap();
+		}
+	}
+
+	/// Allocates physical memory for every pages of the mapping that are not allocated yet.
+	///
+	/// Pages waiting for Copy-On-Write are left untouched.
+	///
+	/// This function doesn't flush the virtual memory context.
+	pub fn populate(&mut self) -> AllocResult<()> {
+		for i in 0..self.size.get() {
+			if self.get_physical_page(i).is_none() {
+				self.map(i)?;
+			}
+		}
+
+		Ok(())
+	}
+
 	/// Updates the virtual memory context according to the mapping for the page
 	/// at offset `offset`.
 	pub fn update_vmem(&mut self, offset: usize) {
@@ -413,10 +575,14 @@ impl MemMapping {
 	/// After a fork operation failed, frees the pages that were already
 	/// allocated.
 	///
-	/// `n` is the number of pages to free from the beginning.
-	fn fork_fail_clean(&self, ref_counter: &mut PhysRefCounter, n: usize) {
+	/// Arguments:
+	/// - `vmem` is the virtual memory context of the new mapping
+	/// - `n` is the number of pages to free from the beginning
+	fn fork_fail_clean(&self, ref_counter: &mut PhysRefCounter, vmem: &Arc<dyn VMem>, n: usize) {
 		for i in 0..n {
 			if let Some(phys_ptr) = self.get_physical_page(i) {
+				let virt_ptr = unsafe { self.begin.add(i * memory::PAGE_SIZE) };
+				rmap::remove(phys_ptr, vmem, virt_ptr);
 				ref_counter.decrement(phys_ptr);
 			}
 		}
@@ -440,6 +606,7 @@ impl MemMapping {
 			residence: self.residence.clone(),
 
 			vmem: mem_space.vmem.clone(),
+			rss: mem_space.rss.clone(),
 		};
 		let nolazy = (new_mapping.get_flags() & super::MAPPING_FLAG_NOLAZY) != 0;
 
@@ -453,67 +620,37 @@ impl MemMapping {
 		} else {
 			let mut ref_counter = super::PHYSICAL_REF_COUNTER.lock();
 
+			let mut pages = 0;
 			for i in 0..self.size.get() {
 				if let Some(phys_ptr) = self.get_physical_page(i) {
 					if let Err(errno) = ref_counter.increment(phys_ptr) {
-						self.fork_fail_clean(&mut ref_counter, i);
+						self.fork_fail_clean(&mut ref_counter, &new_mapping.vmem, i);
 						return Err(errno);
 					}
+					let virt_ptr = unsafe { self.begin.add(i * memory::PAGE_SIZE) };
+					let shared = self.flags & super::MAPPING_FLAG_SHARED != 0;
+					if let Err(errno) = rmap::add(
+						phys_ptr,
+						&new_mapping.vmem,
+						virt_ptr,
+						&new_mapping.rss,
+						shared,
+					) {
+						ref_counter.decrement(phys_ptr);
+						self.fork_fail_clean(&mut ref_counter, &new_mapping.vmem, i);
+						return Err(errno);
+					}
+					pages += 1;
 				}
 			}
+			// Shared pages are accounted for in both memory spaces
+			new_mapping.rss.add(pages);
 		}
 
 		mem_space
 			.mappings
 			.insert(new_mapping.get_begin(), new_mapping)
 	}
-
-	/// Synchronizes the data on the memory mapping back to the filesystem.
-	///
-	/// The function does nothing if:
-	/// - The mapping is not shared
-	/// - The mapping is not associated with a file
-	/// - The associated file has been removed or cannot be accessed
-	///
-	/// If the mapping is lock, the function returns [`crate::errno::EBUSY`].
-	pub fn fs_sync(&self) -> EResult<()> {
-		if self.flags & super::MAPPING_FLAG_SHARED == 0 {
-			return Ok(());
-		}
-		// TODO if locked, EBUSY
-
-		let MapResidence::File {
-			location,
-			off,
-		} = &self.residence
-		else {
-			return Ok(());
-		};
-		let Ok(file_mutex) = vfs::get_file_by_location(location) else {
-			return Ok(());
-		};
-
-		unsafe {
-			vmem::switch(&*self.vmem, || {
-				let mut file = file_mutex.lock();
-
-				// TODO Make use of dirty flag if present on the current architecure to update
-				// only pages that have been modified
-				let slice = slice::from_raw_parts(
-					self.begin as *mut u8,
-					self.size.get() * memory::PAGE_SIZE,
-				);
-
-				let mut i = 0;
-				while i < slice.len() {
-					let l = file.write(*off, &slice[i..])?;
-					i += l as usize;
-				}
-
-				Ok(())
-			})
-		}
-	}
 }
 
 impl fmt::Debug for MemMapping {
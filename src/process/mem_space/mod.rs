@@ -8,28 +8,42 @@
 mod gap;
 mod mapping;
 pub mod ptr;
+pub mod rmap;
 
+use crate::cpu::smap::UserAccess;
+use crate::crypto::rand;
 use crate::errno::AllocError;
+use crate::errno::EResult;
 use crate::errno::Errno;
+use crate::file::buffer::memfd;
+use crate::file::page_cache;
 use crate::file::perm::AccessProfile;
+use crate::file::vfs;
+use crate::file::writeback;
 use crate::file::FileLocation;
 use crate::idt;
 use crate::memory;
 use crate::memory::buddy;
 use crate::memory::physical_ref_counter::PhysRefCounter;
 use crate::memory::stack;
+use crate::memory::stats;
 use crate::memory::vmem;
 use crate::memory::vmem::VMem;
+use crate::process::cgroup;
+use crate::process::cgroup::CGroup;
 use crate::process::oom;
 use crate::process::open_file::OpenFile;
+use crate::process::rlimit::RLim;
 use crate::process::AllocResult;
 use crate::util;
+use crate::util::container::hashmap::HashMap;
 use crate::util::container::map::Map;
 use crate::util::container::vec::Vec;
 use crate::util::lock::Mutex;
 use crate::util::math;
 use crate::util::ptr::arc::Arc;
 use crate::util::TryClone;
+use core::cmp::max;
 use core::cmp::min;
 use core::cmp::Ordering;
 use core::ffi::c_void;
@@ -38,6 +52,10 @@ use core::mem::size_of;
 use core::num::NonZeroUsize;
 use core::ptr::null_mut;
 use core::ptr::NonNull;
+use core::sync::atomic;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::AtomicUsize;
 use gap::MemGap;
 use mapping::MemMapping;
 
@@ -56,6 +74,37 @@ pub const MAPPING_FLAG_NOLAZY: u8 = 0b01000;
 /// If the mapping is associated with a file, modifications made to the mapping are update to the
 /// file.
 pub const MAPPING_FLAG_SHARED: u8 = 0b10000;
+/// Flag telling that a memory mapping is locked into memory, which means its pages must never
+/// be discarded nor swapped out.
+pub const MAPPING_FLAG_LOCKED: u8 = 0b100000;
+
+/// Tells whether userspace is allowed to create mappings that are both writable and executable.
+///
+/// This is enforced by [`check_write_exec`] and can be enabled with the `-allow-wx` command line
+/// argument.
+pub static ALLOW_WRITE_EXEC: AtomicBool = AtomicBool::new(false);
+
+/// Checks the mapping flags `flags` against the W^X (write xor execute) policy.
+///
+/// If the flags make a mapping both writable and executable and this is not allowed, the
+/// function returns [`crate::errno::EACCES`].
+pub fn check_write_exec(flags: u8) -> EResult<()> {
+	let wx = MAPPING_FLAG_WRITE | MAPPING_FLAG_EXEC;
+	if flags & wx == wx && !ALLOW_WRITE_EXEC.load(atomic::Ordering::Relaxed) {
+		return Err(errno!(EACCES));
+	}
+	Ok(())
+}
+
+/// The level of randomization of the userspace memory layout, exposed in
+/// `/proc/sys/kernel/randomize_va_space`:
+/// - `0`: no randomization
+/// - `1`: the stack, the mmap base and the program interpreter are randomized
+/// - `2`: same as `1`, plus the `brk` base
+pub static RANDOMIZE_VA_SPACE: AtomicU32 = AtomicU32::new(2);
+
+/// The number of bits of randomness of the mmap base, in pages.
+const MMAP_RND_BITS: u32 = 8;
 
 /// The physical pages reference counter.
 pub static PHYSICAL_REF_COUNTER: Mutex<PhysRefCounter> = Mutex::new(PhysRefCounter::new());
@@ -160,11 +209,12 @@ impl MapResidence {
 			}
 
 			MapResidence::File {
-				location: _,
-				off: _,
+				location,
+				off: file_off,
 			} => {
-				// TODO get physical page for this offset
-				todo!();
+				let off = *file_off / memory::PAGE_SIZE as u64 + off as u64;
+				// TODO Forward the error instead of reporting an allocation failure
+				vfs::map_file(location, off).map_err(|_| AllocError)
 			}
 
 			MapResidence::Swap {
@@ -190,11 +240,13 @@ impl MapResidence {
 			}
 
 			MapResidence::File {
-				location: _,
-				off: _,
+				location,
+				off: file_off,
 			} => {
-				// TODO
-				todo!();
+				// The page may be either the file's page or a private copy of it
+				Self::free(ptr);
+				let off = *file_off / memory::PAGE_SIZE as u64 + off as u64;
+				vfs::unmap_file(location, off, ptr);
 			}
 
 			MapResidence::Swap {
@@ -225,6 +277,59 @@ pub enum MapConstraint {
 	None,
 }
 
+/// The counter of physical pages mapped in a memory space (Resident Set Size).
+///
+/// The pages are charged to a control group.
+pub struct Rss {
+	/// The number of pages.
+	pages: AtomicUsize,
+	/// The control group the pages are charged to.
+	cgroup: Mutex<Arc<CGroup>>,
+}
+
+impl Rss {
+	/// Creates a new counter, charging the control group `cgroup`.
+	pub fn new(cgroup: Arc<CGroup>) -> Self {
+		Self {
+			pages: AtomicUsize::new(0),
+			cgroup: Mutex::new(cgroup),
+		}
+	}
+
+	/// Returns the number of pages.
+	pub fn get(&self) -> usize {
+		self.pages.load(atomic::Ordering::Relaxed)
+	}
+
+	/// Adds `pages` pages to the counter.
+	pub fn add(&self, pages: usize) {
+		let cgroup = self.cgroup.lock();
+		self.pages.fetch_add(pages, atomic::Ordering::Relaxed);
+		cgroup.charge_memory(pages);
+	}
+
+	/// Subtracts `pages` pages from the counter.
+	pub fn sub(&self, pages: usize) {
+		let cgroup = self.cgroup.lock();
+		self.pages.fetch_sub(pages, atomic::Ordering::Relaxed);
+		cgroup.uncharge_memory(pages);
+	}
+
+	/// Returns the control group the pages are charged to.
+	pub fn get_cgroup(&self) -> Arc<CGroup> {
+		self.cgroup.lock().clone()
+	}
+
+	/// Moves the charge of the pages to the control group `cgroup`.
+	pub fn set_cgroup(&self, cgroup: Arc<CGroup>) {
+		let mut cur = self.cgroup.lock();
+		let pages = self.get();
+		cur.uncharge_memory(pages);
+		cgroup.charge_memory(pages);
+		*cur = cgroup;
+	}
+}
+
 /// Structure representing the virtual memory space of a context.
 pub struct MemSpace {
 	/// Binary tree storing the list of memory gaps, ready for new mappings.
@@ -240,14 +345,44 @@ pub struct MemSpace {
 	/// Sorted by pointer to the beginning of the mapping on the virtual memory.
 	mappings: Map<*mut c_void, MemMapping>,
 
+	/// The set of pages that have been marked as lazily freeable with `MADV_FREE`.
+	///
+	/// Those pages are write-protected so that a write to one of them removes it from the set.
+	/// Pages remaining in the set can be discarded when the system needs to reclaim memory.
+	lazy_free: Map<*mut c_void, ()>,
+	/// The set of pages of shared file mappings that have been written since they were last
+	/// synchronized with `msync`.
+	///
+	/// Clean pages of those mappings are write-protected so that the first write to one of them
+	/// adds it to the set.
+	dirty: Map<*mut c_void, ()>,
+
 	/// The number of used virtual memory pages.
 	vmem_usage: usize,
+	/// The number of locked virtual memory pages.
+	locked_pages: usize,
+	/// The number of physical pages mapped in the memory space (Resident Set Size).
+	///
+	/// The counter is shared with the mappings, which update it.
+	rss: Arc<Rss>,
+	/// If `Some`, mappings created from now on are locked. The inner value tells whether
+	/// physical pages are allocated only when accessed.
+	lock_future: Option<bool>,
 
 	/// The initial pointer of the `brk` system call.
 	brk_init: *mut c_void,
 	/// The current pointer of the `brk` system call.
 	brk_ptr: *mut c_void,
 
+	/// The maximum number of virtual memory pages of userspace mappings (`RLIMIT_AS`).
+	as_limit: usize,
+	/// The maximum number of locked virtual memory pages (`RLIMIT_MEMLOCK`).
+	memlock_limit: usize,
+	/// A pointer to the top of the user stack. If null, the stack's growth is not limited.
+	stack_top: *mut c_void,
+	/// The maximum size of the user stack in bytes (`RLIMIT_STACK`).
+	stack_limit: usize,
+
 	/// The virtual memory context handler.
 	vmem: Arc<dyn VMem>,
 }
@@ -340,12 +475,22 @@ impl MemSpace {
 			gaps_size: Map::new(),
 
 			mappings: Map::new(),
+			lazy_free: Map::new(),
+			dirty: Map::new(),
 
 			vmem_usage: 0,
+			locked_pages: 0,
+			rss: Arc::new(Rss::new(cgroup::get_root()?))?,
+			lock_future: None,
 
 			brk_init: null_mut::<_>(),
 			brk_ptr: null_mut::<_>(),
 
+			as_limit: usize::MAX,
+			memlock_limit: usize::MAX,
+			stack_top: null_mut::<_>(),
+			stack_limit: usize::MAX,
+
 			vmem: Arc::try_from(vmem::new()?)?,
 		};
 
@@ -357,6 +502,20 @@ impl MemSpace {
 		Ok(s)
 	}
 
+	/// Randomizes the beginning of the region in which mappings without constraint are placed.
+	///
+	/// This function must be called before any mapping is created.
+	pub fn randomize_mmap_base(&mut self) -> AllocResult<()> {
+		let off = rand::get_random_u32() as usize & ((1 << MMAP_RND_BITS) - 1);
+		let Some(gap) = self.gap_remove(memory::ALLOC_BEGIN) else {
+			return Ok(());
+		};
+		// Since the gap spans most of the userspace, it is always large enough
+		let begin = unsafe { gap.get_begin().add(off * memory::PAGE_SIZE) };
+		let size = NonZeroUsize::new(gap.get_size().get() - off).unwrap();
+		self.gap_insert(MemGap::new(begin, size))
+	}
+
 	/// Returns a mutable reference to the virtual memory context.
 	pub fn get_vmem(&self) -> &Arc<dyn VMem> {
 		&self.vmem
@@ -367,6 +526,50 @@ impl MemSpace {
 		self.vmem_usage
 	}
 
+	/// Sets the limits of the memory space.
+	///
+	/// Arguments:
+	/// - `as_limit` is the maximum size of the memory space in bytes (`RLIMIT_AS`)
+	/// - `stack_limit` is the maximum size of the user stack in bytes (`RLIMIT_STACK`)
+	/// - `memlock_limit` is the maximum size of the locked memory in bytes (`RLIMIT_MEMLOCK`)
+	///
+	/// Existing mappings are kept even if they exceed the new limits.
+	pub fn set_limits(&mut self, as_limit: RLim, stack_limit: RLim, memlock_limit: RLim) {
+		let as_limit = usize::try_from(as_limit).unwrap_or(usize::MAX);
+		self.as_limit = as_limit / memory::PAGE_SIZE;
+		self.stack_limit = usize::try_from(stack_limit).unwrap_or(usize::MAX);
+		let memlock_limit = usize::try_from(memlock_limit).unwrap_or(usize::MAX);
+		self.memlock_limit = memlock_limit / memory::PAGE_SIZE;
+	}
+
+	/// Sets the pointer to the top of the user stack, whose growth is then restricted by the
+	/// stack limit.
+	pub fn set_stack_top(&mut self, stack_top: *mut c_void) {
+		self.stack_top = stack_top;
+	}
+
+	/// Returns the number of physical memory pages mapped in the memory space (Resident Set
+	/// Size).
+	pub fn get_rss(&self) -> usize {
+		self.rss.get()
+	}
+
+	/// Returns the control group the physical memory of the memory space is charged to.
+	pub fn get_cgroup(&self) -> Arc<CGroup> {
+		self.rss.get_cgroup()
+	}
+
+	/// Charges the physical memory of the memory space to the control group `cgroup` instead of
+	/// the current one.
+	pub fn set_cgroup(&self, cgroup: Arc<CGroup>) {
+		self.rss.set_cgroup(cgroup);
+	}
+
+	/// Returns an iterator over the memory mappings, sorted by address.
+	pub fn iter_mappings(&self) -> impl Iterator<Item = &MemMapping> {
+		self.mappings.iter().map(|(_, m)| m)
+	}
+
 	// TODO Fix potential invalid state on fail
 	/// Maps a chunk of memory.
 	///
@@ -383,19 +586,23 @@ impl MemSpace {
 	///
 	/// On success, the function returns a pointer to the newly mapped virtual memory.
 	///
-	/// If the given pointer is not page-aligned, the function returns an error.
+	/// If the given pointer is not page-aligned or if a userspace mapping would make the memory
+	/// space exceed its size limit, the function returns an error.
+	///
+	/// If mappings created from now on are locked and the mapping would exceed the locked memory
+	/// limit, the function returns [`crate::errno::EAGAIN`].
 	pub fn map(
 		&mut self,
 		map_constraint: MapConstraint,
 		size: NonZeroUsize,
 		flags: u8,
 		residence: MapResidence,
-	) -> AllocResult<*mut c_void> {
+	) -> EResult<*mut c_void> {
 		// Checking arguments are valid
 		match map_constraint {
 			MapConstraint::Fixed(ptr) | MapConstraint::Hint(ptr) => {
 				if !ptr.is_aligned_to(memory::PAGE_SIZE) {
-					return Err(AllocError);
+					return Err(errno!(ENOMEM));
 				}
 			}
 
@@ -438,15 +645,43 @@ impl MemSpace {
 				(Some(gap), gap.get_begin())
 			}
 		};
+		if flags & MAPPING_FLAG_USER != 0 && self.vmem_usage + size.get() > self.as_limit {
+			return Err(errno!(ENOMEM));
+		}
+
+		if self.lock_future.is_some()
+			&& self.locked_pages.saturating_add(size.get()) > self.memlock_limit
+		{
+			return Err(errno!(EAGAIN));
+		}
 
 		// Creating the mapping
-		let mapping = MemMapping::new(addr, size, flags, residence, self.vmem.clone());
+		let flags = match self.lock_future {
+			Some(_) => flags | MAPPING_FLAG_LOCKED,
+			None => flags,
+		};
+		let mapping = MemMapping::new(
+			addr,
+			size,
+			flags,
+			residence,
+			self.vmem.clone(),
+			self.rss.clone(),
+		);
 		let m = self.mappings.insert(addr, mapping)?;
 
 		// Mapping default pages
 		if let Err(e) = m.map_default() {
 			self.mappings.remove(&addr);
-			return Err(e);
+			return Err(e.into());
+		}
+		// Locked mappings are populated right away, unless told otherwise
+		if self.lock_future == Some(false) {
+			if let Err(e) = m.populate() {
+				m.unmap()?;
+				self.mappings.remove(&addr);
+				return Err(e.into());
+			}
 		}
 
 		// Splitting the old gap to fit the mapping if needed
@@ -468,12 +703,15 @@ impl MemSpace {
 		}
 
 		self.vmem_usage += size.get();
+		if flags & MAPPING_FLAG_LOCKED != 0 {
+			self.locked_pages += size.get();
+		}
 		Ok(addr)
 	}
 
 	/// Same as `map`, except the function returns a pointer to the end of the
 	/// memory mapping.
-	pub fn map_stack(&mut self, size: NonZeroUsize, flags: u8) -> AllocResult<*mut c_void> {
+	pub fn map_stack(&mut self, size: NonZeroUsize, flags: u8) -> EResult<*mut c_void> {
 		let mapping_ptr = self.map(MapConstraint::None, size, flags, MapResidence::Normal)?;
 		Ok(unsafe {
 			// Safe because the new pointer stays in the range of the allocated mapping
@@ -559,6 +797,10 @@ impl MemSpace {
 		if !ptr.is_aligned_to(memory::PAGE_SIZE) {
 			return Err(AllocError);
 		}
+		self.lazy_free_remove(ptr, size.get())
+			.map_err(|_| AllocError)?;
+		// Unmapped pages of files are written back by the page cache
+		self.dirty_remove(ptr, size.get()).map_err(|_| AllocError)?;
 
 		// Removing every mappings in the chunk to unmap
 		let mut i = 0;
@@ -579,6 +821,9 @@ impl MemSpace {
 			let begin = (page_ptr as usize - mapping_ptr as usize) / memory::PAGE_SIZE;
 			// The number of pages to unmap in the mapping
 			let pages = min(size.get() - i, mapping.get_size().get() - begin);
+			if mapping.get_flags() & MAPPING_FLAG_LOCKED != 0 {
+				self.locked_pages -= pages;
+			}
 
 			// Newly created mappings and gap after removing parts of the previous one
 			let (prev, gap, next) = mapping.partial_unmap(begin, pages);
@@ -691,6 +936,8 @@ impl MemSpace {
 
 		unsafe {
 			vmem::switch(self.vmem.as_ref(), move || {
+				let _access = UserAccess::new();
+
 				let mut i = 0;
 				'outer: loop {
 					// Safe because not dereferenced before checking if accessible
@@ -749,16 +996,28 @@ impl MemSpace {
 			gaps_size: self.gaps_size_clone()?,
 
 			mappings: Map::new(),
+			lazy_free: Map::new(),
+			dirty: Map::new(),
 
 			vmem_usage: self.vmem_usage,
+			// Memory locks are not inherited by the child
+			locked_pages: 0,
+			rss: Arc::new(Rss::new(self.rss.get_cgroup()))?,
+			lock_future: None,
 
 			brk_init: self.brk_init,
 			brk_ptr: self.brk_ptr,
 
+			as_limit: self.as_limit,
+			memlock_limit: self.memlock_limit,
+			stack_top: self.stack_top,
+			stack_limit: self.stack_limit,
+
 			vmem: Arc::try_from(vmem::try_clone(&*self.vmem)?)?,
 		};
 		for (_, m) in self.mappings.iter_mut() {
 			let new_mapping = m.fork(&mut mem_space)?;
+			new_mapping.set_flags(new_mapping.get_flags() & !MAPPING_FLAG_LOCKED);
 
 			for i in 0..new_mapping.get_size().get() {
 				m.update_vmem(i);
@@ -771,6 +1030,9 @@ impl MemSpace {
 
 	/// Clones the current memory space for process forking.
 	pub fn fork(&mut self) -> AllocResult<MemSpace> {
+		// Forking gives write permissions back on every pages, which would make lazily freed
+		// pages unnoticed when written
+		self.lazy_free = Map::new();
 		idt::wrap_disable_interrupts(|| unsafe { stack::switch(None, || self.do_fork()) })?
 	}
 
@@ -801,29 +1063,563 @@ impl MemSpace {
 		Ok(())
 	}
 
+	/// Returns the end address of the range beginning at `addr` with size `pages` pages.
+	///
+	/// If the end address overflows, the function returns [`crate::errno::ENOMEM`].
+	fn range_end(addr: *const c_void, pages: usize) -> EResult<usize> {
+		pages
+			.checked_mul(memory::PAGE_SIZE)
+			.and_then(|size| (addr as usize).checked_add(size))
+			.ok_or_else(|| errno!(ENOMEM))
+	}
+
+	/// Checks that every pages in the range beginning at `addr` with size `pages` pages are
+	/// mapped, then calls `f` on every mapping in the range.
+	///
+	/// If a page is not mapped, the function returns [`crate::errno::ENOMEM`] without calling
+	/// `f`.
+	fn check_range<F: FnMut(&MemMapping) -> EResult<()>>(
+		&self,
+		addr: *const c_void,
+		pages: usize,
+		mut f: F,
+	) -> EResult<()> {
+		if Self::range_end(addr, pages)? > memory::PROCESS_END as usize {
+			return Err(errno!(ENOMEM));
+		}
+
+		// Checking the whole range before calling the closure
+		let mut i = 0;
+		while i < pages {
+			let page_ptr = (addr as usize + i * memory::PAGE_SIZE) as *const c_void;
+			let mapping =
+				Self::get_mapping_for_(&self.mappings, page_ptr).ok_or_else(|| errno!(ENOMEM))?;
+			let begin = (page_ptr as usize - mapping.get_begin() as usize) / memory::PAGE_SIZE;
+			i += mapping.get_size().get() - begin;
+		}
+
+		let mut i = 0;
+		while i < pages {
+			let page_ptr = (addr as usize + i * memory::PAGE_SIZE) as *const c_void;
+			let mapping = Self::get_mapping_for_(&self.mappings, page_ptr).unwrap();
+			f(mapping)?;
+			let begin = (page_ptr as usize - mapping.get_begin() as usize) / memory::PAGE_SIZE;
+			i += mapping.get_size().get() - begin;
+		}
+
+		Ok(())
+	}
+
+	/// Calls the given closure `f` on the mappings covering the range beginning at `addr` with
+	/// size `pages` pages.
+	///
+	/// Mappings that are only partially covered by the range are split beforehand so that the
+	/// closure only affects pages in the range. Once the closure returns, the virtual memory
+	/// context is updated according to the mapping.
+	///
+	/// The range must have been checked with `check_range` beforehand.
+	///
+	/// If the closure returns an error, the function stops and returns it. Mappings that have
+	/// been processed so far remain modified.
+	fn update_range<F: FnMut(&mut MemMapping) -> EResult<()>>(
+		&mut self,
+		addr: *const c_void,
+		pages: usize,
+		mut f: F,
+	) -> EResult<()> {
+		let mut res = Ok(());
+
+		let mut i = 0;
+		while i < pages {
+			let page_ptr = (addr as usize + i * memory::PAGE_SIZE) as *const c_void;
+			// Cannot fail since the range has been checked
+			let mapping_ptr = Self::get_mapping_for_(&self.mappings, page_ptr)
+				.unwrap()
+				.get_begin();
+			let mapping = self.mappings.remove(&mapping_ptr).unwrap();
+
+			// The offset in the mapping of the beginning of pages to update
+			let begin = (page_ptr as usize - mapping_ptr as usize) / memory::PAGE_SIZE;
+			// The number of pages to update in the mapping
+			let size = min(pages - i, mapping.get_size().get() - begin);
+
+			let (prev, mut middle, next) = mapping.split(begin, NonZeroUsize::new(size).unwrap());
+			res = f(&mut middle);
+			for j in 0..size {
+				middle.update_vmem(j);
+			}
+
+			// Inserting mappings back. Since a mapping has just been removed, there is enough
+			// room for at least one
+			for m in [prev, Some(middle), next].into_iter().flatten() {
+				oom::wrap(|| {
+					self.mappings.insert(m.get_begin(), m.clone())?;
+					Ok(())
+				});
+			}
+
+			if res.is_err() {
+				break;
+			}
+			i += size;
+		}
+
+		self.vmem.flush();
+		res
+	}
+
 	/// Sets protection for the given range of memory.
 	///
 	/// Arguments:
 	/// - `addr` is the address to the beginning of the range to be set
 	/// - `len` is the length of the range in bytes
-	/// - `prot` is a set of mapping flags
+	/// - `prot` is a set of mapping flags. Only `MAPPING_FLAG_WRITE` and `MAPPING_FLAG_EXEC` are
+	/// taken into account
 	/// - `access_profile` is the access profile to check permissions
 	///
-	/// If a mapping to be modified is associated with a file, and the file doesn't have the
-	/// matching permissions, the function returns an error.
+	/// Mappings that are only partially covered by the range are split so that only the pages in
+	/// the range are affected.
+	///
+	/// If a page in the range is not mapped, the function returns [`crate::errno::ENOMEM`] and
+	/// nothing is modified.
+	///
+	/// If a mapping to be modified is shared and associated with a file, and the file doesn't
+	/// have the matching permissions, the function returns [`crate::errno::EACCES`].
+	///
+	/// If write access is requested on a shared mapping of an anonymous file whose seals forbid
+	/// writing, the function returns [`crate::errno::EPERM`].
+	///
+	/// The new protection is also checked against the W^X policy (see [`check_write_exec`]).
 	pub fn set_prot(
 		&mut self,
-		_addr: *mut c_void,
-		_len: usize,
-		_prot: u8,
-		_access_profile: &AccessProfile,
+		addr: *mut c_void,
+		len: usize,
+		prot: u8,
+		access_profile: &AccessProfile,
 	) -> Result<(), Errno> {
-		// TODO Iterate on mappings in the range:
-		//		If the mapping is shared and associated to a file, check file permissions match
-		// `prot` (only write)
-		//		Split the mapping if needed
-		//		Set permissions
-		//		Update vmem
+		let prot = prot & (MAPPING_FLAG_WRITE | MAPPING_FLAG_EXEC);
+		check_write_exec(prot)?;
+		let pages = math::ceil_div(len, memory::PAGE_SIZE);
+
+		self.check_range(addr, pages, |mapping| {
+			let shared = mapping.get_flags() & MAPPING_FLAG_SHARED != 0;
+			if !shared || prot & MAPPING_FLAG_WRITE == 0 {
+				return Ok(());
+			}
+			if let MapResidence::File {
+				location, ..
+			} = mapping.get_residence()
+			{
+				// Sealed anonymous files cannot be modified. With `F_SEAL_FUTURE_WRITE`, mappings
+				// that were already writable when the seal was added keep writing
+				let seals = memfd::get_seals(location).unwrap_or(0);
+				let writable = mapping.get_flags() & MAPPING_FLAG_WRITE != 0;
+				if seals & memfd::F_SEAL_WRITE != 0
+					|| (seals & memfd::F_SEAL_FUTURE_WRITE != 0 && !writable)
+				{
+					return Err(errno!(EPERM));
+				}
+
+				let file_mutex = vfs::get_file_by_location(location)?;
+				let file = file_mutex.lock();
+				if !access_profile.can_write_file(&file) {
+					return Err(errno!(EACCES));
+				}
+			}
+
+			Ok(())
+		})?;
+
+		self.lazy_free_remove(addr, pages)?;
+		self.update_range(addr, pages, |mapping| {
+			let flags = (mapping.get_flags() & !(MAPPING_FLAG_WRITE | MAPPING_FLAG_EXEC)) | prot;
+			mapping.set_flags(flags);
+			Ok(())
+		})
+	}
+
+	/// Tells whether the memory space contains a shared writable mapping of the file at location
+	/// `loc`.
+	pub fn has_shared_writable_mapping(&self, loc: &FileLocation) -> bool {
+		let flags = MAPPING_FLAG_SHARED | MAPPING_FLAG_WRITE;
+		self.mappings.iter().any(|(_, mapping)| {
+			let residence_match = matches!(
+				mapping.get_residence(),
+				MapResidence::File { location, .. } if location == loc
+			);
+			mapping.get_flags() & flags == flags && residence_match
+		})
+	}
+
+	/// Returns the number of locked pages in the memory space.
+	pub fn get_locked_pages(&self) -> usize {
+		self.locked_pages
+	}
+
+	/// Returns the number of pages that are not locked yet in the range beginning at `addr` with
+	/// size `pages` pages.
+	///
+	/// If a page in the range is not mapped, the function returns [`crate::errno::ENOMEM`].
+	pub fn count_unlocked(&self, addr: *const c_void, pages: usize) -> EResult<usize> {
+		let begin = addr as usize;
+		let end = Self::range_end(addr, pages)?;
+
+		let mut count = 0;
+		self.check_range(addr, pages, |mapping| {
+			if mapping.get_flags() & MAPPING_FLAG_LOCKED == 0 {
+				let mapping_begin = mapping.get_begin() as usize;
+				let mapping_end = mapping_begin + mapping.get_size().get() * memory::PAGE_SIZE;
+				count += (min(end, mapping_end) - max(begin, mapping_begin)) / memory::PAGE_SIZE;
+			}
+			Ok(())
+		})?;
+
+		Ok(count)
+	}
+
+	/// Locks the pages in the range beginning at `addr` with size `pages` pages into memory.
+	///
+	/// Arguments:
+	/// - `addr` is the beginning of the range. It must be page-aligned
+	/// - `pages` is the size of the range in pages
+	/// - `on_fault` tells whether physical pages are allocated only when accessed instead of
+	/// right away
+	///
+	/// Locked pages are never discarded nor swapped out.
+	///
+	/// The caller is responsible for checking the locked memory limit beforehand.
+	///
+	/// If a page in the range is not mapped, the function returns [`crate::errno::ENOMEM`] and
+	/// nothing is modified.
+	pub fn lock(&mut self, addr: *const c_void, pages: usize, on_fault: bool) -> EResult<()> {
+		self.check_range(addr, pages, |_| Ok(()))?;
+
+		self.lazy_free_remove(addr, pages)?;
+		let mut locked_pages = self.locked_pages;
+		let res = self.update_range(addr, pages, |mapping| {
+			if mapping.get_flags() & MAPPING_FLAG_LOCKED == 0 {
+				mapping.set_flags(mapping.get_flags() | MAPPING_FLAG_LOCKED);
+				locked_pages += mapping.get_size().get();
+			}
+			if !on_fault {
+				mapping.populate()?;
+			}
+			Ok(())
+		});
+		self.locked_pages = locked_pages;
+
+		res
+	}
+
+	/// Unlocks the pages in the range beginning at `addr` with size `pages` pages.
+	///
+	/// If a page in the range is not mapped, the function returns [`crate::errno::ENOMEM`] and
+	/// nothing is modified.
+	pub fn unlock(&mut self, addr: *const c_void, pages: usize) -> EResult<()> {
+		self.check_range(addr, pages, |_| Ok(()))?;
+
+		let mut locked_pages = self.locked_pages;
+		let res = self.update_range(addr, pages, |mapping| {
+			if mapping.get_flags() & MAPPING_FLAG_LOCKED != 0 {
+				mapping.set_flags(mapping.get_flags() & !MAPPING_FLAG_LOCKED);
+				locked_pages -= mapping.get_size().get();
+			}
+			Ok(())
+		});
+		self.locked_pages = locked_pages;
+
+		res
+	}
+
+	/// Returns the number of pages in the memory space that are not locked yet.
+	pub fn count_all_unlocked(&self) -> usize {
+		self.mappings
+			.iter()
+			.filter(|(_, m)| m.get_flags() & MAPPING_FLAG_LOCKED == 0)
+			.map(|(_, m)| m.get_size().get())
+			.sum()
+	}
+
+	/// Locks every mappings of the memory space.
+	///
+	/// Arguments:
+	/// - `current` tells whether currently existing mappings are locked
+	/// - `future` tells whether mappings created from now on are locked
+	/// - `on_fault` has the same meaning as for `lock`
+	///
+	/// The caller is responsible for checking the locked memory limit beforehand.
+	pub fn lock_all(&mut self, current: bool, future: bool, on_fault: bool) -> EResult<()> {
+		self.lock_future = future.then_some(on_fault);
+		if !current {
+			return Ok(());
+		}
+
+		self.lazy_free = Map::new();
+		let mut res = Ok(());
+		for (_, m) in self.mappings.iter_mut() {
+			if m.get_flags() & MAPPING_FLAG_LOCKED == 0 {
+				m.set_flags(m.get_flags() | MAPPING_FLAG_LOCKED);
+				self.locked_pages += m.get_size().get();
+			}
+			if !on_fault {
+				res = res.and(m.populate());
+			}
+			for i in 0..m.get_size().get() {
+				m.update_vmem(i);
+			}
+		}
+
+		self.vmem.flush();
+		Ok(res?)
+	}
+
+	/// Unlocks every mappings of the memory space and cancels the locking of future mappings.
+	pub fn unlock_all(&mut self) {
+		self.lock_future = None;
+		for (_, m) in self.mappings.iter_mut() {
+			m.set_flags(m.get_flags() & !MAPPING_FLAG_LOCKED);
+		}
+		self.locked_pages = 0;
+	}
+
+	/// Tells whether a page in the range beginning at `addr` with size `pages` pages is locked.
+	fn is_range_locked(&self, addr: *const c_void, pages: usize) -> bool {
+		(0..pages).any(|i| {
+			let page_ptr = (addr as usize + i * memory::PAGE_SIZE) as *const c_void;
+			Self::get_mapping_for_(&self.mappings, page_ptr)
+				.map(|m| m.get_flags() & MAPPING_FLAG_LOCKED != 0)
+				.unwrap_or(false)
+		})
+	}
+
+	/// Removes the pages in the range beginning at `addr` with size `pages` pages from the set
+	/// of lazily freeable pages.
+	///
+	/// If the end of the range overflows, the function returns [`crate::errno::ENOMEM`].
+	fn lazy_free_remove(&mut self, addr: *const c_void, pages: usize) -> EResult<()> {
+		let begin = addr as usize;
+		let end = Self::range_end(addr, pages)?;
+		self.lazy_free
+			.retain(|ptr, _| !(begin..end).contains(&(*ptr as usize)));
+		Ok(())
+	}
+
+	/// Removes the pages in the range beginning at `addr` with size `pages` pages from the set
+	/// of dirty pages.
+	///
+	/// If the end of the range overflows, the function returns [`crate::errno::ENOMEM`].
+	fn dirty_remove(&mut self, addr: *const c_void, pages: usize) -> EResult<()> {
+		let begin = addr as usize;
+		let end = Self::range_end(addr, pages)?;
+		self.dirty
+			.retain(|ptr, _| !(begin..end).contains(&(*ptr as usize)));
+		Ok(())
+	}
+
+	/// Executes the given closure `f` on every private and anonymous pages in the range beginning
+	/// at `addr` with size `pages` pages.
+	///
+	/// The closure takes the mapping containing the page and the offset of the page in it.
+	///
+	/// Pages that are shared or associated with a file are skipped.
+	///
+	/// If a page in the range is not mapped, the function returns [`crate::errno::ENOMEM`] after
+	/// processing the other pages.
+	fn foreach_anon_page<F: FnMut(&mut MemMapping, usize) -> EResult<()>>(
+		&mut self,
+		addr: *const c_void,
+		pages: usize,
+		mut f: F,
+	) -> EResult<()> {
+		let mut res = Ok(());
+
+		for i in 0..pages {
+			let page_ptr = (addr as usize + i * memory::PAGE_SIZE) as *const c_void;
+			let Some(mapping) = Self::get_mapping_mut_for_(&mut self.mappings, page_ptr) else {
+				res = Err(errno!(ENOMEM));
+				continue;
+			};
+			if mapping.get_flags() & MAPPING_FLAG_SHARED != 0
+				|| !mapping.get_residence().is_normal()
+			{
+				continue;
+			}
+
+			let offset = (page_ptr as usize - mapping.get_begin() as usize) / memory::PAGE_SIZE;
+			f(mapping, offset)?;
+		}
+
+		res
+	}
+
+	/// Discards the private and anonymous pages in the range beginning at `addr` with size
+	/// `pages` pages.
+	///
+	/// Subsequent accesses to the pages return zero-filled pages, allocated on demand.
+	///
+	/// If a page in the range is not mapped, the function returns [`crate::errno::ENOMEM`].
+	///
+	/// If a page in the range is locked, the function returns [`crate::errno::EINVAL`].
+	pub fn discard(&mut self, addr: *const c_void, pages: usize) -> EResult<()> {
+		if self.is_range_locked(addr, pages) {
+			return Err(errno!(EINVAL));
+		}
+		self.lazy_free_remove(addr, pages)?;
+		let res = self.foreach_anon_page(addr, pages, |mapping, offset| {
+			mapping.discard_page(offset)?;
+			Ok(())
+		});
+
+		self.vmem.flush();
+		res
+	}
+
+	/// Marks the private and anonymous pages in the range beginning at `addr` with size `pages`
+	/// pages as lazily freeable.
+	///
+	/// The content of those pages is kept until the system needs to reclaim memory, in which case
+	/// they get discarded. Writing to a page cancels the operation for it.
+	///
+	/// If a page in the range is not mapped, the function returns [`crate::errno::ENOMEM`].
+	///
+	/// If a page in the range is locked, the function returns [`crate::errno::EINVAL`].
+	pub fn lazy_free(&mut self, addr: *const c_void, pages: usize) -> EResult<()> {
+		if self.is_range_locked(addr, pages) {
+			return Err(errno!(EINVAL));
+		}
+		let mut marked = Vec::new();
+		let res = self.foreach_anon_page(addr, pages, |mapping, offset| {
+			// Pages that are not allocated are already free
+			if mapping.get_physical_page(offset).is_none() {
+				return Ok(());
+			}
+
+			mapping.write_protect_page(offset);
+			let page_ptr = unsafe { mapping.get_begin().add(offset * memory::PAGE_SIZE) };
+			marked.push(page_ptr)?;
+			Ok(())
+		});
+		for page_ptr in marked {
+			self.lazy_free.insert(page_ptr, ())?;
+		}
+
+		self.vmem.flush();
+		res
+	}
+
+	/// Discards every pages that have been marked as lazily freeable and haven't been written
+	/// since.
+	///
+	/// This function is meant to be called when the system runs low on memory.
+	///
+	/// The function returns the number of discarded pages.
+	pub fn reclaim_lazy_free(&mut self) -> usize {
+		let mut count = 0;
+
+		while let Some((page_ptr, _)) = self.lazy_free.pop_first() {
+			let Some(mapping) = Self::get_mapping_mut_for_(&mut self.mappings, page_ptr) else {
+				continue;
+			};
+			// Locked pages must stay in memory
+			if mapping.get_flags() & MAPPING_FLAG_LOCKED != 0 {
+				continue;
+			}
+			let offset = (page_ptr as usize - mapping.get_begin() as usize) / memory::PAGE_SIZE;
+			if mapping.get_physical_page(offset).is_none() {
+				continue;
+			}
+
+			// Failure only means that the page cannot be replaced by the default page, in which
+			// case the page is kept
+			if mapping.discard_page(offset).is_ok() {
+				count += 1;
+			}
+		}
+
+		self.vmem.flush();
+		count
+	}
+
+	/// Synchronizes the pages of shared file mappings in the range beginning at `addr` with size
+	/// `pages` pages with their respective files.
+	///
+	/// Arguments:
+	/// - `sync` tells whether the pages are written back before the function returns. If not,
+	/// they are written back later, in the background
+	/// - `invalidate` tells whether other mappings of the same files must be invalidated. Since
+	/// shared mappings of a file all use the pages of the page cache, they are always up to date
+	///
+	/// Only pages that have been written since the last synchronization are written back.
+	///
+	/// If a page in the range is not mapped, the function returns [`crate::errno::ENOMEM`].
+	///
+	/// If `invalidate` is set and a page in the range is locked, the function returns
+	/// [`crate::errno::EBUSY`].
+	pub fn sync(
+		&mut self,
+		addr: *const c_void,
+		pages: usize,
+		sync: bool,
+		invalidate: bool,
+	) -> EResult<()> {
+		self.check_range(addr, pages, |_| Ok(()))?;
+		if invalidate && self.is_range_locked(addr, pages) {
+			return Err(errno!(EBUSY));
+		}
+
+		let begin = addr as *mut c_void;
+		let end = (addr as usize + pages * memory::PAGE_SIZE) as *mut c_void;
+		let mut dirty = Vec::new();
+		for (page_ptr, _) in self.dirty.range(begin..end) {
+			dirty.push(*page_ptr)?;
+		}
+
+		// Transferring the dirty state of pages to the page cache. Pages are write-protected
+		// again so that subsequent writes are noticed
+		let mut files: HashMap<FileLocation, (u64, u64)> = HashMap::new();
+		for page_ptr in dirty {
+			self.dirty.remove(&page_ptr);
+			let Some(mapping) = Self::get_mapping_mut_for_(&mut self.mappings, page_ptr) else {
+				continue;
+			};
+			let offset = (page_ptr as usize - mapping.get_begin() as usize) / memory::PAGE_SIZE;
+			mapping.write_protect_page(offset);
+
+			let MapResidence::File {
+				location,
+				off,
+			} = mapping.get_residence()
+			else {
+				continue;
+			};
+			let file_off = *off / memory::PAGE_SIZE as u64 + offset as u64;
+			page_cache::mark_dirty(location, file_off);
+
+			// Keeping the range of modified pages for each file
+			match files.get_mut(location) {
+				Some((first, last)) => {
+					*first = min(*first, file_off);
+					*last = max(*last, file_off);
+				}
+				None => {
+					files.insert(location.clone(), (file_off, file_off))?;
+				}
+			}
+		}
+		self.vmem.flush();
+
+		for (location, (first, last)) in files.iter() {
+			// The file may have been removed in the meantime
+			let Ok(file_mutex) = vfs::get_file_by_location(location) else {
+				continue;
+			};
+			if sync {
+				let mut file = file_mutex.lock();
+				page_cache::sync_range(&mut file, *first, (*last - *first + 1) as usize)?;
+			} else {
+				writeback::mark_dirty(&file_mutex, location)?;
+			}
+		}
 
 		Ok(())
 	}
@@ -848,13 +1644,13 @@ impl MemSpace {
 	/// Sets the pointer for the `brk` syscall.
 	///
 	/// If the memory cannot be allocated, the function returns an error.
-	pub fn set_brk_ptr(&mut self, ptr: *mut c_void) -> AllocResult<()> {
+	pub fn set_brk_ptr(&mut self, ptr: *mut c_void) -> EResult<()> {
 		if ptr >= self.brk_ptr {
 			// Allocate memory
 
 			// Checking the pointer is valid
 			if ptr > memory::PROCESS_END {
-				return Err(AllocError);
+				return Err(errno!(ENOMEM));
 			}
 
 			let begin = util::align(self.brk_ptr, memory::PAGE_SIZE);
@@ -875,7 +1671,7 @@ impl MemSpace {
 
 			// Checking the pointer is valid
 			if ptr < self.brk_init {
-				return Err(AllocError);
+				return Err(errno!(ENOMEM));
 			}
 
 			let begin = util::align(ptr, memory::PAGE_SIZE);
@@ -904,9 +1700,7 @@ impl MemSpace {
 	///
 	/// If the process should continue, the function returns `true`, else `false`.
 	pub fn handle_page_fault(&mut self, virt_addr: *const c_void, code: u32) -> bool {
-		if code & vmem::x86::PAGE_FAULT_PRESENT == 0 {
-			return false;
-		}
+		stats::PGFAULT.fetch_add(1, atomic::Ordering::Relaxed);
 
 		let Some(mapping) = Self::get_mapping_mut_for_(&mut self.mappings, virt_addr) else {
 			return false;
@@ -917,17 +1711,40 @@ impl MemSpace {
 			return false;
 		}
 
-		// TODO check exec
+		// TODO check exec. Instruction fetches are reported only with the NX bit, which requires
+		// PAE
 
 		let userspace_mapping = mapping.get_flags() & MAPPING_FLAG_USER != 0;
 		if code & vmem::x86::PAGE_FAULT_USER != 0 && !userspace_mapping {
 			return false;
 		}
 
+		// The stack cannot grow beyond its limit
+		let begin = mapping.get_begin() as usize;
+		let end = begin + mapping.get_size().get() * memory::PAGE_SIZE;
+		let stack_top = self.stack_top as usize;
+		let stack_mapping = begin < stack_top && stack_top <= end;
+		if stack_mapping && stack_top - virt_addr as usize > self.stack_limit {
+			return false;
+		}
+
+		// If the page is not present, it has been unmapped through the reverse mapping and is
+		// mapped again
 		let page_offset = (virt_addr as usize - mapping.get_begin() as usize) / memory::PAGE_SIZE;
 		oom::wrap(|| mapping.map(page_offset));
 
 		mapping.update_vmem(page_offset);
+
+		// The page has been written, it is not lazily freeable anymore
+		let page_ptr = util::down_align(virt_addr, memory::PAGE_SIZE) as *mut c_void;
+		self.lazy_free.remove(&page_ptr);
+
+		// Writing to a page of a shared file mapping makes it dirty
+		if code & vmem::x86::PAGE_FAULT_WRITE != 0 && mapping.is_write_tracked() {
+			oom::wrap(|| self.dirty.insert(page_ptr, ()).map(|_| ()));
+			mapping.allow_write(page_offset);
+		}
+
 		true
 	}
 }
@@ -8,15 +8,87 @@
 //! pointer while it is being used.
 //!
 //! Those structures are also usable as system call arguments.
+//!
+//! References to userspace memory are returned wrapped in [`UserRef`] and [`UserMut`], which
+//! keep a user-access window open while they exist (see [`crate::cpu::smap`]).
 
 use super::MemSpace;
+use crate::cpu::smap::UserAccess;
 use crate::errno::Errno;
 use crate::process::Process;
 use crate::util::DisplayableStr;
 use core::fmt;
 use core::mem::size_of;
+use core::ops::Deref;
+use core::ops::DerefMut;
 use core::slice;
 
+/// An immutable reference to userspace memory.
+pub struct UserRef<'a, T: ?Sized> {
+	/// The reference.
+	val: &'a T,
+	/// The user-access window, open as long as the reference exists.
+	_access: UserAccess,
+}
+
+impl<'a, T: ?Sized> UserRef<'a, T> {
+	/// Creates a new instance.
+	///
+	/// # Safety
+	///
+	/// Access to the memory behind `val` must have been checked.
+	unsafe fn new(val: &'a T) -> Self {
+		Self {
+			val,
+			_access: UserAccess::new(),
+		}
+	}
+}
+
+impl<'a, T: ?Sized> Deref for UserRef<'a, T> {
+	type Target = T;
+
+	fn deref(&self) -> &Self::Target {
+		self.val
+	}
+}
+
+/// A mutable reference to userspace memory.
+pub struct UserMut<'a, T: ?Sized> {
+	/// The reference.
+	val: &'a mut T,
+	/// The user-access window, open as long as the reference exists.
+	_access: UserAccess,
+}
+
+impl<'a, T: ?Sized> UserMut<'a, T> {
+	/// Creates a new instance.
+	///
+	/// # Safety
+	///
+	/// Access to the memory behind `val` must have been checked.
+	unsafe fn new(val: &'a mut T) -> Self {
+		Self {
+			val,
+			_access: UserAccess::new(),
+		}
+	}
+}
+
+impl<'a, T: ?Sized> Deref for UserMut<'a, T> {
+	type Target = T;
+
+	fn deref(&self) -> &Self::Target {
+		self.val
+	}
+}
+
+impl<'a, T: ?Sized> DerefMut for UserMut<'a, T> {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		self.val
+	}
+}
+
 /// Wrapper for a pointer to a simple data.
 pub struct SyscallPtr<T: Sized> {
 	/// The pointer.
@@ -52,7 +124,7 @@ impl<T: Sized> SyscallPtr<T> {
 	/// If the pointer is null, the function returns `None`.
 	///
 	/// If the value is not accessible, the function returns an error.
-	pub fn get<'a>(&self, mem_space: &'a MemSpace) -> Result<Option<&'a T>, Errno> {
+	pub fn get<'a>(&self, mem_space: &'a MemSpace) -> Result<Option<UserRef<'a, T>>, Errno> {
 		if self.is_null() {
 			return Ok(None);
 		}
@@ -60,7 +132,7 @@ impl<T: Sized> SyscallPtr<T> {
 		if mem_space.can_access(self.ptr as _, size_of::<T>(), true, false) {
 			Ok(Some(unsafe {
 				// Safe because access is checked before
-				&*self.ptr
+				UserRef::new(&*self.ptr)
 			}))
 		} else {
 			Err(errno!(EFAULT))
@@ -75,7 +147,10 @@ impl<T: Sized> SyscallPtr<T> {
 	///
 	/// If the value is located on lazily allocated pages, the function
 	/// allocates physical pages in order to allow writing.
-	pub fn get_mut<'a>(&self, mem_space: &'a mut MemSpace) -> Result<Option<&'a mut T>, Errno> {
+	pub fn get_mut<'a>(
+		&self,
+		mem_space: &'a mut MemSpace,
+	) -> Result<Option<UserMut<'a, T>>, Errno> {
 		if self.is_null() {
 			return Ok(None);
 		}
@@ -86,7 +161,7 @@ impl<T: Sized> SyscallPtr<T> {
 
 			Ok(Some(unsafe {
 				// Safe because access is checked before
-				&mut *self.ptr
+				UserMut::new(&mut *self.ptr)
 			}))
 		} else {
 			Err(errno!(EFAULT))
@@ -103,7 +178,7 @@ impl<T: fmt::Debug> fmt::Debug for SyscallPtr<T> {
 		let mem_space = mem_space_mutex.lock();
 
 		match self.get(&mem_space) {
-			Ok(Some(s)) => write!(fmt, "{:p} = {:?}", self.as_ptr(), s),
+			Ok(Some(s)) => write!(fmt, "{:p} = {:?}", self.as_ptr(), &*s),
 
 			Ok(None) => write!(fmt, "NULL"),
 
@@ -151,7 +226,11 @@ impl<T: Sized> SyscallSlice<T> {
 	/// `len` is the in number of elements in the slice.
 	///
 	/// If the slice is not accessible, the function returns an error.
-	pub fn get<'a>(&self, mem_space: &'a MemSpace, len: usize) -> Result<Option<&'a [T]>, Errno> {
+	pub fn get<'a>(
+		&self,
+		mem_space: &'a MemSpace,
+		len: usize,
+	) -> Result<Option<UserRef<'a, [T]>>, Errno> {
 		if self.is_null() {
 			return Ok(None);
 		}
@@ -160,7 +239,7 @@ impl<T: Sized> SyscallSlice<T> {
 		if mem_space.can_access(self.ptr as _, size, true, false) {
 			Ok(Some(unsafe {
 				// Safe because access is checked before
-				slice::from_raw_parts(self.ptr, len)
+				UserRef::new(slice::from_raw_parts(self.ptr, len))
 			}))
 		} else {
 			Err(errno!(EFAULT))
@@ -179,7 +258,7 @@ impl<T: Sized> SyscallSlice<T> {
 		&self,
 		mem_space: &'a mut MemSpace,
 		len: usize,
-	) -> Result<Option<&'a mut [T]>, Errno> {
+	) -> Result<Option<UserMut<'a, [T]>>, Errno> {
 		if self.is_null() {
 			return Ok(None);
 		}
@@ -191,7 +270,7 @@ impl<T: Sized> SyscallSlice<T> {
 
 			Ok(Some(unsafe {
 				// Safe because access is checked before
-				slice::from_raw_parts_mut(self.ptr, len)
+				UserMut::new(slice::from_raw_parts_mut(self.ptr, len))
 			}))
 		} else {
 			Err(errno!(EFAULT))
@@ -245,7 +324,7 @@ impl SyscallString {
 	/// Returns an immutable reference to the string.
 	///
 	/// If the string is not accessible, the function returns an error.
-	pub fn get<'a>(&self, mem_space: &'a MemSpace) -> Result<Option<&'a [u8]>, Errno> {
+	pub fn get<'a>(&self, mem_space: &'a MemSpace) -> Result<Option<UserRef<'a, [u8]>>, Errno> {
 		if self.is_null() {
 			return Ok(None);
 		}
@@ -255,7 +334,7 @@ impl SyscallString {
 			.ok_or_else(|| errno!(EFAULT))?;
 		Ok(Some(unsafe {
 			// Safe because access is checked before
-			slice::from_raw_parts(self.ptr, len)
+			UserRef::new(slice::from_raw_parts(self.ptr, len))
 		}))
 	}
 
@@ -265,7 +344,10 @@ impl SyscallString {
 	/// allocates physical pages in order to allow writing.
 	///
 	/// If the string is not accessible, the function returns an error.
-	pub fn get_mut<'a>(&self, mem_space: &'a mut MemSpace) -> Result<Option<&'a mut [u8]>, Errno> {
+	pub fn get_mut<'a>(
+		&self,
+		mem_space: &'a mut MemSpace,
+	) -> Result<Option<UserMut<'a, [u8]>>, Errno> {
 		if self.is_null() {
 			return Ok(None);
 		}
@@ -279,7 +361,7 @@ impl SyscallString {
 
 		Ok(Some(unsafe {
 			// Safe because access is checked before
-			slice::from_raw_parts_mut(self.ptr, len)
+			UserMut::new(slice::from_raw_parts_mut(self.ptr, len))
 		}))
 	}
 }
@@ -296,7 +378,7 @@ impl fmt::Debug for SyscallString {
 			Ok(Some(s)) => {
 				// TODO Add backslashes to escape `"` and `\`
 
-				let s = DisplayableStr(s);
+				let s = DisplayableStr(&s);
 				write!(fmt, "{:p} = \"{}\"", self.as_ptr(), s)
 			}
 
@@ -0,0 +1,137 @@
+//! The reverse mapping (rmap) allows to find every mappings referencing a physical page.
+//!
+//! Mappings register each physical page they map, along with the virtual memory context and the
+//! address at which it is mapped. This allows to unmap a page from every memory spaces, for
+//! example to evict it from the page cache, or to swap it out.
+//!
+//! A page that has been unmapped this way is mapped again on the next access to it.
+
+use super::PhysRefCounter;
+use super::Rss;
+use crate::errno::AllocResult;
+use crate::memory::vmem::VMem;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::vec::Vec;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use core::ffi::c_void;
+use core::ptr;
+
+/// A mapping of a physical page.
+struct Entry {
+	/// The virtual memory context in which the page is mapped.
+	vmem: Arc<dyn VMem>,
+	/// The virtual address at which the page is mapped.
+	virt: *const c_void,
+	/// The counter of physical pages of the memory space.
+	rss: Arc<Rss>,
+	/// Tells whether the page is mapped by a shared mapping.
+	shared: bool,
+}
+
+impl Entry {
+	/// Tells whether the entry corresponds to a mapping of `virt` in `vmem`.
+	fn is(&self, vmem: &Arc<dyn VMem>, virt: *const c_void) -> bool {
+		ptr::eq(
+			Arc::as_ptr(&self.vmem) as *const (),
+			Arc::as_ptr(vmem) as *const (),
+		) && self.virt == virt
+	}
+}
+
+/// The mappings of each physical page, by physical address.
+///
+/// To avoid deadlocks, this lock must never be acquired before the lock of the physical reference
+/// counter.
+static RMAP: Mutex<HashMap<*const c_void, Vec<Entry>>> = Mutex::new(HashMap::new());
+
+/// Registers the mapping of the physical page `phys`.
+///
+/// Arguments:
+/// - `vmem` is the virtual memory context in which the page is mapped
+/// - `virt` is the virtual address at which the page is mapped
+/// - `rss` is the counter of physical pages of the memory space, decremented if the page gets
+/// unmapped with [`unmap_private`]
+/// - `shared` tells whether the mapping is shared
+pub fn add(
+	phys: *const c_void,
+	vmem: &Arc<dyn VMem>,
+	virt: *const c_void,
+	rss: &Arc<Rss>,
+	shared: bool,
+) -> AllocResult<()> {
+	let entry = Entry {
+		vmem: vmem.clone(),
+		virt,
+		rss: rss.clone(),
+		shared,
+	};
+
+	let mut rmap = RMAP.lock();
+	match rmap.get_mut(&phys) {
+		Some(entries) => entries.push(entry),
+		None => {
+			let mut entries = Vec::new();
+			entries.push(entry)?;
+			rmap.insert(phys, entries)?;
+			Ok(())
+		}
+	}
+}
+
+/// Unregisters the mapping of the physical page `phys` at the virtual address `virt` in `vmem`.
+///
+/// If the mapping is not registered, the function does nothing.
+pub fn remove(phys: *const c_void, vmem: &Arc<dyn VMem>, virt: *const c_void) {
+	let mut rmap = RMAP.lock();
+	let Some(entries) = rmap.get_mut(&phys) else {
+		return;
+	};
+	entries.retain(|e| !e.is(vmem, virt));
+	if entries.is_empty() {
+		rmap.remove(&phys);
+	}
+}
+
+/// Returns the number of mappings of the physical page `phys`.
+pub fn count(phys: *const c_void) -> usize {
+	RMAP.lock().get(&phys).map(Vec::len).unwrap_or(0)
+}
+
+/// Unmaps the physical page `phys` from every memory spaces, if it is only mapped by private
+/// mappings.
+///
+/// The reference of each mapping on the page is released on `ref_counter`. The page itself is
+/// not freed.
+///
+/// Since private mappings never write to a shared page, the content of the page is left
+/// untouched by them.
+///
+/// The page is left untouched if it is referenced by something else than the registered
+/// mappings and `owners` other owners.
+///
+/// The function returns `true` if the page has been unmapped.
+pub fn unmap_private(
+	phys: *const c_void,
+	ref_counter: &mut PhysRefCounter,
+	owners: usize,
+) -> bool {
+	let mut rmap = RMAP.lock();
+	let Some(entries) = rmap.get(&phys) else {
+		return false;
+	};
+	if entries.iter().any(|e| e.shared)
+		|| ref_counter.get_ref_count(phys) != entries.len() + owners
+	{
+		return false;
+	}
+
+	let entries = rmap.remove(&phys).unwrap();
+	for e in entries.iter() {
+		// Unmapping cannot fail since the page table already exists
+		let _ = e.vmem.unmap(e.virt);
+		e.rss.sub(1);
+		ref_counter.decrement(phys);
+	}
+	true
+}
@@ -7,18 +7,27 @@
 // TODO Do not reallocate a PID of used as a pgid
 // TODO When a process receives a signal, log it if the `strace` feature is enabled
 
+pub mod acct;
+pub mod cgroup;
+pub mod coredump;
 pub mod exec;
+pub mod futex;
 pub mod iovec;
+pub mod kthread;
 pub mod mem_space;
 pub mod oom;
+pub mod pgroup;
 pub mod pid;
 pub mod regs;
+pub mod rlimit;
 pub mod rusage;
 pub mod scheduler;
+pub mod seccomp;
 pub mod signal;
 #[cfg(target_arch = "x86")]
 pub mod tss;
 pub mod user_desc;
+pub mod workqueue;
 
 use crate::cpu;
 use crate::errno;
@@ -28,44 +37,74 @@ use crate::errno::Errno;
 use crate::event;
 use crate::event::CallbackResult;
 use crate::file;
+use crate::file::aio::AioContextTable;
+use crate::file::buffer::pidfd;
 use crate::file::fd::FileDescriptorTable;
 use crate::file::fd::NewFDConstraint;
 use crate::file::fs::procfs::ProcFS;
 use crate::file::mountpoint;
+use crate::file::mountpoint::MountNamespace;
 use crate::file::open_file;
 use crate::file::path::Path;
 use crate::file::perm::AccessProfile;
-use crate::file::perm::ROOT_UID;
+use crate::file::perm::CAP_IPC_LOCK;
+use crate::file::perm::CAP_KILL;
+use crate::file::perm::CAP_SYS_ADMIN;
+use crate::file::perm::CAP_SYS_RESOURCE;
+use crate::file::record_lock;
 use crate::file::vfs;
 use crate::gdt;
 use crate::memory;
+use crate::memory::slab;
+use crate::memory::slab::SlabCache;
 use crate::process::mountpoint::MountSource;
 use crate::process::open_file::OpenFile;
+use crate::syscall::restart_syscall::RestartBlock;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
 use crate::time::timer::TimerManager;
+use crate::time::unit::TimeUnit;
+use crate::time::unit::Timestamp;
+use crate::time::unit::TimestampScale;
+use crate::time::unit::Timeval;
 use crate::tty;
 use crate::tty::TTYHandle;
-use crate::util::container::bitfield::Bitfield;
+use crate::util::container::hashmap::HashMap;
 use crate::util::container::string::String;
 use crate::util::container::vec::Vec;
 use crate::util::lock::*;
 use crate::util::ptr::arc::Arc;
 use crate::util::ptr::arc::Weak;
-use crate::util::TryClone;
+use cgroup::CGroup;
 use core::any::Any;
+use core::cmp::max;
+use core::cmp::min;
+use core::ffi::c_int;
 use core::ffi::c_void;
+use core::mem;
 use core::mem::size_of;
 use core::mem::ManuallyDrop;
 use core::mem::MaybeUninit;
+use core::ptr;
 use core::ptr::NonNull;
+use core::sync::atomic;
+use core::sync::atomic::AtomicBool;
 use mem_space::MemSpace;
-use pid::PIDManager;
 use pid::Pid;
+use pid::PidNamespace;
 use regs::Regs;
+use rlimit::RLimit;
+use rlimit::RLimits;
+use rusage::CpuTimes;
 use rusage::RUsage;
 use scheduler::Scheduler;
+use seccomp::Seccomp;
+use signal::SigInfo;
+use signal::SigSet;
 use signal::Signal;
 use signal::SignalAction;
 use signal::SignalHandler;
+use signal::SI_KERNEL;
 #[cfg(target_arch = "x86")]
 use tss::TSS;
 
@@ -97,6 +136,9 @@ const STDERR_FILENO: u32 = 2;
 /// The number of TLS entries per process.
 pub const TLS_ENTRIES_COUNT: usize = 3;
 
+/// The size of the buffer holding the name of a process, including the terminating nullbyte.
+pub const COMM_LEN: usize = 16;
+
 /// The size of the redzone in userspace, in bytes.
 const REDZONE_SIZE: usize = 128;
 
@@ -150,6 +192,16 @@ pub struct ForkOptions {
 	/// If `true`, the parent and child processes both share the same signal
 	/// handlers table.
 	pub share_sighand: bool,
+	/// If `true`, the parent and child processes both share the same filesystem information.
+	pub share_fs: bool,
+	/// If `true`, the child process is a thread in the same thread group as the parent.
+	pub thread: bool,
+	/// If `true`, the child process is created in a new PID namespace, of which it is the init
+	/// process.
+	pub new_pid_ns: bool,
+	/// If `true`, the child process is created in a new mount namespace, which is a copy of the
+	/// parent's.
+	pub new_mnt_ns: bool,
 
 	/// If `true`, the parent is paused until the child process exits or executes
 	/// a program.
@@ -167,12 +219,26 @@ impl Default for ForkOptions {
 			share_memory: false,
 			share_fd: false,
 			share_sighand: false,
+			share_fs: false,
+			thread: false,
+			new_pid_ns: false,
+			new_mnt_ns: false,
 
 			vfork: false,
 		}
 	}
 }
 
+/// Filesystem information of a process.
+pub struct FsInfo {
+	/// Current working directory
+	pub cwd: Arc<Path>,
+	/// Current root path used by the process
+	pub chroot: Arc<Path>,
+	/// The process's current umask.
+	pub umask: file::Mode,
+}
+
 /// The vfork operation is similar to the fork operation except the parent
 /// process isn't executed until the child process exits or executes a program.
 ///
@@ -188,10 +254,18 @@ enum VForkState {
 
 	/// The process is the parent waiting for the child to terminate.
 	Waiting,
-	/// The process is the child the parent waits for.
-	Executing,
+	/// The process is the child the parent waits for. The value is the PID of the parent, which
+	/// is not necessarily the parent of the thread group.
+	Executing(Pid),
 }
 
+/// The slab cache of processes.
+pub static PROCESS_CACHE: SlabCache = SlabCache::new(
+	"task_struct",
+	Arc::<IntMutex<Process>>::inner_size(),
+	Some(slab::zero_ctor::<{ Arc::<IntMutex<Process>>::inner_size() }>),
+);
+
 /// The Process Control Block (PCB). This structure stores all the informations
 /// about a process.
 pub struct Process {
@@ -199,8 +273,21 @@ pub struct Process {
 	pub pid: Pid,
 	/// The ID of the process group.
 	pub pgid: Pid,
+	/// The ID of the session.
+	pub sid: Pid,
 	/// The thread ID of the process.
 	pub tid: Pid,
+	/// The ID of the thread group, which is the PID of its leader.
+	pub tgid: Pid,
+	/// The PIDs of the threads of the thread group which have not exited yet. The list is shared
+	/// between the threads of the group.
+	threads: Arc<Mutex<Vec<Pid>>>,
+	/// The PID namespace of the process.
+	pid_ns: Arc<PidNamespace>,
+	/// The PID namespace in which the children of the process are created.
+	pid_ns_for_children: Arc<PidNamespace>,
+	/// The mount namespace of the process.
+	mnt_ns: Arc<MountNamespace>,
 
 	/// The argv of the process.
 	pub argv: Arc<Vec<String>>,
@@ -212,8 +299,23 @@ pub struct Process {
 
 	/// The process's access profile, containing user and group IDs.
 	pub access_profile: AccessProfile,
-	/// The process's current umask.
-	pub umask: file::Mode,
+	/// The process's execution domain, as set with the `personality` system call.
+	pub personality: u32,
+	/// The adjustment of the process's OOM score, between [`oom::OOM_SCORE_ADJ_MIN`] and
+	/// [`oom::OOM_SCORE_ADJ_MAX`].
+	pub oom_score_adj: i16,
+	/// The name of the process, padded with nullbytes.
+	comm: [u8; COMM_LEN],
+	/// Tells whether the process is dumpable.
+	pub dumpable: bool,
+	/// The signal to send to the process when its parent exits.
+	pub pdeath_signal: Option<Signal>,
+	/// If set, executing a program cannot grant more privileges.
+	pub no_new_privs: bool,
+	/// If set, the process adopts its orphaned descendants instead of the init process.
+	pub child_subreaper: bool,
+	/// The secure computing state of the process.
+	pub seccomp: Seccomp,
 
 	/// The current state of the process.
 	state: State,
@@ -221,29 +323,42 @@ pub struct Process {
 	/// `VForkState`).
 	vfork_state: VForkState,
 
-	/// The priority of the process.
+	/// The scheduling weight of the process, derived from its nice value. A process with a
+	/// higher weight receives more CPU time.
+	///
+	/// The weight may be raised above the value given by the nice value by priority inheritance.
 	pub priority: usize,
 	/// The nice value of the process.
-	pub nice: usize,
-	/// The number of quantum run during the cycle.
-	quantum_count: usize,
+	nice: i32,
+	/// The set of CPUs the process is allowed to run on.
+	pub cpu_affinity: scheduler::CpuSet,
+	/// The scheduling policy of the process.
+	pub sched_policy: c_int,
+	/// The real-time priority of the process. Meaningful only for real-time scheduling policies.
+	pub rt_priority: u32,
+	/// The state of the process for the fair scheduler.
+	sched_entity: scheduler::SchedEntity,
+	/// The control group of the process.
+	cgroup: Arc<CGroup>,
 
 	/// A pointer to the parent process.
 	parent: Option<Weak<IntMutex<Process>>>,
 	/// The list of children processes.
 	children: Vec<Pid>,
-	/// The list of processes in the process group.
-	process_group: Vec<Pid>,
 
 	/// The last saved registers state.
 	pub regs: Regs,
 	/// Tells whether the process was syscalling or not.
 	pub syscalling: bool,
+	/// The state used to resume the last system call interrupted by a signal, if any.
+	pub restart_block: Option<RestartBlock>,
 
 	/// Tells whether the process is handling a signal.
 	handled_signal: Option<Signal>,
 	/// The saved state of registers, used when handling a signal.
 	saved_regs: Regs,
+	/// The saved set of blocked signals, used when handling a signal.
+	saved_sigmask: SigSet,
 	/// Tells whether the process has information that can be retrieved by
 	/// wait/waitpid.
 	waitable: bool,
@@ -254,22 +369,28 @@ pub struct Process {
 
 	/// The virtual memory of the process containing every mappings.
 	mem_space: Option<Arc<IntMutex<MemSpace>>>,
+	/// The AIO contexts, shared by the processes sharing the memory space.
+	aio_contexts: Arc<Mutex<AioContextTable>>,
 	/// A pointer to the userspace stack.
 	user_stack: Option<*mut c_void>,
 	/// A pointer to the kernelspace stack.
 	kernel_stack: Option<*mut c_void>,
 
-	/// Current working directory
-	pub cwd: Arc<Path>,
-	/// Current root path used by the process
-	pub chroot: Arc<Path>,
+	/// Filesystem information, shared between the processes created with `CLONE_FS`.
+	fs: Arc<Mutex<FsInfo>>,
 	/// The list of open file descriptors with their respective ID.
 	file_descriptors: Option<Arc<Mutex<FileDescriptorTable>>>,
 
-	/// A bitfield storing the set of blocked signals.
-	pub sigmask: Bitfield,
-	/// A bitfield storing the set of pending signals.
-	sigpending: Bitfield,
+	/// The set of blocked signals.
+	pub sigmask: SigSet,
+	/// The set of signals the process is waiting for with `rt_sigtimedwait`. A signal in this
+	/// set wakes the process up even if blocked.
+	pub sigwait: SigSet,
+	/// The set of pending signals.
+	sigpending: SigSet,
+	/// The informations of pending signals, in the order they have been sent. A real-time
+	/// signal may have several entries.
+	sigqueue: Vec<SigInfo>,
 	/// The list of signal handlers.
 	signal_handlers: Arc<Mutex<[SignalHandler; signal::SIGNALS_COUNT]>>,
 
@@ -282,29 +403,49 @@ pub struct Process {
 	/// If a thread is started using `clone` with the `CLONE_CHILD_CLEARTID` flag, clear_child_tid
 	/// is set to the value passed in the ctid argument of that system call.
 	clear_child_tid: Option<NonNull<i32>>,
+	/// The head of the list of robust futexes held by the process, registered with
+	/// `set_robust_list`.
+	robust_list: Option<NonNull<futex::RobustListHead>>,
 
 	/// The process's resources usage.
 	rusage: RUsage,
+	/// The CPU time used by the process and its children.
+	cpu_times: CpuTimes,
+	/// The timestamp since which the CPU time of the process has not been accounted, in
+	/// nanoseconds on the monotonic clock.
+	cpu_time_start: Timestamp,
+	/// The time at which the process was created, in nanoseconds on the monotonic clock.
+	start_time: Timestamp,
+	/// The flags of the process's accounting record.
+	acct_flags: u8,
+	/// The process's resource limits.
+	pub rlimits: RLimits,
 
 	/// The exit status of the process after exiting.
 	exit_status: ExitStatus,
 	/// The terminating signal.
 	termsig: u8,
+	/// Tells whether the process has dumped a core when terminated.
+	core_dumped: bool,
+
+	/// If the process is a kernel thread, the state specific to kernel threads.
+	kthread: Option<kthread::KThreadInfo>,
 }
 
-/// The PID manager.
-static mut PID_MANAGER: MaybeUninit<Mutex<PIDManager>> = MaybeUninit::uninit();
 /// The processes scheduler.
 static mut SCHEDULER: MaybeUninit<Arc<IntMutex<Scheduler>>> = MaybeUninit::uninit();
+/// Tells whether the processes system has been initialized.
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
+/// The threads which have exited, waiting to be removed from the scheduler.
+static ZOMBIE_THREADS: Mutex<Vec<Pid>> = Mutex::new(Vec::new());
 
 /// Initializes processes system. This function must be called only once, at
 /// kernel initialization.
 pub fn init() -> Result<(), Errno> {
 	TSS::init();
 
-	let cores_count = 1; // TODO
+	let cores_count = cpu::smp::get_cpus_count();
 	unsafe {
-		PID_MANAGER.write(Mutex::new(PIDManager::new()?));
 		SCHEDULER.write(Scheduler::new(cores_count)?);
 	}
 
@@ -388,6 +529,23 @@ pub fn init() -> Result<(), Errno> {
 		};
 		let mut curr_proc = curr_proc.lock();
 
+		// Userspace cannot allocate memory beyond the limit of its control group. Faults of the
+		// kernel are not limited since they cannot be interrupted
+		let cgroup = curr_proc.get_mem_space().unwrap().lock().get_cgroup();
+		if ring == 3 && !oom::make_room(&cgroup) {
+			cgroup
+				.memory_events
+				.oom_kill
+				.fetch_add(1, atomic::Ordering::Relaxed);
+			curr_proc.kill(&Signal::SIGKILL, true);
+			curr_proc.signal_next();
+			return if matches!(curr_proc.get_state(), State::Running) {
+				CallbackResult::Continue
+			} else {
+				CallbackResult::Idle
+			};
+		}
+
 		// Handle page fault
 		let success = {
 			let mem_space_mutex = curr_proc.get_mem_space().unwrap();
@@ -421,9 +579,15 @@ pub fn init() -> Result<(), Errno> {
 	let _ = ManuallyDrop::new(event::register_callback(0x11, callback)?);
 	let _ = ManuallyDrop::new(event::register_callback(0x13, callback)?);
 
+	INITIALIZED.store(true, atomic::Ordering::Release);
 	Ok(())
 }
 
+/// Tells whether the processes system has been initialized.
+pub fn is_initialized() -> bool {
+	INITIALIZED.load(atomic::Ordering::Acquire)
+}
+
 /// Returns a mutable reference to the scheduler's `Mutex`.
 pub fn get_scheduler() -> &'static IntMutex<Scheduler> {
 	unsafe {
@@ -432,6 +596,19 @@ pub fn get_scheduler() -> &'static IntMutex<Scheduler> {
 	}
 }
 
+/// Removes the threads which have exited from the scheduler.
+///
+/// This function is called each time a process returns from a system call.
+pub fn reap_threads() {
+	let threads = mem::take(&mut *ZOMBIE_THREADS.lock());
+	for pid in threads.iter() {
+		let mut sched = get_scheduler().lock();
+		// The process is dropped after unlocking the scheduler
+		let _thread = sched.get_by_pid(*pid);
+		sched.remove_process(*pid);
+	}
+}
+
 impl Process {
 	/// Returns the process with PID `pid`.
 	///
@@ -441,6 +618,16 @@ impl Process {
 		sched_mutex.lock().get_by_pid(pid)
 	}
 
+	/// Returns the process with PID `pid` in the PID namespace of the current process.
+	///
+	/// Contrary to [`Self::get_by_pid`], this function is meant for PIDs given by userspace.
+	///
+	/// If the process doesn't exist or is not visible, the function returns `None`.
+	pub fn get_by_vpid(pid: Pid) -> Option<Arc<IntMutex<Self>>> {
+		let pid = Self::current_pid_ns()?.to_global(pid)?;
+		Self::get_by_pid(pid)
+	}
+
 	/// Returns the process with TID `tid`.
 	///
 	/// If the process doesn't exist, the function returns `None`.
@@ -457,6 +644,30 @@ impl Process {
 		sched_mutex.lock().get_current_process()
 	}
 
+	/// Returns the PID namespace of the current running process.
+	///
+	/// Since the namespace of a process never changes, this function can be called while the
+	/// current process is locked.
+	///
+	/// If no process is running, the function returns `None`.
+	pub fn current_pid_ns() -> Option<Arc<PidNamespace>> {
+		let curr_mutex = Self::current()?;
+		let pid_ns = unsafe { curr_mutex.get_payload() }.pid_ns.clone();
+		Some(pid_ns)
+	}
+
+	/// Returns the mount namespace of the current process.
+	///
+	/// Since only the process itself changes its namespace, this function can be called while the
+	/// current process is locked.
+	///
+	/// If no process is running, the function returns `None`.
+	pub fn current_mnt_ns() -> Option<Arc<MountNamespace>> {
+		let curr_mutex = Self::current()?;
+		let mnt_ns = unsafe { curr_mutex.get_payload() }.mnt_ns.clone();
+		Some(mnt_ns)
+	}
+
 	/// Returns the current running process.
 	///
 	/// If no process is running, the function makes the kernel panic.
@@ -521,10 +732,20 @@ impl Process {
 			fds_table
 		};
 
+		let pid_ns = PidNamespace::get_root()?;
+		let pid = pid_ns.alloc()?;
+		debug_assert_eq!(pid, pid::INIT_PID);
+
 		let process = Self {
 			pid: pid::INIT_PID,
 			pgid: pid::INIT_PID,
+			sid: pid::INIT_PID,
 			tid: pid::INIT_PID,
+			tgid: pid::INIT_PID,
+			threads: Arc::new(Mutex::new(crate::vec![pid::INIT_PID]?))?,
+			pid_ns: pid_ns.clone(),
+			pid_ns_for_children: pid_ns,
+			mnt_ns: MountNamespace::get_root()?,
 
 			argv: Arc::new(Vec::new())?,
 			exec_path: Arc::new(Path::root())?,
@@ -532,38 +753,56 @@ impl Process {
 			tty: tty::get(None).unwrap(), // Initialization with the init TTY
 
 			access_profile,
-			umask: DEFAULT_UMASK,
+			personality: 0,
+			oom_score_adj: 0,
+			comm: [0; COMM_LEN],
+			dumpable: true,
+			pdeath_signal: None,
+			no_new_privs: false,
+			child_subreaper: false,
+			seccomp: Seccomp::default(),
 
 			state: State::Running,
 			vfork_state: VForkState::None,
 
-			priority: 0,
+			priority: scheduler::NICE_0_WEIGHT,
 			nice: 0,
-			quantum_count: 0,
+			cpu_affinity: scheduler::CPU_SET_ALL,
+			sched_policy: scheduler::SCHED_OTHER,
+			rt_priority: 0,
+			sched_entity: Default::default(),
+			cgroup: cgroup::get_root()?,
 
 			parent: None,
 			children: Vec::new(),
-			process_group: Vec::new(),
 
 			regs: Regs::default(),
 			syscalling: false,
+			restart_block: None,
 
 			handled_signal: None,
 			saved_regs: Regs::default(),
+			saved_sigmask: 0,
 			waitable: false,
 
 			timer_manager: Arc::new(Mutex::new(TimerManager::new(pid::INIT_PID)?))?,
 
 			mem_space: None,
+			aio_contexts: Arc::new(Mutex::new(HashMap::new()))?,
 			user_stack: None,
 			kernel_stack: None,
 
-			cwd: Arc::new(Path::root())?,
-			chroot: Arc::new(Path::root())?,
+			fs: Arc::new(Mutex::new(FsInfo {
+				cwd: Arc::new(Path::root())?,
+				chroot: Arc::new(Path::root())?,
+				umask: DEFAULT_UMASK,
+			}))?,
 			file_descriptors: Some(Arc::new(Mutex::new(file_descriptors))?),
 
-			sigmask: Bitfield::new(signal::SIGNALS_COUNT)?,
-			sigpending: Bitfield::new(signal::SIGNALS_COUNT)?,
+			sigmask: 0,
+			sigwait: 0,
+			sigpending: 0,
+			sigqueue: Vec::new(),
 			signal_handlers: Arc::new(Mutex::new(
 				[SignalHandler::Default; signal::SIGNALS_COUNT],
 			))?,
@@ -572,12 +811,29 @@ impl Process {
 
 			set_child_tid: None,
 			clear_child_tid: None,
+			robust_list: None,
 
 			rusage: RUsage::default(),
+			cpu_times: CpuTimes::default(),
+			cpu_time_start: 0,
+			start_time: clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond)?,
+			acct_flags: 0,
+			rlimits: RLimits::default(),
 
 			exit_status: 0,
 			termsig: 0,
+			core_dumped: false,
+
+			kthread: None,
 		};
+		process.cgroup.attach();
+		pgroup::add(process.pgid, process.sid, process.pid)?;
+		// The init TTY is the controlling terminal of the first session
+		{
+			let mut tty = process.tty.lock();
+			tty.set_sid(pid::INIT_PID);
+			tty.set_pgrp(pid::INIT_PID);
+		}
 
 		process.register_procfs()?;
 
@@ -591,6 +847,12 @@ impl Process {
 		self.pid == pid::INIT_PID
 	}
 
+	/// Tells whether the process is a kernel thread.
+	#[inline(always)]
+	pub fn is_kthread(&self) -> bool {
+		self.kthread.is_some()
+	}
+
 	/// Tells whether the process is among a group and is not its owner.
 	#[inline(always)]
 	pub fn is_in_group(&self) -> bool {
@@ -598,6 +860,12 @@ impl Process {
 	}
 
 	/// Sets the process's group ID to the given value `pgid`.
+	///
+	/// If `pgid` is zero, the process is moved to a new group whose ID is the PID of the process.
+	///
+	/// If the group doesn't exist and isn't a new group of the process, the function returns
+	/// [`errno::EPERM`]. The function also returns this error if the group belongs to another
+	/// session.
 	pub fn set_pgid(&mut self, pgid: Pid) -> Result<(), Errno> {
 		let old_pgid = self.pgid;
 		let new_pgid = if pgid == 0 { self.pid } else { pgid };
@@ -605,51 +873,287 @@ impl Process {
 		if old_pgid == new_pgid {
 			return Ok(());
 		}
+		if new_pgid != self.pid && !pgroup::exists(new_pgid) {
+			return Err(errno!(EPERM));
+		}
 
-		if new_pgid != self.pid {
-			// Adding the process to the new group
-			if let Some(proc_mutex) = Process::get_by_pid(new_pgid) {
-				let mut new_group_process = proc_mutex.lock();
+		pgroup::add(new_pgid, self.sid, self.pid)?;
+		pgroup::remove(old_pgid, self.pid);
+		self.pgid = new_pgid;
+		Ok(())
+	}
+
+	/// Tells whether the process is a session leader.
+	#[inline(always)]
+	pub fn is_session_leader(&self) -> bool {
+		self.sid == self.pid
+	}
+
+	/// Creates a new session, of which the process is the leader. The process is moved to a new
+	/// process group in this session, and has no controlling terminal.
+	///
+	/// If the process is already the leader of a process group, the function returns
+	/// [`errno::EPERM`].
+	pub fn new_session(&mut self) -> EResult<()> {
+		if pgroup::exists(self.pid) {
+			return Err(errno!(EPERM));
+		}
+
+		pgroup::add(self.pid, self.pid, self.pid)?;
+		pgroup::remove(self.pgid, self.pid);
+		self.sid = self.pid;
+		self.pgid = self.pid;
+		Ok(())
+	}
 
-				let i = new_group_process
-					.process_group
-					.binary_search(&self.pid)
-					.unwrap_err();
-				new_group_process.process_group.insert(i, self.pid)?;
+	/// Tells whether the process group `pgid` is orphaned, ignoring the process with PID
+	/// `ignore`.
+	///
+	/// A process group is orphaned when none of its members has a parent in another process group
+	/// of the same session. Such a group cannot be controlled by a job control shell anymore.
+	///
+	/// Since the process is locked, it is used in place of its own lock.
+	fn is_orphan_group(&self, pgid: Pid, ignore: Pid) -> bool {
+		let Some(sid) = pgroup::get_session(pgid) else {
+			return true;
+		};
+		let members = oom::wrap(|| pgroup::get_members(pgid));
+		let this = Process::get_by_pid(self.pid);
+		for pid in members {
+			if pid == ignore {
+				continue;
+			}
+			let parent = if pid == self.pid {
+				self.parent.clone()
 			} else {
-				return Err(errno!(ESRCH));
+				let Some(member_mutex) = Process::get_by_pid(pid) else {
+					continue;
+				};
+				let parent = member_mutex.lock().parent.clone();
+				parent
+			};
+			let Some(parent_mutex) = parent.and_then(|parent| parent.upgrade()) else {
+				continue;
+			};
+			let is_self = this
+				.as_ref()
+				.map(|this| this.as_ptr() == parent_mutex.as_ptr())
+				.unwrap_or(false);
+			let (parent_pid, parent_pgid, parent_sid) = if is_self {
+				(self.pid, self.pgid, self.sid)
+			} else {
+				let parent = parent_mutex.lock();
+				(parent.pid, parent.pgid, parent.sid)
+			};
+			if parent_pid != ignore && parent_pgid != pgid && parent_sid == sid {
+				return false;
 			}
 		}
+		true
+	}
+
+	/// Tells whether the process is in an orphaned process group.
+	pub fn is_in_orphan_process_group(&self) -> bool {
+		self.is_orphan_group(self.pgid, 0)
+	}
 
-		// Removing the process from its old group
-		if self.is_in_group() {
-			if let Some(proc_mutex) = Process::get_by_pid(old_pgid) {
-				let mut old_group_process = proc_mutex.lock();
+	/// Tells whether the process group `pgid` has stopped members, ignoring the process itself.
+	fn has_stopped_members(&self, pgid: Pid) -> bool {
+		oom::wrap(|| pgroup::get_members(pgid))
+			.into_iter()
+			.filter(|pid| *pid != self.pid)
+			.filter_map(Process::get_by_pid)
+			.any(|proc_mutex| proc_mutex.lock().get_state() == &State::Stopped)
+	}
 
-				if let Ok(i) = old_group_process.process_group.binary_search(&self.pid) {
-					old_group_process.process_group.remove(i);
-				}
-			}
+	/// Returns the PID namespace of the process.
+	pub fn get_pid_ns(&self) -> &Arc<PidNamespace> {
+		&self.pid_ns
+	}
+
+	/// Returns the PID namespace in which the children of the process are created.
+	pub fn get_pid_ns_for_children(&self) -> &Arc<PidNamespace> {
+		&self.pid_ns_for_children
+	}
+
+	/// Makes the future children of the process be created in a new PID namespace. The process
+	/// itself remains in its current namespace.
+	///
+	/// If the children of the process are already created in another namespace, the function
+	/// returns [`errno::EINVAL`].
+	pub fn unshare_pid_ns(&mut self) -> EResult<()> {
+		if !ptr::eq(&*self.pid_ns_for_children, &*self.pid_ns) {
+			return Err(errno!(EINVAL));
 		}
+		self.pid_ns_for_children = PidNamespace::new_child(&self.pid_ns)?;
+		Ok(())
+	}
 
-		self.pgid = new_pgid;
+	/// Returns the mount namespace of the process.
+	pub fn get_mnt_ns(&self) -> &Arc<MountNamespace> {
+		&self.mnt_ns
+	}
+
+	/// Moves the process into a new mount namespace, which is a copy of its current one.
+	pub fn unshare_mnt_ns(&mut self) -> EResult<()> {
+		self.mnt_ns = self.mnt_ns.copy()?;
 		Ok(())
 	}
 
-	/// Returns a reference to the list of PIDs of processes in the current
-	/// process's group.
-	#[inline(always)]
-	pub fn get_group_processes(&self) -> &Vec<Pid> {
-		&self.process_group
+	/// Makes the future children of the process be created in the PID namespace `pid_ns`.
+	///
+	/// If `pid_ns` is neither the namespace of the process nor one of its descendants, the
+	/// function returns [`errno::EINVAL`].
+	pub fn set_pid_ns_for_children(&mut self, pid_ns: Arc<PidNamespace>) -> EResult<()> {
+		if !pid_ns.is_in(&self.pid_ns) {
+			return Err(errno!(EINVAL));
+		}
+		self.pid_ns_for_children = pid_ns;
+		Ok(())
 	}
 
-	/// The function tells whether the process is in an orphaned process group.
-	pub fn is_in_orphan_process_group(&self) -> bool {
-		if !self.is_in_group() {
-			return false;
+	/// Moves the process into the mount namespace `mnt_ns`.
+	///
+	/// Since paths may not exist in the new namespace, the root and working directories of the
+	/// process are reset to the root of the namespace. Filesystem information is not shared with
+	/// other processes anymore.
+	pub fn set_mnt_ns(&mut self, mnt_ns: Arc<MountNamespace>) -> EResult<()> {
+		let umask = self.fs.lock().umask;
+		let root = Arc::new(Path::root())?;
+		self.fs = Arc::new(Mutex::new(FsInfo {
+			cwd: root.clone(),
+			chroot: root,
+			umask,
+		}))?;
+		self.mnt_ns = mnt_ns;
+		Ok(())
+	}
+
+	/// Translates the PID `pid` in the root namespace into the PID seen from the process's
+	/// namespace.
+	///
+	/// If the corresponding process is not visible from the namespace, the function returns `0`.
+	pub fn to_local_pid(&self, pid: Pid) -> Pid {
+		self.pid_ns.to_local(pid).unwrap_or(0)
+	}
+
+	/// Translates the PID `pid` seen from the process's namespace into the PID in the root
+	/// namespace.
+	///
+	/// If no process has this PID in the namespace, the function returns `None`.
+	pub fn to_global_pid(&self, pid: Pid) -> Option<Pid> {
+		self.pid_ns.to_global(pid)
+	}
+
+	/// Tells whether the process is the init process of its PID namespace.
+	pub fn is_ns_init(&self) -> bool {
+		self.pid_ns.get_init() == Some(self.pid)
+	}
+
+	/// Returns the PID of the process adopting the children of the process when it exits.
+	///
+	/// This is the closest living ancestor marked as a child subreaper, within the process's
+	/// namespace. If there is none, the init process of the namespace is used. If it has exited,
+	/// the init process of the closest ancestor namespace is used instead.
+	fn get_reaper(&self) -> Pid {
+		if !self.is_ns_init() {
+			if let Some(pid) = self.find_subreaper() {
+				return pid;
+			}
 		}
+		self.pid_ns
+			.iter_path()
+			.filter(|ns| !ns.is_dead())
+			.filter_map(|ns| ns.get_init())
+			.find(|pid| *pid != self.pid)
+			.unwrap_or(pid::INIT_PID)
+	}
 
-		Process::get_by_pid(self.pgid).is_none()
+	/// Returns the PID of the closest ancestor of the process marked as a child subreaper.
+	///
+	/// The search stops at the init process of the process's namespace. Ancestors that have
+	/// exited are skipped.
+	fn find_subreaper(&self) -> Option<Pid> {
+		let ns_init = self.pid_ns.get_init();
+		let mut parent = self.parent.as_ref().and_then(|parent| parent.upgrade());
+		while let Some(proc_mutex) = parent {
+			let proc = proc_mutex.lock();
+			if Some(proc.pid) == ns_init {
+				break;
+			}
+			if proc.child_subreaper && proc.state != State::Zombie {
+				return Some(proc.pid);
+			}
+			parent = proc.parent.as_ref().and_then(|parent| parent.upgrade());
+		}
+		None
+	}
+
+	/// Kills every other process of the process's PID namespace and of its descendants.
+	///
+	/// This function is called when the init process of the namespace exits.
+	fn kill_pid_ns(&self) {
+		let procs = oom::wrap(|| {
+			let mut procs = Vec::new();
+			let mut sched = get_scheduler().lock();
+			for (pid, proc_mutex) in sched.iter_process() {
+				if *pid != self.pid && proc_mutex.lock().pid_ns.is_in(&self.pid_ns) {
+					procs.push(proc_mutex.clone())?;
+				}
+			}
+			Ok(procs)
+		});
+		for proc_mutex in procs {
+			proc_mutex.lock().kill(&Signal::SIGKILL, false);
+		}
+	}
+
+	/// Sends the job control signals caused by the exit of the process.
+	///
+	/// If the process is the leader of a session, its controlling terminal is released and the
+	/// foreground process group receives `SIGHUP`.
+	///
+	/// Process groups that become orphaned because of the exit and have stopped members receive
+	/// `SIGHUP`, then `SIGCONT`, since they would be stopped forever otherwise.
+	fn hangup(&self) {
+		if self.is_session_leader() {
+			let fg_pgrp = {
+				let mut tty = self.tty.lock();
+				if tty.get_sid() == self.sid {
+					let pgrp = tty.get_pgrp();
+					tty.set_sid(0);
+					tty.set_pgrp(0);
+					Some(pgrp)
+				} else {
+					None
+				}
+			};
+			if let Some(pgrp) = fg_pgrp.filter(|pgrp| *pgrp != 0) {
+				self.kill_other_group(pgrp, &Signal::SIGHUP, false);
+				self.kill_other_group(pgrp, &Signal::SIGCONT, false);
+			}
+		}
+
+		// The children have been reparented already, thus their groups are checked as well
+		let groups = oom::wrap(|| {
+			let mut groups = crate::vec![self.pgid]?;
+			for child_pid in self.children.iter() {
+				let Some(child_mutex) = Process::get_by_pid(*child_pid) else {
+					continue;
+				};
+				let pgid = child_mutex.lock().pgid;
+				if !groups.contains(&pgid) {
+					groups.push(pgid)?;
+				}
+			}
+			Ok(groups)
+		});
+		for pgid in groups {
+			if self.is_orphan_group(pgid, self.pid) && self.has_stopped_members(pgid) {
+				self.kill_other_group(pgid, &Signal::SIGHUP, false);
+				self.kill_other_group(pgid, &Signal::SIGCONT, false);
+			}
+		}
 	}
 
 	/// Returns the parent process's PID.
@@ -657,7 +1161,7 @@ impl Process {
 		self.parent
 			.as_ref()
 			.and_then(|parent| parent.upgrade())
-			.map(|parent| parent.lock().pid)
+			.map(|parent| parent.lock().tgid)
 			.unwrap_or(self.pid)
 	}
 
@@ -666,6 +1170,17 @@ impl Process {
 		self.tty.clone()
 	}
 
+	/// Sets the TTY associated with the process.
+	pub fn set_tty(&mut self, tty: TTYHandle) {
+		self.tty = tty;
+	}
+
+	/// Tells whether the TTY associated with the process is the controlling terminal of its
+	/// session.
+	pub fn has_ctty(&self) -> bool {
+		self.tty.lock().get_sid() == self.sid
+	}
+
 	/// Returns the process's current state.
 	#[inline(always)]
 	pub fn get_state(&self) -> &State {
@@ -680,7 +1195,7 @@ impl Process {
 
 		// Update the number of running processes
 		if self.state != State::Running && new_state == State::Running {
-			get_scheduler().lock().increment_running();
+			get_scheduler().lock().wake_process(self);
 		} else if self.state == State::Running {
 			get_scheduler().lock().decrement_running();
 		}
@@ -692,12 +1207,22 @@ impl Process {
 				panic!("Terminated init process!");
 			}
 
+			// A zombie process does not belong to its control group anymore
+			self.cgroup.detach();
+
 			// Removing the memory space and file descriptors table to save memory
 			//self.mem_space = None; // TODO Handle the case where the memory space is bound
 			self.file_descriptors = None;
+			record_lock::release_all(self.pid);
 
-			// Attaching every child to the init process
-			let init_proc_mutex = Process::get_by_pid(pid::INIT_PID).unwrap();
+			// The namespace dies with its init process
+			let ns_init = self.is_ns_init();
+			if ns_init {
+				self.pid_ns.disable_alloc();
+			}
+
+			// Attaching every child to the reaper
+			let init_proc_mutex = Process::get_by_pid(self.get_reaper()).unwrap();
 			let mut init_proc = init_proc_mutex.lock();
 			for child_pid in self.children.iter() {
 				// Check just in case
@@ -706,20 +1231,86 @@ impl Process {
 				}
 
 				if let Some(child_mutex) = Process::get_by_pid(*child_pid) {
-					child_mutex.lock().parent = Some(Arc::downgrade(&init_proc_mutex));
+					let mut child = child_mutex.lock();
+					child.parent = Some(Arc::downgrade(&init_proc_mutex));
 					oom::wrap(|| init_proc.add_child(*child_pid));
+					if let Some(sig) = child.pdeath_signal.clone() {
+						child.kill(&sig, false);
+					}
+					// The reaper has to collect children that have already exited
+					if child.is_waitable() {
+						init_proc.kill(&Signal::SIGCHLD, false);
+						init_proc.wake();
+					}
 				}
 			}
+			drop(init_proc);
+
+			if ns_init {
+				self.kill_pid_ns();
+			}
+			self.hangup();
 
 			self.waitable = true;
 		}
 	}
 
+	/// Returns the nice value of the process.
+	pub fn get_nice(&self) -> i32 {
+		self.nice
+	}
+
+	/// Sets the nice value of the process, updating its scheduling weight.
+	///
+	/// The value is clamped between [`scheduler::NICE_MIN`] and [`scheduler::NICE_MAX`].
+	pub fn set_nice(&mut self, nice: i32) {
+		self.nice = nice.clamp(scheduler::NICE_MIN, scheduler::NICE_MAX);
+		self.priority = scheduler::nice_to_weight(self.nice);
+	}
+
+	/// Returns the state of the process for the fair scheduler.
+	pub fn get_sched_entity(&self) -> &scheduler::SchedEntity {
+		&self.sched_entity
+	}
+
+	/// Returns the control group of the process.
+	pub fn get_cgroup(&self) -> &Arc<CGroup> {
+		&self.cgroup
+	}
+
+	/// Moves the process to the control group `cgroup`.
+	///
+	/// The memory charged for the process's memory space is moved to the new group.
+	///
+	/// If the process is a zombie, the function returns [`errno::ESRCH`].
+	pub fn set_cgroup(&mut self, cgroup: Arc<CGroup>) -> EResult<()> {
+		if self.state == State::Zombie {
+			return Err(errno!(ESRCH));
+		}
+		self.cgroup.detach();
+		cgroup.attach();
+		if let Some(mem_space) = &self.mem_space {
+			mem_space.lock().set_cgroup(cgroup.clone());
+		}
+		self.cgroup = cgroup;
+		Ok(())
+	}
+
 	/// Tells whether the scheduler can run the process.
 	pub fn can_run(&self) -> bool {
 		matches!(self.get_state(), State::Running) && self.vfork_state != VForkState::Waiting
 	}
 
+	/// Returns the real-time priority of the process.
+	///
+	/// If the process is not scheduled with a real-time policy, the function returns `None`.
+	pub fn get_rt_priority(&self) -> Option<u32> {
+		match self.sched_policy {
+			scheduler::SCHED_FIFO | scheduler::SCHED_RR => Some(self.rt_priority),
+			_ => None,
+		}
+	}
+
 	/// Wakes the process if sleeping.
 	pub fn wake(&mut self) {
 		if self.state == State::Sleeping {
@@ -800,6 +1391,12 @@ impl Process {
 		self.mem_space.as_ref()
 	}
 
+	/// Returns the AIO contexts of the process's memory space.
+	#[inline(always)]
+	pub fn get_aio_contexts(&self) -> &Arc<Mutex<AioContextTable>> {
+		&self.aio_contexts
+	}
+
 	/// Sets the new memory space for the process, dropping the previous if any.
 	#[inline(always)]
 	pub fn set_mem_space(&mut self, mem_space: Option<Arc<IntMutex<MemSpace>>>) {
@@ -812,10 +1409,25 @@ impl Process {
 				panic!("Dropping the memory space of a running process!");
 			}
 		}
+		// The memory of the new memory space is charged to the process's control group
+		if let Some(mem_space) = &mem_space {
+			mem_space.lock().set_cgroup(self.cgroup.clone());
+		}
 
 		self.mem_space = mem_space;
 	}
 
+	/// Returns the permissions `mode` of a file to be created by the process, with the process's
+	/// umask applied.
+	pub fn apply_umask(&self, mode: file::Mode) -> file::Mode {
+		mode & 0o7777 & !self.fs.lock().umask
+	}
+
+	/// Returns the filesystem information of the process.
+	pub fn get_fs(&self) -> &Arc<Mutex<FsInfo>> {
+		&self.fs
+	}
+
 	/// Returns the file descriptor table associated with the process.
 	pub fn get_fds(&self) -> Option<&Arc<Mutex<FileDescriptorTable>>> {
 		self.file_descriptors.as_ref()
@@ -826,6 +1438,43 @@ impl Process {
 		self.file_descriptors = fds;
 	}
 
+	/// Sets the limit for the given resource, then applies the limits to the resources of the
+	/// process.
+	///
+	/// Arguments and errors are the same as [`RLimits::set`].
+	pub fn set_rlimit(
+		&mut self,
+		resource: i32,
+		limit: RLimit,
+		access_profile: &AccessProfile,
+	) -> EResult<()> {
+		self.rlimits.set(resource, limit, access_profile)?;
+		self.apply_rlimits();
+		Ok(())
+	}
+
+	/// Applies the resource limits of the process to its memory space and its file descriptor
+	/// table.
+	pub fn apply_rlimits(&self) {
+		if let Some(mem_space) = &self.mem_space {
+			// Privileged processes are not restricted in the amount of memory they lock
+			let memlock_limit = if self.access_profile.has_cap(CAP_IPC_LOCK) {
+				rlimit::RLIM_INFINITY
+			} else {
+				self.rlimits.get_cur(rlimit::RLIMIT_MEMLOCK)
+			};
+			mem_space.lock().set_limits(
+				self.rlimits.get_cur(rlimit::RLIMIT_AS),
+				self.rlimits.get_cur(rlimit::RLIMIT_STACK),
+				memlock_limit,
+			);
+		}
+		if let Some(fds) = &self.file_descriptors {
+			fds.lock()
+				.set_limit(self.rlimits.get_cur(rlimit::RLIMIT_NOFILE));
+		}
+	}
+
 	/// Updates the TSS on the current core for the process.
 	pub fn update_tss(&self) {
 		// Compute the kernel stack pointer
@@ -836,11 +1485,10 @@ impl Process {
 		}
 
 		// Fill the TSS
-		unsafe {
-			TSS.0.esp0 = kernel_stack_ptr as _;
-			TSS.0.ss0 = gdt::KERNEL_DS as _;
-			TSS.0.ss = gdt::USER_DS as _;
-		}
+		let tss = unsafe { tss::get_current() };
+		tss.esp0 = kernel_stack_ptr as _;
+		tss.ss0 = gdt::KERNEL_DS as _;
+		tss.ss = gdt::USER_DS as _;
 	}
 
 	/// Prepares for context switching to the process.
@@ -870,11 +1518,11 @@ impl Process {
 		}
 		gdt::flush();
 
-		// Bind the memory space
-		self.get_mem_space().unwrap().lock().bind();
-
-		// Increment the number of ticks the process had
-		self.quantum_count += 1;
+		// Bind the memory space. Kernel threads use the kernel's
+		match self.get_mem_space() {
+			Some(mem_space) => mem_space.lock().bind(),
+			None => crate::bind_vmem(),
+		}
 	}
 
 	/// Returns the exit status if the process has ended.
@@ -893,13 +1541,35 @@ impl Process {
 		self.termsig
 	}
 
+	/// Tells whether the process has dumped a core when terminated.
+	#[inline(always)]
+	pub fn has_core_dumped(&self) -> bool {
+		self.core_dumped
+	}
+
+	/// Records that the process has dumped a core.
+	///
+	/// The thread group leader is marked too, since it is the one being waited for.
+	pub fn set_core_dumped(&mut self) {
+		self.core_dumped = true;
+		self.acct_flags |= acct::ACORE;
+		if !self.is_thread_group_leader() {
+			if let Some(leader_mutex) = Process::get_by_pid(self.tgid) {
+				let mut leader = leader_mutex.lock();
+				leader.core_dumped = true;
+				leader.acct_flags |= acct::ACORE;
+			}
+		}
+	}
+
 	/// Forks the current process.
 	///
 	/// The internal state of the process (registers and memory) are always copied.
 	/// Other data may be copied according to provided fork options.
 	///
 	/// Arguments:
-	/// - `parent` is the parent of the new process.
+	/// - `parent` is the parent of the new process. If the new process is a thread, it takes the
+	/// parent of the current process instead.
 	/// - `fork_options` are the options for the fork operation.
 	///
 	/// On fail, the function returns an `Err` with the appropriate Errno.
@@ -912,10 +1582,28 @@ impl Process {
 	) -> EResult<Arc<IntMutex<Self>>> {
 		debug_assert!(matches!(self.get_state(), State::Running));
 
-		// Handle vfork
+		// Enforce the limit on the number of processes of the user
+		let nproc_limit = self.rlimits.get_cur(rlimit::RLIMIT_NPROC);
+		let privileged = self.access_profile.has_cap(CAP_SYS_RESOURCE)
+			|| self.access_profile.has_cap(CAP_SYS_ADMIN);
+		if nproc_limit != rlimit::RLIM_INFINITY && !privileged {
+			let uid = self.access_profile.get_uid();
+			let sched_mutex = unsafe { SCHEDULER.assume_init_mut() };
+			let count = sched_mutex
+				.lock()
+				.iter_process()
+				.filter(|(pid, proc_mutex)| {
+					**pid == self.pid || proc_mutex.lock().access_profile.get_uid() == uid
+				})
+				.count();
+			if count as rlimit::RLim >= nproc_limit {
+				return Err(errno!(EAGAIN));
+			}
+		}
+
+		// Handle vfork. The current process waits only once the child has been created
 		let vfork_state = if fork_options.vfork {
-			self.vfork_state = VForkState::Waiting; // TODO Cancel if the following code fails
-			VForkState::Executing
+			VForkState::Executing(self.pid)
 		} else {
 			VForkState::None
 		};
@@ -939,6 +1627,13 @@ impl Process {
 			}
 		};
 
+		// AIO contexts are bound to the memory space
+		let aio_contexts = if fork_options.share_memory || fork_options.vfork {
+			self.aio_contexts.clone()
+		} else {
+			Arc::new(Mutex::new(HashMap::new()))?
+		};
+
 		// Clone file descriptors
 		let file_descriptors = if fork_options.share_fd {
 			self.file_descriptors.clone()
@@ -954,23 +1649,59 @@ impl Process {
 				.transpose()?
 		};
 
+		// Clone filesystem information
+		let fs = if fork_options.share_fs {
+			self.fs.clone()
+		} else {
+			let fs = self.fs.lock();
+			Arc::new(Mutex::new(FsInfo {
+				cwd: fs.cwd.clone(),
+				chroot: fs.chroot.clone(),
+				umask: fs.umask,
+			}))?
+		};
+
 		// Clone signal handlers
 		let signal_handlers = if fork_options.share_sighand {
 			self.signal_handlers.clone()
 		} else {
-			Arc::new(Mutex::new(self.signal_handlers.lock().clone()))?
+			Arc::new(Mutex::new(*self.signal_handlers.lock()))?
 		};
 
+		let pid_ns = if fork_options.new_pid_ns {
+			PidNamespace::new_child(&self.pid_ns_for_children)?
+		} else {
+			self.pid_ns_for_children.clone()
+		};
+		// A thread cannot be in another namespace than the rest of its group
+		if fork_options.thread && !ptr::eq(&*pid_ns, &*self.pid_ns) {
+			return Err(errno!(EINVAL));
+		}
+		let mnt_ns = if fork_options.new_mnt_ns {
+			self.mnt_ns.copy()?
+		} else {
+			self.mnt_ns.clone()
+		};
 		// FIXME PID is leaked if the following code fails
-		let pid = {
-			let mutex = unsafe { PID_MANAGER.assume_init_mut() };
-			mutex.lock().get_unique_pid()
-		}?;
+		let pid = pid_ns.alloc()?;
 
+		let (parent, tgid, threads) = if fork_options.thread {
+			self.threads.lock().push(pid)?;
+			(self.parent.clone(), self.tgid, self.threads.clone())
+		} else {
+			let threads = Arc::new(Mutex::new(crate::vec![pid]?))?;
+			(Some(parent), pid, threads)
+		};
 		let process = Self {
 			pid,
 			pgid: self.pgid,
+			sid: self.sid,
 			tid: pid,
+			tgid,
+			threads,
+			pid_ns: pid_ns.clone(),
+			pid_ns_for_children: pid_ns,
+			mnt_ns,
 
 			argv: self.argv.clone(),
 			exec_path: self.exec_path.clone(),
@@ -978,58 +1709,100 @@ impl Process {
 			tty: self.tty.clone(),
 
 			access_profile: self.access_profile,
-			umask: self.umask,
+			personality: self.personality,
+			oom_score_adj: self.oom_score_adj,
+			comm: self.comm,
+			dumpable: self.dumpable,
+			pdeath_signal: None,
+			no_new_privs: self.no_new_privs,
+			child_subreaper: false,
+			seccomp: self.seccomp.clone(),
 
 			state: State::Running,
 			vfork_state,
 
 			priority: self.priority,
 			nice: self.nice,
-			quantum_count: 0,
-
-			parent: Some(parent),
+			cpu_affinity: self.cpu_affinity,
+			sched_policy: self.sched_policy,
+			rt_priority: self.rt_priority,
+			// The child starts with the same virtual runtime to avoid favoring it
+			sched_entity: scheduler::SchedEntity {
+				vruntime: self.sched_entity.vruntime,
+				..Default::default()
+			},
+			cgroup: self.cgroup.clone(),
+
+			parent,
 			children: Vec::new(),
-			process_group: Vec::new(),
 
 			regs: self.regs.clone(),
 			syscalling: false,
+			restart_block: None,
 
 			handled_signal: self.handled_signal.clone(),
 			saved_regs: self.saved_regs.clone(),
+			saved_sigmask: self.saved_sigmask,
 			waitable: false,
 
-			// TODO if creating a thread: timer_manager: self.timer_manager.clone(),
-			timer_manager: Arc::new(Mutex::new(TimerManager::new(pid)?))?,
+			timer_manager: if fork_options.thread {
+				self.timer_manager.clone()
+			} else {
+				Arc::new(Mutex::new(TimerManager::new(pid)?))?
+			},
 
 			mem_space: Some(mem_space),
+			aio_contexts,
 			user_stack: self.user_stack,
 			kernel_stack,
 
-			cwd: self.cwd.clone(),
-			chroot: self.chroot.clone(),
+			fs,
 			file_descriptors,
 
-			sigmask: self.sigmask.try_clone()?,
-			sigpending: Bitfield::new(signal::SIGNALS_COUNT)?,
+			sigmask: self.sigmask,
+			sigwait: 0,
+			sigpending: 0,
+			sigqueue: Vec::new(),
 			signal_handlers,
 
 			tls_entries: self.tls_entries,
 
-			set_child_tid: self.set_child_tid,
-			clear_child_tid: self.clear_child_tid,
+			// Set by `clone` according to its flags
+			set_child_tid: None,
+			clear_child_tid: None,
+			// The child does not hold the parent's futexes
+			robust_list: None,
 
 			rusage: RUsage::default(),
+			cpu_times: CpuTimes::default(),
+			cpu_time_start: 0,
+			start_time: clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond)?,
+			acct_flags: acct::AFORK,
+			rlimits: self.rlimits.clone(),
 
 			exit_status: self.exit_status,
 			termsig: 0,
+			core_dumped: false,
+
+			kthread: None,
 		};
+		process.cgroup.attach();
 
 		process.register_procfs()?;
 
-		self.add_child(pid)?;
+		// A thread is not a child of the current process. Only thread group leaders are members
+		// of process groups, so that signals are sent once to each thread group
+		if !fork_options.thread {
+			pgroup::add(process.pgid, process.sid, pid)?;
+			self.add_child(pid)?;
+		}
 
 		let sched_mutex = unsafe { SCHEDULER.assume_init_mut() };
-		Ok(sched_mutex.lock().add_process(process)?)
+		let process = sched_mutex.lock().add_process(process)?;
+		if fork_options.vfork {
+			self.vfork_state = VForkState::Waiting;
+		}
+		Ok(process)
 	}
 
 	// TODO return a &Arc instead of locking
@@ -1060,12 +1833,24 @@ impl Process {
 	/// the function executes the default action of the signal regardless the
 	/// user-specified action.
 	pub fn kill(&mut self, sig: &Signal, no_handler: bool) {
-		if sig.can_catch() && self.sigmask.is_set(sig.get_id() as _) {
-			return;
-		}
-
-		self.rusage.ru_nsignals += 1;
+		// If the signal cannot be queued, it is discarded
+		let _ = self.kill_with_info(sig, SigInfo::new(sig, SI_KERNEL), no_handler);
+	}
 
+	/// Same as [`Self::kill`], except the signal is sent with the given informations.
+	///
+	/// If the signal is real-time and the limit of queued signals is reached, the function
+	/// returns [`errno::EAGAIN`].
+	pub fn kill_with_info(
+		&mut self,
+		sig: &Signal,
+		info: SigInfo,
+		no_handler: bool,
+	) -> EResult<()> {
+		// Kernel threads ignore signals
+		if self.is_kthread() {
+			return Ok(());
+		}
 		if matches!(self.get_state(), State::Stopped)
 			&& sig.get_default_action() == SignalAction::Continue
 		{
@@ -1074,43 +1859,76 @@ impl Process {
 
 		let no_handler = self.is_handling_signal() && no_handler;
 		if !sig.can_catch() || no_handler {
+			self.rusage.ru_nsignals += 1;
 			sig.execute_action(self, no_handler);
-		} else {
-			self.sigpending.set(sig.get_id() as _);
+			return Ok(());
+		}
+
+		// Queue the signal. Only one instance of a standard signal can be pending
+		let pending = self.sigpending & sig.get_mask() != 0;
+		if sig.is_realtime() {
+			let limit = self.rlimits.get_cur(rlimit::RLIMIT_SIGPENDING);
+			if self.sigqueue.len() as rlimit::RLim >= limit {
+				return Err(errno!(EAGAIN));
+			}
+		} else if pending {
+			return Ok(());
+		}
+		self.sigqueue.push(info)?;
+		self.sigpending |= sig.get_mask();
+		self.rusage.ru_nsignals += 1;
+
+		// Interrupt the blocking operation so that the signal can be handled or accepted
+		if !self.is_signal_blocked(sig) || self.sigwait & sig.get_mask() != 0 {
+			self.wake();
 		}
+		Ok(())
 	}
 
 	/// Kills every processes in the process group.
 	///
 	/// Arguments are the same as `kill`.
 	pub fn kill_group(&mut self, sig: Signal, no_handler: bool) {
-		for pid in self.process_group.iter() {
-			if *pid != self.pid {
-				if let Some(proc_mutex) = Process::get_by_pid(*pid) {
-					let mut proc = proc_mutex.lock();
-					proc.kill(&sig, no_handler);
-				}
+		let pgid = self.pgid;
+		self.kill_other_group(pgid, &sig, no_handler);
+		self.kill(&sig, no_handler);
+	}
+
+	/// Kills every processes in the process group `pgid`, except the process itself.
+	///
+	/// Arguments are the same as `kill`.
+	fn kill_other_group(&self, pgid: Pid, sig: &Signal, no_handler: bool) {
+		for pid in oom::wrap(|| pgroup::get_members(pgid)) {
+			if pid == self.pid {
+				continue;
+			}
+			if let Some(proc_mutex) = Process::get_by_pid(pid) {
+				proc_mutex.lock().kill(sig, no_handler);
 			}
 		}
-
-		self.kill(&sig, no_handler);
 	}
 
 	/// Tells whether the given signal is blocked by the process.
 	pub fn is_signal_blocked(&self, sig: &Signal) -> bool {
-		self.sigmask.is_set(sig.get_id() as _)
+		sig.can_catch() && self.sigmask & sig.get_mask() != 0
 	}
 
-	/// Returns an immutable reference to the process's pending signals mask.
+	/// Returns the set of pending signals.
 	#[inline(always)]
-	pub fn get_pending_signals(&self) -> &[u8] {
-		self.sigpending.as_slice()
+	pub fn get_pending_signals(&self) -> SigSet {
+		self.sigpending
+	}
+
+	/// Returns the number of signal instances waiting to be delivered.
+	#[inline(always)]
+	pub fn get_queued_signals_count(&self) -> usize {
+		self.sigqueue.len()
 	}
 
 	/// Tells whether the process has a signal pending.
 	#[inline(always)]
 	pub fn has_signal_pending(&self) -> bool {
-		self.sigpending.find_set().is_some()
+		self.sigpending != 0
 	}
 
 	/// Returns the ID of the next signal to be executed.
@@ -1122,22 +1940,10 @@ impl Process {
 			return None;
 		}
 
-		self.sigpending
-			.iter()
-			.enumerate()
-			.filter_map(|(i, b)| {
-				if !b {
-					return None;
-				}
-
-				let s = Signal::try_from(i as u32).ok()?;
-				if !s.can_catch() || !self.sigmask.is_set(i) {
-					Some(s)
-				} else {
-					None
-				}
-			})
-			.next()
+		// Signals with the lowest ID are delivered first
+		(1..signal::SIGNALS_COUNT as u32)
+			.filter_map(|i| Signal::try_from(i).ok())
+			.find(|s| self.sigpending & s.get_mask() != 0 && !self.is_signal_blocked(s))
 	}
 
 	/// Makes the process handle the next signal.
@@ -1160,7 +1966,27 @@ impl Process {
 	///
 	/// If the signal is already cleared, the function does nothing.
 	pub fn signal_clear(&mut self, sig: Signal) {
-		self.sigpending.clear(sig.get_id() as _);
+		let id = sig.get_id() as i32;
+		self.sigqueue.retain(|info| info.si_signo != id);
+		self.sigpending &= !sig.get_mask();
+	}
+
+	/// Removes the first pending instance of the given signal and returns its informations.
+	///
+	/// If the signal is not pending, the function returns informations telling the signal has
+	/// been sent by the kernel.
+	pub fn signal_dequeue(&mut self, sig: &Signal) -> SigInfo {
+		let id = sig.get_id() as i32;
+		let info = self
+			.sigqueue
+			.iter()
+			.position(|info| info.si_signo == id)
+			.map(|i| self.sigqueue.remove(i))
+			.unwrap_or_else(|| SigInfo::new(sig, SI_KERNEL));
+		if !self.sigqueue.iter().any(|info| info.si_signo == id) {
+			self.sigpending &= !sig.get_mask();
+		}
+		info
 	}
 
 	/// Saves the process's state to handle a signal.
@@ -1172,6 +1998,7 @@ impl Process {
 		debug_assert!(!self.is_handling_signal());
 
 		self.saved_regs = self.regs.clone();
+		self.saved_sigmask = self.sigmask;
 		self.handled_signal = Some(sig);
 	}
 
@@ -1180,6 +2007,7 @@ impl Process {
 		if self.handled_signal.is_some() {
 			self.handled_signal = None;
 			self.regs = self.saved_regs.clone();
+			self.sigmask = self.saved_sigmask;
 		}
 	}
 
@@ -1214,29 +2042,93 @@ impl Process {
 		self.clear_child_tid = ptr;
 	}
 
+	/// Returns the head of the process's robust list.
+	pub fn get_robust_list(&self) -> Option<NonNull<futex::RobustListHead>> {
+		self.robust_list
+	}
+
+	/// Sets the head of the process's robust list.
+	pub fn set_robust_list(&mut self, head: Option<NonNull<futex::RobustListHead>>) {
+		self.robust_list = head;
+	}
+
+	/// Returns the name of the process.
+	pub fn get_comm(&self) -> &[u8] {
+		let len = self.comm.iter().position(|b| *b == 0).unwrap_or(COMM_LEN);
+		&self.comm[..len]
+	}
+
+	/// Sets the name of the process. The name is truncated if too long.
+	pub fn set_comm(&mut self, name: &[u8]) {
+		let len = min(name.len(), COMM_LEN - 1);
+		self.comm = [0; COMM_LEN];
+		self.comm[..len].copy_from_slice(&name[..len]);
+	}
+
 	/// Returns an immutable reference to the process's resource usage
 	/// structure.
 	pub fn get_rusage(&self) -> &RUsage {
 		&self.rusage
 	}
 
+	/// Returns the CPU time used by the process and its children.
+	pub fn get_cpu_times(&self) -> &CpuTimes {
+		&self.cpu_times
+	}
+
+	/// Accounts for the CPU time used by the process since the last accounting, up to `now`.
+	///
+	/// If `user` is set, the time is accounted as spent in userspace. Else, it is accounted as
+	/// spent in kernelspace.
+	///
+	/// `now` is a timestamp of the monotonic clock, in nanoseconds.
+	pub fn account_cpu_time(&mut self, now: Timestamp, user: bool) {
+		let delta = now.saturating_sub(self.cpu_time_start);
+		self.cpu_time_start = now;
+		if user {
+			self.cpu_times.utime += delta;
+			self.rusage.ru_utime = Timeval::from_nano(self.cpu_times.utime);
+		} else {
+			self.cpu_times.stime += delta;
+			self.rusage.ru_stime = Timeval::from_nano(self.cpu_times.stime);
+		}
+	}
+
+	/// Starts accounting for the CPU time of the process from `now`, when it starts running.
+	///
+	/// `now` is a timestamp of the monotonic clock, in nanoseconds.
+	pub fn start_cpu_time(&mut self, now: Timestamp) {
+		self.cpu_time_start = now;
+	}
+
+	/// Adds the CPU time used by the terminated child `child` and by its own children to the
+	/// process's children CPU time. This function is called when the child is waited for.
+	pub fn add_child_cpu_times(&mut self, child: &Process) {
+		let times = &child.cpu_times;
+		self.cpu_times.cutime += times.utime + times.cutime;
+		self.cpu_times.cstime += times.stime + times.cstime;
+	}
+
 	/// If the process is a vfork child, resets its state and its parent's
 	/// state.
 	pub fn reset_vfork(&mut self) {
-		if self.vfork_state != VForkState::Executing {
+		let VForkState::Executing(parent_pid) = self.vfork_state else {
 			return;
-		}
-
+		};
 		self.vfork_state = VForkState::None;
 
-		// Resetting the parent's vfork state if needed
-		let parent = self.get_parent().and_then(|parent| parent.upgrade());
-		if let Some(parent) = parent {
+		// Resuming the parent
+		if let Some(parent) = Process::get_by_pid(parent_pid) {
 			let mut parent = parent.lock();
 			parent.vfork_state = VForkState::None;
 		}
 	}
 
+	/// Tells whether the process is waiting for a vfork child to exit or execute a program.
+	pub fn is_vfork_waiting(&self) -> bool {
+		self.vfork_state == VForkState::Waiting
+	}
+
 	/// Exits the process with the given `status`.
 	///
 	/// This function changes the process's status to `Zombie`.
@@ -1254,9 +2146,86 @@ impl Process {
 			0
 		};
 
+		let robust_list = self.robust_list.take();
+		let clear_child_tid = self.clear_child_tid.take();
+		if let Some(mem_space) = self.mem_space.clone() {
+			// The process may be killed while another memory space is bound
+			if robust_list.is_some() || clear_child_tid.is_some() {
+				let mem_space = mem_space.lock();
+				if !mem_space.is_bound() {
+					mem_space.bind();
+				}
+			}
+			// Errors are ignored since the addresses are given by the process
+			if let Some(head) = robust_list {
+				let head = (head.as_ptr() as usize).into();
+				let _ = futex::exit_robust_list(&mem_space, head, self.to_local_pid(self.tid));
+			}
+			if let Some(tidptr) = clear_child_tid {
+				let tidptr = (tidptr.as_ptr() as usize).into();
+				let _ = futex::clear_tid(&mem_space, tidptr);
+			}
+		}
+
 		self.set_state(State::Zombie);
 		self.reset_vfork();
-		self.set_waitable(sig);
+
+		let last_thread = {
+			let mut threads = self.threads.lock();
+			threads.retain(|pid| *pid != self.pid);
+			threads.is_empty()
+		};
+		if last_thread {
+			// Errors are ignored since the process is exiting anyway
+			let _ = acct::write_record(self);
+		}
+		if self.is_thread_group_leader() {
+			// The parent is notified once every thread of the group has exited
+			if last_thread {
+				self.set_waitable(sig);
+			}
+		} else {
+			// A thread cannot be waited for. It is removed once it has exited
+			oom::wrap(|| ZOMBIE_THREADS.lock().push(self.pid));
+			// If the leader has already exited, notify its parent
+			let leader = Process::get_by_pid(self.tgid).filter(|_| last_thread);
+			if let Some(leader_mutex) = leader {
+				let mut leader = leader_mutex.lock();
+				if matches!(leader.get_state(), State::Zombie) {
+					let termsig = leader.termsig;
+					leader.set_waitable(termsig);
+				}
+			}
+		}
+		pidfd::notify_exit(self.pid);
+		futex::FUTEXES.lock().remove_process(self.pid);
+	}
+
+	/// Exits every other thread of the process's thread group.
+	///
+	/// Arguments are the same as [`Self::exit`].
+	pub fn exit_other_threads(&self, status: u32, signaled: bool) {
+		loop {
+			let pid = self
+				.threads
+				.lock()
+				.iter()
+				.find(|pid| **pid != self.pid)
+				.cloned();
+			let Some(pid) = pid else {
+				break;
+			};
+			match Process::get_by_pid(pid) {
+				// Exiting removes the thread from the list
+				Some(thread) => thread.lock().exit(status, signaled),
+				None => self.threads.lock().retain(|p| *p != pid),
+			}
+		}
+	}
+
+	/// Tells whether the process is the leader of its thread group.
+	pub fn is_thread_group_leader(&self) -> bool {
+		self.pid == self.tgid
 	}
 
 	/// Returns the number of virtual memory pages used by the process.
@@ -1269,36 +2238,54 @@ impl Process {
 		}
 	}
 
+	/// Returns the number of physical memory pages used by the process (Resident Set Size).
+	pub fn get_rss(&self) -> usize {
+		if let Some(mem_space_mutex) = &self.mem_space {
+			// The memory space may be locked by the caller. This is safe since the counter is
+			// atomic
+			let mem_space = unsafe { mem_space_mutex.get_payload() };
+			mem_space.get_rss()
+		} else {
+			0
+		}
+	}
+
 	/// Returns the OOM score, used by the OOM killer to determine the process
 	/// to kill in case the system runs out of memory.
 	///
-	/// A higher score means a higher probability of getting killed.
+	/// The score is the share of the physical memory used by the process, in thousandths,
+	/// adjusted by `oom_score_adj`.
+	///
+	/// A higher score means a higher probability of getting killed. If zero, the process cannot
+	/// be killed.
 	pub fn get_oom_score(&self) -> u16 {
-		let mut score = 0;
+		if self.is_init() || self.oom_score_adj == oom::OOM_SCORE_ADJ_MIN {
+			return 0;
+		}
 
+		let total_pages = memory::stats::MEM_INFO.lock().mem_total * 1024 / memory::PAGE_SIZE;
+		let mut score = (self.get_rss() * 1000 / max(total_pages, 1)) as i32;
 		// If the process is owned by the superuser, give it a bonus
-		if self.access_profile.is_privileged() {
-			score -= 100;
+		if self.access_profile.has_cap(CAP_SYS_ADMIN) {
+			score -= 30;
 		}
+		score += self.oom_score_adj as i32;
 
-		// TODO Compute the score using physical memory usage
-		// TODO Take into account userspace-set values (oom may be disabled for this
-		// process, an absolute score or a bonus might be given, etc...)
-
-		score
+		// A process that can be killed always has a non-zero score
+		score.clamp(1, 2000) as _
 	}
 }
 
 impl AccessProfile {
 	/// Tells whether the agent can kill the process.
 	pub fn can_kill(&self, proc: &Process) -> bool {
-		let uid = self.get_uid();
-		let euid = self.get_euid();
-		// if privileged
-		if uid == ROOT_UID || euid == ROOT_UID {
+		if self.has_cap(CAP_KILL) {
 			return true;
 		}
 
+		let uid = self.get_uid();
+		let euid = self.get_euid();
+
 		// if sender's `uid` or `euid` equals receiver's `uid` or `suid`
 		uid == proc.access_profile.get_uid()
 			|| uid == proc.access_profile.get_suid()
@@ -1313,6 +2300,10 @@ impl Drop for Process {
 			panic!("Terminated init process!");
 		}
 
+		if self.state != State::Zombie {
+			self.cgroup.detach();
+		}
+
 		// Unregister the process from the procfs
 		oom::wrap(|| self.unregister_procfs());
 
@@ -1329,8 +2320,9 @@ impl Drop for Process {
 			Ok(())
 		});
 
+		pgroup::remove(self.pgid, self.pid);
+
 		// Freeing the PID
-		let mut pid_manager = unsafe { PID_MANAGER.assume_init_mut() }.lock();
-		pid_manager.release_pid(self.pid);
+		self.pid_ns.release(self.pid);
 	}
 }
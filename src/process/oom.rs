@@ -1,18 +1,38 @@
 //! OOM killing is a procedure which is invoked when the kernel runs out of
 //! memory.
 //!
-//! The OOM killer terminates one or more processes according to a score computed for
-//! each of them.
+//! The OOM killer first tries to reclaim memory that can be freed without harm. If this is not
+//! enough, it terminates the process with the highest OOM score (see
+//! [`Process::get_oom_score`]) and frees its memory.
 //!
 //! This is an emergency procedure which is not supposed to be used under normal conditions.
+//!
+//! The same procedure is applied to a control group reaching its memory limit, in which case
+//! only processes of the group are considered.
 
 use crate::errno::AllocResult;
+use crate::file::dcache;
+use crate::file::icache;
+use crate::file::page_cache;
+use crate::process;
+use crate::process::cgroup::CGroup;
+use crate::process::signal::Signal;
+use crate::process::Process;
+use crate::util::lock::IntMutex;
 use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use core::sync::atomic::Ordering;
 
 /// The maximum number of times the kernel tries to kill a process to retrieve
 /// memory.
 const MAX_TRIES: u32 = 5;
 
+/// The minimum value of `oom_score_adj`. With this value, the process is never killed by the
+/// OOM killer.
+pub const OOM_SCORE_ADJ_MIN: i16 = -1000;
+/// The maximum value of `oom_score_adj`.
+pub const OOM_SCORE_ADJ_MAX: i16 = 1000;
+
 /// Variable telling whether the OOM killer is enabled.
 static KILLER_ENABLE: Mutex<bool> = Mutex::new(true);
 
@@ -26,13 +46,146 @@ pub fn set_killer_enabled(enable: bool) {
 	*KILLER_ENABLE.lock() = enable;
 }
 
+/// Discards the pages of memory spaces that have been marked as lazily freeable with
+/// `MADV_FREE`.
+///
+/// Memory spaces that are locked are skipped since they may be locked by the caller, such as
+/// the memory space of the current process.
+///
+/// The function returns the number of freed pages.
+fn reclaim_lazy_free() -> usize {
+	let current = Process::current().map(|proc| proc.as_ptr());
+
+	let mut sched = process::get_scheduler().lock();
+	let mut count = 0;
+	for (_, proc_mutex) in sched.iter_process() {
+		if Some(proc_mutex.as_ptr()) == current {
+			continue;
+		}
+		let Some(mem_space_mutex) = proc_mutex.lock().get_mem_space().cloned() else {
+			continue;
+		};
+		// Threads share their memory space. Once reclaimed, it has nothing left to free
+		let Some(mut mem_space) = mem_space_mutex.try_lock() else {
+			continue;
+		};
+		count += mem_space.reclaim_lazy_free();
+	}
+	count
+}
+
+/// Frees memory that is not required for the system to work.
+///
+/// The function returns the number of freed pages.
+fn reclaim() -> usize {
+	// The caches of files hold memory from the heap, which is not accounted for in the result
+	dcache::shrink(usize::MAX);
+	icache::shrink(usize::MAX);
+	page_cache::shrink(usize::MAX) + reclaim_lazy_free()
+}
+
+/// Returns the process to be killed, which is the one with the highest OOM score.
+///
+/// If `cgroup` is specified, only processes of this control group or its descendants are
+/// considered.
+///
+/// The current process is never selected since it may be locked by the caller and its memory
+/// space cannot be freed while it is running.
+///
+/// If no process can be killed, the function returns `None`.
+fn select_victim(cgroup: Option<&CGroup>) -> Option<Arc<IntMutex<Process>>> {
+	let current = Process::current().map(|proc| proc.as_ptr());
+
+	let mut sched = process::get_scheduler().lock();
+	let mut victim: Option<(u16, Arc<IntMutex<Process>>)> = None;
+	for (_, proc_mutex) in sched.iter_process() {
+		if Some(proc_mutex.as_ptr()) == current {
+			continue;
+		}
+
+		let score = {
+			let proc = proc_mutex.lock();
+			if proc.is_init() || proc.get_mem_space().is_none() {
+				continue;
+			}
+			if cgroup.is_some_and(|cgroup| !proc.get_cgroup().is_in(cgroup)) {
+				continue;
+			}
+			proc.get_oom_score()
+		};
+		if score > 0 && victim.as_ref().map(|(s, _)| score > *s).unwrap_or(true) {
+			victim = Some((score, proc_mutex.clone()));
+		}
+	}
+
+	victim.map(|(_, proc)| proc)
+}
+
 /// Runs the OOM killer.
 pub fn kill() {
 	if !is_killer_enabled() {
 		panic!("Out of memory");
 	}
 
-	// TODO Get the process with the highest OOM score (ignore init process)
+	if reclaim() > 0 || !process::is_initialized() {
+		return;
+	}
+
+	let Some(victim_mutex) = select_victim(None) else {
+		return;
+	};
+	let mut victim = victim_mutex.lock();
+	crate::println!(
+		"Out of memory: killed process {} (RSS: {} pages, score: {})",
+		victim.pid,
+		victim.get_rss(),
+		victim.get_oom_score()
+	);
+	victim.kill(&Signal::SIGKILL, false);
+	// The process does not run anymore, so its memory can be freed right away
+	victim.set_mem_space(None);
+}
+
+/// Makes room for one more page in the control group `cgroup`, if it or one of its ancestors
+/// reached its memory limit.
+///
+/// The function first tries to reclaim memory, then kills processes of the limited group.
+///
+/// If room cannot be made, the function returns `false`.
+pub fn make_room(cgroup: &CGroup) -> bool {
+	let mut reclaimed = false;
+	for _ in 0..MAX_TRIES {
+		let Some(limited) = cgroup.get_memory_limited() else {
+			return true;
+		};
+		if !reclaimed {
+			reclaimed = true;
+			limited.memory_events.max.fetch_add(1, Ordering::Relaxed);
+			if reclaim() > 0 {
+				continue;
+			}
+		}
+
+		limited.memory_events.oom.fetch_add(1, Ordering::Relaxed);
+		let Some(victim_mutex) = select_victim(Some(limited)) else {
+			return false;
+		};
+		let mut victim = victim_mutex.lock();
+		crate::println!(
+			"Memory cgroup out of memory: killed process {} (RSS: {} pages, score: {})",
+			victim.pid,
+			victim.get_rss(),
+			victim.get_oom_score()
+		);
+		victim.kill(&Signal::SIGKILL, false);
+		victim.set_mem_space(None);
+		limited
+			.memory_events
+			.oom_kill
+			.fetch_add(1, Ordering::Relaxed);
+	}
+
+	false
 }
 
 /// Executes the given function.
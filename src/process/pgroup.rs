@@ -0,0 +1,98 @@
+//! Process groups and sessions, used for job control.
+//!
+//! A process group is a set of processes that receive signals together, such as the processes
+//! of a shell pipeline. Its ID is the PID of the process that created it.
+//!
+//! Each process group belongs to a session, created by its session leader with `setsid`. A
+//! session may have a controlling terminal, which sends signals to the foreground process group
+//! of the session.
+//!
+//! A process group keeps existing as long as it has members, even after the process that created
+//! it has exited.
+
+use super::pid::Pid;
+use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::vec::Vec;
+use crate::util::lock::Mutex;
+use crate::util::TryClone;
+
+/// A process group.
+struct ProcessGroup {
+	/// The ID of the session the group belongs to.
+	sid: Pid,
+	/// The PIDs of the members of the group, sorted.
+	members: Vec<Pid>,
+}
+
+/// The list of process groups, by ID.
+static GROUPS: Mutex<HashMap<Pid, ProcessGroup>> = Mutex::new(HashMap::new());
+
+/// Adds the process with PID `pid` to the process group `pgid`.
+///
+/// If the group doesn't exist, it is created in the session `sid`.
+///
+/// If the group exists but belongs to another session, the function returns
+/// [`crate::errno::EPERM`].
+pub fn add(pgid: Pid, sid: Pid, pid: Pid) -> EResult<()> {
+	let mut groups = GROUPS.lock();
+	match groups.get_mut(&pgid) {
+		Some(group) => {
+			if group.sid != sid {
+				return Err(errno!(EPERM));
+			}
+			if let Err(i) = group.members.binary_search(&pid) {
+				group.members.insert(i, pid)?;
+			}
+		}
+		None => {
+			groups.insert(
+				pgid,
+				ProcessGroup {
+					sid,
+					members: crate::vec![pid]?,
+				},
+			)?;
+		}
+	}
+	Ok(())
+}
+
+/// Removes the process with PID `pid` from the process group `pgid`.
+///
+/// If the group becomes empty, it is destroyed.
+pub fn remove(pgid: Pid, pid: Pid) {
+	let mut groups = GROUPS.lock();
+	let Some(group) = groups.get_mut(&pgid) else {
+		return;
+	};
+	if let Ok(i) = group.members.binary_search(&pid) {
+		group.members.remove(i);
+	}
+	if group.members.is_empty() {
+		groups.remove(&pgid);
+	}
+}
+
+/// Tells whether the process group `pgid` exists.
+pub fn exists(pgid: Pid) -> bool {
+	GROUPS.lock().get(&pgid).is_some()
+}
+
+/// Returns the ID of the session the process group `pgid` belongs to.
+///
+/// If the group doesn't exist, the function returns `None`.
+pub fn get_session(pgid: Pid) -> Option<Pid> {
+	GROUPS.lock().get(&pgid).map(|group| group.sid)
+}
+
+/// Returns the PIDs of the members of the process group `pgid`.
+///
+/// If the group doesn't exist, the function returns an empty list.
+pub fn get_members(pgid: Pid) -> AllocResult<Vec<Pid>> {
+	match GROUPS.lock().get(&pgid) {
+		Some(group) => group.members.try_clone(),
+		None => Ok(Vec::new()),
+	}
+}
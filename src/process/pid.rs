@@ -2,9 +2,27 @@
 //!
 //! Each process must have an unique PID, thus they have to be allocated.
 //! A bitfield is used to store the used PIDs.
+//!
+//! PID namespaces isolate the PIDs of processes: a process only sees the processes of its own
+//! namespace and of its descendant namespaces. Namespaces are nested, thus a process has a PID
+//! in its own namespace and in each of its ancestors.
+//!
+//! The first process of a namespace has PID [`INIT_PID`] in it and behaves as the init process
+//! of the namespace: it adopts orphaned processes, and when it exits, every other process of the
+//! namespace is killed.
+//!
+//! Inside of the kernel, processes are identified by their PID in the root namespace. PIDs are
+//! translated when communicating with userspace.
 
 use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::util::container::hashmap::HashMap;
 use crate::util::container::id_allocator::IDAllocator;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use core::iter;
+use core::sync::atomic;
+use core::sync::atomic::AtomicU32;
 
 /// Type representing a Process ID. This ID is unique for every running
 /// processes.
@@ -15,6 +33,9 @@ const MAX_PID: Pid = 32768;
 /// The PID of the init process.
 pub const INIT_PID: Pid = 1;
 
+/// The maximum nesting level of PID namespaces. The root namespace has level `0`.
+const MAX_NS_LEVEL: usize = 32;
+
 /// A structure handling PID allocations.
 pub struct PIDManager {
 	/// The PID allocator.
@@ -24,11 +45,9 @@ pub struct PIDManager {
 impl PIDManager {
 	/// Creates a new instance.
 	pub fn new() -> AllocResult<Self> {
-		let mut s = Self {
+		Ok(Self {
 			allocator: IDAllocator::new(MAX_PID as _)?,
-		};
-		s.allocator.set_used((INIT_PID - 1) as _);
-		Ok(s)
+		})
 	}
 
 	/// Returns a unused PID and marks it as used.
@@ -54,3 +73,228 @@ impl PIDManager {
 		self.allocator.free((pid - 1) as _)
 	}
 }
+
+/// The mutable state of a PID namespace.
+struct NamespaceState {
+	/// The allocator of the PIDs local to the namespace.
+	manager: PIDManager,
+	/// Local PIDs to PIDs in the root namespace.
+	to_global: HashMap<Pid, Pid>,
+	/// PIDs in the root namespace to local PIDs.
+	to_local: HashMap<Pid, Pid>,
+	/// The last PID allocated in the namespace, local to the namespace.
+	last: Pid,
+
+	/// The PID, in the root namespace, of the init process of the namespace.
+	init: Option<Pid>,
+	/// If `true`, the init process of the namespace has exited and no process can be created in
+	/// the namespace anymore.
+	dead: bool,
+}
+
+/// A PID namespace.
+pub struct PidNamespace {
+	/// The ID of the namespace.
+	id: u32,
+	/// The parent namespace. If `None`, the namespace is the root namespace.
+	parent: Option<Arc<PidNamespace>>,
+	/// The nesting level of the namespace.
+	level: usize,
+
+	/// The state of the namespace.
+	state: Mutex<NamespaceState>,
+}
+
+/// The root PID namespace.
+static ROOT: Mutex<Option<Arc<PidNamespace>>> = Mutex::new(None);
+/// The ID of the next PID namespace to be created.
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+impl PidNamespace {
+	/// Creates a namespace with the given parent.
+	fn new(parent: Option<Arc<PidNamespace>>) -> AllocResult<Arc<Self>> {
+		let level = parent.as_ref().map(|p| p.level + 1).unwrap_or(0);
+		Arc::new(Self {
+			id: NEXT_ID.fetch_add(1, atomic::Ordering::Relaxed),
+			parent,
+			level,
+
+			state: Mutex::new(NamespaceState {
+				manager: PIDManager::new()?,
+				to_global: HashMap::new(),
+				to_local: HashMap::new(),
+				last: 0,
+
+				init: None,
+				dead: false,
+			}),
+		})
+	}
+
+	/// Returns the root namespace.
+	pub fn get_root() -> AllocResult<Arc<Self>> {
+		let mut root = ROOT.lock();
+		if let Some(root) = &*root {
+			return Ok(root.clone());
+		}
+		let ns = Self::new(None)?;
+		*root = Some(ns.clone());
+		Ok(ns)
+	}
+
+	/// Creates a new namespace, child of `parent`.
+	///
+	/// If the maximum nesting level is reached, the function returns [`crate::errno::ENOSPC`].
+	pub fn new_child(parent: &Arc<Self>) -> EResult<Arc<Self>> {
+		if parent.level >= MAX_NS_LEVEL {
+			return Err(errno!(ENOSPC));
+		}
+		Ok(Self::new(Some(parent.clone()))?)
+	}
+
+	/// Returns the ID of the namespace.
+	pub fn get_id(&self) -> u32 {
+		self.id
+	}
+
+	/// Returns the parent namespace. If the namespace is the root, the function returns `None`.
+	pub fn get_parent(&self) -> Option<&Arc<Self>> {
+		self.parent.as_ref()
+	}
+
+	/// Returns the nesting level of the namespace.
+	pub fn get_level(&self) -> usize {
+		self.level
+	}
+
+	/// Tells whether the namespace is the root namespace.
+	pub fn is_root(&self) -> bool {
+		self.parent.is_none()
+	}
+
+	/// Returns an iterator over the namespace and its ancestors, up to the root.
+	pub fn iter_path(&self) -> impl Iterator<Item = &PidNamespace> {
+		iter::successors(Some(self), |ns| ns.parent.as_deref())
+	}
+
+	/// Tells whether the namespace is `other` or one of its descendants.
+	pub fn is_in(&self, other: &PidNamespace) -> bool {
+		self.iter_path().any(|ns| core::ptr::eq(ns, other))
+	}
+
+	/// Returns the PID, in the root namespace, of the init process of the namespace.
+	///
+	/// If the namespace has no init process yet, the function returns `None`.
+	pub fn get_init(&self) -> Option<Pid> {
+		self.state.lock().init
+	}
+
+	/// Returns the last PID allocated in the namespace, local to the namespace.
+	pub fn get_last_pid(&self) -> Pid {
+		self.state.lock().last
+	}
+
+	/// Tells whether the init process of the namespace has exited.
+	pub fn is_dead(&self) -> bool {
+		self.state.lock().dead
+	}
+
+	/// Prevents the creation of processes in the namespace. This function is called when the
+	/// init process of the namespace exits.
+	pub fn disable_alloc(&self) {
+		self.state.lock().dead = true;
+	}
+
+	/// Translates the PID `pid`, local to the namespace, into the PID in the root namespace.
+	///
+	/// If no process has this PID in the namespace, the function returns `None`.
+	pub fn to_global(&self, pid: Pid) -> Option<Pid> {
+		if self.is_root() {
+			return Some(pid);
+		}
+		self.state.lock().to_global.get(&pid).cloned()
+	}
+
+	/// Translates the PID `pid` in the root namespace into the PID local to the namespace.
+	///
+	/// If the process is not visible from the namespace, the function returns `None`.
+	pub fn to_local(&self, pid: Pid) -> Option<Pid> {
+		if self.is_root() {
+			return Some(pid);
+		}
+		self.state.lock().to_local.get(&pid).cloned()
+	}
+
+	/// Allocates a PID for a new process in the namespace. The process also receives a PID in
+	/// each ancestor namespace.
+	///
+	/// The function returns the PID of the process in the root namespace.
+	///
+	/// If the init process of the namespace has exited, the function returns
+	/// [`crate::errno::ENOMEM`].
+	pub fn alloc(&self) -> EResult<Pid> {
+		if self.is_dead() {
+			return Err(errno!(ENOMEM));
+		}
+		let root = self.iter_path().last().unwrap();
+		let pid = {
+			let mut state = root.state.lock();
+			let pid = state.manager.get_unique_pid()?;
+			if pid == INIT_PID {
+				state.init = Some(pid);
+			}
+			state.last = pid;
+			pid
+		};
+		for (i, ns) in self.iter_path().filter(|ns| !ns.is_root()).enumerate() {
+			let res = (|| {
+				let mut state = ns.state.lock();
+				let local = state.manager.get_unique_pid()?;
+				let res = state
+					.to_global
+					.insert(local, pid)
+					.and_then(|_| state.to_local.insert(pid, local));
+				if let Err(e) = res {
+					state.to_global.remove(&local);
+					state.manager.release_pid(local);
+					return Err(e);
+				}
+				if local == INIT_PID {
+					state.init = Some(pid);
+				}
+				state.last = local;
+				Ok(())
+			})();
+			if let Err(e) = res {
+				// Rollback
+				for ns in self.iter_path().filter(|ns| !ns.is_root()).take(i) {
+					ns.unregister(pid);
+				}
+				root.state.lock().manager.release_pid(pid);
+				return Err(e.into());
+			}
+		}
+		Ok(pid)
+	}
+
+	/// Removes the PID `pid`, in the root namespace, from the namespace's mappings.
+	fn unregister(&self, pid: Pid) {
+		let mut state = self.state.lock();
+		if let Some(local) = state.to_local.remove(&pid) {
+			state.to_global.remove(&local);
+			state.manager.release_pid(local);
+		}
+	}
+
+	/// Releases the PID `pid`, in the root namespace, of a process of the namespace, along with
+	/// its PIDs in every ancestor namespace.
+	pub fn release(&self, pid: Pid) {
+		for ns in self.iter_path() {
+			if ns.is_root() {
+				ns.state.lock().manager.release_pid(pid);
+			} else {
+				ns.unregister(pid);
+			}
+		}
+	}
+}
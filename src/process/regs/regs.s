@@ -5,6 +5,24 @@
 // The size in bytes of the structure storing the registers' states
 .set REGS_SIZE, 560
 
+/*
+ * This macro clears the AC flag of EFLAGS, so that SMAP is enforced while handling an interruption,
+ * even if the interrupted code was inside of a user-access window. The previous value of the flag
+ * is restored by `iret`, from the EFLAGS saved by the CPU.
+ *
+ * `clac` being an invalid instruction on CPUs that do not support SMAP, it is executed only if SMAP
+ * is enabled.
+ *
+ * The macro must be used at the entry of interruption handlers, before anything else. It clobbers
+ * the arithmetic flags.
+ */
+.macro CLEAR_AC
+	cmpb $0, SMAP_ENABLED
+	je 1f
+	clac
+1:
+.endm
+
 /*
  * This macro stores the values of every registers after an interruption was triggered.
  *
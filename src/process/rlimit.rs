@@ -0,0 +1,176 @@
+//! Resource limits restrict the amount of resources a process can consume.
+//!
+//! Each limit has a soft value, which is the one enforced by the kernel, and a hard value, which
+//! is the ceiling for the soft value.
+
+use crate::errno::EResult;
+use crate::file::fd;
+use crate::file::perm::AccessProfile;
+use crate::file::perm::CAP_SYS_RESOURCE;
+use crate::limits;
+
+/// The amount of seconds of CPU time the process can consume.
+pub const RLIMIT_CPU: i32 = 0;
+/// The maximum size of a file the process may create, in bytes.
+pub const RLIMIT_FSIZE: i32 = 1;
+/// The maximum size of the process's data segment in bytes, rounded down to the
+/// page size.
+pub const RLIMIT_DATA: i32 = 2;
+/// The maximum size of the process stack, in bytes.
+pub const RLIMIT_STACK: i32 = 3;
+/// The maximum size of a core file the process may dump in bytes.
+pub const RLIMIT_CORE: i32 = 4;
+/// A limit on the process's resident set (the numbe rof virtual pages resident in RAM).
+pub const RLIMIT_RSS: i32 = 5;
+/// The limit on the number of threads for the real user ID of the calling process.
+pub const RLIMIT_NPROC: i32 = 6;
+/// A value one greater than the maximum number of file descriptors that can be
+/// open by the process.
+pub const RLIMIT_NOFILE: i32 = 7;
+/// The maximum number of butes of memory that may be locked into RAM.
+pub const RLIMIT_MEMLOCK: i32 = 8;
+/// The maximum size of the memory space in bytes, rounded down to the page
+/// size.
+pub const RLIMIT_AS: i32 = 9;
+/// The limit on the combined number of flock(2) locks and fcntl(2) leases the
+/// process may establish.
+pub const RLIMIT_LOCKS: i32 = 10;
+/// The limit on the number of signals that may be queued for the real user ID of the calling
+/// process.
+pub const RLIMIT_SIGPENDING: i32 = 11;
+/// The limit on the number of butes that can be allocated for POSIX message queues for the real
+/// user IF of the calling process.
+pub const RLIMIT_MSGQUEUE: i32 = 12;
+/// The ceiling to which the process's nice value can be raised.
+pub const RLIMIT_NICE: i32 = 13;
+/// The ceiling on the real-time priority that may be set for this process.
+pub const RLIMIT_RTPRIO: i32 = 14;
+/// The limit (in microseconds) on the amount of CPU that a process scheduled under a real-time
+/// scheduling policy may consume without masking a blocking system call.
+pub const RLIMIT_RTTIME: i32 = 15;
+/// The number of resource limits.
+pub const RLIMIT_NLIMITS: i32 = 16;
+
+/// Value telling that a resource is not limited.
+pub const RLIM_INFINITY: RLim = !0;
+
+/// Type representing a resource limit.
+pub type RLim = u64;
+
+/// Structure representing a resource limit.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct RLimit {
+	/// Soft limit
+	pub rlim_cur: RLim,
+	/// Hard limit (ceiling for rlim_cur)
+	pub rlim_max: RLim,
+}
+
+/// Structure representing a resource limit with 32 bits values, used by legacy system calls.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct RLimit32 {
+	/// Soft limit
+	pub rlim_cur: u32,
+	/// Hard limit (ceiling for rlim_cur)
+	pub rlim_max: u32,
+}
+
+impl RLimit {
+	/// Returns a limit with the same soft and hard value `val`.
+	const fn new(val: RLim) -> Self {
+		Self {
+			rlim_cur: val,
+			rlim_max: val,
+		}
+	}
+}
+
+/// The table of resource limits of a process.
+#[derive(Clone, Debug)]
+pub struct RLimits {
+	/// The limits, indexed by resource.
+	limits: [RLimit; RLIMIT_NLIMITS as usize],
+}
+
+impl Default for RLimits {
+	fn default() -> Self {
+		let mut limits = [RLimit::new(RLIM_INFINITY); RLIMIT_NLIMITS as usize];
+		limits[RLIMIT_STACK as usize] = RLimit {
+			rlim_cur: 8 * 1024 * 1024,
+			rlim_max: RLIM_INFINITY,
+		};
+		limits[RLIMIT_CORE as usize] = RLimit {
+			rlim_cur: 0,
+			rlim_max: RLIM_INFINITY,
+		};
+		limits[RLIMIT_NOFILE as usize] = RLimit {
+			rlim_cur: limits::OPEN_MAX as _,
+			rlim_max: 4096,
+		};
+		limits[RLIMIT_MEMLOCK as usize] = RLimit::new(8 * 1024 * 1024);
+		limits[RLIMIT_RTPRIO as usize] = RLimit::new(0);
+
+		Self {
+			limits,
+		}
+	}
+}
+
+impl RLimits {
+	/// Returns the limit for the given resource.
+	///
+	/// If the resource doesn't exist, the function returns [`crate::errno::EINVAL`].
+	pub fn get(&self, resource: i32) -> EResult<RLimit> {
+		usize::try_from(resource)
+			.ok()
+			.and_then(|i| self.limits.get(i))
+			.cloned()
+			.ok_or_else(|| errno!(EINVAL))
+	}
+
+	/// Returns the soft limit for the given resource.
+	///
+	/// If the resource doesn't exist, the function panics.
+	pub fn get_cur(&self, resource: i32) -> RLim {
+		self.limits[resource as usize].rlim_cur
+	}
+
+	/// Sets the limit for the given resource.
+	///
+	/// Arguments:
+	/// - `resource` is the resource to set the limit for
+	/// - `limit` is the new limit
+	/// - `access_profile` is the access profile of the agent setting the limit
+	///
+	/// If the resource doesn't exist or if the soft limit is greater than the hard limit, the
+	/// function returns [`crate::errno::EINVAL`].
+	///
+	/// If the hard limit is raised and the agent is not privileged, or if the hard limit on the
+	/// number of file descriptors exceeds [`fd::NR_OPEN`], the function returns
+	/// [`crate::errno::EPERM`].
+	pub fn set(
+		&mut self,
+		resource: i32,
+		limit: RLimit,
+		access_profile: &AccessProfile,
+	) -> EResult<()> {
+		let curr = usize::try_from(resource)
+			.ok()
+			.and_then(|i| self.limits.get_mut(i))
+			.ok_or_else(|| errno!(EINVAL))?;
+		if limit.rlim_cur > limit.rlim_max {
+			return Err(errno!(EINVAL));
+		}
+		if limit.rlim_max > curr.rlim_max && !access_profile.has_cap(CAP_SYS_RESOURCE) {
+			return Err(errno!(EPERM));
+		}
+		if resource == RLIMIT_NOFILE && limit.rlim_max > fd::NR_OPEN as RLim {
+			return Err(errno!(EPERM));
+		}
+
+		*curr = limit;
+		Ok(())
+	}
+}
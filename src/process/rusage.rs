@@ -2,6 +2,27 @@
 
 use crate::time::unit::Timeval;
 
+/// The number of clock ticks per second, in which CPU times are given to userspace.
+pub const USER_HZ: u64 = 100;
+
+/// Converts the duration `nanos`, in nanoseconds, to clock ticks.
+pub fn to_clock_ticks(nanos: u64) -> u64 {
+	nanos / (1_000_000_000 / USER_HZ)
+}
+
+/// The CPU time used by a process and by its terminated children, in nanoseconds.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct CpuTimes {
+	/// The time spent in userspace.
+	pub utime: u64,
+	/// The time spent in kernelspace.
+	pub stime: u64,
+	/// The time spent in userspace by the terminated children which have been waited for.
+	pub cutime: u64,
+	/// The time spent in kernelspace by the terminated children which have been waited for.
+	pub cstime: u64,
+}
+
 /// Usage of each resource by a process.
 #[derive(Clone, Default, Debug)]
 pub struct RUsage {
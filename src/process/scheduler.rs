@@ -3,16 +3,38 @@
 //!
 //! The interruption is fired by the PIT on IDT0.
 //!
-//! A scheduler cycle is a period during which the scheduler iterates through
-//! every processes. The scheduler works by assigning a number of quantum for
-//! each process, based on the number of running processes and their priority.
-//! This number represents the number of ticks during which the process keeps
-//! running until switching to the next process.
-
+//! Normal processes are scheduled fairly: each process accumulates a virtual runtime, which is
+//! the time it ran weighted by its nice value. The process with the lowest virtual runtime runs
+//! next, thus processes with a lower nice value receive more CPU time. Processes are kept ordered
+//! by virtual runtime in a balanced tree. A process runs for a time slice proportional to its
+//! weight, unless a process with a sufficiently lower virtual runtime wakes up, in which case it
+//! is preempted.
+//!
+//! Real-time processes (scheduling policies [`SCHED_FIFO`] and [`SCHED_RR`]) are layered above
+//! normal processes: as long as a real-time process is runnable, no normal process runs. Among
+//! real-time processes, the one with the highest real-time priority runs first.
+//!
+//! Each CPU core has its own run queue, containing the processes that run on it. The bootstrap
+//! processor is ticked by the PIT while application processors are ticked by their local APIC
+//! timer. At each tick, a core balances the load by pulling a runnable process from the busiest
+//! core if it has nothing to run.
+//!
+//! The scheduler also computes the load averages of the system, which are the number of running
+//! processes averaged over 1, 5 and 15 minutes with an exponential decay.
+//!
+//! When a core has nothing to run, its tick is stopped until a process becomes runnable on it,
+//! so that it can stay idle. Idle cores whose tick is stopped are woken up to pull processes when
+//! other cores become busy.
+
+use crate::cpu;
+use crate::cpu::apic;
+use crate::cpu::idle;
+use crate::cpu::smp;
 use crate::errno::AllocResult;
+use crate::errno::CollectResult;
 use crate::event;
 use crate::event::CallbackHook;
-use crate::idt::pic;
+use crate::idt;
 use crate::memory;
 use crate::memory::malloc;
 use crate::memory::stack;
@@ -21,24 +43,208 @@ use crate::process::pid::Pid;
 use crate::process::regs::Regs;
 use crate::process::Process;
 use crate::process::State;
+use crate::process::PROCESS_CACHE;
 use crate::time;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::unit::TimestampScale;
 use crate::util::container::map::Map;
 use crate::util::container::map::MapIterator;
 use crate::util::container::vec::Vec;
 use crate::util::lock::*;
-use crate::util::math;
 use crate::util::math::rational::Rational;
 use crate::util::ptr::arc::Arc;
 use core::arch::asm;
 use core::cmp::max;
+use core::ffi::c_int;
 use core::ffi::c_void;
+use core::mem;
 
 /// The size of the temporary stack for context switching.
 const TMP_STACK_SIZE: usize = 16 * memory::PAGE_SIZE;
-/// The number of quanta for the process with the average priority.
-const AVERAGE_PRIORITY_QUANTA: usize = 10;
-/// The number of quanta for the process with the maximum priority.
-const MAX_PRIORITY_QUANTA: usize = 30;
+/// The period during which every runnable process should run at least once, in nanoseconds.
+const SCHED_LATENCY: u64 = 20_000_000;
+/// The minimum time slice of a process, in nanoseconds.
+const MIN_GRANULARITY: u64 = 4_000_000;
+/// The advance in virtual runtime a woken process of weight [`NICE_0_WEIGHT`] must have on the
+/// running process to preempt it, in nanoseconds.
+const WAKEUP_GRANULARITY: u64 = 1_000_000;
+/// The frequency of the tick scheduling a process woken up while the CPU is idle, in hertz.
+const IDLE_WAKE_FREQUENCY: i64 = 1000;
+
+/// The interval between two updates of the load averages, in nanoseconds.
+const LOAD_FREQ: u64 = 5_000_000_000;
+/// The number of bits of the fractional part of load averages, which are fixed-point numbers.
+pub const FSHIFT: u32 = 11;
+/// The value `1.0` for load averages.
+pub const FIXED_1: usize = 1 << FSHIFT;
+/// The decay factors of the load averages over 1, 5 and 15 minutes, for an interval of
+/// [`LOAD_FREQ`]. Each factor is `FIXED_1 / exp(5s / period)`.
+const LOAD_EXP: [usize; 3] = [1884, 2014, 2037];
+
+/// The normal scheduling policy.
+pub const SCHED_OTHER: c_int = 0;
+/// Real-time, first-in first-out scheduling policy. A process runs until it blocks or until a
+/// process with a higher priority becomes runnable.
+pub const SCHED_FIFO: c_int = 1;
+/// Real-time, round-robin scheduling policy. Processes with the same priority share the CPU.
+pub const SCHED_RR: c_int = 2;
+
+/// The minimum real-time priority.
+pub const RT_PRIORITY_MIN: u32 = 1;
+/// The maximum real-time priority.
+pub const RT_PRIORITY_MAX: u32 = 99;
+
+/// The minimum nice value, giving the highest priority.
+pub const NICE_MIN: i32 = -20;
+/// The maximum nice value, giving the lowest priority.
+pub const NICE_MAX: i32 = 19;
+/// The scheduling weight of a process with a nice value of zero.
+pub const NICE_0_WEIGHT: usize = 1024;
+
+/// The scheduling weight for each nice value, from [`NICE_MIN`] to [`NICE_MAX`].
+///
+/// Each step changes the CPU time received by a process by about 10%.
+static NICE_TO_WEIGHT: [usize; 40] = [
+	88761, 71755, 56483, 46273, 36291, 29154, 23254, 18705, 14949, 11916, 9548, 7620, 6100, 4904,
+	3906, 3121, 2501, 1991, 1586, 1277, 1024, 820, 655, 526, 423, 335, 272, 215, 172, 137, 110,
+	87, 70, 56, 45, 36, 29, 23, 18, 15,
+];
+
+/// Returns the scheduling weight for the nice value `nice`.
+pub fn nice_to_weight(nice: i32) -> usize {
+	NICE_TO_WEIGHT[(nice.clamp(NICE_MIN, NICE_MAX) - NICE_MIN) as usize]
+}
+
+/// Returns the fixed-point number `x` raised to the power `n`.
+fn fixed_power(mut x: usize, mut n: u64) -> usize {
+	let mut result = FIXED_1;
+	while n > 0 {
+		if n & 1 != 0 {
+			result = (result * x + FIXED_1 / 2) >> FSHIFT;
+		}
+		x = (x * x + FIXED_1 / 2) >> FSHIFT;
+		n >>= 1;
+	}
+	result
+}
+
+/// Returns the load average `load` updated with the number of active processes `active`, using
+/// the decay factor `exp`.
+///
+/// Every values are fixed-point numbers.
+fn calc_load(load: usize, exp: usize, active: usize) -> usize {
+	let new_load = load * exp + active * (FIXED_1 - exp);
+	// Round up when the load increases, so that it can reach `active`
+	if active >= load {
+		(new_load + FIXED_1 - 1) >> FSHIFT
+	} else {
+		new_load >> FSHIFT
+	}
+}
+
+/// Scheduling parameters, as exchanged with userspace.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct SchedParam {
+	/// The real-time priority.
+	pub sched_priority: c_int,
+}
+
+/// A set of CPUs, where each bit represents the CPU with the same ID.
+pub type CpuSet = u32;
+/// The set containing every CPUs.
+pub const CPU_SET_ALL: CpuSet = !0;
+
+/// The state of a process for the fair scheduler, along with statistics.
+///
+/// Durations and timestamps are in nanoseconds.
+#[derive(Clone, Debug, Default)]
+pub struct SchedEntity {
+	/// The time the process ran, weighted by its scheduling weight.
+	pub vruntime: u64,
+	/// The total time the process ran.
+	pub sum_exec_runtime: u64,
+	/// The timestamp at which the runtime of the process was last accounted.
+	pub exec_start: u64,
+	/// The number of times the process has been switched out.
+	pub nr_switches: u64,
+	/// The number of times the process has been switched out because it was not runnable
+	/// anymore.
+	pub nr_voluntary_switches: u64,
+	/// The number of times the process has been switched out while still runnable.
+	pub nr_involuntary_switches: u64,
+}
+
+impl SchedEntity {
+	/// Accounts for the time the process ran since the last accounting, up to `now`.
+	///
+	/// `weight` is the scheduling weight of the process.
+	///
+	/// The function returns the time the process ran since the last accounting.
+	fn account(&mut self, now: u64, weight: usize) -> u64 {
+		let delta = now.saturating_sub(self.exec_start);
+		self.sum_exec_runtime += delta;
+		self.vruntime += delta * NICE_0_WEIGHT as u64 / max(weight, 1) as u64;
+		self.exec_start = now;
+		delta
+	}
+}
+
+/// The processes assigned to a CPU core.
+struct RunQueue {
+	/// The processes that run on the core.
+	processes: Map<Pid, Arc<IntMutex<Process>>>,
+	/// The processes of the queue, except the running one, ordered by virtual runtime.
+	timeline: Map<(u64, Pid), ()>,
+	/// The lowest virtual runtime of the queue. This value only increases.
+	min_vruntime: u64,
+
+	/// The currently running process with its PID.
+	curr_proc: Option<(Pid, Arc<IntMutex<Process>>)>,
+	/// The virtual runtime of the running process when it was last accounted.
+	curr_vruntime: u64,
+	/// The timestamp at which the running process started its time slice, in nanoseconds.
+	slice_start: u64,
+	/// Tells whether the running process has to be preempted at the next tick.
+	need_resched: bool,
+}
+
+impl RunQueue {
+	/// Creates an empty run queue.
+	fn new() -> Self {
+		Self {
+			processes: Map::new(),
+			timeline: Map::new(),
+			min_vruntime: 0,
+
+			curr_proc: None,
+			curr_vruntime: 0,
+			slice_start: 0,
+			need_resched: false,
+		}
+	}
+
+	/// Returns the number of processes of the queue that can run on the core `core`.
+	fn runnable_count(&self, core: usize) -> usize {
+		self.processes
+			.iter()
+			.filter(|(_, proc)| Scheduler::can_run(&proc.lock(), core))
+			.count()
+	}
+
+	/// Returns the sum of the weights of the normal processes of the queue that can run on the
+	/// core `core`, along with the number of such processes.
+	fn load(&self, core: usize) -> (usize, usize) {
+		self.processes
+			.iter()
+			.map(|(_, proc)| proc.lock())
+			.filter(|proc| Scheduler::can_run(proc, core) && proc.get_rt_priority().is_none())
+			.fold((0, 0), |(weight, count), proc| {
+				(weight + Scheduler::get_weight(&proc), count + 1)
+			})
+	}
+}
 
 /// The structure representing the process scheduler.
 pub struct Scheduler {
@@ -48,32 +254,36 @@ pub struct Scheduler {
 	/// The ticking callback hook, called at a regular interval to make the
 	/// scheduler work.
 	tick_callback_hook: CallbackHook,
+	/// The ticking callback hook for the local APIC timer of application processors.
+	apic_tick_callback_hook: CallbackHook,
 	/// The total number of ticks since the instanciation of the scheduler.
 	total_ticks: u64,
 
 	/// A binary tree containing all processes registered to the current
 	/// scheduler.
 	processes: Map<Pid, Arc<IntMutex<Process>>>,
-	/// The currently running process with its PID.
-	curr_proc: Option<(Pid, Arc<IntMutex<Process>>)>,
+	/// The run queue of each CPU core.
+	run_queues: Vec<RunQueue>,
 
 	/// The current number of running processes.
 	running_procs: usize,
 
-	/// The sum of all priorities, used to compute the average priority.
-	priority_sum: usize,
-	/// The priority of the processs which has the current highest priority.
-	priority_max: usize,
+	/// The load averages over 1, 5 and 15 minutes, as fixed-point numbers.
+	load_avg: [usize; 3],
+	/// The timestamp of the next update of the load averages, in nanoseconds.
+	next_load_update: u64,
 }
 
 impl Scheduler {
 	/// Creates a new instance of scheduler.
 	pub fn new(cores_count: usize) -> AllocResult<Arc<IntMutex<Self>>> {
 		let mut tmp_stacks = Vec::new();
+		let mut run_queues = Vec::new();
 		for _ in 0..cores_count {
 			tmp_stacks.push(malloc::Alloc::new_default(
 				TMP_STACK_SIZE.try_into().unwrap(),
 			)?)?;
+			run_queues.push(RunQueue::new())?;
 		}
 
 		// Register tick handler
@@ -81,8 +291,15 @@ impl Scheduler {
 		let pit = clocks.get_mut(b"pit".as_slice()).unwrap();
 		let tick_callback_hook = event::register_callback(
 			pit.get_interrupt_vector(),
-			|_: u32, _: u32, regs: &Regs, ring: u32| {
-				Scheduler::tick(process::get_scheduler(), regs, ring);
+			|id: u32, _: u32, regs: &Regs, ring: u32| {
+				Scheduler::tick(process::get_scheduler(), id, regs, ring);
+			},
+		)?
+		.unwrap();
+		let apic_tick_callback_hook = event::register_callback(
+			idt::APIC_TIMER_VECTOR,
+			|id: u32, _: u32, regs: &Regs, ring: u32| {
+				Scheduler::tick(process::get_scheduler(), id, regs, ring);
 			},
 		)?
 		.unwrap();
@@ -91,15 +308,16 @@ impl Scheduler {
 			tmp_stacks,
 
 			tick_callback_hook,
+			apic_tick_callback_hook,
 			total_ticks: 0,
 
 			processes: Map::new(),
-			curr_proc: None,
+			run_queues,
 
 			running_procs: 0,
 
-			priority_sum: 0,
-			priority_max: 0,
+			load_avg: [0; 3],
+			next_load_update: 0,
 		}))
 	}
 
@@ -112,12 +330,54 @@ impl Scheduler {
 		}
 	}
 
+	/// Returns the set of CPUs which are online.
+	pub fn get_online_cpus(&self) -> CpuSet {
+		smp::get_online_cpus()
+	}
+
 	/// Returns the total number of ticks since the instanciation of the
 	/// scheduler.
 	pub fn get_total_ticks(&self) -> u64 {
 		self.total_ticks
 	}
 
+	/// Returns the number of processes.
+	pub fn get_processes_count(&self) -> usize {
+		self.processes.len()
+	}
+
+	/// Returns the number of running processes.
+	pub fn get_running_count(&self) -> usize {
+		self.running_procs
+	}
+
+	/// Returns the load averages over 1, 5 and 15 minutes, as fixed-point numbers with
+	/// [`FSHIFT`] bits of fractional part.
+	pub fn get_load_avg(&self) -> [usize; 3] {
+		self.load_avg
+	}
+
+	/// Updates the load averages if the update interval has elapsed at the timestamp `now`, in
+	/// nanoseconds.
+	///
+	/// Since ticks may be skipped while idle, several intervals may have elapsed since the last
+	/// update. The number of running processes is assumed not to have changed in the meantime.
+	fn update_load_avg(&mut self, now: u64) {
+		if self.next_load_update == 0 {
+			self.next_load_update = now + LOAD_FREQ;
+			return;
+		}
+		if now < self.next_load_update {
+			return;
+		}
+		let missed = (now - self.next_load_update) / LOAD_FREQ + 1;
+		let active = self.running_procs * FIXED_1;
+		for (load, exp) in self.load_avg.iter_mut().zip(LOAD_EXP) {
+			*load = calc_load(*load, fixed_power(exp, missed), active);
+		}
+		self.next_load_update += missed * LOAD_FREQ;
+	}
+
 	/// Returns an iterator on the scheduler's processes.
 	pub fn iter_process(&mut self) -> MapIterator<'_, Pid, Arc<IntMutex<Process>>> {
 		self.processes.iter()
@@ -133,49 +393,63 @@ impl Scheduler {
 	/// Returns the process with TID `tid`.
 	///
 	/// If the process doesn't exist, the function returns `None`.
-	pub fn get_by_tid(&self, _tid: Pid) -> Option<Arc<IntMutex<Process>>> {
-		// TODO
-		todo!();
+	pub fn get_by_tid(&self, tid: Pid) -> Option<Arc<IntMutex<Process>>> {
+		// Each thread has its own PID, which is its TID
+		self.get_by_pid(tid)
 	}
 
-	/// Returns the current running process.
+	/// Returns the process running on the current CPU core.
 	///
 	/// If no process is running, the function returns `None`.
 	pub fn get_current_process(&mut self) -> Option<Arc<IntMutex<Process>>> {
-		Some(self.curr_proc.as_ref().cloned()?.1)
+		let rq = self.run_queues.get(cpu::get_current_id())?;
+		Some(rq.curr_proc.as_ref().cloned()?.1)
 	}
 
-	/// Updates the scheduler's heuristic with the new priority of a process.
+	/// Returns the online CPU core allowed by `affinity` with the least processes.
 	///
-	/// Arguments:
-	/// - `old` is the old priority of the process.
-	/// - `new` is the new priority of the process.
-	///
-	/// The function doesn't need to know the process which has been updated
-	/// since it updates global informations.
-	pub fn update_priority(&mut self, old: usize, new: usize) {
-		self.priority_sum = self.priority_sum - old + new;
-
-		if new >= self.priority_max {
-			self.priority_max = new;
-		}
-
-		// FIXME: Unable to determine priority_max when new < old
+	/// If no online core is allowed, the function returns the bootstrap processor.
+	fn select_cpu(&self, affinity: CpuSet) -> usize {
+		let online = smp::get_online_cpus();
+		self.run_queues
+			.iter()
+			.enumerate()
+			.filter(|(core, _)| (online & affinity) & (1 << core) != 0)
+			.min_by_key(|(_, rq)| rq.processes.len())
+			.map(|(core, _)| core)
+			.unwrap_or(0)
 	}
 
 	/// Adds a process to the scheduler.
-	pub fn add_process(&mut self, process: Process) -> AllocResult<Arc<IntMutex<Process>>> {
+	pub fn add_process(&mut self, mut process: Process) -> AllocResult<Arc<IntMutex<Process>>> {
 		let pid = process.pid;
-		let priority = process.priority;
 
-		if *process.get_state() == State::Running {
-			self.increment_running();
+		let core = self.select_cpu(process.cpu_affinity);
+		let rq = &mut self.run_queues[core];
+		// Do not let the process run before the others of the queue
+		let vruntime = max(process.sched_entity.vruntime, rq.min_vruntime);
+		process.sched_entity.vruntime = vruntime;
+		let running = *process.get_state() == State::Running;
+
+		let ptr = Arc::new_in(IntMutex::new(process), &PROCESS_CACHE)?;
+		rq.timeline.insert((vruntime, pid), ())?;
+		if let Err(e) = rq.processes.insert(pid, ptr.clone()) {
+			rq.timeline.remove(&(vruntime, pid));
+			return Err(e);
+		}
+		if let Err(e) = self.processes.insert(pid, ptr.clone()) {
+			let rq = &mut self.run_queues[core];
+			rq.timeline.remove(&(vruntime, pid));
+			rq.processes.remove(&pid);
+			return Err(e);
 		}
 
-		let ptr = Arc::new(IntMutex::new(process))?;
-		self.processes.insert(pid, ptr.clone())?;
-		self.update_priority(0, priority);
-
+		if running {
+			self.increment_running();
+			if self.run_queues[core].curr_proc.is_some() {
+				self.kick_idle(core);
+			}
+		}
 		Ok(ptr)
 	}
 
@@ -188,9 +462,117 @@ impl Scheduler {
 				self.decrement_running();
 			}
 
-			let priority = proc.priority;
+			let vruntime = proc.sched_entity.vruntime;
 			self.processes.remove(&pid);
-			self.update_priority(priority, 0);
+			for rq in self.run_queues.iter_mut() {
+				rq.processes.remove(&pid);
+				rq.timeline.remove(&(vruntime, pid));
+			}
+		}
+	}
+
+	/// Updates the virtual runtime of the process `process`, which is waking up, then preempts
+	/// the process running on its core if the woken process should run first.
+	///
+	/// This function also increments the number of running processes.
+	pub fn wake_process(&mut self, process: &mut Process) {
+		self.increment_running();
+
+		let pid = process.pid;
+		let Some(core) = self
+			.run_queues
+			.iter()
+			.position(|rq| rq.processes.get(pid).is_some())
+		else {
+			return;
+		};
+		let rq = &mut self.run_queues[core];
+		if rq.curr_proc.as_ref().map(|(pid, _)| *pid) == Some(pid) {
+			return;
+		}
+
+		// A process that slept for a long time must not monopolize the CPU to catch up
+		let old = process.sched_entity.vruntime;
+		let new = max(old, rq.min_vruntime.saturating_sub(SCHED_LATENCY / 2));
+		if new != old {
+			rq.timeline.remove(&(old, pid));
+			process.sched_entity.vruntime = new;
+			// On allocation failure, the process is still found by scanning the queue
+			let _ = rq.timeline.insert((new, pid), ());
+		}
+
+		let Some(curr_vruntime) = rq.curr_proc.as_ref().map(|_| rq.curr_vruntime) else {
+			// The core is idle
+			if core != cpu::get_current_id() || idle::is_tick_stopped(core) {
+				Self::resched(core);
+			}
+			return;
+		};
+		// The core is busy, let an idle core take the process
+		self.kick_idle(core);
+		let rq = &mut self.run_queues[core];
+		let preempt = if process.get_rt_priority().is_some() {
+			true
+		} else {
+			let weight = Self::get_weight(process);
+			let gran = WAKEUP_GRANULARITY * NICE_0_WEIGHT as u64 / max(weight, 1) as u64;
+			curr_vruntime > new + gran
+		};
+		if preempt {
+			rq.need_resched = true;
+			Self::resched(core);
+		}
+	}
+
+	/// Triggers a tick of the scheduler on the CPU core `core` as soon as possible.
+	///
+	/// If the local APIC is not available, the core is rescheduled at its next tick.
+	fn resched(core: usize) {
+		if !apic::is_mapped() {
+			return;
+		}
+		if core == cpu::get_current_id() {
+			// The interrupt is received once interrupts are enabled again
+			apic::send_self_ipi(idt::APIC_TIMER_VECTOR);
+		} else {
+			apic::send_ipi(smp::get_apic_id(core), idt::APIC_TIMER_VECTOR);
+		}
+	}
+
+	/// Triggers a tick on an idle CPU core other than `busy` whose tick is stopped, so that it
+	/// pulls a process from the busiest core.
+	fn kick_idle(&self, busy: usize) {
+		let online = smp::get_online_cpus();
+		let idle_core = self.run_queues.iter().enumerate().position(|(core, rq)| {
+			core != busy
+				&& online & (1 << core) != 0
+				&& rq.curr_proc.is_none()
+				&& idle::is_tick_stopped(core)
+		});
+		if let Some(core) = idle_core {
+			Self::resched(core);
+		}
+	}
+
+	/// Stops or restarts the tick of the current CPU core `core`, according to whether it is
+	/// `idle`.
+	///
+	/// The tick of the bootstrap processor is restarted when a process becomes runnable.
+	fn update_tick(core: usize, idle: bool) {
+		if idle::is_tick_stopped(core) == idle {
+			return;
+		}
+		idle::set_tick_stopped(core, idle);
+		if core == 0 {
+			if idle {
+				let mut clocks = time::hw::CLOCKS.lock();
+				let pit = clocks.get_mut(b"pit".as_slice()).unwrap();
+				pit.set_enabled(false);
+			}
+		} else if idle {
+			apic::stop_timer();
+		} else {
+			apic::start_timer(smp::AP_TICK_FREQUENCY);
 		}
 	}
 
@@ -206,12 +588,28 @@ impl Scheduler {
 		let mut clocks = time::hw::CLOCKS.lock();
 		let pit = clocks.get_mut(b"pit".as_slice()).unwrap();
 
-		if self.running_procs > 1 {
+		if self.run_queues[0].curr_proc.is_none() {
+			// The CPU is idle, thus a tick is needed soon to run the process
+			pit.set_frequency(Rational::from_integer(IDLE_WAKE_FREQUENCY));
+			pit.set_enabled(true);
+		} else if self.running_procs > 1 {
 			pit.set_frequency(self.get_ticking_frequency());
 			pit.set_enabled(true);
 		}
 	}
 
+	/// Updates the PIT according to the number of running processes.
+	fn update_pit(&self) {
+		let mut clocks = time::hw::CLOCKS.lock();
+		let pit = clocks.get_mut(b"pit".as_slice()).unwrap();
+
+		if self.running_procs > 1 {
+			pit.set_frequency(self.get_ticking_frequency());
+		} else {
+			pit.set_enabled(false);
+		}
+	}
+
 	/// Decrements the number of running processes.
 	pub fn decrement_running(&mut self) {
 		self.running_procs -= 1;
@@ -226,90 +624,278 @@ impl Scheduler {
 		}
 	}
 
-	// TODO Clean
-	/// Returns the number of quantum for the given priority.
+	/// Returns the scheduling weight of the process `process`, scaled according to its control
+	/// group.
+	fn get_weight(process: &Process) -> usize {
+		process.cgroup.scale_weight(process.priority)
+	}
+
+	/// Tells whether the given process `process` can run on the CPU core `core`.
+	fn can_run(process: &Process, core: usize) -> bool {
+		if process.cpu_affinity & (1 << core) == 0 {
+			return false;
+		}
+		process.can_run()
+	}
+
+	/// Balances the load between CPU cores in favor of the core `core`.
 	///
-	/// Arguments:
-	/// - `priority` is the process's priority.
-	/// - `priority_sum` is the sum of all processes' priorities.
-	/// - `priority_max` is the highest priority a process currently has.
-	/// - `processes_count` is the number of processes.
-	fn get_quantum_count(
-		priority: usize,
-		priority_sum: usize,
-		priority_max: usize,
-		processes_count: usize,
-	) -> usize {
-		let n = math::integer_linear_interpolation::<isize>(
-			priority as _,
-			(priority_sum / processes_count) as _,
-			priority_max as _,
-			AVERAGE_PRIORITY_QUANTA as _,
-			MAX_PRIORITY_QUANTA as _,
-		);
-		max(1, n) as _
-	}
-
-	// TODO Clean
-	/// Tells whether the given process `process` can run.
+	/// Processes of the core's queue that are not allowed to run on it anymore are moved to
+	/// another core. Then, if the core has nothing to run, a runnable process is pulled from the
+	/// busiest core.
+	fn balance(&mut self, core: usize) -> AllocResult<()> {
+		// Move processes whose affinity excludes the core
+		let curr_pid = self.run_queues[core]
+			.curr_proc
+			.as_ref()
+			.map(|(pid, _)| *pid);
+		let misplaced = self.run_queues[core]
+			.processes
+			.iter()
+			.filter(|(pid, proc)| {
+				Some(**pid) != curr_pid && proc.lock().cpu_affinity & (1 << core) == 0
+			})
+			.map(|(pid, proc)| (*pid, proc.lock().cpu_affinity))
+			.collect::<CollectResult<Vec<_>>>()
+			.0?;
+		for (pid, affinity) in misplaced {
+			let target = self.select_cpu(affinity);
+			if target != core {
+				self.migrate(pid, core, target)?;
+			}
+		}
+
+		if self.run_queues[core].runnable_count(core) > 0 {
+			return Ok(());
+		}
+		// Pull a process from the busiest core
+		let busiest = self
+			.run_queues
+			.iter()
+			.enumerate()
+			.filter(|(i, _)| *i != core)
+			.map(|(i, rq)| (i, rq.runnable_count(i)))
+			.filter(|(_, count)| *count > 1)
+			.max_by_key(|(_, count)| *count)
+			.map(|(i, _)| i);
+		let Some(busiest) = busiest else {
+			return Ok(());
+		};
+		let rq = &self.run_queues[busiest];
+		let busiest_curr = rq.curr_proc.as_ref().map(|(pid, _)| *pid);
+		let pid = rq
+			.processes
+			.iter()
+			.find(|(pid, proc)| Some(**pid) != busiest_curr && Self::can_run(&proc.lock(), core))
+			.map(|(pid, _)| *pid);
+		if let Some(pid) = pid {
+			self.migrate(pid, busiest, core)?;
+		}
+		Ok(())
+	}
+
+	/// Moves the process with PID `pid` from the run queue of the core `from` to the run queue
+	/// of the core `to`.
 	///
-	/// TODO args
-	fn can_run(
-		process: &Process,
-		_priority_sum: usize,
-		_priority_max: usize,
-		_processes_count: usize,
-	) -> bool {
-		if process.can_run() {
-			// TODO fix
-			//process.quantum_count < Self::get_quantum_count(process.get_priority(),
-			// priority_sum, 	priority_max, processes_count)
-			true
-		} else {
-			false
+	/// The virtual runtime of the process is adjusted relatively to the new queue.
+	///
+	/// The process must not be running.
+	fn migrate(&mut self, pid: Pid, from: usize, to: usize) -> AllocResult<()> {
+		let Some(proc_mutex) = self.run_queues[from].processes.get(pid).cloned() else {
+			return Ok(());
+		};
+		let mut proc = proc_mutex.lock();
+		let old = proc.sched_entity.vruntime;
+		let new = (old + self.run_queues[to].min_vruntime)
+			.saturating_sub(self.run_queues[from].min_vruntime);
+
+		// Insert first so that the process is not lost on allocation failure
+		let dest = &mut self.run_queues[to];
+		dest.timeline.insert((new, pid), ())?;
+		if let Err(e) = dest.processes.insert(pid, proc_mutex.clone()) {
+			dest.timeline.remove(&(new, pid));
+			return Err(e);
 		}
+		let src = &mut self.run_queues[from];
+		src.processes.remove(&pid);
+		src.timeline.remove(&(old, pid));
+		proc.sched_entity.vruntime = new;
+		Ok(())
 	}
 
-	// TODO Clean
-	/// Returns the next process to run with its PID.
+	/// Returns the real-time process to run next on the CPU core `core` with its PID.
+	///
+	/// Only processes with the highest real-time priority are considered. A [`SCHED_FIFO`]
+	/// process keeps running as long as it is among them. Otherwise, processes take turns.
+	///
+	/// `prev` is the previously running process.
 	///
-	/// If the process is changed, the quantum count of the previous process is reset.
-	fn get_next_process(&self) -> Option<(Pid, Arc<IntMutex<Process>>)> {
-		let priority_sum = self.priority_sum;
-		let priority_max = self.priority_max;
-		let processes_count = self.processes.len();
-
-		// Getting the current process, or take the first process in the list if no
-		// process is running
-		let (curr_pid, curr_proc) = self.curr_proc.clone().or_else(|| {
-			self.processes
+	/// If no real-time process is runnable, the function returns `None`.
+	fn pick_rt(
+		rq: &RunQueue,
+		core: usize,
+		prev: Option<&(Pid, Arc<IntMutex<Process>>)>,
+	) -> Option<(Pid, Arc<IntMutex<Process>>)> {
+		// The highest real-time priority among runnable processes
+		let rt_priority = rq
+			.processes
+			.iter()
+			.filter_map(|(_, proc)| {
+				let guard = proc.lock();
+				Self::can_run(&guard, core)
+					.then(|| guard.get_rt_priority())
+					.flatten()
+			})
+			.max();
+		rt_priority?;
+
+		// Getting the previous process, or take the first process in the list if no
+		// process was running
+		let (prev_pid, prev_proc) = prev.cloned().or_else(|| {
+			rq.processes
 				.iter()
 				.next()
 				.map(|(pid, proc)| (*pid, proc.clone()))
 		})?;
+		{
+			let guard = prev_proc.lock();
+			if guard.sched_policy == SCHED_FIFO
+				&& guard.get_rt_priority() == rt_priority
+				&& Self::can_run(&guard, core)
+			{
+				drop(guard);
+				return Some((prev_pid, prev_proc));
+			}
+		}
 
 		let process_filter = |(_, proc): &(&Pid, &Arc<IntMutex<Process>>)| {
 			let guard = proc.lock();
-			Self::can_run(&guard, priority_sum, priority_max, processes_count)
+			guard.get_rt_priority() == rt_priority && Self::can_run(&guard, core)
 		};
-
-		let next_proc = self
-			.processes
-			.range((curr_pid + 1)..)
+		rq.processes
+			.range((prev_pid + 1)..)
 			.find(process_filter)
 			.or_else(|| {
 				// If no suitable process is found, go back to the beginning to check processes
 				// located before the previous process (looping)
+				rq.processes.iter().find(process_filter)
+			})
+			.map(|(pid, proc)| (*pid, proc.clone()))
+	}
 
-				self.processes.iter().find(process_filter)
+	/// Returns the normal process with the lowest virtual runtime that can run on the CPU core
+	/// `core`, with its PID.
+	///
+	/// Processes whose control group is throttled at the timestamp `now` are skipped.
+	fn pick_fair(rq: &RunQueue, core: usize, now: u64) -> Option<(Pid, Arc<IntMutex<Process>>)> {
+		let can_run = |proc: &Process| Self::can_run(proc, core) && !proc.cgroup.is_throttled(now);
+		let next = rq
+			.timeline
+			.iter()
+			.filter_map(|((_, pid), _)| Some((*pid, rq.processes.get(*pid)?)))
+			.find(|(_, proc)| can_run(&proc.lock()));
+		if let Some((pid, proc)) = next {
+			return Some((pid, proc.clone()));
+		}
+		// A process might be missing from the timeline after an allocation failure
+		rq.processes
+			.iter()
+			.filter_map(|(pid, proc)| {
+				let guard = proc.lock();
+				can_run(&guard).then_some((guard.sched_entity.vruntime, *pid, proc))
 			})
-			.map(|(pid, proc)| (*pid, proc));
+			.min_by_key(|(vruntime, pid, _)| (*vruntime, *pid))
+			.map(|(_, pid, proc)| (pid, proc.clone()))
+	}
 
-		let (next_pid, next_proc) = next_proc?;
-		if next_pid != curr_pid || processes_count == 1 {
-			curr_proc.lock().quantum_count = 0;
+	/// Accounts for the runtime of the process running on the CPU core `core`, then selects the
+	/// next process to run on it and returns it with its PID.
+	///
+	/// `now` is the current timestamp, in nanoseconds.
+	///
+	/// The running process keeps running until the end of its time slice, unless it has to be
+	/// preempted.
+	fn pick_next(&mut self, core: usize, now: u64) -> Option<(Pid, Arc<IntMutex<Process>>)> {
+		let (load_weight, load_count) = self.run_queues[core].load(core);
+		let rq = &mut self.run_queues[core];
+		let need_resched = mem::take(&mut rq.need_resched);
+		let prev = rq.curr_proc.take();
+
+		// Put the previous process back into the timeline
+		let mut prev_keeps_running = false;
+		if let Some((pid, proc)) = &prev {
+			// The process may have been removed in the meantime
+			if rq.processes.get(*pid).is_some() {
+				let mut proc = proc.lock();
+				let user = !proc.syscalling;
+				proc.account_cpu_time(now, user);
+				let weight = Self::get_weight(&proc);
+				let delta = proc.sched_entity.account(now, weight);
+				if proc.get_rt_priority().is_none() {
+					proc.cgroup.charge_cpu(delta, now);
+				}
+
+				let period = max(SCHED_LATENCY, load_count as u64 * MIN_GRANULARITY);
+				let slice = max(
+					period * weight as u64 / max(load_weight, 1) as u64,
+					MIN_GRANULARITY,
+				);
+				prev_keeps_running = !need_resched
+					&& Self::can_run(&proc, core)
+					&& proc.get_rt_priority().is_none()
+					&& !proc.cgroup.is_throttled(now)
+					&& now.saturating_sub(rq.slice_start) < slice;
+				// On allocation failure, the process is still found by scanning the queue
+				let _ = rq.timeline.insert((proc.sched_entity.vruntime, *pid), ());
+			}
+		}
+
+		let next = match Self::pick_rt(rq, core, prev.as_ref()) {
+			Some(next) => Some(next),
+			None if prev_keeps_running => prev.clone(),
+			None => Self::pick_fair(rq, core, now),
+		};
+
+		let prev_pid = prev.as_ref().map(|(pid, _)| *pid);
+		let next_pid = next.as_ref().map(|(pid, _)| *pid);
+		if let Some((pid, proc)) = &next {
+			let mut proc = proc.lock();
+			let vruntime = proc.sched_entity.vruntime;
+			rq.timeline.remove(&(vruntime, *pid));
+			if proc.get_rt_priority().is_none() {
+				rq.min_vruntime = max(rq.min_vruntime, vruntime);
+			}
+			rq.curr_vruntime = vruntime;
+			if next_pid != prev_pid {
+				rq.slice_start = now;
+			}
+			proc.sched_entity.exec_start = now;
+			proc.start_cpu_time(now);
 		}
-		Some((next_pid, next_proc.clone()))
+		if let Some((_, proc)) = prev.as_ref().filter(|_| next_pid != prev_pid) {
+			let mut proc = proc.lock();
+			let runnable = Self::can_run(&proc, core);
+			let entity = &mut proc.sched_entity;
+			entity.nr_switches += 1;
+			if runnable {
+				entity.nr_involuntary_switches += 1;
+			} else {
+				entity.nr_voluntary_switches += 1;
+			}
+		}
+
+		rq.curr_proc = next.clone();
+		// If only throttled processes are left, the core must keep ticking to run them again at
+		// the end of their period
+		let throttled = next.is_none() && rq.runnable_count(core) > 0;
+		if throttled && core == 0 {
+			let mut clocks = time::hw::CLOCKS.lock();
+			let pit = clocks.get_mut(b"pit".as_slice()).unwrap();
+			pit.set_frequency(Rational::from_integer(IDLE_WAKE_FREQUENCY));
+			pit.set_enabled(true);
+		}
+		Self::update_tick(core, next.is_none() && !throttled);
+		next
 	}
 
 	/// Ticking the scheduler.
@@ -321,15 +907,25 @@ impl Scheduler {
 	///
 	/// Arguments:
 	/// - `sched_mutex` is the scheduler's mutex.
+	/// - `id` is the ID of the interrupt that ticked the scheduler.
 	/// - `regs` is the state of the registers from the paused context.
 	/// - `ring` is the ring of the paused context.
-	fn tick(sched_mutex: &IntMutex<Self>, regs: &Regs, ring: u32) -> ! {
+	fn tick(sched_mutex: &IntMutex<Self>, id: u32, regs: &Regs, ring: u32) -> ! {
 		// Disabling interrupts to avoid getting one right after unlocking mutexes
 		cli!();
 
+		// The current core ID
+		let core_id = cpu::get_current_id();
 		let tmp_stack = {
 			let mut sched = sched_mutex.lock();
 			sched.total_ticks += 1;
+			let now =
+				clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond).unwrap_or(0);
+			sched.update_load_avg(now);
+			// The PIT may have been enabled to leave the idle state
+			if core_id == 0 {
+				sched.update_pit();
+			}
 
 			// If a process is running, save its registers
 			if let Some(curr_proc) = sched.get_current_process() {
@@ -339,18 +935,18 @@ impl Scheduler {
 				curr_proc.syscalling = ring < 3;
 			}
 
-			// The current core ID
-			let core_id = 0; // TODO
-			sched.get_tmp_stack(core_id)
+			// On allocation failure, balancing is retried at the next tick
+			let _ = sched.balance(core_id);
+			sched.get_tmp_stack(core_id as _)
 		};
 
 		loop {
 			let mut sched = sched_mutex.lock();
 
-			if let Some(next_proc) = sched.get_next_process() {
-				// Set the process as current
-				sched.curr_proc = Some(next_proc.clone());
-
+			let now =
+				clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond).unwrap_or(0);
+			// Select the next process and set it as current
+			if let Some(next_proc) = sched.pick_next(core_id, now) {
 				drop(sched);
 
 				unsafe {
@@ -370,8 +966,8 @@ impl Scheduler {
 						}
 
 						// Resume execution
-						event::unlock_callbacks(0x20);
-						pic::end_of_interrupt(0x0);
+						event::unlock_callbacks(id as _);
+						idt::end_of_interrupt(id);
 						regs.switch(!syscalling);
 					})
 					.unwrap();
@@ -382,13 +978,9 @@ impl Scheduler {
 			}
 		}
 
-		{
-			sched_mutex.lock().curr_proc = None;
-		}
-
 		unsafe {
-			event::unlock_callbacks(0x20);
-			pic::end_of_interrupt(0x0);
+			event::unlock_callbacks(id as _);
+			idt::end_of_interrupt(id);
 			crate::loop_reset(tmp_stack);
 		}
 	}
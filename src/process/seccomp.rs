@@ -0,0 +1,450 @@
+//! Secure computing mode (seccomp) restricts the system calls a process is allowed to perform.
+//!
+//! Two modes are available:
+//! - Strict mode: only `read`, `write`, `exit` and `sigreturn` are allowed. Any other system
+//! call kills the process.
+//! - Filter mode: every system call is evaluated by the classic BPF programs installed by the
+//! process, which decide the action to take.
+//!
+//! Filters are inherited on fork and cannot be removed. When several filters are installed, all
+//! of them are evaluated and the most restrictive action is taken.
+
+use crate::errno::EResult;
+use crate::process::regs::Regs;
+use crate::util::container::vec::Vec;
+use crate::util::ptr::arc::Arc;
+use core::mem::size_of;
+
+/// Seccomp is disabled.
+pub const SECCOMP_MODE_DISABLED: u32 = 0;
+/// Strict mode.
+pub const SECCOMP_MODE_STRICT: u32 = 1;
+/// Filter mode.
+pub const SECCOMP_MODE_FILTER: u32 = 2;
+
+/// Kills the whole process.
+pub const SECCOMP_RET_KILL_PROCESS: u32 = 0x80000000;
+/// Kills the thread.
+pub const SECCOMP_RET_KILL_THREAD: u32 = 0x00000000;
+/// Sends `SIGSYS` to the thread.
+pub const SECCOMP_RET_TRAP: u32 = 0x00030000;
+/// Returns an errno without performing the system call.
+pub const SECCOMP_RET_ERRNO: u32 = 0x00050000;
+/// Notifies the tracer.
+pub const SECCOMP_RET_TRACE: u32 = 0x7ff00000;
+/// Allows the system call after logging it.
+pub const SECCOMP_RET_LOG: u32 = 0x7ffc0000;
+/// Allows the system call.
+pub const SECCOMP_RET_ALLOW: u32 = 0x7fff0000;
+
+/// Mask of the action part of a filter's return value.
+const SECCOMP_RET_ACTION_FULL: u32 = 0xffff0000;
+/// Mask of the data part of a filter's return value.
+const SECCOMP_RET_DATA: u32 = 0x0000ffff;
+
+/// The audit architecture value for x86 (32 bits).
+const AUDIT_ARCH_I386: u32 = 0x40000003;
+
+/// The maximum number of instructions in a filter.
+const BPF_MAXINSNS: usize = 4096;
+/// The number of words in the scratch memory of the BPF machine.
+const BPF_MEMWORDS: usize = 16;
+
+/// The system calls allowed in strict mode: `exit`, `read`, `write` and `sigreturn`.
+const STRICT_SYSCALLS: [u32; 4] = [0x001, 0x003, 0x004, 0x077];
+
+// Instruction classes
+const BPF_LD: u16 = 0x00;
+const BPF_LDX: u16 = 0x01;
+const BPF_ST: u16 = 0x02;
+const BPF_STX: u16 = 0x03;
+const BPF_ALU: u16 = 0x04;
+const BPF_JMP: u16 = 0x05;
+const BPF_RET: u16 = 0x06;
+const BPF_MISC: u16 = 0x07;
+
+// Load sizes and modes
+const BPF_W: u16 = 0x00;
+const BPF_IMM: u16 = 0x00;
+const BPF_ABS: u16 = 0x20;
+const BPF_MEM: u16 = 0x60;
+const BPF_LEN: u16 = 0x80;
+
+// ALU operations
+const BPF_ADD: u16 = 0x00;
+const BPF_SUB: u16 = 0x10;
+const BPF_MUL: u16 = 0x20;
+const BPF_DIV: u16 = 0x30;
+const BPF_OR: u16 = 0x40;
+const BPF_AND: u16 = 0x50;
+const BPF_LSH: u16 = 0x60;
+const BPF_RSH: u16 = 0x70;
+const BPF_NEG: u16 = 0x80;
+const BPF_MOD: u16 = 0x90;
+const BPF_XOR: u16 = 0xa0;
+
+// Jump operations
+const BPF_JA: u16 = 0x00;
+const BPF_JEQ: u16 = 0x10;
+const BPF_JGT: u16 = 0x20;
+const BPF_JGE: u16 = 0x30;
+const BPF_JSET: u16 = 0x40;
+
+// Operand sources
+const BPF_K: u16 = 0x00;
+const BPF_X: u16 = 0x08;
+const BPF_A: u16 = 0x10;
+
+// Miscellaneous operations
+const BPF_TAX: u16 = 0x00;
+const BPF_TXA: u16 = 0x80;
+
+/// A classic BPF instruction.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct SockFilter {
+	/// The opcode.
+	pub code: u16,
+	/// The jump offset if the condition is true.
+	pub jt: u8,
+	/// The jump offset if the condition is false.
+	pub jf: u8,
+	/// Generic field.
+	pub k: u32,
+}
+
+/// A classic BPF program, as given by userspace.
+#[repr(C)]
+#[derive(Debug)]
+pub struct SockFprog {
+	/// The number of instructions.
+	pub len: u16,
+	/// The instructions.
+	pub filter: *const SockFilter,
+}
+
+/// The data on which a filter operates.
+#[repr(C)]
+struct SeccompData {
+	/// The system call number.
+	nr: i32,
+	/// The architecture the system call is performed on.
+	arch: u32,
+	/// The instruction pointer at the time of the system call.
+	instruction_pointer: u64,
+	/// The arguments of the system call.
+	args: [u64; 6],
+}
+
+impl SeccompData {
+	/// Returns the data for the system call described by the given registers.
+	fn from_regs(regs: &Regs) -> Self {
+		Self {
+			nr: regs.eax as _,
+			arch: AUDIT_ARCH_I386,
+			instruction_pointer: regs.eip as _,
+			args: [
+				regs.ebx as _,
+				regs.ecx as _,
+				regs.edx as _,
+				regs.esi as _,
+				regs.edi as _,
+				regs.ebp as _,
+			],
+		}
+	}
+
+	/// Returns the 32 bits word at the given offset in the structure.
+	///
+	/// The offset must have been checked beforehand.
+	fn load(&self, off: usize) -> u32 {
+		let buf = unsafe {
+			core::slice::from_raw_parts(self as *const _ as *const u8, size_of::<Self>())
+		};
+		u32::from_ne_bytes(buf[off..(off + 4)].try_into().unwrap())
+	}
+}
+
+/// A classic BPF filter, chained with the filters installed before it.
+#[derive(Debug)]
+pub struct Filter {
+	/// The program.
+	prog: Vec<SockFilter>,
+	/// The previously installed filter.
+	prev: Option<Arc<Filter>>,
+}
+
+impl Filter {
+	/// Checks the given program.
+	///
+	/// A program is valid if every instruction is allowed for seccomp, jumps stay in bounds and
+	/// the last instruction is a return.
+	fn check(prog: &[SockFilter]) -> EResult<()> {
+		if prog.is_empty() || prog.len() > BPF_MAXINSNS {
+			return Err(errno!(EINVAL));
+		}
+		for (pc, ins) in prog.iter().enumerate() {
+			let remaining = prog.len() - pc - 1;
+			let k = ins.k as usize;
+			let valid = match ins.code {
+				c if c == BPF_LD | BPF_W | BPF_ABS => {
+					k % 4 == 0 && k + 4 <= size_of::<SeccompData>()
+				}
+				c if c == BPF_LD | BPF_W | BPF_LEN
+					|| c == BPF_LDX | BPF_W | BPF_LEN
+					|| c == BPF_LD | BPF_IMM
+					|| c == BPF_LDX | BPF_IMM =>
+				{
+					true
+				}
+				c if c == BPF_LD | BPF_MEM
+					|| c == BPF_LDX | BPF_MEM
+					|| c == BPF_ST || c == BPF_STX =>
+				{
+					k < BPF_MEMWORDS
+				}
+				c if c & !0xf8 == BPF_ALU => {
+					let op = c & 0xf0;
+					let known = matches!(
+						op,
+						BPF_ADD
+							| BPF_SUB | BPF_MUL | BPF_DIV
+							| BPF_OR | BPF_AND | BPF_LSH | BPF_RSH
+							| BPF_NEG | BPF_MOD | BPF_XOR
+					);
+					// Constant divisors and shift amounts are checked statically
+					let invalid_k = c & BPF_X == 0
+						&& match op {
+							BPF_DIV | BPF_MOD => ins.k == 0,
+							BPF_LSH | BPF_RSH => ins.k >= 32,
+							_ => false,
+						};
+					known && !invalid_k
+				}
+				c if c == BPF_JMP | BPF_JA => k < remaining,
+				c if c & !0xf8 == BPF_JMP => {
+					matches!(c & 0xf0, BPF_JEQ | BPF_JGT | BPF_JGE | BPF_JSET)
+						&& (ins.jt as usize) < remaining
+						&& (ins.jf as usize) < remaining
+				}
+				c if c == BPF_RET | BPF_K || c == BPF_RET | BPF_A => true,
+				c if c == BPF_MISC | BPF_TAX || c == BPF_MISC | BPF_TXA => true,
+				_ => false,
+			};
+			if !valid {
+				return Err(errno!(EINVAL));
+			}
+		}
+		// The program must end with a return
+		if prog[prog.len() - 1].code & 0x07 != BPF_RET {
+			return Err(errno!(EINVAL));
+		}
+		Ok(())
+	}
+
+	/// Runs the program on the given data and returns the value it returns.
+	///
+	/// The program must have been checked beforehand.
+	fn run(&self, data: &SeccompData) -> u32 {
+		let mut a: u32 = 0;
+		let mut x: u32 = 0;
+		let mut mem = [0u32; BPF_MEMWORDS];
+
+		let mut pc = 0;
+		while let Some(ins) = self.prog.get(pc) {
+			pc += 1;
+			let k = ins.k;
+			match ins.code & 0x07 {
+				BPF_LD => {
+					a = match ins.code & 0xe0 {
+						BPF_ABS => data.load(k as _),
+						BPF_LEN => size_of::<SeccompData>() as _,
+						BPF_MEM => mem[k as usize],
+						_ => k,
+					}
+				}
+				BPF_LDX => {
+					x = match ins.code & 0xe0 {
+						BPF_LEN => size_of::<SeccompData>() as _,
+						BPF_MEM => mem[k as usize],
+						_ => k,
+					}
+				}
+				BPF_ST => mem[k as usize] = a,
+				BPF_STX => mem[k as usize] = x,
+				BPF_ALU => {
+					let operand = if ins.code & BPF_X != 0 { x } else { k };
+					a = match ins.code & 0xf0 {
+						BPF_ADD => a.wrapping_add(operand),
+						BPF_SUB => a.wrapping_sub(operand),
+						BPF_MUL => a.wrapping_mul(operand),
+						// Division by zero makes the program return zero
+						BPF_DIV => match a.checked_div(operand) {
+							Some(a) => a,
+							None => return 0,
+						},
+						BPF_MOD => match a.checked_rem(operand) {
+							Some(a) => a,
+							None => return 0,
+						},
+						BPF_OR => a | operand,
+						BPF_AND => a & operand,
+						BPF_LSH => a.checked_shl(operand).unwrap_or(0),
+						BPF_RSH => a.checked_shr(operand).unwrap_or(0),
+						BPF_XOR => a ^ operand,
+						_ => a.wrapping_neg(),
+					}
+				}
+				BPF_JMP => {
+					let operand = if ins.code & BPF_X != 0 { x } else { k };
+					let cond = match ins.code & 0xf0 {
+						BPF_JA => {
+							pc += k as usize;
+							continue;
+						}
+						BPF_JEQ => a == operand,
+						BPF_JGT => a > operand,
+						BPF_JGE => a >= operand,
+						_ => a & operand != 0,
+					};
+					pc += if cond { ins.jt } else { ins.jf } as usize;
+				}
+				BPF_RET => {
+					return if ins.code & BPF_A != 0 { a } else { k };
+				}
+				_ => {
+					if ins.code & BPF_TXA != 0 {
+						a = x;
+					} else {
+						x = a;
+					}
+				}
+			}
+		}
+		// Cannot happen since the last instruction is a return
+		SECCOMP_RET_KILL_PROCESS
+	}
+}
+
+/// The action to take for a system call.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Action {
+	/// The system call is performed.
+	Allow,
+	/// The system call is not performed and the given errno is returned.
+	Errno(u16),
+	/// The system call is not performed and `SIGSYS` is sent to the thread.
+	Trap,
+	/// The thread is killed.
+	KillThread,
+	/// The process is killed.
+	KillProcess,
+}
+
+/// The seccomp state of a process.
+#[derive(Clone, Debug, Default)]
+pub struct Seccomp {
+	/// The current mode.
+	mode: u32,
+	/// The last installed filter.
+	filter: Option<Arc<Filter>>,
+}
+
+impl Seccomp {
+	/// Returns the current mode.
+	pub fn get_mode(&self) -> u32 {
+		self.mode
+	}
+
+	/// Returns the number of installed filters.
+	pub fn get_filters_count(&self) -> usize {
+		let mut count = 0;
+		let mut filter = self.filter.as_ref();
+		while let Some(f) = filter {
+			count += 1;
+			filter = f.prev.as_ref();
+		}
+		count
+	}
+
+	/// Switches to strict mode.
+	///
+	/// If filter mode is enabled, the function returns [`crate::errno::EINVAL`].
+	pub fn set_strict(&mut self) -> EResult<()> {
+		if self.mode == SECCOMP_MODE_FILTER {
+			return Err(errno!(EINVAL));
+		}
+		self.mode = SECCOMP_MODE_STRICT;
+		Ok(())
+	}
+
+	/// Installs the given filter program, switching to filter mode.
+	///
+	/// If the program is invalid or if strict mode is enabled, the function returns
+	/// [`crate::errno::EINVAL`].
+	pub fn add_filter(&mut self, prog: Vec<SockFilter>) -> EResult<()> {
+		if self.mode == SECCOMP_MODE_STRICT {
+			return Err(errno!(EINVAL));
+		}
+		Filter::check(&prog)?;
+		self.filter = Some(Arc::new(Filter {
+			prog,
+			prev: self.filter.take(),
+		})?);
+		self.mode = SECCOMP_MODE_FILTER;
+		Ok(())
+	}
+
+	/// Evaluates the system call described by the given registers and returns the action to
+	/// take.
+	pub fn check(&self, regs: &Regs) -> Action {
+		match self.mode {
+			SECCOMP_MODE_STRICT => {
+				if STRICT_SYSCALLS.contains(&{ regs.eax }) {
+					Action::Allow
+				} else {
+					Action::KillThread
+				}
+			}
+			SECCOMP_MODE_FILTER => {
+				let data = SeccompData::from_regs(regs);
+				// Keep the most restrictive action. Lower action values have precedence
+				let mut ret = SECCOMP_RET_ALLOW;
+				let mut filter = self.filter.as_ref();
+				while let Some(f) = filter {
+					let r = f.run(&data);
+					if ((r & SECCOMP_RET_ACTION_FULL) as i32)
+						< ((ret & SECCOMP_RET_ACTION_FULL) as i32)
+					{
+						ret = r;
+					}
+					filter = f.prev.as_ref();
+				}
+				match ret & SECCOMP_RET_ACTION_FULL {
+					SECCOMP_RET_ALLOW | SECCOMP_RET_LOG => Action::Allow,
+					SECCOMP_RET_ERRNO => Action::Errno((ret & SECCOMP_RET_DATA) as _),
+					// No tracer can be attached
+					SECCOMP_RET_TRACE => Action::Errno(crate::errno::ENOSYS as _),
+					SECCOMP_RET_TRAP => Action::Trap,
+					SECCOMP_RET_KILL_THREAD => Action::KillThread,
+					_ => Action::KillProcess,
+				}
+			}
+			_ => Action::Allow,
+		}
+	}
+}
+
+/// Tells whether the given action is supported, for `SECCOMP_GET_ACTION_AVAIL`.
+pub fn is_action_available(action: u32) -> bool {
+	matches!(
+		action,
+		SECCOMP_RET_KILL_PROCESS
+			| SECCOMP_RET_KILL_THREAD
+			| SECCOMP_RET_TRAP
+			| SECCOMP_RET_ERRNO
+			| SECCOMP_RET_TRACE
+			| SECCOMP_RET_LOG
+			| SECCOMP_RET_ALLOW
+	)
+}
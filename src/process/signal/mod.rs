@@ -2,8 +2,10 @@
 
 mod signal_trampoline;
 
+use super::coredump;
 use super::Process;
 use super::State;
+use crate::cpu::smap;
 use crate::errno::Errno;
 use crate::file::perm::Uid;
 use crate::process::oom;
@@ -20,11 +22,31 @@ use signal_trampoline::signal_trampoline;
 
 /// Type representing a signal handler.
 pub type SigHandler = extern "C" fn(i32);
+/// Type representing a signal handler installed with [`SA_SIGINFO`].
+pub type SigInfoHandler = extern "C" fn(i32, *mut SigInfo, *mut c_void);
 
-/// Ignoring the signal.
-pub const SIG_IGN: *const c_void = 0x0 as _;
 /// The default action for the signal.
-pub const SIG_DFL: *const c_void = 0x1 as _;
+pub const SIG_DFL: *const c_void = 0x0 as _;
+/// Ignoring the signal.
+pub const SIG_IGN: *const c_void = 0x1 as _;
+
+/// Action flag: the handler takes the signal's informations as arguments.
+pub const SA_SIGINFO: u32 = 0x00000004;
+/// Action flag: system calls interrupted by the signal are restarted.
+pub const SA_RESTART: u32 = 0x10000000;
+/// Action flag: the signal is not blocked while its handler is executed.
+pub const SA_NODEFER: u32 = 0x40000000;
+/// Action flag: the handler is reset to the default once the signal is delivered.
+pub const SA_RESETHAND: u32 = 0x80000000;
+
+/// Signal code: the signal has been sent by `kill`.
+pub const SI_USER: i32 = 0;
+/// Signal code: the signal has been sent by the kernel.
+pub const SI_KERNEL: i32 = 0x80;
+/// Signal code: the signal has been sent by `sigqueue`.
+pub const SI_QUEUE: i32 = -1;
+/// Signal code: the signal has been sent by `tkill` or `tgkill`.
+pub const SI_TKILL: i32 = -6;
 
 /// Notify method: generate a signal
 pub const SIGEV_SIGNAL: c_int = 0;
@@ -33,9 +55,38 @@ pub const SIGEV_NONE: c_int = 1;
 /// Notify method: starts a function as a new thread
 pub const SIGEV_THREAD: c_int = 2;
 
+/// `SIGCHLD` code: the child has exited.
+pub const CLD_EXITED: i32 = 1;
+/// `SIGCHLD` code: the child was killed.
+pub const CLD_KILLED: i32 = 2;
+/// `SIGCHLD` code: the child was killed and dumped its core.
+pub const CLD_DUMPED: i32 = 3;
+/// `SIGCHLD` code: the traced child has trapped.
+pub const CLD_TRAPPED: i32 = 4;
+/// `SIGCHLD` code: the child has stopped.
+pub const CLD_STOPPED: i32 = 5;
+/// `SIGCHLD` code: the stopped child has continued.
+pub const CLD_CONTINUED: i32 = 6;
+
+/// `SIGPOLL` code: input data is available.
+pub const POLL_IN: i32 = 1;
+/// `SIGPOLL` code: output buffers are available.
+pub const POLL_OUT: i32 = 2;
+/// `SIGPOLL` code: an IO error occurred.
+pub const POLL_ERR: i32 = 4;
+/// `SIGPOLL` code: high priority input is available.
+pub const POLL_PRI: i32 = 5;
+/// `SIGPOLL` code: the device has been disconnected.
+pub const POLL_HUP: i32 = 6;
+
+/// The first real-time signal.
+pub const SIGRTMIN: u8 = 32;
+/// The last real-time signal.
+pub const SIGRTMAX: u8 = 64;
+
 /// The size of the signal handlers table (the number of signals + 1, since
 /// indexing begins at 1 instead of 0).
-pub const SIGNALS_COUNT: usize = 32;
+pub const SIGNALS_COUNT: usize = SIGRTMAX as usize + 1;
 
 /// Enumeration representing the action to perform for a signal.
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -70,77 +121,82 @@ impl Debug for SigVal {
 }
 
 /// Structure storing signal informations.
+///
+/// The structure has the layout of Linux's `siginfo_t`. Fields after `si_code` are shared by
+/// several kinds of signals, only the ones for process-related signals are exposed.
 #[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
 pub struct SigInfo {
 	/// Signal number.
-	si_signo: i32,
+	pub si_signo: i32,
 	/// An errno value.
-	si_errno: i32,
+	pub si_errno: i32,
 	/// Signal code.
-	si_code: i32,
-	/// Trap number that caused hardware-generated signal.
-	si_trapno: i32,
+	pub si_code: i32,
 	/// Sending process ID.
-	si_pid: Pid,
+	pub si_pid: i32,
 	/// Real user ID of sending process.
-	si_uid: Uid,
+	pub si_uid: u32,
 	/// Exit value or signal.
-	si_status: i32,
-	/// User time consumed.
-	si_utime: ClockIdT,
-	/// System time consumed.
-	si_stime: ClockIdT,
-	/// Signal value
-	si_value: SigVal,
-	/// POSIX.1b signal.
-	si_int: i32,
-	/// POSIX.1b signal.
-	si_ptr: *mut c_void,
-	/// Timer overrun count.
-	si_overrun: i32,
-	/// Timer ID.
-	si_timerid: i32,
-	/// Memory location which caused fault.
-	si_addr: *mut c_void,
-	/// Band event.
-	si_band: i32, // FIXME long (64bits?)
-	/// File descriptor.
-	si_fd: i32,
-	/// Least significant bit of address.
-	si_addr_lsb: i16,
-	/// Lower bound when address violation.
-	si_lower: *mut c_void,
-	/// Upper bound when address violation.
-	si_upper: *mut c_void,
-	/// Protection key on PTE that caused fault.
-	si_pkey: i32,
-	/// Address of system call instruction.
-	si_call_addr: *mut c_void,
-	/// Number of attempted system call.
-	si_syscall: i32,
-	/// Architecture of attempted system call.
-	si_arch: u32,
+	pub si_status: i32,
+	/// User time consumed, in clock ticks.
+	pub si_utime: ClockIdT,
+	/// System time consumed, in clock ticks.
+	pub si_stime: ClockIdT,
+	/// Padding to the size of the structure.
+	_pad: [u32; 24],
 }
 
-// TODO Check the type is correct
-/// Type representing a signal mask.
-pub type SigSet = u32;
+impl SigInfo {
+	/// Creates a new instance for the given signal, with the given code.
+	pub fn new(sig: &Signal, code: i32) -> Self {
+		Self {
+			si_signo: sig.get_id() as _,
+			si_code: code,
+			..Default::default()
+		}
+	}
+
+	/// Creates a new instance for a signal sent by the process with the given PID and real user
+	/// ID, with the given code.
+	pub fn from_sender(sig: &Signal, code: i32, pid: Pid, uid: Uid) -> Self {
+		Self {
+			si_pid: pid as _,
+			si_uid: uid as _,
+			..Self::new(sig, code)
+		}
+	}
+
+	/// Creates a new instance for a signal reporting the poll events `band` on the file
+	/// descriptor `fd`, with the given code.
+	pub fn from_poll(sig: &Signal, code: i32, band: u32, fd: c_int) -> Self {
+		// In the userspace structure, `si_band` and `si_fd` share their location with `si_pid`
+		// and `si_uid`
+		Self {
+			si_pid: band as _,
+			si_uid: fd as _,
+			..Self::new(sig, code)
+		}
+	}
+}
+
+/// Type representing a signal mask. Signal `n` is represented by the bit `n - 1`.
+pub type SigSet = u64;
 
 /// Structure storing an action to be executed when a signal is received.
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct SigAction {
-	/// The action associated with the signal.
+	/// The action associated with the signal. If [`SA_SIGINFO`] is set in `sa_flags`, the
+	/// handler is a [`SigInfoHandler`].
 	pub sa_handler: Option<SigHandler>,
-	/// Used instead of `sa_handler` if SA_SIGINFO is specified in `sa_flags`.
-	pub sa_sigaction: Option<extern "C" fn(i32, *mut SigInfo, *mut c_void)>,
-	/// A mask of signals that should be masked while executing the signal
-	/// handler.
-	pub sa_mask: SigSet,
 	/// A set of flags which modifies the behaviour of the signal.
-	pub sa_flags: i32,
+	pub sa_flags: u32,
 	/// Unused.
 	pub sa_restorer: Option<extern "C" fn()>,
+	/// A mask of signals that should be masked while executing the signal
+	/// handler.
+	pub sa_mask: SigSet,
 }
 
 /// Structure for notification from asynchronous routines.
@@ -193,30 +249,16 @@ impl SignalHandler {
 		match self {
 			Self::Ignore => SigAction {
 				sa_handler: unsafe { transmute::<_, _>(SIG_IGN) },
-				sa_sigaction: #[allow(invalid_value)]
-				unsafe {
-					core::mem::zeroed()
-				},
-				sa_mask: 0,
 				sa_flags: 0,
-				sa_restorer: #[allow(invalid_value)]
-				unsafe {
-					core::mem::zeroed()
-				},
+				sa_restorer: None,
+				sa_mask: 0,
 			},
 
 			Self::Default => SigAction {
-				sa_handler: unsafe { transmute::<_, _>(SIG_DFL) },
-				sa_sigaction: #[allow(invalid_value)]
-				unsafe {
-					core::mem::zeroed()
-				},
-				sa_mask: 0,
+				sa_handler: None,
 				sa_flags: 0,
-				sa_restorer: #[allow(invalid_value)]
-				unsafe {
-					core::mem::zeroed()
-				},
+				sa_restorer: None,
+				sa_mask: 0,
 			},
 
 			Self::Handler(action) => *action,
@@ -285,6 +327,8 @@ pub enum Signal {
 	SIGPOLL,
 	/// Bad system call.
 	SIGSYS,
+	/// Real-time signal, with its ID between [`SIGRTMIN`] and [`SIGRTMAX`].
+	SIGRT(u8),
 }
 
 impl TryFrom<u32> for Signal {
@@ -322,6 +366,7 @@ impl TryFrom<u32> for Signal {
 			28 => Ok(Self::SIGWINCH),
 			29 => Ok(Self::SIGPOLL),
 			31 => Ok(Self::SIGSYS),
+			id if (SIGRTMIN as u32..=SIGRTMAX as u32).contains(&id) => Ok(Self::SIGRT(id as _)),
 
 			_ => Err(errno!(EINVAL)),
 		}
@@ -361,6 +406,7 @@ impl Signal {
 			Self::SIGWINCH => 28,
 			Self::SIGPOLL => 29,
 			Self::SIGSYS => 31,
+			Self::SIGRT(id) => *id,
 		}
 	}
 
@@ -396,9 +442,21 @@ impl Signal {
 			Self::SIGWINCH => SignalAction::Ignore,
 			Self::SIGPOLL => SignalAction::Terminate,
 			Self::SIGSYS => SignalAction::Abort,
+			Self::SIGRT(_) => SignalAction::Terminate,
 		}
 	}
 
+	/// Returns the mask representing the signal in a [`SigSet`].
+	pub fn get_mask(&self) -> SigSet {
+		1 << (self.get_id() - 1)
+	}
+
+	/// Tells whether the signal is a real-time signal. Several instances of a real-time signal
+	/// can be pending at the same time.
+	pub fn is_realtime(&self) -> bool {
+		matches!(self, Self::SIGRT(_))
+	}
+
 	/// Tells whether the signal can be caught.
 	pub fn can_catch(&self) -> bool {
 		!matches!(
@@ -414,7 +472,7 @@ impl Signal {
 	/// If `no_handler` is `true`, the function executes the default action of the
 	/// signal regardless the user-specified action.
 	pub fn execute_action(&self, process: &mut Process, no_handler: bool) {
-		process.signal_clear(self.clone());
+		let info = process.signal_dequeue(self);
 
 		let process_state = process.get_state();
 		if matches!(process_state, State::Zombie) {
@@ -438,7 +496,18 @@ impl Signal {
 
 				let action = self.get_default_action();
 				match action {
-					SignalAction::Terminate | SignalAction::Abort => {
+					SignalAction::Terminate => {
+						// The whole thread group is terminated
+						process.exit_other_threads(self.get_id() as _, true);
+						process.exit(self.get_id() as _, true);
+					}
+
+					SignalAction::Abort => {
+						process.exit_other_threads(self.get_id() as _, true);
+						// Failing to dump the core does not prevent the termination
+						if let Ok(true) = coredump::dump(process, self, &info) {
+							process.set_core_dumped();
+						}
 						process.exit(self.get_id() as _, true);
 					}
 
@@ -464,15 +533,16 @@ impl Signal {
 				}
 			}
 
-			// TODO Handle sa_sigaction, sa_flags and sa_mask
 			SignalHandler::Handler(action) if !process.is_handling_signal() => {
 				// TODO Handle the case where an alternate stack is specified (only if the
 				// action has the flag)
 				// The signal handler stack
 				let stack = process.get_signal_stack();
 
-				let signal_data_size = size_of::<[u32; 3]>();
-				let signal_esp = (stack as usize) - signal_data_size;
+				// The signal's informations are placed above the arguments of the trampoline
+				let info_ptr = (stack as usize - size_of::<SigInfo>()) & !(size_of::<u32>() - 1);
+				let signal_data_size = size_of::<[u32; 5]>();
+				let signal_esp = info_ptr - signal_data_size;
 
 				// FIXME Don't write data out of the stack
 				oom::wrap(|| {
@@ -480,22 +550,28 @@ impl Signal {
 					let mut mem_space = mem_space.lock();
 
 					mem_space.bind();
-					mem_space.alloc(signal_esp as *mut u32, 3)
+					mem_space.alloc(signal_esp as *mut u8, stack as usize - signal_esp)
+				});
+				smap::wrap(|| {
+					unsafe {
+						*(info_ptr as *mut SigInfo) = info;
+					}
+					let signal_data =
+						unsafe { slice::from_raw_parts_mut(signal_esp as *mut u32, 5) };
+
+					// The context of the process (TODO)
+					signal_data[4] = 0;
+					// The signal's informations
+					signal_data[3] = info_ptr as _;
+					// The signal number
+					signal_data[2] = self.get_id() as _;
+					// The pointer to the signal handler
+					signal_data[1] = action.sa_handler.map(|f| f as usize).unwrap_or(0) as _;
+					// Padding (return pointer)
+					signal_data[0] = 0;
 				});
-				let signal_data = unsafe { slice::from_raw_parts_mut(signal_esp as *mut u32, 3) };
-
-				// The signal number
-				signal_data[2] = self.get_id() as _;
-				// The pointer to the signal handler
-				signal_data[1] = action.sa_handler.map(|f| f as usize).unwrap_or(0) as _;
-				// Padding (return pointer)
-				signal_data[0] = 0;
 
-				let signal_trampoline = unsafe {
-					transmute::<extern "C" fn(*const c_void, i32) -> !, *const c_void>(
-						signal_trampoline,
-					)
-				};
+				let signal_trampoline = signal_trampoline as *const c_void;
 
 				let mut regs = process.regs.clone();
 				// Setting the stack to point to the signal's data
@@ -508,6 +584,16 @@ impl Signal {
 				process.signal_save(self.clone());
 				// Setting the process's registers to call the signal handler
 				process.regs = regs;
+
+				// Block signals while the handler is executed
+				let mut mask = action.sa_mask;
+				if action.sa_flags & SA_NODEFER == 0 {
+					mask |= self.get_mask();
+				}
+				process.sigmask |= mask;
+				if action.sa_flags & SA_RESETHAND != 0 {
+					process.set_signal_handler(self, SignalHandler::Default);
+				}
 			}
 
 			_ => {}
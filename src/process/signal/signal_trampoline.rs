@@ -9,6 +9,8 @@
 //!
 //! When the signal handler returns, the process returns directly to execution.
 
+use super::SigInfo;
+use super::SigInfoHandler;
 use core::arch::asm;
 use core::ffi::c_void;
 use core::mem::transmute;
@@ -24,12 +26,25 @@ use core::mem::transmute;
 /// Arguments:
 /// - `handler` is a pointer to the handler function for the signal.
 /// - `sig` is the signal number.
+/// - `info` is a pointer to the signal's informations.
+/// - `ctx` is a pointer to the context of the process before the signal.
+///
+/// The last two arguments are ignored by handlers installed without `SA_SIGINFO`.
+///
+/// The function is placed in the `.user_text` section, which is the only part of the kernel
+/// that can be executed from userspace.
 #[no_mangle]
-pub extern "C" fn signal_trampoline(handler: *const c_void, sig: i32) -> ! {
+#[link_section = ".user_text"]
+pub extern "C" fn signal_trampoline(
+	handler: *const c_void,
+	sig: i32,
+	info: *mut SigInfo,
+	ctx: *mut c_void,
+) -> ! {
 	// Calling the signal handler
 	unsafe {
-		let handler = transmute::<*const c_void, unsafe extern "C" fn(i32)>(handler);
-		handler(sig);
+		let handler = transmute::<*const c_void, SigInfoHandler>(handler);
+		handler(sig, info, ctx);
 	}
 
 	// Calling `sigreturn` to end signal handling.
@@ -7,6 +7,7 @@
 //! The structure has to be registered into the GDT into the TSS segment, and must be loaded using
 //! instruction `ltr`.
 
+use crate::cpu;
 use crate::gdt;
 use core::arch::asm;
 use core::mem::size_of;
@@ -77,10 +78,10 @@ impl TSS {
 		}
 	}
 
-	/// Initializes the TSS.
+	/// Initializes the TSS of the current CPU.
 	pub fn init() {
 		let limit = size_of::<Self>() as u64;
-		let base = unsafe { &TSS as *const _ as u64 };
+		let base = unsafe { get_current() as *const _ as u64 };
 		let flags = 0b0100000010001001_u64;
 		let tss_value = (limit & 0xffff)
 			| ((base & 0xffffff) << 16)
@@ -113,6 +114,16 @@ impl TSS {
 #[repr(align(4096))]
 pub struct TSSWrap(pub TSS);
 
-/// The Task State Segment.
-#[no_mangle]
-pub static mut TSS: TSSWrap = TSSWrap(TSS::new());
+/// The initial value of an entry in `TSS`.
+const TSS_INIT: TSSWrap = TSSWrap(TSS::new());
+/// The Task State Segment of each CPU.
+static mut TSS: [TSSWrap; cpu::MAX_CPUS] = [TSS_INIT; cpu::MAX_CPUS];
+
+/// Returns the Task State Segment of the current CPU.
+///
+/// # Safety
+///
+/// The caller must ensure no other reference to the TSS of the current CPU is alive.
+pub unsafe fn get_current() -> &'static mut TSS {
+	&mut TSS[cpu::get_current_id()].0
+}
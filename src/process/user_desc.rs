@@ -10,11 +10,42 @@ pub const USER_DESC_SIZE: usize = 16;
 
 /// The `user_desc` structure.
 #[repr(transparent)]
+#[derive(Clone, Copy)]
 pub struct UserDesc {
 	val: [i8; USER_DESC_SIZE],
 }
 
 impl UserDesc {
+	/// Creates a structure describing the GDT entry `entry`, with the entry number
+	/// `entry_number`.
+	pub fn from_descriptor(entry_number: i32, entry: &gdt::Entry) -> Self {
+		let mut desc = Self {
+			val: [0; USER_DESC_SIZE],
+		};
+		desc.set_entry_number(entry_number);
+		// A cleared entry is described by the empty structure
+		if entry.0 == 0 {
+			desc.val[12] = 0b101000;
+			return desc;
+		}
+
+		let access_byte = entry.get_access_byte();
+		let flags = entry.get_flags();
+		let mut bits = (flags >> 2) & 0b1;
+		bits |= ((access_byte >> 2) & 0b11) << 1;
+		bits |= (((access_byte >> 1) & 0b1) ^ 0b1) << 3;
+		bits |= ((flags >> 3) & 0b1) << 4;
+		bits |= (((access_byte >> 7) & 0b1) ^ 0b1) << 5;
+		bits |= (flags & 0b1) << 6;
+		// Safe because the structure is large enough
+		unsafe {
+			*(&mut desc.val[4] as *mut _ as *mut u32) = entry.get_base();
+			*(&mut desc.val[8] as *mut _ as *mut u32) = entry.get_limit();
+		}
+		desc.val[12] = bits as _;
+		desc
+	}
+
 	/// Returns the entry number.
 	#[inline(always)]
 	pub fn get_entry_number(&self) -> i32 {
@@ -53,7 +84,13 @@ impl UserDesc {
 		(self.val[12] & 0b1) != 0
 	}
 
-	/// Tells whether the segment is writable.
+	/// Returns the type of the segment's content.
+	#[inline(always)]
+	pub fn get_contents(&self) -> u8 {
+		((self.val[12] >> 1) & 0b11) as _
+	}
+
+	/// Tells whether the segment is not writable.
 	#[inline(always)]
 	pub fn is_read_exec_only(&self) -> bool {
 		(self.val[12] & 0b1000) != 0
@@ -77,17 +114,37 @@ impl UserDesc {
 		(self.val[12] & 0b1000000) != 0
 	}
 
+	/// Tells whether the structure describes an empty entry, used to clear an entry.
+	pub fn is_empty(&self) -> bool {
+		self.get_base_addr().is_null()
+			&& self.get_limit() == 0
+			&& self.get_contents() == 0
+			&& self.is_read_exec_only()
+			&& !self.is_32bits()
+			&& !self.is_limit_in_pages()
+			&& !self.is_present()
+			&& !self.is_usable()
+	}
+
 	/// Converts the current descriptor to a GDT entry.
 	pub fn to_descriptor(&self) -> gdt::Entry {
-		let mut access_byte = 0b01110010;
-		if self.is_present() && self.is_usable() {
+		if self.is_empty() {
+			return gdt::Entry::default();
+		}
+
+		// Userspace segment, with privilege level 3
+		let mut access_byte = 0b01110000 | (self.get_contents() << 2);
+		if self.is_present() {
 			access_byte |= 1 << 7;
 		}
-		if self.is_read_exec_only() {
-			access_byte |= 1 << 3;
+		if !self.is_read_exec_only() {
+			access_byte |= 1 << 1;
 		}
 
 		let mut flags = 0b0000;
+		if self.is_usable() {
+			flags |= 1;
+		}
 		if self.is_32bits() {
 			flags |= 1 << 2;
 		}
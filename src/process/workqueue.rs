@@ -0,0 +1,259 @@
+//! Workqueues allow to defer work to be executed later in process context, by a kernel thread.
+//!
+//! This is typically used by interrupt handlers, which have to return quickly and cannot sleep:
+//! the heavy processing is queued and done by the worker thread of the workqueue.
+//!
+//! A work item is a function which can be queued several times. While it is pending, queueing it
+//! again has no effect. Once the worker has started running it, it can be queued again.
+//!
+//! Work can also be delayed: the worker thread sleeps on a high resolution timer until the
+//! deadline of the next delayed work item is reached.
+//!
+//! The system workqueue is shared by the whole kernel. Drivers which may block for a long time
+//! should create their own workqueue to avoid delaying the work of others.
+
+use super::kthread;
+use super::kthread::KThread;
+use super::Process;
+use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::hrtimer;
+use crate::time::unit::Timestamp;
+use crate::time::unit::TimestampScale;
+use crate::util::boxed::Box;
+use crate::util::container::map::Map;
+use crate::util::container::vec::Vec;
+use crate::util::lock::IntMutex;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use core::mem;
+use core::sync::atomic;
+use core::sync::atomic::AtomicBool;
+
+/// A work item.
+pub struct Work {
+	/// The function to run.
+	func: Mutex<Box<dyn FnMut()>>,
+	/// Tells whether the work is queued and has not started running yet.
+	pending: AtomicBool,
+}
+
+impl Work {
+	/// Creates a work item running the function `f`.
+	pub fn new<F: FnMut() + 'static>(f: F) -> AllocResult<Arc<Self>> {
+		Arc::new(Self {
+			func: Mutex::new(Box::new(f)?),
+			pending: AtomicBool::new(false),
+		})
+	}
+
+	/// Tells whether the work is queued and has not started running yet.
+	pub fn is_pending(&self) -> bool {
+		self.pending.load(atomic::Ordering::Acquire)
+	}
+
+	/// Runs the work's function.
+	fn run(&self) {
+		self.pending.store(false, atomic::Ordering::Release);
+		let mut func = self.func.lock();
+		(*func)();
+	}
+}
+
+/// The queue of work items of a workqueue.
+#[derive(Default)]
+struct Queue {
+	/// Work items to run as soon as possible, in order.
+	ready: Vec<Arc<Work>>,
+	/// Delayed work items.
+	///
+	/// The key has the following elements:
+	/// - the deadline on the monotonic clock, in nanoseconds
+	/// - the address of the work item, to make the key unique
+	delayed: Map<(Timestamp, usize), Arc<Work>>,
+}
+
+impl Queue {
+	/// Moves the delayed work items whose deadline has been reached to the ready list.
+	///
+	/// `now` is the current timestamp of the monotonic clock, in nanoseconds.
+	///
+	/// The function returns the deadline of the next delayed work item, if any.
+	fn promote(&mut self, now: Timestamp) -> AllocResult<Option<Timestamp>> {
+		while let Some(((deadline, _), _)) = self.delayed.first_key_value() {
+			if *deadline > now {
+				return Ok(Some(*deadline));
+			}
+			let (key, work) = self.delayed.pop_first().unwrap();
+			if let Err(e) = self.ready.push(work.clone()) {
+				self.delayed.insert(key, work)?;
+				return Err(e);
+			}
+		}
+		Ok(None)
+	}
+}
+
+/// A queue of work items, executed by a kernel worker thread.
+///
+/// When dropped, the workqueue runs the work items which are ready, then stops its worker.
+/// Delayed work items which have not been run yet are discarded.
+pub struct WorkQueue {
+	/// The queue of work items, shared with the worker.
+	queue: Arc<IntMutex<Queue>>,
+	/// The worker thread.
+	worker: Option<KThread>,
+}
+
+impl WorkQueue {
+	/// Creates a workqueue whose worker thread has the name `name`.
+	pub fn new(name: &[u8]) -> EResult<Self> {
+		let queue = Arc::new(IntMutex::new(Queue::default()))?;
+		let worker_queue = queue.clone();
+		let worker = kthread::spawn(name, move || worker(&worker_queue))?;
+		Ok(Self {
+			queue,
+			worker: Some(worker),
+		})
+	}
+
+	/// Wakes the worker up.
+	fn wake(&self) {
+		if let Some(worker) = &self.worker {
+			worker.wake();
+		}
+	}
+
+	/// Queues `work` to be run as soon as possible.
+	///
+	/// The function can be called from interrupt context.
+	///
+	/// If the work is already pending, the function does nothing and returns `false`.
+	pub fn queue_work(&self, work: &Arc<Work>) -> AllocResult<bool> {
+		{
+			let mut queue = self.queue.lock();
+			if work.pending.swap(true, atomic::Ordering::AcqRel) {
+				return Ok(false);
+			}
+			if let Err(e) = queue.ready.push(work.clone()) {
+				work.pending.store(false, atomic::Ordering::Release);
+				return Err(e);
+			}
+		}
+		self.wake();
+		Ok(true)
+	}
+
+	/// Queues `work` to be run after `delay` nanoseconds.
+	///
+	/// The function can be called from interrupt context.
+	///
+	/// If the work is already pending, the function does nothing and returns `false`.
+	pub fn queue_delayed_work(&self, work: &Arc<Work>, delay: Timestamp) -> EResult<bool> {
+		let now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond)?;
+		{
+			let mut queue = self.queue.lock();
+			if work.pending.swap(true, atomic::Ordering::AcqRel) {
+				return Ok(false);
+			}
+			let key = (now.saturating_add(delay), work.as_ptr() as usize);
+			if let Err(e) = queue.delayed.insert(key, work.clone()) {
+				work.pending.store(false, atomic::Ordering::Release);
+				return Err(e.into());
+			}
+		}
+		// The worker has to update the deadline it is sleeping on
+		self.wake();
+		Ok(true)
+	}
+
+	/// Removes `work` from the queue if it is pending.
+	///
+	/// If the work is already running, the function does not wait for it to finish.
+	///
+	/// The function returns `true` if the work was pending.
+	pub fn cancel_work(&self, work: &Arc<Work>) -> bool {
+		let mut queue = self.queue.lock();
+		if !work.pending.swap(false, atomic::Ordering::AcqRel) {
+			return false;
+		}
+		let ptr = work.as_ptr();
+		queue.ready.retain(|w| w.as_ptr() != ptr);
+		queue.delayed.retain(|(_, addr), _| *addr != ptr as usize);
+		true
+	}
+}
+
+impl Drop for WorkQueue {
+	fn drop(&mut self) {
+		if let Some(worker) = self.worker.take() {
+			worker.stop();
+		}
+	}
+}
+
+/// The function of the worker thread of a workqueue.
+fn worker(queue: &IntMutex<Queue>) {
+	let pid = Process::current_assert().lock().pid;
+	loop {
+		let now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond).unwrap_or(0);
+		let (ready, next) = {
+			let mut queue = queue.lock();
+			// On allocation failure, delayed work stays in the queue and is retried later
+			let next = queue.promote(now).unwrap_or(Some(now));
+			(mem::take(&mut queue.ready), next)
+		};
+		if !ready.is_empty() {
+			for work in ready.iter() {
+				work.run();
+			}
+			continue;
+		}
+		if kthread::should_stop() {
+			break;
+		}
+
+		// Sleep until the next delayed work item, or until new work is queued
+		let timer = next.filter(|deadline| hrtimer::insert(*deadline, pid).is_ok());
+		kthread::sleep();
+		if let Some(deadline) = timer {
+			hrtimer::remove(deadline, pid);
+		}
+	}
+}
+
+/// The system workqueue.
+static SYSTEM: IntMutex<Option<WorkQueue>> = IntMutex::new(None);
+
+/// Creates the system workqueue.
+///
+/// This function must be called only once, after the creation of the init process.
+pub fn init() -> EResult<()> {
+	let wq = WorkQueue::new(b"kworker")?;
+	*SYSTEM.lock() = Some(wq);
+	Ok(())
+}
+
+/// Queues `work` on the system workqueue.
+///
+/// See [`WorkQueue::queue_work`].
+pub fn schedule_work(work: &Arc<Work>) -> AllocResult<bool> {
+	SYSTEM
+		.lock()
+		.as_ref()
+		.expect("system workqueue not initialized")
+		.queue_work(work)
+}
+
+/// Queues `work` on the system workqueue, to be run after `delay` nanoseconds.
+///
+/// See [`WorkQueue::queue_delayed_work`].
+pub fn schedule_delayed_work(work: &Arc<Work>, delay: Timestamp) -> EResult<bool> {
+	SYSTEM
+		.lock()
+		.as_ref()
+		.expect("system workqueue not initialized")
+		.queue_delayed_work(work, delay)
+}
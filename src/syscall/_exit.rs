@@ -13,18 +13,14 @@ use macros::syscall;
 /// - `status` is the exit status.
 /// - `thread_group`: if `true`, the function exits the whole process group.
 pub fn do_exit(status: u32, thread_group: bool) -> ! {
-	let (_pid, _tid) = {
+	{
 		let proc_mutex = Process::current_assert();
 		let mut proc = proc_mutex.lock();
 
+		if thread_group {
+			proc.exit_other_threads(status, false);
+		}
 		proc.exit(status, false);
-
-		(proc.pid, proc.tid)
-	};
-
-	if thread_group {
-		// TODO Iterate on every process of thread group `tid`, except the
-		// process with pid `pid`
 	}
 
 	scheduler::end_tick();
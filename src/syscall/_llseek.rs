@@ -1,6 +1,8 @@
 //! The `_llseek` system call repositions the offset of a file descriptor.
 
+use crate::errno::EResult;
 use crate::errno::Errno;
+use crate::file::open_file::OpenFile;
 use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::Process;
 use crate::util::io::IO;
@@ -14,6 +16,38 @@ const SEEK_SET: u32 = 0;
 const SEEK_CUR: u32 = 1;
 /// Sets the offset relative to the end of the file.
 const SEEK_END: u32 = 2;
+/// Sets the offset to the next region of data at or after the given offset.
+const SEEK_DATA: u32 = 3;
+/// Sets the offset to the next hole at or after the given offset.
+const SEEK_HOLE: u32 = 4;
+
+/// Computes the new offset of the open file `open_file`.
+///
+/// Arguments:
+/// - `off` is the offset given by userspace
+/// - `whence` tells how `off` is interpreted
+pub fn compute_offset(open_file: &OpenFile, off: i64, whence: u32) -> EResult<u64> {
+	let base = match whence {
+		SEEK_SET => 0,
+		SEEK_CUR => open_file.get_offset(),
+		SEEK_END => open_file.get_size(),
+
+		SEEK_DATA | SEEK_HOLE => {
+			let off: u64 = off.try_into().map_err(|_| errno!(ENXIO))?;
+			let mut file = open_file.get_file().lock();
+			return file.next_extent(off, whence == SEEK_HOLE);
+		}
+
+		_ => return Err(errno!(EINVAL)),
+	};
+	let off = base
+		.checked_add_signed(off)
+		.ok_or_else(|| errno!(EOVERFLOW))?;
+	if off > i64::MAX as u64 {
+		return Err(errno!(EINVAL));
+	}
+	Ok(off)
+}
 
 #[syscall]
 pub fn _llseek(
@@ -46,24 +80,12 @@ pub fn _llseek(
 
 	// Compute the offset
 	let off = ((offset_high as u64) << 32) | (offset_low as u64);
-	let off = match whence {
-		SEEK_SET => off,
-		SEEK_CUR => open_file
-			.get_offset()
-			.checked_add(off)
-			.ok_or_else(|| errno!(EOVERFLOW))?,
-		SEEK_END => open_file
-			.get_size()
-			.checked_add(off)
-			.ok_or_else(|| errno!(EOVERFLOW))?,
-
-		_ => return Err(errno!(EINVAL)),
-	};
+	let off = compute_offset(&open_file, off as i64, whence)?;
 
 	{
 		let mut mem_space_guard = mem_space.lock();
 		// Write the result to the userspace
-		if let Some(result) = result.get_mut(&mut mem_space_guard)? {
+		if let Some(mut result) = result.get_mut(&mut mem_space_guard)? {
 			*result = off;
 		}
 	}
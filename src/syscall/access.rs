@@ -1,8 +1,7 @@
 //! The `access` system call allows to check access to a given file.
 
+use super::util;
 use crate::errno::Errno;
-use crate::file::path::Path;
-use crate::file::vfs;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
 use core::ffi::c_int;
@@ -54,51 +53,37 @@ pub fn do_access(
 	flags: Option<i32>,
 ) -> Result<i32, Errno> {
 	let flags = flags.unwrap_or(0);
-	let follow_symlinks = flags & AT_SYMLINK_NOFOLLOW == 0;
 	// Use effective IDs instead of real IDs
 	let eaccess = flags & AT_EACCESS != 0;
 
-	let (path, cwd, ap) = {
+	let (file, access_profile) = {
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
+		let access_profile = proc.access_profile;
 
-		let mem_space_mutex = proc.get_mem_space().unwrap();
+		let mem_space_mutex = proc.get_mem_space().unwrap().clone();
 		let mem_space_guard = mem_space_mutex.lock();
 
 		let pathname = pathname
 			.get(&mem_space_guard)?
 			.ok_or_else(|| errno!(EINVAL))?;
-		let path = Path::from_str(pathname, true)?;
+		// The path is resolved from the directory `dirfd`, or the current working directory
+		let dirfd = dirfd.unwrap_or(AT_FDCWD);
+		let file = util::get_file_at(proc, dirfd, &pathname, true, flags)?;
 
-		let cwd = proc.cwd.clone();
-
-		(path, cwd, proc.access_profile)
+		(file, access_profile)
 	};
 
-	// Get file
-	let mut path = path;
-	if path.is_absolute() {
-		// TODO
-	} else if let Some(dirfd) = dirfd {
-		if dirfd == AT_FDCWD {
-			path = cwd.concat(&path)?;
-		} else {
-			// TODO Get file from fd and get its path to concat
-			todo!();
-		}
-	}
-	let file = vfs::get_file_from_path(&path, &ap, follow_symlinks)?;
-
 	// Do access checks
 	{
 		let file = file.lock();
-		if (mode & R_OK != 0) && !ap.check_read_access(&*file, eaccess) {
+		if (mode & R_OK != 0) && !access_profile.check_read_access(&*file, eaccess) {
 			return Err(errno!(EACCES));
 		}
-		if (mode & W_OK != 0) && !ap.check_write_access(&*file, eaccess) {
+		if (mode & W_OK != 0) && !access_profile.check_write_access(&*file, eaccess) {
 			return Err(errno!(EACCES));
 		}
-		if (mode & X_OK != 0) && !ap.check_execute_access(&*file, eaccess) {
+		if (mode & X_OK != 0) && !access_profile.check_execute_access(&*file, eaccess) {
 			return Err(errno!(EACCES));
 		}
 	}
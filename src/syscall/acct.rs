@@ -0,0 +1,51 @@
+//! The `acct` system call enables or disables process accounting.
+
+use crate::errno::Errno;
+use crate::file::path::Path;
+use crate::file::perm::CAP_SYS_PACCT;
+use crate::file::FileType;
+use crate::process::acct;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::Process;
+use crate::vfs;
+use crate::vfs::ResolutionSettings;
+use macros::syscall;
+
+#[syscall]
+pub fn acct(filename: SyscallString) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+	// Check permission
+	if !proc.access_profile.has_cap(CAP_SYS_PACCT) {
+		return Err(errno!(EPERM));
+	}
+
+	let path = {
+		let mem_space = proc.get_mem_space().unwrap();
+		let mem_space_guard = mem_space.lock();
+		let Some(path) = filename.get(&mem_space_guard)? else {
+			// Disable accounting
+			acct::set_file(None);
+			return Ok(0);
+		};
+		Path::from_str(&path, true)?
+	};
+	let path = super::util::get_absolute_path(&proc, path)?;
+
+	let rs = ResolutionSettings::for_process(&proc, true)?;
+	drop(proc);
+	let file_mutex = vfs::resolve_path(&path, &rs)?;
+	{
+		let file = file_mutex.lock();
+		if file.get_type() != FileType::Regular {
+			return Err(errno!(EACCES));
+		}
+		if !rs.access_profile.can_write_file(&file) {
+			return Err(errno!(EACCES));
+		}
+		file.check_mount_writable()?;
+	}
+	acct::set_file(Some(file_mutex));
+
+	Ok(0)
+}
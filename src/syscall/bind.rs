@@ -41,6 +41,6 @@ pub fn bind(sockfd: c_int, addr: SyscallSlice<u8>, addrlen: isize) -> Result<i32
 		.get(&mut mem_space_guard, addrlen as _)?
 		.ok_or(errno!(EFAULT))?;
 
-	sock.bind(addr_slice)?;
+	sock.bind(&addr_slice)?;
 	Ok(0)
 }
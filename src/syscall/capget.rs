@@ -0,0 +1,105 @@
+//! The `capget` system call returns the capabilities of a process.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::perm::CapSet;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::MemSpace;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Version 1 of the capabilities interface, with 32 bits sets.
+const LINUX_CAPABILITY_VERSION_1: u32 = 0x19980330;
+/// Version 2 of the capabilities interface, with 64 bits sets (deprecated).
+const LINUX_CAPABILITY_VERSION_2: u32 = 0x20071026;
+/// Version 3 of the capabilities interface, with 64 bits sets.
+const LINUX_CAPABILITY_VERSION_3: u32 = 0x20080522;
+
+/// The header of capabilities data.
+#[repr(C)]
+#[derive(Debug)]
+pub struct CapUserHeader {
+	/// The version of the interface.
+	version: u32,
+	/// The PID of the target process.
+	pid: c_int,
+}
+
+/// A 32 bits chunk of each capabilities set.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CapUserData {
+	/// The effective capabilities.
+	pub effective: u32,
+	/// The permitted capabilities.
+	pub permitted: u32,
+	/// The inheritable capabilities.
+	pub inheritable: u32,
+}
+
+/// Checks the header of capabilities data and returns the target PID along with the number of
+/// data structures to use.
+///
+/// If the version is not supported, the function writes the preferred version in the header
+/// and returns `None`.
+pub fn read_header(
+	mem_space: &mut MemSpace,
+	hdrp: &SyscallPtr<CapUserHeader>,
+) -> EResult<Option<(c_int, usize)>> {
+	let mut hdr = hdrp.get_mut(mem_space)?.ok_or_else(|| errno!(EFAULT))?;
+	match hdr.version {
+		LINUX_CAPABILITY_VERSION_1 => Ok(Some((hdr.pid, 1))),
+		LINUX_CAPABILITY_VERSION_2 | LINUX_CAPABILITY_VERSION_3 => Ok(Some((hdr.pid, 2))),
+		_ => {
+			hdr.version = LINUX_CAPABILITY_VERSION_3;
+			Ok(None)
+		}
+	}
+}
+
+#[syscall]
+pub fn capget(
+	hdrp: SyscallPtr<CapUserHeader>,
+	datap: SyscallSlice<CapUserData>,
+) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	let mem_space = proc.get_mem_space().unwrap().clone();
+	let mut mem_space_guard = mem_space.lock();
+	let Some((pid, count)) = read_header(&mut mem_space_guard, &hdrp)? else {
+		// Allow probing the preferred version
+		return if datap.is_null() {
+			Ok(0)
+		} else {
+			Err(errno!(EINVAL))
+		};
+	};
+	if pid < 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	let ap = if pid == 0 || pid as u32 == proc.to_local_pid(proc.pid) as u32 {
+		proc.access_profile
+	} else {
+		let target_mutex = Process::get_by_vpid(pid as _).ok_or_else(|| errno!(ESRCH))?;
+		let target = target_mutex.lock();
+		target.access_profile
+	};
+	let chunk = |set: CapSet, i: usize| (set >> (i * 32)) as u32;
+
+	// A null pointer only probes the version
+	if let Some(mut data) = datap.get_mut(&mut mem_space_guard, count)? {
+		for (i, d) in data.iter_mut().enumerate() {
+			*d = CapUserData {
+				effective: chunk(ap.get_cap_effective(), i),
+				permitted: chunk(ap.get_cap_permitted(), i),
+				inheritable: chunk(ap.get_cap_inheritable(), i),
+			};
+		}
+	}
+
+	Ok(0)
+}
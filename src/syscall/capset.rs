@@ -0,0 +1,49 @@
+//! The `capset` system call sets the capabilities of the current process.
+
+use super::capget;
+use super::capget::CapUserData;
+use super::capget::CapUserHeader;
+use crate::errno::Errno;
+use crate::file::perm::CapSet;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::Process;
+use macros::syscall;
+
+#[syscall]
+pub fn capset(
+	hdrp: SyscallPtr<CapUserHeader>,
+	datap: SyscallSlice<CapUserData>,
+) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let mut proc = proc_mutex.lock();
+
+	let (effective, permitted, inheritable) = {
+		let mem_space = proc.get_mem_space().unwrap();
+		let mut mem_space_guard = mem_space.lock();
+		let (pid, count) =
+			capget::read_header(&mut mem_space_guard, &hdrp)?.ok_or_else(|| errno!(EINVAL))?;
+		// Only the capabilities of the current process can be set
+		if pid != 0 && pid as u32 != proc.pid as u32 {
+			return Err(errno!(EPERM));
+		}
+
+		let data = datap
+			.get(&mem_space_guard, count)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		data.iter()
+			.enumerate()
+			.fold((0, 0, 0), |(e, p, i), (n, d)| {
+				let shift = n * 32;
+				(
+					e | (d.effective as CapSet) << shift,
+					p | (d.permitted as CapSet) << shift,
+					i | (d.inheritable as CapSet) << shift,
+				)
+			})
+	};
+	proc.access_profile
+		.set_caps(effective, permitted, inheritable)?;
+
+	Ok(0)
+}
@@ -5,6 +5,7 @@ use crate::errno;
 use crate::errno::Errno;
 use crate::file::path::Path;
 use crate::file::vfs;
+use crate::file::vfs::ResolutionSettings;
 use crate::file::FileType;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
@@ -13,7 +14,7 @@ use macros::syscall;
 
 #[syscall]
 pub fn chdir(path: SyscallString) -> Result<i32, Errno> {
-	let (new_cwd, ap) = {
+	let (new_cwd, rs) = {
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
 
@@ -21,29 +22,32 @@ pub fn chdir(path: SyscallString) -> Result<i32, Errno> {
 		let mem_space_guard = mem_space.lock();
 
 		let path_str = path.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
-		let new_cwd = super::util::get_absolute_path(&proc, Path::from_str(path_str, true)?)?;
+		let new_cwd = super::util::get_absolute_path(&proc, Path::from_str(&path_str, true)?)?;
 
-		(new_cwd, proc.access_profile)
+		(new_cwd, ResolutionSettings::for_process(&proc, true)?)
 	};
 
-	{
-		let dir_mutex = vfs::get_file_from_path(&new_cwd, &ap, true)?;
+	let new_cwd = {
+		let dir_mutex = vfs::resolve_path(&new_cwd, &rs)?;
 		let dir = dir_mutex.lock();
 
 		// Check for errors
 		if dir.get_type() != FileType::Directory {
 			return Err(errno!(ENOTDIR));
 		}
-		if !ap.can_list_directory(&*dir) {
+		if !rs.access_profile.can_list_directory(&*dir) {
 			return Err(errno!(EACCES));
 		}
-	}
+
+		// The resolved path does not contain `..` nor symbolic links
+		dir.get_path()?
+	};
 
 	// Set new cwd
 	{
 		let proc_mutex = Process::current_assert();
-		let mut proc = proc_mutex.lock();
-		proc.cwd = Arc::new(new_cwd)?;
+		let proc = proc_mutex.lock();
+		proc.get_fs().lock().cwd = Arc::new(new_cwd)?;
 	}
 
 	Ok(0)
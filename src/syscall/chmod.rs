@@ -3,6 +3,7 @@
 use crate::errno::Errno;
 use crate::file::path::Path;
 use crate::file::vfs;
+use crate::file::vfs::ResolutionSettings;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
 use core::ffi::c_int;
@@ -10,7 +11,7 @@ use macros::syscall;
 
 #[syscall]
 pub fn chmod(pathname: SyscallString, mode: c_int) -> Result<i32, Errno> {
-	let (path, ap) = {
+	let (path, rs) = {
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
 
@@ -20,19 +21,20 @@ pub fn chmod(pathname: SyscallString, mode: c_int) -> Result<i32, Errno> {
 		let path = pathname
 			.get(&mem_space_guard)?
 			.ok_or_else(|| errno!(EFAULT))?;
-		let path = Path::from_str(path, true)?;
+		let path = Path::from_str(&path, true)?;
 		let path = super::util::get_absolute_path(&proc, path)?;
 
-		(path, proc.access_profile)
+		(path, ResolutionSettings::for_process(&proc, true)?)
 	};
 
-	let file_mutex = vfs::get_file_from_path(&path, &ap, true)?;
+	let file_mutex = vfs::resolve_path(&path, &rs)?;
 	let mut file = file_mutex.lock();
 
 	// Check permissions
-	if !ap.can_set_file_permissions(&*file) {
+	if !rs.access_profile.can_set_file_permissions(&*file) {
 		return Err(errno!(EPERM));
 	}
+	file.check_mount_writable()?;
 
 	file.set_permissions(mode as _);
 	// TODO lazy sync
@@ -3,7 +3,9 @@
 use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file::path::Path;
+use crate::file::perm::CAP_CHOWN;
 use crate::file::vfs;
+use crate::file::vfs::ResolutionSettings;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
 use core::ffi::c_int;
@@ -20,7 +22,7 @@ pub fn do_chown(
 		return Err(errno!(EINVAL));
 	}
 
-	let (path, ap) = {
+	let (path, rs) = {
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
 
@@ -28,15 +30,19 @@ pub fn do_chown(
 		let mem_space = mem_space.lock();
 
 		let path = pathname.get(&*mem_space)?.ok_or_else(|| errno!(EFAULT))?;
-		(Path::from_str(path, true)?, proc.access_profile)
+		let path = Path::from_str(&path, true)?;
+		let path = super::util::get_absolute_path(&proc, path)?;
+
+		(path, ResolutionSettings::for_process(&proc, follow_links)?)
 	};
 
-	let file_mutex = vfs::get_file_from_path(&path, &ap, follow_links)?;
+	let file_mutex = vfs::resolve_path(&path, &rs)?;
 	let mut file = file_mutex.lock();
 	// TODO allow changing group to any group whose owner is member
-	if !ap.is_privileged() {
+	if !rs.access_profile.has_cap(CAP_CHOWN) {
 		return Err(errno!(EPERM));
 	}
+	file.check_mount_writable()?;
 	if owner != -1 {
 		file.set_uid(owner as _);
 	}
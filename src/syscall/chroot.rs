@@ -3,18 +3,21 @@
 
 use crate::errno::Errno;
 use crate::file::path::Path;
+use crate::file::perm::CAP_SYS_CHROOT;
+use crate::file::FileType;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
 use crate::util::ptr::arc::Arc;
 use crate::vfs;
+use crate::vfs::ResolutionSettings;
 use macros::syscall;
 
 #[syscall]
 pub fn chroot(path: SyscallString) -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
-	let mut proc = proc_mutex.lock();
+	let proc = proc_mutex.lock();
 	// Check permission
-	if !proc.access_profile.is_privileged() {
+	if !proc.access_profile.has_cap(CAP_SYS_CHROOT) {
 		return Err(errno!(EPERM));
 	}
 
@@ -22,12 +25,17 @@ pub fn chroot(path: SyscallString) -> Result<i32, Errno> {
 		let mem_space = proc.get_mem_space().unwrap();
 		let mem_space_guard = mem_space.lock();
 		let path = path.get(&mem_space_guard)?.ok_or(errno!(EFAULT))?;
-		Path::from_str(path, true)?
+		Path::from_str(&path, true)?
 	};
+	let path = super::util::get_absolute_path(&proc, path)?;
 
-	// Check access to file
-	vfs::get_file_from_path(&path, &proc.access_profile, true)?;
-	proc.chroot = Arc::new(path)?;
+	let rs = ResolutionSettings::for_process(&proc, true)?;
+	let dir_mutex = vfs::resolve_path(&path, &rs)?;
+	let dir = dir_mutex.lock();
+	if dir.get_type() != FileType::Directory {
+		return Err(errno!(ENOTDIR));
+	}
+	proc.get_fs().lock().chroot = Arc::new(dir.get_path()?)?;
 
 	Ok(0)
 }
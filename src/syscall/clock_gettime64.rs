@@ -18,7 +18,7 @@ pub fn clock_gettime64(clockid: ClockIdT, tp: SyscallPtr<Timespec>) -> Result<i3
 
 		let mem_space = proc.get_mem_space().unwrap();
 		let mut mem_space_guard = mem_space.lock();
-		let timespec = tp.get_mut(&mut mem_space_guard)?.ok_or(errno!(EFAULT))?;
+		let mut timespec = tp.get_mut(&mut mem_space_guard)?.ok_or(errno!(EFAULT))?;
 
 		*timespec = curr_time;
 	}
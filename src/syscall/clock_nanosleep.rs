@@ -0,0 +1,133 @@
+//! The `clock_nanosleep` system call allows to make the current process sleep until a given
+//! time, measured by the given clock.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::scheduler;
+use crate::process::Process;
+use crate::process::State;
+use crate::syscall::restart_syscall::RestartBlock;
+use crate::time::clock;
+use crate::time::hrtimer;
+use crate::time::unit::ClockIdT;
+use crate::time::unit::TimeUnit;
+use crate::time::unit::Timespec32;
+use crate::time::unit::Timestamp;
+use crate::time::unit::TimestampScale;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// If set, the requested time is an absolute time instead of a delay.
+const TIMER_ABSTIME: c_int = 1;
+
+/// Makes the current process sleep.
+///
+/// Arguments:
+/// - `clockid` is the clock measuring the time.
+/// - `flags` is the set of flags.
+/// - `req` is the requested time.
+/// - `rem` is where the remaining time is written if the sleep is interrupted by a signal and
+/// the requested time is a delay. It may be null.
+pub fn do_clock_nanosleep<T: TimeUnit + Copy>(
+	clockid: ClockIdT,
+	flags: c_int,
+	req: SyscallPtr<T>,
+	rem: SyscallPtr<T>,
+) -> EResult<i32> {
+	let proc_mutex = Process::current_assert();
+
+	let req = {
+		let proc = proc_mutex.lock();
+		let mem_space = proc.get_mem_space().unwrap();
+		let mem_space_guard = mem_space.lock();
+		*req.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?
+	};
+	// If the number of nanoseconds is out of range, the value is not normalized
+	if T::from_nano(req.to_nano()) != req {
+		return Err(errno!(EINVAL));
+	}
+
+	let now = clock::current_time(clockid, TimestampScale::Nanosecond)?;
+	let abstime = flags & TIMER_ABSTIME != 0;
+	let deadline = if abstime {
+		req.to_nano()
+	} else {
+		now.saturating_add(req.to_nano())
+	};
+	sleep_until(clockid, deadline, abstime, rem)
+}
+
+/// Makes the current process sleep until the clock `clockid` reaches `deadline`, in
+/// nanoseconds.
+///
+/// If the sleep is interrupted by a signal and `abstime` is not set, the remaining time is
+/// written to `rem` and the sleep is resumed with `restart_syscall` if no signal handler is
+/// executed.
+fn sleep_until<T: TimeUnit + Copy>(
+	clockid: ClockIdT,
+	deadline: Timestamp,
+	abstime: bool,
+	rem: SyscallPtr<T>,
+) -> EResult<i32> {
+	let proc_mutex = Process::current_assert();
+	let mono_deadline = hrtimer::to_monotonic(clockid, deadline)?;
+
+	loop {
+		let now = clock::current_time(clockid, TimestampScale::Nanosecond)?;
+		if now >= deadline {
+			return Ok(0);
+		}
+
+		{
+			let mut proc = proc_mutex.lock();
+			if proc.get_next_signal().is_some() {
+				// An absolute deadline does not change, thus the system call can be restarted
+				// as is
+				if abstime {
+					return Err(errno!(ERESTARTNOHAND));
+				}
+
+				// Report the remaining time
+				let mem_space = proc.get_mem_space().unwrap().clone();
+				let mut mem_space_guard = mem_space.lock();
+				if let Some(mut rem) = rem.get_mut(&mut mem_space_guard)? {
+					*rem = T::from_nano(deadline - now);
+				}
+
+				proc.restart_block = Some(RestartBlock {
+					func: restart::<T>,
+					args: [clockid as _, deadline, rem.as_ptr() as _, 0],
+				});
+				return Err(errno!(ERESTART_RESTARTBLOCK));
+			}
+
+			// The process is woken up when the deadline is reached, or by a signal
+			hrtimer::insert(mono_deadline, proc.pid)?;
+			proc.set_state(State::Sleeping);
+		}
+		scheduler::end_tick();
+		hrtimer::remove(mono_deadline, proc_mutex.lock().pid);
+	}
+}
+
+/// Resumes a relative sleep that has been interrupted by a signal.
+fn restart<T: TimeUnit + Copy>(block: &RestartBlock) -> EResult<i32> {
+	let [clockid, deadline, rem, _] = block.args;
+	sleep_until::<T>(
+		clockid as _,
+		deadline,
+		false,
+		SyscallPtr::from(rem as usize),
+	)
+}
+
+#[syscall]
+pub fn clock_nanosleep(
+	clockid: ClockIdT,
+	flags: c_int,
+	request: SyscallPtr<Timespec32>,
+	remain: SyscallPtr<Timespec32>,
+) -> Result<i32, Errno> {
+	do_clock_nanosleep(clockid, flags, request, remain)
+}
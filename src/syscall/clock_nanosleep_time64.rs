@@ -0,0 +1,18 @@
+//! `clock_nanosleep_time64` is like `clock_nanosleep` but using 64 bits.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::time::unit::ClockIdT;
+use crate::time::unit::Timespec;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall]
+pub fn clock_nanosleep_time64(
+	clockid: ClockIdT,
+	flags: c_int,
+	request: SyscallPtr<Timespec>,
+	remain: SyscallPtr<Timespec>,
+) -> Result<i32, Errno> {
+	super::clock_nanosleep::do_clock_nanosleep(clockid, flags, request, remain)
+}
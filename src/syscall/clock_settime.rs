@@ -0,0 +1,40 @@
+//! The `clock_settime` system call sets the time of the given clock.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::perm::CAP_SYS_TIME;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::Process;
+use crate::time::clock;
+use crate::time::unit::ClockIdT;
+use crate::time::unit::TimeUnit;
+use crate::time::unit::Timespec;
+use macros::syscall;
+
+/// Sets the clock `clockid` to the time at `tp`.
+///
+/// Only privileged processes may set clocks.
+pub fn do_clock_settime(clockid: ClockIdT, tp: SyscallPtr<Timespec>) -> EResult<i32> {
+	let ts = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+		if !proc.access_profile.has_cap(CAP_SYS_TIME) {
+			return Err(errno!(EPERM));
+		}
+
+		let mem_space = proc.get_mem_space().unwrap();
+		let mem_space_guard = mem_space.lock();
+		*tp.get(&mem_space_guard)?.ok_or(errno!(EFAULT))?
+	};
+	if !(0..1_000_000_000).contains(&ts.tv_nsec) {
+		return Err(errno!(EINVAL));
+	}
+
+	clock::set(clockid, ts.to_nano())?;
+	Ok(0)
+}
+
+#[syscall]
+pub fn clock_settime(clockid: ClockIdT, tp: SyscallPtr<Timespec>) -> Result<i32, Errno> {
+	do_clock_settime(clockid, tp)
+}
@@ -0,0 +1,12 @@
+//! `clock_settime64` is like `clock_settime` but using 64 bits.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::time::unit::ClockIdT;
+use crate::time::unit::Timespec;
+use macros::syscall;
+
+#[syscall]
+pub fn clock_settime64(clockid: ClockIdT, tp: SyscallPtr<Timespec>) -> Result<i32, Errno> {
+	super::clock_settime::do_clock_settime(clockid, tp)
+}
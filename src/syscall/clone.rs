@@ -1,6 +1,12 @@
 //! The `clone` system call creates a child process.
 
+use crate::errno;
 use crate::errno::Errno;
+use crate::file::buffer::pidfd;
+use crate::file::fd::FD_CLOEXEC;
+use crate::file::open_file;
+use crate::file::open_file::OpenFile;
+use crate::file::perm::CAP_SYS_ADMIN;
 use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::scheduler;
 use crate::process::user_desc::UserDesc;
@@ -8,13 +14,15 @@ use crate::process::ForkOptions;
 use crate::process::Process;
 use crate::util::ptr::arc::Arc;
 use core::ffi::c_void;
+use core::ptr::NonNull;
 use macros::syscall;
 
 /// TODO doc
 const CLONE_IO: i32 = -0x80000000;
 /// If specified, the parent and child processes share the same memory space.
 const CLONE_VM: i32 = 0x100;
-/// TODO doc
+/// If specified, the parent and child processes share the same filesystem information (current
+/// working directory, root directory and umask).
 const CLONE_FS: i32 = 0x200;
 /// If specified, the parent and child processes share the same file descriptors
 /// table.
@@ -22,7 +30,8 @@ const CLONE_FILES: i32 = 0x400;
 /// If specified, the parent and child processes share the same signal handlers
 /// table.
 const CLONE_SIGHAND: i32 = 0x800;
-/// TODO doc
+/// If specified, a pidfd referring to the child process is created in the parent process. Its
+/// file descriptor is stored at `parent_tid`.
 const CLONE_PIDFD: i32 = 0x1000;
 /// TODO doc
 const CLONE_PTRACE: i32 = 0x2000;
@@ -30,23 +39,26 @@ const CLONE_PTRACE: i32 = 0x2000;
 const CLONE_VFORK: i32 = 0x4000;
 /// TODO doc
 const CLONE_PARENT: i32 = 0x8000;
-/// TODO doc
+/// If specified, the child process is a thread in the same thread group as the parent.
 const CLONE_THREAD: i32 = 0x10000;
-/// TODO doc
-const CLONE_NEWNS: i32 = 0x20000;
+/// If specified, the child process is created in a new mount namespace, which is a copy of the
+/// parent's.
+pub const CLONE_NEWNS: i32 = 0x20000;
 /// TODO doc
 const CLONE_SYSVSEM: i32 = 0x40000;
-/// TODO doc
+/// If specified, the TLS entry described by the `user_desc` structure at `tls` is set for the
+/// child process.
 const CLONE_SETTLS: i32 = 0x80000;
-/// TODO doc
+/// If specified, the TID of the child process is written at `parent_tid` in the parent's memory.
 const CLONE_PARENT_SETTID: i32 = 0x100000;
-/// TODO doc
+/// If specified, zero is written at `child_tid` in the child's memory when it exits, and a process
+/// waiting on this address with `futex` is woken up.
 const CLONE_CHILD_CLEARTID: i32 = 0x200000;
 /// TODO doc
 const CLONE_DETACHED: i32 = 0x400000;
 /// TODO doc
 const CLONE_UNTRACED: i32 = 0x800000;
-/// TODO doc
+/// If specified, the TID of the child process is written at `child_tid` in the child's memory.
 const CLONE_CHILD_SETTID: i32 = 0x1000000;
 /// TODO doc
 const CLONE_NEWCGROUP: i32 = 0x2000000;
@@ -56,8 +68,8 @@ const CLONE_NEWUTS: i32 = 0x4000000;
 const CLONE_NEWIPC: i32 = 0x8000000;
 /// TODO doc
 const CLONE_NEWUSER: i32 = 0x10000000;
-/// TODO doc
-const CLONE_NEWPID: i32 = 0x20000000;
+/// If specified, the child process is created in a new PID namespace.
+pub const CLONE_NEWPID: i32 = 0x20000000;
 /// TODO doc
 const CLONE_NEWNET: i32 = 0x40000000;
 
@@ -66,10 +78,30 @@ const CLONE_NEWNET: i32 = 0x40000000;
 pub fn clone(
 	flags: i32,
 	stack: *mut c_void,
-	_parent_tid: SyscallPtr<i32>,
+	parent_tid: SyscallPtr<i32>,
 	tls: i32,
-	_child_tid: SyscallPtr<i32>,
+	child_tid: SyscallPtr<i32>,
 ) -> Result<i32, Errno> {
+	// `parent_tid` cannot be used for both the TID and the pidfd
+	if flags & CLONE_PIDFD != 0 && flags & (CLONE_PARENT_SETTID | CLONE_THREAD) != 0 {
+		return Err(errno!(EINVAL));
+	}
+	// Threads share signal handlers, which refer to the memory space
+	if flags & CLONE_THREAD != 0 && flags & CLONE_SIGHAND == 0 {
+		return Err(errno!(EINVAL));
+	}
+	if flags & CLONE_SIGHAND != 0 && flags & CLONE_VM == 0 {
+		return Err(errno!(EINVAL));
+	}
+	// A thread must be in the same PID namespace as its group
+	if flags & CLONE_NEWPID != 0 && flags & CLONE_THREAD != 0 {
+		return Err(errno!(EINVAL));
+	}
+	// Processes sharing filesystem information must see the same mountpoints
+	if flags & CLONE_NEWNS != 0 && flags & CLONE_FS != 0 {
+		return Err(errno!(EINVAL));
+	}
+
 	let new_tid = {
 		// The current process
 		let curr_mutex = Process::current_assert();
@@ -77,16 +109,20 @@ pub fn clone(
 		let parent = Arc::downgrade(&curr_mutex);
 
 		let mut curr_proc = curr_mutex.lock();
-
-		if flags & CLONE_PARENT_SETTID != 0 {
-			// TODO
-			todo!();
+		if flags & (CLONE_NEWPID | CLONE_NEWNS) != 0
+			&& !curr_proc.access_profile.has_cap(CAP_SYS_ADMIN)
+		{
+			return Err(errno!(EPERM));
 		}
 
 		let fork_options = ForkOptions {
 			share_memory: flags & CLONE_VM != 0,
 			share_fd: flags & CLONE_FILES != 0,
 			share_sighand: flags & CLONE_SIGHAND != 0,
+			share_fs: flags & CLONE_FS != 0,
+			thread: flags & CLONE_THREAD != 0,
+			new_pid_ns: flags & CLONE_NEWPID != 0,
+			new_mnt_ns: flags & CLONE_NEWNS != 0,
 
 			vfork: flags & CLONE_VFORK != 0,
 		};
@@ -105,29 +141,78 @@ pub fn clone(
 		};
 		// Setting TLS
 		if flags & CLONE_SETTLS != 0 {
-			let _tls: SyscallPtr<UserDesc> = (tls as usize).into();
+			let tls: SyscallPtr<UserDesc> = (tls as usize).into();
+			let info = {
+				let mem_space = curr_proc.get_mem_space().unwrap();
+				let mem_space_guard = mem_space.lock();
+				*tls.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?
+			};
 
-			// TODO
-			todo!();
+			// An entry cannot be allocated, since it could not be reported to the child
+			let entry_number = info.get_entry_number();
+			if entry_number == -1 {
+				return Err(errno!(EINVAL));
+			}
+			let (_, entry) = super::set_thread_area::get_entry(&mut new_proc, entry_number)?;
+			*entry = info.to_descriptor();
 		}
 		new_proc.regs = new_regs;
 
+		if flags & CLONE_PARENT_SETTID != 0 {
+			let mem_space = curr_proc.get_mem_space().unwrap();
+			let mut mem_space_guard = mem_space.lock();
+			let mut tid = parent_tid
+				.get_mut(&mut mem_space_guard)?
+				.ok_or_else(|| errno!(EFAULT))?;
+			*tid = curr_proc.to_local_pid(new_proc.tid) as _;
+		}
 		if flags & CLONE_CHILD_CLEARTID != 0 {
-			// TODO new_proc.set_clear_child_tid(child_tid);
-			todo!();
+			new_proc.set_clear_child_tid(NonNull::new(child_tid.as_ptr_mut()));
 		}
 		if flags & CLONE_CHILD_SETTID != 0 {
-			// TODO
-			todo!();
+			let mem_space = new_proc.get_mem_space().unwrap();
+			let mut mem_space_guard = mem_space.lock();
+			// The child's memory space has to be bound to write in it
+			let bind = !mem_space_guard.is_bound();
+			if bind {
+				mem_space_guard.bind();
+			}
+			let res = child_tid
+				.get_mut(&mut mem_space_guard)
+				.and_then(|tid| tid.ok_or_else(|| errno!(EFAULT)))
+				.map(|mut tid| *tid = new_proc.to_local_pid(new_proc.tid) as _);
+			if bind {
+				curr_proc.get_mem_space().unwrap().lock().bind();
+			}
+			res?;
 		}
 
-		new_proc.tid
+		if flags & CLONE_PIDFD != 0 {
+			let file = pidfd::create(&new_mutex, &new_proc, &curr_proc.access_profile)?;
+			let open_file = OpenFile::new(file, open_file::O_RDWR)?;
+			let fd = {
+				let fds_mutex = curr_proc.get_fds().unwrap();
+				let mut fds = fds_mutex.lock();
+				fds.create_fd(FD_CLOEXEC, open_file)?.get_id()
+			};
+
+			let mem_space = curr_proc.get_mem_space().unwrap();
+			let mut mem_space_guard = mem_space.lock();
+			let mut pidfd = parent_tid
+				.get_mut(&mut mem_space_guard)?
+				.ok_or_else(|| errno!(EFAULT))?;
+			*pidfd = fd as _;
+		}
+
+		curr_proc.to_local_pid(new_proc.tid)
 	};
 
 	if flags & CLONE_VFORK != 0 {
 		// Letting another process run instead of the current. Because the current
 		// process must now wait for the child process to terminate or execute a program
-		scheduler::end_tick();
+		while Process::current_assert().lock().is_vfork_waiting() {
+			scheduler::end_tick();
+		}
 	}
 
 	Ok(new_tid as _)
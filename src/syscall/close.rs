@@ -2,6 +2,7 @@
 
 use crate::errno;
 use crate::errno::Errno;
+use crate::file::record_lock;
 use crate::process::Process;
 use core::ffi::c_int;
 use macros::syscall;
@@ -18,6 +19,15 @@ pub fn close(fd: c_int) -> Result<i32, Errno> {
 	let fds_mutex = proc.get_fds().unwrap();
 	let mut fds = fds_mutex.lock();
 
+	let location = fds
+		.get_fd(fd as _)
+		.ok_or_else(|| errno!(EBADF))?
+		.get_open_file()
+		.lock()
+		.get_location()
+		.clone();
 	fds.close_fd(fd as _)?;
+	// Closing any file descriptor to the file releases the record locks of the process on it
+	record_lock::release_file(&location, proc.pid);
 	Ok(0)
 }
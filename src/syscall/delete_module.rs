@@ -2,6 +2,7 @@
 
 use crate::errno;
 use crate::errno::Errno;
+use crate::file::perm::CAP_SYS_MODULE;
 use crate::module;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
@@ -17,7 +18,7 @@ pub fn delete_module(name: SyscallString, _flags: c_uint) -> Result<i32, Errno>
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
 
-		if !proc.access_profile.is_privileged() {
+		if !proc.access_profile.has_cap(CAP_SYS_MODULE) {
 			return Err(errno!(EPERM));
 		}
 
@@ -25,7 +26,7 @@ pub fn delete_module(name: SyscallString, _flags: c_uint) -> Result<i32, Errno>
 		let mem_space_guard = mem_space.lock();
 
 		let name = name.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
-		String::try_from(name)?
+		String::try_from(&*name)?
 	};
 
 	// TODO handle dependency (don't unload a module that is required by another)
@@ -1,11 +1,15 @@
 //! The `execve` system call allows to execute a program from a file.
 
+use super::personality::ADDR_NO_RANDOMIZE;
+use crate::cpu;
 use crate::errno;
 use crate::errno::EResult;
 use crate::errno::Errno;
+use crate::file::mountpoint;
 use crate::file::path::Path;
 use crate::file::perm::AccessProfile;
 use crate::file::vfs;
+use crate::file::vfs::ResolutionSettings;
 use crate::file::File;
 use crate::memory::stack;
 use crate::process;
@@ -15,11 +19,13 @@ use crate::process::exec::ProgramImage;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::regs::Regs;
 use crate::process::Process;
+use crate::process::COMM_LEN;
 use crate::util::container::string::String;
 use crate::util::container::vec::Vec;
 use crate::util::io::IO;
 use crate::util::lock::Mutex;
 use crate::util::ptr::arc::Arc;
+use core::cmp::min;
 use core::ops::Range;
 use macros::syscall;
 
@@ -44,72 +50,82 @@ struct Shebang {
 	arg: Option<Range<usize>>,
 }
 
-/// Peeks the shebang in the file.
-///
-/// Arguments:
-/// - `file` is the file from which the shebang is to be read.
-/// - `buff` is the buffer to write the shebang into.
+/// Tells whether the given character separates the interpreter from its argument.
+fn is_blank(c: &u8) -> bool {
+	*c == b' ' || *c == b'\t'
+}
+
+/// Parses the shebang in the buffer `buff`, containing the first `size` bytes of a file.
 ///
-/// If the file has a shebang, the function returns its size in bytes + the
-/// offset to the end of the interpreter.
+/// If the file doesn't begin with a shebang, the function returns `None`.
 ///
 /// If the string is longer than the interpreter's name, the remaining characters shall be used as
-/// an argument.
-fn peek_shebang(file: &mut File) -> Result<Option<Shebang>, Errno> {
-	let mut buff: [u8; SHEBANG_MAX] = [0; SHEBANG_MAX];
+/// an argument, without surrounding blanks.
+///
+/// If the shebang has no interpreter or doesn't fit in the buffer, the function returns
+/// [`errno::ENOEXEC`].
+fn parse_shebang(buff: [u8; SHEBANG_MAX], size: usize) -> EResult<Option<Shebang>> {
+	if size < 2 || buff[0..2] != [b'#', b'!'] {
+		return Ok(None);
+	}
 
-	let (size, _) = file.read(0, &mut buff)?;
-	let size = size as usize;
-
-	if size >= 2 && buff[0..2] == [b'#', b'!'] {
-		// Getting the end of the shebang
-		let shebang_end = buff[..size]
-			.iter()
-			.enumerate()
-			.filter(|(_, c)| **c == b'\n')
-			.map(|(off, _)| off)
-			.next();
-		let shebang_end = match shebang_end {
-			Some(shebang_end) => shebang_end,
-			None => return Ok(None),
-		};
+	// Getting the end of the shebang. If the file has only one line, it ends with the file
+	let shebang_end = match buff[..size].iter().position(|c| *c == b'\n') {
+		Some(end) => end,
+		None if size < SHEBANG_MAX => size,
+		None => return Err(errno!(ENOEXEC)),
+	};
+	let line = &buff[..shebang_end];
+
+	// Getting the range of the interpreter, skipping leading blanks
+	let interp_begin = line[2..]
+		.iter()
+		.position(|c| !is_blank(c))
+		.map(|off| 2 + off)
+		.ok_or_else(|| errno!(ENOEXEC))?;
+	let interp_end = line[interp_begin..]
+		.iter()
+		.position(is_blank)
+		.map(|off| interp_begin + off)
+		.unwrap_or(shebang_end);
+	let interp = interp_begin..interp_end;
+
+	// Getting the range of the optional argument
+	let arg = line[interp_end..]
+		.iter()
+		.position(|c| !is_blank(c))
+		.map(|off| {
+			// Cannot fail since the line contains a non-blank character after the interpreter
+			let arg_end = line.iter().rposition(|c| !is_blank(c)).unwrap() + 1;
+			(interp_end + off)..arg_end
+		});
+
+	Ok(Some(Shebang {
+		buff,
+		interp,
+		arg,
+	}))
+}
 
-		// Getting the range of the interpreter
-		let interp_end = buff[..size]
-			.iter()
-			.enumerate()
-			.filter(|(_, c)| **c == b' ' || **c == b'\t' || **c == b'\n')
-			.map(|(off, _)| off)
-			.next()
-			.unwrap_or(shebang_end);
-		let interp = 2..interp_end;
-
-		// Getting the range of the optional argument
-		let arg = buff[..size]
-			.iter()
-			.enumerate()
-			.skip(interp_end)
-			.filter(|(_, c)| **c != b' ' && **c != b'\t')
-			.map(|(off, _)| off..shebang_end)
-			.find(|arg| !arg.is_empty());
-
-		Ok(Some(Shebang {
-			buff,
-			interp,
-			arg,
-		}))
-	} else {
-		Ok(None)
-	}
+/// Peeks the shebang in the file `file`.
+///
+/// For details on the returned value, see [`parse_shebang`].
+fn peek_shebang(file: &mut File) -> EResult<Option<Shebang>> {
+	let mut buff: [u8; SHEBANG_MAX] = [0; SHEBANG_MAX];
+	let (size, _) = file.read(0, &mut buff)?;
+	parse_shebang(buff, size as _)
 }
 
 /// Performs the execution on the current process.
-fn do_exec(program_image: ProgramImage) -> Result<Regs, Errno> {
+///
+/// `comm` is the new name of the process.
+fn do_exec(program_image: ProgramImage, comm: &[u8]) -> Result<Regs, Errno> {
 	let proc_mutex = Process::current_assert();
 	let mut proc = proc_mutex.lock();
 
 	// Execute the program
 	exec::exec(&mut proc, program_image)?;
+	proc.set_comm(comm);
 	Ok(proc.regs.clone())
 }
 
@@ -120,126 +136,153 @@ fn do_exec(program_image: ProgramImage) -> Result<Regs, Errno> {
 /// - `access_profile` is the access profile to check permissions
 /// - `argv` is the arguments list.
 /// - `envp` is the environment variables list.
+/// - `randomize` tells whether the layout of the memory space may be randomized.
+/// - `no_new_privs` tells whether set-user-ID and set-group-ID bits are ignored.
 fn build_image(
 	file: Arc<Mutex<File>>,
 	access_profile: AccessProfile,
 	argv: Vec<String>,
 	envp: Vec<String>,
+	randomize: bool,
+	no_new_privs: bool,
 ) -> EResult<ProgramImage> {
 	let mut file = file.lock();
-	if !access_profile.can_execute_file(&*file) {
+	if !access_profile.can_execute_file(&*file) || !file.is_mount_executable() {
 		return Err(errno!(EACCES));
 	}
+	// Set-user-ID and set-group-ID bits are ignored on mountpoints that do not allow them
+	let setid =
+		file.get_location().get_mount_flags() & mountpoint::FLAG_NOSUID == 0 && !no_new_privs;
+	let access_profile = access_profile.for_exec(&file, setid);
+	// The layout of set-user-ID and set-group-ID programs is always randomized, since the caller
+	// could otherwise predict it
+	let ap = &access_profile;
+	let setid = ap.get_euid() != ap.get_uid() || ap.get_egid() != ap.get_gid();
 
 	let exec_info = ExecInfo {
 		access_profile,
 		argv,
 		envp,
+		randomize: randomize || setid,
 	};
 	exec::build_image(&mut file, exec_info)
 }
 
-#[syscall]
-pub fn execve(
-	pathname: SyscallString,
-	argv: *const *const u8,
-	envp: *const *const u8,
-) -> Result<i32, Errno> {
-	let (mut path, mut argv, envp, ap) = {
+/// Executes the program in the given file on the current process.
+///
+/// Arguments:
+/// - `file` is the program's file.
+/// - `path` is the path to the file, used to name the process and passed to the interpreter if the
+///   file is a script.
+/// - `script` tells whether the file may be executed as a script. If not and the file has a
+///   shebang, the function returns [`errno::ENOENT`], since the interpreter would not be able to
+///   access it.
+/// - `argv` is the arguments list.
+/// - `envp` is the environment variables list.
+/// - `rs` is the resolution settings used to find interpreters.
+///
+/// On success, the function doesn't return.
+pub fn do_execve(
+	mut file: Arc<Mutex<File>>,
+	mut path: Path,
+	script: bool,
+	mut argv: Vec<String>,
+	envp: Vec<String>,
+	rs: ResolutionSettings,
+) -> EResult<i32> {
+	let (randomize, no_new_privs) = {
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
-
-		let path = {
-			let mem_space = proc.get_mem_space().unwrap();
-			let mem_space_guard = mem_space.lock();
-
-			Path::from_str(
-				pathname
-					.get(&mem_space_guard)?
-					.ok_or_else(|| errno!(EFAULT))?,
-				true,
-			)?
-		};
-		let path = super::util::get_absolute_path(&proc, path)?;
-
-		let argv = unsafe { super::util::get_str_array(&proc, argv)? };
-		let envp = unsafe { super::util::get_str_array(&proc, envp)? };
-
-		(path, argv, envp, proc.access_profile)
+		let randomize = proc.personality & ADDR_NO_RANDOMIZE == 0;
+		(randomize, proc.no_new_privs)
 	};
 
+	// The process is named after the executed file
+	let mut comm = [0; COMM_LEN];
+	if let Some(name) = path.last() {
+		let len = min(name.len(), COMM_LEN - 1);
+		comm[..len].copy_from_slice(&name[..len]);
+	}
+
 	// Handling shebang
 	let mut i = 0;
-	while i < INTERP_MAX + 1 {
-		// The file
-		let file = vfs::get_file_from_path(&path, &ap, true)?;
-		let mut f = file.lock();
-
-		if !ap.can_execute_file(&*f) {
-			return Err(errno!(EACCES));
-		}
-
-		// If the file has a shebang, process it
-		if let Some(shebang) = peek_shebang(&mut f)? {
-			// If too many interpreter recursions, abort
-			if i == INTERP_MAX {
-				return Err(errno!(ELOOP));
+	loop {
+		let shebang = {
+			let mut f = file.lock();
+			if !rs.access_profile.can_execute_file(&*f) || !f.is_mount_executable() {
+				return Err(errno!(EACCES));
 			}
+			peek_shebang(&mut f)?
+		};
+		// If the file has a shebang, process it
+		let Some(shebang) = shebang else {
+			break;
+		};
+		if !script {
+			return Err(errno!(ENOENT));
+		}
+		// If too many interpreter recursions, abort
+		if i == INTERP_MAX {
+			return Err(errno!(ELOOP));
+		}
 
-			// Add the script to arguments
-			if argv.is_empty() {
-				argv.push(crate::format!("{path}")?)?;
-			} else {
-				argv[0] = crate::format!("{path}")?;
-			}
+		// Add the script to arguments
+		if argv.is_empty() {
+			argv.push(crate::format!("{path}")?)?;
+		} else {
+			argv[0] = crate::format!("{path}")?;
+		}
 
-			// Set interpreter to arguments
-			let interp = String::try_from(&shebang.buff[shebang.interp.clone()])?;
-			argv.insert(0, interp)?;
+		// Set interpreter to arguments
+		let interp = String::try_from(&shebang.buff[shebang.interp.clone()])?;
+		argv.insert(0, interp)?;
 
-			// Set optional argument if it exists
-			if let Some(arg) = shebang.arg {
-				let arg = String::try_from(&shebang.buff[arg])?;
-				argv.insert(1, arg)?;
-			}
+		// Set optional argument if it exists
+		if let Some(arg) = shebang.arg {
+			let arg = String::try_from(&shebang.buff[arg])?;
+			argv.insert(1, arg)?;
+		}
 
-			// Set interpreter's path
-			path = Path::from_str(&shebang.buff[shebang.interp], true)?;
+		// Set interpreter's path, which begins from the root directory of the process
+		let mut interp_path = Path::from_str(&shebang.buff[shebang.interp], true)?;
+		interp_path.set_absolute(false);
+		path = rs.root.concat(&interp_path)?;
+		file = vfs::resolve_path(&path, &rs)?;
 
-			i += 1;
-		} else {
-			break;
-		}
+		i += 1;
 	}
 
-	// The file
-	let file = vfs::get_file_from_path(&path, &ap, true)?;
-
-	// Drop path to avoid memory leak
+	// Drop paths to avoid memory leak
 	drop(path);
+	let ap = rs.access_profile;
+	drop(rs);
 
 	// Disable interrupt to prevent stack switching while using a temporary stack,
 	// preventing this temporary stack from being used as a signal handling stack
 	cli!();
 
 	// Build the program's image
-	let program_image =
-		unsafe { stack::switch(None, move || build_image(file, ap, argv, envp)).unwrap()? };
+	let program_image = unsafe {
+		stack::switch(None, move || {
+			build_image(file, ap, argv, envp, randomize, no_new_privs)
+		})
+		.unwrap()?
+	};
 
 	// The temporary stack will not be used since the scheduler cannot be ticked when
 	// interrupts are disabled
 	// A temporary stack cannot be allocated since it wouldn't be possible to free
 	// it on success
 	let tmp_stack = {
-		let core = 0; // TODO Get current core ID
-		process::get_scheduler().lock().get_tmp_stack(core)
+		let core = cpu::get_current_id();
+		process::get_scheduler().lock().get_tmp_stack(core as _)
 	};
 
 	// Switch to another stack in order to avoid crashing when switching to the
 	// new memory space
 	unsafe {
 		stack::switch(Some(tmp_stack), move || -> EResult<()> {
-			let regs = do_exec(program_image)?;
+			let regs = do_exec(program_image, &comm)?;
 			regs.switch(true);
 		})
 		// `unwrap` cannot fail since the stack is provided
@@ -249,3 +292,94 @@ pub fn execve(
 	// Cannot be reached since on success
 	unreachable!();
 }
+
+#[syscall]
+pub fn execve(
+	pathname: SyscallString,
+	argv: *const *const u8,
+	envp: *const *const u8,
+) -> Result<i32, Errno> {
+	let (path, argv, envp, rs) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let path = {
+			let mem_space = proc.get_mem_space().unwrap();
+			let mem_space_guard = mem_space.lock();
+
+			Path::from_str(
+				&pathname
+					.get(&mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?,
+				true,
+			)?
+		};
+		let path = super::util::get_absolute_path(&proc, path)?;
+
+		let argv = unsafe { super::util::get_str_array(&proc, argv)? };
+		let envp = unsafe { super::util::get_str_array(&proc, envp)? };
+
+		let rs = ResolutionSettings::for_process(&proc, true)?;
+
+		(path, argv, envp, rs)
+	};
+
+	let file = vfs::resolve_path(&path, &rs)?;
+	do_execve(file, path, true, argv, envp, rs)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	/// Parses the given file content.
+	fn parse(content: &[u8]) -> EResult<Option<Shebang>> {
+		let mut buff = [0; SHEBANG_MAX];
+		let size = min(content.len(), SHEBANG_MAX);
+		buff[..size].copy_from_slice(&content[..size]);
+		parse_shebang(buff, size)
+	}
+
+	/// Returns the interpreter and the argument of the given shebang.
+	fn split(shebang: &Shebang) -> (&[u8], Option<&[u8]>) {
+		let interp = &shebang.buff[shebang.interp.clone()];
+		let arg = shebang.arg.clone().map(|arg| &shebang.buff[arg]);
+		(interp, arg)
+	}
+
+	#[test_case]
+	fn shebang_none() {
+		assert!(parse(b"").unwrap().is_none());
+		assert!(parse(b"\x7fELF").unwrap().is_none());
+		assert!(parse(b"# comment\n").unwrap().is_none());
+	}
+
+	#[test_case]
+	fn shebang_interp() {
+		let shebang = parse(b"#!/bin/sh\necho\n").unwrap().unwrap();
+		assert_eq!(split(&shebang), (b"/bin/sh".as_slice(), None));
+		let shebang = parse(b"#! \t/bin/sh  \n").unwrap().unwrap();
+		assert_eq!(split(&shebang), (b"/bin/sh".as_slice(), None));
+		// No newline
+		let shebang = parse(b"#!/bin/sh").unwrap().unwrap();
+		assert_eq!(split(&shebang), (b"/bin/sh".as_slice(), None));
+	}
+
+	#[test_case]
+	fn shebang_arg() {
+		let shebang = parse(b"#!/usr/bin/env  python3 -u \n").unwrap().unwrap();
+		assert_eq!(
+			split(&shebang),
+			(b"/usr/bin/env".as_slice(), Some(b"python3 -u".as_slice()))
+		);
+	}
+
+	#[test_case]
+	fn shebang_invalid() {
+		assert!(parse(b"#!\n").is_err());
+		assert!(parse(b"#!  \t\n").is_err());
+		let mut long = [b'a'; SHEBANG_MAX];
+		long[..2].copy_from_slice(b"#!");
+		assert!(parse(&long).is_err());
+	}
+}
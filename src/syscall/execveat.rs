@@ -0,0 +1,70 @@
+//! The `execveat` system call allows to execute a program from a file, given relative to a
+//! directory file descriptor or as a file descriptor itself.
+
+use super::access::AT_EMPTY_PATH;
+use super::access::AT_FDCWD;
+use super::access::AT_SYMLINK_NOFOLLOW;
+use super::execve;
+use crate::errno::Errno;
+use crate::file::fd::FD_CLOEXEC;
+use crate::file::path::Path;
+use crate::file::vfs::ResolutionSettings;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::Process;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall]
+pub fn execveat(
+	dirfd: c_int,
+	pathname: SyscallString,
+	argv: *const *const u8,
+	envp: *const *const u8,
+	flags: c_int,
+) -> Result<i32, Errno> {
+	if flags & !(AT_EMPTY_PATH | AT_SYMLINK_NOFOLLOW) != 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	let pathname = {
+		let mem_space = proc.get_mem_space().unwrap();
+		let mem_space_guard = mem_space.lock();
+		let pathname = pathname
+			.get(&mem_space_guard)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		let mut buf = Vec::new();
+		buf.extend_from_slice(&pathname)?;
+		buf
+	};
+	let argv = unsafe { super::util::get_str_array(&proc, argv)? };
+	let envp = unsafe { super::util::get_str_array(&proc, envp)? };
+	let rs = ResolutionSettings::for_process(&proc, true)?;
+
+	// The path of the file, as seen from the new program
+	let (path, script) = if pathname.first() == Some(&b'/') || dirfd == AT_FDCWD {
+		let path = Path::from_str(&pathname, true)?;
+		(super::util::get_absolute_path(&proc, path)?, true)
+	} else {
+		// The file cannot be accessed by an interpreter if the file descriptor is closed on exec
+		let cloexec = {
+			let fds_mutex = proc.get_fds().unwrap();
+			let fds = fds_mutex.lock();
+			let fd = fds.get_fd(dirfd as _).ok_or_else(|| errno!(EBADF))?;
+			fd.get_flags() & FD_CLOEXEC != 0
+		};
+		let path = if pathname.is_empty() {
+			crate::format!("/dev/fd/{dirfd}")?
+		} else {
+			crate::format!("/dev/fd/{dirfd}/{}", String::try_from(&*pathname)?)?
+		};
+		(Path::from_str(path.as_bytes(), true)?, !cloexec)
+	};
+
+	let file = super::util::get_file_at(proc, dirfd, &pathname, true, flags)?;
+	execve::do_execve(file, path, script, argv, envp, rs)
+}
@@ -0,0 +1,19 @@
+//! The `fadvise64` syscall gives hints to the kernel about file accesses.
+
+use super::fadvise64_64::do_fadvise;
+use crate::errno::Errno;
+use core::ffi::c_int;
+use core::ffi::c_ulong;
+use macros::syscall;
+
+#[syscall]
+pub fn fadvise64(
+	fd: c_int,
+	offset_low: c_ulong,
+	offset_high: c_ulong,
+	len: usize,
+	advice: c_int,
+) -> Result<i32, Errno> {
+	let offset = ((offset_high as u64) << 32) | offset_low as u64;
+	do_fadvise(fd, offset, len as _, advice)
+}
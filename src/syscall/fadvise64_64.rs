@@ -1,12 +1,109 @@
 //! The `fadvise64_64` syscall gives hints to the kernel about file accesses.
 
+use crate::errno;
+use crate::errno::EResult;
 use crate::errno::Errno;
+use crate::file::page_cache;
+use crate::file::readahead;
+use crate::file::readahead::Advice;
+use crate::file::FileType;
+use crate::memory;
+use crate::process::Process;
 use core::ffi::c_int;
+use core::ffi::c_ulong;
 use macros::syscall;
 
-// TODO Check args type
-#[syscall]
-pub fn fadvise64_64(_fd: c_int, _offset: u64, _len: u64, _advice: c_int) -> Result<i32, Errno> {
-	// TODO
+/// No special treatment.
+const POSIX_FADV_NORMAL: c_int = 0;
+/// Expect accesses in random order.
+const POSIX_FADV_RANDOM: c_int = 1;
+/// Expect accesses in sequential order.
+const POSIX_FADV_SEQUENTIAL: c_int = 2;
+/// Expect accesses in the near future.
+const POSIX_FADV_WILLNEED: c_int = 3;
+/// Do not expect accesses in the near future.
+const POSIX_FADV_DONTNEED: c_int = 4;
+/// Expect data to be accessed only once.
+const POSIX_FADV_NOREUSE: c_int = 5;
+
+/// Converts the given number of pages to `usize`, saturating if it does not fit.
+fn to_usize(n: u64) -> usize {
+	usize::try_from(n).unwrap_or(usize::MAX)
+}
+
+/// Performs the `fadvise` operation.
+///
+/// Arguments:
+/// - `fd` is the file descriptor of the file
+/// - `offset` is the offset of the beginning of the range the advice applies to
+/// - `len` is the length of the range. If zero, the range extends to the end of the file
+/// - `advice` is the advice
+pub fn do_fadvise(fd: c_int, offset: u64, len: u64, advice: c_int) -> EResult<i32> {
+	if fd < 0 {
+		return Err(errno!(EBADF));
+	}
+	if (len as i64) < 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	let open_file_mutex = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let fds_mutex = proc.get_fds().unwrap();
+		let fds = fds_mutex.lock();
+
+		fds.get_fd(fd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file()
+			.clone()
+	};
+	let mut open_file = open_file_mutex.lock();
+	let file_mutex = open_file.get_file().clone();
+	if matches!(file_mutex.lock().get_type(), FileType::Fifo) {
+		return Err(errno!(ESPIPE));
+	}
+
+	let page_size = memory::PAGE_SIZE as u64;
+	let end = match len {
+		0 => u64::MAX,
+		len => offset.saturating_add(len),
+	};
+	match advice {
+		POSIX_FADV_NORMAL => open_file.set_advice(Advice::Normal),
+		POSIX_FADV_RANDOM => open_file.set_advice(Advice::Random),
+		POSIX_FADV_SEQUENTIAL => open_file.set_advice(Advice::Sequential),
+
+		POSIX_FADV_WILLNEED => {
+			let first = offset / page_size;
+			let pages = end.div_ceil(page_size) - first;
+			readahead::schedule(&file_mutex, to_usize(first), to_usize(pages))?;
+		}
+		POSIX_FADV_DONTNEED => {
+			// Only pages that are entirely in the range are discarded
+			let first = offset.div_ceil(page_size);
+			let pages = (end / page_size).saturating_sub(first);
+			drop(open_file);
+			page_cache::discard(&mut file_mutex.lock(), first, to_usize(pages))?;
+		}
+		POSIX_FADV_NOREUSE => {}
+
+		_ => return Err(errno!(EINVAL)),
+	}
+
 	Ok(0)
 }
+
+#[syscall]
+pub fn fadvise64_64(
+	fd: c_int,
+	offset_low: c_ulong,
+	offset_high: c_ulong,
+	len_low: c_ulong,
+	len_high: c_ulong,
+	advice: c_int,
+) -> Result<i32, Errno> {
+	let offset = ((offset_high as u64) << 32) | offset_low as u64;
+	let len = ((len_high as u64) << 32) | len_low as u64;
+	do_fadvise(fd, offset, len, advice)
+}
@@ -0,0 +1,85 @@
+//! The `fallocate` system call manipulates the space allocated for the content of a file.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::FileType;
+use crate::process::Process;
+use core::ffi::c_int;
+use core::ffi::c_ulong;
+use macros::syscall;
+
+/// Mode flag: do not change the size of the file, even if the range extends past its end.
+const FALLOC_FL_KEEP_SIZE: c_int = 0x01;
+/// Mode flag: deallocate the range, making a hole. Requires [`FALLOC_FL_KEEP_SIZE`].
+const FALLOC_FL_PUNCH_HOLE: c_int = 0x02;
+
+#[syscall]
+pub fn fallocate(
+	fd: c_int,
+	mode: c_int,
+	offset_low: c_ulong,
+	offset_high: c_ulong,
+	len_low: c_ulong,
+	len_high: c_ulong,
+) -> Result<i32, Errno> {
+	let offset = ((offset_high as u64) << 32) | offset_low as u64;
+	let len = ((len_high as u64) << 32) | len_low as u64;
+
+	if mode & !(FALLOC_FL_KEEP_SIZE | FALLOC_FL_PUNCH_HOLE) != 0 {
+		return Err(errno!(EOPNOTSUPP));
+	}
+	let punch_hole = mode & FALLOC_FL_PUNCH_HOLE != 0;
+	let keep_size = mode & FALLOC_FL_KEEP_SIZE != 0;
+	if punch_hole && !keep_size {
+		return Err(errno!(EOPNOTSUPP));
+	}
+	if (offset as i64) < 0 || (len as i64) <= 0 {
+		return Err(errno!(EINVAL));
+	}
+	if offset.checked_add(len).map_or(true, |end| (end as i64) < 0) {
+		return Err(errno!(EFBIG));
+	}
+
+	if fd < 0 {
+		return Err(errno!(EBADF));
+	}
+	let open_file_mutex = {
+		let proc_mutex = Process::current_assert();
+		let mut proc = proc_mutex.lock();
+
+		let fds_mutex = proc.get_fds().unwrap().clone();
+		let fds = fds_mutex.lock();
+		let open_file_mutex = fds
+			.get_fd(fd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file()
+			.clone();
+
+		if !keep_size {
+			super::util::check_file_size_limit(&mut proc, offset + len)?;
+		}
+		open_file_mutex
+	};
+	let file_mutex = {
+		let open_file = open_file_mutex.lock();
+		if !open_file.can_write() {
+			return Err(errno!(EBADF));
+		}
+		open_file.get_file().clone()
+	};
+
+	let mut file = file_mutex.lock();
+	match file.get_type() {
+		FileType::Regular => {}
+		FileType::Fifo => return Err(errno!(ESPIPE)),
+		FileType::Directory => return Err(errno!(EISDIR)),
+		_ => return Err(errno!(ENODEV)),
+	}
+	if punch_hole {
+		file.punch_hole(offset, len)?;
+	} else {
+		file.allocate(offset, len, keep_size)?;
+	}
+
+	Ok(0)
+}
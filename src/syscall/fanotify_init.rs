@@ -0,0 +1,87 @@
+//! The `fanotify_init` system call creates a fanotify instance and returns a file descriptor to
+//! it.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::buffer::fanotify;
+use crate::file::buffer::fanotify::Fanotify;
+use crate::file::fd::FD_CLOEXEC;
+use crate::file::open_file;
+use crate::file::open_file::OpenFile;
+use crate::file::perm::CAP_SYS_ADMIN;
+use crate::process::Process;
+use core::ffi::c_uint;
+use macros::syscall;
+
+/// Set the close-on-exec flag on the new file descriptor.
+const FAN_CLOEXEC: c_uint = 0x1;
+/// Make reading the file descriptor non-blocking.
+const FAN_NONBLOCK: c_uint = 0x2;
+/// Mask of the bits giving the class of the instance.
+const FAN_CLASS_MASK: c_uint = 0xc;
+/// Do not limit the number of queued events.
+const FAN_UNLIMITED_QUEUE: c_uint = 0x10;
+/// Do not limit the number of marks.
+const FAN_UNLIMITED_MARKS: c_uint = 0x20;
+
+/// The flags that may be used for the open file descriptions created for events.
+const EVENT_F_FLAGS: c_uint = (0b11
+	| open_file::O_APPEND
+	| open_file::O_CLOEXEC
+	| open_file::O_LARGEFILE
+	| open_file::O_NOATIME
+	| open_file::O_NONBLOCK
+	| open_file::O_SYNC) as _;
+
+#[syscall]
+pub fn fanotify_init(flags: c_uint, event_f_flags: c_uint) -> Result<i32, Errno> {
+	let valid_flags =
+		FAN_CLOEXEC | FAN_NONBLOCK | FAN_CLASS_MASK | FAN_UNLIMITED_QUEUE | FAN_UNLIMITED_MARKS;
+	if flags & !valid_flags != 0 {
+		return Err(errno!(EINVAL));
+	}
+	let class = flags & FAN_CLASS_MASK;
+	if !matches!(
+		class,
+		fanotify::FAN_CLASS_NOTIF | fanotify::FAN_CLASS_CONTENT | fanotify::FAN_CLASS_PRE_CONTENT
+	) {
+		return Err(errno!(EINVAL));
+	}
+	if event_f_flags & !EVENT_F_FLAGS != 0 || event_f_flags & 0b11 == 0b11 {
+		return Err(errno!(EINVAL));
+	}
+
+	let (fds_mutex, access_profile) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let fds_mutex = proc.get_fds().unwrap().clone();
+		(fds_mutex, proc.access_profile)
+	};
+	if !access_profile.has_cap(CAP_SYS_ADMIN) {
+		return Err(errno!(EPERM));
+	}
+
+	let fanotify = Fanotify::new(
+		class,
+		event_f_flags as _,
+		flags & FAN_UNLIMITED_QUEUE != 0,
+		flags & FAN_UNLIMITED_MARKS != 0,
+	);
+	let file = fanotify::create(fanotify, &access_profile)?;
+	let mut open_flags = open_file::O_RDWR;
+	if flags & FAN_NONBLOCK != 0 {
+		open_flags |= open_file::O_NONBLOCK;
+	}
+	let open_file = OpenFile::new(file, open_flags)?;
+
+	let fd_flags = if flags & FAN_CLOEXEC != 0 {
+		FD_CLOEXEC
+	} else {
+		0
+	};
+	let mut fds = fds_mutex.lock();
+	let fd = fds.create_fd(fd_flags, open_file)?;
+
+	Ok(fd.get_id() as _)
+}
@@ -0,0 +1,133 @@
+//! The `fanotify_mark` system call adds, removes or modifies a mark of a fanotify instance.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::buffer::fanotify;
+use crate::file::buffer::fanotify::MarkTarget;
+use crate::file::FileType;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::Process;
+use core::ffi::c_int;
+use core::ffi::c_uint;
+use macros::syscall;
+
+/// Add the events to the mark.
+const FAN_MARK_ADD: c_uint = 0x1;
+/// Remove the events from the mark.
+const FAN_MARK_REMOVE: c_uint = 0x2;
+/// Do not follow symbolic links.
+const FAN_MARK_DONT_FOLLOW: c_uint = 0x4;
+/// Fail if the file is not a directory.
+const FAN_MARK_ONLYDIR: c_uint = 0x8;
+/// Mark the mountpoint of the file instead of the file itself.
+const FAN_MARK_MOUNT: c_uint = 0x10;
+/// Operate on the mask of ignored events.
+const FAN_MARK_IGNORED_MASK: c_uint = 0x20;
+/// The mask of ignored events is not cleared when the file is modified.
+const FAN_MARK_IGNORED_SURV_MODIFY: c_uint = 0x40;
+/// Remove every marks on files, or on mountpoints if [`FAN_MARK_MOUNT`] is set.
+const FAN_MARK_FLUSH: c_uint = 0x80;
+
+// TODO Support FAN_MARK_FILESYSTEM
+
+// The mask is 64 bits long, and thus split into two arguments
+#[syscall]
+pub fn fanotify_mark(
+	fanotify_fd: c_int,
+	flags: c_uint,
+	mask_lo: u32,
+	mask_hi: u32,
+	dirfd: c_int,
+	pathname: SyscallString,
+) -> Result<i32, Errno> {
+	let valid_flags = FAN_MARK_ADD
+		| FAN_MARK_REMOVE
+		| FAN_MARK_DONT_FOLLOW
+		| FAN_MARK_ONLYDIR
+		| FAN_MARK_MOUNT
+		| FAN_MARK_IGNORED_MASK
+		| FAN_MARK_IGNORED_SURV_MODIFY
+		| FAN_MARK_FLUSH;
+	if flags & !valid_flags != 0 {
+		return Err(errno!(EINVAL));
+	}
+	let op = flags & (FAN_MARK_ADD | FAN_MARK_REMOVE | FAN_MARK_FLUSH);
+	if !matches!(op, FAN_MARK_ADD | FAN_MARK_REMOVE | FAN_MARK_FLUSH) {
+		return Err(errno!(EINVAL));
+	}
+	let mask = ((mask_hi as u64) << 32) | mask_lo as u64;
+	if mask & !fanotify::FAN_ALL_MARK_BITS != 0 || (op != FAN_MARK_FLUSH && mask == 0) {
+		return Err(errno!(EINVAL));
+	}
+	if fanotify_fd < 0 {
+		return Err(errno!(EBADF));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	let fanotify_mutex = {
+		let fds_mutex = proc.get_fds().unwrap();
+		let fds = fds_mutex.lock();
+		let open_file_mutex = fds
+			.get_fd(fanotify_fd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file();
+		let open_file = open_file_mutex.lock();
+		fanotify::get(open_file.get_location()).ok_or_else(|| errno!(EINVAL))?
+	};
+	if op == FAN_MARK_FLUSH {
+		drop(proc);
+		fanotify_mutex
+			.lock()
+			.flush_marks(flags & FAN_MARK_MOUNT != 0);
+		return Ok(0);
+	}
+
+	let ap = proc.access_profile;
+	let file_mutex = {
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let mem_space_guard = mem_space.lock();
+
+		match pathname.get(&mem_space_guard)? {
+			Some(pathname) => {
+				let at_flags = if flags & FAN_MARK_DONT_FOLLOW != 0 {
+					super::access::AT_SYMLINK_NOFOLLOW
+				} else {
+					0
+				};
+				super::util::get_file_at(proc, dirfd, &pathname, true, at_flags)?
+			}
+			None => {
+				super::util::get_file_at(proc, dirfd, b"", true, super::access::AT_EMPTY_PATH)?
+			}
+		}
+	};
+
+	let location = {
+		let file = file_mutex.lock();
+		if !ap.can_read_file(&file) {
+			return Err(errno!(EACCES));
+		}
+		if flags & FAN_MARK_ONLYDIR != 0 && file.get_type() != FileType::Directory {
+			return Err(errno!(ENOTDIR));
+		}
+		file.get_location().clone()
+	};
+	let target = if flags & FAN_MARK_MOUNT != 0 {
+		MarkTarget::Mount(location.get_mountpoint_id().ok_or_else(|| errno!(EINVAL))?)
+	} else {
+		MarkTarget::File(location)
+	};
+
+	let mut fanotify = fanotify_mutex.lock();
+	let ignored = flags & FAN_MARK_IGNORED_MASK != 0;
+	if op == FAN_MARK_ADD {
+		let surv_modify = flags & FAN_MARK_IGNORED_SURV_MODIFY != 0;
+		fanotify.add_mark(target, mask, ignored, surv_modify)?;
+	} else {
+		fanotify.remove_mark(&target, mask, ignored)?;
+	}
+
+	Ok(0)
+}
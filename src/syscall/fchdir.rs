@@ -48,10 +48,9 @@ pub fn fchdir(fd: c_int) -> Result<i32, Errno> {
 
 	{
 		let proc_mutex = Process::current_assert();
-		let mut proc = proc_mutex.lock();
+		let proc = proc_mutex.lock();
 
-		let new_cwd = super::util::get_absolute_path(&proc, new_cwd)?;
-		proc.cwd = Arc::new(new_cwd)?;
+		proc.get_fs().lock().cwd = Arc::new(new_cwd)?;
 	}
 
 	Ok(0)
@@ -32,6 +32,7 @@ pub fn fchmod(fd: c_int, mode: i32) -> Result<i32, Errno> {
 	if !ap.can_set_file_permissions(&*file) {
 		return Err(errno!(EPERM));
 	}
+	file.check_mount_writable()?;
 
 	file.set_permissions(mode as _);
 	// TODO lazy sync
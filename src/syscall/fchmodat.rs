@@ -27,7 +27,7 @@ pub fn fchmodat(
 		let pathname = pathname
 			.get(&mem_space_guard)?
 			.ok_or_else(|| errno!(EFAULT))?;
-		let file_mutex = util::get_file_at(proc, dirfd, pathname, true, flags)?;
+		let file_mutex = util::get_file_at(proc, dirfd, &pathname, true, flags)?;
 
 		(file_mutex, ap)
 	};
@@ -37,6 +37,7 @@ pub fn fchmodat(
 	if !ap.can_set_file_permissions(&*file) {
 		return Err(errno!(EPERM));
 	}
+	file.check_mount_writable()?;
 
 	file.set_permissions(mode as _);
 	// TODO lazy sync
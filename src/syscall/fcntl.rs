@@ -1,11 +1,25 @@
 //! The `fcntl` syscall call allows to manipulate a file descriptor.
 
+use crate::errno;
+use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file::buffer;
-use crate::file::buffer::pipe::PipeBuffer;
+use crate::file::buffer::memfd;
+use crate::file::buffer::memfd::MemFd;
+use crate::file::buffer::pipe;
+use crate::file::buffer::pipe::PIPE_MAX_SIZE;
+use crate::file::buffer::Buffer;
 use crate::file::fd::NewFDConstraint;
-use crate::file::FileContent;
+use crate::file::open_file::Owner;
+use crate::file::open_file::O_ASYNC;
+use crate::file::perm::CAP_SYS_RESOURCE;
+use crate::file::record_lock;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::scheduler;
+use crate::process::signal::Signal;
 use crate::process::Process;
+use crate::util::io::IO;
+use core::any::Any;
 use core::ffi::c_int;
 use core::ffi::c_void;
 use macros::syscall;
@@ -21,11 +35,11 @@ const F_SETFD: i32 = 2;
 const F_GETFL: i32 = 3;
 /// Set the file status flag.
 const F_SETFL: i32 = 4;
-/// TODO doc
+/// Return a record lock preventing the placement of the given lock, if any.
 const F_GETLK: i32 = 5;
-/// TODO doc
+/// Place or remove a record lock, failing if another process holds a conflicting lock.
 const F_SETLK: i32 = 6;
-/// TODO doc
+/// Like `F_SETLK`, but wait for conflicting locks to be released.
 const F_SETLKW: i32 = 7;
 /// Set the process ID or process group ID that will receive `SIGIO` and `SIGURG` signals for
 /// events on the file descriptor.
@@ -37,11 +51,11 @@ const F_GETOWN: i32 = 9;
 const F_SETSIG: i32 = 10;
 /// Return the signal sent when input or output becomes possible.
 const F_GETSIG: i32 = 11;
-/// TODO doc
+/// Like `F_GETLK`, with 64 bits offsets.
 const F_GETLK64: i32 = 12;
-/// TODO doc
+/// Like `F_SETLK`, with 64 bits offsets.
 const F_SETLK64: i32 = 13;
-/// TODO doc
+/// Like `F_SETLKW`, with 64 bits offsets.
 const F_SETLKW64: i32 = 14;
 /// Similar to `F_SETOWN`, except it allows to specifiy a thread ID using the `f_owner_ex`
 /// structure.
@@ -86,9 +100,6 @@ const F_GET_FILE_RW_HINT: i32 = 1037;
 /// descriptor.
 const F_SET_FILE_RW_HINT: i32 = 1038;
 
-/// TODO doc
-const F_SEAL_FUTURE_WRITE: i32 = 16;
-
 /// Take out a read lease.
 const F_RDLCK: i32 = 0;
 /// Take out a write lease.
@@ -96,6 +107,13 @@ const F_WRLCK: i32 = 1;
 /// Remove our lease from the file.
 const F_UNLCK: i32 = 2;
 
+/// Lock offsets are relative to the beginning of the file.
+const SEEK_SET: i16 = 0;
+/// Lock offsets are relative to the current offset of the open file.
+const SEEK_CUR: i16 = 1;
+/// Lock offsets are relative to the end of the file.
+const SEEK_END: i16 = 2;
+
 /// Send the signal to the process group whose ID is specified.
 const F_OWNER_PGRP: i32 = 2;
 /// Send the signal to the process whose ID is specified.
@@ -103,14 +121,218 @@ const F_OWNER_PID: i32 = 1;
 /// Send the signal to the thread whose thread ID is specified.
 const F_OWNER_TID: i32 = 0;
 
-/// If this seal is set, the size of the file in question cannot be increased.
-const F_SEAL_GROW: i32 = 4;
-/// If this seal is set, any further call to `fcntl` with `F_ADD_SEALS` fails.
-const F_SEAL_SEAL: i32 = 1;
-/// If this seal is set, the size of the file in question cannot be reduced.
-const F_SEAL_SHRINK: i32 = 2;
-/// If this seal is set, you cannot modify the contents of the file.
-const F_SEAL_WRITE: i32 = 8;
+/// The recipient of signals, as given to `F_SETOWN_EX` and `F_GETOWN_EX` (`struct f_owner_ex`).
+#[repr(C)]
+#[derive(Debug)]
+struct FOwnerEx {
+	/// The type of the recipient.
+	type_: c_int,
+	/// The ID of the recipient.
+	pid: c_int,
+}
+
+/// A record lock, as given to `F_GETLK`, `F_SETLK` and `F_SETLKW` (`struct flock`).
+#[repr(C)]
+#[derive(Debug)]
+struct Flock {
+	/// The type of the lock.
+	l_type: i16,
+	/// The origin of `l_start`.
+	l_whence: i16,
+	/// The offset of the beginning of the range.
+	l_start: i32,
+	/// The length of the range. If zero, the range extends to the end of the file. If negative,
+	/// the range ends at `l_start`.
+	l_len: i32,
+	/// The PID of the process holding the lock, returned by `F_GETLK`.
+	l_pid: i32,
+}
+
+/// Like [`Flock`], with 64 bits offsets (`struct flock64`).
+#[repr(C)]
+#[derive(Clone, Debug)]
+struct Flock64 {
+	/// The type of the lock.
+	l_type: i16,
+	/// The origin of `l_start`.
+	l_whence: i16,
+	/// The offset of the beginning of the range.
+	l_start: i64,
+	/// The length of the range. If zero, the range extends to the end of the file. If negative,
+	/// the range ends at `l_start`.
+	l_len: i64,
+	/// The PID of the process holding the lock, returned by `F_GETLK`.
+	l_pid: i32,
+}
+
+/// Performs the record lock command `cmd` on the file descriptor `fd`.
+///
+/// `wide` tells whether `arg` points to a [`Flock64`] instead of a [`Flock`].
+fn record_lock_cmd(fd: i32, cmd: i32, arg: *mut c_void, wide: bool) -> EResult<i32> {
+	let (pid, mem_space, open_file_mutex) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let fds_mutex = proc.get_fds().unwrap();
+		let fds = fds_mutex.lock();
+		let open_file_mutex = fds
+			.get_fd(fd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file()
+			.clone();
+		(proc.pid, mem_space, open_file_mutex)
+	};
+
+	let desc = {
+		let mem_space_guard = mem_space.lock();
+		if wide {
+			let ptr: SyscallPtr<Flock64> = (arg as usize).into();
+			let desc = ptr.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
+			(*desc).clone()
+		} else {
+			let ptr: SyscallPtr<Flock> = (arg as usize).into();
+			let desc = ptr.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
+			Flock64 {
+				l_type: desc.l_type,
+				l_whence: desc.l_whence,
+				l_start: desc.l_start as _,
+				l_len: desc.l_len as _,
+				l_pid: desc.l_pid,
+			}
+		}
+	};
+
+	let kind = match desc.l_type as i32 {
+		F_RDLCK => Some(record_lock::Kind::Read),
+		F_WRLCK => Some(record_lock::Kind::Write),
+		F_UNLCK => None,
+		_ => return Err(errno!(EINVAL)),
+	};
+	let (location, base, can_read, can_write) = {
+		let open_file = open_file_mutex.lock();
+		let base = match desc.l_whence {
+			SEEK_SET => 0,
+			SEEK_CUR => open_file.get_offset(),
+			SEEK_END => open_file.get_size(),
+			_ => return Err(errno!(EINVAL)),
+		};
+		(
+			open_file.get_location().clone(),
+			base,
+			open_file.can_read(),
+			open_file.can_write(),
+		)
+	};
+
+	// Compute the range
+	let start = (base as i64)
+		.checked_add(desc.l_start)
+		.ok_or_else(|| errno!(EOVERFLOW))?;
+	let (start, end) = match desc.l_len {
+		0 => (start, None),
+		len @ 1.. => (
+			start,
+			Some(start.checked_add(len).ok_or_else(|| errno!(EOVERFLOW))?),
+		),
+		len => (start + len, Some(start)),
+	};
+	if start < 0 {
+		return Err(errno!(EINVAL));
+	}
+	let start = start as u64;
+	let end = end.map(|end| end as u64).unwrap_or(u64::MAX);
+
+	if matches!(cmd, F_GETLK | F_GETLK64) {
+		let kind = kind.ok_or_else(|| errno!(EINVAL))?;
+		let lock = record_lock::Lock {
+			kind,
+			start,
+			end,
+			pid,
+		};
+		let mut desc = desc;
+		match record_lock::get_conflict(&location, &lock) {
+			Some(conflict) => {
+				desc.l_type = match conflict.kind {
+					record_lock::Kind::Read => F_RDLCK as _,
+					record_lock::Kind::Write => F_WRLCK as _,
+				};
+				desc.l_whence = SEEK_SET;
+				desc.l_start = conflict.start as _;
+				desc.l_len = if conflict.end == u64::MAX {
+					0
+				} else {
+					(conflict.end - conflict.start) as _
+				};
+				desc.l_pid = conflict.pid as _;
+			}
+			None => desc.l_type = F_UNLCK as _,
+		}
+
+		let mut mem_space_guard = mem_space.lock();
+		if wide {
+			let ptr: SyscallPtr<Flock64> = (arg as usize).into();
+			let mut desc_ref = ptr
+				.get_mut(&mut mem_space_guard)?
+				.ok_or_else(|| errno!(EFAULT))?;
+			*desc_ref = desc;
+		} else {
+			let ptr: SyscallPtr<Flock> = (arg as usize).into();
+			let mut desc_ref = ptr
+				.get_mut(&mut mem_space_guard)?
+				.ok_or_else(|| errno!(EFAULT))?;
+			*desc_ref = Flock {
+				l_type: desc.l_type,
+				l_whence: desc.l_whence,
+				l_start: desc.l_start.try_into().map_err(|_| errno!(EOVERFLOW))?,
+				l_len: desc.l_len.try_into().map_err(|_| errno!(EOVERFLOW))?,
+				l_pid: desc.l_pid,
+			};
+		}
+		return Ok(0);
+	}
+
+	let Some(kind) = kind else {
+		record_lock::unlock(&location, pid, start, end)?;
+		return Ok(0);
+	};
+	let allowed = match kind {
+		record_lock::Kind::Read => can_read,
+		record_lock::Kind::Write => can_write,
+	};
+	if !allowed {
+		return Err(errno!(EBADF));
+	}
+	let lock = record_lock::Lock {
+		kind,
+		start,
+		end,
+		pid,
+	};
+	if matches!(cmd, F_SETLK | F_SETLK64) {
+		if !record_lock::set(&location, lock, None)? {
+			return Err(errno!(EAGAIN));
+		}
+		return Ok(0);
+	}
+	loop {
+		// If interrupted, the process is not waiting anymore
+		record_lock::cancel_wait(pid);
+		super::util::signal_check()?;
+
+		{
+			let proc_mutex = Process::current_assert();
+			let mut proc = proc_mutex.lock();
+			if record_lock::set(&location, lock.clone(), Some(&mut proc))? {
+				return Ok(0);
+			}
+		}
+
+		// Make current process sleep
+		scheduler::end_tick();
+	}
+}
 
 /// Performs the fcntl system call.
 ///
@@ -120,11 +342,22 @@ pub fn do_fcntl(fd: i32, cmd: i32, arg: *mut c_void, _fcntl64: bool) -> Result<i
 		return Err(errno!(EBADF));
 	}
 
-	let fds_mutex = {
+	// Record locks may wait, and thus must not hold the file descriptors table
+	match cmd {
+		F_GETLK | F_SETLK | F_SETLKW => return record_lock_cmd(fd, cmd, arg, false),
+		F_GETLK64 | F_SETLK64 | F_SETLKW64 => return record_lock_cmd(fd, cmd, arg, true),
+		_ => {}
+	}
+
+	let (fds_mutex, mem_space, access_profile) = {
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
 
-		proc.get_fds().unwrap().clone()
+		(
+			proc.get_fds().unwrap().clone(),
+			proc.get_mem_space().unwrap().clone(),
+			proc.access_profile,
+		)
 	};
 	let mut fds = fds_mutex.lock();
 
@@ -153,72 +386,94 @@ pub fn do_fcntl(fd: i32, cmd: i32, arg: *mut c_void, _fcntl64: bool) -> Result<i
 		}
 
 		F_SETFL => {
-			let fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
-			let open_file_mutex = fd.get_open_file();
+			let open_file_mutex = fds
+				.get_fd(fd as _)
+				.ok_or_else(|| errno!(EBADF))?
+				.get_open_file();
 			let mut open_file = open_file_mutex.lock();
 
+			// Register the open file for signal-driven IO if `O_ASYNC` changes
+			let async_ = arg as i32 & O_ASYNC != 0;
+			if (open_file.get_flags() & O_ASYNC != 0) != async_ {
+				open_file.set_async(fd, async_)?;
+			}
 			open_file.set_flags(arg as _);
 			Ok(0)
 		}
 
-		F_GETLK => {
-			// TODO
-			todo!();
-		}
-
-		F_SETLK => {
-			// TODO
-			todo!();
-		}
-
-		F_SETLKW => {
-			// TODO
-			todo!();
-		}
-
 		F_SETOWN => {
-			// TODO
-			todo!();
+			let fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
+			let owner = match arg as c_int {
+				0 => Owner::None,
+				id @ 1.. => Owner::Process(id.try_into().map_err(|_| errno!(ESRCH))?),
+				id => Owner::ProcessGroup((-id).try_into().map_err(|_| errno!(ESRCH))?),
+			};
+			fd.get_open_file().lock().set_owner(owner);
+			Ok(0)
 		}
 
 		F_GETOWN => {
-			// TODO
-			todo!();
+			let fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
+			let owner = fd.get_open_file().lock().get_owner();
+			Ok(match owner {
+				Owner::None => 0,
+				Owner::Thread(id) | Owner::Process(id) => id as _,
+				Owner::ProcessGroup(id) => -(id as i32),
+			})
 		}
 
 		F_SETSIG => {
-			// TODO
-			todo!();
+			let fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
+			let sig = arg as c_int;
+			if sig != 0 {
+				Signal::try_from(sig as u32)?;
+			}
+			fd.get_open_file().lock().set_sig(sig);
+			Ok(0)
 		}
 
 		F_GETSIG => {
-			// TODO
-			todo!();
-		}
-
-		F_GETLK64 => {
-			// TODO
-			todo!();
-		}
-
-		F_SETLK64 => {
-			// TODO
-			todo!();
-		}
-
-		F_SETLKW64 => {
-			// TODO
-			todo!();
+			let fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
+			Ok(fd.get_open_file().lock().get_sig())
 		}
 
 		F_SETOWN_EX => {
-			// TODO
-			todo!();
+			let fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
+			let owner = {
+				let mem_space_guard = mem_space.lock();
+				let ptr: SyscallPtr<FOwnerEx> = (arg as usize).into();
+				let owner = ptr.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
+				let id = owner.pid.try_into().map_err(|_| errno!(ESRCH))?;
+				match (owner.type_, id) {
+					(F_OWNER_TID | F_OWNER_PID | F_OWNER_PGRP, 0) => Owner::None,
+					(F_OWNER_TID, id) => Owner::Thread(id),
+					(F_OWNER_PID, id) => Owner::Process(id),
+					(F_OWNER_PGRP, id) => Owner::ProcessGroup(id),
+					_ => return Err(errno!(EINVAL)),
+				}
+			};
+			fd.get_open_file().lock().set_owner(owner);
+			Ok(0)
 		}
 
 		F_GETOWN_EX => {
-			// TODO
-			todo!();
+			let fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
+			let (type_, pid) = match fd.get_open_file().lock().get_owner() {
+				Owner::None => (F_OWNER_PID, 0),
+				Owner::Thread(id) => (F_OWNER_TID, id),
+				Owner::Process(id) => (F_OWNER_PID, id),
+				Owner::ProcessGroup(id) => (F_OWNER_PGRP, id),
+			};
+			let mut mem_space_guard = mem_space.lock();
+			let ptr: SyscallPtr<FOwnerEx> = (arg as usize).into();
+			let mut owner = ptr
+				.get_mut(&mut mem_space_guard)?
+				.ok_or_else(|| errno!(EFAULT))?;
+			*owner = FOwnerEx {
+				type_,
+				pid: pid as _,
+			};
+			Ok(0)
 		}
 
 		F_OFD_GETLK => {
@@ -256,37 +511,59 @@ pub fn do_fcntl(fd: i32, cmd: i32, arg: *mut c_void, _fcntl64: bool) -> Result<i
 			.get_id() as _),
 
 		F_SETPIPE_SZ => {
-			// TODO
-			todo!();
+			let size: usize = (arg as c_int).try_into().map_err(|_| errno!(EINVAL))?;
+			if size > PIPE_MAX_SIZE && !access_profile.has_cap(CAP_SYS_RESOURCE) {
+				return Err(errno!(EPERM));
+			}
+			let fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
+			let open_file_mutex = fd.get_open_file();
+			let open_file = open_file_mutex.lock();
+			let cap = pipe::pipe_do(open_file.get_location(), |pipe| pipe.set_capacity(size))
+				.ok_or_else(|| errno!(EBADF))??;
+			Ok(cap as _)
 		}
 
 		F_GETPIPE_SZ => {
 			let fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
-
 			let open_file_mutex = fd.get_open_file();
 			let open_file = open_file_mutex.lock();
+			let cap = pipe::pipe_do(open_file.get_location(), |pipe| pipe.get_capacity())
+				.ok_or_else(|| errno!(EBADF))?;
+			Ok(cap as _)
+		}
 
-			let file_mutex = open_file.get_file();
-			let file = file_mutex.lock();
+		F_ADD_SEALS => {
+			let fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
 
-			match file.get_content() {
-				FileContent::Fifo => {
-					let buf = buffer::get_or_default::<PipeBuffer>(file.get_location())?;
-					let cap = buf.lock().get_capacity();
-					Ok(cap as _)
+			let open_file_mutex = fd.get_open_file().clone();
+			let loc = {
+				let open_file = open_file_mutex.lock();
+				if !open_file.can_write() {
+					return Err(errno!(EPERM));
 				}
-				_ => Ok(0),
-			}
-		}
+				open_file.get_location().clone()
+			};
+			// Looking for mappings requires locking processes, which must not be done while
+			// holding the file descriptors table
+			drop(fds);
+
+			let mapped = memfd::has_shared_writable_mapping(&loc)?;
+			let buff_mutex = buffer::get(&loc).ok_or_else(|| errno!(EINVAL))?;
+			let mut buff = buff_mutex.lock();
+			let memfd = (&mut *buff as &mut dyn Any)
+				.downcast_mut::<MemFd>()
+				.ok_or_else(|| errno!(EINVAL))?;
+			memfd.add_seals(arg as _, mapped)?;
 
-		F_ADD_SEALS => {
-			// TODO
-			todo!();
+			Ok(0)
 		}
 
 		F_GET_SEALS => {
-			// TODO
-			todo!();
+			let fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
+
+			let open_file_mutex = fd.get_open_file();
+			let open_file = open_file_mutex.lock();
+			memfd::get_seals(open_file.get_location()).ok_or_else(|| errno!(EINVAL))
 		}
 
 		F_GET_RW_HINT => {
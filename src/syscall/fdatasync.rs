@@ -0,0 +1,37 @@
+//! The `fdatasync` system call synchronizes the content of a file to storage.
+//!
+//! Unlike `fsync`, the metadata of the file only has to be written if it is required to
+//! retrieve the data. Since changes to metadata are not tracked separately from the size of the
+//! file, the status of the file is always written back.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::writeback;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall]
+pub fn fdatasync(fd: c_int) -> Result<i32, Errno> {
+	if fd < 0 {
+		return Err(errno!(EBADF));
+	}
+
+	let file_mutex = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let fds_mutex = proc.get_fds().unwrap();
+		let fds = fds_mutex.lock();
+
+		let fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
+
+		let open_file_mutex = fd.get_open_file();
+		let open_file = open_file_mutex.lock();
+
+		open_file.get_file().clone()
+	};
+
+	writeback::sync_file(&file_mutex)?;
+	Ok(0)
+}
@@ -0,0 +1,18 @@
+//! The `fgetxattr` system call returns the value of an extended attribute of the file
+//! associated with a file descriptor.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::ptr::SyscallString;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall]
+pub fn fgetxattr(
+	fd: c_int,
+	name: SyscallString,
+	value: SyscallSlice<u8>,
+	size: usize,
+) -> Result<i32, Errno> {
+	super::getxattr::do_getxattr(fd, None, true, name, value, size)
+}
@@ -3,6 +3,7 @@
 use crate::errno;
 use crate::errno::AllocError;
 use crate::errno::Errno;
+use crate::file::perm::CAP_SYS_MODULE;
 use crate::module;
 use crate::module::Module;
 use crate::process::mem_space::ptr::SyscallString;
@@ -21,7 +22,7 @@ pub fn finit_module(fd: c_int, _param_values: SyscallString, _flags: c_int) -> R
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
 
-		if !proc.access_profile.is_privileged() {
+		if !proc.access_profile.has_cap(CAP_SYS_MODULE) {
 			return Err(errno!(EPERM));
 		}
 
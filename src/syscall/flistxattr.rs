@@ -0,0 +1,12 @@
+//! The `flistxattr` system call returns the list of the names of the extended attributes of the
+//! file associated with a file descriptor.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallSlice;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall]
+pub fn flistxattr(fd: c_int, list: SyscallSlice<u8>, size: usize) -> Result<i32, Errno> {
+	super::listxattr::do_listxattr(fd, None, true, list, size)
+}
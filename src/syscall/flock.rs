@@ -0,0 +1,69 @@
+//! The `flock` system call places or removes an advisory lock on an open file.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::flock;
+use crate::process::scheduler;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Place a shared lock.
+const LOCK_SH: c_int = 1;
+/// Place an exclusive lock.
+const LOCK_EX: c_int = 2;
+/// Do not block if the lock cannot be acquired.
+const LOCK_NB: c_int = 4;
+/// Remove the lock.
+const LOCK_UN: c_int = 8;
+
+#[syscall]
+pub fn flock(fd: c_int, operation: c_int) -> Result<i32, Errno> {
+	let kind = match operation & !LOCK_NB {
+		LOCK_SH => Some(flock::Kind::Shared),
+		LOCK_EX => Some(flock::Kind::Exclusive),
+		LOCK_UN => None,
+		_ => return Err(errno!(EINVAL)),
+	};
+	let nonblock = operation & LOCK_NB != 0;
+	if fd < 0 {
+		return Err(errno!(EBADF));
+	}
+
+	let open_file_mutex = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let fds_mutex = proc.get_fds().unwrap();
+		let fds = fds_mutex.lock();
+		fds.get_fd(fd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file()
+			.clone()
+	};
+
+	let Some(kind) = kind else {
+		open_file_mutex.lock().release_flock();
+		return Ok(0);
+	};
+	loop {
+		super::util::signal_check()?;
+
+		{
+			let mut open_file = open_file_mutex.lock();
+			let proc_mutex = Process::current_assert();
+			let mut proc = proc_mutex.lock();
+
+			let waiter = (!nonblock).then_some(&mut *proc);
+			if open_file.flock(kind, waiter)? {
+				return Ok(0);
+			}
+			if nonblock {
+				return Err(errno!(EWOULDBLOCK));
+			}
+		}
+
+		// Make current process sleep
+		scheduler::end_tick();
+	}
+}
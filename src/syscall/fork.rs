@@ -26,5 +26,5 @@ pub fn fork() -> Result<i32, Errno> {
 	regs.eax = 0;
 	new_proc.regs = regs;
 
-	Ok(new_proc.pid as _)
+	Ok(curr_proc.to_local_pid(new_proc.pid) as _)
 }
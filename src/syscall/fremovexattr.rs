@@ -0,0 +1,12 @@
+//! The `fremovexattr` system call removes an extended attribute of the file associated with a
+//! file descriptor.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallString;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall]
+pub fn fremovexattr(fd: c_int, name: SyscallString) -> Result<i32, Errno> {
+	super::removexattr::do_removexattr(fd, None, true, name)
+}
@@ -0,0 +1,19 @@
+//! The `fsetxattr` system call sets the value of an extended attribute of the file associated
+//! with a file descriptor.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::ptr::SyscallString;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall]
+pub fn fsetxattr(
+	fd: c_int,
+	name: SyscallString,
+	value: SyscallSlice<u8>,
+	size: usize,
+	flags: c_int,
+) -> Result<i32, Errno> {
+	super::setxattr::do_setxattr(fd, None, true, name, value, size, flags)
+}
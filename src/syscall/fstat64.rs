@@ -7,9 +7,7 @@ use crate::file::INode;
 use crate::file::Mode;
 use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::Process;
-use crate::time::unit::TimeUnit;
 use crate::time::unit::Timespec;
-use crate::time::unit::TimestampScale;
 use crate::util::io::IO;
 use core::ffi::c_int;
 use core::ffi::c_long;
@@ -100,21 +98,9 @@ pub fn fstat64(fd: c_int, statbuf: SyscallPtr<Stat>) -> Result<i32, Errno> {
 		st_blksize: 512, // TODO
 		st_blocks: file.blocks_count,
 
-		st_atim: Timespec::from_nano(TimestampScale::convert(
-			file.atime,
-			TimestampScale::Second,
-			TimestampScale::Nanosecond,
-		)),
-		st_mtim: Timespec::from_nano(TimestampScale::convert(
-			file.mtime,
-			TimestampScale::Second,
-			TimestampScale::Nanosecond,
-		)),
-		st_ctim: Timespec::from_nano(TimestampScale::convert(
-			file.ctime,
-			TimestampScale::Second,
-			TimestampScale::Nanosecond,
-		)),
+		st_atim: file.atime,
+		st_mtim: file.mtime,
+		st_ctim: file.ctime,
 	};
 
 	{
@@ -124,7 +110,7 @@ pub fn fstat64(fd: c_int, statbuf: SyscallPtr<Stat>) -> Result<i32, Errno> {
 		let mem_space = proc.get_mem_space().unwrap();
 		let mut mem_space_guard = mem_space.lock();
 
-		let statbuf = statbuf
+		let mut statbuf = statbuf
 			.get_mut(&mut mem_space_guard)?
 			.ok_or(errno!(EFAULT))?;
 		*statbuf = stat;
@@ -50,7 +50,7 @@ pub fn fstatfs(fd: c_int, buf: SyscallPtr<Statfs>) -> Result<i32, Errno> {
 		let mem_space = proc.get_mem_space().unwrap();
 		let mut mem_space_guard = mem_space.lock();
 
-		let buf = buf
+		let mut buf = buf
 			.get_mut(&mut mem_space_guard)?
 			.ok_or_else(|| errno!(EFAULT))?;
 		*buf = stat;
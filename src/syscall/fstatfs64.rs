@@ -52,7 +52,7 @@ pub fn fstatfs64(fd: c_int, _sz: usize, buf: SyscallPtr<Statfs>) -> Result<i32,
 		let mem_space = proc.get_mem_space().unwrap();
 		let mut mem_space_guard = mem_space.lock();
 
-		let buf = buf
+		let mut buf = buf
 			.get_mut(&mut mem_space_guard)?
 			.ok_or_else(|| errno!(EFAULT))?;
 		*buf = stat;
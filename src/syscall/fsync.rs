@@ -2,6 +2,7 @@
 
 use crate::errno;
 use crate::errno::Errno;
+use crate::file::writeback;
 use crate::process::Process;
 use core::ffi::c_int;
 use macros::syscall;
@@ -27,8 +28,6 @@ pub fn fsync(fd: c_int) -> Result<i32, Errno> {
 		open_file.get_file().clone()
 	};
 
-	let file = file_mutex.lock();
-	file.sync()?;
-
+	writeback::sync_file(&file_mutex)?;
 	Ok(0)
 }
@@ -0,0 +1,49 @@
+//! The `ftruncate` syscall allows to truncate a file designated by a file descriptor.
+
+use crate::errno::Errno;
+use crate::file::FileType;
+use crate::process::Process;
+use core::ffi::c_int;
+use core::ffi::c_long;
+use macros::syscall;
+
+#[syscall]
+pub fn ftruncate(fd: c_int, length: c_long) -> Result<i32, Errno> {
+	if length < 0 {
+		return Err(errno!(EINVAL));
+	}
+	if fd < 0 {
+		return Err(errno!(EBADF));
+	}
+
+	let open_file_mutex = {
+		let proc_mutex = Process::current_assert();
+		let mut proc = proc_mutex.lock();
+
+		let fds_mutex = proc.get_fds().unwrap().clone();
+		let fds = fds_mutex.lock();
+		let open_file_mutex = fds
+			.get_fd(fd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file()
+			.clone();
+
+		super::util::check_file_size_limit(&mut proc, length as _)?;
+		open_file_mutex
+	};
+	let file_mutex = {
+		let open_file = open_file_mutex.lock();
+		if !open_file.can_write() {
+			return Err(errno!(EINVAL));
+		}
+		open_file.get_file().clone()
+	};
+
+	let mut file = file_mutex.lock();
+	if !matches!(file.get_type(), FileType::Regular) {
+		return Err(errno!(EINVAL));
+	}
+	file.truncate(length as _)?;
+
+	Ok(0)
+}
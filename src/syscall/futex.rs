@@ -0,0 +1,401 @@
+//! The `futex` system call allows to wait on a futex, or to wake processes waiting on it.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::process::futex::FutexKey;
+use crate::process::futex::FUTEXES;
+use crate::process::futex::FUTEX_BITSET_MATCH_ANY;
+use crate::process::futex::FUTEX_OWNER_DIED;
+use crate::process::futex::FUTEX_TID_MASK;
+use crate::process::futex::FUTEX_WAITERS;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::scheduler;
+use crate::process::Process;
+use crate::process::State;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::clock::CLOCK_REALTIME;
+use crate::time::hrtimer;
+use crate::time::unit::ClockIdT;
+use crate::time::unit::TimeUnit;
+use crate::time::unit::Timespec32;
+use crate::time::unit::Timestamp;
+use crate::time::unit::TimestampScale;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Operation: wait on the futex if it holds the given value.
+const FUTEX_WAIT: c_int = 0;
+/// Operation: wake processes waiting on the futex.
+const FUTEX_WAKE: c_int = 1;
+/// Operation: wake processes waiting on the futex and move the others to another futex.
+const FUTEX_REQUEUE: c_int = 3;
+/// Operation: like `FUTEX_REQUEUE`, but only if the futex holds the given value.
+const FUTEX_CMP_REQUEUE: c_int = 4;
+/// Operation: lock the PI futex, waiting if it is held.
+const FUTEX_LOCK_PI: c_int = 6;
+/// Operation: unlock the PI futex, handing it over to a waiter if any.
+const FUTEX_UNLOCK_PI: c_int = 7;
+/// Operation: lock the PI futex, failing if it is held.
+const FUTEX_TRYLOCK_PI: c_int = 8;
+/// Operation: like `FUTEX_WAIT`, with a bitset and an absolute timeout.
+const FUTEX_WAIT_BITSET: c_int = 9;
+/// Operation: like `FUTEX_WAKE`, with a bitset.
+const FUTEX_WAKE_BITSET: c_int = 10;
+
+/// Flag: the futex is private to the memory space of the process.
+const FUTEX_PRIVATE_FLAG: c_int = 128;
+/// Flag: the timeout is measured with `CLOCK_REALTIME` instead of `CLOCK_MONOTONIC`.
+const FUTEX_CLOCK_REALTIME: c_int = 256;
+/// The mask of the operation's command.
+const FUTEX_CMD_MASK: c_int = !(FUTEX_PRIVATE_FLAG | FUTEX_CLOCK_REALTIME);
+
+/// Returns the key of the futex at `uaddr` for the current process.
+fn get_key(uaddr: &SyscallPtr<u32>, private: bool) -> EResult<FutexKey> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+	let mem_space_mutex = proc.get_mem_space().unwrap().clone();
+	let mut mem_space = mem_space_mutex.lock();
+	FutexKey::new(&mem_space_mutex, &mut mem_space, uaddr.as_ptr(), private)
+}
+
+/// Reads the value of the futex at `uaddr`.
+fn read_value(uaddr: &SyscallPtr<u32>) -> EResult<u32> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+	let mem_space = proc.get_mem_space().unwrap();
+	let mem_space_guard = mem_space.lock();
+	Ok(*uaddr.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?)
+}
+
+/// Writes the value `val` to the futex at `uaddr`.
+fn write_value(uaddr: &SyscallPtr<u32>, val: u32) -> EResult<()> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+	*uaddr
+		.get_mut(&mut mem_space_guard)?
+		.ok_or_else(|| errno!(EFAULT))? = val;
+	Ok(())
+}
+
+/// Makes the current process wait on the futex at `uaddr` if it holds the value `val`.
+///
+/// Arguments:
+/// - `key` is the key of the futex.
+/// - `bitset` is the bitset of the wait.
+/// - `deadline` is the timeout, as a timestamp of the clock `clockid` in nanoseconds.
+fn wait(
+	uaddr: &SyscallPtr<u32>,
+	key: FutexKey,
+	val: u32,
+	bitset: u32,
+	deadline: Option<(ClockIdT, Timestamp)>,
+) -> EResult<i32> {
+	let proc_mutex = Process::current_assert();
+	let pid = proc_mutex.lock().pid;
+	let mono_deadline = deadline
+		.map(|(clockid, deadline)| hrtimer::to_monotonic(clockid, deadline))
+		.transpose()?;
+
+	{
+		// The value is checked while holding the table so that a wake cannot be missed
+		let mut futexes = FUTEXES.lock();
+		if read_value(uaddr)? != val {
+			return Err(errno!(EAGAIN));
+		}
+		futexes.enqueue(key, pid, bitset)?;
+		if let Some(mono_deadline) = mono_deadline {
+			if let Err(e) = hrtimer::insert(mono_deadline, pid) {
+				futexes.dequeue(pid);
+				return Err(e.into());
+			}
+		}
+		proc_mutex.lock().set_state(State::Sleeping);
+	}
+
+	loop {
+		scheduler::end_tick();
+
+		let mut futexes = FUTEXES.lock();
+		let res = if !futexes.is_waiting(pid) {
+			Some(Ok(0))
+		} else if deadline
+			.map(|(clockid, deadline)| {
+				clock::current_time(clockid, TimestampScale::Nanosecond)
+					.map(|now| now >= deadline)
+					.unwrap_or(true)
+			})
+			.unwrap_or(false)
+		{
+			Some(Err(errno!(ETIMEDOUT)))
+		} else if proc_mutex.lock().get_next_signal().is_some() {
+			// A wait with a timeout is not restarted since the timeout is relative
+			if deadline.is_some() {
+				Some(Err(errno!(EINTR)))
+			} else {
+				Some(Err(errno!(ERESTARTSYS)))
+			}
+		} else {
+			None
+		};
+		match res {
+			Some(res) => {
+				futexes.dequeue(pid);
+				if let Some(mono_deadline) = mono_deadline {
+					hrtimer::remove(mono_deadline, pid);
+				}
+				return res;
+			}
+			// Spurious wakeup
+			None => proc_mutex.lock().set_state(State::Sleeping),
+		}
+	}
+}
+
+/// Locks the PI futex at `uaddr` for the current process.
+///
+/// Arguments:
+/// - `key` is the key of the futex.
+/// - `deadline` is the timeout, as a timestamp of `CLOCK_REALTIME` in nanoseconds.
+/// - `try_lock` tells whether the function fails instead of waiting if the futex is held.
+fn lock_pi(
+	uaddr: &SyscallPtr<u32>,
+	key: FutexKey,
+	deadline: Option<Timestamp>,
+	try_lock: bool,
+) -> EResult<i32> {
+	let proc_mutex = Process::current_assert();
+	let (pid, tid) = {
+		let proc = proc_mutex.lock();
+		(proc.pid, proc.to_local_pid(proc.tid))
+	};
+	let mono_deadline = deadline
+		.map(|deadline| hrtimer::to_monotonic(CLOCK_REALTIME, deadline))
+		.transpose()?;
+
+	loop {
+		{
+			let mut futexes = FUTEXES.lock();
+			let val = read_value(uaddr)?;
+			let owner = val & FUTEX_TID_MASK;
+			if owner == 0 {
+				// Take the futex, keeping the mark of a dead previous owner
+				let mut new = tid as u32 | (val & FUTEX_OWNER_DIED);
+				if futexes.has_waiters(&key) {
+					futexes.set_pi_owner(key.clone(), pid)?;
+					new |= FUTEX_WAITERS;
+				}
+				write_value(uaddr, new)?;
+				return Ok(0);
+			}
+			if owner == tid as u32 {
+				return Err(errno!(EDEADLK));
+			}
+			if try_lock {
+				return Err(errno!(EAGAIN));
+			}
+			if Process::get_by_vpid(owner as _).is_none() {
+				return Err(errno!(ESRCH));
+			}
+			if let Some(deadline) = deadline {
+				if clock::current_time(CLOCK_REALTIME, TimestampScale::Nanosecond)? >= deadline {
+					return Err(errno!(ETIMEDOUT));
+				}
+			}
+
+			// Tell the owner to unlock through the kernel, then wait
+			write_value(uaddr, val | FUTEX_WAITERS)?;
+			futexes.enqueue(key.clone(), pid, FUTEX_BITSET_MATCH_ANY)?;
+			let res =
+				futexes
+					.set_pi_owner(key.clone(), owner as _)
+					.and_then(|_| match mono_deadline {
+						Some(mono_deadline) => hrtimer::insert(mono_deadline, pid),
+						None => Ok(()),
+					});
+			if let Err(e) = res {
+				futexes.dequeue(pid);
+				return Err(e.into());
+			}
+			proc_mutex.lock().set_state(State::Sleeping);
+		}
+		scheduler::end_tick();
+
+		{
+			let mut futexes = FUTEXES.lock();
+			if let Some(mono_deadline) = mono_deadline {
+				hrtimer::remove(mono_deadline, pid);
+			}
+			if !futexes.is_waiting(pid) {
+				// Either the futex has been handed over, or its owner died
+				if read_value(uaddr)? & FUTEX_TID_MASK == tid as u32 {
+					return Ok(0);
+				}
+				continue;
+			}
+			// Woken up by the timeout or a signal
+			futexes.dequeue(pid);
+		}
+		// The lock is always retried after the signal has been handled
+		super::util::signal_check().map_err(|_| errno!(ERESTARTNOINTR))?;
+	}
+}
+
+/// Unlocks the PI futex at `uaddr`, held by the current process.
+///
+/// If processes are waiting on the futex, it is handed over to the first of them.
+fn unlock_pi(uaddr: &SyscallPtr<u32>, key: FutexKey) -> EResult<i32> {
+	let (tid, pid_ns) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+		(proc.to_local_pid(proc.tid), proc.get_pid_ns().clone())
+	};
+	// The new owner is written as seen from the namespace of the current process
+	let to_local = |pid| pid_ns.to_local(pid).unwrap_or(0) as u32;
+
+	let mut futexes = FUTEXES.lock();
+	let val = read_value(uaddr)?;
+	if val & FUTEX_TID_MASK != tid as u32 {
+		return Err(errno!(EPERM));
+	}
+	let new = match futexes.pi_handoff(&key) {
+		Some((new_owner, true)) => to_local(new_owner) | FUTEX_WAITERS,
+		Some((new_owner, false)) => to_local(new_owner),
+		None => 0,
+	};
+	write_value(uaddr, new)?;
+	Ok(0)
+}
+
+/// Performs the `futex` system call.
+///
+/// `timeout` is either a pointer to the timeout, or an integer, depending on the operation.
+pub fn do_futex<T: TimeUnit + Copy>(
+	uaddr: SyscallPtr<u32>,
+	futex_op: c_int,
+	val: u32,
+	timeout: usize,
+	uaddr2: SyscallPtr<u32>,
+	val3: u32,
+) -> EResult<i32> {
+	let private = futex_op & FUTEX_PRIVATE_FLAG != 0;
+	let clockid = if futex_op & FUTEX_CLOCK_REALTIME != 0 {
+		CLOCK_REALTIME
+	} else {
+		CLOCK_MONOTONIC
+	};
+	let cmd = futex_op & FUTEX_CMD_MASK;
+
+	match cmd {
+		FUTEX_WAIT | FUTEX_WAIT_BITSET => {
+			let bitset = if cmd == FUTEX_WAIT {
+				FUTEX_BITSET_MATCH_ANY
+			} else {
+				val3
+			};
+			if bitset == 0 {
+				return Err(errno!(EINVAL));
+			}
+			let timeout_ptr: SyscallPtr<T> = timeout.into();
+			let timeout = {
+				let proc_mutex = Process::current_assert();
+				let proc = proc_mutex.lock();
+				let mem_space = proc.get_mem_space().unwrap();
+				let mem_space_guard = mem_space.lock();
+				timeout_ptr.get(&mem_space_guard)?.map(|t| *t)
+			};
+			let deadline = match timeout {
+				Some(timeout) => {
+					// If the number of nanoseconds is out of range, the value is not normalized
+					if T::from_nano(timeout.to_nano()) != timeout {
+						return Err(errno!(EINVAL));
+					}
+					// The timeout of `FUTEX_WAIT` is relative
+					let deadline = if cmd == FUTEX_WAIT {
+						clock::current_time(clockid, TimestampScale::Nanosecond)?
+							.saturating_add(timeout.to_nano())
+					} else {
+						timeout.to_nano()
+					};
+					Some((clockid, deadline))
+				}
+				None => None,
+			};
+			let key = get_key(&uaddr, private)?;
+			wait(&uaddr, key, val, bitset, deadline)
+		}
+
+		FUTEX_WAKE | FUTEX_WAKE_BITSET => {
+			let bitset = if cmd == FUTEX_WAKE {
+				FUTEX_BITSET_MATCH_ANY
+			} else {
+				val3
+			};
+			if bitset == 0 {
+				return Err(errno!(EINVAL));
+			}
+			let key = get_key(&uaddr, private)?;
+			let woken = FUTEXES.lock().wake(&key, val as _, bitset);
+			Ok(woken as _)
+		}
+
+		FUTEX_REQUEUE | FUTEX_CMP_REQUEUE => {
+			let wake_count: usize = (val as c_int).try_into().map_err(|_| errno!(EINVAL))?;
+			let requeue_count: usize =
+				(timeout as c_int).try_into().map_err(|_| errno!(EINVAL))?;
+			let key = get_key(&uaddr, private)?;
+			let key2 = get_key(&uaddr2, private)?;
+
+			let mut futexes = FUTEXES.lock();
+			if cmd == FUTEX_CMP_REQUEUE && read_value(&uaddr)? != val3 {
+				return Err(errno!(EAGAIN));
+			}
+			let (woken, requeued) = futexes.requeue(&key, &key2, wake_count, requeue_count)?;
+			Ok((woken + requeued) as _)
+		}
+
+		FUTEX_LOCK_PI | FUTEX_TRYLOCK_PI => {
+			let timeout_ptr: SyscallPtr<T> = timeout.into();
+			let deadline = if cmd == FUTEX_LOCK_PI {
+				let proc_mutex = Process::current_assert();
+				let proc = proc_mutex.lock();
+				let mem_space = proc.get_mem_space().unwrap();
+				let mem_space_guard = mem_space.lock();
+				timeout_ptr.get(&mem_space_guard)?.map(|t| *t)
+			} else {
+				None
+			};
+			// The timeout of `FUTEX_LOCK_PI` is an absolute time of `CLOCK_REALTIME`
+			let deadline = match deadline {
+				Some(deadline) if T::from_nano(deadline.to_nano()) != deadline => {
+					return Err(errno!(EINVAL));
+				}
+				Some(deadline) => Some(deadline.to_nano()),
+				None => None,
+			};
+			let key = get_key(&uaddr, private)?;
+			lock_pi(&uaddr, key, deadline, cmd == FUTEX_TRYLOCK_PI)
+		}
+
+		FUTEX_UNLOCK_PI => {
+			let key = get_key(&uaddr, private)?;
+			unlock_pi(&uaddr, key)
+		}
+
+		_ => Err(errno!(ENOSYS)),
+	}
+}
+
+#[syscall]
+pub fn futex(
+	uaddr: SyscallPtr<u32>,
+	futex_op: c_int,
+	val: u32,
+	timeout: usize,
+	uaddr2: SyscallPtr<u32>,
+	val3: u32,
+) -> Result<i32, Errno> {
+	do_futex::<Timespec32>(uaddr, futex_op, val, timeout, uaddr2, val3)
+}
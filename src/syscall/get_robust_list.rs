@@ -0,0 +1,43 @@
+//! The `get_robust_list` system call returns the list of robust futexes of a process.
+
+use crate::errno::Errno;
+use crate::process::futex::RobustListHead;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::pid::Pid;
+use crate::process::Process;
+use core::mem::size_of;
+use core::ptr;
+use macros::syscall;
+
+#[syscall]
+pub fn get_robust_list(
+	pid: Pid,
+	head_ptr: SyscallPtr<usize>,
+	len_ptr: SyscallPtr<usize>,
+) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	let head = if pid == 0 || pid == proc.to_local_pid(proc.pid) {
+		proc.get_robust_list()
+	} else {
+		let target_mutex = Process::get_by_vpid(pid).ok_or_else(|| errno!(ESRCH))?;
+		let target = target_mutex.lock();
+		if !proc.access_profile.can_kill(&target) {
+			return Err(errno!(EPERM));
+		}
+		target.get_robust_list()
+	};
+	let head = head.map(|head| head.as_ptr()).unwrap_or(ptr::null_mut());
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+	*head_ptr
+		.get_mut(&mut mem_space_guard)?
+		.ok_or_else(|| errno!(EFAULT))? = head as usize;
+	*len_ptr
+		.get_mut(&mut mem_space_guard)?
+		.ok_or_else(|| errno!(EFAULT))? = size_of::<RobustListHead>();
+
+	Ok(0)
+}
@@ -0,0 +1,27 @@
+//! The `get_thread_area` system call returns a TLS entry of the current process.
+
+use super::set_thread_area;
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::user_desc::UserDesc;
+use crate::process::Process;
+use macros::syscall;
+
+#[syscall]
+pub fn get_thread_area(u_info: SyscallPtr<UserDesc>) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let mut proc = proc_mutex.lock();
+
+	let mem_space = proc.get_mem_space().unwrap().clone();
+	let mut mem_space_guard = mem_space.lock();
+
+	let mut info = u_info
+		.get_mut(&mut mem_space_guard)?
+		.ok_or(errno!(EFAULT))?;
+
+	let entry_number = info.get_entry_number();
+	let id = set_thread_area::get_entry_id(entry_number)?;
+	*info = UserDesc::from_descriptor(entry_number, &proc.get_tls_entries()[id]);
+
+	Ok(0)
+}
@@ -17,7 +17,17 @@ pub fn getcwd(buf: SyscallSlice<u8>, size: usize) -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let proc = proc_mutex.lock();
 
-	let cwd = crate::format!("{}", proc.cwd)?;
+	// The working directory is displayed relative to the root of the process
+	let cwd = {
+		let fs = proc.get_fs().lock();
+		if fs.cwd.begins_with(&fs.chroot) {
+			let mut cwd = fs.cwd.range_from(fs.chroot.get_elements_count()..)?;
+			cwd.set_absolute(true);
+			crate::format!("{}", cwd)?
+		} else {
+			crate::format!("{}", fs.cwd)?
+		}
+	};
 
 	// Checking that the buffer is large enough
 	if size < cwd.len() + 1 {
@@ -28,10 +38,10 @@ pub fn getcwd(buf: SyscallSlice<u8>, size: usize) -> Result<i32, Errno> {
 	let mut mem_space_guard = mem_space.lock();
 
 	let cwd_slice = cwd.as_bytes();
-	let buf_slice = buf
+	let mut buf_slice = buf
 		.get_mut(&mut mem_space_guard, size as _)?
 		.ok_or_else(|| errno!(EINVAL))?;
-	util::slice_copy(cwd_slice, buf_slice);
+	util::slice_copy(cwd_slice, &mut buf_slice);
 	buf_slice[cwd.len()] = b'\0';
 
 	Ok(buf.as_ptr() as _)
@@ -2,7 +2,7 @@
 //! directory.
 
 use crate::errno::{EResult, Errno};
-use crate::file::{FileContent, FileType, INode};
+use crate::file::{FileType, INode};
 use crate::process::mem_space::ptr::SyscallSlice;
 use crate::process::Process;
 use core::ffi::c_uint;
@@ -27,7 +27,15 @@ pub trait Dirent: Sized {
 	/// - `inode` is the inode of the entry.
 	/// - `entry_type` is the type of the entry.
 	/// - `name` is the name of the entry.
-	fn write(slice: &mut [u8], off: usize, inode: INode, entry_type: FileType, name: &[u8]);
+	/// - `next` is the offset of the next entry in the directory.
+	fn write(
+		slice: &mut [u8],
+		off: usize,
+		inode: INode,
+		entry_type: FileType,
+		name: &[u8],
+		next: u64,
+	);
 }
 
 /// Performs the getdents system call.
@@ -51,46 +59,50 @@ pub fn do_getdents<E: Dirent>(fd: c_uint, dirp: SyscallSlice<u8>, count: usize)
 	};
 
 	let mut mem_space_guard = mem_space.lock();
-	let dirp_slice = dirp
+	let mut dirp_slice = dirp
 		.get_mut(&mut mem_space_guard, count as _)?
 		.ok_or_else(|| errno!(EFAULT))?;
 
 	let mut open_file = open_file_mutex.lock();
 	let start = open_file.get_offset();
 
+	// The offset in the buffer
 	let mut off = 0;
-	let mut entries_count = 0;
-
+	// The offset of the next entry in the directory
+	let mut next_off = start;
 	{
 		let file_mutex = open_file.get_file();
 		let file = file_mutex.lock();
 
-		let FileContent::Directory(entries) = file.get_content() else {
-			return Err(errno!(ENOTDIR));
-		};
-		// TODO skip entries whose inode cannot fit in struct
-		let entries = entries.iter().skip(start as _);
-
 		// Iterate over entries and fill the buffer
-		for (name, entry) in entries {
+		// TODO skip entries whose inode cannot fit in struct
+		file.iter_entries(start, |name, entry, next| {
 			let len = E::required_length(name);
 			// If the buffer is not large enough, return an error
 			if off == 0 && len > count {
 				return Err(errno!(EINVAL));
 			}
-			// If reaching the end of the buffer, break
+			// If reaching the end of the buffer, stop
 			if off + len > count {
-				break;
+				return Ok(false);
 			}
 
-			E::write(dirp_slice, off, entry.inode, entry.entry_type, name);
+			E::write(
+				&mut dirp_slice,
+				off,
+				entry.inode,
+				entry.entry_type,
+				name,
+				next,
+			);
 
 			off += len;
-			entries_count += 1;
-		}
+			next_off = next;
+			Ok(true)
+		})?;
 	}
 
-	open_file.set_offset(start + entries_count);
+	open_file.set_offset(next_off);
 	Ok(off as _)
 }
 
@@ -117,11 +129,18 @@ impl Dirent for LinuxDirent {
 			.next_multiple_of(size_of::<usize>())
 	}
 
-	fn write(slice: &mut [u8], off: usize, inode: INode, entry_type: FileType, name: &[u8]) {
+	fn write(
+		slice: &mut [u8],
+		off: usize,
+		inode: INode,
+		entry_type: FileType,
+		name: &[u8],
+		next: u64,
+	) {
 		let len = Self::required_length(name);
 		let ent = Self {
 			d_ino: inode as _,
-			d_off: (off + len) as _,
+			d_off: next as _,
 			d_reclen: len as _,
 			d_name: [],
 		};
@@ -32,11 +32,18 @@ impl Dirent for LinuxDirent64 {
 			.next_multiple_of(size_of::<usize>())
 	}
 
-	fn write(slice: &mut [u8], off: usize, inode: INode, entry_type: FileType, name: &[u8]) {
+	fn write(
+		slice: &mut [u8],
+		off: usize,
+		inode: INode,
+		entry_type: FileType,
+		name: &[u8],
+		next: u64,
+	) {
 		let len = Self::required_length(name);
 		let ent = Self {
 			d_ino: inode,
-			d_off: (off + len) as _,
+			d_off: next,
 			d_reclen: len as _,
 			d_type: entry_type.to_dirent_type(),
 			d_name: [],
@@ -12,18 +12,13 @@ pub fn getpgid(pid: Pid) -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let proc = proc_mutex.lock();
 
-	if pid == 0 {
-		Ok(proc.pgid as _)
+	let pgid = if pid == 0 {
+		proc.pgid
 	} else {
-		let proc_mutex = {
-			if let Some(proc) = Process::get_by_pid(pid) {
-				proc
-			} else {
-				return Err(errno!(ESRCH));
-			}
-		};
-		let proc = proc_mutex.lock();
+		let target_mutex = Process::get_by_vpid(pid).ok_or_else(|| errno!(ESRCH))?;
+		let target = target_mutex.lock();
 
-		Ok(proc.pgid as _)
-	}
+		target.pgid
+	};
+	Ok(proc.to_local_pid(pgid) as _)
 }
@@ -1,4 +1,5 @@
-//! The `getpid` system call returns the PID of the current process.
+//! The `getpid` system call returns the PID of the current process, which is the ID of its
+//! thread group.
 
 use crate::errno::Errno;
 use crate::process::Process;
@@ -9,5 +10,5 @@ pub fn getpid() -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let proc = proc_mutex.lock();
 
-	Ok(proc.pid as _)
+	Ok(proc.to_local_pid(proc.tgid) as _)
 }
@@ -9,5 +9,5 @@ pub fn getppid() -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let proc = proc_mutex.lock();
 
-	Ok(proc.get_parent_pid() as _)
+	Ok(proc.to_local_pid(proc.get_parent_pid()) as _)
 }
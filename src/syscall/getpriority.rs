@@ -0,0 +1,18 @@
+//! The `getpriority` system call returns the nice value of processes.
+
+use super::setpriority;
+use crate::errno::Errno;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall]
+pub fn getpriority(which: c_int, who: c_int) -> Result<i32, Errno> {
+	// The highest priority among the processes
+	let nice = setpriority::get_targets(which, who)?
+		.iter()
+		.map(|proc_mutex| proc_mutex.lock().get_nice())
+		.min()
+		.unwrap_or(0);
+	// The value is offset to avoid negative values, which would be interpreted as errors
+	Ok(20 - nice)
+}
@@ -3,45 +3,55 @@
 use crate::crypto::rand;
 use crate::errno::Errno;
 use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::scheduler;
 use crate::process::Process;
+use core::cmp::min;
 use core::ffi::c_uint;
 use macros::syscall;
 
-/// If set, bytes are draw from the random source instead of urandom.
-const GRND_RANDOM: u32 = 2;
-/// If set, the function doesn't block. If no entropy is available, the function
-/// returns EAGAIN.
+/// If set, the function doesn't block. If the entropy pool is not ready, the function returns
+/// `EAGAIN`.
 const GRND_NONBLOCK: u32 = 1;
+/// If set, bytes are drawn from the random source instead of urandom. Both sources being the
+/// same generator, this only makes the function block until the entropy pool is ready.
+const GRND_RANDOM: u32 = 2;
+/// If set, the function doesn't wait for the entropy pool to be ready.
+const GRND_INSECURE: u32 = 4;
+
+/// The maximum number of bytes returned by one call.
+const MAX_LEN: usize = 33554431;
 
 #[syscall]
 pub fn getrandom(buf: SyscallSlice<u8>, buflen: usize, flags: c_uint) -> Result<i32, Errno> {
-	let bypass_threshold = flags & GRND_RANDOM == 0;
-	let nonblock = flags & GRND_NONBLOCK != 0;
-
-	let mut pool_guard = rand::ENTROPY_POOL.lock();
-	let Some(pool) = &mut *pool_guard else {
-		return Ok(0);
-	};
-
-	if nonblock && buflen > pool.available_bytes() {
-		return Err(errno!(EAGAIN));
+	if flags & !(GRND_NONBLOCK | GRND_RANDOM | GRND_INSECURE) != 0 {
+		return Err(errno!(EINVAL));
+	}
+	if flags & GRND_RANDOM != 0 && flags & GRND_INSECURE != 0 {
+		return Err(errno!(EINVAL));
+	}
+	let len = min(buflen, MAX_LEN);
+
+	// Wait for the entropy pool to be ready
+	if flags & GRND_INSECURE == 0 {
+		while !rand::is_ready() {
+			if flags & GRND_NONBLOCK != 0 {
+				return Err(errno!(EAGAIN));
+			}
+			super::util::signal_check()?;
+			scheduler::end_tick();
+		}
 	}
 
-	// Getting current process
 	let proc_mutex = Process::current_assert();
 	let proc = proc_mutex.lock();
 
 	let mem_space_mutex = proc.get_mem_space().unwrap();
 	let mut mem_space_guard = mem_space_mutex.lock();
 
-	if let Some(buf) = buf.get_mut(&mut mem_space_guard, buflen)? {
-		let mut i = 0;
-		while i < buf.len() {
-			i += pool.read(&mut buf[i..], bypass_threshold);
-		}
+	let mut buf = buf
+		.get_mut(&mut mem_space_guard, len)?
+		.ok_or_else(|| errno!(EFAULT))?;
+	rand::fill(&mut buf);
 
-		Ok(buf.len() as _)
-	} else {
-		Ok(0)
-	}
+	Ok(len as _)
 }
@@ -0,0 +1,28 @@
+//! The `getresgid` syscall returns the real, effective and saved GIDs of the process's owner.
+
+use crate::errno::Errno;
+use crate::file::perm::Gid;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::Process;
+use macros::syscall;
+
+#[syscall]
+pub fn getresgid(
+	rgid: SyscallPtr<Gid>,
+	egid: SyscallPtr<Gid>,
+	sgid: SyscallPtr<Gid>,
+) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	let ap = &proc.access_profile;
+	let ids = [ap.get_gid(), ap.get_egid(), ap.get_sgid()];
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+	for (ptr, id) in [rgid, egid, sgid].into_iter().zip(ids) {
+		*ptr.get_mut(&mut mem_space_guard)?
+			.ok_or_else(|| errno!(EFAULT))? = id as _;
+	}
+	Ok(0)
+}
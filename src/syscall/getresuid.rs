@@ -0,0 +1,28 @@
+//! The `getresuid` syscall returns the real, effective and saved UIDs of the process's owner.
+
+use crate::errno::Errno;
+use crate::file::perm::Uid;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::Process;
+use macros::syscall;
+
+#[syscall]
+pub fn getresuid(
+	ruid: SyscallPtr<Uid>,
+	euid: SyscallPtr<Uid>,
+	suid: SyscallPtr<Uid>,
+) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	let ap = &proc.access_profile;
+	let ids = [ap.get_uid(), ap.get_euid(), ap.get_suid()];
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+	for (ptr, id) in [ruid, euid, suid].into_iter().zip(ids) {
+		*ptr.get_mut(&mut mem_space_guard)?
+			.ok_or_else(|| errno!(EFAULT))? = id as _;
+	}
+	Ok(0)
+}
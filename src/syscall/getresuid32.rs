@@ -0,0 +1,27 @@
+//! The `getresuid32` syscall returns the real, effective and saved UIDs of the process's owner.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::Process;
+use macros::syscall;
+
+#[syscall]
+pub fn getresuid32(
+	ruid: SyscallPtr<u32>,
+	euid: SyscallPtr<u32>,
+	suid: SyscallPtr<u32>,
+) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	let ap = &proc.access_profile;
+	let ids = [ap.get_uid(), ap.get_euid(), ap.get_suid()];
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+	for (ptr, id) in [ruid, euid, suid].into_iter().zip(ids) {
+		*ptr.get_mut(&mut mem_space_guard)?
+			.ok_or_else(|| errno!(EFAULT))? = id as _;
+	}
+	Ok(0)
+}
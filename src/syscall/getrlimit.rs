@@ -0,0 +1,51 @@
+//! The `getrlimit` system call returns the limit for a given resource, using the legacy 32 bits
+//! structure.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::rlimit;
+use crate::process::rlimit::RLim;
+use crate::process::rlimit::RLimit32;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Performs the `getrlimit` operation.
+///
+/// Arguments:
+/// - `resource` is the resource to get the limit for
+/// - `rlim` is the pointer to write the limit to
+/// - `infinity` is the value to write for limits which cannot be represented on 32 bits
+pub fn do_getrlimit(
+	resource: c_int,
+	rlim: SyscallPtr<RLimit32>,
+	infinity: u32,
+) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+	let limit = proc.rlimits.get(resource)?;
+
+	let to_32 = |val: RLim| {
+		if val == rlimit::RLIM_INFINITY || val > infinity as RLim {
+			infinity
+		} else {
+			val as u32
+		}
+	};
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+	*rlim
+		.get_mut(&mut mem_space_guard)?
+		.ok_or_else(|| errno!(EFAULT))? = RLimit32 {
+		rlim_cur: to_32(limit.rlim_cur),
+		rlim_max: to_32(limit.rlim_max),
+	};
+
+	Ok(0)
+}
+
+#[syscall]
+pub fn getrlimit(resource: c_int, rlim: SyscallPtr<RLimit32>) -> Result<i32, Errno> {
+	// The legacy interface represents infinity as the maximum signed value
+	do_getrlimit(resource, rlim, i32::MAX as _)
+}
@@ -6,6 +6,8 @@ use crate::errno::Errno;
 use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::rusage::RUsage;
 use crate::process::Process;
+use crate::time::unit::TimeUnit;
+use crate::time::unit::Timeval;
 use core::ffi::c_int;
 use macros::syscall;
 
@@ -25,8 +27,13 @@ pub fn getrusage(who: c_int, usage: SyscallPtr<RUsage>) -> Result<i32, Errno> {
 		RUSAGE_SELF => proc.get_rusage().clone(),
 
 		RUSAGE_CHILDREN => {
-			// TODO Return resources of terminates children
-			RUsage::default()
+			// TODO Return other resources of terminated children
+			let times = proc.get_cpu_times();
+			RUsage {
+				ru_utime: Timeval::from_nano(times.cutime),
+				ru_stime: Timeval::from_nano(times.cstime),
+				..Default::default()
+			}
 		}
 
 		_ => return Err(errno!(EINVAL)),
@@ -35,7 +42,7 @@ pub fn getrusage(who: c_int, usage: SyscallPtr<RUsage>) -> Result<i32, Errno> {
 	let mem_space = proc.get_mem_space().unwrap();
 	let mut mem_space_guard = mem_space.lock();
 
-	let usage_val = usage
+	let mut usage_val = usage
 		.get_mut(&mut mem_space_guard)?
 		.ok_or_else(|| errno!(EFAULT))?;
 	*usage_val = rusage;
@@ -0,0 +1,23 @@
+//! The `getsid` system call returns the ID of the session of a process.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::process::pid::Pid;
+use crate::process::Process;
+use macros::syscall;
+
+#[syscall]
+pub fn getsid(pid: Pid) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	let sid = if pid == 0 || pid == proc.to_local_pid(proc.pid) {
+		proc.sid
+	} else {
+		let target_mutex = Process::get_by_vpid(pid).ok_or_else(|| errno!(ESRCH))?;
+		let target = target_mutex.lock();
+
+		target.sid
+	};
+	Ok(proc.to_local_pid(sid) as _)
+}
@@ -49,13 +49,13 @@ pub fn getsockname(
 	let addrlen_val = *addrlen_val as usize;
 
 	// Read socket name
-	let addr_slice = addr
+	let mut addr_slice = addr
 		.get_mut(&mut mem_space_guard, addrlen_val)?
 		.ok_or(errno!(EFAULT))?;
-	let len = sock.read_sockname(addr_slice) as _;
+	let len = sock.read_sockname(&mut addr_slice) as _;
 
 	// Update actual length of the address
-	let addrlen_val = addrlen
+	let mut addrlen_val = addrlen
 		.get_mut(&mut mem_space_guard)?
 		.ok_or(errno!(EFAULT))?;
 	*addrlen_val = len;
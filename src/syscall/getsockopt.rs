@@ -40,9 +40,9 @@ pub fn getsockopt(
 	// Get optval slice
 	let mem_space = proc.get_mem_space().unwrap();
 	let mut mem_space_guard = mem_space.lock();
-	let optval_slice = optval
+	let mut optval_slice = optval
 		.get_mut(&mut mem_space_guard, optlen)?
 		.ok_or(errno!(EFAULT))?;
 
-	sock.get_opt(level, optname, optval_slice)
+	sock.get_opt(level, optname, &mut optval_slice)
 }
@@ -9,5 +9,5 @@ pub fn gettid() -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let proc = proc_mutex.lock();
 
-	Ok(proc.tid as _)
+	Ok(proc.to_local_pid(proc.tid) as _)
 }
@@ -0,0 +1,77 @@
+//! The `getxattr` system call returns the value of an extended attribute of a file.
+
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::Process;
+use crate::util;
+use crate::util::container::string::String;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Performs the `*getxattr` system calls.
+///
+/// Arguments:
+/// - `fd` is the file descriptor of the file, used if `pathname` is `None`.
+/// - `pathname` is the path to the file.
+/// - `follow_links` tells whether symbolic links are followed.
+/// - `name` is the name of the attribute.
+/// - `value` is the buffer to write the value to, of size `size`.
+///
+/// If `size` is zero, the function only returns the size of the value.
+pub fn do_getxattr(
+	fd: c_int,
+	pathname: Option<SyscallString>,
+	follow_links: bool,
+	name: SyscallString,
+	value: SyscallSlice<u8>,
+	size: usize,
+) -> EResult<i32> {
+	let (name, ap) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let mem_space = proc.get_mem_space().unwrap();
+		let mem_space_guard = mem_space.lock();
+
+		let name = name.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
+		(String::try_from(&*name)?, proc.access_profile)
+	};
+
+	let val = {
+		let file_mutex = super::util::get_file_from_path_or_fd(fd, pathname, follow_links)?;
+		let file = file_mutex.lock();
+		file.get_xattr(&ap, name.as_bytes())?
+	};
+	if size == 0 {
+		return Ok(val.len() as _);
+	}
+	if size < val.len() {
+		return Err(errno!(ERANGE));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+
+	let mut buf = value
+		.get_mut(&mut mem_space_guard, val.len())?
+		.ok_or_else(|| errno!(EFAULT))?;
+	util::slice_copy(&val, &mut buf);
+
+	Ok(val.len() as _)
+}
+
+#[syscall]
+pub fn getxattr(
+	pathname: SyscallString,
+	name: SyscallString,
+	value: SyscallSlice<u8>,
+	size: usize,
+) -> Result<i32, Errno> {
+	do_getxattr(-1, Some(pathname), true, name, value, size)
+}
@@ -2,6 +2,7 @@
 
 use crate::errno;
 use crate::errno::Errno;
+use crate::file::perm::CAP_SYS_MODULE;
 use crate::module;
 use crate::module::Module;
 use crate::process::mem_space::ptr::SyscallSlice;
@@ -20,7 +21,7 @@ pub fn init_module(
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
 
-		if !proc.access_profile.is_privileged() {
+		if !proc.access_profile.has_cap(CAP_SYS_MODULE) {
 			return Err(errno!(EPERM));
 		}
 
@@ -30,7 +31,7 @@ pub fn init_module(
 			.get(&mem_space_guard, len as usize)?
 			.ok_or_else(|| errno!(EFAULT))?;
 
-		Module::load(image)?
+		Module::load(&image)?
 	};
 
 	if !module::is_loaded(module.get_name()) {
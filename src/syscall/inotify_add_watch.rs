@@ -0,0 +1,72 @@
+//! The `inotify_add_watch` system call adds a watch to an inotify instance, or modifies an
+//! existing one.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::buffer::inotify;
+use crate::file::FileType;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall]
+pub fn inotify_add_watch(fd: c_int, pathname: SyscallString, mask: u32) -> Result<i32, Errno> {
+	if mask & inotify::IN_ALL_EVENTS == 0 {
+		return Err(errno!(EINVAL));
+	}
+	if mask & inotify::IN_MASK_ADD != 0 && mask & inotify::IN_MASK_CREATE != 0 {
+		return Err(errno!(EINVAL));
+	}
+	if fd < 0 {
+		return Err(errno!(EBADF));
+	}
+
+	let (inotify_mutex, file_mutex, ap) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let inotify_mutex = {
+			let fds_mutex = proc.get_fds().unwrap();
+			let fds = fds_mutex.lock();
+			let open_file_mutex = fds
+				.get_fd(fd as _)
+				.ok_or_else(|| errno!(EBADF))?
+				.get_open_file();
+			let open_file = open_file_mutex.lock();
+			inotify::get(open_file.get_location()).ok_or_else(|| errno!(EINVAL))?
+		};
+
+		let ap = proc.access_profile;
+
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let mem_space_guard = mem_space.lock();
+
+		let pathname = pathname
+			.get(&mem_space_guard)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		let flags = if mask & inotify::IN_DONT_FOLLOW != 0 {
+			super::access::AT_SYMLINK_NOFOLLOW
+		} else {
+			0
+		};
+		let file_mutex =
+			super::util::get_file_at(proc, super::access::AT_FDCWD, &pathname, true, flags)?;
+
+		(inotify_mutex, file_mutex, ap)
+	};
+
+	let location = {
+		let file = file_mutex.lock();
+		if !ap.can_read_file(&file) {
+			return Err(errno!(EACCES));
+		}
+		if mask & inotify::IN_ONLYDIR != 0 && file.get_type() != FileType::Directory {
+			return Err(errno!(ENOTDIR));
+		}
+		file.get_location().clone()
+	};
+
+	let wd = inotify_mutex.lock().add_watch(location, mask)?;
+	Ok(wd as _)
+}
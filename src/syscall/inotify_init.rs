@@ -0,0 +1,10 @@
+//! The `inotify_init` system call creates an inotify instance and returns a file descriptor to
+//! it.
+
+use crate::errno::Errno;
+use macros::syscall;
+
+#[syscall]
+pub fn inotify_init() -> Result<i32, Errno> {
+	super::inotify_init1::do_inotify_init1(0)
+}
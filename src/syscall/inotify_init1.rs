@@ -0,0 +1,51 @@
+//! The `inotify_init1` system call creates an inotify instance and returns a file descriptor to
+//! it.
+
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::buffer::inotify;
+use crate::file::fd::FD_CLOEXEC;
+use crate::file::open_file;
+use crate::file::open_file::OpenFile;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Set the close-on-exec flag on the new file descriptor.
+const IN_CLOEXEC: c_int = open_file::O_CLOEXEC;
+/// Make reading the file descriptor non-blocking.
+const IN_NONBLOCK: c_int = open_file::O_NONBLOCK;
+
+/// Performs the `inotify_init*` system calls.
+pub fn do_inotify_init1(flags: c_int) -> EResult<i32> {
+	if flags & !(IN_CLOEXEC | IN_NONBLOCK) != 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	let (fds_mutex, access_profile) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let fds_mutex = proc.get_fds().unwrap().clone();
+		(fds_mutex, proc.access_profile)
+	};
+
+	let file = inotify::create(&access_profile)?;
+	let open_file = OpenFile::new(file, open_file::O_RDONLY | (flags & IN_NONBLOCK))?;
+
+	let fd_flags = if flags & IN_CLOEXEC != 0 {
+		FD_CLOEXEC
+	} else {
+		0
+	};
+	let mut fds = fds_mutex.lock();
+	let fd = fds.create_fd(fd_flags, open_file)?;
+
+	Ok(fd.get_id() as _)
+}
+
+#[syscall]
+pub fn inotify_init1(flags: c_int) -> Result<i32, Errno> {
+	do_inotify_init1(flags)
+}
@@ -0,0 +1,32 @@
+//! The `inotify_rm_watch` system call removes a watch from an inotify instance.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::buffer::inotify;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall]
+pub fn inotify_rm_watch(fd: c_int, wd: c_int) -> Result<i32, Errno> {
+	if fd < 0 {
+		return Err(errno!(EBADF));
+	}
+
+	let inotify_mutex = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let fds_mutex = proc.get_fds().unwrap();
+		let fds = fds_mutex.lock();
+		let open_file_mutex = fds
+			.get_fd(fd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file();
+		let open_file = open_file_mutex.lock();
+		inotify::get(open_file.get_location()).ok_or_else(|| errno!(EINVAL))?
+	};
+
+	inotify_mutex.lock().remove_watch(wd)?;
+	Ok(0)
+}
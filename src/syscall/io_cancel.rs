@@ -0,0 +1,28 @@
+//! The `io_cancel` system call cancels an operation submitted to an AIO context.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::aio::AioContextId;
+use crate::file::aio::IoEvent;
+use crate::file::aio::Iocb;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::Process;
+use macros::syscall;
+
+#[syscall]
+pub fn io_cancel(
+	ctx_id: AioContextId,
+	_iocb: SyscallPtr<Iocb>,
+	_result: SyscallPtr<IoEvent>,
+) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	if proc.get_aio_contexts().lock().get(&ctx_id).is_none() {
+		return Err(errno!(EINVAL));
+	}
+
+	// Operations are executed on submission, so they are always complete and cannot be
+	// cancelled anymore
+	Err(errno!(EINVAL))
+}
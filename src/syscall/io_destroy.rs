@@ -0,0 +1,25 @@
+//! The `io_destroy` system call destroys an AIO context.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::aio;
+use crate::file::aio::AioContextId;
+use crate::process::Process;
+use macros::syscall;
+
+#[syscall]
+pub fn io_destroy(ctx_id: AioContextId) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	proc.get_aio_contexts()
+		.lock()
+		.remove(&ctx_id)
+		.ok_or_else(|| errno!(EINVAL))?;
+
+	// Operations are executed on submission, so there is no operation to wait for
+	let mem_space = proc.get_mem_space().unwrap();
+	aio::destroy(&mut mem_space.lock(), ctx_id)?;
+
+	Ok(0)
+}
@@ -0,0 +1,81 @@
+//! The `io_getevents` system call reaps the completion events of an AIO context.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::aio::AioContextId;
+use crate::file::aio::IoEvent;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::scheduler;
+use crate::process::Process;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::unit::Timespec;
+use core::cmp::min;
+use core::ffi::c_long;
+use macros::syscall;
+
+#[syscall]
+pub fn io_getevents(
+	ctx_id: AioContextId,
+	min_nr: c_long,
+	nr: c_long,
+	events: SyscallSlice<IoEvent>,
+	timeout: SyscallPtr<Timespec>,
+) -> Result<i32, Errno> {
+	if min_nr < 0 || nr < 0 || min_nr > nr {
+		return Err(errno!(EINVAL));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let (mem_space, ctx_mutex, timeout) = {
+		let proc = proc_mutex.lock();
+
+		let ctx_mutex = proc
+			.get_aio_contexts()
+			.lock()
+			.get(&ctx_id)
+			.cloned()
+			.ok_or_else(|| errno!(EINVAL))?;
+
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let timeout = timeout.get(&mem_space.lock())?.map(|t| *t);
+
+		(mem_space, ctx_mutex, timeout)
+	};
+	// The time at which the system call times out. If `None`, it never does
+	let end = timeout
+		.map(|timeout| -> Result<_, Errno> {
+			let now = clock::current_time_struct::<Timespec>(CLOCK_MONOTONIC)?;
+			Ok(now + timeout)
+		})
+		.transpose()?;
+
+	loop {
+		super::util::signal_check()?;
+
+		{
+			let timed_out = end
+				.map(|end| -> Result<_, Errno> {
+					let now = clock::current_time_struct::<Timespec>(CLOCK_MONOTONIC)?;
+					Ok(now >= end)
+				})
+				.transpose()?
+				.unwrap_or(false);
+
+			let mut ctx = ctx_mutex.lock();
+			let count = min(ctx.get_events_count(), nr as usize);
+			if count >= min_nr as usize || timed_out {
+				let mut mem_space_guard = mem_space.lock();
+				let mut events = events
+					.get_mut(&mut mem_space_guard, count)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				ctx.pop_events(&mut events);
+				return Ok(count as _);
+			}
+		}
+
+		// Events are pushed by other processes sharing the context
+		scheduler::end_tick();
+	}
+}
@@ -0,0 +1,47 @@
+//! The `io_setup` system call creates an AIO context.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::aio;
+use crate::file::aio::AioContextId;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::Process;
+use core::ffi::c_uint;
+use macros::syscall;
+
+#[syscall]
+pub fn io_setup(nr_events: c_uint, ctx_idp: SyscallPtr<AioContextId>) -> Result<i32, Errno> {
+	if nr_events == 0 {
+		return Err(errno!(EINVAL));
+	}
+	if nr_events > aio::MAX_EVENTS {
+		return Err(errno!(EAGAIN));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	let mem_space = proc.get_mem_space().unwrap().clone();
+	let mut mem_space_guard = mem_space.lock();
+
+	// The context ID must be initialized to zero
+	let ctx_id = ctx_idp
+		.get(&mem_space_guard)?
+		.ok_or_else(|| errno!(EFAULT))?;
+	if *ctx_id != 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	let (id, ctx) = aio::create(&mut mem_space_guard, nr_events)?;
+	if let Err(e) = proc.get_aio_contexts().lock().insert(id, ctx) {
+		let _ = aio::destroy(&mut mem_space_guard, id);
+		return Err(e.into());
+	}
+
+	let mut ctx_id = ctx_idp
+		.get_mut(&mut mem_space_guard)?
+		.ok_or_else(|| errno!(EFAULT))?;
+	*ctx_id = id;
+
+	Ok(0)
+}
@@ -0,0 +1,184 @@
+//! The `io_submit` system call submits operations to an AIO context.
+
+use super::readv;
+use super::writev;
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::aio;
+use crate::file::aio::AioContext;
+use crate::file::aio::AioContextId;
+use crate::file::aio::IoEvent;
+use crate::file::aio::Iocb;
+use crate::file::writeback;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::MemSpace;
+use crate::process::Process;
+use crate::util::container::vec::Vec;
+use crate::util::lock::IntMutex;
+use crate::util::lock::Mutex;
+use core::cmp::min;
+use core::ffi::c_long;
+use macros::syscall;
+
+/// Executes the operation described by the control block `iocb`.
+///
+/// Arguments:
+/// - `proc` is the current process.
+/// - `mem_space` is the memory space of the current process.
+///
+/// If the operation cannot be submitted, the function returns an error. Otherwise, it returns
+/// the result of the operation.
+fn execute(proc: &IntMutex<Process>, mem_space: &IntMutex<MemSpace>, iocb: &Iocb) -> EResult<i64> {
+	if !matches!(
+		iocb.aio_lio_opcode,
+		aio::IOCB_CMD_PREAD
+			| aio::IOCB_CMD_PWRITE
+			| aio::IOCB_CMD_FSYNC
+			| aio::IOCB_CMD_FDSYNC
+			| aio::IOCB_CMD_PREADV
+			| aio::IOCB_CMD_PWRITEV
+	) {
+		return Err(errno!(EINVAL));
+	}
+
+	let open_file_mutex = {
+		let proc = proc.lock();
+		let fds_mutex = proc.get_fds().unwrap();
+		let fds = fds_mutex.lock();
+		fds.get_fd(iocb.aio_fildes)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file()
+			.clone()
+	};
+
+	// From here, errors are reported in the completion event
+	let offset = iocb.aio_offset;
+	let len = min(iocb.aio_nbytes, i32::MAX as u64) as usize;
+	let res = match iocb.aio_lio_opcode {
+		aio::IOCB_CMD_FSYNC | aio::IOCB_CMD_FDSYNC => {
+			let file_mutex = open_file_mutex.lock().get_file().clone();
+			writeback::sync_file(&file_mutex).map(|_| 0)
+		}
+
+		_ if offset < 0 => Err(errno!(EINVAL)),
+
+		aio::IOCB_CMD_PREAD | aio::IOCB_CMD_PWRITE => aio::read_write(
+			mem_space,
+			&open_file_mutex,
+			SyscallSlice::from(iocb.aio_buf as usize),
+			len,
+			offset as _,
+			iocb.aio_lio_opcode == aio::IOCB_CMD_PWRITE,
+		),
+
+		aio::IOCB_CMD_PREADV => readv::readv_file(
+			proc,
+			mem_space,
+			&open_file_mutex,
+			SyscallSlice::from(iocb.aio_buf as usize),
+			len as _,
+			Some(offset as _),
+			iocb.aio_rw_flags as _,
+		)
+		.map(|len| len as _),
+
+		aio::IOCB_CMD_PWRITEV => writev::writev_file(
+			proc,
+			mem_space,
+			&open_file_mutex,
+			SyscallSlice::from(iocb.aio_buf as usize),
+			len as _,
+			Some(offset as _),
+			iocb.aio_rw_flags as _,
+		)
+		.map(|len| len as _),
+
+		_ => unreachable!(),
+	};
+	Ok(res.unwrap_or_else(|e| -e.as_int() as i64))
+}
+
+/// Submits the operation whose control block is at address `addr` in userspace.
+///
+/// Arguments:
+/// - `proc` is the current process.
+/// - `mem_space` is the memory space of the current process.
+/// - `ctx` is the context to submit to.
+fn submit(
+	proc: &IntMutex<Process>,
+	mem_space: &IntMutex<MemSpace>,
+	ctx: &Mutex<AioContext>,
+	addr: usize,
+) -> EResult<()> {
+	let iocb = {
+		let mem_space_guard = mem_space.lock();
+		let iocb_ptr: SyscallPtr<Iocb> = addr.into();
+		*iocb_ptr
+			.get(&mem_space_guard)?
+			.ok_or_else(|| errno!(EFAULT))?
+	};
+	// Notification through an eventfd is not supported
+	if iocb.aio_reserved2 != 0 || iocb.aio_flags != 0 {
+		return Err(errno!(EINVAL));
+	}
+	if ctx.lock().is_full() {
+		return Err(errno!(EAGAIN));
+	}
+
+	let res = execute(proc, mem_space, &iocb)?;
+	ctx.lock().push_event(IoEvent {
+		data: iocb.aio_data,
+		obj: addr as _,
+		res,
+		res2: 0,
+	})?;
+	Ok(())
+}
+
+#[syscall]
+pub fn io_submit(
+	ctx_id: AioContextId,
+	nr: c_long,
+	iocbpp: SyscallSlice<usize>,
+) -> Result<i32, Errno> {
+	if nr < 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let (mem_space, ctx_mutex, iocbs) = {
+		let proc = proc_mutex.lock();
+
+		let ctx_mutex = proc
+			.get_aio_contexts()
+			.lock()
+			.get(&ctx_id)
+			.cloned()
+			.ok_or_else(|| errno!(EINVAL))?;
+
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let mem_space_guard = mem_space.lock();
+		let iocbpp = iocbpp
+			.get(&mem_space_guard, nr as _)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		let mut iocbs = Vec::new();
+		iocbs.extend_from_slice(&iocbpp)?;
+		drop(mem_space_guard);
+
+		(mem_space, ctx_mutex, iocbs)
+	};
+
+	// If an operation cannot be submitted, the error is returned only if it is the first
+	let mut submitted = 0;
+	for addr in iocbs {
+		match submit(&proc_mutex, &mem_space, &ctx_mutex, addr) {
+			Ok(()) => submitted += 1,
+			Err(e) if submitted == 0 => return Err(e),
+			Err(_) => break,
+		}
+	}
+
+	Ok(submitted)
+}
@@ -0,0 +1,184 @@
+//! The `io_uring_enter` system call submits the operations placed on the submission queue of an
+//! io_uring instance, and waits for their completion.
+//!
+//! Operations are executed synchronously, in the context of the calling process.
+
+use super::readv;
+use super::writev;
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::buffer::io_uring;
+use crate::file::buffer::io_uring::IoUring;
+use crate::file::buffer::io_uring::Sqe;
+use crate::file::buffer::Buffer;
+use crate::file::open_file::OpenFile;
+use crate::file::writeback;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::MemSpace;
+use crate::process::scheduler;
+use crate::process::Process;
+use crate::util::io;
+use crate::util::lock::IntMutex;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use core::cmp::min;
+use core::ffi::c_int;
+use core::ffi::c_void;
+use macros::syscall;
+
+/// Wait for `min_complete` completions.
+const IORING_ENTER_GETEVENTS: u32 = 1 << 0;
+
+/// Returns the open file an operation is performed on.
+///
+/// Arguments:
+/// - `proc` is the current process.
+/// - `io_uring` is the instance.
+/// - `sqe` is the submission.
+fn get_open_file(
+	proc: &IntMutex<Process>,
+	io_uring: &Mutex<IoUring>,
+	sqe: &Sqe,
+) -> EResult<Arc<Mutex<OpenFile>>> {
+	let fd: usize = sqe.fd.try_into().map_err(|_| errno!(EBADF))?;
+	if sqe.flags & io_uring::IOSQE_FIXED_FILE != 0 {
+		return io_uring.lock().get_file(fd).ok_or_else(|| errno!(EBADF));
+	}
+
+	let proc = proc.lock();
+	let fds_mutex = proc.get_fds().unwrap();
+	let fds = fds_mutex.lock();
+	Ok(fds
+		.get_fd(fd as _)
+		.ok_or_else(|| errno!(EBADF))?
+		.get_open_file()
+		.clone())
+}
+
+/// Executes the operation of the submission `sqe`.
+///
+/// Arguments:
+/// - `proc` is the current process.
+/// - `mem_space` is the memory space of the current process.
+/// - `io_uring` is the instance.
+///
+/// The function returns the result of the operation.
+fn execute(
+	proc: &IntMutex<Process>,
+	mem_space: &IntMutex<MemSpace>,
+	io_uring: &Mutex<IoUring>,
+	sqe: &Sqe,
+) -> EResult<i32> {
+	if sqe.flags & !io_uring::IOSQE_FIXED_FILE != 0 {
+		return Err(errno!(EINVAL));
+	}
+	if sqe.opcode == io_uring::IORING_OP_NOP {
+		return Ok(0);
+	}
+	if sqe.opcode > io_uring::IORING_OP_LAST {
+		return Err(errno!(EINVAL));
+	}
+
+	let open_file_mutex = get_open_file(proc, io_uring, sqe)?;
+	let iov = SyscallSlice::from(sqe.addr as usize);
+	// An offset of `-1` means the current offset of the file
+	let offset = Some(sqe.off as i64 as _);
+	match sqe.opcode {
+		io_uring::IORING_OP_READV => readv::readv_file(
+			proc,
+			mem_space,
+			&open_file_mutex,
+			iov,
+			sqe.len as _,
+			offset,
+			sqe.op_flags as _,
+		),
+
+		io_uring::IORING_OP_WRITEV => writev::writev_file(
+			proc,
+			mem_space,
+			&open_file_mutex,
+			iov,
+			sqe.len as _,
+			offset,
+			sqe.op_flags as _,
+		),
+
+		io_uring::IORING_OP_FSYNC => {
+			let file_mutex = open_file_mutex.lock().get_file().clone();
+			writeback::sync_file(&file_mutex)?;
+			Ok(0)
+		}
+
+		_ => unreachable!(),
+	}
+}
+
+#[syscall]
+pub fn io_uring_enter(
+	fd: c_int,
+	to_submit: u32,
+	min_complete: u32,
+	flags: u32,
+	_sig: *const c_void,
+) -> Result<i32, Errno> {
+	if flags & !IORING_ENTER_GETEVENTS != 0 {
+		return Err(errno!(EINVAL));
+	}
+	if fd < 0 {
+		return Err(errno!(EBADF));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let (mem_space, io_uring_mutex) = {
+		let proc = proc_mutex.lock();
+
+		let mem_space = proc.get_mem_space().unwrap().clone();
+
+		let fds_mutex = proc.get_fds().unwrap();
+		let fds = fds_mutex.lock();
+		let open_file_mutex = fds
+			.get_fd(fd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file();
+		let open_file = open_file_mutex.lock();
+		let io_uring_mutex =
+			io_uring::get(open_file.get_location()).ok_or_else(|| errno!(EOPNOTSUPP))?;
+		(mem_space, io_uring_mutex)
+	};
+
+	let mut submitted = 0;
+	while submitted < to_submit {
+		// The instance is not locked while the operation is executed, since it may block
+		let Some(sqe) = io_uring_mutex.lock().pop_sqe() else {
+			break;
+		};
+		let res = execute(&proc_mutex, &mem_space, &io_uring_mutex, &sqe)
+			.unwrap_or_else(|e| -e.as_int());
+		io_uring_mutex.lock().push_cqe(sqe.user_data, res);
+		submitted += 1;
+	}
+
+	if flags & IORING_ENTER_GETEVENTS != 0 {
+		loop {
+			// TODO super::util::signal_check()?;
+
+			{
+				let mut io_uring = io_uring_mutex.lock();
+				let min_complete = min(min_complete, io_uring.get_cq_entries());
+				if io_uring.get_completions_count() >= min_complete {
+					break;
+				}
+
+				let mut proc = proc_mutex.lock();
+				io_uring.add_waiting_process(&mut proc, io::POLLIN)?;
+			}
+
+			// Make current process sleep
+			scheduler::end_tick();
+		}
+	}
+
+	Ok(submitted as _)
+}
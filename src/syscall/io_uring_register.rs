@@ -0,0 +1,124 @@
+//! The `io_uring_register` system call registers resources on an io_uring instance, or queries
+//! it.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::buffer::io_uring;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::Process;
+use crate::util::container::vec::Vec;
+use core::cmp::min;
+use core::ffi::c_int;
+use core::ffi::c_void;
+use macros::syscall;
+
+/// Register files, which are then designated by their index.
+const IORING_REGISTER_FILES: u32 = 2;
+/// Unregister the registered files.
+const IORING_UNREGISTER_FILES: u32 = 3;
+/// Query the supported operations.
+const IORING_REGISTER_PROBE: u32 = 8;
+
+/// Probe flag: The operation is supported.
+const IO_URING_OP_SUPPORTED: u16 = 1 << 0;
+/// The size of the header of a probe (`struct io_uring_probe`).
+const PROBE_HEADER_SIZE: usize = 16;
+/// The size of an operation in a probe (`struct io_uring_probe_op`).
+const PROBE_OP_SIZE: usize = 8;
+/// The maximum number of operations in a probe.
+const PROBE_MAX_OPS: u32 = 256;
+
+#[syscall]
+pub fn io_uring_register(
+	fd: c_int,
+	opcode: u32,
+	arg: *const c_void,
+	nr_args: u32,
+) -> Result<i32, Errno> {
+	if fd < 0 {
+		return Err(errno!(EBADF));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	let fds_mutex = proc.get_fds().unwrap().clone();
+	let io_uring_mutex = {
+		let fds = fds_mutex.lock();
+		let open_file_mutex = fds
+			.get_fd(fd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file();
+		let open_file = open_file_mutex.lock();
+		io_uring::get(open_file.get_location()).ok_or_else(|| errno!(EOPNOTSUPP))?
+	};
+
+	let mem_space = proc.get_mem_space().unwrap().clone();
+	let mut mem_space_guard = mem_space.lock();
+
+	match opcode {
+		IORING_REGISTER_FILES => {
+			if nr_args == 0 || nr_args as usize > io_uring::MAX_FIXED_FILES {
+				return Err(errno!(EINVAL));
+			}
+			let fds_ptr: SyscallSlice<c_int> = (arg as usize).into();
+			let user_fds = fds_ptr
+				.get(&mem_space_guard, nr_args as _)?
+				.ok_or_else(|| errno!(EFAULT))?;
+
+			let fds = fds_mutex.lock();
+			let mut files = Vec::with_capacity(nr_args as _)?;
+			for fd in user_fds.iter() {
+				// A value of `-1` leaves a hole
+				if *fd == -1 {
+					files.push(None)?;
+					continue;
+				}
+				let fd: u32 = (*fd).try_into().map_err(|_| errno!(EBADF))?;
+				let open_file_mutex = fds
+					.get_fd(fd)
+					.ok_or_else(|| errno!(EBADF))?
+					.get_open_file()
+					.clone();
+				// An instance cannot be registered on an instance
+				if io_uring::get(open_file_mutex.lock().get_location()).is_some() {
+					return Err(errno!(EBADF));
+				}
+				files.push(Some(open_file_mutex))?;
+			}
+			io_uring_mutex.lock().register_files(files)?;
+		}
+
+		IORING_UNREGISTER_FILES => {
+			if !arg.is_null() || nr_args != 0 {
+				return Err(errno!(EINVAL));
+			}
+			io_uring_mutex.lock().unregister_files()?;
+		}
+
+		IORING_REGISTER_PROBE => {
+			let ops_len = min(nr_args, PROBE_MAX_OPS) as usize;
+			let probe_ptr: SyscallSlice<u8> = (arg as usize).into();
+			let mut probe = probe_ptr
+				.get_mut(
+					&mut mem_space_guard,
+					PROBE_HEADER_SIZE + ops_len * PROBE_OP_SIZE,
+				)?
+				.ok_or_else(|| errno!(EFAULT))?;
+			probe.fill(0);
+
+			let supported = io_uring::IORING_OP_LAST as usize + 1;
+			probe[0] = io_uring::IORING_OP_LAST;
+			probe[1] = min(ops_len, supported) as u8;
+			let ops = probe[PROBE_HEADER_SIZE..].chunks_exact_mut(PROBE_OP_SIZE);
+			for (op, buf) in ops.take(supported).enumerate() {
+				buf[0] = op as u8;
+				buf[2..4].copy_from_slice(&IO_URING_OP_SUPPORTED.to_ne_bytes());
+			}
+		}
+
+		_ => return Err(errno!(EINVAL)),
+	}
+
+	Ok(0)
+}
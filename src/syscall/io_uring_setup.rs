@@ -0,0 +1,48 @@
+//! The `io_uring_setup` system call creates an io_uring instance and returns a file descriptor to
+//! it.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::buffer::io_uring;
+use crate::file::buffer::io_uring::IoUringParams;
+use crate::file::fd::FD_CLOEXEC;
+use crate::file::open_file;
+use crate::file::open_file::OpenFile;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::Process;
+use macros::syscall;
+
+#[syscall]
+pub fn io_uring_setup(entries: u32, params: SyscallPtr<IoUringParams>) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let (mem_space, fds_mutex, access_profile) = {
+		let proc = proc_mutex.lock();
+
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let fds_mutex = proc.get_fds().unwrap().clone();
+		(mem_space, fds_mutex, proc.access_profile)
+	};
+
+	let mut p = {
+		let mem_space_guard = mem_space.lock();
+		*params
+			.get(&mem_space_guard)?
+			.ok_or_else(|| errno!(EFAULT))?
+	};
+	if p.resv.iter().any(|r| *r != 0) {
+		return Err(errno!(EINVAL));
+	}
+	p.sq_entries = entries;
+
+	let file = io_uring::create(&mut p, &access_profile)?;
+	let open_file = OpenFile::new(file, open_file::O_RDWR)?;
+	let fd = fds_mutex.lock().create_fd(FD_CLOEXEC, open_file)?.get_id();
+
+	let mut mem_space_guard = mem_space.lock();
+	let mut params = params
+		.get_mut(&mut mem_space_guard)?
+		.ok_or_else(|| errno!(EFAULT))?;
+	*params = p;
+
+	Ok(fd as _)
+}
@@ -36,6 +36,9 @@ pub const TCSETSW: u32 = 0x00005403;
 /// ioctl request: Sets the serial port settings. Making the change only when
 /// all currently written data has been transmitted.
 pub const TCSETSF: u32 = 0x00005404;
+/// ioctl request: Makes the terminal the controlling terminal of the session of the calling
+/// process.
+pub const TIOCSCTTY: u32 = 0x0000540e;
 /// ioctl request: Get the foreground process group ID on the terminal.
 pub const TIOCGPGRP: u32 = 0x0000540f;
 /// ioctl request: Set the foreground process group ID on the terminal.
@@ -46,6 +49,11 @@ pub const TIOCGWINSZ: u32 = 0x00005413;
 pub const TIOCSWINSZ: u32 = 0x00005414;
 /// ioctl request: Returns the number of bytes available on the file descriptor.
 pub const FIONREAD: u32 = 0x0000541b;
+/// ioctl request: Gives up the controlling terminal of the calling process.
+pub const TIOCNOTTY: u32 = 0x00005422;
+/// ioctl request: Returns the ID of the session of which the terminal is the controlling
+/// terminal.
+pub const TIOCGSID: u32 = 0x00005429;
 
 /// Enumeration of IO directions for ioctl requests.
 #[derive(Eq, PartialEq)]
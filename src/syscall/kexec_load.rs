@@ -0,0 +1,93 @@
+//! The `kexec_load` system call loads a new kernel to be executed later with
+//! `reboot`.
+
+use crate::errno::Errno;
+use crate::file::perm::CAP_SYS_BOOT;
+use crate::kexec;
+use crate::kexec::Image;
+use crate::kexec::Segment;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::Process;
+use crate::util::container::vec::Vec;
+use core::ffi::c_ulong;
+use core::ffi::c_void;
+use macros::syscall;
+
+/// Flag telling to load the kernel to be executed on crash.
+const KEXEC_ON_CRASH: c_ulong = 0x00000001;
+/// Mask of the architecture in flags.
+const KEXEC_ARCH_MASK: c_ulong = 0xffff0000;
+/// Architecture value for the default architecture.
+const KEXEC_ARCH_DEFAULT: c_ulong = 0 << 16;
+/// Architecture value for x86.
+const KEXEC_ARCH_386: c_ulong = 3 << 16;
+
+/// A segment of the kernel to be loaded, as given by userspace.
+#[repr(C)]
+#[derive(Debug)]
+pub struct KexecSegment {
+	/// The buffer containing the data of the segment.
+	buf: *const c_void,
+	/// The size of the buffer in bytes.
+	bufsz: usize,
+	/// The physical address of the destination.
+	mem: *const c_void,
+	/// The size of the destination in bytes.
+	memsz: usize,
+}
+
+#[syscall]
+pub fn kexec_load(
+	entry: c_ulong,
+	nr_segments: c_ulong,
+	segments: SyscallSlice<KexecSegment>,
+	flags: c_ulong,
+) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+	if !proc.access_profile.has_cap(CAP_SYS_BOOT) {
+		return Err(errno!(EPERM));
+	}
+
+	// TODO Support loading a crash kernel
+	if flags & KEXEC_ON_CRASH != 0 {
+		return Err(errno!(EINVAL));
+	}
+	let arch = flags & KEXEC_ARCH_MASK;
+	if arch != KEXEC_ARCH_DEFAULT && arch != KEXEC_ARCH_386 {
+		return Err(errno!(EINVAL));
+	}
+	let nr_segments = nr_segments as usize;
+	if nr_segments > kexec::SEGMENT_MAX {
+		return Err(errno!(EINVAL));
+	}
+	if nr_segments == 0 {
+		kexec::load(None);
+		return Ok(0);
+	}
+
+	let segs = {
+		let mem_space = proc.get_mem_space().unwrap();
+		let mem_space_guard = mem_space.lock();
+		let user_segs = segments
+			.get(&mem_space_guard, nr_segments)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		let mut segs = Vec::with_capacity(nr_segments)?;
+		for seg in user_segs.iter() {
+			let buf = SyscallSlice::<u8>::from(seg.buf as usize)
+				.get(&mem_space_guard, seg.bufsz)?
+				.ok_or_else(|| errno!(EFAULT))?;
+			segs.push(Segment {
+				data: Vec::from_slice(&buf)?,
+				mem: seg.mem as _,
+				memsz: seg.memsz,
+			})?;
+		}
+		segs
+	};
+	drop(proc);
+
+	let image = Image::new(entry as _, &segs)?;
+	kexec::load(Some(image));
+	Ok(0)
+}
@@ -5,14 +5,17 @@ use super::util;
 use crate::errno;
 use crate::errno::Errno;
 use crate::process;
+use crate::process::pgroup;
 use crate::process::pid::Pid;
+use crate::process::signal::SigInfo;
 use crate::process::signal::Signal;
+use crate::process::signal::SI_USER;
 use crate::process::Process;
 use crate::process::State;
 use core::ffi::c_int;
 use macros::syscall;
 
-/// Tries to kill the process with PID `pid` with the signal `sig`.
+/// Tries to kill the process with PID `pid`, in the root namespace, with the signal `sig`.
 ///
 /// If `sig` is `None`, the function doesn't send a signal, but still checks if
 /// there is a process that could be killed.
@@ -21,6 +24,7 @@ fn try_kill(pid: Pid, sig: &Option<Signal>) -> Result<(), Errno> {
 	let mut proc = proc_mutex.lock();
 
 	let ap = proc.access_profile;
+	let sender = proc.to_local_pid(proc.tgid);
 
 	// Closure sending the signal
 	let f = |target: &mut Process| {
@@ -32,7 +36,8 @@ fn try_kill(pid: Pid, sig: &Option<Signal>) -> Result<(), Errno> {
 		}
 
 		if let Some(sig) = sig {
-			target.kill(sig, false);
+			let info = SigInfo::from_sender(sig, SI_USER, sender, ap.get_uid());
+			target.kill_with_info(sig, info, false)?;
 		}
 
 		Ok(())
@@ -59,36 +64,25 @@ fn try_kill(pid: Pid, sig: &Option<Signal>) -> Result<(), Errno> {
 /// If `sig` is `None`, the function doesn't send a signal, but still checks if
 /// there is a process that could be killed.
 fn try_kill_group(pid: i32, sig: &Option<Signal>) -> Result<(), Errno> {
-	let pgid = match pid {
-		0 => {
-			let proc_mutex = Process::current_assert();
-			let proc = proc_mutex.lock();
+	let pgid = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
 
-			proc.pgid
+		match pid {
+			0 => proc.pgid,
+			i if i < 0 => proc.to_global_pid(-pid as _).ok_or_else(|| errno!(ESRCH))?,
+			_ => proc.to_global_pid(pid as _).ok_or_else(|| errno!(ESRCH))?,
 		}
-
-		i if i < 0 => -pid as Pid,
-		_ => pid as Pid,
 	};
 
-	// Killing process group
-	{
-		let proc_mutex = Process::get_by_pid(pgid).ok_or_else(|| errno!(ESRCH))?;
-		let proc = proc_mutex.lock();
-
-		let group = proc.get_group_processes();
-
-		for pid in group {
-			if *pid == pgid {
-				continue;
-			}
-
-			try_kill(*pid as _, sig)?;
-		}
+	// The group keeps existing after the exit of its leader
+	let members = pgroup::get_members(pgid)?;
+	if members.is_empty() {
+		return Err(errno!(ESRCH));
+	}
+	for pid in members {
+		try_kill(pid, sig)?;
 	}
-
-	// Killing process group owner
-	try_kill(pgid, sig)?;
 
 	Ok(())
 }
@@ -99,16 +93,21 @@ fn try_kill_group(pid: i32, sig: &Option<Signal>) -> Result<(), Errno> {
 fn send_signal(pid: i32, sig: Option<Signal>) -> Result<(), Errno> {
 	if pid > 0 {
 		// Kill the process with the given PID
-		try_kill(pid as _, &sig)
+		let pid = Process::current_assert()
+			.lock()
+			.to_global_pid(pid as _)
+			.ok_or_else(|| errno!(ESRCH))?;
+		try_kill(pid, &sig)
 	} else if pid == 0 {
 		// Kill all processes in the current process group
 		try_kill_group(0, &sig)
 	} else if pid == -1 {
 		// Kill all processes for which the current process has the permission
+		let pid_ns = Process::current_assert().lock().get_pid_ns().clone();
 		let mut sched = process::get_scheduler().lock();
 
 		for (pid, _) in sched.iter_process() {
-			if *pid == process::pid::INIT_PID {
+			if *pid == process::pid::INIT_PID || pid_ns.to_local(*pid).is_none() {
 				continue;
 			}
 
@@ -28,14 +28,14 @@ pub fn linkat(
 		let oldpath = oldpath
 			.get(&mem_space_guard)?
 			.ok_or_else(|| errno!(EFAULT))?;
-		let old = super::util::get_file_at(proc, olddirfd, oldpath, false, flags)?;
+		let old = super::util::get_file_at(proc, olddirfd, &oldpath, false, flags)?;
 
 		let proc = proc_mutex.lock();
 		let newpath = newpath
 			.get(&mem_space_guard)?
 			.ok_or_else(|| errno!(EFAULT))?;
 		let (new_parent, new_name) =
-			super::util::get_parent_at_with_name(proc, newdirfd, newpath, false, flags)?;
+			super::util::get_parent_at_with_name(proc, newdirfd, &newpath, false, flags)?;
 
 		(old, new_parent, new_name, ap)
 	};
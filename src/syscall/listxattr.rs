@@ -0,0 +1,76 @@
+//! The `listxattr` system call returns the list of the names of the extended attributes of a
+//! file.
+
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::limits;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Performs the `*listxattr` system calls.
+///
+/// Arguments:
+/// - `fd` is the file descriptor of the file, used if `pathname` is `None`.
+/// - `pathname` is the path to the file.
+/// - `follow_links` tells whether symbolic links are followed.
+/// - `list` is the buffer to write the list to, of size `size`. Each name is followed by a
+/// null byte.
+///
+/// If `size` is zero, the function only returns the size of the list.
+pub fn do_listxattr(
+	fd: c_int,
+	pathname: Option<SyscallString>,
+	follow_links: bool,
+	list: SyscallSlice<u8>,
+	size: usize,
+) -> EResult<i32> {
+	let ap = Process::current_assert().lock().access_profile;
+
+	let names = {
+		let file_mutex = super::util::get_file_from_path_or_fd(fd, pathname, follow_links)?;
+		let file = file_mutex.lock();
+		file.list_xattr(&ap)?
+	};
+	let len: usize = names.iter().map(|name| name.len() + 1).sum();
+	if len > limits::XATTR_LIST_MAX {
+		return Err(errno!(E2BIG));
+	}
+	if size == 0 {
+		return Ok(len as _);
+	}
+	if size < len {
+		return Err(errno!(ERANGE));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+
+	let mut buf = list
+		.get_mut(&mut mem_space_guard, len)?
+		.ok_or_else(|| errno!(EFAULT))?;
+	let mut off = 0;
+	for name in names.iter() {
+		let name = name.as_bytes();
+		buf[off..(off + name.len())].copy_from_slice(name);
+		buf[off + name.len()] = b'\0';
+		off += name.len() + 1;
+	}
+
+	Ok(len as _)
+}
+
+#[syscall]
+pub fn listxattr(
+	pathname: SyscallString,
+	list: SyscallSlice<u8>,
+	size: usize,
+) -> Result<i32, Errno> {
+	do_listxattr(-1, Some(pathname), true, list, size)
+}
@@ -0,0 +1,16 @@
+//! The `llistxattr` system call returns the list of the names of the extended attributes of a
+//! file, without following symbolic links.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::ptr::SyscallString;
+use macros::syscall;
+
+#[syscall]
+pub fn llistxattr(
+	pathname: SyscallString,
+	list: SyscallSlice<u8>,
+	size: usize,
+) -> Result<i32, Errno> {
+	super::listxattr::do_listxattr(-1, Some(pathname), false, list, size)
+}
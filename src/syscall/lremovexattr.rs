@@ -0,0 +1,11 @@
+//! The `lremovexattr` system call removes an extended attribute of a file, without following
+//! symbolic links.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallString;
+use macros::syscall;
+
+#[syscall]
+pub fn lremovexattr(pathname: SyscallString, name: SyscallString) -> Result<i32, Errno> {
+	super::removexattr::do_removexattr(-1, Some(pathname), false, name)
+}
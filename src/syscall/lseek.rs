@@ -0,0 +1,32 @@
+//! The `lseek` system call repositions the offset of a file descriptor.
+
+use super::_llseek;
+use crate::errno::Errno;
+use crate::process::Process;
+use core::ffi::c_long;
+use core::ffi::c_uint;
+use macros::syscall;
+
+#[syscall]
+pub fn lseek(fd: c_uint, offset: c_long, whence: c_uint) -> Result<i32, Errno> {
+	let open_file_mutex = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let fds_mutex = proc.get_fds().unwrap().clone();
+		let fds = fds_mutex.lock();
+
+		fds.get_fd(fd)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file()
+			.clone()
+	};
+	let mut open_file = open_file_mutex.lock();
+
+	let off = _llseek::compute_offset(&open_file, offset as _, whence)?;
+	// The offset must be representable in the returned value
+	let ret: i32 = off.try_into().map_err(|_| errno!(EOVERFLOW))?;
+	open_file.set_offset(off);
+
+	Ok(ret)
+}
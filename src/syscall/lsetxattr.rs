@@ -0,0 +1,19 @@
+//! The `lsetxattr` system call sets the value of an extended attribute of a file, without
+//! following symbolic links.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::ptr::SyscallString;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall]
+pub fn lsetxattr(
+	pathname: SyscallString,
+	name: SyscallString,
+	value: SyscallSlice<u8>,
+	size: usize,
+	flags: c_int,
+) -> Result<i32, Errno> {
+	super::setxattr::do_setxattr(-1, Some(pathname), false, name, value, size, flags)
+}
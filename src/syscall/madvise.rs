@@ -2,12 +2,56 @@
 //! memory in order to allow optimizations.
 
 use crate::errno::Errno;
+use crate::memory;
+use crate::process::Process;
+use crate::util::math;
 use core::ffi::c_int;
 use core::ffi::c_void;
 use macros::syscall;
 
+/// No special treatment.
+pub const MADV_NORMAL: c_int = 0;
+/// Expect page references in random order.
+pub const MADV_RANDOM: c_int = 1;
+/// Expect page references in sequential order.
+pub const MADV_SEQUENTIAL: c_int = 2;
+/// Expect access in the near future.
+pub const MADV_WILLNEED: c_int = 3;
+/// Do not expect access in the near future. Pages are discarded.
+pub const MADV_DONTNEED: c_int = 4;
+/// The pages in the range can be freed when the system needs memory.
+pub const MADV_FREE: c_int = 8;
+
 #[syscall]
-pub fn madvise(_addr: *mut c_void, _length: usize, _advice: c_int) -> Result<i32, Errno> {
-	// TODO
+pub fn madvise(addr: *mut c_void, length: usize, advice: c_int) -> Result<i32, Errno> {
+	if !addr.is_aligned_to(memory::PAGE_SIZE) {
+		return Err(errno!(EINVAL));
+	}
+	let pages = math::ceil_div(length, memory::PAGE_SIZE);
+	let end = pages
+		.checked_mul(memory::PAGE_SIZE)
+		.and_then(|size| (addr as usize).checked_add(size))
+		.ok_or_else(|| errno!(EINVAL))?;
+	if end > memory::PROCESS_END as usize {
+		return Err(errno!(EINVAL));
+	}
+
+	let mem_space_mutex = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+		proc.get_mem_space().unwrap().clone()
+	};
+	let mut mem_space = mem_space_mutex.lock();
+
+	match advice {
+		// Hints are not used yet
+		MADV_NORMAL | MADV_RANDOM | MADV_SEQUENTIAL | MADV_WILLNEED => {}
+
+		MADV_DONTNEED => mem_space.discard(addr, pages)?,
+		MADV_FREE => mem_space.lazy_free(addr, pages)?,
+
+		_ => return Err(errno!(EINVAL)),
+	}
+
 	Ok(0)
 }
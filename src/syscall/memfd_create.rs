@@ -0,0 +1,62 @@
+//! The `memfd_create` system call creates an anonymous file and returns a file descriptor to it.
+
+use crate::errno::Errno;
+use crate::file::buffer::memfd;
+use crate::file::fd::FD_CLOEXEC;
+use crate::file::open_file;
+use crate::file::open_file::OpenFile;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::Process;
+use core::ffi::c_uint;
+use macros::syscall;
+
+/// Set the close-on-exec flag on the new file descriptor.
+const MFD_CLOEXEC: c_uint = 1;
+/// Allow adding seals to the file.
+const MFD_ALLOW_SEALING: c_uint = 2;
+/// Create the file in the hugetlbfs filesystem.
+const MFD_HUGETLB: c_uint = 4;
+
+/// The maximum length of the name of an anonymous file, excluding the prefix.
+const NAME_MAX: usize = 249;
+
+#[syscall]
+pub fn memfd_create(name: SyscallString, flags: c_uint) -> Result<i32, Errno> {
+	if flags & !(MFD_CLOEXEC | MFD_ALLOW_SEALING | MFD_HUGETLB) != 0 {
+		return Err(errno!(EINVAL));
+	}
+	// TODO Support huge pages
+	if flags & MFD_HUGETLB != 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	let (mem_space_mutex, fds_mutex, access_profile) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let fds_mutex = proc.get_fds().unwrap().clone();
+		(mem_space, fds_mutex, proc.access_profile)
+	};
+
+	let file = {
+		let mem_space = mem_space_mutex.lock();
+		let name = name.get(&mem_space)?.ok_or_else(|| errno!(EFAULT))?;
+		if name.len() > NAME_MAX {
+			return Err(errno!(EINVAL));
+		}
+
+		memfd::create(&name, flags & MFD_ALLOW_SEALING != 0, &access_profile)?
+	};
+	let open_file = OpenFile::new(file, open_file::O_RDWR)?;
+
+	let fd_flags = if flags & MFD_CLOEXEC != 0 {
+		FD_CLOEXEC
+	} else {
+		0
+	};
+	let mut fds = fds_mutex.lock();
+	let fd = fds.create_fd(fd_flags, open_file)?;
+
+	Ok(fd.get_id() as _)
+}
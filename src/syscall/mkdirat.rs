@@ -0,0 +1,35 @@
+//! The `mkdirat` system call allows to create a directory, relative to a directory.
+
+use super::util;
+use crate::errno::Errno;
+use crate::file;
+use crate::file::FileContent;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::Process;
+use crate::util::container::hashmap::HashMap;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall]
+pub fn mkdirat(dirfd: c_int, pathname: SyscallString, mode: file::Mode) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	let mem_space = proc.get_mem_space().unwrap().clone();
+	let mem_space_guard = mem_space.lock();
+
+	let pathname = pathname
+		.get(&mem_space_guard)?
+		.ok_or_else(|| errno!(EFAULT))?;
+	util::create_file_at(
+		proc,
+		dirfd,
+		&pathname,
+		mode,
+		FileContent::Directory(HashMap::new()),
+		true,
+		0,
+	)?;
+
+	Ok(0)
+}
@@ -2,32 +2,75 @@
 
 use crate::device::id;
 use crate::errno;
+use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file;
 use crate::file::path::Path;
+use crate::file::perm::AccessProfile;
+use crate::file::perm::CAP_MKNOD;
 use crate::file::vfs;
+use crate::file::vfs::ResolutionSettings;
 use crate::file::FileContent;
 use crate::file::FileType;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
 use macros::syscall;
 
+/// Returns the content of a node to be created with the mode `mode`.
+///
+/// Arguments:
+/// - `mode` is the mode of the node.
+/// - `dev` is the device number, used only for device files.
+/// - `ap` is the access profile of the agent creating the node.
+///
+/// If the node cannot be created with `mknod`, the function returns an error.
+pub fn get_node_content(mode: file::Mode, dev: u64, ap: &AccessProfile) -> EResult<FileContent> {
+	let file_type = FileType::from_mode(mode).ok_or(errno!(EPERM))?;
+	// Creating a device file requires a dedicated capability
+	let device = matches!(file_type, FileType::BlockDevice | FileType::CharDevice);
+	if device && !ap.has_cap(CAP_MKNOD) {
+		return Err(errno!(EPERM));
+	}
+
+	// Get the major and minor IDs
+	let major = id::major(dev);
+	let minor = id::minor(dev);
+
+	match file_type {
+		FileType::Regular => Ok(FileContent::Regular),
+		FileType::Fifo => Ok(FileContent::Fifo),
+		FileType::Socket => Ok(FileContent::Socket),
+		FileType::BlockDevice => Ok(FileContent::BlockDevice {
+			major,
+			minor,
+		}),
+		FileType::CharDevice => Ok(FileContent::CharDevice {
+			major,
+			minor,
+		}),
+		_ => Err(errno!(EPERM)),
+	}
+}
+
 // TODO Check args type
 #[syscall]
 pub fn mknod(pathname: SyscallString, mode: file::Mode, dev: u64) -> Result<i32, Errno> {
-	let (path, umask, ap) = {
+	let (path, perms, rs) = {
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
 
 		let mem_space = proc.get_mem_space().unwrap();
 		let mem_space_guard = mem_space.lock();
 
-		let path = Path::from_str(pathname.get(&mem_space_guard)?.ok_or(errno!(EFAULT))?, true)?;
+		let path = Path::from_str(
+			&pathname.get(&mem_space_guard)?.ok_or(errno!(EFAULT))?,
+			true,
+		)?;
 		let path = super::util::get_absolute_path(&proc, path)?;
 
-		let umask = proc.umask;
+		let perms = proc.apply_umask(mode);
 
-		(path, umask, proc.access_profile)
+		(path, perms, ResolutionSettings::for_process(&proc, true)?)
 	};
 
 	// Path of the parent directory
@@ -37,33 +80,12 @@ pub fn mknod(pathname: SyscallString, mode: file::Mode, dev: u64) -> Result<i32,
 		return Err(errno!(EEXIST));
 	};
 
-	let mode = mode & !umask;
-	let file_type = FileType::from_mode(mode).ok_or(errno!(EPERM))?;
-
-	// Get the major and minor IDs
-	let major = id::major(dev);
-	let minor = id::minor(dev);
-
-	// The file's content
-	let file_content = match file_type {
-		FileType::Regular => FileContent::Regular,
-		FileType::Fifo => FileContent::Fifo,
-		FileType::Socket => FileContent::Socket,
-		FileType::BlockDevice => FileContent::BlockDevice {
-			major,
-			minor,
-		},
-		FileType::CharDevice => FileContent::CharDevice {
-			major,
-			minor,
-		},
-		_ => return Err(errno!(EPERM)),
-	};
+	let file_content = get_node_content(mode, dev, &rs.access_profile)?;
 
 	// Create the node
-	let parent_mutex = vfs::get_file_from_path(&parent_path, &ap, true)?;
+	let parent_mutex = vfs::resolve_path(&parent_path, &rs)?;
 	let mut parent = parent_mutex.lock();
-	vfs::create_file(&mut parent, name, &ap, mode, file_content)?;
+	vfs::create_file(&mut parent, name, &rs.access_profile, perms, file_content)?;
 
 	Ok(0)
 }
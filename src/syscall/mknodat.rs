@@ -0,0 +1,34 @@
+//! The `mknodat` system call allows to create a new node on a filesystem, relative to a
+//! directory.
+
+use super::mknod;
+use super::util;
+use crate::errno::Errno;
+use crate::file;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall]
+pub fn mknodat(
+	dirfd: c_int,
+	pathname: SyscallString,
+	mode: file::Mode,
+	dev: u64,
+) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	let file_content = mknod::get_node_content(mode, dev, &proc.access_profile)?;
+
+	let mem_space = proc.get_mem_space().unwrap().clone();
+	let mem_space_guard = mem_space.lock();
+
+	let pathname = pathname
+		.get(&mem_space_guard)?
+		.ok_or_else(|| errno!(EFAULT))?;
+	util::create_file_at(proc, dirfd, &pathname, mode, file_content, true, 0)?;
+
+	Ok(0)
+}
@@ -0,0 +1,82 @@
+//! The `mlock` system call locks pages of memory into RAM, preventing them from being swapped
+//! out.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::perm::AccessProfile;
+use crate::file::perm::CAP_IPC_LOCK;
+use crate::memory;
+use crate::process::mem_space::MemSpace;
+use crate::process::rlimit;
+use crate::process::rlimit::RLim;
+use crate::process::Process;
+use crate::util;
+use crate::util::math;
+use core::ffi::c_void;
+use macros::syscall;
+
+/// Checks that locking `pages` more pages in `mem_space` does not exceed the locked memory
+/// limit `limit` in bytes.
+///
+/// If the agent is privileged, the limit is ignored.
+///
+/// If the limit is zero, the function returns [`crate::errno::EPERM`]. If the limit is exceeded,
+/// the function returns [`crate::errno::ENOMEM`].
+pub fn check_limit(
+	mem_space: &MemSpace,
+	pages: usize,
+	limit: RLim,
+	access_profile: &AccessProfile,
+) -> EResult<()> {
+	if access_profile.has_cap(CAP_IPC_LOCK) || limit == rlimit::RLIM_INFINITY {
+		return Ok(());
+	}
+	if limit == 0 {
+		return Err(errno!(EPERM));
+	}
+
+	let total = (mem_space.get_locked_pages() as RLim).saturating_add(pages as RLim);
+	if total > limit / memory::PAGE_SIZE as RLim {
+		return Err(errno!(ENOMEM));
+	}
+
+	Ok(())
+}
+
+/// Performs the `mlock` system call.
+///
+/// `on_fault` tells whether physical pages are allocated only when accessed.
+pub fn do_mlock(addr: *const c_void, len: usize, on_fault: bool) -> EResult<()> {
+	// Rounding the range to page boundaries
+	let begin = util::down_align(addr, memory::PAGE_SIZE);
+	let len = (addr as usize - begin as usize)
+		.checked_add(len)
+		.ok_or_else(|| errno!(ENOMEM))?;
+	let pages = math::ceil_div(len, memory::PAGE_SIZE);
+	if pages == 0 {
+		return Ok(());
+	}
+
+	let (mem_space_mutex, access_profile, limit) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+		let mem_space = proc.get_mem_space().unwrap().clone();
+
+		(
+			mem_space,
+			proc.access_profile,
+			proc.rlimits.get_cur(rlimit::RLIMIT_MEMLOCK),
+		)
+	};
+	let mut mem_space = mem_space_mutex.lock();
+
+	let new_pages = mem_space.count_unlocked(begin, pages)?;
+	check_limit(&mem_space, new_pages, limit, &access_profile)?;
+	mem_space.lock(begin, pages, on_fault)
+}
+
+#[syscall]
+pub fn mlock(addr: *const c_void, len: usize) -> Result<i32, Errno> {
+	do_mlock(addr, len, false)?;
+	Ok(0)
+}
@@ -0,0 +1,21 @@
+//! The `mlock2` system call is similar to `mlock`, but allows to specify flags.
+
+use super::mlock;
+use crate::errno::Errno;
+use core::ffi::c_int;
+use core::ffi::c_void;
+use macros::syscall;
+
+/// Lock pages that are currently resident and mark the rest of the range so that pages are
+/// locked when they are faulted in.
+pub const MLOCK_ONFAULT: c_int = 1;
+
+#[syscall]
+pub fn mlock2(addr: *const c_void, len: usize, flags: c_int) -> Result<i32, Errno> {
+	if flags & !MLOCK_ONFAULT != 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	mlock::do_mlock(addr, len, flags & MLOCK_ONFAULT != 0)?;
+	Ok(0)
+}
@@ -0,0 +1,53 @@
+//! The `mlockall` system call locks every pages of the process's memory space into RAM.
+
+use super::mlock;
+use crate::errno::Errno;
+use crate::process::rlimit;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Lock every pages currently mapped.
+pub const MCL_CURRENT: c_int = 1;
+/// Lock every pages mapped in the future.
+pub const MCL_FUTURE: c_int = 2;
+/// Lock pages only when they are faulted in.
+pub const MCL_ONFAULT: c_int = 4;
+
+#[syscall]
+pub fn mlockall(flags: c_int) -> Result<i32, Errno> {
+	if flags & !(MCL_CURRENT | MCL_FUTURE | MCL_ONFAULT) != 0 {
+		return Err(errno!(EINVAL));
+	}
+	if flags & (MCL_CURRENT | MCL_FUTURE) == 0 {
+		return Err(errno!(EINVAL));
+	}
+	let current = flags & MCL_CURRENT != 0;
+	let future = flags & MCL_FUTURE != 0;
+	let on_fault = flags & MCL_ONFAULT != 0;
+
+	let (mem_space_mutex, access_profile, limit) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+		// The limit enforced on future mappings depends on the current privileges
+		proc.apply_rlimits();
+		let mem_space = proc.get_mem_space().unwrap().clone();
+
+		(
+			mem_space,
+			proc.access_profile,
+			proc.rlimits.get_cur(rlimit::RLIMIT_MEMLOCK),
+		)
+	};
+	let mut mem_space = mem_space_mutex.lock();
+
+	let new_pages = if current {
+		mem_space.count_all_unlocked()
+	} else {
+		0
+	};
+	mlock::check_limit(&mem_space, new_pages, limit, &access_profile)?;
+	mem_space.lock_all(current, future, on_fault)?;
+
+	Ok(0)
+}
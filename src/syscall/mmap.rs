@@ -2,6 +2,8 @@
 
 use crate::errno;
 use crate::errno::Errno;
+use crate::file::buffer::io_uring;
+use crate::file::buffer::memfd;
 use crate::file::FileType;
 use crate::memory;
 use crate::process::mem_space;
@@ -85,6 +87,9 @@ pub fn do_mmap(
 		}
 	};
 
+	// Mappings cannot be both writable and executable, unless allowed
+	mem_space::check_write_exec(get_flags(flags, prot))?;
+
 	// Get the current process
 	let proc_mutex = Process::current_assert();
 	let proc = proc_mutex.lock();
@@ -108,8 +113,25 @@ pub fn do_mmap(
 	// TODO anon flag
 
 	// Get residence
-	let residence = match file_mutex {
-		Some(file_mutex) => {
+	let io_uring = file_mutex
+		.as_ref()
+		.and_then(|file_mutex| io_uring::get(file_mutex.lock().get_location()));
+	let residence = match (file_mutex, io_uring) {
+		// The rings of an io_uring instance reside in the kernel's memory
+		(Some(_), Some(io_uring)) => {
+			let pages_list = io_uring
+				.lock()
+				.get_region(offset)
+				.ok_or_else(|| errno!(EINVAL))?;
+			if pages.get() > pages_list.len() {
+				return Err(errno!(EINVAL));
+			}
+			MapResidence::Static {
+				pages: pages_list,
+			}
+		}
+
+		(Some(file_mutex), None) => {
 			let file = file_mutex.lock();
 			// Check the file is suitable
 			if !matches!(file.get_type(), FileType::Regular) {
@@ -118,10 +140,20 @@ pub fn do_mmap(
 			if prot & PROT_READ != 0 && !proc.access_profile.can_read_file(&*file) {
 				return Err(errno!(EPERM));
 			}
-			if prot & PROT_WRITE != 0 && !proc.access_profile.can_write_file(&*file) {
+			// Writes on a private mapping are not carried to the file
+			let shared = flags & MAP_SHARED != 0;
+			if shared && prot & PROT_WRITE != 0 && !proc.access_profile.can_write_file(&*file) {
+				return Err(errno!(EPERM));
+			}
+			// Sealed anonymous files cannot be modified
+			let seals = memfd::get_seals(file.get_location()).unwrap_or(0);
+			let write_seals = memfd::F_SEAL_WRITE | memfd::F_SEAL_FUTURE_WRITE;
+			if shared && prot & PROT_WRITE != 0 && seals & write_seals != 0 {
 				return Err(errno!(EPERM));
 			}
-			if prot & PROT_EXEC != 0 && !proc.access_profile.can_execute_file(&*file) {
+			if prot & PROT_EXEC != 0
+				&& (!proc.access_profile.can_execute_file(&*file) || !file.is_mount_executable())
+			{
 				return Err(errno!(EPERM));
 			}
 
@@ -130,7 +162,7 @@ pub fn do_mmap(
 				off: offset,
 			}
 		}
-		None => {
+		(None, _) => {
 			// TODO If the mapping requires a fd, return an error
 			MapResidence::Normal
 		}
@@ -151,7 +183,7 @@ pub fn do_mmap(
 				let ptr = mem_space.map(MapConstraint::None, pages, flags, residence)?;
 				Ok(ptr as _)
 			} else {
-				Err(e.into())
+				Err(e)
 			}
 		}
 	}
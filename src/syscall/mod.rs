@@ -8,10 +8,13 @@ mod _exit;
 mod _llseek;
 mod _newselect;
 mod access;
+mod acct;
 mod arch_prctl;
 mod bind;
 mod r#break;
 mod brk;
+mod capget;
+mod capset;
 mod chdir;
 mod chmod;
 mod chown;
@@ -19,6 +22,10 @@ mod chown32;
 mod chroot;
 mod clock_gettime;
 mod clock_gettime64;
+mod clock_nanosleep;
+mod clock_nanosleep_time64;
+mod clock_settime;
+mod clock_settime64;
 mod clone;
 mod close;
 mod connect;
@@ -27,21 +34,37 @@ mod delete_module;
 mod dup;
 mod dup2;
 mod execve;
+mod execveat;
 mod exit_group;
 mod faccessat;
 mod faccessat2;
+mod fadvise64;
 mod fadvise64_64;
+mod fallocate;
+mod fanotify_init;
+mod fanotify_mark;
 mod fchdir;
 mod fchmod;
 mod fchmodat;
 mod fcntl;
 mod fcntl64;
+mod fdatasync;
+mod fgetxattr;
 mod finit_module;
+mod flistxattr;
+mod flock;
 mod fork;
+mod fremovexattr;
+mod fsetxattr;
 mod fstat64;
 mod fstatfs;
 mod fstatfs64;
 mod fsync;
+mod ftruncate;
+mod futex;
+mod futex_time64;
+mod get_robust_list;
+mod get_thread_area;
 mod getcwd;
 mod getdents;
 mod getdents64;
@@ -54,34 +77,76 @@ mod getgid32;
 mod getpgid;
 mod getpid;
 mod getppid;
+mod getpriority;
 mod getrandom;
+mod getresgid;
+mod getresgid32;
+mod getresuid;
+mod getresuid32;
+mod getrlimit;
 mod getrusage;
+mod getsid;
 mod getsockname;
 mod getsockopt;
 mod gettid;
 mod getuid;
 mod getuid32;
+mod getxattr;
 mod init_module;
+mod inotify_add_watch;
+mod inotify_init;
+mod inotify_init1;
+mod inotify_rm_watch;
+mod io_cancel;
+mod io_destroy;
+mod io_getevents;
+mod io_setup;
+mod io_submit;
+mod io_uring_enter;
+mod io_uring_register;
+mod io_uring_setup;
 pub mod ioctl;
+mod kexec_load;
 mod kill;
 mod lchown;
+mod lgetxattr;
 mod link;
 mod linkat;
+mod listxattr;
+mod llistxattr;
+mod lremovexattr;
+mod lseek;
+mod lsetxattr;
 mod madvise;
+mod memfd_create;
 mod mkdir;
+mod mkdirat;
 mod mknod;
+mod mknodat;
+mod mlock;
+mod mlock2;
+mod mlockall;
 mod mmap;
 mod mmap2;
 mod mount;
 mod mprotect;
 mod msync;
+mod munlock;
+mod munlockall;
 mod munmap;
+mod name_to_handle_at;
 mod nanosleep;
+mod nice;
 mod open;
+mod open_by_handle_at;
 mod openat;
+pub mod personality;
+mod pidfd_open;
+mod pidfd_send_signal;
 mod pipe;
 mod pipe2;
 mod poll;
+mod prctl;
 mod preadv;
 mod preadv2;
 mod prlimit64;
@@ -92,23 +157,54 @@ mod read;
 mod readlink;
 mod readv;
 mod reboot;
+mod removexattr;
 mod rename;
 mod renameat2;
+pub mod restart_syscall;
 mod rmdir;
 mod rt_sigaction;
 mod rt_sigprocmask;
+mod rt_sigqueueinfo;
+mod rt_sigtimedwait;
+mod rt_sigtimedwait_time64;
+mod sched_get_priority_max;
+mod sched_get_priority_min;
+mod sched_getaffinity;
+mod sched_getparam;
+mod sched_getscheduler;
+mod sched_setaffinity;
+mod sched_setparam;
+mod sched_setscheduler;
 mod sched_yield;
+mod seccomp;
 mod select;
+mod sendfile;
+mod sendfile64;
 mod sendto;
+mod set_robust_list;
 mod set_thread_area;
 mod set_tid_address;
+mod setfsgid;
+mod setfsgid32;
+mod setfsuid;
+mod setfsuid32;
 mod setgid;
 mod setgid32;
 mod sethostname;
+mod setns;
 mod setpgid;
+mod setpriority;
+mod setresgid;
+mod setresgid32;
+mod setresuid;
+mod setresuid32;
+mod setrlimit;
+mod setsid;
 mod setsockopt;
+mod settimeofday;
 mod setuid;
 mod setuid32;
+mod setxattr;
 mod shutdown;
 mod signal;
 mod sigreturn;
@@ -120,40 +216,56 @@ mod statfs64;
 mod statx;
 mod symlink;
 mod symlinkat;
+mod sync;
 mod syncfs;
+mod sysinfo;
+mod tee;
 mod time;
 mod timer_create;
 mod timer_delete;
 mod timer_settime;
+mod times;
 mod tkill;
 mod truncate;
+mod ugetrlimit;
 mod umask;
 mod umount;
 mod uname;
 mod unlink;
 mod unlinkat;
+mod unshare;
 mod util;
 mod utimensat;
 mod vfork;
+mod vmsplice;
 mod wait;
 mod wait4;
+mod waitid;
 mod waitpid;
 mod write;
 mod writev;
 
 use crate::errno::Errno;
+use crate::process;
 use crate::process::regs::Regs;
 use crate::process::signal::Signal;
 use crate::process::Process;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::unit::TimestampScale;
+use core::cmp::min;
 
 //use wait::wait;
 use _exit::_exit;
 use _llseek::_llseek;
 use _newselect::_newselect;
 use access::access;
+use acct::acct;
 use arch_prctl::arch_prctl;
 use bind::bind;
 use brk::brk;
+use capget::capget;
+use capset::capset;
 use chdir::chdir;
 use chmod::chmod;
 use chown::chown;
@@ -161,6 +273,10 @@ use chown32::chown32;
 use chroot::chroot;
 use clock_gettime::clock_gettime;
 use clock_gettime64::clock_gettime64;
+use clock_nanosleep::clock_nanosleep;
+use clock_nanosleep_time64::clock_nanosleep_time64;
+use clock_settime::clock_settime;
+use clock_settime64::clock_settime64;
 use clone::clone;
 use close::close;
 use connect::connect;
@@ -169,21 +285,37 @@ use delete_module::delete_module;
 use dup::dup;
 use dup2::dup2;
 use execve::execve;
+use execveat::execveat;
 use exit_group::exit_group;
 use faccessat::faccessat;
 use faccessat2::faccessat2;
+use fadvise64::fadvise64;
 use fadvise64_64::fadvise64_64;
+use fallocate::fallocate;
+use fanotify_init::fanotify_init;
+use fanotify_mark::fanotify_mark;
 use fchdir::fchdir;
 use fchmod::fchmod;
 use fchmodat::fchmodat;
 use fcntl::fcntl;
 use fcntl64::fcntl64;
+use fdatasync::fdatasync;
+use fgetxattr::fgetxattr;
 use finit_module::finit_module;
+use flistxattr::flistxattr;
+use flock::flock;
 use fork::fork;
+use fremovexattr::fremovexattr;
+use fsetxattr::fsetxattr;
 use fstat64::fstat64;
 use fstatfs::fstatfs;
 use fstatfs64::fstatfs64;
 use fsync::fsync;
+use ftruncate::ftruncate;
+use futex::futex;
+use futex_time64::futex_time64;
+use get_robust_list::get_robust_list;
+use get_thread_area::get_thread_area;
 use getcwd::getcwd;
 use getdents::getdents;
 use getdents64::getdents64;
@@ -196,34 +328,76 @@ use getgid32::getgid32;
 use getpgid::getpgid;
 use getpid::getpid;
 use getppid::getppid;
+use getpriority::getpriority;
 use getrandom::getrandom;
+use getresgid::getresgid;
+use getresgid32::getresgid32;
+use getresuid::getresuid;
+use getresuid32::getresuid32;
+use getrlimit::getrlimit;
 use getrusage::getrusage;
+use getsid::getsid;
 use getsockname::getsockname;
 use getsockopt::getsockopt;
 use gettid::gettid;
 use getuid::getuid;
 use getuid32::getuid32;
+use getxattr::getxattr;
 use init_module::init_module;
+use inotify_add_watch::inotify_add_watch;
+use inotify_init::inotify_init;
+use inotify_init1::inotify_init1;
+use inotify_rm_watch::inotify_rm_watch;
+use io_cancel::io_cancel;
+use io_destroy::io_destroy;
+use io_getevents::io_getevents;
+use io_setup::io_setup;
+use io_submit::io_submit;
+use io_uring_enter::io_uring_enter;
+use io_uring_register::io_uring_register;
+use io_uring_setup::io_uring_setup;
 use ioctl::ioctl;
+use kexec_load::kexec_load;
 use kill::kill;
 use lchown::lchown;
+use lgetxattr::lgetxattr;
 use link::link;
 use linkat::linkat;
+use listxattr::listxattr;
+use llistxattr::llistxattr;
+use lremovexattr::lremovexattr;
+use lseek::lseek;
+use lsetxattr::lsetxattr;
 use madvise::madvise;
+use memfd_create::memfd_create;
 use mkdir::mkdir;
+use mkdirat::mkdirat;
 use mknod::mknod;
+use mknodat::mknodat;
+use mlock::mlock;
+use mlock2::mlock2;
+use mlockall::mlockall;
 use mmap::mmap;
 use mmap2::mmap2;
 use mount::mount;
 use mprotect::mprotect;
 use msync::msync;
+use munlock::munlock;
+use munlockall::munlockall;
 use munmap::munmap;
+use name_to_handle_at::name_to_handle_at;
 use nanosleep::nanosleep;
+use nice::nice;
 use open::open;
+use open_by_handle_at::open_by_handle_at;
 use openat::openat;
+use personality::personality;
+use pidfd_open::pidfd_open;
+use pidfd_send_signal::pidfd_send_signal;
 use pipe::pipe;
 use pipe2::pipe2;
 use poll::poll;
+use prctl::prctl;
 use preadv::preadv;
 use preadv2::preadv2;
 use prlimit64::prlimit64;
@@ -235,23 +409,54 @@ use read::read;
 use readlink::readlink;
 use readv::readv;
 use reboot::reboot;
+use removexattr::removexattr;
 use rename::rename;
 use renameat2::renameat2;
+use restart_syscall::restart_syscall;
 use rmdir::rmdir;
 use rt_sigaction::rt_sigaction;
 use rt_sigprocmask::rt_sigprocmask;
+use rt_sigqueueinfo::rt_sigqueueinfo;
+use rt_sigtimedwait::rt_sigtimedwait;
+use rt_sigtimedwait_time64::rt_sigtimedwait_time64;
+use sched_get_priority_max::sched_get_priority_max;
+use sched_get_priority_min::sched_get_priority_min;
+use sched_getaffinity::sched_getaffinity;
+use sched_getparam::sched_getparam;
+use sched_getscheduler::sched_getscheduler;
+use sched_setaffinity::sched_setaffinity;
+use sched_setparam::sched_setparam;
+use sched_setscheduler::sched_setscheduler;
 use sched_yield::sched_yield;
+use seccomp::seccomp;
 use select::select;
+use sendfile::sendfile;
+use sendfile64::sendfile64;
 use sendto::sendto;
+use set_robust_list::set_robust_list;
 use set_thread_area::set_thread_area;
 use set_tid_address::set_tid_address;
+use setfsgid::setfsgid;
+use setfsgid32::setfsgid32;
+use setfsuid::setfsuid;
+use setfsuid32::setfsuid32;
 use setgid::setgid;
 use setgid32::setgid32;
 use sethostname::sethostname;
+use setns::setns;
 use setpgid::setpgid;
+use setpriority::setpriority;
+use setresgid::setresgid;
+use setresgid32::setresgid32;
+use setresuid::setresuid;
+use setresuid32::setresuid32;
+use setrlimit::setrlimit;
+use setsid::setsid;
 use setsockopt::setsockopt;
+use settimeofday::settimeofday;
 use setuid::setuid;
 use setuid32::setuid32;
+use setxattr::setxattr;
 use shutdown::shutdown;
 use signal::signal;
 use sigreturn::sigreturn;
@@ -263,21 +468,29 @@ use statfs64::statfs64;
 use statx::statx;
 use symlink::symlink;
 use symlinkat::symlinkat;
+use sync::sync;
 use syncfs::syncfs;
+use sysinfo::sysinfo;
+use tee::tee;
 use time::time;
 use timer_create::timer_create;
 use timer_delete::timer_delete;
 use timer_settime::timer_settime;
+use times::times;
 use tkill::tkill;
 use truncate::truncate;
+use ugetrlimit::ugetrlimit;
 use umask::umask;
 use umount::umount;
 use uname::uname;
 use unlink::unlink;
 use unlinkat::unlinkat;
+use unshare::unshare;
 use utimensat::utimensat;
 use vfork::vfork;
+use vmsplice::vmsplice;
 use wait4::wait4;
+use waitid::waitid;
 use waitpid::waitpid;
 use write::write;
 use writev::writev;
@@ -289,6 +502,7 @@ type SyscallHandler = &'static dyn Fn(&Regs) -> Result<i32, Errno>;
 /// If the syscall doesn't exist, the function returns `None`.
 fn get_syscall(id: u32) -> Option<SyscallHandler> {
 	match id {
+		0x000 => Some(&restart_syscall),
 		0x001 => Some(&_exit),
 		0x002 => Some(&fork),
 		0x003 => Some(&read),
@@ -307,7 +521,7 @@ fn get_syscall(id: u32) -> Option<SyscallHandler> {
 		0x010 => Some(&lchown),
 		0x011 => Some(&r#break),
 		// TODO 0x012 => Some(&oldstat),
-		// TODO 0x013 => Some(&lseek),
+		0x013 => Some(&lseek),
 		0x014 => Some(&getpid),
 		0x015 => Some(&mount),
 		0x016 => Some(&umount),
@@ -322,16 +536,16 @@ fn get_syscall(id: u32) -> Option<SyscallHandler> {
 		// TODO 0x01f => Some(&stty),
 		// TODO 0x020 => Some(&gtty),
 		0x021 => Some(&access),
-		// TODO 0x022 => Some(&nice),
+		0x022 => Some(&nice),
 		// TODO 0x023 => Some(&ftime),
-		// TODO 0x024 => Some(&sync),
+		0x024 => Some(&sync),
 		0x025 => Some(&kill),
 		0x026 => Some(&rename),
 		0x027 => Some(&mkdir),
 		0x028 => Some(&rmdir),
 		0x029 => Some(&dup),
 		0x02a => Some(&pipe),
-		// TODO 0x02b => Some(&times),
+		0x02b => Some(&times),
 		// TODO 0x02c => Some(&prof),
 		0x02d => Some(&brk),
 		0x02e => Some(&setgid),
@@ -339,7 +553,7 @@ fn get_syscall(id: u32) -> Option<SyscallHandler> {
 		0x030 => Some(&signal),
 		0x031 => Some(&geteuid),
 		0x032 => Some(&getegid),
-		// TODO 0x033 => Some(&acct),
+		0x033 => Some(&acct),
 		// TODO 0x034 => Some(&umount2),
 		// TODO 0x035 => Some(&lock),
 		0x036 => Some(&ioctl),
@@ -354,7 +568,7 @@ fn get_syscall(id: u32) -> Option<SyscallHandler> {
 		0x03f => Some(&dup2),
 		0x040 => Some(&getppid),
 		// TODO 0x041 => Some(&getpgrp),
-		// TODO 0x042 => Some(&setsid),
+		0x042 => Some(&setsid),
 		// TODO 0x043 => Some(&sigaction),
 		// TODO 0x044 => Some(&sgetmask),
 		// TODO 0x045 => Some(&ssetmask),
@@ -363,11 +577,11 @@ fn get_syscall(id: u32) -> Option<SyscallHandler> {
 		// TODO 0x048 => Some(&sigsuspend),
 		// TODO 0x049 => Some(&sigpending),
 		0x04a => Some(&sethostname),
-		// TODO 0x04b => Some(&setrlimit),
-		// TODO 0x04c => Some(&getrlimit),
+		0x04b => Some(&setrlimit),
+		0x04c => Some(&getrlimit),
 		0x04d => Some(&getrusage),
 		// TODO 0x04e => Some(&gettimeofday),
-		// TODO 0x04f => Some(&settimeofday),
+		0x04f => Some(&settimeofday),
 		// TODO 0x050 => Some(&getgroups),
 		// TODO 0x051 => Some(&setgroups),
 		0x052 => Some(&select),
@@ -381,11 +595,11 @@ fn get_syscall(id: u32) -> Option<SyscallHandler> {
 		0x05a => Some(&mmap),
 		0x05b => Some(&munmap),
 		0x05c => Some(&truncate),
-		// TODO 0x05d => Some(&ftruncate),
+		0x05d => Some(&ftruncate),
 		0x05e => Some(&fchmod),
 		// TODO 0x05f => Some(&fchown),
-		// TODO 0x060 => Some(&getpriority),
-		// TODO 0x061 => Some(&setpriority),
+		0x060 => Some(&getpriority),
+		0x061 => Some(&setpriority),
 		// TODO 0x062 => Some(&profil),
 		0x063 => Some(&statfs),
 		0x064 => Some(&fstatfs),
@@ -404,7 +618,7 @@ fn get_syscall(id: u32) -> Option<SyscallHandler> {
 		// TODO 0x071 => Some(&vm86old),
 		0x072 => Some(&wait4),
 		// TODO 0x073 => Some(&swapoff),
-		// TODO 0x074 => Some(&sysinfo),
+		0x074 => Some(&sysinfo),
 		// TODO 0x075 => Some(&ipc),
 		0x076 => Some(&fsync),
 		0x077 => Some(&sigreturn),
@@ -422,62 +636,62 @@ fn get_syscall(id: u32) -> Option<SyscallHandler> {
 		0x085 => Some(&fchdir),
 		// TODO 0x086 => Some(&bdflush),
 		// TODO 0x087 => Some(&sysfs),
-		// TODO 0x088 => Some(&personality),
+		0x088 => Some(&personality),
 		// TODO 0x089 => Some(&afs_syscall),
-		// TODO 0x08a => Some(&setfsuid),
-		// TODO 0x08b => Some(&setfsgid),
+		0x08a => Some(&setfsuid),
+		0x08b => Some(&setfsgid),
 		0x08c => Some(&_llseek),
 		0x08d => Some(&getdents),
 		0x08e => Some(&_newselect),
-		// TODO 0x08f => Some(&flock),
+		0x08f => Some(&flock),
 		0x090 => Some(&msync),
 		0x091 => Some(&readv),
 		0x092 => Some(&writev),
-		// TODO 0x093 => Some(&getsid),
-		// TODO 0x094 => Some(&fdatasync),
+		0x093 => Some(&getsid),
+		0x094 => Some(&fdatasync),
 		// TODO 0x095 => Some(&_sysctl),
-		// TODO 0x096 => Some(&mlock),
-		// TODO 0x097 => Some(&munlock),
-		// TODO 0x098 => Some(&mlockall),
-		// TODO 0x099 => Some(&munlockall),
-		// TODO 0x09a => Some(&sched_setparam),
-		// TODO 0x09b => Some(&sched_getparam),
-		// TODO 0x09c => Some(&sched_setscheduler),
-		// TODO 0x09d => Some(&sched_getscheduler),
+		0x096 => Some(&mlock),
+		0x097 => Some(&munlock),
+		0x098 => Some(&mlockall),
+		0x099 => Some(&munlockall),
+		0x09a => Some(&sched_setparam),
+		0x09b => Some(&sched_getparam),
+		0x09c => Some(&sched_setscheduler),
+		0x09d => Some(&sched_getscheduler),
 		0x09e => Some(&sched_yield),
-		// TODO 0x09f => Some(&sched_get_priority_max),
-		// TODO 0x0a0 => Some(&sched_get_priority_min),
+		0x09f => Some(&sched_get_priority_max),
+		0x0a0 => Some(&sched_get_priority_min),
 		// TODO 0x0a1 => Some(&sched_rr_get_interval),
 		0x0a2 => Some(&nanosleep),
 		// TODO 0x0a3 => Some(&mremap),
-		// TODO 0x0a4 => Some(&setresuid),
-		// TODO 0x0a5 => Some(&getresuid),
+		0x0a4 => Some(&setresuid),
+		0x0a5 => Some(&getresuid),
 		// TODO 0x0a6 => Some(&vm86),
 		// TODO 0x0a7 => Some(&query_module),
 		0x0a8 => Some(&poll),
 		// TODO 0x0a9 => Some(&nfsservctl),
-		// TODO 0x0aa => Some(&setresgid),
-		// TODO 0x0ab => Some(&getresgid),
-		// TODO 0x0ac => Some(&prctl),
+		0x0aa => Some(&setresgid),
+		0x0ab => Some(&getresgid),
+		0x0ac => Some(&prctl),
 		// TODO 0x0ad => Some(&rt_sigreturn),
 		0x0ae => Some(&rt_sigaction),
 		0x0af => Some(&rt_sigprocmask),
 		// TODO 0x0b0 => Some(&rt_sigpending),
-		// TODO 0x0b1 => Some(&rt_sigtimedwait),
-		// TODO 0x0b2 => Some(&rt_sigqueueinfo),
+		0x0b1 => Some(&rt_sigtimedwait),
+		0x0b2 => Some(&rt_sigqueueinfo),
 		// TODO 0x0b3 => Some(&rt_sigsuspend),
 		// TODO 0x0b4 => Some(&pread64),
 		// TODO 0x0b5 => Some(&pwrite64),
 		0x0b6 => Some(&chown),
 		0x0b7 => Some(&getcwd),
-		// TODO 0x0b8 => Some(&capget),
-		// TODO 0x0b9 => Some(&capset),
+		0x0b8 => Some(&capget),
+		0x0b9 => Some(&capset),
 		// TODO 0x0ba => Some(&sigaltstack),
-		// TODO 0x0bb => Some(&sendfile),
+		0x0bb => Some(&sendfile),
 		// TODO 0x0bc => Some(&getpmsg),
 		// TODO 0x0bd => Some(&putpmsg),
 		0x0be => Some(&vfork),
-		// TODO 0x0bf => Some(&ugetrlimit),
+		0x0bf => Some(&ugetrlimit),
 		0x0c0 => Some(&mmap2),
 		// TODO 0x0c1 => Some(&truncate64),
 		// TODO 0x0c2 => Some(&ftruncate64),
@@ -494,15 +708,15 @@ fn get_syscall(id: u32) -> Option<SyscallHandler> {
 		// TODO 0x0cd => Some(&getgroups32),
 		// TODO 0x0ce => Some(&setgroups32),
 		// TODO 0x0cf => Some(&fchown32),
-		// TODO 0x0d0 => Some(&setresuid32),
-		// TODO 0x0d1 => Some(&getresuid32),
-		// TODO 0x0d2 => Some(&setresgid32),
-		// TODO 0x0d3 => Some(&getresgid32),
+		0x0d0 => Some(&setresuid32),
+		0x0d1 => Some(&getresuid32),
+		0x0d2 => Some(&setresgid32),
+		0x0d3 => Some(&getresgid32),
 		0x0d4 => Some(&chown32),
 		0x0d5 => Some(&setuid32),
 		0x0d6 => Some(&setgid32),
-		// TODO 0x0d7 => Some(&setfsuid32),
-		// TODO 0x0d8 => Some(&setfsgid32),
+		0x0d7 => Some(&setfsuid32),
+		0x0d8 => Some(&setfsgid32),
 		// TODO 0x0d9 => Some(&pivot_root),
 		// TODO 0x0da => Some(&mincore),
 		0x0db => Some(&madvise),
@@ -510,31 +724,31 @@ fn get_syscall(id: u32) -> Option<SyscallHandler> {
 		0x0dd => Some(&fcntl64),
 		0x0e0 => Some(&gettid),
 		// TODO 0x0e1 => Some(&readahead),
-		// TODO 0x0e2 => Some(&setxattr),
-		// TODO 0x0e3 => Some(&lsetxattr),
-		// TODO 0x0e4 => Some(&fsetxattr),
-		// TODO 0x0e5 => Some(&getxattr),
-		// TODO 0x0e6 => Some(&lgetxattr),
-		// TODO 0x0e7 => Some(&fgetxattr),
-		// TODO 0x0e8 => Some(&listxattr),
-		// TODO 0x0e9 => Some(&llistxattr),
-		// TODO 0x0ea => Some(&flistxattr),
-		// TODO 0x0eb => Some(&removexattr),
-		// TODO 0x0ec => Some(&lremovexattr),
-		// TODO 0x0ed => Some(&fremovexattr),
+		0x0e2 => Some(&setxattr),
+		0x0e3 => Some(&lsetxattr),
+		0x0e4 => Some(&fsetxattr),
+		0x0e5 => Some(&getxattr),
+		0x0e6 => Some(&lgetxattr),
+		0x0e7 => Some(&fgetxattr),
+		0x0e8 => Some(&listxattr),
+		0x0e9 => Some(&llistxattr),
+		0x0ea => Some(&flistxattr),
+		0x0eb => Some(&removexattr),
+		0x0ec => Some(&lremovexattr),
+		0x0ed => Some(&fremovexattr),
 		0x0ee => Some(&tkill),
-		// TODO 0x0ef => Some(&sendfile64),
-		// TODO 0x0f0 => Some(&futex),
-		// TODO 0x0f1 => Some(&sched_setaffinity),
-		// TODO 0x0f2 => Some(&sched_getaffinity),
+		0x0ef => Some(&sendfile64),
+		0x0f0 => Some(&futex),
+		0x0f1 => Some(&sched_setaffinity),
+		0x0f2 => Some(&sched_getaffinity),
 		0x0f3 => Some(&set_thread_area),
-		// TODO 0x0f4 => Some(&get_thread_area),
-		// TODO 0x0f5 => Some(&io_setup),
-		// TODO 0x0f6 => Some(&io_destroy),
-		// TODO 0x0f7 => Some(&io_getevents),
-		// TODO 0x0f8 => Some(&io_submit),
-		// TODO 0x0f9 => Some(&io_cancel),
-		// TODO 0x0fa => Some(&fadvise64),
+		0x0f4 => Some(&get_thread_area),
+		0x0f5 => Some(&io_setup),
+		0x0f6 => Some(&io_destroy),
+		0x0f7 => Some(&io_getevents),
+		0x0f8 => Some(&io_submit),
+		0x0f9 => Some(&io_cancel),
+		0x0fa => Some(&fadvise64),
 		0x0fc => Some(&exit_group),
 		// TODO 0x0fd => Some(&lookup_dcookie),
 		// TODO 0x0fe => Some(&epoll_create),
@@ -547,10 +761,10 @@ fn get_syscall(id: u32) -> Option<SyscallHandler> {
 		// TODO 0x105 => Some(&timer_gettime),
 		// TODO 0x106 => Some(&timer_getoverrun),
 		0x107 => Some(&timer_delete),
-		// TODO 0x108 => Some(&clock_settime),
+		0x108 => Some(&clock_settime),
 		0x109 => Some(&clock_gettime),
 		// TODO 0x10a => Some(&clock_getres),
-		// TODO 0x10b => Some(&clock_nanosleep),
+		0x10b => Some(&clock_nanosleep),
 		0x10c => Some(&statfs64),
 		0x10d => Some(&fstatfs64),
 		// TODO 0x10e => Some(&tgkill),
@@ -566,20 +780,20 @@ fn get_syscall(id: u32) -> Option<SyscallHandler> {
 		// TODO 0x118 => Some(&mq_timedreceive),
 		// TODO 0x119 => Some(&mq_notify),
 		// TODO 0x11a => Some(&mq_getsetattr),
-		// TODO 0x11b => Some(&kexec_load),
-		// TODO 0x11c => Some(&waitid),
+		0x11b => Some(&kexec_load),
+		0x11c => Some(&waitid),
 		// TODO 0x11e => Some(&add_key),
 		// TODO 0x11f => Some(&request_key),
 		// TODO 0x120 => Some(&keyctl),
 		// TODO 0x121 => Some(&ioprio_set),
 		// TODO 0x122 => Some(&ioprio_get),
-		// TODO 0x123 => Some(&inotify_init),
-		// TODO 0x124 => Some(&inotify_add_watch),
-		// TODO 0x125 => Some(&inotify_rm_watch),
+		0x123 => Some(&inotify_init),
+		0x124 => Some(&inotify_add_watch),
+		0x125 => Some(&inotify_rm_watch),
 		// TODO 0x126 => Some(&migrate_pages),
 		0x127 => Some(&openat),
-		// TODO 0x128 => Some(&mkdirat),
-		// TODO 0x129 => Some(&mknodat),
+		0x128 => Some(&mkdirat),
+		0x129 => Some(&mknodat),
 		// TODO 0x12a => Some(&fchownat),
 		// TODO 0x12b => Some(&futimesat),
 		// TODO 0x12c => Some(&fstatat64),
@@ -592,13 +806,13 @@ fn get_syscall(id: u32) -> Option<SyscallHandler> {
 		0x133 => Some(&faccessat),
 		0x134 => Some(&pselect6),
 		// TODO 0x135 => Some(&ppoll),
-		// TODO 0x136 => Some(&unshare),
-		// TODO 0x137 => Some(&set_robust_list),
-		// TODO 0x138 => Some(&get_robust_list),
+		0x136 => Some(&unshare),
+		0x137 => Some(&set_robust_list),
+		0x138 => Some(&get_robust_list),
 		0x139 => Some(&splice),
 		// TODO 0x13a => Some(&sync_file_range),
-		// TODO 0x13b => Some(&tee),
-		// TODO 0x13c => Some(&vmsplice),
+		0x13b => Some(&tee),
+		0x13c => Some(&vmsplice),
 		// TODO 0x13d => Some(&move_pages),
 		// TODO 0x13e => Some(&getcpu),
 		// TODO 0x13f => Some(&epoll_pwait),
@@ -606,7 +820,7 @@ fn get_syscall(id: u32) -> Option<SyscallHandler> {
 		// TODO 0x141 => Some(&signalfd),
 		// TODO 0x142 => Some(&timerfd_create),
 		// TODO 0x143 => Some(&eventfd),
-		// TODO 0x144 => Some(&fallocate),
+		0x144 => Some(&fallocate),
 		// TODO 0x145 => Some(&timerfd_settime),
 		// TODO 0x146 => Some(&timerfd_gettime),
 		// TODO 0x147 => Some(&signalfd4),
@@ -614,21 +828,21 @@ fn get_syscall(id: u32) -> Option<SyscallHandler> {
 		// TODO 0x149 => Some(&epoll_create1),
 		// TODO 0x14a => Some(&dup3),
 		0x14b => Some(&pipe2),
-		// TODO 0x14c => Some(&inotify_init1),
+		0x14c => Some(&inotify_init1),
 		0x14d => Some(&preadv),
 		0x14e => Some(&pwritev),
 		// TODO 0x14f => Some(&rt_tgsigqueueinfo),
 		// TODO 0x150 => Some(&perf_event_open),
 		// TODO 0x151 => Some(&recvmmsg),
-		// TODO 0x152 => Some(&fanotify_init),
-		// TODO 0x153 => Some(&fanotify_mark),
+		0x152 => Some(&fanotify_init),
+		0x153 => Some(&fanotify_mark),
 		0x154 => Some(&prlimit64),
-		// TODO 0x155 => Some(&name_to_handle_at),
-		// TODO 0x156 => Some(&open_by_handle_at),
+		0x155 => Some(&name_to_handle_at),
+		0x156 => Some(&open_by_handle_at),
 		// TODO 0x157 => Some(&clock_adjtime),
 		0x158 => Some(&syncfs),
 		// TODO 0x159 => Some(&sendmmsg),
-		// TODO 0x15a => Some(&setns),
+		0x15a => Some(&setns),
 		// TODO 0x15b => Some(&process_vm_readv),
 		// TODO 0x15c => Some(&process_vm_writev),
 		// TODO 0x15d => Some(&kcmp),
@@ -636,11 +850,11 @@ fn get_syscall(id: u32) -> Option<SyscallHandler> {
 		// TODO 0x15f => Some(&sched_setattr),
 		// TODO 0x160 => Some(&sched_getattr),
 		0x161 => Some(&renameat2),
-		// TODO 0x162 => Some(&seccomp),
+		0x162 => Some(&seccomp),
 		0x163 => Some(&getrandom),
-		// TODO 0x164 => Some(&memfd_create),
+		0x164 => Some(&memfd_create),
 		// TODO 0x165 => Some(&bpf),
-		// TODO 0x166 => Some(&execveat),
+		0x166 => Some(&execveat),
 		0x167 => Some(&socket),
 		0x168 => Some(&socketpair),
 		0x169 => Some(&bind),
@@ -658,7 +872,7 @@ fn get_syscall(id: u32) -> Option<SyscallHandler> {
 		0x175 => Some(&shutdown),
 		// TODO 0x176 => Some(&userfaultfd),
 		// TODO 0x177 => Some(&membarrier),
-		// TODO 0x178 => Some(&mlock2),
+		0x178 => Some(&mlock2),
 		// TODO 0x179 => Some(&copy_file_range),
 		0x17a => Some(&preadv2),
 		0x17b => Some(&pwritev2),
@@ -680,10 +894,10 @@ fn get_syscall(id: u32) -> Option<SyscallHandler> {
 		// TODO 0x191 => Some(&msgrcv),
 		// TODO 0x192 => Some(&msgctl),
 		0x193 => Some(&clock_gettime64),
-		// TODO 0x194 => Some(&clock_settime64),
+		0x194 => Some(&clock_settime64),
 		// TODO 0x195 => Some(&clock_adjtime64),
 		// TODO 0x196 => Some(&clock_getres_time64),
-		// TODO 0x197 => Some(&clock_nanosleep_time64),
+		0x197 => Some(&clock_nanosleep_time64),
 		// TODO 0x198 => Some(&timer_gettime64),
 		// TODO 0x199 => Some(&timer_settime64),
 		// TODO 0x19a => Some(&timerfd_gettime64),
@@ -696,20 +910,20 @@ fn get_syscall(id: u32) -> Option<SyscallHandler> {
 		// TODO 0x1a2 => Some(&mq_timedsend_time64),
 		// TODO 0x1a3 => Some(&mq_timedreceive_time64),
 		// TODO 0x1a4 => Some(&semtimedop_time64),
-		// TODO 0x1a5 => Some(&rt_sigtimedwait_time64),
-		// TODO 0x1a6 => Some(&futex_time64),
+		0x1a5 => Some(&rt_sigtimedwait_time64),
+		0x1a6 => Some(&futex_time64),
 		// TODO 0x1a7 => Some(&sched_rr_get_interval_time64),
-		// TODO 0x1a8 => Some(&pidfd_send_signal),
-		// TODO 0x1a9 => Some(&io_uring_setup),
-		// TODO 0x1aa => Some(&io_uring_enter),
-		// TODO 0x1ab => Some(&io_uring_register),
+		0x1a8 => Some(&pidfd_send_signal),
+		0x1a9 => Some(&io_uring_setup),
+		0x1aa => Some(&io_uring_enter),
+		0x1ab => Some(&io_uring_register),
 		// TODO 0x1ac => Some(&open_tree),
 		// TODO 0x1ad => Some(&move_mount),
 		// TODO 0x1ae => Some(&fsopen),
 		// TODO 0x1af => Some(&fsconfig),
 		// TODO 0x1b0 => Some(&fsmount),
 		// TODO 0x1b1 => Some(&fspick),
-		// TODO 0x1b2 => Some(&pidfd_open),
+		0x1b2 => Some(&pidfd_open),
 		// TODO 0x1b3 => Some(&clone3),
 		// TODO 0x1b4 => Some(&close_range),
 		// TODO 0x1b5 => Some(&openat2),
@@ -734,7 +948,57 @@ fn get_syscall(id: u32) -> Option<SyscallHandler> {
 #[no_mangle]
 pub extern "C" fn syscall_handler(regs: &mut Regs) {
 	let id = regs.eax;
-	let result = match get_syscall(id) {
+
+	let action = {
+		let proc_mutex = Process::current_assert();
+		let mut proc = proc_mutex.lock();
+		// Until now, the process was running in userspace
+		let now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond).unwrap_or(0);
+		proc.account_cpu_time(now, true);
+		// Check the system call against the secure computing mode of the process
+		proc.seccomp.check(regs)
+	};
+	let result = match action {
+		process::seccomp::Action::Allow => do_syscall(id, regs),
+		// The errno is clamped to the maximum errno value
+		process::seccomp::Action::Errno(errno) => Ok(-(min(errno, 4095) as i32)),
+		process::seccomp::Action::Trap => {
+			Process::current_assert()
+				.lock()
+				.kill(&Signal::SIGSYS, false);
+			Err(errno!(ENOSYS))
+		}
+		process::seccomp::Action::KillThread => {
+			Process::current_assert()
+				.lock()
+				.exit(Signal::SIGSYS.get_id() as _, true);
+			crate::enter_loop();
+		}
+		process::seccomp::Action::KillProcess => {
+			Process::current_assert().lock().kill(&Signal::SIGSYS, true);
+			crate::enter_loop();
+		}
+	};
+
+	match result {
+		Err(errno) if errno.is_restart() => util::handle_interrupted(id, regs, errno),
+		_ => regs.set_syscall_return(result),
+	}
+
+	process::reap_threads();
+
+	// The system call has been executed in kernelspace
+	if let Some(proc_mutex) = Process::current() {
+		let now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond).unwrap_or(0);
+		proc_mutex.lock().account_cpu_time(now, false);
+	}
+}
+
+/// Performs the system call with the given ID.
+///
+/// If the system call doesn't exist, the process is killed.
+fn do_syscall(id: u32, regs: &Regs) -> Result<i32, Errno> {
+	match get_syscall(id) {
 		Some(handler) => (handler)(regs),
 
 		// The system call doesn't exist. Kill the process with SIGSYS
@@ -757,7 +1021,5 @@ pub extern "C" fn syscall_handler(regs: &mut Regs) {
 
 			crate::enter_loop();
 		}
-	};
-
-	regs.set_syscall_return(result);
+	}
 }
@@ -5,33 +5,210 @@ use crate::errno::Errno;
 use crate::file::fs;
 use crate::file::mountpoint;
 use crate::file::mountpoint::MountSource;
+use crate::file::mountpoint::PropagationType;
 use crate::file::path::Path;
+use crate::file::perm::CAP_SYS_ADMIN;
 use crate::file::vfs;
+use crate::file::vfs::ResolutionSettings;
+use crate::file::writeback;
 use crate::file::FileType;
-use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
+use crate::util::container::vec::Vec;
 use crate::util::TryClone;
 use core::ffi::c_ulong;
-use core::ffi::c_void;
 use macros::syscall;
 
+/// Mount flag: mount the filesystem in read-only.
+const MS_RDONLY: c_ulong = 1;
+/// Mount flag: ignore set-user-ID and set-group-ID bits.
+const MS_NOSUID: c_ulong = 2;
+/// Mount flag: do not allow access to device files.
+const MS_NODEV: c_ulong = 4;
+/// Mount flag: do not allow executing files.
+const MS_NOEXEC: c_ulong = 8;
+/// Mount flag: make writes synchronous.
+const MS_SYNCHRONOUS: c_ulong = 16;
+/// Mount flag: change the flags of an existing mountpoint.
+const MS_REMOUNT: c_ulong = 32;
+/// Mount flag: permit mandatory locking.
+const MS_MANDLOCK: c_ulong = 64;
+/// Mount flag: do not update access times.
+const MS_NOATIME: c_ulong = 1024;
+/// Mount flag: do not update access times of directories.
+const MS_NODIRATIME: c_ulong = 2048;
+/// Mount flag: bind the source file on the target instead of mounting a filesystem.
+const MS_BIND: c_ulong = 4096;
+/// Mount flag: with [`MS_BIND`] or a propagation flag, also apply to the mountpoints located
+/// under the source.
+const MS_REC: c_ulong = 16384;
+/// Mount flag: make the target mountpoint private.
+const MS_PRIVATE: c_ulong = 1 << 18;
+/// Mount flag: make the target mountpoint a slave of its peer group.
+const MS_SLAVE: c_ulong = 1 << 19;
+/// Mount flag: make the target mountpoint shared.
+const MS_SHARED: c_ulong = 1 << 20;
+/// Mount flag: suppress some warning messages.
+const MS_SILENT: c_ulong = 32768;
+/// Mount flag: update access times only if older than the modification or change time.
+const MS_RELATIME: c_ulong = 1 << 21;
+/// Mount flag: always update access times.
+const MS_STRICTATIME: c_ulong = 1 << 24;
+
+/// Converts the flags `mountflags` given to the system call into mountpoint flags.
+fn get_mount_flags(mountflags: c_ulong) -> u32 {
+	[
+		(MS_RDONLY, mountpoint::FLAG_RDONLY),
+		(MS_NOSUID, mountpoint::FLAG_NOSUID),
+		(MS_NODEV, mountpoint::FLAG_NODEV),
+		(MS_NOEXEC, mountpoint::FLAG_NOEXEC),
+		(MS_SYNCHRONOUS, mountpoint::FLAG_SYNCHRONOUS),
+		(MS_MANDLOCK, mountpoint::FLAG_MANDLOCK),
+		(MS_NOATIME, mountpoint::FLAG_NOATIME),
+		(MS_NODIRATIME, mountpoint::FLAG_NODIRATIME),
+		(MS_SILENT, mountpoint::FLAG_SILENT),
+		(MS_RELATIME, mountpoint::FLAG_RELATIME),
+		(MS_STRICTATIME, mountpoint::FLAG_STRICTATIME),
+	]
+	.into_iter()
+	.filter(|(ms, _)| mountflags & ms != 0)
+	.fold(0, |flags, (_, flag)| flags | flag)
+}
+
+/// Changes the flags of the mountpoint at `target` to `flags`.
+fn remount(target: SyscallString, flags: u32) -> Result<i32, Errno> {
+	let target_path = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let mem_space = proc.get_mem_space().unwrap();
+		let mem_space_guard = mem_space.lock();
+
+		let target_slice = target.get(&mem_space_guard)?.ok_or(errno!(EFAULT))?;
+		let target_path = Path::from_str(&target_slice, true)?;
+		super::util::get_absolute_path(&proc, target_path)?
+	};
+
+	// Modified files are written back before the mountpoint may become read-only
+	if flags & mountpoint::FLAG_RDONLY != 0 {
+		writeback::flush(true)?;
+	}
+	mountpoint::remount(&target_path, flags)?;
+	Ok(0)
+}
+
+/// Performs a bind mount of the file at `source` on the directory `target`.
+///
+/// `recursive` tells whether the mountpoints located under `source` are bound as well.
+fn bind(source: SyscallString, target: SyscallString, recursive: bool) -> Result<i32, Errno> {
+	let (source_mutex, target_mutex, target_path) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let mem_space = proc.get_mem_space().unwrap();
+		let mem_space_guard = mem_space.lock();
+
+		let source_slice = source.get(&mem_space_guard)?.ok_or(errno!(EFAULT))?;
+		let target_slice = target.get(&mem_space_guard)?.ok_or(errno!(EFAULT))?;
+
+		let rs = ResolutionSettings::for_process(&proc, true)?;
+		let source_path = Path::from_str(&source_slice, true)?;
+		let source_path = super::util::get_absolute_path(&proc, source_path)?;
+		let source_mutex = vfs::resolve_path(&source_path, &rs)?;
+
+		let target_path = Path::from_str(&target_slice, true)?;
+		let target_path = super::util::get_absolute_path(&proc, target_path)?;
+		let target_mutex = vfs::resolve_path(&target_path, &rs)?;
+
+		(source_mutex, target_mutex, target_path)
+	};
+
+	let source = source_mutex.lock();
+	// A directory can only be bound on a directory, and a file on a file
+	let source_dir = source.get_type() == FileType::Directory;
+	let target_dir = target_mutex.lock().get_type() == FileType::Directory;
+	match (source_dir, target_dir) {
+		(true, false) => return Err(errno!(ENOTDIR)),
+		(false, true) => return Err(errno!(EISDIR)),
+		_ => {}
+	}
+
+	mountpoint::create_bind(&source, target_path, recursive)?;
+	Ok(0)
+}
+
+/// Changes the propagation type of the mountpoint at `target`.
+///
+/// `recursive` tells whether the mountpoints located under `target` are changed as well.
+fn set_propagation(
+	target: SyscallString,
+	propagation: PropagationType,
+	recursive: bool,
+) -> Result<i32, Errno> {
+	let target_path = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let mem_space = proc.get_mem_space().unwrap();
+		let mem_space_guard = mem_space.lock();
+
+		let target_slice = target.get(&mem_space_guard)?.ok_or(errno!(EFAULT))?;
+		let target_path = Path::from_str(&target_slice, true)?;
+		super::util::get_absolute_path(&proc, target_path)?
+	};
+
+	mountpoint::set_propagation(&target_path, propagation, recursive)?;
+	Ok(0)
+}
+
 #[syscall]
 pub fn mount(
 	source: SyscallString,
 	target: SyscallString,
 	filesystemtype: SyscallString,
 	mountflags: c_ulong,
-	_data: SyscallPtr<c_void>,
+	data: SyscallString,
 ) -> Result<i32, Errno> {
-	let (mount_source, fs_type, target_path) = {
+	if !Process::current_assert()
+		.lock()
+		.access_profile
+		.has_cap(CAP_SYS_ADMIN)
+	{
+		return Err(errno!(EPERM));
+	}
+
+	let propagation = match mountflags & (MS_PRIVATE | MS_SLAVE | MS_SHARED) {
+		0 => None,
+		MS_PRIVATE => Some(PropagationType::Private),
+		MS_SLAVE => Some(PropagationType::Slave),
+		MS_SHARED => Some(PropagationType::Shared),
+		// Only one propagation type can be given at once
+		_ => return Err(errno!(EINVAL)),
+	};
+	if let Some(propagation) = propagation {
+		// The source, the filesystem type and the data are ignored
+		return set_propagation(target, propagation, mountflags & MS_REC != 0);
+	}
+	if mountflags & MS_REMOUNT != 0 {
+		// The source, the filesystem type and the data are ignored
+		return remount(target, get_mount_flags(mountflags));
+	}
+	if mountflags & MS_BIND != 0 {
+		// The filesystem type and the data are ignored
+		return bind(source, target, mountflags & MS_REC != 0);
+	}
+
+	let (mount_source, fs_type, target_path, data) = {
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
 
 		let mem_space = proc.get_mem_space().unwrap();
 		let mem_space_guard = mem_space.lock();
 
-		let cwd = proc.chroot.try_clone()?.concat(&proc.cwd)?;
+		let cwd = {
+			let fs = proc.get_fs().lock();
+			fs.chroot.try_clone()?.concat(&fs.cwd)?
+		};
 
 		// Get strings
 		let source_slice = source.get(&mem_space_guard)?.ok_or(errno!(EFAULT))?;
@@ -41,12 +218,13 @@ pub fn mount(
 			.ok_or(errno!(EFAULT))?;
 
 		// Get the mount source
-		let mount_source = MountSource::from_str(source_slice, cwd)?;
+		let mount_source = MountSource::from_str(&source_slice, cwd)?;
 
 		// Get the target file
-		let target_path = Path::from_str(target_slice, true)?;
+		let target_path = Path::from_str(&target_slice, true)?;
 		let target_path = super::util::get_absolute_path(&proc, target_path)?;
-		let target_mutex = vfs::get_file_from_path(&target_path, &proc.access_profile, true)?;
+		let rs = ResolutionSettings::for_process(&proc, true)?;
+		let target_mutex = vfs::resolve_path(&target_path, &rs)?;
 		let target_file = target_mutex.lock();
 
 		// Check the target is a directory
@@ -56,14 +234,25 @@ pub fn mount(
 
 		// TODO Check for loop between source and target
 
-		let fs_type = fs::get_type(filesystemtype_slice).ok_or(errno!(ENODEV))?;
+		let fs_type = fs::get_type(&filesystemtype_slice).ok_or(errno!(ENODEV))?;
+
+		// Get filesystem-specific options. If not specified, there is none
+		let mut data_vec = Vec::new();
+		if let Some(data_slice) = data.get(&mem_space_guard)? {
+			data_vec.extend_from_slice(&data_slice)?;
+		}
 
-		(mount_source, fs_type, target_path)
+		(mount_source, fs_type, target_path, data_vec)
 	};
 
-	// TODO Use `data`
 	// Create mountpoint
-	mountpoint::create(mount_source, Some(fs_type), mountflags, target_path)?;
+	mountpoint::create(
+		mount_source,
+		Some(fs_type),
+		get_mount_flags(mountflags),
+		target_path,
+		&data,
+	)?;
 
 	Ok(0)
 }
@@ -25,10 +25,16 @@ fn prot_to_flags(prot: i32) -> u8 {
 
 #[syscall]
 pub fn mprotect(addr: *mut c_void, len: usize, prot: c_int) -> Result<i32, Errno> {
-	// Checking alignment of `addr` and `length`
-	if !addr.is_aligned_to(memory::PAGE_SIZE) || len == 0 {
+	// Checking alignment of `addr` and validity of `prot`
+	if !addr.is_aligned_to(memory::PAGE_SIZE) {
 		return Err(errno!(EINVAL));
 	}
+	if prot & !(mmap::PROT_READ | mmap::PROT_WRITE | mmap::PROT_EXEC) != 0 {
+		return Err(errno!(EINVAL));
+	}
+	if len == 0 {
+		return Ok(0);
+	}
 	let flags = prot_to_flags(prot);
 
 	let (mem_space_mutex, ap) = {
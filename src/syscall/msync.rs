@@ -5,6 +5,7 @@ use crate::errno;
 use crate::errno::Errno;
 use crate::memory;
 use crate::process::Process;
+use crate::util::math;
 use core::ffi::c_int;
 use core::ffi::c_void;
 use macros::syscall;
@@ -22,25 +23,35 @@ pub fn msync(addr: *mut c_void, length: usize, flags: c_int) -> Result<i32, Errn
 	if !addr.is_aligned_to(memory::PAGE_SIZE) {
 		return Err(errno!(EINVAL));
 	}
-	// Checking for conflicts in flags
+	// Checking flags
+	if flags & !(MS_ASYNC | MS_SYNC | MS_INVALIDATE) != 0 {
+		return Err(errno!(EINVAL));
+	}
 	if flags & MS_ASYNC != 0 && flags & MS_SYNC != 0 {
 		return Err(errno!(EINVAL));
 	}
 
+	let pages = math::ceil_div(length, memory::PAGE_SIZE);
+	// Checking for overflow
+	if (addr as usize)
+		.checked_add(pages * memory::PAGE_SIZE)
+		.is_none()
+	{
+		return Err(errno!(ENOMEM));
+	}
+
 	let proc_mutex = Process::current_assert();
 	let proc = proc_mutex.lock();
 
 	// The process's memory space
 	let mem_space = proc.get_mem_space().unwrap();
 	let mut mem_space = mem_space.lock();
-
-	let mut i = 0;
-	while i < length {
-		let mapping = mem_space.get_mapping_mut_for(addr).ok_or(errno!(ENOMEM))?;
-		mapping.fs_sync()?; // TODO Use flags
-
-		i += mapping.get_size().get() * memory::PAGE_SIZE;
-	}
+	mem_space.sync(
+		addr,
+		pages,
+		flags & MS_SYNC != 0,
+		flags & MS_INVALIDATE != 0,
+	)?;
 
 	Ok(0)
 }
@@ -0,0 +1,32 @@
+//! The `munlock` system call unlocks pages of memory that were locked with `mlock`.
+
+use crate::errno::Errno;
+use crate::memory;
+use crate::process::Process;
+use crate::util;
+use crate::util::math;
+use core::ffi::c_void;
+use macros::syscall;
+
+#[syscall]
+pub fn munlock(addr: *const c_void, len: usize) -> Result<i32, Errno> {
+	// Rounding the range to page boundaries
+	let begin = util::down_align(addr, memory::PAGE_SIZE);
+	let len = (addr as usize - begin as usize)
+		.checked_add(len)
+		.ok_or_else(|| errno!(ENOMEM))?;
+	let pages = math::ceil_div(len, memory::PAGE_SIZE);
+	if pages == 0 {
+		return Ok(0);
+	}
+
+	let mem_space_mutex = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+		proc.get_mem_space().unwrap().clone()
+	};
+	let mut mem_space = mem_space_mutex.lock();
+	mem_space.unlock(begin, pages)?;
+
+	Ok(0)
+}
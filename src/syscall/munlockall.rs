@@ -0,0 +1,17 @@
+//! The `munlockall` system call unlocks every pages of the process's memory space.
+
+use crate::errno::Errno;
+use crate::process::Process;
+use macros::syscall;
+
+#[syscall]
+pub fn munlockall() -> Result<i32, Errno> {
+	let mem_space_mutex = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+		proc.get_mem_space().unwrap().clone()
+	};
+	mem_space_mutex.lock().unlock_all();
+
+	Ok(0)
+}
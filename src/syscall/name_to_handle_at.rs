@@ -0,0 +1,132 @@
+//! The `name_to_handle_at` system call returns a handle to a file, which allows to open it
+//! later with `open_by_handle_at`.
+//!
+//! Unlike paths, handles remain valid when the file is renamed, and across reboots. If the file
+//! is removed, the handle becomes stale.
+
+use super::access;
+use super::util;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::mountpoint;
+use crate::file::INode;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::Process;
+use crate::util::container::vec::Vec;
+use core::ffi::c_int;
+use core::mem::size_of;
+use macros::syscall;
+
+/// Handle type: a 64 bits inode number followed by a 32 bits generation number.
+pub const FILEID_INO64_GEN: c_int = 0x81;
+/// The size of a handle, in bytes.
+pub const HANDLE_SIZE: usize = 12;
+/// The maximum size of a handle, in bytes.
+pub const MAX_HANDLE_SZ: u32 = 128;
+
+/// The header of a file handle in userspace, followed by the handle itself.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct FileHandle {
+	/// The size of the handle in bytes.
+	pub handle_bytes: u32,
+	/// The type of the handle.
+	pub handle_type: c_int,
+}
+
+/// The size of the header of a file handle, in bytes.
+pub const HEADER_SIZE: usize = size_of::<FileHandle>();
+
+/// Returns the generation number of the inode `inode` on the mountpoint with ID
+/// `mountpoint_id`.
+///
+/// If the filesystem does not support file handles, the function returns an error.
+pub fn get_generation(mountpoint_id: u32, inode: INode) -> EResult<u32> {
+	let mountpoint_mutex = mountpoint::from_id(mountpoint_id).ok_or_else(|| errno!(ESTALE))?;
+	let mountpoint = mountpoint_mutex.lock();
+
+	let io_mutex = mountpoint.get_source().get_io()?;
+	let mut io = io_mutex.lock();
+
+	let fs_mutex = mountpoint.get_filesystem();
+	let mut fs = fs_mutex.lock();
+	fs.get_generation(&mut *io, inode)
+}
+
+#[syscall]
+pub fn name_to_handle_at(
+	dirfd: c_int,
+	pathname: SyscallString,
+	handle: SyscallSlice<u8>,
+	mount_id: SyscallPtr<c_int>,
+	flags: c_int,
+) -> Result<i32, Errno> {
+	if flags & !(access::AT_SYMLINK_FOLLOW | access::AT_EMPTY_PATH) != 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let (file_mutex, handle_bytes) = {
+		let proc = proc_mutex.lock();
+
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let mem_space_guard = mem_space.lock();
+
+		let header = handle
+			.get(&mem_space_guard, HEADER_SIZE)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		let handle_bytes = u32::from_ne_bytes(header[..4].try_into().unwrap());
+		if handle_bytes > MAX_HANDLE_SZ {
+			return Err(errno!(EINVAL));
+		}
+
+		let pathname = pathname
+			.get(&mem_space_guard)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		let mut path = Vec::new();
+		path.extend_from_slice(&pathname)?;
+		drop(mem_space_guard);
+
+		// Symbolic links are not followed unless specified
+		let file_mutex = util::get_file_at(proc, dirfd, &path, false, flags)?;
+		(file_mutex, handle_bytes)
+	};
+
+	let location = file_mutex.lock().get_location().clone();
+	let mountpoint_id = location
+		.get_mountpoint_id()
+		.ok_or_else(|| errno!(EOPNOTSUPP))?;
+	let inode = location.get_inode();
+	let generation = get_generation(mountpoint_id, inode)?;
+
+	let proc = proc_mutex.lock();
+	let mem_space = proc.get_mem_space().unwrap().clone();
+	let mut mem_space_guard = mem_space.lock();
+
+	// If the buffer is too small, the required size is returned to userspace
+	if (handle_bytes as usize) < HANDLE_SIZE {
+		let mut header = handle
+			.get_mut(&mut mem_space_guard, HEADER_SIZE)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		header[..4].copy_from_slice(&(HANDLE_SIZE as u32).to_ne_bytes());
+		return Err(errno!(EOVERFLOW));
+	}
+
+	{
+		let mut buf = handle
+			.get_mut(&mut mem_space_guard, HEADER_SIZE + HANDLE_SIZE)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		buf[..4].copy_from_slice(&(HANDLE_SIZE as u32).to_ne_bytes());
+		buf[4..8].copy_from_slice(&FILEID_INO64_GEN.to_ne_bytes());
+		buf[8..16].copy_from_slice(&inode.to_le_bytes());
+		buf[16..20].copy_from_slice(&generation.to_le_bytes());
+	}
+	let mut mount_id = mount_id
+		.get_mut(&mut mem_space_guard)?
+		.ok_or_else(|| errno!(EFAULT))?;
+	*mount_id = mountpoint_id as _;
+
+	Ok(0)
+}
@@ -0,0 +1,20 @@
+//! The `nice` system call changes the nice value of the current process.
+
+use super::setpriority;
+use crate::errno::Errno;
+use crate::process::rlimit;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall]
+pub fn nice(inc: c_int) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let mut proc = proc_mutex.lock();
+
+	let ap = proc.access_profile;
+	let nice_limit = proc.rlimits.get_cur(rlimit::RLIMIT_NICE);
+	let nice = proc.get_nice().saturating_add(inc);
+	setpriority::set_nice(&ap, nice_limit, &mut proc, nice)?;
+	Ok(0)
+}
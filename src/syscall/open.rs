@@ -5,12 +5,15 @@ use crate::errno;
 use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file;
+use crate::file::buffer::fanotify;
 use crate::file::fd::FD_CLOEXEC;
+use crate::file::mountpoint;
 use crate::file::open_file;
 use crate::file::open_file::OpenFile;
 use crate::file::path::Path;
 use crate::file::perm::AccessProfile;
 use crate::file::vfs;
+use crate::file::vfs::ResolutionSettings;
 use crate::file::File;
 use crate::file::FileContent;
 use crate::file::FileType;
@@ -32,42 +35,63 @@ pub const STATUS_FLAGS_MASK: i32 = !(open_file::O_CLOEXEC
 	| open_file::O_NOFOLLOW
 	| open_file::O_TRUNC);
 
+/// Flags taken into account when `O_PATH` is set.
+const PATH_FLAGS: i32 =
+	open_file::O_PATH | open_file::O_CLOEXEC | open_file::O_DIRECTORY | open_file::O_NOFOLLOW;
+
 // TODO Implement all flags
 
+/// Returns the given open flags without those which are ignored.
+pub fn filter_flags(flags: i32) -> i32 {
+	if flags & open_file::O_PATH != 0 {
+		flags & PATH_FLAGS
+	} else {
+		flags
+	}
+}
+
 /// Returns the file at the given path `path`.
 ///
 /// If the file doesn't exist and the `O_CREAT` flag is set, the file is created,
 /// then the function returns it.
 /// If the flag is not set, the function returns an error with the appropriate errno.
 ///
-/// If the file is to be created, the function uses `mode` to set its permissions and the provided
-/// access profile to set the user ID and group ID.
+/// If the file is to be created, the function uses `mode` to set its permissions and the access
+/// profile of the resolution settings `rs` to set the user ID and group ID.
 ///
-/// The access profile is also used to check permissions.
+/// The resolution settings are also used to resolve the path and check permissions.
 fn get_file(
 	path: Path,
 	flags: i32,
 	mode: Mode,
-	access_profile: &AccessProfile,
+	rs: &ResolutionSettings,
 ) -> EResult<Arc<Mutex<File>>> {
-	// Tells whether to follow symbolic links on the last component of the path.
-	let follow_links = flags & open_file::O_NOFOLLOW == 0;
+	let access_profile = &rs.access_profile;
 
 	if flags & open_file::O_CREAT != 0 {
 		// Get the path of the parent directory
 		let mut parent_path = path;
 		// The file's basename
 		let name = parent_path.pop().ok_or_else(|| errno!(ENOENT))?;
+		// Such entries always exist and are directories
+		if matches!(name.as_bytes(), b"." | b"..") {
+			return Err(errno!(EISDIR));
+		}
 
 		// The parent directory
-		let parent_mutex = vfs::get_file_from_path(&parent_path, access_profile, true)?;
+		let parent_rs = ResolutionSettings {
+			root: rs.root.try_clone()?,
+			access_profile: *access_profile,
+			follow_links: true,
+		};
+		let parent_mutex = vfs::resolve_path(&parent_path, &parent_rs)?;
 		let mut parent = parent_mutex.lock();
 
 		let file_result = vfs::get_file_from_parent(
 			&mut parent,
 			name.try_clone()?,
 			access_profile,
-			follow_links,
+			rs.follow_links,
 		);
 		let file = match file_result {
 			// If the file is found, return it
@@ -93,8 +117,7 @@ fn get_file(
 			_ => Ok(file),
 		}
 	} else {
-		// The file is the root directory
-		vfs::get_file_from_path(&path, access_profile, follow_links)
+		vfs::resolve_path(&path, rs)
 	}
 }
 
@@ -106,6 +129,15 @@ fn get_file(
 /// - `flags` is the set of flags provided by userspace
 /// - `access_profile` is the access profile to check permissions
 pub fn handle_flags(file: &mut File, flags: i32, access_profile: &AccessProfile) -> EResult<()> {
+	// If O_DIRECTORY is set and the file is not a directory, return an error
+	if flags & open_file::O_DIRECTORY != 0 && file.get_type() != FileType::Directory {
+		return Err(errno!(ENOTDIR));
+	}
+	// The file is not accessed, so no other check is required
+	if flags & open_file::O_PATH != 0 {
+		return Ok(());
+	}
+
 	let (read, write) = match flags & 0b11 {
 		open_file::O_RDONLY => (true, false),
 		open_file::O_WRONLY => (false, true),
@@ -118,10 +150,25 @@ pub fn handle_flags(file: &mut File, flags: i32, access_profile: &AccessProfile)
 	if write && !access_profile.can_write_file(file) {
 		return Err(errno!(EACCES));
 	}
+	let mount_flags = file.get_location().get_mount_flags();
+	match file.get_type() {
+		// Writing to these files modifies the filesystem
+		FileType::Regular | FileType::Directory | FileType::Link => {
+			if write || flags & open_file::O_TRUNC != 0 {
+				file.check_mount_writable()?;
+			}
+		}
+		FileType::BlockDevice | FileType::CharDevice => {
+			if mount_flags & mountpoint::FLAG_NODEV != 0 {
+				return Err(errno!(EACCES));
+			}
+		}
+		_ => {}
+	}
 
-	// If O_DIRECTORY is set and the file is not a directory, return an error
-	if flags & open_file::O_DIRECTORY != 0 && file.get_type() != FileType::Directory {
-		return Err(errno!(ENOTDIR));
+	// If O_NOFOLLOW is set and the file is a symbolic link, return an error
+	if file.get_type() == FileType::Link {
+		return Err(errno!(ELOOP));
 	}
 	// Truncate the file if necessary
 	if flags & open_file::O_TRUNC != 0 {
@@ -133,31 +180,47 @@ pub fn handle_flags(file: &mut File, flags: i32, access_profile: &AccessProfile)
 
 /// Performs the open system call.
 pub fn open_(pathname: SyscallString, flags: i32, mode: file::Mode) -> EResult<i32> {
+	let flags = filter_flags(flags);
 	let proc_mutex = Process::current_assert();
-	let (path, mode, ap, fds_mutex) = {
+	let (path, mode, rs, fds_mutex) = {
 		let proc = proc_mutex.lock();
 
 		let mem_space = proc.get_mem_space().unwrap();
 		let mem_space_guard = mem_space.lock();
-		let path = Path::from_str(pathname.get(&mem_space_guard)?.ok_or(errno!(EFAULT))?, true)?;
+		let path = Path::from_str(
+			&pathname.get(&mem_space_guard)?.ok_or(errno!(EFAULT))?,
+			true,
+		)?;
 		let abs_path = super::util::get_absolute_path(&proc, path)?;
 
-		let mode = mode & !proc.umask;
+		let mode = proc.apply_umask(mode);
+
+		let follow_links = flags & open_file::O_NOFOLLOW == 0;
+		let rs = ResolutionSettings::for_process(&proc, follow_links)?;
 
 		let fds_mutex = proc.get_fds().unwrap().clone();
-		(abs_path, mode, proc.access_profile, fds_mutex)
+		(abs_path, mode, rs, fds_mutex)
 	};
 
 	// Get file
-	let file_mutex = get_file(path, flags, mode, &ap)?;
+	let file_mutex = get_file(path, flags, mode, &rs)?;
 	let mut file = file_mutex.lock();
 
 	// Handle flags
-	handle_flags(&mut file, flags, &ap)?;
+	handle_flags(&mut file, flags, &rs.access_profile)?;
+	let location = file.get_location().clone();
+	let fanotify_mask = fanotify::dir_flag(&file);
 	drop(file);
 
+	fanotify::check_permission(
+		&file_mutex,
+		&location,
+		fanotify::FAN_OPEN_PERM | fanotify_mask,
+	)?;
+
 	// Create open file description
 	let open_file = OpenFile::new(file_mutex.clone(), flags)?;
+	fanotify::notify(&file_mutex, &location, fanotify::FAN_OPEN | fanotify_mask);
 
 	// Create FD
 	let mut fd_flags = 0;
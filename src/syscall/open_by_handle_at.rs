@@ -0,0 +1,111 @@
+//! The `open_by_handle_at` system call opens a file from a handle returned by
+//! `name_to_handle_at`.
+
+use super::access;
+use super::name_to_handle_at;
+use super::name_to_handle_at::FILEID_INO64_GEN;
+use super::name_to_handle_at::HANDLE_SIZE;
+use super::name_to_handle_at::HEADER_SIZE;
+use super::name_to_handle_at::MAX_HANDLE_SZ;
+use super::util;
+use crate::errno::Errno;
+use crate::file::buffer::fanotify;
+use crate::file::fd::FD_CLOEXEC;
+use crate::file::open_file;
+use crate::file::open_file::OpenFile;
+use crate::file::perm::CAP_DAC_READ_SEARCH;
+use crate::file::vfs;
+use crate::file::FileLocation;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall]
+pub fn open_by_handle_at(
+	mount_fd: c_int,
+	handle: SyscallSlice<u8>,
+	flags: c_int,
+) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let (mount_file_mutex, ap, inode, generation) = {
+		let proc = proc_mutex.lock();
+
+		// Handles bypass the permissions of the directories on the path to the file
+		let ap = proc.access_profile;
+		if !ap.has_cap(CAP_DAC_READ_SEARCH) {
+			return Err(errno!(EPERM));
+		}
+
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let mem_space_guard = mem_space.lock();
+
+		let header = handle
+			.get(&mem_space_guard, HEADER_SIZE)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		let handle_bytes = u32::from_ne_bytes(header[..4].try_into().unwrap());
+		let handle_type = c_int::from_ne_bytes(header[4..8].try_into().unwrap());
+		if handle_bytes == 0 || handle_bytes > MAX_HANDLE_SZ {
+			return Err(errno!(EINVAL));
+		}
+		// The handle has not been created by this system
+		if handle_bytes as usize != HANDLE_SIZE || handle_type != FILEID_INO64_GEN {
+			return Err(errno!(ESTALE));
+		}
+		let buf = handle
+			.get(&mem_space_guard, HEADER_SIZE + HANDLE_SIZE)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		let inode = u64::from_le_bytes(buf[8..16].try_into().unwrap());
+		let generation = u32::from_le_bytes(buf[16..20].try_into().unwrap());
+		drop(mem_space_guard);
+
+		// The handle is resolved on the mountpoint of `mount_fd`
+		let mount_file_mutex = if mount_fd == access::AT_FDCWD {
+			util::get_file_at(proc, mount_fd, b".", true, 0)?
+		} else {
+			util::get_file_at(proc, mount_fd, b"", true, access::AT_EMPTY_PATH)?
+		};
+		(mount_file_mutex, ap, inode, generation)
+	};
+
+	let mountpoint_id = mount_file_mutex
+		.lock()
+		.get_location()
+		.get_mountpoint_id()
+		.ok_or_else(|| errno!(EOPNOTSUPP))?;
+	if name_to_handle_at::get_generation(mountpoint_id, inode)? != generation {
+		return Err(errno!(ESTALE));
+	}
+
+	let location = FileLocation::Filesystem {
+		mountpoint_id,
+		inode,
+	};
+	let file_mutex = vfs::get_file_by_location(&location)?;
+	let mut file = file_mutex.lock();
+
+	// Handle flags
+	super::open::handle_flags(&mut file, flags, &ap)?;
+	let fanotify_mask = fanotify::dir_flag(&file);
+	drop(file);
+
+	fanotify::check_permission(
+		&file_mutex,
+		&location,
+		fanotify::FAN_OPEN_PERM | fanotify_mask,
+	)?;
+
+	let open_file = OpenFile::new(file_mutex.clone(), flags)?;
+	fanotify::notify(&file_mutex, &location, fanotify::FAN_OPEN | fanotify_mask);
+
+	let mut fd_flags = 0;
+	if flags & open_file::O_CLOEXEC != 0 {
+		fd_flags |= FD_CLOEXEC;
+	}
+	let proc = proc_mutex.lock();
+	let fds_mutex = proc.get_fds().unwrap();
+	let mut fds = fds_mutex.lock();
+	let fd = fds.create_fd(fd_flags, open_file)?;
+
+	Ok(fd.get_id() as _)
+}
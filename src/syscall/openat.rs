@@ -3,6 +3,7 @@
 use super::util;
 use crate::errno::Errno;
 use crate::file;
+use crate::file::buffer::fanotify;
 use crate::file::fd::FD_CLOEXEC;
 use crate::file::open_file;
 use crate::file::open_file::OpenFile;
@@ -56,14 +57,14 @@ fn get_file(
 		util::create_file_at(
 			proc,
 			dirfd,
-			pathname,
+			&pathname,
 			mode,
 			FileContent::Regular,
 			follow_links,
 			0,
 		)
 	} else {
-		util::get_file_at(proc, dirfd, pathname, follow_links, 0)
+		util::get_file_at(proc, dirfd, &pathname, follow_links, 0)
 	}
 }
 
@@ -74,6 +75,7 @@ pub fn openat(
 	flags: c_int,
 	mode: file::Mode,
 ) -> Result<i32, Errno> {
+	let flags = super::open::filter_flags(flags);
 	let proc_mutex = Process::current_assert();
 	let ap = proc_mutex.lock().access_profile;
 
@@ -83,9 +85,18 @@ pub fn openat(
 
 	// Handle flags
 	super::open::handle_flags(&mut file, flags, &ap)?;
+	let location = file.get_location().clone();
+	let fanotify_mask = fanotify::dir_flag(&file);
 	drop(file);
 
-	let open_file = OpenFile::new(file_mutex, flags)?;
+	fanotify::check_permission(
+		&file_mutex,
+		&location,
+		fanotify::FAN_OPEN_PERM | fanotify_mask,
+	)?;
+
+	let open_file = OpenFile::new(file_mutex.clone(), flags)?;
+	fanotify::notify(&file_mutex, &location, fanotify::FAN_OPEN | fanotify_mask);
 
 	let mut fd_flags = 0;
 	if flags & open_file::O_CLOEXEC != 0 {
@@ -0,0 +1,25 @@
+//! The `personality` system call allows to get or set the execution domain of the process.
+
+use crate::errno::Errno;
+use crate::process::Process;
+use core::ffi::c_ulong;
+use macros::syscall;
+
+/// Personality flag: disables the randomization of the address space.
+pub const ADDR_NO_RANDOMIZE: u32 = 0x0040000;
+
+/// Value of `persona` to only query the current personality.
+const PERSONALITY_QUERY: c_ulong = 0xffffffff;
+
+#[syscall]
+pub fn personality(persona: c_ulong) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let mut proc = proc_mutex.lock();
+
+	let prev = proc.personality;
+	if persona != PERSONALITY_QUERY {
+		proc.personality = persona as _;
+	}
+
+	Ok(prev as _)
+}
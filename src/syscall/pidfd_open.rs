@@ -0,0 +1,47 @@
+//! The `pidfd_open` system call returns a file descriptor referring to a process.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::buffer::pidfd;
+use crate::file::fd::FD_CLOEXEC;
+use crate::file::open_file;
+use crate::file::open_file::OpenFile;
+use crate::process::pid::Pid;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Make waiting on the file descriptor non-blocking.
+const PIDFD_NONBLOCK: c_int = open_file::O_NONBLOCK;
+
+#[syscall]
+pub fn pidfd_open(pid: c_int, flags: c_int) -> Result<i32, Errno> {
+	if flags & !PIDFD_NONBLOCK != 0 {
+		return Err(errno!(EINVAL));
+	}
+	if pid <= 0 {
+		return Err(errno!(EINVAL));
+	}
+	let pid: Pid = pid.try_into().map_err(|_| errno!(ESRCH))?;
+
+	let (fds_mutex, access_profile) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let fds_mutex = proc.get_fds().unwrap().clone();
+		(fds_mutex, proc.access_profile)
+	};
+
+	let file = {
+		let target_mutex = Process::get_by_vpid(pid).ok_or_else(|| errno!(ESRCH))?;
+		let target = target_mutex.lock();
+		pidfd::create(&target_mutex, &target, &access_profile)?
+	};
+	let open_file = OpenFile::new(file, open_file::O_RDWR | (flags & PIDFD_NONBLOCK))?;
+
+	// The close-on-exec flag is always set on pidfds
+	let mut fds = fds_mutex.lock();
+	let fd = fds.create_fd(FD_CLOEXEC, open_file)?;
+
+	Ok(fd.get_id() as _)
+}
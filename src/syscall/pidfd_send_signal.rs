@@ -0,0 +1,107 @@
+//! The `pidfd_send_signal` system call sends a signal to the process referred to by a pidfd.
+
+use super::rt_sigqueueinfo;
+use super::util;
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::buffer::pidfd;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::signal::SigInfo;
+use crate::process::signal::Signal;
+use crate::process::signal::SI_USER;
+use crate::process::Process;
+use crate::process::State;
+use core::ffi::c_int;
+use core::ffi::c_uint;
+use macros::syscall;
+
+#[syscall]
+pub fn pidfd_send_signal(
+	pidfd: c_int,
+	sig: c_int,
+	info: SyscallPtr<SigInfo>,
+	flags: c_uint,
+) -> Result<i32, Errno> {
+	if flags != 0 {
+		return Err(errno!(EINVAL));
+	}
+	if pidfd < 0 {
+		return Err(errno!(EBADF));
+	}
+	if sig < 0 {
+		return Err(errno!(EINVAL));
+	}
+	let sig = if sig > 0 {
+		Some(Signal::try_from(sig as u32)?)
+	} else {
+		None
+	};
+	let proc_mutex = Process::current_assert();
+	let (pidfd_mutex, ap) = {
+		let proc = proc_mutex.lock();
+
+		let fds_mutex = proc.get_fds().unwrap();
+		let fds = fds_mutex.lock();
+		let open_file_mutex = fds
+			.get_fd(pidfd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file();
+		let open_file = open_file_mutex.lock();
+		let pidfd_mutex = pidfd::get(open_file.get_location()).ok_or_else(|| errno!(EBADF))?;
+		(pidfd_mutex, proc.access_profile)
+	};
+
+	// If the process has been reaped, the pointer cannot be upgraded
+	let target_mutex = pidfd_mutex
+		.lock()
+		.get_process()
+		.ok_or_else(|| errno!(ESRCH))?;
+	let to_self = target_mutex.as_ptr() == proc_mutex.as_ptr();
+
+	// The signal's informations
+	let info = match &sig {
+		Some(sig) => {
+			let proc = proc_mutex.lock();
+			let info = if info.is_null() {
+				SigInfo::from_sender(sig, SI_USER, proc.to_local_pid(proc.tgid), ap.get_uid())
+			} else {
+				rt_sigqueueinfo::read_info(&proc, &info, sig, to_self)?
+			};
+			Some(info)
+		}
+		None => None,
+	};
+
+	cli!();
+
+	{
+		let mut target = target_mutex.lock();
+		if matches!(target.get_state(), State::Zombie) {
+			return Err(errno!(ESRCH));
+		}
+		if !ap.can_kill(&target) {
+			return Err(errno!(EPERM));
+		}
+		if let (Some(sig), Some(info)) = (&sig, info) {
+			target.kill_with_info(sig, info, false)?;
+		}
+	}
+
+	// If the process sent the signal to itself, it is executed before returning
+	if to_self {
+		let mut proc = proc_mutex.lock();
+		if proc.has_signal_pending() {
+			// Setting the return value of the system call to `0` after executing a signal
+			let mut return_regs = regs.clone();
+			return_regs.eax = 0;
+			proc.regs = return_regs;
+
+			// Set the process to execute the signal action
+			proc.signal_next();
+		}
+	}
+
+	util::handle_proc_state();
+
+	Ok(0)
+}
@@ -40,7 +40,7 @@ pub fn pipe2(pipefd: SyscallPtr<[c_int; 2]>, flags: c_int) -> Result<i32, Errno>
 	let mut fds = fds_mutex.lock();
 	let mut mem_space_guard = mem_space.lock();
 
-	let pipefd_slice = pipefd
+	let mut pipefd_slice = pipefd
 		.get_mut(&mut mem_space_guard)?
 		.ok_or(errno!(EFAULT))?;
 	let fd0 = fds.create_fd(0, open_file0)?;
@@ -59,7 +59,7 @@ pub fn poll(fds: SyscallSlice<PollFD>, nfds: usize, timeout: c_int) -> Result<i3
 				.ok_or_else(|| errno!(EFAULT))?;
 
 			// Checking the file descriptors list
-			for fd in fds {
+			for fd in fds.iter() {
 				if fd.events as u32 & io::POLLIN != 0 {
 					// TODO
 					todo!();
@@ -105,6 +105,9 @@ pub fn poll(fds: SyscallSlice<PollFD>, nfds: usize, timeout: c_int) -> Result<i3
 			}
 		}
 
+		// The call is interrupted to handle the signal
+		super::util::signal_check().map_err(|_| errno!(ERESTARTNOHAND))?;
+
 		// TODO Make process sleep until an event occurs on a file descriptor in
 		// `fds`
 		scheduler::end_tick();
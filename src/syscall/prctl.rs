@@ -0,0 +1,161 @@
+//! The `prctl` system call allows to perform operations on the current process.
+
+use crate::errno::Errno;
+use crate::file::perm;
+use crate::file::perm::CAP_LAST_CAP;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::signal::Signal;
+use crate::process::Process;
+use crate::process::COMM_LEN;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Sets the signal sent to the process when its parent exits.
+const PR_SET_PDEATHSIG: c_int = 1;
+/// Returns the signal sent to the process when its parent exits.
+const PR_GET_PDEATHSIG: c_int = 2;
+/// Returns whether the process is dumpable.
+const PR_GET_DUMPABLE: c_int = 3;
+/// Sets whether the process is dumpable.
+const PR_SET_DUMPABLE: c_int = 4;
+/// Sets the name of the process.
+const PR_SET_NAME: c_int = 15;
+/// Returns the name of the process.
+const PR_GET_NAME: c_int = 16;
+/// Returns the secure computing mode of the process.
+const PR_GET_SECCOMP: c_int = 21;
+/// Sets the secure computing mode of the process.
+const PR_SET_SECCOMP: c_int = 22;
+/// Tells whether a capability is in the bounding set.
+const PR_CAPBSET_READ: c_int = 23;
+/// Removes a capability from the bounding set.
+const PR_CAPBSET_DROP: c_int = 24;
+/// Sets whether the process adopts its orphaned descendants.
+const PR_SET_CHILD_SUBREAPER: c_int = 36;
+/// Returns whether the process adopts its orphaned descendants.
+const PR_GET_CHILD_SUBREAPER: c_int = 37;
+/// Prevents executed programs from granting more privileges.
+const PR_SET_NO_NEW_PRIVS: c_int = 38;
+/// Tells whether executed programs may grant more privileges.
+const PR_GET_NO_NEW_PRIVS: c_int = 39;
+
+#[syscall]
+pub fn prctl(
+	option: c_int,
+	arg2: usize,
+	arg3: usize,
+	arg4: usize,
+	arg5: usize,
+) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let mut proc = proc_mutex.lock();
+
+	match option {
+		PR_SET_PDEATHSIG => {
+			proc.pdeath_signal = match arg2 {
+				0 => None,
+				sig => Some(Signal::try_from(sig as u32)?),
+			};
+			Ok(0)
+		}
+
+		PR_GET_PDEATHSIG => {
+			let sig = proc
+				.pdeath_signal
+				.as_ref()
+				.map(|sig| sig.get_id() as c_int)
+				.unwrap_or(0);
+
+			let sig_ptr: SyscallPtr<c_int> = arg2.into();
+			let mem_space = proc.get_mem_space().unwrap();
+			let mut mem_space_guard = mem_space.lock();
+			*sig_ptr
+				.get_mut(&mut mem_space_guard)?
+				.ok_or_else(|| errno!(EFAULT))? = sig;
+			Ok(0)
+		}
+
+		PR_GET_DUMPABLE => Ok(proc.dumpable as _),
+
+		PR_SET_DUMPABLE => {
+			proc.dumpable = match arg2 {
+				0 => false,
+				1 => true,
+				_ => return Err(errno!(EINVAL)),
+			};
+			Ok(0)
+		}
+
+		PR_SET_NAME => {
+			let name: SyscallString = arg2.into();
+			let mem_space = proc.get_mem_space().unwrap().clone();
+			let mem_space_guard = mem_space.lock();
+			let name = name.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
+			proc.set_comm(&name);
+			Ok(0)
+		}
+
+		PR_GET_NAME => {
+			let mut comm = [0; COMM_LEN];
+			let name = proc.get_comm();
+			comm[..name.len()].copy_from_slice(name);
+
+			let buf: SyscallSlice<u8> = arg2.into();
+			let mem_space = proc.get_mem_space().unwrap();
+			let mut mem_space_guard = mem_space.lock();
+			buf.get_mut(&mut mem_space_guard, COMM_LEN)?
+				.ok_or_else(|| errno!(EFAULT))?
+				.copy_from_slice(&comm);
+			Ok(0)
+		}
+
+		PR_GET_SECCOMP => Ok(proc.seccomp.get_mode() as _),
+
+		PR_SET_SECCOMP => {
+			super::seccomp::set_mode(&mut proc, arg2 as _, arg3.into())?;
+			Ok(0)
+		}
+
+		PR_CAPBSET_READ => {
+			if arg2 > CAP_LAST_CAP as usize {
+				return Err(errno!(EINVAL));
+			}
+			let set = proc.access_profile.get_cap_bounding();
+			Ok((set & perm::cap_to_set(arg2 as _) != 0) as _)
+		}
+
+		PR_CAPBSET_DROP => {
+			proc.access_profile.drop_bounding_cap(arg2 as _)?;
+			Ok(0)
+		}
+
+		PR_SET_CHILD_SUBREAPER => {
+			proc.child_subreaper = arg2 != 0;
+			Ok(0)
+		}
+
+		PR_GET_CHILD_SUBREAPER => {
+			let ptr: SyscallPtr<c_int> = arg2.into();
+			let mem_space = proc.get_mem_space().unwrap();
+			let mut mem_space_guard = mem_space.lock();
+			*ptr.get_mut(&mut mem_space_guard)?
+				.ok_or_else(|| errno!(EFAULT))? = proc.child_subreaper as _;
+			Ok(0)
+		}
+
+		PR_SET_NO_NEW_PRIVS => {
+			// The flag cannot be unset
+			if arg2 != 1 || arg3 != 0 || arg4 != 0 || arg5 != 0 {
+				return Err(errno!(EINVAL));
+			}
+			proc.no_new_privs = true;
+			Ok(0)
+		}
+
+		PR_GET_NO_NEW_PRIVS => Ok(proc.no_new_privs as _),
+
+		_ => Err(errno!(EINVAL)),
+	}
+}
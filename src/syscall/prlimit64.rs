@@ -3,108 +3,47 @@
 use crate::errno::Errno;
 use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::pid::Pid;
+use crate::process::rlimit::RLimit;
 use crate::process::Process;
 use core::ffi::c_int;
 use macros::syscall;
 
-/// The amount of seconds of CPU time the process can consume.
-const RLIMIT_CPU: i32 = 0;
-/// The maximum size of a file the process may create, in bytes.
-const RLIMIT_FSIZE: i32 = 1;
-/// The maximum size of the process's data segment in bytes, rounded down to the
-/// page size.
-const RLIMIT_DATA: i32 = 2;
-/// The maximum size of the process stack, in bytes.
-const RLIMIT_STACK: i32 = 3;
-/// The maximum size of a core file the process may dump in bytes.
-const RLIMIT_CORE: i32 = 4;
-/// A limit on the process's resident set (the numbe rof virtual pages resident in RAM).
-const RLIMIT_RSS: i32 = 5;
-/// The limit on the number of threads for the real user ID of the calling process.
-const RLIMIT_NPROC: i32 = 6;
-/// A value one greater than the maximum number of file descriptors that can be
-/// open by the process.
-const RLIMIT_NOFILE: i32 = 7;
-/// The maximum number of butes of memory that may be locked into RAM.
-const RLIMIT_MEMLOCK: i32 = 8;
-/// The maximum size of the memory space in bytes, rounded down to the page
-/// size.
-const RLIMIT_AS: i32 = 9;
-/// The limit on the combined number of flock(2) locks and fcntl(2) leases the
-/// process may establish.
-const RLIMIT_LOCKS: i32 = 10;
-/// The limit on the number of signals that may be queued for the real user ID of the calling
-/// process.
-const RLIMIT_SIGPENDING: i32 = 11;
-/// The limit on the number of butes that can be allocated for POSIX message queues for the real
-/// user IF of the calling process.
-const RLIMIT_MSGQUEUE: i32 = 12;
-/// The ceiling to which the process's nice value can be raised.
-const RLIMIT_NICE: i32 = 13;
-/// The ceiling on the real-time priority that may be set for this process.
-const RLIMIT_RTPRIO: i32 = 14;
-/// The limit (in microseconds) on the amount of CPU that a process scheduled under a real-time
-/// scheduling policy may consume without masking a blocking system call.
-const RLIMIT_RTTIME: i32 = 15;
-/// TODO doc
-const RLIMIT_NLIMITS: i32 = 16;
-
-/// Type representing a resource limit.
-type RLim = u64;
-
-/// Structure representing a resource limit.
-#[repr(C)]
-#[derive(Debug)]
-struct RLimit {
-	/// Soft limit
-	rlim_cur: RLim,
-	/// Hard limit (ceiling for rlim_cur)
-	rlim_max: RLim,
-}
-
 // TODO Check args types
 #[syscall]
 pub fn prlimit64(
 	pid: Pid,
 	resource: c_int,
-	_new_limit: SyscallPtr<RLimit>,
-	_old_limit: SyscallPtr<RLimit>,
+	new_limit: SyscallPtr<RLimit>,
+	old_limit: SyscallPtr<RLimit>,
 ) -> Result<i32, Errno> {
-	// The target process. If None, the current process is the target
-	let _target_proc = if pid == 0 {
-		None
+	let (mem_space_mutex, access_profile) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+		(proc.get_mem_space().unwrap().clone(), proc.access_profile)
+	};
+	// The target process
+	let target_mutex = if pid == 0 {
+		Process::current_assert()
 	} else {
-		// TODO Check permission
-		Some(Process::get_by_pid(pid).ok_or_else(|| errno!(ESRCH))?)
+		let target_mutex = Process::get_by_vpid(pid).ok_or_else(|| errno!(ESRCH))?;
+		if !access_profile.can_access_rlimits(&target_mutex.lock().access_profile) {
+			return Err(errno!(EPERM));
+		}
+		target_mutex
 	};
 
-	let proc_mutex = Process::current_assert();
-	let proc = proc_mutex.lock();
-
-	let mem_space_mutex = proc.get_mem_space().unwrap();
-	let _mem_space_guard = mem_space_mutex.lock();
+	let mut mem_space = mem_space_mutex.lock();
+	let new_limit = new_limit.get(&mem_space)?.as_deref().cloned();
 
-	// TODO Implement all
-	match resource {
-		RLIMIT_CPU => {}
-		RLIMIT_FSIZE => {}
-		RLIMIT_DATA => {}
-		RLIMIT_STACK => {}
-		RLIMIT_CORE => {}
-		RLIMIT_RSS => {}
-		RLIMIT_NPROC => {}
-		RLIMIT_NOFILE => {}
-		RLIMIT_MEMLOCK => {}
-		RLIMIT_AS => {}
-		RLIMIT_LOCKS => {}
-		RLIMIT_SIGPENDING => {}
-		RLIMIT_MSGQUEUE => {}
-		RLIMIT_NICE => {}
-		RLIMIT_RTPRIO => {}
-		RLIMIT_RTTIME => {}
-		RLIMIT_NLIMITS => {}
+	let mut target = target_mutex.lock();
+	let prev = target.rlimits.get(resource)?;
+	if let Some(new_limit) = new_limit {
+		target.set_rlimit(resource, new_limit, &access_profile)?;
+	}
+	drop(target);
 
-		_ => return Err(errno!(EINVAL)),
+	if let Some(mut old_limit) = old_limit.get_mut(&mut mem_space)? {
+		*old_limit = prev;
 	}
 
 	Ok(0)
@@ -2,6 +2,8 @@
 
 use crate::errno;
 use crate::errno::Errno;
+use crate::file::buffer::fanotify;
+use crate::file::open_file::OpenFile;
 use crate::file::open_file::O_NONBLOCK;
 use crate::process::mem_space::ptr::SyscallSlice;
 use crate::process::scheduler;
@@ -12,8 +14,6 @@ use core::cmp::min;
 use core::ffi::c_int;
 use macros::syscall;
 
-// TODO O_ASYNC
-
 #[syscall]
 pub fn read(fd: c_int, buf: SyscallSlice<u8>, count: usize) -> Result<i32, Errno> {
 	if fd < 0 {
@@ -43,19 +43,21 @@ pub fn read(fd: c_int, buf: SyscallSlice<u8>, count: usize) -> Result<i32, Errno
 		(proc_mutex, mem_space, open_file_mutex)
 	};
 
+	OpenFile::check_permission(&open_file, fanotify::FAN_ACCESS_PERM)?;
+
 	loop {
-		super::util::signal_check(regs);
+		super::util::signal_check()?;
 
 		{
 			let mut mem_space_guard = mem_space.lock();
-			let buf_slice = buf
+			let mut buf_slice = buf
 				.get_mut(&mut mem_space_guard, len)?
 				.ok_or(errno!(EFAULT))?;
 
 			// Read file
 			let mut open_file = open_file.lock();
 			let flags = open_file.get_flags();
-			let (len, eof) = open_file.read(0, buf_slice)?;
+			let (len, eof) = open_file.read(0, &mut buf_slice)?;
 
 			if len == 0 && eof {
 				return Ok(0);
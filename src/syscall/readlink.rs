@@ -3,6 +3,7 @@
 use crate::errno::Errno;
 use crate::file::path::Path;
 use crate::file::vfs;
+use crate::file::vfs::ResolutionSettings;
 use crate::file::FileContent;
 use crate::process::mem_space::ptr::SyscallSlice;
 use crate::process::mem_space::ptr::SyscallString;
@@ -18,7 +19,7 @@ pub fn readlink(
 	bufsiz: usize,
 ) -> Result<i32, Errno> {
 	// process lock has to be dropped to avoid deadlock with procfs
-	let (mem_space_mutex, path, ap) = {
+	let (mem_space_mutex, path, rs) = {
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
 
@@ -27,15 +28,19 @@ pub fn readlink(
 
 		// Get file's path
 		let path = pathname.get(&mem_space)?.ok_or(errno!(EFAULT))?;
-		let path = Path::from_str(path, true)?;
+		let path = Path::from_str(&path, true)?;
 		let path = super::util::get_absolute_path(&proc, path)?;
 
 		drop(mem_space);
-		(mem_space_mutex, path, proc.access_profile)
+		(
+			mem_space_mutex,
+			path,
+			ResolutionSettings::for_process(&proc, false)?,
+		)
 	};
 
 	// Get link's target
-	let file_mutex = vfs::get_file_from_path(&path, &ap, false)?;
+	let file_mutex = vfs::resolve_path(&path, &rs)?;
 	let file = file_mutex.lock();
 	let FileContent::Link(target) = file.get_content() else {
 		return Err(errno!(EINVAL));
@@ -43,8 +48,8 @@ pub fn readlink(
 
 	// Copy to userspace buffer
 	let mut mem_space = mem_space_mutex.lock();
-	let buffer = buf.get_mut(&mut mem_space, bufsiz)?.ok_or(errno!(EFAULT))?;
-	util::slice_copy(target.as_bytes(), buffer);
+	let mut buffer = buf.get_mut(&mut mem_space, bufsiz)?.ok_or(errno!(EFAULT))?;
+	util::slice_copy(target.as_bytes(), &mut buffer);
 
 	Ok(min(bufsiz, target.len()) as _)
 }
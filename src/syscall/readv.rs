@@ -3,9 +3,11 @@
 use crate::errno;
 use crate::errno::EResult;
 use crate::errno::Errno;
+use crate::file::buffer::fanotify;
 use crate::file::open_file::OpenFile;
 use crate::file::open_file::O_NONBLOCK;
 use crate::limits;
+use crate::process::iovec;
 use crate::process::iovec::IOVec;
 use crate::process::mem_space::ptr::SyscallSlice;
 use crate::process::mem_space::MemSpace;
@@ -14,6 +16,8 @@ use crate::process::Process;
 use crate::util::container::vec::Vec;
 use crate::util::io;
 use crate::util::io::IO;
+use crate::util::lock::IntMutex;
+use crate::util::lock::Mutex;
 use core::cmp::min;
 use core::ffi::c_int;
 use macros::syscall;
@@ -28,20 +32,23 @@ use macros::syscall;
 /// - `iov` is the set of chunks
 /// - `iovcnt` is the number of chunks in `iov`
 /// - `open_file` is the file to read from
+///
+/// The function returns the number of bytes read and whether the end of file has been reached.
 fn read(
 	mem_space: &mut MemSpace,
 	iov: &SyscallSlice<IOVec>,
 	iovcnt: usize,
 	open_file: &mut OpenFile,
-) -> EResult<i32> {
+) -> EResult<(i32, bool)> {
 	let iov = {
 		let iov_slice = iov.get(&mem_space, iovcnt)?.ok_or(errno!(EFAULT))?;
 		let mut iov = Vec::new();
-		iov.extend_from_slice(iov_slice)?;
+		iov.extend_from_slice(&iov_slice)?;
 		iov
 	};
 
 	let mut total_len = 0;
+	let mut eof = false;
 
 	for i in iov {
 		// Ignore zero entry
@@ -53,17 +60,18 @@ fn read(
 		let l = min(i.iov_len, i32::MAX as usize - total_len);
 		let ptr = SyscallSlice::<u8>::from(i.iov_base as usize);
 
-		if let Some(slice) = ptr.get_mut(mem_space, l)? {
+		if let Some(mut slice) = ptr.get_mut(mem_space, l)? {
 			// The offset is ignored
-			let (len, eof) = open_file.read(0, slice)?;
+			let (len, e) = open_file.read(0, &mut slice)?;
 			total_len += len as usize;
-			if eof {
+			if e {
+				eof = true;
 				break;
 			}
 		}
 	}
 
-	Ok(total_len as _)
+	Ok((total_len as _, eof))
 }
 
 /// Performs the readv operation.
@@ -79,16 +87,11 @@ pub fn do_readv(
 	iov: SyscallSlice<IOVec>,
 	iovcnt: c_int,
 	offset: Option<isize>,
-	_flags: Option<i32>,
+	flags: Option<i32>,
 ) -> Result<i32, Errno> {
 	if fd < 0 {
 		return Err(errno!(EBADF));
 	}
-	if iovcnt < 0 || iovcnt as usize > limits::IOV_MAX {
-		return Err(errno!(EINVAL));
-	}
-
-	// TODO Handle flags
 
 	let (proc, mem_space, open_file_mutex) = {
 		let proc_mutex = Process::current_assert();
@@ -108,6 +111,41 @@ pub fn do_readv(
 		(proc_mutex, mem_space, open_file_mutex)
 	};
 
+	readv_file(
+		&proc,
+		&mem_space,
+		&open_file_mutex,
+		iov,
+		iovcnt,
+		offset,
+		flags.unwrap_or(0),
+	)
+}
+
+/// Reads from the open file `open_file_mutex` into the IO vector `iov`.
+///
+/// Arguments:
+/// - `proc` is the current process
+/// - `mem_space` is the memory space of the current process
+/// - `open_file_mutex` is the open file
+/// - `iov` the IO vector
+/// - `iovcnt` the number of entries in the IO vector
+/// - `offset` is the offset in the file
+/// - `rw_flags` is the set of per-call flags (`RWF_*`)
+pub fn readv_file(
+	proc: &IntMutex<Process>,
+	mem_space: &IntMutex<MemSpace>,
+	open_file_mutex: &Mutex<OpenFile>,
+	iov: SyscallSlice<IOVec>,
+	iovcnt: c_int,
+	offset: Option<isize>,
+	rw_flags: c_int,
+) -> EResult<i32> {
+	if iovcnt < 0 || iovcnt as usize > limits::IOV_MAX {
+		return Err(errno!(EINVAL));
+	}
+	iovec::check_rw_flags(rw_flags)?;
+
 	let (start_off, update_off) = match offset {
 		Some(o @ 0..) => (o as u64, false),
 		None | Some(-1) => {
@@ -120,8 +158,10 @@ pub fn do_readv(
 		Some(_) => unreachable!(),
 	};
 
+	OpenFile::check_permission(open_file_mutex, fanotify::FAN_ACCESS_PERM)?;
+
 	loop {
-		// TODO super::util::signal_check(regs);
+		// TODO super::util::signal_check()?;
 
 		{
 			let mut open_file = open_file_mutex.lock();
@@ -132,17 +172,17 @@ pub fn do_readv(
 			open_file.set_offset(start_off);
 
 			let mut mem_space_guard = mem_space.lock();
-			let len = read(&mut mem_space_guard, &iov, iovcnt as _, &mut open_file)?;
+			let (len, eof) = read(&mut mem_space_guard, &iov, iovcnt as _, &mut open_file)?;
 
 			// Restore previous offset
 			if !update_off {
 				open_file.set_offset(prev_off);
 			}
 
-			if len > 0 {
+			if len > 0 || eof {
 				return Ok(len as _);
 			}
-			if flags & O_NONBLOCK != 0 {
+			if flags & O_NONBLOCK != 0 || rw_flags & iovec::RWF_NOWAIT != 0 {
 				// The file descriptor is non blocking
 				return Err(errno!(EAGAIN));
 			}
@@ -2,57 +2,87 @@
 //! suspend the system.
 
 use crate::errno::Errno;
+use crate::file::perm::CAP_SYS_BOOT;
+use crate::file::writeback;
+use crate::kexec;
 use crate::process::Process;
 use crate::{errno, power};
 use core::ffi::c_int;
 use core::ffi::c_void;
+use core::sync::atomic;
 use macros::syscall;
 
 /// First magic number.
-const MAGIC: u32 = 0xde145e83;
-/// Second magic number.
-const MAGIC2: u32 = 0x40367d6e;
+const MAGIC: u32 = 0xfee1dead;
+/// Accepted values for the second magic number.
+const MAGIC2: [u32; 4] = [672274793, 85072278, 369367448, 537993216];
 
-/// Command to power off the system.
-const CMD_POWEROFF: u32 = 0;
 /// Command to reboot the system.
-const CMD_REBOOT: u32 = 1;
+const CMD_RESTART: u32 = 0x01234567;
 /// Command to halt the system.
-const CMD_HALT: u32 = 2;
+const CMD_HALT: u32 = 0xcdef0123;
+/// Command to make Ctrl-Alt-Del reboot the system.
+const CMD_CAD_ON: u32 = 0x89abcdef;
+/// Command to make Ctrl-Alt-Del send `SIGINT` to the init process.
+const CMD_CAD_OFF: u32 = 0x00000000;
+/// Command to power off the system.
+const CMD_POWER_OFF: u32 = 0x4321fedc;
 /// Command to suspend the system.
-const CMD_SUSPEND: u32 = 3;
+const CMD_SW_SUSPEND: u32 = 0xd000fce2;
+/// Command to execute the kernel loaded with `kexec_load`.
+const CMD_KEXEC: u32 = 0x45584543;
 
 #[syscall]
 pub fn reboot(magic: c_int, magic2: c_int, cmd: c_int, _arg: *const c_void) -> Result<i32, Errno> {
-	if (magic as u32) != MAGIC || (magic2 as u32) != MAGIC2 {
+	if (magic as u32) != MAGIC || !MAGIC2.contains(&(magic2 as u32)) {
 		return Err(errno!(EINVAL));
 	}
 
 	{
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
-		if !proc.access_profile.is_privileged() {
+		if !proc.access_profile.has_cap(CAP_SYS_BOOT) {
 			return Err(errno!(EPERM));
 		}
 	}
 
 	match cmd as u32 {
-		CMD_POWEROFF => {
+		CMD_CAD_ON => {
+			power::CTRL_ALT_DEL.store(true, atomic::Ordering::Relaxed);
+			Ok(0)
+		}
+		CMD_CAD_OFF => {
+			power::CTRL_ALT_DEL.store(false, atomic::Ordering::Relaxed);
+			Ok(0)
+		}
+		CMD_POWER_OFF => {
+			// Errors cannot be reported anymore
+			let _ = writeback::sync_all();
 			crate::println!("Power down...");
 			power::shutdown();
 		}
-		CMD_REBOOT => {
+		CMD_RESTART => {
+			let _ = writeback::sync_all();
 			crate::println!("Rebooting...");
 			power::reboot();
 		}
 		CMD_HALT => {
+			let _ = writeback::sync_all();
 			crate::println!("Halting...");
 			power::halt();
 		}
-		CMD_SUSPEND => {
-			// TODO Use ACPI to suspend the system
-			todo!()
+		CMD_KEXEC => {
+			if !kexec::is_loaded() {
+				return Err(errno!(EINVAL));
+			}
+			let _ = writeback::sync_all();
+			crate::println!("Starting new kernel...");
+			kexec::exec();
+			// Jumping to the new kernel failed
+			Err(errno!(EINVAL))
 		}
+		// TODO Use ACPI to suspend the system
+		CMD_SW_SUSPEND => Err(errno!(ENOSYS)),
 		_ => Err(errno!(EINVAL)),
 	}
 }
@@ -0,0 +1,46 @@
+//! The `removexattr` system call removes an extended attribute of a file.
+
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::Process;
+use crate::util::container::string::String;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Performs the `*removexattr` system calls.
+///
+/// Arguments:
+/// - `fd` is the file descriptor of the file, used if `pathname` is `None`.
+/// - `pathname` is the path to the file.
+/// - `follow_links` tells whether symbolic links are followed.
+/// - `name` is the name of the attribute.
+pub fn do_removexattr(
+	fd: c_int,
+	pathname: Option<SyscallString>,
+	follow_links: bool,
+	name: SyscallString,
+) -> EResult<i32> {
+	let (name, ap) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let mem_space = proc.get_mem_space().unwrap();
+		let mem_space_guard = mem_space.lock();
+
+		let name = name.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
+		(String::try_from(&*name)?, proc.access_profile)
+	};
+
+	let file_mutex = super::util::get_file_from_path_or_fd(fd, pathname, follow_links)?;
+	let file = file_mutex.lock();
+	file.remove_xattr(&ap, name.as_bytes())?;
+
+	Ok(0)
+}
+
+#[syscall]
+pub fn removexattr(pathname: SyscallString, name: SyscallString) -> Result<i32, Errno> {
+	do_removexattr(-1, Some(pathname), true, name)
+}
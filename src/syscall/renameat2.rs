@@ -1,9 +1,7 @@
 //! The `renameat2` allows to rename a file.
 
 use crate::errno::Errno;
-use crate::file;
 use crate::file::vfs;
-use crate::file::FileType;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
 use core::ffi::c_int;
@@ -34,14 +32,14 @@ pub fn renameat2(
 		let oldpath = oldpath
 			.get(&mem_space_guard)?
 			.ok_or_else(|| errno!(EFAULT))?;
-		let old = super::util::get_file_at(proc, olddirfd, oldpath, false, 0)?;
+		let old = super::util::get_file_at(proc, olddirfd, &oldpath, false, 0)?;
 
 		let proc = proc_mutex.lock();
 		let newpath = newpath
 			.get(&mem_space_guard)?
 			.ok_or_else(|| errno!(EFAULT))?;
 		let (new_parent, new_name) =
-			super::util::get_parent_at_with_name(proc, newdirfd, newpath, false, 0)?;
+			super::util::get_parent_at_with_name(proc, newdirfd, &newpath, false, 0)?;
 
 		(old, new_parent, new_name, ap)
 	};
@@ -49,29 +47,7 @@ pub fn renameat2(
 	let mut old = old_mutex.lock();
 	let mut new_parent = new_parent_mutex.lock();
 
-	// TODO Check permissions if sticky bit is set
-
-	if new_parent.get_location().get_mountpoint_id() == old.get_location().get_mountpoint_id() {
-		// Old and new are both on the same filesystem
-
-		// TODO On fail, undo
-
-		// Create link at new location
-		// The `..` entry is already updated by the file system since having the same
-		// directory in several locations is not allowed
-		vfs::create_link(&mut old, &mut new_parent, &new_name, &ap)?;
-
-		if old.get_type() != FileType::Directory {
-			vfs::remove_file(&mut old, &ap)?;
-		}
-	} else {
-		// Old and new are on different filesystems.
-
-		// TODO On fail, undo
-
-		file::util::copy_file(&mut old, &mut new_parent, new_name)?;
-		file::util::remove_recursive(&mut old, &ap)?;
-	}
+	vfs::rename(&mut old, &mut new_parent, new_name, &ap)?;
 
 	Ok(0)
 }
@@ -0,0 +1,33 @@
+//! The `restart_syscall` system call resumes a system call that has been interrupted by a
+//! signal, taking into account the time that elapsed since the interruption.
+//!
+//! This system call is not meant to be used by userspace. The kernel places it in the
+//! registers of the process when restarting a system call that returned
+//! [`crate::errno::ERESTART_RESTARTBLOCK`].
+
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::process::Process;
+use macros::syscall;
+
+/// The ID of the `restart_syscall` system call.
+pub const RESTART_SYSCALL_ID: u32 = 0x000;
+
+/// The state required to resume an interrupted system call.
+#[derive(Clone, Copy, Debug)]
+pub struct RestartBlock {
+	/// The function resuming the system call.
+	pub func: fn(&RestartBlock) -> EResult<i32>,
+	/// The arguments of the function. Their meaning depends on the system call.
+	pub args: [u64; 4],
+}
+
+#[syscall]
+pub fn restart_syscall() -> Result<i32, Errno> {
+	let block = Process::current_assert().lock().restart_block.take();
+	match block {
+		Some(block) => (block.func)(&block),
+		None => Err(errno!(EINTR)),
+	}
+}
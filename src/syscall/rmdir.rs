@@ -5,39 +5,40 @@
 use crate::errno::Errno;
 use crate::file::path::Path;
 use crate::file::vfs;
-use crate::file::FileContent;
+use crate::file::vfs::ResolutionSettings;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
 use macros::syscall;
 
 #[syscall]
 pub fn rmdir(pathname: SyscallString) -> Result<i32, Errno> {
-	let (path, ap) = {
+	let (path, rs) = {
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
 
 		let mem_space = proc.get_mem_space().unwrap();
 		let mem_space_guard = mem_space.lock();
 
-		let path = Path::from_str(pathname.get(&mem_space_guard)?.ok_or(errno!(EFAULT))?, true)?;
+		let path = Path::from_str(
+			&pathname.get(&mem_space_guard)?.ok_or(errno!(EFAULT))?,
+			true,
+		)?;
 		let path = super::util::get_absolute_path(&proc, path)?;
 
-		(path, proc.access_profile)
+		(path, ResolutionSettings::for_process(&proc, true)?)
 	};
 
 	// Remove the directory
 	{
 		// Get directory
-		let file_mutex = vfs::get_file_from_path(&path, &ap, true)?;
+		let file_mutex = vfs::resolve_path(&path, &rs)?;
 		let mut file = file_mutex.lock();
 
-		match file.get_content() {
-			FileContent::Directory(entries) if entries.len() > 2 => return Err(errno!(ENOTEMPTY)),
-			FileContent::Directory(_) => {}
-			_ => return Err(errno!(ENOTDIR)),
+		if !file.is_empty_directory()? {
+			return Err(errno!(ENOTEMPTY));
 		}
 
-		vfs::remove_file(&mut file, &ap)?;
+		vfs::remove_file(&mut file, &rs.access_profile)?;
 	}
 
 	Ok(0)
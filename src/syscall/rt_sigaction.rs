@@ -2,11 +2,15 @@
 
 use crate::errno::Errno;
 use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::signal;
 use crate::process::signal::SigAction;
+use crate::process::signal::SigSet;
 use crate::process::signal::SignalHandler;
 use crate::process::Process;
 use crate::syscall::Signal;
 use core::ffi::c_int;
+use core::ffi::c_void;
+use core::mem::size_of;
 use macros::syscall;
 
 #[syscall]
@@ -14,7 +18,11 @@ pub fn rt_sigaction(
 	signum: c_int,
 	act: SyscallPtr<SigAction>,
 	oldact: SyscallPtr<SigAction>,
+	sigsetsize: usize,
 ) -> Result<i32, Errno> {
+	if sigsetsize != size_of::<SigSet>() {
+		return Err(errno!(EINVAL));
+	}
 	let signal = Signal::try_from(signum as u32)?;
 
 	let proc_mutex = Process::current_assert();
@@ -24,14 +32,24 @@ pub fn rt_sigaction(
 	let mut mem_space_guard = mem_space.lock();
 
 	// Save the old structure
-	if let Some(oldact) = oldact.get_mut(&mut mem_space_guard)? {
+	if let Some(mut oldact) = oldact.get_mut(&mut mem_space_guard)? {
 		let action = proc.get_signal_handler(&signal).get_action();
 		*oldact = action;
 	}
 
 	// Set the new structure
 	if let Some(act) = act.get(&mem_space_guard)? {
-		proc.set_signal_handler(&signal, SignalHandler::Handler(*act));
+		// The action of these signals cannot be changed
+		if matches!(signal, Signal::SIGKILL | Signal::SIGSTOP) {
+			return Err(errno!(EINVAL));
+		}
+		let handler = act.sa_handler.map(|f| f as *const c_void);
+		let handler = match handler.unwrap_or(signal::SIG_DFL) {
+			signal::SIG_DFL => SignalHandler::Default,
+			signal::SIG_IGN => SignalHandler::Ignore,
+			_ => SignalHandler::Handler(*act),
+		};
+		proc.set_signal_handler(&signal, handler);
 	}
 
 	Ok(0)
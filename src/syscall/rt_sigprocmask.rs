@@ -2,10 +2,11 @@
 
 use crate::errno;
 use crate::errno::Errno;
-use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::signal::SigSet;
 use crate::process::Process;
-use core::cmp::min;
 use core::ffi::c_int;
+use core::mem::size_of;
 use macros::syscall;
 
 /// Performs the union of the given mask with the current mask.
@@ -15,51 +16,34 @@ const SIG_UNBLOCK: i32 = 1;
 /// Sets the mask with the given one.
 const SIG_SETMASK: i32 = 2;
 
-// TODO Use SigSet in crate::process::signal
 #[syscall]
 pub fn rt_sigprocmask(
 	how: c_int,
-	set: SyscallSlice<u8>,
-	oldset: SyscallSlice<u8>,
+	set: SyscallPtr<SigSet>,
+	oldset: SyscallPtr<SigSet>,
 	sigsetsize: usize,
 ) -> Result<i32, Errno> {
+	if sigsetsize != size_of::<SigSet>() {
+		return Err(errno!(EINVAL));
+	}
+
 	let proc_mutex = Process::current_assert();
 	let mut proc = proc_mutex.lock();
 
 	let mem_space = proc.get_mem_space().unwrap().clone();
 	let mut mem_space_guard = mem_space.lock();
 
-	let curr = proc.sigmask.as_slice_mut();
-
-	let oldset_slice = oldset.get_mut(&mut mem_space_guard, sigsetsize as _)?;
-	if let Some(oldset) = oldset_slice {
-		// Saving the old set
-		for i in 0..min(oldset.len(), curr.len()) {
-			oldset[i] = curr[i];
-		}
+	// Saving the old set
+	if let Some(mut oldset) = oldset.get_mut(&mut mem_space_guard)? {
+		*oldset = proc.sigmask;
 	}
 
-	let set_slice = set.get(&mem_space_guard, sigsetsize as _)?;
-	if let Some(set) = set_slice {
+	if let Some(set) = set.get(&mem_space_guard)? {
 		// Applies the operation
 		match how {
-			SIG_BLOCK => {
-				for i in 0..min(set.len(), curr.len()) {
-					curr[i] |= set[i];
-				}
-			}
-
-			SIG_UNBLOCK => {
-				for i in 0..min(set.len(), curr.len()) {
-					curr[i] &= !set[i];
-				}
-			}
-
-			SIG_SETMASK => {
-				for i in 0..min(set.len(), curr.len()) {
-					curr[i] = set[i];
-				}
-			}
+			SIG_BLOCK => proc.sigmask |= *set,
+			SIG_UNBLOCK => proc.sigmask &= !*set,
+			SIG_SETMASK => proc.sigmask = *set,
 
 			_ => return Err(errno!(EINVAL)),
 		}
@@ -0,0 +1,96 @@
+//! The `rt_sigqueueinfo` system call sends a signal with informations to a process.
+
+use super::util;
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::pid::Pid;
+use crate::process::signal::SigInfo;
+use crate::process::signal::Signal;
+use crate::process::signal::SI_TKILL;
+use crate::process::Process;
+use crate::process::State;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Reads the signal informations `info` provided by userspace to send the signal `sig`.
+///
+/// Arguments:
+/// - `proc` is the current process.
+/// - `info` is the pointer to the informations.
+/// - `sig` is the signal to be sent.
+/// - `to_self` tells whether the signal is sent to the current process. If not, the process cannot
+///   pretend the signal has been sent by the kernel or by `kill`.
+pub fn read_info(
+	proc: &Process,
+	info: &SyscallPtr<SigInfo>,
+	sig: &Signal,
+	to_self: bool,
+) -> EResult<SigInfo> {
+	let mem_space = proc.get_mem_space().unwrap();
+	let mem_space_guard = mem_space.lock();
+	let mut info = *info.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
+	if !to_self && (info.si_code >= 0 || info.si_code == SI_TKILL) {
+		return Err(errno!(EPERM));
+	}
+	info.si_signo = sig.get_id() as _;
+	Ok(info)
+}
+
+#[syscall]
+pub fn rt_sigqueueinfo(tgid: Pid, sig: c_int, info: SyscallPtr<SigInfo>) -> Result<i32, Errno> {
+	if sig < 0 {
+		return Err(errno!(EINVAL));
+	}
+	let sig = if sig > 0 {
+		Some(Signal::try_from(sig as u32)?)
+	} else {
+		None
+	};
+
+	let proc_mutex = Process::current_assert();
+	let target_mutex = Process::get_by_vpid(tgid).ok_or_else(|| errno!(ESRCH))?;
+	let to_self = target_mutex.as_ptr() == proc_mutex.as_ptr();
+	let (info, ap) = {
+		let proc = proc_mutex.lock();
+		let info = sig
+			.as_ref()
+			.map(|sig| read_info(&proc, &info, sig, to_self))
+			.transpose()?;
+		(info, proc.access_profile)
+	};
+
+	cli!();
+
+	{
+		let mut target = target_mutex.lock();
+		if matches!(target.get_state(), State::Zombie) {
+			return Err(errno!(ESRCH));
+		}
+		if !ap.can_kill(&target) {
+			return Err(errno!(EPERM));
+		}
+		if let (Some(sig), Some(info)) = (&sig, info) {
+			target.kill_with_info(sig, info, false)?;
+		}
+	}
+
+	// If the process sent the signal to itself, it is executed before returning
+	if to_self {
+		let mut proc = proc_mutex.lock();
+		if proc.has_signal_pending() {
+			// Setting the return value of the system call to `0` after executing a signal
+			let mut return_regs = regs.clone();
+			return_regs.eax = 0;
+			proc.regs = return_regs;
+
+			// Set the process to execute the signal action
+			proc.signal_next();
+		}
+	}
+
+	util::handle_proc_state();
+
+	Ok(0)
+}
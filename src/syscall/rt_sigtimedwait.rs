@@ -0,0 +1,115 @@
+//! The `rt_sigtimedwait` system call waits for a signal among a given set to be pending, then
+//! accepts it synchronously instead of executing its action.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::scheduler;
+use crate::process::signal;
+use crate::process::signal::SigInfo;
+use crate::process::signal::SigSet;
+use crate::process::signal::Signal;
+use crate::process::Process;
+use crate::process::State;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::hrtimer;
+use crate::time::unit::TimeUnit;
+use crate::time::unit::Timespec32;
+use crate::time::unit::TimestampScale;
+use core::mem::size_of;
+use macros::syscall;
+
+/// Waits for a signal in `set`.
+///
+/// Arguments:
+/// - `set` is the set of signals to wait for.
+/// - `info` is where the informations of the accepted signal are written. It may be null.
+/// - `timeout` is the maximum duration of the wait. If null, the wait is not bounded.
+/// - `sigsetsize` is the size of `set` in bytes.
+///
+/// On success, the function returns the ID of the accepted signal.
+pub fn do_rt_sigtimedwait<T: TimeUnit + Copy>(
+	set: SyscallPtr<SigSet>,
+	info: SyscallPtr<SigInfo>,
+	timeout: SyscallPtr<T>,
+	sigsetsize: usize,
+) -> EResult<i32> {
+	if sigsetsize != size_of::<SigSet>() {
+		return Err(errno!(EINVAL));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let (set, timeout) = {
+		let proc = proc_mutex.lock();
+		let mem_space = proc.get_mem_space().unwrap();
+		let mem_space_guard = mem_space.lock();
+		let set = *set.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
+		let timeout = timeout.get(&mem_space_guard)?.map(|t| *t);
+		(set, timeout)
+	};
+	// Signals that cannot be caught cannot be waited for either
+	let set = set & !(Signal::SIGKILL.get_mask() | Signal::SIGSTOP.get_mask());
+	let deadline = match timeout {
+		Some(timeout) => {
+			// If the number of nanoseconds is out of range, the value is not normalized
+			if T::from_nano(timeout.to_nano()) != timeout {
+				return Err(errno!(EINVAL));
+			}
+			let now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond)?;
+			Some(now.saturating_add(timeout.to_nano()))
+		}
+		None => None,
+	};
+
+	loop {
+		{
+			let mut proc = proc_mutex.lock();
+			// Signals with the lowest ID are accepted first
+			let sig = (1..signal::SIGNALS_COUNT as u32)
+				.filter_map(|i| Signal::try_from(i).ok())
+				.find(|s| proc.get_pending_signals() & set & s.get_mask() != 0);
+			if let Some(sig) = sig {
+				proc.sigwait = 0;
+				let sig_info = proc.signal_dequeue(&sig);
+				let mem_space = proc.get_mem_space().unwrap().clone();
+				let mut mem_space_guard = mem_space.lock();
+				if let Some(mut info) = info.get_mut(&mut mem_space_guard)? {
+					*info = sig_info;
+				}
+				return Ok(sig.get_id() as _);
+			}
+			// Another signal interrupts the wait
+			if proc.get_next_signal().is_some() {
+				proc.sigwait = 0;
+				return Err(errno!(EINTR));
+			}
+
+			if let Some(deadline) = deadline {
+				let now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond)?;
+				if now >= deadline {
+					proc.sigwait = 0;
+					return Err(errno!(EAGAIN));
+				}
+				hrtimer::insert(deadline, proc.pid)?;
+			}
+			// The process is woken up when a signal of the set is sent, even if blocked
+			proc.sigwait = set;
+			proc.set_state(State::Sleeping);
+		}
+		scheduler::end_tick();
+		if let Some(deadline) = deadline {
+			hrtimer::remove(deadline, proc_mutex.lock().pid);
+		}
+	}
+}
+
+#[syscall]
+pub fn rt_sigtimedwait(
+	set: SyscallPtr<SigSet>,
+	info: SyscallPtr<SigInfo>,
+	timeout: SyscallPtr<Timespec32>,
+	sigsetsize: usize,
+) -> Result<i32, Errno> {
+	do_rt_sigtimedwait(set, info, timeout, sigsetsize)
+}
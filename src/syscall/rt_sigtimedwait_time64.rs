@@ -0,0 +1,18 @@
+//! `rt_sigtimedwait_time64` is like `rt_sigtimedwait` but using 64 bits.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::signal::SigInfo;
+use crate::process::signal::SigSet;
+use crate::time::unit::Timespec;
+use macros::syscall;
+
+#[syscall]
+pub fn rt_sigtimedwait_time64(
+	set: SyscallPtr<SigSet>,
+	info: SyscallPtr<SigInfo>,
+	timeout: SyscallPtr<Timespec>,
+	sigsetsize: usize,
+) -> Result<i32, Errno> {
+	super::rt_sigtimedwait::do_rt_sigtimedwait(set, info, timeout, sigsetsize)
+}
@@ -0,0 +1,15 @@
+//! The `sched_get_priority_max` system call returns the maximum priority for a scheduling policy.
+
+use crate::errno::Errno;
+use crate::process::scheduler;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall]
+pub fn sched_get_priority_max(policy: c_int) -> Result<i32, Errno> {
+	match policy {
+		scheduler::SCHED_OTHER => Ok(0),
+		scheduler::SCHED_FIFO | scheduler::SCHED_RR => Ok(scheduler::RT_PRIORITY_MAX as _),
+		_ => Err(errno!(EINVAL)),
+	}
+}
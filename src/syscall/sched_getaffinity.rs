@@ -0,0 +1,41 @@
+//! The `sched_getaffinity` system call returns the set of CPUs a process is allowed to run on.
+
+use crate::errno::Errno;
+use crate::process;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::pid::Pid;
+use crate::process::scheduler::CpuSet;
+use crate::process::Process;
+use core::mem::size_of;
+use macros::syscall;
+
+#[syscall]
+pub fn sched_getaffinity(pid: Pid, len: usize, mask: SyscallSlice<u8>) -> Result<i32, Errno> {
+	// The buffer must be large enough to hold every CPUs
+	if len < size_of::<CpuSet>() {
+		return Err(errno!(EINVAL));
+	}
+
+	let online = process::get_scheduler().lock().get_online_cpus();
+
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	let affinity = if pid == 0 || pid == proc.to_local_pid(proc.pid) {
+		proc.cpu_affinity
+	} else {
+		let target_mutex = Process::get_by_vpid(pid).ok_or_else(|| errno!(ESRCH))?;
+		let target = target_mutex.lock();
+		target.cpu_affinity
+	};
+	let affinity = affinity & online;
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+	let mut mask = mask
+		.get_mut(&mut mem_space_guard, size_of::<CpuSet>())?
+		.ok_or_else(|| errno!(EFAULT))?;
+	mask.copy_from_slice(&affinity.to_ne_bytes());
+
+	Ok(size_of::<CpuSet>() as _)
+}
@@ -0,0 +1,30 @@
+//! The `sched_getparam` system call returns the scheduling parameters of a process.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::pid::Pid;
+use crate::process::scheduler::SchedParam;
+use crate::process::Process;
+use macros::syscall;
+
+#[syscall]
+pub fn sched_getparam(pid: Pid, param: SyscallPtr<SchedParam>) -> Result<i32, Errno> {
+	let target_mutex = if pid == 0 {
+		Process::current_assert()
+	} else {
+		Process::get_by_vpid(pid).ok_or_else(|| errno!(ESRCH))?
+	};
+	let rt_priority = target_mutex.lock().get_rt_priority().unwrap_or(0);
+
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+	*param
+		.get_mut(&mut mem_space_guard)?
+		.ok_or_else(|| errno!(EINVAL))? = SchedParam {
+		sched_priority: rt_priority as _,
+	};
+
+	Ok(0)
+}
@@ -0,0 +1,17 @@
+//! The `sched_getscheduler` system call returns the scheduling policy of a process.
+
+use crate::errno::Errno;
+use crate::process::pid::Pid;
+use crate::process::Process;
+use macros::syscall;
+
+#[syscall]
+pub fn sched_getscheduler(pid: Pid) -> Result<i32, Errno> {
+	let target_mutex = if pid == 0 {
+		Process::current_assert()
+	} else {
+		Process::get_by_vpid(pid).ok_or_else(|| errno!(ESRCH))?
+	};
+	let target = target_mutex.lock();
+	Ok(target.sched_policy)
+}
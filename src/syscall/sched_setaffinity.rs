@@ -0,0 +1,57 @@
+//! The `sched_setaffinity` system call sets the set of CPUs a process is allowed to run on.
+
+use crate::cpu;
+use crate::errno::Errno;
+use crate::process;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::pid::Pid;
+use crate::process::scheduler;
+use crate::process::scheduler::CpuSet;
+use crate::process::Process;
+use core::cmp::min;
+use core::mem::size_of;
+use macros::syscall;
+
+#[syscall]
+pub fn sched_setaffinity(pid: Pid, len: usize, mask: SyscallSlice<u8>) -> Result<i32, Errno> {
+	let online = process::get_scheduler().lock().get_online_cpus();
+
+	let proc_mutex = Process::current_assert();
+	let mut proc = proc_mutex.lock();
+
+	// Read the mask. Bits beyond the given length are cleared
+	let new_mask = {
+		let mem_space = proc.get_mem_space().unwrap();
+		let mem_space_guard = mem_space.lock();
+		let len = min(len, size_of::<CpuSet>());
+		let mask = mask
+			.get(&mem_space_guard, len)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		mask.iter()
+			.enumerate()
+			.fold(0 as CpuSet, |set, (i, b)| set | ((*b as CpuSet) << (i * 8)))
+	};
+	// The process must be allowed to run on at least one online CPU
+	if new_mask & online == 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	if pid == 0 || pid == proc.to_local_pid(proc.pid) {
+		proc.cpu_affinity = new_mask;
+		drop(proc);
+
+		// The current CPU might not be allowed anymore
+		if new_mask & (1 << cpu::get_current_id()) == 0 {
+			scheduler::end_tick();
+		}
+	} else {
+		let target_mutex = Process::get_by_vpid(pid).ok_or_else(|| errno!(ESRCH))?;
+		let mut target = target_mutex.lock();
+		if !proc.access_profile.can_kill(&target) {
+			return Err(errno!(EPERM));
+		}
+		target.cpu_affinity = new_mask;
+	}
+
+	Ok(0)
+}
@@ -0,0 +1,13 @@
+//! The `sched_setparam` system call sets the scheduling parameters of a process, keeping its
+//! scheduling policy.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::pid::Pid;
+use crate::process::scheduler::SchedParam;
+use macros::syscall;
+
+#[syscall]
+pub fn sched_setparam(pid: Pid, param: SyscallPtr<SchedParam>) -> Result<i32, Errno> {
+	super::sched_setscheduler::do_sched_setscheduler(pid, None, param)
+}
@@ -0,0 +1,100 @@
+//! The `sched_setscheduler` system call sets the scheduling policy and parameters of a process.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::perm::CAP_SYS_NICE;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::pid::Pid;
+use crate::process::rlimit;
+use crate::process::scheduler;
+use crate::process::scheduler::SchedParam;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Sets the scheduling policy and parameters of a process.
+///
+/// Arguments:
+/// - `pid` is the PID of the process. If zero, the current process is used.
+/// - `policy` is the new scheduling policy. If `None`, the policy is left unchanged.
+/// - `param` is the pointer to the new scheduling parameters.
+pub fn do_sched_setscheduler(
+	pid: Pid,
+	policy: Option<c_int>,
+	param: SyscallPtr<SchedParam>,
+) -> EResult<i32> {
+	let (param, access_profile) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+		let mem_space = proc.get_mem_space().unwrap();
+		let mem_space_guard = mem_space.lock();
+		let param = *param.get(&mem_space_guard)?.ok_or_else(|| errno!(EINVAL))?;
+		(param, proc.access_profile)
+	};
+	let target_mutex = if pid == 0 {
+		Process::current_assert()
+	} else {
+		Process::get_by_vpid(pid).ok_or_else(|| errno!(ESRCH))?
+	};
+
+	{
+		let mut target = target_mutex.lock();
+		let policy = policy.unwrap_or(target.sched_policy);
+
+		// Validate parameters
+		let prio = param.sched_priority;
+		let rt = match policy {
+			scheduler::SCHED_OTHER => false,
+			scheduler::SCHED_FIFO | scheduler::SCHED_RR => true,
+			_ => return Err(errno!(EINVAL)),
+		};
+		let valid_prio = if rt {
+			(scheduler::RT_PRIORITY_MIN as c_int..=scheduler::RT_PRIORITY_MAX as c_int)
+				.contains(&prio)
+		} else {
+			prio == 0
+		};
+		if !valid_prio {
+			return Err(errno!(EINVAL));
+		}
+		let prio = prio as u32;
+
+		// Check permissions
+		if !access_profile.has_cap(CAP_SYS_NICE) {
+			if !access_profile.can_kill(&target) {
+				return Err(errno!(EPERM));
+			}
+			if rt {
+				let limit = target.rlimits.get_cur(rlimit::RLIMIT_RTPRIO);
+				// Switching to a real-time policy requires a non-zero limit
+				if policy != target.sched_policy && limit == 0 {
+					return Err(errno!(EPERM));
+				}
+				// The priority may not be raised above the limit
+				let curr_prio = target.get_rt_priority().unwrap_or(0);
+				if prio > curr_prio && prio as rlimit::RLim > limit {
+					return Err(errno!(EPERM));
+				}
+			}
+		}
+
+		target.sched_policy = policy;
+		target.rt_priority = prio;
+	}
+
+	// Another process may now have precedence
+	scheduler::end_tick();
+	Ok(0)
+}
+
+#[syscall]
+pub fn sched_setscheduler(
+	pid: Pid,
+	policy: c_int,
+	param: SyscallPtr<SchedParam>,
+) -> Result<i32, Errno> {
+	if policy < 0 {
+		return Err(errno!(EINVAL));
+	}
+	do_sched_setscheduler(pid, Some(policy), param)
+}
@@ -0,0 +1,100 @@
+//! The `seccomp` system call restricts the system calls the current process is allowed to
+//! perform.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::perm::CAP_SYS_ADMIN;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::seccomp;
+use crate::process::seccomp::SockFilter;
+use crate::process::seccomp::SockFprog;
+use crate::process::Process;
+use crate::util::container::vec::Vec;
+use core::ffi::c_uint;
+use core::ffi::c_void;
+use macros::syscall;
+
+/// Operation: enable strict mode.
+const SECCOMP_SET_MODE_STRICT: c_uint = 0;
+/// Operation: install a filter.
+const SECCOMP_SET_MODE_FILTER: c_uint = 1;
+/// Operation: tell whether an action is supported.
+const SECCOMP_GET_ACTION_AVAIL: c_uint = 2;
+
+/// Sets the seccomp mode of the given process.
+///
+/// Arguments:
+/// - `proc` is the process.
+/// - `mode` is the mode to enable.
+/// - `prog` is the filter to install, if `mode` is [`seccomp::SECCOMP_MODE_FILTER`].
+pub fn set_mode(proc: &mut Process, mode: u32, prog: SyscallPtr<SockFprog>) -> EResult<()> {
+	match mode {
+		seccomp::SECCOMP_MODE_STRICT => proc.seccomp.set_strict(),
+
+		seccomp::SECCOMP_MODE_FILTER => {
+			// Filters cannot be used to confuse privileged programs
+			if !proc.no_new_privs && !proc.access_profile.has_cap(CAP_SYS_ADMIN) {
+				return Err(errno!(EACCES));
+			}
+
+			let prog = {
+				let mem_space = proc.get_mem_space().unwrap();
+				let mem_space_guard = mem_space.lock();
+				let fprog = prog.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
+				let filter: SyscallSlice<SockFilter> = (fprog.filter as usize).into();
+				let filter = filter
+					.get(&mem_space_guard, fprog.len as _)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				Vec::from_slice(&filter)?
+			};
+			proc.seccomp.add_filter(prog)
+		}
+
+		_ => Err(errno!(EINVAL)),
+	}
+}
+
+#[syscall]
+pub fn seccomp(operation: c_uint, flags: c_uint, args: *mut c_void) -> Result<i32, Errno> {
+	// Flags are not supported
+	if flags != 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let mut proc = proc_mutex.lock();
+
+	match operation {
+		SECCOMP_SET_MODE_STRICT => {
+			if !args.is_null() {
+				return Err(errno!(EINVAL));
+			}
+			set_mode(&mut proc, seccomp::SECCOMP_MODE_STRICT, 0.into())?;
+		}
+
+		SECCOMP_SET_MODE_FILTER => {
+			set_mode(
+				&mut proc,
+				seccomp::SECCOMP_MODE_FILTER,
+				(args as usize).into(),
+			)?;
+		}
+
+		SECCOMP_GET_ACTION_AVAIL => {
+			let action: SyscallPtr<u32> = (args as usize).into();
+			let mem_space = proc.get_mem_space().unwrap();
+			let mem_space_guard = mem_space.lock();
+			let action = *action
+				.get(&mem_space_guard)?
+				.ok_or_else(|| errno!(EFAULT))?;
+			if !seccomp::is_action_available(action) {
+				return Err(errno!(EOPNOTSUPP));
+			}
+		}
+
+		_ => return Err(errno!(EINVAL)),
+	}
+
+	Ok(0)
+}
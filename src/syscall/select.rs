@@ -80,7 +80,11 @@ pub fn do_select<T: TimeUnit>(
 
 		let mem_space = proc.get_mem_space().unwrap();
 		let mem_space_guard = mem_space.lock();
-		timeout.get(&mem_space_guard)?.cloned().unwrap_or_default()
+		timeout
+			.get(&mem_space_guard)?
+			.as_deref()
+			.cloned()
+			.unwrap_or_default()
 	};
 
 	// Tells whether the syscall immediately returns
@@ -165,32 +169,32 @@ pub fn do_select<T: TimeUnit>(
 			if read && result & io::POLLIN != 0 {
 				readfds
 					.get_mut(&mut mem_space_guard)?
-					.map(|fds| fds.set(fd_id));
+					.map(|mut fds| fds.set(fd_id));
 				events_count += 1;
 			} else {
 				readfds
 					.get_mut(&mut mem_space_guard)?
-					.map(|fds| fds.clear(fd_id));
+					.map(|mut fds| fds.clear(fd_id));
 			}
 			if write && result & io::POLLOUT != 0 {
 				writefds
 					.get_mut(&mut mem_space_guard)?
-					.map(|fds| fds.set(fd_id));
+					.map(|mut fds| fds.set(fd_id));
 				events_count += 1;
 			} else {
 				writefds
 					.get_mut(&mut mem_space_guard)?
-					.map(|fds| fds.clear(fd_id));
+					.map(|mut fds| fds.clear(fd_id));
 			}
 			if except && result & io::POLLPRI != 0 {
 				exceptfds
 					.get_mut(&mut mem_space_guard)?
-					.map(|fds| fds.set(fd_id));
+					.map(|mut fds| fds.set(fd_id));
 				events_count += 1;
 			} else {
 				exceptfds
 					.get_mut(&mut mem_space_guard)?
-					.map(|fds| fds.clear(fd_id));
+					.map(|mut fds| fds.clear(fd_id));
 			}
 		}
 
@@ -205,6 +209,9 @@ pub fn do_select<T: TimeUnit>(
 			return Ok(0);
 		}
 
+		// The call is interrupted to handle the signal
+		super::util::signal_check().map_err(|_| errno!(ERESTARTNOHAND))?;
+
 		// TODO Make the process sleep?
 		scheduler::end_tick();
 	}
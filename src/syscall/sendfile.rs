@@ -0,0 +1,226 @@
+//! The `sendfile` system call copies data from one file descriptor to another.
+//!
+//! The data is transferred inside of the kernel. When the input is a file whose content is held
+//! by the page cache, pages are written to the output directly from the cache, without being
+//! copied to an intermediate buffer.
+
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::buffer::fanotify;
+use crate::file::open_file::OpenFile;
+use crate::file::open_file::O_APPEND;
+use crate::file::page_cache;
+use crate::file::FileType;
+use crate::memory;
+use crate::memory::malloc;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::Process;
+use crate::util::io::IO;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use core::cmp::min;
+use core::ffi::c_int;
+use core::ffi::c_long;
+use core::num::NonZeroUsize;
+use macros::syscall;
+
+/// The maximum number of bytes transferred by one call.
+const MAX_COUNT: usize = 0x7ffff000;
+
+/// Writes the whole buffer `buf` to the open file `output`.
+///
+/// The function returns the number of bytes written, which is lower than the size of the buffer
+/// only if the output cannot take more data.
+fn write_all(output: &Mutex<OpenFile>, buf: &[u8]) -> EResult<usize> {
+	let mut output = output.lock();
+	let mut i = 0;
+	while i < buf.len() {
+		let l = output.write(0, &buf[i..])? as usize;
+		if l == 0 {
+			break;
+		}
+		i += l;
+	}
+	Ok(i)
+}
+
+/// Transfers the page of the input file containing the offset `off`, writing at most `len`
+/// bytes to the output.
+///
+/// If the content of the input file is not held by the page cache, the function returns `None`.
+///
+/// Else, it returns the number of bytes written, or zero if the end of the input file is reached.
+fn transfer_cached(
+	input: &Mutex<OpenFile>,
+	output: &Mutex<OpenFile>,
+	off: u64,
+	len: usize,
+) -> EResult<Option<usize>> {
+	let page_off = off / memory::PAGE_SIZE as u64;
+	let inner_off = (off % memory::PAGE_SIZE as u64) as usize;
+	let (location, ptr, len) = {
+		let input = input.lock();
+		let mut file = input.get_file().lock();
+		if !file.is_cached() {
+			return Ok(None);
+		}
+		let size = file.get_size();
+		if off >= size {
+			return Ok(Some(0));
+		}
+		let len = min(len, memory::PAGE_SIZE - inner_off).min((size - off) as usize);
+		let location = file.get_location().clone();
+		let ptr = page_cache::pin(&mut file, page_off)?;
+		(location, ptr, len)
+	};
+	// The lock on the input file is not held, so that the output may be the same file
+	let content = unsafe { &page_cache::pinned_content(ptr)[inner_off..(inner_off + len)] };
+	let res = write_all(output, content);
+	page_cache::unpin(&location, page_off, ptr);
+	res.map(Some)
+}
+
+/// Performs the `sendfile` system call.
+///
+/// Arguments:
+/// - `out_fd` and `in_fd` are the output and input file descriptors.
+/// - `offset` is the offset at which reading begins. If `None`, the offset of the input file is
+/// used and updated.
+/// - `count` is the number of bytes to transfer.
+///
+/// On success, the function returns the number of bytes transferred and the offset following the
+/// last byte read.
+pub fn do_sendfile(
+	out_fd: c_int,
+	in_fd: c_int,
+	offset: Option<u64>,
+	count: usize,
+) -> EResult<(usize, u64)> {
+	if out_fd < 0 || in_fd < 0 {
+		return Err(errno!(EBADF));
+	}
+
+	let (input_mutex, output_mutex) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let fds_mutex = proc.get_fds().unwrap();
+		let fds = fds_mutex.lock();
+
+		let input: Arc<Mutex<OpenFile>> = fds
+			.get_fd(in_fd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file()
+			.clone();
+		let output: Arc<Mutex<OpenFile>> = fds
+			.get_fd(out_fd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file()
+			.clone();
+		(input, output)
+	};
+
+	let mut off = {
+		let input = input_mutex.lock();
+		if !input.can_read() {
+			return Err(errno!(EBADF));
+		}
+		let input_type = input.get_file().lock().get_type();
+		if offset.is_some() && matches!(input_type, FileType::Fifo | FileType::Socket) {
+			return Err(errno!(ESPIPE));
+		}
+		offset.unwrap_or_else(|| input.get_offset())
+	};
+	{
+		let output = output_mutex.lock();
+		if !output.can_write() {
+			return Err(errno!(EBADF));
+		}
+		if output.get_flags() & O_APPEND != 0 {
+			return Err(errno!(EINVAL));
+		}
+	}
+
+	let count = min(count, MAX_COUNT);
+	if count == 0 {
+		return Ok((0, off));
+	}
+
+	OpenFile::check_permission(&input_mutex, fanotify::FAN_ACCESS_PERM)?;
+
+	// The buffer used when the input is not in the page cache, allocated on first use
+	let mut buff: Option<malloc::Alloc<u8>> = None;
+
+	let mut total = 0;
+	while total < count {
+		// TODO Check for signals (and return the number of bytes already transferred)
+		let len = count - total;
+		let l = match transfer_cached(&input_mutex, &output_mutex, off, len)? {
+			Some(l) => l,
+			None => {
+				let buff = match &mut buff {
+					Some(buff) => buff,
+					None => buff.insert(unsafe {
+						// Safe because initialized memory is never read
+						malloc::Alloc::<u8>::new(NonZeroUsize::new(memory::PAGE_SIZE).unwrap())?
+					}),
+				};
+				let len = min(len, memory::PAGE_SIZE);
+				let len = {
+					let mut input = input_mutex.lock();
+					let prev_off = input.get_offset();
+					input.set_offset(off);
+					let (len, _) = input.read(0, &mut buff.as_slice_mut()[..len])?;
+					if offset.is_some() {
+						input.set_offset(prev_off);
+					}
+					len as usize
+				};
+				write_all(&output_mutex, &buff.as_slice()[..len])?
+			}
+		};
+		if l == 0 {
+			break;
+		}
+		off += l as u64;
+		total += l;
+	}
+
+	if offset.is_none() {
+		input_mutex.lock().set_offset(off);
+	}
+	Ok((total, off))
+}
+
+#[syscall]
+pub fn sendfile(
+	out_fd: c_int,
+	in_fd: c_int,
+	offset: SyscallPtr<c_long>,
+	count: usize,
+) -> Result<i32, Errno> {
+	let mem_space = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+		proc.get_mem_space().unwrap().clone()
+	};
+
+	let off = {
+		let mem_space_guard = mem_space.lock();
+		offset.get(&mem_space_guard)?.as_deref().cloned()
+	};
+	let off = off
+		.map(|off| u64::try_from(off).map_err(|_| errno!(EINVAL)))
+		.transpose()?;
+
+	let (len, new_off) = do_sendfile(out_fd, in_fd, off, count)?;
+
+	if off.is_some() {
+		let mut mem_space_guard = mem_space.lock();
+		if let Some(mut offset) = offset.get_mut(&mut mem_space_guard)? {
+			*offset = new_off.try_into().map_err(|_| errno!(EOVERFLOW))?;
+		}
+	}
+	Ok(len as _)
+}
@@ -0,0 +1,37 @@
+//! The `sendfile64` system call copies data from one file descriptor to another, taking a 64 bits
+//! offset.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall]
+pub fn sendfile64(
+	out_fd: c_int,
+	in_fd: c_int,
+	offset: SyscallPtr<u64>,
+	count: usize,
+) -> Result<i32, Errno> {
+	let mem_space = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+		proc.get_mem_space().unwrap().clone()
+	};
+
+	let off = {
+		let mem_space_guard = mem_space.lock();
+		offset.get(&mem_space_guard)?.as_deref().cloned()
+	};
+
+	let (len, new_off) = super::sendfile::do_sendfile(out_fd, in_fd, off, count)?;
+
+	if off.is_some() {
+		let mut mem_space_guard = mem_space.lock();
+		if let Some(mut offset) = offset.get_mut(&mut mem_space_guard)? {
+			*offset = new_off;
+		}
+	}
+	Ok(len as _)
+}
@@ -0,0 +1,23 @@
+//! The `set_robust_list` system call registers the list of robust futexes held by the current
+//! process.
+
+use crate::errno::Errno;
+use crate::process::futex::RobustListHead;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::Process;
+use core::mem::size_of;
+use core::ptr::NonNull;
+use macros::syscall;
+
+#[syscall]
+pub fn set_robust_list(head: SyscallPtr<RobustListHead>, len: usize) -> Result<i32, Errno> {
+	if len != size_of::<RobustListHead>() {
+		return Err(errno!(EINVAL));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let mut proc = proc_mutex.lock();
+	proc.set_robust_list(NonNull::new(head.as_ptr_mut()));
+
+	Ok(0)
+}
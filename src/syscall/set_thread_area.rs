@@ -25,6 +25,17 @@ pub fn get_free_entry(process: &mut Process) -> Result<usize, Errno> {
 	Err(errno!(ESRCH))
 }
 
+/// Returns the index in the process's TLS entries of the GDT entry number `entry_number`.
+///
+/// If the entry number is not a TLS entry, the function returns `EINVAL`.
+pub fn get_entry_id(entry_number: i32) -> Result<usize, Errno> {
+	entry_number
+		.checked_sub(TLS_BEGIN_INDEX as i32)
+		.filter(|id| (0..process::TLS_ENTRIES_COUNT as i32).contains(id))
+		.map(|id| id as usize)
+		.ok_or_else(|| errno!(EINVAL))
+}
+
 /// Returns an entry ID for the given process and entry number.
 ///
 /// If the id is `-1`, the function shall find a free entry.
@@ -32,21 +43,12 @@ pub fn get_entry(
 	proc: &mut Process,
 	entry_number: i32,
 ) -> Result<(usize, &mut gdt::Entry), Errno> {
-	let end_entry = (TLS_BEGIN_INDEX + process::TLS_ENTRIES_COUNT) as i32;
-
-	// Checking the entry number is in bound
-	if entry_number != -1 && entry_number < TLS_BEGIN_INDEX as i32 || entry_number > end_entry {
-		return Err(errno!(EINVAL));
-	}
-
 	// The entry's ID
-	let id = {
-		if entry_number == -1 {
-			// Allocating an entry
-			get_free_entry(proc)?
-		} else {
-			entry_number as usize
-		}
+	let id = if entry_number == -1 {
+		// Allocating an entry
+		get_free_entry(proc)?
+	} else {
+		get_entry_id(entry_number)?
 	};
 
 	Ok((id, &mut proc.get_tls_entries()[id]))
@@ -61,7 +63,7 @@ pub fn set_thread_area(u_info: SyscallPtr<UserDesc>) -> Result<i32, Errno> {
 	let mut mem_space_guard = mem_space.lock();
 
 	// A reference to the user_desc structure
-	let info = u_info
+	let mut info = u_info
 		.get_mut(&mut mem_space_guard)?
 		.ok_or(errno!(EFAULT))?;
 
@@ -1,5 +1,8 @@
 //! The `set_tid_address` system call sets the `clear_child_tid` attribute with
 //! the given pointer.
+//!
+//! When the process exits, zero is written at this address and a process waiting on it with
+//! `futex` is woken up.
 
 use crate::errno::Errno;
 use crate::process::mem_space::ptr::SyscallPtr;
@@ -16,12 +19,5 @@ pub fn set_tid_address(tidptr: SyscallPtr<c_int>) -> Result<i32, Errno> {
 	let ptr = NonNull::new(tidptr.as_ptr_mut());
 	proc.set_clear_child_tid(ptr);
 
-	let mem_space = proc.get_mem_space().unwrap();
-	let mut mem_space_guard = mem_space.lock();
-	// Setting the TID at pointer if accessible
-	if let Some(tidptr) = tidptr.get_mut(&mut mem_space_guard)? {
-		*tidptr = proc.tid as _;
-	}
-
 	Ok(proc.tid as _)
 }
@@ -0,0 +1,16 @@
+//! The `setfsgid32` syscall sets the GID of the process's owner used to check accesses to files.
+//!
+//! The syscall never fails. It returns the previous ID, whether it has been changed or not.
+
+use crate::errno::Errno;
+use crate::file::perm::Gid;
+use crate::process::Process;
+use macros::syscall;
+
+#[syscall]
+pub fn setfsgid32(gid: Gid) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let mut proc = proc_mutex.lock();
+
+	Ok(proc.access_profile.set_fsgid(gid) as _)
+}
@@ -0,0 +1,16 @@
+//! The `setfsuid32` syscall sets the UID of the process's owner used to check accesses to files.
+//!
+//! The syscall never fails. It returns the previous ID, whether it has been changed or not.
+
+use crate::errno::Errno;
+use crate::file::perm::Uid;
+use crate::process::Process;
+use macros::syscall;
+
+#[syscall]
+pub fn setfsuid32(uid: Uid) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let mut proc = proc_mutex.lock();
+
+	Ok(proc.access_profile.set_fsuid(uid) as _)
+}
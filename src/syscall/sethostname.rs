@@ -1,6 +1,7 @@
 //! The `sethostname` syscall sets the hostname of the system.
 
 use crate::errno::Errno;
+use crate::file::perm::CAP_SYS_ADMIN;
 use crate::limits;
 use crate::process::mem_space::ptr::SyscallSlice;
 use crate::process::Process;
@@ -17,7 +18,7 @@ pub fn sethostname(name: SyscallSlice<u8>, len: usize) -> Result<i32, Errno> {
 	let proc = proc_mutex.lock();
 
 	// Checking permission
-	if !proc.access_profile.is_privileged() {
+	if !proc.access_profile.has_cap(CAP_SYS_ADMIN) {
 		return Err(errno!(EPERM));
 	}
 
@@ -27,7 +28,7 @@ pub fn sethostname(name: SyscallSlice<u8>, len: usize) -> Result<i32, Errno> {
 
 	let mut hostname = crate::HOSTNAME.lock();
 	hostname.resize(len)?;
-	hostname.as_mut_slice().copy_from_slice(name_slice);
+	hostname.as_mut_slice().copy_from_slice(&name_slice);
 
 	Ok(0)
 }
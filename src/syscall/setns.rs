@@ -0,0 +1,106 @@
+//! The `setns` system call allows the current process to join the namespaces of another
+//! process.
+//!
+//! The namespace is designated either by a file of the `/proc/[pid]/ns` directory or by a pidfd.
+//! In the latter case, the process joins every namespace given in `nstype` from the referred
+//! process.
+
+use super::clone::CLONE_NEWNS;
+use super::clone::CLONE_NEWPID;
+use crate::errno::Errno;
+use crate::file::buffer::pidfd;
+use crate::file::fs::procfs::NsType;
+use crate::file::fs::procfs::ProcFS;
+use crate::file::mountpoint::MountNamespace;
+use crate::file::perm::CAP_SYS_ADMIN;
+use crate::file::FileLocation;
+use crate::process::pid::Pid;
+use crate::process::pid::PidNamespace;
+use crate::process::Process;
+use crate::util::ptr::arc::Arc;
+use core::any::Any;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Returns the PID and the namespace type referred to by the namespace file at location
+/// `location`.
+///
+/// If the file is not a namespace file, the function returns `None`.
+fn get_ns_file(location: &FileLocation) -> Option<(Pid, NsType)> {
+	let mp_mutex = location.get_mountpoint()?;
+	let fs_mutex = mp_mutex.lock().get_filesystem();
+	let fs = fs_mutex.lock();
+	let procfs = (&*fs as &dyn Any).downcast_ref::<ProcFS>()?;
+	procfs.get_ns_file(location.get_inode())
+}
+
+#[syscall]
+pub fn setns(fd: c_int, nstype: c_int) -> Result<i32, Errno> {
+	if fd < 0 {
+		return Err(errno!(EBADF));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let location = {
+		let proc = proc_mutex.lock();
+		if !proc.access_profile.has_cap(CAP_SYS_ADMIN) {
+			return Err(errno!(EPERM));
+		}
+
+		let fds_mutex = proc.get_fds().unwrap();
+		let fds = fds_mutex.lock();
+		let open_file_mutex = fds
+			.get_fd(fd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file();
+		let open_file = open_file_mutex.lock();
+		open_file.get_location().clone()
+	};
+
+	// The namespaces to join
+	let mut mnt_ns: Option<Arc<MountNamespace>> = None;
+	let mut pid_ns: Option<Arc<PidNamespace>> = None;
+	if let Some(pidfd_mutex) = pidfd::get(&location) {
+		if nstype == 0 || nstype & !(CLONE_NEWNS | CLONE_NEWPID) != 0 {
+			return Err(errno!(EINVAL));
+		}
+		// If the process has been reaped, the pointer cannot be upgraded
+		let target_mutex = pidfd_mutex
+			.lock()
+			.get_process()
+			.ok_or_else(|| errno!(ESRCH))?;
+		let target = target_mutex.lock();
+		if nstype & CLONE_NEWNS != 0 {
+			mnt_ns = Some(target.get_mnt_ns().clone());
+		}
+		if nstype & CLONE_NEWPID != 0 {
+			pid_ns = Some(target.get_pid_ns().clone());
+		}
+	} else {
+		let (pid, ns_type) = get_ns_file(&location).ok_or_else(|| errno!(EINVAL))?;
+		let flag = match ns_type {
+			NsType::Mnt => CLONE_NEWNS,
+			NsType::Pid | NsType::PidForChildren => CLONE_NEWPID,
+		};
+		if nstype != 0 && nstype != flag {
+			return Err(errno!(EINVAL));
+		}
+		let target_mutex = Process::get_by_pid(pid).ok_or_else(|| errno!(ESRCH))?;
+		let target = target_mutex.lock();
+		match ns_type {
+			NsType::Mnt => mnt_ns = Some(target.get_mnt_ns().clone()),
+			NsType::Pid => pid_ns = Some(target.get_pid_ns().clone()),
+			NsType::PidForChildren => pid_ns = Some(target.get_pid_ns_for_children().clone()),
+		}
+	}
+
+	let mut proc = proc_mutex.lock();
+	if let Some(pid_ns) = pid_ns {
+		proc.set_pid_ns_for_children(pid_ns)?;
+	}
+	if let Some(mnt_ns) = mnt_ns {
+		proc.set_mnt_ns(mnt_ns)?;
+	}
+
+	Ok(0)
+}
@@ -9,31 +9,40 @@ use macros::syscall;
 
 #[syscall]
 pub fn setpgid(pid: Pid, pgid: Pid) -> Result<i32, Errno> {
-	let mut pid = pid;
-	let mut pgid = pgid;
-
-	// TODO Check processes SID
-
 	let proc_mutex = Process::current_assert();
-	let mut proc = proc_mutex.lock();
-
-	if pid == 0 {
-		pid = proc.pid;
-	}
-	if pgid == 0 {
-		pgid = pid;
-	}
-
-	if pid == proc.pid {
-		proc.pgid = pgid;
+	let (curr_pid, curr_tgid, curr_sid, pid, pgid) = {
+		let proc = proc_mutex.lock();
+
+		let pid = if pid == 0 {
+			proc.pid
+		} else {
+			proc.to_global_pid(pid).ok_or_else(|| errno!(ESRCH))?
+		};
+		let pgid = if pgid == 0 {
+			pid
+		} else {
+			proc.to_global_pid(pgid).ok_or_else(|| errno!(EPERM))?
+		};
+		(proc.pid, proc.tgid, proc.sid, pid, pgid)
+	};
+
+	// The target must be the current process or one of its children
+	let target_mutex = if pid == curr_pid {
+		proc_mutex
 	} else {
-		drop(proc);
-
-		let proc_mutex = Process::get_by_pid(pid).ok_or_else(|| errno!(ESRCH))?;
-		let mut proc = proc_mutex.lock();
-
-		proc.set_pgid(pgid)?;
+		let target_mutex = Process::get_by_pid(pid).ok_or_else(|| errno!(ESRCH))?;
+		if target_mutex.lock().get_parent_pid() != curr_tgid {
+			return Err(errno!(ESRCH));
+		}
+		target_mutex
+	};
+	let mut target = target_mutex.lock();
+
+	// A session leader cannot change group, and groups cannot be shared across sessions
+	if target.is_session_leader() || target.sid != curr_sid {
+		return Err(errno!(EPERM));
 	}
+	target.set_pgid(pgid)?;
 
 	Ok(0)
 }
@@ -0,0 +1,110 @@
+//! The `setpriority` system call sets the nice value of processes.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::perm::AccessProfile;
+use crate::file::perm::CAP_SYS_NICE;
+use crate::process;
+use crate::process::rlimit;
+use crate::process::scheduler;
+use crate::process::Process;
+use crate::util::container::vec::Vec;
+use crate::util::lock::IntMutex;
+use crate::util::ptr::arc::Arc;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// `which` value: `who` is the PID of a process.
+pub const PRIO_PROCESS: c_int = 0;
+/// `which` value: `who` is the ID of a process group.
+pub const PRIO_PGRP: c_int = 1;
+/// `which` value: `who` is the real user ID of processes.
+pub const PRIO_USER: c_int = 2;
+
+/// Returns the processes designated by `which` and `who`.
+///
+/// If `who` is zero, the current process, its process group or its real user ID is used.
+///
+/// If no process matches, the function returns an error.
+pub fn get_targets(which: c_int, who: c_int) -> EResult<Vec<Arc<IntMutex<Process>>>> {
+	let (pgid, uid) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+		let pgid = match who {
+			0 => Some(proc.pgid),
+			who => proc.to_global_pid(who as _),
+		};
+		(pgid, proc.access_profile.get_uid())
+	};
+
+	let mut targets = Vec::new();
+	match which {
+		PRIO_PROCESS => {
+			let target = if who == 0 {
+				Process::current_assert()
+			} else {
+				Process::get_by_vpid(who as _).ok_or_else(|| errno!(ESRCH))?
+			};
+			targets.push(target)?;
+		}
+		PRIO_PGRP | PRIO_USER => {
+			let mut sched = process::get_scheduler().lock();
+			for (_, proc_mutex) in sched.iter_process() {
+				let matches = {
+					let proc = proc_mutex.lock();
+					if which == PRIO_PGRP {
+						Some(proc.pgid) == pgid
+					} else {
+						proc.access_profile.get_uid() == if who == 0 { uid } else { who as _ }
+					}
+				};
+				if matches {
+					targets.push(proc_mutex.clone())?;
+				}
+			}
+		}
+		_ => return Err(errno!(EINVAL)),
+	}
+	if targets.is_empty() {
+		return Err(errno!(ESRCH));
+	}
+	Ok(targets)
+}
+
+/// Sets the nice value of the process `target` to `nice`, checking that the process with access
+/// profile `ap` and nice limit `nice_limit` is allowed to do so.
+pub fn set_nice(
+	ap: &AccessProfile,
+	nice_limit: rlimit::RLim,
+	target: &mut Process,
+	nice: c_int,
+) -> EResult<()> {
+	let nice = nice.clamp(scheduler::NICE_MIN, scheduler::NICE_MAX);
+	if !ap.has_cap(CAP_SYS_NICE) {
+		if !ap.can_kill(target) {
+			return Err(errno!(EPERM));
+		}
+		// Lowering the nice value is allowed up to the limit
+		if nice < target.get_nice() && (20 - nice) as rlimit::RLim > nice_limit {
+			return Err(errno!(EACCES));
+		}
+	}
+	target.set_nice(nice);
+	Ok(())
+}
+
+#[syscall]
+pub fn setpriority(which: c_int, who: c_int, prio: c_int) -> Result<i32, Errno> {
+	let (ap, nice_limit) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+		(
+			proc.access_profile,
+			proc.rlimits.get_cur(rlimit::RLIMIT_NICE),
+		)
+	};
+	for target_mutex in get_targets(which, who)? {
+		set_nice(&ap, nice_limit, &mut target_mutex.lock(), prio)?;
+	}
+	Ok(0)
+}
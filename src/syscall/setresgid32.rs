@@ -0,0 +1,19 @@
+//! The `setresgid32` syscall sets the real, effective and saved GIDs of the process's owner.
+
+use crate::errno::Errno;
+use crate::file::perm::Gid;
+use crate::process::Process;
+use macros::syscall;
+
+#[syscall]
+pub fn setresgid32(rgid: Gid, egid: Gid, sgid: Gid) -> Result<i32, Errno> {
+	// `-1` leaves the ID unchanged
+	let id = |id: Gid| (id != Gid::MAX).then_some(id);
+
+	let proc_mutex = Process::current_assert();
+	let mut proc = proc_mutex.lock();
+
+	proc.access_profile
+		.set_resgid(id(rgid), id(egid), id(sgid))?;
+	Ok(0)
+}
@@ -0,0 +1,19 @@
+//! The `setresuid32` syscall sets the real, effective and saved UIDs of the process's owner.
+
+use crate::errno::Errno;
+use crate::file::perm::Uid;
+use crate::process::Process;
+use macros::syscall;
+
+#[syscall]
+pub fn setresuid32(ruid: Uid, euid: Uid, suid: Uid) -> Result<i32, Errno> {
+	// `-1` leaves the ID unchanged
+	let id = |id: Uid| (id != Uid::MAX).then_some(id);
+
+	let proc_mutex = Process::current_assert();
+	let mut proc = proc_mutex.lock();
+
+	proc.access_profile
+		.set_resuid(id(ruid), id(euid), id(suid))?;
+	Ok(0)
+}
@@ -0,0 +1,37 @@
+//! The `setrlimit` system call sets the limit for a given resource.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::rlimit;
+use crate::process::rlimit::RLimit;
+use crate::process::rlimit::RLimit32;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall]
+pub fn setrlimit(resource: c_int, rlim: SyscallPtr<RLimit32>) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let mut proc = proc_mutex.lock();
+
+	let limit = {
+		let mem_space = proc.get_mem_space().unwrap();
+		let mem_space_guard = mem_space.lock();
+		*rlim.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?
+	};
+	let from_32 = |val: u32| {
+		if val == u32::MAX {
+			rlimit::RLIM_INFINITY
+		} else {
+			val as _
+		}
+	};
+	let limit = RLimit {
+		rlim_cur: from_32(limit.rlim_cur),
+		rlim_max: from_32(limit.rlim_max),
+	};
+
+	let access_profile = proc.access_profile;
+	proc.set_rlimit(resource, limit, &access_profile)?;
+	Ok(0)
+}
@@ -0,0 +1,14 @@
+//! The `setsid` system call creates a new session, of which the current process is the leader.
+
+use crate::errno::Errno;
+use crate::process::Process;
+use macros::syscall;
+
+#[syscall]
+pub fn setsid() -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let mut proc = proc_mutex.lock();
+
+	proc.new_session()?;
+	Ok(proc.to_local_pid(proc.sid) as _)
+}
@@ -44,5 +44,5 @@ pub fn setsockopt(
 		.get(&mut mem_space_guard, optlen)?
 		.ok_or(errno!(EFAULT))?;
 
-	sock.set_opt(level, optname, optval_slice)
+	sock.set_opt(level, optname, &optval_slice)
 }
@@ -0,0 +1,37 @@
+//! The `settimeofday` system call sets the wall time.
+
+use crate::errno::Errno;
+use crate::file::perm::CAP_SYS_TIME;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::Process;
+use crate::time::clock;
+use crate::time::clock::CLOCK_REALTIME;
+use crate::time::unit::TimeUnit;
+use crate::time::unit::Timeval;
+use core::ffi::c_void;
+use macros::syscall;
+
+#[syscall]
+pub fn settimeofday(tv: SyscallPtr<Timeval>, _tz: *const c_void) -> Result<i32, Errno> {
+	// The timezone is obsolete and thus ignored
+	let tv = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+		if !proc.access_profile.has_cap(CAP_SYS_TIME) {
+			return Err(errno!(EPERM));
+		}
+
+		let mem_space = proc.get_mem_space().unwrap();
+		let mem_space_guard = mem_space.lock();
+		match tv.get(&mem_space_guard)? {
+			Some(tv) => *tv,
+			None => return Ok(0),
+		}
+	};
+	if tv.tv_usec >= 1_000_000 {
+		return Err(errno!(EINVAL));
+	}
+
+	clock::set(CLOCK_REALTIME, tv.to_nano())?;
+	Ok(0)
+}
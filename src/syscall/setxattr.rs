@@ -0,0 +1,74 @@
+//! The `setxattr` system call sets the value of an extended attribute of a file.
+
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::limits;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::Process;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Performs the `*setxattr` system calls.
+///
+/// Arguments:
+/// - `fd` is the file descriptor of the file, used if `pathname` is `None`.
+/// - `pathname` is the path to the file.
+/// - `follow_links` tells whether symbolic links are followed.
+/// - `name` is the name of the attribute.
+/// - `value` is the new value of the attribute, of size `size`.
+/// - `flags` is a combination of `XATTR_CREATE` and `XATTR_REPLACE`.
+pub fn do_setxattr(
+	fd: c_int,
+	pathname: Option<SyscallString>,
+	follow_links: bool,
+	name: SyscallString,
+	value: SyscallSlice<u8>,
+	size: usize,
+	flags: c_int,
+) -> EResult<i32> {
+	if size > limits::XATTR_SIZE_MAX {
+		return Err(errno!(E2BIG));
+	}
+
+	let (name, value, ap) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let mem_space = proc.get_mem_space().unwrap();
+		let mem_space_guard = mem_space.lock();
+
+		let name = name.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
+		let name = String::try_from(&*name)?;
+
+		let mut buf = Vec::new();
+		if size > 0 {
+			let value = value
+				.get(&mem_space_guard, size)?
+				.ok_or_else(|| errno!(EFAULT))?;
+			buf.extend_from_slice(&value)?;
+		}
+
+		(name, buf, proc.access_profile)
+	};
+
+	let file_mutex = super::util::get_file_from_path_or_fd(fd, pathname, follow_links)?;
+	let file = file_mutex.lock();
+	file.set_xattr(&ap, name.as_bytes(), &value, flags)?;
+
+	Ok(0)
+}
+
+#[syscall]
+pub fn setxattr(
+	pathname: SyscallString,
+	name: SyscallString,
+	value: SyscallSlice<u8>,
+	size: usize,
+	flags: c_int,
+) -> Result<i32, Errno> {
+	do_setxattr(-1, Some(pathname), true, name, value, size, flags)
+}
@@ -30,10 +30,9 @@ pub fn signal(signum: c_int, handler: *const c_void) -> Result<i32, Errno> {
 
 			SignalHandler::Handler(SigAction {
 				sa_handler: Some(handler_fn),
-				sa_sigaction: None,
-				sa_mask: 0,
 				sa_flags: 0,
 				sa_restorer: None,
+				sa_mask: 0,
 			})
 		}
 	};
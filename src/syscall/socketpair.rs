@@ -28,7 +28,7 @@ pub fn socketpair(
 
 	let mem_space = proc.get_mem_space().unwrap();
 	let mut mem_space_guard = mem_space.lock();
-	let sv_slice = sv.get_mut(&mut mem_space_guard)?.ok_or(errno!(EFAULT))?;
+	let mut sv_slice = sv.get_mut(&mut mem_space_guard)?.ok_or(errno!(EFAULT))?;
 
 	let sock_domain = SocketDomain::try_from(domain as u32)?;
 	let sock_type = SocketType::try_from(r#type as u32)?;
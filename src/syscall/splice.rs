@@ -1,17 +1,266 @@
-//! The `splice` system call splice data from one pipe to another.
+//! The `splice` system call moves data between a pipe and another file without copying it to
+//! userspace.
+//!
+//! Pages are moved from pipe to pipe, and pages of files held by the page cache are referenced
+//! by the pipe instead of being copied.
 
+use crate::errno;
+use crate::errno::EResult;
 use crate::errno::Errno;
-use crate::file::FileType;
-use crate::memory::malloc;
+use crate::file::buffer::pipe;
+use crate::file::buffer::pipe::PipePage;
+use crate::file::buffer::Buffer;
+use crate::file::open_file::OpenFile;
+use crate::file::open_file::O_NONBLOCK;
+use crate::file::page_cache;
+use crate::file::FileLocation;
+use crate::memory;
 use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::scheduler;
 use crate::process::Process;
+use crate::util::container::vec::Vec;
+use crate::util::io;
 use crate::util::io::IO;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
 use core::cmp::min;
 use core::ffi::c_int;
 use core::ffi::c_uint;
-use core::num::NonZeroUsize;
 use macros::syscall;
 
+/// Splice flag: do not block on pipes.
+pub const SPLICE_F_NONBLOCK: c_uint = 2;
+
+/// The result of an attempt to transfer data with a pipe.
+pub enum Transfer {
+	/// The given number of bytes has been transferred.
+	Done(usize),
+	/// The transfer must wait for the event `mask` on the pipe at the given location.
+	Wait(FileLocation, u32),
+}
+
+/// Returns the open file for the file descriptor `fd` of the current process.
+pub fn get_open_file(fd: c_int) -> EResult<Arc<Mutex<OpenFile>>> {
+	if fd < 0 {
+		return Err(errno!(EBADF));
+	}
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	let fds_mutex = proc.get_fds().unwrap();
+	let fds = fds_mutex.lock();
+
+	let open_file = fds
+		.get_fd(fd as _)
+		.ok_or_else(|| errno!(EBADF))?
+		.get_open_file()
+		.clone();
+	Ok(open_file)
+}
+
+/// Tells whether the file at location `loc` is a pipe.
+pub fn is_pipe(loc: &FileLocation) -> bool {
+	pipe::pipe_do(loc, |_| ()).is_some()
+}
+
+/// Makes the current process wait for the event `mask` on the pipe at location `loc`.
+pub fn wait(loc: &FileLocation, mask: u32) -> EResult<()> {
+	{
+		let proc_mutex = Process::current_assert();
+		let mut proc = proc_mutex.lock();
+		pipe::pipe_do(loc, |pipe| pipe.add_waiting_process(&mut proc, mask)).transpose()?;
+	}
+	scheduler::end_tick();
+	Ok(())
+}
+
+/// Returns the number of pages that can be added to the pipe at location `loc`.
+///
+/// If no reading end is attached to the pipe, the function returns `EPIPE`.
+pub fn free_pages(loc: &FileLocation) -> EResult<usize> {
+	pipe::pipe_do(loc, |pipe| {
+		if !pipe.has_readers() {
+			return Err(errno!(EPIPE));
+		}
+		Ok(pipe.get_free_pages())
+	})
+	.ok_or_else(|| errno!(EINVAL))?
+}
+
+/// Returns references to the pages holding the first `len` bytes of data of the pipe at location
+/// `loc`, with at most `max_pages` pages.
+///
+/// If `consume` is set, the data is removed from the pipe.
+///
+/// If the pipe is empty, the function returns `None` if data may still be written to it, or an
+/// empty list else.
+pub fn pipe_pages(
+	loc: &FileLocation,
+	len: usize,
+	max_pages: usize,
+	consume: bool,
+) -> EResult<Option<Vec<PipePage>>> {
+	pipe::pipe_do(loc, |pipe| {
+		if pipe.get_data_len() == 0 {
+			return Ok((!pipe.has_writers()).then(Vec::new));
+		}
+		let pages = if consume {
+			pipe.take_pages(len, max_pages)?
+		} else {
+			pipe.peek_pages(len, max_pages)?
+		};
+		Ok(Some(pages))
+	})
+	.ok_or_else(|| errno!(EINVAL))?
+}
+
+/// Appends the pages `pages` to the pipe at location `loc`.
+///
+/// The function returns the number of bytes appended.
+pub fn push_pages(loc: &FileLocation, pages: Vec<PipePage>) -> EResult<usize> {
+	pipe::pipe_do(loc, |pipe| {
+		let mut len = 0;
+		for page in pages {
+			len += page.len();
+			pipe.push_page(page)?;
+		}
+		Ok(len)
+	})
+	.ok_or_else(|| errno!(EINVAL))?
+}
+
+/// Moves at most `len` bytes from the pipe at location `in_loc` to the pipe at location
+/// `out_loc`.
+fn pipe_to_pipe(in_loc: &FileLocation, out_loc: &FileLocation, len: usize) -> EResult<Transfer> {
+	let free = free_pages(out_loc)?;
+	if free == 0 {
+		return Ok(Transfer::Wait(out_loc.clone(), io::POLLOUT));
+	}
+	let Some(pages) = pipe_pages(in_loc, len, free, true)? else {
+		return Ok(Transfer::Wait(in_loc.clone(), io::POLLIN));
+	};
+	Ok(Transfer::Done(push_pages(out_loc, pages)?))
+}
+
+/// Moves at most `len` bytes from the file `input` at offset `off` to the pipe at location
+/// `out_loc`.
+///
+/// If `off` is `None`, the offset of the file is used and updated.
+fn file_to_pipe(
+	input: &Mutex<OpenFile>,
+	off: Option<u64>,
+	out_loc: &FileLocation,
+	len: usize,
+) -> EResult<Transfer> {
+	let res = pipe::pipe_do(out_loc, |pipe| {
+		if !pipe.has_readers() {
+			return Err(errno!(EPIPE));
+		}
+		let free = pipe.get_free_pages();
+		if free == 0 {
+			return Ok(Transfer::Wait(out_loc.clone(), io::POLLOUT));
+		}
+
+		let mut input = input.lock();
+		let prev_off = input.get_offset();
+		let start = off.unwrap_or(prev_off);
+		let mut total = 0;
+		let file_mutex = input.get_file().clone();
+		let mut file = file_mutex.lock();
+		if file.is_cached() {
+			// Reference the pages of the cache
+			let size = file.get_size();
+			let location = file.get_location().clone();
+			let mut cur = start;
+			while pipe.get_free_pages() > 0 && total < len && cur < size {
+				let page_off = cur / memory::PAGE_SIZE as u64;
+				let inner_off = (cur % memory::PAGE_SIZE as u64) as usize;
+				let l = min(len - total, memory::PAGE_SIZE - inner_off).min((size - cur) as usize);
+				let ptr = page_cache::pin(&mut file, page_off)?;
+				let page = PipePage::from_cache(location.clone(), page_off, ptr, inner_off, l);
+				pipe.push_page(page)?;
+				total += l;
+				cur += l as u64;
+			}
+		} else {
+			// Copy the data to new pages
+			drop(file);
+			while pipe.get_free_pages() > 0 && total < len {
+				let mut page = PipePage::new()?;
+				input.set_offset(start + total as u64);
+				let (l, _) = page.read_from(&mut *input, 0, len - total)?;
+				if l == 0 {
+					break;
+				}
+				pipe.push_page(page)?;
+				total += l;
+			}
+		}
+		match off {
+			Some(_) => input.set_offset(prev_off),
+			None => input.set_offset(start + total as u64),
+		}
+		Ok(Transfer::Done(total))
+	});
+	res.ok_or_else(|| errno!(EINVAL))?
+}
+
+/// Moves at most `len` bytes from the pipe at location `in_loc` to the file `output` at offset
+/// `off`.
+///
+/// If `off` is `None`, the offset of the file is used and updated.
+fn pipe_to_file(
+	in_loc: &FileLocation,
+	output: &Mutex<OpenFile>,
+	off: Option<u64>,
+	len: usize,
+) -> EResult<Transfer> {
+	let res = pipe::pipe_do(in_loc, |pipe| {
+		if pipe.get_data_len() == 0 {
+			if pipe.has_writers() {
+				return Ok(Transfer::Wait(in_loc.clone(), io::POLLIN));
+			}
+			return Ok(Transfer::Done(0));
+		}
+		let pages = pipe.peek_pages(len, usize::MAX)?;
+
+		let mut output = output.lock();
+		let prev_off = output.get_offset();
+		if let Some(off) = off {
+			output.set_offset(off);
+		}
+		let mut total = 0;
+		let mut res = Ok(());
+		'outer: for page in pages.iter() {
+			let data = page.data();
+			let mut i = 0;
+			while i < data.len() {
+				match output.write(0, &data[i..]) {
+					Ok(0) => break 'outer,
+					Ok(l) => {
+						i += l as usize;
+						total += l as usize;
+					}
+					Err(e) => {
+						res = Err(e);
+						break 'outer;
+					}
+				}
+			}
+		}
+		if off.is_some() {
+			output.set_offset(prev_off);
+		}
+		// Only the written data is removed from the pipe
+		pipe.consume(total);
+		match res {
+			Err(e) if total == 0 => Err(e),
+			_ => Ok(Transfer::Done(total)),
+		}
+	});
+	res.ok_or_else(|| errno!(EINVAL))?
+}
+
 #[syscall]
 pub fn splice(
 	fd_in: c_int,
@@ -19,96 +268,83 @@ pub fn splice(
 	fd_out: c_int,
 	off_out: SyscallPtr<u64>,
 	len: usize,
-	_flags: c_uint,
+	flags: c_uint,
 ) -> Result<i32, Errno> {
-	if fd_in < 0 || fd_out < 0 {
-		return Err(errno!(EBADF));
-	}
+	let input_mutex = get_open_file(fd_in)?;
+	let output_mutex = get_open_file(fd_out)?;
 
-	let (input_mutex, off_in, output_mutex, off_out) = {
+	let mem_space = {
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
-
-		let fds_mutex = proc.get_fds().unwrap();
-		let fds = fds_mutex.lock();
-
-		let input = fds
-			.get_fd(fd_in as _)
-			.ok_or_else(|| errno!(EBADF))?
-			.get_open_file()
-			.clone();
-		let output = fds
-			.get_fd(fd_out as _)
-			.ok_or_else(|| errno!(EBADF))?
-			.get_open_file()
-			.clone();
-
-		let mem_space = proc.get_mem_space().unwrap();
+		proc.get_mem_space().unwrap().clone()
+	};
+	let (off_in_val, off_out_val) = {
 		let mem_space_guard = mem_space.lock();
-
-		let off_in = off_in.get(&mem_space_guard)?.cloned();
-		let off_out = off_out.get(&mem_space_guard)?.cloned();
-
-		(input, off_in, output, off_out)
+		let off_in = off_in.get(&mem_space_guard)?.as_deref().cloned();
+		let off_out = off_out.get(&mem_space_guard)?.as_deref().cloned();
+		(off_in, off_out)
 	};
 
-	{
-		let input_type = input_mutex.lock().get_file().lock().get_type();
-		let output_type = output_mutex.lock().get_file().lock().get_type();
-
-		let in_is_pipe = matches!(input_type, FileType::Fifo);
-		let out_is_pipe = matches!(output_type, FileType::Fifo);
-
-		if !in_is_pipe && !out_is_pipe {
-			return Err(errno!(EINVAL));
-		}
-		if in_is_pipe && off_in.is_some() {
-			return Err(errno!(ESPIPE));
+	let (in_loc, in_flags) = {
+		let input = input_mutex.lock();
+		if !input.can_read() {
+			return Err(errno!(EBADF));
 		}
-		if out_is_pipe && off_out.is_some() {
-			return Err(errno!(ESPIPE));
+		(input.get_location().clone(), input.get_flags())
+	};
+	let (out_loc, out_flags) = {
+		let output = output_mutex.lock();
+		if !output.can_write() {
+			return Err(errno!(EBADF));
 		}
+		(output.get_location().clone(), output.get_flags())
+	};
+
+	let in_is_pipe = is_pipe(&in_loc);
+	let out_is_pipe = is_pipe(&out_loc);
+	if !in_is_pipe && !out_is_pipe {
+		return Err(errno!(EINVAL));
+	}
+	if (in_is_pipe && off_in_val.is_some()) || (out_is_pipe && off_out_val.is_some()) {
+		return Err(errno!(ESPIPE));
+	}
+	if in_loc == out_loc {
+		return Err(errno!(EINVAL));
 	}
 
 	let len = min(len, i32::MAX as usize);
-	let Some(len) = NonZeroUsize::new(len) else {
+	if len == 0 {
 		return Ok(0);
-	};
-
-	// TODO implement flags
+	}
 
-	let mut buff = unsafe {
-		// Safe because initialized memory is never read
-		malloc::Alloc::<u8>::new(len)
-	}?;
+	let nonblock = flags & SPLICE_F_NONBLOCK != 0
+		|| (in_is_pipe && in_flags & O_NONBLOCK != 0)
+		|| (out_is_pipe && out_flags & O_NONBLOCK != 0);
+	let len = loop {
+		super::util::signal_check()?;
 
-	let len = {
-		let mut input = input_mutex.lock();
-		let prev_off = input.get_offset();
-		let (len, _) = input.read(off_in.unwrap_or(0), buff.as_slice_mut())?;
-		if off_in.is_some() {
-			input.set_offset(prev_off);
+		let res = match (in_is_pipe, out_is_pipe) {
+			(true, true) => pipe_to_pipe(&in_loc, &out_loc, len)?,
+			(false, true) => file_to_pipe(&input_mutex, off_in_val, &out_loc, len)?,
+			(true, false) => pipe_to_file(&in_loc, &output_mutex, off_out_val, len)?,
+			(false, false) => unreachable!(),
+		};
+		match res {
+			Transfer::Done(len) => break len,
+			Transfer::Wait(..) if nonblock => return Err(errno!(EAGAIN)),
+			Transfer::Wait(loc, mask) => wait(&loc, mask)?,
 		}
-		len
 	};
 
-	let in_slice = &buff.as_slice()[..(len as usize)];
-	let mut i = 0;
-
-	while i < len {
-		// TODO Check for signal (and handle syscall restart correctly with offsets)
-
-		let mut output = output_mutex.lock();
-
-		let prev_off = output.get_offset();
-
-		let l = output.write(off_out.unwrap_or(0), in_slice)?;
-
-		if off_out.is_some() {
-			output.set_offset(prev_off);
+	// Update offsets
+	{
+		let mut mem_space_guard = mem_space.lock();
+		if let (Some(off), Some(mut ptr)) = (off_in_val, off_in.get_mut(&mut mem_space_guard)?) {
+			*ptr = off + len as u64;
+		}
+		if let (Some(off), Some(mut ptr)) = (off_out_val, off_out.get_mut(&mut mem_space_guard)?) {
+			*ptr = off + len as u64;
 		}
-
-		i += l;
 	}
 
 	Ok(len as _)
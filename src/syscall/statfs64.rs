@@ -5,6 +5,7 @@ use crate::errno::Errno;
 use crate::file::fs::Statfs;
 use crate::file::path::Path;
 use crate::file::vfs;
+use crate::file::vfs::ResolutionSettings;
 use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
@@ -17,7 +18,7 @@ use macros::syscall;
 pub fn statfs64(path: SyscallString, _sz: usize, buf: SyscallPtr<Statfs>) -> Result<i32, Errno> {
 	// TODO Use `sz`
 
-	let (path, ap) = {
+	let (path, rs) = {
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
 
@@ -25,13 +26,13 @@ pub fn statfs64(path: SyscallString, _sz: usize, buf: SyscallPtr<Statfs>) -> Res
 		let mem_space_guard = mem_space.lock();
 
 		let path = path.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
-		let path = Path::from_str(path, true)?;
+		let path = Path::from_str(&path, true)?;
 		let path = super::util::get_absolute_path(&proc, path)?;
 
-		(path, proc.access_profile)
+		(path, ResolutionSettings::for_process(&proc, true)?)
 	};
 
-	let file_mutex = vfs::get_file_from_path(&path, &ap, true)?;
+	let file_mutex = vfs::resolve_path(&path, &rs)?;
 	let file = file_mutex.lock();
 
 	let mountpoint_mutex = file.get_location().get_mountpoint().unwrap();
@@ -53,7 +54,7 @@ pub fn statfs64(path: SyscallString, _sz: usize, buf: SyscallPtr<Statfs>) -> Res
 		let mem_space = proc.get_mem_space().unwrap();
 		let mut mem_space_guard = mem_space.lock();
 
-		let buf = buf
+		let mut buf = buf
 			.get_mut(&mut mem_space_guard)?
 			.ok_or_else(|| errno!(EFAULT))?;
 		*buf = stat;
@@ -105,7 +105,7 @@ pub fn statx(
 		let pathname = pathname
 			.get(&mem_space_guard)?
 			.ok_or_else(|| errno!(EFAULT))?;
-		util::get_file_at(proc, dirfd, pathname, true, flags)?
+		util::get_file_at(proc, dirfd, &pathname, true, flags)?
 	};
 	let file = file_mutex.lock();
 
@@ -167,8 +167,8 @@ pub fn statx(
 		stx_attributes_mask: 0, // TODO
 
 		stx_atime: StatxTimestamp {
-			tv_sec: file.atime as _,
-			tv_nsec: 0, // TODO
+			tv_sec: file.atime.tv_sec as _,
+			tv_nsec: file.atime.tv_nsec as _,
 			__reserved: 0,
 		},
 		stx_btime: StatxTimestamp {
@@ -177,13 +177,13 @@ pub fn statx(
 			__reserved: 0,
 		},
 		stx_ctime: StatxTimestamp {
-			tv_sec: file.ctime as _,
-			tv_nsec: 0, // TODO
+			tv_sec: file.ctime.tv_sec as _,
+			tv_nsec: file.ctime.tv_nsec as _,
 			__reserved: 0,
 		},
 		stx_mtime: StatxTimestamp {
-			tv_sec: file.mtime as _,
-			tv_nsec: 0, // TODO
+			tv_sec: file.mtime.tv_sec as _,
+			tv_nsec: file.mtime.tv_nsec as _,
 			__reserved: 0,
 		},
 
@@ -204,7 +204,7 @@ pub fn statx(
 		let mem_space = proc.get_mem_space().unwrap();
 		let mut mem_space_guard = mem_space.lock();
 
-		let statx = statxbuff
+		let mut statx = statxbuff
 			.get_mut(&mut mem_space_guard)?
 			.ok_or(errno!(EFAULT))?;
 		*statx = statx_val;
@@ -3,6 +3,7 @@
 use crate::errno::Errno;
 use crate::file::path::Path;
 use crate::file::vfs;
+use crate::file::vfs::ResolutionSettings;
 use crate::file::FileContent;
 use crate::limits;
 use crate::process::mem_space::ptr::SyscallString;
@@ -12,7 +13,7 @@ use macros::syscall;
 
 #[syscall]
 pub fn symlink(target: SyscallString, linkpath: SyscallString) -> Result<i32, Errno> {
-	let (target, linkpath, ap) = {
+	let (target, linkpath, rs) = {
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
 
@@ -25,14 +26,19 @@ pub fn symlink(target: SyscallString, linkpath: SyscallString) -> Result<i32, Er
 		if target_slice.len() > limits::SYMLINK_MAX {
 			return Err(errno!(ENAMETOOLONG));
 		}
-		let target = String::try_from(target_slice)?;
+		let target = String::try_from(&*target_slice)?;
 
 		let linkpath = linkpath
 			.get(&mem_space_guard)?
 			.ok_or_else(|| errno!(EFAULT))?;
-		let linkpath = Path::from_str(linkpath, true)?;
-
-		(target, linkpath, proc.access_profile)
+		let linkpath = Path::from_str(&linkpath, true)?;
+		let linkpath = super::util::get_absolute_path(&proc, linkpath)?;
+
+		(
+			target,
+			linkpath,
+			ResolutionSettings::for_process(&proc, true)?,
+		)
 	};
 
 	// Get the path of the parent directory
@@ -41,10 +47,16 @@ pub fn symlink(target: SyscallString, linkpath: SyscallString) -> Result<i32, Er
 	let name = parent_path.pop().ok_or_else(|| errno!(ENOENT))?;
 
 	// The parent directory
-	let parent_mutex = vfs::get_file_from_path(&parent_path, &ap, true)?;
+	let parent_mutex = vfs::resolve_path(&parent_path, &rs)?;
 	let mut parent = parent_mutex.lock();
 
-	vfs::create_file(&mut parent, name, &ap, 0o777, FileContent::Link(target))?;
+	vfs::create_file(
+		&mut parent,
+		name,
+		&rs.access_profile,
+		0o777,
+		FileContent::Link(target),
+	)?;
 
 	Ok(0)
 }
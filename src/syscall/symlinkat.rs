@@ -28,14 +28,14 @@ pub fn symlinkat(
 	if target_slice.len() > limits::SYMLINK_MAX {
 		return Err(errno!(ENAMETOOLONG));
 	}
-	let target = String::try_from(target_slice)?;
+	let target = String::try_from(&*target_slice)?;
 	let file_content = FileContent::Link(target);
 
 	let linkpath = linkpath
 		.get(&mem_space_guard)?
 		.ok_or_else(|| errno!(EFAULT))?;
 
-	util::create_file_at(proc, newdirfd, linkpath, 0, file_content, true, 0)?;
+	util::create_file_at(proc, newdirfd, &linkpath, 0o777, file_content, true, 0)?;
 
 	Ok(0)
 }
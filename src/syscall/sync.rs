@@ -0,0 +1,13 @@
+//! The `sync` system call writes every pending modification of files back to storage.
+
+use crate::errno::Errno;
+use crate::file::writeback;
+use macros::syscall;
+
+#[syscall]
+pub fn sync() -> Result<i32, Errno> {
+	// `sync` cannot fail. Errors are kept on the files that failed, to be reported when they are
+	// synchronized with `fsync`
+	let _ = writeback::sync_all();
+	Ok(0)
+}
@@ -2,6 +2,7 @@
 //! file pointed by the given file descriptor.
 
 use crate::errno::Errno;
+use crate::file::writeback;
 use crate::process::Process;
 use core::ffi::c_int;
 use macros::syscall;
@@ -25,15 +26,14 @@ pub fn syncfs(fd: c_int) -> Result<i32, Errno> {
 			.clone()
 	};
 
-	let open_file = open_file_mutex.lock();
-
-	let file_mutex = open_file.get_file();
-	let file = file_mutex.lock();
-
-	let location = file.get_location();
-	let _mountpoint = location.get_mountpoint();
-
-	// TODO Sync all files on mountpoint
+	let mountpoint_id = {
+		let open_file = open_file_mutex.lock();
+		let file = open_file.get_file().lock();
+		file.get_location().get_mountpoint_id()
+	};
+	if let Some(mountpoint_id) = mountpoint_id {
+		writeback::sync_mountpoint(mountpoint_id)?;
+	}
 
 	Ok(0)
 }
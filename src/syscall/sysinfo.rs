@@ -0,0 +1,98 @@
+//! The `sysinfo` system call returns statistics about the system: uptime, load averages, memory
+//! usage and number of processes.
+
+use crate::errno::Errno;
+use crate::memory;
+use crate::process;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::scheduler::FSHIFT;
+use crate::process::Process;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::unit::TimestampScale;
+use core::ffi::c_char;
+use core::ffi::c_long;
+use core::ffi::c_uint;
+use core::ffi::c_ulong;
+use core::ffi::c_ushort;
+use core::mem::size_of;
+use macros::syscall;
+
+/// The number of bits of the fractional part of load averages given to userspace.
+const SI_LOAD_SHIFT: u32 = 16;
+
+/// Statistics about the system.
+#[repr(C)]
+#[derive(Debug)]
+pub struct Sysinfo {
+	/// The number of seconds since boot.
+	uptime: c_long,
+	/// The load averages over 1, 5 and 15 minutes.
+	loads: [c_ulong; 3],
+	/// The total usable memory, in units of `mem_unit` bytes.
+	totalram: c_ulong,
+	/// The available memory, in units of `mem_unit` bytes.
+	freeram: c_ulong,
+	/// The shared memory, in units of `mem_unit` bytes.
+	sharedram: c_ulong,
+	/// The memory used by buffers, in units of `mem_unit` bytes.
+	bufferram: c_ulong,
+	/// The total swap space, in units of `mem_unit` bytes.
+	totalswap: c_ulong,
+	/// The available swap space, in units of `mem_unit` bytes.
+	freeswap: c_ulong,
+	/// The number of processes.
+	procs: c_ushort,
+	/// Padding.
+	pad: c_ushort,
+	/// The total high memory, in units of `mem_unit` bytes.
+	totalhigh: c_ulong,
+	/// The available high memory, in units of `mem_unit` bytes.
+	freehigh: c_ulong,
+	/// The size of the memory unit, in bytes.
+	mem_unit: c_uint,
+	/// Padding.
+	_f: [c_char; 20 - 2 * size_of::<c_long>() - size_of::<c_uint>()],
+}
+
+#[syscall]
+pub fn sysinfo(info: SyscallPtr<Sysinfo>) -> Result<i32, Errno> {
+	let uptime = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Second)?;
+	let (load_avg, procs) = {
+		let sched = process::get_scheduler().lock();
+		(sched.get_load_avg(), sched.get_processes_count())
+	};
+	let (mem_total, mem_free) = {
+		let mem_info = memory::stats::MEM_INFO.lock();
+		(mem_info.mem_total, mem_info.mem_free)
+	};
+
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+
+	let mut info = info
+		.get_mut(&mut mem_space_guard)?
+		.ok_or_else(|| errno!(EFAULT))?;
+	*info = Sysinfo {
+		uptime: uptime as _,
+		loads: load_avg.map(|load| (load << (SI_LOAD_SHIFT - FSHIFT)) as _),
+		// Memory sizes are in kilobytes
+		totalram: mem_total as _,
+		freeram: mem_free as _,
+		sharedram: 0,
+		bufferram: 0,
+		totalswap: 0,
+		freeswap: 0,
+		procs: procs as _,
+		pad: 0,
+		totalhigh: 0,
+		freehigh: 0,
+		mem_unit: 1024,
+		_f: [0; 20 - 2 * size_of::<c_long>() - size_of::<c_uint>()],
+	};
+
+	Ok(0)
+}
@@ -0,0 +1,64 @@
+//! The `tee` system call duplicates data from one pipe to another, without consuming it.
+//!
+//! The pages of the input pipe are shared with the output pipe instead of being copied.
+
+use super::splice;
+use super::splice::Transfer;
+use super::splice::SPLICE_F_NONBLOCK;
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::open_file::O_NONBLOCK;
+use crate::util::io;
+use core::cmp::min;
+use core::ffi::c_int;
+use core::ffi::c_uint;
+use macros::syscall;
+
+#[syscall]
+pub fn tee(fd_in: c_int, fd_out: c_int, len: usize, flags: c_uint) -> Result<i32, Errno> {
+	let input_mutex = splice::get_open_file(fd_in)?;
+	let output_mutex = splice::get_open_file(fd_out)?;
+
+	let (in_loc, in_flags) = {
+		let input = input_mutex.lock();
+		if !input.can_read() {
+			return Err(errno!(EBADF));
+		}
+		(input.get_location().clone(), input.get_flags())
+	};
+	let (out_loc, out_flags) = {
+		let output = output_mutex.lock();
+		if !output.can_write() {
+			return Err(errno!(EBADF));
+		}
+		(output.get_location().clone(), output.get_flags())
+	};
+	if !splice::is_pipe(&in_loc) || !splice::is_pipe(&out_loc) || in_loc == out_loc {
+		return Err(errno!(EINVAL));
+	}
+
+	let len = min(len, i32::MAX as usize);
+	if len == 0 {
+		return Ok(0);
+	}
+
+	let nonblock = flags & SPLICE_F_NONBLOCK != 0 || (in_flags | out_flags) & O_NONBLOCK != 0;
+	loop {
+		super::util::signal_check()?;
+
+		let free = splice::free_pages(&out_loc)?;
+		let res = if free > 0 {
+			match splice::pipe_pages(&in_loc, len, free, false)? {
+				Some(pages) => Transfer::Done(splice::push_pages(&out_loc, pages)?),
+				None => Transfer::Wait(in_loc.clone(), io::POLLIN),
+			}
+		} else {
+			Transfer::Wait(out_loc.clone(), io::POLLOUT)
+		};
+		match res {
+			Transfer::Done(len) => return Ok(len as _),
+			Transfer::Wait(..) if nonblock => return Err(errno!(EAGAIN)),
+			Transfer::Wait(loc, mask) => splice::wait(&loc, mask)?,
+		}
+	}
+}
@@ -5,7 +5,7 @@ use crate::errno::Errno;
 use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::Process;
 use crate::time::clock;
-use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::clock::CLOCK_REALTIME;
 use crate::time::unit::TimestampScale;
 use macros::syscall;
 
@@ -20,10 +20,10 @@ pub fn time(tloc: SyscallPtr<u32>) -> Result<i32, Errno> {
 	let mut mem_space_guard = mem_space.lock();
 
 	// Getting the current timestamp
-	let time = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Second)?;
+	let time = clock::current_time(CLOCK_REALTIME, TimestampScale::Second)?;
 
 	// Writing the timestamp to the given location, if not null
-	if let Some(tloc) = tloc.get_mut(&mut mem_space_guard)? {
+	if let Some(mut tloc) = tloc.get_mut(&mut mem_space_guard)? {
 		*tloc = time as _;
 	}
 
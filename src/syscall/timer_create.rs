@@ -31,6 +31,7 @@ pub fn timer_create(
 
 	let sevp_val = sevp
 		.get(&mem_space_guard)?
+		.as_deref()
 		.cloned()
 		.unwrap_or_else(|| SigEvent {
 			sigev_notify: SIGEV_SIGNAL,
@@ -49,7 +50,7 @@ pub fn timer_create(
 		.create_timer(clockid, sevp_val)?;
 
 	// Return timer ID
-	let timerid_val = timerid
+	let mut timerid_val = timerid
 		.get_mut(&mut mem_space_guard)?
 		.ok_or_else(|| errno!(EFAULT))?;
 	*timerid_val = id as _;
@@ -26,6 +26,7 @@ pub fn timer_settime(
 
 	let mut new_value_val = new_value
 		.get(&mem_space_guard)?
+		.as_deref()
 		.cloned()
 		.ok_or_else(|| errno!(EFAULT))?;
 
@@ -44,7 +45,7 @@ pub fn timer_settime(
 		old
 	};
 
-	let old_value_val = old_value
+	let mut old_value_val = old_value
 		.get_mut(&mut mem_space_guard)?
 		.ok_or_else(|| errno!(EFAULT))?;
 	*old_value_val = old;
@@ -0,0 +1,49 @@
+//! The `times` system call returns the CPU time used by the current process and by its
+//! terminated children.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::rusage;
+use crate::process::Process;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::unit::TimestampScale;
+use core::ffi::c_long;
+use macros::syscall;
+
+/// The CPU times of a process, in clock ticks.
+#[repr(C)]
+#[derive(Debug)]
+pub struct Tms {
+	/// The time spent in userspace.
+	tms_utime: c_long,
+	/// The time spent in kernelspace.
+	tms_stime: c_long,
+	/// The time spent in userspace by the terminated children.
+	tms_cutime: c_long,
+	/// The time spent in kernelspace by the terminated children.
+	tms_cstime: c_long,
+}
+
+#[syscall]
+pub fn times(buf: SyscallPtr<Tms>) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+
+	if let Some(mut buf) = buf.get_mut(&mut mem_space_guard)? {
+		let times = proc.get_cpu_times();
+		*buf = Tms {
+			tms_utime: rusage::to_clock_ticks(times.utime) as _,
+			tms_stime: rusage::to_clock_ticks(times.stime) as _,
+			tms_cutime: rusage::to_clock_ticks(times.cutime) as _,
+			tms_cstime: rusage::to_clock_ticks(times.cstime) as _,
+		};
+	}
+
+	// The number of clock ticks elapsed since boot
+	let now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond)?;
+	Ok(rusage::to_clock_ticks(now) as _)
+}
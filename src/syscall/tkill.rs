@@ -3,7 +3,9 @@
 use crate::errno;
 use crate::errno::Errno;
 use crate::process::pid::Pid;
+use crate::process::signal::SigInfo;
 use crate::process::signal::Signal;
+use crate::process::signal::SI_TKILL;
 use crate::process::Process;
 use core::ffi::c_int;
 use macros::syscall;
@@ -17,13 +19,15 @@ pub fn tkill(tid: Pid, sig: c_int) -> Result<i32, Errno> {
 
 	let proc_mutex = Process::current_assert();
 	let mut proc = proc_mutex.lock();
+	let sender = proc.to_local_pid(proc.tgid);
+	let info = SigInfo::from_sender(&signal, SI_TKILL, sender, proc.access_profile.get_uid());
 
 	// Check if the thread to kill is the current
-	if proc.tid == tid {
-		proc.kill(&signal, false);
+	if proc.to_local_pid(proc.tid) == tid {
+		proc.kill_with_info(&signal, info, false)?;
 	} else {
 		// Get the thread
-		let thread_mutex = Process::get_by_tid(tid).ok_or(errno!(ESRCH))?;
+		let thread_mutex = Process::get_by_vpid(tid).ok_or(errno!(ESRCH))?;
 		let mut thread = thread_mutex.lock();
 
 		// Check permissions
@@ -31,7 +35,7 @@ pub fn tkill(tid: Pid, sig: c_int) -> Result<i32, Errno> {
 			return Err(errno!(EPERM));
 		}
 
-		thread.kill(&signal, false);
+		thread.kill_with_info(&signal, info, false)?;
 	}
 
 	Ok(0)
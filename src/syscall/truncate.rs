@@ -3,6 +3,7 @@
 use crate::errno::Errno;
 use crate::file::path::Path;
 use crate::file::vfs;
+use crate::file::vfs::ResolutionSettings;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
 use macros::syscall;
@@ -10,17 +11,20 @@ use macros::syscall;
 #[syscall]
 pub fn truncate(path: SyscallString, length: usize) -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
-	let proc = proc_mutex.lock();
+	let mut proc = proc_mutex.lock();
 
-	let mem_space_mutex = proc.get_mem_space().unwrap();
+	let mem_space_mutex = proc.get_mem_space().unwrap().clone();
 	let mem_space = mem_space_mutex.lock();
 
-	let path = Path::from_str(path.get(&mem_space)?.ok_or(errno!(EFAULT))?, true)?;
+	let path = Path::from_str(&path.get(&mem_space)?.ok_or(errno!(EFAULT))?, true)?;
 	let path = super::util::get_absolute_path(&proc, path)?;
 
-	let file_mutex = vfs::get_file_from_path(&path, &proc.access_profile, true)?;
+	let rs = ResolutionSettings::for_process(&proc, true)?;
+	let file_mutex = vfs::resolve_path(&path, &rs)?;
 	let mut file = file_mutex.lock();
-	file.set_size(length as _);
+	file.check_mount_writable()?;
+	super::util::check_file_size_limit(&mut proc, length as _)?;
+	file.truncate(length as _)?;
 
 	Ok(0)
 }
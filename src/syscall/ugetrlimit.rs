@@ -0,0 +1,12 @@
+//! The `ugetrlimit` system call returns the limit for a given resource.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::rlimit::RLimit32;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall]
+pub fn ugetrlimit(resource: c_int, rlim: SyscallPtr<RLimit32>) -> Result<i32, Errno> {
+	super::getrlimit::do_getrlimit(resource, rlim, u32::MAX)
+}
@@ -8,10 +8,11 @@ use macros::syscall;
 #[syscall]
 pub fn umask(mask: file::Mode) -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
-	let mut proc = proc_mutex.lock();
+	let proc = proc_mutex.lock();
 
-	let prev = proc.umask;
-	proc.umask = mask & 0o777;
+	let mut fs = proc.get_fs().lock();
+	let prev = fs.umask;
+	fs.umask = mask & 0o777;
 
 	Ok(prev as _)
 }
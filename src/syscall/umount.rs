@@ -5,6 +5,7 @@ use crate::errno;
 use crate::errno::Errno;
 use crate::file::mountpoint;
 use crate::file::path::Path;
+use crate::file::perm::CAP_SYS_ADMIN;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
 use macros::syscall;
@@ -13,6 +14,9 @@ use macros::syscall;
 pub fn umount(target: SyscallString) -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let proc = proc_mutex.lock();
+	if !proc.access_profile.has_cap(CAP_SYS_ADMIN) {
+		return Err(errno!(EPERM));
+	}
 
 	// Getting a slice to the string
 	let mem_space = proc.get_mem_space().unwrap();
@@ -20,7 +24,7 @@ pub fn umount(target: SyscallString) -> Result<i32, Errno> {
 	let target_slice = target.get(&mem_space_guard)?.ok_or(errno!(EFAULT))?;
 
 	// Getting the mountpoint
-	let target_path = Path::from_str(target_slice, true)?;
+	let target_path = Path::from_str(&target_slice, true)?;
 	mountpoint::remove(&target_path)?;
 
 	Ok(0)
@@ -32,7 +32,7 @@ pub fn uname(buf: SyscallPtr<Utsname>) -> Result<i32, Errno> {
 
 	let mem_space = proc.get_mem_space().unwrap();
 	let mut mem_space_guard = mem_space.lock();
-	let utsname = buf.get_mut(&mut mem_space_guard)?.ok_or(errno!(EFAULT))?;
+	let mut utsname = buf.get_mut(&mut mem_space_guard)?.ok_or(errno!(EFAULT))?;
 
 	*utsname = Utsname {
 		sysname: [0; UTSNAME_LENGTH],
@@ -0,0 +1,32 @@
+//! The `unshare` system call allows the current process to disassociate parts of its execution
+//! context that are shared with other processes.
+
+use super::clone::CLONE_NEWNS;
+use super::clone::CLONE_NEWPID;
+use crate::errno::Errno;
+use crate::file::perm::CAP_SYS_ADMIN;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall]
+pub fn unshare(flags: c_int) -> Result<i32, Errno> {
+	if flags & !(CLONE_NEWPID | CLONE_NEWNS) != 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let mut proc = proc_mutex.lock();
+
+	if flags & (CLONE_NEWPID | CLONE_NEWNS) != 0 && !proc.access_profile.has_cap(CAP_SYS_ADMIN) {
+		return Err(errno!(EPERM));
+	}
+	if flags & CLONE_NEWPID != 0 {
+		proc.unshare_pid_ns()?;
+	}
+	if flags & CLONE_NEWNS != 0 {
+		proc.unshare_mnt_ns()?;
+	}
+
+	Ok(0)
+}
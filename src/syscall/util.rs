@@ -3,16 +3,23 @@
 use crate::errno;
 use crate::errno::AllocResult;
 use crate::errno::EResult;
+use crate::errno::Errno;
 use crate::file::path::Path;
 use crate::file::vfs;
+use crate::file::vfs::ResolutionSettings;
 use crate::file::File;
 use crate::file::FileContent;
 use crate::file::Mode;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::regs::Regs;
+use crate::process::rlimit;
 use crate::process::scheduler;
+use crate::process::signal::Signal;
+use crate::process::signal::SignalHandler;
+use crate::process::signal::SA_RESTART;
 use crate::process::Process;
 use crate::process::State;
+use crate::syscall::restart_syscall::RESTART_SYSCALL_ID;
 use crate::util::container::string::String;
 use crate::util::container::vec::Vec;
 use crate::util::lock::Mutex;
@@ -20,20 +27,35 @@ use crate::util::lock::MutexGuard;
 use crate::util::ptr::arc::Arc;
 use core::mem::size_of;
 
-/// Returns the absolute path according to the process's current working
-/// directory.
+/// Returns the absolute path in the VFS according to the process's current working directory
+/// and root directory.
 ///
 /// Arguments:
 /// - `process` is the process.
 /// - `path` is the path.
-pub fn get_absolute_path(process: &Process, path: Path) -> AllocResult<Path> {
-	// TODO use chain + collect to allocate once
-	let path = if !path.is_absolute() {
-		process.cwd.concat(&path)?
+pub fn get_absolute_path(process: &Process, mut path: Path) -> AllocResult<Path> {
+	let fs = process.get_fs().lock();
+	if path.is_absolute() {
+		// Absolute paths begin from the root directory of the process
+		path.set_absolute(false);
+		fs.chroot.concat(&path)
 	} else {
-		path
-	};
-	process.chroot.concat(&path)
+		fs.cwd.concat(&path)
+	}
+}
+
+/// Checks that a file may grow up to `size` bytes according to the `RLIMIT_FSIZE` limit of the
+/// process `process`.
+///
+/// If the limit is exceeded, the process is killed with `SIGXFSZ` and the function returns
+/// [`errno::EFBIG`].
+pub fn check_file_size_limit(process: &mut Process, size: u64) -> EResult<()> {
+	let limit = process.rlimits.get_cur(rlimit::RLIMIT_FSIZE);
+	if limit != rlimit::RLIM_INFINITY && size > limit {
+		process.kill(&Signal::SIGXFSZ, false);
+		return Err(errno!(EFBIG));
+	}
+	Ok(())
 }
 
 // TODO Find a safer and cleaner solution
@@ -72,7 +94,7 @@ pub unsafe fn get_str_array(process: &Process, ptr: *const *const u8) -> EResult
 		let elem = *ptr.add(i);
 		let s: SyscallString = (elem as usize).into();
 
-		arr.push(String::try_from(s.get(&mem_space_guard)?.unwrap())?)?;
+		arr.push(String::try_from(&*s.get(&mem_space_guard)?.unwrap())?)?;
 	}
 
 	Ok(arr)
@@ -91,10 +113,10 @@ fn build_path_from_fd(
 
 	if path.is_absolute() {
 		// Using the given absolute path
-		Ok(path)
+		Ok(get_absolute_path(&process, path)?)
 	} else if dirfd == super::access::AT_FDCWD {
 		// Using path relative to the current working directory
-		Ok(process.cwd.concat(&path)?)
+		Ok(process.get_fs().lock().cwd.concat(&path)?)
 	} else {
 		// Using path relative to the directory given by `dirfd`
 
@@ -168,12 +190,49 @@ pub fn get_file_at(
 			Err(errno!(ENOENT))
 		}
 	} else {
-		let ap = process.access_profile;
+		let rs = ResolutionSettings::for_process(&process, follow_links)?;
 		let path = build_path_from_fd(process, dirfd, pathname)?;
-		vfs::get_file_from_path(&path, &ap, follow_links)
+		vfs::resolve_path(&path, &rs)
 	}
 }
 
+/// Returns the file designated by the path `pathname`, or by the file descriptor `fd` if
+/// `pathname` is `None`.
+///
+/// This function is useful for system calls coming with variants taking a path, and a file
+/// descriptor (`f` prefix).
+///
+/// `follow_links` tells whether symbolic links may be followed on the last component of the
+/// path.
+pub fn get_file_from_path_or_fd(
+	fd: i32,
+	pathname: Option<SyscallString>,
+	follow_links: bool,
+) -> EResult<Arc<Mutex<File>>> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	let Some(pathname) = pathname else {
+		return get_file_at(proc, fd, b"", true, super::access::AT_EMPTY_PATH);
+	};
+	let path = {
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let mem_space_guard = mem_space.lock();
+		let path = pathname
+			.get(&mem_space_guard)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		let mut buf = Vec::new();
+		buf.extend_from_slice(&path)?;
+		buf
+	};
+	let flags = if follow_links {
+		0
+	} else {
+		super::access::AT_SYMLINK_NOFOLLOW
+	};
+	get_file_at(proc, super::access::AT_FDCWD, &path, true, flags)
+}
+
 /// Returns the parent directory of the file for the given path `pathname`.
 ///
 /// This function is useful for system calls with the `at` prefix.
@@ -197,7 +256,7 @@ pub fn get_parent_at_with_name(
 	} else {
 		flags & super::access::AT_SYMLINK_FOLLOW != 0
 	};
-	let ap = process.access_profile;
+	let rs = ResolutionSettings::for_process(&process, follow_links)?;
 
 	if pathname.is_empty() {
 		return Err(errno!(ENOENT));
@@ -205,7 +264,7 @@ pub fn get_parent_at_with_name(
 	let mut path = build_path_from_fd(process, dirfd, pathname)?;
 	let name = path.pop().unwrap();
 
-	let parent_mutex = vfs::get_file_from_path(&path, &ap, follow_links)?;
+	let parent_mutex = vfs::resolve_path(&path, &rs)?;
 	Ok((parent_mutex, name))
 }
 
@@ -230,7 +289,11 @@ pub fn create_file_at(
 	flags: i32,
 ) -> EResult<Arc<Mutex<File>>> {
 	let ap = process.access_profile;
-	let mode = mode & !process.umask;
+	// The umask does not apply to symbolic links
+	let mode = match content {
+		FileContent::Link(_) => mode,
+		_ => process.apply_umask(mode),
+	};
 
 	let (parent_mutex, name) =
 		get_parent_at_with_name(process, dirfd, pathname, follow_links_default, flags)?;
@@ -287,27 +350,66 @@ pub fn handle_proc_state() {
 
 /// Checks whether the current syscall must be interrupted to execute a signal.
 ///
-/// If interrupted, the function doesn't return and the control flow jumps
-/// directly to handling the signal.
+/// If a signal is pending, the function returns [`errno::ERESTARTSYS`]. The system call is
+/// then either restarted or made to fail with `EINTR` once the signal has been handled,
+/// according to the signal's action.
+///
+/// The functions locks the mutex of the current process. Thus, the caller must
+/// ensure the mutex isn't already locked to prevent a deadlock.
+pub fn signal_check() -> EResult<()> {
+	if Process::current_assert().lock().get_next_signal().is_some() {
+		return Err(errno!(ERESTARTSYS));
+	}
+	Ok(())
+}
+
+/// Handles the system call with ID `id`, which has been interrupted by a signal with the
+/// restart errno `errno`.
+///
+/// The function decides whether the system call is restarted or returns `EINTR`, then executes
+/// the pending signal. If the signal has a handler, the function doesn't return and the control
+/// flow jumps directly to it.
 ///
 /// The functions locks the mutex of the current process. Thus, the caller must
 /// ensure the mutex isn't already locked to prevent a deadlock.
 ///
 /// `regs` is the registers state passed to the current syscall.
-pub fn signal_check(regs: &Regs) {
+pub fn handle_interrupted(id: u32, regs: &mut Regs, errno: Errno) {
 	let proc_mutex = Process::current_assert();
 	let mut proc = proc_mutex.lock();
 
-	if proc.get_next_signal().is_some() {
-		// Returning the system call early to resume it later
-		let mut r = regs.clone();
-		// TODO Clean
-		r.eip -= 2; // TODO Handle the case where the instruction insn't two bytes long (sysenter)
-		proc.regs = r;
-		proc.syscalling = false;
+	let sig = proc.get_next_signal();
+	// The action of the handler that is about to be executed, if any
+	let action = sig
+		.as_ref()
+		.and_then(|sig| match proc.get_signal_handler(sig) {
+			SignalHandler::Handler(action) if sig.can_catch() && !proc.is_handling_signal() => {
+				Some(action)
+			}
+			_ => None,
+		});
+	let restart = match (errno.as_int(), action) {
+		(errno::ERESTARTNOINTR, _) | (_, None) => true,
+		(errno::ERESTARTSYS, Some(action)) => action.sa_flags & SA_RESTART != 0,
+		_ => false,
+	};
+	if restart {
+		regs.eax = if errno.as_int() == errno::ERESTART_RESTARTBLOCK {
+			RESTART_SYSCALL_ID
+		} else {
+			id
+		};
+		// TODO Handle the case where the instruction insn't two bytes long (sysenter)
+		regs.eip -= 2;
+	} else {
+		proc.restart_block = None;
+		regs.set_syscall_return(Err(errno!(EINTR)));
+	}
 
-		// Switching to handle the signal
-		proc.prepare_switch();
+	if sig.is_some() {
+		proc.regs = regs.clone();
+		proc.syscalling = false;
+		proc.signal_next();
 
 		drop(proc);
 		drop(proc_mutex);
@@ -3,16 +3,91 @@
 use super::access::AT_FDCWD;
 use super::util;
 use crate::errno::Errno;
+use crate::file::perm::AccessProfile;
 use crate::file::File;
 use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
-use crate::time::unit::TimeUnit;
+use crate::time::clock;
+use crate::time::clock::CLOCK_REALTIME;
 use crate::time::unit::Timespec;
 use crate::util::lock::Mutex;
 use core::ffi::c_int;
+use core::ffi::c_long;
 use macros::syscall;
 
+/// Special value for `tv_nsec`: set the timestamp to the current time.
+const UTIME_NOW: c_long = (1 << 30) - 1;
+/// Special value for `tv_nsec`: leave the timestamp unchanged.
+const UTIME_OMIT: c_long = (1 << 30) - 2;
+
+/// Returns the new value for a timestamp given by userspace.
+///
+/// Arguments:
+/// - `ts` is the value given by userspace.
+/// - `now` is the current time.
+///
+/// If the timestamp must be left unchanged, the function returns `None`.
+fn get_timestamp(ts: &Timespec, now: &Timespec) -> Result<Option<Timespec>, Errno> {
+	match ts.tv_nsec {
+		UTIME_NOW => Ok(Some(*now)),
+		UTIME_OMIT => Ok(None),
+		0..=999_999_999 => Ok(Some(*ts)),
+		_ => Err(errno!(EINVAL)),
+	}
+}
+
+/// Sets the timestamps of the given file.
+///
+/// Arguments:
+/// - `file_mutex` is the file.
+/// - `ap` is the access profile of the calling process.
+/// - `times` is the array of timestamps given by userspace. If `None`, both timestamps are set
+/// to the current time.
+fn set_timestamps(
+	file_mutex: &Mutex<File>,
+	ap: &AccessProfile,
+	times: Option<[Timespec; 2]>,
+) -> Result<(), Errno> {
+	let now = clock::current_time_struct::<Timespec>(CLOCK_REALTIME)?;
+	let (atime, mtime) = match times {
+		Some([atime, mtime]) => (get_timestamp(&atime, &now)?, get_timestamp(&mtime, &now)?),
+		None => (Some(now), Some(now)),
+	};
+	if atime.is_none() && mtime.is_none() {
+		return Ok(());
+	}
+
+	let mut file = file_mutex.lock();
+	// Setting a timestamp to an arbitrary value requires to be the owner. Setting it to the
+	// current time only requires write access
+	let only_now = times
+		.map(|t| {
+			t.iter()
+				.all(|ts| matches!(ts.tv_nsec, UTIME_NOW | UTIME_OMIT))
+		})
+		.unwrap_or(true);
+	if !ap.can_set_file_permissions(&file) {
+		if !only_now {
+			return Err(errno!(EPERM));
+		}
+		if !ap.can_write_file(&file) {
+			return Err(errno!(EACCES));
+		}
+	}
+	file.check_mount_writable()?;
+
+	if let Some(atime) = atime {
+		file.atime = atime;
+	}
+	if let Some(mtime) = mtime {
+		file.mtime = mtime;
+	}
+	file.ctime = now;
+	// TODO sync only when required
+	file.sync()
+}
+
 #[syscall]
 pub fn utimensat(
 	dirfd: c_int,
@@ -22,27 +97,17 @@ pub fn utimensat(
 ) -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let proc = proc_mutex.lock();
+	let ap = proc.access_profile;
 
 	let mem_space = proc.get_mem_space().unwrap().clone();
 	let mem_space_guard = mem_space.lock();
 
-	let times_val = times.get(&mem_space_guard)?.ok_or(errno!(EFAULT))?;
-	let atime = times_val[0];
-	let mtime = times_val[1];
-
-	let set = |file_mutex: &Mutex<File>| {
-		let mut file = file_mutex.lock();
-		// TODO clean
-		file.atime = atime.to_nano() / 1000000000;
-		file.mtime = mtime.to_nano() / 1000000000;
-		// TODO sync only when required
-		file.sync()
-	};
+	let times = times.get(&mem_space_guard)?.map(|t| *t);
 
 	match pathname.get(&mem_space_guard)? {
 		Some(pathname) => {
-			let file_mutex = util::get_file_at(proc, dirfd, pathname, true, flags)?;
-			set(&file_mutex)?;
+			let file_mutex = util::get_file_at(proc, dirfd, &pathname, true, flags)?;
+			set_timestamps(&file_mutex, &ap, times)?;
 		}
 		None if dirfd != AT_FDCWD => {
 			if dirfd < 0 {
@@ -52,7 +117,7 @@ pub fn utimensat(
 			let fds = proc.get_fds().unwrap().lock();
 			let fd = fds.get_fd(dirfd as _).ok_or(errno!(EBADF))?;
 			let open_file = fd.get_open_file().lock();
-			set(open_file.get_file())?;
+			set_timestamps(open_file.get_file(), &ap, times)?;
 		}
 		_ => return Err(errno!(EFAULT)),
 	}
@@ -1,5 +1,5 @@
 //! The `vfork` system call works the same as the `fork` system call, except the
-//! parnet process is blocked until the child process exits or executes a
+//! parent process is blocked until the child process exits or executes a
 //! program. During that time, the child process also shares the same memory
 //! space as the parent.
 
@@ -33,12 +33,14 @@ pub fn vfork() -> Result<i32, Errno> {
 		regs.eax = 0;
 		new_proc.regs = regs;
 
-		new_proc.pid
+		curr_proc.to_local_pid(new_proc.pid)
 	};
 
 	// Letting another process run instead of the current. Because the current
 	// process must now wait for the child process to terminate or execute a program
-	scheduler::end_tick();
+	while Process::current_assert().lock().is_vfork_waiting() {
+		scheduler::end_tick();
+	}
 
 	Ok(new_pid as _)
 }
@@ -0,0 +1,168 @@
+//! The `vmsplice` system call transfers data between userspace memory and a pipe.
+//!
+//! When the file descriptor is the writing end of a pipe, the data of the given chunks is
+//! appended to the pipe. When it is the reading end, data is read from the pipe into the chunks.
+
+use super::splice;
+use super::splice::Transfer;
+use super::splice::SPLICE_F_NONBLOCK;
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::buffer::pipe;
+use crate::file::buffer::pipe::PipeBuffer;
+use crate::file::open_file::O_NONBLOCK;
+use crate::limits;
+use crate::process::iovec::IOVec;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::MemSpace;
+use crate::process::Process;
+use crate::util::container::vec::Vec;
+use crate::util::io;
+use crate::util::io::IO;
+use core::cmp::min;
+use core::ffi::c_int;
+use core::ffi::c_uint;
+use core::ffi::c_ulong;
+use macros::syscall;
+
+/// Appends the data of the given chunks to the pipe `pipe`.
+///
+/// Arguments:
+/// - `mem_space` is the memory space of the current process
+/// - `iov` is the set of chunks
+/// - `iovcnt` is the number of chunks in `iov`
+///
+/// The function returns the number of bytes written.
+fn user_to_pipe(
+	mem_space: &MemSpace,
+	iov: &SyscallSlice<IOVec>,
+	iovcnt: usize,
+	pipe: &mut PipeBuffer,
+) -> EResult<usize> {
+	let iov = iov.get(mem_space, iovcnt)?.ok_or(errno!(EFAULT))?;
+	let mut total_len = 0;
+
+	for i in iov.iter() {
+		if i.iov_len == 0 {
+			continue;
+		}
+
+		// The size to write. This is limited to avoid an overflow on the total length
+		let l = min(i.iov_len, i32::MAX as usize - total_len);
+		let ptr = SyscallSlice::<u8>::from(i.iov_base as usize);
+		let slice = ptr.get(mem_space, l)?.ok_or(errno!(EFAULT))?;
+
+		let len = pipe.write(0, &slice)? as usize;
+		total_len += len;
+		if len < l {
+			break;
+		}
+	}
+
+	Ok(total_len)
+}
+
+/// Reads data from the pipe `pipe` into the given chunks.
+///
+/// Arguments:
+/// - `mem_space` is the memory space of the current process
+/// - `iov` is the set of chunks
+/// - `iovcnt` is the number of chunks in `iov`
+///
+/// The function returns the number of bytes read.
+fn pipe_to_user(
+	mem_space: &mut MemSpace,
+	iov: &SyscallSlice<IOVec>,
+	iovcnt: usize,
+	pipe: &mut PipeBuffer,
+) -> EResult<usize> {
+	let iov = {
+		let iov_slice = iov.get(mem_space, iovcnt)?.ok_or(errno!(EFAULT))?;
+		let mut iov = Vec::new();
+		iov.extend_from_slice(&iov_slice)?;
+		iov
+	};
+	let mut total_len = 0;
+
+	for i in iov {
+		if i.iov_len == 0 {
+			continue;
+		}
+
+		// The size to read. This is limited to avoid an overflow on the total length
+		let l = min(i.iov_len, i32::MAX as usize - total_len);
+		let ptr = SyscallSlice::<u8>::from(i.iov_base as usize);
+		let mut slice = ptr.get_mut(mem_space, l)?.ok_or(errno!(EFAULT))?;
+
+		let (len, _) = pipe.read(0, &mut slice)?;
+		let len = len as usize;
+		total_len += len;
+		if len < l {
+			break;
+		}
+	}
+
+	Ok(total_len)
+}
+
+#[syscall]
+pub fn vmsplice(
+	fd: c_int,
+	iov: SyscallSlice<IOVec>,
+	nr_segs: c_ulong,
+	flags: c_uint,
+) -> Result<i32, Errno> {
+	if nr_segs as usize > limits::IOV_MAX {
+		return Err(errno!(EINVAL));
+	}
+	let open_file_mutex = splice::get_open_file(fd)?;
+	let (loc, read, write, file_flags) = {
+		let open_file = open_file_mutex.lock();
+		(
+			open_file.get_location().clone(),
+			open_file.can_read(),
+			open_file.can_write(),
+			open_file.get_flags(),
+		)
+	};
+	if !splice::is_pipe(&loc) {
+		return Err(errno!(EBADF));
+	}
+
+	let mem_space = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+		proc.get_mem_space().unwrap().clone()
+	};
+
+	let nonblock = flags & SPLICE_F_NONBLOCK != 0 || file_flags & O_NONBLOCK != 0;
+	loop {
+		super::util::signal_check()?;
+
+		let res = pipe::pipe_do(&loc, |pipe| -> EResult<Transfer> {
+			let mut mem_space_guard = mem_space.lock();
+			if write {
+				let len = user_to_pipe(&mem_space_guard, &iov, nr_segs as _, pipe)?;
+				if len == 0 && pipe.get_available_len() == 0 {
+					return Ok(Transfer::Wait(loc.clone(), io::POLLOUT));
+				}
+				Ok(Transfer::Done(len))
+			} else if read {
+				if pipe.get_data_len() == 0 && pipe.has_writers() {
+					return Ok(Transfer::Wait(loc.clone(), io::POLLIN));
+				}
+				let len = pipe_to_user(&mut mem_space_guard, &iov, nr_segs as _, pipe)?;
+				Ok(Transfer::Done(len))
+			} else {
+				Err(errno!(EBADF))
+			}
+		})
+		.ok_or_else(|| errno!(EBADF))??;
+		match res {
+			Transfer::Done(len) => return Ok(len as _),
+			Transfer::Wait(..) if nonblock => return Err(errno!(EAGAIN)),
+			Transfer::Wait(loc, mask) => splice::wait(&loc, mask)?,
+		}
+	}
+}
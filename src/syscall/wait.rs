@@ -8,5 +8,5 @@ use macros::syscall;
 
 #[syscall]
 pub fn wait(wstatus: SyscallPtr<c_int>) -> Result<i32, Errno> {
-	waitpid::do_waitpid(regs, -1, wstatus, waitpid::WEXITED, None)
+	waitpid::do_waitpid(-1, wstatus, waitpid::WEXITED, None)
 }
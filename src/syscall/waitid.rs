@@ -0,0 +1,64 @@
+//! The `waitid` system call waits for a process to change state, reporting it
+//! with signal informations.
+
+use super::waitpid;
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::rusage::RUsage;
+use crate::process::signal::SigInfo;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Wait for any child.
+const P_ALL: c_int = 0;
+/// Wait for the child with the given PID.
+const P_PID: c_int = 1;
+/// Wait for any child in the given process group.
+const P_PGID: c_int = 2;
+
+/// Wait flag. Returns if a child has stopped.
+const WSTOPPED: c_int = waitpid::WUNTRACED;
+
+#[syscall]
+pub fn waitid(
+	idtype: c_int,
+	id: c_int,
+	infop: SyscallPtr<SigInfo>,
+	options: c_int,
+	rusage: SyscallPtr<RUsage>,
+) -> Result<i32, Errno> {
+	let valid_flags =
+		waitpid::WNOHANG | WSTOPPED | waitpid::WEXITED | waitpid::WCONTINUED | waitpid::WNOWAIT;
+	if options & !valid_flags != 0
+		|| options & (WSTOPPED | waitpid::WEXITED | waitpid::WCONTINUED) == 0
+	{
+		return Err(errno!(EINVAL));
+	}
+	// Convert to the constraint used by `waitpid`
+	let pid = match idtype {
+		P_ALL => -1,
+		P_PID if id > 0 => id,
+		P_PGID if id >= 0 => -id,
+		// TODO Support P_PIDFD
+		_ => return Err(errno!(EINVAL)),
+	};
+
+	let info = waitpid::do_wait(pid, options)?;
+
+	// Setting values to userspace
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+
+	if let Some(mut infop) = infop.get_mut(&mut mem_space_guard)? {
+		// If no child is waitable, the structure is zeroed
+		*infop = info.as_ref().map(|info| info.siginfo).unwrap_or_default();
+	}
+	if let Some(mut rusage) = rusage.get_mut(&mut mem_space_guard)? {
+		*rusage = info.map(|info| info.rusage).unwrap_or_default();
+	}
+
+	Ok(0)
+}
@@ -5,11 +5,18 @@ use crate::errno::Errno;
 use crate::process;
 use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::pid::Pid;
-use crate::process::regs::Regs;
 use crate::process::rusage::RUsage;
 use crate::process::scheduler;
+use crate::process::signal::SigInfo;
+use crate::process::signal::Signal;
+use crate::process::signal::CLD_CONTINUED;
+use crate::process::signal::CLD_DUMPED;
+use crate::process::signal::CLD_EXITED;
+use crate::process::signal::CLD_KILLED;
+use crate::process::signal::CLD_STOPPED;
 use crate::process::Process;
 use crate::process::State;
+use crate::time::unit::TimeUnit;
 use core::ffi::c_int;
 use macros::syscall;
 
@@ -26,6 +33,9 @@ pub const WCONTINUED: i32 = 8;
 /// child.
 pub const WNOWAIT: i32 = 0x1000000;
 
+/// The number of clock ticks per second reported to userspace.
+const USER_HZ: u64 = 100;
+
 /// Returns the `i`th target process for the given constraint `pid`.
 ///
 /// Arguments:
@@ -36,14 +46,23 @@ pub const WNOWAIT: i32 = 0x1000000;
 /// The function is built such as iterating on `i` until the function returns
 /// `None` gives every targets for the system call.
 fn get_target(curr_proc: &Process, pid: i32, i: usize) -> Option<Pid> {
-	if pid < -1 {
-		let group_processes = curr_proc.get_group_processes();
-
-		if i < group_processes.len() {
-			Some(group_processes[i])
+	if pid < -1 || pid == 0 {
+		// Children in the given process group
+		let pgid = if pid == 0 {
+			curr_proc.pgid
 		} else {
-			None
-		}
+			curr_proc.to_global_pid(-pid as _)?
+		};
+		curr_proc
+			.get_children()
+			.iter()
+			.filter(|child| {
+				Process::get_by_pid(**child)
+					.map(|child| child.lock().pgid == pgid)
+					.unwrap_or(false)
+			})
+			.nth(i)
+			.cloned()
 	} else if pid == -1 {
 		let children = curr_proc.get_children();
 
@@ -52,16 +71,8 @@ fn get_target(curr_proc: &Process, pid: i32, i: usize) -> Option<Pid> {
 		} else {
 			None
 		}
-	} else if pid == 0 {
-		let group = curr_proc.get_group_processes();
-
-		if i < group.len() {
-			Some(group[i])
-		} else {
-			None
-		}
 	} else if i == 0 {
-		Some(pid as _)
+		curr_proc.to_global_pid(pid as _)
 	} else {
 		None
 	}
@@ -72,37 +83,67 @@ fn get_wstatus(proc: &Process) -> i32 {
 	let status = proc.get_exit_status().unwrap_or(0);
 	let termsig = proc.get_termsig();
 
-	let wstatus = match proc.get_state() {
+	let mut wstatus = match proc.get_state() {
 		State::Running | State::Sleeping => 0xffff,
 		State::Stopped => ((termsig as i32 & 0xff) << 8) | 0x7f,
 		State::Zombie => ((status as i32 & 0xff) << 8) | (termsig as i32 & 0x7f),
 	};
 
-	// TODO
-	/*if coredump {
+	if proc.has_core_dumped() {
 		wstatus |= 0x80;
-	}*/
+	}
 
 	wstatus
 }
 
+/// Returns the signal informations describing the change of state of the given process.
+fn get_siginfo(proc: &Process) -> SigInfo {
+	let termsig = proc.get_termsig() as i32;
+	let (code, status) = match proc.get_state() {
+		State::Running | State::Sleeping => (CLD_CONTINUED, termsig),
+		State::Stopped => (CLD_STOPPED, termsig),
+		State::Zombie if proc.has_core_dumped() => (CLD_DUMPED, termsig),
+		State::Zombie if termsig != 0 => (CLD_KILLED, termsig),
+		State::Zombie => (CLD_EXITED, proc.get_exit_status().unwrap_or(0) as i32),
+	};
+	let rusage = proc.get_rusage();
+
+	let mut info = SigInfo::default();
+	info.si_signo = Signal::SIGCHLD.get_id() as _;
+	info.si_code = code;
+	info.si_pid = proc.pid as _;
+	info.si_uid = proc.access_profile.get_uid() as _;
+	info.si_status = status;
+	info.si_utime = (rusage.ru_utime.to_nano() / (1000000000 / USER_HZ)) as _;
+	info.si_stime = (rusage.ru_stime.to_nano() / (1000000000 / USER_HZ)) as _;
+	info
+}
+
+/// Informations about a process that has been waited for.
+#[derive(Default)]
+pub struct WaitInfo {
+	/// The PID of the process, as seen from the namespace of the waiting process.
+	pub pid: Pid,
+	/// The wait status.
+	pub wstatus: i32,
+	/// The resource usage of the process.
+	pub rusage: RUsage,
+	/// The signal informations describing the change of state.
+	pub siginfo: SigInfo,
+}
+
 /// Checks if at least one process corresponding to the given constraint is
-/// waitable. If yes, the function clears its waitable state, sets the wstatus
-/// and returns the process's PID.
+/// waitable. If yes, the function clears its waitable state and returns informations about it.
 ///
 /// Arguments:
 /// - `curr_proc` is the current process.
 /// - `pid` is the constraint given to the system call.
-/// - `wstatus` is a reference to the wait status.
 /// - `options` is a set of flags.
-/// - `rusage` is the pointer to the resource usage structure.
 fn check_waitable(
 	curr_proc: &mut Process,
 	pid: i32,
-	wstatus: &mut i32,
 	options: i32,
-	rusage: &mut RUsage,
-) -> Result<Option<Pid>, Errno> {
+) -> Result<Option<WaitInfo>, Errno> {
 	// Iterating on every target processes, checking if they can be waited on
 	let mut i = 0;
 	while let Some(pid) = get_target(curr_proc, pid, i) {
@@ -121,8 +162,15 @@ fn check_waitable(
 
 			// If waitable, return
 			if p.is_waitable() && (stop_check || exit_check || continue_check) {
-				*wstatus = get_wstatus(&p);
-				*rusage = p.get_rusage().clone();
+				let local_pid = curr_proc.to_local_pid(pid);
+				let mut siginfo = get_siginfo(&p);
+				siginfo.si_pid = local_pid as _;
+				let info = WaitInfo {
+					pid: local_pid,
+					wstatus: get_wstatus(&p),
+					rusage: p.get_rusage().clone(),
+					siginfo,
+				};
 
 				let clear_waitable = options & WNOWAIT == 0;
 				if clear_waitable {
@@ -130,6 +178,7 @@ fn check_waitable(
 
 					// If the process was a zombie, remove it
 					if exit_check {
+						curr_proc.add_child_cpu_times(&p);
 						drop(p);
 
 						curr_proc.remove_child(pid);
@@ -137,7 +186,7 @@ fn check_waitable(
 					}
 				}
 
-				return Ok(Some(pid));
+				return Ok(Some(info));
 			}
 		}
 
@@ -152,25 +201,16 @@ fn check_waitable(
 	}
 }
 
-/// Executes the `waitpid` system call.
+/// Waits for a process to change state.
 ///
 /// Arguments:
-/// - `regs` is the registers state.
 /// - `pid` is the PID to wait for.
-/// - `wstatus` is the pointer on which to write the status.
 /// - `options` are flags passed with the syscall.
-/// - `rusage` is the pointer to the resource usage structure.
-pub fn do_waitpid(
-	regs: &Regs,
-	pid: i32,
-	wstatus: SyscallPtr<i32>,
-	options: i32,
-	rusage: Option<SyscallPtr<RUsage>>,
-) -> Result<i32, Errno> {
+///
+/// If [`WNOHANG`] is set and no process is waitable, the function returns `None`.
+pub fn do_wait(pid: i32, options: i32) -> Result<Option<WaitInfo>, Errno> {
 	// Sleeping until a target process is waitable
 	loop {
-		super::util::signal_check(regs);
-
 		cli!();
 
 		{
@@ -178,35 +218,19 @@ pub fn do_waitpid(
 			let mut proc = proc_mutex.lock();
 
 			// Check if at least one target process is waitable
-			let mut wstatus_val = Default::default();
-			let mut rusage_val = Default::default();
-			let result =
-				check_waitable(&mut proc, pid, &mut wstatus_val, options, &mut rusage_val)?;
-
-			// Setting values to userspace
-			{
-				let mem_space = proc.get_mem_space().unwrap();
-				let mut mem_space_guard = mem_space.lock();
-
-				if let Some(wstatus) = wstatus.get_mut(&mut mem_space_guard)? {
-					*wstatus = wstatus_val;
-				}
-
-				if let Some(ref rusage) = rusage {
-					if let Some(rusage) = rusage.get_mut(&mut mem_space_guard)? {
-						*rusage = rusage_val;
-					}
-				}
-			}
-
+			let result = check_waitable(&mut proc, pid, options)?;
 			// On success, return
-			if let Some(p) = result {
-				return Ok(p as _);
+			if result.is_some() {
+				return Ok(result);
 			}
 
 			// If the flag is set, do not wait
 			if options & WNOHANG != 0 {
-				return Ok(0);
+				return Ok(None);
+			}
+			// The wait is interrupted to handle the signal
+			if proc.get_next_signal().is_some() {
+				return Err(errno!(ERESTARTSYS));
 			}
 
 			// When a child process is paused or resumed by a signal or is terminated, it
@@ -218,7 +242,43 @@ pub fn do_waitpid(
 	}
 }
 
+/// Executes the `waitpid` system call.
+///
+/// Arguments:
+/// - `pid` is the PID to wait for.
+/// - `wstatus` is the pointer on which to write the status.
+/// - `options` are flags passed with the syscall.
+/// - `rusage` is the pointer to the resource usage structure.
+pub fn do_waitpid(
+	pid: i32,
+	wstatus: SyscallPtr<i32>,
+	options: i32,
+	rusage: Option<SyscallPtr<RUsage>>,
+) -> Result<i32, Errno> {
+	let Some(info) = do_wait(pid, options)? else {
+		return Ok(0);
+	};
+
+	// Setting values to userspace
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+
+	if let Some(mut wstatus) = wstatus.get_mut(&mut mem_space_guard)? {
+		*wstatus = info.wstatus;
+	}
+
+	if let Some(ref rusage) = rusage {
+		if let Some(mut rusage) = rusage.get_mut(&mut mem_space_guard)? {
+			*rusage = info.rusage;
+		}
+	}
+
+	Ok(info.pid as _)
+}
+
 #[syscall]
 pub fn waitpid(pid: c_int, wstatus: SyscallPtr<c_int>, options: c_int) -> Result<i32, Errno> {
-	do_waitpid(regs, pid, wstatus, options | WEXITED, None)
+	do_waitpid(pid, wstatus, options | WEXITED, None)
 }
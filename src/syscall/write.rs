@@ -5,6 +5,7 @@ use crate::errno;
 use crate::errno::Errno;
 use crate::file::open_file::O_NONBLOCK;
 use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::rlimit;
 use crate::process::scheduler;
 use crate::process::Process;
 use crate::syscall::Signal;
@@ -14,8 +15,6 @@ use core::cmp::min;
 use core::ffi::c_int;
 use macros::syscall;
 
-// TODO O_ASYNC
-
 #[syscall]
 pub fn write(fd: c_int, buf: SyscallSlice<u8>, count: usize) -> Result<i32, Errno> {
 	if fd < 0 {
@@ -27,11 +26,12 @@ pub fn write(fd: c_int, buf: SyscallSlice<u8>, count: usize) -> Result<i32, Errn
 		return Ok(0);
 	}
 
-	let (proc, mem_space, open_file) = {
+	let (proc, mem_space, open_file, fsize_limit) = {
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
 
 		let mem_space = proc.get_mem_space().unwrap().clone();
+		let fsize_limit = proc.rlimits.get_cur(rlimit::RLIMIT_FSIZE);
 
 		let fds_mutex = proc.get_fds().unwrap().clone();
 		let fds = fds_mutex.lock();
@@ -42,27 +42,34 @@ pub fn write(fd: c_int, buf: SyscallSlice<u8>, count: usize) -> Result<i32, Errn
 			.clone();
 
 		drop(proc);
-		(proc_mutex, mem_space, open_file_mutex)
+		(proc_mutex, mem_space, open_file_mutex, fsize_limit)
 	};
 
 	loop {
-		super::util::signal_check(regs);
+		super::util::signal_check()?;
 
 		{
 			let mem_space_guard = mem_space.lock();
-			let buf_slice = buf.get(&mem_space_guard, len)?.ok_or(errno!(EFAULT))?;
 
 			// Write file
 			let mut open_file = open_file.lock();
 			let flags = open_file.get_flags();
-			let len = match open_file.write(0, buf_slice) {
+			let res = open_file
+				.check_size_limit(len, fsize_limit)
+				.and_then(|len| {
+					let buf_slice = buf.get(&mem_space_guard, len)?.ok_or(errno!(EFAULT))?;
+					open_file.write(0, &buf_slice)
+				});
+			let len = match res {
 				Ok(len) => len,
 
 				Err(e) => {
-					// If writing to a broken pipe, kill with SIGPIPE
-					if e.as_int() == errno::EPIPE {
-						let mut proc = proc.lock();
-						proc.kill(&Signal::SIGPIPE, false);
+					match e.as_int() {
+						// If writing to a broken pipe, kill with SIGPIPE
+						errno::EPIPE => proc.lock().kill(&Signal::SIGPIPE, false),
+						// If exceeding the file size limit, kill with SIGXFSZ
+						errno::EFBIG => proc.lock().kill(&Signal::SIGXFSZ, false),
+						_ => {}
 					}
 
 					return Err(e);
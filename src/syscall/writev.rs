@@ -5,15 +5,21 @@ use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file::open_file::OpenFile;
 use crate::file::open_file::O_NONBLOCK;
+use crate::file::writeback;
 use crate::limits;
+use crate::process::iovec;
 use crate::process::iovec::IOVec;
 use crate::process::mem_space::ptr::SyscallSlice;
 use crate::process::mem_space::MemSpace;
+use crate::process::rlimit;
+use crate::process::rlimit::RLim;
 use crate::process::scheduler;
 use crate::process::signal::Signal;
 use crate::process::Process;
 use crate::util::io;
 use crate::util::io::IO;
+use crate::util::lock::IntMutex;
+use crate::util::lock::Mutex;
 use core::cmp::min;
 use core::ffi::c_int;
 use macros::syscall;
@@ -27,16 +33,18 @@ use macros::syscall;
 /// - `iov` is the set of chunks
 /// - `iovcnt` is the number of chunks in `iov`
 /// - `open_file` is the file to write to
+/// - `fsize_limit` is the maximum size of the file (`RLIMIT_FSIZE`)
 fn write(
 	mem_space: &mut MemSpace,
 	iov: &SyscallSlice<IOVec>,
 	iovcnt: usize,
 	open_file: &mut OpenFile,
+	fsize_limit: RLim,
 ) -> EResult<i32> {
 	let iov = iov.get(&mem_space, iovcnt)?.ok_or(errno!(EFAULT))?;
 	let mut total_len = 0;
 
-	for i in iov {
+	for i in iov.iter() {
 		// Ignore zero entry
 		if i.iov_len == 0 {
 			continue;
@@ -44,11 +52,17 @@ fn write(
 
 		// The size to write. This is limited to avoid an overflow on the total length
 		let l = min(i.iov_len, i32::MAX as usize - total_len);
+		let l = match open_file.check_size_limit(l, fsize_limit) {
+			Ok(l) => l,
+			// Stop at the limit if some data has already been written
+			Err(_) if total_len > 0 => break,
+			Err(e) => return Err(e),
+		};
 		let ptr = SyscallSlice::<u8>::from(i.iov_base as usize);
 
 		if let Some(slice) = ptr.get(mem_space, l)? {
 			// The offset is ignored
-			total_len += open_file.write(0, slice)? as usize;
+			total_len += open_file.write(0, &slice)? as usize;
 		}
 	}
 
@@ -68,14 +82,11 @@ pub fn do_writev(
 	iov: SyscallSlice<IOVec>,
 	iovcnt: i32,
 	offset: Option<isize>,
-	_flags: Option<i32>,
+	flags: Option<i32>,
 ) -> Result<i32, Errno> {
 	if fd < 0 {
 		return Err(errno!(EBADF));
 	}
-	if iovcnt < 0 || iovcnt as usize > limits::IOV_MAX {
-		return Err(errno!(EINVAL));
-	}
 
 	let (proc, mem_space, open_file_mutex) = {
 		let proc_mutex = Process::current_assert();
@@ -95,6 +106,41 @@ pub fn do_writev(
 		(proc_mutex, mem_space, open_file_mutex)
 	};
 
+	writev_file(
+		&proc,
+		&mem_space,
+		&open_file_mutex,
+		iov,
+		iovcnt,
+		offset,
+		flags.unwrap_or(0),
+	)
+}
+
+/// Writes to the open file `open_file_mutex` from the IO vector `iov`.
+///
+/// Arguments:
+/// - `proc` is the current process
+/// - `mem_space` is the memory space of the current process
+/// - `open_file_mutex` is the open file
+/// - `iov` the IO vector
+/// - `iovcnt` the number of entries in the IO vector
+/// - `offset` is the offset in the file
+/// - `rw_flags` is the set of per-call flags (`RWF_*`)
+pub fn writev_file(
+	proc: &IntMutex<Process>,
+	mem_space: &IntMutex<MemSpace>,
+	open_file_mutex: &Mutex<OpenFile>,
+	iov: SyscallSlice<IOVec>,
+	iovcnt: i32,
+	offset: Option<isize>,
+	rw_flags: i32,
+) -> EResult<i32> {
+	if iovcnt < 0 || iovcnt as usize > limits::IOV_MAX {
+		return Err(errno!(EINVAL));
+	}
+	iovec::check_rw_flags(rw_flags)?;
+
 	let (start_off, update_off) = match offset {
 		Some(o @ 0..) => (o as u64, false),
 		None | Some(-1) => {
@@ -107,8 +153,10 @@ pub fn do_writev(
 		Some(_) => unreachable!(),
 	};
 
+	let fsize_limit = proc.lock().rlimits.get_cur(rlimit::RLIMIT_FSIZE);
+
 	loop {
-		// TODO super::util::signal_check(regs);
+		// TODO super::util::signal_check()?;
 
 		{
 			let mut open_file = open_file_mutex.lock();
@@ -119,13 +167,21 @@ pub fn do_writev(
 			open_file.set_offset(start_off);
 
 			let mut mem_space_guard = mem_space.lock();
-			let len = match write(&mut mem_space_guard, &iov, iovcnt as _, &mut open_file) {
+			let len = match write(
+				&mut mem_space_guard,
+				&iov,
+				iovcnt as _,
+				&mut open_file,
+				fsize_limit,
+			) {
 				Ok(len) => len,
 				Err(e) => {
-					// If writing to a broken pipe, kill with SIGPIPE
-					if e.as_int() == errno::EPIPE {
-						let mut proc = proc.lock();
-						proc.kill(&Signal::SIGPIPE, false);
+					match e.as_int() {
+						// If writing to a broken pipe, kill with SIGPIPE
+						errno::EPIPE => proc.lock().kill(&Signal::SIGPIPE, false),
+						// If exceeding the file size limit, kill with SIGXFSZ
+						errno::EFBIG => proc.lock().kill(&Signal::SIGXFSZ, false),
+						_ => {}
 					}
 
 					return Err(e);
@@ -138,9 +194,15 @@ pub fn do_writev(
 			}
 
 			if len > 0 {
+				if rw_flags & (iovec::RWF_DSYNC | iovec::RWF_SYNC) != 0 {
+					let file_mutex = open_file.get_file().clone();
+					drop(mem_space_guard);
+					drop(open_file);
+					writeback::sync_file(&file_mutex)?;
+				}
 				return Ok(len as _);
 			}
-			if flags & O_NONBLOCK != 0 {
+			if flags & O_NONBLOCK != 0 || rw_flags & iovec::RWF_NOWAIT != 0 {
 				// The file descriptor is non blocking
 				return Err(errno!(EAGAIN));
 			}
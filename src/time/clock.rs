@@ -6,7 +6,6 @@ use crate::time::unit::ClockIdT;
 use crate::time::unit::TimeUnit;
 use crate::time::Timestamp;
 use crate::time::TimestampScale;
-use core::cmp::max;
 
 /// System clock ID
 pub const CLOCK_REALTIME: ClockIdT = 0;
@@ -35,10 +34,10 @@ pub const CLOCK_TAI: ClockIdT = 11;
 
 // TODO allow accessing clocks through an address shared with userspace (vDSO)
 
-/// The current timestamp of the real time clock, in nanoseconds.
+/// The current timestamp of the real time clock, in nanoseconds since the Unix epoch.
 static REALTIME: AtomicTimestamp = AtomicTimestamp::new(0);
-/// On time adjustement, this value is updated with the previous value of the real time clock so
-/// that it can be used if the clock went backwards in time.
+/// The time elapsed since boot time, in nanoseconds. Unlike the real time clock, this clock cannot
+/// be set, which guarantees it never goes backwards.
 static MONOTONIC: AtomicTimestamp = AtomicTimestamp::new(0);
 /// The time elapsed since boot time, in nanoseconds.
 static BOOTTIME: AtomicTimestamp = AtomicTimestamp::new(0);
@@ -50,6 +49,24 @@ pub fn update(delta: Timestamp) {
 	BOOTTIME.fetch_add(delta as _);
 }
 
+/// Sets the timestamp of the clock with the given ID.
+///
+/// Arguments:
+/// - `clk` is the ID of the clock to set.
+/// - `ts` is the new timestamp in nanoseconds.
+///
+/// Only the real time clock can be set. Other clocks are left untouched. If the clock is
+/// invalid or cannot be set, the function returns an error.
+pub fn set(clk: ClockIdT, ts: Timestamp) -> EResult<()> {
+	match clk {
+		CLOCK_REALTIME => {
+			REALTIME.store(ts);
+			Ok(())
+		}
+		_ => Err(errno!(EINVAL)),
+	}
+}
+
 /// Returns the current timestamp according to the clock with the given ID.
 ///
 /// Arguments:
@@ -60,13 +77,10 @@ pub fn update(delta: Timestamp) {
 pub fn current_time(clk: ClockIdT, scale: TimestampScale) -> EResult<Timestamp> {
 	// TODO implement all clocks
 	let raw_ts = match clk {
-		CLOCK_REALTIME | CLOCK_REALTIME_ALARM => REALTIME.load(),
-		CLOCK_MONOTONIC => {
-			let realtime = REALTIME.load();
-			let monotonic = MONOTONIC.load();
-
-			max(realtime, monotonic)
+		CLOCK_REALTIME | CLOCK_REALTIME_COARSE | CLOCK_REALTIME_ALARM | CLOCK_TAI => {
+			REALTIME.load()
 		}
+		CLOCK_MONOTONIC | CLOCK_MONOTONIC_RAW | CLOCK_MONOTONIC_COARSE => MONOTONIC.load(),
 		CLOCK_BOOTTIME | CLOCK_BOOTTIME_ALARM => BOOTTIME.load(),
 
 		_ => return Err(errno!(EINVAL)),
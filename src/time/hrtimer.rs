@@ -0,0 +1,70 @@
+//! High resolution timers allow processes to sleep until a deadline, with a precision finer than
+//! the ticks of the scheduler.
+//!
+//! Deadlines are checked at each tick of the hardware clock driving software clocks, which is
+//! much more frequent than the ticks of the scheduler. When a deadline is reached, the sleeping
+//! process is woken up right away.
+//!
+//! Deadlines are stored on the timeline of the monotonic clock, which cannot go backwards.
+
+use super::clock;
+use super::clock::CLOCK_MONOTONIC;
+use super::unit::ClockIdT;
+use super::unit::Timestamp;
+use super::unit::TimestampScale;
+use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::process::pid::Pid;
+use crate::process::Process;
+use crate::util::container::map::Map;
+use crate::util::lock::IntMutex;
+
+/// The queue of sleeping processes, ordered by deadline.
+///
+/// The key has the following elements:
+/// - the deadline on the monotonic clock, in nanoseconds
+/// - the PID of the sleeping process
+static QUEUE: IntMutex<Map<(Timestamp, Pid), ()>> = IntMutex::new(Map::new());
+
+/// Converts the timestamp `ts` of the clock `clockid` into a timestamp of the monotonic clock.
+///
+/// Timestamps are in nanoseconds.
+pub fn to_monotonic(clockid: ClockIdT, ts: Timestamp) -> EResult<Timestamp> {
+	let now = clock::current_time(clockid, TimestampScale::Nanosecond)?;
+	let mono_now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond)?;
+	Ok(mono_now.saturating_add(ts.saturating_sub(now)))
+}
+
+/// Inserts the process with PID `pid` in the queue, to be woken up at `deadline`.
+///
+/// `deadline` is a timestamp of the monotonic clock in nanoseconds.
+pub fn insert(deadline: Timestamp, pid: Pid) -> AllocResult<()> {
+	QUEUE.lock().insert((deadline, pid), ())?;
+	Ok(())
+}
+
+/// Removes the process with PID `pid` to be woken up at `deadline` from the queue.
+///
+/// If the entry does not exist, the function does nothing.
+pub fn remove(deadline: Timestamp, pid: Pid) {
+	QUEUE.lock().remove(&(deadline, pid));
+}
+
+/// Wakes up the processes whose deadline has been reached.
+pub(super) fn tick() {
+	let Ok(now) = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond) else {
+		return;
+	};
+	let mut queue = QUEUE.lock();
+	while let Some(((deadline, pid), _)) = queue.first_key_value() {
+		if *deadline > now {
+			break;
+		}
+		let pid = *pid;
+		queue.pop_first();
+
+		if let Some(proc_mutex) = Process::get_by_pid(pid) {
+			proc_mutex.lock().wake();
+		}
+	}
+}
@@ -10,6 +10,20 @@ const SELECT_PORT: u16 = 0x70;
 /// The ID of the port to read or write a CMOS port previously selected.
 const VALUE_PORT: u16 = 0x71;
 
+/// The ID of the register holding the seconds.
+const SECONDS_REGISTER: u8 = 0x00;
+/// The ID of the register holding the minutes.
+const MINUTES_REGISTER: u8 = 0x02;
+/// The ID of the register holding the hours.
+const HOURS_REGISTER: u8 = 0x04;
+/// The ID of the register holding the day of the month.
+const DAY_REGISTER: u8 = 0x07;
+/// The ID of the register holding the month.
+const MONTH_REGISTER: u8 = 0x08;
+/// The ID of the register holding the year in the century.
+const YEAR_REGISTER: u8 = 0x09;
+/// The ID of the register holding the century, on most systems.
+const CENTURY_REGISTER: u8 = 0x32;
 /// The ID of the status register A.
 const STATUS_A_REGISTER: u8 = 0x0a;
 /// The ID of the status register B.
@@ -17,6 +31,81 @@ const STATUS_B_REGISTER: u8 = 0x0b;
 /// The ID of the status register C.
 const STATUS_C_REGISTER: u8 = 0x0c;
 
+/// Status register A: an update of the date and time is in progress.
+const STATUS_A_UPDATE_IN_PROGRESS: u8 = 0x80;
+/// Status register B: the hours are in 24 hours format instead of 12 hours.
+const STATUS_B_24_HOURS: u8 = 0x02;
+/// Status register B: values are in binary instead of BCD.
+const STATUS_B_BINARY: u8 = 0x04;
+/// In 12 hours format, the bit of the hours register telling the time is PM.
+const HOURS_PM: u8 = 0x80;
+
+/// Reads the CMOS register `reg`.
+fn read_register(reg: u8) -> u8 {
+	unsafe {
+		io::outb(SELECT_PORT, reg);
+		io::inb(VALUE_PORT)
+	}
+}
+
+/// Converts the BCD value `val` to binary.
+fn bcd_to_binary(val: u8) -> u8 {
+	(val & 0x0f) + (val >> 4) * 10
+}
+
+/// The raw date and time as stored in the registers of the RTC.
+#[derive(Clone, Copy, Eq, PartialEq)]
+struct RawDateTime {
+	/// The seconds.
+	sec: u8,
+	/// The minutes.
+	min: u8,
+	/// The hours.
+	hour: u8,
+	/// The day of the month.
+	day: u8,
+	/// The month.
+	month: u8,
+	/// The year in the century.
+	year: u8,
+	/// The century.
+	century: u8,
+}
+
+impl RawDateTime {
+	/// Reads the date and time from the registers, once no update is in progress.
+	fn read() -> Self {
+		while read_register(STATUS_A_REGISTER) & STATUS_A_UPDATE_IN_PROGRESS != 0 {}
+		Self {
+			sec: read_register(SECONDS_REGISTER),
+			min: read_register(MINUTES_REGISTER),
+			hour: read_register(HOURS_REGISTER),
+			day: read_register(DAY_REGISTER),
+			month: read_register(MONTH_REGISTER),
+			year: read_register(YEAR_REGISTER),
+			century: read_register(CENTURY_REGISTER),
+		}
+	}
+}
+
+/// Returns the number of days between the Unix epoch and the given date.
+///
+/// `month` and `day` start at `1`.
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+	// Years start in March so that the leap day is at the end of the year
+	let (year, month) = if month <= 2 {
+		(year - 1, month + 9)
+	} else {
+		(year, month - 3)
+	};
+	let era = year / 400;
+	let year_of_era = year % 400;
+	let day_of_year = (153 * month + 2) / 5 + day - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+	// 719468 is the number of days between 0000-03-01 and 1970-01-01
+	era * 146097 + day_of_era - 719468
+}
+
 // FIXME prevent having several instances at the same time
 
 /// The RTC.
@@ -35,6 +124,48 @@ impl RTC {
 		s
 	}
 
+	/// Returns the current date and time, in seconds since the Unix epoch.
+	pub fn read_time() -> u64 {
+		let (time, status_b) = idt::wrap_disable_interrupts(|| {
+			// Read until two consecutive reads match, to avoid reading during an update
+			let mut time = RawDateTime::read();
+			loop {
+				let next = RawDateTime::read();
+				if next == time {
+					break;
+				}
+				time = next;
+			}
+			(time, read_register(STATUS_B_REGISTER))
+		});
+
+		let convert = |val: u8| {
+			if status_b & STATUS_B_BINARY != 0 {
+				val
+			} else {
+				bcd_to_binary(val)
+			}
+		};
+		let pm = status_b & STATUS_B_24_HOURS == 0 && time.hour & HOURS_PM != 0;
+		let mut hour = convert(time.hour & !HOURS_PM) as u64;
+		if status_b & STATUS_B_24_HOURS == 0 {
+			// In 12 hours format, midnight and noon are represented by `12`
+			hour %= 12;
+			if pm {
+				hour += 12;
+			}
+		}
+		// If the century register is not supported, the 21st century is assumed
+		let century = match convert(time.century) {
+			c @ 19..=99 => c as u64,
+			_ => 20,
+		};
+		let year = century * 100 + convert(time.year) as u64;
+
+		let days = days_from_civil(year, convert(time.month) as _, convert(time.day) as _);
+		days * 86400 + hour * 3600 + convert(time.min) as u64 * 60 + convert(time.sec) as u64
+	}
+
 	/// Resets the timer to make it ready for the next tick.
 	#[inline]
 	pub fn reset() {
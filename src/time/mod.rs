@@ -7,6 +7,7 @@
 //! - Software Clocks, which maintain a timestamp based on hardware clocks.
 
 pub mod clock;
+pub mod hrtimer;
 pub mod hw;
 pub mod timer;
 pub mod unit;
@@ -107,6 +108,10 @@ pub fn init() -> EResult<()> {
 	// Link hardware clock to software clock
 	#[cfg(target_arch = "x86")]
 	{
+		// Initialize the real time clock with the wall time kept by the CMOS
+		let now = hw::rtc::RTC::read_time();
+		clock::set(clock::CLOCK_REALTIME, now * 1_000_000_000)?;
+
 		let rtc = hw_clocks.get_mut(b"rtc".as_slice()).unwrap();
 		let freq = Rational::from_frac(1, 1024);
 		rtc.set_frequency(freq);
@@ -116,6 +121,7 @@ pub fn init() -> EResult<()> {
 			// FIXME: the value is probably not right
 			clock::update(i64::from(freq * 1_000_000_000) as _);
 			timer::tick();
+			hrtimer::tick();
 
 			CallbackResult::Continue
 		})?;
@@ -149,6 +149,16 @@ pub struct Timespec {
 	pub tv_nsec: c_long,
 }
 
+impl Timespec {
+	/// Creates a timestamp from the given number of seconds.
+	pub const fn from_secs(sec: Timestamp) -> Self {
+		Self {
+			tv_sec: sec,
+			tv_nsec: 0,
+		}
+	}
+}
+
 impl TimeUnit for Timespec {
 	fn from_nano(timestamp: u64) -> Self {
 		let sec = timestamp / 1000000000;
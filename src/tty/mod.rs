@@ -10,9 +10,12 @@ mod ansi;
 pub mod termios;
 
 use crate::device::serial;
+use crate::errno::AllocResult;
 use crate::errno::Errno;
 use crate::file::blocking::BlockHandler;
+use crate::file::open_file::FileOwner;
 use crate::memory::vmem;
+use crate::process::pgroup;
 use crate::process::pid::Pid;
 use crate::process::signal::Signal;
 use crate::process::Process;
@@ -25,6 +28,7 @@ use crate::util::lock::MutexGuard;
 use crate::util::ptr::arc::Arc;
 use crate::vga;
 use core::cmp::*;
+use core::ffi::c_int;
 use core::mem::MaybeUninit;
 use core::ptr;
 
@@ -114,6 +118,9 @@ pub struct TTY {
 	/// Terminal IO settings.
 	termios: Termios,
 
+	/// The ID of the session of which the TTY is the controlling terminal. If zero, the TTY is
+	/// not a controlling terminal.
+	sid: Pid,
 	/// The current foreground Program Group ID.
 	pgrp: Pid,
 
@@ -154,6 +161,15 @@ impl<'a> TTYHandle {
 			Self::Normal(m) => m.lock(),
 		}
 	}
+
+	/// Tells whether both handles refer to the same TTY.
+	pub fn is_same(&self, other: &Self) -> bool {
+		let ptr = |handle: &Self| match handle {
+			Self::Init(m) => *m as *const IntMutex<TTY>,
+			Self::Normal(m) => m.as_ptr(),
+		};
+		ptr(self) == ptr(other)
+	}
 }
 
 /// Returns a mutable reference to the TTY with identifier `id`.
@@ -681,6 +697,18 @@ impl TTY {
 		self.termios = termios;
 	}
 
+	/// Returns the ID of the session of which the TTY is the controlling terminal.
+	///
+	/// If the TTY is not a controlling terminal, the function returns zero.
+	pub fn get_sid(&self) -> Pid {
+		self.sid
+	}
+
+	/// Sets the ID of the session of which the TTY is the controlling terminal.
+	pub fn set_sid(&mut self, sid: Pid) {
+		self.sid = sid;
+	}
+
 	/// Returns the current foreground Program Group ID.
 	pub fn get_pgrp(&self) -> Pid {
 		self.pgrp
@@ -697,9 +725,14 @@ impl TTY {
 			return;
 		}
 
-		if let Some(proc_mutex) = Process::get_by_pid(self.pgrp) {
-			let mut proc = proc_mutex.lock();
-			proc.kill_group(sig, false);
+		// The group keeps existing after the exit of its leader
+		let Ok(members) = pgroup::get_members(self.pgrp) else {
+			return;
+		};
+		for pid in members {
+			if let Some(proc_mutex) = Process::get_by_pid(pid) {
+				proc_mutex.lock().kill(&sig, false);
+			}
 		}
 	}
 
@@ -739,4 +772,19 @@ impl TTY {
 	pub fn add_waiting_process(&mut self, proc: &mut Process, mask: u32) -> Result<(), Errno> {
 		self.block_handler.add_waiting_process(proc, mask)
 	}
+
+	/// Registers or unregisters an open file for signal-driven IO on the TTY.
+	///
+	/// Arguments:
+	/// - `fd` is the file descriptor reported to the owner.
+	/// - `owner` is the owner of the open file.
+	/// - `on` tells whether the open file is registered.
+	pub fn set_async(
+		&mut self,
+		fd: c_int,
+		owner: &Arc<IntMutex<FileOwner>>,
+		on: bool,
+	) -> AllocResult<()> {
+		self.block_handler.set_async(fd, owner, on)
+	}
 }
@@ -0,0 +1,171 @@
+//! A LRU (Least Recently Used) cache is a map holding a bounded number of elements.
+//!
+//! When the cache is full, inserting a new element evicts the element that has not been accessed
+//! for the longest time.
+
+use super::hashmap::HashMap;
+use crate::errno::AllocResult;
+use core::borrow::Borrow;
+use core::hash::Hash;
+
+/// A LRU cache.
+#[derive(Debug)]
+pub struct LruCache<K: Eq + Hash, V> {
+	/// The elements, along with the value of the clock at their last access.
+	elements: HashMap<K, (V, u64)>,
+	/// The maximum number of elements.
+	capacity: usize,
+	/// Clock incremented on each access.
+	clock: u64,
+}
+
+impl<K: Eq + Hash, V> LruCache<K, V> {
+	/// Creates a new instance, holding at most `capacity` elements.
+	pub const fn new(capacity: usize) -> Self {
+		Self {
+			elements: HashMap::with_buckets(capacity / 4 + 1),
+			capacity,
+			clock: 0,
+		}
+	}
+
+	/// Returns the number of elements in the cache.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.elements.len()
+	}
+
+	/// Tells whether the cache is empty.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.elements.is_empty()
+	}
+
+	/// Returns the next value of the clock.
+	fn tick(&mut self) -> u64 {
+		self.clock += 1;
+		self.clock
+	}
+
+	/// Returns a reference to the value with the given key `k`, marking it as the most recently
+	/// used.
+	///
+	/// If the key isn't present, the function return `None`.
+	pub fn get<Q: ?Sized>(&mut self, k: &Q) -> Option<&V>
+	where
+		K: Borrow<Q>,
+		Q: Hash + Eq,
+	{
+		let now = self.tick();
+		let (v, last_use) = self.elements.get_mut(k)?;
+		*last_use = now;
+		Some(v)
+	}
+
+	/// Inserts a new element into the cache.
+	///
+	/// If the cache is full, the least recently used element is evicted first.
+	///
+	/// If the key was already present, the function returns the previous value.
+	pub fn insert(&mut self, k: K, v: V) -> AllocResult<Option<V>> {
+		if self.capacity == 0 {
+			return Ok(None);
+		}
+		if self.elements.len() >= self.capacity && !self.elements.contains_key(&k) {
+			self.shrink(1);
+		}
+		let now = self.tick();
+		Ok(self.elements.insert(k, (v, now))?.map(|(v, _)| v))
+	}
+
+	/// Removes an element from the cache.
+	///
+	/// If the key was present, the function returns the previous value.
+	pub fn remove<Q: ?Sized>(&mut self, k: &Q) -> Option<V>
+	where
+		K: Borrow<Q>,
+		Q: Hash + Eq,
+	{
+		self.elements.remove(k).map(|(v, _)| v)
+	}
+
+	/// Retains only the elements for which the given predicate returns `true`.
+	pub fn retain<F: FnMut(&K, &mut V) -> bool>(&mut self, mut f: F) {
+		self.elements.retain(|k, (v, _)| f(k, v));
+	}
+
+	/// Evicts at most `count` elements, starting from the least recently used.
+	///
+	/// The function returns the number of evicted elements.
+	pub fn shrink(&mut self, count: usize) -> usize {
+		let mut evicted = 0;
+		while evicted < count {
+			let Some(oldest) = self
+				.elements
+				.iter()
+				.map(|(_, (_, last_use))| *last_use)
+				.min()
+			else {
+				break;
+			};
+			// Clock values are unique, so only one element is removed
+			self.elements.retain(|_, (_, last_use)| *last_use != oldest);
+			evicted += 1;
+		}
+		evicted
+	}
+
+	/// Drops all elements in the cache.
+	pub fn clear(&mut self) {
+		self.elements.clear();
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn lru_evict() {
+		let mut lru = LruCache::<u32, u32>::new(3);
+		for i in 0..3 {
+			lru.insert(i, i * 10).unwrap();
+		}
+		// Accessing `0` makes `1` the least recently used
+		assert_eq!(lru.get(&0), Some(&0));
+		lru.insert(3, 30).unwrap();
+
+		assert_eq!(lru.len(), 3);
+		assert_eq!(lru.get(&1), None);
+		assert_eq!(lru.get(&0), Some(&0));
+		assert_eq!(lru.get(&2), Some(&20));
+		assert_eq!(lru.get(&3), Some(&30));
+	}
+
+	#[test_case]
+	fn lru_replace() {
+		let mut lru = LruCache::<u32, u32>::new(2);
+		lru.insert(0, 0).unwrap();
+		lru.insert(1, 1).unwrap();
+		assert_eq!(lru.insert(0, 2).unwrap(), Some(0));
+
+		assert_eq!(lru.len(), 2);
+		assert_eq!(lru.get(&0), Some(&2));
+		assert_eq!(lru.get(&1), Some(&1));
+	}
+
+	#[test_case]
+	fn lru_shrink() {
+		let mut lru = LruCache::<u32, u32>::new(8);
+		for i in 0..8 {
+			lru.insert(i, i).unwrap();
+		}
+		assert_eq!(lru.shrink(5), 5);
+		assert_eq!(lru.len(), 3);
+		for i in 5..8 {
+			assert_eq!(lru.get(&i), Some(&i));
+		}
+		assert_eq!(lru.shrink(5), 3);
+		assert!(lru.is_empty());
+	}
+}
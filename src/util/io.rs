@@ -54,6 +54,13 @@ pub trait IO {
 	///
 	/// The function returns the mask with available events set.
 	fn poll(&mut self, mask: u32) -> Result<u32, Errno>;
+
+	/// Makes sure every data previously written to the I/O reached persistent storage.
+	///
+	/// For interfaces without a volatile cache, the function does nothing.
+	fn flush(&mut self) -> Result<(), Errno> {
+		Ok(())
+	}
 }
 
 /// Structure representing a dummy I/O interface.
@@ -19,14 +19,14 @@
 
 pub mod spinlock;
 
+use crate::cpu;
 use crate::idt;
 use crate::util::lock::spinlock::Spinlock;
 use core::cell::UnsafeCell;
 use core::ops::Deref;
 use core::ops::DerefMut;
 
-/// Structure representing the saved state of interruptions for the current
-/// thread.
+/// Structure representing the saved state of interruptions for a CPU core.
 struct State {
 	/// The number of currently locked mutexes that disable interruptions.
 	ref_count: usize,
@@ -35,16 +35,29 @@ struct State {
 	enabled: bool,
 }
 
-// TODO When implementing multicore, use an array. One element per core
-/// Saved state of interruptions for the current thread.
-///
-/// This variable doesn't require synchonization since interruptions are always
-/// disabled when it is accessed.
-static mut INT_DISABLE_REFS: State = State {
+/// The initial value of an entry in `INT_DISABLE_REFS`.
+const STATE_INIT: State = State {
 	ref_count: 0,
 
 	enabled: false,
 };
+/// Saved state of interruptions, by core ID.
+///
+/// Every entry is initialized statically, thus before application processors start.
+///
+/// An entry doesn't require synchonization since it is only accessed by its own core, with
+/// interruptions disabled.
+static mut INT_DISABLE_REFS: [State; cpu::MAX_CPUS] = [STATE_INIT; cpu::MAX_CPUS];
+
+/// Returns the saved state of interruptions of the current core.
+///
+/// # Safety
+///
+/// Interruptions must be disabled while the returned reference is used, so that the current
+/// thread cannot be preempted and resumed on another core.
+unsafe fn current_state() -> &'static mut State {
+	&mut INT_DISABLE_REFS[cpu::get_current_id()]
+}
 
 /// Type used to declare a guard meant to unlock the associated `Mutex` at the
 /// moment the execution gets out of the scope of its declaration.
@@ -153,15 +166,13 @@ impl<T: ?Sized, const INT: bool> Mutex<T, INT> {
 
 			inner.spin.lock();
 
-			// Updating the current thread's state
-			// Safe because interrupts are disabled and the value can be accessed only by
-			// the current core
-			unsafe {
-				if INT_DISABLE_REFS.ref_count == 0 {
-					INT_DISABLE_REFS.enabled = state;
-				}
-				INT_DISABLE_REFS.ref_count += 1;
+			// Updating the current core's state
+			// Safe because interrupts are disabled, so the thread remains on the current core
+			let refs = unsafe { current_state() };
+			if refs.ref_count == 0 {
+				refs.enabled = state;
 			}
+			refs.ref_count += 1;
 		} else {
 			inner.spin.lock();
 		}
@@ -171,6 +182,42 @@ impl<T: ?Sized, const INT: bool> Mutex<T, INT> {
 		}
 	}
 
+	/// Tries to lock the mutex without waiting.
+	///
+	/// If the mutex is already locked, the function returns `None`. This allows to access a
+	/// resource which may already be locked by the current thread, without deadlocking.
+	pub fn try_lock(&self) -> Option<MutexGuard<T, INT>> {
+		let inner = unsafe {
+			// Safe because using the spinlock later
+			&mut *self.inner.get()
+		};
+
+		if !INT {
+			let state = idt::is_interrupt_enabled();
+			crate::cli!();
+
+			if !inner.spin.try_lock() {
+				if state {
+					crate::sti!();
+				}
+				return None;
+			}
+
+			// Safe because interrupts are disabled, so the thread remains on the current core
+			let refs = unsafe { current_state() };
+			if refs.ref_count == 0 {
+				refs.enabled = state;
+			}
+			refs.ref_count += 1;
+		} else if !inner.spin.try_lock() {
+			return None;
+		}
+
+		Some(MutexGuard {
+			mutex: self,
+		})
+	}
+
 	/// Unlocks the mutex. This function shouldn't be used directly since it is called when the
 	/// mutex guard is dropped.
 	///
@@ -184,9 +231,12 @@ impl<T: ?Sized, const INT: bool> Mutex<T, INT> {
 
 		if !INT {
 			// Updating references count
-			INT_DISABLE_REFS.ref_count -= 1;
-			let state = if INT_DISABLE_REFS.ref_count == 0 {
-				INT_DISABLE_REFS.enabled
+			// Interrupts are still disabled since the mutex is locked, so the thread is on the
+			// core that locked it
+			let refs = current_state();
+			refs.ref_count -= 1;
+			let state = if refs.ref_count == 0 {
+				refs.enabled
 			} else {
 				false
 			};
@@ -3,6 +3,7 @@
 //!
 //! Unless for special cases, other locks should be used instead.
 
+use crate::memory::vmem::tlb;
 use core::hint;
 use core::sync::atomic::AtomicBool;
 use core::sync::atomic::Ordering;
@@ -35,10 +36,21 @@ impl Spinlock {
 	#[inline(always)]
 	pub fn lock(&mut self) {
 		while self.locked.swap(true, Ordering::Acquire) {
+			// Interrupts are usually disabled while waiting, thus TLB shootdowns have to be
+			// handled here to avoid deadlocks
+			tlb::handle_pending();
 			hint::spin_loop();
 		}
 	}
 
+	/// Tries to lock the spinlock without waiting.
+	///
+	/// The function returns `true` if the spinlock has been locked.
+	#[inline(always)]
+	pub fn try_lock(&mut self) -> bool {
+		!self.locked.swap(true, Ordering::Acquire)
+	}
+
 	/// Unlocks the spinlock.
 	#[inline(always)]
 	pub unsafe fn unlock(&mut self) {
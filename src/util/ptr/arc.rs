@@ -2,12 +2,15 @@
 
 use crate::errno::{AllocError, AllocResult};
 use crate::memory::malloc;
+use crate::memory::slab::SlabCache;
 use crate::util::boxed::Box;
 use core::alloc::Layout;
 use core::borrow::Borrow;
 use core::intrinsics::size_of_val;
 use core::marker::Unsize;
+use core::mem::size_of;
 use core::mem::ManuallyDrop;
+use core::num::NonZeroUsize;
 use core::ops::CoerceUnsized;
 use core::ops::Deref;
 use core::ops::DispatchFromDyn;
@@ -35,9 +38,15 @@ impl<T: ?Sized> ArcInner<T> {
 	/// Arguments:
 	/// - `ptr` is a pointer to the data to place in the `Arc`. This is used as a helper for memory
 	/// allocation
+	/// - `cache` is the slab cache to allocate from. If `None`, the memory is allocated with
+	/// `malloc`
 	/// - `init` is the function to initialize the object to place in the `Arc`
-	unsafe fn new<I: FnOnce(&mut T)>(ptr: *const T, init: I) -> AllocResult<NonNull<Self>> {
-		let size = Layout::new::<ArcInner<()>>()
+	unsafe fn new<I: FnOnce(&mut T)>(
+		ptr: *const T,
+		cache: Option<&'static SlabCache>,
+		init: I,
+	) -> AllocResult<NonNull<Self>> {
+		let size: NonZeroUsize = Layout::new::<ArcInner<()>>()
 			.extend(Layout::for_value(&*ptr))
 			.unwrap()
 			.0
@@ -45,8 +54,15 @@ impl<T: ?Sized> ArcInner<T> {
 			.size()
 			.try_into()
 			.unwrap();
-		// Allocate and make usable
-		let inner = malloc::alloc(size)?;
+		// Allocate and make usable. The memory is freed with `malloc`, which gives objects back
+		// to the cache they come from
+		let inner = match cache {
+			Some(cache) => {
+				debug_assert!(size.get() <= cache.get_obj_size());
+				cache.alloc()?
+			}
+			None => malloc::alloc(size)?,
+		};
 		let inner = inner.as_ptr().with_metadata_of(ptr as *const Self);
 		let mut inner = NonNull::new_unchecked(inner);
 
@@ -81,7 +97,7 @@ impl<T: ?Sized> TryFrom<Box<T>> for Arc<T> {
 
 	fn try_from(obj: Box<T>) -> AllocResult<Self> {
 		let inner = unsafe {
-			ArcInner::new(obj.as_ptr(), |o: &mut T| {
+			ArcInner::new(obj.as_ptr(), None, |o: &mut T| {
 				// Copy data
 				ptr::copy_nonoverlapping(
 					obj.as_ref() as *const _ as *const u8,
@@ -106,12 +122,30 @@ impl<T> Arc<T> {
 	///
 	/// This function allocates memory. On fail, it returns an error.
 	pub fn new(obj: T) -> AllocResult<Self> {
-		let inner = unsafe { ArcInner::new(&obj, |o: &mut T| ptr::write(o, obj))? };
+		let inner = unsafe { ArcInner::new(&obj, None, |o: &mut T| ptr::write(o, obj))? };
+		Ok(Self {
+			inner,
+		})
+	}
+
+	/// Creates a new `Arc` for the given object, allocated from the slab cache `cache`.
+	///
+	/// The objects of the cache must be large enough to hold the `Arc`'s inner structure, whose
+	/// size is given by [`Arc::inner_size`].
+	///
+	/// This function allocates memory. On fail, it returns an error.
+	pub fn new_in(obj: T, cache: &'static SlabCache) -> AllocResult<Self> {
+		let inner = unsafe { ArcInner::new(&obj, Some(cache), |o: &mut T| ptr::write(o, obj))? };
 		Ok(Self {
 			inner,
 		})
 	}
 
+	/// Returns the size of the memory allocated for an `Arc` holding an object of type `T`.
+	pub const fn inner_size() -> usize {
+		size_of::<ArcInner<T>>()
+	}
+
 	/// Returns the inner value of the `Arc` if the this is the last reference to it.
 	pub fn into_inner(this: Self) -> Option<T> {
 		let inner = this.inner();
@@ -284,6 +318,12 @@ impl<T: ?Sized> Clone for Weak<T> {
 	}
 }
 
+impl<T: ?Sized> fmt::Debug for Weak<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "(Weak)")
+	}
+}
+
 impl<T: ?Sized> Drop for Weak<T> {
 	fn drop(&mut self) {
 		let inner = self.inner();